@@ -9,25 +9,40 @@
 //! (`tty=false`) we use plain pipes. VM backends create the PTY inside the guest
 //! via the vsock streaming protocol, so the guest shell still sees a real TTY.
 
+use crate::api_auth::ApiAuth;
 use axum::{
-    Router,
+    Json, Router,
     extract::{
         Path, Query, State,
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
     },
     response::IntoResponse,
-    routing::get,
+    routing::{get, put},
 };
 use futures_util::{SinkExt, StreamExt};
 use pkg_container::ContainerRuntime;
+use pkg_container::logs::LogOptions;
+use pkg_container::vm_utils::ExitTrailerFilter;
+use pkg_metrics::MetricsRegistry;
+use pkg_metrics::log_filter::LogFilter;
+use pkg_proxy::service_proxy::ServiceProxy;
+use pkg_types::config::LogLevel;
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::exec::{ExecControl, ExecExit, ExecInput};
 use serde::Deserialize;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[derive(Clone)]
 pub struct AgentState {
     pub runtime: Arc<ContainerRuntime>,
+    pub service_proxy: Arc<ServiceProxy>,
+    pub metrics: Arc<MetricsRegistry>,
+    pub log_filter: Arc<LogFilter>,
+    /// Base directory of local-path volumes
+    pub local_path_dir: PathBuf,
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,12 +56,246 @@ pub struct ExecQuery {
     pub tty: bool,
 }
 
-pub fn create_agent_router(state: AgentState) -> Router {
-    Router::new()
+#[derive(Debug, Deserialize)]
+pub struct LogsQuery {
+    /// Keep the response open and stream new lines as they are written.
+    #[serde(default)]
+    pub follow: bool,
+    /// Only return lines written at or after this RFC3339 time.
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Only return the last N lines of the existing log.
+    pub tail: Option<usize>,
+    /// Keep the RFC3339 timestamp prefix on each line.
+    #[serde(default)]
+    pub timestamps: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PortForwardQuery {
+    /// TCP port on the container's loopback interface.
+    pub port: u16,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveQuery {
+    /// Absolute path inside the container.
+    pub path: String,
+}
+
+impl LogsQuery {
+    fn options(&self) -> LogOptions {
+        LogOptions {
+            since: self.since,
+            tail: self.tail,
+            timestamps: self.timestamps,
+        }
+    }
+}
+
+/// Every route but `/metrics` needs the credential `auth` asks for.
+pub fn create_agent_router(state: AgentState, auth: ApiAuth) -> Router {
+    let protected = Router::new()
         .route("/exec/{container_id}", get(exec_handler))
+        .route("/portforward/{container_id}", get(port_forward_handler))
+        .route("/containers/{container_id}/logs", get(logs_handler))
+        .route(
+            "/containers/{container_id}/archive",
+            get(archive_get_handler).put(archive_put_handler),
+        )
+        .route(
+            "/volumes/{name}",
+            put(crate::local_path::put_volume_handler)
+                .delete(crate::local_path::delete_volume_handler),
+        )
+        .route("/debug/endpoints", get(endpoints_handler))
+        .route(
+            "/debug/loglevel",
+            get(get_log_level_handler).put(put_log_level_handler),
+        );
+    auth.protect(protected)
+        .route("/metrics", get(metrics_handler))
         .with_state(state)
 }
 
+/// Prometheus metrics for this node, in text exposition format.
+async fn metrics_handler(State(state): State<AgentState>) -> impl IntoResponse {
+    let pulls = state.runtime.image_pull_stats();
+    let body = crate::metrics::scrape(
+        &state.metrics,
+        state.runtime.container_store().running_count(),
+        pulls.pulled(),
+        pulls.failed(),
+    );
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        body,
+    )
+}
+
+/// Probe status of every service endpoint this node's proxy balances over.
+async fn endpoints_handler(State(state): State<AgentState>) -> impl IntoResponse {
+    Json(state.service_proxy.endpoint_health())
+}
+
+/// The agent's log filter.
+async fn get_log_level_handler(State(state): State<AgentState>) -> Json<LogLevel> {
+    Json(LogLevel {
+        filter: state.log_filter.current(),
+    })
+}
+
+/// Replace the agent's log filter; an invalid one is a `400` and the
+/// current filter stays. The body is JSON [`LogLevel`], read whatever its
+/// content type, as the server relays it without one.
+async fn put_log_level_handler(
+    State(state): State<AgentState>,
+    body: axum::body::Bytes,
+) -> axum::response::Response {
+    let bad_request = |message: String| {
+        let err = ApiError::new(ErrorReason::BadRequest, message);
+        (axum::http::StatusCode::BAD_REQUEST, Json(err)).into_response()
+    };
+    let level: LogLevel = match serde_json::from_slice(&body) {
+        Ok(level) => level,
+        Err(e) => return bad_request(format!("invalid log level body: {}", e)),
+    };
+    if let Err(e) = state.log_filter.set(Some(&level.filter)) {
+        return bad_request(e);
+    }
+    info!("Log filter set to {}", level.filter);
+    Json(LogLevel {
+        filter: state.log_filter.current(),
+    })
+    .into_response()
+}
+
+/// Stream a container's logs as a chunked `text/plain` body, one line per chunk.
+async fn logs_handler(
+    Path(container_id): Path<String>,
+    Query(query): Query<LogsQuery>,
+    State(state): State<AgentState>,
+) -> impl IntoResponse {
+    info!(
+        "Log stream: container={} follow={}",
+        container_id, query.follow
+    );
+    match state
+        .runtime
+        .container_logs_stream(&container_id, query.follow, &query.options())
+        .await
+    {
+        Ok(stream) => {
+            let body = axum::body::Body::from_stream(
+                stream.map(|line| Ok::<_, std::convert::Infallible>(format!("{}\n", line))),
+            );
+            (
+                [(
+                    axum::http::header::CONTENT_TYPE,
+                    "text/plain; charset=utf-8",
+                )],
+                body,
+            )
+                .into_response()
+        }
+        Err(e) => {
+            error!("Failed to open log stream for {}: {}", container_id, e);
+            (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to open log stream: {}", e),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Stream `path` out of a container as an `application/x-tar` body.
+async fn archive_get_handler(
+    Path(container_id): Path<String>,
+    Query(query): Query<ArchiveQuery>,
+    State(state): State<AgentState>,
+) -> impl IntoResponse {
+    info!(
+        "Archive read: container={} path={}",
+        container_id, query.path
+    );
+    match state
+        .runtime
+        .copy_from_container(&container_id, &query.path)
+        .await
+    {
+        Ok(stream) => (
+            [(axum::http::header::CONTENT_TYPE, "application/x-tar")],
+            axum::body::Body::from_stream(stream),
+        )
+            .into_response(),
+        Err(e) => {
+            error!("Failed to read {} from {}: {}", query.path, container_id, e);
+            (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to copy {}: {}", query.path, e),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Unpack the tar archive in the request body at `path` inside a container.
+async fn archive_put_handler(
+    Path(container_id): Path<String>,
+    Query(query): Query<ArchiveQuery>,
+    State(state): State<AgentState>,
+    body: axum::body::Body,
+) -> impl IntoResponse {
+    info!(
+        "Archive write: container={} path={}",
+        container_id, query.path
+    );
+    match state
+        .runtime
+        .copy_into_container(&container_id, &query.path, body.into_data_stream())
+        .await
+    {
+        Ok(bytes) => Json(serde_json::json!({ "bytes": bytes })).into_response(),
+        Err(e) => {
+            error!(
+                "Failed to write {} into {}: {}",
+                query.path, container_id, e
+            );
+            (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to copy to {}: {}", query.path, e),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Relay one TCP connection to a container port over the WebSocket.
+async fn port_forward_handler(
+    ws: WebSocketUpgrade,
+    Path(container_id): Path<String>,
+    Query(query): Query<PortForwardQuery>,
+    State(state): State<AgentState>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| async move {
+        info!(
+            "Port-forward: container={} port={}",
+            container_id, query.port
+        );
+        let (mut ws_tx, ws_rx) = socket.split();
+        match state.runtime.port_forward(&container_id, query.port).await {
+            Ok(stream) => crate::port_forward::relay(ws_tx, ws_rx, stream).await,
+            Err(e) => {
+                warn!("Port-forward to {} failed: {}", container_id, e);
+                crate::port_forward::close_with_error(&mut ws_tx, &e.to_string()).await;
+            }
+        }
+    })
+}
+
 async fn exec_handler(
     ws: WebSocketUpgrade,
     Path(container_id): Path<String>,
@@ -273,30 +522,39 @@ async fn handle_tty(
         }
     });
 
-    // Task: WebSocket → PTY master
+    // Task: WebSocket → PTY master. Resize control messages go to the PTY
+    // as a window size change instead of input.
     let mut write_task = tokio::spawn(async move {
+        use std::os::unix::io::AsRawFd;
         let mut ws_receiver = ws_receiver;
         while let Some(Ok(msg)) = ws_receiver.next().await {
-            match msg {
-                Message::Binary(bytes) => {
-                    if master_write.write_all(&bytes).await.is_err() {
+            let input = match &msg {
+                Message::Binary(bytes) => ExecInput::from_binary(bytes),
+                Message::Text(text) => ExecInput::from_text(text.as_str()),
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            match input {
+                ExecInput::Stdin(bytes) => {
+                    if master_write.write_all(bytes).await.is_err() {
                         break;
                     }
                 }
-                Message::Text(text) => {
-                    // Accept text frames too (some clients send text for keystrokes)
-                    if master_write.write_all(text.as_bytes()).await.is_err() {
-                        break;
+                ExecInput::Control(ExecControl::Resize { cols, rows }) => {
+                    if let Err(e) = set_window_size(master_write.as_raw_fd(), cols, rows) {
+                        warn!("PTY resize to {}x{} failed: {}", cols, rows, e);
                     }
                 }
-                Message::Close(_) => break,
-                _ => {}
+                // The PTY is the session's only stdin; it stays open.
+                ExecInput::Control(ExecControl::Eof) => {}
             }
         }
     });
 
     // Task: wait for child exit
     let mut child_task = tokio::spawn(async move { child.wait().await });
+    let mut exit_code = None;
+    let mut child_done = false;
 
     // Main loop: ws_sender stays in scope so we can send Close when the session ends.
     loop {
@@ -312,13 +570,22 @@ async fn handle_tty(
                 }
             }
             _ = &mut write_task => break, // client disconnected
-            _ = &mut child_task => {
+            status = &mut child_task => {
                 info!("PTY exec process exited for {}", container_id);
+                child_done = true;
+                exit_code = exit_status(status);
                 break;
             }
         }
     }
 
+    // The output can end just before the process is reaped.
+    if !child_done
+        && let Ok(status) =
+            tokio::time::timeout(std::time::Duration::from_millis(200), &mut child_task).await
+    {
+        exit_code = exit_status(status);
+    }
     child_task.abort();
     write_task.abort();
 
@@ -334,7 +601,38 @@ async fn handle_tty(
     }
 
     // Signal the client that the session is over.
-    let _ = ws_sender.send(Message::Close(None)).await;
+    let _ = ws_sender.send(close_frame(exit_code)).await;
+}
+
+/// Exit code of a waited-for exec process, if it could be waited for.
+fn exit_status(
+    status: Result<std::io::Result<std::process::ExitStatus>, tokio::task::JoinError>,
+) -> Option<i32> {
+    status.ok()?.ok().map(pkg_container::backend::exit_code)
+}
+
+/// The close frame ending an exec session, carrying the process's exit code
+/// when it is known.
+fn close_frame(exit_code: Option<i32>) -> Message {
+    Message::Close(exit_code.map(|code| CloseFrame {
+        code: close_code::NORMAL,
+        reason: ExecExit { code }.encode().into(),
+    }))
+}
+
+/// Set the window size of the PTY behind `fd`; the foreground process gets
+/// `SIGWINCH`.
+fn set_window_size(fd: std::os::unix::io::RawFd, cols: u16, rows: u16) -> std::io::Result<()> {
+    let winsize = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    if unsafe { libc::ioctl(fd, libc::TIOCSWINSZ as _, &winsize) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
 }
 
 // ─── Pipe / non-interactive or VM-backend interactive mode ───────────────────
@@ -369,9 +667,12 @@ async fn handle_pipe(
         }
     };
 
-    let mut stdin = child.stdin.take().unwrap();
+    let stdin = child.stdin.take().unwrap();
     let mut stdout = child.stdout.take().unwrap();
     let mut stderr = child.stderr.take().unwrap();
+    // A VM session's output ends with k3rs-init's exit status trailer, unless
+    // k3rs-vmm already took it off.
+    let vm = runtime.backend_name_for(&container_id) == "vm";
 
     // Drop original tx so the channel closes when BOTH reader tasks finish.
     let (tx, mut rx) = tokio::sync::mpsc::channel::<Vec<u8>>(64);
@@ -379,16 +680,26 @@ async fn handle_pipe(
     let tx_out = tx.clone();
     let stdout_task = tokio::spawn(async move {
         let mut buf = [0u8; 1024];
+        let mut trailer = vm.then(ExitTrailerFilter::default);
         loop {
             match stdout.read(&mut buf).await {
                 Ok(0) | Err(_) => break,
                 Ok(n) => {
-                    if tx_out.send(buf[..n].to_vec()).await.is_err() {
-                        break;
+                    let bytes = match trailer.as_mut() {
+                        Some(trailer) => trailer.push(&buf[..n]),
+                        None => buf[..n].to_vec(),
+                    };
+                    if !bytes.is_empty() && tx_out.send(bytes).await.is_err() {
+                        return None;
                     }
                 }
             }
         }
+        let (rest, code) = trailer?.finish();
+        if !rest.is_empty() {
+            let _ = tx_out.send(rest).await;
+        }
+        code
     });
 
     let tx_err = tx.clone();
@@ -411,22 +722,31 @@ async fn handle_pipe(
 
     // WS → stdin: relay raw bytes without modification.
     // Binary frames carry raw terminal input; Text frames are treated as raw bytes too
-    // (some WebSocket clients send keystrokes as Text).
+    // (some WebSocket clients send keystrokes as Text). The guest PTY of a VM
+    // session can't be resized from here, so resize messages are dropped.
+    // An EOF message closes stdin but keeps the session: the client is still
+    // there for the output.
     let mut stdin_task = tokio::spawn(async move {
+        let mut stdin = Some(stdin);
         while let Some(Ok(msg)) = ws_receiver.next().await {
-            match msg {
-                Message::Binary(bytes) => {
-                    if stdin.write_all(&bytes).await.is_err() {
-                        break;
-                    }
-                }
-                Message::Text(text) => {
-                    if stdin.write_all(text.as_bytes()).await.is_err() {
-                        break;
+            let input = match &msg {
+                Message::Binary(bytes) => ExecInput::from_binary(bytes),
+                Message::Text(text) => ExecInput::from_text(text.as_str()),
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            match input {
+                ExecInput::Stdin(bytes) => {
+                    let Some(pipe) = stdin.as_mut() else {
+                        continue;
+                    };
+                    // The process stopped reading; the rest of the input is dropped.
+                    if pipe.write_all(bytes).await.is_err() {
+                        stdin = None;
                     }
                 }
-                Message::Close(_) => break,
-                _ => {}
+                ExecInput::Control(ExecControl::Eof) => stdin = None,
+                ExecInput::Control(ExecControl::Resize { .. }) => {}
             }
         }
         // Dropping stdin signals EOF to the child process.
@@ -434,6 +754,8 @@ async fn handle_pipe(
 
     // Wait for child exit concurrently with output streaming.
     let mut child_task = tokio::spawn(async move { child.wait().await });
+    let mut exit_code = None;
+    let mut child_done = false;
 
     // Main loop: stream output to WebSocket while child is running.
     // Break when:
@@ -457,22 +779,35 @@ async fn handle_pipe(
                 // to the child, which causes it to exit and closes stdout/stderr.
                 break;
             }
-            _ = &mut child_task => {
+            status = &mut child_task => {
                 info!("Exec process exited for {}", container_id);
+                child_done = true;
+                exit_code = exit_status(status);
                 break;
             }
         }
     }
 
+    // The output can end just before the process is reaped.
+    if !child_done
+        && let Ok(status) =
+            tokio::time::timeout(std::time::Duration::from_millis(200), &mut child_task).await
+    {
+        exit_code = exit_status(status);
+    }
     child_task.abort();
     stdin_task.abort();
 
     // Allow stdout/stderr tasks up to 200ms to drain remaining buffered output.
-    let _ = tokio::time::timeout(
+    // The guest's own report beats the status of the process relaying it.
+    if let Ok((Ok(Some(code)), _)) = tokio::time::timeout(
         std::time::Duration::from_millis(200),
         futures_util::future::join(stdout_task, stderr_task),
     )
-    .await;
+    .await
+    {
+        exit_code = Some(code);
+    }
 
     // Drain the channel.
     while let Ok(Some(b)) =
@@ -481,5 +816,5 @@ async fn handle_pipe(
         ws_sender.send(Message::Binary(b.into())).await.ok();
     }
 
-    let _ = ws_sender.send(Message::Close(None)).await;
+    let _ = ws_sender.send(close_frame(exit_code)).await;
 }
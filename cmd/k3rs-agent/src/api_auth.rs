@@ -0,0 +1,74 @@
+//! Authentication of the agent API.
+//!
+//! The server issues each node a bearer token when it registers and presents
+//! it on every request it proxies to the agent (exec, logs, archive,
+//! port-forward). The token lives in the agent's state cache, so a
+//! re-registration swaps it in without restarting the API listener.
+
+use crate::cache::AgentStateCache;
+use axum::{
+    Json, Router,
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::rbac::{bearer_token, secrets_match};
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+
+/// Who may call the agent API.
+#[derive(Clone)]
+pub enum ApiAuth {
+    /// Anyone. Only for local development (`--insecure-agent-api`).
+    Disabled,
+    /// Callers presenting the token from the node's latest registration.
+    Token(Arc<RwLock<AgentStateCache>>),
+}
+
+impl ApiAuth {
+    /// Why a request with `headers` is turned away, if it is.
+    fn reject(&self, headers: &HeaderMap) -> Option<&'static str> {
+        let Self::Token(cache) = self else {
+            return None;
+        };
+        let Some(expected) = cache.read().unwrap().agent_token.clone() else {
+            return Some("the agent has not been issued an API token yet");
+        };
+        match headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(bearer_token)
+        {
+            None => Some("missing bearer token"),
+            Some(token) if secrets_match(token, &expected) => None,
+            Some(_) => Some("invalid bearer token"),
+        }
+    }
+
+    /// Put every route of `router` behind this check.
+    pub fn protect<S: Clone + Send + Sync + 'static>(self, router: Router<S>) -> Router<S> {
+        router.layer(middleware::from_fn_with_state(self, require_token))
+    }
+}
+
+/// Middleware: answer 401 with an [`ApiError`] body unless the request
+/// carries the agent's token.
+async fn require_token(State(auth): State<ApiAuth>, req: Request, next: Next) -> Response {
+    match auth.reject(req.headers()) {
+        None => next.run(req).await,
+        Some(message) => {
+            warn!(
+                "Rejected agent API request to {}: {}",
+                req.uri().path(),
+                message
+            );
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(ApiError::new(ErrorReason::Unauthorized, message)),
+            )
+                .into_response()
+        }
+    }
+}
@@ -5,7 +5,10 @@
 //! purely the in-memory view passed through `Arc<RwLock<AgentStateCache>>`.
 
 use chrono::{DateTime, Utc};
-use pkg_types::{endpoint::Endpoint, ingress::Ingress, pod::Pod, service::Service};
+use pkg_types::{
+    endpoint::Endpoint, ingress::Ingress, node::NodeRegistrationResponse, pod::Pod,
+    service::Service,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -20,6 +23,12 @@ pub struct AgentStateCache {
     /// Remembered from registration for agent API server.
     #[serde(default)]
     pub agent_api_port: Option<u16>,
+    /// Pod CIDR assigned at registration, for bridge networking.
+    #[serde(default)]
+    pub pod_cidr: Option<String>,
+    /// Token the server presents on the agent API, from registration.
+    #[serde(default)]
+    pub agent_token: Option<String>,
     /// Monotonic sequence from server EventLog.
     pub server_seq: u64,
     /// Timestamp of last successful server sync.
@@ -41,6 +50,8 @@ impl AgentStateCache {
             node_name,
             node_id: None,
             agent_api_port: None,
+            pod_cidr: None,
+            agent_token: None,
             server_seq: 0,
             last_synced_at: Utc::now(),
             pods: Vec::new(),
@@ -50,6 +61,25 @@ impl AgentStateCache {
         }
     }
 
+    /// Remember the identity, pod CIDR and agent API token the server
+    /// assigned at registration.
+    pub fn set_registration(&mut self, resp: NodeRegistrationResponse) {
+        self.node_id = Some(resp.node_id);
+        self.agent_api_port = Some(resp.agent_api_port);
+        self.pod_cidr = resp.pod_cidr;
+        self.agent_token = resp.agent_token;
+    }
+
+    /// Node id to probe the server with before registering. `None` forces a
+    /// full registration, which bridge networking needs to learn its pod CIDR
+    /// and every agent needs to be issued its API token.
+    pub fn probe_node_id(&self, bridge_networking: bool) -> Option<String> {
+        if self.agent_token.is_none() || (bridge_networking && self.pod_cidr.is_none()) {
+            return None;
+        }
+        self.node_id.clone()
+    }
+
     /// Seconds since last successful sync.
     pub fn age_secs(&self) -> i64 {
         Utc::now()
@@ -66,9 +96,8 @@ impl AgentStateCache {
         let mut routes: HashMap<String, Vec<String>> = HashMap::new();
 
         for svc in &self.services {
-            let cluster_ip = match &svc.cluster_ip {
-                Some(ip) => ip.clone(),
-                None => continue,
+            let Some(cluster_ip) = svc.virtual_ip() else {
+                continue;
             };
 
             let matching_eps: Vec<&Endpoint> = self
@@ -106,9 +135,9 @@ impl AgentStateCache {
         let mut records: HashMap<String, String> = HashMap::new();
 
         for svc in &self.services {
-            if let Some(ref cluster_ip) = svc.cluster_ip {
+            if let Some(cluster_ip) = svc.virtual_ip() {
                 let fqdn = format!("{}.{}.{}", svc.name, svc.namespace, domain_suffix);
-                records.insert(fqdn, cluster_ip.clone());
+                records.insert(fqdn, cluster_ip.to_string());
             }
         }
 
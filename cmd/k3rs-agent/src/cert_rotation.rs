@@ -0,0 +1,223 @@
+//! Node certificate rotation.
+//!
+//! The agent checks when its certificate expires at startup and then daily.
+//! Once less than the renewal threshold remains it asks the server for a new
+//! one, authenticating with the current certificate, swaps the files on disk
+//! and rebuilds the HTTP client that presents them.
+
+use crate::cache::AgentStateCache;
+use crate::connectivity::ConnectivityManager;
+use crate::registration;
+use chrono::{DateTime, Duration, Utc};
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::node::{
+    CertificateRenewalRequest, CertificateRenewalResponse, NodeRegistrationRequest,
+};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+const CERT_FILE: &str = "node.crt";
+const KEY_FILE: &str = "node.key";
+const CA_FILE: &str = "ca.crt";
+
+/// Where a node's certificate, key and the cluster CA are kept.
+pub fn cert_dir(node_name: &str) -> PathBuf {
+    Path::new(pkg_constants::paths::CONFIG_DIR)
+        .join("certs")
+        .join(node_name)
+}
+
+/// Replace the certificate, key and CA in `dir`. Every file is written to a
+/// temporary name first and then renamed over the old one, so a failed
+/// write leaves the previous set in place and readers never see a partial
+/// file.
+pub async fn write_certs(dir: &Path, cert: &str, key: &str, ca: &str) -> anyhow::Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    let files = [(CERT_FILE, cert), (KEY_FILE, key), (CA_FILE, ca)];
+    for (name, contents) in files {
+        let tmp = dir.join(format!("{}.tmp", name));
+        let mut file = tokio::fs::File::create(&tmp).await?;
+        tokio::io::AsyncWriteExt::write_all(&mut file, contents.as_bytes()).await?;
+        file.sync_all().await?;
+    }
+    // The key goes in before the certificate it belongs to.
+    for name in [KEY_FILE, CERT_FILE, CA_FILE] {
+        tokio::fs::rename(dir.join(format!("{}.tmp", name)), dir.join(name)).await?;
+    }
+    Ok(())
+}
+
+/// Whether a certificate expiring at `expires_at` is due for renewal at `now`.
+pub fn needs_renewal(
+    expires_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    renew_before: Duration,
+) -> bool {
+    expires_at - now < renew_before
+}
+
+/// The node's certificate files and the HTTP client that presents them.
+pub struct NodeCerts {
+    dir: PathBuf,
+    node_name: String,
+    client: RwLock<reqwest::Client>,
+}
+
+impl NodeCerts {
+    pub fn new(dir: PathBuf, node_name: String) -> Self {
+        let certs = Self {
+            dir,
+            node_name,
+            client: RwLock::new(base_client().build().expect("HTTP client")),
+        };
+        if let Err(e) = certs.rebuild_client() {
+            warn!("Not presenting a client certificate: {}", e);
+        }
+        certs
+    }
+
+    /// Client presenting the current certificate.
+    pub fn client(&self) -> reqwest::Client {
+        self.client.read().unwrap().clone()
+    }
+
+    /// When the certificate on disk expires; `None` before registration.
+    pub fn expires_at(&self) -> anyhow::Result<Option<DateTime<Utc>>> {
+        match std::fs::read_to_string(self.dir.join(CERT_FILE)) {
+            Ok(pem) => Ok(Some(pkg_pki::cert::not_after(&pem)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Swap in a new certificate set and start presenting it.
+    pub async fn install(&self, cert: &str, key: &str, ca: &str) -> anyhow::Result<()> {
+        write_certs(&self.dir, cert, key, ca).await?;
+        self.rebuild_client()
+    }
+
+    fn rebuild_client(&self) -> anyhow::Result<()> {
+        let cert = std::fs::read(self.dir.join(CERT_FILE))?;
+        let key = std::fs::read(self.dir.join(KEY_FILE))?;
+        let identity = reqwest::Identity::from_pem(&[cert, key].concat())?;
+        *self.client.write().unwrap() = base_client().identity(identity).build()?;
+        Ok(())
+    }
+
+    /// Ask the server for a new certificate, signing the request with the
+    /// current key, and install it. Returns when the new one expires.
+    pub async fn renew(&self, server: &str) -> Result<DateTime<Utc>, ApiError> {
+        let cert = tokio::fs::read_to_string(self.dir.join(CERT_FILE))
+            .await
+            .map_err(|e| ApiError::internal(format!("reading certificate: {}", e)))?;
+        let key = tokio::fs::read_to_string(self.dir.join(KEY_FILE))
+            .await
+            .map_err(|e| ApiError::internal(format!("reading key: {}", e)))?;
+        let timestamp = Utc::now();
+        let message = pkg_pki::cert::renewal_message(&self.node_name, timestamp);
+        let signature = pkg_pki::cert::sign(&key, &message)
+            .map_err(|e| ApiError::internal(format!("signing renewal request: {}", e)))?;
+
+        let url = format!(
+            "{}/api/v1/nodes/{}/certificate/renew",
+            server.trim_end_matches('/'),
+            self.node_name
+        );
+        let resp = self
+            .client()
+            .post(&url)
+            .json(&CertificateRenewalRequest {
+                certificate: cert,
+                timestamp,
+                signature,
+            })
+            .send()
+            .await
+            .map_err(|e| ApiError::new(ErrorReason::ServiceUnavailable, e.to_string()))?;
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ApiError::from_response(status.as_u16(), &body));
+        }
+        let renewed: CertificateRenewalResponse = resp
+            .json()
+            .await
+            .map_err(|e| ApiError::internal(format!("decoding renewal response: {}", e)))?;
+        self.install(
+            &renewed.certificate,
+            &renewed.private_key,
+            &renewed.server_ca,
+        )
+        .await
+        .map_err(|e| ApiError::internal(format!("installing certificate: {}", e)))?;
+        Ok(renewed.expires_at)
+    }
+}
+
+fn base_client() -> reqwest::ClientBuilder {
+    reqwest::Client::builder().danger_accept_invalid_certs(true)
+}
+
+/// Check the certificate now and every `CERT_CHECK_INTERVAL_SECS`, renewing
+/// it when less than `renew_before` remains. A certificate the server no
+/// longer accepts (expired, or issued by a CA from before a server restart)
+/// is replaced by registering again with the join token, which also brings
+/// a new agent API token into `cache`.
+pub fn start(
+    certs: Arc<NodeCerts>,
+    server: String,
+    renew_before: Duration,
+    reg_req: NodeRegistrationRequest,
+    connectivity: Arc<ConnectivityManager>,
+    cache: Arc<RwLock<AgentStateCache>>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            pkg_constants::timings::CERT_CHECK_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            if !connectivity.is_connected() {
+                continue;
+            }
+            let expires_at = match certs.expires_at() {
+                Ok(Some(at)) => at,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Cannot read node certificate: {}", e);
+                    continue;
+                }
+            };
+            if !needs_renewal(expires_at, Utc::now(), renew_before) {
+                info!("Node certificate valid until {}", expires_at);
+                continue;
+            }
+
+            info!("Node certificate expires at {}; renewing", expires_at);
+            match certs.renew(&server).await {
+                Ok(renewed) => info!("Node certificate renewed, valid until {}", renewed),
+                Err(e) if e.reason == ErrorReason::Unauthorized => {
+                    warn!("Certificate renewal refused ({}); registering again", e);
+                    match registration::try_register(
+                        &certs.client(),
+                        &server,
+                        &reg_req,
+                        &certs.node_name,
+                    )
+                    .await
+                    {
+                        Ok((_, _, resp)) => {
+                            cache.write().unwrap().set_registration(resp);
+                            if let Err(e) = certs.rebuild_client() {
+                                warn!("Not presenting a client certificate: {}", e);
+                            }
+                        }
+                        Err(e) => warn!("Re-registration failed: {}", e),
+                    }
+                }
+                Err(e) => warn!("Certificate renewal failed: {}", e),
+            }
+        }
+    });
+}
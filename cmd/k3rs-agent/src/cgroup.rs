@@ -0,0 +1,126 @@
+//! Container usage from cgroup v2 files.
+//!
+//! OCI containers run in their own cgroup, which accounts for every process
+//! in the container rather than just the init process sysinfo would see.
+//! The cgroup is found through `/proc/<pid>/cgroup`; CPU is the growth of
+//! `cpu.stat` `usage_usec` between two samples and memory is `memory.current`.
+//! Containers killed for exceeding their memory limit show up in the
+//! `oom_kill` counter of `memory.events`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// The cgroup v2 path in a `/proc/<pid>/cgroup` file (the `0::<path>` line).
+pub fn cgroup_path(proc_cgroup: &str) -> Option<&str> {
+    proc_cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+}
+
+/// Total CPU time in microseconds from a `cpu.stat` file.
+pub fn parse_usage_usec(cpu_stat: &str) -> Option<u64> {
+    cpu_stat.lines().find_map(|line| {
+        let (key, value) = line.split_once(' ')?;
+        if key == "usage_usec" {
+            value.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Bytes in use from a `memory.current` file.
+pub fn parse_memory_current(memory_current: &str) -> Option<u64> {
+    memory_current.trim().parse().ok()
+}
+
+/// Number of processes the OOM killer ended, from a `memory.events` file.
+pub fn parse_oom_kills(memory_events: &str) -> Option<u64> {
+    memory_events.lines().find_map(|line| {
+        let (key, value) = line.split_once(' ')?;
+        if key == "oom_kill" {
+            value.trim().parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+/// Whether the OOM killer hit a process in the container's cgroup. The
+/// cgroup outlives the container's processes until the runtime deletes it,
+/// so this still answers after the container has exited.
+pub fn oom_killed(cgroup_root: &Path, container_id: &str) -> bool {
+    let cgroup = pkg_container::rootfs::container_cgroup_path(container_id);
+    read(
+        &cgroup_root
+            .join(cgroup.trim_start_matches('/'))
+            .join("memory.events"),
+    )
+    .and_then(|events| parse_oom_kills(&events))
+    .is_some_and(|kills| kills > 0)
+}
+
+/// Reads container usage from cgroups, remembering each container's last
+/// CPU reading so the next sample can report the rate since then.
+pub struct CgroupSampler {
+    proc_root: PathBuf,
+    cgroup_root: PathBuf,
+    last_cpu: HashMap<String, (u64, Instant)>,
+}
+
+impl CgroupSampler {
+    pub fn new() -> Self {
+        Self::with_roots("/proc", "/sys/fs/cgroup")
+    }
+
+    pub fn with_roots(proc_root: impl Into<PathBuf>, cgroup_root: impl Into<PathBuf>) -> Self {
+        Self {
+            proc_root: proc_root.into(),
+            cgroup_root: cgroup_root.into(),
+            last_cpu: HashMap::new(),
+        }
+    }
+
+    /// CPU millicores and memory bytes of the cgroup `pid` runs in, sampled
+    /// at `now`. CPU reads 0 on a container's first sample. `None` when the
+    /// cgroup files can't be read, or when the process shares the agent's
+    /// own cgroup and the numbers would cover the agent too.
+    pub fn sample(&mut self, container_id: &str, pid: u32, now: Instant) -> Option<(u64, u64)> {
+        let cgroup = self.cgroup_of(&pid.to_string())?;
+        if self.cgroup_of("self").as_deref() == Some(cgroup.as_str()) {
+            return None;
+        }
+        let dir = self.cgroup_root.join(cgroup.trim_start_matches('/'));
+        let usage_usec = parse_usage_usec(&read(&dir.join("cpu.stat"))?)?;
+        let memory_bytes = parse_memory_current(&read(&dir.join("memory.current"))?)?;
+
+        let previous = self
+            .last_cpu
+            .insert(container_id.to_string(), (usage_usec, now));
+        let cpu_millis = match previous {
+            Some((last_usec, at)) if now > at => {
+                let elapsed_usec = now.duration_since(at).as_micros() as u64;
+                usage_usec.saturating_sub(last_usec) * 1000 / elapsed_usec.max(1)
+            }
+            _ => 0,
+        };
+        Some((cpu_millis, memory_bytes))
+    }
+
+    /// Forget the readings of containers not in `ids`.
+    pub fn retain(&mut self, ids: &[String]) {
+        self.last_cpu.retain(|id, _| ids.contains(id));
+    }
+
+    fn cgroup_of(&self, pid: &str) -> Option<String> {
+        let contents = read(&self.proc_root.join(pid).join("cgroup"))?;
+        cgroup_path(&contents).map(str::to_string)
+    }
+}
+
+fn read(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
@@ -27,6 +27,10 @@ pub struct Cli {
     #[arg(long)]
     pub service_proxy_port: Option<u16>,
 
+    /// Local port for the ingress proxy (external HTTP traffic)
+    #[arg(long)]
+    pub ingress_port: Option<u16>,
+
     /// Local port for the embedded DNS server
     #[arg(long)]
     pub dns_port: Option<u16>,
@@ -39,7 +43,26 @@ pub struct Cli {
     #[arg(long, default_value_t = pkg_constants::paths::DATA_DIR.to_string())]
     pub data_dir: String,
 
+    /// Base directory of local-path volumes, one directory per
+    /// PersistentVolume
+    #[arg(long, default_value_t = format!("{}/local-path", pkg_constants::paths::DATA_DIR))]
+    pub local_path_dir: String,
+
+    /// Renew the node certificate when fewer than this many days of validity remain
+    #[arg(long)]
+    pub cert_renew_before_days: Option<i64>,
+
     /// Path to the VPC daemon Unix socket
     #[arg(long, default_value_t = format!("{}/k3rs-vpc.sock", pkg_constants::paths::DATA_DIR))]
     pub vpc_socket: String,
+
+    /// Pod networking: 'vpc' (k3rs-vpc daemon, default) or 'bridge' (veth
+    /// pairs on a local bridge, addressed from the node's pod CIDR)
+    #[arg(long)]
+    pub pod_network: Option<String>,
+
+    /// Serve the agent API (exec, logs, archive, port-forward) without
+    /// checking the server's token. For local development only
+    #[arg(long, default_value_t = false)]
+    pub insecure_agent_api: bool,
 }
@@ -0,0 +1,112 @@
+//! Per-container statuses the agent reports along with a pod's phase.
+
+use chrono::{DateTime, Utc};
+use pkg_types::pod::{ContainerState, ContainerStatus, OOM_KILLED, Pod, container_id};
+
+/// `ContainerState::Waiting` reason for an image that could not be pulled.
+pub const ERR_IMAGE_PULL: &str = "ErrImagePull";
+
+/// `ContainerState::Waiting` reason for a container that could not be created.
+pub const CREATE_CONTAINER_ERROR: &str = "CreateContainerError";
+
+/// `ContainerState::Waiting` reason for a container that could not be started.
+pub const RUN_CONTAINER_ERROR: &str = "RunContainerError";
+
+/// What the runtime knows of one of a pod's containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Observed {
+    Running {
+        started_at: Option<DateTime<Utc>>,
+    },
+    /// Stopped, or gone from the runtime.
+    Exited {
+        exit_code: Option<i32>,
+        oom_killed: bool,
+        finished_at: Option<DateTime<Utc>>,
+    },
+}
+
+/// Status of the pod's `index`-th container in `state`. The restart count
+/// carries over from the pod's last reported status for the container.
+pub fn status(pod: &Pod, index: usize, state: ContainerState) -> ContainerStatus {
+    let spec = &pod.spec.containers[index];
+    let previous = pod.container_statuses.iter().find(|s| s.name == spec.name);
+    let created = !matches!(state, ContainerState::Waiting { .. });
+    let ready = matches!(state, ContainerState::Running { .. })
+        && (spec.readiness_probe.is_none() || pod.ready);
+    ContainerStatus {
+        name: spec.name.clone(),
+        image: spec.image.clone(),
+        container_id: created.then(|| container_id(&pod.id, &spec.name)),
+        state,
+        restart_count: previous.map_or(0, |s| s.restart_count),
+        ready,
+    }
+}
+
+/// Every container of the pod waiting for `reason`.
+pub fn waiting(pod: &Pod, reason: &str, message: Option<String>) -> Vec<ContainerStatus> {
+    (0..pod.spec.containers.len())
+        .map(|index| {
+            let state = ContainerState::Waiting {
+                reason: reason.to_string(),
+                message: message.clone(),
+            };
+            status(pod, index, state)
+        })
+        .collect()
+}
+
+/// Statuses from what the runtime reports for each container, in spec
+/// order. Times the runtime does not know are taken from the last report,
+/// or `now`, so an unchanged container reports an unchanged status.
+pub fn observed(pod: &Pod, observed: &[Observed], now: DateTime<Utc>) -> Vec<ContainerStatus> {
+    observed
+        .iter()
+        .enumerate()
+        .map(|(index, observed)| {
+            let previous = pod
+                .container_statuses
+                .iter()
+                .find(|s| s.name == pod.spec.containers[index].name)
+                .map(|s| &s.state);
+            let state = match *observed {
+                Observed::Running { started_at } => ContainerState::Running {
+                    started_at: started_at
+                        .or(match previous {
+                            Some(ContainerState::Running { started_at }) => Some(*started_at),
+                            _ => None,
+                        })
+                        .unwrap_or(now),
+                },
+                Observed::Exited {
+                    exit_code,
+                    oom_killed,
+                    finished_at,
+                } => ContainerState::Terminated {
+                    exit_code,
+                    reason: terminated_reason(exit_code, oom_killed).to_string(),
+                    finished_at: finished_at
+                        .or(match previous {
+                            Some(ContainerState::Terminated { finished_at, .. }) => {
+                                Some(*finished_at)
+                            }
+                            _ => None,
+                        })
+                        .unwrap_or(now),
+                },
+            };
+            status(pod, index, state)
+        })
+        .collect()
+}
+
+/// `Completed` for a clean exit, `OOMKilled` for a container killed on its
+/// memory limit, otherwise `Error`.
+pub fn terminated_reason(exit_code: Option<i32>, oom_killed: bool) -> &'static str {
+    match exit_code {
+        _ if oom_killed => OOM_KILLED,
+        Some(0) => "Completed",
+        _ => "Error",
+    }
+}
@@ -1,15 +1,33 @@
 use crate::cache::AgentStateCache;
+use crate::cgroup::CgroupSampler;
 use crate::connectivity::ConnectivityManager;
-use std::sync::Arc;
+use crate::metrics::HEARTBEAT_FAILURES_METRIC;
+use crate::pressure::{PressureMonitor, PressureThresholds, Usage};
+use crate::registration::ServerToken;
+use crate::runtime_config::SharedRuntimeConfig;
+use chrono::Utc;
+use pkg_container::ContainerStore;
+use pkg_metrics::MetricsRegistry;
+use pkg_types::metrics::PodUsage;
+use pkg_types::node::{NodeConditionType, NodeHeartbeat};
+use pkg_types::pod::Pod;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use sysinfo::{Pid, ProcessesToUpdate, System};
 use tracing::{info, warn};
 
 /// Start the heartbeat loop on a dedicated OS thread with its own tokio runtime.
+#[allow(clippy::too_many_arguments)]
 pub fn start_heartbeat_loop(
     server_base: String,
     node_name: String,
-    token: String,
+    token: ServerToken,
     connectivity: Arc<ConnectivityManager>,
     cache: Arc<std::sync::RwLock<AgentStateCache>>,
+    containers: Arc<OnceLock<ContainerStore>>,
+    pressure: PressureThresholds,
+    runtime_config: SharedRuntimeConfig,
+    metrics: Arc<MetricsRegistry>,
 ) {
     std::thread::spawn(move || {
         let rt = tokio::runtime::Builder::new_current_thread()
@@ -18,16 +36,19 @@ pub fn start_heartbeat_loop(
             .expect("heartbeat runtime");
         rt.block_on(async move {
             let client = reqwest::Client::new();
+            let mut sys = System::new();
+            let mut cgroups = CgroupSampler::new();
+            let mut pressure = PressureMonitor::new(pressure);
             let mut fail_count = 0u32;
             loop {
-                // Connected: poll every 10s. Failing: exponential backoff 1s→2s→4s→30s.
+                // Connected: poll every heartbeat-interval (10s). Failing: exponential backoff 1s→2s→4s→30s.
                 //
                 // `fail_count` is incremented *after* each failure, so it is
                 // 1-based at the top of the loop. We subtract 1 to convert to
                 // the 0-based index that `backoff_duration` expects, ensuring
                 // the first retry fires after 1s (not 2s).
                 let delay = if fail_count == 0 {
-                    std::time::Duration::from_secs(pkg_constants::timings::HEARTBEAT_INTERVAL_SECS)
+                    runtime_config.read().unwrap().heartbeat_interval
                 } else {
                     ConnectivityManager::backoff_duration(fail_count.saturating_sub(1))
                 };
@@ -44,9 +65,13 @@ pub fn start_heartbeat_loop(
                     server_base.trim_end_matches('/'),
                     node_name
                 );
+                let pods = cache.read().unwrap().pods.clone();
+                let report =
+                    sample_usage(&mut sys, &mut cgroups, &mut pressure, &containers, &pods);
                 match client
                     .put(&url)
                     .header("Authorization", format!("Bearer {}", token))
+                    .json(&report)
                     .timeout(std::time::Duration::from_secs(
                         pkg_constants::timings::HEARTBEAT_TIMEOUT_SECS,
                     ))
@@ -63,6 +88,7 @@ pub fn start_heartbeat_loop(
                     }
                     Ok(resp) => {
                         fail_count += 1;
+                        metrics.counter_inc(HEARTBEAT_FAILURES_METRIC);
                         warn!(
                             "Heartbeat failed for {} (status={})",
                             node_name,
@@ -77,6 +103,7 @@ pub fn start_heartbeat_loop(
                     }
                     Err(e) => {
                         fail_count += 1;
+                        metrics.counter_inc(HEARTBEAT_FAILURES_METRIC);
                         warn!("Heartbeat failed for {}: {}", node_name, e);
                         let age = cache.read().unwrap().age_secs();
                         connectivity.set_reconnecting(fail_count);
@@ -91,3 +118,107 @@ pub fn start_heartbeat_loop(
     });
     info!("Heartbeat loop started");
 }
+
+/// Measure current node and per-pod usage, and the pressure conditions, for
+/// the heartbeat body. CPU usage is the average since the previous call, so
+/// the first sample after start reads low.
+fn sample_usage(
+    sys: &mut System,
+    cgroups: &mut CgroupSampler,
+    pressure: &mut PressureMonitor,
+    containers: &OnceLock<ContainerStore>,
+    pods: &[Pod],
+) -> NodeHeartbeat {
+    sys.refresh_cpu_usage();
+    sys.refresh_memory();
+    let cores = sys.cpus().len() as f64;
+    let pods = containers
+        .get()
+        .map(|store| pod_usage(sys, cgroups, store, pods, Instant::now()))
+        .unwrap_or_default();
+
+    let now = Utc::now();
+    // Where the container runtime keeps images, bundles and logs
+    let data_dir = std::path::Path::new(pkg_constants::paths::DATA_DIR).join("runtime");
+    let disk = crate::loops::image_gc::disk_usage(&data_dir).map(|d| Usage {
+        used: d.capacity.saturating_sub(d.available),
+        total: d.capacity,
+    });
+    pressure.observe(NodeConditionType::DiskPressure, disk, now);
+    let memory = Usage {
+        used: sys.total_memory().saturating_sub(sys.available_memory()),
+        total: sys.total_memory(),
+    };
+    pressure.observe(NodeConditionType::MemoryPressure, Some(memory), now);
+
+    NodeHeartbeat {
+        cpu_millis: (sys.global_cpu_usage() as f64 / 100.0 * cores * 1000.0) as u64,
+        memory_bytes: sys.used_memory(),
+        running_containers: containers.get().map_or(0, |s| s.running_count() as u32),
+        agent_version: env!("CARGO_PKG_VERSION").to_string(),
+        timestamp: now,
+        pods,
+        conditions: pressure.conditions(),
+    }
+}
+
+/// Usage of each pod with a running container process, summed over its
+/// containers. OCI containers are read from their cgroup; microVM containers,
+/// and containers whose cgroup can't be read, from their process — for a
+/// microVM that is the VMM, so the numbers cover the whole guest.
+pub(crate) fn pod_usage(
+    sys: &mut System,
+    cgroups: &mut CgroupSampler,
+    store: &ContainerStore,
+    pods: &[Pod],
+    now: Instant,
+) -> Vec<PodUsage> {
+    let mut usage: Vec<Option<PodUsage>> = vec![None; pods.len()];
+    let mut sampled = Vec::new();
+    let mut by_process: Vec<(usize, Pid)> = Vec::new();
+    for (i, pod) in pods.iter().enumerate() {
+        for id in pod.container_ids() {
+            let Some(entry) = store.get(&id) else {
+                continue;
+            };
+            let Some(pid) = entry.pid else {
+                continue;
+            };
+            let from_cgroup = if entry.runtime_name == "vm" {
+                None
+            } else {
+                cgroups.sample(&id, pid, now)
+            };
+            sampled.push(id);
+            match from_cgroup {
+                Some((cpu_millis, memory_bytes)) => {
+                    add(&mut usage, pods, i, cpu_millis, memory_bytes)
+                }
+                None => by_process.push((i, Pid::from_u32(pid))),
+            }
+        }
+    }
+    cgroups.retain(&sampled);
+
+    let to_refresh: Vec<Pid> = by_process.iter().map(|(_, pid)| *pid).collect();
+    sys.refresh_processes(ProcessesToUpdate::Some(&to_refresh), true);
+    for (i, pid) in by_process {
+        if let Some(process) = sys.process(pid) {
+            // `cpu_usage` is a percentage of one core.
+            let cpu_millis = (process.cpu_usage() as f64 * 10.0) as u64;
+            add(&mut usage, pods, i, cpu_millis, process.memory());
+        }
+    }
+    usage.into_iter().flatten().collect()
+}
+
+fn add(usage: &mut [Option<PodUsage>], pods: &[Pod], i: usize, cpu_millis: u64, memory_bytes: u64) {
+    let entry = usage[i].get_or_insert_with(|| PodUsage {
+        namespace: pods[i].namespace.clone(),
+        name: pods[i].name.clone(),
+        cpu_millis: 0,
+        memory_bytes: 0,
+    });
+    entry.cpu_millis += cpu_millis;
+    entry.memory_bytes += memory_bytes;
+}
@@ -0,0 +1,107 @@
+//! Image garbage collection: when the node's disk or image cache grows past
+//! its limits, delete the least recently used images no pod needs.
+
+use pkg_container::image::ImageInfo;
+use pkg_types::config::AgentConfigFile;
+use pkg_types::pod::{Pod, PodStatus};
+use std::collections::HashSet;
+
+const DEFAULT_HIGH_THRESHOLD: u8 = 85;
+const DEFAULT_LOW_THRESHOLD: u8 = 80;
+
+/// Limits that trigger image GC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcPolicy {
+    /// Disk usage (percent) of the data dir filesystem that starts GC.
+    pub high_threshold: u8,
+    /// Disk usage (percent) GC frees down to.
+    pub low_threshold: u8,
+    /// Cap on the total size of cached images.
+    pub max_image_bytes: Option<u64>,
+}
+
+impl GcPolicy {
+    pub fn from_config(cfg: &AgentConfigFile) -> Self {
+        let high_threshold = cfg
+            .image_gc_high_threshold
+            .unwrap_or(DEFAULT_HIGH_THRESHOLD)
+            .min(100);
+        let low_threshold = cfg
+            .image_gc_low_threshold
+            .unwrap_or(DEFAULT_LOW_THRESHOLD)
+            .min(high_threshold);
+        Self {
+            high_threshold,
+            low_threshold,
+            max_image_bytes: cfg.image_gc_max_bytes,
+        }
+    }
+}
+
+/// Size and free space of the filesystem holding the images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskUsage {
+    pub capacity: u64,
+    pub available: u64,
+}
+
+/// Bytes of images to delete to get back within `policy`: down to the low
+/// threshold once disk usage reaches the high one, and under the image size
+/// cap. Zero when nothing needs collecting.
+pub fn bytes_to_free(policy: &GcPolicy, disk: Option<DiskUsage>, image_bytes: u64) -> u64 {
+    let mut to_free = 0;
+    if let Some(disk) = disk
+        && disk.capacity > 0
+    {
+        let capacity = disk.capacity as u128;
+        let used = disk.capacity.saturating_sub(disk.available) as u128;
+        if used * 100 >= capacity * policy.high_threshold as u128 {
+            let target = capacity * policy.low_threshold as u128 / 100;
+            to_free = used.saturating_sub(target) as u64;
+        }
+    }
+    if let Some(max) = policy.max_image_bytes {
+        to_free = to_free.max(image_bytes.saturating_sub(max));
+    }
+    to_free
+}
+
+/// IDs of the images needed by the pods assigned to this node — including
+/// pods waiting to restart — and by the containers the runtime still tracks.
+pub fn images_in_use<'a>(
+    pods: &'a [Pod],
+    tracked_images: impl IntoIterator<Item = &'a str>,
+    image_id: impl Fn(&str) -> String,
+) -> HashSet<String> {
+    pods.iter()
+        .filter(|p| !matches!(p.status, PodStatus::Succeeded | PodStatus::Failed))
+        .flat_map(|p| p.spec.init_containers.iter().chain(&p.spec.containers))
+        .map(|c| c.image.as_str())
+        .chain(tracked_images)
+        .map(image_id)
+        .collect()
+}
+
+/// The images to delete, least recently used first, until `to_free` bytes
+/// are reclaimed. Images in `in_use` are never chosen, so the result may
+/// reclaim less than asked.
+pub fn select_victims<'a>(
+    images: &'a [ImageInfo],
+    in_use: &HashSet<String>,
+    to_free: u64,
+) -> Vec<&'a ImageInfo> {
+    let mut candidates: Vec<&ImageInfo> =
+        images.iter().filter(|i| !in_use.contains(&i.id)).collect();
+    // Never-used images sort first.
+    candidates.sort_by_key(|i| i.last_used);
+
+    let mut freed = 0;
+    candidates
+        .into_iter()
+        .take_while(|i| {
+            let take = freed < to_free;
+            freed += i.size;
+            take
+        })
+        .collect()
+}
@@ -0,0 +1,116 @@
+//! Init containers: run to completion, one at a time, before the app container.
+
+use crate::restart::{self, CrashLoopTracker, ExitAction};
+use pkg_types::pod::{
+    CRASH_LOOP_BACK_OFF, ContainerSpec, INIT_STATUS_PREFIX, Pod, PodStatus, PodStatusUpdate,
+};
+use std::future::Future;
+use std::time::Instant;
+
+/// Why a pod's init containers did not all succeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitFailure {
+    /// The container ran and exited non-zero.
+    Exited { container: String, exit_code: i32 },
+    /// The container could not be pulled, created or run.
+    Error { container: String, message: String },
+}
+
+/// Container ID of a pod's `index`-th init container.
+pub fn init_container_id(pod_id: &str, index: usize) -> String {
+    format!("{}-init-{}", pod_id, index)
+}
+
+/// Status reported once `done` of the pod's init containers have completed.
+pub fn init_progress(pod: &Pod, done: usize) -> PodStatusUpdate {
+    PodStatusUpdate::Detailed {
+        status: PodStatus::ContainerCreating,
+        status_message: Some(format!(
+            "{}{}/{}",
+            INIT_STATUS_PREFIX,
+            done,
+            pod.spec.init_containers.len()
+        )),
+        restart_count: None,
+        ready: None,
+        pod_ip: None,
+        container_statuses: None,
+    }
+}
+
+/// Run the pod's init containers in order. `run` starts the `index`-th one
+/// and resolves to its exit code; the first failure stops the sequence.
+pub async fn run_all<F, Fut>(pod: &Pod, mut run: F) -> Result<(), InitFailure>
+where
+    F: FnMut(usize, ContainerSpec) -> Fut,
+    Fut: Future<Output = anyhow::Result<i32>>,
+{
+    for (index, spec) in pod.spec.init_containers.iter().enumerate() {
+        let container = spec.name.clone();
+        match run(index, spec.clone()).await {
+            Ok(0) => {}
+            Ok(exit_code) => {
+                return Err(InitFailure::Exited {
+                    container,
+                    exit_code,
+                });
+            }
+            Err(e) => {
+                return Err(InitFailure::Error {
+                    container,
+                    message: e.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Status to report after an init container failed. Policies that restart
+/// containers send the pod back to Scheduled behind a crash-loop delay;
+/// `Never` fails it.
+pub fn failure_update(
+    pod: &Pod,
+    failure: &InitFailure,
+    restarts: &mut CrashLoopTracker,
+    now: Instant,
+) -> PodStatusUpdate {
+    let (container, exit_code, detail) = match failure {
+        InitFailure::Exited {
+            container,
+            exit_code,
+        } => (
+            container,
+            Some(*exit_code),
+            format!("exit code {}", exit_code),
+        ),
+        InitFailure::Error { container, message } => (container, None, message.clone()),
+    };
+    match restart::exit_action(pod.spec.restart_policy, exit_code) {
+        ExitAction::Restart => {
+            let delay = restarts.record_exit(&pod.id, now);
+            PodStatusUpdate::Detailed {
+                status: PodStatus::Scheduled,
+                status_message: Some(format!(
+                    "{}: back-off {}s restarting failed init container {} ({})",
+                    CRASH_LOOP_BACK_OFF,
+                    delay.as_secs(),
+                    container,
+                    detail
+                )),
+                restart_count: Some(pod.restart_count + 1),
+                ready: None,
+                pod_ip: None,
+                container_statuses: None,
+            }
+        }
+        ExitAction::Succeeded | ExitAction::Failed => PodStatusUpdate::Detailed {
+            status: PodStatus::Failed,
+            status_message: Some(format!("Init container {} failed ({})", container, detail)),
+            restart_count: None,
+            ready: None,
+            pod_ip: None,
+            container_statuses: None,
+        },
+    }
+}
@@ -0,0 +1,103 @@
+//! The node's side of the local-path provisioner: one directory per volume
+//! under `--local-path-dir`, created and removed on the server's request.
+
+use crate::api::AgentState;
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::volume::{LocalVolume, LocalVolumeRequest};
+use std::path::{Path as FsPath, PathBuf};
+use tracing::{error, info};
+
+/// File next to a volume's data recording the claim it was made for.
+pub const CLAIM_FILE: &str = ".k3rs-claim.json";
+
+/// The directory of volume `name`, or an error when the name could escape
+/// the base directory.
+pub fn volume_dir(base: &FsPath, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(format!("invalid volume name '{}'", name));
+    }
+    Ok(base.join(name))
+}
+
+/// Create the directory of volume `name`. Creating one that exists keeps
+/// its data, so a retried request is harmless.
+pub fn create_volume(
+    base: &FsPath,
+    name: &str,
+    request: &LocalVolumeRequest,
+) -> anyhow::Result<PathBuf> {
+    let dir = volume_dir(base, name).map_err(anyhow::Error::msg)?;
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join(CLAIM_FILE), serde_json::to_vec_pretty(request)?)?;
+    Ok(dir)
+}
+
+/// Remove the directory of volume `name` and its data. One already gone is
+/// not an error.
+pub fn remove_volume(base: &FsPath, name: &str) -> anyhow::Result<()> {
+    let dir = volume_dir(base, name).map_err(anyhow::Error::msg)?;
+    match std::fs::remove_dir_all(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// PUT /volumes/{name} — create a volume's directory; returns its path.
+pub async fn put_volume_handler(
+    Path(name): Path<String>,
+    State(state): State<AgentState>,
+    Json(request): Json<LocalVolumeRequest>,
+) -> Response {
+    if let Err(message) = volume_dir(&state.local_path_dir, &name) {
+        return bad_request(message);
+    }
+    match create_volume(&state.local_path_dir, &name, &request) {
+        Ok(dir) => {
+            info!(
+                "Volume {} created at {} for {}",
+                name,
+                dir.display(),
+                request.claim
+            );
+            Json(LocalVolume {
+                path: dir.to_string_lossy().into_owned(),
+            })
+            .into_response()
+        }
+        Err(e) => internal_error(format!("failed to create volume {}: {:#}", name, e)),
+    }
+}
+
+/// DELETE /volumes/{name} — remove a volume's directory and its data.
+pub async fn delete_volume_handler(
+    Path(name): Path<String>,
+    State(state): State<AgentState>,
+) -> Response {
+    if let Err(message) = volume_dir(&state.local_path_dir, &name) {
+        return bad_request(message);
+    }
+    match remove_volume(&state.local_path_dir, &name) {
+        Ok(()) => {
+            info!("Volume {} removed", name);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(e) => internal_error(format!("failed to remove volume {}: {:#}", name, e)),
+    }
+}
+
+fn bad_request(message: String) -> Response {
+    let err = ApiError::new(ErrorReason::BadRequest, message);
+    (StatusCode::BAD_REQUEST, Json(err)).into_response()
+}
+
+fn internal_error(message: String) -> Response {
+    error!("{}", message);
+    let err = ApiError::internal(message);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(err)).into_response()
+}
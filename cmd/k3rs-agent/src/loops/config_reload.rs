@@ -0,0 +1,53 @@
+use crate::runtime_config::{self, SharedRuntimeConfig};
+use pkg_metrics::log_filter::LogFilter;
+use pkg_types::config::{AgentConfigFile, ConfigFileWatcher};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Start the config reload loop (every 5s): apply the live settings of a
+/// changed config file, and log the changed settings that need a restart.
+pub fn start(
+    mut watcher: ConfigFileWatcher<AgentConfigFile>,
+    path: String,
+    runtime_config: SharedRuntimeConfig,
+    log_filter: Arc<LogFilter>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            pkg_constants::timings::CONFIG_RELOAD_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            let reloaded = match runtime_config::reload(&mut watcher, &runtime_config) {
+                Ok(Some(reloaded)) => reloaded,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!(
+                        "Config file {} not reloaded, keeping the previous config: {:#}",
+                        path, e
+                    );
+                    continue;
+                }
+            };
+            if reloaded.applied.iter().any(|key| key == "log-level")
+                && let Err(e) = log_filter.set(reloaded.value.log_level.as_deref())
+            {
+                warn!("Failed to apply log-level: {}", e);
+            }
+            if !reloaded.applied.is_empty() {
+                info!(
+                    "Config file {} reloaded: {}",
+                    path,
+                    reloaded.applied.join(", ")
+                );
+            }
+            if !reloaded.restart_required.is_empty() {
+                warn!(
+                    "Config file {}: {} changed; restart k3rs-agent to apply",
+                    path,
+                    reloaded.restart_required.join(", ")
+                );
+            }
+        }
+    });
+}
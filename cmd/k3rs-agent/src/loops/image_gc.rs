@@ -0,0 +1,149 @@
+use crate::connectivity::ConnectivityManager;
+use crate::image_gc::{self, DiskUsage};
+use crate::registration::ServerToken;
+use crate::runtime_config::SharedRuntimeConfig;
+use pkg_container::ContainerRuntime;
+use pkg_metrics::MetricsRegistry;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Images deleted by image GC.
+pub const IMAGES_GC_REMOVED_METRIC: &str = "k3rs_images_gc_removed_total";
+/// Bytes of image layers reclaimed by image GC.
+pub const IMAGES_GC_RECLAIMED_METRIC: &str = "k3rs_images_gc_reclaimed_bytes_total";
+
+/// Start the image garbage collection loop (every 5 minutes).
+#[allow(clippy::too_many_arguments)]
+pub fn start(
+    runtime: Option<Arc<ContainerRuntime>>,
+    client: reqwest::Client,
+    server: String,
+    token: ServerToken,
+    node_name: String,
+    connectivity: Arc<ConnectivityManager>,
+    runtime_config: SharedRuntimeConfig,
+    metrics: Arc<MetricsRegistry>,
+) {
+    metrics.register_counter(IMAGES_GC_REMOVED_METRIC, "Images deleted by image GC");
+    metrics.register_counter(
+        IMAGES_GC_RECLAIMED_METRIC,
+        "Bytes of image layers reclaimed by image GC",
+    );
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            pkg_constants::timings::IMAGE_GC_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+
+            // "In use" comes from the server — never collect without it
+            if !connectivity.is_connected() {
+                continue;
+            }
+            let Some(ref rt) = runtime else {
+                continue;
+            };
+
+            let images = match rt.list_images().await {
+                Ok(images) => images,
+                Err(e) => {
+                    warn!("Image GC failed to list images: {}", e);
+                    continue;
+                }
+            };
+            let image_bytes = images.iter().map(|i| i.size).sum();
+            let policy = runtime_config.read().unwrap().image_gc;
+            let to_free = image_gc::bytes_to_free(&policy, disk_usage(rt.data_dir()), image_bytes);
+            if to_free == 0 {
+                continue;
+            }
+
+            let url = format!(
+                "{}/api/v1/pods?fieldSelector=spec.nodeName={}",
+                server.trim_end_matches('/'),
+                node_name
+            );
+            let pods = match client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", token))
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+            {
+                Ok(resp) => match resp.json::<Vec<pkg_types::pod::Pod>>().await {
+                    Ok(pods) => pods,
+                    Err(e) => {
+                        warn!("Image GC failed to parse pods: {}", e);
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    warn!("Image GC failed to fetch pods: {}", e);
+                    continue;
+                }
+            };
+            let tracked = rt.container_store().list();
+            let in_use = image_gc::images_in_use(
+                &pods,
+                tracked.iter().map(|e| e.image.as_str()),
+                pkg_container::image::image_id,
+            );
+
+            info!(
+                "Image GC: {} cached in {} images, freeing {}",
+                format_bytes(image_bytes),
+                images.len(),
+                format_bytes(to_free)
+            );
+            let mut reclaimed = 0;
+            for image in image_gc::select_victims(&images, &in_use, to_free) {
+                match rt.delete_image(&image.id).await {
+                    Ok(()) => {
+                        info!(
+                            "Image GC: removed {} ({}, last used {})",
+                            image.id,
+                            image.size_human,
+                            image
+                                .last_used
+                                .map(|t| t.to_rfc3339())
+                                .unwrap_or_else(|| "never".to_string())
+                        );
+                        reclaimed += image.size;
+                        metrics.counter_inc(IMAGES_GC_REMOVED_METRIC);
+                        metrics.counter_add(IMAGES_GC_RECLAIMED_METRIC, image.size);
+                    }
+                    Err(e) => warn!("Image GC failed to remove {}: {}", image.id, e),
+                }
+            }
+            if reclaimed < to_free {
+                warn!(
+                    "Image GC reclaimed {} of {}: remaining images are in use",
+                    format_bytes(reclaimed),
+                    format_bytes(to_free)
+                );
+            }
+        }
+    });
+}
+
+/// Usage of the filesystem holding `path`: the mounted disk with the longest
+/// mount point that contains it.
+pub(crate) fn disk_usage(path: &Path) -> Option<DiskUsage> {
+    let path = path.canonicalize().ok()?;
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|d| path.starts_with(d.mount_point()))
+        .max_by_key(|d| d.mount_point().as_os_str().len())
+        .map(|d| DiskUsage {
+            capacity: d.total_space(),
+            available: d.available_space(),
+        })
+}
+
+pub(super) fn format_bytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1_048_576.0)
+}
@@ -1,5 +1,6 @@
 use crate::cache::AgentStateCache;
 use crate::connectivity::ConnectivityManager;
+use crate::registration::ServerToken;
 use pkg_container::ContainerRuntime;
 use std::sync::Arc;
 use tracing::warn;
@@ -9,7 +10,7 @@ pub fn start(
     runtime: Option<Arc<ContainerRuntime>>,
     client: reqwest::Client,
     server: String,
-    token: String,
+    token: ServerToken,
     cache: Arc<std::sync::RwLock<AgentStateCache>>,
     connectivity: Arc<ConnectivityManager>,
 ) {
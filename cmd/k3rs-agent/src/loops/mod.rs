@@ -1,25 +1,41 @@
 use crate::cache::AgentStateCache;
 use crate::connectivity::ConnectivityManager;
+use crate::registration::ServerToken;
+use crate::runtime_config::SharedRuntimeConfig;
 use crate::store::AgentStore;
 use crate::vpc_client::VpcClient;
-use pkg_container::ContainerRuntime;
+use pkg_container::logs::LogRotation;
+use pkg_container::{ContainerRuntime, ContainerStore};
+use pkg_metrics::MetricsRegistry;
+use pkg_metrics::log_filter::LogFilter;
 use pkg_network::dns::DnsServer;
+use pkg_proxy::ingress_proxy::IngressProxy;
 use pkg_proxy::service_proxy::ServiceProxy;
 use pkg_types::node::NodeRegistrationRequest;
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 use tracing::{info, warn};
 
+pub mod config_reload;
+pub mod image_gc;
 pub mod image_report;
+pub mod orphan_gc;
 pub mod pod_sync;
+pub mod pod_watch;
 pub mod reconnect;
 pub mod route_sync;
+pub mod static_pods;
+pub mod tunnel;
 
 /// Start all controller loops on a dedicated OS thread with its own multi-threaded runtime.
 #[allow(clippy::too_many_arguments)]
 pub fn start_controller_loops(
     server: String,
-    token: String,
+    token: ServerToken,
     service_proxy: Arc<ServiceProxy>,
+    ingress_proxy: Arc<IngressProxy>,
     dns_server: Arc<DnsServer>,
     cache: Arc<std::sync::RwLock<AgentStateCache>>,
     connectivity: Arc<ConnectivityManager>,
@@ -27,8 +43,20 @@ pub fn start_controller_loops(
     node_name: String,
     store: AgentStore,
     vpc_client: Arc<VpcClient>,
+    containers: Arc<OnceLock<ContainerStore>>,
+    runtime_config: SharedRuntimeConfig,
+    orphan_gc_grace: Duration,
+    static_pod_dir: PathBuf,
+    local_path_dir: PathBuf,
+    log_rotation: LogRotation,
+    metrics: Arc<MetricsRegistry>,
+    log_filter: Arc<LogFilter>,
+    bridge_networking: bool,
+    api_auth: crate::api_auth::ApiAuth,
 ) {
-    info!("Starting node controllers (pod-sync, image-report, route-sync)");
+    info!(
+        "Starting node controllers (pod-sync, pod-watch, image-report, image-gc, orphan-gc, static-pods, route-sync)"
+    );
 
     std::thread::spawn(move || {
         let rt = tokio::runtime::Builder::new_multi_thread()
@@ -51,10 +79,11 @@ pub fn start_controller_loops(
 
             // Init container runtime (may download youki/crun)
             let runtime: Option<Arc<ContainerRuntime>> =
-                match ContainerRuntime::new(None::<&str>).await {
+                match ContainerRuntime::new(None::<&str>, log_rotation).await {
                     Ok(rt) => {
                         let rt_arc = Arc::new(rt);
                         info!("Container runtime ready: {}", rt_arc.backend_name());
+                        let _ = containers.set(rt_arc.container_store().clone());
 
                         // Initialize k3rs0 dummy device for DNS VIP + routing anchor (Linux only, non-fatal)
                         #[cfg(target_os = "linux")]
@@ -68,16 +97,35 @@ pub fn start_controller_loops(
                                 );
                             }
 
-                            // Start DNS listener on bridge VIP (port 53) so pods can resolve
+                            // Start DNS listener on bridge VIP (port 53) so pods can resolve.
+                            // Without the privilege (or with port 53 taken), listen on the
+                            // DNS port instead and DNAT the VIP's port 53 to it.
+                            let dns_port = dns_server.listen_addr().port();
                             let dns_vip_addr: std::net::SocketAddr =
                                 format!("[{}]:53", pkg_constants::network::DNS_VIP)
                                     .parse()
                                     .expect("invalid DNS_VIP");
+                            let mut redirect_vip = false;
                             if let Err(e) = dns_server.start_on(dns_vip_addr).await {
+                                let high_addr = std::net::SocketAddr::new(dns_vip_addr.ip(), dns_port);
                                 warn!(
-                                    "DNS VIP listener on {} failed: {} (pod DNS may not work)",
-                                    dns_vip_addr, e
+                                    "DNS VIP listener on {} failed: {}, falling back to {}",
+                                    dns_vip_addr, e, high_addr
                                 );
+                                match dns_server.start_on(high_addr).await {
+                                    Ok(()) => redirect_vip = true,
+                                    Err(e) => warn!(
+                                        "DNS VIP listener on {} failed: {} (pod DNS may not work)",
+                                        high_addr, e
+                                    ),
+                                }
+                            }
+                            // VM guests use the link-local DNS proxy address on port 53.
+                            if let Err(e) =
+                                pkg_network::linux::dns_redirect::ensure_dns_redirect(dns_port, redirect_vip)
+                                    .await
+                            {
+                                warn!("DNS port redirect not installed: {} (pod DNS may not work)", e);
                             }
                         }
 
@@ -85,8 +133,13 @@ pub fn start_controller_loops(
                         if let Some(api_port) = initial_api_port {
                             let agent_state = crate::api::AgentState {
                                 runtime: rt_arc.clone(),
+                                service_proxy: service_proxy.clone(),
+                                metrics: metrics.clone(),
+                                log_filter: log_filter.clone(),
+                                local_path_dir: local_path_dir.clone(),
                             };
-                            let agent_router = crate::api::create_agent_router(agent_state);
+                            let agent_router =
+                                crate::api::create_agent_router(agent_state, api_auth);
                             let listener =
                                 tokio::net::TcpListener::bind(format!("0.0.0.0:{}", api_port))
                                     .await
@@ -95,6 +148,13 @@ pub fn start_controller_loops(
                             tokio::spawn(async move {
                                 axum::serve(listener, agent_router).await.ok();
                             });
+                            tunnel::start(
+                                server.clone(),
+                                token.clone(),
+                                node_name.clone(),
+                                cache.clone(),
+                                api_port,
+                            );
                         }
 
                         // --- Agent Recovery ---
@@ -128,6 +188,42 @@ pub fn start_controller_loops(
                 connectivity.clone(),
             );
 
+            image_gc::start(
+                runtime.clone(),
+                client.clone(),
+                server.clone(),
+                token.clone(),
+                node_name.clone(),
+                connectivity.clone(),
+                runtime_config.clone(),
+                metrics.clone(),
+            );
+
+            // Pods pod sync is creating or tearing down; orphan GC leaves them be.
+            let in_flight: Arc<Mutex<HashSet<String>>> = Default::default();
+            orphan_gc::start(
+                runtime.clone(),
+                client.clone(),
+                server.clone(),
+                token.clone(),
+                node_name.clone(),
+                connectivity.clone(),
+                in_flight.clone(),
+                orphan_gc_grace,
+                metrics.clone(),
+            );
+
+            static_pods::start(
+                runtime.clone(),
+                client.clone(),
+                server.clone(),
+                token.clone(),
+                node_name.clone(),
+                connectivity.clone(),
+                static_pod_dir,
+                runtime_config.clone(),
+            );
+
             reconnect::start(
                 client.clone(),
                 server.clone(),
@@ -137,6 +233,7 @@ pub fn start_controller_loops(
                 cache.clone(),
                 connectivity.clone(),
                 store.clone(),
+                bridge_networking,
             );
 
             // macOS: create userspace switch for VM VPC networking
@@ -148,6 +245,15 @@ pub fn start_controller_loops(
                 Some(switch)
             };
 
+            let pod_watch = Arc::new(pod_watch::PodWatch::default());
+            pod_watch::start(
+                client.clone(),
+                server.clone(),
+                token.clone(),
+                node_name.clone(),
+                connectivity.clone(),
+                pod_watch.clone(),
+            );
             pod_sync::start(
                 runtime,
                 client.clone(),
@@ -158,6 +264,11 @@ pub fn start_controller_loops(
                 connectivity.clone(),
                 store.clone(),
                 vpc_client.clone(),
+                pod_watch,
+                in_flight,
+                runtime_config.clone(),
+                metrics.clone(),
+                bridge_networking,
                 #[cfg(target_os = "macos")]
                 mac_switch,
             );
@@ -167,11 +278,13 @@ pub fn start_controller_loops(
                 server.clone(),
                 token.clone(),
                 service_proxy,
+                ingress_proxy,
                 dns_server,
                 cache.clone(),
                 connectivity.clone(),
                 store.clone(),
                 vpc_client,
+                runtime_config,
             );
 
             // Keep this thread alive forever
@@ -0,0 +1,153 @@
+use crate::connectivity::ConnectivityManager;
+use crate::orphan_gc;
+use crate::registration::ServerToken;
+use pkg_container::ContainerRuntime;
+use pkg_metrics::MetricsRegistry;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// Leftover container, VM and log entries deleted by orphan GC.
+pub const ORPHANS_REMOVED_METRIC: &str = "k3rs_agent_orphans_removed_total";
+/// Bytes reclaimed by orphan GC.
+pub const ORPHANS_RECLAIMED_METRIC: &str = "k3rs_agent_orphans_reclaimed_bytes_total";
+
+/// Start the orphan garbage collection loop (every minute). `in_flight`
+/// holds the pods pod sync is creating or tearing down; their files are
+/// never touched.
+#[allow(clippy::too_many_arguments)]
+pub fn start(
+    runtime: Option<Arc<ContainerRuntime>>,
+    client: reqwest::Client,
+    server: String,
+    token: ServerToken,
+    node_name: String,
+    connectivity: Arc<ConnectivityManager>,
+    in_flight: Arc<Mutex<HashSet<String>>>,
+    grace: Duration,
+    metrics: Arc<MetricsRegistry>,
+) {
+    metrics.register_counter(
+        ORPHANS_REMOVED_METRIC,
+        "Leftover container, VM and log entries deleted by orphan GC",
+    );
+    metrics.register_counter(ORPHANS_RECLAIMED_METRIC, "Bytes reclaimed by orphan GC");
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            pkg_constants::timings::ORPHAN_GC_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+
+            // What is wanted comes from the server — never collect without it
+            if !connectivity.is_connected() {
+                continue;
+            }
+            let Some(ref rt) = runtime else {
+                continue;
+            };
+
+            let url = format!(
+                "{}/api/v1/pods?fieldSelector=spec.nodeName={}",
+                server.trim_end_matches('/'),
+                node_name
+            );
+            let pods = match client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", token))
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+            {
+                Ok(resp) => match resp.json::<Vec<pkg_types::pod::Pod>>().await {
+                    Ok(pods) => pods,
+                    Err(e) => {
+                        warn!("Orphan GC skipped: failed to parse pods: {}", e);
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    warn!("Orphan GC skipped: failed to fetch pods: {}", e);
+                    continue;
+                }
+            };
+
+            let data_dir = rt.data_dir().to_path_buf();
+            let leftovers =
+                match tokio::task::spawn_blocking(move || orphan_gc::scan(&data_dir)).await {
+                    Ok(leftovers) => leftovers,
+                    Err(e) => {
+                        warn!("Orphan GC failed to scan the data dir: {}", e);
+                        continue;
+                    }
+                };
+            let tracked = rt.container_store().list();
+            let owners = {
+                let in_flight = in_flight.lock().unwrap();
+                orphan_gc::owners(
+                    &pods,
+                    in_flight.iter().map(String::as_str),
+                    tracked.iter().map(|e| e.id.as_str()),
+                )
+            };
+
+            let mut removed = 0;
+            let mut reclaimed = 0;
+            for orphan in orphan_gc::orphans(&leftovers, &owners, grace, SystemTime::now()) {
+                // Checked and moved aside under the lock, so pod sync cannot
+                // start creating this container while it is being deleted.
+                let aside = {
+                    let in_flight = in_flight.lock().unwrap();
+                    if orphan_gc::is_owned(&orphan.container_id, &in_flight) {
+                        continue;
+                    }
+                    orphan_gc::set_aside(&orphan.path)
+                };
+                let aside = match aside {
+                    Ok(aside) => aside,
+                    Err(e) => {
+                        warn!(
+                            "Orphan GC failed to remove {}: {}",
+                            orphan.path.display(),
+                            e
+                        );
+                        continue;
+                    }
+                };
+                match tokio::task::spawn_blocking(move || orphan_gc::remove(&aside)).await {
+                    Ok(Ok(bytes)) => {
+                        info!("Orphan GC: removed {}", orphan.path.display());
+                        removed += 1;
+                        reclaimed += bytes;
+                        metrics.counter_inc(ORPHANS_REMOVED_METRIC);
+                        metrics.counter_add(ORPHANS_RECLAIMED_METRIC, bytes);
+                    }
+                    // Left set aside; the next cycle picks it up again.
+                    Ok(Err(e)) => {
+                        warn!(
+                            "Orphan GC failed to remove {}: {}",
+                            orphan.path.display(),
+                            e
+                        )
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Orphan GC failed to remove {}: {}",
+                            orphan.path.display(),
+                            e
+                        )
+                    }
+                }
+            }
+            if removed > 0 {
+                info!(
+                    "Orphan GC: removed {} leftovers, reclaimed {}",
+                    removed,
+                    super::image_gc::format_bytes(reclaimed)
+                );
+            }
+        }
+    });
+}
@@ -1,36 +1,74 @@
 use crate::cache::AgentStateCache;
+use crate::cgroup;
 use crate::connectivity::ConnectivityManager;
+use crate::container_statuses::{self, Observed};
+use crate::init_containers;
+use crate::loops::pod_watch::PodWatch;
+use crate::pod_bridge::PodBridge;
+use crate::probe::{ExecFn, ProbeEvent, ProbeManager};
+use crate::pull_secrets;
+use crate::registration::ServerToken;
+use crate::restart::{self, ContainerPhase, CrashLoopTracker, ExitAction};
+use crate::runtime_config::{self, SharedRuntimeConfig};
 use crate::store::AgentStore;
+use crate::volumes;
 use crate::vpc_client::VpcClient;
 use chrono::Utc;
 use pkg_container::ContainerRuntime;
+use pkg_metrics::MetricsRegistry;
 #[cfg(target_os = "macos")]
 use pkg_network::macos::switch::MacSwitch;
 use std::sync::Arc;
+use std::time::Instant;
 use tracing::{error, info, warn};
 
-/// Start the pod sync loop (every 5s).
+/// Start the pod sync loop: relist this node's pods every pod-sync-interval
+/// (5s by default), or as soon as `watch` sees one of them change.
+/// `in_flight` holds the IDs of the pods being created or terminated.
 #[allow(clippy::too_many_arguments)]
 pub fn start(
     runtime: Option<Arc<ContainerRuntime>>,
     client: reqwest::Client,
     server: String,
-    token: String,
+    token: ServerToken,
     node_name: String,
     cache: Arc<std::sync::RwLock<AgentStateCache>>,
     connectivity: Arc<ConnectivityManager>,
     store: AgentStore,
     vpc_client: Arc<VpcClient>,
+    watch: Arc<PodWatch>,
+    in_flight: std::sync::Arc<std::sync::Mutex<std::collections::HashSet<String>>>,
+    runtime_config: SharedRuntimeConfig,
+    metrics: Arc<MetricsRegistry>,
+    bridge_networking: bool,
     #[cfg(target_os = "macos")] mac_switch: Option<Arc<MacSwitch>>,
 ) {
-    let in_flight: std::sync::Arc<std::sync::Mutex<std::collections::HashSet<String>>> =
-        std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashSet::new()));
+    let restarts = Arc::new(std::sync::Mutex::new(CrashLoopTracker::default()));
+    let (probe_tx, probe_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut probes = runtime.as_ref().map(|rt| {
+        handle_probe_events(
+            probe_rx,
+            rt.clone(),
+            client.clone(),
+            server.clone(),
+            token.clone(),
+            runtime_config.clone(),
+        );
+        ProbeManager::new(probe_exec(rt.clone()), probe_tx)
+    });
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
-            pkg_constants::timings::POD_SYNC_INTERVAL_SECS,
-        ));
+        // Set up on the first sync that knows the node's pod CIDR.
+        let mut bridge: Option<Arc<PodBridge>> = None;
+        let mut interval = tokio::time::interval(runtime_config.read().unwrap().pod_sync_interval);
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = watch.woken() => interval.reset(),
+            }
+            runtime_config::retune(
+                &mut interval,
+                runtime_config.read().unwrap().pod_sync_interval,
+            );
 
             // Skip when not connected — do not create containers from stale cache
             if !connectivity.is_connected() {
@@ -42,6 +80,7 @@ pub fn start(
             if !has_registration {
                 continue;
             };
+            let started = Instant::now();
 
             let url = format!(
                 "{}/api/v1/pods?fieldSelector=spec.nodeName={}",
@@ -62,6 +101,16 @@ pub fn start(
                 }
             };
 
+            // The watch resumes from the seq this list was served at.
+            if let Some(seq) = resp
+                .headers()
+                .get(pkg_constants::state::WATCH_SEQ_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+            {
+                watch.listed(seq);
+            }
+
             match resp.json::<Vec<pkg_types::pod::Pod>>().await {
                 Ok(pods) => {
                     // Update in-memory cache with fetched pods (outside lock for save)
@@ -74,6 +123,18 @@ pub fn start(
                     if let Err(e) = store.save(&snapshot).await {
                         warn!("Failed to save to AgentStore after pod sync: {}", e);
                     }
+                    // Static pods run from their manifests, not their mirrors
+                    let pods: Vec<_> = pods.into_iter().filter(|p| !p.mirror).collect();
+
+                    restarts
+                        .lock()
+                        .unwrap()
+                        .retain(|id| pods.iter().any(|p| p.id == id));
+
+                    if bridge_networking && bridge.is_none() {
+                        bridge = init_bridge(&cache, &pods).await.map(Arc::new);
+                    }
+                    let default_grace = runtime_config.read().unwrap().termination_grace_period;
 
                     // --- Health monitoring: check Running pods ---
                     if let Some(ref runtime) = runtime {
@@ -84,13 +145,41 @@ pub fn start(
                             &server,
                             &token,
                             &vpc_client,
+                            &bridge,
+                            &restarts,
+                            default_grace,
                             #[cfg(target_os = "macos")]
                             &mac_switch,
                         )
                         .await;
                     }
 
+                    // --- Liveness / readiness probes ---
+                    if let Some(ref mut probes) = probes {
+                        probes.sync(&pods);
+                    }
+
+                    // --- Stop pods being deleted ---
+                    terminate_pods(
+                        &pods,
+                        &runtime,
+                        &client,
+                        &server,
+                        &token,
+                        &in_flight,
+                        &vpc_client,
+                        &bridge,
+                        default_grace,
+                        #[cfg(target_os = "macos")]
+                        &mac_switch,
+                    );
+
                     // --- Schedule new pods ---
+                    // Bridge-networked pods wait until the bridge is up.
+                    if bridge_networking && bridge.is_none() {
+                        crate::metrics::record_pod_sync(&metrics, started.elapsed());
+                        continue;
+                    }
                     schedule_new_pods(
                         &pods,
                         &runtime,
@@ -99,9 +188,12 @@ pub fn start(
                         &token,
                         &in_flight,
                         &vpc_client,
+                        &bridge,
+                        &restarts,
                         #[cfg(target_os = "macos")]
                         &mac_switch,
                     );
+                    crate::metrics::record_pod_sync(&metrics, started.elapsed());
                 }
                 Err(e) => {
                     warn!("Failed to parse pods from JSON: {}", e);
@@ -111,127 +203,517 @@ pub fn start(
     });
 }
 
-/// Check health of Running pods and report failures.
-async fn check_running_pods(
+/// Bridge networking for the node's pod CIDR, with the bridge and NAT rule
+/// in place. `None` (logged) until the server has assigned a pod CIDR.
+async fn init_bridge(
+    cache: &std::sync::RwLock<AgentStateCache>,
+    pods: &[pkg_types::pod::Pod],
+) -> Option<PodBridge> {
+    let Some(pod_cidr) = cache.read().unwrap().pod_cidr.clone() else {
+        warn!("Bridge pod networking: no pod CIDR assigned to this node yet");
+        return None;
+    };
+    let bridge = match PodBridge::new(&pod_cidr, pods).await {
+        Ok(bridge) => bridge,
+        Err(e) => {
+            error!("Bridge pod networking for {} failed: {}", pod_cidr, e);
+            return None;
+        }
+    };
+    if let Err(e) = bridge.setup_host().await {
+        error!("Pod bridge setup for {} failed: {}", pod_cidr, e);
+        return None;
+    }
+    info!("Bridge pod networking ready for {}", pod_cidr);
+    Some(bridge)
+}
+
+/// Check health of Running pods and apply each pod's restart policy to
+/// containers that have exited.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn check_running_pods(
     pods: &[pkg_types::pod::Pod],
     runtime: &Arc<ContainerRuntime>,
     client: &reqwest::Client,
     server: &str,
-    token: &str,
+    token: &ServerToken,
     vpc_client: &Arc<VpcClient>,
+    bridge: &Option<Arc<PodBridge>>,
+    restarts: &std::sync::Mutex<CrashLoopTracker>,
+    default_grace: std::time::Duration,
     #[cfg(target_os = "macos")] mac_switch: &Option<Arc<MacSwitch>>,
 ) {
-    for pod in pods
-        .iter()
-        .filter(|p| p.status == pkg_types::pod::PodStatus::Running)
-    {
-        match runtime.container_state(&pod.id).await {
-            Ok(state) if state.status == "stopped" || state.status == "exited" => {
-                warn!("[pod:{}] Container stopped unexpectedly", pod.name);
-
-                // Unregister VM from userspace switch (macOS only)
-                #[cfg(target_os = "macos")]
-                if let Some(switch) = mac_switch {
-                    switch.remove_vm(&pod.id).await;
-                }
-
-                // Detach eBPF classifiers (best-effort)
-                #[cfg(target_os = "linux")]
-                {
-                    let short = &pod.id[..8.min(pod.id.len())];
-                    if runtime.backend_name_for(&pod.id) == "vm" {
-                        let tap_name = format!("tap-{}", short);
-                        let _ = vpc_client.detach_tap(&tap_name).await;
-                    } else {
-                        let nk_name = format!("nk-{}", short);
-                        let _ = vpc_client.detach_netkit(&nk_name).await;
-                    }
-                }
-
-                // Tear down pod network (best-effort)
-                #[cfg(target_os = "linux")]
-                pkg_network::linux::netns::teardown_pod_network(&pod.id, None).await;
-
-                // Release VPC allocation (best-effort)
-                let vpc_name = pod
-                    .vpc_name
-                    .as_deref()
-                    .or(pod.spec.vpc.as_deref())
-                    .unwrap_or(pkg_constants::network::DEFAULT_VPC_NAME);
-                if let Err(e) = vpc_client.release(&pod.id, vpc_name).await {
-                    warn!("[pod:{}] VPC release failed: {}", pod.name, e);
-                }
+    use pkg_types::pod::{CRASH_LOOP_BACK_OFF, OOM_KILLED, PodStatus, PodStatusUpdate};
 
-                if let Ok(logs) = runtime.container_logs(&pod.id, 20).await {
-                    for line in logs {
-                        warn!("[pod:{}]   > {}", pod.name, line);
-                    }
+    for pod in pods.iter().filter(|p| p.status == PodStatus::Running) {
+        let status_url = format!(
+            "{}/api/v1/namespaces/{}/pods/{}/status",
+            server.trim_end_matches('/'),
+            pod.namespace,
+            pod.name
+        );
+        let ids = pod.container_ids();
+        let mut phases = Vec::with_capacity(ids.len());
+        let mut missing = Vec::with_capacity(ids.len());
+        let mut observed = Vec::with_capacity(ids.len());
+        for id in &ids {
+            let state = runtime.container_state(id).await;
+            let entry = runtime.container_store().get(id);
+            missing.push(state.is_err());
+            let phase = match state {
+                Ok(state) if state.status == "stopped" || state.status == "exited" => {
+                    ContainerPhase::Exited(runtime.container_exit_code(id).await)
                 }
-
-                let status_url = format!(
-                    "{}/api/v1/namespaces/{}/pods/{}/status",
-                    server.trim_end_matches('/'),
-                    pod.namespace,
-                    pod.name
-                );
+                Err(_) => ContainerPhase::Exited(None),
+                _ => ContainerPhase::Running,
+            };
+            observed.push(match phase {
+                ContainerPhase::Running => Observed::Running {
+                    started_at: entry.and_then(|e| e.started_at),
+                },
+                ContainerPhase::Exited(exit_code) => Observed::Exited {
+                    exit_code,
+                    // The container's cgroup is still there until cleanup deletes it.
+                    oom_killed: !*missing.last().unwrap()
+                        && cgroup::oom_killed(std::path::Path::new("/sys/fs/cgroup"), id),
+                    finished_at: entry.and_then(|e| e.finished_at),
+                },
+            });
+            phases.push(phase);
+        }
+        let mut statuses = container_statuses::observed(pod, &observed, Utc::now());
+        let Some((action, index)) = restart::pod_exit_action(pod.spec.restart_policy, &phases)
+        else {
+            // Still running; report containers whose status changed.
+            if statuses != pod.container_statuses {
+                let update = PodStatusUpdate::Detailed {
+                    status: PodStatus::from_containers(pod.spec.restart_policy, &statuses)
+                        .unwrap_or(PodStatus::Running),
+                    status_message: pod.status_message.clone(),
+                    restart_count: None,
+                    ready: None,
+                    pod_ip: None,
+                    container_statuses: Some(statuses),
+                };
                 let _ = client
                     .put(&status_url)
                     .header("Authorization", format!("Bearer {}", token))
-                    .json(&pkg_types::pod::PodStatus::Failed)
+                    .json(&update)
                     .send()
                     .await;
             }
-            Err(_) => {
-                warn!("[pod:{}] Container not found in runtime", pod.name);
+            continue;
+        };
 
-                // Unregister VM from userspace switch (macOS only)
-                #[cfg(target_os = "macos")]
-                if let Some(switch) = mac_switch {
-                    switch.remove_vm(&pod.id).await;
+        let container = &pod.spec.containers[index].name;
+        let exit_code = match phases[index] {
+            ContainerPhase::Exited(code) => code,
+            ContainerPhase::Running => None,
+        };
+        let oom_killed = matches!(
+            observed[index],
+            Observed::Exited {
+                oom_killed: true,
+                ..
+            }
+        );
+        if missing[index] {
+            warn!(
+                "[pod:{}:{}] Container not found in runtime",
+                pod.name, container
+            );
+        } else if oom_killed {
+            warn!(
+                "[pod:{}:{}] Container was OOM-killed (code {:?})",
+                pod.name, container, exit_code
+            );
+        } else {
+            warn!(
+                "[pod:{}:{}] Container exited (code {:?})",
+                pod.name, container, exit_code
+            );
+            if let Ok(logs) = runtime
+                .container_logs(
+                    &ids[index],
+                    &pkg_container::logs::LogOptions {
+                        tail: Some(20),
+                        ..Default::default()
+                    },
+                )
+                .await
+            {
+                for line in logs {
+                    warn!("[pod:{}:{}]   > {}", pod.name, container, line);
                 }
+            }
+        }
 
-                // Detach eBPF classifiers (best-effort)
-                #[cfg(target_os = "linux")]
-                {
-                    let short = &pod.id[..8.min(pod.id.len())];
-                    if runtime.backend_name_for(&pod.id) == "vm" {
-                        let tap_name = format!("tap-{}", short);
-                        let _ = vpc_client.detach_tap(&tap_name).await;
+        release_pod_network(
+            pod,
+            runtime,
+            vpc_client,
+            bridge,
+            #[cfg(target_os = "macos")]
+            mac_switch,
+        )
+        .await;
+
+        let exit = match exit_code {
+            Some(code) => format!("exit code {}", code),
+            None => "exit code unknown".to_string(),
+        };
+        let cause = if oom_killed {
+            format!("{}, {}", OOM_KILLED, exit)
+        } else {
+            exit.clone()
+        };
+        let update = match action {
+            ExitAction::Restart => {
+                // Remove every container, dead or not, so the lifecycle can
+                // recreate the pod under the same IDs once the backoff has passed.
+                for id in &ids {
+                    let _ = runtime.cleanup_container(id).await;
+                }
+                let delay = restarts
+                    .lock()
+                    .unwrap()
+                    .record_exit(&pod.id, Instant::now());
+                info!(
+                    "[pod:{}] Restarting in {}s (restart policy {}, container {} {})",
+                    pod.name,
+                    delay.as_secs(),
+                    pod.spec.restart_policy,
+                    container,
+                    cause
+                );
+                let message = format!(
+                    "{}: back-off {}s restarting container {} ({})",
+                    CRASH_LOOP_BACK_OFF,
+                    delay.as_secs(),
+                    container,
+                    cause
+                );
+                let mut waiting =
+                    container_statuses::waiting(pod, CRASH_LOOP_BACK_OFF, Some(message.clone()));
+                waiting[index].restart_count += 1;
+                // Back to the agent to recreate once the backoff has passed.
+                PodStatusUpdate::Detailed {
+                    status: PodStatus::Scheduled,
+                    status_message: Some(message),
+                    restart_count: Some(pod.restart_count + 1),
+                    ready: None,
+                    pod_ip: None,
+                    container_statuses: Some(waiting),
+                }
+            }
+            ExitAction::Succeeded => PodStatusUpdate::Detailed {
+                status: PodStatus::from_containers(pod.spec.restart_policy, &statuses)
+                    .unwrap_or(PodStatus::Succeeded),
+                status_message: Some(format!("Container completed ({})", exit)),
+                restart_count: None,
+                ready: None,
+                pod_ip: None,
+                container_statuses: Some(statuses),
+            },
+            ExitAction::Failed => {
+                // The pod is done; stop the containers that are still up.
+                let running: Vec<String> = ids
+                    .iter()
+                    .zip(&phases)
+                    .filter(|(_, phase)| **phase == ContainerPhase::Running)
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                stop_containers(pod, runtime, running, default_grace);
+                for status in &mut statuses {
+                    status.ready = false;
+                }
+                PodStatusUpdate::Detailed {
+                    status: PodStatus::from_containers(pod.spec.restart_policy, &statuses)
+                        .unwrap_or(PodStatus::Failed),
+                    status_message: Some(if oom_killed {
+                        format!(
+                            "{}: container {} exceeded its memory limit ({})",
+                            OOM_KILLED, container, exit
+                        )
                     } else {
-                        let nk_name = format!("nk-{}", short);
-                        let _ = vpc_client.detach_netkit(&nk_name).await;
-                    }
+                        format!("Container {} failed ({})", container, exit)
+                    }),
+                    restart_count: None,
+                    ready: None,
+                    pod_ip: None,
+                    container_statuses: Some(statuses),
                 }
+            }
+        };
+
+        let _ = client
+            .put(&status_url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&update)
+            .send()
+            .await;
+    }
+}
 
-                // Tear down pod network (best-effort)
-                #[cfg(target_os = "linux")]
-                pkg_network::linux::netns::teardown_pod_network(&pod.id, None).await;
-
-                // Release VPC allocation (best-effort)
-                let vpc_name = pod
-                    .vpc_name
-                    .as_deref()
-                    .or(pod.spec.vpc.as_deref())
-                    .unwrap_or(pkg_constants::network::DEFAULT_VPC_NAME);
-                if let Err(e) = vpc_client.release(&pod.id, vpc_name).await {
-                    warn!("[pod:{}] VPC release failed: {}", pod.name, e);
+/// Stop the given containers of a pod in the background, each with the
+/// pod's grace period (`default_grace` if it sets none).
+fn stop_containers(
+    pod: &pkg_types::pod::Pod,
+    runtime: &Arc<ContainerRuntime>,
+    ids: Vec<String>,
+    default_grace: std::time::Duration,
+) {
+    let grace = termination_grace_period(pod, default_grace);
+    for id in ids {
+        let runtime = runtime.clone();
+        let name = pod.name.clone();
+        tokio::spawn(async move {
+            if let Err(e) = runtime.stop_container(&id, grace).await {
+                warn!("[pod:{}] Stop of {} failed: {}", name, id, e);
+            }
+        });
+    }
+}
+
+/// Exec probes run through the pod's runtime backend.
+fn probe_exec(runtime: Arc<ContainerRuntime>) -> ExecFn {
+    Arc::new(move |container_id: String, command: Vec<String>| {
+        let runtime = runtime.clone();
+        Box::pin(async move {
+            let command: Vec<&str> = command.iter().map(String::as_str).collect();
+            let result = runtime.exec_in_container(&container_id, &command).await?;
+            anyhow::ensure!(
+                result.success(),
+                "exited with code {}: {}{}",
+                result.exit_code,
+                result.stdout.trim(),
+                result.stderr.trim()
+            );
+            Ok(())
+        })
+    })
+}
+
+/// Act on probe results: stop containers whose liveness probe failed (the
+/// next health check restarts the pod according to its restart policy) and
+/// report readiness changes to the server.
+fn handle_probe_events(
+    mut events: tokio::sync::mpsc::UnboundedReceiver<ProbeEvent>,
+    runtime: Arc<ContainerRuntime>,
+    client: reqwest::Client,
+    server: String,
+    token: ServerToken,
+    runtime_config: SharedRuntimeConfig,
+) {
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            match event {
+                ProbeEvent::LivenessFailed {
+                    pod,
+                    container,
+                    reason,
+                } => {
+                    warn!(
+                        "[pod:{}:{}] Liveness probe failed: {}; stopping container",
+                        pod.name, container, reason
+                    );
+                    let id = pkg_types::pod::container_id(&pod.id, &container);
+                    let default_grace = runtime_config.read().unwrap().termination_grace_period;
+                    stop_containers(&pod, &runtime, vec![id], default_grace);
                 }
+                ProbeEvent::ReadinessChanged { pod, ready } => {
+                    info!("[pod:{}] Readiness changed: ready={}", pod.name, ready);
+                    let status_url = format!(
+                        "{}/api/v1/namespaces/{}/pods/{}/status",
+                        server.trim_end_matches('/'),
+                        pod.namespace,
+                        pod.name
+                    );
+                    let update = pkg_types::pod::PodStatusUpdate::Detailed {
+                        status: pkg_types::pod::PodStatus::Running,
+                        status_message: pod.status_message.clone(),
+                        restart_count: None,
+                        ready: Some(ready),
+                        pod_ip: None,
+                        container_statuses: None,
+                    };
+                    if let Err(e) = client
+                        .put(&status_url)
+                        .header("Authorization", format!("Bearer {}", token))
+                        .json(&update)
+                        .send()
+                        .await
+                    {
+                        warn!("[pod:{}] Failed to report readiness: {}", pod.name, e);
+                    }
+                }
+            }
+        }
+    });
+}
 
-                let status_url = format!(
-                    "{}/api/v1/namespaces/{}/pods/{}/status",
-                    server.trim_end_matches('/'),
-                    pod.namespace,
-                    pod.name
+/// Time a pod's containers get between SIGTERM and SIGKILL: the pod's own
+/// grace period, else the node's `default`.
+pub(super) fn termination_grace_period(
+    pod: &pkg_types::pod::Pod,
+    default: std::time::Duration,
+) -> std::time::Duration {
+    pod.spec
+        .termination_grace_period_seconds
+        .map_or(default, std::time::Duration::from_secs)
+}
+
+/// Runtime ID of the pod's first container, which owns the pod network.
+fn primary_container_id(pod: &pkg_types::pod::Pod) -> String {
+    pod.select_container_id(None)
+        .unwrap_or_else(|| pod.id.clone())
+}
+
+/// Detach a pod from the switch / eBPF, tear down its netns and release its
+/// VPC address, or with bridge networking delete its veth and free its pod
+/// IP. All steps are best-effort.
+#[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+async fn release_pod_network(
+    pod: &pkg_types::pod::Pod,
+    runtime: &Arc<ContainerRuntime>,
+    vpc_client: &Arc<VpcClient>,
+    bridge: &Option<Arc<PodBridge>>,
+    #[cfg(target_os = "macos")] mac_switch: &Option<Arc<MacSwitch>>,
+) {
+    if let Some(bridge) = bridge {
+        bridge.release(&pod.id).await;
+        return;
+    }
+
+    // Unregister VM from userspace switch (macOS only)
+    #[cfg(target_os = "macos")]
+    if let Some(switch) = mac_switch {
+        switch.remove_vm(&pod.id).await;
+    }
+
+    // Detach eBPF classifiers (best-effort)
+    #[cfg(target_os = "linux")]
+    {
+        let short = &pod.id[..8.min(pod.id.len())];
+        if runtime.backend_name_for(&primary_container_id(pod)) == "vm" {
+            let tap_name = format!("tap-{}", short);
+            let _ = vpc_client.detach_tap(&tap_name).await;
+        } else {
+            let nk_name = format!("nk-{}", short);
+            let _ = vpc_client.detach_netkit(&nk_name).await;
+        }
+    }
+
+    // Tear down pod network (best-effort)
+    #[cfg(target_os = "linux")]
+    pkg_network::linux::netns::teardown_pod_network(&pod.id, None).await;
+
+    // Release VPC allocation (best-effort)
+    let vpc_name = pod
+        .vpc_name
+        .as_deref()
+        .or(pod.spec.vpc.as_deref())
+        .unwrap_or(pkg_constants::network::DEFAULT_VPC_NAME);
+    if let Err(e) = vpc_client.release(&pod.id, vpc_name).await {
+        warn!("[pod:{}] VPC release failed: {}", pod.name, e);
+    }
+}
+
+/// Stop the containers of pods marked `Terminating`, honouring each pod's
+/// grace period, then ask the server to drop the record.
+#[allow(clippy::too_many_arguments)]
+fn terminate_pods(
+    pods: &[pkg_types::pod::Pod],
+    runtime: &Option<Arc<ContainerRuntime>>,
+    client: &reqwest::Client,
+    server: &str,
+    token: &ServerToken,
+    in_flight: &std::sync::Arc<std::sync::Mutex<std::collections::HashSet<String>>>,
+    vpc_client: &Arc<VpcClient>,
+    bridge: &Option<Arc<PodBridge>>,
+    default_grace: std::time::Duration,
+    #[cfg(target_os = "macos")] mac_switch: &Option<Arc<MacSwitch>>,
+) {
+    for pod in pods
+        .iter()
+        .filter(|p| p.status == pkg_types::pod::PodStatus::Terminating)
+    {
+        // Also waits out a lifecycle task still creating this pod.
+        if !in_flight.lock().unwrap().insert(pod.id.clone()) {
+            continue;
+        }
+
+        let pod = pod.clone();
+        let runtime = runtime.clone();
+        let client = client.clone();
+        let delete_url = format!(
+            "{}/api/v1/namespaces/{}/pods/{}?force=true",
+            server.trim_end_matches('/'),
+            pod.namespace,
+            pod.name
+        );
+        let token = token.to_string();
+        let in_flight = in_flight.clone();
+        let vpc_client = vpc_client.clone();
+        let bridge = bridge.clone();
+        #[cfg(target_os = "macos")]
+        let mac_switch = mac_switch.clone();
+
+        tokio::spawn(async move {
+            let grace = termination_grace_period(&pod, default_grace);
+            if let Some(runtime) = runtime {
+                info!(
+                    "[pod:{}] Terminating (grace period {}s)",
+                    pod.name,
+                    grace.as_secs()
                 );
-                let _ = client
-                    .put(&status_url)
-                    .header("Authorization", format!("Bearer {}", token))
-                    .json(&pkg_types::pod::PodStatus::Failed)
-                    .send()
-                    .await;
+                let ids = pod.container_ids();
+                futures_util::future::join_all(ids.iter().map(|id| {
+                    let runtime = &runtime;
+                    let pod = &pod;
+                    async move {
+                        if let Err(e) = runtime.stop_container(id, grace).await {
+                            warn!("[pod:{}] Stop of {} failed: {}", pod.name, id, e);
+                        }
+                    }
+                }))
+                .await;
+                release_pod_network(
+                    &pod,
+                    &runtime,
+                    &vpc_client,
+                    &bridge,
+                    #[cfg(target_os = "macos")]
+                    &mac_switch,
+                )
+                .await;
+                for id in &ids {
+                    let _ = runtime.cleanup_container(id).await;
+                }
+                let _ = runtime.remove_pod_volumes(&pod.id).await;
             }
-            _ => {} // Still running, all good
-        }
+
+            match client
+                .delete(&delete_url)
+                .header("Authorization", format!("Bearer {}", token))
+                .send()
+                .await
+            {
+                Ok(resp) if resp.status().is_success() => {
+                    info!("[pod:{}] Terminated", pod.name)
+                }
+                Ok(resp) => warn!(
+                    "[pod:{}] Failed to delete after termination: {}",
+                    pod.name,
+                    resp.status()
+                ),
+                Err(e) => warn!(
+                    "[pod:{}] Failed to delete after termination: {}",
+                    pod.name, e
+                ),
+            }
+            in_flight.lock().unwrap().remove(&pod.id);
+        });
     }
 }
 
@@ -242,14 +724,17 @@ fn schedule_new_pods(
     runtime: &Option<Arc<ContainerRuntime>>,
     client: &reqwest::Client,
     server: &str,
-    token: &str,
+    token: &ServerToken,
     in_flight: &std::sync::Arc<std::sync::Mutex<std::collections::HashSet<String>>>,
     vpc_client: &Arc<VpcClient>,
+    bridge: &Option<Arc<PodBridge>>,
+    restarts: &Arc<std::sync::Mutex<CrashLoopTracker>>,
     #[cfg(target_os = "macos")] mac_switch: &Option<Arc<MacSwitch>>,
 ) {
     for pod in pods {
-        if pod.status == pkg_types::pod::PodStatus::Scheduled
-            || pod.status == pkg_types::pod::PodStatus::ContainerCreating
+        if (pod.status == pkg_types::pod::PodStatus::Scheduled
+            || pod.status == pkg_types::pod::PodStatus::ContainerCreating)
+            && !restarts.lock().unwrap().in_backoff(&pod.id, Instant::now())
         {
             info!(
                 "Found scheduled pod: {} (image: {})",
@@ -285,9 +770,11 @@ fn schedule_new_pods(
             let pod_runtime = rt_arc.clone();
             let pod_client = client.clone();
             let pod_server = server.to_string();
-            let pod_token = token.to_string();
+            let pod_token = token.clone();
             let pod_in_flight = in_flight.clone();
             let pod_vpc = vpc_client.clone();
+            let pod_bridge = bridge.clone();
+            let pod_restarts = restarts.clone();
             let pod = pod.clone();
             #[cfg(target_os = "macos")]
             let pod_switch = mac_switch.clone();
@@ -309,6 +796,8 @@ fn schedule_new_pods(
                     pod_token,
                     pod_in_flight,
                     pod_vpc,
+                    pod_bridge,
+                    pod_restarts,
                     #[cfg(target_os = "macos")]
                     pod_switch,
                 )
@@ -318,16 +807,21 @@ fn schedule_new_pods(
     }
 }
 
-/// Full pod lifecycle: allocate VPC → pull image → create container → start → report.
+/// Full pod lifecycle: allocate VPC (or bridge) address → run init
+/// containers → pull images → create containers → start → report. The pod
+/// network belongs to the first container; the pod is Running once every
+/// container has started.
 #[allow(clippy::too_many_arguments)]
 async fn run_pod_lifecycle(
     pod: pkg_types::pod::Pod,
     runtime: Arc<ContainerRuntime>,
     client: reqwest::Client,
     server: String,
-    token: String,
+    token: ServerToken,
     in_flight: std::sync::Arc<std::sync::Mutex<std::collections::HashSet<String>>>,
     vpc_client: Arc<VpcClient>,
+    bridge: Option<Arc<PodBridge>>,
+    restarts: Arc<std::sync::Mutex<CrashLoopTracker>>,
     #[cfg(target_os = "macos")] mac_switch: Option<Arc<MacSwitch>>,
 ) {
     let status_url = format!(
@@ -337,100 +831,331 @@ async fn run_pod_lifecycle(
         pod.name
     );
 
-    let container_spec = pod.spec.containers.first();
-    let image = container_spec
-        .map(|c| c.image.clone())
-        .unwrap_or_else(|| "alpine:latest".to_string());
-    let command: Vec<String> = container_spec
-        .map(|c| {
-            let mut cmd = c.command.clone();
-            cmd.extend(c.args.clone());
-            cmd
-        })
-        .unwrap_or_default();
-    let mut env = container_spec.map(|c| c.env.clone()).unwrap_or_default();
+    let ids = pod.container_ids();
+    let Some(primary) = ids.first().cloned() else {
+        error!("[pod:{}] Pod has no containers", pod.name);
+        let _ = client
+            .put(&status_url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&pkg_types::pod::PodStatus::Failed)
+            .send()
+            .await;
+        in_flight.lock().unwrap().remove(&pod.id);
+        return;
+    };
 
-    // 0. Allocate VPC address
-    let vpc_name = pod
+    // A claim is bound once its volume is provisioned: until then the pod
+    // stays as it is and the next sync tries again
+    let claim_paths = match volumes::fetch_claim_paths(&client, &server, &token, &pod).await {
+        Ok(paths) => paths,
+        Err(e) => {
+            info!("[pod:{}] Waiting for volumes: {:#}", pod.name, e);
+            in_flight.lock().unwrap().remove(&pod.id);
+            return;
+        }
+    };
+
+    // Resolve volume mounts up front: a mount of an undeclared volume fails the pod
+    let empty_dir = |volume: &str| runtime.empty_dir_path(&pod.id, volume);
+    let claim_path = |claim: &str| claim_paths.get(claim).cloned();
+    let resolved: Result<Vec<_>, String> = pod
         .spec
-        .vpc
-        .as_deref()
-        .unwrap_or(pkg_constants::network::DEFAULT_VPC_NAME);
-    let vpc_alloc = match vpc_client.allocate(&pod.id, vpc_name).await {
-        Ok(alloc) => {
-            info!(
-                "[pod:{}] VPC allocated: ghost_ipv6={}, guest_ipv4={}, vpc_id={}",
-                pod.name, alloc.1, alloc.0, alloc.2
-            );
-            Some(alloc)
+        .init_containers
+        .iter()
+        .chain(&pod.spec.containers)
+        .map(|c| volumes::resolve_mounts(&pod, c, empty_dir, claim_path))
+        .collect();
+    let mut mounts = match resolved {
+        Ok(mounts) => mounts,
+        Err(message) => {
+            error!("[pod:{}] {}", pod.name, message);
+            let _ = client
+                .put(&status_url)
+                .header("Authorization", format!("Bearer {}", token))
+                .json(&pkg_types::pod::PodStatusUpdate::Detailed {
+                    status: pkg_types::pod::PodStatus::Failed,
+                    status_message: Some(message),
+                    restart_count: None,
+                    ready: None,
+                    pod_ip: None,
+                    container_statuses: None,
+                })
+                .send()
+                .await;
+            in_flight.lock().unwrap().remove(&pod.id);
+            return;
         }
-        Err(e) => {
-            // If VPC daemon is not running (socket missing), keep pod Pending
-            // so it will be retried on the next sync cycle.
-            let msg = e.to_string();
-            let is_transient = msg.contains("os error 2")
-                || msg.contains("Connection refused")
-                || msg.contains("socket not found");
-            if is_transient {
-                warn!(
-                    "[pod:{}] VPC daemon not available, will retry: {}",
-                    pod.name, e
-                );
-            } else {
-                error!("[pod:{}] VPC allocation failed: {}", pod.name, e);
+    };
+    let app_mounts = mounts.split_off(pod.spec.init_containers.len());
+    let init_mounts = mounts;
+
+    // Registry credentials from the pod's image pull secrets
+    let keychain = pull_secrets::fetch_keychain(&client, &server, &token, &pod).await;
+
+    // 0. Allocate the pod address: from the node's pod CIDR with bridge
+    //    networking, otherwise from the pod's VPC
+    let pod_ip = match bridge {
+        Some(ref bridge) => match bridge.allocate(&pod.id).await {
+            Ok(ip) => {
+                info!("[pod:{}] Pod IP allocated: {}", pod.name, ip);
+                Some(ip)
+            }
+            Err(e) => {
+                error!("[pod:{}] Pod IP allocation failed: {}", pod.name, e);
                 let _ = client
                     .put(&status_url)
                     .header("Authorization", format!("Bearer {}", token))
-                    .json(&pkg_types::pod::PodStatus::Failed)
+                    .json(&pkg_types::pod::PodStatusUpdate::Detailed {
+                        status: pkg_types::pod::PodStatus::Failed,
+                        status_message: Some(e.to_string()),
+                        restart_count: None,
+                        ready: None,
+                        pod_ip: None,
+                        container_statuses: None,
+                    })
                     .send()
                     .await;
+                in_flight.lock().unwrap().remove(&pod.id);
+                return;
+            }
+        },
+        None => None,
+    };
+    let vpc_name = pod
+        .spec
+        .vpc
+        .as_deref()
+        .unwrap_or(pkg_constants::network::DEFAULT_VPC_NAME);
+    let vpc_alloc = if pod_ip.is_some() {
+        None
+    } else {
+        match vpc_client.allocate(&pod.id, vpc_name).await {
+            Ok(alloc) => {
+                info!(
+                    "[pod:{}] VPC allocated: ghost_ipv6={}, guest_ipv4={}, vpc_id={}",
+                    pod.name, alloc.1, alloc.0, alloc.2
+                );
+                Some(alloc)
+            }
+            Err(e) => {
+                // If VPC daemon is not running (socket missing), keep pod Pending
+                // so it will be retried on the next sync cycle.
+                let msg = e.to_string();
+                let is_transient = msg.contains("os error 2")
+                    || msg.contains("Connection refused")
+                    || msg.contains("socket not found");
+                if is_transient {
+                    warn!(
+                        "[pod:{}] VPC daemon not available, will retry: {}",
+                        pod.name, e
+                    );
+                } else {
+                    error!("[pod:{}] VPC allocation failed: {}", pod.name, e);
+                    let _ = client
+                        .put(&status_url)
+                        .header("Authorization", format!("Bearer {}", token))
+                        .json(&pkg_types::pod::PodStatus::Failed)
+                        .send()
+                        .await;
+                }
+                in_flight.lock().unwrap().remove(&pod.id);
+                return;
             }
-            in_flight.lock().unwrap().remove(&pod.id);
-            return;
         }
     };
 
-    // Inject VPC addresses as environment variables
+    // Pod addresses, injected into every container as environment variables
+    let mut vpc_env = std::collections::HashMap::new();
     if let Some((ref guest_ipv4, ref ghost_ipv6, _, _)) = vpc_alloc {
-        env.insert("K3RS_POD_IP".to_string(), guest_ipv4.clone());
-        env.insert("K3RS_POD_IPV6".to_string(), ghost_ipv6.clone());
+        vpc_env.insert("K3RS_POD_IP".to_string(), guest_ipv4.clone());
+        vpc_env.insert("K3RS_POD_IPV6".to_string(), ghost_ipv6.clone());
+    }
+    if let Some(ref ip) = pod_ip {
+        vpc_env.insert("K3RS_POD_IP".to_string(), ip.clone());
     }
 
-    // 1. Pull Image
-    info!("[pod:{}] Pulling image: {}", pod.name, image);
-    if let Err(e) = runtime.pull_image(&image).await {
-        error!("[pod:{}] Image pull failed: {}", pod.name, e);
+    // 0b. Init containers, in order; the app container starts only after all succeed
+    if !pod.spec.init_containers.is_empty() {
+        let (runtime, client, status_url, token, pod, vpc_env) =
+            (&runtime, &client, &status_url, &token, &pod, &vpc_env);
+        let (init_mounts, keychain) = (&init_mounts, &keychain);
+        let result = init_containers::run_all(pod, move |index, spec| async move {
+            let _ = client
+                .put(status_url)
+                .header("Authorization", format!("Bearer {}", token))
+                .json(&init_containers::init_progress(pod, index))
+                .send()
+                .await;
+
+            let id = init_containers::init_container_id(&pod.id, index);
+            let mut command = spec.command.clone();
+            command.extend(spec.args.iter().cloned());
+            let mut env = spec.env.clone();
+            env.extend(vpc_env.clone());
+
+            info!(
+                "[pod:{}] Running init container {} ({})",
+                pod.name, spec.name, spec.image
+            );
+            let result = runtime
+                .run_container(
+                    &id,
+                    &pod.namespace,
+                    &spec.image,
+                    &command,
+                    &env,
+                    &init_mounts[index],
+                    &spec.effective_limits(),
+                    &spec.security_context,
+                    spec.pull_policy(),
+                    keychain,
+                    pod.spec.runtime.as_deref(),
+                )
+                .await;
+            if let Ok(code) = result
+                && code != 0
+                && let Ok(logs) = runtime
+                    .container_logs(
+                        &id,
+                        &pkg_container::logs::LogOptions {
+                            tail: Some(20),
+                            ..Default::default()
+                        },
+                    )
+                    .await
+            {
+                for line in logs {
+                    warn!("[pod:{}:{}]   > {}", pod.name, spec.name, line);
+                }
+            }
+            let _ = runtime.cleanup_container(&id).await;
+            result
+        })
+        .await;
+
+        if let Err(failure) = result {
+            error!("[pod:{}] Init container failed: {:?}", pod.name, failure);
+            let update = init_containers::failure_update(
+                pod,
+                &failure,
+                &mut restarts.lock().unwrap(),
+                Instant::now(),
+            );
+            let _ = client
+                .put(status_url)
+                .header("Authorization", format!("Bearer {}", token))
+                .json(&update)
+                .send()
+                .await;
+            if let Some(ref bridge) = bridge {
+                bridge.release(&pod.id).await;
+            } else if let Err(e) = vpc_client.release(&pod.id, vpc_name).await {
+                warn!("[pod:{}] VPC release failed: {}", pod.name, e);
+            }
+            in_flight.lock().unwrap().remove(&pod.id);
+            return;
+        }
+        info!("[pod:{}] Init containers completed", pod.name);
         let _ = client
-            .put(&status_url)
+            .put(status_url)
             .header("Authorization", format!("Bearer {}", token))
-            .json(&pkg_types::pod::PodStatus::Failed)
+            .json(&init_containers::init_progress(
+                pod,
+                pod.spec.init_containers.len(),
+            ))
             .send()
             .await;
-        return;
     }
 
-    // 2. Create Container
-    info!("[pod:{}] Creating container: {}", pod.name, pod.id);
-    if let Err(e) = runtime
-        .create_container(&pod.id, &image, &command, &env, pod.spec.runtime.as_deref())
-        .await
+    // 1–2. Pull each image (per its pull policy) and create each container
+    for (index, ((spec, id), mounts)) in pod
+        .spec
+        .containers
+        .iter()
+        .zip(&ids)
+        .zip(&app_mounts)
+        .enumerate()
     {
-        error!("[pod:{}] Container creation failed: {}", pod.name, e);
-        let _ = client
-            .put(&status_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .json(&pkg_types::pod::PodStatus::Failed)
-            .send()
+        info!(
+            "[pod:{}] Creating container {} from {} (pull policy {})",
+            pod.name,
+            id,
+            spec.image,
+            spec.pull_policy()
+        );
+        let mut command = spec.command.clone();
+        command.extend(spec.args.iter().cloned());
+        let mut env = spec.env.clone();
+        env.extend(vpc_env.clone());
+
+        let created = runtime
+            .create_container(
+                id,
+                &pod.namespace,
+                &spec.image,
+                &command,
+                &env,
+                mounts,
+                &spec.effective_limits(),
+                &spec.security_context,
+                spec.pull_policy(),
+                &keychain,
+                pod.spec.runtime.as_deref(),
+            )
             .await;
-        return;
+        if let Err(e) = created {
+            error!("[pod:{}] Container {} failed: {:#}", pod.name, spec.name, e);
+            let status_message = pull_secrets::pull_failure_message(&e);
+            let reason = if status_message.is_some() {
+                container_statuses::ERR_IMAGE_PULL
+            } else {
+                container_statuses::CREATE_CONTAINER_ERROR
+            };
+            fail_pod(
+                &pod,
+                &runtime,
+                &client,
+                &status_url,
+                &token,
+                &ids,
+                &in_flight,
+                bridge.as_deref(),
+                status_message,
+                failed_container(&pod, index, reason, format!("{:#}", e)),
+            )
+            .await;
+            return;
+        }
+    }
+
+    // 2a. Bridge networking: veth pair into the primary container's netns
+    #[cfg(target_os = "linux")]
+    if let (Some(bridge), Some(ip)) = (&bridge, &pod_ip) {
+        if runtime.backend_name_for(&primary) == "vm" {
+            warn!(
+                "[pod:{}] Bridge networking does not cover VM pods, continuing without network",
+                pod.name
+            );
+        } else {
+            let attached = match runtime.container_pid(&primary) {
+                Some(pid) => bridge.attach(&pod.id, ip, pid).await,
+                None => Err(anyhow::anyhow!("container PID not available")),
+            };
+            if let Err(e) = attached {
+                warn!(
+                    "[pod:{}] Pod veth setup failed: {} (continuing without network)",
+                    pod.name, e
+                );
+            }
+        }
     }
 
     // 2b. Pod network setup (netkit pair + Ghost IPv6) — skip for VM backends
     #[cfg(target_os = "linux")]
-    if runtime.backend_name_for(&pod.id) != "vm"
+    if runtime.backend_name_for(&primary) != "vm"
         && let Some((ref guest_ipv4, ref ghost_ipv6, vpc_id, ref vpc_cidr)) = vpc_alloc
     {
-        if let Some(pid) = runtime.container_pid(&pod.id) {
+        if let Some(pid) = runtime.container_pid(&primary) {
             let net_config = pkg_network::linux::netns::PodNetworkConfig {
                 pod_id: pod.id.clone(),
                 ghost_ipv6: ghost_ipv6.clone(),
@@ -467,7 +1192,7 @@ async fn run_pod_lifecycle(
     // 2d. Set VPC config on VM backend before start (macOS only)
     // This creates a socketpair and passes VPC params to the VMM process.
     #[cfg(target_os = "macos")]
-    if runtime.backend_name_for(&pod.id) == "vm"
+    if runtime.backend_name_for(&primary) == "vm"
         && let Some((ref guest_ipv4, ref ghost_ipv6, vpc_id, ref vpc_cidr)) = vpc_alloc
     {
         let vpc_config = pkg_container::vm_utils::VmNetworkConfig {
@@ -479,27 +1204,39 @@ async fn run_pod_lifecycle(
             platform_prefix: pkg_constants::network::PLATFORM_PREFIX,
             cluster_id: 0, // TODO: get from cluster registration
         };
-        runtime.set_vm_network_config(&pod.id, vpc_config).await;
+        runtime.set_vm_network_config(&primary, vpc_config).await;
     }
 
-    // 3. Start Container
-    info!("[pod:{}] Starting container: {}", pod.name, pod.id);
-    if let Err(e) = runtime.start_container(&pod.id).await {
-        error!("[pod:{}] Container start failed: {}", pod.name, e);
-        in_flight.lock().unwrap().remove(&pod.id);
-        let _ = runtime.cleanup_container(&pod.id).await;
-        let _ = client
-            .put(&status_url)
-            .header("Authorization", format!("Bearer {}", token))
-            .json(&pkg_types::pod::PodStatus::Failed)
-            .send()
+    // 3. Start Containers
+    for (index, id) in ids.iter().enumerate() {
+        info!("[pod:{}] Starting container: {}", pod.name, id);
+        if let Err(e) = runtime.start_container(id).await {
+            error!("[pod:{}] Container {} start failed: {}", pod.name, id, e);
+            fail_pod(
+                &pod,
+                &runtime,
+                &client,
+                &status_url,
+                &token,
+                &ids,
+                &in_flight,
+                bridge.as_deref(),
+                None,
+                failed_container(
+                    &pod,
+                    index,
+                    container_statuses::RUN_CONTAINER_ERROR,
+                    format!("{:#}", e),
+                ),
+            )
             .await;
-        return;
+            return;
+        }
     }
 
     // 3b. Attach eBPF classifiers for VM backends (TAP created during start_container)
     #[cfg(target_os = "linux")]
-    if runtime.backend_name_for(&pod.id) == "vm"
+    if runtime.backend_name_for(&primary) == "vm"
         && let Some((ref guest_ipv4, ref ghost_ipv6, vpc_id, ref vpc_cidr)) = vpc_alloc
     {
         let short = &pod.id[..8.min(pod.id.len())];
@@ -517,10 +1254,10 @@ async fn run_pod_lifecycle(
 
     // 3c. Register VM with userspace switch (macOS only)
     #[cfg(target_os = "macos")]
-    if runtime.backend_name_for(&pod.id) == "vm"
+    if runtime.backend_name_for(&primary) == "vm"
         && let Some((ref guest_ipv4, _, vpc_id, _)) = vpc_alloc
         && let Some(switch) = &mac_switch
-        && let Some(socket) = runtime.take_vm_net_socket(&pod.id).await
+        && let Some(socket) = runtime.take_vm_net_socket(&primary).await
         && let Ok(ip) = guest_ipv4.parse()
         && let Err(e) = switch.add_vm(pod.id.clone(), socket, ip, vpc_id).await
     {
@@ -533,14 +1270,29 @@ async fn run_pod_lifecycle(
     // 4. Success
     in_flight.lock().unwrap().remove(&pod.id);
     info!(
-        "[pod:{}] Container running via {}",
+        "[pod:{}] {} container(s) running via {}",
         pod.name,
-        runtime.backend_name_for(&pod.id)
+        ids.len(),
+        runtime.backend_name_for(&primary)
     );
+    let started: Vec<_> = ids
+        .iter()
+        .map(|id| Observed::Running {
+            started_at: runtime.container_store().get(id).and_then(|e| e.started_at),
+        })
+        .collect();
+    let running = pkg_types::pod::PodStatusUpdate::Detailed {
+        status: pkg_types::pod::PodStatus::Running,
+        status_message: None,
+        restart_count: None,
+        ready: None,
+        pod_ip,
+        container_statuses: Some(container_statuses::observed(&pod, &started, Utc::now())),
+    };
     let _ = client
         .put(&status_url)
         .header("Authorization", format!("Bearer {}", token))
-        .json(&pkg_types::pod::PodStatus::Running)
+        .json(&running)
         .send()
         .await;
 
@@ -567,3 +1319,62 @@ async fn run_pod_lifecycle(
         }
     }
 }
+
+/// Container statuses of a pod whose `index`-th container could not be
+/// created or started: that one waiting for `reason`, the rest for their
+/// turn.
+fn failed_container(
+    pod: &pkg_types::pod::Pod,
+    index: usize,
+    reason: &str,
+    message: String,
+) -> Vec<pkg_types::pod::ContainerStatus> {
+    let mut statuses = container_statuses::waiting(pod, pkg_types::pod::CONTAINER_CREATING, None);
+    statuses[index] = container_statuses::status(
+        pod,
+        index,
+        pkg_types::pod::ContainerState::Waiting {
+            reason: reason.to_string(),
+            message: Some(message),
+        },
+    );
+    statuses
+}
+
+/// Remove the containers created so far, free the pod's bridge address and
+/// report the pod Failed, with `status_message` as the reason when there is
+/// one.
+#[allow(clippy::too_many_arguments)]
+async fn fail_pod(
+    pod: &pkg_types::pod::Pod,
+    runtime: &Arc<ContainerRuntime>,
+    client: &reqwest::Client,
+    status_url: &str,
+    token: &ServerToken,
+    ids: &[String],
+    in_flight: &std::sync::Mutex<std::collections::HashSet<String>>,
+    bridge: Option<&PodBridge>,
+    status_message: Option<String>,
+    container_statuses: Vec<pkg_types::pod::ContainerStatus>,
+) {
+    for id in ids {
+        let _ = runtime.cleanup_container(id).await;
+    }
+    if let Some(bridge) = bridge {
+        bridge.release(&pod.id).await;
+    }
+    in_flight.lock().unwrap().remove(&pod.id);
+    let _ = client
+        .put(status_url)
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&pkg_types::pod::PodStatusUpdate::Detailed {
+            status: pkg_types::pod::PodStatus::Failed,
+            status_message,
+            restart_count: None,
+            ready: None,
+            pod_ip: None,
+            container_statuses: Some(container_statuses),
+        })
+        .send()
+        .await;
+}
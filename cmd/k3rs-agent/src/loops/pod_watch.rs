@@ -0,0 +1,137 @@
+//! Watch on the server's pod keys that wakes the pod sync loop as soon as a
+//! pod on this node changes. Pod sync stays a relist: the watch resumes from
+//! the seq the last list was served at, and the interval relist remains the
+//! fallback while the stream is down.
+
+use crate::connectivity::ConnectivityManager;
+use crate::registration::ServerToken;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio::time::{Duration, sleep};
+use tracing::{debug, info};
+
+/// State shared between the pod sync loop and the watch.
+#[derive(Default)]
+pub struct PodWatch {
+    /// Seq to resume the watch after; `None` until the first list, and again
+    /// after the server no longer had the events we missed.
+    seq: Mutex<Option<u64>>,
+    wake: Notify,
+}
+
+impl PodWatch {
+    /// Record the event-log seq a pod list was served at.
+    pub fn listed(&self, seq: u64) {
+        *self.seq.lock().unwrap() = Some(seq);
+    }
+
+    /// Resolves when a watched change asks for an early relist.
+    pub async fn woken(&self) {
+        self.wake.notified().await
+    }
+}
+
+/// Why [`stream_once`] returned.
+enum StreamEnd {
+    Closed,
+    /// 410: the events after our seq are no longer buffered.
+    Gone,
+}
+
+/// Start watching `/registry/pods/` for changes to pods on `node_name`.
+pub fn start(
+    client: reqwest::Client,
+    server: String,
+    token: ServerToken,
+    node_name: String,
+    connectivity: Arc<ConnectivityManager>,
+    watch: Arc<PodWatch>,
+) {
+    tokio::spawn(async move {
+        loop {
+            let from = *watch.seq.lock().unwrap();
+            if let Some(from) = from.filter(|_| connectivity.is_connected()) {
+                match stream_once(&client, &server, &token, &node_name, &watch, from).await {
+                    Ok(StreamEnd::Gone) => {
+                        info!("Pod watch expired at seq {}; relisting", from);
+                        *watch.seq.lock().unwrap() = None;
+                        watch.wake.notify_one();
+                    }
+                    Ok(StreamEnd::Closed) => {}
+                    Err(e) => debug!("Pod watch interrupted: {}", e),
+                }
+            }
+            sleep(Duration::from_millis(
+                pkg_constants::timings::WATCH_RECONNECT_DELAY_MS,
+            ))
+            .await;
+        }
+    });
+}
+
+async fn stream_once(
+    client: &reqwest::Client,
+    server: &str,
+    token: &ServerToken,
+    node_name: &str,
+    watch: &PodWatch,
+    from: u64,
+) -> anyhow::Result<StreamEnd> {
+    let url = format!(
+        "{}/api/v1/watch?prefix=/registry/pods/&seq={}",
+        server.trim_end_matches('/'),
+        from
+    );
+    let resp = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?;
+    if resp.status() == reqwest::StatusCode::GONE {
+        return Ok(StreamEnd::Gone);
+    }
+    let mut resp = resp.error_for_status()?;
+
+    // The server sends each event as a single `data:` line.
+    let mut buf = String::new();
+    while let Some(chunk) = resp.chunk().await? {
+        buf.push_str(&String::from_utf8_lossy(&chunk));
+        while let Some(end) = buf.find('\n') {
+            let line: String = buf.drain(..=end).collect();
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let Ok(event) = serde_json::from_str::<Value>(data.trim_start()) else {
+                continue;
+            };
+            if let Some(seq) = event.get("seq").and_then(Value::as_u64) {
+                let mut last = watch.seq.lock().unwrap();
+                *last = Some(last.map_or(seq, |last| last.max(seq)));
+            }
+            if wakes_pod_sync(&event, node_name) {
+                watch.wake.notify_one();
+            }
+        }
+    }
+    Ok(StreamEnd::Closed)
+}
+
+/// Whether a store event on a pod key concerns `node_name`: a pod written
+/// with that node name, or any deletion (the event no longer says where the
+/// pod ran).
+pub(crate) fn wakes_pod_sync(event: &Value, node_name: &str) -> bool {
+    if event.get("event_type").and_then(Value::as_str) == Some("Delete") {
+        return true;
+    }
+    let Some(value) = event
+        .get("value")
+        .and_then(|v| serde_json::from_value::<Vec<u8>>(v.clone()).ok())
+    else {
+        return false;
+    };
+    serde_json::from_slice::<Value>(&value)
+        .ok()
+        .and_then(|pod| pod.get("node_name")?.as_str().map(|n| n == node_name))
+        .unwrap_or(false)
+}
@@ -1,6 +1,7 @@
 use crate::cache::AgentStateCache;
 use crate::connectivity::ConnectivityManager;
 use crate::registration;
+use crate::registration::ServerToken;
 use crate::store::AgentStore;
 use pkg_types::node::NodeRegistrationRequest;
 use std::sync::Arc;
@@ -11,12 +12,13 @@ use tracing::{info, warn};
 pub fn start(
     client: reqwest::Client,
     server: String,
-    token: String,
+    token: ServerToken,
     reg_req: NodeRegistrationRequest,
     node_name: String,
     cache: Arc<std::sync::RwLock<AgentStateCache>>,
     connectivity: Arc<ConnectivityManager>,
     store: AgentStore,
+    bridge_networking: bool,
 ) {
     tokio::spawn(async move {
         let mut attempt = 0u32;
@@ -34,7 +36,7 @@ pub fn start(
             let delay = ConnectivityManager::backoff_duration(attempt);
             tokio::time::sleep(delay).await;
 
-            let cached_node_id = cache.read().unwrap().node_id.clone();
+            let cached_node_id = cache.read().unwrap().probe_node_id(bridge_networking);
             match registration::try_connect(
                 &client,
                 &server,
@@ -47,12 +49,8 @@ pub fn start(
             {
                 Ok(new_identity) => {
                     info!("Reconnected to server after {} attempts", attempt + 1);
-                    if let Some((node_id, api_port)) = new_identity {
-                        {
-                            let mut c = cache.write().unwrap();
-                            c.node_id = Some(node_id);
-                            c.agent_api_port = Some(api_port);
-                        }
+                    if let Some(resp) = new_identity {
+                        cache.write().unwrap().set_registration(resp);
                         let snapshot = cache.read().unwrap().clone();
                         if let Err(e) = store.save(&snapshot).await {
                             warn!("Failed to save to AgentStore after reconnect: {}", e);
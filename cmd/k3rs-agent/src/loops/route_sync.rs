@@ -1,31 +1,44 @@
 use crate::cache::AgentStateCache;
 use crate::connectivity::ConnectivityManager;
+use crate::registration::ServerToken;
+use crate::runtime_config::{self, SharedRuntimeConfig};
 use crate::store::AgentStore;
 use crate::vpc_client::VpcClient;
 use chrono::Utc;
 use pkg_network::dns::DnsServer;
+use pkg_proxy::ingress_proxy::IngressProxy;
+use pkg_proxy::network_policy::PolicyTable;
 use pkg_proxy::service_proxy::ServiceProxy;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tracing::warn;
 
-/// Start the route sync loop (every 10s).
+/// Start the route sync loop (every route-sync-interval, 10s by default): services, endpoints and
+/// NetworkPolicies for the service proxy, Ingress rules for the ingress
+/// proxy, plus DNS records.
 #[allow(clippy::too_many_arguments)]
 pub fn start(
     client: reqwest::Client,
     server: String,
-    token: String,
+    token: ServerToken,
     service_proxy: Arc<ServiceProxy>,
+    ingress_proxy: Arc<IngressProxy>,
     dns_server: Arc<DnsServer>,
     cache: Arc<std::sync::RwLock<AgentStateCache>>,
     connectivity: Arc<ConnectivityManager>,
     store: AgentStore,
     vpc_client: Arc<VpcClient>,
+    runtime_config: SharedRuntimeConfig,
 ) {
     tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(10));
+        let mut interval =
+            tokio::time::interval(runtime_config.read().unwrap().route_sync_interval);
         loop {
             interval.tick().await;
+            runtime_config::retune(
+                &mut interval,
+                runtime_config.read().unwrap().route_sync_interval,
+            );
 
             // Skip when not connected
             if !connectivity.is_connected() {
@@ -56,6 +69,8 @@ pub fn start(
 
             let mut all_services = Vec::new();
             let mut all_endpoints = Vec::new();
+            let mut all_policies = Vec::new();
+            let mut all_ingresses = Vec::new();
 
             for ns in &ns_names {
                 let services: Vec<pkg_types::service::Service> = match client
@@ -84,10 +99,59 @@ pub fn start(
                     }
                 };
 
+                let policies: Vec<pkg_types::network_policy::NetworkPolicy> = match client
+                    .get(format!("{}/api/v1/namespaces/{}/networkpolicies", base, ns))
+                    .header("Authorization", &auth)
+                    .send()
+                    .await
+                {
+                    Ok(r) => r.json().await.unwrap_or_default(),
+                    Err(e) => {
+                        warn!(
+                            "Route sync: failed to fetch network policies for ns {}: {}",
+                            ns, e
+                        );
+                        continue;
+                    }
+                };
+
+                let ingresses: Vec<pkg_types::ingress::Ingress> = match client
+                    .get(format!("{}/api/v1/namespaces/{}/ingresses", base, ns))
+                    .header("Authorization", &auth)
+                    .send()
+                    .await
+                {
+                    Ok(r) => r.json().await.unwrap_or_default(),
+                    Err(e) => {
+                        warn!("Route sync: failed to fetch ingresses for ns {}: {}", ns, e);
+                        continue;
+                    }
+                };
+
                 all_services.extend(services);
                 all_endpoints.extend(endpoints);
+                all_policies.extend(policies);
+                all_ingresses.extend(ingresses);
             }
 
+            // Pod labels identify policy peers; only needed once a policy exists
+            let all_pods: Vec<pkg_types::pod::Pod> = if all_policies.is_empty() {
+                Vec::new()
+            } else {
+                match client
+                    .get(format!("{}/api/v1/pods", base))
+                    .header("Authorization", &auth)
+                    .send()
+                    .await
+                {
+                    Ok(r) => r.json().await.unwrap_or_default(),
+                    Err(e) => {
+                        warn!("Route sync: failed to fetch pods: {}", e);
+                        continue;
+                    }
+                }
+            };
+
             // Build VPC pod-IP maps from k3rs-vpc daemon
             let mut vpc_pod_ips: HashMap<String, HashSet<String>> = HashMap::new();
             let mut ip_to_vpc: HashMap<String, String> = HashMap::new();
@@ -131,7 +195,21 @@ pub fn start(
             service_proxy
                 .update_routes(&all_services, &all_endpoints, &vpc_pod_ips)
                 .await;
+            service_proxy
+                .update_policies(PolicyTable::new(
+                    &all_policies,
+                    &namespaces,
+                    &all_pods,
+                    &all_endpoints,
+                ))
+                .await;
+            ingress_proxy
+                .update_rules(&all_ingresses, &all_services)
+                .await;
             dns_server.update_records(&all_services).await;
+            dns_server
+                .update_endpoints(&all_services, &all_endpoints)
+                .await;
             dns_server
                 .update_records_vpc(&all_services, &ip_to_vpc, &vpc_name_to_id)
                 .await;
@@ -143,6 +221,7 @@ pub fn start(
                 let mut c = cache.write().unwrap();
                 c.services = all_services;
                 c.endpoints = all_endpoints;
+                c.ingresses = all_ingresses;
                 c.last_synced_at = Utc::now();
             }
             let snapshot = cache.read().unwrap().clone();
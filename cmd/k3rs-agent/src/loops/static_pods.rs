@@ -0,0 +1,164 @@
+use crate::connectivity::ConnectivityManager;
+use crate::init_containers;
+use crate::registration::ServerToken;
+use crate::runtime_config::SharedRuntimeConfig;
+use crate::static_pods::{self, PodRuntime, StaticPods};
+use crate::volumes;
+use pkg_container::ContainerRuntime;
+use pkg_container::registry_auth::RegistryKeychain;
+use pkg_types::pod::Pod;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Start the static pod loop (every 20s): run the pods in `dir`, and while
+/// the server is reachable, mirror them to it.
+#[allow(clippy::too_many_arguments)]
+pub fn start(
+    runtime: Option<Arc<ContainerRuntime>>,
+    client: reqwest::Client,
+    server: String,
+    token: ServerToken,
+    node_name: String,
+    connectivity: Arc<ConnectivityManager>,
+    dir: PathBuf,
+    runtime_config: SharedRuntimeConfig,
+) {
+    let Some(runtime) = runtime else {
+        return;
+    };
+    info!("Static pods from {}", dir.display());
+    tokio::spawn(async move {
+        let mut pods = StaticPods::new(RuntimePods(runtime, runtime_config));
+        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+            pkg_constants::timings::STATIC_POD_SYNC_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            pods.sync(&dir).await;
+
+            if !connectivity.is_connected() {
+                continue;
+            }
+            let mirrors: Vec<Pod> = pods
+                .pods()
+                .into_iter()
+                .map(|pod| static_pods::mirror(pod, &node_name))
+                .collect();
+            let url = format!(
+                "{}/api/v1/nodes/{}/mirror-pods",
+                server.trim_end_matches('/'),
+                node_name
+            );
+            if let Err(e) = client
+                .put(&url)
+                .header("Authorization", format!("Bearer {}", token))
+                .json(&mirrors)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+            {
+                warn!("Failed to mirror static pods: {}", e);
+            }
+        }
+    });
+}
+
+/// Static pods on the container runtime: init containers run to completion,
+/// then the app containers are created and started. They get no pod network
+/// and pull images with the node's registry credentials only. Stopping one
+/// uses the node's default grace period unless the pod sets its own.
+struct RuntimePods(Arc<ContainerRuntime>, SharedRuntimeConfig);
+
+impl RuntimePods {
+    async fn run(&self, pod: &Pod, ids: &[String]) -> anyhow::Result<()> {
+        let runtime = &self.0;
+        let keychain = RegistryKeychain::default();
+        let empty_dir = |volume: &str| runtime.empty_dir_path(&pod.id, volume);
+        // Static pods run without the server: there is no claim to bind.
+        let no_claims = |_: &str| None::<std::path::PathBuf>;
+
+        let keychain = &keychain;
+        init_containers::run_all(pod, |index, spec| async move {
+            let id = init_containers::init_container_id(&pod.id, index);
+            let mounts = volumes::resolve_mounts(pod, &spec, empty_dir, no_claims)
+                .map_err(anyhow::Error::msg)?;
+            let mut command = spec.command.clone();
+            command.extend(spec.args.iter().cloned());
+            let result = runtime
+                .run_container(
+                    &id,
+                    &pod.namespace,
+                    &spec.image,
+                    &command,
+                    &spec.env,
+                    &mounts,
+                    &spec.effective_limits(),
+                    &spec.security_context,
+                    spec.pull_policy(),
+                    keychain,
+                    pod.spec.runtime.as_deref(),
+                )
+                .await;
+            let _ = runtime.cleanup_container(&id).await;
+            result
+        })
+        .await
+        .map_err(|failure| anyhow::anyhow!("init container failed: {:?}", failure))?;
+
+        for (spec, id) in pod.spec.containers.iter().zip(ids) {
+            let mounts = volumes::resolve_mounts(pod, spec, empty_dir, no_claims)
+                .map_err(anyhow::Error::msg)?;
+            let mut command = spec.command.clone();
+            command.extend(spec.args.iter().cloned());
+            runtime
+                .create_container(
+                    id,
+                    &pod.namespace,
+                    &spec.image,
+                    &command,
+                    &spec.env,
+                    &mounts,
+                    &spec.effective_limits(),
+                    &spec.security_context,
+                    spec.pull_policy(),
+                    keychain,
+                    pod.spec.runtime.as_deref(),
+                )
+                .await?;
+        }
+        for id in ids {
+            runtime.start_container(id).await?;
+        }
+        Ok(())
+    }
+}
+
+impl PodRuntime for RuntimePods {
+    async fn start_pod(&self, pod: &Pod) -> anyhow::Result<()> {
+        let ids = pod.container_ids();
+        // Left over from before an agent restart, or a failed start
+        for id in &ids {
+            let _ = self.0.cleanup_container(id).await;
+        }
+        let result = self.run(pod, &ids).await;
+        if result.is_err() {
+            for id in &ids {
+                let _ = self.0.cleanup_container(id).await;
+            }
+        }
+        result
+    }
+
+    async fn stop_pod(&self, pod: &Pod) -> anyhow::Result<()> {
+        let default_grace = self.1.read().unwrap().termination_grace_period;
+        let grace = super::pod_sync::termination_grace_period(pod, default_grace);
+        for id in pod.container_ids() {
+            if let Err(e) = self.0.stop_container(&id, grace).await {
+                warn!("Static pod {}: stop of {} failed: {}", pod.name, id, e);
+            }
+            self.0.cleanup_container(&id).await?;
+        }
+        self.0.remove_pod_volumes(&pod.id).await
+    }
+}
@@ -0,0 +1,51 @@
+use crate::cache::AgentStateCache;
+use crate::registration::ServerToken;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderValue, header};
+use tracing::{info, warn};
+
+/// Start the tunnel loop — keeps a WebSocket open to the server over which
+/// it reaches the agent API on `api_port`, so exec, logs and the rest work
+/// when the server cannot dial this node (e.g. behind NAT). Waits for the
+/// node's agent token, which the server checks, and reconnects with backoff.
+pub fn start(
+    server: String,
+    token: ServerToken,
+    node_name: String,
+    cache: Arc<RwLock<AgentStateCache>>,
+    api_port: u16,
+) {
+    let url = format!(
+        "{}/api/v1/nodes/{}/tunnel",
+        server
+            .trim_end_matches('/')
+            .replace("http://", "ws://")
+            .replace("https://", "wss://"),
+        node_name
+    );
+    info!("Starting agent tunnel to {}", url);
+    let target = SocketAddr::from(([127, 0, 0, 1], api_port));
+    tokio::spawn(pkg_tunnel::agent::run(
+        move || {
+            let agent_token = cache.read().unwrap().agent_token.clone()?;
+            let mut request = match url.as_str().into_client_request() {
+                Ok(request) => request,
+                Err(e) => {
+                    warn!("Invalid tunnel URL {}: {}", url, e);
+                    return None;
+                }
+            };
+            let headers = request.headers_mut();
+            if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+                headers.insert(header::AUTHORIZATION, value);
+            }
+            if let Ok(value) = HeaderValue::from_str(&agent_token) {
+                headers.insert(pkg_tunnel::AGENT_TOKEN_HEADER, value);
+            }
+            Some(request)
+        },
+        target,
+    ));
+}
@@ -1,58 +1,120 @@
 mod api;
+mod api_auth;
 mod cache;
+mod cert_rotation;
+mod cgroup;
 mod cli;
 mod connectivity;
+mod container_statuses;
 mod heartbeat;
+mod image_gc;
+mod init_containers;
+mod local_path;
 mod loops;
+mod metrics;
+mod orphan_gc;
+mod pod_bridge;
+mod port_forward;
+mod pressure;
+mod probe;
+mod pull_secrets;
 mod recovery;
 mod registration;
+mod restart;
+mod runtime_config;
+mod static_pods;
 mod store;
 #[cfg(test)]
 mod tests;
+mod volumes;
 mod vpc_client;
 
 use cache::AgentStateCache;
 use clap::Parser;
 use connectivity::ConnectivityManager;
+use pkg_container::logs::LogRotation;
+use pkg_metrics::MetricsRegistry;
 use pkg_network::dns::DnsServer;
+use pkg_proxy::health::HealthConfig;
+use pkg_proxy::ingress_proxy::IngressProxy;
 use pkg_proxy::service_proxy::ServiceProxy;
 use pkg_proxy::tunnel::TunnelProxy;
-use pkg_types::config::{AgentConfigFile, load_config_file};
+use pkg_types::config::{AgentConfigFile, ConfigFileWatcher};
 use pkg_types::node::NodeRegistrationRequest;
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use store::AgentStore;
 use tracing::{info, warn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = cli::Cli::parse();
 
+    // Load config file (returns defaults if file not found)
+    let config_watcher =
+        ConfigFileWatcher::<AgentConfigFile>::open(&cli.config, runtime_config::LIVE_CONFIG_KEYS)?;
+    let file_cfg = config_watcher.current().clone();
+    let runtime_config = Arc::new(std::sync::RwLock::new(
+        runtime_config::RuntimeConfig::from_config(&file_cfg)?,
+    ));
+
     // Initialize logging based on format
-    match cli.log_format.as_str() {
-        "json" => {
-            tracing_subscriber::fmt()
-                .json()
-                .with_env_filter(
-                    tracing_subscriber::EnvFilter::from_default_env()
-                        .add_directive(tracing::level_filters::LevelFilter::INFO.into()),
-                )
-                .init();
+    let (log_filter_layer, log_filter) = pkg_metrics::log_filter::LogFilter::new(
+        file_cfg.log_level.as_deref(),
+        runtime_config::PINNED_LOG_DIRECTIVES,
+    )
+    .map_err(anyhow::Error::msg)?;
+    let log_filter = Arc::new(log_filter);
+    let json = cli.log_format == "json";
+    tracing_subscriber::registry()
+        .with(log_filter_layer)
+        .with(json.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json).then(tracing_subscriber::fmt::layer))
+        .init();
+    info!("Config file: {}", cli.config);
+    loops::config_reload::start(
+        config_watcher,
+        cli.config.clone(),
+        runtime_config.clone(),
+        log_filter.clone(),
+    );
+    let orphan_gc_grace = orphan_gc::grace_period(&file_cfg);
+    let pressure_thresholds = pressure::PressureThresholds::from_config(&file_cfg);
+    let static_pod_dir = static_pods::manifest_dir(&file_cfg);
+    let endpoint_health = {
+        let default = HealthConfig::default();
+        HealthConfig {
+            interval: file_cfg
+                .endpoint_probe_interval
+                .map(|secs| std::time::Duration::from_secs(secs.max(1)))
+                .unwrap_or(default.interval),
+            timeout: default.timeout,
+            failure_threshold: file_cfg
+                .endpoint_probe_failure_threshold
+                .unwrap_or(default.failure_threshold)
+                .max(1),
+            success_threshold: file_cfg
+                .endpoint_probe_success_threshold
+                .unwrap_or(default.success_threshold)
+                .max(1),
         }
-        _ => {
-            tracing_subscriber::fmt()
-                .with_env_filter(
-                    tracing_subscriber::EnvFilter::from_default_env()
-                        .add_directive(tracing::level_filters::LevelFilter::INFO.into()),
-                )
-                .init();
+    };
+    let log_rotation = {
+        let default = LogRotation::default();
+        LogRotation {
+            max_bytes: file_cfg
+                .container_log_max_bytes
+                .unwrap_or(default.max_bytes)
+                .max(1),
+            max_files: file_cfg
+                .container_log_max_files
+                .unwrap_or(default.max_files),
+            compress: file_cfg.container_log_compress.unwrap_or(default.compress),
         }
-    }
-
-    // Load config file (returns defaults if file not found)
-    let file_cfg: AgentConfigFile = load_config_file(&cli.config)?;
-    info!("Config file: {}", cli.config);
+    };
 
     // Merge: CLI args > config file > defaults
     let server = cli
@@ -75,10 +137,22 @@ async fn main() -> anyhow::Result<()> {
         .service_proxy_port
         .or(file_cfg.service_proxy_port)
         .unwrap_or(pkg_constants::network::DEFAULT_SERVICE_PROXY_PORT);
+    let ingress_port = cli
+        .ingress_port
+        .or(file_cfg.ingress_port)
+        .unwrap_or(pkg_constants::network::DEFAULT_INGRESS_PORT);
     let dns_port = cli
         .dns_port
         .or(file_cfg.dns_port)
         .unwrap_or(pkg_constants::network::DEFAULT_DNS_PORT);
+    let bridge_networking = match cli.pod_network.or(file_cfg.pod_network).as_deref() {
+        None | Some("vpc") => false,
+        Some("bridge") if cfg!(target_os = "linux") => true,
+        Some("bridge") => anyhow::bail!("bridge pod networking is only supported on Linux"),
+        Some(other) => anyhow::bail!("unknown pod network '{}' (expected vpc or bridge)", other),
+    };
+
+    let insecure_agent_api = cli.insecure_agent_api || file_cfg.insecure_agent_api.unwrap_or(false);
 
     info!("Starting k3rs-agent for node: {}", node_name);
 
@@ -121,9 +195,24 @@ async fn main() -> anyhow::Result<()> {
     // Phase B: Start services with stale data (before server contact)
     // =========================================================================
 
+    // Container inits are reparented to the agent once the OCI runtime
+    // exits, so their exit codes can be collected; orphans nobody waits on
+    // are reaped in the background.
+    if let Err(e) = pkg_container::reaper::become_subreaper() {
+        warn!(
+            "Failed to become child subreaper, container exit codes will be unknown: {}",
+            e
+        );
+    }
+    pkg_container::reaper::start();
+
+    let metrics = Arc::new(MetricsRegistry::new());
+    metrics::register(&metrics);
+
     // Start the Pingora Service Proxy
-    let service_proxy = Arc::new(ServiceProxy::new(service_proxy_port));
+    let service_proxy = Arc::new(ServiceProxy::new(service_proxy_port, metrics.clone()));
     service_proxy.start().await?;
+    service_proxy.spawn_health_checks(endpoint_health);
 
     // Pre-populate routes from cached services/endpoints if available.
     if let Some(ref c) = cached {
@@ -136,13 +225,23 @@ async fn main() -> anyhow::Result<()> {
         );
     }
 
+    // Start the Pingora Ingress Proxy, routing through the service proxy's backends
+    let ingress_proxy = Arc::new(IngressProxy::new(ingress_port, &service_proxy));
+    if let Some(ref c) = cached {
+        ingress_proxy.update_rules(&c.ingresses, &c.services).await;
+    }
+    ingress_proxy.start().await?;
+
     // Start the embedded DNS server.
     let dns_addr: SocketAddr = format!("0.0.0.0:{}", dns_port).parse()?;
-    let dns_server = Arc::new(DnsServer::new(dns_addr));
+    let mut dns_server = DnsServer::new(dns_addr);
+    dns_server.set_upstreams(pkg_network::resolv::load_host_upstreams());
+    let dns_server = Arc::new(dns_server);
 
     // Pre-populate DNS records from cached services if available.
     if let Some(ref c) = cached {
         dns_server.update_records(&c.services).await;
+        dns_server.update_endpoints(&c.services, &c.endpoints).await;
         info!(
             "DnsServer pre-loaded {} cached services as DNS records",
             c.services.len()
@@ -226,11 +325,12 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Connecting to server at {}", server);
 
-    let cached_node_id = cache.read().unwrap().node_id.clone();
+    let server_token = registration::ServerToken::new(cache.clone());
+    let cached_node_id = cache.read().unwrap().probe_node_id(bridge_networking);
     match registration::try_connect(
         &client,
         &server,
-        &token,
+        &server_token,
         &reg_req,
         &node_name,
         cached_node_id.as_deref(),
@@ -239,12 +339,8 @@ async fn main() -> anyhow::Result<()> {
     {
         Ok(new_identity) => {
             connectivity.set_connected();
-            if let Some((node_id, api_port)) = new_identity {
-                {
-                    let mut c = cache.write().unwrap();
-                    c.node_id = Some(node_id);
-                    c.agent_api_port = Some(api_port);
-                }
+            if let Some(resp) = new_identity {
+                cache.write().unwrap().set_registration(resp);
                 let snapshot = cache.read().unwrap().clone();
                 if let Err(e) = store.save(&snapshot).await {
                     warn!("Failed to save to AgentStore after registration: {}", e);
@@ -268,24 +364,57 @@ async fn main() -> anyhow::Result<()> {
         }
     }
 
+    // Renew the node certificate before it expires
+    let cert_renew_before = chrono::Duration::days(
+        cli.cert_renew_before_days
+            .or(file_cfg.cert_renew_before_days)
+            .unwrap_or(pkg_constants::timings::CERT_RENEW_BEFORE_DAYS),
+    );
+    cert_rotation::start(
+        Arc::new(cert_rotation::NodeCerts::new(
+            cert_rotation::cert_dir(&node_name),
+            node_name.clone(),
+        )),
+        server.clone(),
+        cert_renew_before,
+        reg_req.clone(),
+        connectivity.clone(),
+        cache.clone(),
+    );
+
     // =========================================================================
     // Phase D: Start heartbeat (connectivity-aware)
     // =========================================================================
+    // Filled in by the controller loops once the container runtime is up.
+    let containers = Arc::new(std::sync::OnceLock::new());
     heartbeat::start_heartbeat_loop(
         server.clone(),
         node_name.clone(),
-        token.clone(),
+        server_token.clone(),
         connectivity.clone(),
         cache.clone(),
+        containers.clone(),
+        pressure_thresholds,
+        runtime_config.clone(),
+        metrics.clone(),
     );
 
     // =========================================================================
     // Phase E: Agent controller loops
     // =========================================================================
+    let api_auth = if insecure_agent_api {
+        warn!(
+            "Agent API authentication is disabled (insecure-agent-api); anyone who can reach the agent can exec into its containers"
+        );
+        api_auth::ApiAuth::Disabled
+    } else {
+        api_auth::ApiAuth::Token(cache.clone())
+    };
     loops::start_controller_loops(
         server.clone(),
-        token.clone(),
+        server_token,
         service_proxy.clone(),
+        ingress_proxy.clone(),
         dns_server.clone(),
         cache.clone(),
         connectivity.clone(),
@@ -293,6 +422,16 @@ async fn main() -> anyhow::Result<()> {
         node_name.clone(),
         store.clone(),
         vpc_client.clone(),
+        containers,
+        runtime_config,
+        orphan_gc_grace,
+        static_pod_dir,
+        std::path::PathBuf::from(&cli.local_path_dir),
+        log_rotation,
+        metrics,
+        log_filter,
+        bridge_networking,
+        api_auth,
     );
 
     // Block until Ctrl-C
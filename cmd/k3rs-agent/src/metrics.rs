@@ -0,0 +1,55 @@
+//! Agent metrics served on the agent API's `GET /metrics`.
+//!
+//! Loops update their series as they run; the container count and image pull
+//! totals are kept by the container runtime and copied in on each scrape.
+
+use pkg_metrics::MetricsRegistry;
+
+/// Containers in the running state on this node.
+pub const CONTAINERS_RUNNING_METRIC: &str = "k3rs_agent_containers_running";
+/// Image pulls that went to a registry.
+pub const IMAGE_PULLS_METRIC: &str = "k3rs_agent_image_pulls_total";
+/// Image pulls that failed.
+pub const IMAGE_PULL_FAILURES_METRIC: &str = "k3rs_agent_image_pull_failures_total";
+/// Completed pod sync iterations.
+pub const POD_SYNCS_METRIC: &str = "k3rs_agent_pod_syncs_total";
+/// Duration of the last pod sync iteration.
+pub const POD_SYNC_DURATION_METRIC: &str = "k3rs_agent_pod_sync_duration_milliseconds";
+/// Heartbeats the server did not accept.
+pub const HEARTBEAT_FAILURES_METRIC: &str = "k3rs_agent_heartbeat_failures_total";
+
+/// Register the agent's series. Loops that own their metrics (image and orphan
+/// GC, the service proxy) register those themselves.
+pub fn register(metrics: &MetricsRegistry) {
+    metrics.register_gauge(CONTAINERS_RUNNING_METRIC, "Containers running on this node");
+    metrics.register_counter(IMAGE_PULLS_METRIC, "Image pulls from a registry");
+    metrics.register_counter(IMAGE_PULL_FAILURES_METRIC, "Image pulls that failed");
+    metrics.register_counter(POD_SYNCS_METRIC, "Pod sync loop iterations");
+    metrics.register_gauge(
+        POD_SYNC_DURATION_METRIC,
+        "Duration of the last pod sync loop iteration in milliseconds",
+    );
+    metrics.register_counter(
+        HEARTBEAT_FAILURES_METRIC,
+        "Heartbeats that failed or were rejected",
+    );
+}
+
+/// Record one pod sync iteration that took `elapsed`.
+pub fn record_pod_sync(metrics: &MetricsRegistry, elapsed: std::time::Duration) {
+    metrics.counter_inc(POD_SYNCS_METRIC);
+    metrics.gauge_set(POD_SYNC_DURATION_METRIC, elapsed.as_millis() as i64);
+}
+
+/// Render the registry after copying in the runtime's current counts.
+pub fn scrape(
+    metrics: &MetricsRegistry,
+    running_containers: usize,
+    image_pulls: u64,
+    image_pull_failures: u64,
+) -> String {
+    metrics.gauge_set(CONTAINERS_RUNNING_METRIC, running_containers as i64);
+    metrics.counter_set(IMAGE_PULLS_METRIC, image_pulls);
+    metrics.counter_set(IMAGE_PULL_FAILURES_METRIC, image_pull_failures);
+    metrics.render()
+}
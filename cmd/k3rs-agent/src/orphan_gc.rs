@@ -0,0 +1,150 @@
+//! Orphan garbage collection: container bundles, VM instance files and log
+//! directories left behind by containers no pod on this node owns any more.
+
+use pkg_types::config::AgentConfigFile;
+use pkg_types::pod::Pod;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_GRACE_PERIOD_SECS: u64 = 600;
+
+/// VM instance files are the container ID plus one of these; the longer
+/// ones come first, so `<id>-vsock.sock` is not read as `<id>-vsock`.
+const VM_FILE_SUFFIXES: &[&str] = &[
+    "-rootfs.ext4",
+    "-vsock.sock",
+    "-rootfs",
+    ".sock",
+    ".log",
+    ".pid",
+];
+
+/// Prefix of the entries [`set_aside`] for deletion.
+const SET_ASIDE_PREFIX: &str = ".gc-";
+
+/// How long an unreferenced leftover is kept before it is deleted.
+pub fn grace_period(cfg: &AgentConfigFile) -> Duration {
+    Duration::from_secs(
+        cfg.orphan_gc_grace_period
+            .unwrap_or(DEFAULT_GRACE_PERIOD_SECS),
+    )
+}
+
+/// A file or directory under the runtime data dir that belongs to one
+/// container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leftover {
+    pub path: PathBuf,
+    /// Runtime ID of the container it belongs to.
+    pub container_id: String,
+    pub modified: SystemTime,
+}
+
+/// Everything under `containers/`, `vms/` and `logs/` of `data_dir` that is
+/// named after a container. Missing directories yield nothing.
+pub fn scan(data_dir: &Path) -> Vec<Leftover> {
+    let mut leftovers = Vec::new();
+    for (dir, is_vm) in [("containers", false), ("vms", true), ("logs", false)] {
+        let Ok(entries) = std::fs::read_dir(data_dir.join(dir)) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let container_id = if is_vm {
+                match VM_FILE_SUFFIXES.iter().find_map(|s| name.strip_suffix(s)) {
+                    Some(id) => id.to_string(),
+                    None => continue,
+                }
+            } else {
+                name
+            };
+            let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+                continue;
+            };
+            leftovers.push(Leftover {
+                path: entry.path(),
+                container_id,
+                modified,
+            });
+        }
+    }
+    leftovers
+}
+
+/// IDs that keep leftovers alive: the pods assigned to this node, the pods
+/// still being created or torn down, and the containers the runtime tracks.
+pub fn owners<'a>(
+    pods: &'a [Pod],
+    in_flight: impl IntoIterator<Item = &'a str>,
+    tracked: impl IntoIterator<Item = &'a str>,
+) -> HashSet<String> {
+    pods.iter()
+        .map(|p| p.id.as_str())
+        .chain(in_flight)
+        .chain(tracked)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether `container_id` is an owner or one of an owner's containers
+/// (`<pod-id>-<name>`, `<pod-id>-init-<n>`).
+pub fn is_owned(container_id: &str, owners: &HashSet<String>) -> bool {
+    owners.contains(container_id)
+        || container_id
+            .match_indices('-')
+            .any(|(at, _)| owners.contains(&container_id[..at]))
+}
+
+/// The leftovers no owner claims that have not changed for `grace`.
+pub fn orphans<'a>(
+    leftovers: &'a [Leftover],
+    owners: &HashSet<String>,
+    grace: Duration,
+    now: SystemTime,
+) -> Vec<&'a Leftover> {
+    leftovers
+        .iter()
+        .filter(|l| !is_owned(&l.container_id, owners))
+        .filter(|l| now.duration_since(l.modified).is_ok_and(|age| age >= grace))
+        .collect()
+}
+
+/// Rename `path` to a hidden name in the same directory, which no container
+/// uses, and return the new path. A later [`scan`] still finds it, as owned
+/// by nobody.
+pub fn set_aside(path: &Path) -> std::io::Result<PathBuf> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if name.starts_with(SET_ASIDE_PREFIX) {
+        return Ok(path.to_path_buf());
+    }
+    let aside = path.with_file_name(format!("{}{}", SET_ASIDE_PREFIX, name));
+    std::fs::rename(path, &aside)?;
+    Ok(aside)
+}
+
+/// Delete `path`, returning the bytes it held. Symlinks are removed, not
+/// followed.
+pub fn remove(path: &Path) -> std::io::Result<u64> {
+    let size = disk_size(path);
+    let meta = std::fs::symlink_metadata(path)?;
+    if meta.is_dir() {
+        std::fs::remove_dir_all(path)?;
+    } else {
+        std::fs::remove_file(path)?;
+    }
+    Ok(size)
+}
+
+/// Total size of the files under `path`.
+fn disk_size(path: &Path) -> u64 {
+    let Ok(meta) = std::fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| disk_size(&e.path())).sum())
+        .unwrap_or(0)
+}
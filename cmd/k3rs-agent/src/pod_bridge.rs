@@ -0,0 +1,73 @@
+//! Bridge pod networking (`--pod-network bridge`).
+//!
+//! Pod IPs come from the pod CIDR the server assigned this node instead of
+//! the VPC daemon. Each pod's primary container is wired to the `k3rs-br0`
+//! bridge with a veth pair, and traffic leaving the node is masqueraded.
+
+use pkg_network::cni::PodNetwork;
+use pkg_types::pod::{Pod, PodStatus};
+use tracing::warn;
+
+pub struct PodBridge {
+    ipam: PodNetwork,
+}
+
+impl PodBridge {
+    /// IPAM for `pod_cidr`, with the addresses `pods` were given before an
+    /// agent restart reserved again.
+    pub async fn new(pod_cidr: &str, pods: &[Pod]) -> anyhow::Result<Self> {
+        let ipam = PodNetwork::new(pod_cidr)?;
+        for pod in pods
+            .iter()
+            .filter(|p| !matches!(p.status, PodStatus::Succeeded | PodStatus::Failed))
+        {
+            if let Some(ref ip) = pod.pod_ip
+                && let Err(e) = ipam.reserve(&pod.id, ip).await
+            {
+                warn!("[pod:{}] Pod IP {} not reserved: {}", pod.name, ip, e);
+            }
+        }
+        Ok(Self { ipam })
+    }
+
+    /// Create the bridge and the egress NAT rule on the host.
+    pub async fn setup_host(&self) -> anyhow::Result<()> {
+        #[cfg(target_os = "linux")]
+        {
+            pkg_network::linux::veth::ensure_pod_bridge(
+                &self.ipam.gateway(),
+                self.ipam.prefix_len(),
+            )
+            .await?;
+            pkg_network::linux::veth::ensure_masquerade(&self.ipam.cidr()).await?;
+        }
+        Ok(())
+    }
+
+    /// The pod's address, allocating one on first use.
+    pub async fn allocate(&self, pod_id: &str) -> anyhow::Result<String> {
+        self.ipam.allocate_ip(pod_id).await
+    }
+
+    /// Move a veth into the netns of `pid` with the pod's address on it.
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    pub async fn attach(&self, pod_id: &str, pod_ip: &str, pid: u32) -> anyhow::Result<()> {
+        #[cfg(target_os = "linux")]
+        pkg_network::linux::veth::setup_pod_veth(&pkg_network::linux::veth::VethConfig {
+            pod_id: pod_id.to_string(),
+            pod_ip: pod_ip.to_string(),
+            prefix_len: self.ipam.prefix_len(),
+            gateway: self.ipam.gateway(),
+            container_pid: pid,
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Delete the pod's veth and free its address. Best-effort.
+    pub async fn release(&self, pod_id: &str) {
+        #[cfg(target_os = "linux")]
+        pkg_network::linux::veth::teardown_pod_veth(pod_id).await;
+        self.ipam.release_ip(pod_id).await;
+    }
+}
@@ -0,0 +1,63 @@
+//! Agent side of `k3rsctl port-forward`: one WebSocket per forwarded TCP
+//! connection to a container port.
+//!
+//! Binary frames carry the connection's bytes in both directions. When the
+//! container side closes the connection the WebSocket is closed; when it
+//! fails, a text frame with the error goes first. A closed WebSocket drops
+//! the TCP connection.
+
+use axum::extract::ws::Message;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// Tell the client why its connection ended, then close the WebSocket.
+pub async fn close_with_error<Tx>(ws_tx: &mut Tx, error: &str)
+where
+    Tx: Sink<Message> + Unpin,
+{
+    let _ = ws_tx.send(Message::Text(error.into())).await;
+    let _ = ws_tx.send(Message::Close(None)).await;
+}
+
+/// Relay bytes between the WebSocket halves and `stream` until either side
+/// closes.
+pub async fn relay<Tx, Rx, S>(mut ws_tx: Tx, mut ws_rx: Rx, stream: S)
+where
+    Tx: Sink<Message> + Unpin,
+    Rx: Stream<Item = Result<Message, axum::Error>> + Unpin,
+    S: AsyncRead + AsyncWrite,
+{
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    loop {
+        tokio::select! {
+            read = reader.read(&mut buf) => match read {
+                Ok(0) => {
+                    let _ = ws_tx.send(Message::Close(None)).await;
+                    break;
+                }
+                Ok(n) => {
+                    if ws_tx.send(Message::Binary(buf[..n].to_vec().into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    close_with_error(&mut ws_tx, &format!("connection error: {}", e)).await;
+                    break;
+                }
+            },
+            msg = ws_rx.next() => match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    if let Err(e) = writer.write_all(&data).await {
+                        close_with_error(&mut ws_tx, &format!("connection error: {}", e)).await;
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
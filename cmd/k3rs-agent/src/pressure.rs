@@ -0,0 +1,113 @@
+//! Node pressure conditions: DiskPressure when the runtime data dir
+//! filesystem fills up, MemoryPressure when available memory runs low.
+//! Reported with each heartbeat; the server taints the node while they hold.
+
+use chrono::{DateTime, Utc};
+use pkg_types::config::AgentConfigFile;
+use pkg_types::node::{NodeCondition, NodeConditionType};
+use std::collections::BTreeMap;
+
+const DEFAULT_DISK_THRESHOLD: u8 = 90;
+const DEFAULT_MEMORY_THRESHOLD: u8 = 95;
+/// Points below its threshold usage must fall before a condition clears, so
+/// usage hovering at the threshold does not flap the condition.
+const HYSTERESIS_PERCENT: u8 = 5;
+
+/// Usage (percent) at which each condition starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PressureThresholds {
+    pub disk: u8,
+    pub memory: u8,
+}
+
+impl PressureThresholds {
+    pub fn from_config(cfg: &AgentConfigFile) -> Self {
+        Self {
+            disk: cfg
+                .disk_pressure_threshold
+                .unwrap_or(DEFAULT_DISK_THRESHOLD)
+                .min(100),
+            memory: cfg
+                .memory_pressure_threshold
+                .unwrap_or(DEFAULT_MEMORY_THRESHOLD)
+                .min(100),
+        }
+    }
+
+    fn threshold(&self, condition: NodeConditionType) -> u8 {
+        match condition {
+            NodeConditionType::DiskPressure => self.disk,
+            NodeConditionType::MemoryPressure => self.memory,
+        }
+    }
+}
+
+/// Bytes in use out of a total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub used: u64,
+    pub total: u64,
+}
+
+/// Tracks which conditions hold across heartbeats.
+pub struct PressureMonitor {
+    thresholds: PressureThresholds,
+    active: BTreeMap<NodeConditionType, NodeCondition>,
+}
+
+impl PressureMonitor {
+    pub fn new(thresholds: PressureThresholds) -> Self {
+        Self {
+            thresholds,
+            active: BTreeMap::new(),
+        }
+    }
+
+    /// Update `condition` from a fresh sample. A condition starts once usage
+    /// reaches its threshold and clears once usage is more than
+    /// [`HYSTERESIS_PERCENT`] points below it. Without a sample (`None`) it
+    /// is left as it was.
+    pub fn observe(
+        &mut self,
+        condition: NodeConditionType,
+        usage: Option<Usage>,
+        now: DateTime<Utc>,
+    ) {
+        let Some(usage) = usage.filter(|u| u.total > 0) else {
+            return;
+        };
+        let percent = usage.used as u128 * 100 / usage.total as u128;
+        let threshold = self.thresholds.threshold(condition) as u128;
+        let message = format!(
+            "{} {}% used (threshold {}%)",
+            match condition {
+                NodeConditionType::DiskPressure => "disk",
+                NodeConditionType::MemoryPressure => "memory",
+            },
+            percent,
+            threshold
+        );
+        match self.active.get_mut(&condition) {
+            Some(_) if percent + (HYSTERESIS_PERCENT as u128) < threshold => {
+                self.active.remove(&condition);
+            }
+            Some(active) => active.message = message,
+            None if percent >= threshold => {
+                self.active.insert(
+                    condition,
+                    NodeCondition {
+                        condition_type: condition,
+                        message,
+                        since: now,
+                    },
+                );
+            }
+            None => {}
+        }
+    }
+
+    /// The conditions that hold, for the heartbeat.
+    pub fn conditions(&self) -> Vec<NodeCondition> {
+        self.active.values().cloned().collect()
+    }
+}
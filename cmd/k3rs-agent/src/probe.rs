@@ -0,0 +1,268 @@
+//! Liveness and readiness probes.
+//!
+//! Every Running pod with probes gets one long-lived task that runs the
+//! checks of all its containers on their own intervals and reports outcomes
+//! to the pod-sync loop over a channel. The task is replaced when the pod
+//! restarts and stopped when the pod leaves Running.
+
+use pkg_types::pod::{Pod, PodStatus, Probe, ProbeHandler, container_id};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Runs an exec probe command inside a container: `(container_id, command)`.
+/// Resolves to an error when the command fails or exits non-zero.
+pub type ExecFn = Arc<
+    dyn Fn(String, Vec<String>) -> Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>
+        + Send
+        + Sync,
+>;
+
+/// What a pod's probe task reports.
+#[derive(Debug)]
+pub enum ProbeEvent {
+    /// A container's liveness probe failed `failure_threshold` times in a
+    /// row; that container must be restarted.
+    LivenessFailed {
+        pod: Pod,
+        container: String,
+        reason: String,
+    },
+    /// The pod's readiness (all readiness probes passing) changed.
+    ReadinessChanged { pod: Pod, ready: bool },
+}
+
+/// Run a single probe attempt against a container whose pod is reachable at `host`.
+pub async fn run_probe(
+    probe: &Probe,
+    host: &str,
+    container_id: &str,
+    http: &reqwest::Client,
+    exec: &ExecFn,
+) -> Result<(), String> {
+    let timeout = Duration::from_secs(probe.timeout_seconds.max(1));
+    let check = async {
+        match probe.handler {
+            ProbeHandler::HttpGet { ref path, port } => {
+                let host = if host.contains(':') {
+                    format!("[{}]", host)
+                } else {
+                    host.to_string()
+                };
+                let url = format!("http://{}:{}{}", host, port, path);
+                let resp = http.get(&url).send().await.map_err(|e| e.to_string())?;
+                let status = resp.status();
+                if status.is_success() || status.is_redirection() {
+                    Ok(())
+                } else {
+                    Err(format!("HTTP probe returned {}", status))
+                }
+            }
+            ProbeHandler::TcpSocket { port } => tokio::net::TcpStream::connect((host, port))
+                .await
+                .map(|_| ())
+                .map_err(|e| format!("dial tcp port {}: {}", port, e)),
+            ProbeHandler::Exec { ref command } => exec(container_id.to_string(), command.clone())
+                .await
+                .map_err(|e| e.to_string()),
+        }
+    };
+    match tokio::time::timeout(timeout, check).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("timed out after {}s", timeout.as_secs())),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckKind {
+    Liveness,
+    Readiness,
+}
+
+/// One probe of one container, with its schedule and failure streak.
+struct Check {
+    container: String,
+    container_id: String,
+    kind: CheckKind,
+    probe: Probe,
+    next: Instant,
+    failures: u32,
+    passing: bool,
+}
+
+fn pod_checks(pod: &Pod) -> Vec<Check> {
+    let start = Instant::now();
+    let mut checks = Vec::new();
+    for c in &pod.spec.containers {
+        let probes = [
+            (CheckKind::Liveness, &c.liveness_probe),
+            (CheckKind::Readiness, &c.readiness_probe),
+        ];
+        for (kind, probe) in probes {
+            let Some(probe) = probe else { continue };
+            checks.push(Check {
+                container: c.name.clone(),
+                container_id: container_id(&pod.id, &c.name),
+                kind,
+                probe: probe.clone(),
+                next: start + Duration::from_secs(probe.initial_delay_seconds),
+                failures: 0,
+                passing: pod.ready,
+            });
+        }
+    }
+    checks
+}
+
+/// Probe loop for one pod. Each check first runs after its initial delay,
+/// then every period after the previous attempt finished. Returns after
+/// reporting a liveness failure; the restarted pod gets a fresh task.
+async fn probe_pod(
+    pod: Pod,
+    host: String,
+    http: reqwest::Client,
+    exec: ExecFn,
+    events: UnboundedSender<ProbeEvent>,
+) {
+    let mut checks = pod_checks(&pod);
+    let mut ready = pod.ready;
+
+    while let Some(check) = checks.iter_mut().min_by_key(|c| c.next) {
+        tokio::time::sleep_until(check.next).await;
+        let kind = check.kind;
+        let threshold = check.probe.failure_threshold.max(1);
+        let result = run_probe(&check.probe, &host, &check.container_id, &http, &exec).await;
+        check.next = Instant::now() + Duration::from_secs(check.probe.period_seconds.max(1));
+
+        match (kind, result) {
+            (_, Ok(())) => {
+                check.failures = 0;
+                check.passing = true;
+            }
+            (CheckKind::Liveness, Err(reason)) => {
+                check.failures += 1;
+                tracing::warn!(
+                    "[pod:{}:{}] Liveness probe failed ({}/{}): {}",
+                    pod.name,
+                    check.container,
+                    check.failures,
+                    threshold,
+                    reason
+                );
+                if check.failures >= threshold {
+                    let container = check.container.clone();
+                    let _ = events.send(ProbeEvent::LivenessFailed {
+                        pod,
+                        container,
+                        reason,
+                    });
+                    return;
+                }
+            }
+            (CheckKind::Readiness, Err(reason)) => {
+                check.failures += 1;
+                tracing::debug!(
+                    "[pod:{}:{}] Readiness probe failed ({}/{}): {}",
+                    pod.name,
+                    check.container,
+                    check.failures,
+                    threshold,
+                    reason
+                );
+                if check.failures >= threshold {
+                    check.passing = false;
+                }
+            }
+        }
+
+        if kind != CheckKind::Readiness {
+            continue;
+        }
+        let now_ready = checks
+            .iter()
+            .filter(|c| c.kind == CheckKind::Readiness)
+            .all(|c| c.passing);
+        if now_ready != ready {
+            ready = now_ready;
+            let _ = events.send(ProbeEvent::ReadinessChanged {
+                pod: pod.clone(),
+                ready,
+            });
+        }
+    }
+}
+
+fn has_probes(pod: &Pod) -> bool {
+    pod.spec
+        .containers
+        .iter()
+        .any(|c| c.liveness_probe.is_some() || c.readiness_probe.is_some())
+}
+
+/// Owns the probe tasks of the pods on this node.
+pub struct ProbeManager {
+    http: reqwest::Client,
+    exec: ExecFn,
+    events: UnboundedSender<ProbeEvent>,
+    /// Pod ID → (restart count the task was started for, task).
+    tasks: HashMap<String, (u32, JoinHandle<()>)>,
+}
+
+impl ProbeManager {
+    pub fn new(exec: ExecFn, events: UnboundedSender<ProbeEvent>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            exec,
+            events,
+            tasks: HashMap::new(),
+        }
+    }
+
+    /// Start probing newly Running pods and stop the tasks of pods that left
+    /// Running, restarted, or are gone. Probing waits until the pod's address
+    /// has been reported.
+    pub fn sync(&mut self, pods: &[Pod]) {
+        self.tasks.retain(|id, (restart_count, task)| {
+            let keep = pods.iter().any(|p| {
+                &p.id == id && p.status == PodStatus::Running && p.restart_count == *restart_count
+            });
+            if !keep {
+                task.abort();
+            }
+            keep
+        });
+
+        for pod in pods {
+            if pod.status != PodStatus::Running
+                || !has_probes(pod)
+                || self.tasks.contains_key(&pod.id)
+            {
+                continue;
+            }
+            let Some(host) = pod.ghost_ipv6.clone() else {
+                continue;
+            };
+            let task = tokio::spawn(probe_pod(
+                pod.clone(),
+                host,
+                self.http.clone(),
+                self.exec.clone(),
+                self.events.clone(),
+            ));
+            self.tasks.insert(pod.id.clone(), (pod.restart_count, task));
+        }
+    }
+}
+
+impl Drop for ProbeManager {
+    fn drop(&mut self) {
+        for (_, task) in self.tasks.values() {
+            task.abort();
+        }
+    }
+}
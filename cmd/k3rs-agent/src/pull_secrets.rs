@@ -0,0 +1,59 @@
+//! Registry credentials for a pod, from the Secrets named in its
+//! `image_pull_secrets`.
+
+use crate::registration::ServerToken;
+use pkg_container::registry_auth::{AuthenticationRequired, RegistryKeychain};
+use pkg_types::pod::Pod;
+use pkg_types::secret::Secret;
+use tracing::warn;
+
+/// Status message of a pod whose registry refused an anonymous pull.
+pub const ERR_IMAGE_PULL_AUTH: &str = "ErrImagePull: authentication required";
+
+/// Fetch the pod's pull secrets from the server and merge them into one
+/// keychain. A secret that is missing or malformed is skipped with a
+/// warning; its registry is then pulled from anonymously.
+pub async fn fetch_keychain(
+    client: &reqwest::Client,
+    server: &str,
+    token: &ServerToken,
+    pod: &Pod,
+) -> RegistryKeychain {
+    let mut keychain = RegistryKeychain::default();
+    for name in &pod.spec.image_pull_secrets {
+        let url = format!(
+            "{}/api/v1/namespaces/{}/secrets/{}",
+            server.trim_end_matches('/'),
+            pod.namespace,
+            name
+        );
+        let secret = match client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+        {
+            Ok(resp) => resp.json::<Secret>().await,
+            Err(e) => Err(e),
+        };
+        match secret
+            .map_err(anyhow::Error::from)
+            .and_then(|s| RegistryKeychain::from_secret(&s))
+        {
+            Ok(secret_keychain) => keychain.merge(secret_keychain),
+            Err(e) => warn!(
+                "[pod:{}] Skipping image pull secret {}: {:#}",
+                pod.name, name, e
+            ),
+        }
+    }
+    keychain
+}
+
+/// Status message for an image pull that failed for a well-known reason.
+pub fn pull_failure_message(err: &anyhow::Error) -> Option<String> {
+    err.chain()
+        .any(|cause| cause.is::<AuthenticationRequired>())
+        .then(|| ERR_IMAGE_PULL_AUTH.to_string())
+}
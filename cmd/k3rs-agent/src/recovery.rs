@@ -1,3 +1,4 @@
+use crate::registration::ServerToken;
 use pkg_container::ContainerRuntime;
 use std::sync::Arc;
 use tracing::{info, warn};
@@ -8,7 +9,7 @@ pub async fn run_recovery(
     node_id: Option<&str>,
     server: &str,
     node_name: &str,
-    token: &str,
+    token: &ServerToken,
     client: &reqwest::Client,
     cached_pods: Vec<pkg_types::pod::Pod>,
 ) {
@@ -39,8 +40,11 @@ pub async fn run_recovery(
         };
 
         let mut desired_running_ids = std::collections::HashMap::new();
-        for pod in &desired_pods {
-            desired_running_ids.insert(pod.id.clone(), (pod.name.clone(), pod.namespace.clone()));
+        // Static pods are restarted from their manifests
+        for pod in desired_pods.iter().filter(|p| !p.mirror) {
+            for cid in pod.container_ids() {
+                desired_running_ids.insert(cid, (pod.name.clone(), pod.namespace.clone()));
+            }
         }
 
         for cid in discovered {
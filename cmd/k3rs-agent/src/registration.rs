@@ -1,6 +1,32 @@
+use crate::cache::AgentStateCache;
 use pkg_types::node::{NodeRegistrationRequest, NodeRegistrationResponse};
+use std::fmt;
+use std::sync::{Arc, RwLock};
 use tracing::info;
 
+/// The bearer token the agent presents to the server: its node's agent
+/// token from the latest registration (the join token only registers).
+/// Read from the cache each time it is formatted, so a re-registration's
+/// fresh token is used at once; empty before the first registration.
+#[derive(Clone)]
+pub struct ServerToken(Arc<RwLock<AgentStateCache>>);
+
+impl ServerToken {
+    pub fn new(cache: Arc<RwLock<AgentStateCache>>) -> Self {
+        Self(cache)
+    }
+}
+
+impl fmt::Display for ServerToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cache = self.0.read().unwrap();
+        match &cache.agent_token {
+            Some(token) => f.write_str(&pkg_types::rbac::node_bearer(&cache.node_name, token)),
+            None => Ok(()),
+        }
+    }
+}
+
 /// Attempt registration with the server. Returns (node_id, agent_api_port, response) on success.
 pub async fn try_register(
     client: &reqwest::Client,
@@ -20,12 +46,15 @@ pub async fn try_register(
         );
 
         // Store certs to disk for future mTLS connections
-        let cert_dir = format!("{}/certs/{}", pkg_constants::paths::CONFIG_DIR, node_name);
-        tokio::fs::create_dir_all(&cert_dir).await?;
-        tokio::fs::write(format!("{}/node.crt", cert_dir), &reg_resp.certificate).await?;
-        tokio::fs::write(format!("{}/node.key", cert_dir), &reg_resp.private_key).await?;
-        tokio::fs::write(format!("{}/ca.crt", cert_dir), &reg_resp.server_ca).await?;
-        info!("Certificates saved to {}", cert_dir);
+        let cert_dir = crate::cert_rotation::cert_dir(node_name);
+        crate::cert_rotation::write_certs(
+            &cert_dir,
+            &reg_resp.certificate,
+            &reg_resp.private_key,
+            &reg_resp.server_ca,
+        )
+        .await?;
+        info!("Certificates saved to {}", cert_dir.display());
 
         Ok((node_id, port, reg_resp))
     } else {
@@ -43,17 +72,17 @@ pub async fn try_register(
 /// a cached node_id). If the server still knows us, skip registration entirely.
 /// Otherwise fall back to full registration.
 ///
-/// Returns `Ok(true)` if we're now connected (either via probe or registration),
-/// `Ok(false)` if we had a cached probe success (node_id already set),
-/// `Err` if both probe and registration failed.
+/// Returns the registration response if the agent registered, `Ok(None)` if
+/// the probe succeeded and the cached identity still holds, and `Err` if
+/// both probe and registration failed.
 pub async fn try_connect(
     client: &reqwest::Client,
     server: &str,
-    token: &str,
+    token: &ServerToken,
     reg_req: &NodeRegistrationRequest,
     node_name: &str,
     cached_node_id: Option<&str>,
-) -> anyhow::Result<Option<(String, u16)>> {
+) -> anyhow::Result<Option<NodeRegistrationResponse>> {
     // If we have a cached node_id, probe the server with a heartbeat first.
     // This avoids re-registration when the server already knows this node
     // (e.g. agent restart while server is still running).
@@ -92,6 +121,6 @@ pub async fn try_connect(
     }
 
     // Probe failed or no cached node_id — do full registration
-    let (node_id, port, _resp) = try_register(client, server, reg_req, node_name).await?;
-    Ok(Some((node_id, port)))
+    let (_, _, resp) = try_register(client, server, reg_req, node_name).await?;
+    Ok(Some(resp))
 }
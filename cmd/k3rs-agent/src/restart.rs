@@ -0,0 +1,113 @@
+//! Container restart policy and crash-loop backoff.
+
+use pkg_constants::timings::{
+    BACKOFF_SHIFT_CAP, CRASH_LOOP_BACKOFF_BASE_SECS, CRASH_LOOP_BACKOFF_MAX_SECS,
+    CRASH_LOOP_BACKOFF_RESET_SECS,
+};
+use pkg_types::pod::RestartPolicy;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// What happens to a pod after its container exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitAction {
+    Restart,
+    Succeeded,
+    Failed,
+}
+
+/// Decide from the pod's restart policy and the container's exit code.
+///
+/// An unknown exit code (the backend could not report one) counts as a failure.
+pub fn exit_action(policy: RestartPolicy, exit_code: Option<i32>) -> ExitAction {
+    let succeeded = exit_code == Some(0);
+    match (policy, succeeded) {
+        (RestartPolicy::Always, _) | (RestartPolicy::OnFailure, false) => ExitAction::Restart,
+        (RestartPolicy::OnFailure | RestartPolicy::Never, true) => ExitAction::Succeeded,
+        (RestartPolicy::Never, false) => ExitAction::Failed,
+    }
+}
+
+/// Observed state of one of a pod's containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerPhase {
+    Running,
+    /// Stopped or missing, with the exit code if the backend reported one.
+    Exited(Option<i32>),
+}
+
+/// Decide what happens to a pod from the state of all its containers, in
+/// spec order. Returns the action and the index of the container that
+/// triggered it, or `None` while the pod should keep running.
+///
+/// Any container the policy would restart or fail decides for the whole
+/// pod; the pod succeeds only once every container has exited cleanly.
+pub fn pod_exit_action(
+    policy: RestartPolicy,
+    containers: &[ContainerPhase],
+) -> Option<(ExitAction, usize)> {
+    let mut all_succeeded = !containers.is_empty();
+    for (index, phase) in containers.iter().enumerate() {
+        match *phase {
+            ContainerPhase::Running => all_succeeded = false,
+            ContainerPhase::Exited(exit_code) => match exit_action(policy, exit_code) {
+                ExitAction::Succeeded => {}
+                action => return Some((action, index)),
+            },
+        }
+    }
+    all_succeeded.then_some((ExitAction::Succeeded, 0))
+}
+
+/// Delay before restart `attempt` (0-based): 10s, 20s, 40s, … capped at 5m.
+pub fn crash_loop_backoff(attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.min(BACKOFF_SHIFT_CAP);
+    let secs = CRASH_LOOP_BACKOFF_BASE_SECS
+        .saturating_mul(factor)
+        .min(CRASH_LOOP_BACKOFF_MAX_SECS);
+    Duration::from_secs(secs)
+}
+
+struct Backoff {
+    attempt: u32,
+    restart_at: Instant,
+}
+
+/// Per-pod crash-loop state, keyed by pod ID. Lives only in the agent; after
+/// an agent restart every pod starts again from the first delay.
+#[derive(Default)]
+pub struct CrashLoopTracker {
+    pods: HashMap<String, Backoff>,
+}
+
+impl CrashLoopTracker {
+    /// Record a container exit at `now` and return the delay before it is
+    /// restarted. The delay resets once a container has stayed up for
+    /// `CRASH_LOOP_BACKOFF_RESET_SECS` after its last restart.
+    pub fn record_exit(&mut self, pod_id: &str, now: Instant) -> Duration {
+        let reset = Duration::from_secs(CRASH_LOOP_BACKOFF_RESET_SECS);
+        let attempt = match self.pods.get(pod_id) {
+            Some(b) if now.saturating_duration_since(b.restart_at) < reset => b.attempt + 1,
+            _ => 0,
+        };
+        let delay = crash_loop_backoff(attempt);
+        self.pods.insert(
+            pod_id.to_string(),
+            Backoff {
+                attempt,
+                restart_at: now + delay,
+            },
+        );
+        delay
+    }
+
+    /// Whether the pod is still waiting out its backoff at `now`.
+    pub fn in_backoff(&self, pod_id: &str, now: Instant) -> bool {
+        self.pods.get(pod_id).is_some_and(|b| now < b.restart_at)
+    }
+
+    /// Forget pods that are no longer assigned to this node.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.pods.retain(|id, _| keep(id));
+    }
+}
@@ -0,0 +1,94 @@
+//! Settings the agent picks up from its config file while running. The loops
+//! read them from a shared [`RuntimeConfig`] on every tick; the config reload
+//! loop replaces it when the file changes.
+
+use crate::image_gc::GcPolicy;
+use pkg_types::config::{AgentConfigFile, ConfigFileWatcher, Reloaded};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::time::{Instant, Interval};
+
+/// Config file keys applied when the file changes; the rest need a restart.
+pub const LIVE_CONFIG_KEYS: &[&str] = &[
+    "log-level",
+    "heartbeat-interval",
+    "pod-sync-interval",
+    "route-sync-interval",
+    "termination-grace-period",
+    "image-gc-high-threshold",
+    "image-gc-low-threshold",
+    "image-gc-max-bytes",
+];
+
+/// Added to every log filter: the registry client logs token responses at
+/// debug level.
+pub const PINNED_LOG_DIRECTIVES: &[&str] = &["oci_client=info"];
+
+pub type SharedRuntimeConfig = Arc<RwLock<RuntimeConfig>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    /// Log filter from the config file, `None` for the default.
+    pub log_level: Option<String>,
+    pub heartbeat_interval: Duration,
+    pub pod_sync_interval: Duration,
+    pub route_sync_interval: Duration,
+    /// Grace period of pods that set none.
+    pub termination_grace_period: Duration,
+    pub image_gc: GcPolicy,
+}
+
+impl RuntimeConfig {
+    /// The live settings in `cfg`. Fails on a `log-level` that is not a
+    /// valid filter.
+    pub fn from_config(cfg: &AgentConfigFile) -> anyhow::Result<Self> {
+        if let Some(ref level) = cfg.log_level {
+            pkg_metrics::log_filter::parse(level).map_err(anyhow::Error::msg)?;
+        }
+        let interval =
+            |secs: Option<u64>, default: u64| Duration::from_secs(secs.unwrap_or(default).max(1));
+        Ok(Self {
+            log_level: cfg.log_level.clone(),
+            heartbeat_interval: interval(
+                cfg.heartbeat_interval,
+                pkg_constants::timings::HEARTBEAT_INTERVAL_SECS,
+            ),
+            pod_sync_interval: interval(
+                cfg.pod_sync_interval,
+                pkg_constants::timings::POD_SYNC_INTERVAL_SECS,
+            ),
+            route_sync_interval: interval(
+                cfg.route_sync_interval,
+                pkg_constants::timings::ROUTE_SYNC_INTERVAL_SECS,
+            ),
+            termination_grace_period: Duration::from_secs(
+                cfg.termination_grace_period
+                    .unwrap_or(pkg_constants::runtime::DEFAULT_TERMINATION_GRACE_PERIOD_SECS),
+            ),
+            image_gc: GcPolicy::from_config(cfg),
+        })
+    }
+}
+
+/// Re-read the config file and, if it changed and is valid, replace `shared`
+/// with its live settings. An invalid file leaves `shared` as it was.
+pub fn reload(
+    watcher: &mut ConfigFileWatcher<AgentConfigFile>,
+    shared: &SharedRuntimeConfig,
+) -> anyhow::Result<Option<Reloaded<RuntimeConfig>>> {
+    let reloaded = watcher.poll(RuntimeConfig::from_config)?;
+    if let Some(ref reloaded) = reloaded {
+        *shared.write().unwrap() = reloaded.value.clone();
+    }
+    Ok(reloaded)
+}
+
+/// Restart `interval` with `period` if that is not its period already, so a
+/// reloaded interval applies from the next tick.
+pub fn retune(interval: &mut Interval, period: Duration) {
+    if interval.period() != period {
+        let behavior = interval.missed_tick_behavior();
+        *interval = tokio::time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(behavior);
+    }
+}
@@ -0,0 +1,236 @@
+//! Static pods: pods the agent runs from Pod manifests in a local directory,
+//! without the server — for bootstrapping add-ons such as DNS or ingress.
+
+use anyhow::Context;
+use pkg_types::config::AgentConfigFile;
+use pkg_types::pod::{Pod, PodStatus, mirror_pod_name};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Directory static pod manifests are read from.
+pub fn manifest_dir(cfg: &AgentConfigFile) -> PathBuf {
+    cfg.static_pod_dir
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(pkg_constants::paths::CONFIG_DIR).join("manifests"))
+}
+
+/// Starts and stops static pods: the container runtime in the agent, a stub
+/// in tests.
+pub trait PodRuntime {
+    fn start_pod(&self, pod: &Pod) -> impl Future<Output = anyhow::Result<()>> + Send;
+    fn stop_pod(&self, pod: &Pod) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+/// A manifest file's contents and their hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFile {
+    pub hash: u64,
+    pub content: String,
+}
+
+/// The manifests (`*.yaml`, `*.yml`) in `dir`. A missing directory has none.
+pub fn scan(dir: &Path) -> BTreeMap<PathBuf, ManifestFile> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return BTreeMap::new();
+    };
+    entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|ext| ext == "yaml" || ext == "yml")
+        })
+        .filter_map(|path| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            let mut hasher = DefaultHasher::new();
+            content.hash(&mut hasher);
+            let hash = hasher.finish();
+            Some((path, ManifestFile { hash, content }))
+        })
+        .collect()
+}
+
+/// ID of the static pod `namespace/name`; the same across agent restarts.
+pub fn static_pod_id(namespace: &str, name: &str) -> String {
+    format!("static-{}-{}", namespace, name)
+}
+
+/// Parse a Pod manifest. `id`, `namespace` (`default`), `status` and
+/// `created_at` may be left out; the ID is always [`static_pod_id`], and
+/// must be a valid container ID.
+pub fn parse(content: &str) -> anyhow::Result<Pod> {
+    let mut value: serde_yaml::Value = serde_yaml::from_str(content)?;
+    if let Some(kind) = value.get("kind").and_then(|k| k.as_str())
+        && kind != "Pod"
+    {
+        anyhow::bail!("kind {} is not Pod", kind);
+    }
+    let fields = value
+        .as_mapping_mut()
+        .context("manifest is not a mapping")?;
+    let created_at = chrono::Utc::now().to_rfc3339();
+    for (key, default) in [
+        ("id", ""),
+        ("namespace", "default"),
+        ("status", "Pending"),
+        ("created_at", created_at.as_str()),
+    ] {
+        if !fields.contains_key(key) {
+            fields.insert(key.into(), default.into());
+        }
+    }
+    let mut pod: Pod = serde_yaml::from_value(value)?;
+    if pod.name.is_empty() {
+        anyhow::bail!("name is required");
+    }
+    pod.id = static_pod_id(&pod.namespace, &pod.name);
+    pkg_types::validate::validate_container_id(&pod.id)?;
+    Ok(pod)
+}
+
+/// The read-only record of `pod` the server shows for it.
+pub fn mirror(pod: &Pod, node_name: &str) -> Pod {
+    let mut mirror = pod.clone();
+    mirror.name = mirror_pod_name(&pod.name, node_name);
+    mirror.node_name = Some(node_name.to_string());
+    mirror.status = PodStatus::Running;
+    mirror.mirror = true;
+    mirror
+}
+
+struct Running {
+    hash: u64,
+    pod: Pod,
+}
+
+/// The static pods running from a manifest directory.
+pub struct StaticPods<R> {
+    runtime: R,
+    running: BTreeMap<PathBuf, Running>,
+    /// Manifests not run, by the hash they were rejected at.
+    rejected: HashMap<PathBuf, u64>,
+}
+
+impl<R: PodRuntime> StaticPods<R> {
+    pub fn new(runtime: R) -> Self {
+        Self {
+            runtime,
+            running: BTreeMap::new(),
+            rejected: HashMap::new(),
+        }
+    }
+
+    /// Bring the pods in line with the manifests in `dir`: start new ones,
+    /// restart those whose file changed and stop those whose file is gone.
+    /// A manifest that does not parse leaves its pod as it was; one that
+    /// fails to start is retried on the next sync.
+    pub async fn sync(&mut self, dir: &Path) {
+        let files = scan(dir);
+
+        let gone: Vec<PathBuf> = self
+            .running
+            .keys()
+            .filter(|path| !files.contains_key(*path))
+            .cloned()
+            .collect();
+        for path in &gone {
+            let Some(running) = self.running.remove(path) else {
+                continue;
+            };
+            info!(
+                "Static pod {}/{}: {} removed, stopping",
+                running.pod.namespace,
+                running.pod.name,
+                path.display()
+            );
+            if let Err(e) = self.runtime.stop_pod(&running.pod).await {
+                warn!(
+                    "Static pod {}/{} failed to stop: {:#}",
+                    running.pod.namespace, running.pod.name, e
+                );
+            }
+        }
+        // A removed pod may free the name a rejected manifest wanted.
+        if !gone.is_empty() {
+            self.rejected.clear();
+        }
+        self.rejected.retain(|path, _| files.contains_key(path));
+
+        for (path, file) in files {
+            let seen = self.running.get(&path).map(|r| r.hash);
+            if seen == Some(file.hash) || self.rejected.get(&path) == Some(&file.hash) {
+                continue;
+            }
+            let pod = match parse(&file.content) {
+                Ok(pod) => pod,
+                Err(e) => {
+                    warn!("Static pod manifest {} is invalid: {:#}", path.display(), e);
+                    self.rejected.insert(path, file.hash);
+                    continue;
+                }
+            };
+            if let Some(other) = self
+                .running
+                .iter()
+                .find(|(other, r)| **other != path && r.pod.id == pod.id)
+                .map(|(other, _)| other)
+            {
+                warn!(
+                    "Static pod manifest {}: {} already defines pod {}/{}",
+                    path.display(),
+                    other.display(),
+                    pod.namespace,
+                    pod.name
+                );
+                self.rejected.insert(path, file.hash);
+                continue;
+            }
+
+            if let Some(old) = self.running.remove(&path) {
+                info!(
+                    "Static pod {}/{}: {} changed, restarting",
+                    pod.namespace,
+                    pod.name,
+                    path.display()
+                );
+                if let Err(e) = self.runtime.stop_pod(&old.pod).await {
+                    warn!(
+                        "Static pod {}/{} failed to stop: {:#}",
+                        old.pod.namespace, old.pod.name, e
+                    );
+                }
+            }
+            match self.runtime.start_pod(&pod).await {
+                Ok(()) => {
+                    info!(
+                        "Static pod {}/{} started from {}",
+                        pod.namespace,
+                        pod.name,
+                        path.display()
+                    );
+                    self.running.insert(
+                        path,
+                        Running {
+                            hash: file.hash,
+                            pod,
+                        },
+                    );
+                }
+                Err(e) => warn!(
+                    "Static pod {}/{} failed to start: {:#}",
+                    pod.namespace, pod.name, e
+                ),
+            }
+        }
+    }
+
+    /// The pods running now.
+    pub fn pods(&self) -> Vec<&Pod> {
+        self.running.values().map(|r| &r.pod).collect()
+    }
+}
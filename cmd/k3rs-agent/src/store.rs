@@ -49,6 +49,10 @@ struct AgentMeta {
     node_name: String,
     node_id: Option<String>,
     agent_api_port: Option<u16>,
+    #[serde(default)]
+    pod_cidr: Option<String>,
+    #[serde(default)]
+    agent_token: Option<String>,
     server_seq: u64,
     last_synced_at: DateTime<Utc>,
 }
@@ -92,6 +96,8 @@ impl AgentStore {
             node_name: cache.node_name.clone(),
             node_id: cache.node_id.clone(),
             agent_api_port: cache.agent_api_port,
+            pod_cidr: cache.pod_cidr.clone(),
+            agent_token: cache.agent_token.clone(),
             server_seq: cache.server_seq,
             last_synced_at: cache.last_synced_at,
         };
@@ -148,6 +154,8 @@ impl AgentStore {
             node_name: meta.node_name,
             node_id: meta.node_id,
             agent_api_port: meta.agent_api_port,
+            pod_cidr: meta.pod_cidr,
+            agent_token: meta.agent_token,
             server_seq: meta.server_seq,
             last_synced_at: meta.last_synced_at,
             pods,
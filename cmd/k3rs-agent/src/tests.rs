@@ -3,6 +3,26 @@
 //! These tests run in-process (no server, no containers) using #[tokio::test].
 //! They cover:
 //!   - `ConnectivityManager::backoff_duration`: sequence, overflow safety, heartbeat off-by-one
+//!   - `restart`: crash-loop backoff schedule and restart-policy decisions, per container and per pod
+//!   - `pod_sync`: exited containers of Running pods against a stub runtime and status server
+//!   - `container_statuses`: per-container statuses from runtime observations
+//!   - `init_containers`: sequential init containers and their failure handling
+//!   - `probe`: HTTP / TCP / exec probe checks and the per-pod probe tasks
+//!   - `volumes`: resolving container volume mounts against the pod's volumes
+//!   - `pull_secrets`: registry credentials from image pull secrets
+//!   - `image_gc`: watermark math and least-recently-used image selection
+//!   - `orphan_gc`: leftover container, VM and log entries, the grace period and in-flight pods
+//!   - `pressure`: disk and memory pressure thresholds and their hysteresis
+//!   - `runtime_config`: config file reload, the settings loops read each tick, and rejected files
+//!   - `static_pods`: manifest change detection, the static pod lifecycle against a stub runtime, and mirror records
+//!   - `heartbeat::pod_usage`: per-pod usage from container cgroups and processes
+//!   - `cgroup`: cgroup v2 file parsing, CPU rate between samples and OOM kills
+//!   - `pod_watch::wakes_pod_sync`: which watch events trigger an early pod relist
+//!   - `pod_bridge`: pod IPs held before a restart, and when to re-register for a pod CIDR
+//!   - `cert_rotation`: renewal threshold, atomic certificate swap and the renewal request
+//!   - `metrics`: the series the agent's `/metrics` endpoint renders
+//!   - `api_auth`: the agent API's token check, including on WebSocket upgrades
+//!   - `port_forward::relay`: WebSocket ↔ TCP relaying against an echo server
 //!   - `ConnectivityManager` state-machine transitions
 //!   - `AgentStateCache::derive_routes_map` / `derive_dns_map`: routing and DNS derivation logic
 //!   - `AgentStore::open` / `save` / `load` / `load_routes` / `load_dns_records`: SlateDB round-trips
@@ -13,141 +33,2605 @@
 
 #[cfg(test)]
 mod helpers {
+    use crate::cache::AgentStateCache;
+    use crate::registration::ServerToken;
     use chrono::Utc;
     use pkg_types::{
         endpoint::{Endpoint, EndpointAddress},
         service::{Service, ServicePort, ServiceSpec, ServiceType},
     };
     use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+
+    /// Build a Service fixture with a single TCP port.
+    pub fn make_service(
+        id: &str,
+        name: &str,
+        ns: &str,
+        cluster_ip: &str,
+        port: u16,
+        target_port: u16,
+    ) -> Service {
+        Service {
+            id: id.to_string(),
+            name: name.to_string(),
+            namespace: ns.to_string(),
+            cluster_ip: Some(cluster_ip.to_string()),
+            vpc: Some("default".to_string()),
+            spec: ServiceSpec {
+                selector: HashMap::new(),
+                ports: vec![ServicePort {
+                    name: "http".to_string(),
+                    protocol: Default::default(),
+                    port,
+                    target_port,
+                    node_port: None,
+                }],
+                service_type: ServiceType::ClusterIP,
+                session_affinity: None,
+                session_affinity_timeout_seconds: None,
+                health_check_path: None,
+            },
+            created_at: Utc::now(),
+            resource_version: 0,
+        }
+    }
+
+    /// Build an Endpoint fixture with a single pod IP address.
+    pub fn make_endpoint(
+        id: &str,
+        service_id: &str,
+        service_name: &str,
+        ns: &str,
+        pod_ip: &str,
+    ) -> Endpoint {
+        Endpoint {
+            id: id.to_string(),
+            service_id: service_id.to_string(),
+            service_name: service_name.to_string(),
+            namespace: ns.to_string(),
+            addresses: vec![EndpointAddress {
+                ip: pod_ip.to_string(),
+                node_name: None,
+                pod_id: None,
+                hostname: None,
+            }],
+            ports: vec![],
+            created_at: Utc::now(),
+        }
+    }
+
+    /// The server token of node `worker-1` after it was issued `agent_token`.
+    pub fn server_token(agent_token: &str) -> ServerToken {
+        let mut cache = AgentStateCache::new("worker-1".to_string());
+        cache.agent_token = Some(agent_token.to_string());
+        ServerToken::new(Arc::new(RwLock::new(cache)))
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Group 2 — Backoff
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod backoff_tests {
+    use crate::connectivity::ConnectivityManager;
+    use std::time::Duration;
+
+    /// Verify the full 1s → 2s → 4s → 8s → 16s → 30s sequence.
+    #[test]
+    fn backoff_sequence_is_correct() {
+        let cases = [
+            (0, 1u64),
+            (1, 2),
+            (2, 4),
+            (3, 8),
+            (4, 16),
+            (5, 30), // 32 → capped to 30
+            (6, 30),
+            (10, 30),
+        ];
+        for (attempt, expected_secs) in cases {
+            assert_eq!(
+                ConnectivityManager::backoff_duration(attempt),
+                Duration::from_secs(expected_secs),
+                "attempt={} must give {}s",
+                attempt,
+                expected_secs
+            );
+        }
+    }
+
+    /// Regression: `1u64 << attempt` panicked when attempt >= 64.
+    /// Fixed by capping shift index at 30 (`attempt.min(30)`).
+    #[test]
+    fn backoff_does_not_panic_on_large_attempt() {
+        let _ = ConnectivityManager::backoff_duration(63);
+        let _ = ConnectivityManager::backoff_duration(64); // was panic before fix
+        let _ = ConnectivityManager::backoff_duration(128);
+        let _ = ConnectivityManager::backoff_duration(u32::MAX);
+    }
+
+    /// Verify the heartbeat off-by-one fix: `fail_count.saturating_sub(1)`.
+    ///
+    /// In the heartbeat loop, `fail_count` is incremented *after* a failure and
+    /// is therefore 1-based at the top of the next loop iteration. The fix
+    /// subtracts 1 to get a 0-based index, so the first retry fires after 1s
+    /// rather than 2s.
+    #[test]
+    fn heartbeat_backoff_is_1s_on_first_retry() {
+        // fail_count=1 after first failure → saturating_sub(1) = 0 → 1s
+        let first = ConnectivityManager::backoff_duration(1u32.saturating_sub(1));
+        assert_eq!(
+            first,
+            Duration::from_secs(1),
+            "first heartbeat retry must be 1s"
+        );
+
+        // fail_count=2 → index 1 → 2s
+        let second = ConnectivityManager::backoff_duration(2u32.saturating_sub(1));
+        assert_eq!(second, Duration::from_secs(2));
+
+        // fail_count=3 → index 2 → 4s
+        let third = ConnectivityManager::backoff_duration(3u32.saturating_sub(1));
+        assert_eq!(third, Duration::from_secs(4));
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Container restart policy / crash-loop backoff
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod restart_tests {
+    use crate::restart::{
+        ContainerPhase, CrashLoopTracker, ExitAction, crash_loop_backoff, exit_action,
+        pod_exit_action,
+    };
+    use pkg_types::pod::RestartPolicy;
+    use std::time::{Duration, Instant};
+
+    /// 10s → 20s → 40s → 80s → 160s → 300s cap.
+    #[test]
+    fn crash_loop_backoff_doubles_and_caps_at_five_minutes() {
+        let cases = [
+            (0, 10u64),
+            (1, 20),
+            (2, 40),
+            (3, 80),
+            (4, 160),
+            (5, 300), // 320 → capped
+            (9, 300),
+            (u32::MAX, 300),
+        ];
+        for (attempt, expected_secs) in cases {
+            assert_eq!(
+                crash_loop_backoff(attempt),
+                Duration::from_secs(expected_secs),
+                "attempt={} must give {}s",
+                attempt,
+                expected_secs
+            );
+        }
+    }
+
+    #[test]
+    fn exit_action_matrix() {
+        use ExitAction::*;
+        use RestartPolicy::*;
+        let cases = [
+            (Always, Some(0), Restart),
+            (Always, Some(1), Restart),
+            (Always, None, Restart),
+            (OnFailure, Some(0), Succeeded),
+            (OnFailure, Some(137), Restart),
+            (OnFailure, None, Restart),
+            (Never, Some(0), Succeeded),
+            (Never, Some(2), Failed),
+            (Never, None, Failed),
+        ];
+        for (policy, code, expected) in cases {
+            assert_eq!(
+                exit_action(policy, code),
+                expected,
+                "policy={} exit_code={:?}",
+                policy,
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn pod_exit_action_aggregates_containers() {
+        use ContainerPhase::*;
+        use RestartPolicy::*;
+
+        // Every container up: nothing to do.
+        assert_eq!(pod_exit_action(Always, &[Running, Running]), None);
+        // One crashed sidecar restarts the whole pod.
+        assert_eq!(
+            pod_exit_action(Always, &[Running, Exited(Some(1))]),
+            Some((ExitAction::Restart, 1))
+        );
+        // A container that finished cleanly waits for its siblings...
+        assert_eq!(
+            pod_exit_action(OnFailure, &[Exited(Some(0)), Running]),
+            None
+        );
+        assert_eq!(pod_exit_action(Never, &[Running, Exited(Some(0))]), None);
+        // ...and the pod succeeds once all have.
+        assert_eq!(
+            pod_exit_action(Never, &[Exited(Some(0)), Exited(Some(0))]),
+            Some((ExitAction::Succeeded, 0))
+        );
+        // A failure decides even while others still run; a missing container
+        // counts as one.
+        assert_eq!(
+            pod_exit_action(Never, &[Exited(Some(0)), Running, Exited(None)]),
+            Some((ExitAction::Failed, 2))
+        );
+        assert_eq!(
+            pod_exit_action(OnFailure, &[Running, Exited(Some(137))]),
+            Some((ExitAction::Restart, 1))
+        );
+        assert_eq!(pod_exit_action(Always, &[]), None);
+    }
+
+    #[test]
+    fn tracker_grows_backoff_until_container_stays_up() {
+        let mut tracker = CrashLoopTracker::default();
+        let t0 = Instant::now();
+
+        assert_eq!(tracker.record_exit("p1", t0), Duration::from_secs(10));
+        assert!(tracker.in_backoff("p1", t0 + Duration::from_secs(5)));
+        assert!(!tracker.in_backoff("p1", t0 + Duration::from_secs(10)));
+
+        // Crashes again shortly after the restart: next step.
+        let t1 = t0 + Duration::from_secs(15);
+        assert_eq!(tracker.record_exit("p1", t1), Duration::from_secs(20));
+        let t2 = t1 + Duration::from_secs(30);
+        assert_eq!(tracker.record_exit("p1", t2), Duration::from_secs(40));
+
+        // Ran for over ten minutes after the last restart: back to the start.
+        let t3 = t2 + Duration::from_secs(40 + 601);
+        assert_eq!(tracker.record_exit("p1", t3), Duration::from_secs(10));
+
+        // Other pods are independent, and removed pods are forgotten.
+        assert!(!tracker.in_backoff("p2", t3));
+        tracker.retain(|id| id != "p1");
+        assert!(!tracker.in_backoff("p1", t3));
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Pod sync: exited containers
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod pod_sync_tests {
+    use super::helpers::server_token;
+    use crate::loops::pod_sync::check_running_pods;
+    use crate::restart::CrashLoopTracker;
+    use crate::vpc_client::VpcClient;
+    use pkg_container::ContainerRuntime;
+    use pkg_container::backend::OciBackend;
+    use pkg_container::logs::LogRotation;
+    use pkg_container::test_util::temp_dir;
+    use pkg_types::pod::Pod;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::mpsc;
+
+    /// An OCI runtime whose `start` runs `sleep 0.2; exit <code>` as the
+    /// container process and whose `state` reports it stopped once it is
+    /// gone or a zombie, as youki and crun do.
+    fn stub_runtime(dir: &Path, code: i32) -> PathBuf {
+        pkg_container::test_util::stub_runtime(
+            dir,
+            &format!(
+                "#!/bin/sh\n\
+                 dir=$(dirname \"$0\")\n\
+                 while [ $# -gt 0 ]; do\n\
+                   case \"$1\" in\n\
+                     start) sh -c 'sleep 0.2; exit {}' </dev/null >/dev/null 2>&1 &\n\
+                       echo $! > \"$dir/logs/$2/container.pid\"; exit 0 ;;\n\
+                     state) pid=$(cat \"$dir/logs/$2/container.pid\")\n\
+                       status=stopped\n\
+                       if kill -0 \"$pid\" 2>/dev/null && ! grep -q ') Z' \"/proc/$pid/stat\"; then status=running; fi\n\
+                       echo \"{{\\\"id\\\":\\\"$2\\\",\\\"status\\\":\\\"$status\\\",\\\"pid\\\":$pid}}\"; exit 0 ;;\n\
+                     *) shift ;;\n\
+                   esac\n\
+                 done\n",
+                code
+            ),
+        )
+    }
+
+    /// Accepts status updates and forwards each request body.
+    async fn status_server() -> (String, mpsc::UnboundedReceiver<serde_json::Value>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = vec![0u8; 4096];
+                let body = loop {
+                    let n = conn.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break None;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = head
+                        .lines()
+                        .find_map(|l| {
+                            l.to_ascii_lowercase()
+                                .strip_prefix("content-length:")
+                                .map(|v| v.trim().parse::<usize>().unwrap_or(0))
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break Some(body.to_string());
+                    }
+                };
+                if let Some(body) = body.and_then(|b| serde_json::from_str(&b).ok()) {
+                    let _ = tx.send(body);
+                }
+                let _ = conn
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .await;
+            }
+        });
+        (format!("http://{}", addr), rx)
+    }
+
+    /// A clean exit is reported as exit code 0, so `OnFailure` ends the pod
+    /// in `Succeeded` instead of restarting it as an unknown failure.
+    #[tokio::test]
+    async fn clean_exit_ends_in_succeeded() {
+        // As the agent does at startup, so the stub's container process is
+        // reparented to this process once the stub exits.
+        pkg_container::reaper::become_subreaper().unwrap();
+        let dir = temp_dir("pod-sync-exit");
+        std::fs::create_dir_all(&dir).unwrap();
+        let backend = OciBackend::new(stub_runtime(&dir, 0).to_str().unwrap(), &dir);
+        let runtime = Arc::new(ContainerRuntime::with_backend(
+            Arc::new(backend),
+            dir.clone(),
+            LogRotation::default(),
+        ));
+        let pod: Pod = serde_json::from_value(serde_json::json!({
+            "id": "p1",
+            "name": "job-0",
+            "namespace": "default",
+            "status": "Running",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "restart_policy": "OnFailure",
+                "containers": [{ "name": "task", "image": "busybox" }]
+            }
+        }))
+        .unwrap();
+        let id = pod.container_ids().remove(0);
+        std::fs::create_dir_all(dir.join("logs").join(&id)).unwrap();
+        runtime
+            .container_store()
+            .track(&id, "busybox", "stub-runtime", "", "");
+        runtime.start_container(&id).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while runtime.container_state(&id).await.unwrap().status != "stopped" {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("container stopped");
+
+        let (server, mut updates) = status_server().await;
+        let vpc = Arc::new(VpcClient::new(
+            dir.join("vpc.sock").to_string_lossy().to_string(),
+        ));
+        check_running_pods(
+            std::slice::from_ref(&pod),
+            &runtime,
+            &reqwest::Client::new(),
+            &server,
+            &server_token("node-token"),
+            &vpc,
+            &None,
+            &Mutex::new(CrashLoopTracker::default()),
+            Duration::from_secs(1),
+            #[cfg(target_os = "macos")]
+            &None,
+        )
+        .await;
+
+        let update = tokio::time::timeout(Duration::from_secs(5), updates.recv())
+            .await
+            .expect("status update sent")
+            .unwrap();
+        assert_eq!(update["status"], "Succeeded");
+        assert_eq!(
+            update["status_message"],
+            "Container completed (exit code 0)"
+        );
+        assert_eq!(runtime.container_exit_code(&id).await, Some(0));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Per-container statuses
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod container_statuses_tests {
+    use crate::container_statuses::{Observed, observed, waiting};
+    use chrono::{Duration, TimeZone, Utc};
+    use pkg_types::pod::{ContainerState, Pod};
+
+    fn pod() -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": "p1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": "Running",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "containers": [
+                    {
+                        "name": "web",
+                        "image": "nginx",
+                        "readiness_probe": { "exec": { "command": ["true"] } }
+                    },
+                    { "name": "sidecar", "image": "busybox" }
+                ]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn unchanged_containers_report_unchanged_statuses() {
+        let mut pod = pod();
+        let t0 = Utc.with_ymd_and_hms(2024, 2, 25, 0, 0, 0).unwrap();
+        let running = [
+            Observed::Running { started_at: None },
+            Observed::Running { started_at: None },
+        ];
+
+        let first = observed(&pod, &running, t0);
+        assert_eq!(first[0].state, ContainerState::Running { started_at: t0 });
+        assert!(first[0].container_id.is_some());
+        // The probe has not passed yet; the sidecar has none.
+        assert!(!first[0].ready);
+        assert!(first[1].ready);
+
+        pod.container_statuses = first.clone();
+        assert_eq!(observed(&pod, &running, t0 + Duration::seconds(30)), first);
+
+        pod.ready = true;
+        assert!(observed(&pod, &running, t0)[0].ready);
+    }
+
+    #[test]
+    fn exited_containers_carry_a_reason() {
+        let pod = pod();
+        let now = Utc::now();
+        let statuses = observed(
+            &pod,
+            &[
+                Observed::Exited {
+                    exit_code: Some(0),
+                    oom_killed: false,
+                    finished_at: None,
+                },
+                Observed::Exited {
+                    exit_code: Some(137),
+                    oom_killed: true,
+                    finished_at: None,
+                },
+            ],
+            now,
+        );
+        let reasons: Vec<String> = statuses
+            .iter()
+            .map(|s| match &s.state {
+                ContainerState::Terminated { reason, .. } => reason.clone(),
+                other => panic!("expected terminated, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(reasons, ["Completed", "OOMKilled"]);
+        assert!(statuses.iter().all(|s| !s.ready));
+
+        let statuses = observed(
+            &pod,
+            &[
+                Observed::Exited {
+                    exit_code: None,
+                    oom_killed: false,
+                    finished_at: None,
+                },
+                Observed::Running { started_at: None },
+            ],
+            now,
+        );
+        assert_eq!(
+            statuses[0].state,
+            ContainerState::Terminated {
+                exit_code: None,
+                reason: "Error".into(),
+                finished_at: now,
+            }
+        );
+    }
+
+    #[test]
+    fn waiting_keeps_restart_counts() {
+        let mut pod = pod();
+        pod.container_statuses = observed(
+            &pod,
+            &[
+                Observed::Running { started_at: None },
+                Observed::Running { started_at: None },
+            ],
+            Utc::now(),
+        );
+        pod.container_statuses[1].restart_count = 3;
+
+        let statuses = waiting(&pod, "CrashLoopBackOff", Some("back-off 10s".into()));
+        assert_eq!(statuses[0].restart_count, 0);
+        assert_eq!(statuses[1].restart_count, 3);
+        assert!(
+            statuses
+                .iter()
+                .all(|s| s.container_id.is_none() && !s.ready)
+        );
+        assert_eq!(
+            statuses[1].state,
+            ContainerState::Waiting {
+                reason: "CrashLoopBackOff".into(),
+                message: Some("back-off 10s".into()),
+            }
+        );
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Init containers
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod init_container_tests {
+    use crate::init_containers::{InitFailure, failure_update, init_progress, run_all};
+    use crate::restart::CrashLoopTracker;
+    use pkg_types::pod::{Pod, PodStatus, PodStatusUpdate, RestartPolicy};
+    use std::time::Instant;
+
+    fn pod_with_init(names: &[&str]) -> Pod {
+        let init: Vec<serde_json::Value> = names
+            .iter()
+            .map(|n| serde_json::json!({ "name": n, "image": "busybox" }))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "id": "p1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": "Scheduled",
+            "restart_count": 1,
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "init_containers": init,
+                "containers": [{ "name": "web", "image": "nginx" }]
+            }
+        }))
+        .unwrap()
+    }
+
+    fn message(update: &PodStatusUpdate) -> (PodStatus, String, Option<u32>) {
+        let PodStatusUpdate::Detailed {
+            status,
+            status_message,
+            restart_count,
+            ..
+        } = update
+        else {
+            panic!("expected a detailed update, got {:?}", update);
+        };
+        (
+            status.clone(),
+            status_message.clone().unwrap_or_default(),
+            *restart_count,
+        )
+    }
+
+    #[tokio::test]
+    async fn two_init_containers_run_in_order() {
+        let pod = pod_with_init(&["migrate", "seed"]);
+        let mut ran = Vec::new();
+        let result = run_all(&pod, |index, spec| {
+            ran.push((index, spec.name));
+            std::future::ready(Ok(0))
+        })
+        .await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(ran, [(0, "migrate".to_string()), (1, "seed".to_string())]);
+        assert_eq!(
+            message(&init_progress(&pod, 0)),
+            (PodStatus::ContainerCreating, "Init:0/2".to_string(), None)
+        );
+        assert_eq!(message(&init_progress(&pod, 2)).1, "Init:2/2");
+    }
+
+    #[tokio::test]
+    async fn failing_init_container_blocks_the_rest() {
+        let mut pod = pod_with_init(&["migrate", "seed", "warm"]);
+        let mut ran = Vec::new();
+        let result = run_all(&pod, |index, spec| {
+            ran.push(spec.name);
+            std::future::ready(Ok(if index == 1 { 3 } else { 0 }))
+        })
+        .await;
+
+        let failure = InitFailure::Exited {
+            container: "seed".to_string(),
+            exit_code: 3,
+        };
+        assert_eq!(result, Err(failure.clone()));
+        assert_eq!(ran, ["migrate", "seed"]);
+
+        // Never: the pod fails and the app container never starts.
+        pod.spec.restart_policy = RestartPolicy::Never;
+        let mut tracker = CrashLoopTracker::default();
+        let now = Instant::now();
+        let (status, msg, restarts) = message(&failure_update(&pod, &failure, &mut tracker, now));
+        assert_eq!(status, PodStatus::Failed);
+        assert_eq!(msg, "Init container seed failed (exit code 3)");
+        assert_eq!(restarts, None);
+        assert!(!tracker.in_backoff("p1", now));
+
+        // Restarting policies retry the whole pod behind the crash-loop delay.
+        pod.spec.restart_policy = RestartPolicy::OnFailure;
+        let (status, msg, restarts) = message(&failure_update(&pod, &failure, &mut tracker, now));
+        assert_eq!(status, PodStatus::Scheduled);
+        assert!(msg.starts_with("CrashLoopBackOff: back-off 10s"), "{}", msg);
+        assert_eq!(restarts, Some(2));
+        assert!(tracker.in_backoff("p1", now));
+
+        // A container that could not even run counts as a failure too.
+        let result = run_all(&pod, |_, _| {
+            std::future::ready(Err(anyhow::anyhow!("image pull failed")))
+        })
+        .await;
+        assert_eq!(
+            result,
+            Err(InitFailure::Error {
+                container: "migrate".to_string(),
+                message: "image pull failed".to_string(),
+            })
+        );
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Liveness / readiness probes
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod probe_tests {
+    use crate::probe::{ExecFn, ProbeEvent, ProbeManager, run_probe};
+    use crate::restart::{ExitAction, exit_action};
+    use pkg_types::pod::{Pod, Probe, ProbeHandler};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn probe(handler: ProbeHandler) -> Probe {
+        Probe {
+            handler,
+            initial_delay_seconds: 0,
+            period_seconds: 1,
+            timeout_seconds: 1,
+            failure_threshold: 2,
+        }
+    }
+
+    fn exec_result(ok: bool) -> ExecFn {
+        Arc::new(move |_pod_id: String, _command: Vec<String>| {
+            Box::pin(async move {
+                if ok {
+                    Ok(())
+                } else {
+                    Err(anyhow::anyhow!("exit status 1"))
+                }
+            })
+        })
+    }
+
+    /// Answers each connection with a fixed HTTP status line.
+    async fn http_listener(status: &'static str) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = conn.read(&mut buf).await;
+                let resp = format!(
+                    "HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    status
+                );
+                let _ = conn.write_all(resp.as_bytes()).await;
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn http_probe_checks_status_code() {
+        let http = reqwest::Client::new();
+        let exec = exec_result(true);
+        let healthy = http_listener("200 OK").await;
+        let broken = http_listener("500 Internal Server Error").await;
+
+        let get = |port| {
+            probe(ProbeHandler::HttpGet {
+                path: "/healthz".to_string(),
+                port,
+            })
+        };
+        assert_eq!(
+            run_probe(&get(healthy), "127.0.0.1", "p1", &http, &exec).await,
+            Ok(())
+        );
+        let err = run_probe(&get(broken), "127.0.0.1", "p1", &http, &exec)
+            .await
+            .unwrap_err();
+        assert!(err.contains("500"), "unexpected error: {}", err);
+
+        // TCP probes only need the port to accept connections.
+        let tcp = probe(ProbeHandler::TcpSocket { port: healthy });
+        assert_eq!(
+            run_probe(&tcp, "127.0.0.1", "p1", &http, &exec).await,
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_exec_liveness_probe_restarts_container() {
+        let mut pod: Pod = serde_json::from_value(serde_json::json!({
+            "id": "p1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": "Running",
+            "ghost_ipv6": "::1",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": { "containers": [{ "name": "web", "image": "nginx" }] }
+        }))
+        .unwrap();
+        pod.spec.containers[0].liveness_probe = Some(probe(ProbeHandler::Exec {
+            command: vec!["cat".to_string(), "/tmp/healthy".to_string()],
+        }));
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut probes = ProbeManager::new(exec_result(false), tx);
+        probes.sync(std::slice::from_ref(&pod));
+        // Later syncs keep the existing task instead of starting another.
+        probes.sync(std::slice::from_ref(&pod));
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("liveness failure reported")
+            .unwrap();
+        let ProbeEvent::LivenessFailed {
+            pod: failed,
+            container,
+            reason,
+        } = event
+        else {
+            panic!("expected a liveness failure, got {:?}", event);
+        };
+        assert_eq!(failed.id, "p1");
+        assert_eq!(container, "web");
+        assert!(reason.contains("exit status 1"));
+
+        // Exactly one report per failure streak.
+        assert!(
+            tokio::time::timeout(Duration::from_millis(1500), rx.recv())
+                .await
+                .is_err()
+        );
+
+        // The stopped container has no exit code, so the default policy restarts it.
+        assert_eq!(
+            exit_action(pod.spec.restart_policy, None),
+            ExitAction::Restart
+        );
+    }
+
+    #[tokio::test]
+    async fn readiness_probe_reports_changes_only() {
+        let mut pod: Pod = serde_json::from_value(serde_json::json!({
+            "id": "p2",
+            "name": "api-0",
+            "namespace": "default",
+            "status": "Running",
+            "ghost_ipv6": "::1",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": { "containers": [{ "name": "api", "image": "api" }] }
+        }))
+        .unwrap();
+        pod.spec.containers[0].readiness_probe = Some(probe(ProbeHandler::Exec {
+            command: vec!["true".to_string()],
+        }));
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut probes = ProbeManager::new(exec_result(true), tx);
+        probes.sync(std::slice::from_ref(&pod));
+
+        let event = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("readiness reported")
+            .unwrap();
+        assert!(matches!(
+            event,
+            ProbeEvent::ReadinessChanged { ready: true, .. }
+        ));
+        // Still passing: nothing more to report.
+        assert!(
+            tokio::time::timeout(Duration::from_millis(1500), rx.recv())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn pod_is_ready_only_when_every_container_is() {
+        let mut pod: Pod = serde_json::from_value(serde_json::json!({
+            "id": "p3",
+            "name": "api-0",
+            "namespace": "default",
+            "status": "Running",
+            "ghost_ipv6": "::1",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": { "containers": [
+                { "name": "api", "image": "api" },
+                { "name": "proxy", "image": "envoy" }
+            ] }
+        }))
+        .unwrap();
+        for c in &mut pod.spec.containers {
+            c.readiness_probe = Some(probe(ProbeHandler::Exec {
+                command: vec!["true".to_string()],
+            }));
+        }
+
+        // Exec probes run in each container; the proxy never passes.
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let exec: ExecFn = {
+            let seen = seen.clone();
+            Arc::new(move |id: String, _command: Vec<String>| {
+                seen.lock().unwrap().push(id.clone());
+                Box::pin(async move {
+                    if id == "p3-api" {
+                        Ok(())
+                    } else {
+                        Err(anyhow::anyhow!("not ready"))
+                    }
+                })
+            })
+        };
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut probes = ProbeManager::new(exec, tx);
+        probes.sync(std::slice::from_ref(&pod));
+
+        assert!(
+            tokio::time::timeout(Duration::from_millis(1500), rx.recv())
+                .await
+                .is_err()
+        );
+        let seen = seen.lock().unwrap();
+        assert!(seen.contains(&"p3-api".to_string()));
+        assert!(seen.contains(&"p3-proxy".to_string()));
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Volume mounts
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod volume_tests {
+    use crate::volumes::resolve_mounts;
+    use pkg_types::pod::Pod;
+    use std::path::PathBuf;
+
+    fn pod(volumes: serde_json::Value, mounts: serde_json::Value) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": "p1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": "Scheduled",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "volumes": volumes,
+                "containers": [{ "name": "web", "image": "nginx", "volume_mounts": mounts }]
+            }
+        }))
+        .unwrap()
+    }
+
+    fn empty_dir(volume: &str) -> PathBuf {
+        PathBuf::from("/data/volumes/p1").join(volume)
+    }
+
+    fn no_claims(_: &str) -> Option<PathBuf> {
+        None
+    }
+
+    #[test]
+    fn mounts_resolve_against_pod_volumes() {
+        let pod = pod(
+            serde_json::json!([
+                { "name": "cache", "source": { "type": "emptyDir" } },
+                { "name": "certs", "source": { "type": "hostPath", "path": "/etc/ssl/certs" } }
+            ]),
+            serde_json::json!([
+                { "name": "cache", "mount_path": "/var/cache/nginx" },
+                { "name": "certs", "mount_path": "/etc/ssl/certs", "read_only": true }
+            ]),
+        );
+        let mounts = resolve_mounts(&pod, &pod.spec.containers[0], empty_dir, no_claims).unwrap();
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[0].source, PathBuf::from("/data/volumes/p1/cache"));
+        assert_eq!(mounts[0].destination, "/var/cache/nginx");
+        assert!(!mounts[0].read_only);
+        assert_eq!(mounts[1].source, PathBuf::from("/etc/ssl/certs"));
+        assert!(mounts[1].read_only);
+    }
+
+    #[test]
+    fn undeclared_or_unsupported_volume_is_rejected() {
+        let pod = pod(
+            serde_json::json!([{ "name": "cfg", "source": { "type": "configMap", "name": "app" } }]),
+            serde_json::json!([{ "name": "data", "mount_path": "/data" }]),
+        );
+        assert_eq!(
+            resolve_mounts(&pod, &pod.spec.containers[0], empty_dir, no_claims).unwrap_err(),
+            "Container web mounts undeclared volume data"
+        );
+
+        // Declared, but not a type the agent can mount yet.
+        let mut pod = pod;
+        pod.spec.containers[0].volume_mounts[0].name = "cfg".to_string();
+        let err = resolve_mounts(&pod, &pod.spec.containers[0], empty_dir, no_claims).unwrap_err();
+        assert!(err.contains("unsupported type"), "{}", err);
+    }
+
+    #[test]
+    fn claim_mounts_use_the_bound_volume_directory() {
+        let pod = pod(
+            serde_json::json!([
+                { "name": "data", "source": { "type": "persistentVolumeClaim", "claim_name": "pg-data" } }
+            ]),
+            serde_json::json!([{ "name": "data", "mount_path": "/var/lib/postgresql" }]),
+        );
+        let bound = |claim: &str| {
+            (claim == "pg-data").then(|| PathBuf::from("/var/lib/k3rs/local-path/pvc-c1"))
+        };
+        let mounts = resolve_mounts(&pod, &pod.spec.containers[0], empty_dir, bound).unwrap();
+        assert_eq!(
+            mounts[0].source,
+            PathBuf::from("/var/lib/k3rs/local-path/pvc-c1")
+        );
+        assert_eq!(mounts[0].destination, "/var/lib/postgresql");
+
+        assert_eq!(
+            resolve_mounts(&pod, &pod.spec.containers[0], empty_dir, no_claims).unwrap_err(),
+            "Volume data: claim pg-data is not bound"
+        );
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Local-path volumes
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod local_path_tests {
+    use crate::local_path::{CLAIM_FILE, create_volume, remove_volume, volume_dir};
+    use pkg_container::test_util::temp_dir;
+    use pkg_types::volume::LocalVolumeRequest;
+    use std::path::Path;
+
+    #[test]
+    fn volumes_are_created_and_removed_under_the_base_dir() {
+        let base = temp_dir("local-path");
+        let base = Path::new(&base);
+        let request = LocalVolumeRequest {
+            claim: "default/pg-data".to_string(),
+            capacity_bytes: 1 << 30,
+        };
+
+        let dir = create_volume(base, "pvc-c1", &request).unwrap();
+        assert_eq!(dir, base.join("pvc-c1"));
+        assert!(dir.join(CLAIM_FILE).exists());
+        std::fs::write(dir.join("data"), b"rows").unwrap();
+        // A retried create keeps what is there.
+        create_volume(base, "pvc-c1", &request).unwrap();
+        assert!(dir.join("data").exists());
+
+        remove_volume(base, "pvc-c1").unwrap();
+        assert!(!dir.exists());
+        remove_volume(base, "pvc-c1").unwrap();
+        std::fs::remove_dir_all(base).ok();
+    }
+
+    #[test]
+    fn volume_names_cannot_leave_the_base_dir() {
+        let base = Path::new("/var/lib/k3rs/local-path");
+        for name in ["", "..", ".hidden", "a/b", "../etc"] {
+            assert!(volume_dir(base, name).is_err(), "{:?}", name);
+        }
+        assert_eq!(volume_dir(base, "pvc-c1").unwrap(), base.join("pvc-c1"));
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Image pull secrets
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod pull_secrets_tests {
+    use super::helpers::server_token;
+    use crate::pull_secrets::{ERR_IMAGE_PULL_AUTH, fetch_keychain, pull_failure_message};
+    use pkg_container::registry_auth::AuthenticationRequired;
+    use pkg_types::pod::Pod;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serves the `regcred` secret to requests bearing node `worker-1`'s
+    /// agent token `node-token`.
+    async fn stub_server() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // base64 of {"auths":{"ghcr.io":{"auth":"cm9ib3Q6czNjcmV0"}}}
+        let secret = serde_json::json!({
+            "id": "s1",
+            "name": "regcred",
+            "namespace": "default",
+            "data": {
+                ".dockerconfigjson": "eyJhdXRocyI6eyJnaGNyLmlvIjp7ImF1dGgiOiJjbTlpYjNRNmN6TmpjbVYwIn19fQ=="
+            },
+            "created_at": "2024-02-25T00:00:00Z"
+        })
+        .to_string();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = conn.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let authorized = request.contains("Bearer node:worker-1:node-token");
+                let (status, body) = if authorized
+                    && request.starts_with("GET /api/v1/namespaces/default/secrets/regcred ")
+                {
+                    ("200 OK", secret.clone())
+                } else {
+                    ("404 Not Found", String::new())
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = conn.write_all(response.as_bytes()).await;
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    fn pod(secrets: &[&str]) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": "p1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": "Scheduled",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "containers": [{ "name": "web", "image": "ghcr.io/corp/web" }],
+                "image_pull_secrets": secrets
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn keychain_comes_from_the_pods_secrets() {
+        let server = stub_server().await;
+        let client = reqwest::Client::new();
+
+        // A missing secret is skipped; the others still apply.
+        let keychain = fetch_keychain(
+            &client,
+            &server,
+            &server_token("node-token"),
+            &pod(&["missing", "regcred"]),
+        )
+        .await;
+        let creds = keychain.get("ghcr.io").unwrap();
+        assert_eq!(creds.username, "robot");
+        assert_eq!(creds.password(), "s3cret");
+
+        let keychain =
+            fetch_keychain(&client, &server, &server_token("node-token"), &pod(&[])).await;
+        assert!(keychain.is_empty());
+    }
+
+    #[test]
+    fn unauthenticated_pull_reports_err_image_pull() {
+        let err = anyhow::Error::new(AuthenticationRequired {
+            registry: "ghcr.io".to_string(),
+        })
+        .context("creating container p1-web");
+        assert_eq!(
+            pull_failure_message(&err).as_deref(),
+            Some(ERR_IMAGE_PULL_AUTH)
+        );
+        assert_eq!(ERR_IMAGE_PULL_AUTH, "ErrImagePull: authentication required");
+
+        let other = anyhow::anyhow!("Failed to pull layer sha256:abc: connection reset");
+        assert_eq!(pull_failure_message(&other), None);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Image GC
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod image_gc_tests {
+    use crate::image_gc::{DiskUsage, GcPolicy, bytes_to_free, images_in_use, select_victims};
+    use chrono::{TimeZone, Utc};
+    use pkg_container::image::ImageInfo;
+    use pkg_types::config::AgentConfigFile;
+    use pkg_types::pod::Pod;
+    use std::collections::HashSet;
+
+    const GB: u64 = 1 << 30;
+
+    fn image(id: &str, size: u64, last_used_hour: Option<u32>) -> ImageInfo {
+        ImageInfo {
+            id: id.to_string(),
+            node_name: String::new(),
+            size,
+            size_human: String::new(),
+            layers: 1,
+            architecture: "amd64".to_string(),
+            os: "linux".to_string(),
+            created: String::new(),
+            last_used: last_used_hour.map(|h| Utc.with_ymd_and_hms(2024, 3, 1, h, 0, 0).unwrap()),
+        }
+    }
+
+    fn pod(status: &str, images: &[&str]) -> Pod {
+        let containers: Vec<_> = images
+            .iter()
+            .enumerate()
+            .map(|(i, image)| serde_json::json!({ "name": format!("c{}", i), "image": image }))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "id": "p1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": status,
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "init_containers": [{ "name": "migrate", "image": "busybox" }],
+                "containers": containers
+            }
+        }))
+        .unwrap()
+    }
+
+    fn policy(max_image_bytes: Option<u64>) -> GcPolicy {
+        GcPolicy {
+            high_threshold: 85,
+            low_threshold: 80,
+            max_image_bytes,
+        }
+    }
+
+    #[test]
+    fn watermarks_decide_how_much_to_free() {
+        let disk = |used: u64| {
+            Some(DiskUsage {
+                capacity: 100 * GB,
+                available: (100 - used) * GB,
+            })
+        };
+        // Below the high watermark: nothing to do.
+        assert_eq!(bytes_to_free(&policy(None), disk(84), 50 * GB), 0);
+        // At or past it: free down to the low watermark.
+        assert_eq!(bytes_to_free(&policy(None), disk(85), 50 * GB), 5 * GB);
+        assert_eq!(bytes_to_free(&policy(None), disk(92), 50 * GB), 12 * GB);
+        // The image size cap applies on its own, and the larger need wins.
+        assert_eq!(
+            bytes_to_free(&policy(Some(40 * GB)), disk(50), 50 * GB),
+            10 * GB
+        );
+        assert_eq!(
+            bytes_to_free(&policy(Some(45 * GB)), disk(90), 50 * GB),
+            10 * GB
+        );
+        // Unknown disk: only the cap counts.
+        assert_eq!(bytes_to_free(&policy(None), None, 50 * GB), 0);
+    }
+
+    #[test]
+    fn policy_defaults_and_clamps_thresholds() {
+        assert_eq!(
+            GcPolicy::from_config(&AgentConfigFile::default()),
+            policy(None)
+        );
+        let cfg: AgentConfigFile = serde_yaml::from_str(
+            "image-gc-high-threshold: 70\nimage-gc-low-threshold: 90\nimage-gc-max-bytes: 1024\n",
+        )
+        .unwrap();
+        let p = GcPolicy::from_config(&cfg);
+        assert_eq!((p.high_threshold, p.low_threshold), (70, 70));
+        assert_eq!(p.max_image_bytes, Some(1024));
+    }
+
+    #[test]
+    fn least_recently_used_images_go_first() {
+        let images = [
+            image("recent", 4 * GB, Some(12)),
+            image("old", 2 * GB, Some(1)),
+            image("never", GB, None),
+            image("middle", 3 * GB, Some(6)),
+        ];
+        let ids = |victims: Vec<&ImageInfo>| -> Vec<String> {
+            victims.iter().map(|i| i.id.clone()).collect()
+        };
+        let none = HashSet::new();
+
+        assert_eq!(ids(select_victims(&images, &none, 0)), Vec::<String>::new());
+        assert_eq!(ids(select_victims(&images, &none, GB)), vec!["never"]);
+        // Stops as soon as enough is reclaimed.
+        assert_eq!(
+            ids(select_victims(&images, &none, 3 * GB + 1)),
+            vec!["never", "old", "middle"]
+        );
+        assert_eq!(ids(select_victims(&images, &none, 100 * GB)).len(), 4);
+    }
+
+    #[test]
+    fn images_of_assigned_pods_are_never_collected() {
+        let id = |image: &str| format!("id-{}", image);
+        // A pod waiting out a crash-loop backoff has no containers, but its
+        // images stay in use; finished pods release theirs.
+        let pods = [
+            pod("Scheduled", &["nginx"]),
+            pod("Running", &["redis"]),
+            pod("Failed", &["postgres"]),
+        ];
+        let in_use = images_in_use(&pods, ["sidecar"], id);
+        let expected: HashSet<String> = ["busybox", "nginx", "redis", "sidecar"]
+            .into_iter()
+            .map(id)
+            .collect();
+        assert_eq!(in_use, expected);
+
+        let images = [
+            image("id-nginx", GB, Some(1)),
+            image("id-postgres", GB, Some(2)),
+            image("id-sidecar", GB, Some(3)),
+        ];
+        let victims = select_victims(&images, &in_use, 100 * GB);
+        assert_eq!(victims.len(), 1);
+        assert_eq!(victims[0].id, "id-postgres");
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Orphan GC
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod orphan_gc_tests {
+    use crate::orphan_gc::{is_owned, orphans, owners, remove, scan, set_aside};
+    use pkg_container::test_util::temp_dir;
+    use pkg_types::pod::Pod;
+    use std::collections::HashSet;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime};
+
+    const GRACE: Duration = Duration::from_secs(600);
+
+    fn pod(id: &str) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "namespace": "default",
+            "status": "Running",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": { "containers": [{ "name": "web", "image": "nginx" }] }
+        }))
+        .unwrap()
+    }
+
+    /// A runtime data dir holding the files of the given containers, the
+    /// way the runtime and VM backends lay them out.
+    fn data_dir(label: &str, containers: &[&str], vms: &[&str]) -> String {
+        let dir = temp_dir(label).to_string_lossy().into_owned();
+        let root = Path::new(&dir);
+        for id in containers {
+            let rootfs = root.join("containers").join(id).join("rootfs");
+            std::fs::create_dir_all(&rootfs).unwrap();
+            std::fs::write(rootfs.join("app"), vec![0u8; 1000]).unwrap();
+            let logs = root.join("logs").join(id);
+            std::fs::create_dir_all(&logs).unwrap();
+            std::fs::write(logs.join("stdout.log"), b"hello\n").unwrap();
+        }
+        std::fs::create_dir_all(root.join("vms")).unwrap();
+        for id in vms {
+            for suffix in [".sock", "-vsock.sock", ".log", ".pid", "-rootfs.ext4"] {
+                std::fs::write(root.join("vms").join(format!("{}{}", id, suffix)), b"x").unwrap();
+            }
+            std::fs::create_dir_all(root.join("vms").join(format!("{}-rootfs", id))).unwrap();
+        }
+        // Shared by every VM; not a container's.
+        std::fs::write(root.join("vms").join("README"), b"x").unwrap();
+        dir
+    }
+
+    fn orphan_ids(dir: &str, owners: &HashSet<String>, now: SystemTime) -> Vec<(String, String)> {
+        let leftovers = scan(Path::new(dir));
+        let mut found: Vec<(String, String)> = orphans(&leftovers, owners, GRACE, now)
+            .into_iter()
+            .map(|l| {
+                let name = l.path.file_name().unwrap().to_string_lossy().into_owned();
+                (l.container_id.clone(), name)
+            })
+            .collect();
+        found.sort();
+        found
+    }
+
+    fn later() -> SystemTime {
+        SystemTime::now() + GRACE + Duration::from_secs(1)
+    }
+
+    #[test]
+    fn containers_of_known_pods_are_owned() {
+        let owners: HashSet<String> = ["pod-1".to_string()].into();
+        assert!(is_owned("pod-1", &owners));
+        assert!(is_owned("pod-1-web", &owners));
+        assert!(is_owned("pod-1-init-0", &owners));
+        assert!(!is_owned("pod-10-web", &owners));
+        assert!(!is_owned("pod-2-web", &owners));
+        assert!(!is_owned("pod", &owners));
+    }
+
+    #[test]
+    fn finds_what_no_pod_owns() {
+        let dir = data_dir(
+            "orphan-gc-detect",
+            &["pod-1-web", "pod-2-web"],
+            &["pod-1-web", "pod-3-web"],
+        );
+        let owners = owners(&[pod("pod-1")], [], []);
+
+        let found = orphan_ids(&dir, &owners, later());
+        // containers/pod-2-web and logs/pod-2-web, plus every file of VM pod-3-web.
+        let mut expected = vec![("pod-2-web".to_string(), "pod-2-web".to_string()); 2];
+        for suffix in [
+            ".sock",
+            "-vsock.sock",
+            ".log",
+            ".pid",
+            "-rootfs.ext4",
+            "-rootfs",
+        ] {
+            expected.push(("pod-3-web".to_string(), format!("pod-3-web{}", suffix)));
+        }
+        expected.sort();
+        assert_eq!(found, expected);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn keeps_leftovers_until_the_grace_period_passes() {
+        let dir = data_dir("orphan-gc-grace", &["pod-2-web"], &[]);
+        let owners = owners(&[], [], []);
+
+        assert!(orphan_ids(&dir, &owners, SystemTime::now()).is_empty());
+        assert!(orphan_ids(&dir, &owners, SystemTime::now() + GRACE / 2).is_empty());
+        assert_eq!(orphan_ids(&dir, &owners, later()).len(), 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn never_collects_pods_in_flight_or_tracked_containers() {
+        let dir = data_dir(
+            "orphan-gc-in-flight",
+            &["pod-4-web", "pod-5-init-0", "pod-6-web"],
+            &["pod-4-web"],
+        );
+        // pod-4 is being created and not listed by the server yet; pod-5's
+        // init container is still tracked by the runtime.
+        let owners = owners(&[], ["pod-4"], ["pod-5-init-0"]);
+
+        let found = orphan_ids(&dir, &owners, later());
+        assert!(found.iter().all(|(id, _)| id == "pod-6-web"), "{:?}", found);
+        assert_eq!(found.len(), 2);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn removes_set_aside_entries_and_counts_their_bytes() {
+        let dir = data_dir("orphan-gc-remove", &["pod-2-web"], &[]);
+        let bundle = Path::new(&dir).join("containers").join("pod-2-web");
+
+        let aside = set_aside(&bundle).unwrap();
+        assert!(!bundle.exists());
+        // An entry left set aside is still found, as owned by nobody.
+        let leftovers = scan(Path::new(&dir));
+        assert!(leftovers.iter().any(|l| l.path == aside));
+        assert_eq!(orphan_ids(&dir, &owners(&[], [], []), later()).len(), 2);
+
+        assert_eq!(remove(&aside).unwrap(), 1000);
+        assert!(!aside.exists());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Node pressure
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod pressure_tests {
+    use crate::pressure::{PressureMonitor, PressureThresholds, Usage};
+    use chrono::Utc;
+    use pkg_types::config::AgentConfigFile;
+    use pkg_types::node::NodeConditionType::{DiskPressure, MemoryPressure};
+
+    fn percent(used: u64) -> Option<Usage> {
+        Some(Usage { used, total: 100 })
+    }
+
+    fn monitor() -> PressureMonitor {
+        PressureMonitor::new(PressureThresholds {
+            disk: 90,
+            memory: 95,
+        })
+    }
+
+    #[test]
+    fn thresholds_default_and_cap() {
+        let defaults = PressureThresholds::from_config(&AgentConfigFile::default());
+        assert_eq!(
+            defaults,
+            PressureThresholds {
+                disk: 90,
+                memory: 95
+            }
+        );
+        let cfg = AgentConfigFile {
+            disk_pressure_threshold: Some(80),
+            memory_pressure_threshold: Some(150),
+            ..Default::default()
+        };
+        assert_eq!(
+            PressureThresholds::from_config(&cfg),
+            PressureThresholds {
+                disk: 80,
+                memory: 100
+            }
+        );
+    }
+
+    #[test]
+    fn condition_starts_at_the_threshold() {
+        let mut pressure = monitor();
+        pressure.observe(DiskPressure, percent(89), Utc::now());
+        assert!(pressure.conditions().is_empty());
+
+        let since = Utc::now();
+        pressure.observe(DiskPressure, percent(90), since);
+        let conditions = pressure.conditions();
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].condition_type, DiskPressure);
+        assert_eq!(conditions[0].since, since);
+        assert_eq!(conditions[0].message, "disk 90% used (threshold 90%)");
+    }
+
+    #[test]
+    fn usage_at_the_boundary_does_not_flap() {
+        let mut pressure = monitor();
+        let since = Utc::now();
+        pressure.observe(DiskPressure, percent(91), since);
+        // Hovering around the threshold keeps the condition, and its start.
+        for used in [89, 90, 88, 91, 86, 85] {
+            pressure.observe(DiskPressure, percent(used), Utc::now());
+            let conditions = pressure.conditions();
+            assert_eq!(conditions.len(), 1, "cleared at {}%", used);
+            assert_eq!(conditions[0].since, since);
+        }
+        assert_eq!(
+            pressure.conditions()[0].message,
+            "disk 85% used (threshold 90%)"
+        );
+
+        pressure.observe(DiskPressure, percent(84), Utc::now());
+        assert!(pressure.conditions().is_empty());
+        // Back under the threshold after clearing: stays clear.
+        pressure.observe(DiskPressure, percent(89), Utc::now());
+        assert!(pressure.conditions().is_empty());
+    }
+
+    #[test]
+    fn conditions_are_tracked_separately() {
+        let mut pressure = monitor();
+        pressure.observe(DiskPressure, percent(95), Utc::now());
+        pressure.observe(MemoryPressure, percent(94), Utc::now());
+        let kinds: Vec<_> = pressure
+            .conditions()
+            .iter()
+            .map(|c| c.condition_type)
+            .collect();
+        assert_eq!(kinds, vec![DiskPressure]);
+
+        pressure.observe(MemoryPressure, percent(97), Utc::now());
+        // A failed sample leaves the condition as it was.
+        pressure.observe(DiskPressure, None, Utc::now());
+        pressure.observe(DiskPressure, Some(Usage { used: 0, total: 0 }), Utc::now());
+        let kinds: Vec<_> = pressure
+            .conditions()
+            .iter()
+            .map(|c| c.condition_type)
+            .collect();
+        assert_eq!(kinds, vec![DiskPressure, MemoryPressure]);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Runtime config reload
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod runtime_config_tests {
+    use crate::runtime_config::{self, LIVE_CONFIG_KEYS, RuntimeConfig};
+    use pkg_container::test_util::temp_dir;
+    use pkg_types::config::{AgentConfigFile, ConfigFileWatcher};
+    use std::sync::{Arc, RwLock};
+    use std::time::Duration;
+
+    #[test]
+    fn test_defaults_and_minimum_interval() {
+        let defaults = RuntimeConfig::from_config(&AgentConfigFile::default()).unwrap();
+        assert_eq!(defaults.log_level, None);
+        assert_eq!(defaults.heartbeat_interval, Duration::from_secs(10));
+        assert_eq!(defaults.pod_sync_interval, Duration::from_secs(5));
+        assert_eq!(defaults.route_sync_interval, Duration::from_secs(10));
+        assert_eq!(defaults.termination_grace_period, Duration::from_secs(30));
+
+        let cfg = AgentConfigFile {
+            pod_sync_interval: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            RuntimeConfig::from_config(&cfg).unwrap().pod_sync_interval,
+            Duration::from_secs(1)
+        );
+        // A zero grace period is kept: it means kill straight away.
+        let cfg = AgentConfigFile {
+            termination_grace_period: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            RuntimeConfig::from_config(&cfg)
+                .unwrap()
+                .termination_grace_period,
+            Duration::ZERO
+        );
+    }
+
+    #[tokio::test]
+    async fn test_loops_observe_reloaded_intervals() {
+        let path = temp_dir("agent-config")
+            .join("agent.yaml")
+            .to_string_lossy()
+            .into_owned();
+        std::fs::write(&path, "node-name: worker-1\npod-sync-interval: 5\n").unwrap();
+        let mut watcher =
+            ConfigFileWatcher::<AgentConfigFile>::open(&path, LIVE_CONFIG_KEYS).unwrap();
+        let shared = Arc::new(RwLock::new(
+            RuntimeConfig::from_config(watcher.current()).unwrap(),
+        ));
+        let mut interval = tokio::time::interval(shared.read().unwrap().pod_sync_interval);
+        assert!(
+            runtime_config::reload(&mut watcher, &shared)
+                .unwrap()
+                .is_none()
+        );
+
+        std::fs::write(
+            &path,
+            "node-name: worker-2\npod-sync-interval: 2\nroute-sync-interval: 30\n\
+             heartbeat-interval: 15\nimage-gc-high-threshold: 70\nlog-level: debug\n",
+        )
+        .unwrap();
+        let reloaded = runtime_config::reload(&mut watcher, &shared)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            reloaded.applied,
+            vec![
+                "heartbeat-interval",
+                "image-gc-high-threshold",
+                "log-level",
+                "pod-sync-interval",
+                "route-sync-interval",
+            ]
+        );
+        assert_eq!(reloaded.restart_required, vec!["node-name"]);
+
+        let current = shared.read().unwrap().clone();
+        assert_eq!(current.heartbeat_interval, Duration::from_secs(15));
+        assert_eq!(current.route_sync_interval, Duration::from_secs(30));
+        assert_eq!(current.image_gc.high_threshold, 70);
+        assert_eq!(current.log_level.as_deref(), Some("debug"));
+
+        // What the pod sync loop does after each tick.
+        runtime_config::retune(&mut interval, current.pod_sync_interval);
+        assert_eq!(interval.period(), Duration::from_secs(2));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_config_keeps_previous_settings() {
+        let path = temp_dir("agent-config-bad")
+            .join("agent.yaml")
+            .to_string_lossy()
+            .into_owned();
+        std::fs::write(&path, "pod-sync-interval: 3\n").unwrap();
+        let mut watcher =
+            ConfigFileWatcher::<AgentConfigFile>::open(&path, LIVE_CONFIG_KEYS).unwrap();
+        let shared = Arc::new(RwLock::new(
+            RuntimeConfig::from_config(watcher.current()).unwrap(),
+        ));
+        let before = shared.read().unwrap().clone();
+
+        std::fs::write(&path, "pod-sync-interval: [3\n").unwrap();
+        assert!(runtime_config::reload(&mut watcher, &shared).is_err());
+        assert_eq!(*shared.read().unwrap(), before);
+
+        std::fs::write(
+            &path,
+            "pod-sync-interval: 1\nlog-level: \"pkg_container=loud\"\n",
+        )
+        .unwrap();
+        assert!(runtime_config::reload(&mut watcher, &shared).is_err());
+        assert_eq!(*shared.read().unwrap(), before);
+        assert_eq!(watcher.current().pod_sync_interval, Some(3));
+
+        // Fixing the file applies it.
+        std::fs::write(&path, "pod-sync-interval: 1\n").unwrap();
+        assert!(
+            runtime_config::reload(&mut watcher, &shared)
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(
+            shared.read().unwrap().pod_sync_interval,
+            Duration::from_secs(1)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Static pods
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod static_pods_tests {
+    use crate::static_pods::{self, PodRuntime, StaticPods};
+    use pkg_container::test_util::temp_dir;
+    use pkg_types::pod::{Pod, PodStatus};
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+
+    /// Records what the loop asks of the runtime; fails to start pods named
+    /// in `failing`.
+    #[derive(Clone, Default)]
+    struct StubRuntime {
+        calls: Arc<Mutex<Vec<String>>>,
+        failing: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl StubRuntime {
+        fn take(&self) -> Vec<String> {
+            std::mem::take(&mut self.calls.lock().unwrap())
+        }
+    }
+
+    impl PodRuntime for StubRuntime {
+        async fn start_pod(&self, pod: &Pod) -> anyhow::Result<()> {
+            let image = &pod.spec.containers[0].image;
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("start {} {}", pod.name, image));
+            if self.failing.lock().unwrap().contains(&pod.name) {
+                anyhow::bail!("image pull failed");
+            }
+            Ok(())
+        }
+
+        async fn stop_pod(&self, pod: &Pod) -> anyhow::Result<()> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("stop {}", pod.name));
+            Ok(())
+        }
+    }
+
+    fn manifest(name: &str, image: &str) -> String {
+        format!(
+            "kind: Pod\nname: {}\nnamespace: kube-system\nspec:\n  containers:\n    - name: app\n      image: {}\n",
+            name, image
+        )
+    }
+
+    fn write(dir: &Path, file: &str, content: &str) -> PathBuf {
+        std::fs::create_dir_all(dir).unwrap();
+        let path = dir.join(file);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn scan_detects_changed_files() {
+        let dir = temp_dir("static-scan");
+        assert!(static_pods::scan(&dir).is_empty());
+        let dns = write(&dir, "dns.yaml", &manifest("dns", "coredns:1"));
+        write(&dir, "ingress.yml", &manifest("ingress", "pingora:1"));
+        write(&dir, "notes.txt", "not a manifest");
+
+        let before = static_pods::scan(&dir);
+        assert_eq!(before.len(), 2);
+        assert_eq!(static_pods::scan(&dir), before);
+
+        write(&dir, "dns.yaml", &manifest("dns", "coredns:2"));
+        let after = static_pods::scan(&dir);
+        assert_ne!(after[&dns].hash, before[&dns].hash);
+        let ingress = dir.join("ingress.yml");
+        assert_eq!(after[&ingress].hash, before[&ingress].hash);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parse_fills_in_defaults() {
+        let pod = static_pods::parse("name: dns\nspec:\n  containers: []\n").unwrap();
+        assert_eq!(pod.namespace, "default");
+        assert_eq!(pod.id, "static-default-dns");
+        assert_eq!(pod.status, PodStatus::Pending);
+        assert!(static_pods::parse("kind: Deployment\nname: dns\n").is_err());
+        assert!(static_pods::parse("- not\n- a pod\n").is_err());
+        // The ID names the pod's containers on disk.
+        assert!(static_pods::parse("name: ../etc\nspec:\n  containers: []\n").is_err());
+    }
+
+    #[test]
+    fn mirror_is_named_after_the_node() {
+        let pod = static_pods::parse(&manifest("dns", "coredns:1")).unwrap();
+        let mirror = static_pods::mirror(&pod, "worker-1");
+        assert_eq!(mirror.name, "dns-worker-1");
+        assert_eq!(mirror.namespace, "kube-system");
+        assert_eq!(mirror.id, pod.id);
+        assert_eq!(mirror.node_name.as_deref(), Some("worker-1"));
+        assert_eq!(mirror.status, PodStatus::Running);
+        assert!(mirror.mirror);
+    }
+
+    #[tokio::test]
+    async fn pods_follow_their_manifests() {
+        let dir = temp_dir("static-lifecycle");
+        let runtime = StubRuntime::default();
+        let mut pods = StaticPods::new(runtime.clone());
+
+        write(&dir, "dns.yaml", &manifest("dns", "coredns:1"));
+        write(&dir, "ingress.yaml", &manifest("ingress", "pingora:1"));
+        pods.sync(&dir).await;
+        assert_eq!(
+            runtime.take(),
+            vec!["start dns coredns:1", "start ingress pingora:1"]
+        );
+        // Nothing changed: nothing restarts.
+        pods.sync(&dir).await;
+        assert!(runtime.take().is_empty());
+
+        write(&dir, "dns.yaml", &manifest("dns", "coredns:2"));
+        pods.sync(&dir).await;
+        assert_eq!(runtime.take(), vec!["stop dns", "start dns coredns:2"]);
+
+        // A broken edit keeps the pod as it is.
+        write(&dir, "dns.yaml", "name: [unclosed");
+        pods.sync(&dir).await;
+        pods.sync(&dir).await;
+        assert!(runtime.take().is_empty());
+        assert_eq!(pods.pods().len(), 2);
+
+        std::fs::remove_file(dir.join("ingress.yaml")).unwrap();
+        pods.sync(&dir).await;
+        assert_eq!(runtime.take(), vec!["stop ingress"]);
+        let names: Vec<_> = pods.pods().iter().map(|p| p.name.clone()).collect();
+        assert_eq!(names, vec!["dns"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_starts_are_retried() {
+        let dir = temp_dir("static-retry");
+        let runtime = StubRuntime::default();
+        runtime.failing.lock().unwrap().push("dns".to_string());
+        let mut pods = StaticPods::new(runtime.clone());
+
+        write(&dir, "dns.yaml", &manifest("dns", "coredns:1"));
+        pods.sync(&dir).await;
+        assert_eq!(runtime.take(), vec!["start dns coredns:1"]);
+        assert!(pods.pods().is_empty());
+
+        runtime.failing.lock().unwrap().clear();
+        pods.sync(&dir).await;
+        assert_eq!(runtime.take(), vec!["start dns coredns:1"]);
+        assert_eq!(pods.pods().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn duplicate_pods_run_once() {
+        let dir = temp_dir("static-duplicate");
+        let runtime = StubRuntime::default();
+        let mut pods = StaticPods::new(runtime.clone());
+
+        write(&dir, "a.yaml", &manifest("dns", "coredns:1"));
+        write(&dir, "b.yaml", &manifest("dns", "coredns:2"));
+        pods.sync(&dir).await;
+        assert_eq!(runtime.take(), vec!["start dns coredns:1"]);
+
+        // Once the first is gone, the other takes its place.
+        std::fs::remove_file(dir.join("a.yaml")).unwrap();
+        pods.sync(&dir).await;
+        assert_eq!(runtime.take(), vec!["stop dns", "start dns coredns:2"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Heartbeat pod usage
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod heartbeat_tests {
+    use super::cgroup_tests::fixture;
+    use crate::cgroup::CgroupSampler;
+    use crate::heartbeat::pod_usage;
+    use pkg_container::ContainerStore;
+    use pkg_types::pod::{Pod, container_id};
+    use std::time::{Duration, Instant};
+    use sysinfo::System;
+
+    fn pod(id: &str, name: &str) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "namespace": "default",
+            "status": "Running",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": { "containers": [{ "name": "app", "image": "nginx" }] }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn pod_usage_sums_container_processes() {
+        let store = ContainerStore::new();
+        // The test process stands in for a running microVM.
+        let running = container_id("p1", "app");
+        store.track(&running, "nginx", "vm", "/tmp/bundle", "/tmp/log");
+        store.set_pid(&running, std::process::id());
+        // Tracked but not started: no PID, no sample.
+        store.track(
+            &container_id("p2", "app"),
+            "nginx",
+            "test",
+            "/tmp/b",
+            "/tmp/l",
+        );
+        let pods = vec![pod("p1", "web-0"), pod("p2", "web-1"), pod("p3", "web-2")];
+
+        let mut sys = System::new();
+        let mut cgroups = CgroupSampler::with_roots("/nonexistent", "/nonexistent");
+        let usage = pod_usage(&mut sys, &mut cgroups, &store, &pods, Instant::now());
+        assert_eq!(usage.len(), 1);
+        assert_eq!(usage[0].name, "web-0");
+        assert_eq!(usage[0].namespace, "default");
+        assert!(usage[0].memory_bytes > 0);
+    }
+
+    #[test]
+    fn pod_usage_reads_oci_containers_from_their_cgroup() {
+        let root = fixture("hb-cgroup", 1_000_000, 64 << 20);
+        let store = ContainerStore::new();
+        let id = container_id("p1", "app");
+        store.track(&id, "nginx", "youki", "/tmp/bundle", "/tmp/log");
+        store.set_pid(&id, 4242);
+        let pods = vec![pod("p1", "web-0")];
+
+        let mut sys = System::new();
+        let mut cgroups = CgroupSampler::with_roots(root.join("proc"), root.join("cgroup"));
+        let start = Instant::now();
+        let first = pod_usage(&mut sys, &mut cgroups, &store, &pods, start);
+        assert_eq!((first[0].cpu_millis, first[0].memory_bytes), (0, 64 << 20));
+
+        // Half a second of CPU over one second of wall time: 500 millicores.
+        super::cgroup_tests::write_cpu(&root, 1_500_000);
+        let second = pod_usage(
+            &mut sys,
+            &mut cgroups,
+            &store,
+            &pods,
+            start + Duration::from_secs(1),
+        );
+        assert_eq!(second[0].cpu_millis, 500);
+    }
+}
 
-    /// Build a Service fixture with a single TCP port.
-    pub fn make_service(
-        id: &str,
-        name: &str,
-        ns: &str,
-        cluster_ip: &str,
-        port: u16,
-        target_port: u16,
-    ) -> Service {
-        Service {
-            id: id.to_string(),
-            name: name.to_string(),
-            namespace: ns.to_string(),
-            cluster_ip: Some(cluster_ip.to_string()),
-            vpc: Some("default".to_string()),
-            spec: ServiceSpec {
-                selector: HashMap::new(),
-                ports: vec![ServicePort {
-                    name: "http".to_string(),
-                    port,
-                    target_port,
-                    node_port: None,
-                }],
-                service_type: ServiceType::ClusterIP,
-            },
-            created_at: Utc::now(),
+// ─────────────────────────────────────────────────────────────────────────────
+// Cgroup usage
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod cgroup_tests {
+    use crate::cgroup::{
+        CgroupSampler, cgroup_path, oom_killed, parse_memory_current, parse_oom_kills,
+        parse_usage_usec,
+    };
+    use pkg_container::test_util::temp_dir;
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    const CONTAINER_CGROUP: &str = "/k3rs/web-0-app";
+
+    /// A fake `/proc` and `/sys/fs/cgroup`: the agent (`self`) in
+    /// `/system.slice/k3rs-agent.service`, PID 4242 in a container cgroup
+    /// with the given CPU time and memory, and PID 5000 in the agent's cgroup.
+    pub fn fixture(label: &str, usage_usec: u64, memory_bytes: u64) -> PathBuf {
+        let root = temp_dir(label);
+        for (pid, cgroup) in [
+            ("self", "/system.slice/k3rs-agent.service"),
+            ("4242", CONTAINER_CGROUP),
+            ("5000", "/system.slice/k3rs-agent.service"),
+        ] {
+            let dir = root.join("proc").join(pid);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("cgroup"), format!("0::{}\n", cgroup)).unwrap();
         }
+        for cgroup in [CONTAINER_CGROUP, "/system.slice/k3rs-agent.service"] {
+            let dir = root.join("cgroup").join(cgroup.trim_start_matches('/'));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("memory.current"), format!("{}\n", memory_bytes)).unwrap();
+        }
+        write_cpu(&root, usage_usec);
+        std::fs::write(
+            root.join("cgroup/system.slice/k3rs-agent.service/cpu.stat"),
+            "usage_usec 99\n",
+        )
+        .unwrap();
+        root
     }
 
-    /// Build an Endpoint fixture with a single pod IP address.
-    pub fn make_endpoint(
-        id: &str,
-        service_id: &str,
-        service_name: &str,
-        ns: &str,
-        pod_ip: &str,
-    ) -> Endpoint {
-        Endpoint {
-            id: id.to_string(),
-            service_id: service_id.to_string(),
-            service_name: service_name.to_string(),
-            namespace: ns.to_string(),
-            addresses: vec![EndpointAddress {
-                ip: pod_ip.to_string(),
-                node_name: None,
-                pod_id: None,
-            }],
-            ports: vec![],
-            created_at: Utc::now(),
+    /// Set the container cgroup's `cpu.stat` in a [`fixture`].
+    pub fn write_cpu(root: &Path, usage_usec: u64) {
+        let stat = format!(
+            "usage_usec {}\nuser_usec {}\nsystem_usec 0\nnr_periods 0\nnr_throttled 0\nthrottled_usec 0\n",
+            usage_usec, usage_usec
+        );
+        std::fs::write(
+            root.join("cgroup")
+                .join(CONTAINER_CGROUP.trim_start_matches('/'))
+                .join("cpu.stat"),
+            stat,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn parses_cgroup_files() {
+        // Hybrid hosts list v1 controllers before the unified hierarchy.
+        let proc_cgroup = "12:pids:/user.slice\n1:name=systemd:/init.scope\n0::/k3rs/web-0-app\n";
+        assert_eq!(cgroup_path(proc_cgroup), Some("/k3rs/web-0-app"));
+        assert_eq!(cgroup_path("1:cpu:/docker/abc\n"), None);
+        assert_eq!(cgroup_path("0::\n"), None);
+
+        assert_eq!(
+            parse_usage_usec("usage_usec 123456\nuser_usec 100000\nsystem_usec 23456\n"),
+            Some(123456)
+        );
+        assert_eq!(parse_usage_usec("user_usec 100000\n"), None);
+        assert_eq!(parse_memory_current("52428800\n"), Some(52428800));
+        assert_eq!(parse_memory_current("max\n"), None);
+    }
+
+    #[test]
+    fn cpu_is_the_rate_between_samples() {
+        let root = fixture("cgroup-rate", 2_000_000, 32 << 20);
+        let mut sampler = CgroupSampler::with_roots(root.join("proc"), root.join("cgroup"));
+        let start = Instant::now();
+
+        assert_eq!(sampler.sample("c1", 4242, start), Some((0, 32 << 20)));
+        // 3 s of CPU over 2 s: one and a half cores.
+        write_cpu(&root, 5_000_000);
+        assert_eq!(
+            sampler.sample("c1", 4242, start + Duration::from_secs(2)),
+            Some((1500, 32 << 20))
+        );
+
+        // A forgotten container starts over.
+        sampler.retain(&[]);
+        assert_eq!(
+            sampler.sample("c1", 4242, start + Duration::from_secs(3)),
+            Some((0, 32 << 20))
+        );
+    }
+
+    #[test]
+    fn no_sample_outside_a_container_cgroup() {
+        let root = fixture("cgroup-shared", 1, 1);
+        let mut sampler = CgroupSampler::with_roots(root.join("proc"), root.join("cgroup"));
+        // Same cgroup as the agent: the files would count the agent too.
+        assert_eq!(sampler.sample("c1", 5000, Instant::now()), None);
+        // Process gone.
+        assert_eq!(sampler.sample("c2", 7777, Instant::now()), None);
+    }
+
+    #[test]
+    fn oom_kill_counter_marks_the_container_oom_killed() {
+        let root = fixture("cgroup-oom", 1, 1);
+        let cgroup = root.join("cgroup");
+        let events = cgroup
+            .join(CONTAINER_CGROUP.trim_start_matches('/'))
+            .join("memory.events");
+
+        // No memory.events: the cgroup is gone or the runtime made none.
+        assert!(!oom_killed(&cgroup, "web-0-app"));
+
+        std::fs::write(&events, "low 0\nhigh 0\nmax 3\noom 1\noom_kill 0\n").unwrap();
+        assert_eq!(
+            parse_oom_kills(&std::fs::read_to_string(&events).unwrap()),
+            Some(0)
+        );
+        assert!(!oom_killed(&cgroup, "web-0-app"));
+
+        std::fs::write(&events, "low 0\nhigh 0\nmax 12\noom 2\noom_kill 1\n").unwrap();
+        assert!(oom_killed(&cgroup, "web-0-app"));
+        assert!(!oom_killed(&cgroup, "web-1-app"));
+        assert_eq!(parse_oom_kills("low 0\n"), None);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Pod watch
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod pod_watch_tests {
+    use crate::loops::pod_watch::wakes_pod_sync;
+
+    fn put(pod: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "seq": 7,
+            "event_type": "Put",
+            "key": "/registry/pods/default/web",
+            "value": serde_json::to_vec(&pod).unwrap(),
+        })
+    }
+
+    #[test]
+    fn wakes_for_pods_on_this_node_only() {
+        let ours = put(serde_json::json!({ "name": "web", "node_name": "node-a" }));
+        let theirs = put(serde_json::json!({ "name": "web", "node_name": "node-b" }));
+        let unscheduled = put(serde_json::json!({ "name": "web", "node_name": null }));
+        assert!(wakes_pod_sync(&ours, "node-a"));
+        assert!(!wakes_pod_sync(&theirs, "node-a"));
+        assert!(!wakes_pod_sync(&unscheduled, "node-a"));
+
+        let delete = serde_json::json!({
+            "seq": 8, "event_type": "Delete", "key": "/registry/pods/default/web", "value": null,
+        });
+        assert!(wakes_pod_sync(&delete, "node-a"));
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Bridge pod networking
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod pod_bridge_tests {
+    use crate::cache::AgentStateCache;
+    use crate::pod_bridge::PodBridge;
+    use crate::registration::ServerToken;
+    use pkg_types::pod::Pod;
+    use std::sync::{Arc, RwLock};
+
+    fn pod(id: &str, status: &str, pod_ip: Option<&str>) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "namespace": "default",
+            "status": status,
+            "pod_ip": pod_ip,
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": { "containers": [{ "name": "app", "image": "nginx" }] }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn keeps_addresses_of_pods_from_before_a_restart() {
+        let pods = [
+            pod("running", "Running", Some("10.42.3.2")),
+            pod("done", "Succeeded", Some("10.42.3.3")),
+            pod("elsewhere", "Running", Some("10.42.9.2")),
+            pod("new", "Scheduled", None),
+        ];
+        let bridge = PodBridge::new("10.42.3.0/24", &pods).await.unwrap();
+
+        assert_eq!(bridge.allocate("running").await.unwrap(), "10.42.3.2");
+        // The finished pod's address is free again.
+        assert_eq!(bridge.allocate("new").await.unwrap(), "10.42.3.3");
+        bridge.release("new").await;
+        assert_eq!(bridge.allocate("next").await.unwrap(), "10.42.3.3");
+    }
+
+    #[test]
+    fn bridge_networking_registers_until_it_has_a_pod_cidr() {
+        let mut cache = AgentStateCache::new("node-a".to_string());
+        cache.node_id = Some("id-1".to_string());
+        cache.agent_token = Some("secret".to_string());
+        assert_eq!(cache.probe_node_id(false).as_deref(), Some("id-1"));
+        assert_eq!(cache.probe_node_id(true), None);
+
+        cache.pod_cidr = Some("10.42.3.0/24".to_string());
+        assert_eq!(cache.probe_node_id(true).as_deref(), Some("id-1"));
+    }
+
+    #[test]
+    fn server_token_follows_the_latest_registration() {
+        let cache = Arc::new(RwLock::new(AgentStateCache::new("node-a".to_string())));
+        let token = ServerToken::new(cache.clone());
+        assert_eq!(token.to_string(), "");
+
+        cache.write().unwrap().agent_token = Some("first".to_string());
+        assert_eq!(token.to_string(), "node:node-a:first");
+        cache.write().unwrap().agent_token = Some("second".to_string());
+        assert_eq!(token.to_string(), "node:node-a:second");
+    }
+
+    #[test]
+    fn registers_until_it_has_an_agent_token() {
+        let mut cache = AgentStateCache::new("node-a".to_string());
+        cache.node_id = Some("id-1".to_string());
+        assert_eq!(cache.probe_node_id(false), None);
+
+        cache.agent_token = Some("secret".to_string());
+        assert_eq!(cache.probe_node_id(false).as_deref(), Some("id-1"));
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Node certificate rotation
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod cert_rotation_tests {
+    use crate::cert_rotation::{NodeCerts, needs_renewal, write_certs};
+    use axum::{
+        Json, Router,
+        extract::{Path, State},
+        http::StatusCode,
+        routing::post,
+    };
+    use chrono::{Duration, Utc};
+    use pkg_container::test_util::temp_dir;
+    use pkg_pki::ca::ClusterCA;
+    use pkg_types::error::{ApiError, ErrorReason};
+    use pkg_types::node::{CertificateRenewalRequest, CertificateRenewalResponse};
+    use std::sync::Arc;
+
+    /// Serve the renewal endpoint, checking requests the way the API server
+    /// does and issuing year-long certificates.
+    async fn serve_renewals(ca: Arc<ClusterCA>) -> String {
+        async fn renew(
+            State(ca): State<Arc<ClusterCA>>,
+            Path(name): Path<String>,
+            Json(req): Json<CertificateRenewalRequest>,
+        ) -> Result<Json<CertificateRenewalResponse>, (StatusCode, Json<ApiError>)> {
+            let now = Utc::now();
+            let message = pkg_pki::cert::renewal_message(&name, req.timestamp);
+            let valid = ca.verify_node_cert(&req.certificate, &name, now).is_ok()
+                && pkg_pki::cert::verify_signature(&req.certificate, &message, &req.signature)
+                    .unwrap_or(false);
+            if !valid {
+                let err = ApiError::new(ErrorReason::Unauthorized, "renewal rejected");
+                return Err((StatusCode::UNAUTHORIZED, Json(err)));
+            }
+            let (certificate, private_key) =
+                ca.issue_node_cert(&name, Duration::days(365)).unwrap();
+            Ok(Json(CertificateRenewalResponse {
+                certificate,
+                private_key,
+                server_ca: ca.ca_cert_pem().to_string(),
+                expires_at: now + Duration::days(365),
+            }))
         }
+
+        let app = Router::new()
+            .route("/api/v1/nodes/{name}/certificate/renew", post(renew))
+            .with_state(ca);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.ok();
+        });
+        format!("http://{}", addr)
+    }
+
+    fn read(dir: &std::path::Path, name: &str) -> String {
+        std::fs::read_to_string(dir.join(name)).unwrap()
+    }
+
+    #[test]
+    fn renews_once_below_the_threshold() {
+        let now = Utc::now();
+        let threshold = Duration::days(30);
+        assert!(!needs_renewal(now + Duration::days(31), now, threshold));
+        assert!(needs_renewal(now + Duration::days(29), now, threshold));
+        assert!(needs_renewal(now - Duration::days(1), now, threshold));
     }
 
-    /// Create and return a unique temp directory path (does NOT create the dir).
-    pub fn temp_dir(label: &str) -> String {
-        let ns = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
+    #[tokio::test]
+    async fn write_certs_replaces_the_whole_set() {
+        let dir = temp_dir("certs-swap");
+        write_certs(&dir, "cert-1", "key-1", "ca-1").await.unwrap();
+        write_certs(&dir, "cert-2", "key-2", "ca-2").await.unwrap();
+        assert_eq!(read(&dir, "node.crt"), "cert-2");
+        assert_eq!(read(&dir, "node.key"), "key-2");
+        assert_eq!(read(&dir, "ca.crt"), "ca-2");
+        // No temporary files are left behind.
+        let mut names: Vec<String> = std::fs::read_dir(&dir)
             .unwrap()
-            .subsec_nanos();
-        format!("/tmp/k3rs-test-{}-{}", label, ns)
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["ca.crt", "node.crt", "node.key"]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn renewal_swaps_in_a_fresh_certificate() {
+        let ca = Arc::new(ClusterCA::new().unwrap());
+        let server = serve_renewals(ca.clone()).await;
+        let dir = temp_dir("certs-renew");
+
+        // A short-lived certificate, well inside the renewal threshold.
+        let (cert, key) = ca.issue_node_cert("node-a", Duration::hours(1)).unwrap();
+        write_certs(&dir, &cert, &key, ca.ca_cert_pem())
+            .await
+            .unwrap();
+        let certs = NodeCerts::new(dir.clone(), "node-a".to_string());
+        let expires_at = certs.expires_at().unwrap().unwrap();
+        assert!(needs_renewal(expires_at, Utc::now(), Duration::days(30)));
+
+        let renewed = certs.renew(&server).await.unwrap();
+        assert!(renewed > Utc::now() + Duration::days(300));
+        let on_disk = certs.expires_at().unwrap().unwrap();
+        assert!((on_disk - renewed).num_seconds().abs() <= 1);
+        assert_ne!(read(&dir, "node.key"), key);
+        // The new certificate is the one presented next time.
+        assert!(certs.renew(&server).await.is_ok());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn renewal_with_an_expired_certificate_is_refused() {
+        let ca = Arc::new(ClusterCA::new().unwrap());
+        let server = serve_renewals(ca.clone()).await;
+        let dir = temp_dir("certs-expired");
+        let now = Utc::now();
+        let (cert, key) = ca
+            .issue_node_cert_between("node-a", now - Duration::hours(2), now - Duration::hours(1))
+            .unwrap();
+        write_certs(&dir, &cert, &key, ca.ca_cert_pem())
+            .await
+            .unwrap();
+        let certs = NodeCerts::new(dir.clone(), "node-a".to_string());
+
+        let err = certs.renew(&server).await.unwrap_err();
+        assert_eq!(err.reason, ErrorReason::Unauthorized);
+        // The old files are left alone.
+        assert_eq!(read(&dir, "node.crt"), cert);
+        assert_eq!(read(&dir, "node.key"), key);
+        std::fs::remove_dir_all(&dir).ok();
     }
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
-// Group 2 — Backoff
+// Agent metrics
 // ─────────────────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
-mod backoff_tests {
-    use crate::connectivity::ConnectivityManager;
+mod metrics_tests {
+    use crate::metrics::{HEARTBEAT_FAILURES_METRIC, record_pod_sync, register, scrape};
+    use pkg_metrics::MetricsRegistry;
     use std::time::Duration;
 
-    /// Verify the full 1s → 2s → 4s → 8s → 16s → 30s sequence.
     #[test]
-    fn backoff_sequence_is_correct() {
-        let cases = [
-            (0, 1u64),
-            (1, 2),
-            (2, 4),
-            (3, 8),
-            (4, 16),
-            (5, 30), // 32 → capped to 30
-            (6, 30),
-            (10, 30),
-        ];
-        for (attempt, expected_secs) in cases {
-            assert_eq!(
-                ConnectivityManager::backoff_duration(attempt),
-                Duration::from_secs(expected_secs),
-                "attempt={} must give {}s",
-                attempt,
-                expected_secs
+    fn test_scrape_reports_agent_series() {
+        let metrics = MetricsRegistry::new();
+        register(&metrics);
+        record_pod_sync(&metrics, Duration::from_millis(40));
+        record_pod_sync(&metrics, Duration::from_millis(25));
+        metrics.counter_inc(HEARTBEAT_FAILURES_METRIC);
+
+        let text = scrape(&metrics, 3, 5, 1);
+        for series in [
+            "k3rs_agent_containers_running 3",
+            "k3rs_agent_image_pulls_total 5",
+            "k3rs_agent_image_pull_failures_total 1",
+            "k3rs_agent_pod_syncs_total 2",
+            "k3rs_agent_pod_sync_duration_milliseconds 25",
+            "k3rs_agent_heartbeat_failures_total 1",
+        ] {
+            assert!(
+                text.lines().any(|line| line == series),
+                "missing {}",
+                series
             );
         }
+
+        // Totals kept by the runtime replace, not add to, the last scrape.
+        let text = scrape(&metrics, 0, 6, 1);
+        assert!(text.lines().any(|l| l == "k3rs_agent_image_pulls_total 6"));
+        assert!(text.lines().any(|l| l == "k3rs_agent_containers_running 0"));
     }
+}
 
-    /// Regression: `1u64 << attempt` panicked when attempt >= 64.
-    /// Fixed by capping shift index at 30 (`attempt.min(30)`).
-    #[test]
-    fn backoff_does_not_panic_on_large_attempt() {
-        let _ = ConnectivityManager::backoff_duration(63);
-        let _ = ConnectivityManager::backoff_duration(64); // was panic before fix
-        let _ = ConnectivityManager::backoff_duration(128);
-        let _ = ConnectivityManager::backoff_duration(u32::MAX);
+// ─────────────────────────────────────────────────────────────────────────────
+// Agent API authentication
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod api_auth_tests {
+    use crate::api_auth::ApiAuth;
+    use crate::cache::AgentStateCache;
+    use axum::{Router, extract::ws::WebSocketUpgrade, http::StatusCode, routing::get};
+    use pkg_types::error::{ApiError, ErrorReason};
+    use std::net::SocketAddr;
+    use std::sync::{Arc, RwLock};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn token_auth(token: Option<&str>) -> ApiAuth {
+        let mut cache = AgentStateCache::new("node-a".to_string());
+        cache.agent_token = token.map(String::from);
+        ApiAuth::Token(Arc::new(RwLock::new(cache)))
     }
 
-    /// Verify the heartbeat off-by-one fix: `fail_count.saturating_sub(1)`.
-    ///
-    /// In the heartbeat loop, `fail_count` is incremented *after* a failure and
-    /// is therefore 1-based at the top of the next loop iteration. The fix
-    /// subtracts 1 to get a 0-based index, so the first retry fires after 1s
-    /// rather than 2s.
-    #[test]
-    fn heartbeat_backoff_is_1s_on_first_retry() {
-        // fail_count=1 after first failure → saturating_sub(1) = 0 → 1s
-        let first = ConnectivityManager::backoff_duration(1u32.saturating_sub(1));
-        assert_eq!(
-            first,
-            Duration::from_secs(1),
-            "first heartbeat retry must be 1s"
-        );
+    /// Serve an exec-like route, a WebSocket upgrade, behind `auth`.
+    async fn serve(auth: ApiAuth) -> SocketAddr {
+        let router = auth
+            .protect(Router::new().route(
+                "/exec/{id}",
+                get(|ws: WebSocketUpgrade| async move { ws.on_upgrade(|_| async {}) }),
+            ))
+            .route("/metrics", get(|| async { "up" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.ok() });
+        addr
+    }
 
-        // fail_count=2 → index 1 → 2s
-        let second = ConnectivityManager::backoff_duration(2u32.saturating_sub(1));
-        assert_eq!(second, Duration::from_secs(2));
+    async fn status(auth: ApiAuth, path: &str, token: Option<&str>) -> (StatusCode, String) {
+        let addr = serve(auth).await;
+        let mut req = reqwest::Client::new().get(format!("http://{}{}", addr, path));
+        if let Some(token) = token {
+            req = req.bearer_auth(token);
+        }
+        let resp = req.send().await.unwrap();
+        let code = StatusCode::from_u16(resp.status().as_u16()).unwrap();
+        (code, resp.text().await.unwrap())
+    }
 
-        // fail_count=3 → index 2 → 4s
-        let third = ConnectivityManager::backoff_duration(3u32.saturating_sub(1));
-        assert_eq!(third, Duration::from_secs(4));
+    #[tokio::test]
+    async fn test_rejects_requests_without_the_token() {
+        for token in [None, Some("wrong")] {
+            let (code, body) = status(token_auth(Some("secret")), "/exec/c1", token).await;
+            assert_eq!(code, StatusCode::UNAUTHORIZED);
+            let err: ApiError = serde_json::from_str(&body).unwrap();
+            assert_eq!(err.reason, ErrorReason::Unauthorized);
+        }
+        // Before registration no token is good enough.
+        let (code, _) = status(token_auth(None), "/exec/c1", Some("secret")).await;
+        assert_eq!(code, StatusCode::UNAUTHORIZED);
+        // Metrics stay open for scrapers.
+        let (code, _) = status(token_auth(Some("secret")), "/metrics", None).await;
+        assert_eq!(code, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_token_follows_reregistration() {
+        let auth = token_auth(Some("old"));
+        let ApiAuth::Token(ref cache) = auth else {
+            unreachable!()
+        };
+        cache.write().unwrap().agent_token = Some("new".to_string());
+        // Not a WebSocket handshake, so a passing request fails the upgrade.
+        let (code, _) = status(auth.clone(), "/exec/c1", Some("new")).await;
+        assert_ne!(code, StatusCode::UNAUTHORIZED);
+        let (code, _) = status(auth, "/exec/c1", Some("old")).await;
+        assert_eq!(code, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_lets_everything_through() {
+        let (code, _) = status(ApiAuth::Disabled, "/exec/c1", None).await;
+        assert_ne!(code, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_upgrade_passes_the_middleware() {
+        let addr = serve(token_auth(Some("secret"))).await;
+        let handshake = |token: &str| {
+            format!(
+                "GET /exec/c1 HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\n\
+                 Upgrade: websocket\r\nSec-WebSocket-Version: 13\r\n\
+                 Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                 Authorization: Bearer {}\r\n\r\n",
+                addr, token
+            )
+        };
+        for (token, expected) in [("secret", "HTTP/1.1 101"), ("wrong", "HTTP/1.1 401")] {
+            let mut conn = tokio::net::TcpStream::connect(addr).await.unwrap();
+            conn.write_all(handshake(token).as_bytes()).await.unwrap();
+            let mut buf = [0u8; 12];
+            conn.read_exact(&mut buf).await.unwrap();
+            assert_eq!(String::from_utf8_lossy(&buf), expected);
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Port-forward relay
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod port_forward_tests {
+    use crate::port_forward::relay;
+    use axum::extract::ws::Message;
+    use futures_util::{Sink, Stream};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+
+    type FakeSocket = (
+        std::pin::Pin<Box<dyn Sink<Message, Error = ()> + Send>>,
+        std::pin::Pin<Box<dyn Stream<Item = Result<Message, axum::Error>> + Send>>,
+    );
+
+    /// WebSocket halves backed by channels: what the relay sends shows up on
+    /// the returned receiver, and frames pushed into the returned sender
+    /// reach the relay as if from the client.
+    fn fake_socket() -> (
+        FakeSocket,
+        UnboundedSender<Message>,
+        UnboundedReceiver<Message>,
+    ) {
+        let (out_tx, out_rx) = unbounded_channel();
+        let (in_tx, in_rx) = unbounded_channel();
+        let sink = futures_util::sink::unfold(out_tx, |tx, msg: Message| async move {
+            tx.send(msg).map_err(|_| ())?;
+            Ok(tx)
+        });
+        let stream = futures_util::stream::unfold(in_rx, |mut rx| async move {
+            rx.recv().await.map(|msg| (Ok(msg), rx))
+        });
+        ((Box::pin(sink), Box::pin(stream)), in_tx, out_rx)
+    }
+
+    /// A TCP echo server standing in for the container's port.
+    async fn echo_server() -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    while let Ok(n) = conn.read(&mut buf).await {
+                        if n == 0 || conn.write_all(&buf[..n]).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    async fn next_frame(rx: &mut UnboundedReceiver<Message>) -> Message {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("relay sent nothing")
+            .expect("relay dropped the socket")
+    }
+
+    #[tokio::test]
+    async fn test_relays_bytes_both_ways() {
+        let conn = tokio::net::TcpStream::connect(echo_server().await)
+            .await
+            .unwrap();
+        let ((ws_tx, ws_rx), client, mut frames) = fake_socket();
+        let task = tokio::spawn(relay(ws_tx, ws_rx, conn));
+
+        let payload: Vec<u8> = (0..=255u8).collect();
+        client
+            .send(Message::Binary(payload.clone().into()))
+            .unwrap();
+        let mut echoed = Vec::new();
+        while echoed.len() < payload.len() {
+            match next_frame(&mut frames).await {
+                Message::Binary(data) => echoed.extend_from_slice(&data),
+                other => panic!("unexpected frame {:?}", other),
+            }
+        }
+        assert_eq!(echoed, payload);
+
+        // The client going away ends the relay.
+        client.send(Message::Close(None)).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .expect("relay kept running after the client closed")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_container_side_close_closes_socket() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (conn, _) = listener.accept().await.unwrap();
+            drop(conn);
+        });
+        let conn = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let ((ws_tx, ws_rx), _client, mut frames) = fake_socket();
+        let task = tokio::spawn(relay(ws_tx, ws_rx, conn));
+
+        assert!(matches!(next_frame(&mut frames).await, Message::Close(_)));
+        tokio::time::timeout(Duration::from_secs(5), task)
+            .await
+            .unwrap()
+            .unwrap();
     }
 }
 
@@ -270,6 +2754,26 @@ mod cache_derivation_tests {
         assert!(c.derive_dns_map().is_empty(), "headless → no DNS record");
     }
 
+    #[test]
+    fn derive_maps_skip_explicit_headless_services() {
+        let mut c = AgentStateCache::new("node".to_string());
+        c.services = vec![make_service(
+            "svc-1",
+            "db",
+            "default",
+            pkg_types::service::CLUSTER_IP_NONE,
+            5432,
+            5432,
+        )];
+        c.endpoints = vec![make_endpoint("ep-1", "svc-1", "db", "default", "10.42.0.5")];
+
+        assert!(c.derive_dns_map().is_empty(), "no VIP → no DNS record");
+        assert!(
+            c.derive_routes_map().is_empty(),
+            "no VIP → nothing to proxy"
+        );
+    }
+
     #[test]
     fn derive_dns_map_empty_cache_returns_empty_map() {
         let c = AgentStateCache::new("node".to_string());
@@ -310,6 +2814,7 @@ mod cache_derivation_tests {
             ip: "192.168.1.11".to_string(),
             node_name: None,
             pod_id: None,
+            hostname: None,
         });
         c.endpoints = vec![ep];
 
@@ -348,7 +2853,7 @@ mod cache_derivation_tests {
         )];
         // No endpoints → no backends → route key omitted entirely
         assert!(
-            c.derive_routes_map().get("10.0.0.1:80").is_none(),
+            !c.derive_routes_map().contains_key("10.0.0.1:80"),
             "no endpoints → no route entry"
         );
     }
@@ -369,7 +2874,7 @@ mod cache_derivation_tests {
         )];
 
         assert!(
-            c.derive_routes_map().get("10.0.0.1:80").is_none(),
+            !c.derive_routes_map().contains_key("10.0.0.1:80"),
             "endpoint in different namespace must not match"
         );
     }
@@ -381,14 +2886,15 @@ mod cache_derivation_tests {
 
 #[cfg(test)]
 mod agent_store_tests {
-    use super::helpers::{make_endpoint, make_service, temp_dir};
+    use super::helpers::{make_endpoint, make_service};
     use crate::{cache::AgentStateCache, store::AgentStore};
     use chrono::Utc;
+    use pkg_container::test_util::temp_dir;
 
     /// Scenario 4 (fresh start): fresh store must report no cached state.
     #[tokio::test]
     async fn fresh_store_load_returns_none() {
-        let dir = temp_dir("fresh");
+        let dir = temp_dir("fresh").to_string_lossy().into_owned();
         let store = AgentStore::open(&dir).await.unwrap();
         assert!(
             store.load().await.unwrap().is_none(),
@@ -401,12 +2907,13 @@ mod agent_store_tests {
     /// a save → reload cycle.
     #[tokio::test]
     async fn roundtrip_identity_fields() {
-        let dir = temp_dir("identity");
+        let dir = temp_dir("identity").to_string_lossy().into_owned();
         let store = AgentStore::open(&dir).await.unwrap();
 
         let mut cache = AgentStateCache::new("test-node".to_string());
         cache.node_id = Some("node-abc".to_string());
         cache.agent_api_port = Some(9443);
+        cache.pod_cidr = Some("10.42.3.0/24".to_string());
         cache.server_seq = 42;
         cache.last_synced_at = Utc::now();
 
@@ -416,6 +2923,7 @@ mod agent_store_tests {
         assert_eq!(loaded.node_name, "test-node");
         assert_eq!(loaded.node_id.as_deref(), Some("node-abc"));
         assert_eq!(loaded.agent_api_port, Some(9443));
+        assert_eq!(loaded.pod_cidr.as_deref(), Some("10.42.3.0/24"));
         assert_eq!(loaded.server_seq, 42);
 
         std::fs::remove_dir_all(&dir).ok();
@@ -425,7 +2933,7 @@ mod agent_store_tests {
     /// and field values.
     #[tokio::test]
     async fn roundtrip_services_and_endpoints() {
-        let dir = temp_dir("collections");
+        let dir = temp_dir("collections").to_string_lossy().into_owned();
         let store = AgentStore::open(&dir).await.unwrap();
 
         let mut cache = AgentStateCache::new("node".to_string());
@@ -461,7 +2969,7 @@ mod agent_store_tests {
     /// instead of scanning all collections.
     #[tokio::test]
     async fn derived_views_are_stored_and_fast_loadable() {
-        let dir = temp_dir("derived");
+        let dir = temp_dir("derived").to_string_lossy().into_owned();
         let store = AgentStore::open(&dir).await.unwrap();
 
         let mut cache = AgentStateCache::new("node".to_string());
@@ -510,7 +3018,7 @@ mod agent_store_tests {
     /// keys from the first — no merging, server state always wins.
     #[tokio::test]
     async fn second_save_overwrites_first_server_wins() {
-        let dir = temp_dir("overwrite");
+        let dir = temp_dir("overwrite").to_string_lossy().into_owned();
         let store = AgentStore::open(&dir).await.unwrap();
 
         // First sync: service A only
@@ -551,7 +3059,7 @@ mod agent_store_tests {
     /// Opening the same store path twice returns independent handles to the same data.
     #[tokio::test]
     async fn reopen_reads_persisted_data() {
-        let dir = temp_dir("reopen");
+        let dir = temp_dir("reopen").to_string_lossy().into_owned();
 
         {
             let store = AgentStore::open(&dir).await.unwrap();
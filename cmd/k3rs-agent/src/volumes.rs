@@ -0,0 +1,107 @@
+//! Resolve a container's volume mounts against the pod's declared volumes.
+
+use crate::registration::ServerToken;
+use pkg_container::volume::BindMount;
+use pkg_types::pod::{ContainerSpec, Pod};
+use pkg_types::volume::{PersistentVolume, PersistentVolumeClaim, VolumeSource};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Bind mounts for one of the pod's containers. `empty_dir` gives the host
+/// directory of an `emptyDir` volume by name, `claim_path` the directory of
+/// the volume bound to a PersistentVolumeClaim by claim name.
+///
+/// Fails with a message fit for the pod's status when a mount names a
+/// volume the pod does not declare or of a type the agent cannot mount.
+pub fn resolve_mounts(
+    pod: &Pod,
+    container: &ContainerSpec,
+    empty_dir: impl Fn(&str) -> PathBuf,
+    claim_path: impl Fn(&str) -> Option<PathBuf>,
+) -> Result<Vec<BindMount>, String> {
+    container
+        .volume_mounts
+        .iter()
+        .map(|mount| {
+            let volume = pod
+                .spec
+                .volumes
+                .iter()
+                .find(|v| v.name == mount.name)
+                .ok_or_else(|| {
+                    format!(
+                        "Container {} mounts undeclared volume {}",
+                        container.name, mount.name
+                    )
+                })?;
+            let source = match &volume.source {
+                VolumeSource::EmptyDir {} => empty_dir(&volume.name),
+                VolumeSource::HostPath { path } => PathBuf::from(path),
+                VolumeSource::PersistentVolumeClaim { claim_name } => claim_path(claim_name)
+                    .ok_or_else(|| {
+                        format!("Volume {}: claim {} is not bound", volume.name, claim_name)
+                    })?,
+                VolumeSource::ConfigMap { .. } | VolumeSource::Secret { .. } => {
+                    return Err(format!(
+                        "Volume {} has an unsupported type (only emptyDir, hostPath and persistentVolumeClaim can be mounted)",
+                        volume.name
+                    ));
+                }
+            };
+            Ok(BindMount {
+                source,
+                destination: mount.mount_path.clone(),
+                read_only: mount.read_only,
+            })
+        })
+        .collect()
+}
+
+/// Host directories of the volumes bound to the pod's claims, by claim name,
+/// fetched from the server. A claim that is not bound yet — or whose volume
+/// is not on the pod's node — is an error naming it, and the pod waits.
+pub async fn fetch_claim_paths(
+    client: &reqwest::Client,
+    server: &str,
+    token: &ServerToken,
+    pod: &Pod,
+) -> anyhow::Result<HashMap<String, PathBuf>> {
+    let server = server.trim_end_matches('/');
+    let mut paths = HashMap::new();
+    for claim_name in pod.spec.claim_names() {
+        let url = format!(
+            "{}/api/v1/namespaces/{}/pvcs/{}",
+            server, pod.namespace, claim_name
+        );
+        let claim: PersistentVolumeClaim = get_json(client, &url, token).await?;
+        let Some(volume_name) = claim.volume_name else {
+            anyhow::bail!("claim {} is not bound yet", claim_name);
+        };
+        let url = format!("{}/api/v1/persistentvolumes/{}", server, volume_name);
+        let volume: PersistentVolume = get_json(client, &url, token).await?;
+        if pod.node_name.as_deref() != Some(volume.node_name.as_str()) {
+            anyhow::bail!(
+                "claim {} is bound to a volume on node {}",
+                claim_name,
+                volume.node_name
+            );
+        }
+        paths.insert(claim_name.to_string(), PathBuf::from(volume.path));
+    }
+    Ok(paths)
+}
+
+async fn get_json<T: serde::de::DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+    token: &ServerToken,
+) -> anyhow::Result<T> {
+    Ok(client
+        .get(url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
@@ -69,6 +69,8 @@ fn server_config() -> DevConfig {
             &pkg_constants::network::DEFAULT_API_PORT.to_string(),
             "--token",
             pkg_constants::auth::DEFAULT_JOIN_TOKEN,
+            "--admin-token",
+            pkg_constants::auth::DEFAULT_ADMIN_TOKEN,
             "--data-dir",
             pkg_constants::paths::DATA_DIR,
             "--node-name",
@@ -126,7 +128,7 @@ fn vpc_config() -> DevConfig {
             "--server-url",
             pkg_constants::network::DEFAULT_API_ADDR,
             "--token",
-            pkg_constants::auth::DEFAULT_JOIN_TOKEN,
+            pkg_constants::auth::DEFAULT_ADMIN_TOKEN,
             "--socket",
             &vpc_socket,
             "--data-dir",
@@ -2,15 +2,23 @@ use std::io::Write;
 #[cfg(target_os = "linux")]
 use std::os::unix::process::CommandExt;
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use crate::is_initrd_mode;
+#[cfg(target_os = "linux")]
+use crate::logforward;
 use crate::oci_spec::OciProcess;
 #[cfg(target_os = "linux")]
+use crate::privileges::Privileges;
+#[cfg(target_os = "linux")]
 use crate::signals::{reap_zombies, reaper_loop};
 #[cfg(target_os = "linux")]
 use crate::vsock::chroot_into_rootfs;
 
+/// How long the last output of the entrypoint may take to reach the host.
+#[cfg(target_os = "linux")]
+const LOG_FLUSH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// Execute the OCI entrypoint process.
 #[cfg(target_os = "linux")]
 pub fn run_entrypoint(process: OciProcess) {
@@ -22,7 +30,25 @@ pub fn run_entrypoint(process: OciProcess) {
     let program = &process.args[0];
     let args = &process.args[1..];
 
-    log_info!("executing entrypoint: {} {}", program, args.join(" "));
+    // Resolved up front: the child may not allocate after the fork. A bad
+    // user or rlimit stops here rather than running the entrypoint as root.
+    let privileges = match Privileges::from_process(&process) {
+        Ok(privileges) => privileges,
+        Err(e) => {
+            log_error!(
+                "invalid process privileges: {} — not starting the entrypoint",
+                e
+            );
+            reaper_loop();
+        }
+    };
+
+    log_info!(
+        "executing entrypoint: {} {} ({})",
+        program,
+        args.join(" "),
+        privileges.describe()
+    );
 
     // Set working directory
     if let Some(ref cwd) = process.cwd {
@@ -42,16 +68,18 @@ pub fn run_entrypoint(process: OciProcess) {
 
     // Spawn the entrypoint. In initrd mode chroot into the virtiofs-mounted rootfs
     // first; in no-initrd mode we are already running inside the container rootfs.
+    // Then drop to the OCI user and limits; if that fails the exec never happens.
     // PID 1 stays alive to continue reaping orphaned zombies.
     match unsafe {
         Command::new(program)
             .args(args)
-            .pre_exec(|| {
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .pre_exec(move || {
                 if is_initrd_mode() {
-                    chroot_into_rootfs()
-                } else {
-                    Ok(())
+                    chroot_into_rootfs()?;
                 }
+                privileges.apply()
             })
             .spawn()
     } {
@@ -59,6 +87,9 @@ pub fn run_entrypoint(process: OciProcess) {
             let child_pid = child.id();
             log_info!("entrypoint spawned (pid={})", child_pid);
 
+            // Output goes to the host over vsock, not the console.
+            logforward::forward(child.stdout.take(), child.stderr.take());
+
             // Poll loop: wait for entrypoint while reaping zombies
             loop {
                 match child.try_wait() {
@@ -66,6 +97,7 @@ pub fn run_entrypoint(process: OciProcess) {
                         let code = status.code().unwrap_or(1);
                         log_info!("entrypoint exited with code {}", code);
                         reap_zombies();
+                        logforward::flush(LOG_FLUSH_TIMEOUT);
                         shutdown(code);
                         return;
                     }
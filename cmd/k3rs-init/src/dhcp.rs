@@ -12,7 +12,7 @@ use std::io::Write;
 
 /// Result of a successful DHCP exchange.
 #[cfg(target_os = "linux")]
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct DhcpLease {
     /// Assigned IP address (e.g. "192.168.64.2")
     pub ip: String,
@@ -77,6 +77,7 @@ const XID: u32 = 0x4b335253; // "K3RS" in hex
 pub fn do_dhcp(iface: &str) -> Result<DhcpLease, Box<dyn std::error::Error>> {
     // Create raw UDP socket bound to 0.0.0.0:68
     let sock = create_dhcp_socket(iface)?;
+    let mac = interface_mac(iface);
 
     // Try up to 3 times with increasing timeout
     for attempt in 0..3 {
@@ -89,7 +90,7 @@ pub fn do_dhcp(iface: &str) -> Result<DhcpLease, Box<dyn std::error::Error>> {
             attempt + 1,
             timeout_ms
         );
-        let discover = build_discover();
+        let discover = build_discover(mac);
         send_broadcast(sock, &discover)?;
 
         // 2. Wait for OFFER
@@ -301,8 +302,26 @@ fn recv_dhcp(sock: i32) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
 
 // ─── Packet builders ─────────────────────────────────────────────
 
+/// MAC address of `iface` from sysfs, or a k3rs-specific local MAC if it
+/// cannot be read.
 #[cfg(target_os = "linux")]
-fn build_discover() -> Vec<u8> {
+fn interface_mac(iface: &str) -> [u8; 6] {
+    let mut mac = [0x02, 0x6b, 0x33, 0x72, 0x73, 0x01];
+    if let Ok(mac_str) = std::fs::read_to_string(format!("/sys/class/net/{}/address", iface)) {
+        let parts: Vec<u8> = mac_str
+            .trim()
+            .split(':')
+            .filter_map(|s| u8::from_str_radix(s, 16).ok())
+            .collect();
+        if parts.len() == 6 {
+            mac.copy_from_slice(&parts);
+        }
+    }
+    mac
+}
+
+#[cfg(target_os = "linux")]
+fn build_discover(mac: [u8; 6]) -> Vec<u8> {
     let mut pkt = vec![0u8; 300];
 
     pkt[0] = 1; // op: BOOTREQUEST
@@ -318,26 +337,8 @@ fn build_discover() -> Vec<u8> {
     pkt[10] = 0x80;
     pkt[11] = 0x00;
 
-    // chaddr: use a static MAC (will be replaced by actual MAC from eth0)
-    // We use a k3rs-specific MAC prefix
-    pkt[28] = 0x02;
-    pkt[29] = 0x6b;
-    pkt[30] = 0x33;
-    pkt[31] = 0x72;
-    pkt[32] = 0x73;
-    pkt[33] = 0x01;
-
-    // Try to read actual MAC from sysfs
-    if let Ok(mac_str) = std::fs::read_to_string("/sys/class/net/eth0/address") {
-        let parts: Vec<u8> = mac_str
-            .trim()
-            .split(':')
-            .filter_map(|s| u8::from_str_radix(s, 16).ok())
-            .collect();
-        if parts.len() == 6 {
-            pkt[28..34].copy_from_slice(&parts);
-        }
-    }
+    // chaddr
+    pkt[28..34].copy_from_slice(&mac);
 
     // Magic cookie
     pkt[236..240].copy_from_slice(&MAGIC_COOKIE);
@@ -559,3 +560,56 @@ fn parse_ip_to_bytes(ip: &str) -> Result<[u8; 4], Box<dyn std::error::Error>> {
     }
     Ok([parts[0], parts[1], parts[2], parts[3]])
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    // Exchange with the Virtualization.framework NAT DHCP server (bootpd on
+    // 192.168.64.1) for a guest with MAC 02:6b:33:72:73:05.
+    const MAC: [u8; 6] = [0x02, 0x6b, 0x33, 0x72, 0x73, 0x05];
+    const DISCOVER: &[u8] = include_bytes!("testdata/dhcp_discover.bin");
+    const OFFER: &[u8] = include_bytes!("testdata/dhcp_offer.bin");
+    const REQUEST: &[u8] = include_bytes!("testdata/dhcp_request.bin");
+    const ACK: &[u8] = include_bytes!("testdata/dhcp_ack.bin");
+
+    #[test]
+    fn test_encode_discover() {
+        assert_eq!(build_discover(MAC), DISCOVER);
+    }
+
+    #[test]
+    fn test_encode_request_from_offer() {
+        assert_eq!(get_msg_type(OFFER), Some(DHCP_OFFER));
+        let server = get_option_ip(OFFER, OPT_SERVER_ID);
+        assert_eq!(server.as_deref(), Some("192.168.64.1"));
+        assert_eq!(
+            build_request(OFFER, "192.168.64.5", server.as_deref()),
+            REQUEST
+        );
+    }
+
+    #[test]
+    fn test_decode_ack() {
+        assert_eq!(get_msg_type(ACK), Some(DHCP_ACK));
+        assert_eq!(
+            parse_lease(ACK),
+            DhcpLease {
+                ip: "192.168.64.5".to_string(),
+                prefix_len: 24,
+                gateway: Some("192.168.64.1".to_string()),
+                dns_servers: vec!["192.168.64.1".to_string(), "192.168.64.2".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_truncated_options() {
+        // Options cut off mid-way decode as far as they go.
+        let ack = &ACK[..246];
+        assert_eq!(get_msg_type(ack), Some(DHCP_ACK));
+        assert_eq!(get_option_ip(ack, OPT_SERVER_ID), None);
+        assert_eq!(get_msg_type(&ACK[..200]), None);
+        assert_eq!(mask_to_prefix("255.255.240.0"), 20);
+    }
+}
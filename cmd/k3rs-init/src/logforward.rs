@@ -0,0 +1,324 @@
+//! Forwards the entrypoint's stdout/stderr to the host over vsock.
+//!
+//! Each line is framed with its stream tag and the time it was read
+//! (`tag | unix nanos | length | line`, see `pkg_constants::vm`) and queued.
+//! The host connects to `VSOCK_LOG_PORT` and reads the frames, keeping
+//! container output apart from the kernel and k3rs-init messages on the
+//! console.
+//!
+//! The queue is bounded: when the host is not reading, new lines are dropped
+//! and counted, and the count goes out as a marker line once there is room
+//! again. The workload never blocks on its own output.
+
+use std::collections::VecDeque;
+#[cfg(target_os = "linux")]
+use std::io::{BufRead, BufReader, Read, Write};
+#[cfg(target_os = "linux")]
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(target_os = "linux")]
+use std::time::Duration;
+
+use pkg_constants::vm::{LOG_FRAME_HEADER_LEN, LOG_FRAME_STDERR};
+#[cfg(target_os = "linux")]
+use pkg_constants::vm::{LOG_FRAME_MAX_LINE, LOG_FRAME_STDOUT, VSOCK_LOG_PORT};
+
+/// Bytes of frames held while the host is not reading.
+#[cfg(target_os = "linux")]
+const LOG_BUFFER_BYTES: usize = 1024 * 1024;
+
+/// Frame one line (without its newline).
+pub fn encode_frame(tag: u8, unix_nanos: u64, line: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(LOG_FRAME_HEADER_LEN + line.len());
+    frame.push(tag);
+    frame.extend_from_slice(&unix_nanos.to_be_bytes());
+    frame.extend_from_slice(&(line.len() as u32).to_be_bytes());
+    frame.extend_from_slice(line);
+    frame
+}
+
+/// Frames waiting for the host, at most `capacity` bytes of them.
+#[derive(Debug)]
+pub struct LogBuffer {
+    frames: VecDeque<Vec<u8>>,
+    bytes: usize,
+    capacity: usize,
+    /// Lines dropped since the last marker.
+    dropped: u64,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            bytes: 0,
+            capacity,
+            dropped: 0,
+        }
+    }
+
+    /// Queue `frame`, or drop it if it does not fit. After drops, a marker
+    /// frame saying how many lines were lost goes ahead of the next frame
+    /// that fits along with it. Returns whether `frame` was queued.
+    pub fn push(&mut self, frame: Vec<u8>, unix_nanos: u64) -> bool {
+        let marker = (self.dropped > 0).then(|| {
+            let message = format!(
+                "[k3rs-init] {} log lines dropped: host not reading",
+                self.dropped
+            );
+            encode_frame(LOG_FRAME_STDERR, unix_nanos, message.as_bytes())
+        });
+        let needed = frame.len() + marker.as_ref().map_or(0, Vec::len);
+        if self.bytes + needed > self.capacity {
+            self.dropped += 1;
+            return false;
+        }
+        if let Some(marker) = marker {
+            self.dropped = 0;
+            self.enqueue(marker);
+        }
+        self.enqueue(frame);
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let frame = self.frames.pop_front()?;
+        self.bytes -= frame.len();
+        Some(frame)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    fn enqueue(&mut self, frame: Vec<u8>) {
+        self.bytes += frame.len();
+        self.frames.push_back(frame);
+    }
+}
+
+#[cfg(target_os = "linux")]
+struct State {
+    buffer: LogBuffer,
+    /// Pipes still being read.
+    readers: usize,
+}
+
+#[cfg(target_os = "linux")]
+type Shared = Arc<(Mutex<State>, Condvar)>;
+
+#[cfg(target_os = "linux")]
+static SHARED: std::sync::OnceLock<Shared> = std::sync::OnceLock::new();
+
+/// Start forwarding the entrypoint's output: one thread per pipe, and one
+/// serving the frames to the host.
+#[cfg(target_os = "linux")]
+pub fn forward(
+    stdout: Option<std::process::ChildStdout>,
+    stderr: Option<std::process::ChildStderr>,
+) {
+    let shared: Shared = Arc::new((
+        Mutex::new(State {
+            buffer: LogBuffer::new(LOG_BUFFER_BYTES),
+            readers: 0,
+        }),
+        Condvar::new(),
+    ));
+    if SHARED.set(Arc::clone(&shared)).is_err() {
+        log_error!("log forwarding already started");
+        return;
+    }
+
+    let pipes: [(u8, Option<Box<dyn Read + Send>>); 2] = [
+        (LOG_FRAME_STDOUT, stdout.map(|p| Box::new(p) as _)),
+        (LOG_FRAME_STDERR, stderr.map(|p| Box::new(p) as _)),
+    ];
+    for (tag, pipe) in pipes {
+        let Some(pipe) = pipe else { continue };
+        shared.0.lock().unwrap().readers += 1;
+        let shared = Arc::clone(&shared);
+        std::thread::spawn(move || read_pipe(pipe, tag, &shared));
+    }
+
+    std::thread::spawn(move || {
+        if let Err(e) = serve(&shared) {
+            log_error!("log forwarding listener failed: {}", e);
+        }
+    });
+    log_info!(
+        "forwarding entrypoint output on vsock port {}",
+        VSOCK_LOG_PORT
+    );
+}
+
+/// Wait until the entrypoint's pipes are closed and every queued line has
+/// gone to the host, or `timeout` passes. Called before powering off.
+#[cfg(target_os = "linux")]
+pub fn flush(timeout: Duration) {
+    let Some(shared) = SHARED.get() else {
+        return;
+    };
+    let (lock, cv) = shared.as_ref();
+    let state = lock.lock().unwrap();
+    let (state, result) = cv
+        .wait_timeout_while(state, timeout, |s| s.readers > 0 || !s.buffer.is_empty())
+        .unwrap();
+    if result.timed_out() {
+        log_error!(
+            "log flush timed out with {} pipe(s) open, buffer empty: {}",
+            state.readers,
+            state.buffer.is_empty()
+        );
+    }
+}
+
+/// Frame the lines of one pipe until EOF. Lines longer than
+/// `LOG_FRAME_MAX_LINE` are split over several frames.
+#[cfg(target_os = "linux")]
+fn read_pipe(pipe: Box<dyn Read + Send>, tag: u8, shared: &Shared) {
+    let (lock, cv) = shared.as_ref();
+    let mut reader = BufReader::new(pipe);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let limit = LOG_FRAME_MAX_LINE as u64;
+        match (&mut reader).take(limit).read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        let now = unix_nanos();
+        lock.lock()
+            .unwrap()
+            .buffer
+            .push(encode_frame(tag, now, &line), now);
+        cv.notify_all();
+    }
+    lock.lock().unwrap().readers -= 1;
+    cv.notify_all();
+}
+
+/// Accept the host's reader and write it frames as they come. A frame whose
+/// write fails is lost with the connection; the next host gets the rest.
+#[cfg(target_os = "linux")]
+fn serve(shared: &Shared) -> Result<(), Box<dyn std::error::Error>> {
+    let (lock, cv) = shared.as_ref();
+    let sock = crate::vsock::vsock_listen(VSOCK_LOG_PORT)?;
+    loop {
+        let fd = unsafe { libc::accept(sock, std::ptr::null_mut(), std::ptr::null_mut()) };
+        if fd < 0 {
+            continue;
+        }
+        log_info!("host log reader connected");
+        loop {
+            let frame = {
+                let mut state = lock.lock().unwrap();
+                loop {
+                    if let Some(frame) = state.buffer.pop() {
+                        break frame;
+                    }
+                    state = cv.wait(state).unwrap();
+                }
+            };
+            cv.notify_all();
+            if let Err(e) = send_all(fd, &frame) {
+                log_error!("host log reader gone: {}", e);
+                break;
+            }
+        }
+        unsafe { libc::close(fd) };
+    }
+}
+
+/// Write all of `data`; `MSG_NOSIGNAL` keeps a closed peer from raising SIGPIPE.
+#[cfg(target_os = "linux")]
+fn send_all(fd: i32, mut data: &[u8]) -> std::io::Result<()> {
+    while !data.is_empty() {
+        let n = unsafe {
+            libc::send(
+                fd,
+                data.as_ptr() as *const libc::c_void,
+                data.len(),
+                libc::MSG_NOSIGNAL,
+            )
+        };
+        if n < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        data = &data[n as usize..];
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn unix_nanos() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_frame() {
+        // pkg_types::guest_log decodes the same bytes in its tests.
+        let frame = encode_frame(LOG_FRAME_STDERR, 1_700_000_000_123_456_789, b"oops");
+        assert_eq!(
+            frame,
+            [
+                2, 0x17, 0x97, 0x9c, 0xfe, 0x3d, 0x85, 0xcd, 0x15, 0, 0, 0, 4, b'o', b'o', b'p',
+                b's'
+            ]
+        );
+        assert_eq!(encode_frame(1, 0, b"").len(), LOG_FRAME_HEADER_LEN);
+    }
+
+    #[test]
+    fn test_buffer_drops_with_marker() {
+        // 30-byte frames: three fit, and later one does along with a marker.
+        let frame = |line: &str| encode_frame(1, 7, format!("{:<17}", line).as_bytes());
+        let mut buffer = LogBuffer::new(100);
+        for i in 0..5 {
+            let queued = buffer.push(frame(&format!("line-{}", i)), 7);
+            assert_eq!(queued, i < 3, "line {}", i);
+        }
+
+        // The host catches up, then the next line is preceded by the count.
+        assert_eq!(buffer.pop(), Some(frame("line-0")));
+        assert_eq!(buffer.pop(), Some(frame("line-1")));
+        assert_eq!(buffer.pop(), Some(frame("line-2")));
+        assert!(buffer.is_empty());
+        assert!(buffer.push(frame("line-5"), 9));
+        let marker = buffer.pop().unwrap();
+        assert_eq!(marker[0], LOG_FRAME_STDERR);
+        assert_eq!(
+            &marker[LOG_FRAME_HEADER_LEN..],
+            b"[k3rs-init] 2 log lines dropped: host not reading"
+        );
+        assert_eq!(buffer.pop(), Some(frame("line-5")));
+        assert_eq!(buffer.pop(), None);
+    }
+
+    #[test]
+    fn test_buffer_keeps_counting_until_marker_fits() {
+        let frame = encode_frame(1, 0, b"x");
+        let mut buffer = LogBuffer::new(frame.len());
+        assert!(buffer.push(frame.clone(), 0));
+        assert!(!buffer.push(frame.clone(), 0));
+        buffer.pop();
+        // Room for the line again, but not for the marker too.
+        assert!(!buffer.push(frame.clone(), 0));
+        assert_eq!(buffer.dropped, 2);
+        assert!(buffer.is_empty());
+    }
+}
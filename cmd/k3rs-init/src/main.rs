@@ -5,10 +5,12 @@
 //!
 //! ## Responsibilities
 //! 1. Mount essential pseudo-filesystems (`/proc`, `/sys`, `/dev`, `/tmp`, `/run`)
-//! 2. Setup basic networking (loopback `lo`, `eth0`)
+//! 2. Setup networking (loopback `lo`, `eth0` via VPC params, static config or DHCP)
 //! 3. Set hostname
 //! 4. Reap orphaned zombie processes (critical for PID 1)
-//! 5. Parse OCI `config.json` and exec the container entrypoint
+//! 5. Parse OCI `config.json` and exec the container entrypoint as its
+//!    `process.user`, with its `rlimits` and `noNewPrivileges`
+//! 6. Forward the entrypoint's stdout/stderr to the host over vsock
 //!
 //! ## Build
 //! ```bash
@@ -32,12 +34,14 @@ use std::sync::OnceLock;
 
 // Modules
 mod oci_spec;
+mod privileges;
 #[macro_use]
 mod logging;
 mod container;
 mod dhcp;
 mod ebpf;
 mod filesystem;
+mod logforward;
 mod networking;
 mod signals;
 mod vsock;
@@ -109,8 +113,11 @@ fn linux_main() {
         log_info!("hostname set to '{}'", DEFAULT_HOSTNAME);
     }
 
-    // 3. Setup networking
-    if let Err(e) = networking::setup_networking() {
+    // 3. Setup networking — the OCI config may carry a `network` section, so
+    //    it is loaded first (the rootfs is mounted by now).
+    let spec = filesystem::load_oci_config();
+    let network = spec.as_ref().ok().and_then(|s| s.network.as_ref());
+    if let Err(e) = networking::setup_networking(network) {
         log_error!("failed to setup networking: {}", e);
     }
 
@@ -125,8 +132,8 @@ fn linux_main() {
     // 4b. Start vsock exec listener (background thread)
     vsock::start_vsock_listener();
 
-    // 5. Parse OCI config and execute entrypoint
-    match filesystem::load_oci_config() {
+    // 5. Execute the entrypoint from the OCI config
+    match spec {
         Ok(spec) => {
             // Override hostname if OCI spec provides one
             if let Some(ref h) = spec.hostname {
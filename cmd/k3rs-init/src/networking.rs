@@ -1,6 +1,9 @@
 use std::io::Write;
 use std::path::Path;
 
+#[cfg(target_os = "linux")]
+use crate::oci_spec::{NetworkConfig, NetworkMode};
+
 /// k3rs VPC boot parameters parsed from /proc/cmdline.
 #[cfg(target_os = "linux")]
 #[derive(Debug, Default)]
@@ -59,9 +62,13 @@ pub fn parse_cmdline() -> K3rsBootParams {
     params
 }
 
-/// Setup networking: bring up loopback and eth0, optionally configure VPC networking.
+/// Setup networking: bring up loopback and eth0, then give eth0 an address.
+///
+/// VPC boot parameters take precedence; otherwise the `network` section the
+/// host wrote into config.json, then a kernel `ip=` parameter, then DHCP.
+/// Address failures are logged and the boot carries on.
 #[cfg(target_os = "linux")]
-pub fn setup_networking() -> Result<(), Box<dyn std::error::Error>> {
+pub fn setup_networking(network: Option<&NetworkConfig>) -> Result<(), Box<dyn std::error::Error>> {
     // Bring up loopback
     if Path::new("/sys/class/net/lo/operstate").exists() {
         bring_interface_up("lo")?;
@@ -95,28 +102,74 @@ pub fn setup_networking() -> Result<(), Box<dyn std::error::Error>> {
         if let Err(e) = configure_vpc_networking(ipv4, ipv6, vpc_cidr, gw_mac) {
             log_error!("failed to configure VPC networking: {}", e);
         }
-    } else if has_kernel_ip_param() {
-        // Kernel ip= parameter handles networking (Firecracker legacy path)
-        log_info!("kernel ip= parameter present — skipping DHCP");
-    } else {
-        // No VPC params, no kernel ip= → use DHCP (macOS Virtualization.framework NAT)
-        log_info!("no VPC boot params — attempting DHCP on eth0");
-        match crate::dhcp::do_dhcp("eth0") {
-            Ok(lease) => {
-                // Apply the lease to eth0
-                if let Err(e) = apply_dhcp_lease(&lease) {
-                    log_error!("failed to apply DHCP lease: {}", e);
-                }
-            }
-            Err(e) => {
-                log_error!("DHCP failed: {} — networking may be unavailable", e);
+        return Ok(());
+    }
+
+    let mode = match network.map(NetworkConfig::mode) {
+        Some(Ok(mode)) => Some(mode),
+        Some(Err(e)) => {
+            log_error!("invalid network config: {} — ignoring it", e);
+            None
+        }
+        None => None,
+    };
+    let dns = network.map(|n| n.dns.as_slice()).unwrap_or_default();
+    match mode {
+        Some(NetworkMode::Static {
+            ip,
+            prefix,
+            gateway,
+        }) => {
+            log_info!(
+                "static network config: ip={}/{} gw={:?}",
+                ip,
+                prefix,
+                gateway
+            );
+            if let Err(e) = configure_ipv4("eth0", ip, prefix, gateway) {
+                log_error!("failed to apply static network config: {}", e);
             }
+            fill_resolv_conf(dns);
+        }
+        Some(NetworkMode::Dhcp) => run_dhcp(dns),
+        Some(NetworkMode::Unconfigured) | None if has_kernel_ip_param() => {
+            // Kernel ip= parameter handles networking (Firecracker legacy path)
+            log_info!("kernel ip= parameter present — skipping DHCP");
+            fill_resolv_conf(dns);
+        }
+        Some(NetworkMode::Unconfigured) | None => {
+            // No address configured → use DHCP (macOS Virtualization.framework NAT)
+            log_info!("no VPC boot params or static address — attempting DHCP on eth0");
+            run_dhcp(dns);
         }
     }
 
     Ok(())
 }
 
+/// DHCP on eth0 and apply the lease. DNS servers from the config take
+/// precedence over the lease's.
+#[cfg(target_os = "linux")]
+fn run_dhcp(dns: &[String]) {
+    match crate::dhcp::do_dhcp("eth0") {
+        Ok(lease) => {
+            let gateway = lease.gateway.as_deref();
+            if let Err(e) = configure_ipv4("eth0", &lease.ip, lease.prefix_len, gateway) {
+                log_error!("failed to apply DHCP lease: {}", e);
+            }
+            if dns.is_empty() {
+                fill_resolv_conf(&lease.dns_servers);
+            } else {
+                fill_resolv_conf(dns);
+            }
+        }
+        Err(e) => {
+            log_error!("DHCP failed: {} — networking may be unavailable", e);
+            fill_resolv_conf(dns);
+        }
+    }
+}
+
 /// Check if the kernel ip= parameter was given on the cmdline.
 #[cfg(target_os = "linux")]
 fn has_kernel_ip_param() -> bool {
@@ -125,42 +178,70 @@ fn has_kernel_ip_param() -> bool {
         .unwrap_or(false)
 }
 
-/// Apply a DHCP lease: set IP + netmask, add default route, write /etc/resolv.conf.
+/// Set an IPv4 address + netmask on `iface` and add the default route.
 #[cfg(target_os = "linux")]
-fn apply_dhcp_lease(lease: &crate::dhcp::DhcpLease) -> Result<(), Box<dyn std::error::Error>> {
+fn configure_ipv4(
+    iface: &str,
+    ip: &str,
+    prefix_len: u32,
+    gateway: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let sock = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
     if sock < 0 {
         return Err("socket() failed".into());
     }
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        // 1. Set IPv4 address
+        set_ipv4_addr(sock, iface, ip)?;
+        log_info!("{}: IP set to {}", iface, ip);
+
+        // 2. Set netmask
+        let mask = if prefix_len == 0 {
+            0u32
+        } else {
+            !0u32 << (32 - prefix_len)
+        };
+        set_ipv4_netmask(sock, iface, mask)?;
+        log_info!("{}: netmask set to /{}", iface, prefix_len);
+
+        // 3. Add default route via gateway
+        if let Some(gw) = gateway {
+            add_default_route(sock, gw)?;
+            log_info!("{}: default route via {}", iface, gw);
+        }
+        Ok(())
+    })();
+    unsafe { libc::close(sock) };
+    result
+}
 
-    // 1. Set IPv4 address
-    set_ipv4_addr(sock, "eth0", &lease.ip)?;
-    log_info!("eth0: IP set to {}", lease.ip);
-
-    // 2. Set netmask
-    let mask = if lease.prefix_len == 0 {
-        0u32
+/// resolv.conf of the container: in initrd mode the entrypoint chroots into
+/// the virtiofs rootfs, so the initrd's own /etc would go unread.
+#[cfg(target_os = "linux")]
+fn resolv_conf_path() -> &'static str {
+    if crate::is_initrd_mode() {
+        "/mnt/rootfs/etc/resolv.conf"
     } else {
-        !0u32 << (32 - lease.prefix_len)
-    };
-    set_ipv4_netmask(sock, "eth0", mask)?;
-    log_info!("eth0: netmask set to /{}", lease.prefix_len);
-
-    // 3. Add default route via gateway
-    if let Some(ref gw) = lease.gateway {
-        add_default_route(sock, gw)?;
-        log_info!("eth0: default route via {}", gw);
+        "/etc/resolv.conf"
     }
+}
 
-    unsafe { libc::close(sock) };
-
-    // 4. Write /etc/resolv.conf
-    write_resolv_conf(&lease.dns_servers);
-
-    Ok(())
+/// Write resolv.conf unless the runtime already put one in the rootfs (the
+/// cluster DNS config) — same rule as the host backends. Explicit servers
+/// from the network config always win.
+#[cfg(target_os = "linux")]
+fn fill_resolv_conf(dns_servers: &[String]) {
+    let present = std::fs::symlink_metadata(resolv_conf_path())
+        .map(|meta| !meta.is_file() || meta.len() > 0)
+        .unwrap_or(false);
+    if present && dns_servers.is_empty() {
+        log_info!("keeping existing {}", resolv_conf_path());
+        return;
+    }
+    write_resolv_conf(dns_servers);
 }
 
-/// Write /etc/resolv.conf with the given DNS servers.
+/// Write resolv.conf with the given DNS servers.
 /// Falls back to 8.8.8.8 + 8.8.4.4 if no servers provided.
 #[cfg(target_os = "linux")]
 fn write_resolv_conf(dns_servers: &[String]) {
@@ -177,12 +258,16 @@ fn write_resolv_conf(dns_servers: &[String]) {
         .join("\n")
         + "\n";
 
-    match std::fs::write("/etc/resolv.conf", &content) {
+    let path = resolv_conf_path();
+    if let Some(dir) = Path::new(path).parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    match std::fs::write(path, &content) {
         Ok(_) => {
-            log_info!("wrote /etc/resolv.conf: {:?}", servers);
+            log_info!("wrote {}: {:?}", path, servers);
         }
         Err(e) => {
-            log_error!("failed to write /etc/resolv.conf: {}", e);
+            log_error!("failed to write {}: {}", path, e);
         }
     }
 }
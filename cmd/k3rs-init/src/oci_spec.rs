@@ -7,6 +7,66 @@ pub struct OciSpec {
     pub process: Option<OciProcess>,
     #[serde(default)]
     pub hostname: Option<String>,
+    /// Not part of the OCI spec: eth0 setup, written by the host backend.
+    #[serde(default)]
+    pub network: Option<NetworkConfig>,
+}
+
+/// Guest network configuration: `{"dhcp": true}` or a static
+/// `{"ip", "prefix", "gateway"}`, plus DNS servers for resolv.conf.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct NetworkConfig {
+    #[serde(default)]
+    pub dhcp: bool,
+    #[serde(default)]
+    pub ip: Option<String>,
+    #[serde(default)]
+    pub prefix: Option<u32>,
+    #[serde(default)]
+    pub gateway: Option<String>,
+    #[serde(default)]
+    pub dns: Vec<String>,
+}
+
+/// How eth0 gets its IPv4 address.
+#[derive(Debug, PartialEq, Eq)]
+pub enum NetworkMode<'a> {
+    Dhcp,
+    Static {
+        ip: &'a str,
+        prefix: u32,
+        gateway: Option<&'a str>,
+    },
+    /// Only DNS is configured.
+    Unconfigured,
+}
+
+impl NetworkConfig {
+    /// Validate the section. A static `prefix` defaults to 24, like a lease
+    /// without a subnet mask.
+    pub fn mode(&self) -> Result<NetworkMode<'_>, String> {
+        for addr in self.ip.iter().chain(&self.gateway).chain(&self.dns) {
+            if addr.parse::<std::net::Ipv4Addr>().is_err() {
+                return Err(format!("'{}' is not an IPv4 address", addr));
+            }
+        }
+        match (self.dhcp, self.ip.as_deref()) {
+            (true, Some(_)) => Err("both dhcp and a static ip are set".to_string()),
+            (true, None) => Ok(NetworkMode::Dhcp),
+            (false, Some(ip)) => {
+                let prefix = self.prefix.unwrap_or(24);
+                if prefix > 32 {
+                    return Err(format!("prefix /{} is out of range", prefix));
+                }
+                Ok(NetworkMode::Static {
+                    ip,
+                    prefix,
+                    gateway: self.gateway.as_deref(),
+                })
+            }
+            (false, None) => Ok(NetworkMode::Unconfigured),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -17,4 +77,142 @@ pub struct OciProcess {
     pub env: Vec<String>,
     #[serde(default)]
     pub cwd: Option<String>,
+    /// Identity to run the entrypoint as; root when absent.
+    #[serde(default)]
+    pub user: Option<OciUser>,
+    #[serde(default)]
+    pub rlimits: Vec<OciRlimit>,
+    #[serde(default, rename = "noNewPrivileges")]
+    pub no_new_privileges: bool,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct OciUser {
+    #[serde(default)]
+    pub uid: u32,
+    #[serde(default)]
+    pub gid: u32,
+    #[serde(default, rename = "additionalGids")]
+    pub additional_gids: Vec<u32>,
+}
+
+/// One `setrlimit` call, e.g. `{"type": "RLIMIT_NOFILE", "hard": 1024, "soft": 1024}`.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct OciRlimit {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub hard: u64,
+    pub soft: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_process_identity() {
+        let spec: OciSpec = serde_json::from_str(
+            r#"{
+                "ociVersion": "1.0.2",
+                "process": {
+                    "args": ["/app"],
+                    "cwd": "/srv",
+                    "user": { "uid": 1000, "gid": 2000, "additionalGids": [10, 20] },
+                    "rlimits": [{ "type": "RLIMIT_NOFILE", "hard": 4096, "soft": 1024 }],
+                    "noNewPrivileges": true,
+                    "capabilities": { "bounding": [] }
+                },
+                "hostname": "web-0"
+            }"#,
+        )
+        .unwrap();
+        let process = spec.process.unwrap();
+        assert_eq!(
+            process.user,
+            Some(OciUser {
+                uid: 1000,
+                gid: 2000,
+                additional_gids: vec![10, 20],
+            })
+        );
+        assert_eq!(
+            process.rlimits,
+            [OciRlimit {
+                kind: "RLIMIT_NOFILE".to_string(),
+                hard: 4096,
+                soft: 1024,
+            }]
+        );
+        assert!(process.no_new_privileges);
+    }
+
+    #[test]
+    fn test_parse_process_defaults() {
+        // Configs written before these fields existed still run as root.
+        let spec: OciSpec =
+            serde_json::from_str(r#"{"process": {"args": ["/bin/sh"], "env": [], "cwd": "/"}}"#)
+                .unwrap();
+        let process = spec.process.unwrap();
+        assert_eq!(process.user, None);
+        assert!(process.rlimits.is_empty());
+        assert!(!process.no_new_privileges);
+
+        let user: OciUser = serde_json::from_str(r#"{"uid": 65534}"#).unwrap();
+        assert_eq!(user.gid, 0);
+        assert!(user.additional_gids.is_empty());
+    }
+
+    #[test]
+    fn test_parse_network_modes() {
+        let parse = |json: &str| -> NetworkConfig {
+            let spec: OciSpec = serde_json::from_str(json).unwrap();
+            spec.network.unwrap()
+        };
+
+        let dhcp = parse(r#"{"network": {"dhcp": true}}"#);
+        assert_eq!(dhcp.mode(), Ok(NetworkMode::Dhcp));
+
+        let fixed = parse(
+            r#"{"network": {"ip": "10.0.2.15", "prefix": 24, "gateway": "10.0.2.2",
+                "dns": ["10.0.2.3", "1.1.1.1"]}}"#,
+        );
+        assert_eq!(
+            fixed.mode(),
+            Ok(NetworkMode::Static {
+                ip: "10.0.2.15",
+                prefix: 24,
+                gateway: Some("10.0.2.2"),
+            })
+        );
+        assert_eq!(fixed.dns, ["10.0.2.3", "1.1.1.1"]);
+
+        let dns_only = parse(r#"{"network": {"dns": ["10.0.2.3"]}}"#);
+        assert_eq!(dns_only.mode(), Ok(NetworkMode::Unconfigured));
+
+        let no_prefix = parse(r#"{"network": {"ip": "10.0.2.15"}}"#);
+        assert!(matches!(
+            no_prefix.mode(),
+            Ok(NetworkMode::Static { prefix: 24, .. })
+        ));
+
+        let spec: OciSpec = serde_json::from_str(r#"{"process": {"args": []}}"#).unwrap();
+        assert_eq!(spec.network, None);
+    }
+
+    #[test]
+    fn test_invalid_network_config() {
+        let config = |ip: Option<&str>, prefix, dhcp, dns: &[&str]| NetworkConfig {
+            dhcp,
+            ip: ip.map(str::to_string),
+            prefix,
+            gateway: None,
+            dns: dns.iter().map(|s| s.to_string()).collect(),
+        };
+        assert!(config(Some("10.0.2.15"), None, true, &[]).mode().is_err());
+        assert!(config(Some("10.0.2"), None, false, &[]).mode().is_err());
+        assert!(config(Some("10.0.2.15"), Some(33), false, &[])
+            .mode()
+            .is_err());
+        assert!(config(None, None, true, &["fd00::1"]).mode().is_err());
+    }
 }
@@ -0,0 +1,225 @@
+use crate::oci_spec::OciProcess;
+
+/// The identity and limits the entrypoint runs with, taken from the OCI
+/// `process`. Resolved before the fork, so applying them in the child is
+/// syscalls only — nothing may allocate between fork and exec while the
+/// vsock listener thread runs.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Privileges {
+    /// `(uid, gid, additional gids)`; `None` keeps root.
+    user: Option<(u32, u32, Vec<u32>)>,
+    /// `(resource, soft, hard)`.
+    rlimits: Vec<(i32, u64, u64)>,
+    no_new_privs: bool,
+}
+
+impl Privileges {
+    /// An rlimit type this guest does not know is an error: the entrypoint
+    /// must not start with a limit silently missing.
+    pub fn from_process(process: &OciProcess) -> Result<Self, String> {
+        let rlimits = process
+            .rlimits
+            .iter()
+            .map(|r| {
+                let resource = rlimit_resource(&r.kind)
+                    .ok_or_else(|| format!("unknown rlimit type '{}'", r.kind))?;
+                if r.soft > r.hard {
+                    return Err(format!(
+                        "{} soft limit {} is above its hard limit {}",
+                        r.kind, r.soft, r.hard
+                    ));
+                }
+                Ok((resource, r.soft, r.hard))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            user: process
+                .user
+                .as_ref()
+                .map(|u| (u.uid, u.gid, u.additional_gids.clone())),
+            rlimits,
+            no_new_privs: process.no_new_privileges,
+        })
+    }
+
+    /// For the console log, e.g. `uid=1000 gid=1000 groups=[10]`.
+    pub fn describe(&self) -> String {
+        let mut out = match &self.user {
+            Some((uid, gid, groups)) => format!("uid={} gid={} groups={:?}", uid, gid, groups),
+            None => "uid=0 gid=0".to_string(),
+        };
+        if self.no_new_privs {
+            out.push_str(" no_new_privs");
+        }
+        out
+    }
+
+    /// Apply in the child, before exec: limits while still privileged
+    /// (raising a hard limit needs it), then supplementary groups, gid and
+    /// uid in that order, then `no_new_privs`. Any failure aborts the exec.
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self) -> std::io::Result<()> {
+        for &(resource, soft, hard) in &self.rlimits {
+            let limit = libc::rlimit {
+                rlim_cur: soft as libc::rlim_t,
+                rlim_max: hard as libc::rlim_t,
+            };
+            if unsafe { libc::setrlimit(resource as _, &limit) } != 0 {
+                return Err(child_error(b"setrlimit"));
+            }
+        }
+        if let Some((uid, gid, groups)) = &self.user {
+            let groups: &[libc::gid_t] = groups;
+            if unsafe { libc::setgroups(groups.len() as _, groups.as_ptr()) } != 0 {
+                return Err(child_error(b"setgroups"));
+            }
+            if unsafe { libc::setgid(*gid) } != 0 {
+                return Err(child_error(b"setgid"));
+            }
+            if unsafe { libc::setuid(*uid) } != 0 {
+                return Err(child_error(b"setuid"));
+            }
+        }
+        if self.no_new_privs && unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+            return Err(child_error(b"prctl(PR_SET_NO_NEW_PRIVS)"));
+        }
+        Ok(())
+    }
+}
+
+/// Log which step failed from the forked child, without allocating, and
+/// return its errno for `spawn` to report.
+#[cfg(target_os = "linux")]
+fn child_error(step: &[u8]) -> std::io::Error {
+    let err = std::io::Error::last_os_error();
+    let parts: [&[u8]; 3] = [b"[k3rs-init] ERROR: ", step, b" failed before exec\n"];
+    for part in parts {
+        unsafe { libc::write(libc::STDERR_FILENO, part.as_ptr().cast(), part.len()) };
+    }
+    err
+}
+
+#[cfg(target_os = "linux")]
+fn rlimit_resource(kind: &str) -> Option<i32> {
+    let resource = match kind {
+        "RLIMIT_AS" => libc::RLIMIT_AS,
+        "RLIMIT_CORE" => libc::RLIMIT_CORE,
+        "RLIMIT_CPU" => libc::RLIMIT_CPU,
+        "RLIMIT_DATA" => libc::RLIMIT_DATA,
+        "RLIMIT_FSIZE" => libc::RLIMIT_FSIZE,
+        "RLIMIT_LOCKS" => libc::RLIMIT_LOCKS,
+        "RLIMIT_MEMLOCK" => libc::RLIMIT_MEMLOCK,
+        "RLIMIT_MSGQUEUE" => libc::RLIMIT_MSGQUEUE,
+        "RLIMIT_NICE" => libc::RLIMIT_NICE,
+        "RLIMIT_NOFILE" => libc::RLIMIT_NOFILE,
+        "RLIMIT_NPROC" => libc::RLIMIT_NPROC,
+        "RLIMIT_RSS" => libc::RLIMIT_RSS,
+        "RLIMIT_RTPRIO" => libc::RLIMIT_RTPRIO,
+        "RLIMIT_RTTIME" => libc::RLIMIT_RTTIME,
+        "RLIMIT_SIGPENDING" => libc::RLIMIT_SIGPENDING,
+        "RLIMIT_STACK" => libc::RLIMIT_STACK,
+        _ => return None,
+    };
+    Some(resource as i32)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn rlimit_resource(_kind: &str) -> Option<i32> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::oci_spec::{OciRlimit, OciUser};
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+
+    fn process(
+        user: Option<OciUser>,
+        rlimits: Vec<OciRlimit>,
+        no_new_privileges: bool,
+    ) -> OciProcess {
+        OciProcess {
+            args: Vec::new(),
+            env: Vec::new(),
+            cwd: None,
+            user,
+            rlimits,
+            no_new_privileges,
+        }
+    }
+
+    fn nofile(soft: u64, hard: u64) -> OciRlimit {
+        OciRlimit {
+            kind: "RLIMIT_NOFILE".to_string(),
+            hard,
+            soft,
+        }
+    }
+
+    #[test]
+    fn test_invalid_rlimits_are_refused() {
+        let mut bad = nofile(1, 1);
+        bad.kind = "RLIMIT_BOGUS".to_string();
+        let err = Privileges::from_process(&process(None, vec![bad], false)).unwrap_err();
+        assert!(err.contains("RLIMIT_BOGUS"));
+        assert!(Privileges::from_process(&process(None, vec![nofile(10, 5)], false)).is_err());
+        assert_eq!(
+            Privileges::from_process(&process(None, Vec::new(), false)).unwrap(),
+            Privileges::default()
+        );
+    }
+
+    /// Runs `id` with the privileges dropped; needs root to change uid.
+    #[test]
+    fn test_privileges_are_dropped_before_exec() {
+        if unsafe { libc::geteuid() } != 0 {
+            eprintln!("skipping: needs root");
+            return;
+        }
+        let user = OciUser {
+            uid: 65534,
+            gid: 65533,
+            additional_gids: vec![65532],
+        };
+        let privileges =
+            Privileges::from_process(&process(Some(user), vec![nofile(256, 512)], true)).unwrap();
+        let output = unsafe {
+            Command::new("sh")
+                .args(["-c", "id; ulimit -n; grep NoNewPrivs /proc/self/status"])
+                .pre_exec(move || privileges.apply())
+                .output()
+                .unwrap()
+        };
+        let out = String::from_utf8_lossy(&output.stdout);
+        let mut lines = out.lines();
+        let id = lines.next().unwrap();
+        assert!(id.starts_with("uid=65534"), "{}", id);
+        assert!(id.contains("gid=65533"), "{}", id);
+        assert!(id.contains(",65532"), "{}", id);
+        assert_eq!(lines.next(), Some("256"));
+        assert!(lines.next().unwrap().ends_with('1'));
+
+        // Fails closed: the child never execs as root.
+        let user = OciUser {
+            uid: 65534,
+            gid: 65534,
+            additional_gids: Vec::new(),
+        };
+        let privileges = Privileges::from_process(&process(Some(user), Vec::new(), false)).unwrap();
+        let spawned = unsafe {
+            Command::new("true")
+                .pre_exec(move || {
+                    privileges.apply()?;
+                    // Back to root must not work once dropped.
+                    if libc::setuid(0) == 0 {
+                        return Err(std::io::Error::other("still root"));
+                    }
+                    Ok(())
+                })
+                .status()
+        };
+        assert!(spawned.unwrap().success());
+    }
+}
@@ -9,6 +9,12 @@ use crate::VSOCK_EXEC_PORT;
 /// Byte prefix that switches vsock exec into streaming PTY mode (must match k3rs-vmm).
 const STREAM_PREFIX: u8 = pkg_constants::vm::VSOCK_STREAM_PREFIX;
 
+/// Byte prefix that turns the connection into a TCP forward (must match the host).
+const FORWARD_PREFIX: u8 = pkg_constants::vm::VSOCK_FORWARD_PREFIX;
+
+/// Byte prefix that runs the command with stdin framed by the host.
+const STDIN_PREFIX: u8 = pkg_constants::vm::VSOCK_STDIN_PREFIX;
+
 /// Start a vsock listener for exec commands from the host (k3rs-vmm).
 ///
 /// Listens on VSOCK_EXEC_PORT (5555) and for each connection:
@@ -26,12 +32,9 @@ pub fn start_vsock_listener() {
     log_info!("vsock exec listener started on port {}", VSOCK_EXEC_PORT);
 }
 
-/// Main vsock listener loop.
+/// Create a vsock socket listening on `port` for connections from the host.
 #[cfg(target_os = "linux")]
-fn vsock_listener_loop() -> Result<(), Box<dyn std::error::Error>> {
-    #[allow(unused_imports)]
-    use std::io::{Read, Write};
-
+pub(crate) fn vsock_listen(port: u32) -> Result<i32, Box<dyn std::error::Error>> {
     // Create vsock socket
     // AF_VSOCK = 40, SOCK_STREAM = 1
     let sock = unsafe { libc::socket(40, libc::SOCK_STREAM, 0) };
@@ -39,11 +42,11 @@ fn vsock_listener_loop() -> Result<(), Box<dyn std::error::Error>> {
         return Err("failed to create vsock socket".into());
     }
 
-    // Bind to VMADDR_CID_ANY (u32::MAX = -1) on our exec port
+    // Bind to VMADDR_CID_ANY (u32::MAX = -1) on the port
     // struct sockaddr_vm { sa_family, reserved, port, cid }
     let mut addr: libc::sockaddr_vm = unsafe { std::mem::zeroed() };
     addr.svm_family = 40; // AF_VSOCK
-    addr.svm_port = VSOCK_EXEC_PORT;
+    addr.svm_port = port;
     addr.svm_cid = libc::VMADDR_CID_ANY;
 
     let ret = unsafe {
@@ -65,7 +68,14 @@ fn vsock_listener_loop() -> Result<(), Box<dyn std::error::Error>> {
         return Err("vsock listen failed".into());
     }
 
-    log_info!("vsock listening on port {}", VSOCK_EXEC_PORT);
+    log_info!("vsock listening on port {}", port);
+    Ok(sock)
+}
+
+/// Main vsock listener loop.
+#[cfg(target_os = "linux")]
+fn vsock_listener_loop() -> Result<(), Box<dyn std::error::Error>> {
+    let sock = vsock_listen(VSOCK_EXEC_PORT)?;
 
     loop {
         let mut client_addr: libc::sockaddr_vm = unsafe { std::mem::zeroed() };
@@ -93,6 +103,8 @@ fn vsock_listener_loop() -> Result<(), Box<dyn std::error::Error>> {
 ///
 /// Protocol detection (first byte):
 /// - `\x01` → streaming PTY mode: create PTY, spawn command, bridge PTY ↔ vsock
+/// - `\x02` → port forward: connect to a guest TCP port, bridge it ↔ vsock
+/// - `\x03` → stdin mode: spawn command on pipes, feed it framed host stdin
 /// - anything else → one-shot mode: run command, collect output, write, close
 #[cfg(target_os = "linux")]
 fn handle_vsock_exec(fd: i32) {
@@ -104,12 +116,18 @@ fn handle_vsock_exec(fd: i32) {
         return;
     }
 
+    if first[0] == FORWARD_PREFIX {
+        handle_vsock_forward(fd);
+        return;
+    }
+
     let streaming = first[0] == STREAM_PREFIX;
+    let with_stdin = first[0] == STDIN_PREFIX;
 
-    // Read command: if streaming, everything until '\n'; if one-shot, same but
+    // Read command: if prefixed, everything until '\n'; if one-shot, same but
     // prepend the first byte (it's the first char of the command).
     let mut cmd_buf = Vec::new();
-    if !streaming {
+    if !streaming && !with_stdin {
         cmd_buf.push(first[0]);
     }
     let mut b = [0u8; 1];
@@ -137,6 +155,9 @@ fn handle_vsock_exec(fd: i32) {
     if streaming {
         log_info!("vsock PTY exec: {:?}", args);
         handle_vsock_pty_exec(fd, &args);
+    } else if with_stdin {
+        log_info!("vsock stdin exec: {:?}", args);
+        handle_vsock_stdin_exec(fd, &args);
     } else {
         log_info!("vsock exec: {:?}", args);
 
@@ -183,17 +204,55 @@ fn handle_vsock_exec(fd: i32) {
                         log_info!("vsock write stderr: n={}", n);
                     }
                 }
+                write_exit_trailer(fd, exit_code(out.status));
             }
             Err(e) => {
                 log_error!("exec failed: {}", e);
                 let msg = format!("exec error: {}\n", e);
                 unsafe { libc::write(fd, msg.as_ptr() as *const libc::c_void, msg.len()) };
+                write_exit_trailer(fd, spawn_error_code(&e));
             }
         }
         unsafe { libc::close(fd) };
     }
 }
 
+/// Exit code of a finished command, `128 + signal` if it was killed.
+#[cfg(any(target_os = "linux", test))]
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .or_else(|| status.signal().map(|sig| 128 + sig))
+        .unwrap_or(1)
+}
+
+/// `\x02exit:<code>\n`, sent after a command's output so the host can
+/// report its exit status.
+#[cfg(any(target_os = "linux", test))]
+fn exit_trailer(code: i32) -> Vec<u8> {
+    let mut trailer = pkg_constants::vm::VSOCK_EXIT_TRAILER.to_vec();
+    trailer.extend_from_slice(format!("{}\n", code).as_bytes());
+    trailer
+}
+
+/// Exit code for a command that could not be started, as a shell reports
+/// one it cannot find or run.
+#[cfg(target_os = "linux")]
+fn spawn_error_code(e: &std::io::Error) -> i32 {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        127
+    } else {
+        126
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn write_exit_trailer(fd: i32, code: i32) {
+    let trailer = exit_trailer(code);
+    unsafe { libc::write(fd, trailer.as_ptr() as *const libc::c_void, trailer.len()) };
+}
+
 /// Chroot into the container rootfs if it is mounted at /mnt/rootfs.
 /// Called via pre_exec in spawned command children.
 #[cfg(target_os = "linux")]
@@ -329,9 +388,15 @@ fn handle_vsock_pty_exec(vsock_fd: i32, args: &[&str]) {
             }
         }
         unsafe { libc::close(master_read_fd) };
-        unsafe { libc::close(vsock_write_fd) };
     });
 
+    // The child called setsid(), so its process group ID is its PID.
+    let child_pgid = child.id() as libc::pid_t;
+    // Set once the session is over on our side, so thread B can tell our own
+    // shutdown from the host going away.
+    let finished = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let finished_b = finished.clone();
+
     // Thread B: vsock → PTY master  (host input → shell)
     let t_vsock_to_pty = std::thread::spawn(move || {
         let mut buf = [0u8; 1024];
@@ -366,10 +431,251 @@ fn handle_vsock_pty_exec(vsock_fd: i32, args: &[&str]) {
         }
         unsafe { libc::close(vsock_read_fd) };
         unsafe { libc::close(master_write_fd) };
+        // The host is gone (client exited or crashed): hang up the session the
+        // way a closed terminal would, or child.wait() below never returns.
+        if !finished_b.load(std::sync::atomic::Ordering::Acquire) {
+            unsafe { libc::kill(-child_pgid, libc::SIGHUP) };
+        }
     });
 
-    // Wait for child to exit, then join threads.
-    let _ = child.wait();
+    // Wait for child to exit and its output to drain, then report its status
+    // after the output and close the connection, which also ends thread B.
+    let code = child.wait().map(exit_code).unwrap_or(1);
     let _ = t_pty_to_vsock.join();
+    finished.store(true, std::sync::atomic::Ordering::Release);
+    write_exit_trailer(vsock_write_fd, code);
+    unsafe { libc::shutdown(vsock_write_fd, libc::SHUT_RDWR) };
+    unsafe { libc::close(vsock_write_fd) };
     let _ = t_vsock_to_pty.join();
 }
+
+/// Run `args` on pipes, feeding it the stdin the host sends as frames on
+/// vsock `fd` and streaming its output back as it comes.
+///
+/// Stdout and stderr share one pipe, so their writes arrive in order. The
+/// EOF frame closes the command's stdin while the connection stays up for
+/// its output, which is followed by the exit trailer. If the host goes away
+/// first, the command is killed.
+#[cfg(target_os = "linux")]
+fn handle_vsock_stdin_exec(fd: i32, args: &[&str]) {
+    use std::os::unix::io::FromRawFd;
+    use std::process::Stdio;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let mut vsock = unsafe { std::fs::File::from_raw_fd(fd) };
+    let Ok(mut input) = vsock.try_clone() else {
+        return;
+    };
+
+    let spawned = std::io::pipe().and_then(|(output, stdout)| {
+        let stderr = stdout.try_clone()?;
+        let child = unsafe {
+            Command::new(args[0])
+                .args(&args[1..])
+                .env(
+                    "PATH",
+                    "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin",
+                )
+                .stdin(Stdio::piped())
+                .stdout(stdout)
+                .stderr(stderr)
+                .pre_exec(|| {
+                    if is_initrd_mode() {
+                        chroot_into_rootfs()
+                    } else {
+                        Ok(())
+                    }
+                })
+                .spawn()?
+        };
+        Ok((child, output))
+    });
+    let (mut child, mut output) = match spawned {
+        Ok(spawned) => spawned,
+        Err(e) => {
+            log_error!("exec failed: {}", e);
+            let _ = writeln!(vsock, "exec error: {}", e);
+            write_exit_trailer(fd, spawn_error_code(&e));
+            return;
+        }
+    };
+
+    let pid = child.id() as libc::pid_t;
+    let finished = Arc::new(AtomicBool::new(false));
+    let finished_feeder = finished.clone();
+    let mut stdin = child.stdin.take();
+
+    // Host stdin → command. Frames after the command stopped reading are
+    // dropped; reading goes on to notice the host hanging up.
+    let feeder = std::thread::spawn(move || {
+        loop {
+            match read_stdin_frame(&mut input) {
+                Ok(Some(frame)) => {
+                    if let Some(pipe) = stdin.as_mut() {
+                        if pipe.write_all(&frame).is_err() {
+                            stdin = None;
+                        }
+                    }
+                }
+                Ok(None) => stdin = None,
+                Err(_) => break,
+            }
+        }
+        if !finished_feeder.load(Ordering::Acquire) {
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+        }
+    });
+
+    // Command output → host, until both stdout and stderr are closed.
+    let _ = std::io::copy(&mut output, &mut vsock);
+    drop(output);
+    let code = child.wait().map(exit_code).unwrap_or(1);
+    finished.store(true, Ordering::Release);
+    write_exit_trailer(fd, code);
+    unsafe { libc::shutdown(fd, libc::SHUT_RDWR) };
+    let _ = feeder.join();
+}
+
+/// Read one stdin frame: its bytes, or `None` for the EOF frame.
+#[cfg(any(target_os = "linux", test))]
+fn read_stdin_frame(r: &mut impl std::io::Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 {
+        return Ok(None);
+    }
+    if len > pkg_constants::vm::VSOCK_STDIN_MAX_FRAME {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("stdin frame of {} bytes", len),
+        ));
+    }
+    let mut frame = vec![0u8; len];
+    r.read_exact(&mut frame)?;
+    Ok(Some(frame))
+}
+
+/// Connect to the guest TCP port named on the next line of vsock `fd` and
+/// relay bytes both ways until either side closes.
+///
+/// The host gets `OK\n` once the connection is up, or `ERR <reason>\n`
+/// before the vsock connection is closed.
+#[cfg(target_os = "linux")]
+fn handle_vsock_forward(fd: i32) {
+    use std::io::Read;
+    use std::net::{Shutdown, TcpStream};
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+
+    let mut vsock = unsafe { std::fs::File::from_raw_fd(fd) };
+    let mut line = Vec::new();
+    let mut b = [0u8; 1];
+    while line.len() < 8 {
+        match vsock.read(&mut b) {
+            Ok(1) if b[0] == b'\n' => break,
+            Ok(1) => line.push(b[0]),
+            _ => return,
+        }
+    }
+    let port: Option<u16> = String::from_utf8_lossy(&line).trim().parse().ok();
+    let tcp = match port.map(|p| TcpStream::connect(("127.0.0.1", p))) {
+        Some(Ok(tcp)) => tcp,
+        Some(Err(e)) => {
+            let _ = writeln!(vsock, "ERR {}", e);
+            return;
+        }
+        None => {
+            let _ = writeln!(
+                vsock,
+                "ERR invalid port {:?}",
+                String::from_utf8_lossy(&line)
+            );
+            return;
+        }
+    };
+    log_info!("vsock forward to 127.0.0.1:{}", port.unwrap_or_default());
+    if vsock.write_all(b"OK\n").is_err() {
+        return;
+    }
+
+    let (Ok(mut tcp_read), Ok(mut vsock_write)) = (tcp.try_clone(), vsock.try_clone()) else {
+        return;
+    };
+    // Guest port → host; on EOF, pass the half-close on to the host.
+    let upstream = std::thread::spawn(move || {
+        let _ = std::io::copy(&mut tcp_read, &mut vsock_write);
+        unsafe { libc::shutdown(vsock_write.as_raw_fd(), libc::SHUT_WR) };
+    });
+    let mut tcp_write = tcp;
+    let _ = std::io::copy(&mut vsock, &mut tcp_write);
+    // The host hung up; stop the other direction too.
+    let _ = tcp_write.shutdown(Shutdown::Both);
+    let _ = upstream.join();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    #[test]
+    fn test_exit_trailer() {
+        assert_eq!(exit_trailer(0), b"\x02exit:0\n");
+        assert_eq!(
+            exit_trailer(exit_code(ExitStatus::from_raw(3 << 8))),
+            b"\x02exit:3\n"
+        );
+        // Killed by SIGKILL.
+        assert_eq!(exit_code(ExitStatus::from_raw(9)), 137);
+    }
+
+    #[test]
+    fn test_read_stdin_frames() {
+        let mut stream: &[u8] = b"\0\0\0\x05hello\0\0\0\x01\n\0\0\0\0";
+        assert_eq!(
+            read_stdin_frame(&mut stream).unwrap(),
+            Some(b"hello".to_vec())
+        );
+        assert_eq!(read_stdin_frame(&mut stream).unwrap(), Some(b"\n".to_vec()));
+        assert_eq!(read_stdin_frame(&mut stream).unwrap(), None);
+        // The host hung up without sending the EOF frame.
+        assert!(read_stdin_frame(&mut stream).is_err());
+
+        let mut truncated: &[u8] = b"\0\0\0\x05hel";
+        assert!(read_stdin_frame(&mut truncated).is_err());
+        let mut oversized: &[u8] = b"\xff\xff\xff\xff";
+        assert_eq!(
+            read_stdin_frame(&mut oversized).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stdin_exec_sees_eof() {
+        use std::io::Read;
+        use std::os::unix::io::IntoRawFd;
+        use std::os::unix::net::UnixStream;
+
+        let (mut host, guest) = UnixStream::pair().unwrap();
+        let guest = guest.into_raw_fd();
+        let session = std::thread::spawn(move || handle_vsock_stdin_exec(guest, &["wc", "-c"]));
+
+        let data = vec![b'x'; 100_000];
+        for chunk in data.chunks(4096) {
+            host.write_all(&(chunk.len() as u32).to_be_bytes()).unwrap();
+            host.write_all(chunk).unwrap();
+        }
+        host.write_all(&[0; 4]).unwrap();
+
+        let mut output = Vec::new();
+        host.read_to_end(&mut output).unwrap();
+        session.join().unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap().trim_start(),
+            "100000\n\x02exit:0\n"
+        );
+    }
+}
@@ -1,8 +1,23 @@
 use clap::Parser;
+use pkg_api::handlers::register::AddressConflictPolicy;
+use pkg_api::node_ports::NodePortRange;
+use pkg_api::pod_cidrs::ClusterCidr;
 use pkg_api::server::{ServerConfig, start_server};
-use pkg_types::config::{ServerConfigFile, load_config_file};
+use pkg_metrics::log_filter::{LogFilter, LogFilterLayer};
+use pkg_scheduler::{Scheduler, SchedulerConfig, ScoringStrategy};
+use pkg_state::backend::{BackendConfig, EtcdConfig, ObjectStoreConfig, etcd, slatedb};
+use pkg_types::config::{
+    ConfigFileWatcher, EtcdConfigFile, ObjectStoreConfigFile, ServerConfigFile,
+};
 use std::net::SocketAddr;
-use tracing::info;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Config file keys applied when the file changes; the rest need a restart.
+const LIVE_CONFIG_KEYS: &[&str] = &["log-level", "scheduler-strategy"];
 
 #[derive(Parser, Debug)]
 #[command(name = "k3rs-server", about = "k3rs control plane server")]
@@ -20,18 +35,70 @@ struct Cli {
     #[arg(long)]
     port: Option<u16>,
 
-    /// Directory for SlateDB state storage
+    /// Directory for SlateDB state storage (unless the config file names an
+    /// object-store bucket)
     #[arg(long)]
     data_dir: Option<String>,
 
+    /// Where cluster state is kept: slatedb (default, under --data-dir) or etcd
+    #[arg(long)]
+    state_store: Option<String>,
+
+    /// etcd client URLs for --state-store etcd, comma-separated
+    #[arg(long, value_delimiter = ',')]
+    etcd_endpoints: Vec<String>,
+
+    /// etcd user, when etcd has authentication enabled (the password comes
+    /// from the config file or K3RS_ETCD_PASSWORD)
+    #[arg(long)]
+    etcd_username: Option<String>,
+
+    /// Key file Secret data is encrypted with at rest (generated if missing;
+    /// K3RS_SECRETS_ENCRYPTION_KEY takes precedence)
+    #[arg(long)]
+    secrets_key_file: Option<String>,
+
     /// Join token for agent registration
     #[arg(long)]
     token: Option<String>,
 
+    /// Bootstrap admin token for k3rsctl and the UI
+    #[arg(long)]
+    admin_token: Option<String>,
+
     /// Name for this master/control-plane node
     #[arg(long)]
     node_name: Option<String>,
 
+    /// Scheduler node scoring strategy: round-robin, least-allocated, most-allocated
+    #[arg(long)]
+    scheduler_strategy: Option<String>,
+
+    /// Port range for NodePort services, as start-end (default 30000-32767)
+    #[arg(long)]
+    service_node_port_range: Option<String>,
+
+    /// What to do when a registered node name registers again from another
+    /// address: reject (default) or takeover
+    #[arg(long)]
+    node_address_conflict: Option<String>,
+
+    /// IPv4 range node pod CIDRs are assigned from (default 10.42.0.0/16)
+    #[arg(long)]
+    cluster_cidr: Option<String>,
+
+    /// Prefix length of the pod CIDR each node gets (default 24)
+    #[arg(long)]
+    node_cidr_mask_size: Option<u8>,
+
+    /// Seconds without a heartbeat before a node is marked NotReady (default 30)
+    #[arg(long)]
+    node_not_ready_grace_period: Option<u64>,
+
+    /// Seconds without a heartbeat before a node's pods are evicted (default 300)
+    #[arg(long)]
+    pod_eviction_timeout: Option<u64>,
+
     /// Log format: 'text' or 'json'
     #[arg(long, default_value = "text")]
     log_format: String,
@@ -55,24 +122,36 @@ struct Cli {
     /// Number of backup files to keep
     #[arg(long, default_value_t = pkg_constants::timings::DEFAULT_BACKUP_RETENTION)]
     backup_retention: usize,
+
+    /// Number of Failed/Succeeded pods to keep per ReplicaSet
+    #[arg(long, default_value_t = pkg_constants::timings::DEFAULT_FAILED_POD_RETENTION)]
+    failed_pod_retention: usize,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    // Initialize logging based on format, optionally with OpenTelemetry
-    if cli.enable_otel {
-        init_tracing_with_otel(&cli.log_format, &cli.otel_endpoint)?;
-    } else {
-        init_tracing(&cli.log_format);
-    }
-
     // Load config file (returns defaults if file not found)
-    let file_cfg: ServerConfigFile = load_config_file(&cli.config)?;
+    let config_watcher =
+        ConfigFileWatcher::<ServerConfigFile>::open(&cli.config, LIVE_CONFIG_KEYS)?;
+    let file_cfg = config_watcher.current().clone();
+
+    // Initialize logging based on format, optionally with OpenTelemetry
+    let (log_filter_layer, log_filter) =
+        LogFilter::new(file_cfg.log_level.as_deref(), &[]).map_err(anyhow::Error::msg)?;
+    let log_filter = Arc::new(log_filter);
+    init_tracing(
+        &cli.log_format,
+        log_filter_layer,
+        cli.enable_otel.then_some(cli.otel_endpoint.as_str()),
+    )?;
     info!("Config file: {}", cli.config);
 
     // Merge: CLI args > config file > defaults
+    let scheduler = Arc::new(Scheduler::new_with_config(SchedulerConfig {
+        strategy: scheduler_strategy(cli.scheduler_strategy.as_deref(), &file_cfg)?,
+    }));
     let port = cli
         .port
         .or(file_cfg.port)
@@ -81,19 +160,90 @@ async fn main() -> anyhow::Result<()> {
         .data_dir
         .or(file_cfg.data_dir)
         .unwrap_or_else(|| format!("{}/server", pkg_constants::paths::DATA_DIR));
+    let state_store = match cli
+        .state_store
+        .or(file_cfg.state_store)
+        .as_deref()
+        .unwrap_or("slatedb")
+    {
+        "slatedb" => BackendConfig::SlateDb {
+            path: data_dir.clone(),
+            object_store: file_cfg.object_store.map(object_store_config).transpose()?,
+        },
+        "etcd" => BackendConfig::Etcd(etcd_config(
+            cli.etcd_endpoints,
+            cli.etcd_username,
+            file_cfg.etcd.unwrap_or_default(),
+        )?),
+        other => anyhow::bail!("unknown state store '{}' (expected slatedb or etcd)", other),
+    };
+    let secrets_key_file = cli
+        .secrets_key_file
+        .or(file_cfg.secrets_key_file)
+        .unwrap_or_else(|| {
+            format!(
+                "{}/secrets-encryption.key",
+                pkg_constants::paths::CONFIG_DIR
+            )
+        });
     let token = cli
         .token
         .or(file_cfg.token)
         .unwrap_or_else(|| pkg_constants::auth::DEFAULT_JOIN_TOKEN.to_string());
+    let admin_token = cli
+        .admin_token
+        .or(file_cfg.admin_token)
+        .unwrap_or_else(|| pkg_constants::auth::DEFAULT_ADMIN_TOKEN.to_string());
     let node_name = cli
         .node_name
         .or(file_cfg.node_name)
         .unwrap_or_else(hostname);
+    let node_port_range = match cli
+        .service_node_port_range
+        .or(file_cfg.service_node_port_range)
+    {
+        Some(range) => range.parse::<NodePortRange>().map_err(anyhow::Error::msg)?,
+        None => NodePortRange::default(),
+    };
+    let address_conflict = match cli.node_address_conflict.or(file_cfg.node_address_conflict) {
+        Some(policy) => policy
+            .parse::<AddressConflictPolicy>()
+            .map_err(anyhow::Error::msg)?,
+        None => AddressConflictPolicy::default(),
+    };
+    let cluster_cidr = ClusterCidr::new(
+        cli.cluster_cidr
+            .or(file_cfg.cluster_cidr)
+            .as_deref()
+            .unwrap_or(pkg_constants::network::DEFAULT_POD_CIDR),
+        cli.node_cidr_mask_size
+            .or(file_cfg.node_cidr_mask_size)
+            .unwrap_or(pkg_constants::network::DEFAULT_NODE_CIDR_MASK_SIZE),
+    )
+    .map_err(anyhow::Error::msg)?;
+    let node_not_ready_grace = Duration::from_secs(
+        cli.node_not_ready_grace_period
+            .or(file_cfg.node_not_ready_grace_period)
+            .unwrap_or(pkg_constants::runtime::DEFAULT_NODE_NOT_READY_GRACE_SECS),
+    );
+    let pod_eviction_timeout = Duration::from_secs(
+        cli.pod_eviction_timeout
+            .or(file_cfg.pod_eviction_timeout)
+            .unwrap_or(pkg_constants::runtime::DEFAULT_POD_EVICTION_TIMEOUT_SECS),
+    );
 
     info!("Starting k3rs-server");
     info!("  Node:      {}", node_name);
     info!("  Port:      {}", port);
     info!("  Data dir:  {}", data_dir);
+    match &state_store {
+        BackendConfig::Etcd(etcd) => info!("  State:     etcd ({})", etcd.endpoints.join(", ")),
+        BackendConfig::SlateDb {
+            object_store: Some(bucket),
+            ..
+        } => info!("  State:     SlateDB ({})", bucket.location()),
+        BackendConfig::SlateDb { .. } => {}
+    }
     info!("  Token:     {}***", &token[..token.len().min(4)]);
     if cli.enable_otel {
         info!("  OTel:      {} (enabled)", cli.otel_endpoint);
@@ -101,83 +251,233 @@ async fn main() -> anyhow::Result<()> {
 
     let config = ServerConfig {
         addr: SocketAddr::from(([0, 0, 0, 0], port)),
-        data_dir,
+        state_store,
+        secrets_key_file,
         join_token: token,
+        admin_token,
         node_name: node_name.clone(),
         server_id: node_name,
         backup_dir: cli.backup_dir,
         backup_interval_secs: cli.backup_interval_secs,
         backup_retention: cli.backup_retention,
+        log_filter: log_filter.clone(),
+        scheduler: scheduler.clone(),
+        failed_pod_retention: cli.failed_pod_retention,
+        node_port_range,
+        address_conflict,
+        cluster_cidr,
+        node_not_ready_grace,
+        pod_eviction_timeout,
     };
 
+    watch_config(
+        config_watcher,
+        cli.config,
+        cli.scheduler_strategy,
+        scheduler,
+        log_filter,
+    );
     start_server(config).await?;
 
     Ok(())
 }
 
-/// Standard tracing initialization (text or json).
-fn init_tracing(log_format: &str) {
-    match log_format {
-        "json" => {
-            tracing_subscriber::fmt()
-                .json()
-                .with_env_filter(
-                    tracing_subscriber::EnvFilter::from_default_env()
-                        .add_directive(tracing::level_filters::LevelFilter::INFO.into()),
-                )
-                .init();
-        }
-        _ => {
-            tracing_subscriber::fmt()
-                .with_env_filter(
-                    tracing_subscriber::EnvFilter::from_default_env()
-                        .add_directive(tracing::level_filters::LevelFilter::INFO.into()),
-                )
-                .init();
-        }
+/// The etcd connection: CLI flags over the config file. The password comes
+/// from `K3RS_ETCD_PASSWORD`, else the config file's `password-file` or
+/// `password`.
+fn etcd_config(
+    endpoints: Vec<String>,
+    username: Option<String>,
+    file: EtcdConfigFile,
+) -> anyhow::Result<EtcdConfig> {
+    let endpoints = if endpoints.is_empty() {
+        file.endpoints
+    } else {
+        endpoints
+    };
+    if endpoints.is_empty() {
+        anyhow::bail!("state store etcd needs endpoints (--etcd-endpoints or etcd.endpoints)");
+    }
+    let password = secret(
+        etcd::PASSWORD_ENV,
+        file.password_file,
+        file.password,
+        "etcd password",
+    )?;
+    Ok(EtcdConfig {
+        endpoints,
+        username: username.or(file.username),
+        password,
+    })
+}
+
+/// The SlateDB bucket from the config file. The secret access key comes
+/// from `K3RS_OBJECT_STORE_SECRET_KEY`, else `secret-access-key-file` or
+/// `secret-access-key`.
+fn object_store_config(file: ObjectStoreConfigFile) -> anyhow::Result<ObjectStoreConfig> {
+    let provider = file.provider.parse().map_err(anyhow::Error::msg)?;
+    if file.bucket.is_empty() {
+        anyhow::bail!("object-store needs a bucket");
     }
+    Ok(ObjectStoreConfig {
+        provider,
+        bucket: file.bucket,
+        prefix: file.prefix.unwrap_or_default(),
+        region: file.region,
+        endpoint: file.endpoint,
+        access_key_id: file.access_key_id,
+        secret_access_key: secret(
+            slatedb::SECRET_KEY_ENV,
+            file.secret_access_key_file,
+            file.secret_access_key,
+            "object store secret access key",
+        )?,
+        credentials_file: file.credentials_file,
+    })
 }
 
-/// Initialize tracing with OpenTelemetry OTLP export layer.
-fn init_tracing_with_otel(log_format: &str, endpoint: &str) -> anyhow::Result<()> {
+/// A secret from the environment variable `env`, else the file at `path`,
+/// else the inline `value`.
+fn secret(
+    env: &str,
+    path: Option<String>,
+    value: Option<String>,
+    what: &str,
+) -> anyhow::Result<Option<String>> {
+    if let Ok(secret) = std::env::var(env) {
+        return Ok(Some(secret));
+    }
+    match path {
+        Some(path) => Ok(Some(
+            std::fs::read_to_string(&path)
+                .map_err(|e| anyhow::anyhow!("failed to read {} file {}: {}", what, path, e))?
+                .trim()
+                .to_string(),
+        )),
+        None => Ok(value),
+    }
+}
+
+/// The scheduling strategy: the command line's, else the config file's,
+/// else round-robin.
+fn scheduler_strategy(
+    cli: Option<&str>,
+    file_cfg: &ServerConfigFile,
+) -> anyhow::Result<ScoringStrategy> {
+    match cli.or(file_cfg.scheduler_strategy.as_deref()) {
+        Some(name) => name.parse(),
+        None => Ok(ScoringStrategy::default()),
+    }
+}
+
+/// Poll the config file and apply the settings that can change while the
+/// server runs. The others are logged as needing a restart; an invalid file
+/// is logged and the previous config kept.
+fn watch_config(
+    mut watcher: ConfigFileWatcher<ServerConfigFile>,
+    path: String,
+    cli_strategy: Option<String>,
+    scheduler: Arc<Scheduler>,
+    log_filter: Arc<LogFilter>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            pkg_constants::timings::CONFIG_RELOAD_INTERVAL_SECS,
+        ));
+        loop {
+            interval.tick().await;
+            let reloaded = watcher.poll(|cfg| {
+                log_filter
+                    .validate(cfg.log_level.as_deref())
+                    .map_err(anyhow::Error::msg)?;
+                scheduler_strategy(cli_strategy.as_deref(), cfg)
+            });
+            let reloaded = match reloaded {
+                Ok(Some(reloaded)) => reloaded,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!(
+                        "Config file {} not reloaded, keeping the previous config: {:#}",
+                        path, e
+                    );
+                    continue;
+                }
+            };
+            let strategy = reloaded.value;
+            if reloaded.applied.iter().any(|key| key == "log-level")
+                && let Err(e) = log_filter.set(watcher.current().log_level.as_deref())
+            {
+                warn!("Failed to apply log-level: {}", e);
+            }
+            if scheduler.config().strategy != strategy {
+                scheduler.set_strategy(strategy);
+                info!("Scheduler strategy: {}", strategy);
+            }
+            if !reloaded.applied.is_empty() {
+                info!(
+                    "Config file {} reloaded: {}",
+                    path,
+                    reloaded.applied.join(", ")
+                );
+            }
+            if !reloaded.restart_required.is_empty() {
+                warn!(
+                    "Config file {}: {} changed; restart k3rs-server to apply",
+                    path,
+                    reloaded.restart_required.join(", ")
+                );
+            }
+        }
+    });
+}
+
+/// Tracing initialization (text or json), optionally exporting spans to an
+/// OpenTelemetry OTLP endpoint, with `filter` deciding what is logged.
+fn init_tracing(
+    log_format: &str,
+    filter: LogFilterLayer,
+    otel_endpoint: Option<&str>,
+) -> anyhow::Result<()> {
     use opentelemetry::trace::TracerProvider;
     use opentelemetry_otlp::WithExportConfig;
     use opentelemetry_sdk::trace::SdkTracerProvider;
     use tracing_opentelemetry::OpenTelemetryLayer;
-    use tracing_subscriber::layer::SubscriberExt;
-    use tracing_subscriber::util::SubscriberInitExt;
 
-    let exporter = opentelemetry_otlp::SpanExporter::builder()
-        .with_tonic()
-        .with_endpoint(endpoint)
-        .build()
-        .map_err(|e| anyhow::anyhow!("Failed to create OTLP exporter: {}", e))?;
+    let otel_layer = match otel_endpoint {
+        Some(endpoint) => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(endpoint)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Failed to create OTLP exporter: {}", e))?;
 
-    let provider = SdkTracerProvider::builder()
-        .with_batch_exporter(exporter)
-        .build();
+            let provider = SdkTracerProvider::builder()
+                .with_batch_exporter(exporter)
+                .build();
 
-    let tracer = provider.tracer("k3rs-server");
-    // Keep provider alive — it will be cleaned up when the process exits
-    std::mem::forget(provider);
+            let tracer = provider.tracer("k3rs-server");
+            // Keep provider alive — it will be cleaned up when the process exits
+            std::mem::forget(provider);
 
-    let otel_layer = OpenTelemetryLayer::new(tracer);
-
-    let filter = tracing_subscriber::EnvFilter::from_default_env()
-        .add_directive(tracing::level_filters::LevelFilter::INFO.into());
+            Some(OpenTelemetryLayer::new(tracer))
+        }
+        None => None,
+    };
 
-    // Use text format when OTel is enabled (avoids complex type layering with JSON)
-    let _ = log_format; // acknowledged but simplified
+    let json = log_format == "json";
     tracing_subscriber::registry()
         .with(filter)
-        .with(tracing_subscriber::fmt::layer())
+        .with(json.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json).then(tracing_subscriber::fmt::layer))
         .with(otel_layer)
         .init();
 
-    info!(
-        "OpenTelemetry tracing initialized — exporting to {}",
-        endpoint
-    );
+    if let Some(endpoint) = otel_endpoint {
+        info!(
+            "OpenTelemetry tracing initialized — exporting to {}",
+            endpoint
+        );
+    }
     Ok(())
 }
 
@@ -0,0 +1,180 @@
+//! The cordon, uncordon and drain buttons of the node page: each asks for
+//! confirmation, and while one runs none can be pressed.
+
+use crate::api::ApiError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeAction {
+    Cordon,
+    Uncordon,
+    Drain,
+}
+
+impl NodeAction {
+    pub const ALL: [NodeAction; 3] = [Self::Cordon, Self::Uncordon, Self::Drain];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Cordon => "Cordon",
+            Self::Uncordon => "Uncordon",
+            Self::Drain => "Drain",
+        }
+    }
+
+    /// Shown on the button while the action runs.
+    pub fn running_label(self) -> &'static str {
+        match self {
+            Self::Cordon => "Cordoning...",
+            Self::Uncordon => "Uncordoning...",
+            Self::Drain => "Draining...",
+        }
+    }
+
+    /// What the confirmation dialog asks.
+    pub fn question(self, node: &str) -> String {
+        match self {
+            Self::Cordon => format!("Stop scheduling new pods on {}?", node),
+            Self::Uncordon => format!("Allow new pods on {} again?", node),
+            Self::Drain => format!(
+                "Cordon {} and evict its pods? DaemonSet pods stay; the rest are rescheduled elsewhere by their controllers.",
+                node
+            ),
+        }
+    }
+}
+
+/// Where the buttons are in running an action.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ActionState {
+    #[default]
+    Idle,
+    /// The dialog asking to confirm the action is open.
+    Confirming(NodeAction),
+    /// Sent to the server; the buttons stay disabled until it answers.
+    Running(NodeAction),
+    Done(NodeAction, String),
+    Failed(NodeAction, ApiError),
+}
+
+impl ActionState {
+    /// Ask to confirm `action`; ignored while another one is open or runs.
+    pub fn request(&mut self, action: NodeAction) {
+        if !self.busy() {
+            *self = Self::Confirming(action);
+        }
+    }
+
+    /// Close the dialog without running the action.
+    pub fn cancel(&mut self) {
+        if matches!(self, Self::Confirming(_)) {
+            *self = Self::Idle;
+        }
+    }
+
+    /// The confirmed action to run, now marked as running.
+    pub fn confirm(&mut self) -> Option<NodeAction> {
+        let Self::Confirming(action) = *self else {
+            return None;
+        };
+        *self = Self::Running(action);
+        Some(action)
+    }
+
+    /// Record how the running action ended, with a message on success.
+    pub fn finish(&mut self, result: Result<String, ApiError>) {
+        let Self::Running(action) = *self else {
+            return;
+        };
+        *self = match result {
+            Ok(message) => Self::Done(action, message),
+            Err(err) => Self::Failed(action, err),
+        };
+    }
+
+    pub fn running(&self) -> Option<NodeAction> {
+        match self {
+            Self::Running(action) => Some(*action),
+            _ => None,
+        }
+    }
+
+    fn busy(&self) -> bool {
+        matches!(self, Self::Confirming(_) | Self::Running(_))
+    }
+
+    /// Whether the button of `action` can be pressed, for a node that is
+    /// `cordoned` or not. Draining a cordoned node is allowed; it evicts
+    /// what is still there.
+    pub fn enabled(&self, action: NodeAction, cordoned: bool) -> bool {
+        !self.busy()
+            && match action {
+                NodeAction::Cordon => !cordoned,
+                NodeAction::Uncordon => cordoned,
+                NodeAction::Drain => true,
+            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmed_action_disables_buttons_until_it_finishes() {
+        let mut state = ActionState::default();
+        assert!(state.enabled(NodeAction::Drain, false));
+
+        state.request(NodeAction::Drain);
+        assert_eq!(state, ActionState::Confirming(NodeAction::Drain));
+        assert!(!state.enabled(NodeAction::Cordon, false));
+
+        assert_eq!(state.confirm(), Some(NodeAction::Drain));
+        assert_eq!(state.running(), Some(NodeAction::Drain));
+        for action in NodeAction::ALL {
+            assert!(!state.enabled(action, false));
+            assert!(!state.enabled(action, true));
+        }
+        // Pressing again while it runs changes nothing.
+        state.request(NodeAction::Cordon);
+        assert_eq!(state.running(), Some(NodeAction::Drain));
+        assert_eq!(state.confirm(), None);
+
+        state.finish(Ok("Node drained".to_string()));
+        assert_eq!(
+            state,
+            ActionState::Done(NodeAction::Drain, "Node drained".to_string())
+        );
+        assert!(state.enabled(NodeAction::Uncordon, true));
+    }
+
+    #[test]
+    fn test_cancel_and_failure() {
+        let mut state = ActionState::default();
+        state.request(NodeAction::Cordon);
+        state.cancel();
+        assert_eq!(state, ActionState::Idle);
+        assert_eq!(state.confirm(), None);
+
+        state.request(NodeAction::Cordon);
+        state.confirm();
+        let err = ApiError::Forbidden("role viewer cannot cordon".to_string());
+        state.finish(Err(err.clone()));
+        assert_eq!(state, ActionState::Failed(NodeAction::Cordon, err));
+        // A failure can be retried right away.
+        state.request(NodeAction::Cordon);
+        assert_eq!(state, ActionState::Confirming(NodeAction::Cordon));
+        // A result without a running action is ignored.
+        state.finish(Ok(String::new()));
+        assert_eq!(state, ActionState::Confirming(NodeAction::Cordon));
+    }
+
+    #[test]
+    fn test_cordon_buttons_follow_the_node() {
+        let state = ActionState::default();
+        assert!(state.enabled(NodeAction::Cordon, false));
+        assert!(!state.enabled(NodeAction::Uncordon, false));
+        assert!(!state.enabled(NodeAction::Cordon, true));
+        assert!(state.enabled(NodeAction::Uncordon, true));
+        assert!(state.enabled(NodeAction::Drain, true));
+    }
+}
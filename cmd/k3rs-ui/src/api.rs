@@ -1,282 +1,1377 @@
+//! Typed client for the k3rs API server.
+//!
+//! Every call is a server function: the browser sends it the [`ApiSettings`]
+//! (in the request body, so the token stays out of URLs), and the UI server
+//! makes the request against the API server, retrying transient failures.
+//! Failures come back as an [`ApiError`] the pages show as a toast.
+
 use crate::*;
+// The prelude's `Ok` is fixed to `CapturedError`.
+use std::result::Result::Ok;
 
+use crate::manifest::ManifestKind;
+#[cfg(feature = "server")]
+use crate::manifest::{to_yaml, Manifest};
+use dioxus::fullstack::{
+    AsStatusCode, ServerEvents, StatusCode, TextStream, WebSocketOptions, Websocket,
+};
 use pkg_constants::{auth, network};
 
-/// Server config re-exported as locals for ergonomic use below.
-const K3RS_API: &str = network::DEFAULT_API_ADDR;
-const K3RS_TOKEN: &str = auth::DEFAULT_JOIN_TOKEN;
+/// localStorage key the settings are saved under.
+const SETTINGS_KEY: &str = "k3rs-ui.settings";
+
+/// Where the API server is and the token to authenticate with. Edited on the
+/// Settings page and saved in the browser's localStorage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiSettings {
+    pub server: String,
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            server: network::DEFAULT_API_ADDR.to_string(),
+            token: auth::DEFAULT_ADMIN_TOKEN.to_string(),
+        }
+    }
+}
+
+impl ApiSettings {
+    /// The settings saved in this browser, if any.
+    pub async fn load() -> Option<Self> {
+        let js = format!(
+            "return localStorage.getItem({});",
+            serde_json::to_string(SETTINGS_KEY).ok()?
+        );
+        let saved = document::eval(&js).await.ok()?;
+        serde_json::from_str(saved.as_str()?).ok()
+    }
+
+    /// Save the settings in this browser's localStorage.
+    pub fn save(&self) {
+        let (Ok(key), Ok(value)) = (
+            serde_json::to_string(SETTINGS_KEY),
+            serde_json::to_string(self).and_then(|json| serde_json::to_string(&json)),
+        ) else {
+            return;
+        };
+        document::eval(&format!("localStorage.setItem({}, {});", key, value));
+    }
+}
+
+/// Why a call to the API server failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ApiError {
+    /// 401: the token is missing or not valid.
+    Unauthorized(String),
+    /// 403: the token's role does not allow the request.
+    Forbidden(String),
+    /// 404.
+    NotFound(String),
+    /// Any other 4xx; `details` names the failing fields of an invalid
+    /// object.
+    Rejected {
+        status: u16,
+        message: String,
+        #[serde(default)]
+        details: Vec<FieldError>,
+    },
+    /// 5xx.
+    Server { status: u16, message: String },
+    /// The API server (or the UI server) could not be reached.
+    Unreachable(String),
+    /// The settings or the response could not be used.
+    Invalid(String),
+}
+
+impl ApiError {
+    /// Decode an error response. The API server's JSON errors give their
+    /// `message` and `details`; other bodies are used as they are.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let json = serde_json::from_str::<serde_json::Value>(body).ok();
+        let details = json
+            .as_ref()
+            .and_then(|v| serde_json::from_value(v.get("details")?.clone()).ok())
+            .unwrap_or_default();
+        let message = json
+            .and_then(|v| v.get("message")?.as_str().map(str::to_string))
+            .unwrap_or_else(|| match body.trim() {
+                "" => format!("request failed with status {}", status),
+                text => text.to_string(),
+            });
+        match status {
+            401 => Self::Unauthorized(message),
+            403 => Self::Forbidden(message),
+            404 => Self::NotFound(message),
+            500..=599 => Self::Server { status, message },
+            _ => Self::Rejected {
+                status,
+                message,
+                details,
+            },
+        }
+    }
+
+    /// Worth retrying: the server was unreachable, overloaded or restarting.
+    #[cfg(feature = "server")]
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Unreachable(_)
+                | Self::Server {
+                    status: 502..=504,
+                    ..
+                }
+                | Self::Rejected { status: 429, .. }
+        )
+    }
+
+    /// Short heading for the toast.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Unauthorized(_) => "Not authenticated",
+            Self::Forbidden(_) => "Permission denied",
+            Self::NotFound(_) => "Not found",
+            Self::Rejected { .. } => "Request rejected",
+            Self::Server { .. } => "Server error",
+            Self::Unreachable(_) => "API server unreachable",
+            Self::Invalid(_) => "Unexpected response",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Unauthorized(m)
+            | Self::Forbidden(m)
+            | Self::NotFound(m)
+            | Self::Unreachable(m)
+            | Self::Invalid(m) => m,
+            Self::Rejected { message, .. } | Self::Server { message, .. } => message,
+        }
+    }
+}
+
+/// One failing field of an invalid object, e.g.
+/// `spec.containers[0].image`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.title(), self.message())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// The status the UI server answers the browser with.
+impl AsStatusCode for ApiError {
+    fn as_status_code(&self) -> StatusCode {
+        let code = match self {
+            Self::Unauthorized(_) => 401,
+            Self::Forbidden(_) => 403,
+            Self::NotFound(_) => 404,
+            Self::Rejected { status, .. } | Self::Server { status, .. } => *status,
+            Self::Unreachable(_) => 502,
+            Self::Invalid(_) => 500,
+        };
+        StatusCode::from_u16(code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+/// Failures of the server function itself, e.g. the UI server being down.
+impl From<ServerFnError> for ApiError {
+    fn from(err: ServerFnError) -> Self {
+        match err {
+            ServerFnError::ServerError { code, message, .. } => Self::from_response(code, &message),
+            ServerFnError::Request(err) => Self::Unreachable(err.to_string()),
+            other => Self::Invalid(other.to_string()),
+        }
+    }
+}
+
+/// Content type of a node label patch.
+#[cfg(feature = "server")]
+const MERGE_PATCH: &str = "application/merge-patch+json";
+
+/// A request to the API server; a GET unless [`ApiRequest::send_body`] or
+/// [`ApiRequest::method`] says otherwise.
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, PartialEq)]
+struct ApiRequest {
+    method: reqwest::Method,
+    url: reqwest::Url,
+    token: Option<String>,
+    /// Content type and body.
+    body: Option<(&'static str, String)>,
+    /// Replaces the client's timeout, for calls the server answers slowly.
+    timeout: Option<std::time::Duration>,
+}
+
+#[cfg(feature = "server")]
+impl ApiRequest {
+    /// `path` on the server in `settings`.
+    fn new(settings: &ApiSettings, path: &str) -> Result<Self, ApiError> {
+        let base = settings.server.trim().trim_end_matches('/');
+        let url = reqwest::Url::parse(&format!("{}{}", base, path)).map_err(|e| {
+            ApiError::Invalid(format!("invalid server URL '{}': {}", settings.server, e))
+        })?;
+        let token = Some(settings.token.trim())
+            .filter(|t| !t.is_empty())
+            .map(str::to_string);
+        Ok(Self {
+            method: reqwest::Method::GET,
+            url,
+            token,
+            body: None,
+            timeout: None,
+        })
+    }
+
+    /// Node `name`, or its `sub` resource, e.g. `cordon`.
+    fn node(settings: &ApiSettings, name: &str, sub: Option<&str>) -> Result<Self, ApiError> {
+        if name.is_empty() {
+            return Err(ApiError::Invalid("no node selected".to_string()));
+        }
+        let mut req = Self::new(settings, "/api/v1/nodes")?;
+        if let Ok(mut path) = req.url.path_segments_mut() {
+            path.push(name).extend(sub);
+        }
+        Ok(req)
+    }
+
+    /// The `resource` collection of namespace `ns`.
+    fn namespaced(settings: &ApiSettings, ns: &str, resource: &str) -> Result<Self, ApiError> {
+        if ns.is_empty() {
+            return Err(ApiError::Invalid("no namespace selected".to_string()));
+        }
+        let mut req = Self::new(settings, "/api/v1/namespaces")?;
+        req.url
+            .path_segments_mut()
+            .map_err(|_| ApiError::Invalid(format!("invalid server URL '{}'", settings.server)))?
+            .extend([ns, resource]);
+        Ok(req)
+    }
+
+    /// The `sub` resource of pod `pod` in `ns`, e.g. its `logs`.
+    fn pod(settings: &ApiSettings, ns: &str, pod: &str, sub: &str) -> Result<Self, ApiError> {
+        let mut req = Self::namespaced(settings, ns, "pods")?;
+        if let Ok(mut path) = req.url.path_segments_mut() {
+            path.extend([pod, sub]);
+        }
+        Ok(req)
+    }
+
+    /// Object `name` of `kind`, in `ns` if the kind is namespaced.
+    fn object(
+        settings: &ApiSettings,
+        kind: ManifestKind,
+        ns: &str,
+        name: &str,
+    ) -> Result<Self, ApiError> {
+        let mut req = if kind.namespaced() {
+            Self::namespaced(settings, ns, kind.resource())?
+        } else {
+            Self::new(settings, &format!("/api/v1/{}", kind.resource()))?
+        };
+        if let Ok(mut path) = req.url.path_segments_mut() {
+            path.push(name);
+        }
+        Ok(req)
+    }
+
+    fn query(mut self, key: &str, value: &str) -> Self {
+        self.url.query_pairs_mut().append_pair(key, value);
+        self
+    }
+
+    fn method(mut self, method: reqwest::Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Send `body` as JSON of `content_type`.
+    fn send_body(
+        mut self,
+        method: reqwest::Method,
+        content_type: &'static str,
+        body: &impl Serialize,
+    ) -> Result<Self, ApiError> {
+        let body = serde_json::to_string(body)
+            .map_err(|e| ApiError::Invalid(format!("could not encode the request: {}", e)))?;
+        self.method = method;
+        self.body = Some((content_type, body));
+        Ok(self)
+    }
+
+    fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The same URL with `ws:` (or `wss:`), to open a WebSocket.
+    fn websocket(mut self) -> Result<Self, ApiError> {
+        let scheme = if self.url.scheme() == "https" {
+            "wss"
+        } else {
+            "ws"
+        };
+        self.url
+            .set_scheme(scheme)
+            .map_err(|_| ApiError::Invalid(format!("cannot open a WebSocket to '{}'", self.url)))?;
+        Ok(self)
+    }
+}
+
+/// What came back for an [`ApiRequest`].
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, Default)]
+struct RawResponse {
+    status: u16,
+    continue_token: Option<String>,
+    /// The event seq the server read before answering.
+    watch_seq: Option<u64>,
+    body: String,
+}
+
+/// Sends requests to the API server; mocked in tests.
+#[cfg(feature = "server")]
+trait Transport {
+    /// `Err` only when no response was received.
+    async fn send(&self, req: &ApiRequest) -> Result<RawResponse, ApiError>;
+}
+
+#[cfg(feature = "server")]
+struct HttpTransport;
+
+#[cfg(feature = "server")]
+impl Transport for HttpTransport {
+    async fn send(&self, req: &ApiRequest) -> Result<RawResponse, ApiError> {
+        let mut builder = http_client().request(req.method.clone(), req.url.clone());
+        if let Some(token) = &req.token {
+            builder = builder.bearer_auth(token);
+        }
+        if let Some((content_type, body)) = &req.body {
+            builder = builder
+                .header(reqwest::header::CONTENT_TYPE, *content_type)
+                .body(body.clone());
+        }
+        if let Some(timeout) = req.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let resp = builder
+            .send()
+            .await
+            .map_err(|e| ApiError::Unreachable(e.without_url().to_string()))?;
+        let status = resp.status().as_u16();
+        let continue_token = resp
+            .headers()
+            .get(pkg_constants::state::CONTINUE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let watch_seq = resp
+            .headers()
+            .get(pkg_constants::state::WATCH_SEQ_HEADER)
+            .and_then(|v| v.to_str().ok()?.parse().ok());
+        let body = resp
+            .text()
+            .await
+            .map_err(|e| ApiError::Unreachable(e.without_url().to_string()))?;
+        Ok(RawResponse {
+            status,
+            continue_token,
+            watch_seq,
+            body,
+        })
+    }
+}
+
+/// One client for every call, so connections are reused.
+#[cfg(feature = "server")]
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: std::sync::OnceLock<reqwest::Client> = std::sync::OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// Client for watch streams, which stay open for as long as the server
+/// sends events.
+#[cfg(feature = "server")]
+fn stream_client() -> &'static reqwest::Client {
+    static CLIENT: std::sync::OnceLock<reqwest::Client> = std::sync::OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// How many times a request is tried, and the wait before the first retry;
+/// each later retry waits twice as long as the one before.
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    attempts: u32,
+    backoff: std::time::Duration,
+}
+
+#[cfg(feature = "server")]
+const RETRY: RetryPolicy = RetryPolicy {
+    attempts: 3,
+    backoff: std::time::Duration::from_millis(200),
+};
+
+/// For changes: after a gateway error the server may still have applied
+/// one, so it is not sent again.
+#[cfg(feature = "server")]
+const ONCE: RetryPolicy = RetryPolicy {
+    attempts: 1,
+    backoff: std::time::Duration::ZERO,
+};
+
+/// Send `req`, retrying transient failures; error statuses become an
+/// [`ApiError`].
+#[cfg(feature = "server")]
+async fn send(
+    transport: &impl Transport,
+    req: &ApiRequest,
+    policy: RetryPolicy,
+) -> Result<RawResponse, ApiError> {
+    let mut delay = policy.backoff;
+    let mut attempt = 1;
+    loop {
+        let result = match transport.send(req).await {
+            Ok(resp) if resp.status >= 400 => Err(ApiError::from_response(resp.status, &resp.body)),
+            other => other,
+        };
+        match result {
+            Err(err) if err.is_transient() && attempt < policy.attempts => {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            other => return other,
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn decode<T: serde::de::DeserializeOwned>(resp: &RawResponse) -> Result<T, ApiError> {
+    serde_json::from_str(&resp.body)
+        .map_err(|e| ApiError::Invalid(format!("could not decode the response: {}", e)))
+}
+
+/// GET `req` and decode its JSON body.
+#[cfg(feature = "server")]
+async fn get_json<T: serde::de::DeserializeOwned>(req: ApiRequest) -> Result<T, ApiError> {
+    decode(&send(&HttpTransport, &req, RETRY).await?)
+}
+
+/// Send the change `req` once and decode its JSON body.
+#[cfg(feature = "server")]
+async fn change<T: serde::de::DeserializeOwned>(req: ApiRequest) -> Result<T, ApiError> {
+    decode(&send(&HttpTransport, &req, ONCE).await?)
+}
+
+/// GET one page of the list `req`, after the page `cont` was returned with.
+#[cfg(feature = "server")]
+async fn get_page<T: serde::de::DeserializeOwned>(
+    transport: &impl Transport,
+    req: ApiRequest,
+    limit: usize,
+    cont: Option<String>,
+) -> Result<ListPage<T>, ApiError> {
+    let mut req = req.query("limit", &limit.to_string());
+    if let Some(token) = cont {
+        req = req.query("continue", &token);
+    }
+    let resp = send(transport, &req, RETRY).await?;
+    Ok(ListPage {
+        items: decode(&resp)?,
+        continue_token: resp.continue_token,
+    })
+}
+
+/// GET the list `req` along with the seq to watch it from.
+#[cfg(feature = "server")]
+async fn get_snapshot<T: serde::de::DeserializeOwned>(
+    transport: &impl Transport,
+    req: ApiRequest,
+) -> Result<Snapshot<T>, ApiError> {
+    let resp = send(transport, &req, RETRY).await?;
+    Ok(Snapshot {
+        items: decode(&resp)?,
+        seq: resp.watch_seq.unwrap_or_default(),
+    })
+}
+
+/// GET `req` and return the response as it starts, for a body streamed for
+/// as long as the connection stays open.
+#[cfg(feature = "server")]
+async fn open_stream(req: &ApiRequest) -> Result<reqwest::Response, ApiError> {
+    let mut builder = stream_client().get(req.url.clone());
+    if let Some(token) = &req.token {
+        builder = builder.bearer_auth(token);
+    }
+    let resp = builder
+        .send()
+        .await
+        .map_err(|e| ApiError::Unreachable(e.without_url().to_string()))?;
+    let status = resp.status().as_u16();
+    if status >= 400 {
+        let body = resp.text().await.unwrap_or_default();
+        return Err(ApiError::from_response(status, &body));
+    }
+    Ok(resp)
+}
+
+/// Take the text off the front of a stream's `buf`; a character cut off at
+/// its end stays for the next chunk.
+#[cfg(feature = "server")]
+fn take_utf8(buf: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(buf) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => buf.len(),
+    };
+    let rest = buf.split_off(valid);
+    let text = String::from_utf8_lossy(buf).into_owned();
+    *buf = rest;
+    text
+}
+
+/// Take the complete events off the front of an SSE stream's `buf`; a
+/// partly received one stays for the next chunk.
+#[cfg(feature = "server")]
+fn take_sse_events(buf: &mut Vec<u8>) -> Vec<WatchEvent> {
+    let mut events = Vec::new();
+    while let Some(end) = buf.windows(2).position(|w| w == b"\n\n") {
+        let block: Vec<u8> = buf.drain(..end + 2).collect();
+        let data: String = String::from_utf8_lossy(&block)
+            .lines()
+            .filter_map(|l| l.strip_prefix("data:"))
+            .map(str::trim_start)
+            .collect();
+        // Keep-alive comments carry no data.
+        if let Ok(event) = serde_json::from_str(&data) {
+            events.push(event);
+        }
+    }
+    events
+}
 
 // ============================================================
 // Server functions — run on the server, called from WASM client
 // ============================================================
 
-#[get("/api/ui/cluster-info")]
-pub async fn get_cluster_info() -> Result<ClusterInfo> {
-    let url = format!("{}/api/v1/cluster/info", K3RS_API);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let info: ClusterInfo = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(info)
+#[post("/api/ui/cluster-info")]
+pub async fn get_cluster_info(settings: ApiSettings) -> Result<ClusterInfo, ApiError> {
+    get_json(ApiRequest::new(&settings, "/api/v1/cluster/info")?).await
 }
 
-#[get("/api/ui/nodes")]
-pub async fn get_nodes() -> Result<Vec<Node>> {
-    let url = format!("{}/api/v1/nodes", K3RS_API);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let nodes: Vec<Node> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(nodes)
+#[post("/api/ui/nodes")]
+pub async fn get_nodes(settings: ApiSettings) -> Result<Vec<Node>, ApiError> {
+    get_json(ApiRequest::new(&settings, "/api/v1/nodes")?).await
 }
 
-#[get("/api/ui/pods?ns")]
-pub async fn get_pods(ns: String) -> Result<Vec<Pod>> {
-    let url = format!("{}/api/v1/namespaces/{}/pods", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let pods: Vec<Pod> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(pods)
+#[post("/api/ui/pods")]
+pub async fn get_pods(settings: ApiSettings, ns: String) -> Result<Vec<Pod>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "pods")?).await
 }
 
-#[get("/api/ui/services?ns")]
-pub async fn get_services(ns: String) -> Result<Vec<Service>> {
-    let url = format!("{}/api/v1/namespaces/{}/services", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let svcs: Vec<Service> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(svcs)
+/// At most `limit` pods of `ns`, after the page `cont` was returned with.
+#[post("/api/ui/pods-page")]
+pub async fn get_pods_page(
+    settings: ApiSettings,
+    ns: String,
+    limit: usize,
+    cont: Option<String>,
+) -> Result<ListPage<Pod>, ApiError> {
+    let req = ApiRequest::namespaced(&settings, &ns, "pods")?;
+    get_page(&HttpTransport, req, limit, cont).await
 }
 
-#[get("/api/ui/deployments?ns")]
-pub async fn get_deployments(ns: String) -> Result<Vec<Deployment>> {
-    let url = format!("{}/api/v1/namespaces/{}/deployments", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let deps: Vec<Deployment> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(deps)
+/// Every pod of `ns`, to keep current with [`watch`].
+#[post("/api/ui/pods-snapshot")]
+pub async fn list_pods(settings: ApiSettings, ns: String) -> Result<Snapshot<Pod>, ApiError> {
+    get_snapshot(
+        &HttpTransport,
+        ApiRequest::namespaced(&settings, &ns, "pods")?,
+    )
+    .await
 }
 
-#[get("/api/ui/configmaps?ns")]
-pub async fn get_configmaps(ns: String) -> Result<Vec<ConfigMap>> {
-    let url = format!("{}/api/v1/namespaces/{}/configmaps", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let cms: Vec<ConfigMap> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(cms)
+#[post("/api/ui/services")]
+pub async fn get_services(settings: ApiSettings, ns: String) -> Result<Vec<Service>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "services")?).await
 }
 
-#[get("/api/ui/secrets?ns")]
-pub async fn get_secrets(ns: String) -> Result<Vec<Secret>> {
-    let url = format!("{}/api/v1/namespaces/{}/secrets", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let secrets: Vec<Secret> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(secrets)
+#[post("/api/ui/deployments")]
+pub async fn get_deployments(
+    settings: ApiSettings,
+    ns: String,
+) -> Result<Vec<Deployment>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "deployments")?).await
 }
 
-#[get("/api/ui/ingresses?ns")]
-pub async fn get_ingresses(ns: String) -> Result<Vec<IngressObj>> {
-    let url = format!("{}/api/v1/namespaces/{}/ingresses", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let ings: Vec<IngressObj> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(ings)
+#[post("/api/ui/configmaps")]
+pub async fn get_configmaps(settings: ApiSettings, ns: String) -> Result<Vec<ConfigMap>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "configmaps")?).await
 }
 
-#[get("/api/ui/quotas?ns")]
-pub async fn get_quotas(ns: String) -> Result<Vec<ResourceQuota>> {
-    let url = format!("{}/api/v1/namespaces/{}/resourcequotas", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let items: Vec<ResourceQuota> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(items)
+#[post("/api/ui/secrets")]
+pub async fn get_secrets(settings: ApiSettings, ns: String) -> Result<Vec<Secret>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "secrets")?).await
 }
 
-#[get("/api/ui/network-policies?ns")]
-pub async fn get_network_policies(ns: String) -> Result<Vec<NetworkPolicyObj>> {
-    let url = format!("{}/api/v1/namespaces/{}/networkpolicies", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let items: Vec<NetworkPolicyObj> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(items)
+#[post("/api/ui/ingresses")]
+pub async fn get_ingresses(settings: ApiSettings, ns: String) -> Result<Vec<IngressObj>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "ingresses")?).await
 }
 
-#[get("/api/ui/pvcs?ns")]
-pub async fn get_pvcs(ns: String) -> Result<Vec<PVC>> {
-    let url = format!("{}/api/v1/namespaces/{}/pvcs", K3RS_API, ns);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let items: Vec<PVC> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(items)
+#[post("/api/ui/quotas")]
+pub async fn get_quotas(settings: ApiSettings, ns: String) -> Result<Vec<ResourceQuota>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "resourcequotas")?).await
 }
 
-#[get("/api/ui/events")]
-pub async fn get_events() -> Result<Vec<WatchEvent>> {
-    let url = format!("{}/api/v1/events", K3RS_API);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let events: Vec<WatchEvent> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(events)
+#[post("/api/ui/limitranges")]
+pub async fn get_limit_ranges(
+    settings: ApiSettings,
+    ns: String,
+) -> Result<Vec<LimitRange>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "limitranges")?).await
 }
 
-#[get("/api/ui/metrics")]
-pub async fn get_metrics() -> Result<String> {
-    let url = format!("{}/metrics", K3RS_API);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let text = resp
-        .text()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(text)
+#[post("/api/ui/network-policies")]
+pub async fn get_network_policies(
+    settings: ApiSettings,
+    ns: String,
+) -> Result<Vec<NetworkPolicyObj>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "networkpolicies")?).await
 }
 
-#[get("/api/ui/processes")]
-pub async fn get_processes() -> Result<Vec<ProcessInfo>> {
-    let url = format!("{}/api/v1/processes", K3RS_API);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let procs: Vec<ProcessInfo> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(procs)
+#[post("/api/ui/pvcs")]
+pub async fn get_pvcs(settings: ApiSettings, ns: String) -> Result<Vec<PVC>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "pvcs")?).await
 }
 
-#[get("/api/ui/vpcs")]
-pub async fn get_vpcs() -> Result<Vec<Vpc>> {
-    let url = format!("{}/api/v1/vpcs", K3RS_API);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let vpcs: Vec<Vpc> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(vpcs)
+/// Events about the objects in `ns`, oldest first.
+#[post("/api/ui/events")]
+pub async fn get_events(settings: ApiSettings, ns: String) -> Result<Vec<ObjectEvent>, ApiError> {
+    get_json(ApiRequest::namespaced(&settings, &ns, "events")?).await
 }
 
-#[get("/api/ui/vpc-peerings")]
-pub async fn get_vpc_peerings() -> Result<Vec<VpcPeering>> {
-    let url = format!("{}/api/v1/vpc-peerings", K3RS_API);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let peerings: Vec<VpcPeering> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(peerings)
+/// The events of `ns`, to keep current with [`watch`].
+#[post("/api/ui/events-snapshot")]
+pub async fn list_events(
+    settings: ApiSettings,
+    ns: String,
+) -> Result<Snapshot<ObjectEvent>, ApiError> {
+    get_snapshot(
+        &HttpTransport,
+        ApiRequest::namespaced(&settings, &ns, "events")?,
+    )
+    .await
 }
 
-#[get("/api/ui/images")]
-pub async fn get_images() -> Result<Vec<ImageInfo>> {
-    let url = format!("{}/api/v1/images", K3RS_API);
-    let resp = reqwest::Client::new()
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", K3RS_TOKEN))
-        .send()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    let imgs: Vec<ImageInfo> = resp
-        .json()
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?;
-    Ok(imgs)
+/// Changes to the keys under `prefix` after `seq`, relayed from the API
+/// server's watch stream; ends when that stream does. A 410 means the
+/// events after `seq` are gone and the caller has to list again.
+#[post("/api/ui/watch")]
+pub async fn watch(
+    settings: ApiSettings,
+    prefix: String,
+    seq: u64,
+) -> Result<ServerEvents<WatchEvent>, ApiError> {
+    let req = ApiRequest::new(&settings, "/api/v1/watch")?
+        .query("prefix", &prefix)
+        .query("seq", &seq.to_string());
+    let mut resp = open_stream(&req).await?;
+    Ok(ServerEvents::new(move |mut tx| async move {
+        let mut buf = Vec::new();
+        while let Ok(Some(chunk)) = resp.chunk().await {
+            buf.extend_from_slice(&chunk);
+            for event in take_sse_events(&mut buf) {
+                if tx.send(event).await.is_err() {
+                    // The browser went away.
+                    return;
+                }
+            }
+        }
+    }))
+}
+
+/// The log of `pod`, from its last `tail` lines on and followed as it grows.
+/// The connection to the API server closes when the browser drops the
+/// stream.
+#[post("/api/ui/pod-logs")]
+pub async fn follow_logs(
+    settings: ApiSettings,
+    ns: String,
+    pod: String,
+    container: Option<String>,
+    tail: usize,
+) -> Result<TextStream, ApiError> {
+    let mut req = ApiRequest::pod(&settings, &ns, &pod, "logs")?
+        .query("follow", "true")
+        .query("tail", &tail.to_string());
+    if let Some(container) = &container {
+        req = req.query("container", container);
+    }
+    let resp = open_stream(&req).await?;
+    let text = futures_util::stream::unfold((resp, Vec::new()), |(mut resp, mut buf)| async move {
+        let chunk = resp.chunk().await.ok()??;
+        buf.extend_from_slice(&chunk);
+        let text = take_utf8(&mut buf);
+        Some((text, (resp, buf)))
+    });
+    Ok(TextStream::new(text))
+}
+
+/// What the browser sends on the [`exec`] socket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExecInput {
+    /// Sent first: where to start the shell. The settings come in-band so
+    /// the token stays out of the socket's URL.
+    Start {
+        settings: ApiSettings,
+        namespace: String,
+        pod: String,
+        container: Option<String>,
+    },
+    /// Input for the shell, e.g. a line typed into the pane.
+    Stdin(String),
+}
+
+/// What the [`exec`] socket sends back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExecOutput {
+    /// What the shell wrote, or a status line from the API server.
+    Output(String),
+    /// The session could not be started; the socket closes after this.
+    Failed(ApiError),
+}
+
+/// Started by [`exec`]. It gets no TTY, so it prints no prompt and reads
+/// whole lines.
+#[cfg(feature = "server")]
+const EXEC_SHELL: &str = "sh";
+
+/// A shell in a pod, relayed to the API server's exec WebSocket.
+#[get("/api/ui/exec")]
+pub async fn exec(options: WebSocketOptions) -> Result<Websocket<ExecInput, ExecOutput>, ApiError> {
+    use dioxus::fullstack::TypedWebsocket;
+
+    Ok(options.on_upgrade(
+        |mut socket: TypedWebsocket<ExecInput, ExecOutput>| async move {
+            let Ok(ExecInput::Start {
+                settings,
+                namespace,
+                pod,
+                container,
+            }) = socket.recv().await
+            else {
+                return;
+            };
+            match connect_exec(&settings, &namespace, &pod, container.as_deref()).await {
+                Ok(upstream) => relay_exec(socket, upstream).await,
+                Err(err) => {
+                    let _ = socket.send(ExecOutput::Failed(err)).await;
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(feature = "server")]
+type ExecStream =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Open the exec WebSocket of `pod`, running [`EXEC_SHELL`].
+#[cfg(feature = "server")]
+async fn connect_exec(
+    settings: &ApiSettings,
+    ns: &str,
+    pod: &str,
+    container: Option<&str>,
+) -> Result<ExecStream, ApiError> {
+    use tokio_tungstenite::tungstenite::{client::IntoClientRequest, http::HeaderValue, Error};
+
+    let mut req = ApiRequest::pod(settings, ns, pod, "exec")?
+        .websocket()?
+        .query("cmd", EXEC_SHELL);
+    if let Some(container) = container {
+        req = req.query("container", container);
+    }
+    let mut request = req
+        .url
+        .as_str()
+        .into_client_request()
+        .map_err(|e| ApiError::Invalid(e.to_string()))?;
+    if let Some(token) = &req.token {
+        let value = HeaderValue::from_str(&format!("Bearer {}", token))
+            .map_err(|_| ApiError::Invalid("the token is not a valid header value".to_string()))?;
+        request.headers_mut().insert("Authorization", value);
+    }
+    match tokio_tungstenite::connect_async(request).await {
+        Ok((stream, _)) => Ok(stream),
+        Err(Error::Http(resp)) => {
+            let body = resp.body().as_deref().unwrap_or_default();
+            Err(ApiError::from_response(
+                resp.status().as_u16(),
+                &String::from_utf8_lossy(body),
+            ))
+        }
+        Err(e) => Err(ApiError::Unreachable(e.to_string())),
+    }
+}
+
+/// Pass input to the shell and its output back until either side closes.
+#[cfg(feature = "server")]
+async fn relay_exec(
+    mut socket: dioxus::fullstack::TypedWebsocket<ExecInput, ExecOutput>,
+    upstream: ExecStream,
+) {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    let (mut up_tx, mut up_rx) = upstream.split();
+    let mut buf = Vec::new();
+    loop {
+        tokio::select! {
+            input = socket.recv() => match input {
+                Ok(ExecInput::Stdin(text)) => {
+                    if up_tx.send(Message::Binary(text.into_bytes().into())).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(ExecInput::Start { .. }) => {}
+                // The browser closed the pane.
+                Err(_) => break,
+            },
+            msg = up_rx.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Binary(bytes))) => {
+                        buf.extend_from_slice(&bytes);
+                        take_utf8(&mut buf)
+                    }
+                    Some(Ok(Message::Text(text))) => text.to_string(),
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                if socket.send(ExecOutput::Output(text)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = up_tx.send(Message::Close(None)).await;
+}
+
+#[post("/api/ui/node")]
+pub async fn get_node(settings: ApiSettings, name: String) -> Result<Node, ApiError> {
+    get_json(ApiRequest::node(&settings, &name, None)?).await
+}
+
+/// The pods placed on node `name`, across namespaces.
+#[post("/api/ui/node-pods")]
+pub async fn get_node_pods(settings: ApiSettings, name: String) -> Result<Vec<Pod>, ApiError> {
+    let req = ApiRequest::new(&settings, "/api/v1/pods")?
+        .query("fieldSelector", &format!("spec.nodeName={}", name));
+    get_json(req).await
+}
+
+/// Stop scheduling new pods on node `name`.
+#[post("/api/ui/node-cordon")]
+pub async fn cordon_node(settings: ApiSettings, name: String) -> Result<(), ApiError> {
+    let req = ApiRequest::node(&settings, &name, Some("cordon"))?.method(reqwest::Method::POST);
+    change::<serde_json::Value>(req).await.map(drop)
+}
+
+#[post("/api/ui/node-uncordon")]
+pub async fn uncordon_node(settings: ApiSettings, name: String) -> Result<(), ApiError> {
+    let req = ApiRequest::node(&settings, &name, Some("uncordon"))?.method(reqwest::Method::POST);
+    change::<serde_json::Value>(req).await.map(drop)
+}
+
+/// Cordon node `name` and evict its pods. The API server answers once they
+/// are gone or its drain timeout passed, so this waits as long.
+#[post("/api/ui/node-drain")]
+pub async fn drain_node(settings: ApiSettings, name: String) -> Result<DrainReport, ApiError> {
+    let wait = pkg_constants::timings::DRAIN_TIMEOUT_SECS + 30;
+    let req = ApiRequest::node(&settings, &name, Some("drain"))?
+        .method(reqwest::Method::POST)
+        .timeout(std::time::Duration::from_secs(wait));
+    change(req).await
+}
+
+/// Add `taint` to node `name`, or change the value of the one with its key
+/// and effect.
+#[post("/api/ui/node-taint")]
+pub async fn add_node_taint(
+    settings: ApiSettings,
+    name: String,
+    taint: Taint,
+) -> Result<Node, ApiError> {
+    let req = ApiRequest::node(&settings, &name, Some("taints"))?.send_body(
+        reqwest::Method::PUT,
+        "application/json",
+        &taint,
+    )?;
+    change(req).await
+}
+
+#[post("/api/ui/node-untaint")]
+pub async fn remove_node_taint(
+    settings: ApiSettings,
+    name: String,
+    key: String,
+    effect: String,
+) -> Result<Node, ApiError> {
+    let req = ApiRequest::node(&settings, &name, Some("taints"))?
+        .query("key", &key)
+        .query("effect", &effect)
+        .method(reqwest::Method::DELETE);
+    change(req).await
+}
+
+/// Set the labels of node `name` given a value, and remove those given
+/// `None`.
+#[post("/api/ui/node-labels")]
+pub async fn patch_node_labels(
+    settings: ApiSettings,
+    name: String,
+    labels: std::collections::BTreeMap<String, Option<String>>,
+) -> Result<Node, ApiError> {
+    let req = ApiRequest::node(&settings, &name, None)?.send_body(
+        reqwest::Method::PATCH,
+        MERGE_PATCH,
+        &serde_json::json!({ "labels": labels }),
+    )?;
+    change(req).await
+}
+
+/// What [`apply_manifest`] did.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Applied {
+    /// The object is new, rather than an update of a stored one.
+    pub created: bool,
+    /// The object as stored, or as it would be stored after a dry run.
+    pub yaml: String,
+}
+
+/// Create or update the object in the manifest `yaml`, in the namespace it
+/// names or else in `ns`. A manifest carrying a `resource_version`, as an
+/// edited one does, is rejected if the object changed since; without one
+/// it replaces what is stored, like `k3rsctl apply`. With `dry_run` the
+/// server validates the object without storing it.
+#[post("/api/ui/apply")]
+pub async fn apply_manifest(
+    settings: ApiSettings,
+    ns: String,
+    yaml: String,
+    dry_run: bool,
+) -> Result<Applied, ApiError> {
+    let manifest = Manifest::parse(&yaml).map_err(|a| ApiError::Rejected {
+        status: 400,
+        message: a.message,
+        details: Vec::new(),
+    })?;
+    let ns = manifest.namespace.unwrap_or(ns);
+    let req = ApiRequest::object(&settings, manifest.kind, &ns, &manifest.name)?;
+    let mut object = manifest.object;
+    if manifest.kind.namespaced() {
+        object["namespace"] = ns.into();
+    }
+    if object.get("resource_version").is_none() {
+        let version = match get_json::<serde_json::Value>(req.clone()).await {
+            Ok(stored) => stored["resource_version"].as_u64().unwrap_or(0),
+            Err(ApiError::NotFound(_)) => 0,
+            Err(err) => return Err(err),
+        };
+        object["resource_version"] = version.into();
+    }
+    let mut req = req.send_body(reqwest::Method::PUT, "application/json", &object)?;
+    if dry_run {
+        req = req.query("dryRun", "All");
+    }
+    let resp = send(&HttpTransport, &req, ONCE).await?;
+    Ok(Applied {
+        created: resp.status == 201,
+        yaml: to_yaml(manifest.kind, &decode(&resp)?)?,
+    })
+}
+
+/// Object `name` of `kind` as a manifest to edit.
+#[post("/api/ui/manifest")]
+pub async fn get_manifest(
+    settings: ApiSettings,
+    kind: ManifestKind,
+    ns: String,
+    name: String,
+) -> Result<String, ApiError> {
+    let object: serde_json::Value =
+        get_json(ApiRequest::object(&settings, kind, &ns, &name)?).await?;
+    to_yaml(kind, &object)
+}
+
+/// The buffered changes to the store, across namespaces.
+#[post("/api/ui/watch-events")]
+pub async fn get_watch_events(settings: ApiSettings) -> Result<Vec<WatchEvent>, ApiError> {
+    get_json(ApiRequest::new(&settings, "/api/v1/events")?).await
+}
+
+#[post("/api/ui/metrics")]
+pub async fn get_metrics(settings: ApiSettings) -> Result<String, ApiError> {
+    let req = ApiRequest::new(&settings, "/metrics")?;
+    Ok(send(&HttpTransport, &req, RETRY).await?.body)
+}
+
+#[post("/api/ui/processes")]
+pub async fn get_processes(settings: ApiSettings) -> Result<Vec<ProcessInfo>, ApiError> {
+    get_json(ApiRequest::new(&settings, "/api/v1/processes")?).await
+}
+
+#[post("/api/ui/vpcs")]
+pub async fn get_vpcs(settings: ApiSettings) -> Result<Vec<Vpc>, ApiError> {
+    get_json(ApiRequest::new(&settings, "/api/v1/vpcs")?).await
+}
+
+#[post("/api/ui/vpc-peerings")]
+pub async fn get_vpc_peerings(settings: ApiSettings) -> Result<Vec<VpcPeering>, ApiError> {
+    get_json(ApiRequest::new(&settings, "/api/v1/vpc-peerings")?).await
+}
+
+#[post("/api/ui/images")]
+pub async fn get_images(settings: ApiSettings) -> Result<Vec<ImageInfo>, ApiError> {
+    get_json(ApiRequest::new(&settings, "/api/v1/images")?).await
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Answers with `responses` in order and records what was sent.
+    #[derive(Default)]
+    struct MockTransport {
+        responses: Mutex<Vec<Result<RawResponse, ApiError>>>,
+        sent: Mutex<Vec<ApiRequest>>,
+    }
+
+    impl MockTransport {
+        fn new(mut responses: Vec<Result<RawResponse, ApiError>>) -> Self {
+            responses.reverse();
+            Self {
+                responses: Mutex::new(responses),
+                ..Default::default()
+            }
+        }
+
+        fn sent(&self) -> Vec<String> {
+            let sent = self.sent.lock().unwrap();
+            sent.iter().map(|r| r.url.to_string()).collect()
+        }
+    }
+
+    impl Transport for MockTransport {
+        async fn send(&self, req: &ApiRequest) -> Result<RawResponse, ApiError> {
+            self.sent.lock().unwrap().push(req.clone());
+            self.responses
+                .lock()
+                .unwrap()
+                .pop()
+                .expect("unexpected request")
+        }
+    }
+
+    fn status(status: u16, body: &str) -> Result<RawResponse, ApiError> {
+        Ok(RawResponse {
+            status,
+            body: body.to_string(),
+            ..Default::default()
+        })
+    }
+
+    const NO_WAIT: RetryPolicy = RetryPolicy {
+        attempts: 3,
+        backoff: Duration::ZERO,
+    };
+
+    fn settings(server: &str, token: &str) -> ApiSettings {
+        ApiSettings {
+            server: server.to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_request_construction() {
+        let s = settings("http://10.0.0.1:6443/", " secret ");
+        let req = ApiRequest::new(&s, "/api/v1/nodes").unwrap();
+        assert_eq!(req.url.as_str(), "http://10.0.0.1:6443/api/v1/nodes");
+        assert_eq!(req.token.as_deref(), Some("secret"));
+
+        let req = ApiRequest::namespaced(&s, "k3rs-system", "networkpolicies").unwrap();
+        assert_eq!(
+            req.url.as_str(),
+            "http://10.0.0.1:6443/api/v1/namespaces/k3rs-system/networkpolicies"
+        );
+
+        let req = ApiRequest::namespaced(&settings("http://h:6443", ""), "a/b", "pods")
+            .unwrap()
+            .query("continue", "x&y=z");
+        assert_eq!(
+            req.url.as_str(),
+            "http://h:6443/api/v1/namespaces/a%2Fb/pods?continue=x%26y%3Dz"
+        );
+        assert_eq!(req.token, None);
+
+        assert!(matches!(
+            ApiRequest::new(&settings("not a url", "t"), "/api/v1/nodes"),
+            Err(ApiError::Invalid(_))
+        ));
+        assert!(matches!(
+            ApiRequest::namespaced(&s, "", "pods"),
+            Err(ApiError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_pod_logs_and_exec_urls() {
+        let s = settings("https://h:6443", "t");
+        let req = ApiRequest::pod(&s, "default", "web-0", "logs")
+            .unwrap()
+            .query("follow", "true");
+        assert_eq!(
+            req.url.as_str(),
+            "https://h:6443/api/v1/namespaces/default/pods/web-0/logs?follow=true"
+        );
+
+        let req = ApiRequest::pod(&s, "default", "web-0", "exec")
+            .unwrap()
+            .websocket()
+            .unwrap();
+        assert_eq!(
+            req.url.as_str(),
+            "wss://h:6443/api/v1/namespaces/default/pods/web-0/exec"
+        );
+        let req = ApiRequest::new(&settings("http://h:6443", ""), "/x")
+            .unwrap()
+            .websocket()
+            .unwrap();
+        assert_eq!(req.url.scheme(), "ws");
+    }
+
+    #[test]
+    fn test_object_urls() {
+        let s = settings("http://h:6443", "t");
+        let req = ApiRequest::object(&s, ManifestKind::HorizontalPodAutoscaler, "prod", "web")
+            .unwrap()
+            .query("dryRun", "All");
+        assert_eq!(
+            req.url.as_str(),
+            "http://h:6443/api/v1/namespaces/prod/hpa/web?dryRun=All"
+        );
+        // Cluster-wide kinds are not under a namespace.
+        let req = ApiRequest::object(&s, ManifestKind::PriorityClass, "prod", "high").unwrap();
+        assert_eq!(
+            req.url.as_str(),
+            "http://h:6443/api/v1/priorityclasses/high"
+        );
+    }
+
+    #[test]
+    fn test_utf8_split_across_chunks() {
+        // "é" is 0xC3 0xA9.
+        let mut buf = b"caf\xC3".to_vec();
+        assert_eq!(take_utf8(&mut buf), "caf");
+        assert_eq!(buf, b"\xC3");
+        buf.extend_from_slice(b"\xA9\n");
+        assert_eq!(take_utf8(&mut buf), "\u{e9}\n");
+        assert!(buf.is_empty());
+
+        let mut buf = b"a\xFFb".to_vec();
+        assert_eq!(take_utf8(&mut buf), "a\u{fffd}b");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_error_mapping() {
+        let body = r#"{"code":401,"reason":"Unauthorized","message":"invalid token","details":[]}"#;
+        assert_eq!(
+            ApiError::from_response(401, body),
+            ApiError::Unauthorized("invalid token".into())
+        );
+        assert_eq!(
+            ApiError::from_response(403, "forbidden by role"),
+            ApiError::Forbidden("forbidden by role".into())
+        );
+        assert_eq!(
+            ApiError::from_response(409, ""),
+            ApiError::Rejected {
+                status: 409,
+                message: "request failed with status 409".into(),
+                details: Vec::new(),
+            }
+        );
+        let body = r#"{"code":422,"reason":"Invalid","message":"pod 'web' is invalid","details":[{"field":"spec.containers[0].image","message":"must not be empty"}]}"#;
+        let ApiError::Rejected { details, .. } = ApiError::from_response(422, body) else {
+            panic!("not a rejection");
+        };
+        assert_eq!(details[0].field, "spec.containers[0].image");
+        let err = ApiError::from_response(503, r#"{"message":"leader election"}"#);
+        assert!(err.is_transient());
+        assert_eq!(err.as_status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!ApiError::from_response(500, "boom").is_transient());
+        assert!(ApiError::from_response(429, "slow down").is_transient());
+        assert!(!ApiError::NotFound("pod".into()).is_transient());
+
+        // Typed errors survive the trip through the server function.
+        let sfe = ServerFnError::ServerError {
+            message: "invalid token".into(),
+            code: 401,
+            details: None,
+        };
+        assert_eq!(
+            ApiError::from(sfe),
+            ApiError::Unauthorized("invalid token".into())
+        );
+        let err = ApiError::Server {
+            status: 502,
+            message: "node unreachable".into(),
+        };
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(serde_json::from_value::<ApiError>(json).unwrap(), err);
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried() {
+        let mock = MockTransport::new(vec![
+            Err(ApiError::Unreachable("connection refused".into())),
+            status(503, "starting"),
+            status(200, "[]"),
+        ]);
+        let req = ApiRequest::new(&settings("http://h:6443", "t"), "/api/v1/nodes").unwrap();
+        let resp = send(&mock, &req, NO_WAIT).await.unwrap();
+        assert_eq!(resp.status, 200);
+        assert_eq!(mock.sent().len(), 3);
+
+        // Gives up after the last attempt.
+        let mock = MockTransport::new(vec![status(502, "a"), status(502, "b"), status(502, "c")]);
+        let err = send(&mock, &req, NO_WAIT).await.unwrap_err();
+        assert_eq!(
+            err,
+            ApiError::Server {
+                status: 502,
+                message: "c".into()
+            }
+        );
+
+        // Other failures are returned at once.
+        let mock = MockTransport::new(vec![status(401, r#"{"message":"no token"}"#)]);
+        let err = send(&mock, &req, NO_WAIT).await.unwrap_err();
+        assert_eq!(err, ApiError::Unauthorized("no token".into()));
+        assert_eq!(mock.sent().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_node_changes_are_sent_once() {
+        let s = settings("http://h:6443", "t");
+        let req = ApiRequest::node(&s, "worker-1", Some("cordon"))
+            .unwrap()
+            .method(reqwest::Method::POST);
+        assert_eq!(
+            req.url.as_str(),
+            "http://h:6443/api/v1/nodes/worker-1/cordon"
+        );
+        let mock = MockTransport::new(vec![status(502, "bad gateway")]);
+        assert!(send(&mock, &req, ONCE).await.is_err());
+        assert_eq!(mock.sent().len(), 1);
+
+        let labels: std::collections::BTreeMap<_, _> =
+            [("zone".to_string(), None::<String>)].into();
+        let req = ApiRequest::node(&s, "worker-1", None)
+            .unwrap()
+            .send_body(
+                reqwest::Method::PATCH,
+                MERGE_PATCH,
+                &serde_json::json!({ "labels": labels }),
+            )
+            .unwrap();
+        assert_eq!(req.method, reqwest::Method::PATCH);
+        assert_eq!(req.url.as_str(), "http://h:6443/api/v1/nodes/worker-1");
+        assert_eq!(
+            req.body,
+            Some((MERGE_PATCH, r#"{"labels":{"zone":null}}"#.to_string()))
+        );
+
+        assert!(matches!(
+            ApiRequest::node(&s, "", None),
+            Err(ApiError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_sse_events_are_split_across_chunks() {
+        let mut buf = b": keep-alive\n\nid: 4\ndata: {\"seq\":4,\"event_type\":\"Put\",".to_vec();
+        assert!(take_sse_events(&mut buf).is_empty());
+        buf.extend_from_slice(
+            b"\"key\":\"/registry/pods/default/a\",\"value\":[123,125]}\n\nid: 5\n",
+        );
+        let events = take_sse_events(&mut buf);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].seq, 4);
+        assert_eq!(events[0].value.as_deref(), Some(&b"{}"[..]));
+        assert_eq!(buf, b"id: 5\n");
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_carries_watch_seq() {
+        let mock = MockTransport::new(vec![Ok(RawResponse {
+            status: 200,
+            watch_seq: Some(42),
+            body: "[]".into(),
+            ..Default::default()
+        })]);
+        let req =
+            ApiRequest::namespaced(&settings("http://h:6443", "t"), "prod", "events").unwrap();
+        let snapshot: Snapshot<ObjectEvent> = get_snapshot(&mock, req).await.unwrap();
+        assert_eq!(snapshot.seq, 42);
+        assert!(snapshot.items.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_page_request_and_decoding() {
+        let mock = MockTransport::new(vec![Ok(RawResponse {
+            status: 200,
+            continue_token: Some("next".into()),
+            watch_seq: None,
+            body: r#"[{"id":"1","name":"web-0","namespace":"prod","status":"Running"}]"#.into(),
+        })]);
+        let req = ApiRequest::namespaced(&settings("http://h:6443", "t"), "prod", "pods").unwrap();
+        let page: ListPage<Pod> = get_page(&mock, req.clone(), 50, Some("abc".into()))
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].name, "web-0");
+        assert_eq!(page.continue_token.as_deref(), Some("next"));
+        assert_eq!(
+            mock.sent(),
+            vec!["http://h:6443/api/v1/namespaces/prod/pods?limit=50&continue=abc"]
+        );
+
+        let mock = MockTransport::new(vec![status(200, "<html>")]);
+        let err = get_page::<Pod>(&mock, req, 50, None).await.unwrap_err();
+        assert!(matches!(err, ApiError::Invalid(_)), "{:?}", err);
+    }
 }
@@ -0,0 +1,355 @@
+//! The YAML editor dialog: apply a new manifest, or edit an object as the
+//! API server has it. Opened from the sidebar and from list rows.
+
+use dioxus::prelude::*;
+use dioxus_free_icons::icons::ld_icons::*;
+use dioxus_free_icons::Icon;
+// The prelude's `Ok` is fixed to `CapturedError`.
+use std::result::Result::Ok;
+
+use crate::api::{self, ApiSettings};
+use crate::manifest::{annotate, Annotation, Manifest, ManifestKind};
+
+/// What the editor was opened on.
+#[derive(Debug, Clone, PartialEq)]
+enum Opened {
+    New,
+    Object {
+        kind: ManifestKind,
+        namespace: String,
+        name: String,
+    },
+}
+
+/// Opens the editor. The layout provides it; list pages get it with
+/// `use_context::<Editor>()`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Editor {
+    opened: Signal<Option<Opened>>,
+    applied: Signal<u64>,
+}
+
+/// Must be created inside a component, like any signal.
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            opened: Signal::new(None),
+            applied: Signal::new(0),
+        }
+    }
+}
+
+impl Editor {
+    /// Open the editor on an empty manifest.
+    pub fn create(mut self) {
+        self.opened.set(Some(Opened::New));
+    }
+
+    /// Open the editor on object `name` of `kind`, as it is stored now.
+    pub fn edit(mut self, kind: ManifestKind, namespace: String, name: String) {
+        self.opened.set(Some(Opened::Object {
+            kind,
+            namespace,
+            name,
+        }));
+    }
+
+    fn close(mut self) {
+        self.opened.set(None);
+    }
+
+    /// Counts the applied manifests; list pages read it in their resource
+    /// to load again after one.
+    pub fn applied(self) -> u64 {
+        (self.applied)()
+    }
+}
+
+const PLACEHOLDER: &str = "kind: Deployment\nname: web\nspec:\n  replicas: 2\n  ...";
+
+/// The dialog, while the editor is open.
+#[component]
+pub fn ManifestEditor() -> Element {
+    let editor = use_context::<Editor>();
+    let opened = editor.opened.read().clone();
+    rsx! {
+        if let Some(opened) = opened {
+            EditorDialog { opened }
+        }
+    }
+}
+
+#[component]
+fn EditorDialog(opened: Opened) -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let mut namespace = use_context::<Signal<String>>();
+    let mut editor = use_context::<Editor>();
+    let mut text = use_signal(String::new);
+    let mut loading = use_signal(|| matches!(opened, Opened::Object { .. }));
+    let mut busy = use_signal(|| false);
+    // From the last dry run or apply; cleared by editing.
+    let mut annotations = use_signal(Vec::<Annotation>::new);
+    let mut note = use_signal(|| None::<String>);
+
+    let title = match &opened {
+        Opened::New => "Create / Apply".to_string(),
+        Opened::Object { kind, name, .. } => format!("Edit {} {}", kind.name(), name),
+    };
+    use_hook(|| {
+        if let Opened::Object {
+            kind,
+            namespace,
+            name,
+        } = opened.clone()
+        {
+            spawn(async move {
+                let settings = settings.peek().clone();
+                match api::get_manifest(settings, kind, namespace, name).await {
+                    Ok(yaml) => text.set(yaml),
+                    Err(err) => annotations.set(annotate("", &err)),
+                }
+                loading.set(false);
+            });
+        }
+    });
+
+    let parsed = use_memo(move || Manifest::parse(&text.read()));
+
+    let mut submit = move |dry_run: bool| {
+        let Ok(manifest) = parsed() else {
+            return;
+        };
+        let settings = settings.read().clone();
+        let (ns, yaml) = (namespace(), text());
+        busy.set(true);
+        note.set(None);
+        spawn(async move {
+            let result = api::apply_manifest(settings, ns.clone(), yaml.clone(), dry_run).await;
+            busy.set(false);
+            let applied = match result {
+                Ok(applied) => applied,
+                Err(err) => {
+                    annotations.set(annotate(&yaml, &err));
+                    return;
+                }
+            };
+            annotations.set(Vec::new());
+            let what = format!("{} {}", manifest.kind.name(), manifest.name);
+            if dry_run {
+                note.set(Some(format!("{} is valid", what)));
+                return;
+            }
+            editor.applied += 1;
+            match manifest.kind.list_route() {
+                Some(route) => {
+                    if let Some(ns) = manifest.namespace {
+                        namespace.set(ns);
+                    }
+                    editor.close();
+                    navigator().push(route);
+                }
+                None => {
+                    let verb = if applied.created {
+                        "created"
+                    } else {
+                        "updated"
+                    };
+                    note.set(Some(format!("{} {}", what, verb)));
+                    text.set(applied.yaml);
+                }
+            }
+        });
+    };
+
+    // A manifest that does not parse is marked as it is typed.
+    let shown: Vec<Annotation> = match &*parsed.read() {
+        Err(err) if !text.read().trim().is_empty() => vec![err.clone()],
+        _ => annotations.read().clone(),
+    };
+    let can_send = parsed.read().is_ok() && !busy() && !loading();
+    let content = text.read();
+    let line_count = content.split('\n').count();
+    let line_class = |n: usize| {
+        if shown.iter().any(|a| a.line == Some(n)) {
+            "text-red-400 bg-red-500/10"
+        } else {
+            "text-slate-600"
+        }
+    };
+
+    rsx! {
+        div { class: "fixed inset-0 z-40 flex items-center justify-center bg-black/60",
+            div { class: "bg-slate-900 border border-slate-700 rounded-xl w-[52rem] max-w-[95vw] shadow-xl flex flex-col",
+                div { class: "flex items-center gap-3 px-5 py-3.5 border-b border-slate-800",
+                    Icon { width: 15, height: 15, icon: LdFileCode }
+                    h3 { class: "text-sm font-semibold text-white", "{title}" }
+                    match &*parsed.read() {
+                        Ok(m) => rsx! {
+                            span { class: "px-2 py-0.5 rounded bg-blue-500/10 text-[11px] font-medium text-blue-400",
+                                "{m.kind.name()}"
+                            }
+                            if m.kind.namespaced() {
+                                span { class: "text-xs text-slate-500",
+                                    "{m.namespace.clone().unwrap_or(namespace())}/{m.name}"
+                                }
+                            } else {
+                                span { class: "text-xs text-slate-500", "{m.name}" }
+                            }
+                        },
+                        Err(_) => rsx! {},
+                    }
+                    button {
+                        class: "ml-auto text-slate-500 hover:text-slate-300",
+                        onclick: move |_| editor.close(),
+                        Icon { width: 14, height: 14, icon: LdX }
+                    }
+                }
+
+                div { class: "flex max-h-[60vh] overflow-auto bg-slate-950 font-mono text-xs leading-5",
+                    div { class: "select-none text-right py-3 shrink-0",
+                        for n in 1..=line_count {
+                            div {
+                                class: "px-3 {line_class(n)}",
+                                title: shown.iter().filter(|a| a.line == Some(n)).map(|a| a.message.as_str()).collect::<Vec<_>>().join("\n"),
+                                "{n}"
+                            }
+                        }
+                    }
+                    div { class: "relative flex-1 min-w-0",
+                        pre { class: "absolute inset-0 px-3 py-3 whitespace-pre pointer-events-none text-slate-300",
+                            for line in content.split('\n') {
+                                div { class: "h-5",
+                                    for (class, token) in highlight(line) {
+                                        span { class, "{token}" }
+                                    }
+                                }
+                            }
+                        }
+                        textarea {
+                            class: "relative w-full px-3 py-3 bg-transparent text-transparent caret-slate-200 whitespace-pre resize-none outline-none overflow-hidden placeholder:text-slate-700",
+                            rows: "{line_count.max(12)}",
+                            wrap: "off",
+                            spellcheck: "false",
+                            disabled: loading(),
+                            placeholder: if loading() { "Loading..." } else { PLACEHOLDER },
+                            value: "{content}",
+                            oninput: move |evt| {
+                                text.set(evt.value());
+                                annotations.set(Vec::new());
+                                note.set(None);
+                            },
+                        }
+                    }
+                }
+
+                if !shown.is_empty() {
+                    div { class: "px-5 py-3 border-t border-slate-800 space-y-1 max-h-40 overflow-y-auto",
+                        for a in shown.iter() {
+                            p { class: "flex items-start gap-2 text-xs text-red-300",
+                                Icon { class: "shrink-0 mt-0.5", width: 12, height: 12, icon: LdTriangleAlert }
+                                if let Some(line) = a.line {
+                                    span { class: "text-red-400 font-mono shrink-0", "line {line}" }
+                                }
+                                span { class: "break-words", "{a.message}" }
+                            }
+                        }
+                    }
+                }
+                if let Some(note) = note() {
+                    div { class: "flex items-center gap-2 px-5 py-3 border-t border-slate-800 text-xs text-emerald-300",
+                        Icon { width: 12, height: 12, icon: LdCircleCheck }
+                        "{note}"
+                    }
+                }
+
+                div { class: "flex justify-end gap-2 px-5 py-3.5 border-t border-slate-800",
+                    button {
+                        class: "px-4 py-2 text-sm rounded-lg text-slate-400 hover:text-slate-200",
+                        onclick: move |_| editor.close(),
+                        "Cancel"
+                    }
+                    button {
+                        class: "flex items-center gap-1.5 px-4 py-2 text-sm rounded-lg bg-slate-800 text-slate-300 hover:bg-slate-700 disabled:opacity-40 disabled:cursor-not-allowed",
+                        disabled: !can_send,
+                        onclick: move |_| submit(true),
+                        Icon { width: 13, height: 13, icon: LdFileCheck }
+                        "Dry run"
+                    }
+                    button {
+                        class: "flex items-center gap-1.5 px-4 py-2 text-sm rounded-lg bg-blue-600 text-white hover:bg-blue-500 disabled:opacity-40 disabled:cursor-not-allowed",
+                        disabled: !can_send,
+                        onclick: move |_| submit(false),
+                        if busy() {
+                            Icon { class: "animate-spin", width: 13, height: 13, icon: LdLoaderCircle }
+                        } else {
+                            Icon { width: 13, height: 13, icon: LdUpload }
+                        }
+                        "Apply"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Button on a list row opening the editor on the row's object.
+#[component]
+pub fn EditYamlButton(kind: ManifestKind, namespace: String, name: String) -> Element {
+    let editor = use_context::<Editor>();
+    rsx! {
+        button {
+            class: "text-slate-500 hover:text-blue-400 transition-colors",
+            title: "Edit YAML",
+            onclick: move |_| editor.edit(kind, namespace.clone(), name.clone()),
+            Icon { width: 13, height: 13, icon: LdFilePen }
+        }
+    }
+}
+
+/// `line` split into pieces with the class to color each in: keys,
+/// strings, numbers and keywords, and comments.
+fn highlight(line: &str) -> Vec<(&'static str, String)> {
+    let mut pieces = Vec::new();
+    let mut rest = line;
+    let indent = rest.len() - rest.trim_start().len();
+    pieces.push(("", rest[..indent].to_string()));
+    rest = &rest[indent..];
+    while let Some(item) = rest.strip_prefix("- ") {
+        pieces.push(("text-slate-500", "- ".to_string()));
+        rest = item;
+    }
+    if rest.starts_with('#') {
+        pieces.push(("text-slate-600 italic", rest.to_string()));
+        return pieces;
+    }
+    let (value, comment) = match rest.find(" #") {
+        Some(at) if !rest[..at].contains(['"', '\'']) => rest.split_at(at),
+        _ => (rest, ""),
+    };
+    let value = match value.find(": ").or(value
+        .ends_with(':')
+        .then_some(value.len().saturating_sub(1)))
+    {
+        Some(at)
+            if !value.starts_with(['"', '\'', '{', '[']) || value[..at].ends_with(['"', '\'']) =>
+        {
+            pieces.push(("text-sky-400", value[..at].to_string()));
+            pieces.push(("text-slate-500", ":".to_string()));
+            &value[at + 1..]
+        }
+        _ => value,
+    };
+    let trimmed = value.trim();
+    let class = if trimmed.starts_with(['"', '\'']) {
+        "text-amber-300"
+    } else if trimmed.parse::<f64>().is_ok() || ["true", "false", "null", "~"].contains(&trimmed) {
+        "text-violet-400"
+    } else {
+        "text-emerald-300"
+    };
+    pieces.push((class, value.to_string()));
+    if !comment.is_empty() {
+        pieces.push(("text-slate-600 italic", comment.to_string()));
+    }
+    pieces
+}
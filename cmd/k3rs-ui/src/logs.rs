@@ -0,0 +1,149 @@
+//! Text streamed into the pod page's logs and exec panes, kept to a fixed
+//! number of lines.
+
+use std::collections::VecDeque;
+
+/// Lines a pane keeps; older ones are dropped.
+pub const MAX_LINES: usize = 5000;
+
+/// Lines of streamed text. While paused, new lines are held back so the
+/// pane stays put, and shown once following resumes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogBuffer {
+    lines: VecDeque<String>,
+    held: VecDeque<String>,
+    /// Text after the last newline, completed by a later chunk.
+    partial: String,
+    cap: usize,
+    following: bool,
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(MAX_LINES)
+    }
+}
+
+impl LogBuffer {
+    /// An empty buffer of at most `cap` lines, following.
+    pub fn new(cap: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            held: VecDeque::new(),
+            partial: String::new(),
+            cap: cap.max(1),
+            following: true,
+        }
+    }
+
+    /// Add a chunk of the stream; it may end, or start, mid-line.
+    pub fn push(&mut self, chunk: &str) {
+        self.partial.push_str(chunk);
+        let Some(end) = self.partial.rfind('\n') else {
+            return;
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        for line in complete.lines() {
+            let line = line.to_string();
+            if self.following {
+                push_capped(&mut self.lines, line, self.cap);
+            } else {
+                push_capped(&mut self.held, line, self.cap);
+            }
+        }
+    }
+
+    /// The lines to show, oldest first. A line still being written is
+    /// included while following.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        let partial = (self.following && !self.partial.is_empty()).then_some(self.partial.as_str());
+        self.lines.iter().map(String::as_str).chain(partial)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines().next().is_none()
+    }
+
+    /// Lines received since pausing.
+    pub fn held(&self) -> usize {
+        self.held.len()
+    }
+
+    pub fn following(&self) -> bool {
+        self.following
+    }
+
+    /// Pause or resume; resuming shows the held lines.
+    pub fn set_following(&mut self, following: bool) {
+        self.following = following;
+        if following {
+            for line in std::mem::take(&mut self.held) {
+                push_capped(&mut self.lines, line, self.cap);
+            }
+        }
+    }
+
+    pub fn toggle_following(&mut self) {
+        self.set_following(!self.following);
+    }
+}
+
+fn push_capped(lines: &mut VecDeque<String>, line: String, cap: usize) {
+    if lines.len() == cap {
+        lines.pop_front();
+    }
+    lines.push_back(line);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shown(buf: &LogBuffer) -> Vec<&str> {
+        buf.lines().collect()
+    }
+
+    #[test]
+    fn test_lines_are_capped() {
+        let mut buf = LogBuffer::new(3);
+        buf.push("1\n2\n3\n4\n5\n");
+        assert_eq!(shown(&buf), ["3", "4", "5"]);
+    }
+
+    #[test]
+    fn test_lines_split_across_chunks() {
+        let mut buf = LogBuffer::new(10);
+        buf.push("starting serv");
+        assert_eq!(shown(&buf), ["starting serv"]);
+        buf.push("er\r\nlistening on :80");
+        buf.push("80\n");
+        assert_eq!(shown(&buf), ["starting server", "listening on :8080"]);
+    }
+
+    #[test]
+    fn test_pausing_holds_new_lines() {
+        let mut buf = LogBuffer::new(10);
+        buf.push("a\n");
+        buf.toggle_following();
+        assert!(!buf.following());
+        buf.push("b\nc\npart");
+        assert_eq!(shown(&buf), ["a"]);
+        assert_eq!(buf.held(), 2);
+
+        buf.toggle_following();
+        assert_eq!(shown(&buf), ["a", "b", "c", "part"]);
+        assert_eq!(buf.held(), 0);
+    }
+
+    #[test]
+    fn test_held_lines_are_capped() {
+        let mut buf = LogBuffer::new(3);
+        buf.push("a\nb\n");
+        buf.set_following(false);
+        buf.push("c\nd\ne\nf\n");
+        assert_eq!(buf.held(), 3);
+        buf.set_following(true);
+        assert_eq!(shown(&buf), ["d", "e", "f"]);
+    }
+}
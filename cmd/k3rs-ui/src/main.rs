@@ -3,10 +3,21 @@ use dioxus_free_icons::icons::ld_icons::*;
 use dioxus_free_icons::Icon;
 use serde::{Deserialize, Serialize};
 
+mod actions;
 mod api;
+mod editor;
+mod logs;
+mod manifest;
 mod pages;
+mod toast;
+mod units;
+mod watch;
 
+use api::ApiSettings;
+use editor::{Editor, ManifestEditor};
 use pages::*;
+use toast::{ToastStack, Toasts};
+use watch::LiveStatus;
 
 // ============================================================
 // Routes
@@ -19,12 +30,16 @@ enum Route {
         Dashboard {},
         #[route("/nodes")]
         Nodes {},
+        #[route("/nodes/:name")]
+        NodeDetail { name: String },
         #[route("/deployments")]
         Deployments {},
         #[route("/services")]
         Services {},
         #[route("/pods")]
         Pods {},
+        #[route("/pods/:namespace/:name")]
+        PodDetail { namespace: String, name: String },
         #[route("/configmaps")]
         ConfigMaps {},
         #[route("/secrets")]
@@ -47,6 +62,8 @@ enum Route {
         Vpcs {},
         #[route("/vpc-peerings")]
         VpcPeerings {},
+        #[route("/settings")]
+        Settings {},
 }
 
 // ============================================================
@@ -78,16 +95,36 @@ fn Layout() -> Element {
     let mut namespace = use_signal(|| "default".to_string());
     let route: Route = use_route();
     use_context_provider(move || namespace);
+    let mut settings = use_signal(ApiSettings::default);
+    use_context_provider(move || settings);
+    let toasts = use_context_provider(Toasts::default);
+    let editor = use_context_provider(Editor::default);
+    // Pages wait for the saved settings, so they do not call the server
+    // with the defaults first.
+    let mut settings_loaded = use_signal(|| false);
+    use_effect(move || {
+        spawn(async move {
+            if let Some(saved) = ApiSettings::load().await {
+                settings.set(saved);
+            }
+            settings_loaded.set(true);
+        });
+    });
+    watch::use_live(namespace, settings, settings_loaded, toasts);
 
     let nav_cls = |target: &Route| {
-        if *target == route {
+        let node_detail =
+            matches!(target, Route::Nodes {}) && matches!(route, Route::NodeDetail { .. });
+        if *target == route || node_detail {
             "flex items-center gap-2.5 px-3 py-2 rounded-lg text-sm font-medium text-blue-400 bg-blue-500/10 ring-1 ring-blue-500/20"
         } else {
             "flex items-center gap-2.5 px-3 py-2 rounded-lg text-sm font-medium text-slate-400 hover:text-slate-200 hover:bg-white/5 transition-all"
         }
     };
     let sub_cls = |target: &Route| {
-        if *target == route {
+        let pod_detail =
+            matches!(target, Route::Pods {}) && matches!(route, Route::PodDetail { .. });
+        if *target == route || pod_detail {
             "flex items-center gap-2.5 px-3 py-1.5 rounded-lg text-[13px] font-medium text-blue-400 bg-blue-500/10 ring-1 ring-blue-500/20"
         } else {
             "flex items-center gap-2.5 px-3 py-1.5 rounded-lg text-[13px] font-medium text-slate-500 hover:text-slate-300 hover:bg-white/5 transition-all"
@@ -109,6 +146,9 @@ fn Layout() -> Element {
                             p { class: "text-sm font-bold text-white leading-tight", "k3rs" }
                             p { class: "text-[9px] font-medium text-slate-500 uppercase tracking-widest", "cluster" }
                         }
+                        div { class: "ml-auto",
+                            LiveStatus {}
+                        }
                     }
                 }
 
@@ -127,6 +167,12 @@ fn Layout() -> Element {
                             option { value: "k3rs-system", "k3rs-system" }
                         }
                     }
+                    button {
+                        class: "w-full mt-2 flex items-center justify-center gap-1.5 px-2.5 py-1.5 rounded-lg bg-blue-600/90 text-xs font-medium text-white hover:bg-blue-500 transition-colors",
+                        onclick: move |_| editor.create(),
+                        Icon { width: 12, height: 12, icon: LdFilePlus }
+                        "Create / Apply"
+                    }
                 }
 
                 // Navigation
@@ -222,17 +268,27 @@ fn Layout() -> Element {
                 }
 
                 // Footer
-                div { class: "px-4 py-3 border-t border-slate-800/60",
+                div { class: "px-4 py-3 border-t border-slate-800/60 flex items-center justify-between",
                     p { class: "text-[10px] text-slate-700 font-mono", "v0.1.0+k3rs" }
+                    Link {
+                        class: if route == (Route::Settings {}) { "text-blue-400" } else { "text-slate-600 hover:text-slate-300 transition-colors" },
+                        to: Route::Settings {},
+                        title: "Settings",
+                        Icon { width: 14, height: 14, icon: LdSettings }
+                    }
                 }
             }
 
             // ── Main ─────────────────────────────────────────────
             main { class: "ml-56 flex-1 min-h-screen",
                 div { class: "max-w-screen-xl mx-auto px-8 py-8",
-                    Outlet::<Route> {}
+                    if settings_loaded() {
+                        Outlet::<Route> {}
+                    }
                 }
             }
+            ManifestEditor {}
+            ToastStack {}
         }
     }
 }
@@ -262,6 +318,70 @@ pub struct Node {
     pub capacity: ResourceRequirements,
     #[serde(default)]
     pub allocated: ResourceRequirements,
+    /// Live usage from the latest heartbeat.
+    #[serde(default)]
+    pub usage: ResourceRequirements,
+    #[serde(default)]
+    pub taints: Vec<Taint>,
+    #[serde(default)]
+    pub conditions: Vec<NodeCondition>,
+    #[serde(default)]
+    pub last_heartbeat: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub address: String,
+    #[serde(default)]
+    pub agent_version: Option<String>,
+    #[serde(default)]
+    pub running_containers: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Taint {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+    /// `NoSchedule`, `PreferNoSchedule` or `NoExecute`.
+    pub effect: String,
+}
+
+/// `key=value:Effect`, or `key:Effect` without a value.
+impl std::fmt::Display for Taint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.value.is_empty() {
+            write!(f, "{}:{}", self.key, self.effect)
+        } else {
+            write!(f, "{}={}:{}", self.key, self.value, self.effect)
+        }
+    }
+}
+
+/// A pressure condition the node's agent reported.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct NodeCondition {
+    /// `DiskPressure` or `MemoryPressure`.
+    #[serde(rename = "type")]
+    pub condition_type: String,
+    pub message: String,
+    pub since: chrono::DateTime<chrono::Utc>,
+}
+
+/// What a drain did to each pod that was on the node.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct DrainReport {
+    pub node: String,
+    pub pods: Vec<DrainedPod>,
+    /// Every evicted pod reached its final state before the timeout.
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct DrainedPod {
+    pub namespace: String,
+    pub name: String,
+    /// e.g. `terminated`, `blocked` or `rescheduled`.
+    pub state: String,
+    #[serde(default)]
+    pub message: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
@@ -269,13 +389,71 @@ pub struct Pod {
     pub id: String,
     pub name: String,
     pub namespace: String,
+    #[serde(default)]
+    pub spec: PodSpec,
     pub status: String,
     #[serde(default)]
+    pub status_message: Option<String>,
+    #[serde(default)]
     pub node_name: Option<String>,
     #[serde(default)]
+    pub labels: std::collections::BTreeMap<String, String>,
+    #[serde(default)]
+    pub restart_count: u32,
+    #[serde(default)]
     pub ghost_ipv6: Option<String>,
     #[serde(default)]
     pub vpc_name: Option<String>,
+    #[serde(default)]
+    pub pod_ip: Option<String>,
+    /// Reported by the node's agent once the containers exist.
+    #[serde(default)]
+    pub container_statuses: Vec<ContainerStatus>,
+    #[serde(default)]
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct PodSpec {
+    #[serde(default)]
+    pub containers: Vec<ContainerSpec>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ContainerSpec {
+    pub name: String,
+    pub image: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ContainerStatus {
+    pub name: String,
+    pub image: String,
+    pub state: ContainerState,
+    #[serde(default)]
+    pub restart_count: u32,
+    #[serde(default)]
+    pub ready: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerState {
+    /// e.g. `ContainerCreating`, `ErrImagePull` or `CrashLoopBackOff`.
+    Waiting {
+        reason: String,
+        #[serde(default)]
+        message: Option<String>,
+    },
+    Running {
+        started_at: chrono::DateTime<chrono::Utc>,
+    },
+    Terminated {
+        #[serde(default)]
+        exit_code: Option<i32>,
+        reason: String,
+        finished_at: chrono::DateTime<chrono::Utc>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
@@ -376,8 +554,40 @@ pub struct IngressBackend {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WatchEvent {
     pub seq: u64,
+    /// `Put` or `Delete`.
     pub event_type: String,
     pub key: String,
+    /// The stored JSON, for a `Put`.
+    #[serde(default)]
+    pub value: Option<Vec<u8>>,
+}
+
+/// Something that happened to an object, e.g. a pod being scheduled.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ObjectEvent {
+    pub kind: String,
+    pub name: String,
+    pub namespace: String,
+    /// `Normal` or `Warning`.
+    pub event_type: String,
+    pub reason: String,
+    #[serde(default)]
+    pub message: String,
+    pub timestamp: String,
+}
+
+/// A whole list, and the watch seq it is current as of.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Snapshot<T> {
+    pub items: Vec<T>,
+    pub seq: u64,
+}
+
+/// One page of a list endpoint; `continue_token` fetches the next one.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct ListPage<T> {
+    pub items: Vec<T>,
+    pub continue_token: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -392,6 +602,15 @@ pub struct ClusterInfo {
 pub struct ResourceQuota {
     pub name: String,
     pub namespace: String,
+    #[serde(default)]
+    pub hard: QuotaLimits,
+    /// Computed by the server from the namespace's current pods.
+    #[serde(default)]
+    pub used: QuotaUsage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct QuotaLimits {
     #[serde(default)]
     pub max_pods: Option<u32>,
     #[serde(default)]
@@ -400,6 +619,30 @@ pub struct ResourceQuota {
     pub max_memory_bytes: Option<u64>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct QuotaUsage {
+    #[serde(default)]
+    pub pods: u32,
+    #[serde(default)]
+    pub cpu_millis: u64,
+    #[serde(default)]
+    pub memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct LimitRange {
+    pub name: String,
+    pub namespace: String,
+    #[serde(default)]
+    pub default_request: ResourceRequirements,
+    #[serde(default)]
+    pub default_limit: ResourceRequirements,
+    #[serde(default)]
+    pub min: ResourceRequirements,
+    #[serde(default)]
+    pub max: ResourceRequirements,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 pub struct NetworkPolicyObj {
     pub name: String,
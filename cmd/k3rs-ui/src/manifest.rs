@@ -0,0 +1,438 @@
+//! Manifests in the YAML editor: which kind they are, where they are
+//! stored, and which of their lines a validation error is about.
+
+use crate::api::ApiError;
+use crate::Route;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The kinds the API server can apply, as `k3rsctl apply` knows them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ManifestKind {
+    Pod,
+    Namespace,
+    Service,
+    Deployment,
+    ReplicaSet,
+    DaemonSet,
+    Job,
+    CronJob,
+    HorizontalPodAutoscaler,
+    ConfigMap,
+    Secret,
+    LimitRange,
+    PodDisruptionBudget,
+    PriorityClass,
+}
+
+impl ManifestKind {
+    pub const ALL: [ManifestKind; 14] = [
+        Self::Pod,
+        Self::Namespace,
+        Self::Service,
+        Self::Deployment,
+        Self::ReplicaSet,
+        Self::DaemonSet,
+        Self::Job,
+        Self::CronJob,
+        Self::HorizontalPodAutoscaler,
+        Self::ConfigMap,
+        Self::Secret,
+        Self::LimitRange,
+        Self::PodDisruptionBudget,
+        Self::PriorityClass,
+    ];
+
+    /// The `kind` written in a manifest.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Pod => "Pod",
+            Self::Namespace => "Namespace",
+            Self::Service => "Service",
+            Self::Deployment => "Deployment",
+            Self::ReplicaSet => "ReplicaSet",
+            Self::DaemonSet => "DaemonSet",
+            Self::Job => "Job",
+            Self::CronJob => "CronJob",
+            Self::HorizontalPodAutoscaler => "HorizontalPodAutoscaler",
+            Self::ConfigMap => "ConfigMap",
+            Self::Secret => "Secret",
+            Self::LimitRange => "LimitRange",
+            Self::PodDisruptionBudget => "PodDisruptionBudget",
+            Self::PriorityClass => "PriorityClass",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.name() == name)
+    }
+
+    /// The collection objects of the kind are stored in, e.g. `pods`.
+    #[cfg(feature = "server")]
+    pub fn resource(self) -> &'static str {
+        match self {
+            Self::Pod => "pods",
+            Self::Namespace => "namespaces",
+            Self::Service => "services",
+            Self::Deployment => "deployments",
+            Self::ReplicaSet => "replicasets",
+            Self::DaemonSet => "daemonsets",
+            Self::Job => "jobs",
+            Self::CronJob => "cronjobs",
+            Self::HorizontalPodAutoscaler => "hpa",
+            Self::ConfigMap => "configmaps",
+            Self::Secret => "secrets",
+            Self::LimitRange => "limitranges",
+            Self::PodDisruptionBudget => "poddisruptionbudgets",
+            Self::PriorityClass => "priorityclasses",
+        }
+    }
+
+    pub fn namespaced(self) -> bool {
+        !matches!(self, Self::Namespace | Self::PriorityClass)
+    }
+
+    /// The page listing objects of the kind, if the UI has one.
+    pub fn list_route(self) -> Option<Route> {
+        match self {
+            Self::Pod => Some(Route::Pods {}),
+            Self::Service => Some(Route::Services {}),
+            Self::Deployment => Some(Route::Deployments {}),
+            Self::ConfigMap => Some(Route::ConfigMaps {}),
+            Self::Secret => Some(Route::Secrets {}),
+            Self::LimitRange => Some(Route::Quotas {}),
+            _ => None,
+        }
+    }
+}
+
+/// A message for the editor, shown next to `line` (1-based) when it is
+/// known and above the text otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// A manifest that parsed, with what is needed to store it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub kind: ManifestKind,
+    pub name: String,
+    /// The namespace the manifest names, if any.
+    pub namespace: Option<String>,
+    pub object: Value,
+}
+
+impl Manifest {
+    /// Parse `yaml`. Without a `kind` it is a Pod, as with `k3rsctl apply`.
+    pub fn parse(yaml: &str) -> Result<Self, Annotation> {
+        let object: Value = serde_yaml::from_str(yaml).map_err(|e| Annotation {
+            line: e.location().map(|l| l.line()),
+            message: e.to_string(),
+        })?;
+        if !object.is_object() {
+            return Err(Annotation {
+                line: None,
+                message: "the manifest must be a mapping of fields".to_string(),
+            });
+        }
+        let kind = match object.get("kind") {
+            None => ManifestKind::Pod,
+            Some(kind) => kind
+                .as_str()
+                .and_then(ManifestKind::from_name)
+                .ok_or_else(|| Annotation {
+                    line: field_line(yaml, "kind"),
+                    message: format!("unsupported kind {}", kind),
+                })?,
+        };
+        let name = match object.get("name").and_then(Value::as_str) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => {
+                return Err(Annotation {
+                    line: field_line(yaml, "name"),
+                    message: "the manifest needs a name".to_string(),
+                })
+            }
+        };
+        let namespace = object
+            .get("namespace")
+            .and_then(Value::as_str)
+            .filter(|ns| !ns.is_empty() && kind.namespaced())
+            .map(str::to_string);
+        Ok(Self {
+            kind,
+            name,
+            namespace,
+            object,
+        })
+    }
+}
+
+/// `object` as YAML, with its `kind` first.
+#[cfg(feature = "server")]
+pub fn to_yaml(kind: ManifestKind, object: &Value) -> Result<String, ApiError> {
+    let mut fields = serde_yaml::Mapping::new();
+    fields.insert("kind".into(), kind.name().into());
+    for (key, value) in object.as_object().into_iter().flatten() {
+        if key != "kind" {
+            let value = serde_yaml::to_value(value)
+                .map_err(|e| ApiError::Invalid(format!("could not write YAML: {}", e)))?;
+            fields.insert(key.as_str().into(), value);
+        }
+    }
+    serde_yaml::to_string(&fields)
+        .map_err(|e| ApiError::Invalid(format!("could not write YAML: {}", e)))
+}
+
+/// The messages to show for `err`, returned for the manifest `yaml`: one
+/// per failing field, on the line of that field (or of the closest
+/// enclosing one that is written out).
+pub fn annotate(yaml: &str, err: &ApiError) -> Vec<Annotation> {
+    match err {
+        ApiError::Rejected { details, .. } if !details.is_empty() => details
+            .iter()
+            .map(|d| Annotation {
+                line: field_line(yaml, &d.field),
+                message: format!("{}: {}", d.field, d.message),
+            })
+            .collect(),
+        _ => vec![Annotation {
+            line: None,
+            message: err.to_string(),
+        }],
+    }
+}
+
+/// One step of a field path such as `spec.containers[0].image`.
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+fn segments(field: &str) -> Option<Vec<Segment<'_>>> {
+    let mut out = Vec::new();
+    for part in field.split('.') {
+        let mut pieces = part.split('[');
+        let key = pieces.next().unwrap_or_default();
+        if !key.is_empty() {
+            out.push(Segment::Key(key));
+        }
+        for index in pieces {
+            out.push(Segment::Index(index.strip_suffix(']')?.parse().ok()?));
+        }
+    }
+    Some(out)
+}
+
+/// A line with text on it: its 0-based number, indent and text.
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+impl Line<'_> {
+    /// Indent and text of the mapping entry on the line; for the first
+    /// line of a list item, `- ` counts as indent.
+    fn entry(&self) -> (usize, &str) {
+        let (mut indent, mut text) = (self.indent, self.text);
+        while let Some(rest) = text.strip_prefix("- ") {
+            let trimmed = rest.trim_start();
+            indent += 2 + rest.len() - trimmed.len();
+            text = trimmed;
+        }
+        (indent, text)
+    }
+}
+
+fn is_key(text: &str, key: &str) -> bool {
+    [
+        key.to_string(),
+        format!("\"{}\"", key),
+        format!("'{}'", key),
+    ]
+    .iter()
+    .any(|k| {
+        text.strip_prefix(k.as_str())
+            .and_then(|rest| rest.strip_prefix(':'))
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+    })
+}
+
+/// The 1-based line in `yaml` of `field`, or of its closest enclosing
+/// field that is written out. Only block-style YAML is followed.
+fn field_line(yaml: &str, field: &str) -> Option<usize> {
+    let lines: Vec<Line> = yaml
+        .lines()
+        .enumerate()
+        .filter_map(|(number, raw)| {
+            let text = raw.trim_start();
+            (!text.is_empty() && !text.starts_with('#')).then_some(Line {
+                number,
+                indent: raw.len() - text.len(),
+                text,
+            })
+        })
+        .filter(|l| l.text != "---")
+        .collect();
+
+    // The lines the current field spans, and the indent its entries must
+    // be deeper than.
+    let (mut lo, mut hi, mut parent) = (0, lines.len(), None::<usize>);
+    let mut found = None;
+    for segment in segments(field)? {
+        match segment {
+            Segment::Key(key) => {
+                let deeper = |i: &usize| parent.is_none_or(|p| lines[*i].entry().0 > p);
+                let Some(block) = (lo..hi).filter(deeper).map(|i| lines[i].entry().0).min() else {
+                    break;
+                };
+                let Some(at) = (lo..hi).filter(deeper).find(|&i| {
+                    let (indent, text) = lines[i].entry();
+                    indent == block && is_key(text, key)
+                }) else {
+                    break;
+                };
+                // The value runs until the next entry at the key's indent;
+                // a list may sit at that indent too.
+                let end = (at + 1..hi)
+                    .find(|&i| {
+                        lines[i].indent < block
+                            || (lines[i].indent == block && !lines[i].text.starts_with('-'))
+                    })
+                    .unwrap_or(hi);
+                (lo, hi, parent) = (at + 1, end, Some(block));
+                found = Some(at);
+            }
+            Segment::Index(n) => {
+                let Some(dash) = (lo..hi).map(|i| lines[i].indent).min() else {
+                    break;
+                };
+                let items: Vec<usize> = (lo..hi)
+                    .filter(|&i| lines[i].indent == dash && lines[i].text.starts_with('-'))
+                    .collect();
+                let Some(&at) = items.get(n) else {
+                    break;
+                };
+                let end = items.get(n + 1).copied().unwrap_or(hi);
+                // The item's first entry is on its own `- ` line.
+                (lo, hi, parent) = (at, end, Some(dash));
+                found = Some(at);
+            }
+        }
+    }
+    found.map(|i| lines[i].number + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::FieldError;
+
+    const POD: &str = "\
+kind: Pod
+name: web
+# the containers
+spec:
+  containers:
+  - name: app
+    image: nginx
+  - name: sidecar
+    image: ''
+    ports:
+      - container_port: 80
+";
+
+    #[test]
+    fn test_kind_detection() {
+        let manifest = Manifest::parse("kind: Deployment\nname: api\nnamespace: prod\n").unwrap();
+        assert_eq!(manifest.kind, ManifestKind::Deployment);
+        assert_eq!(manifest.name, "api");
+        assert_eq!(manifest.namespace.as_deref(), Some("prod"));
+
+        // No kind: a pod, like `k3rsctl apply`.
+        let manifest = Manifest::parse("name: web\n").unwrap();
+        assert_eq!(manifest.kind, ManifestKind::Pod);
+
+        // Cluster-wide kinds ignore a namespace.
+        let manifest = Manifest::parse("kind: PriorityClass\nname: high\nnamespace: x\n").unwrap();
+        assert_eq!(manifest.namespace, None);
+
+        let err = Manifest::parse("name: web\nkind: Gadget\n").unwrap_err();
+        assert_eq!(err.line, Some(2));
+        assert!(err.message.contains("Gadget"));
+
+        let err = Manifest::parse("kind: Service\n").unwrap_err();
+        assert_eq!(err.message, "the manifest needs a name");
+
+        let err = Manifest::parse("kind: Pod\nname: [web\n").unwrap_err();
+        assert!(err.line.is_some());
+
+        for kind in ManifestKind::ALL {
+            assert_eq!(ManifestKind::from_name(kind.name()), Some(kind));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_yaml_round_trip() {
+        let object = serde_json::json!({
+            "name": "web",
+            "namespace": "default",
+            "resource_version": 7,
+            "data": {"a": "1"},
+        });
+        let yaml = to_yaml(ManifestKind::ConfigMap, &object).unwrap();
+        assert!(yaml.starts_with("kind: ConfigMap\n"));
+        let manifest = Manifest::parse(&yaml).unwrap();
+        assert_eq!(manifest.kind, ManifestKind::ConfigMap);
+        assert_eq!(manifest.object["resource_version"], 7);
+        assert_eq!(manifest.object["data"], object["data"]);
+    }
+
+    #[test]
+    fn test_field_errors_map_to_lines() {
+        let err = ApiError::Rejected {
+            status: 422,
+            message: "pod 'web' is invalid".to_string(),
+            details: vec![
+                FieldError {
+                    field: "spec.containers[1].image".to_string(),
+                    message: "must not be empty".to_string(),
+                },
+                // Not written out: the enclosing container's line.
+                FieldError {
+                    field: "spec.containers[0].limits.cpu_millis".to_string(),
+                    message: "must be positive".to_string(),
+                },
+                FieldError {
+                    field: "spec.containers[1].ports[0].container_port".to_string(),
+                    message: "duplicate port 80".to_string(),
+                },
+                FieldError {
+                    field: "name".to_string(),
+                    message: "invalid name".to_string(),
+                },
+            ],
+        };
+        let lines: Vec<_> = annotate(POD, &err).iter().map(|a| a.line).collect();
+        assert_eq!(lines, [Some(9), Some(6), Some(11), Some(2)]);
+        assert_eq!(
+            annotate(POD, &err)[0].message,
+            "spec.containers[1].image: must not be empty"
+        );
+
+        // Errors without field details are shown above the text.
+        let err = ApiError::Forbidden("role viewer cannot update pods".to_string());
+        assert_eq!(
+            annotate(POD, &err),
+            [Annotation {
+                line: None,
+                message: err.to_string(),
+            }]
+        );
+    }
+}
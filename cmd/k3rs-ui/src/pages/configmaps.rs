@@ -1,12 +1,20 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::editor::{EditYamlButton, Editor};
+use crate::manifest::ManifestKind;
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 #[component]
 pub fn ConfigMaps() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
+    let editor = use_context::<Editor>();
     let configmaps = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_configmaps(ns).await.unwrap_or_default() }
+        editor.applied();
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_configmaps(settings, ns).await) }
     });
     let data = configmaps.read();
 
@@ -24,12 +32,13 @@ pub fn ConfigMaps() -> Element {
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Keys" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Namespace" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "ID" }
+                        th { class: "px-5 py-2.5" }
                     }
                 }
                 tbody {
                     if let Some(cms) = data.as_ref() {
                         if cms.is_empty() {
-                            tr { td { colspan: "4", class: "text-center py-16 text-slate-500 text-sm", "No configmaps found" } }
+                            tr { td { colspan: "5", class: "text-center py-16 text-slate-500 text-sm", "No configmaps found" } }
                         } else {
                             for cm in cms.iter() {
                                 tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
@@ -37,6 +46,9 @@ pub fn ConfigMaps() -> Element {
                                     td { class: "px-5 py-3 text-sm text-slate-400", "{cm.data.len()}" }
                                     td { class: "px-5 py-3 text-xs text-slate-500", "{cm.namespace}" }
                                     td { class: "px-5 py-3 text-xs font-mono text-slate-600", "{cm.id}" }
+                                    td { class: "px-5 py-3 text-right",
+                                        EditYamlButton { kind: ManifestKind::ConfigMap, namespace: cm.namespace.clone(), name: cm.name.clone() }
+                                    }
                                 }
                             }
                         }
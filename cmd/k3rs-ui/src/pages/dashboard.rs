@@ -1,21 +1,33 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
+use crate::Route;
 use dioxus::prelude::*;
 use dioxus_free_icons::icons::ld_icons::*;
 use dioxus_free_icons::Icon;
 
 #[component]
 pub fn Dashboard() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
 
-    let cluster_info = use_resource(move || async move { api::get_cluster_info().await.ok() });
-    let nodes = use_resource(move || async move { api::get_nodes().await.unwrap_or_default() });
+    let cluster_info = use_resource(move || {
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_cluster_info(settings).await.map(Some)) }
+    });
+    let nodes = use_resource(move || {
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_nodes(settings).await) }
+    });
     let pods = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_pods(ns).await.unwrap_or_default() }
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_pods(settings, ns).await) }
     });
     let services = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_services(ns).await.unwrap_or_default() }
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_services(settings, ns).await) }
     });
 
     let info = cluster_info.read();
@@ -103,7 +115,13 @@ pub fn Dashboard() -> Element {
                         } else {
                             for node in nodes.iter() {
                                 tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
-                                    td { class: "px-5 py-3 text-sm text-slate-300", "{node.name}" }
+                                    td { class: "px-5 py-3 text-sm text-slate-300",
+                                        Link {
+                                            class: "hover:text-blue-400 transition-colors",
+                                            to: Route::NodeDetail { name: node.name.clone() },
+                                            "{node.name}"
+                                        }
+                                    }
                                     td { class: "px-5 py-3", StatusBadge { status: node.status.clone() } }
                                     td { class: "px-5 py-3 text-xs font-mono text-slate-500", "{node.id}" }
                                 }
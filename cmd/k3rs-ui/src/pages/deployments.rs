@@ -1,12 +1,20 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::editor::{EditYamlButton, Editor};
+use crate::manifest::ManifestKind;
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 #[component]
 pub fn Deployments() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
+    let editor = use_context::<Editor>();
     let deployments = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_deployments(ns).await.unwrap_or_default() }
+        editor.applied();
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_deployments(settings, ns).await) }
     });
     let data = deployments.read();
 
@@ -24,12 +32,13 @@ pub fn Deployments() -> Element {
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Replicas" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Namespace" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "ID" }
+                        th { class: "px-5 py-2.5" }
                     }
                 }
                 tbody {
                     if let Some(deps) = data.as_ref() {
                         if deps.is_empty() {
-                            tr { td { colspan: "4", class: "text-center py-16 text-slate-500 text-sm", "No deployments found" } }
+                            tr { td { colspan: "5", class: "text-center py-16 text-slate-500 text-sm", "No deployments found" } }
                         } else {
                             for dep in deps.iter() {
                                 tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
@@ -37,6 +46,9 @@ pub fn Deployments() -> Element {
                                     td { class: "px-5 py-3 text-sm text-slate-400", "{dep.spec.replicas}" }
                                     td { class: "px-5 py-3 text-xs text-slate-500", "{dep.namespace}" }
                                     td { class: "px-5 py-3 text-xs font-mono text-slate-600", "{dep.id}" }
+                                    td { class: "px-5 py-3 text-right",
+                                        EditYamlButton { kind: ManifestKind::Deployment, namespace: dep.namespace.clone(), name: dep.name.clone() }
+                                    }
                                 }
                             }
                         }
@@ -1,17 +1,17 @@
-use crate::api;
+use crate::watch::Live;
 use dioxus::prelude::*;
 use dioxus_free_icons::icons::ld_icons::*;
 use dioxus_free_icons::Icon;
 
 #[component]
 pub fn Events() -> Element {
-    let events = use_resource(move || async move { api::get_events().await.unwrap_or_default() });
-    let events_data = events.read();
+    let live = use_context::<Live>();
+    let events_data = live.events.read();
 
     rsx! {
         div { class: "mb-6",
             h2 { class: "text-xl font-semibold text-white", "Events" }
-            p { class: "text-sm text-slate-400 mt-1", "Cluster event stream" }
+            p { class: "text-sm text-slate-400 mt-1", "What happened to the objects in the namespace" }
         }
 
         if events_data.is_none() {
@@ -30,20 +30,20 @@ pub fn Events() -> Element {
                 }
             } else {
                 div { class: "space-y-2",
-                    for evt in evts.iter().rev() {
+                    for evt in evts.newest() {
                         div { class: "bg-slate-900 border border-slate-800 rounded-lg px-4 py-3 flex items-center gap-3 hover:border-slate-700 transition-colors",
                             span {
-                                class: if evt.event_type.to_lowercase() == "put" {
-                                    "inline-block px-2 py-0.5 rounded text-[10px] font-semibold uppercase bg-emerald-500/10 text-emerald-400"
+                                class: if evt.event_type == "Warning" {
+                                    "inline-block px-2 py-0.5 rounded text-[10px] font-semibold uppercase bg-amber-500/10 text-amber-400"
                                 } else {
-                                    "inline-block px-2 py-0.5 rounded text-[10px] font-semibold uppercase bg-red-500/10 text-red-400"
+                                    "inline-block px-2 py-0.5 rounded text-[10px] font-semibold uppercase bg-emerald-500/10 text-emerald-400"
                                 },
                                 "{evt.event_type}"
                             }
-                            span { class: "text-xs font-mono text-slate-300 flex-1 truncate",
-                                "{evt.key}"
-                            }
-                            span { class: "text-[11px] text-slate-600 shrink-0", "#{evt.seq}" }
+                            span { class: "text-xs font-semibold text-slate-300 shrink-0", "{evt.reason}" }
+                            span { class: "text-xs font-mono text-slate-400 shrink-0", "{evt.kind}/{evt.name}" }
+                            span { class: "text-xs text-slate-400 flex-1 truncate", "{evt.message}" }
+                            span { class: "text-[11px] text-slate-600 shrink-0", "{evt.timestamp}" }
                         }
                     }
                 }
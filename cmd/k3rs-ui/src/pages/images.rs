@@ -1,9 +1,15 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 #[component]
 pub fn Images() -> Element {
-    let images = use_resource(move || async move { api::get_images().await.unwrap_or_default() });
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
+    let images = use_resource(move || {
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_images(settings).await) }
+    });
     let imgs_data = images.read();
 
     rsx! {
@@ -1,4 +1,5 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 struct IngressRow {
@@ -11,10 +12,13 @@ struct IngressRow {
 
 #[component]
 pub fn Ingress() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
     let ingresses = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_ingresses(ns).await.unwrap_or_default() }
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_ingresses(settings, ns).await) }
     });
     let ing_data = ingresses.read();
 
@@ -5,12 +5,15 @@ mod events;
 mod images;
 mod ingress;
 mod network_policies;
+mod node_detail;
 mod nodes;
+mod pod_detail;
 mod pods;
 mod process_list;
 mod quotas;
 mod secrets;
 mod services;
+mod settings;
 mod volumes;
 mod vpc_peerings;
 mod vpcs;
@@ -22,12 +25,15 @@ pub use events::*;
 pub use images::*;
 pub use ingress::*;
 pub use network_policies::*;
+pub use node_detail::*;
 pub use nodes::*;
+pub use pod_detail::*;
 pub use pods::*;
 pub use process_list::*;
 pub use quotas::*;
 pub use secrets::*;
 pub use services::*;
+pub use settings::*;
 pub use volumes::*;
 pub use vpc_peerings::*;
 pub use vpcs::*;
@@ -1,12 +1,16 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 #[component]
 pub fn NetworkPolicies() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
     let policies = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_network_policies(ns).await.unwrap_or_default() }
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_network_policies(settings, ns).await) }
     });
     let policies_data = policies.read();
 
@@ -0,0 +1,576 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::actions::{ActionState, NodeAction};
+use crate::api::{self, ApiError, ApiSettings};
+use crate::toast::Toasts;
+use crate::units::{age, bar_class, fmt_cpu, fmt_mem, percent};
+use crate::watch::sleep;
+use crate::{DrainReport, Node, Route, Taint};
+use dioxus::prelude::*;
+use dioxus_free_icons::icons::ld_icons::*;
+use dioxus_free_icons::Icon;
+
+use super::dashboard::StatusBadge;
+
+/// Time between refreshes of the node and its pods; every tick while a
+/// drain runs, so the pods can be seen leaving.
+const TICK: Duration = Duration::from_secs(1);
+const TICKS_PER_REFRESH: u64 = 5;
+
+const TAINT_EFFECTS: [&str; 3] = ["NoSchedule", "PreferNoSchedule", "NoExecute"];
+
+fn drain_summary(report: &DrainReport) -> String {
+    if report.complete {
+        format!("Node drained ({} pods)", report.pods.len())
+    } else {
+        "Drain timed out before every pod was done; see the pods below".to_string()
+    }
+}
+
+#[component]
+pub fn NodeDetail(name: String) -> Element {
+    // Keyed so another node's page starts from scratch.
+    rsx! {
+        NodeView { key: "{name}", name: name.clone() }
+    }
+}
+
+#[component]
+fn NodeView(name: String) -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
+    let node_name = use_signal(|| name.clone());
+    let mut action = use_signal(ActionState::default);
+    let mut report = use_signal(|| None::<DrainReport>);
+    let mut refresh = use_signal(|| 0u64);
+
+    use_future(move || async move {
+        let mut ticks = 0u64;
+        loop {
+            sleep(TICK).await;
+            ticks += 1;
+            let draining = action.peek().running() == Some(NodeAction::Drain);
+            if draining || ticks.is_multiple_of(TICKS_PER_REFRESH) {
+                refresh += 1;
+            }
+        }
+    });
+
+    let node = use_resource(move || {
+        let settings = settings.read().clone();
+        refresh();
+        async move { toasts.report(api::get_node(settings, node_name()).await.map(Some)) }
+    });
+    let pods = use_resource(move || {
+        let settings = settings.read().clone();
+        refresh();
+        async move { toasts.report(api::get_node_pods(settings, node_name()).await) }
+    });
+    let images = use_resource(move || {
+        let settings = settings.read().clone();
+        async move {
+            let mut images = toasts.report(api::get_images(settings).await);
+            images.retain(|i| i.node_name == node_name());
+            images
+        }
+    });
+
+    let run = move |_| {
+        let Some(act) = action.write().confirm() else {
+            return;
+        };
+        let settings = settings.read().clone();
+        spawn(async move {
+            let name = node_name();
+            let result = match act {
+                NodeAction::Cordon => api::cordon_node(settings, name)
+                    .await
+                    .map(|_| "Node cordoned".to_string()),
+                NodeAction::Uncordon => api::uncordon_node(settings, name)
+                    .await
+                    .map(|_| "Node uncordoned".to_string()),
+                NodeAction::Drain => api::drain_node(settings, name).await.map(|r| {
+                    let summary = drain_summary(&r);
+                    report.set(Some(r));
+                    summary
+                }),
+            };
+            action.write().finish(result);
+            refresh += 1;
+        });
+    };
+
+    let node_data = node.read();
+    let pods_data = pods.read();
+    let images_data = images.read();
+    let state = action.read();
+    let cordoned = node_data
+        .as_ref()
+        .and_then(|n| n.as_ref())
+        .is_some_and(|n| n.unschedulable);
+
+    rsx! {
+        div { class: "mb-6",
+            Link {
+                class: "inline-flex items-center gap-1.5 text-xs text-slate-500 hover:text-slate-300 mb-3",
+                to: Route::Nodes {},
+                Icon { width: 12, height: 12, icon: LdArrowLeft }
+                "Nodes"
+            }
+            div { class: "flex items-center gap-3",
+                h2 { class: "text-xl font-semibold text-white", "{name}" }
+                if let Some(Some(n)) = node_data.as_ref() {
+                    StatusBadge { status: n.status.clone() }
+                    if n.unschedulable {
+                        span { class: "inline-block px-2.5 py-0.5 rounded-full text-[11px] font-medium bg-amber-500/10 text-amber-400 border border-amber-500/20",
+                            "Cordoned"
+                        }
+                    }
+                }
+                div { class: "ml-auto flex items-center gap-2",
+                    for act in NodeAction::ALL {
+                        button {
+                            class: if act == NodeAction::Drain { "flex items-center gap-1.5 px-3 py-1.5 text-sm rounded-lg bg-red-600/80 text-white hover:bg-red-500 disabled:opacity-40 disabled:cursor-not-allowed" } else { "flex items-center gap-1.5 px-3 py-1.5 text-sm rounded-lg bg-slate-800 text-slate-300 hover:bg-slate-700 disabled:opacity-40 disabled:cursor-not-allowed" },
+                            disabled: node_data.is_none() || !state.enabled(act, cordoned),
+                            onclick: move |_| action.write().request(act),
+                            if state.running() == Some(act) {
+                                Icon { class: "animate-spin", width: 13, height: 13, icon: LdLoaderCircle }
+                                "{act.running_label()}"
+                            } else {
+                                match act {
+                                    NodeAction::Cordon => rsx! { Icon { width: 13, height: 13, icon: LdBan } },
+                                    NodeAction::Uncordon => rsx! { Icon { width: 13, height: 13, icon: LdCircleCheck } },
+                                    NodeAction::Drain => rsx! { Icon { width: 13, height: 13, icon: LdArrowDownToLine } },
+                                }
+                                "{act.label()}"
+                            }
+                        }
+                    }
+                }
+            }
+
+            // Outcome of the last action.
+            match &*state {
+                ActionState::Running(NodeAction::Drain) => rsx! {
+                    div { class: "mt-4 flex items-center gap-2 px-4 py-3 rounded-lg bg-blue-500/10 border border-blue-500/20 text-sm text-blue-300",
+                        Icon { class: "animate-spin", width: 14, height: 14, icon: LdLoaderCircle }
+                        "Draining: {pods_data.as_ref().map_or(0, |p| p.len())} pods still on the node"
+                    }
+                },
+                ActionState::Done(_, message) => rsx! {
+                    div { class: "mt-4 flex items-center gap-2 px-4 py-3 rounded-lg bg-emerald-500/10 border border-emerald-500/20 text-sm text-emerald-300",
+                        Icon { width: 14, height: 14, icon: LdCircleCheck }
+                        "{message}"
+                    }
+                },
+                ActionState::Failed(act, err) => rsx! {
+                    div { class: "mt-4 flex items-start gap-2 px-4 py-3 rounded-lg bg-red-500/10 border border-red-500/20 text-sm text-red-300",
+                        Icon { width: 14, height: 14, icon: LdTriangleAlert }
+                        "{act.label()} failed: {err.title()}: {err.message()}"
+                    }
+                },
+                _ => rsx! {},
+            }
+        }
+
+        if let ActionState::Confirming(act) = *state {
+            div { class: "fixed inset-0 z-40 flex items-center justify-center bg-black/60",
+                div { class: "bg-slate-900 border border-slate-700 rounded-xl p-6 w-[28rem] shadow-xl",
+                    h3 { class: "text-base font-semibold text-white", "{act.label()} {name}" }
+                    p { class: "text-sm text-slate-400 mt-2", "{act.question(&name)}" }
+                    div { class: "flex justify-end gap-2 mt-6",
+                        button {
+                            class: "px-4 py-2 text-sm rounded-lg text-slate-400 hover:text-slate-200",
+                            onclick: move |_| action.write().cancel(),
+                            "Cancel"
+                        }
+                        button {
+                            class: if act == NodeAction::Drain { "px-4 py-2 text-sm rounded-lg bg-red-600 text-white hover:bg-red-500" } else { "px-4 py-2 text-sm rounded-lg bg-blue-600 text-white hover:bg-blue-500" },
+                            onclick: run,
+                            "{act.label()}"
+                        }
+                    }
+                }
+            }
+        }
+
+        match node_data.as_ref() {
+            None => rsx! {
+                div { class: "flex flex-col items-center justify-center py-20 text-slate-500",
+                    Icon { width: 32, height: 32, icon: LdLoader }
+                    p { class: "mt-3 text-sm", "Loading node..." }
+                }
+            },
+            Some(None) => rsx! {
+                div { class: "flex flex-col items-center justify-center py-20 text-slate-500",
+                    Icon { width: 32, height: 32, icon: LdInbox }
+                    p { class: "mt-3 text-sm", "Node not found" }
+                }
+            },
+            Some(Some(n)) => rsx! {
+                NodeSummary { node: n.clone() }
+                NodeMetadata { node: n.clone(), on_change: move |_| refresh += 1 }
+            },
+        }
+
+        if let Some(r) = report.read().as_ref() {
+            div { class: "bg-slate-900 border border-slate-800 rounded-xl overflow-hidden mb-6",
+                div { class: "px-5 py-3.5 border-b border-slate-800",
+                    h3 { class: "text-sm font-semibold text-white", "Last drain" }
+                }
+                table { class: "w-full",
+                    tbody {
+                        if r.pods.is_empty() {
+                            tr { td { class: "text-center py-8 text-slate-500 text-sm", "No pods were on the node" } }
+                        }
+                        for p in r.pods.iter() {
+                            tr { class: "border-b border-slate-800/50",
+                                td { class: "px-5 py-2.5 text-xs font-mono text-slate-300", "{p.namespace}/{p.name}" }
+                                td { class: "px-5 py-2.5 text-xs text-slate-400", "{p.state.replace('_', \" \")}" }
+                                td { class: "px-5 py-2.5 text-xs text-slate-500", "{p.message.as_deref().unwrap_or_default()}" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Pods on the node
+        div { class: "bg-slate-900 border border-slate-800 rounded-xl overflow-hidden mb-6",
+            div { class: "px-5 py-3.5 border-b border-slate-800",
+                h3 { class: "text-sm font-semibold text-white", "Pods" }
+            }
+            table { class: "w-full",
+                thead {
+                    tr { class: "border-b border-slate-800",
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Name" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Namespace" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Status" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Restarts" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Age" }
+                    }
+                }
+                tbody {
+                    if let Some(pods) = pods_data.as_ref() {
+                        if pods.is_empty() {
+                            tr { td { colspan: "5", class: "text-center py-10 text-slate-500 text-sm", "No pods on this node" } }
+                        }
+                        for pod in pods.iter() {
+                            tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
+                                td { class: "px-5 py-3 text-sm text-slate-300 font-medium",
+                                    Link {
+                                        class: "hover:text-blue-400 transition-colors",
+                                        to: Route::PodDetail { namespace: pod.namespace.clone(), name: pod.name.clone() },
+                                        "{pod.name}"
+                                    }
+                                }
+                                td { class: "px-5 py-3 text-xs text-slate-400", "{pod.namespace}" }
+                                td { class: "px-5 py-3", StatusBadge { status: pod.status.clone() } }
+                                td { class: "px-5 py-3 text-xs text-slate-400", "{pod.restart_count}" }
+                                td { class: "px-5 py-3 text-xs text-slate-500", "{age(pod.created_at)}" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Images cached on the node
+        div { class: "bg-slate-900 border border-slate-800 rounded-xl overflow-hidden",
+            div { class: "px-5 py-3.5 border-b border-slate-800",
+                h3 { class: "text-sm font-semibold text-white", "Cached images" }
+            }
+            table { class: "w-full",
+                tbody {
+                    if let Some(imgs) = images_data.as_ref() {
+                        if imgs.is_empty() {
+                            tr { td { class: "text-center py-10 text-slate-500 text-sm", "No images reported by this node" } }
+                        }
+                        for img in imgs.iter() {
+                            tr { class: "border-b border-slate-800/50",
+                                td { class: "px-5 py-2.5 text-xs font-mono text-slate-300",
+                                    div { class: "flex items-center gap-2",
+                                        Icon { width: 12, height: 12, icon: LdLayers }
+                                        "{img.id}"
+                                    }
+                                }
+                                td { class: "px-5 py-2.5 text-xs text-slate-400", "{img.os}/{img.architecture}" }
+                                td { class: "px-5 py-2.5 text-xs text-slate-400", "{img.layers} layers" }
+                                td { class: "px-5 py-2.5 text-xs text-slate-300 text-right", "{img.size_human}" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A bar of `used` out of `total`, or a dash when the total is unknown.
+#[component]
+fn UsageBar(label: String, used: String, total: String, pct: Option<u64>) -> Element {
+    rsx! {
+        div {
+            div { class: "flex justify-between text-[11px] mb-1",
+                span { class: "text-slate-500", "{label}" }
+                if pct.is_some() {
+                    span { class: "text-slate-300", "{used} " span { class: "text-slate-600", "/ {total}" } }
+                } else {
+                    span { class: "text-slate-600", "\u{2014}" }
+                }
+            }
+            div { class: "w-full bg-slate-800 rounded-full h-1",
+                if let Some(pct) = pct {
+                    div { class: "{bar_class(pct)}", style: "width: {pct}%" }
+                }
+            }
+        }
+    }
+}
+
+/// Capacity, allocation and usage, conditions and heartbeat.
+#[component]
+fn NodeSummary(node: Node) -> Element {
+    let (cap, alloc, usage) = (&node.capacity, &node.allocated, &node.usage);
+    let fields = [
+        ("Address", node.address.clone()),
+        (
+            "Agent",
+            node.agent_version.clone().unwrap_or("\u{2014}".to_string()),
+        ),
+        ("Containers", node.running_containers.to_string()),
+        (
+            "Last heartbeat",
+            format!("{} ago", age(node.last_heartbeat)),
+        ),
+        ("Registered", node.registered_at.clone()),
+    ];
+
+    rsx! {
+        div { class: "grid grid-cols-3 gap-4 mb-6",
+            div { class: "col-span-2 bg-slate-900 border border-slate-800 rounded-xl p-5",
+                div { class: "grid grid-cols-2 gap-6",
+                    div { class: "space-y-3",
+                        div { class: "flex items-center gap-2 text-xs font-semibold text-slate-300",
+                            Icon { width: 14, height: 14, icon: LdCpu }
+                            "CPU"
+                        }
+                        UsageBar {
+                            label: "Allocated",
+                            used: fmt_cpu(alloc.cpu_millis),
+                            total: fmt_cpu(cap.cpu_millis),
+                            pct: percent(alloc.cpu_millis, cap.cpu_millis),
+                        }
+                        UsageBar {
+                            label: "In use",
+                            used: fmt_cpu(usage.cpu_millis),
+                            total: fmt_cpu(cap.cpu_millis),
+                            pct: percent(usage.cpu_millis, cap.cpu_millis),
+                        }
+                    }
+                    div { class: "space-y-3",
+                        div { class: "flex items-center gap-2 text-xs font-semibold text-slate-300",
+                            Icon { width: 14, height: 14, icon: LdMemoryStick }
+                            "Memory"
+                        }
+                        UsageBar {
+                            label: "Allocated",
+                            used: fmt_mem(alloc.memory_bytes),
+                            total: fmt_mem(cap.memory_bytes),
+                            pct: percent(alloc.memory_bytes, cap.memory_bytes),
+                        }
+                        UsageBar {
+                            label: "In use",
+                            used: fmt_mem(usage.memory_bytes),
+                            total: fmt_mem(cap.memory_bytes),
+                            pct: percent(usage.memory_bytes, cap.memory_bytes),
+                        }
+                    }
+                }
+                div { class: "mt-5 pt-4 border-t border-slate-800",
+                    if node.conditions.is_empty() {
+                        p { class: "flex items-center gap-2 text-xs text-emerald-400",
+                            Icon { width: 13, height: 13, icon: LdCircleCheck }
+                            "No pressure conditions"
+                        }
+                    }
+                    for c in node.conditions.iter() {
+                        p { class: "flex items-center gap-2 text-xs text-amber-400 mb-1",
+                            Icon { width: 13, height: 13, icon: LdTriangleAlert }
+                            span { class: "font-semibold", "{c.condition_type}" }
+                            span { class: "text-slate-400", "{c.message}, for {age(c.since)}" }
+                        }
+                    }
+                }
+            }
+            div { class: "bg-slate-900 border border-slate-800 rounded-xl p-5 space-y-3",
+                for (label, value) in fields {
+                    div {
+                        p { class: "text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "{label}" }
+                        p { class: "text-sm text-slate-300 mt-0.5 truncate", "{value}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Labels and taints, each removable, with forms to add them. Failures
+/// are shown below the forms.
+#[component]
+fn NodeMetadata(node: Node, on_change: EventHandler<()>) -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let node_name = use_signal(|| node.name.clone());
+    let mut error = use_signal(|| None::<ApiError>);
+    let mut label = use_signal(String::new);
+    let mut taint_key = use_signal(String::new);
+    let mut taint_value = use_signal(String::new);
+    let mut taint_effect = use_signal(|| TAINT_EFFECTS[0].to_string());
+
+    // Takes its own copy of the signal so it can be called from any task.
+    let finish = move |result: Result<Node, ApiError>| {
+        let mut error = error;
+        error.set(result.err());
+        on_change.call(());
+    };
+    let set_labels = move |labels: BTreeMap<String, Option<String>>| {
+        let settings = settings.read().clone();
+        spawn(async move {
+            finish(api::patch_node_labels(settings, node_name(), labels).await);
+        });
+    };
+    let add_label = move |_| {
+        let text = label.read().trim().to_string();
+        let Some((key, value)) = text.split_once('=') else {
+            error.set(Some(ApiError::Invalid(format!(
+                "'{}' is not a label; use key=value",
+                text
+            ))));
+            return;
+        };
+        set_labels([(key.trim().to_string(), Some(value.trim().to_string()))].into());
+        label.set(String::new());
+    };
+    let add_taint = move |_| {
+        let taint = Taint {
+            key: taint_key.read().trim().to_string(),
+            value: taint_value.read().trim().to_string(),
+            effect: taint_effect(),
+        };
+        let settings = settings.read().clone();
+        spawn(async move {
+            let result = api::add_node_taint(settings, node_name(), taint).await;
+            if result.is_ok() {
+                taint_key.set(String::new());
+                taint_value.set(String::new());
+            }
+            finish(result);
+        });
+    };
+
+    let labels: BTreeMap<_, _> = node.labels.iter().collect();
+
+    rsx! {
+        div { class: "bg-slate-900 border border-slate-800 rounded-xl p-5 mb-6 grid grid-cols-2 gap-6",
+            div {
+                h3 { class: "text-sm font-semibold text-white mb-3", "Labels" }
+                div { class: "flex flex-wrap gap-1.5 mb-3",
+                    if labels.is_empty() {
+                        span { class: "text-xs text-slate-600", "No labels" }
+                    }
+                    for (k, v) in labels {
+                        span { class: "inline-flex items-center gap-1 px-2 py-0.5 rounded bg-slate-800 text-[11px] font-mono text-slate-400",
+                            "{k}={v}"
+                            button {
+                                class: "text-slate-500 hover:text-red-400",
+                                title: "Remove",
+                                onclick: {
+                                    let key = k.clone();
+                                    move |_| set_labels([(key.clone(), None)].into())
+                                },
+                                Icon { width: 10, height: 10, icon: LdX }
+                            }
+                        }
+                    }
+                }
+                div { class: "flex gap-2",
+                    input {
+                        class: "flex-1 px-2.5 py-1.5 rounded-lg bg-slate-950 border border-slate-700 text-xs text-slate-200 font-mono outline-none focus:border-blue-500/60",
+                        placeholder: "key=value",
+                        value: "{label}",
+                        oninput: move |evt| label.set(evt.value()),
+                    }
+                    button {
+                        class: "flex items-center gap-1 px-3 py-1.5 text-xs rounded-lg bg-slate-800 text-slate-300 hover:bg-slate-700",
+                        onclick: add_label,
+                        Icon { width: 12, height: 12, icon: LdPlus }
+                        "Add"
+                    }
+                }
+            }
+            div {
+                h3 { class: "text-sm font-semibold text-white mb-3", "Taints" }
+                div { class: "flex flex-wrap gap-1.5 mb-3",
+                    if node.taints.is_empty() {
+                        span { class: "text-xs text-slate-600", "No taints" }
+                    }
+                    for t in node.taints.iter() {
+                        span { class: "inline-flex items-center gap-1 px-2 py-0.5 rounded bg-amber-500/10 text-[11px] font-mono text-amber-400",
+                            "{t}"
+                            button {
+                                class: "text-amber-600 hover:text-red-400",
+                                title: "Remove",
+                                onclick: {
+                                    let (key, effect) = (t.key.clone(), t.effect.clone());
+                                    move |_| {
+                                        let settings = settings.read().clone();
+                                        let (key, effect) = (key.clone(), effect.clone());
+                                        spawn(async move {
+                                            finish(api::remove_node_taint(settings, node_name(), key, effect).await);
+                                        });
+                                    }
+                                },
+                                Icon { width: 10, height: 10, icon: LdX }
+                            }
+                        }
+                    }
+                }
+                div { class: "flex gap-2",
+                    input {
+                        class: "flex-1 min-w-0 px-2.5 py-1.5 rounded-lg bg-slate-950 border border-slate-700 text-xs text-slate-200 font-mono outline-none focus:border-blue-500/60",
+                        placeholder: "key",
+                        value: "{taint_key}",
+                        oninput: move |evt| taint_key.set(evt.value()),
+                    }
+                    input {
+                        class: "flex-1 min-w-0 px-2.5 py-1.5 rounded-lg bg-slate-950 border border-slate-700 text-xs text-slate-200 font-mono outline-none focus:border-blue-500/60",
+                        placeholder: "value (optional)",
+                        value: "{taint_value}",
+                        oninput: move |evt| taint_value.set(evt.value()),
+                    }
+                    select {
+                        class: "px-2 py-1.5 rounded-lg bg-slate-950 border border-slate-700 text-xs text-slate-300",
+                        value: "{taint_effect}",
+                        onchange: move |evt| taint_effect.set(evt.value()),
+                        for effect in TAINT_EFFECTS {
+                            option { value: "{effect}", "{effect}" }
+                        }
+                    }
+                    button {
+                        class: "flex items-center gap-1 px-3 py-1.5 text-xs rounded-lg bg-slate-800 text-slate-300 hover:bg-slate-700",
+                        onclick: add_taint,
+                        Icon { width: 12, height: 12, icon: LdPlus }
+                        "Add"
+                    }
+                }
+            }
+            if let Some(err) = error.read().as_ref() {
+                div { class: "col-span-2 flex items-start gap-2 px-4 py-3 rounded-lg bg-red-500/10 border border-red-500/20 text-sm text-red-300",
+                    Icon { width: 14, height: 14, icon: LdTriangleAlert }
+                    "{err.title()}: {err.message()}"
+                }
+            }
+        }
+    }
+}
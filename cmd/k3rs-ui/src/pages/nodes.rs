@@ -1,31 +1,19 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
+use crate::units::{bar_class, fmt_cpu, fmt_mem, percent};
+use crate::Route;
 use dioxus::prelude::*;
 
 use super::dashboard::StatusBadge;
 
-fn fmt_cpu(millis: u64) -> String {
-    if millis >= 1000 {
-        format!("{:.1}CPU", millis as f64 / 1000.0)
-    } else {
-        format!("{}m", millis)
-    }
-}
-
-fn fmt_mem(bytes: u64) -> String {
-    if bytes >= 1024 * 1024 * 1024 {
-        format!("{:.1}Gi", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
-    } else if bytes >= 1024 * 1024 {
-        format!("{:.0}Mi", bytes as f64 / (1024.0 * 1024.0))
-    } else if bytes >= 1024 {
-        format!("{}Ki", bytes / 1024)
-    } else {
-        format!("{}B", bytes)
-    }
-}
-
 #[component]
 pub fn Nodes() -> Element {
-    let nodes = use_resource(move || async move { api::get_nodes().await.unwrap_or_default() });
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
+    let nodes = use_resource(move || {
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_nodes(settings).await) }
+    });
     let nodes_data = nodes.read();
 
     rsx! {
@@ -88,38 +76,22 @@ pub fn Nodes() -> Element {
 
                                     let cpu_cap = node.capacity.cpu_millis;
                                     let cpu_alloc = node.allocated.cpu_millis;
-                                    let cpu_pct = if cpu_cap > 0 {
-                                        (cpu_alloc * 100 / cpu_cap).min(100)
-                                    } else {
-                                        0
-                                    };
-                                    let cpu_bar_cls = if cpu_pct >= 80 {
-                                        "h-1 rounded-full bg-red-500"
-                                    } else if cpu_pct >= 60 {
-                                        "h-1 rounded-full bg-yellow-500"
-                                    } else {
-                                        "h-1 rounded-full bg-emerald-500"
-                                    };
+                                    let cpu_pct = percent(cpu_alloc, cpu_cap).unwrap_or(0);
+                                    let cpu_bar_cls = bar_class(cpu_pct);
 
                                     let mem_cap = node.capacity.memory_bytes;
                                     let mem_alloc = node.allocated.memory_bytes;
-                                    let mem_pct = if mem_cap > 0 {
-                                        (mem_alloc * 100 / mem_cap).min(100)
-                                    } else {
-                                        0
-                                    };
-                                    let mem_bar_cls = if mem_pct >= 80 {
-                                        "h-1 rounded-full bg-red-500"
-                                    } else if mem_pct >= 60 {
-                                        "h-1 rounded-full bg-yellow-500"
-                                    } else {
-                                        "h-1 rounded-full bg-emerald-500"
-                                    };
+                                    let mem_pct = percent(mem_alloc, mem_cap).unwrap_or(0);
+                                    let mem_bar_cls = bar_class(mem_pct);
 
                                     rsx! {
                                         tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
                                             td { class: "px-5 py-3 text-sm text-slate-300 font-medium",
-                                                "{node.name}"
+                                                Link {
+                                                    class: "hover:text-blue-400 transition-colors",
+                                                    to: Route::NodeDetail { name: node.name.clone() },
+                                                    "{node.name}"
+                                                }
                                             }
                                             td { class: "px-5 py-3",
                                                 StatusBadge { status: node.status.clone() }
@@ -0,0 +1,445 @@
+use crate::api::{self, ApiSettings, ExecInput, ExecOutput};
+use crate::logs::LogBuffer;
+use crate::toast::Toasts;
+use crate::units::age;
+use crate::watch::Live;
+use crate::{ContainerState, Pod, Route};
+use dioxus::fullstack::{use_websocket, WebSocketOptions};
+use dioxus::prelude::*;
+use dioxus_free_icons::icons::ld_icons::*;
+use dioxus_free_icons::Icon;
+// The prelude's `Ok` is fixed to `CapturedError`.
+use std::result::Result::Ok;
+
+use super::dashboard::StatusBadge;
+
+/// Choices for how many past lines the logs pane starts with.
+const TAIL_SIZES: [usize; 4] = [100, 500, 1000, 5000];
+const DEFAULT_TAIL: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tab {
+    Logs,
+    Exec,
+}
+
+/// The container's state for the table, and what to add about it.
+fn container_state(state: &ContainerState) -> (String, String) {
+    match state {
+        ContainerState::Waiting { reason, message } => {
+            (reason.clone(), message.clone().unwrap_or_default())
+        }
+        ContainerState::Running { started_at } => {
+            ("Running".to_string(), format!("for {}", age(*started_at)))
+        }
+        ContainerState::Terminated {
+            exit_code, reason, ..
+        } => (
+            reason.clone(),
+            exit_code
+                .map(|code| format!("exit code {}", code))
+                .unwrap_or_default(),
+        ),
+    }
+}
+
+/// Scroll the element `id` to its end.
+fn scroll_to_end(id: &str) {
+    document::eval(&format!(
+        "const el = document.getElementById({:?}); if (el) {{ el.scrollTop = el.scrollHeight; }}",
+        id
+    ));
+}
+
+#[component]
+pub fn PodDetail(namespace: String, name: String) -> Element {
+    let live = use_context::<Live>();
+    let mut current_ns = use_context::<Signal<String>>();
+    let mut tab = use_signal(|| Tab::Logs);
+    let mut selected = use_signal(|| None::<String>);
+
+    // The pod and its events come from the live caches of its namespace.
+    use_effect(use_reactive!(|namespace| {
+        if *current_ns.peek() != namespace {
+            current_ns.set(namespace);
+        }
+    }));
+
+    let cache = live.pods.read();
+    let pod = cache.as_ref().and_then(|c| c.get(&namespace, &name));
+    let loading = cache.is_none() || current_ns() != namespace;
+
+    rsx! {
+        div { class: "mb-6",
+            Link {
+                class: "inline-flex items-center gap-1.5 text-xs text-slate-500 hover:text-slate-300 mb-3",
+                to: Route::Pods {},
+                Icon { width: 12, height: 12, icon: LdArrowLeft }
+                "Pods"
+            }
+            div { class: "flex items-center gap-3",
+                h2 { class: "text-xl font-semibold text-white", "{name}" }
+                if let Some(pod) = pod {
+                    StatusBadge { status: pod.status.clone() }
+                }
+            }
+            if let Some(message) = pod.and_then(|p| p.status_message.as_deref()) {
+                p { class: "text-sm text-slate-400 mt-1", "{message}" }
+            }
+        }
+
+        if let Some(pod) = pod {
+            PodSummary { pod: pod.clone() }
+
+            {
+                let containers: Vec<String> = pod.spec.containers.iter().map(|c| c.name.clone()).collect();
+                let container = selected()
+                    .filter(|c| containers.contains(c))
+                    .or_else(|| containers.first().cloned());
+                let key = format!("{}/{}/{}", namespace, name, container.as_deref().unwrap_or_default());
+                rsx! {
+                    div { class: "bg-slate-900 border border-slate-800 rounded-xl overflow-hidden mb-6",
+                        div { class: "flex items-center gap-1 px-3 pt-3 border-b border-slate-800",
+                            button {
+                                class: if tab() == Tab::Logs { "flex items-center gap-1.5 px-3 py-2 text-sm text-blue-400 border-b-2 border-blue-500" } else { "flex items-center gap-1.5 px-3 py-2 text-sm text-slate-400 hover:text-slate-200 border-b-2 border-transparent" },
+                                onclick: move |_| tab.set(Tab::Logs),
+                                Icon { width: 14, height: 14, icon: LdScrollText }
+                                "Logs"
+                            }
+                            button {
+                                class: if tab() == Tab::Exec { "flex items-center gap-1.5 px-3 py-2 text-sm text-blue-400 border-b-2 border-blue-500" } else { "flex items-center gap-1.5 px-3 py-2 text-sm text-slate-400 hover:text-slate-200 border-b-2 border-transparent" },
+                                onclick: move |_| tab.set(Tab::Exec),
+                                Icon { width: 14, height: 14, icon: LdTerminal }
+                                "Exec"
+                            }
+                            if containers.len() > 1 {
+                                select {
+                                    class: "ml-auto mb-2 px-2 py-1 rounded-lg bg-slate-950 border border-slate-700 text-xs text-slate-300",
+                                    value: container.clone().unwrap_or_default(),
+                                    onchange: move |evt| selected.set(Some(evt.value())),
+                                    for c in containers.iter() {
+                                        option { value: "{c}", "{c}" }
+                                    }
+                                }
+                            }
+                        }
+                        // Each pane is dropped when another tab or page is
+                        // shown, which closes its connection.
+                        if tab() == Tab::Logs {
+                            LogsPane {
+                                key: "{key}",
+                                namespace: namespace.clone(),
+                                pod: name.clone(),
+                                container: container.clone(),
+                            }
+                        } else {
+                            ExecPane {
+                                key: "{key}",
+                                namespace: namespace.clone(),
+                                pod: name.clone(),
+                                container: container.clone(),
+                            }
+                        }
+                    }
+                }
+            }
+
+            PodEvents { namespace: namespace.clone(), name: name.clone() }
+        } else if loading {
+            div { class: "flex flex-col items-center justify-center py-20 text-slate-500",
+                Icon { width: 32, height: 32, icon: LdLoader }
+                p { class: "mt-3 text-sm", "Loading pod..." }
+            }
+        } else {
+            div { class: "flex flex-col items-center justify-center py-20 text-slate-500",
+                Icon { width: 32, height: 32, icon: LdInbox }
+                p { class: "mt-3 text-sm", "Pod not found in {namespace}" }
+            }
+        }
+    }
+}
+
+/// Where the pod runs, its labels and its containers.
+#[component]
+fn PodSummary(pod: Pod) -> Element {
+    let dash = "\u{2014}".to_string();
+    let fields = [
+        ("Namespace", pod.namespace.clone()),
+        ("Node", pod.node_name.clone().unwrap_or(dash.clone())),
+        ("Restarts", pod.restart_count.to_string()),
+        ("Age", age(pod.created_at)),
+        ("Pod IP", pod.pod_ip.clone().unwrap_or(dash.clone())),
+        ("VPC", pod.vpc_name.clone().unwrap_or(dash.clone())),
+    ];
+
+    rsx! {
+        div { class: "bg-slate-900 border border-slate-800 rounded-xl p-5 mb-6",
+            div { class: "grid grid-cols-6 gap-4",
+                for (label, value) in fields {
+                    div {
+                        p { class: "text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "{label}" }
+                        p { class: "text-sm text-slate-300 mt-1 truncate", "{value}" }
+                    }
+                }
+            }
+            if !pod.labels.is_empty() {
+                div { class: "flex flex-wrap gap-1.5 mt-4",
+                    for (k, v) in pod.labels.iter() {
+                        span { class: "inline-flex items-center gap-1 px-2 py-0.5 rounded bg-slate-800 text-[11px] font-mono text-slate-400",
+                            Icon { width: 10, height: 10, icon: LdTag }
+                            "{k}={v}"
+                        }
+                    }
+                }
+            }
+        }
+
+        div { class: "bg-slate-900 border border-slate-800 rounded-xl overflow-hidden mb-6",
+            table { class: "w-full",
+                thead {
+                    tr { class: "border-b border-slate-800",
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Container" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Image" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "State" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Ready" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Restarts" }
+                    }
+                }
+                tbody {
+                    for c in pod.spec.containers.iter() {
+                        {
+                            let status = pod.container_statuses.iter().find(|s| s.name == c.name);
+                            let (state, detail) = status
+                                .map(|s| container_state(&s.state))
+                                .unwrap_or_else(|| (dash.clone(), String::new()));
+                            rsx! {
+                                tr { class: "border-b border-slate-800/50",
+                                    td { class: "px-5 py-3 text-sm text-slate-300 font-medium",
+                                        div { class: "flex items-center gap-2",
+                                            Icon { width: 13, height: 13, icon: LdBox }
+                                            "{c.name}"
+                                        }
+                                    }
+                                    td { class: "px-5 py-3 text-xs font-mono text-slate-400", "{c.image}" }
+                                    td { class: "px-5 py-3",
+                                        StatusBadge { status: state }
+                                        if !detail.is_empty() {
+                                            span { class: "ml-2 text-xs text-slate-500", "{detail}" }
+                                        }
+                                    }
+                                    td { class: "px-5 py-3 text-xs text-slate-400",
+                                        if status.is_some_and(|s| s.ready) { "Yes" } else { "No" }
+                                    }
+                                    td { class: "px-5 py-3 text-xs text-slate-400",
+                                        "{status.map(|s| s.restart_count).unwrap_or_default()}"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The container's log, followed as it grows.
+#[component]
+fn LogsPane(namespace: String, pod: String, container: Option<String>) -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
+    let mut tail = use_signal(|| DEFAULT_TAIL);
+    let mut logs = use_signal(LogBuffer::default);
+    let mut streaming = use_signal(|| false);
+
+    // Restarted with the tail size; dropping it closes the stream.
+    let _stream = use_resource(move || {
+        let settings = settings.read().clone();
+        let tail = tail();
+        let (namespace, pod, container) = (namespace.clone(), pod.clone(), container.clone());
+        async move {
+            let following = logs.peek().following();
+            let mut buf = LogBuffer::default();
+            buf.set_following(following);
+            logs.set(buf);
+            let mut stream = match api::follow_logs(settings, namespace, pod, container, tail).await
+            {
+                Ok(stream) => stream,
+                Err(err) => return toasts.push(err),
+            };
+            streaming.set(true);
+            while let Some(Ok(text)) = stream.next().await {
+                logs.write().push(&text);
+            }
+            streaming.set(false);
+        }
+    });
+
+    use_effect(move || {
+        if logs.read().following() {
+            scroll_to_end("pod-logs");
+        }
+    });
+
+    let buf = logs.read();
+    let text = buf.lines().collect::<Vec<_>>().join("\n");
+
+    rsx! {
+        div { class: "flex items-center gap-3 px-4 py-2 border-b border-slate-800/60",
+            span {
+                class: if streaming() { "w-1.5 h-1.5 rounded-full bg-emerald-400" } else { "w-1.5 h-1.5 rounded-full bg-slate-600" },
+            }
+            span { class: "text-xs text-slate-500", if streaming() { "Following" } else { "Not connected" } }
+            label { class: "ml-auto text-xs text-slate-500", "Tail" }
+            select {
+                class: "px-2 py-1 rounded-lg bg-slate-950 border border-slate-700 text-xs text-slate-300",
+                value: "{tail}",
+                onchange: move |evt| tail.set(evt.value().parse().unwrap_or(DEFAULT_TAIL)),
+                for size in TAIL_SIZES {
+                    option { value: "{size}", "{size} lines" }
+                }
+            }
+            button {
+                class: "flex items-center gap-1.5 px-3 py-1 text-xs rounded-lg bg-slate-800 text-slate-300 hover:bg-slate-700",
+                onclick: move |_| logs.write().toggle_following(),
+                if buf.following() {
+                    Icon { width: 12, height: 12, icon: LdPause }
+                    "Pause"
+                } else {
+                    Icon { width: 12, height: 12, icon: LdPlay }
+                    if buf.held() > 0 { "Resume ({buf.held()} new)" } else { "Resume" }
+                }
+            }
+        }
+        pre {
+            id: "pod-logs",
+            class: "h-96 overflow-auto px-4 py-3 text-xs font-mono text-slate-300 whitespace-pre-wrap break-all",
+            if buf.is_empty() {
+                span { class: "text-slate-600", "No log output yet" }
+            } else {
+                "{text}"
+            }
+        }
+    }
+}
+
+/// A line-mode shell in the container: each line typed is sent with its
+/// newline, and the output is shown as it comes.
+#[component]
+fn ExecPane(namespace: String, pod: String, container: Option<String>) -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
+    let mut socket = use_websocket(|| api::exec(WebSocketOptions::new()));
+    let mut output = use_signal(LogBuffer::default);
+    let mut line = use_signal(String::new);
+    let mut closed = use_signal(|| false);
+
+    use_future(move || {
+        let start = ExecInput::Start {
+            settings: settings.peek().clone(),
+            namespace: namespace.clone(),
+            pod: pod.clone(),
+            container: container.clone(),
+        };
+        async move {
+            if socket.send(start).await.is_ok() {
+                loop {
+                    match socket.recv().await {
+                        Ok(ExecOutput::Output(text)) => output.write().push(&text),
+                        Ok(ExecOutput::Failed(err)) => toasts.push(err),
+                        Err(_) => break,
+                    }
+                }
+            }
+            closed.set(true);
+        }
+    });
+
+    use_effect(move || {
+        output.read();
+        scroll_to_end("pod-exec");
+    });
+
+    let mut submit = move || {
+        let text = line.take();
+        output.write().push(&format!("$ {}\n", text));
+        spawn(async move {
+            if socket
+                .send(ExecInput::Stdin(format!("{}\n", text)))
+                .await
+                .is_err()
+            {
+                closed.set(true);
+            }
+        });
+    };
+
+    let buf = output.read();
+    let text = buf.lines().collect::<Vec<_>>().join("\n");
+
+    rsx! {
+        pre {
+            id: "pod-exec",
+            class: "h-96 overflow-auto px-4 py-3 text-xs font-mono text-slate-300 whitespace-pre-wrap break-all bg-slate-950",
+            "{text}"
+        }
+        div { class: "flex items-center gap-2 px-4 py-2 border-t border-slate-800/60",
+            span { class: "text-xs font-mono text-slate-500", "$" }
+            input {
+                class: "flex-1 bg-transparent text-xs font-mono text-slate-200 outline-none disabled:opacity-50",
+                placeholder: if closed() { "Session closed" } else { "Type a command and press Enter" },
+                disabled: closed(),
+                value: "{line}",
+                oninput: move |evt| line.set(evt.value()),
+                onkeydown: move |evt| {
+                    if evt.key() == Key::Enter {
+                        submit();
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// Events about the pod, newest first.
+#[component]
+fn PodEvents(namespace: String, name: String) -> Element {
+    let live = use_context::<Live>();
+    let feed = live.events.read();
+    let events: Vec<_> = feed
+        .as_ref()
+        .map(|f| {
+            f.newest()
+                .filter(|e| {
+                    e.kind.eq_ignore_ascii_case("pod") && e.namespace == namespace && e.name == name
+                })
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    rsx! {
+        h3 { class: "text-sm font-semibold text-slate-300 mb-3", "Events" }
+        if events.is_empty() {
+            p { class: "text-sm text-slate-500", "No events for this pod" }
+        } else {
+            div { class: "space-y-2",
+                for evt in events.iter() {
+                    div { class: "bg-slate-900 border border-slate-800 rounded-lg px-4 py-3 flex items-center gap-3",
+                        span {
+                            class: if evt.event_type == "Warning" {
+                                "inline-block px-2 py-0.5 rounded text-[10px] font-semibold uppercase bg-amber-500/10 text-amber-400"
+                            } else {
+                                "inline-block px-2 py-0.5 rounded text-[10px] font-semibold uppercase bg-emerald-500/10 text-emerald-400"
+                            },
+                            "{evt.event_type}"
+                        }
+                        span { class: "text-xs font-semibold text-slate-300 shrink-0", "{evt.reason}" }
+                        span { class: "text-xs text-slate-400 flex-1 truncate", "{evt.message}" }
+                        span { class: "text-[11px] text-slate-600 shrink-0", "{evt.timestamp}" }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,16 +1,23 @@
-use crate::api;
+use crate::editor::EditYamlButton;
+use crate::manifest::ManifestKind;
+use crate::watch::Live;
+use crate::Route;
 use dioxus::prelude::*;
 
 use super::dashboard::StatusBadge;
 
+/// Pods shown at first, and added by each "Load more".
+const PAGE_SIZE: usize = 100;
+
 #[component]
 pub fn Pods() -> Element {
-    let ns = use_context::<Signal<String>>();
-    let pods = use_resource(move || {
-        let ns = ns.read().clone();
-        async move { api::get_pods(ns).await.unwrap_or_default() }
-    });
-    let data = pods.read();
+    let live = use_context::<Live>();
+    let mut limit = use_signal(|| PAGE_SIZE);
+    let cache = live.pods.read();
+    let data = cache
+        .as_ref()
+        .map(|c| c.iter().take(limit()).collect::<Vec<_>>());
+    let more = cache.as_ref().is_some_and(|c| c.len() > limit());
 
     rsx! {
         div { class: "mb-6",
@@ -28,21 +35,31 @@ pub fn Pods() -> Element {
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "VPC" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Ghost IPv6" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "ID" }
+                        th { class: "px-5 py-2.5" }
                     }
                 }
                 tbody {
-                    if let Some(pods) = data.as_ref() {
+                    if let Some(pods) = data {
                         if pods.is_empty() {
-                            tr { td { colspan: "6", class: "text-center py-16 text-slate-500 text-sm", "No pods found" } }
+                            tr { td { colspan: "7", class: "text-center py-16 text-slate-500 text-sm", "No pods found" } }
                         } else {
                             for pod in pods.iter() {
                                 tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
-                                    td { class: "px-5 py-3 text-sm text-slate-300 font-medium", "{pod.name}" }
+                                    td { class: "px-5 py-3 text-sm text-slate-300 font-medium",
+                                        Link {
+                                            class: "hover:text-blue-400 transition-colors",
+                                            to: Route::PodDetail { namespace: pod.namespace.clone(), name: pod.name.clone() },
+                                            "{pod.name}"
+                                        }
+                                    }
                                     td { class: "px-5 py-3", StatusBadge { status: pod.status.clone() } }
                                     td { class: "px-5 py-3 text-xs text-slate-500", "{pod.node_name.as_deref().unwrap_or(\"\u{2014}\")}" }
                                     td { class: "px-5 py-3 text-xs text-slate-400", "{pod.vpc_name.as_deref().unwrap_or(\"\u{2014}\")}" }
                                     td { class: "px-5 py-3 text-xs font-mono text-cyan-400/70", "{pod.ghost_ipv6.as_deref().unwrap_or(\"\u{2014}\")}" }
                                     td { class: "px-5 py-3 text-xs font-mono text-slate-600", "{pod.id}" }
+                                    td { class: "px-5 py-3 text-right",
+                                        EditYamlButton { kind: ManifestKind::Pod, namespace: pod.namespace.clone(), name: pod.name.clone() }
+                                    }
                                 }
                             }
                         }
@@ -50,5 +67,15 @@ pub fn Pods() -> Element {
                 }
             }
         }
+
+        if more {
+            div { class: "mt-4 text-center",
+                button {
+                    class: "px-4 py-2 text-sm rounded-lg bg-slate-800 text-slate-300 hover:bg-slate-700",
+                    onclick: move |_| limit += PAGE_SIZE,
+                    "Load more"
+                }
+            }
+        }
     }
 }
@@ -1,10 +1,13 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 use dioxus_free_icons::icons::ld_icons::*;
 use dioxus_free_icons::Icon;
 
 #[component]
 pub fn ProcessList() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let mut refresh_tick = use_signal(|| 0u32);
     let mut auto_reload = use_signal(|| true);
     let mut interval_secs = use_signal(|| 3u64);
@@ -28,7 +31,8 @@ pub fn ProcessList() -> Element {
     // Fetch processes (re-fetches when refresh_tick changes)
     let processes = use_resource(move || {
         let _tick = *refresh_tick.read(); // subscribe to refresh_tick
-        async move { api::get_processes().await.unwrap_or_default() }
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_processes(settings).await) }
     });
     let procs_data = processes.read();
 
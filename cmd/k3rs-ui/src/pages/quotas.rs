@@ -1,19 +1,30 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
+use crate::ResourceRequirements;
 use dioxus::prelude::*;
 
 #[component]
 pub fn Quotas() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
     let quotas = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_quotas(ns).await.unwrap_or_default() }
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_quotas(settings, ns).await) }
     });
     let quotas_data = quotas.read();
+    let ranges = use_resource(move || {
+        let ns = ns.read().clone();
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_limit_ranges(settings, ns).await) }
+    });
+    let ranges_data = ranges.read();
 
     rsx! {
         div { class: "mb-6",
             h2 { class: "text-xl font-semibold text-white", "Resource Quotas" }
-            p { class: "text-sm text-slate-400 mt-1", "Namespace resource limits" }
+            p { class: "text-sm text-slate-400 mt-1", "Namespace resource usage against its limits" }
         }
 
         div { class: "bg-slate-900 border border-slate-800 rounded-xl overflow-hidden",
@@ -22,9 +33,9 @@ pub fn Quotas() -> Element {
                     tr { class: "border-b border-slate-800",
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Name" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Namespace" }
-                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Max Pods" }
-                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Max CPU (cores)" }
-                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Max Memory" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Pods" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "CPU (cores)" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Memory" }
                     }
                 }
                 tbody {
@@ -36,9 +47,18 @@ pub fn Quotas() -> Element {
                         } else {
                             for q in items.iter() {
                                 {
-                                    let cpu = q.max_cpu_millis.map(|c| format!("{:.1}", c as f64 / 1000.0)).unwrap_or("—".into());
-                                    let mem = q.max_memory_bytes.map(|m| format!("{} MB", m / 1_000_000)).unwrap_or("—".into());
-                                    let pods = q.max_pods.map(|p| p.to_string()).unwrap_or("—".into());
+                                    let hard = |max: Option<String>| max.unwrap_or("—".into());
+                                    let cpu = format!(
+                                        "{:.1} / {}",
+                                        q.used.cpu_millis as f64 / 1000.0,
+                                        hard(q.hard.max_cpu_millis.map(|c| format!("{:.1}", c as f64 / 1000.0)))
+                                    );
+                                    let mem = format!(
+                                        "{} MB / {}",
+                                        q.used.memory_bytes / 1_000_000,
+                                        hard(q.hard.max_memory_bytes.map(|m| format!("{} MB", m / 1_000_000)))
+                                    );
+                                    let pods = format!("{} / {}", q.used.pods, hard(q.hard.max_pods.map(|p| p.to_string())));
                                     rsx! {
                                         tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
                                             td { class: "px-5 py-3 text-sm text-slate-300 font-medium", "{q.name}" }
@@ -55,5 +75,67 @@ pub fn Quotas() -> Element {
                 }
             }
         }
+
+        div { class: "mt-8 mb-4",
+            h3 { class: "text-lg font-semibold text-white", "Limit Ranges" }
+            p { class: "text-sm text-slate-400 mt-1", "Per-container defaults and bounds applied when pods are created" }
+        }
+
+        div { class: "bg-slate-900 border border-slate-800 rounded-xl overflow-hidden",
+            table { class: "w-full",
+                thead {
+                    tr { class: "border-b border-slate-800",
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Name" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Default Request" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Default Limit" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Min" }
+                        th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Max" }
+                    }
+                }
+                tbody {
+                    if let Some(items) = ranges_data.as_ref() {
+                        if items.is_empty() {
+                            tr {
+                                td { colspan: "5", class: "text-center py-16 text-slate-500 text-sm", "No limit ranges defined" }
+                            }
+                        } else {
+                            for lr in items.iter() {
+                                {
+                                    let default_request = format_resources(&lr.default_request);
+                                    let default_limit = format_resources(&lr.default_limit);
+                                    let min = format_resources(&lr.min);
+                                    let max = format_resources(&lr.max);
+                                    rsx! {
+                                        tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
+                                            td { class: "px-5 py-3 text-sm text-slate-300 font-medium", "{lr.name}" }
+                                            td { class: "px-5 py-3 text-sm text-cyan-400 font-mono", "{default_request}" }
+                                            td { class: "px-5 py-3 text-sm text-cyan-400 font-mono", "{default_limit}" }
+                                            td { class: "px-5 py-3 text-sm text-cyan-400 font-mono", "{min}" }
+                                            td { class: "px-5 py-3 text-sm text-cyan-400 font-mono", "{max}" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// `0.1 cores, 64 MB` for the set fields of `r`, `—` if none is set.
+fn format_resources(r: &ResourceRequirements) -> String {
+    let mut parts = Vec::new();
+    if r.cpu_millis > 0 {
+        parts.push(format!("{:.1} cores", r.cpu_millis as f64 / 1000.0));
+    }
+    if r.memory_bytes > 0 {
+        parts.push(format!("{} MB", r.memory_bytes / 1_000_000));
+    }
+    if parts.is_empty() {
+        "—".to_string()
+    } else {
+        parts.join(", ")
     }
 }
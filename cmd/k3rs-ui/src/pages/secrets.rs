@@ -1,12 +1,20 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::editor::{EditYamlButton, Editor};
+use crate::manifest::ManifestKind;
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 #[component]
 pub fn Secrets() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
+    let editor = use_context::<Editor>();
     let secrets = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_secrets(ns).await.unwrap_or_default() }
+        editor.applied();
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_secrets(settings, ns).await) }
     });
     let data = secrets.read();
 
@@ -24,12 +32,13 @@ pub fn Secrets() -> Element {
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Keys" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Namespace" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "ID" }
+                        th { class: "px-5 py-2.5" }
                     }
                 }
                 tbody {
                     if let Some(secrets) = data.as_ref() {
                         if secrets.is_empty() {
-                            tr { td { colspan: "4", class: "text-center py-16 text-slate-500 text-sm", "No secrets found" } }
+                            tr { td { colspan: "5", class: "text-center py-16 text-slate-500 text-sm", "No secrets found" } }
                         } else {
                             for s in secrets.iter() {
                                 tr { class: "border-b border-slate-800/50 hover:bg-slate-800/30 transition-colors",
@@ -37,6 +46,9 @@ pub fn Secrets() -> Element {
                                     td { class: "px-5 py-3 text-sm text-slate-400", "{s.data.len()}" }
                                     td { class: "px-5 py-3 text-xs text-slate-500", "{s.namespace}" }
                                     td { class: "px-5 py-3 text-xs font-mono text-slate-600", "{s.id}" }
+                                    td { class: "px-5 py-3 text-right",
+                                        EditYamlButton { kind: ManifestKind::Secret, namespace: s.namespace.clone(), name: s.name.clone() }
+                                    }
                                 }
                             }
                         }
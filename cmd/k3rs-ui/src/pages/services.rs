@@ -1,12 +1,20 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::editor::{EditYamlButton, Editor};
+use crate::manifest::ManifestKind;
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 #[component]
 pub fn Services() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
+    let editor = use_context::<Editor>();
     let services = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_services(ns).await.unwrap_or_default() }
+        editor.applied();
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_services(settings, ns).await) }
     });
     let svcs_data = services.read();
 
@@ -25,12 +33,13 @@ pub fn Services() -> Element {
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Cluster IP" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "Ports" }
                         th { class: "text-left px-5 py-2.5 text-[11px] uppercase tracking-wider text-slate-500 font-semibold", "ID" }
+                        th { class: "px-5 py-2.5" }
                     }
                 }
                 tbody {
                     if let Some(svcs) = svcs_data.as_ref() {
                         if svcs.is_empty() {
-                            tr { td { colspan: "5", class: "text-center py-16 text-slate-500 text-sm", "No services found" } }
+                            tr { td { colspan: "6", class: "text-center py-16 text-slate-500 text-sm", "No services found" } }
                         } else {
                             for svc in svcs.iter() {
                                 {
@@ -54,6 +63,9 @@ pub fn Services() -> Element {
                                             td { class: "px-5 py-3 text-xs font-mono text-slate-500", "{svc.cluster_ip.as_deref().unwrap_or(\"—\")}" }
                                             td { class: "px-5 py-3 text-xs font-mono text-slate-500", "{ports_display}" }
                                             td { class: "px-5 py-3 text-xs font-mono text-slate-600", "{svc.id}" }
+                                            td { class: "px-5 py-3 text-right",
+                                                EditYamlButton { kind: ManifestKind::Service, namespace: svc.namespace.clone(), name: svc.name.clone() }
+                                            }
                                         }
                                     }
                                 }
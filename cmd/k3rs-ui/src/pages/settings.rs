@@ -0,0 +1,106 @@
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
+use dioxus::prelude::*;
+use dioxus_free_icons::icons::ld_icons::*;
+use dioxus_free_icons::Icon;
+
+/// The API server and token every page uses, saved in this browser.
+#[component]
+pub fn Settings() -> Element {
+    let mut settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
+    let mut server = use_signal(|| settings.read().server.clone());
+    let mut token = use_signal(|| settings.read().token.clone());
+    let mut notice = use_signal(|| None::<String>);
+
+    let draft = move || ApiSettings {
+        server: server.read().trim().to_string(),
+        token: token.read().trim().to_string(),
+    };
+
+    let save = move |_| {
+        let new = draft();
+        new.save();
+        settings.set(new);
+        notice.set(Some("Saved".to_string()));
+    };
+    let reset = move |_| {
+        let defaults = ApiSettings::default();
+        server.set(defaults.server.clone());
+        token.set(defaults.token.clone());
+        defaults.save();
+        settings.set(defaults);
+        notice.set(Some("Reset to the defaults".to_string()));
+    };
+    let test = move |_| async move {
+        notice.set(Some("Connecting...".to_string()));
+        match api::get_cluster_info(draft()).await {
+            Ok(info) => notice.set(Some(format!(
+                "Connected to {} (k3rs {}, {} nodes)",
+                info.endpoint, info.version, info.node_count
+            ))),
+            Err(err) => {
+                notice.set(None);
+                toasts.push(err);
+            }
+        }
+    };
+
+    rsx! {
+        div { class: "mb-6",
+            h2 { class: "text-xl font-semibold text-white", "Settings" }
+            p { class: "text-sm text-slate-400 mt-1", "API server this dashboard talks to" }
+        }
+
+        div { class: "bg-slate-900 border border-slate-800 rounded-xl p-6 max-w-xl space-y-5",
+            div {
+                label { class: "text-[11px] uppercase tracking-wider text-slate-500 font-semibold block mb-1.5",
+                    "Server URL"
+                }
+                input {
+                    class: "w-full px-3 py-2 rounded-lg bg-slate-950 border border-slate-700 text-sm text-slate-200 font-mono outline-none focus:border-blue-500/60",
+                    r#type: "url",
+                    value: "{server}",
+                    oninput: move |evt| server.set(evt.value()),
+                }
+            }
+            div {
+                label { class: "text-[11px] uppercase tracking-wider text-slate-500 font-semibold block mb-1.5",
+                    "Token"
+                }
+                input {
+                    class: "w-full px-3 py-2 rounded-lg bg-slate-950 border border-slate-700 text-sm text-slate-200 font-mono outline-none focus:border-blue-500/60",
+                    r#type: "password",
+                    autocomplete: "off",
+                    value: "{token}",
+                    oninput: move |evt| token.set(evt.value()),
+                }
+                p { class: "text-xs text-slate-600 mt-1.5",
+                    "Kept in this browser's local storage."
+                }
+            }
+
+            div { class: "flex items-center gap-3 pt-1",
+                button {
+                    class: "px-4 py-2 text-sm rounded-lg bg-blue-600 text-white hover:bg-blue-500",
+                    onclick: save,
+                    "Save"
+                }
+                button {
+                    class: "flex items-center gap-1.5 px-4 py-2 text-sm rounded-lg bg-slate-800 text-slate-300 hover:bg-slate-700",
+                    onclick: test,
+                    Icon { width: 13, height: 13, icon: LdPlug }
+                    "Test connection"
+                }
+                button {
+                    class: "px-4 py-2 text-sm rounded-lg text-slate-500 hover:text-slate-300",
+                    onclick: reset,
+                    "Reset"
+                }
+                if let Some(text) = notice.read().as_ref() {
+                    span { class: "text-xs text-slate-400", "{text}" }
+                }
+            }
+        }
+    }
+}
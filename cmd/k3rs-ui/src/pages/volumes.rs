@@ -1,14 +1,18 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 use super::dashboard::StatusBadge;
 
 #[component]
 pub fn Volumes() -> Element {
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
     let ns = use_context::<Signal<String>>();
     let pvcs = use_resource(move || {
         let ns = ns.read().clone();
-        async move { api::get_pvcs(ns).await.unwrap_or_default() }
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_pvcs(settings, ns).await) }
     });
     let pvcs_data = pvcs.read();
 
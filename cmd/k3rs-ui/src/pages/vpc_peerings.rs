@@ -1,12 +1,17 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 use super::dashboard::StatusBadge;
 
 #[component]
 pub fn VpcPeerings() -> Element {
-    let peerings =
-        use_resource(|| async move { api::get_vpc_peerings().await.unwrap_or_default() });
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
+    let peerings = use_resource(move || {
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_vpc_peerings(settings).await) }
+    });
     let data = peerings.read();
 
     rsx! {
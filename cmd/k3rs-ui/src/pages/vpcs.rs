@@ -1,11 +1,17 @@
-use crate::api;
+use crate::api::{self, ApiSettings};
+use crate::toast::Toasts;
 use dioxus::prelude::*;
 
 use super::dashboard::StatusBadge;
 
 #[component]
 pub fn Vpcs() -> Element {
-    let vpcs = use_resource(|| async move { api::get_vpcs().await.unwrap_or_default() });
+    let settings = use_context::<Signal<ApiSettings>>();
+    let toasts = use_context::<Toasts>();
+    let vpcs = use_resource(move || {
+        let settings = settings.read().clone();
+        async move { toasts.report(api::get_vpcs(settings).await) }
+    });
     let data = vpcs.read();
 
     rsx! {
@@ -0,0 +1,103 @@
+//! Failed API calls, shown as dismissible toasts in the corner of the page.
+
+use dioxus::prelude::*;
+use dioxus_free_icons::icons::ld_icons::*;
+use dioxus_free_icons::Icon;
+
+use crate::api::ApiError;
+use crate::Route;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Toast {
+    id: u64,
+    error: ApiError,
+}
+
+/// The toasts on screen. The layout provides them; pages get them with
+/// `use_context::<Toasts>()`.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Toasts {
+    items: Signal<Vec<Toast>>,
+    next_id: Signal<u64>,
+}
+
+/// Must be created inside a component, like any signal.
+impl Default for Toasts {
+    fn default() -> Self {
+        Self {
+            items: Signal::new(Vec::new()),
+            next_id: Signal::new(0),
+        }
+    }
+}
+
+impl Toasts {
+    /// Show `error`, unless the same one is still on screen (pages that
+    /// poll would stack copies of it).
+    pub fn push(mut self, error: ApiError) {
+        // `peek`: a resource reporting its error must not re-run when the
+        // toasts change.
+        if self.items.peek().iter().any(|t| t.error == error) {
+            return;
+        }
+        let id = *self.next_id.peek();
+        self.next_id.set(id + 1);
+        self.items.write().push(Toast { id, error });
+    }
+
+    pub fn dismiss(mut self, id: u64) {
+        self.items.write().retain(|t| t.id != id);
+    }
+
+    /// The value of `result`; `T::default()` once its error is shown.
+    pub fn report<T: Default>(self, result: Result<T, ApiError>) -> T {
+        result.unwrap_or_else(|err| {
+            self.push(err);
+            T::default()
+        })
+    }
+}
+
+/// The toasts, newest at the bottom.
+#[component]
+pub fn ToastStack() -> Element {
+    let toasts = use_context::<Toasts>();
+    let items = toasts.items.read();
+
+    rsx! {
+        div { class: "fixed bottom-4 right-4 z-50 flex flex-col gap-2 w-96",
+            for toast in items.iter() {
+                div {
+                    key: "{toast.id}",
+                    class: "flex items-start gap-3 px-4 py-3 rounded-xl bg-slate-900 border border-red-500/30 shadow-lg shadow-black/40",
+                    div { class: "text-red-400 shrink-0 mt-0.5",
+                        Icon { width: 16, height: 16, icon: LdCircleAlert }
+                    }
+                    div { class: "flex-1 min-w-0",
+                        p { class: "text-sm font-semibold text-slate-200", "{toast.error.title()}" }
+                        p { class: "text-xs text-slate-400 mt-0.5 break-words", "{toast.error.message()}" }
+                        if matches!(
+                            toast.error,
+                            ApiError::Unauthorized(_) | ApiError::Unreachable(_) | ApiError::Invalid(_)
+                        ) {
+                            Link {
+                                class: "inline-block text-xs text-blue-400 hover:text-blue-300 mt-1.5",
+                                to: Route::Settings {},
+                                "Check the API settings"
+                            }
+                        }
+                    }
+                    button {
+                        class: "text-slate-500 hover:text-slate-300 shrink-0",
+                        title: "Dismiss",
+                        onclick: {
+                            let id = toast.id;
+                            move |_| toasts.dismiss(id)
+                        },
+                        Icon { width: 14, height: 14, icon: LdX }
+                    }
+                }
+            }
+        }
+    }
+}
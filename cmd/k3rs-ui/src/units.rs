@@ -0,0 +1,101 @@
+//! CPU, memory and ages as the pages show them, and how full a node is.
+
+use chrono::{DateTime, Utc};
+
+pub fn fmt_cpu(millis: u64) -> String {
+    if millis >= 1000 {
+        format!("{:.1}CPU", millis as f64 / 1000.0)
+    } else {
+        format!("{}m", millis)
+    }
+}
+
+pub fn fmt_mem(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 * 1024 {
+        format!("{:.1}Gi", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+    } else if bytes >= 1024 * 1024 {
+        format!("{:.0}Mi", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{}Ki", bytes / 1024)
+    } else {
+        format!("{}B", bytes)
+    }
+}
+
+/// `used` as a whole percentage of `total`, at most 100; `None` when there
+/// is no `total` to use.
+pub fn percent(used: u64, total: u64) -> Option<u64> {
+    (u128::from(used) * 100)
+        .checked_div(u128::from(total))
+        .map(|pct| pct.min(100) as u64)
+}
+
+/// Class of a bar that is `pct` full: red from 80%, yellow from 60%.
+pub fn bar_class(pct: u64) -> &'static str {
+    if pct >= 80 {
+        "h-1 rounded-full bg-red-500"
+    } else if pct >= 60 {
+        "h-1 rounded-full bg-yellow-500"
+    } else {
+        "h-1 rounded-full bg-emerald-500"
+    }
+}
+
+/// How long before `now` `since` was, as `45s`, `12m`, `3h` or `5d`.
+pub fn age_at(since: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let secs = (now - since).num_seconds().max(0);
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+pub fn age(since: DateTime<Utc>) -> String {
+    age_at(since, Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_cpu_and_memory_formatting() {
+        assert_eq!(fmt_cpu(250), "250m");
+        assert_eq!(fmt_cpu(1500), "1.5CPU");
+        assert_eq!(fmt_mem(512), "512B");
+        assert_eq!(fmt_mem(64 * 1024), "64Ki");
+        assert_eq!(fmt_mem(256 * 1024 * 1024), "256Mi");
+        assert_eq!(fmt_mem(3 * 1024 * 1024 * 1024 / 2), "1.5Gi");
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(500, 2000), Some(25));
+        assert_eq!(percent(1, 3), Some(33));
+        assert_eq!(percent(3000, 2000), Some(100));
+        assert_eq!(percent(5, 0), None);
+        // No overflow for byte counts near u64::MAX.
+        assert_eq!(percent(u64::MAX / 2, u64::MAX), Some(49));
+    }
+
+    #[test]
+    fn test_bar_class_thresholds() {
+        assert!(bar_class(59).contains("emerald"));
+        assert!(bar_class(60).contains("yellow"));
+        assert!(bar_class(80).contains("red"));
+    }
+
+    #[test]
+    fn test_age() {
+        let now = Utc::now();
+        assert_eq!(age_at(now - Duration::seconds(45), now), "45s");
+        assert_eq!(age_at(now - Duration::minutes(12), now), "12m");
+        assert_eq!(age_at(now - Duration::hours(3), now), "3h");
+        assert_eq!(age_at(now - Duration::days(5), now), "5d");
+        // Clock skew puts some timestamps slightly ahead.
+        assert_eq!(age_at(now + Duration::seconds(5), now), "0s");
+    }
+}
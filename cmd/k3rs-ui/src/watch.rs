@@ -0,0 +1,502 @@
+//! Live data: the pods and events of the selected namespace, listed once and
+//! then kept current from the API server's watch stream. When the stream
+//! drops, it is resumed from the last applied seq; while it cannot be, the
+//! lists are polled instead.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::time::Duration;
+
+use dioxus::prelude::*;
+use serde::de::DeserializeOwned;
+
+use crate::api::{self, ApiError, ApiSettings};
+use crate::toast::Toasts;
+use crate::{ObjectEvent, Pod, Snapshot, WatchEvent};
+
+/// Wait before resuming a watch stream that ended.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Time between list requests while the watch stream is down.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Events kept for the Events page; older ones are dropped.
+const MAX_EVENTS: usize = 500;
+
+/// State kept current by watch events.
+pub trait Synced: Sized + 'static {
+    type Item;
+
+    /// Watched key prefix for namespace `ns`.
+    fn prefix(ns: &str) -> String;
+    fn from_list(snapshot: Snapshot<Self::Item>) -> Self;
+    /// Apply `event`, unless it is at or before [`Synced::seq`].
+    fn apply(&mut self, event: &WatchEvent);
+    /// Seq of the last event applied, or of the list.
+    fn seq(&self) -> u64;
+}
+
+/// An object stored under `/registry/{KIND}/{namespace}/{name}`.
+pub trait Stored: Clone + DeserializeOwned + 'static {
+    const KIND: &'static str;
+
+    fn namespace(&self) -> &str;
+    fn name(&self) -> &str;
+}
+
+impl Stored for Pod {
+    const KIND: &'static str = "pods";
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Objects keyed by namespace and name, in that order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectCache<T> {
+    objects: BTreeMap<(String, String), T>,
+    seq: u64,
+}
+
+impl<T: Stored> ObjectCache<T> {
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.objects.values()
+    }
+
+    pub fn get(&self, namespace: &str, name: &str) -> Option<&T> {
+        self.objects.get(&(namespace.to_string(), name.to_string()))
+    }
+}
+
+impl<T: Stored> Synced for ObjectCache<T> {
+    type Item = T;
+
+    fn prefix(ns: &str) -> String {
+        format!("/registry/{}/{}/", T::KIND, ns)
+    }
+
+    fn from_list(snapshot: Snapshot<T>) -> Self {
+        let objects = snapshot
+            .items
+            .into_iter()
+            .map(|o| ((o.namespace().to_string(), o.name().to_string()), o))
+            .collect();
+        Self {
+            objects,
+            seq: snapshot.seq,
+        }
+    }
+
+    fn apply(&mut self, event: &WatchEvent) {
+        if event.seq <= self.seq {
+            return;
+        }
+        self.seq = event.seq;
+        let Some((ns, name)) = event
+            .key
+            .strip_prefix(&format!("/registry/{}/", T::KIND))
+            .and_then(|rest| rest.split_once('/'))
+        else {
+            return;
+        };
+        let key = (ns.to_string(), name.to_string());
+        match event.event_type.as_str() {
+            "Put" => {
+                // A value this UI cannot read leaves the old one in place.
+                let object = event
+                    .value
+                    .as_deref()
+                    .and_then(|v| serde_json::from_slice(v).ok());
+                if let Some(object) = object {
+                    self.objects.insert(key, object);
+                }
+            }
+            "Delete" => {
+                self.objects.remove(&key);
+            }
+            _ => {}
+        }
+    }
+
+    fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+/// Object events, oldest first, at most [`MAX_EVENTS`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EventFeed {
+    events: VecDeque<ObjectEvent>,
+    seq: u64,
+}
+
+impl EventFeed {
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Newest first.
+    pub fn newest(&self) -> impl Iterator<Item = &ObjectEvent> {
+        self.events.iter().rev()
+    }
+
+    fn push(&mut self, event: ObjectEvent) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
+
+impl Synced for EventFeed {
+    type Item = ObjectEvent;
+
+    fn prefix(ns: &str) -> String {
+        format!("/events/{}/", ns)
+    }
+
+    fn from_list(snapshot: Snapshot<ObjectEvent>) -> Self {
+        let mut feed = Self {
+            events: VecDeque::new(),
+            seq: snapshot.seq,
+        };
+        for event in snapshot.items {
+            feed.push(event);
+        }
+        feed
+    }
+
+    fn apply(&mut self, event: &WatchEvent) {
+        if event.seq <= self.seq {
+            return;
+        }
+        self.seq = event.seq;
+        let Some(event) = event
+            .value
+            .as_deref()
+            .and_then(|v| serde_json::from_slice::<ObjectEvent>(v).ok())
+        else {
+            return;
+        };
+        // The list may already hold the first events after its seq.
+        if !self.events.contains(&event) {
+            self.push(event);
+        }
+    }
+
+    fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+/// State of one watch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum WatchStatus {
+    Live,
+    Connecting,
+    /// The stream is down; the list is fetched every [`POLL_INTERVAL`].
+    Polling,
+}
+
+/// The live data of the selected namespace. The layout provides it; pages
+/// get it with `use_context::<Live>()`. `None` until the first list.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Live {
+    pub pods: Signal<Option<ObjectCache<Pod>>>,
+    pub events: Signal<Option<EventFeed>>,
+    pods_status: Signal<WatchStatus>,
+    events_status: Signal<WatchStatus>,
+}
+
+impl Live {
+    /// The worst of the watches' states.
+    pub fn status(&self) -> WatchStatus {
+        (*self.pods_status.read()).max(*self.events_status.read())
+    }
+}
+
+/// Start the watches for the namespace in `ns`, restarting them when it or
+/// the settings change, and provide their [`Live`] data. Nothing starts
+/// before `ready`; list failures are shown in `toasts`.
+pub fn use_live(
+    ns: Signal<String>,
+    settings: Signal<ApiSettings>,
+    ready: Signal<bool>,
+    toasts: Toasts,
+) -> Live {
+    let live = use_context_provider(|| Live {
+        pods: Signal::new(None),
+        events: Signal::new(None),
+        pods_status: Signal::new(WatchStatus::Connecting),
+        events_status: Signal::new(WatchStatus::Connecting),
+    });
+    use_resource(move || {
+        let (ns, settings, ready) = (ns(), settings(), ready());
+        async move {
+            if ready {
+                sync(
+                    ns,
+                    settings,
+                    api::list_pods,
+                    live.pods,
+                    live.pods_status,
+                    toasts,
+                )
+                .await;
+            }
+        }
+    });
+    use_resource(move || {
+        let (ns, settings, ready) = (ns(), settings(), ready());
+        async move {
+            if ready {
+                sync(
+                    ns,
+                    settings,
+                    api::list_events,
+                    live.events,
+                    live.events_status,
+                    toasts,
+                )
+                .await;
+            }
+        }
+    });
+    live
+}
+
+/// Keep `data` current for namespace `ns`: list with `list`, then apply
+/// the watch stream. Runs until the resource driving it is restarted.
+async fn sync<S, F, Fut>(
+    ns: String,
+    settings: ApiSettings,
+    list: F,
+    mut data: Signal<Option<S>>,
+    mut status: Signal<WatchStatus>,
+    toasts: Toasts,
+) where
+    S: Synced,
+    F: Fn(ApiSettings, String) -> Fut,
+    Fut: Future<Output = Result<Snapshot<S::Item>, ApiError>>,
+{
+    data.set(None);
+    status.set(WatchStatus::Connecting);
+    let mut relist = true;
+    loop {
+        if relist {
+            match list(settings.clone(), ns.clone()).await {
+                Ok(snapshot) => data.set(Some(S::from_list(snapshot))),
+                Err(err) => {
+                    toasts.push(err);
+                    status.set(WatchStatus::Polling);
+                    sleep(POLL_INTERVAL).await;
+                    continue;
+                }
+            }
+        }
+        let seq = data.peek().as_ref().map_or(0, S::seq);
+        match api::watch(settings.clone(), S::prefix(&ns), seq).await {
+            Ok(mut events) => {
+                status.set(WatchStatus::Live);
+                while let Some(Ok(event)) = events.recv().await {
+                    if let Some(data) = data.write().as_mut() {
+                        data.apply(&event);
+                    }
+                }
+                // Resume from the last event applied.
+                status.set(WatchStatus::Connecting);
+                relist = false;
+                sleep(RECONNECT_DELAY).await;
+            }
+            Err(ApiError::Rejected { status: 410, .. }) => {
+                // Events after `seq` were dropped; only a new list has them.
+                relist = true;
+                sleep(RECONNECT_DELAY).await;
+            }
+            Err(_) => {
+                status.set(WatchStatus::Polling);
+                relist = true;
+                sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Wait `dur`, on the UI server or in the browser.
+pub async fn sleep(dur: Duration) {
+    #[cfg(feature = "server")]
+    tokio::time::sleep(dur).await;
+    #[cfg(all(feature = "web", not(feature = "server")))]
+    gloo_timers::future::sleep(dur).await;
+}
+
+/// The watches' state, for the sidebar.
+#[component]
+pub fn LiveStatus() -> Element {
+    let live = use_context::<Live>();
+    let (dot, label) = match live.status() {
+        WatchStatus::Live => ("bg-emerald-400 shadow-emerald-400/50", "Live"),
+        WatchStatus::Connecting => ("bg-slate-500 shadow-slate-500/50", "Connecting"),
+        WatchStatus::Polling => ("bg-amber-400 shadow-amber-400/50", "Polling"),
+    };
+
+    rsx! {
+        div {
+            class: "flex items-center gap-1.5 text-[10px] font-medium text-slate-500",
+            title: "Watch stream for pods and events",
+            div { class: "w-1.5 h-1.5 rounded-full shadow-sm {dot}" }
+            "{label}"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pod(name: &str, status: &str) -> Pod {
+        Pod {
+            id: name.to_string(),
+            name: name.to_string(),
+            namespace: "default".to_string(),
+            status: status.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn put(seq: u64, pod: &Pod) -> WatchEvent {
+        WatchEvent {
+            seq,
+            event_type: "Put".to_string(),
+            key: format!("/registry/pods/{}/{}", pod.namespace, pod.name),
+            value: Some(serde_json::to_vec(pod).unwrap()),
+        }
+    }
+
+    fn delete(seq: u64, name: &str) -> WatchEvent {
+        WatchEvent {
+            seq,
+            event_type: "Delete".to_string(),
+            key: format!("/registry/pods/default/{}", name),
+            value: None,
+        }
+    }
+
+    fn statuses(cache: &ObjectCache<Pod>) -> Vec<(String, String)> {
+        cache
+            .iter()
+            .map(|p| (p.name.clone(), p.status.clone()))
+            .collect()
+    }
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_pod_cache_add_update_delete() {
+        let mut cache = ObjectCache::from_list(Snapshot {
+            items: vec![pod("web-1", "Running")],
+            seq: 10,
+        });
+        cache.apply(&put(11, &pod("web-0", "Pending")));
+        cache.apply(&put(12, &pod("web-0", "Running")));
+        assert_eq!(
+            statuses(&cache),
+            pairs(&[("web-0", "Running"), ("web-1", "Running")])
+        );
+
+        cache.apply(&delete(13, "web-1"));
+        assert_eq!(statuses(&cache), pairs(&[("web-0", "Running")]));
+        assert_eq!(cache.seq(), 13);
+
+        // Deleting what is not there, or a key of another kind, only moves the seq.
+        cache.apply(&delete(14, "web-9"));
+        let mut other = put(15, &pod("x", "Running"));
+        other.key = "/registry/services/default/x".to_string();
+        cache.apply(&other);
+        assert_eq!(statuses(&cache), pairs(&[("web-0", "Running")]));
+        assert_eq!(cache.seq(), 15);
+    }
+
+    #[test]
+    fn test_pod_cache_skips_events_already_applied() {
+        let mut cache = ObjectCache::from_list(Snapshot {
+            items: vec![pod("web-0", "Running")],
+            seq: 20,
+        });
+        // Replayed from before the list, or out of order after a resume.
+        cache.apply(&put(19, &pod("web-0", "Pending")));
+        cache.apply(&put(22, &pod("web-0", "Failed")));
+        cache.apply(&delete(21, "web-0"));
+        assert_eq!(statuses(&cache), pairs(&[("web-0", "Failed")]));
+        assert_eq!(cache.seq(), 22);
+
+        // A value that does not decode keeps the object as it was.
+        let mut bad = put(23, &pod("web-0", "Running"));
+        bad.value = Some(b"not json".to_vec());
+        cache.apply(&bad);
+        assert_eq!(statuses(&cache), pairs(&[("web-0", "Failed")]));
+        assert_eq!(ObjectCache::<Pod>::prefix("prod"), "/registry/pods/prod/");
+    }
+
+    fn event(reason: &str) -> ObjectEvent {
+        ObjectEvent {
+            kind: "pod".to_string(),
+            name: "web-0".to_string(),
+            namespace: "default".to_string(),
+            event_type: "Normal".to_string(),
+            reason: reason.to_string(),
+            message: String::new(),
+            timestamp: format!("2026-01-01T00:00:00Z#{}", reason),
+        }
+    }
+
+    fn recorded(seq: u64, event: &ObjectEvent) -> WatchEvent {
+        WatchEvent {
+            seq,
+            event_type: "Put".to_string(),
+            key: "/events/default/pod/web-0".to_string(),
+            value: Some(serde_json::to_vec(event).unwrap()),
+        }
+    }
+
+    fn reasons(feed: &EventFeed) -> Vec<&str> {
+        feed.newest().map(|e| e.reason.as_str()).collect()
+    }
+
+    #[test]
+    fn test_event_feed_appends_in_order() {
+        let mut feed = EventFeed::from_list(Snapshot {
+            items: vec![event("Scheduled"), event("Pulling")],
+            seq: 5,
+        });
+        // The list already had this one; seq 4 was before it.
+        feed.apply(&recorded(6, &event("Pulling")));
+        feed.apply(&recorded(4, &event("Old")));
+        feed.apply(&recorded(7, &event("Started")));
+        assert_eq!(reasons(&feed), ["Started", "Pulling", "Scheduled"]);
+        assert_eq!(feed.seq(), 7);
+        assert_eq!(EventFeed::prefix("prod"), "/events/prod/");
+    }
+
+    #[test]
+    fn test_event_feed_is_capped() {
+        let mut feed = EventFeed::default();
+        for seq in 1..=(MAX_EVENTS as u64 + 2) {
+            feed.apply(&recorded(seq, &event(&seq.to_string())));
+        }
+        assert_eq!(feed.newest().count(), MAX_EVENTS);
+        assert_eq!(feed.newest().last().unwrap().reason, "3");
+    }
+}
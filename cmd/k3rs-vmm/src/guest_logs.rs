@@ -0,0 +1,74 @@
+//! Container output forwarded by k3rs-init over vsock.
+//!
+//! Connects to k3rs-init's log port once the VM is up and appends the lines
+//! it forwards to the container's log file (see `pkg_types::guest_log`). The
+//! kernel console keeps going to the `--log` file.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use dispatch2::MainThreadBound;
+use objc2::rc::Retained;
+use objc2_virtualization::VZVirtualMachine;
+use pkg_constants::vm::VSOCK_LOG_PORT;
+use pkg_types::guest_log::{FrameDecoder, format_frame};
+use tracing::{info, warn};
+
+use crate::vsock::connect_vsock;
+
+/// Delay between attempts to reach the guest's log port.
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// Relay the guest's forwarded output into `path` for as long as the VM
+/// runs, reconnecting whenever k3rs-init is not (yet) listening.
+///
+/// Runs on its own thread; `connect_vsock` must not be called from main.
+pub fn start_relay(vm: Arc<MainThreadBound<Retained<VZVirtualMachine>>>, path: String) {
+    let booted_at = Utc::now();
+    std::thread::spawn(move || {
+        loop {
+            // Refused until k3rs-init has started the entrypoint.
+            if let Ok(fd) = connect_vsock(&vm, VSOCK_LOG_PORT) {
+                info!("relaying guest output to {}", path);
+                if let Err(e) = relay(fd, &path, booted_at) {
+                    warn!("guest log relay stopped: {}", e);
+                }
+                unsafe { libc::close(fd) };
+            }
+            std::thread::sleep(RECONNECT_DELAY);
+        }
+    });
+}
+
+/// Copy frames from `fd` into `path` until the guest closes the connection.
+fn relay(fd: i32, path: &str, booted_at: DateTime<Utc>) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("open {}: {}", path, e))?;
+    let mut decoder = FrameDecoder::default();
+    let mut buf = [0u8; 16 * 1024];
+    loop {
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n == 0 {
+            return Ok(());
+        }
+        if n < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(format!("vsock read: {}", e));
+        }
+        decoder.feed(&buf[..n as usize]);
+        while let Some(frame) = decoder.next_frame()? {
+            let line = format_frame(&frame, booted_at, Utc::now());
+            file.write_all(line.as_bytes())
+                .map_err(|e| format!("write {}: {}", path, e))?;
+        }
+    }
+}
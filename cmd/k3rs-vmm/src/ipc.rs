@@ -4,6 +4,11 @@
 //! The `exec` subcommand connects to that socket, sends the command, and reads
 //! the response.
 //!
+//! Each connection is served on its own thread with its own guest connection,
+//! so one-shot and streaming execs run side by side. At most
+//! `MAX_EXEC_SESSIONS` execs are open per VM; beyond that a client gets
+//! `exec error: ...` and is closed.
+//!
 //! ## Protocols
 //!
 //! ### Regular exec (one-shot)
@@ -13,22 +18,51 @@
 //! ### Streaming exec (interactive / tty=true)
 //! 1. Client: `\x01cmd\0arg1\0arg2\n` — the `\x01` prefix signals streaming mode
 //! 2. Client keeps socket open; data after the command line is stdin for the guest
-//! 3. Server: reads command (until `\n`), calls stream_handler(parts, socket)
+//! 3. Server: reads command (until `\n`), calls stream_handler(parts, tty, socket)
 //! 4. stream_handler relays the open socket ↔ vsock bidirectionally until done
+//!
+//! ### Exec with stdin (`exec --stdin`)
+//! 1. Client: `\x03cmd\0arg1\0arg2\n`, then stdin as `length (4, BE) | bytes`
+//!    frames and a zero-length frame at EOF
+//! 2. Served like streaming mode, without a PTY in the guest; the client
+//!    never shuts down its side, since the EOF frame already says it
+//!
+//! ### Stats
+//! 1. Client: `\x02` then `shutdown(Write)`
+//! 2. Server: writes the VM's [`VmStats`] as one JSON line, closes
 
 use std::io::{Read, Write};
 use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
 use std::path::Path;
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::{io, thread};
 
 use tracing::{error, info};
 
 use pkg_constants::paths::DATA_DIR;
 
+use crate::pidfile;
+use crate::stats::VmStats;
+use crate::trailer;
+
 /// Byte prefix that distinguishes streaming exec from regular one-shot exec.
 const STREAM_PREFIX: u8 = pkg_constants::vm::VSOCK_STREAM_PREFIX;
 
+/// Byte prefix of an exec that passes stdin to a command without a PTY.
+const STDIN_PREFIX: u8 = pkg_constants::vm::VSOCK_STDIN_PREFIX;
+
+/// Byte that asks the boot process for its VM's stats instead of an exec.
+const STATS_REQUEST: u8 = 0x02;
+
+/// Read timeout for a stats request; the boot process answers without
+/// touching the guest.
+const STATS_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Most exec sessions, one-shot and streaming together, a VM serves at once.
+const MAX_EXEC_SESSIONS: usize = 16;
+
 /// Read timeout for one-shot exec (waiting for the guest command to finish).
 const EXEC_TIMEOUT: std::time::Duration =
     std::time::Duration::from_secs(pkg_constants::timings::VM_EXEC_TIMEOUT_SECS);
@@ -41,17 +75,19 @@ pub fn set_active_vm(id: &str) {
     let _ = ACTIVE_VM_ID.set(id.to_string());
 }
 
-/// Clean up IPC socket for the active VM. Safe to call from any exit path.
+/// Clean up IPC socket and pidfile for the active VM. Safe to call from any
+/// exit path.
 pub fn cleanup() {
     if let Some(id) = ACTIVE_VM_ID.get() {
         let path = socket_path(id);
         let _ = std::fs::remove_file(&path);
+        pidfile::remove(id);
         info!("cleaned up IPC socket: {}", path);
     }
 }
 
-/// Get the socket directory for VMM sockets.
-fn vmm_socket_dir() -> String {
+/// Get the socket directory for VMM sockets (and pidfiles).
+pub fn vmm_socket_dir() -> String {
     format!("{}/runtime/vms", DATA_DIR)
 }
 
@@ -60,23 +96,60 @@ pub fn socket_path(id: &str) -> String {
     format!("{}/vmm-{}.sock", vmm_socket_dir(), id)
 }
 
+/// IDs of all VMs with an IPC socket, sorted. Some may be stale; see [`live_pid`].
+pub fn socket_ids() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(vmm_socket_dir()) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.strip_prefix("vmm-")?
+                .strip_suffix(".sock")
+                .map(str::to_string)
+        })
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// PID of the boot process serving `id`. A socket left behind by a boot
+/// process that is gone is removed, so the VM no longer shows up.
+pub fn live_pid(id: &str) -> Option<u32> {
+    let pid = pidfile::find(id);
+    if pid.is_none() {
+        let path = socket_path(id);
+        if std::fs::remove_file(&path).is_ok() {
+            info!("removed stale IPC socket: {}", path);
+        }
+    }
+    pid
+}
+
+pub type ExecHandler = Box<dyn Fn(&[String]) -> String + Send + Sync>;
+pub type StreamHandler = Box<dyn Fn(&[String], bool, UnixStream) + Send + Sync>;
+
+/// What the boot process does with each kind of request.
+pub struct Handlers {
+    /// Regular one-shot command; returns its output.
+    pub exec: ExecHandler,
+    /// Streaming command, on a PTY when `tty` is set and with framed stdin
+    /// otherwise; receives the open `UnixStream` and relays it until the
+    /// session ends.
+    pub stream: StreamHandler,
+    /// Stats request.
+    pub stats: Box<dyn Fn() -> VmStats + Send + Sync>,
+}
+
 /// Start an IPC listener for exec requests on the given VM.
-///
-/// Two handler closures are accepted:
-/// - `exec_handler`:   called for regular one-shot commands; returns output string.
-/// - `stream_handler`: called for streaming/tty commands; receives the open
-///   `UnixStream` and is responsible for bidirectional relay.
-pub fn start_listener(
-    id: &str,
-    exec_handler: impl Fn(&[String]) -> String + Send + Sync + 'static,
-    stream_handler: impl Fn(&[String], std::os::unix::net::UnixStream) + Send + Sync + 'static,
-) {
+pub fn start_listener(id: &str, handlers: Handlers) {
     let path = socket_path(id);
 
     let _ = std::fs::create_dir_all(vmm_socket_dir());
     let _ = std::fs::remove_file(&path);
 
-    let listener = match std::os::unix::net::UnixListener::bind(&path) {
+    let listener = match UnixListener::bind(&path) {
         Ok(l) => l,
         Err(e) => {
             error!("failed to bind IPC socket at {}: {}", path, e);
@@ -87,44 +160,104 @@ pub fn start_listener(
     info!("IPC listener started on {}", path);
 
     let id = id.to_string();
+    thread::spawn(move || serve(listener, &id, handlers, MAX_EXEC_SESSIONS));
+}
 
-    use std::sync::Arc;
-    let exec_handler = Arc::new(exec_handler);
-    let stream_handler = Arc::new(stream_handler);
-
-    thread::spawn(move || {
-        for stream in listener.incoming() {
-            match stream {
-                Ok(stream) => {
-                    let id = id.clone();
-                    let exec_handler = Arc::clone(&exec_handler);
-                    let stream_handler = Arc::clone(&stream_handler);
-
-                    thread::spawn(move || {
-                        handle_connection(stream, &id, &*exec_handler, &*stream_handler);
-                    });
-                }
-                Err(e) => {
-                    error!("IPC accept error: {}", e);
-                }
+/// Accept connections until the listener fails, each on its own thread.
+fn serve(listener: UnixListener, id: &str, handlers: Handlers, max_sessions: usize) {
+    let handlers = Arc::new(handlers);
+    let slots = Arc::new(SessionSlots::new(max_sessions));
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let id = id.to_string();
+                let handlers = Arc::clone(&handlers);
+                let slots = Arc::clone(&slots);
+                thread::spawn(move || handle_connection(stream, &id, &handlers, &slots));
+            }
+            Err(e) => {
+                error!("IPC accept error: {}", e);
             }
         }
-    });
+    }
+}
+
+/// Open exec sessions of one VM, against its limit.
+struct SessionSlots {
+    open: AtomicUsize,
+    max: usize,
+}
+
+impl SessionSlots {
+    fn new(max: usize) -> Self {
+        Self {
+            open: AtomicUsize::new(0),
+            max,
+        }
+    }
+
+    /// A slot held until the returned guard drops, or `None` when all are taken.
+    fn acquire(self: &Arc<Self>) -> Option<SessionSlot> {
+        self.open
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < self.max).then_some(n + 1)
+            })
+            .ok()?;
+        Some(SessionSlot(Arc::clone(self)))
+    }
+}
+
+struct SessionSlot(Arc<SessionSlots>);
+
+impl Drop for SessionSlot {
+    fn drop(&mut self) {
+        self.0.open.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// A session slot held until the handler returns, or `None` after telling
+/// the client the VM is at its limit. Only called once the whole request has
+/// been read: closing on unread bytes would reset the connection instead.
+fn acquire_or_refuse(
+    slots: &Arc<SessionSlots>,
+    stream: &mut UnixStream,
+    id: &str,
+) -> Option<SessionSlot> {
+    let slot = slots.acquire();
+    if slot.is_none() {
+        error!(
+            "VM {} refused exec: {} sessions already open",
+            id, slots.max
+        );
+        let msg = format!(
+            "exec error: VM '{}' already has {} exec sessions open\n",
+            id, slots.max
+        );
+        let _ = stream.write_all(msg.as_bytes());
+    }
+    slot
 }
 
 /// Handle a single IPC connection.
 fn handle_connection(
-    mut stream: std::os::unix::net::UnixStream,
+    mut stream: UnixStream,
     id: &str,
-    exec_handler: &dyn Fn(&[String]) -> String,
-    stream_handler: &dyn Fn(&[String], std::os::unix::net::UnixStream),
+    handlers: &Handlers,
+    slots: &Arc<SessionSlots>,
 ) {
     let mut first = [0u8; 1];
     if stream.read_exact(&mut first).is_err() {
         return;
     }
 
-    if first[0] == STREAM_PREFIX {
+    if first[0] == STATS_REQUEST {
+        let mut line = serde_json::to_string(&(handlers.stats)()).unwrap_or_default();
+        line.push('\n');
+        let _ = stream.write_all(line.as_bytes());
+        return;
+    }
+
+    if first[0] == STREAM_PREFIX || first[0] == STDIN_PREFIX {
         // ── Streaming mode ─────────────────────────────────────────────────────
         let mut cmd_buf = Vec::new();
         let mut byte = [0u8; 1];
@@ -154,8 +287,15 @@ fn handle_connection(
             return;
         }
 
-        info!("IPC streaming exec for VM {}: {:?}", id, parts);
-        stream_handler(&parts, stream);
+        let Some(_slot) = acquire_or_refuse(slots, &mut stream, id) else {
+            return;
+        };
+        let tty = first[0] == STREAM_PREFIX;
+        info!(
+            "IPC streaming exec for VM {} (tty={}): {:?}",
+            id, tty, parts
+        );
+        (handlers.stream)(&parts, tty, stream);
     } else {
         // ── Regular one-shot mode ───────────────────────────────────────────────
         let mut rest = Vec::new();
@@ -183,16 +323,20 @@ fn handle_connection(
         }
 
         let parts: Vec<String> = cmd_line.split('\0').map(|s| s.to_string()).collect();
+        let Some(_slot) = acquire_or_refuse(slots, &mut stream, id) else {
+            return;
+        };
         info!("IPC exec request for VM {}: {:?}", id, parts);
 
-        let output =
-            match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| exec_handler(&parts))) {
-                Ok(out) => out,
-                Err(_) => {
-                    error!("IPC exec handler panicked for VM {}", id);
-                    "exec error: handler panicked\n".to_string()
-                }
-            };
+        let output = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            (handlers.exec)(&parts)
+        })) {
+            Ok(out) => out,
+            Err(_) => {
+                error!("IPC exec handler panicked for VM {}", id);
+                "exec error: handler panicked\n".to_string()
+            }
+        };
         let _ = stream.write_all(output.as_bytes());
     }
 }
@@ -205,8 +349,11 @@ pub fn stop_listener(id: &str) {
 
 /// Connect to a running boot process's IPC socket and send a regular exec request.
 pub fn exec_via_ipc(id: &str, command: &[String]) -> io::Result<String> {
-    let mut stream = connect_to_ipc(id)?;
+    exec_on_stream(connect_to_ipc(id)?, id, command)
+}
 
+/// Send a one-shot exec request on an open IPC connection and read the output.
+fn exec_on_stream(mut stream: UnixStream, id: &str, command: &[String]) -> io::Result<String> {
     // One-shot exec: set a read timeout so a hung guest doesn't block forever.
     stream.set_read_timeout(Some(EXEC_TIMEOUT))?;
 
@@ -239,13 +386,36 @@ pub fn exec_via_ipc(id: &str, command: &[String]) -> io::Result<String> {
     Ok(response)
 }
 
+/// Ask a running boot process for its VM's stats.
+pub fn stats_via_ipc(id: &str) -> io::Result<VmStats> {
+    request_stats(connect_to_ipc(id)?)
+}
+
+/// Send a stats request on an open IPC connection and parse the reply.
+fn request_stats(mut stream: std::os::unix::net::UnixStream) -> io::Result<VmStats> {
+    stream.set_read_timeout(Some(STATS_TIMEOUT))?;
+    stream.write_all(&[STATS_REQUEST])?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    serde_json::from_str(response.trim()).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid stats reply: {}", e),
+        )
+    })
+}
+
 /// Connect to a running boot process's IPC socket and run a streaming PTY exec.
 ///
 /// - Sends `\x01` + NUL-delimited command + `\n` to select streaming mode
 /// - Puts the calling terminal into raw mode (so keystrokes are sent byte-by-byte)
 /// - Relays stdin → socket and socket → stdout until the guest closes the connection
 /// - Restores terminal state on exit
-pub fn exec_streaming_via_ipc(id: &str, command: &[String]) -> io::Result<()> {
+///
+/// Returns the command's exit code, if the guest reported one.
+pub fn exec_streaming_via_ipc(id: &str, command: &[String]) -> io::Result<Option<i32>> {
     // NOTE: no read timeout for streaming — the session can be idle indefinitely.
     let mut stream = connect_to_ipc(id)?;
 
@@ -279,35 +449,28 @@ pub fn exec_streaming_via_ipc(id: &str, command: &[String]) -> io::Result<()> {
     let done_read = done_pipe[0];
     let done_write = done_pipe[1];
 
-    // Thread A: socket → stdout  (guest output → host terminal)
+    // Thread A: socket → stdout  (guest output → host terminal), minus the
+    // exit status trailer, which it returns.
     let t_out = thread::spawn(move || {
         let mut buf = [0u8; 4096];
-        let stdout_fd = libc::STDOUT_FILENO;
+        let mut filter = trailer::Filter::default();
         loop {
             let n =
                 unsafe { libc::read(stream_fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
             if n <= 0 {
                 break;
             }
-            let mut off = 0usize;
-            while off < n as usize {
-                let w = unsafe {
-                    libc::write(
-                        stdout_fd,
-                        buf[off..n as usize].as_ptr() as *const libc::c_void,
-                        n as usize - off,
-                    )
-                };
-                if w <= 0 {
-                    break;
-                }
-                off += w as usize;
+            if !write_stdout(&filter.push(&buf[..n as usize])) {
+                break;
             }
         }
+        let (rest, code) = filter.finish();
+        write_stdout(&rest);
         // Signal t_in that the session is over by closing the write end of the pipe.
         // t_in's poll() will see POLLHUP on done_read and exit its loop.
         unsafe { libc::close(done_write) };
         unsafe { libc::close(stream_fd) };
+        code
     });
 
     // Thread B: stdin → socket  (host terminal input → guest)
@@ -373,13 +536,99 @@ pub fn exec_streaming_via_ipc(id: &str, command: &[String]) -> io::Result<()> {
         unsafe { libc::close(stream_dup) };
     });
 
-    t_out.join().ok();
+    let code = t_out.join().ok().flatten();
     t_in.join().ok();
 
     // Restore terminal before returning.
     restore_terminal(saved_term);
 
-    Ok(())
+    Ok(code)
+}
+
+/// Connect to a running boot process's IPC socket and run `command` with
+/// this process's stdin, printing its output as it comes.
+///
+/// Returns the command's exit code, if the guest reported one.
+pub fn exec_stdin_via_ipc(id: &str, command: &[String]) -> io::Result<Option<i32>> {
+    exec_stdin_on_stream(connect_to_ipc(id)?, command, io::stdin(), &mut io::stdout())
+}
+
+/// Run `command` on an open IPC connection in stdin mode: `input` goes to
+/// the guest in frames until its EOF, and the output, minus the exit
+/// status trailer, to `output`.
+fn exec_stdin_on_stream(
+    mut stream: UnixStream,
+    command: &[String],
+    mut input: impl Read + Send + 'static,
+    output: &mut impl Write,
+) -> io::Result<Option<i32>> {
+    // NOTE: no read timeout — the command may wait on its input for as long
+    // as the input takes.
+    let mut header = vec![STDIN_PREFIX];
+    header.extend_from_slice(command.join("\0").as_bytes());
+    header.push(b'\n');
+    stream.write_all(&header)?;
+
+    // Input → guest. Not joined: a command that exits without reading all of
+    // its input leaves this thread blocked on a read nobody needs.
+    let mut to_guest = stream.try_clone()?;
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = match input.read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => 0,
+            };
+            // A zero-length frame is the EOF.
+            if to_guest.write_all(&stdin_frame(&buf[..n])).is_err() || n == 0 {
+                break;
+            }
+        }
+    });
+
+    // Guest output → `output`, until the guest closes the connection.
+    let mut filter = trailer::Filter::default();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.write_all(&filter.push(&buf[..n]))?;
+        output.flush()?;
+    }
+    let (rest, code) = filter.finish();
+    output.write_all(&rest)?;
+    output.flush()?;
+    Ok(code)
+}
+
+/// `bytes` as one stdin frame: `length (4, BE) | bytes`.
+fn stdin_frame(bytes: &[u8]) -> Vec<u8> {
+    let mut frame = (bytes.len() as u32).to_be_bytes().to_vec();
+    frame.extend_from_slice(bytes);
+    frame
+}
+
+/// Write all of `data` to stdout; `false` once stdout is gone.
+fn write_stdout(mut data: &[u8]) -> bool {
+    while !data.is_empty() {
+        let w = unsafe {
+            libc::write(
+                libc::STDOUT_FILENO,
+                data.as_ptr() as *const libc::c_void,
+                data.len(),
+            )
+        };
+        if w <= 0 {
+            return false;
+        }
+        data = &data[w as usize..];
+    }
+    true
 }
 
 // ─── Terminal raw mode ────────────────────────────────────────────────────────
@@ -432,8 +681,7 @@ fn connect_to_ipc(id: &str) -> io::Result<std::os::unix::net::UnixStream> {
         ));
     }
 
-    if !is_vm_process_alive(id) {
-        let _ = std::fs::remove_file(&path);
+    if live_pid(id).is_none() {
         return Err(io::Error::new(
             io::ErrorKind::NotFound,
             format!(
@@ -453,17 +701,239 @@ fn connect_to_ipc(id: &str) -> io::Result<std::os::unix::net::UnixStream> {
     Ok(stream)
 }
 
-/// Check if the k3rs-vmm boot process for a given VM ID is alive.
-fn is_vm_process_alive(id: &str) -> bool {
-    let output = std::process::Command::new("pgrep")
-        .args(["-f", &format!("k3rs-vmm boot.*--id {}", id)])
-        .output();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session;
+    use pkg_container::test_util::temp_dir;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// Stands in for k3rs-init on `dir/guest.sock`: a one-shot command
+    /// prints 200 `<arg1>:<n>` lines in as many writes and exits with 7
+    /// (`exit` exits with 0); a streaming session
+    /// echoes its input and reports on the channel when the host hangs up;
+    /// a stdin session counts its input like `wc -c`.
+    fn fake_guest(dir: &Path) -> (PathBuf, mpsc::Receiver<()>) {
+        let path = dir.join("guest.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let (hangups, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for conn in listener.incoming() {
+                let mut conn = conn.unwrap();
+                let hangups = hangups.clone();
+                thread::spawn(move || {
+                    let mut line = Vec::new();
+                    let mut byte = [0u8; 1];
+                    while conn.read_exact(&mut byte).is_ok() && byte[0] != b'\n' {
+                        line.push(byte[0]);
+                    }
+                    if line.first() == Some(&STREAM_PREFIX) {
+                        let mut buf = [0u8; 256];
+                        while let Ok(n @ 1..) = conn.read(&mut buf) {
+                            conn.write_all(&buf[..n]).unwrap();
+                        }
+                        hangups.send(()).unwrap();
+                    } else if line.first() == Some(&STDIN_PREFIX) {
+                        let mut count = 0;
+                        loop {
+                            let mut len = [0u8; 4];
+                            conn.read_exact(&mut len).unwrap();
+                            let mut frame = vec![0u8; u32::from_be_bytes(len) as usize];
+                            if frame.is_empty() {
+                                break;
+                            }
+                            conn.read_exact(&mut frame).unwrap();
+                            count += frame.len();
+                        }
+                        conn.write_all(format!("{}\n\x02exit:0\n", count).as_bytes())
+                            .unwrap();
+                    } else {
+                        let line = String::from_utf8(line).unwrap();
+                        let arg = line.split('\0').nth(1).unwrap_or_default().to_string();
+                        for n in 0..200 {
+                            conn.write_all(format!("{}:{}\n", arg, n).as_bytes())
+                                .unwrap();
+                            thread::yield_now();
+                        }
+                        let code = if arg == "exit" { 0 } else { 7 };
+                        conn.write_all(format!("\x02exit:{}\n", code).as_bytes())
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        (path, rx)
+    }
 
-    match output {
-        Ok(o) if o.status.success() => {
-            let pid_str = String::from_utf8_lossy(&o.stdout).trim().to_string();
-            !pid_str.is_empty()
+    /// A boot process's IPC server on `dir/vmm.sock`, exec'ing on `guest`.
+    fn start_server(dir: &Path, guest: PathBuf, max_sessions: usize, stats: VmStats) -> PathBuf {
+        let path = dir.join("vmm.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+        let stream_guest = guest.clone();
+        let handlers = Handlers {
+            exec: Box::new(move |parts| {
+                let fd = UnixStream::connect(&guest).unwrap().into_raw_fd();
+                session::exec_oneshot(fd, &(parts.join("\0") + "\n"))
+            }),
+            stream: Box::new(move |parts, tty, client| {
+                let fd = UnixStream::connect(&stream_guest).unwrap().into_raw_fd();
+                let mut header = vec![if tty { STREAM_PREFIX } else { STDIN_PREFIX }];
+                header.extend_from_slice(parts.join("\0").as_bytes());
+                header.push(b'\n');
+                session::write_all(fd, &header).unwrap();
+                session::relay(fd, client);
+            }),
+            stats: Box::new(move || stats.clone()),
+        };
+        thread::spawn(move || serve(listener, "vm", handlers, max_sessions));
+        path
+    }
+
+    fn expected_output(arg: &str) -> String {
+        (0..200).map(|n| format!("{}:{}\n", arg, n)).collect()
+    }
+
+    /// Output and exit status of a one-shot exec, as `k3rs-vmm exec` sees it.
+    fn exec(ipc: &Path, arg: &str) -> (String, Option<i32>) {
+        let command = ["echo".to_string(), arg.to_string()];
+        let output = exec_on_stream(UnixStream::connect(ipc).unwrap(), "vm", &command).unwrap();
+        let (output, code) = trailer::split(output.as_bytes());
+        (String::from_utf8(output.to_vec()).unwrap(), code)
+    }
+
+    /// Opens a streaming session and checks it echoes.
+    fn open_stream(ipc: &Path) -> UnixStream {
+        let mut stream = UnixStream::connect(ipc).unwrap();
+        stream.write_all(b"\x01/bin/sh\n").unwrap();
+        stream.write_all(b"ping").unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ping");
+        stream
+    }
+
+    fn stats() -> VmStats {
+        VmStats {
+            pid: 7,
+            uptime_secs: 3,
+            cpu_usage_usec: 250_000,
+            rss_bytes: 1 << 20,
+            cpus: 1,
+            memory_mb: 128,
         }
-        _ => false,
+    }
+
+    #[test]
+    fn test_stats_round_trip() {
+        let dir = temp_dir("ipc-stats");
+        let (guest, _) = fake_guest(&dir);
+        let ipc = start_server(&dir, guest, MAX_EXEC_SESSIONS, stats());
+
+        let client = UnixStream::connect(&ipc).unwrap();
+        assert_eq!(request_stats(client).unwrap(), stats());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_concurrent_execs_do_not_interleave() {
+        let dir = temp_dir("ipc-concurrent");
+        let (guest, _) = fake_guest(&dir);
+        let ipc = start_server(&dir, guest, MAX_EXEC_SESSIONS, stats());
+
+        // A tty session stays open while the one-shots run.
+        let mut tty = open_stream(&ipc);
+        let clients: Vec<_> = (0..8)
+            .map(|i| {
+                let ipc = ipc.clone();
+                thread::spawn(move || {
+                    let arg = format!("session-{}", i);
+                    (exec(&ipc, &arg), arg)
+                })
+            })
+            .collect();
+        for client in clients {
+            let ((output, code), arg) = client.join().unwrap();
+            assert_eq!(output, expected_output(&arg));
+            assert_eq!(code, Some(7));
+        }
+
+        tty.write_all(b"pong").unwrap();
+        let mut buf = [0u8; 4];
+        tty.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_session_limit() {
+        let dir = temp_dir("ipc-limit");
+        let (guest, hangups) = fake_guest(&dir);
+        let ipc = start_server(&dir, guest, 2, stats());
+
+        let first = open_stream(&ipc);
+        let _second = open_stream(&ipc);
+        assert_eq!(
+            exec(&ipc, "exit"),
+            (
+                "exec error: VM 'vm' already has 2 exec sessions open\n".to_string(),
+                None
+            )
+        );
+        // Stats are not an exec session.
+        assert_eq!(
+            request_stats(UnixStream::connect(&ipc).unwrap()).unwrap(),
+            stats()
+        );
+
+        drop(first);
+        hangups.recv_timeout(Duration::from_secs(5)).unwrap();
+        // The slot frees once the relay has closed both ends.
+        let mut result = exec(&ipc, "exit");
+        for _ in 0..50 {
+            if result.1.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+            result = exec(&ipc, "exit");
+        }
+        assert_eq!(result, (expected_output("exit"), Some(0)));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_client_crash_ends_guest_session() {
+        let dir = temp_dir("ipc-crash");
+        let (guest, hangups) = fake_guest(&dir);
+        let ipc = start_server(&dir, guest, MAX_EXEC_SESSIONS, stats());
+
+        let tty = open_stream(&ipc);
+        assert!(hangups.try_recv().is_err());
+        // The client goes away without a word: the guest sees the hangup.
+        drop(tty);
+        hangups.recv_timeout(Duration::from_secs(5)).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stdin_session_sees_eof() {
+        let dir = temp_dir("ipc-stdin");
+        let (guest, _) = fake_guest(&dir);
+        let ipc = start_server(&dir, guest, MAX_EXEC_SESSIONS, stats());
+
+        let command = ["wc".to_string(), "-c".to_string()];
+        let input = io::Cursor::new(vec![b'x'; 100_000]);
+        let mut output = Vec::new();
+        let code = exec_stdin_on_stream(
+            UnixStream::connect(&ipc).unwrap(),
+            &command,
+            input,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "100000\n");
+        assert_eq!(code, Some(0));
+        let _ = std::fs::remove_dir_all(&dir);
     }
 }
@@ -3,6 +3,7 @@
 //! Wraps Apple's Virtualization.framework to boot lightweight Linux microVMs
 //! for container pod isolation on macOS. Rewritten in Rust using objc2-virtualization.
 
+use std::io::Write;
 use std::path::Path;
 use std::process;
 use std::sync::Arc;
@@ -16,14 +17,19 @@ use objc2_foundation::MainThreadMarker;
 use objc2_virtualization::VZVirtualMachineDelegate;
 use signal_hook::consts::signal::{SIGINT, SIGQUIT, SIGTERM};
 use signal_hook::iterator::Signals;
-use tracing::info;
 use tracing::level_filters::LevelFilter;
+use tracing::{info, warn};
 use tracing_subscriber::Layer;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
+mod guest_logs;
 mod ipc;
 mod linux_vm;
+mod pidfile;
+mod session;
+mod stats;
+mod trailer;
 mod vm;
 mod vm_delegate;
 mod vsock;
@@ -80,7 +86,7 @@ enum Command {
     State(StateArgs),
     /// List running microVMs
     #[command(name = "ls")]
-    List,
+    List(ListArgs),
     /// Remove (kill) a running microVM
     #[command(name = "rm")]
     Rm(RmArgs),
@@ -106,11 +112,15 @@ struct BootArgs {
     #[arg(long, default_value_t = 128)]
     memory: u64,
     /// Container/VM ID
-    #[arg(long)]
+    #[arg(long, value_parser = vm_id)]
     id: String,
-    /// Path to log file for console output
+    /// Path to log file for console output (kernel and k3rs-init messages)
     #[arg(long)]
     log: Option<String>,
+    /// Path to the container's log file; k3rs-init forwards the entrypoint's
+    /// stdout/stderr here over vsock
+    #[arg(long)]
+    stdout_log: Option<String>,
     /// Run in foreground (block until VM exits)
     #[arg(long, default_value_t = false)]
     foreground: bool,
@@ -146,7 +156,7 @@ struct BootArgs {
 #[derive(clap::Args)]
 struct StopArgs {
     /// Container/VM ID
-    #[arg(long)]
+    #[arg(long, value_parser = vm_id)]
     id: String,
 }
 
@@ -155,11 +165,15 @@ struct StopArgs {
 #[derive(clap::Args)]
 struct ExecArgs {
     /// Container/VM ID
-    #[arg(long)]
+    #[arg(long, value_parser = vm_id)]
     id: String,
     /// Allocate a PTY in the guest for interactive sessions (SSH-like)
     #[arg(long, default_value_t = false)]
     tty: bool,
+    /// Pass stdin to the command, which sees EOF when it ends, and stream
+    /// its output (without --tty)
+    #[arg(long, default_value_t = false)]
+    stdin: bool,
     /// Command to execute in guest
     #[arg(trailing_var_arg = true)]
     command: Vec<String>,
@@ -170,8 +184,20 @@ struct ExecArgs {
 #[derive(clap::Args)]
 struct StateArgs {
     /// Container/VM ID
-    #[arg(long)]
+    #[arg(long, value_parser = vm_id)]
     id: String,
+    /// Print state, uptime and resource usage as JSON
+    #[arg(long, default_value_t = false)]
+    json: bool,
+}
+
+// ── List ────────────────────────────────────────────────────────────────
+
+#[derive(clap::Args)]
+struct ListArgs {
+    /// Print a JSON array of VM states instead of a table
+    #[arg(long, default_value_t = false)]
+    json: bool,
 }
 
 // ── Rm ──────────────────────────────────────────────────────────────────
@@ -179,12 +205,20 @@ struct StateArgs {
 #[derive(clap::Args)]
 struct RmArgs {
     /// VM ID to remove
+    #[arg(value_parser = vm_id)]
     id: String,
     /// Force kill (SIGKILL instead of SIGTERM)
     #[arg(short, long, default_value_t = false)]
     force: bool,
 }
 
+/// Parses a VM ID argument. The ID names the VM's IPC socket and pidfile,
+/// so it is held to the runtime's container ID rules.
+fn vm_id(id: &str) -> Result<String, String> {
+    pkg_types::validate::validate_container_id(id).map_err(|e| e.to_string())?;
+    Ok(id.to_string())
+}
+
 // ════════════════════════════════════════════════════════════════════════
 // Main
 // ════════════════════════════════════════════════════════════════════════
@@ -207,7 +241,7 @@ fn main() {
         Command::Stop(args) => cmd_stop(args),
         Command::Exec(args) => cmd_exec(args),
         Command::State(args) => cmd_state(args),
-        Command::List => cmd_list(),
+        Command::List(args) => cmd_list(args),
         Command::Rm(args) => cmd_rm(args),
     }
 }
@@ -271,19 +305,30 @@ fn cmd_boot(args: BootArgs) {
     let vm = Arc::new(MainThreadBound::new(vz_vm, marker));
     vm::start_vm(Arc::clone(&vm));
 
+    // Container output arrives over vsock once k3rs-init runs the entrypoint.
+    if let Some(path) = args.stdout_log {
+        guest_logs::start_relay(Arc::clone(&vm), path);
+    }
+
     // Register VM ID for cleanup on exit (signal, delegate, start error)
     ipc::set_active_vm(&args.id);
+    if let Err(e) = pidfile::write(&args.id) {
+        warn!("failed to write pidfile for VM {}: {}", args.id, e);
+    }
 
-    // Start IPC listener for exec requests.
-    // Two closures: one for regular one-shot exec, one for streaming PTY exec.
-    let id_for_ipc = args.id.clone();
+    // Start IPC listener for exec requests: one-shot exec, streaming exec
+    // (PTY or stdin) and stats each get a handler.
     let vm_for_ipc = Arc::clone(&vm);
     let vm_for_ipc_stream = Arc::clone(&vm);
+    let (cpus, memory_mb) = (args.cpus, args.memory);
     ipc::start_listener(
-        &id_for_ipc,
-        move |parts| vsock::exec_via_vsock(&vm_for_ipc, parts),
-        move |parts, ipc_stream| {
-            vsock::exec_streaming_via_vsock(&vm_for_ipc_stream, parts, ipc_stream)
+        &args.id,
+        ipc::Handlers {
+            exec: Box::new(move |parts| vsock::exec_via_vsock(&vm_for_ipc, parts)),
+            stream: Box::new(move |parts, tty, ipc_stream| {
+                vsock::exec_streaming_via_vsock(&vm_for_ipc_stream, parts, tty, ipc_stream)
+            }),
+            stats: Box::new(move || stats::VmStats::collect(cpus, memory_mb)),
         },
     );
 
@@ -318,23 +363,10 @@ fn cmd_boot(args: BootArgs) {
 fn cmd_stop(args: StopArgs) {
     info!("stopping VM: {}", args.id);
 
-    // Find PID via pgrep
-    let output = std::process::Command::new("pgrep")
-        .args(["-f", &format!("k3rs-vmm boot.*--id {}", args.id)])
-        .output();
-
-    match output {
-        Ok(o) if o.status.success() => {
-            let pid_str = String::from_utf8_lossy(&o.stdout).trim().to_string();
-            if let Some(first_pid) = pid_str.lines().next()
-                && let Ok(pid) = first_pid.parse::<i32>()
-            {
-                unsafe { libc::kill(pid, libc::SIGTERM) };
-                println!("state=stopped");
-                return;
-            }
-        }
-        _ => {}
+    if let Some(pid) = pidfile::find(&args.id) {
+        unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) };
+        println!("state=stopped");
+        return;
     }
 
     eprintln!("VM {} not found", args.id);
@@ -358,23 +390,41 @@ fn cmd_exec(args: ExecArgs) {
         command.join(" ")
     );
 
+    let failed = |e: std::io::Error| -> ! {
+        eprintln!("exec error: {}", e);
+        process::exit(pkg_constants::vm::VMM_EXEC_FAILED);
+    };
+
+    // Exit with the command's own exit code, as reported by k3rs-init.
     if args.tty {
         // Streaming PTY mode: bidirectional relay through IPC → vsock → guest PTY.
         match ipc::exec_streaming_via_ipc(&args.id, &command) {
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("exec error: {}", e);
-                process::exit(1);
-            }
+            Ok(code) => process::exit(code.unwrap_or(0)),
+            Err(e) => failed(e),
+        }
+    } else if args.stdin {
+        // Stdin mode: framed stdin relay through IPC → vsock → guest pipes.
+        match ipc::exec_stdin_via_ipc(&args.id, &command) {
+            // No status: the boot process could not reach the guest.
+            Ok(code) => process::exit(code.unwrap_or(pkg_constants::vm::VMM_EXEC_FAILED)),
+            Err(e) => failed(e),
         }
     } else {
         // One-shot mode: collect output and print.
-        match ipc::exec_via_ipc(&args.id, &command) {
-            Ok(output) => print!("{}", output),
-            Err(e) => {
-                eprintln!("exec error: {}", e);
-                process::exit(1);
+        let output = ipc::exec_via_ipc(&args.id, &command).unwrap_or_else(|e| failed(e));
+        match trailer::split(output.as_bytes()) {
+            (output, Some(code)) => {
+                std::io::stdout().write_all(output).ok();
+                std::io::stdout().flush().ok();
+                process::exit(code);
+            }
+            // The boot process could not reach the guest.
+            _ if output.starts_with("exec error:") => {
+                eprint!("{}", output);
+                process::exit(pkg_constants::vm::VMM_EXEC_FAILED);
             }
+            // A k3rs-init too old to report a status.
+            _ => print!("{}", output),
         }
     }
 }
@@ -382,77 +432,82 @@ fn cmd_exec(args: ExecArgs) {
 // ── State command ───────────────────────────────────────────────────────
 
 fn cmd_state(args: StateArgs) {
-    let sock_path = ipc::socket_path(&args.id);
-    if Path::new(&sock_path).exists() {
-        println!("state=running");
+    let status = vm_status(&args.id);
+    if args.json {
+        println!("{}", serde_json::to_string(&status).unwrap());
+        return;
+    }
+    println!("state={}", status.state);
+    if let Some(stats) = status.stats {
+        println!("pid={}", stats.pid);
+        println!("uptime_secs={}", stats.uptime_secs);
+        println!("cpu_usage_usec={}", stats.cpu_usage_usec);
+        println!("rss_bytes={}", stats.rss_bytes);
+    }
+}
+
+/// State and, when the boot process answers, usage of one VM. A VM whose
+/// boot process is gone reads as `stale` and its socket is removed.
+fn vm_status(id: &str) -> stats::VmStatus {
+    let state = if !Path::new(&ipc::socket_path(id)).exists() {
+        "not_found"
+    } else if ipc::live_pid(id).is_none() {
+        "stale"
     } else {
-        println!("state=not_found");
+        "running"
+    };
+    let stats = if state == "running" {
+        ipc::stats_via_ipc(id)
+            .inspect_err(|e| warn!("stats for VM {} unavailable: {}", id, e))
+            .ok()
+    } else {
+        None
+    };
+    stats::VmStatus {
+        id: id.to_string(),
+        state: state.to_string(),
+        stats,
     }
 }
 
 // ── List command ────────────────────────────────────────────────────────
 
-fn cmd_list() {
-    let vms_dir = format!("{}/runtime/vms", pkg_constants::paths::DATA_DIR);
-
-    let entries = match std::fs::read_dir(&vms_dir) {
-        Ok(e) => e,
-        Err(_) => {
-            println!("No VMs found");
-            return;
-        }
-    };
+fn cmd_list(args: ListArgs) {
+    let statuses: Vec<stats::VmStatus> = ipc::socket_ids().iter().map(|id| vm_status(id)).collect();
 
-    let mut sockets: Vec<String> = entries
-        .filter_map(|e| e.ok())
-        .map(|e| e.file_name().to_string_lossy().to_string())
-        .filter(|name| name.starts_with("vmm-") && name.ends_with(".sock"))
-        .collect();
-    sockets.sort();
+    if args.json {
+        println!("{}", serde_json::to_string(&statuses).unwrap());
+        return;
+    }
 
-    if sockets.is_empty() {
+    if statuses.is_empty() {
         println!("No VMs running");
         return;
     }
 
-    println!("{:<38}  {:<8}  STATE", "VM ID", "PID");
-    println!("{}", "-".repeat(60));
-
-    for sock_file in &sockets {
-        let id = sock_file
-            .trim_start_matches("vmm-")
-            .trim_end_matches(".sock");
-
-        let mut pid = "-".to_string();
-        let mut state = "running";
-
-        // Find PID via pgrep
-        let output = std::process::Command::new("pgrep")
-            .args(["-f", &format!("k3rs-vmm boot.*--id {}", id)])
-            .output();
-
-        match output {
-            Ok(o) if o.status.success() => {
-                let pid_str = String::from_utf8_lossy(&o.stdout).trim().to_string();
-                if let Some(first) = pid_str.lines().next() {
-                    if !first.is_empty() {
-                        pid = first.to_string();
-                    } else {
-                        state = "stale";
-                        let _ = std::fs::remove_file(format!("{}/{}", vms_dir, sock_file));
-                    }
-                } else {
-                    state = "stale";
-                    let _ = std::fs::remove_file(format!("{}/{}", vms_dir, sock_file));
-                }
-            }
-            _ => {
-                state = "stale";
-                let _ = std::fs::remove_file(format!("{}/{}", vms_dir, sock_file));
-            }
+    println!(
+        "{:<38}  {:<8}  {:>4}  {:>8}  {:>10}  {:>10}  STATE",
+        "VM ID", "PID", "CPUS", "MEMORY", "RSS", "UPTIME"
+    );
+    println!("{}", "-".repeat(98));
+
+    for status in &statuses {
+        match &status.stats {
+            Some(s) => println!(
+                "{:<38}  {:<8}  {:>4}  {:>6}MB  {:>8}MB  {:>9}s  {}",
+                status.id,
+                s.pid,
+                s.cpus,
+                s.memory_mb,
+                s.rss_bytes >> 20,
+                s.uptime_secs,
+                status.state
+            ),
+            None => println!(
+                "{:<38}  {:<8}  {:>4}  {:>8}  {:>10}  {:>10}  {}",
+                status.id, "-", "-", "-", "-", "-", status.state
+            ),
         }
-
-        println!("{:<38}  {:<8}  {}", id, pid, state);
     }
 }
 
@@ -461,42 +516,30 @@ fn cmd_list() {
 fn cmd_rm(args: RmArgs) {
     let sock_path = ipc::socket_path(&args.id);
 
-    // Find PID of the boot process
-    let output = std::process::Command::new("pgrep")
-        .args(["-f", &format!("k3rs-vmm boot.*--id {}", args.id)])
-        .output();
-
-    match output {
-        Ok(o) if o.status.success() => {
-            let pid_str = String::from_utf8_lossy(&o.stdout).trim().to_string();
-            if let Some(first_pid) = pid_str.lines().next() {
-                if let Ok(pid) = first_pid.parse::<i32>() {
-                    if args.force {
-                        unsafe { libc::kill(pid, libc::SIGKILL) };
-                        println!("VM {} force killed (pid={})", args.id, pid);
-                    } else {
-                        unsafe { libc::kill(pid, libc::SIGTERM) };
-                        println!("VM {} stopped (pid={})", args.id, pid);
-
-                        // Wait briefly, then force kill if still running
-                        thread::sleep(std::time::Duration::from_secs(1));
-                        if unsafe { libc::kill(pid, 0) } == 0 {
-                            unsafe { libc::kill(pid, libc::SIGKILL) };
-                            println!("VM {} force killed after timeout", args.id);
-                        }
-                    }
-                } else {
-                    println!("VM {} process not found", args.id);
-                }
+    match pidfile::find(&args.id) {
+        Some(pid) => {
+            let pid = pid as libc::pid_t;
+            if args.force {
+                unsafe { libc::kill(pid, libc::SIGKILL) };
+                println!("VM {} force killed (pid={})", args.id, pid);
             } else {
-                println!("VM {} process not found", args.id);
+                unsafe { libc::kill(pid, libc::SIGTERM) };
+                println!("VM {} stopped (pid={})", args.id, pid);
+
+                // Wait briefly, then force kill if still running
+                thread::sleep(std::time::Duration::from_secs(1));
+                if unsafe { libc::kill(pid, 0) } == 0 {
+                    unsafe { libc::kill(pid, libc::SIGKILL) };
+                    println!("VM {} force killed after timeout", args.id);
+                }
             }
         }
-        _ => {
-            println!("VM {} process not found", args.id);
-        }
+        None => println!("VM {} process not found", args.id),
     }
 
+    // A killed boot process can't clean up after itself.
+    pidfile::remove(&args.id);
+
     // Clean up socket file
     let _ = std::fs::remove_file(&sock_path);
     println!("Cleaned up {}", sock_path);
@@ -0,0 +1,200 @@
+//! Pidfile registry of running boot processes.
+//!
+//! Each `boot` process writes its PID to `{DATA_DIR}/runtime/vms/vmm-{id}.pid`
+//! next to its IPC socket and removes it on a clean exit. `stop`, `rm`,
+//! `state` and `ls` look VMs up here instead of matching command lines with
+//! `pgrep`. A PID is only trusted while that process is alive and its
+//! arguments are a `k3rs-vmm boot` for exactly that ID; anything else is a
+//! stale file, and is removed.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tracing::info;
+
+use crate::ipc;
+
+/// Path of the pidfile for a VM.
+pub fn path(id: &str) -> PathBuf {
+    pidfile_in(Path::new(&ipc::vmm_socket_dir()), id)
+}
+
+/// Register the calling process as the boot process of `id`.
+pub fn write(id: &str) -> io::Result<()> {
+    write_in(Path::new(&ipc::vmm_socket_dir()), id, std::process::id())
+}
+
+/// Drop the registration of `id`.
+pub fn remove(id: &str) {
+    let _ = std::fs::remove_file(path(id));
+}
+
+/// PID of the live boot process of `id`, if there is one.
+pub fn find(id: &str) -> Option<u32> {
+    find_in(Path::new(&ipc::vmm_socket_dir()), id)
+}
+
+fn pidfile_in(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("vmm-{}.pid", id))
+}
+
+/// Written to a temporary file and renamed, so a reader never sees a
+/// partial PID.
+fn write_in(dir: &Path, id: &str, pid: u32) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let path = pidfile_in(dir, id);
+    let tmp = path.with_extension("pid.tmp");
+    std::fs::write(&tmp, format!("{}\n", pid))?;
+    std::fs::rename(&tmp, &path)
+}
+
+fn find_in(dir: &Path, id: &str) -> Option<u32> {
+    let path = pidfile_in(dir, id);
+    let pid: u32 = std::fs::read_to_string(&path).ok()?.trim().parse().ok()?;
+    let alive = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
+    if alive && process_args(pid).is_some_and(|args| is_boot_of(&args, id)) {
+        return Some(pid);
+    }
+    info!("removing stale pidfile {} (pid={})", path.display(), pid);
+    let _ = std::fs::remove_file(&path);
+    None
+}
+
+/// Whether `args` is a `k3rs-vmm boot` for exactly `id`. The ID is compared
+/// whole, so a boot of `web-1` is not taken for `web`.
+fn is_boot_of(args: &[String], id: &str) -> bool {
+    let Some(bin) = args
+        .iter()
+        .position(|a| Path::new(a).file_name().is_some_and(|n| n == "k3rs-vmm"))
+    else {
+        return false;
+    };
+    let rest = &args[bin + 1..];
+    if rest.first().map(String::as_str) != Some("boot") {
+        return false;
+    }
+    rest.windows(2).any(|w| w[0] == "--id" && w[1] == id)
+        || rest.iter().any(|a| a.strip_prefix("--id=") == Some(id))
+}
+
+/// Arguments of a running process, from `KERN_PROCARGS2`.
+#[cfg(target_os = "macos")]
+fn process_args(pid: u32) -> Option<Vec<String>> {
+    let mut mib = [libc::CTL_KERN, libc::KERN_PROCARGS2, pid as libc::c_int];
+    let mut size: libc::size_t = 0;
+    let null = std::ptr::null_mut();
+    if unsafe { libc::sysctl(mib.as_mut_ptr(), 3, null, &mut size, null, 0) } != 0 {
+        return None;
+    }
+    let mut buf = vec![0u8; size];
+    let ptr = buf.as_mut_ptr().cast();
+    if unsafe { libc::sysctl(mib.as_mut_ptr(), 3, ptr, &mut size, null, 0) } != 0 {
+        return None;
+    }
+    buf.truncate(size);
+    parse_procargs2(&buf)
+}
+
+/// `argc (i32) | exec path | NUL padding | argv... | env...`
+#[cfg(any(target_os = "macos", test))]
+fn parse_procargs2(buf: &[u8]) -> Option<Vec<String>> {
+    let argc = i32::from_ne_bytes(buf.get(..4)?.try_into().ok()?) as usize;
+    let rest = &buf[4..];
+    let path_end = rest.iter().position(|&b| b == 0)?;
+    let start = path_end + rest[path_end..].iter().position(|&b| b != 0)?;
+    let args: Vec<String> = rest[start..]
+        .split(|&b| b == 0)
+        .take(argc)
+        .map(|a| String::from_utf8_lossy(a).into_owned())
+        .collect();
+    (args.len() == argc).then_some(args)
+}
+
+/// Arguments of a running process, from `/proc/<pid>/cmdline`.
+#[cfg(not(target_os = "macos"))]
+fn process_args(pid: u32) -> Option<Vec<String>> {
+    let raw = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let raw = raw.strip_suffix(b"\0").unwrap_or(&raw);
+    Some(
+        raw.split(|&b| b == 0)
+            .map(|a| String::from_utf8_lossy(a).into_owned())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pkg_container::test_util::temp_dir;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::{Child, Command};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(str::to_string).collect()
+    }
+
+    /// A process whose arguments read `.../k3rs-vmm boot --id <id>`.
+    fn fake_boot(dir: &Path, id: &str) -> Child {
+        let bin = dir.join("k3rs-vmm");
+        std::fs::write(&bin, "#!/bin/sh\nsleep 30\n").unwrap();
+        std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        Command::new(&bin)
+            .args(["boot", "--id", id])
+            .spawn()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_is_boot_of() {
+        assert!(is_boot_of(
+            &args("/usr/local/bin/k3rs-vmm boot --kernel k --id web"),
+            "web"
+        ));
+        assert!(is_boot_of(
+            &args("k3rs-vmm boot --id=web --foreground"),
+            "web"
+        ));
+        // pgrep's `k3rs-vmm boot.*--id web` matches all of these.
+        assert!(!is_boot_of(&args("k3rs-vmm boot --id web-1"), "web"));
+        assert!(!is_boot_of(&args("k3rs-vmm boot --id=web-1"), "web"));
+        assert!(!is_boot_of(&args("k3rs-vmm exec --id web"), "web"));
+        assert!(!is_boot_of(&args("vim k3rs-vmm-boot --id web"), "web"));
+    }
+
+    #[test]
+    fn test_parse_procargs2() {
+        let mut buf = 3i32.to_ne_bytes().to_vec();
+        buf.extend_from_slice(b"/usr/local/bin/k3rs-vmm\0\0\0\0k3rs-vmm\0boot\0\0HOME=/\0");
+        assert_eq!(
+            parse_procargs2(&buf).unwrap(),
+            ["k3rs-vmm", "boot", ""].map(String::from)
+        );
+        assert!(parse_procargs2(&buf[..2]).is_none());
+    }
+
+    #[test]
+    fn test_pidfile_lifecycle() {
+        let dir = temp_dir("pidfile");
+        let mut boot = fake_boot(&dir, "web-1");
+        let pid = boot.id();
+        // Give the shell a moment to exec.
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        write_in(&dir, "web-1", pid).unwrap();
+        assert_eq!(find_in(&dir, "web-1"), Some(pid));
+
+        // A pidfile for `web` pointing at the boot of `web-1` is stale.
+        write_in(&dir, "web", pid).unwrap();
+        assert_eq!(find_in(&dir, "web"), None);
+        assert!(!pidfile_in(&dir, "web").exists());
+
+        // Once the process is gone the pidfile is stale too.
+        boot.kill().unwrap();
+        boot.wait().unwrap();
+        assert_eq!(find_in(&dir, "web-1"), None);
+        assert!(!pidfile_in(&dir, "web-1").exists());
+        assert_eq!(find_in(&dir, "never-booted"), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,125 @@
+//! One exec session: an IPC client served over its own guest connection.
+//!
+//! Every `k3rs-vmm exec` gets a fresh vsock connection to k3rs-init, which
+//! runs each on its own thread, so sessions share nothing but the VM. The
+//! functions here only see file descriptors; `vsock` opens the guest side.
+
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::net::UnixStream;
+
+use tracing::error;
+
+/// Write all of `data` to `fd`.
+pub fn write_all(fd: i32, mut data: &[u8]) -> std::io::Result<()> {
+    while !data.is_empty() {
+        let n = unsafe { libc::write(fd, data.as_ptr() as *const libc::c_void, data.len()) };
+        if n < 0 {
+            let e = std::io::Error::last_os_error();
+            if e.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+        if n == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        data = &data[n as usize..];
+    }
+    Ok(())
+}
+
+/// Write `cmd` to guest `fd`, read all response bytes, close `fd`.
+pub fn exec_oneshot(fd: i32, cmd: &str) -> String {
+    if let Err(e) = write_all(fd, cmd.as_bytes()) {
+        error!("vsock write failed: {}", e);
+        unsafe { libc::close(fd) };
+        return format!("exec error: write failed: {}\n", e);
+    }
+
+    // NOTE: do NOT shutdown(SHUT_WR) here.  Apple's Virtualization.framework
+    // vsock does not support half-duplex shutdown — calling shutdown(SHUT_WR)
+    // closes the entire connection, causing the guest's write-back to fail
+    // silently and the host to read an empty response.  The '\n' terminator
+    // already tells k3rs-init that the full request has arrived.
+
+    // Read response until EOF (k3rs-init closes the connection after writing output)
+    let mut output = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        match n {
+            0 => break, // EOF — guest closed connection after writing output
+            n if n > 0 => output.extend_from_slice(&buf[..n as usize]),
+            _ => {
+                let e = std::io::Error::last_os_error();
+                if e.kind() != std::io::ErrorKind::Interrupted {
+                    error!("vsock read failed: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    unsafe { libc::close(fd) };
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+/// Relay `client` ↔ guest `fd` until the session is over, then close both.
+///
+/// - Guest EOF (the command exited): everything is sent, so the client is
+///   shut down and the session ends even if it never closes its side.
+/// - Client EOF (input closed, or the client died): the guest side is
+///   half-closed and k3rs-init hangs up the command.
+/// - A failed write to the client means it is gone mid-output: the guest
+///   connection is shut down outright, so nothing waits on a reader that
+///   will never come back.
+pub fn relay(fd: i32, client: UnixStream) {
+    let client_fd = client.into_raw_fd(); // we now own this fd exclusively
+
+    // Guest output → client.
+    let to_client = std::thread::spawn(move || {
+        if copy(fd, client_fd) == CopyEnd::WriteFailed {
+            unsafe { libc::shutdown(fd, libc::SHUT_RDWR) };
+        }
+        unsafe { libc::shutdown(client_fd, libc::SHUT_RDWR) };
+    });
+
+    // Client input → guest.
+    let to_guest = std::thread::spawn(move || {
+        let how = match copy(client_fd, fd) {
+            CopyEnd::ReadEnded => libc::SHUT_WR,
+            CopyEnd::WriteFailed => libc::SHUT_RDWR,
+        };
+        unsafe { libc::shutdown(fd, how) };
+    });
+
+    to_client.join().ok();
+    to_guest.join().ok();
+    // Both directions are done: no thread is left using either fd.
+    unsafe { libc::close(client_fd) };
+    unsafe { libc::close(fd) };
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CopyEnd {
+    /// `from` reached EOF or failed.
+    ReadEnded,
+    /// `to` did not take the data.
+    WriteFailed,
+}
+
+fn copy(from: i32, to: i32) -> CopyEnd {
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = unsafe { libc::read(from, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if n < 0 && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted {
+            continue;
+        }
+        if n <= 0 {
+            return CopyEnd::ReadEnded;
+        }
+        if write_all(to, &buf[..n as usize]).is_err() {
+            return CopyEnd::WriteFailed;
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Resource usage of a running VM.
+//!
+//! The boot process answers IPC `stats` requests with a [`VmStats`] taken
+//! from its own process: the guest's vCPUs and memory are threads and
+//! mappings of the VMM, so its CPU time and resident size are what the VM
+//! costs the host. `state --json` and `ls --json` print it as [`VmStatus`].
+
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// When the VM finished booting; unset until the start completion fires.
+static STARTED_AT: OnceLock<Instant> = OnceLock::new();
+
+/// Record that the VM is up. Uptime counts from the first call.
+pub fn mark_started() {
+    let _ = STARTED_AT.set(Instant::now());
+}
+
+/// Usage of one VM, as served by its boot process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VmStats {
+    /// PID of the boot process.
+    pub pid: u32,
+    /// Seconds since the VM started; 0 while it is still booting.
+    pub uptime_secs: u64,
+    /// User + system CPU time of the boot process.
+    pub cpu_usage_usec: u64,
+    /// Resident memory of the boot process.
+    pub rss_bytes: u64,
+    /// Configured vCPUs.
+    pub cpus: usize,
+    /// Configured memory.
+    pub memory_mb: u64,
+}
+
+impl VmStats {
+    /// Sample the calling (boot) process.
+    pub fn collect(cpus: usize, memory_mb: u64) -> Self {
+        Self {
+            pid: std::process::id(),
+            uptime_secs: STARTED_AT.get().map_or(0, |t| t.elapsed().as_secs()),
+            cpu_usage_usec: cpu_usage_usec(),
+            rss_bytes: rss_bytes(),
+            cpus,
+            memory_mb,
+        }
+    }
+}
+
+/// One line of `state --json` / `ls --json`. `stats` is absent when the VM
+/// is not running or its boot process did not answer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VmStatus {
+    pub id: String,
+    /// `running`, `stale` (socket left by a dead boot process) or `not_found`.
+    pub state: String,
+    #[serde(flatten)]
+    pub stats: Option<VmStats>,
+}
+
+fn cpu_usage_usec() -> u64 {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return 0;
+    }
+    let usec = |tv: libc::timeval| tv.tv_sec as u64 * 1_000_000 + tv.tv_usec as u64;
+    usec(usage.ru_utime) + usec(usage.ru_stime)
+}
+
+/// Current resident size; `getrusage` only reports the peak.
+fn rss_bytes() -> u64 {
+    let mut info: libc::proc_taskinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+    let n = unsafe {
+        libc::proc_pidinfo(
+            std::process::id() as libc::c_int,
+            libc::PROC_PIDTASKINFO,
+            0,
+            &mut info as *mut _ as *mut libc::c_void,
+            size,
+        )
+    };
+    if n == size { info.pti_resident_size } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_json_format() {
+        let running = VmStatus {
+            id: "vm-1".to_string(),
+            state: "running".to_string(),
+            stats: Some(VmStats {
+                pid: 4242,
+                uptime_secs: 90,
+                cpu_usage_usec: 1_500_000,
+                rss_bytes: 64 << 20,
+                cpus: 2,
+                memory_mb: 512,
+            }),
+        };
+        let json = serde_json::to_string(&running).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":"vm-1","state":"running","pid":4242,"uptime_secs":90,"cpu_usage_usec":1500000,"rss_bytes":67108864,"cpus":2,"memory_mb":512}"#
+        );
+        assert_eq!(serde_json::from_str::<VmStatus>(&json).unwrap(), running);
+
+        let gone = VmStatus {
+            id: "vm-2".to_string(),
+            state: "not_found".to_string(),
+            stats: None,
+        };
+        let json = serde_json::to_string(&gone).unwrap();
+        assert_eq!(json, r#"{"id":"vm-2","state":"not_found"}"#);
+        assert_eq!(serde_json::from_str::<VmStatus>(&json).unwrap(), gone);
+    }
+
+    #[test]
+    fn test_collect_samples_this_process() {
+        mark_started();
+        let stats = VmStats::collect(4, 1024);
+        assert_eq!(stats.pid, std::process::id());
+        assert_eq!((stats.cpus, stats.memory_mb), (4, 1024));
+        assert!(stats.rss_bytes > 0);
+    }
+}
@@ -0,0 +1,120 @@
+//! The exit status k3rs-init reports at the end of an exec connection.
+//!
+//! After a command's output, one-shot or streaming, k3rs-init writes
+//! `\x02exit:<code>\n` and closes the connection. The boot process passes it
+//! through untouched; `k3rs-vmm exec` strips it from what it prints and exits
+//! with the code.
+
+use pkg_constants::vm::VSOCK_EXIT_TRAILER;
+
+/// Longest trailer: the marker, an `i32` and the newline.
+const MAX_TRAILER_LEN: usize = VSOCK_EXIT_TRAILER.len() + 11 + 1;
+
+/// Split the trailer off the end of a one-shot exec's output. Output
+/// without one (an `exec error:` from the host side, or an older k3rs-init)
+/// is returned whole.
+pub fn split(output: &[u8]) -> (&[u8], Option<i32>) {
+    let Some(start) = output
+        .windows(VSOCK_EXIT_TRAILER.len())
+        .rposition(|w| w == VSOCK_EXIT_TRAILER)
+    else {
+        return (output, None);
+    };
+    let code = output[start + VSOCK_EXIT_TRAILER.len()..]
+        .strip_suffix(b"\n")
+        .and_then(|digits| std::str::from_utf8(digits).ok())
+        .and_then(|digits| digits.parse().ok());
+    match code {
+        Some(code) => (&output[..start], Some(code)),
+        None => (output, None),
+    }
+}
+
+/// Strips the trailer from a streamed session. Bytes that could still turn
+/// out to be the start of the trailer are held back until more arrive.
+#[derive(Default)]
+pub struct Filter {
+    pending: Vec<u8>,
+}
+
+impl Filter {
+    /// Take in `chunk` and return what can be written out now.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+        let keep = match self
+            .pending
+            .iter()
+            .rposition(|&b| b == VSOCK_EXIT_TRAILER[0])
+        {
+            Some(start) if may_be_trailer(&self.pending[start..]) => start,
+            _ => self.pending.len(),
+        };
+        let rest = self.pending.split_off(keep);
+        std::mem::replace(&mut self.pending, rest)
+    }
+
+    /// The connection closed: the held-back bytes that were not the trailer,
+    /// and the exit code if it was.
+    pub fn finish(self) -> (Vec<u8>, Option<i32>) {
+        let (output, code) = split(&self.pending);
+        (output.to_vec(), code)
+    }
+}
+
+/// Whether `tail` is the trailer, or the start of one.
+fn may_be_trailer(tail: &[u8]) -> bool {
+    if tail.len() > MAX_TRAILER_LEN {
+        return false;
+    }
+    let marker = tail.len().min(VSOCK_EXIT_TRAILER.len());
+    if tail[..marker] != VSOCK_EXIT_TRAILER[..marker] {
+        return false;
+    }
+    let code = &tail[marker..];
+    let digits = code.strip_suffix(b"\n").unwrap_or(code);
+    digits.iter().all(|b| b.is_ascii_digit() || *b == b'-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split(b"hello\n\x02exit:3\n"), (&b"hello\n"[..], Some(3)));
+        assert_eq!(split(b"\x02exit:0\n"), (&b""[..], Some(0)));
+        // No trailer, or something that only looks like the start of one.
+        assert_eq!(
+            split(b"exec error: no VM\n"),
+            (&b"exec error: no VM\n"[..], None)
+        );
+        assert_eq!(split(b"\x02exit:x\n"), (&b"\x02exit:x\n"[..], None));
+        assert_eq!(split(b"\x02exit:1"), (&b"\x02exit:1"[..], None));
+    }
+
+    #[test]
+    fn test_filter_holds_back_a_split_trailer() {
+        let mut filter = Filter::default();
+        let mut out = filter.push(b"$ false\r\n\x02ex");
+        assert_eq!(out, b"$ false\r\n");
+        out.extend(filter.push(b"it:1"));
+        out.extend(filter.push(b"\n"));
+        let (rest, code) = filter.finish();
+        out.extend(rest);
+        assert_eq!(out, b"$ false\r\n");
+        assert_eq!(code, Some(1));
+    }
+
+    #[test]
+    fn test_filter_passes_lookalikes_through() {
+        let mut filter = Filter::default();
+        // A ^B typed into the session is output like any other byte.
+        let mut out = filter.push(b"a\x02b");
+        out.extend(filter.push(b"\x02exi"));
+        out.extend(filter.push(b"t!"));
+        let (rest, code) = filter.finish();
+        out.extend(rest);
+        assert_eq!(out, b"a\x02b\x02exit!");
+        assert_eq!(code, None);
+    }
+}
@@ -15,7 +15,7 @@ use objc2_foundation::NSError;
 use objc2_virtualization::VZVirtualMachine;
 use tracing::{error, info};
 
-use crate::ipc;
+use crate::{ipc, stats};
 
 /// Clean up IPC socket and exit the process.
 fn cleanup_exit(code: i32) -> ! {
@@ -32,6 +32,7 @@ pub fn start_vm(vm: Arc<MainThreadBound<Retained<VZVirtualMachine>>>) {
         let block = &StackBlock::new(move |err: *mut NSError| {
             let boot_elapsed = boot_start.elapsed();
             if err.is_null() {
+                stats::mark_started();
                 info!("vm started successfully in {:?}", boot_elapsed);
                 if boot_elapsed > Duration::from_secs(1) {
                     error!("vm boot time {:?} exceeds 1s target", boot_elapsed);
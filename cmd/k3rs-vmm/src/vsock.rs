@@ -20,6 +20,13 @@
 //! 4. Guest bridges PTY master ↔ vsock bidirectionally
 //! 5. When guest process exits, guest closes connection
 //! 6. Host detects EOF, closes IPC relay
+//!
+//! ### Exec with stdin (port 5555, `\x03` prefix)
+//! 1. Host sends: `\x03arg0\0arg1\0arg2\n`, then the client's stdin frames
+//!    (`length (4, BE) | bytes`, zero length for EOF) as they come
+//! 2. Guest spawns the command on pipes, writes frames to its stdin and
+//!    closes it at the EOF frame
+//! 3. Guest streams stdout+stderr back, then the exit trailer, and closes
 
 use std::sync::{Arc, Condvar, Mutex};
 use std::time::Duration;
@@ -31,6 +38,8 @@ use objc2_foundation::NSError;
 use objc2_virtualization::{VZVirtioSocketConnection, VZVirtioSocketDevice, VZVirtualMachine};
 use tracing::{error, info};
 
+use crate::session;
+
 /// vsock port k3rs-init listens on for exec commands.
 const VSOCK_EXEC_PORT: u32 = pkg_constants::vm::VSOCK_EXEC_PORT;
 
@@ -56,23 +65,24 @@ pub fn exec_via_vsock(
     // NUL-delimited command + newline terminator (matches k3rs-init vsock protocol)
     let cmd_str = command.join("\0") + "\n";
 
-    match connect_vsock(vm) {
-        Ok(fd) => exec_on_fd(fd, &cmd_str),
+    match connect_vsock(vm, VSOCK_EXEC_PORT) {
+        Ok(fd) => session::exec_oneshot(fd, &cmd_str),
         Err(e) => format!("exec error: {}\n", e),
     }
 }
 
-/// Execute a streaming PTY command in the guest via vsock.
+/// Execute a streaming command in the guest via vsock.
 ///
-/// Sends `\x01` + NUL-delimited command to k3rs-init's PTY listener, then
-/// relays `ipc_stream` ↔ vsock bidirectionally until the guest closes the
-/// connection (process exited).
+/// Sends `\x01` (PTY, when `tty` is set) or `\x03` (framed stdin) +
+/// NUL-delimited command to k3rs-init, then relays `ipc_stream` ↔ vsock
+/// bidirectionally until the guest closes the connection (process exited).
 ///
 /// `ipc_stream` is the open UnixStream from the IPC listener, already past the
 /// command-header bytes (it carries stdin/stdout for the exec session).
 pub fn exec_streaming_via_vsock(
     vm: &Arc<MainThreadBound<Retained<VZVirtualMachine>>>,
     command: &[String],
+    tty: bool,
     ipc_stream: std::os::unix::net::UnixStream,
 ) {
     if command.is_empty() {
@@ -82,7 +92,7 @@ pub fn exec_streaming_via_vsock(
 
     info!("vsock streaming exec: {:?}", command);
 
-    let fd = match connect_vsock(vm) {
+    let fd = match connect_vsock(vm, VSOCK_EXEC_PORT) {
         Ok(f) => f,
         Err(e) => {
             error!("vsock connect failed: {}", e);
@@ -91,109 +101,35 @@ pub fn exec_streaming_via_vsock(
     };
 
     // Send streaming prefix + NUL-delimited command + newline.
-    // The `\x01` byte tells k3rs-init to use PTY streaming mode.
-    let mut cmd_bytes = vec![pkg_constants::vm::VSOCK_STREAM_PREFIX];
+    // The `\x01` byte tells k3rs-init to use PTY streaming mode; `\x03` to
+    // read the command's stdin from the frames that follow.
+    let prefix = if tty {
+        pkg_constants::vm::VSOCK_STREAM_PREFIX
+    } else {
+        pkg_constants::vm::VSOCK_STDIN_PREFIX
+    };
+    let mut cmd_bytes = vec![prefix];
     cmd_bytes.extend_from_slice(command.join("\0").as_bytes());
     cmd_bytes.push(b'\n');
 
-    let mut written = 0;
-    while written < cmd_bytes.len() {
-        let n = unsafe {
-            libc::write(
-                fd,
-                cmd_bytes[written..].as_ptr() as *const libc::c_void,
-                cmd_bytes.len() - written,
-            )
-        };
-        if n <= 0 {
-            error!("vsock write failed: {}", std::io::Error::last_os_error());
-            unsafe { libc::close(fd) };
-            return;
-        }
-        written += n as usize;
-    }
-
-    // Bidirectional relay: ipc_stream ↔ vsock fd
-    use std::os::unix::io::IntoRawFd;
-    let ipc_fd = ipc_stream.into_raw_fd(); // we now own this fd exclusively
-    let ipc_fd_dup = unsafe { libc::dup(ipc_fd) };
-    let vsock_fd_dup = unsafe { libc::dup(fd) };
-
-    // Thread A: vsock → ipc  (guest output → exec subprocess stdout)
-    let t_vsock_to_ipc = std::thread::spawn(move || {
-        let mut buf = [0u8; 4096];
-        loop {
-            let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
-            if n <= 0 {
-                break;
-            }
-            let mut off = 0usize;
-            while off < n as usize {
-                let w = unsafe {
-                    libc::write(
-                        ipc_fd,
-                        buf[off..n as usize].as_ptr() as *const libc::c_void,
-                        n as usize - off,
-                    )
-                };
-                if w <= 0 {
-                    break;
-                }
-                off += w as usize;
-            }
-            if off < n as usize {
-                break;
-            }
-        }
-        // Signal to exec subprocess that there is no more output.
-        unsafe { libc::shutdown(ipc_fd, libc::SHUT_WR) };
-        unsafe { libc::close(ipc_fd) };
+    if let Err(e) = session::write_all(fd, &cmd_bytes) {
+        error!("vsock write failed: {}", e);
         unsafe { libc::close(fd) };
-    });
-
-    // Thread B: ipc → vsock  (exec subprocess stdin → guest input)
-    let t_ipc_to_vsock = std::thread::spawn(move || {
-        let mut buf = [0u8; 4096];
-        loop {
-            let n =
-                unsafe { libc::read(ipc_fd_dup, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
-            if n <= 0 {
-                break;
-            }
-            let mut off = 0usize;
-            while off < n as usize {
-                let w = unsafe {
-                    libc::write(
-                        vsock_fd_dup,
-                        buf[off..n as usize].as_ptr() as *const libc::c_void,
-                        n as usize - off,
-                    )
-                };
-                if w <= 0 {
-                    break;
-                }
-                off += w as usize;
-            }
-            if off < n as usize {
-                break;
-            }
-        }
-        // Close vsock write side so guest sees EOF on its read path.
-        unsafe { libc::shutdown(vsock_fd_dup, libc::SHUT_WR) };
-        unsafe { libc::close(ipc_fd_dup) };
-        unsafe { libc::close(vsock_fd_dup) };
-    });
+        return;
+    }
 
-    t_vsock_to_ipc.join().ok();
-    t_ipc_to_vsock.join().ok();
+    session::relay(fd, ipc_stream);
 }
 
 // ─── Internal helpers ─────────────────────────────────────────────────────────
 
-/// Connect to vsock port 5555 in the guest, returning a dup'd file descriptor.
+/// Connect to vsock `port` in the guest, returning a dup'd file descriptor.
 ///
 /// Must be called from a non-main thread; uses `run_on_main` internally.
-fn connect_vsock(vm: &Arc<MainThreadBound<Retained<VZVirtualMachine>>>) -> Result<i32, String> {
+pub(crate) fn connect_vsock(
+    vm: &Arc<MainThreadBound<Retained<VZVirtualMachine>>>,
+    port: u32,
+) -> Result<i32, String> {
     type Pair = (Mutex<Option<Result<i32, String>>>, Condvar);
     let pair: Arc<Pair> = Arc::new((Mutex::new(None), Condvar::new()));
     let pair_for_block = Arc::clone(&pair);
@@ -241,7 +177,7 @@ fn connect_vsock(vm: &Arc<MainThreadBound<Retained<VZVirtualMachine>>>) -> Resul
         );
 
         unsafe {
-            vsock_device.connectToPort_completionHandler(VSOCK_EXEC_PORT, &block);
+            vsock_device.connectToPort_completionHandler(port, &block);
         }
     });
 
@@ -257,57 +193,3 @@ fn connect_vsock(vm: &Arc<MainThreadBound<Retained<VZVirtualMachine>>>) -> Resul
 
     guard.take().unwrap()
 }
-
-/// Write `cmd` to `fd`, shutdown write direction, read all response bytes, close `fd`.
-fn exec_on_fd(fd: i32, cmd: &str) -> String {
-    let bytes = cmd.as_bytes();
-    let mut written = 0;
-
-    // Write the full command
-    while written < bytes.len() {
-        let n = unsafe {
-            libc::write(
-                fd,
-                bytes[written..].as_ptr() as *const libc::c_void,
-                bytes.len() - written,
-            )
-        };
-        if n < 0 {
-            let e = std::io::Error::last_os_error();
-            if e.kind() == std::io::ErrorKind::Interrupted {
-                continue;
-            }
-            error!("vsock write failed: {}", e);
-            unsafe { libc::close(fd) };
-            return format!("exec error: write failed: {}\n", e);
-        }
-        written += n as usize;
-    }
-
-    // NOTE: do NOT shutdown(SHUT_WR) here.  Apple's Virtualization.framework
-    // vsock does not support half-duplex shutdown — calling shutdown(SHUT_WR)
-    // closes the entire connection, causing the guest's write-back to fail
-    // silently and the host to read an empty response.  The '\n' terminator
-    // already tells k3rs-init that the full request has arrived.
-
-    // Read response until EOF (k3rs-init closes the connection after writing output)
-    let mut output = Vec::new();
-    let mut buf = [0u8; 4096];
-    loop {
-        let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
-        match n {
-            0 => break, // EOF — guest closed connection after writing output
-            n if n > 0 => output.extend_from_slice(&buf[..n as usize]),
-            _ => {
-                let e = std::io::Error::last_os_error();
-                if e.kind() != std::io::ErrorKind::Interrupted {
-                    error!("vsock read failed: {}", e);
-                    break;
-                }
-            }
-        }
-    }
-
-    unsafe { libc::close(fd) };
-    String::from_utf8_lossy(&output).into_owned()
-}
@@ -31,7 +31,8 @@ struct Cli {
     #[arg(long)]
     server_url: Option<String>,
 
-    /// Join token
+    /// API token that can read VPCs, peerings and nodes (a `readonly` token
+    /// is enough; the join token only registers nodes)
     #[arg(long)]
     token: Option<String>,
 
@@ -167,7 +168,7 @@ async fn main() -> anyhow::Result<()> {
     let token = cli
         .token
         .or(file_cfg.token)
-        .unwrap_or_else(|| pkg_constants::auth::DEFAULT_JOIN_TOKEN.to_string());
+        .unwrap_or_else(|| pkg_constants::auth::DEFAULT_ADMIN_TOKEN.to_string());
     let data_dir = cli
         .data_dir
         .or(file_cfg.data_dir)
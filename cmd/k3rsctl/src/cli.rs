@@ -1,15 +1,23 @@
+use clap::builder::{PossibleValue, StringValueParser, TypedValueParser};
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
 #[command(name = "k3rsctl", about = "CLI tool for k3rs cluster management")]
 pub struct Cli {
-    /// Server API endpoint
-    #[arg(long, default_value = pkg_constants::network::DEFAULT_API_ADDR)]
-    pub server: String,
+    /// Server API endpoint [default: $K3RS_SERVER, else the current
+    /// context's, else http://127.0.0.1:6443]
+    #[arg(long)]
+    pub server: Option<String>,
 
-    /// Authentication token
-    #[arg(long, default_value = pkg_constants::auth::DEFAULT_JOIN_TOKEN)]
-    pub token: String,
+    /// Authentication token [default: $K3RS_TOKEN, else the current
+    /// context's, else the built-in admin token]
+    #[arg(long)]
+    pub token: Option<String>,
+
+    /// Print tables without colors (also when `NO_COLOR` is set or stdout
+    /// is not a terminal)
+    #[arg(long, global = true)]
+    pub no_color: bool,
 
     #[command(subcommand)]
     pub command: Commands,
@@ -29,15 +37,42 @@ pub enum Commands {
     },
     /// Get resources
     Get {
-        /// Resource type (pods, services, deployments, configmaps, secrets, namespaces, replicasets, daemonsets, jobs, cronjobs, hpa)
+        /// Resource type (pods, services, deployments, configmaps, secrets, namespaces, replicasets, daemonsets, jobs, cronjobs, hpa, priorityclasses)
+        #[arg(value_parser = ResourceTypes, hide_possible_values = true)]
         resource: String,
+        /// Resource name — show a single object in full instead of a table
+        name: Option<String>,
         /// Namespace (default: "default")
         #[arg(short, long, default_value = "default")]
         namespace: String,
+        /// Output format: json, yaml, or wide (extra table columns)
+        #[arg(short, long)]
+        output: Option<String>,
+        /// After listing, keep streaming ADDED/MODIFIED/DELETED changes
+        #[arg(short, long, conflicts_with_all = ["name", "output"])]
+        watch: bool,
+        /// Fetch lists this many objects per request (0: all in one request)
+        #[arg(long, default_value_t = pkg_constants::state::DEFAULT_LIST_CHUNK_SIZE)]
+        chunk_size: usize,
+        /// Order the table by this column (e.g. name, status, age)
+        #[arg(long)]
+        sort_by: Option<String>,
     },
     /// Describe a resource in detail
     Describe {
-        /// Resource type (pod)
+        /// Resource type (pod, deployment, service, node)
+        #[arg(value_parser = ResourceTypes, hide_possible_values = true)]
+        resource: String,
+        /// Resource name
+        name: String,
+        /// Namespace (default: "default")
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+    },
+    /// Edit a resource in $EDITOR and submit the change
+    Edit {
+        /// Resource type (e.g. deployment, configmap)
+        #[arg(value_parser = ResourceTypes, hide_possible_values = true)]
         resource: String,
         /// Resource name
         name: String,
@@ -53,10 +88,46 @@ pub enum Commands {
         /// Namespace (default: "default")
         #[arg(short, long, default_value = "default")]
         namespace: String,
+        /// `server`: have the server validate the manifest and print the
+        /// object it would store, without storing it
+        #[arg(long, value_enum)]
+        dry_run: Option<DryRun>,
+    },
+    /// Update fields of a resource with a JSON merge patch
+    Patch {
+        /// Resource type (e.g. deployment, configmap)
+        #[arg(value_parser = ResourceTypes, hide_possible_values = true)]
+        resource: String,
+        /// Resource name
+        name: String,
+        /// The patch, e.g. '{"spec":{"replicas":3}}'
+        #[arg(short, long)]
+        patch: String,
+        /// Patch format
+        #[arg(long = "type", value_enum, default_value_t = PatchType::Merge)]
+        patch_type: PatchType,
+        /// Namespace (default: "default")
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+    },
+    /// Set or remove labels on a node, pod, deployment or namespace
+    Label {
+        /// Resource type (node, pod, deployment, namespace)
+        #[arg(value_parser = ResourceTypes, hide_possible_values = true)]
+        resource: String,
+        /// Resource name
+        name: String,
+        /// Labels to set, as key=value, or to remove, as key-
+        #[arg(required = true, value_parser = crate::commands::label::parse_label)]
+        labels: Vec<crate::commands::label::LabelChange>,
+        /// Namespace (default: "default")
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
     },
     /// Delete a resource (by type/id or from a manifest file)
     Delete {
         /// Resource type (e.g. pods, deployments)
+        #[arg(value_parser = ResourceTypes, hide_possible_values = true)]
         resource: Option<String>,
         /// Resource ID or name
         id: Option<String>,
@@ -74,9 +145,18 @@ pub enum Commands {
         /// Namespace
         #[arg(short, long, default_value = "default")]
         namespace: String,
-        /// Follow log output (poll every 2s)
+        /// Follow log output, streaming new lines as they are written
         #[arg(short, long, default_value_t = false)]
         follow: bool,
+        /// Only show lines written within this duration (e.g. 30s, 5m, 1h30m)
+        #[arg(long, value_parser = crate::commands::logs::parse_duration)]
+        since: Option<chrono::Duration>,
+        /// Prefix each line with the RFC3339 time it was written
+        #[arg(long, default_value_t = false)]
+        timestamps: bool,
+        /// Container to read logs from (defaults to the pod's first container)
+        #[arg(short, long)]
+        container: Option<String>,
     },
     /// Execute a command in a pod
     Exec {
@@ -88,20 +168,98 @@ pub enum Commands {
         /// Namespace
         #[arg(short, long, default_value = "default")]
         namespace: String,
-        /// Keep stdin open and allocate an interactive session
-        #[arg(short = 'i', long = "it", default_value_t = false)]
-        interactive: bool,
+        /// Pass local stdin to the command
+        #[arg(short = 'i', long, alias = "it", default_value_t = false)]
+        stdin: bool,
+        /// Allocate a TTY: raw local terminal, window size follows resizes
+        #[arg(short = 't', long, default_value_t = false)]
+        tty: bool,
+        /// Container to run the command in (defaults to the pod's first container)
+        #[arg(short, long)]
+        container: Option<String>,
+    },
+    /// Copy files and directories between a pod and the local filesystem
+    Cp {
+        /// Source: a local path, or `<pod>:<absolute path>`
+        src: String,
+        /// Destination: a local path, or `<pod>:<absolute path>`
+        dest: String,
+        /// Namespace
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+        /// Container to copy from or to (defaults to the pod's first container)
+        #[arg(short, long)]
+        container: Option<String>,
+    },
+    /// Forward local ports to ports in a pod
+    PortForward {
+        /// Pod to forward to: `pod/<name>` or `<name>`
+        #[arg(value_parser = crate::commands::port_forward::parse_pod)]
+        pod: String,
+        /// Ports as `LOCAL:REMOTE`, `PORT` for the same port on both sides,
+        /// or `:REMOTE` for any free local port
+        #[arg(required = true, value_parser = crate::commands::port_forward::parse_port_mapping)]
+        ports: Vec<crate::commands::port_forward::PortMapping>,
+        /// Namespace
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+    },
+    /// Set the replica count of a deployment or replicaset
+    Scale {
+        /// Resource type (deployment, replicaset)
+        resource: String,
+        /// Resource name
+        name: String,
+        /// Desired number of replicas
+        #[arg(long)]
+        replicas: u32,
+        /// Only scale if the resource currently wants this many replicas
+        #[arg(long)]
+        current_replicas: Option<u32>,
+        /// Namespace
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+    },
+    /// Show CPU and memory usage of pods or nodes
+    Top {
+        #[command(subcommand)]
+        action: TopAction,
+    },
+    /// Manage deployment rollouts
+    Rollout {
+        #[command(subcommand)]
+        action: RolloutAction,
     },
     /// Manage container runtime
     Runtime {
         #[command(subcommand)]
         action: RuntimeAction,
     },
+    /// Manage API tokens
+    Token {
+        #[command(subcommand)]
+        action: TokenAction,
+    },
+    /// Inspect and tune a running server or agent
+    Debug {
+        #[command(subcommand)]
+        action: DebugAction,
+    },
     /// Cluster backup management
     Backup {
         #[command(subcommand)]
         action: BackupAction,
     },
+    /// Manage contexts: named servers and credentials in ~/.k3rs/config.yaml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Print a shell completion script, e.g. `source <(k3rsctl completion bash)`
+    Completion {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
     /// Local process manager (pm2-style)
     Pm {
         #[command(subcommand)]
@@ -127,20 +285,65 @@ pub enum Commands {
     },
 }
 
+/// How far `apply --dry-run` goes before stopping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum DryRun {
+    /// Submit to the server, which admits the object but does not store it.
+    Server,
+}
+
+/// Patch formats `patch --type` accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum PatchType {
+    /// JSON Merge Patch (RFC 7386): objects merge, `null` removes a key,
+    /// arrays are replaced whole.
+    Merge,
+}
+
 #[derive(Subcommand)]
 pub enum ClusterAction {
     /// Display cluster info
     Info,
 }
 
+#[derive(Subcommand)]
+pub enum TopAction {
+    /// Usage of each pod, busiest first
+    Pods {
+        /// Namespace
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+    },
+    /// Usage of each node, busiest first
+    Nodes,
+}
+
 #[derive(Subcommand)]
 pub enum NodeAction {
     /// List all registered nodes
-    List,
-    /// Drain a node (cordon + evict pods)
+    List {
+        /// Output format: wide adds usage, container count, agent version and
+        /// pressure conditions
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Order the table by this column (e.g. name, status, age)
+        #[arg(long)]
+        sort_by: Option<String>,
+    },
+    /// Drain a node: cordon it, evict its pods (DaemonSet pods stay) and
+    /// wait for them to stop
     Drain {
         /// Node name
         name: String,
+        /// Seconds to wait for the evicted pods (server default: 300)
+        #[arg(long)]
+        timeout: Option<u64>,
+        /// Also wait until ReplicaSet pods are running again on other nodes
+        #[arg(long)]
+        wait_for_reschedule: bool,
+        /// Evict pods even when a PodDisruptionBudget does not allow it
+        #[arg(long)]
+        force: bool,
     },
     /// Mark a node as unschedulable
     Cordon {
@@ -152,6 +355,52 @@ pub enum NodeAction {
         /// Node name
         name: String,
     },
+    /// Add or remove node taints
+    Taint {
+        /// Node name
+        name: String,
+        /// Taints to add, as key=value:Effect or key:Effect, or to remove, as
+        /// key:Effect- or key- (every effect)
+        #[arg(required = true, value_parser = crate::commands::node::parse_taint)]
+        taints: Vec<crate::commands::node::TaintChange>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RolloutAction {
+    /// List the revisions a deployment can be rolled back to
+    History {
+        /// Deployment, as deployment/<name>
+        #[arg(value_parser = crate::commands::rollout::parse_deployment)]
+        deployment: String,
+        /// Namespace
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+    },
+    /// Roll a deployment back to an earlier revision
+    Undo {
+        /// Deployment, as deployment/<name>
+        #[arg(value_parser = crate::commands::rollout::parse_deployment)]
+        deployment: String,
+        /// Revision to restore (default: the previous one)
+        #[arg(long)]
+        to_revision: Option<u64>,
+        /// Namespace
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+    },
+    /// Wait until a deployment's rollout completes or fails
+    Status {
+        /// Deployment, as deployment/<name>
+        #[arg(value_parser = crate::commands::rollout::parse_deployment)]
+        deployment: String,
+        /// Namespace
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+        /// Give up after this long (e.g. 30s, 5m)
+        #[arg(long, default_value = "5m", value_parser = crate::commands::logs::parse_duration)]
+        timeout: chrono::Duration,
+    },
 }
 
 #[derive(Subcommand)]
@@ -168,6 +417,40 @@ pub enum RuntimeAction {
     },
 }
 
+#[derive(Subcommand)]
+pub enum TokenAction {
+    /// Mint a token bound to a role; the token is shown only once
+    Create {
+        /// Role: admin, readonly, editor or node
+        #[arg(long)]
+        role: pkg_types::rbac::TokenRole,
+        /// Confine the token to a namespace (required for editor)
+        #[arg(short, long)]
+        namespace: Option<String>,
+        /// Name to list the token under (default: its id)
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// List tokens
+    List,
+    /// Revoke a token
+    Delete {
+        /// Token id
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DebugAction {
+    /// Show or change the log filter of the server or a node's agent
+    LogLevel {
+        /// `server`, or the name of a node
+        target: String,
+        /// New filter in `RUST_LOG` syntax, e.g. `pkg_container=debug,info`
+        filter: Option<String>,
+    },
+}
+
 #[derive(Subcommand)]
 pub enum BackupAction {
     /// Create a backup and save it to a local file
@@ -190,3 +473,79 @@ pub enum BackupAction {
     /// Show server-side backup status
     Status,
 }
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Create a context, or change the given fields of an existing one
+    SetContext {
+        /// Context name
+        name: String,
+        /// Server API endpoint
+        #[arg(long)]
+        server: Option<String>,
+        /// Authentication token
+        #[arg(long)]
+        token: Option<String>,
+        /// Namespace for commands run without `-n`
+        #[arg(long)]
+        namespace: Option<String>,
+        /// PEM file of the CA that signed the server's certificate
+        #[arg(long)]
+        ca_cert: Option<String>,
+    },
+    /// Switch to another context
+    UseContext {
+        /// Context name
+        name: String,
+    },
+    /// List the contexts (tokens are not shown)
+    GetContexts,
+    /// Print the name of the current context
+    CurrentContext,
+}
+
+/// Value parser for resource type arguments: it accepts any name, which the
+/// command checks, and offers the known types to shell completion.
+#[derive(Clone)]
+pub struct ResourceTypes;
+
+impl TypedValueParser for ResourceTypes {
+    type Value = String;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<String, clap::Error> {
+        StringValueParser::new().parse_ref(cmd, arg, value)
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            crate::commands::get::RESOURCE_TYPES
+                .iter()
+                .map(|&name| PossibleValue::new(name)),
+        ))
+    }
+}
+
+/// Make `namespace` the default of every `-n/--namespace` in `cmd` and its
+/// subcommands that defaults to `default`, for a context with a namespace
+/// set. Ones without a default (e.g. a token's scope) stay unset.
+pub fn with_default_namespace(mut cmd: clap::Command, namespace: &'static str) -> clap::Command {
+    if cmd
+        .get_arguments()
+        .any(|a| a.get_id() == "namespace" && !a.get_default_values().is_empty())
+    {
+        cmd = cmd.mut_arg("namespace", |a| a.default_value(namespace));
+    }
+    let subcommands: Vec<String> = cmd
+        .get_subcommands()
+        .map(|s| s.get_name().to_string())
+        .collect();
+    for name in subcommands {
+        cmd = cmd.mut_subcommand(name, |sub| with_default_namespace(sub, namespace));
+    }
+    cmd
+}
@@ -3,18 +3,25 @@ use pkg_types::daemonset::DaemonSet;
 use pkg_types::deployment::Deployment;
 use pkg_types::hpa::HorizontalPodAutoscaler;
 use pkg_types::job::{CronJob, Job};
+use pkg_types::limit_range::LimitRange;
 use pkg_types::namespace::Namespace;
+use pkg_types::pdb::PodDisruptionBudget;
 use pkg_types::pod::Pod;
+use pkg_types::priority_class::PriorityClass;
 use pkg_types::replicaset::ReplicaSet;
 use pkg_types::secret::Secret;
 use pkg_types::service::Service;
+use serde::Serialize;
 use tracing::info;
 
+use crate::cli::DryRun;
+
 pub async fn handle(
     client: &reqwest::Client,
     base: &str,
     file: &str,
     namespace: &str,
+    dry_run: Option<DryRun>,
 ) -> anyhow::Result<()> {
     info!("Applying manifest from {}", file);
     let content = tokio::fs::read_to_string(file).await?;
@@ -22,128 +29,86 @@ pub async fn handle(
     // Parse as a generic YAML value to detect the kind
     let value: serde_yaml::Value = serde_yaml::from_str(&content)?;
     let kind = value.get("kind").and_then(|v| v.as_str()).unwrap_or("Pod");
+    let ns_base = format!("{}/api/v1/namespaces/{}", base, namespace);
 
     match kind {
         "Pod" => {
             let pod: Pod = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/pods", base, namespace);
-            let resp = client.post(&url).json(&pod).send().await?;
-            if resp.status().is_success() {
-                let created: Pod = resp.json().await?;
-                println!("pod/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/pods/{}", ns_base, pod.name);
+            apply(client, &url, dry_run, "pod", &pod.name, &pod).await?;
         }
         "Namespace" => {
             let ns: Namespace = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces", base);
-            let resp = client.post(&url).json(&ns).send().await?;
-            if resp.status().is_success() {
-                let created: Namespace = resp.json().await?;
-                println!("namespace/{} created", created.name);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/api/v1/namespaces/{}", base, ns.name);
+            apply(client, &url, dry_run, "namespace", &ns.name, &ns).await?;
         }
         "Service" => {
             let svc: Service = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/services", base, namespace);
-            let resp = client.post(&url).json(&svc).send().await?;
-            if resp.status().is_success() {
-                let created: Service = resp.json().await?;
-                println!("service/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/services/{}", ns_base, svc.name);
+            apply(client, &url, dry_run, "service", &svc.name, &svc).await?;
         }
         "Deployment" => {
             let deploy: Deployment = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/deployments", base, namespace);
-            let resp = client.post(&url).json(&deploy).send().await?;
-            if resp.status().is_success() {
-                let created: Deployment = resp.json().await?;
-                println!("deployment/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/deployments/{}", ns_base, deploy.name);
+            apply(client, &url, dry_run, "deployment", &deploy.name, &deploy).await?;
         }
         "ReplicaSet" => {
             let rs: ReplicaSet = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/replicasets", base, namespace);
-            let resp = client.post(&url).json(&rs).send().await?;
-            if resp.status().is_success() {
-                let created: ReplicaSet = resp.json().await?;
-                println!("replicaset/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/replicasets/{}", ns_base, rs.name);
+            apply(client, &url, dry_run, "replicaset", &rs.name, &rs).await?;
         }
         "DaemonSet" => {
             let ds: DaemonSet = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/daemonsets", base, namespace);
-            let resp = client.post(&url).json(&ds).send().await?;
-            if resp.status().is_success() {
-                let created: DaemonSet = resp.json().await?;
-                println!("daemonset/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/daemonsets/{}", ns_base, ds.name);
+            apply(client, &url, dry_run, "daemonset", &ds.name, &ds).await?;
         }
         "Job" => {
             let job: Job = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/jobs", base, namespace);
-            let resp = client.post(&url).json(&job).send().await?;
-            if resp.status().is_success() {
-                let created: Job = resp.json().await?;
-                println!("job/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/jobs/{}", ns_base, job.name);
+            apply(client, &url, dry_run, "job", &job.name, &job).await?;
         }
         "CronJob" => {
             let cj: CronJob = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/cronjobs", base, namespace);
-            let resp = client.post(&url).json(&cj).send().await?;
-            if resp.status().is_success() {
-                let created: CronJob = resp.json().await?;
-                println!("cronjob/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/cronjobs/{}", ns_base, cj.name);
+            apply(client, &url, dry_run, "cronjob", &cj.name, &cj).await?;
         }
         "HorizontalPodAutoscaler" => {
             let hpa: HorizontalPodAutoscaler = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/hpa", base, namespace);
-            let resp = client.post(&url).json(&hpa).send().await?;
-            if resp.status().is_success() {
-                let created: HorizontalPodAutoscaler = resp.json().await?;
-                println!("hpa/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/hpa/{}", ns_base, hpa.name);
+            apply(client, &url, dry_run, "hpa", &hpa.name, &hpa).await?;
         }
         "ConfigMap" => {
             let cm: ConfigMap = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/configmaps", base, namespace);
-            let resp = client.post(&url).json(&cm).send().await?;
-            if resp.status().is_success() {
-                let created: ConfigMap = resp.json().await?;
-                println!("configmap/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/configmaps/{}", ns_base, cm.name);
+            apply(client, &url, dry_run, "configmap", &cm.name, &cm).await?;
         }
         "Secret" => {
             let secret: Secret = serde_yaml::from_str(&content)?;
-            let url = format!("{}/api/v1/namespaces/{}/secrets", base, namespace);
-            let resp = client.post(&url).json(&secret).send().await?;
-            if resp.status().is_success() {
-                let created: Secret = resp.json().await?;
-                println!("secret/{} created (id={})", created.name, created.id);
-            } else {
-                eprintln!("Failed to apply: {}", resp.status());
-            }
+            let url = format!("{}/secrets/{}", ns_base, secret.name);
+            apply(client, &url, dry_run, "secret", &secret.name, &secret).await?;
+        }
+        "LimitRange" => {
+            let range: LimitRange = serde_yaml::from_str(&content)?;
+            let url = format!("{}/limitranges/{}", ns_base, range.name);
+            apply(client, &url, dry_run, "limitrange", &range.name, &range).await?;
+        }
+        "PodDisruptionBudget" => {
+            let pdb: PodDisruptionBudget = serde_yaml::from_str(&content)?;
+            let url = format!("{}/poddisruptionbudgets/{}", ns_base, pdb.name);
+            apply(
+                client,
+                &url,
+                dry_run,
+                "poddisruptionbudget",
+                &pdb.name,
+                &pdb,
+            )
+            .await?;
+        }
+        "PriorityClass" => {
+            let pc: PriorityClass = serde_yaml::from_str(&content)?;
+            let url = format!("{}/api/v1/priorityclasses/{}", base, pc.name);
+            apply(client, &url, dry_run, "priorityclass", &pc.name, &pc).await?;
         }
         other => {
             eprintln!("Unsupported resource kind: {}", other);
@@ -152,3 +117,52 @@ pub async fn handle(
     }
     Ok(())
 }
+
+/// PUT a resource to its named endpoint. The server creates it if missing
+/// and otherwise updates it in place, so applying a manifest is idempotent.
+/// Updates carry the resource version of the stored object, which is re-read
+/// if another writer changed it in between. A server dry run is admitted the
+/// same way but not stored; the object the server would have stored is
+/// printed instead.
+async fn apply<T: Serialize>(
+    client: &reqwest::Client,
+    url: &str,
+    dry_run: Option<DryRun>,
+    kind: &str,
+    name: &str,
+    resource: &T,
+) -> anyhow::Result<()> {
+    let mut body = serde_json::to_value(resource)?;
+    let mut version = super::current_resource_version(client, url).await?;
+    let (put_url, suffix) = match dry_run {
+        Some(DryRun::Server) => (format!("{}?dryRun=All", url), " (server dry run)"),
+        None => (url.to_string(), ""),
+    };
+    for attempt in 1..=super::MAX_UPDATE_ATTEMPTS {
+        body["resource_version"] = version.into();
+        let resp = client.put(&put_url).json(&body).send().await?;
+        let status = resp.status();
+        if status.is_success() {
+            let action = if status == reqwest::StatusCode::CREATED {
+                "created"
+            } else {
+                "configured"
+            };
+            println!("{}/{} {}{}", kind, name, action, suffix);
+            if dry_run.is_some() {
+                let obj: serde_json::Value = resp.json().await?;
+                print!("{}", serde_yaml::to_string(&obj)?);
+            }
+            return Ok(());
+        }
+        let err = super::api_error(resp).await;
+        match super::conflict_version(&err) {
+            Some(current) if attempt < super::MAX_UPDATE_ATTEMPTS => version = current,
+            _ => {
+                eprintln!("{}", super::render_error(&err));
+                break;
+            }
+        }
+    }
+    Ok(())
+}
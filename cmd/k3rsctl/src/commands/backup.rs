@@ -1,9 +1,11 @@
+use super::table::{Table, TableOptions};
 use crate::cli::BackupAction;
 
 pub async fn handle_backup(
     client: &reqwest::Client,
     base: &str,
     action: &BackupAction,
+    table_options: &TableOptions,
 ) -> anyhow::Result<()> {
     match action {
         BackupAction::Create { output } => {
@@ -11,10 +13,7 @@ pub async fn handle_backup(
             println!("Requesting backup from server...");
             let resp = client.post(&url).send().await?;
             if !resp.status().is_success() {
-                eprintln!("Backup failed: server returned {}", resp.status());
-                if let Ok(text) = resp.text().await {
-                    eprintln!("  {}", text);
-                }
+                super::print_api_error(resp).await;
                 std::process::exit(1);
             }
 
@@ -53,11 +52,12 @@ pub async fn handle_backup(
             if files.is_empty() {
                 println!("No backup files found.");
             } else {
-                println!("{:<60} SIZE", "FILE");
+                let mut table = Table::new(&["FILE", "SIZE"]).numeric(&["SIZE"]);
                 for f in &files {
                     let size = tokio::fs::metadata(f).await.map(|m| m.len()).unwrap_or(0);
-                    println!("{:<60} {} bytes", f, size);
+                    table.push(vec![f.clone(), format!("{} bytes", size)]);
                 }
+                table.print(table_options)?;
             }
         }
         BackupAction::Inspect { file } => {
@@ -94,7 +94,7 @@ pub async fn handle_backup(
             let url = format!("{}/api/v1/cluster/backup/status", base);
             let resp = client.get(&url).send().await?;
             if !resp.status().is_success() {
-                eprintln!("Failed to get backup status: {}", resp.status());
+                super::print_api_error(resp).await;
                 std::process::exit(1);
             }
             let status: serde_json::Value = resp.json().await?;
@@ -162,42 +162,36 @@ pub async fn handle_restore(
         .send()
         .await?;
 
-    let status = resp.status();
+    if !resp.status().is_success() {
+        super::print_api_error(resp).await;
+        std::process::exit(1);
+    }
     let result: serde_json::Value = resp.json().await?;
 
-    if status.is_success() {
-        if dry_run {
-            println!("Dry-run validation passed:");
-            println!(
-                "  Entries to restore: {}",
-                result["would_restore"].as_u64().unwrap_or(0)
-            );
-            println!(
-                "  Backup created at:  {}",
-                result["backup_created_at"].as_str().unwrap_or("-")
-            );
-            println!(
-                "  Backup version:     {}",
-                result["backup_version"].as_str().unwrap_or("-")
-            );
-        } else {
-            println!("Restore completed:");
-            println!(
-                "  Entries imported:   {}",
-                result["imported"].as_u64().unwrap_or(0)
-            );
-            println!(
-                "  Backup created at:  {}",
-                result["backup_created_at"].as_str().unwrap_or("-")
-            );
-        }
+    if dry_run {
+        println!("Dry-run validation passed:");
+        println!(
+            "  Entries to restore: {}",
+            result["would_restore"].as_u64().unwrap_or(0)
+        );
+        println!(
+            "  Backup created at:  {}",
+            result["backup_created_at"].as_str().unwrap_or("-")
+        );
+        println!(
+            "  Backup version:     {}",
+            result["backup_version"].as_str().unwrap_or("-")
+        );
     } else {
-        eprintln!(
-            "Restore failed ({}): {}",
-            status,
-            result["error"].as_str().unwrap_or("unknown error")
+        println!("Restore completed:");
+        println!(
+            "  Entries imported:   {}",
+            result["imported"].as_u64().unwrap_or(0)
+        );
+        println!(
+            "  Backup created at:  {}",
+            result["backup_created_at"].as_str().unwrap_or("-")
         );
-        std::process::exit(1);
     }
     Ok(())
 }
@@ -11,13 +11,16 @@ pub async fn handle(
             let url = format!("{}/api/v1/cluster/info", base);
             let resp = client.get(&url).send().await?;
             if !resp.status().is_success() {
-                eprintln!("Error: server returned {}", resp.status());
+                super::print_api_error(resp).await;
                 std::process::exit(1);
             }
             let info: ClusterInfo = resp.json().await?;
             println!("Cluster Endpoint:  {}", info.endpoint);
             println!("Version:           {}", info.version);
             println!("State Store:       {}", info.state_store);
+            if let Some(location) = &info.state_store_location {
+                println!("Store Location:    {}", location);
+            }
             println!("Nodes:             {}", info.node_count);
         }
     }
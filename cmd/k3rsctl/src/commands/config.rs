@@ -0,0 +1,65 @@
+use std::path::Path;
+
+use super::table::{Table, TableOptions};
+use crate::cli::ConfigAction;
+use crate::config::{Config, Context};
+
+/// Read or change the contexts in the config file at `path`. Runs without
+/// a server.
+pub fn handle(
+    action: &ConfigAction,
+    path: &Path,
+    table_options: &TableOptions,
+) -> anyhow::Result<()> {
+    let mut config = Config::load(path)?;
+    match action {
+        ConfigAction::SetContext {
+            name,
+            server,
+            token,
+            namespace,
+            ca_cert,
+        } => {
+            let created = config.context(name).is_none();
+            config.set_context(Context {
+                name: name.clone(),
+                server: server.clone(),
+                token: token.clone(),
+                namespace: namespace.clone(),
+                ca_cert: ca_cert.clone(),
+            });
+            config.save(path)?;
+            let done = if created { "created" } else { "modified" };
+            println!("Context \"{}\" {}", name, done);
+        }
+        ConfigAction::UseContext { name } => {
+            config.use_context(name)?;
+            config.save(path)?;
+            println!("Switched to context \"{}\"", name);
+        }
+        ConfigAction::GetContexts => {
+            let mut table = Table::new(&["CURRENT", "NAME", "SERVER", "NAMESPACE", "CA-CERT"]);
+            let or_dash = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+            for context in &config.contexts {
+                let current = config.current_context.as_deref() == Some(context.name.as_str());
+                table.push(vec![
+                    if current { "*" } else { "" }.to_string(),
+                    context.name.clone(),
+                    or_dash(&context.server),
+                    or_dash(&context.namespace),
+                    or_dash(&context.ca_cert),
+                ]);
+            }
+            let is_empty = table.is_empty();
+            table.print(table_options)?;
+            if is_empty {
+                println!("(no contexts in {})", path.display());
+            }
+        }
+        ConfigAction::CurrentContext => match &config.current_context {
+            Some(name) => println!("{}", name),
+            None => anyhow::bail!("current context is not set"),
+        },
+    }
+    Ok(())
+}
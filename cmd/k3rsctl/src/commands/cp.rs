@@ -0,0 +1,404 @@
+//! `k3rsctl cp` — copy files between a pod's container and the local machine.
+//!
+//! Transfers are tar streams through the server's pod archive endpoint,
+//! which runs `tar` inside the container. Each archive has a single
+//! top-level entry: on the way out it is renamed to the local destination,
+//! on the way in it is named after the last component of the container
+//! path, so that path is exactly where the copy lands. File modes are kept
+//! and symlinks are copied as links, never followed.
+
+use std::io::{BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, bail};
+
+/// Size of the chunks handed between the network and the tar codec.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// One side of a copy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpTarget {
+    Local(PathBuf),
+    Pod { pod: String, path: String },
+}
+
+impl CpTarget {
+    /// `<pod>:<path>` names a path in a pod; anything else is local. A
+    /// local path containing `:` can be given as `./name:with:colons`.
+    pub fn parse(arg: &str) -> anyhow::Result<Self> {
+        match arg.split_once(':') {
+            Some((pod, path)) if !pod.is_empty() && !pod.contains('/') => {
+                if !path.starts_with('/') {
+                    bail!("path in {} must be absolute", arg);
+                }
+                Ok(Self::Pod {
+                    pod: pod.to_string(),
+                    path: path.to_string(),
+                })
+            }
+            _ => Ok(Self::Local(PathBuf::from(arg))),
+        }
+    }
+}
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    src: &str,
+    dest: &str,
+    namespace: &str,
+    container: Option<&str>,
+) -> anyhow::Result<()> {
+    let archive_url = |pod: &str, path: &str| {
+        let mut params = vec![("path", path)];
+        if let Some(container) = container {
+            params.push(("container", container));
+        }
+        reqwest::Url::parse_with_params(
+            &format!(
+                "{}/api/v1/namespaces/{}/pods/{}/archive",
+                base, namespace, pod
+            ),
+            &params,
+        )
+    };
+
+    let bytes = match (CpTarget::parse(src)?, CpTarget::parse(dest)?) {
+        (CpTarget::Pod { pod, path }, CpTarget::Local(local)) => {
+            // Like `cp`, copying onto an existing directory lands inside it.
+            let local = if local.is_dir() {
+                local.join(Path::new(&path).file_name().unwrap_or_default())
+            } else {
+                local
+            };
+            download(client, archive_url(&pod, &path)?, local).await?
+        }
+        (CpTarget::Local(local), CpTarget::Pod { pod, path }) => {
+            let name = Path::new(&path)
+                .file_name()
+                .with_context(|| format!("cannot copy onto {}", path))?
+                .to_string_lossy()
+                .into_owned();
+            std::fs::symlink_metadata(&local)
+                .with_context(|| format!("cannot read {}", local.display()))?;
+            upload(client, archive_url(&pod, &path)?, local, name).await?
+        }
+        (CpTarget::Local(_), CpTarget::Local(_)) => {
+            bail!("one of source and destination must be <pod>:<path>")
+        }
+        (CpTarget::Pod { .. }, CpTarget::Pod { .. }) => {
+            bail!("copying directly between pods is not supported")
+        }
+    };
+    println!("{} -> {} ({} bytes)", src, dest, bytes);
+    Ok(())
+}
+
+/// Stream the archive at `url` into `dest`. Returns the archive size.
+async fn download(
+    client: &reqwest::Client,
+    url: reqwest::Url,
+    dest: PathBuf,
+) -> anyhow::Result<u64> {
+    let mut resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        bail!("{}", super::render_error(&super::api_error(resp).await));
+    }
+
+    let (tx, rx) = tokio::sync::mpsc::channel(16);
+    let unpacker = tokio::task::spawn_blocking(move || {
+        unpack(
+            ChunkReader {
+                rx,
+                chunk: Vec::new(),
+                pos: 0,
+            },
+            &dest,
+        )
+    });
+    let mut bytes = 0u64;
+    while let Some(chunk) = resp.chunk().await? {
+        bytes += chunk.len() as u64;
+        if tx.send(chunk.to_vec()).await.is_err() {
+            break;
+        }
+    }
+    drop(tx);
+    unpacker.await??;
+    Ok(bytes)
+}
+
+/// Stream `src` to `url` as an archive whose top entry is `name`. Returns
+/// the archive size the server reports.
+async fn upload(
+    client: &reqwest::Client,
+    url: reqwest::Url,
+    src: PathBuf,
+    name: String,
+) -> anyhow::Result<u64> {
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<std::io::Result<Vec<u8>>>(16);
+    let errors = tx.clone();
+    let packer = tokio::task::spawn_blocking(move || {
+        let result = pack(
+            &src,
+            &name,
+            BufWriter::with_capacity(CHUNK_SIZE, ChunkWriter { tx }),
+        )
+        .and_then(|mut writer| Ok(writer.flush()?));
+        if let Err(ref e) = result {
+            // Abort the request body so the server doesn't unpack a partial archive.
+            let _ = errors.blocking_send(Err(std::io::Error::other(e.to_string())));
+        }
+        result
+    });
+
+    let body = futures_util::stream::poll_fn(move |cx| rx.poll_recv(cx));
+    let sent = client
+        .put(url)
+        .body(reqwest::Body::wrap_stream(body))
+        .send()
+        .await;
+    packer.await??;
+    let resp = sent?;
+    if !resp.status().is_success() {
+        bail!("{}", super::render_error(&super::api_error(resp).await));
+    }
+    let reply: serde_json::Value = resp.json().await?;
+    Ok(reply["bytes"].as_u64().unwrap_or(0))
+}
+
+/// Write `src` (a file, directory or symlink) as a tar archive whose single
+/// top-level entry is `name`.
+fn pack<W: Write>(src: &Path, name: &str, writer: W) -> anyhow::Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    let meta = std::fs::symlink_metadata(src)?;
+    if meta.is_dir() {
+        builder.append_dir_all(name, src)?;
+    } else {
+        builder.append_path_with_name(src, name)?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Unpack an archive with a single top-level entry at `dest`, keeping file
+/// modes. Entries that would land outside `dest`, through `..` or through a
+/// symlink unpacked earlier, are refused.
+fn unpack<R: Read>(reader: R, dest: &Path) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    archive.set_preserve_permissions(true);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let target = match below_top_entry(&path)? {
+            None => dest.to_path_buf(),
+            Some(rest) => {
+                std::fs::create_dir_all(dest)?;
+                let target = dest.join(rest);
+                // Check before creating anything: the closest directory that
+                // already exists must resolve to somewhere inside `dest`.
+                let existing = target
+                    .ancestors()
+                    .skip(1)
+                    .find(|p| p.exists())
+                    .unwrap_or(dest);
+                if !existing.canonicalize()?.starts_with(dest.canonicalize()?) {
+                    bail!(
+                        "archive entry {} escapes {}",
+                        path.display(),
+                        dest.display()
+                    );
+                }
+                target
+            }
+        };
+        if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry
+            .unpack(&target)
+            .with_context(|| format!("unpacking {}", target.display()))?;
+    }
+    Ok(())
+}
+
+/// The part of an entry path below its top-level component; `None` for the
+/// top-level entry itself.
+fn below_top_entry(path: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let mut parts = path.components().filter(|c| *c != Component::CurDir);
+    if !matches!(parts.next(), Some(Component::Normal(_))) {
+        bail!("unexpected archive entry {}", path.display());
+    }
+    let mut rest = PathBuf::new();
+    for part in parts {
+        let Component::Normal(part) = part else {
+            bail!("unexpected archive entry {}", path.display());
+        };
+        rest.push(part);
+    }
+    Ok((!rest.as_os_str().is_empty()).then_some(rest))
+}
+
+/// Blocking reader over chunks sent from async code.
+struct ChunkReader {
+    rx: tokio::sync::mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Blocking writer handing chunks to async code.
+struct ChunkWriter {
+    tx: tokio::sync::mpsc::Sender<std::io::Result<Vec<u8>>>,
+}
+
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(buf.to_vec()))
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+    use pkg_container::test_util::temp_dir;
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(
+            CpTarget::parse("web-0:/etc/nginx").unwrap(),
+            CpTarget::Pod {
+                pod: "web-0".into(),
+                path: "/etc/nginx".into()
+            }
+        );
+        assert_eq!(
+            CpTarget::parse("./out").unwrap(),
+            CpTarget::Local("./out".into())
+        );
+        assert_eq!(
+            CpTarget::parse("./a:b").unwrap(),
+            CpTarget::Local("./a:b".into())
+        );
+        assert!(CpTarget::parse("web-0:etc/nginx").is_err());
+    }
+
+    #[test]
+    fn test_cp_args_parse() {
+        let cli =
+            Cli::try_parse_from(["k3rsctl", "cp", "web-0:/data", "./data", "-c", "app"]).unwrap();
+        match cli.command {
+            Commands::Cp {
+                src,
+                dest,
+                namespace,
+                container,
+            } => {
+                assert_eq!(src, "web-0:/data");
+                assert_eq!(dest, "./data");
+                assert_eq!(namespace, "default");
+                assert_eq!(container.as_deref(), Some("app"));
+            }
+            _ => panic!("expected cp command"),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_binary_file_and_symlink() {
+        let src = temp_dir("src").join("site");
+        std::fs::create_dir_all(src.join("assets")).unwrap();
+        let binary: Vec<u8> = (0..=255u8).cycle().take(300_000).collect();
+        std::fs::write(src.join("assets/logo.bin"), &binary).unwrap();
+        std::fs::set_permissions(
+            src.join("assets/logo.bin"),
+            PermissionsExt::from_mode(0o640),
+        )
+        .unwrap();
+        symlink("assets/logo.bin", src.join("current")).unwrap();
+
+        // Archives are named after the container path, not the local one.
+        let archive = pack(&src, "html", Vec::new()).unwrap();
+        let dest = temp_dir("dest").join("copy");
+        unpack(archive.as_slice(), &dest).unwrap();
+
+        assert_eq!(std::fs::read(dest.join("assets/logo.bin")).unwrap(), binary);
+        let mode = std::fs::metadata(dest.join("assets/logo.bin"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o640);
+        assert_eq!(
+            std::fs::read_link(dest.join("current")).unwrap(),
+            Path::new("assets/logo.bin")
+        );
+
+        // A single file lands at the destination path itself.
+        let archive = pack(&src.join("assets/logo.bin"), "logo.bin", Vec::new()).unwrap();
+        let file = dest.parent().unwrap().join("logo-copy.bin");
+        unpack(archive.as_slice(), &file).unwrap();
+        assert_eq!(std::fs::read(&file).unwrap(), binary);
+
+        let _ = std::fs::remove_dir_all(src.parent().unwrap());
+        let _ = std::fs::remove_dir_all(dest.parent().unwrap());
+    }
+
+    #[test]
+    fn test_unpack_refuses_entries_through_symlinks() {
+        let dir = temp_dir("escape");
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        builder
+            .append_data(&mut header, "top", std::io::empty())
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        builder.append_link(&mut header, "top/up", "..").unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o644);
+        header.set_size(4);
+        builder
+            .append_data(&mut header, "top/up/sub/evil", &b"evil"[..])
+            .unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let dest = dir.join("dest");
+        let err = unpack(archive.as_slice(), &dest).unwrap_err();
+        assert!(err.to_string().contains("escapes"), "{}", err);
+        assert!(!dir.join("sub").exists());
+
+        assert!(below_top_entry(Path::new("top/../x")).is_err());
+        assert_eq!(below_top_entry(Path::new("./top")).unwrap(), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
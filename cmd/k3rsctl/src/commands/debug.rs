@@ -0,0 +1,41 @@
+use crate::cli::DebugAction;
+use pkg_types::config::LogLevel;
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    action: &DebugAction,
+) -> anyhow::Result<()> {
+    match action {
+        DebugAction::LogLevel { target, filter } => {
+            let url = log_level_url(base, target);
+            let req = match filter {
+                Some(filter) => client.put(&url).json(&LogLevel {
+                    filter: filter.clone(),
+                }),
+                None => client.get(&url),
+            };
+            let resp = req.send().await?;
+            if !resp.status().is_success() {
+                super::print_api_error(resp).await;
+                std::process::exit(1);
+            }
+            let level: LogLevel = resp.json().await?;
+            if filter.is_some() {
+                println!("{} log filter set to {}", target, level.filter);
+            } else {
+                println!("{}", level.filter);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The log level endpoint of the server, or of a node's agent.
+fn log_level_url(base: &str, target: &str) -> String {
+    if target == "server" {
+        format!("{}/api/v1/debug/loglevel", base)
+    } else {
+        format!("{}/api/v1/nodes/{}/debug/loglevel", base, target)
+    }
+}
@@ -64,6 +64,8 @@ pub async fn handle(
                 "HorizontalPodAutoscaler" => "hpa",
                 "Namespace" => "namespaces",
                 "ResourceQuota" => "resourcequotas",
+                "LimitRange" => "limitranges",
+                "PodDisruptionBudget" => "poddisruptionbudgets",
                 "NetworkPolicy" => "networkpolicies",
                 "PersistentVolumeClaim" => "pvcs",
                 other => {
@@ -72,9 +74,11 @@ pub async fn handle(
                 }
             };
 
-            // Use the specific pod delete endpoint, or the generic one
+            // Use the specific pod/namespace delete endpoint, or the generic one
             let url = if resource_type == "pods" {
                 format!("{}/api/v1/namespaces/{}/pods/{}", base, ns, name)
+            } else if resource_type == "namespaces" {
+                format!("{}/api/v1/namespaces/{}", base, name)
             } else {
                 format!("{}/api/v1/{}/{}/{}", base, resource_type, ns, name)
             };
@@ -84,12 +88,7 @@ pub async fn handle(
                 println!("{}/{} deleted", kind.to_lowercase(), name);
                 deleted += 1;
             } else {
-                eprintln!(
-                    "Failed to delete {}/{}: {}",
-                    kind.to_lowercase(),
-                    name,
-                    resp.status()
-                );
+                super::print_api_error(resp).await;
             }
         }
         if deleted == 0 {
@@ -97,12 +96,17 @@ pub async fn handle(
         }
     } else if let (Some(resource), Some(id)) = (resource, id) {
         // Positional args: delete <resource> <id>
-        let url = format!("{}/api/v1/{}/{}/{}", base, resource, namespace, id);
+        let url = match resource {
+            "namespaces" | "namespace" | "ns" => format!("{}/api/v1/namespaces/{}", base, id),
+            _ => format!("{}/api/v1/{}/{}/{}", base, resource, namespace, id),
+        };
         let resp = client.delete(&url).send().await?;
-        if resp.status().is_success() {
+        if resp.status() == reqwest::StatusCode::ACCEPTED {
+            println!("{}/{} terminating", resource, id);
+        } else if resp.status().is_success() {
             println!("{}/{} deleted", resource, id);
         } else {
-            eprintln!("Failed to delete: {}", resp.status());
+            super::print_api_error(resp).await;
         }
     } else {
         eprintln!("Usage: k3rsctl delete <resource> <id> or k3rsctl delete -f <file>");
@@ -1,4 +1,13 @@
-use pkg_types::pod::Pod;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use pkg_types::deployment::{Deployment, DeploymentStrategy};
+use pkg_types::event::{CLUSTER_EVENT_NAMESPACE, Event};
+use pkg_types::node::Node;
+use pkg_types::pod::{ContainerSpec, ContainerState, ContainerStatus, Pod, Probe, ProbeHandler};
+use pkg_types::service::Service;
+
+use super::get::fetch_json;
 
 pub async fn handle(
     client: &reqwest::Client,
@@ -7,95 +16,552 @@ pub async fn handle(
     name: &str,
     namespace: &str,
 ) -> anyhow::Result<()> {
-    match resource {
-        "pod" | "pods" => describe_pod(client, base, name, namespace).await,
+    let (kind, url, event_ns) = match resource {
+        "pods" | "pod" | "po" => (
+            "pod",
+            format!("{}/api/v1/namespaces/{}/pods/{}", base, namespace, name),
+            namespace,
+        ),
+        "deployments" | "deployment" | "deploy" => (
+            "deployment",
+            format!(
+                "{}/api/v1/namespaces/{}/deployments/{}",
+                base, namespace, name
+            ),
+            namespace,
+        ),
+        "services" | "service" | "svc" => (
+            "service",
+            format!("{}/api/v1/namespaces/{}/services/{}", base, namespace, name),
+            namespace,
+        ),
+        "nodes" | "node" | "no" => (
+            "node",
+            format!("{}/api/v1/nodes/{}", base, name),
+            CLUSTER_EVENT_NAMESPACE,
+        ),
         other => {
             eprintln!(
-                "Unknown resource type for describe: {}. Supported: pod",
+                "Unknown resource type for describe: {}. Supported: pod, deployment, service, node",
                 other
             );
             std::process::exit(1);
         }
-    }
-}
+    };
 
-async fn describe_pod(
-    client: &reqwest::Client,
-    base: &str,
-    name: &str,
-    namespace: &str,
-) -> anyhow::Result<()> {
-    // Try by name first, then fall back to listing and matching
-    let url = format!("{}/api/v1/namespaces/{}/pods", base, namespace);
-    let resp = client.get(&url).send().await?;
-    let pods: Vec<Pod> = resp.json().await?;
+    let Some(value) = fetch_json(client, &url).await? else {
+        if kind == "node" {
+            eprintln!("Error: node \"{}\" not found", name);
+        } else {
+            eprintln!(
+                "Error: {} \"{}\" not found in namespace \"{}\"",
+                kind, name, namespace
+            );
+        }
+        std::process::exit(1);
+    };
 
-    let pod = pods.iter().find(|p| p.name == name || p.id == name);
+    let events_url = format!(
+        "{}/api/v1/namespaces/{}/events?involved={}/{}",
+        base, event_ns, kind, name
+    );
+    let events: Vec<Event> = match fetch_json(client, &events_url).await? {
+        Some(v) => serde_json::from_value(v)?,
+        None => Vec::new(),
+    };
 
-    let Some(pod) = pod else {
-        eprintln!("Pod '{}' not found in namespace '{}'", name, namespace);
-        std::process::exit(1);
+    let out = match kind {
+        "pod" => render_pod(&serde_json::from_value(value)?, &events),
+        "deployment" => render_deployment(&serde_json::from_value(value)?, &events),
+        "service" => render_service(&serde_json::from_value(value)?, &events),
+        _ => render_node(&serde_json::from_value(value)?, &events),
     };
+    print!("{}", out);
+    Ok(())
+}
 
-    println!("Name:         {}", pod.name);
-    println!("ID:           {}", pod.id);
-    println!("Namespace:    {}", pod.namespace);
-    println!("Status:       {}", pod.status);
-    if let Some(ref msg) = pod.status_message {
-        println!("Message:      {}", msg);
-    }
-    println!("Node:         {}", pod.node_name.as_deref().unwrap_or("-"));
-    println!(
+fn render_pod(pod: &Pod, events: &[Event]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Name:         {}", pod.name);
+    let _ = writeln!(out, "Namespace:    {}", pod.namespace);
+    let _ = writeln!(out, "ID:           {}", pod.id);
+    let _ = writeln!(
+        out,
+        "Node:         {}",
+        pod.node_name.as_deref().unwrap_or("<none>")
+    );
+    let _ = writeln!(
+        out,
         "Created:      {}",
         pod.created_at.format("%Y-%m-%d %H:%M:%S")
     );
+    write_map(&mut out, "Labels:", &pod.labels);
+    let _ = writeln!(
+        out,
+        "Owner:        {}",
+        pod.owner_ref.as_deref().unwrap_or("<none>")
+    );
 
-    // VPC info
-    println!();
-    println!(
-        "VPC Name:     {}",
+    let _ = writeln!(out, "Status:       {}", pod.status);
+    let _ = writeln!(out, "Ready:        {}", pod.is_ready());
+    let _ = writeln!(
+        out,
+        "Message:      {}",
+        pod.status_message.as_deref().unwrap_or("<none>")
+    );
+    let _ = writeln!(
+        out,
+        "Restarts:     {} (policy {})",
+        pod.restart_count, pod.spec.restart_policy
+    );
+    if let Some(ref cid) = pod.container_id {
+        let _ = writeln!(out, "Container ID: {}", cid);
+    }
+    if let Some(ref rt) = pod.runtime_info {
+        let _ = writeln!(out, "Runtime:      {} ({})", rt.backend, rt.version);
+    }
+    let _ = writeln!(
+        out,
+        "VPC:          {}",
         pod.vpc_name
             .as_deref()
             .or(pod.spec.vpc.as_deref())
             .unwrap_or("default")
     );
-    println!("Ghost IPv6:   {}", pod.ghost_ipv6.as_deref().unwrap_or("-"));
+    let _ = writeln!(
+        out,
+        "Ghost IPv6:   {}",
+        pod.ghost_ipv6.as_deref().unwrap_or("<none>")
+    );
 
-    // Container info
-    println!();
-    if let Some(ref cid) = pod.container_id {
-        println!("Container ID: {}", cid);
+    if !pod.spec.init_containers.is_empty() {
+        write_containers(&mut out, "Init Containers:", &pod.spec.init_containers, &[]);
     }
-    if let Some(ref rt) = pod.runtime_info {
-        println!("Runtime:      {} ({})", rt.backend, rt.version);
+    write_containers(
+        &mut out,
+        "Containers:",
+        &pod.spec.containers,
+        &pod.container_statuses,
+    );
+
+    write_events(&mut out, events);
+    out
+}
+
+fn render_deployment(deploy: &Deployment, events: &[Event]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Name:         {}", deploy.name);
+    let _ = writeln!(out, "Namespace:    {}", deploy.namespace);
+    let _ = writeln!(out, "ID:           {}", deploy.id);
+    let _ = writeln!(
+        out,
+        "Created:      {}",
+        deploy.created_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    write_map(&mut out, "Selector:", &deploy.spec.selector);
+
+    let strategy = match deploy.spec.strategy {
+        DeploymentStrategy::RollingUpdate {
+            max_surge,
+            max_unavailable,
+        } => format!(
+            "RollingUpdate (max surge {}, max unavailable {})",
+            max_surge, max_unavailable
+        ),
+        DeploymentStrategy::Recreate => "Recreate".to_string(),
+        DeploymentStrategy::BlueGreen => "BlueGreen".to_string(),
+        DeploymentStrategy::Canary { weight } => format!("Canary ({}% weight)", weight),
+    };
+    let _ = writeln!(out, "Strategy:     {}", strategy);
+    let _ = writeln!(
+        out,
+        "Replicas:     {} desired | {} updated | {} ready | {} available",
+        deploy.spec.replicas,
+        deploy.status.updated_replicas,
+        deploy.status.ready_replicas,
+        deploy.status.available_replicas
+    );
+    let _ = writeln!(
+        out,
+        "Generation:   {} (observed {})",
+        deploy.generation, deploy.observed_generation
+    );
+    if let Some(cause) = &deploy.change_cause {
+        let _ = writeln!(out, "Change Cause: {}", cause);
+    }
+    if !deploy.status.conditions.is_empty() {
+        let _ = writeln!(out, "Conditions:");
+        let _ = writeln!(out, "  {:<12} {:<7} REASON", "TYPE", "STATUS");
+        for c in &deploy.status.conditions {
+            let _ = writeln!(
+                out,
+                "  {:<12} {:<7} {}",
+                c.condition_type.to_string(),
+                if c.status { "True" } else { "False" },
+                c.reason
+            );
+        }
+    }
+    let _ = writeln!(out, "Pod Template:");
+    for c in &deploy.spec.template.containers {
+        let _ = writeln!(out, "  {}:", c.name);
+        let _ = writeln!(out, "    Image:    {}", c.image);
     }
-    println!("Restarts:     {}", pod.restart_count);
 
-    // Spec
-    println!();
-    println!("Containers:");
-    for (i, c) in pod.spec.containers.iter().enumerate() {
-        println!("  [{}] Image: {}", i, c.image);
+    write_events(&mut out, events);
+    out
+}
+
+fn render_service(svc: &Service, events: &[Event]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Name:         {}", svc.name);
+    let _ = writeln!(out, "Namespace:    {}", svc.namespace);
+    let _ = writeln!(out, "ID:           {}", svc.id);
+    let _ = writeln!(
+        out,
+        "Created:      {}",
+        svc.created_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    write_map(&mut out, "Selector:", &svc.spec.selector);
+    let _ = writeln!(out, "Type:         {}", svc.spec.service_type);
+    let _ = writeln!(
+        out,
+        "Cluster IP:   {}",
+        svc.cluster_ip.as_deref().unwrap_or("<none>")
+    );
+    let _ = writeln!(
+        out,
+        "VPC:          {}",
+        svc.vpc.as_deref().unwrap_or("default")
+    );
+    let _ = writeln!(out, "Ports:");
+    for p in &svc.spec.ports {
+        match p.node_port {
+            Some(node_port) => {
+                let _ = writeln!(
+                    out,
+                    "  {}: {} -> {} (node port {})",
+                    p.name, p.port, p.target_port, node_port
+                );
+            }
+            None => {
+                let _ = writeln!(out, "  {}: {} -> {}", p.name, p.port, p.target_port);
+            }
+        }
+    }
+
+    write_events(&mut out, events);
+    out
+}
+
+fn render_node(node: &Node, events: &[Event]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "Name:           {}", node.name);
+    let _ = writeln!(out, "ID:             {}", node.id);
+    let _ = writeln!(out, "Address:        {}", node.address);
+    write_map(&mut out, "Labels:", &node.labels);
+    let _ = writeln!(
+        out,
+        "Registered:     {}",
+        node.registered_at.format("%Y-%m-%d %H:%M:%S")
+    );
+
+    let _ = writeln!(out, "Status:         {}", node.status);
+    let _ = writeln!(out, "Unschedulable:  {}", node.unschedulable);
+    let _ = writeln!(
+        out,
+        "Last Heartbeat: {}",
+        node.last_heartbeat.format("%Y-%m-%d %H:%M:%S")
+    );
+    if node.taints.is_empty() {
+        let _ = writeln!(out, "Taints:         <none>");
+    } else {
+        let _ = writeln!(out, "Taints:");
+        for t in &node.taints {
+            let _ = writeln!(out, "  {}={}:{:?}", t.key, t.value, t.effect);
+        }
+    }
+    if node.conditions.is_empty() {
+        let _ = writeln!(out, "Conditions:     <none>");
+    } else {
+        let _ = writeln!(out, "Conditions:");
+        for c in &node.conditions {
+            let _ = writeln!(
+                out,
+                "  {} since {}: {}",
+                c.condition_type,
+                c.since.format("%Y-%m-%d %H:%M:%S"),
+                c.message
+            );
+        }
+    }
+    let _ = writeln!(
+        out,
+        "Capacity:       cpu={}m memory={}",
+        node.capacity.cpu_millis, node.capacity.memory_bytes
+    );
+    let _ = writeln!(
+        out,
+        "Allocated:      cpu={}m memory={}",
+        node.allocated.cpu_millis, node.allocated.memory_bytes
+    );
+    let _ = writeln!(
+        out,
+        "Usage:          cpu={}m memory={} containers={}",
+        node.usage.cpu_millis, node.usage.memory_bytes, node.running_containers
+    );
+    let _ = writeln!(
+        out,
+        "Agent Version:  {}",
+        node.agent_version.as_deref().unwrap_or("<unknown>")
+    );
+
+    write_events(&mut out, events);
+    out
+}
+
+/// Per-container details under a section title.
+fn write_containers(
+    out: &mut String,
+    title: &str,
+    containers: &[ContainerSpec],
+    statuses: &[ContainerStatus],
+) {
+    let _ = writeln!(out, "{}", title);
+    for c in containers {
+        let _ = writeln!(out, "  {}:", c.name);
+        let _ = writeln!(out, "    Image:    {}", c.image);
+        if let Some(status) = statuses.iter().find(|s| s.name == c.name) {
+            write_container_status(out, status);
+        }
         if !c.command.is_empty() {
-            println!("      Command: {:?}", c.command);
+            let _ = writeln!(out, "    Command:  {:?}", c.command);
         }
         if !c.args.is_empty() {
-            println!("      Args: {:?}", c.args);
+            let _ = writeln!(out, "    Args:     {:?}", c.args);
+        }
+        if c.resources.cpu_millis > 0 || c.resources.memory_bytes > 0 {
+            let _ = writeln!(
+                out,
+                "    Requests: cpu={}m memory={}",
+                c.resources.cpu_millis, c.resources.memory_bytes
+            );
+        }
+        if c.limits.cpu_millis > 0 || c.limits.memory_bytes > 0 {
+            let _ = writeln!(
+                out,
+                "    Limits:   cpu={}m memory={}",
+                c.limits.cpu_millis, c.limits.memory_bytes
+            );
+        }
+        if let Some(ref probe) = c.liveness_probe {
+            let _ = writeln!(out, "    Liveness:  {}", probe_summary(probe));
+        }
+        if let Some(ref probe) = c.readiness_probe {
+            let _ = writeln!(out, "    Readiness: {}", probe_summary(probe));
         }
     }
+}
 
-    if !pod.labels.is_empty() {
-        println!();
-        println!("Labels:");
-        for (k, v) in &pod.labels {
-            println!("  {}: {}", k, v);
+/// State, restarts and readiness of a container, e.g.
+/// `State:    Terminated` / `  Reason:   Error` / `  Exit Code: 1`.
+fn write_container_status(out: &mut String, status: &ContainerStatus) {
+    if let Some(ref id) = status.container_id {
+        let _ = writeln!(out, "    Container ID: {}", id);
+    }
+    match &status.state {
+        ContainerState::Waiting { reason, message } => {
+            let _ = writeln!(out, "    State:    Waiting");
+            let _ = writeln!(out, "      Reason:   {}", reason);
+            if let Some(message) = message {
+                let _ = writeln!(out, "      Message:  {}", message);
+            }
+        }
+        ContainerState::Running { started_at } => {
+            let _ = writeln!(out, "    State:    Running");
+            let _ = writeln!(
+                out,
+                "      Started:  {}",
+                started_at.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        ContainerState::Terminated {
+            exit_code,
+            reason,
+            finished_at,
+        } => {
+            let _ = writeln!(out, "    State:    Terminated");
+            let _ = writeln!(out, "      Reason:   {}", reason);
+            let _ = writeln!(
+                out,
+                "      Exit Code: {}",
+                exit_code.map_or("<unknown>".to_string(), |c| c.to_string())
+            );
+            let _ = writeln!(
+                out,
+                "      Finished: {}",
+                finished_at.format("%Y-%m-%d %H:%M:%S")
+            );
         }
     }
+    let _ = writeln!(out, "    Ready:    {}", status.ready);
+    let _ = writeln!(out, "    Restarts: {}", status.restart_count);
+}
+
+/// One-line probe description, e.g. `http-get :8080/healthz delay=0s timeout=1s period=10s #failure=3`.
+fn probe_summary(probe: &Probe) -> String {
+    let check = match probe.handler {
+        ProbeHandler::HttpGet { ref path, port } => format!("http-get :{}{}", port, path),
+        ProbeHandler::TcpSocket { port } => format!("tcp-socket :{}", port),
+        ProbeHandler::Exec { ref command } => format!("exec {:?}", command),
+    };
+    format!(
+        "{} delay={}s timeout={}s period={}s #failure={}",
+        check,
+        probe.initial_delay_seconds,
+        probe.timeout_seconds,
+        probe.period_seconds,
+        probe.failure_threshold
+    )
+}
+
+/// Labels/selectors as `key=value` lines, sorted for stable output.
+fn write_map(out: &mut String, title: &str, map: &HashMap<String, String>) {
+    if map.is_empty() {
+        let _ = writeln!(out, "{:<14}<none>", title);
+        return;
+    }
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort();
+    let _ = writeln!(out, "{}", title);
+    for (k, v) in entries {
+        let _ = writeln!(out, "  {}={}", k, v);
+    }
+}
 
-    if let Some(ref owner) = pod.owner_ref {
-        println!();
-        println!("Owner:        {}", owner);
+/// Trailing `Events:` section, oldest first.
+fn write_events(out: &mut String, events: &[Event]) {
+    if events.is_empty() {
+        let _ = writeln!(out, "Events:       <none>");
+        return;
+    }
+    let mut events: Vec<&Event> = events.iter().collect();
+    events.sort_by_key(|e| e.timestamp);
+    let _ = writeln!(out, "Events:");
+    let _ = writeln!(
+        out,
+        "  {:<20} {:<8} {:<20} MESSAGE",
+        "TIME", "TYPE", "REASON"
+    );
+    for e in events {
+        let _ = writeln!(
+            out,
+            "  {:<20} {:<8} {:<20} {}",
+            e.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            e.event_type.to_string(),
+            e.reason,
+            e.message
+        );
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_failed_pod_with_events() {
+        let pod: Pod = serde_json::from_value(serde_json::json!({
+            "id": "pod-1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": "Failed",
+            "status_message": "container exited with code 1",
+            "node_name": "node-a",
+            "restart_count": 2,
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "init_containers": [{ "name": "migrate", "image": "busybox" }],
+                "containers": [{
+                    "name": "nginx",
+                    "image": "nginx:alpine",
+                    "readiness_probe": { "http_get": { "path": "/healthz", "port": 80 } }
+                }]
+            },
+            "container_statuses": [{
+                "name": "nginx",
+                "image": "nginx:alpine",
+                "container_id": "pod-1-nginx",
+                "state": { "terminated": {
+                    "exit_code": 1,
+                    "reason": "Error",
+                    "finished_at": "2024-02-25T00:00:04Z"
+                }},
+                "restart_count": 2
+            }]
+        }))
+        .unwrap();
+        let mut failed = Event::warning(
+            "pod",
+            "default",
+            "web-0",
+            "Failed",
+            "Pod is Failed: container exited with code 1",
+        );
+        failed.timestamp = "2024-02-25T00:00:05Z".parse().unwrap();
+        let mut scheduled = Event::normal(
+            "pod",
+            "default",
+            "web-0",
+            "Scheduled",
+            "Successfully assigned default/web-0 to node-a",
+        );
+        scheduled.timestamp = "2024-02-25T00:00:01Z".parse().unwrap();
+
+        let out = render_pod(&pod, &[failed, scheduled]);
+
+        assert!(out.starts_with("Name:         web-0\n"));
+        assert!(out.contains("Status:       Failed\n"));
+        assert!(out.contains("Message:      container exited with code 1\n"));
+        assert!(out.contains("Node:         node-a\n"));
+        assert!(out.contains("    Image:    nginx:alpine\n"));
+        assert!(out.contains("Ready:        false\n"));
+        assert!(out.contains("Init Containers:\n  migrate:\n    Image:    busybox\nContainers:\n"));
+        assert!(out.contains(
+            "    Image:    nginx:alpine\n    Container ID: pod-1-nginx\n    State:    Terminated\n      \
+             Reason:   Error\n      Exit Code: 1\n      Finished: 2024-02-25 00:00:04\n    \
+             Ready:    false\n    Restarts: 2\n"
+        ));
+        assert!(out.contains(
+            "    Readiness: http-get :80/healthz delay=0s timeout=1s period=10s #failure=3\n"
+        ));
+
+        let events = &out[out.find("Events:\n").expect("events section")..];
+        let lines: Vec<&str> = events.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].contains("TYPE") && lines[1].contains("REASON"));
+        assert!(lines[2].contains("2024-02-25 00:00:01"));
+        assert!(lines[2].contains("Normal   Scheduled"));
+        assert!(lines[3].contains("Warning  Failed"));
+        assert!(lines[3].ends_with("Pod is Failed: container exited with code 1"));
+    }
+
+    #[test]
+    fn test_render_without_events() {
+        let node: Node = serde_json::from_value(serde_json::json!({
+            "id": "n1",
+            "name": "node-a",
+            "address": "10.0.0.5",
+            "agent_api_port": 10250,
+            "status": "Ready",
+            "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:01:00Z",
+            "labels": {}
+        }))
+        .unwrap();
+        let out = render_node(&node, &[]);
+        assert!(out.contains("Status:         Ready\n"));
+        assert!(out.contains("Labels:       <none>\n"));
+        assert!(out.ends_with("Events:       <none>\n"));
+    }
 }
@@ -0,0 +1,357 @@
+use std::path::{Path, PathBuf};
+
+use super::get::{fetch_json, resource_path, resource_url};
+
+/// Kinds without an update endpoint.
+const READ_ONLY: &[&str] = &["vpc"];
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    resource: &str,
+    name: &str,
+    namespace: &str,
+) -> anyhow::Result<()> {
+    let Some((kind, segment, namespaced)) = resource_path(resource) else {
+        anyhow::bail!("unknown resource type '{}'", resource);
+    };
+    if READ_ONLY.contains(&kind) {
+        anyhow::bail!("{} objects cannot be edited", kind);
+    }
+    let url = resource_url(base, segment, namespaced, namespace, Some(name));
+    let editor = std::env::var("EDITOR")
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    match edit(client, &url, kind, name, &editor).await? {
+        Outcome::Edited => println!("{}/{} edited", kind, name),
+        Outcome::Unchanged => println!("Edit cancelled, no changes made."),
+        Outcome::Emptied => println!("Edit cancelled, no objects found."),
+    }
+    Ok(())
+}
+
+/// How an edit session ended.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Edited,
+    /// Saved without changes since the last attempt.
+    Unchanged,
+    /// Saved with nothing but comments and whitespace.
+    Emptied,
+}
+
+/// The temp file being edited, removed when the session ends however it
+/// ends.
+struct EditFile(PathBuf);
+
+impl Drop for EditFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Fetch the object at `url` as YAML, open it in `editor` and PUT the result
+/// back. A rejected update, or a file that no longer parses, reopens the
+/// editor on the edited text with the error in a comment header. The edited
+/// object keeps the resource version it was read at, so the update fails
+/// rather than overwrite a change made in the meantime.
+async fn edit(
+    client: &reqwest::Client,
+    url: &str,
+    kind: &str,
+    name: &str,
+    editor: &str,
+) -> anyhow::Result<Outcome> {
+    let Some(original) = fetch_json(client, url).await? else {
+        anyhow::bail!("{} '{}' not found", kind, name);
+    };
+    let file = EditFile(std::env::temp_dir().join(format!(
+        "k3rsctl-edit-{}-{}-{}.yaml",
+        kind,
+        name,
+        std::process::id()
+    )));
+    let mut last = serde_yaml::to_string(&original)?;
+    let mut error: Option<String> = None;
+    loop {
+        let header = match &error {
+            Some(error) => comment_header(error),
+            None => comment_header(&format!(
+                "Edit the {} below. Saving an unchanged or empty file cancels the edit.",
+                kind
+            )),
+        };
+        tokio::fs::write(&file.0, format!("{}{}", header, last)).await?;
+        run_editor(editor, &file.0).await?;
+        let text = strip_comments(&tokio::fs::read_to_string(&file.0).await?);
+        if text.trim().is_empty() {
+            return Ok(Outcome::Emptied);
+        }
+        if text == last {
+            if let Some(error) = error {
+                anyhow::bail!("{}", error);
+            }
+            return Ok(Outcome::Unchanged);
+        }
+        last = text;
+        let edited: serde_json::Value = match serde_yaml::from_str(&last) {
+            Ok(edited) => edited,
+            Err(e) => {
+                error = Some(format!("The edited file is not valid YAML: {}", e));
+                continue;
+            }
+        };
+        if edited == original {
+            return Ok(Outcome::Unchanged);
+        }
+        let resp = client.put(url).json(&edited).send().await?;
+        if resp.status().is_success() {
+            return Ok(Outcome::Edited);
+        }
+        error = Some(super::render_error(&super::api_error(resp).await));
+    }
+}
+
+/// `text` as `# ` comment lines, followed by a blank comment line.
+fn comment_header(text: &str) -> String {
+    let mut out: String = text.lines().map(|l| format!("# {}\n", l)).collect();
+    out.push_str("#\n");
+    out
+}
+
+/// The file without its leading comment header.
+fn strip_comments(text: &str) -> String {
+    let mut lines = text.lines().peekable();
+    while lines.next_if(|l| l.starts_with('#')).is_some() {}
+    lines.map(|l| format!("{}\n", l)).collect()
+}
+
+/// Run `editor` (a command line, such as `code --wait`) on `path` and wait
+/// for it to exit.
+async fn run_editor(editor: &str, path: &Path) -> anyhow::Result<()> {
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = tokio::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .await
+        .map_err(|e| anyhow::anyhow!("failed to start editor '{}': {}", program, e))?;
+    if !status.success() {
+        anyhow::bail!("editor '{}' exited with {}", program, status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Read one HTTP request: its request line and body.
+    async fn read_request(sock: &mut tokio::net::TcpStream) -> (String, String) {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = sock.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&buf).to_string();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                assert!(n > 0, "connection closed mid-request");
+                continue;
+            };
+            let length = head
+                .lines()
+                .find_map(|l| {
+                    let (name, value) = l.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())?
+                })
+                .unwrap_or(0);
+            if n == 0 || body.len() >= length {
+                let line = head.lines().next().unwrap_or_default().to_string();
+                return (line, body.to_string());
+            }
+        }
+    }
+
+    /// Serve the object on GET, answer PUTs in turn with `put_statuses`, and
+    /// record every PUT body.
+    async fn serve(
+        object: serde_json::Value,
+        put_statuses: Vec<(&'static str, &'static str)>,
+    ) -> (String, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let puts = Arc::new(Mutex::new(Vec::new()));
+        let recorded = puts.clone();
+        tokio::spawn(async move {
+            let mut put_statuses = put_statuses.into_iter();
+            loop {
+                let (mut sock, _) = listener.accept().await.unwrap();
+                let (line, body) = read_request(&mut sock).await;
+                let (status, body) = if line.starts_with("PUT") {
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push(serde_json::from_str(&body).unwrap());
+                    let (status, body) = put_statuses.next().unwrap();
+                    (status, body.to_string())
+                } else {
+                    ("200 OK", object.to_string())
+                };
+                let resp = format!(
+                    "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                sock.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+        (format!("http://{}", addr), puts)
+    }
+
+    /// An executable editor script running `body` with the file as `$1`.
+    fn editor_script(label: &str, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!(
+            "k3rsctl-test-editor-{}-{}.sh",
+            label,
+            std::process::id()
+        ));
+        std::fs::write(&path, format!("#!/bin/sh\nset -e\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn configmap(name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "namespace": "default",
+            "data": { "mode": "slow" },
+            "resource_version": 7,
+        })
+    }
+
+    /// Temp files left behind by editing configmap `name`.
+    fn edit_files(name: &str) -> usize {
+        let prefix = format!("k3rsctl-edit-configmap-{}-", name);
+        std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .filter(|e| {
+                let name = e.as_ref().unwrap().file_name();
+                let name = name.to_string_lossy();
+                name.starts_with(&prefix) && name.contains(&std::process::id().to_string())
+            })
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_edit_puts_the_modified_object() {
+        let (base, puts) = serve(configmap("modify"), vec![("200 OK", "{}")]).await;
+        let editor = editor_script("modify", "sed -i 's/slow/fast/' \"$1\"");
+        let client = reqwest::Client::new();
+        let url = format!("{}/api/v1/namespaces/default/configmaps/modify", base);
+        let outcome = edit(
+            &client,
+            &url,
+            "configmap",
+            "modify",
+            &editor.to_string_lossy(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, Outcome::Edited);
+        let puts = puts.lock().unwrap();
+        assert_eq!(puts.len(), 1);
+        assert_eq!(puts[0]["data"]["mode"], "fast");
+        // Sent at the version it was read at.
+        assert_eq!(puts[0]["resource_version"], 7);
+        assert_eq!(edit_files("modify"), 0);
+        std::fs::remove_file(editor).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_edit_reopens_with_the_error() {
+        let invalid = r#"{"reason":"Invalid","message":"configmap 'retry' is invalid","details":[{"field":"data.bad key","message":"invalid key 'bad key'"}]}"#;
+        let (base, puts) = serve(
+            configmap("retry"),
+            vec![("422 Unprocessable Entity", invalid), ("200 OK", "{}")],
+        )
+        .await;
+        // First pass adds a bad key; the second, seeing the error header,
+        // renames it.
+        let editor = editor_script(
+            "retry",
+            "if grep -q '^# .*invalid key' \"$1\"; then\n  sed -i 's/bad key/good-key/' \"$1\"\nelse\n  sed -i 's/^data:/data:\\n  bad key: x/' \"$1\"\nfi",
+        );
+        let client = reqwest::Client::new();
+        let url = format!("{}/api/v1/namespaces/default/configmaps/retry", base);
+        let outcome = edit(
+            &client,
+            &url,
+            "configmap",
+            "retry",
+            &editor.to_string_lossy(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, Outcome::Edited);
+        let puts = puts.lock().unwrap();
+        assert_eq!(puts.len(), 2);
+        assert_eq!(puts[0]["data"]["bad key"], "x");
+        assert_eq!(puts[1]["data"]["good-key"], "x");
+        assert_eq!(puts[1]["data"]["mode"], "slow");
+        std::fs::remove_file(editor).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_or_emptied_file_cancels() {
+        let (base, puts) = serve(configmap("cancel"), vec![]).await;
+        let client = reqwest::Client::new();
+        let url = format!("{}/api/v1/namespaces/default/configmaps/cancel", base);
+
+        let editor = editor_script("noop", "true");
+        let outcome = edit(
+            &client,
+            &url,
+            "configmap",
+            "cancel",
+            &editor.to_string_lossy(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, Outcome::Unchanged);
+        std::fs::remove_file(editor).unwrap();
+
+        let editor = editor_script("empty", ": > \"$1\"");
+        let outcome = edit(
+            &client,
+            &url,
+            "configmap",
+            "cancel",
+            &editor.to_string_lossy(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(outcome, Outcome::Emptied);
+        std::fs::remove_file(editor).unwrap();
+
+        assert!(puts.lock().unwrap().is_empty());
+        assert_eq!(edit_files("cancel"), 0);
+    }
+
+    #[test]
+    fn test_comment_header_round_trip() {
+        let header = comment_header("Error from server (Invalid): bad\n  data.x: nope");
+        assert_eq!(
+            header,
+            "# Error from server (Invalid): bad\n#   data.x: nope\n#\n"
+        );
+        let body = "name: cfg\n# kept\n";
+        assert_eq!(strip_comments(&format!("{}{}", header, body)), body);
+    }
+}
@@ -1,15 +1,31 @@
 use futures_util::{SinkExt, StreamExt};
+use pkg_types::exec::{ExecControl, ExecExit};
+use std::io::IsTerminal;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 
+/// `stdin` streams local input to the process; `tty` gives it a PTY and
+/// puts the local terminal into raw mode. Input that is piped or redirected
+/// is streamed without `stdin`, and its end is passed on as EOF. Without a
+/// command an interactive shell is started, as with `-it`. Exits with the
+/// remote process's exit code when it is non-zero.
+#[allow(clippy::too_many_arguments)]
 pub async fn handle(
     server: &str,
     token: &str,
     pod_id: &str,
     command: &[String],
     namespace: &str,
-    interactive: bool,
+    stdin: bool,
+    tty: bool,
+    container: Option<&str>,
 ) -> anyhow::Result<()> {
-    // --it / -i flag drives interactive mode.
-    let is_interactive = interactive || command.is_empty();
+    let stdin = stdin || command.is_empty() || !std::io::stdin().is_terminal();
+    let mut tty = tty || command.is_empty();
+    if tty && !std::io::stdin().is_terminal() {
+        eprintln!("Unable to use a TTY - input is not a terminal");
+        tty = false;
+    }
 
     // Build URL — encode and pass the command as a ?cmd= query param so
     // the agent spawns it directly rather than piping it as stdin.
@@ -31,9 +47,12 @@ pub async fn handle(
     if !encoded_cmd.is_empty() {
         params.push(format!("cmd={}", encoded_cmd));
     }
-    if is_interactive {
+    if tty {
         params.push("tty=true".to_string());
     }
+    if let Some(container) = container {
+        params.push(format!("container={}", container));
+    }
     let url = if params.is_empty() {
         format!(
             "{}/api/v1/namespaces/{}/pods/{}/exec",
@@ -49,8 +68,42 @@ pub async fn handle(
         )
     };
 
-    let request = tokio_tungstenite::tungstenite::http::Request::builder()
-        .uri(&url)
+    let (ws_stream, _) = match tokio_tungstenite::connect_async(ws_request(&url, token)).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("Failed to connect WebSocket: {}", ws_error(&e));
+            std::process::exit(1);
+        }
+    };
+
+    let (mut write, mut read) = ws_stream.split();
+
+    let exit = if tty {
+        handle_tty(&mut write, &mut read).await?
+    } else {
+        handle_stream(&mut write, &mut read, stdin).await
+    };
+
+    if let Some(ExecExit { code }) = exit
+        && code != 0
+    {
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+/// The exit status in the server's close frame, if it sent one.
+fn exit_status(frame: Option<&CloseFrame>) -> Option<ExecExit> {
+    ExecExit::decode(&frame?.reason)
+}
+
+/// The upgrade request for the server WebSocket endpoint at `url`.
+pub(crate) fn ws_request(
+    url: &str,
+    token: &str,
+) -> tokio_tungstenite::tungstenite::http::Request<()> {
+    tokio_tungstenite::tungstenite::http::Request::builder()
+        .uri(url)
         .header("Authorization", format!("Bearer {}", token))
         .header("Host", "localhost")
         .header("Connection", "Upgrade")
@@ -61,50 +114,61 @@ pub async fn handle(
             tokio_tungstenite::tungstenite::handshake::client::generate_key(),
         )
         .body(())
-        .expect("Failed to build WebSocket request");
+        .expect("Failed to build WebSocket request")
+}
 
-    let (ws_stream, _) = match tokio_tungstenite::connect_async(request).await {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Failed to connect WebSocket: {}", e);
-            std::process::exit(1);
+/// Describe a failed WebSocket handshake, with the server's error when it
+/// answered with one (e.g. a `502` naming a node that cannot be reached).
+pub(crate) fn ws_error(err: &tokio_tungstenite::tungstenite::Error) -> String {
+    match err {
+        tokio_tungstenite::tungstenite::Error::Http(resp) => {
+            let body = resp.body().as_deref().unwrap_or_default();
+            super::render_error(&pkg_types::error::ApiError::from_response(
+                resp.status().as_u16(),
+                &String::from_utf8_lossy(body),
+            ))
         }
-    };
+        err => err.to_string(),
+    }
+}
 
-    let (mut write, mut read) = ws_stream.split();
+/// Raw mode for the local terminal while a TTY session runs. Dropping the
+/// guard restores the terminal, also when unwinding from a panic.
+struct RawModeGuard;
 
-    if is_interactive {
-        handle_interactive(&mut write, &mut read).await;
-    } else {
-        handle_non_interactive(&mut write, &mut read).await;
+impl RawModeGuard {
+    fn enable() -> anyhow::Result<Self> {
+        crossterm::terminal::enable_raw_mode()
+            .map_err(|e| anyhow::anyhow!("failed to enable raw terminal mode: {}", e))?;
+        Ok(Self)
     }
+}
 
-    Ok(())
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        crossterm::terminal::disable_raw_mode().ok();
+    }
 }
 
-async fn handle_interactive<W, R>(write: &mut W, read: &mut R)
-where
-    W: futures_util::Sink<tokio_tungstenite::tungstenite::Message> + Unpin,
-    R: futures_util::Stream<
-            Item = Result<
-                tokio_tungstenite::tungstenite::Message,
-                tokio_tungstenite::tungstenite::Error,
-            >,
-        > + Unpin,
-{
-    // Enable raw mode: keystrokes sent immediately, no local echo.
-    crossterm::terminal::enable_raw_mode().expect("Failed to enable raw terminal mode");
+/// The local terminal's size as a resize control frame.
+fn resize_frame() -> Option<Message> {
+    let (cols, rows) = crossterm::terminal::size().ok()?;
+    Some(Message::Text(
+        ExecControl::Resize { cols, rows }.encode().into(),
+    ))
+}
 
-    // Drain the "Connecting to …" welcome text frame → print to stderr
-    if let Some(Ok(tokio_tungstenite::tungstenite::Message::Text(text))) = read.next().await {
-        eprint!("{}", text);
-    }
+/// Tells the server local input is over.
+fn eof_frame() -> Message {
+    Message::Text(ExecControl::Eof.encode().into())
+}
 
-    // Spawn a blocking thread to read raw stdin bytes.
-    let (stdin_tx, mut stdin_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(32);
+/// Read local stdin on a blocking thread; the channel closes at EOF.
+fn spawn_stdin_reader() -> tokio::sync::mpsc::Receiver<Vec<u8>> {
+    let (stdin_tx, stdin_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(32);
     std::thread::spawn(move || {
         use std::io::Read as _;
-        let mut buf = [0u8; 256];
+        let mut buf = [0u8; 4096];
         loop {
             match std::io::stdin().read(&mut buf) {
                 Ok(0) | Err(_) => break,
@@ -116,53 +180,64 @@ where
             }
         }
     });
+    stdin_rx
+}
+
+fn write_stdout(bytes: &[u8]) {
+    use std::io::Write as _;
+    std::io::stdout().write_all(bytes).ok();
+    std::io::stdout().flush().ok();
+}
+
+/// Raw byte tunnel between the local terminal and the remote PTY. Every
+/// keystroke, Ctrl-C included, goes to the remote process; the session
+/// ends when the remote side closes. Window size changes follow `SIGWINCH`.
+async fn handle_tty<W, R>(write: &mut W, read: &mut R) -> anyhow::Result<Option<ExecExit>>
+where
+    W: futures_util::Sink<Message> + Unpin,
+    R: futures_util::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut winch = signal(SignalKind::window_change())?;
+    let raw_mode = RawModeGuard::enable()?;
 
-    // Bidirectional raw tunnel.
-    // Double Ctrl+C (0x03) within 1 second exits the session.
-    let mut last_ctrl_c: Option<std::time::Instant> = None;
+    // Drain the "Connecting to …" welcome text frame → print to stderr
+    if let Some(Ok(Message::Text(text))) = read.next().await {
+        eprint!("{}", text);
+    }
+    if let Some(frame) = resize_frame() {
+        let _ = write.send(frame).await;
+    }
+
+    let mut stdin_rx = spawn_stdin_reader();
+    let mut exit = None;
     loop {
         tokio::select! {
             // Keystrokes from local terminal → container
             bytes = stdin_rx.recv() => {
-                match bytes {
-                    Some(b) => {
-                        if b.contains(&0x03) {
-                            if last_ctrl_c
-                                .map(|t| t.elapsed() < std::time::Duration::from_secs(1))
-                                .unwrap_or(false)
-                            {
-                                break;
-                            }
-                            last_ctrl_c = Some(std::time::Instant::now());
-                        } else {
-                            last_ctrl_c = None;
-                        }
-                        if write
-                            .send(tokio_tungstenite::tungstenite::Message::Binary(
-                                b.into(),
-                            ))
-                            .await
-                            .is_err()
-                        {
-                            break;
-                        }
-                    }
-                    None => break,
+                let Some(b) = bytes else { break };
+                if write.send(Message::Binary(b.into())).await.is_err() {
+                    break;
+                }
+            }
+            _ = winch.recv() => {
+                if let Some(frame) = resize_frame()
+                    && write.send(frame).await.is_err()
+                {
+                    break;
                 }
             }
             // Output from container → local terminal
             msg = read.next() => {
                 match msg {
-                    Some(Ok(tokio_tungstenite::tungstenite::Message::Binary(b))) => {
-                        use std::io::Write as _;
-                        std::io::stdout().write_all(&b).ok();
-                        std::io::stdout().flush().ok();
-                    }
-                    Some(Ok(tokio_tungstenite::tungstenite::Message::Text(t))) => {
-                        eprint!("{}", t);
+                    Some(Ok(Message::Binary(b))) => write_stdout(&b),
+                    Some(Ok(Message::Text(t))) => eprint!("{}", t),
+                    Some(Ok(Message::Close(frame))) => {
+                        exit = exit_status(frame.as_ref());
+                        break;
                     }
-                    Some(Ok(tokio_tungstenite::tungstenite::Message::Close(_)))
-                    | None => break,
+                    None => break,
                     _ => {}
                 }
             }
@@ -170,39 +245,99 @@ where
     }
 
     // Restore the terminal before exiting.
-    crossterm::terminal::disable_raw_mode().ok();
+    drop(raw_mode);
     eprintln!("\r\nSession closed.");
+    Ok(exit)
 }
 
-async fn handle_non_interactive<W, R>(write: &mut W, read: &mut R)
+/// Without a TTY: print the process's output, forwarding local stdin as
+/// binary frames when `stdin` is set. The process's stdin is closed at the
+/// end of local input, or right away when there is none to forward.
+async fn handle_stream<W, R>(write: &mut W, read: &mut R, stdin: bool) -> Option<ExecExit>
 where
-    W: futures_util::Sink<tokio_tungstenite::tungstenite::Message> + Unpin,
-    R: futures_util::Stream<
-            Item = Result<
-                tokio_tungstenite::tungstenite::Message,
-                tokio_tungstenite::tungstenite::Error,
-            >,
-        > + Unpin,
+    W: futures_util::Sink<Message> + Unpin,
+    R: futures_util::Stream<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
 {
     // Drain the "Connecting to …" welcome message.
-    if let Some(Ok(tokio_tungstenite::tungstenite::Message::Text(_))) = read.next().await {}
+    if let Some(Ok(Message::Text(_))) = read.next().await {}
 
+    let mut stdin_rx = stdin.then(spawn_stdin_reader);
+    if stdin_rx.is_none() {
+        let _ = write.send(eof_frame()).await;
+    }
+    let mut exit = None;
     loop {
-        match read.next().await {
-            Some(Ok(tokio_tungstenite::tungstenite::Message::Binary(b))) => {
-                use std::io::Write as _;
-                std::io::stdout().write_all(&b).ok();
-                std::io::stdout().flush().ok();
+        tokio::select! {
+            bytes = async { stdin_rx.as_mut().unwrap().recv().await }, if stdin_rx.is_some() => {
+                match bytes {
+                    Some(b) => {
+                        if write.send(Message::Binary(b.into())).await.is_err() {
+                            break;
+                        }
+                    }
+                    // EOF: stop forwarding, keep printing output.
+                    None => {
+                        stdin_rx = None;
+                        if write.send(eof_frame()).await.is_err() {
+                            break;
+                        }
+                    }
+                }
             }
-            Some(Ok(tokio_tungstenite::tungstenite::Message::Text(text))) => {
-                print!("{}", text);
+            msg = read.next() => {
+                match msg {
+                    Some(Ok(Message::Binary(b))) => write_stdout(&b),
+                    Some(Ok(Message::Text(text))) => print!("{}", text),
+                    Some(Ok(Message::Close(frame))) => {
+                        exit = exit_status(frame.as_ref());
+                        break;
+                    }
+                    None => break,
+                    _ => {}
+                }
             }
-            Some(Ok(tokio_tungstenite::tungstenite::Message::Close(_))) | None => break,
-            _ => {}
         }
     }
 
-    let _ = write
-        .send(tokio_tungstenite::tungstenite::Message::Close(None))
-        .await;
+    let _ = write.send(Message::Close(None)).await;
+    exit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+
+    #[tokio::test]
+    async fn test_stream_returns_the_remote_exit_status() {
+        let close = CloseFrame {
+            code: CloseCode::Normal,
+            reason: ExecExit { code: 2 }.encode().into(),
+        };
+        let mut read = futures_util::stream::iter([
+            Ok(Message::Text("Connecting to c1...\r\n".into())),
+            Ok(Message::Binary(b"no such file\n"[..].into())),
+            Ok(Message::Close(Some(close))),
+        ]);
+        let mut write = futures_util::sink::drain();
+        assert_eq!(
+            handle_stream(&mut write, &mut read, false).await,
+            Some(ExecExit { code: 2 })
+        );
+
+        // A session that just drops reports nothing.
+        let mut read = futures_util::stream::iter([Ok(Message::Close(None))]);
+        assert_eq!(handle_stream(&mut write, &mut read, false).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_without_input_closes_remote_stdin() {
+        let mut read = futures_util::stream::iter([
+            Ok(Message::Text("Connecting to c1...\r\n".into())),
+            Ok(Message::Close(None)),
+        ]);
+        let mut write: Vec<Message> = Vec::new();
+        handle_stream(&mut write, &mut read, false).await;
+        assert_eq!(write, [eof_frame(), Message::Close(None)]);
+    }
 }
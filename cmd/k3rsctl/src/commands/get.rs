@@ -3,282 +3,923 @@ use pkg_types::daemonset::DaemonSet;
 use pkg_types::deployment::Deployment;
 use pkg_types::hpa::HorizontalPodAutoscaler;
 use pkg_types::job::{CronJob, Job};
+use pkg_types::limit_range::LimitRange;
 use pkg_types::namespace::Namespace;
-use pkg_types::pod::Pod;
+use pkg_types::pdb::PodDisruptionBudget;
+use pkg_types::pod::{Pod, ResourceRequirements};
+use pkg_types::priority_class::PriorityClass;
 use pkg_types::replicaset::ReplicaSet;
 use pkg_types::secret::Secret;
 use pkg_types::service::Service;
 use pkg_types::vpc::{Vpc, VpcPeering};
+use serde::de::DeserializeOwned;
 
+use super::table::{Table, TableOptions};
+
+/// Resource types offered by shell completion, by their plural names.
+pub(crate) const RESOURCE_TYPES: &[&str] = &[
+    "pods",
+    "services",
+    "deployments",
+    "replicasets",
+    "daemonsets",
+    "jobs",
+    "cronjobs",
+    "hpa",
+    "configmaps",
+    "secrets",
+    "limitranges",
+    "poddisruptionbudgets",
+    "namespaces",
+    "priorityclasses",
+    "vpcs",
+    "vpc-peerings",
+];
+
+/// Singular kind, API path segment and namespacing for a resource type given
+/// by its singular, plural or short name.
+pub(super) fn resource_path(resource: &str) -> Option<(&'static str, &'static str, bool)> {
+    let path = match resource {
+        "pods" | "pod" | "po" => ("pod", "pods", true),
+        "services" | "service" | "svc" => ("service", "services", true),
+        "deployments" | "deployment" | "deploy" => ("deployment", "deployments", true),
+        "replicasets" | "replicaset" | "rs" => ("replicaset", "replicasets", true),
+        "daemonsets" | "daemonset" | "ds" => ("daemonset", "daemonsets", true),
+        "jobs" | "job" => ("job", "jobs", true),
+        "cronjobs" | "cronjob" | "cj" => ("cronjob", "cronjobs", true),
+        "hpa" | "horizontalpodautoscalers" | "horizontalpodautoscaler" => ("hpa", "hpa", true),
+        "configmaps" | "configmap" | "cm" => ("configmap", "configmaps", true),
+        "secrets" | "secret" => ("secret", "secrets", true),
+        "limitranges" | "limitrange" | "limits" => ("limitrange", "limitranges", true),
+        "poddisruptionbudgets" | "poddisruptionbudget" | "pdb" => {
+            ("poddisruptionbudget", "poddisruptionbudgets", true)
+        }
+        "namespaces" | "namespace" | "ns" => ("namespace", "namespaces", false),
+        "priorityclasses" | "priorityclass" | "pc" => ("priorityclass", "priorityclasses", false),
+        "vpcs" | "vpc" => ("vpc", "vpcs", false),
+        _ => return None,
+    };
+    Some(path)
+}
+
+/// URL of a resource collection, or of a single object when `name` is given.
+pub(super) fn resource_url(
+    base: &str,
+    segment: &str,
+    namespaced: bool,
+    namespace: &str,
+    name: Option<&str>,
+) -> String {
+    let collection = if namespaced {
+        format!("{}/api/v1/namespaces/{}/{}", base, namespace, segment)
+    } else {
+        format!("{}/api/v1/{}", base, segment)
+    };
+    match name {
+        Some(name) => format!("{}/{}", collection, name),
+        None => collection,
+    }
+}
+
+/// GET a JSON document, mapping 404 to `None`.
+pub(super) async fn fetch_json(
+    client: &reqwest::Client,
+    url: &str,
+) -> anyhow::Result<Option<serde_json::Value>> {
+    let resp = client.get(url).send().await?;
+    let status = resp.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        anyhow::bail!("{}", super::render_error(&super::api_error(resp).await));
+    }
+    Ok(Some(resp.json().await?))
+}
+
+/// GET a whole list, `chunk_size` objects per request (0: in one request),
+/// following the server's continue token until the last page.
+pub(super) async fn fetch_list<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+    chunk_size: usize,
+) -> anyhow::Result<Vec<T>> {
+    let mut items = Vec::new();
+    let mut token: Option<String> = None;
+    loop {
+        let mut params = Vec::new();
+        if chunk_size > 0 {
+            params.push(format!("limit={}", chunk_size));
+        }
+        if let Some(ref token) = token {
+            // Tokens are hex, so they need no escaping.
+            params.push(format!("continue={}", token));
+        }
+        let page_url = if params.is_empty() {
+            url.to_string()
+        } else {
+            format!("{}?{}", url, params.join("&"))
+        };
+        let resp = client.get(&page_url).send().await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("{}", super::render_error(&super::api_error(resp).await));
+        }
+        token = resp
+            .headers()
+            .get(pkg_constants::state::CONTINUE_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        items.extend(resp.json::<Vec<T>>().await?);
+        if token.is_none() {
+            return Ok(items);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn handle(
     client: &reqwest::Client,
     base: &str,
     resource: &str,
+    name: Option<&str>,
     namespace: &str,
+    output: Option<&str>,
+    chunk_size: usize,
+    table_options: &TableOptions,
 ) -> anyhow::Result<()> {
-    match resource {
+    if let Some(format) = output
+        && !matches!(format, "json" | "yaml" | "wide")
+    {
+        eprintln!(
+            "Unsupported output format: {} (use json, yaml or wide)",
+            format
+        );
+        std::process::exit(1);
+    }
+    // `wide` only adds table columns
+    let wide = output == Some("wide");
+    let output = output.filter(|_| !wide);
+    if name.is_some() || output.is_some() {
+        return get_raw(client, base, resource, name, namespace, output, chunk_size).await;
+    }
+
+    let (table, empty) = match resource {
         "pods" | "pod" => {
             let url = format!("{}/api/v1/namespaces/{}/pods", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let pods: Vec<Pod> = resp.json().await?;
-            println!(
-                "{:<38} {:<20} {:<12} {:<10} NODE",
-                "ID", "NAME", "NAMESPACE", "STATUS"
-            );
+            let pods: Vec<Pod> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&[
+                "ID",
+                "NAME",
+                "NAMESPACE",
+                "READY",
+                "STATUS",
+                "RESTARTS",
+                "NODE",
+            ])
+            .numeric(&["RESTARTS"])
+            .status("STATUS")
+            .with_age();
             for pod in &pods {
-                println!(
-                    "{:<38} {:<20} {:<12} {:<10} {}",
-                    pod.id,
-                    pod.name,
-                    pod.namespace,
-                    pod.status,
-                    pod.node_name.as_deref().unwrap_or("-")
+                let (ready, total) = pod.ready_containers();
+                table.push_aged(
+                    vec![
+                        pod.id.clone(),
+                        pod.name.clone(),
+                        pod.namespace.clone(),
+                        format!("{}/{}", ready, total),
+                        pod.display_status(),
+                        pod.restart_count.to_string(),
+                        pod.node_name.clone().unwrap_or_else(|| "-".to_string()),
+                    ],
+                    pod.created_at,
                 );
             }
-            if pods.is_empty() {
-                println!("No pods found in namespace '{}'", namespace);
-            }
+            (table, format!("No pods found in namespace '{}'", namespace))
         }
         "services" | "service" | "svc" => {
             let url = format!("{}/api/v1/namespaces/{}/services", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let svcs: Vec<Service> = resp.json().await?;
-            println!(
-                "{:<38} {:<20} {:<12} {:<14} CLUSTER-IP",
-                "ID", "NAME", "NAMESPACE", "TYPE"
-            );
+            let svcs: Vec<Service> = fetch_list(client, &url, chunk_size).await?;
+            let mut table =
+                Table::new(&["ID", "NAME", "NAMESPACE", "TYPE", "CLUSTER-IP", "PORT(S)"])
+                    .with_age();
             for svc in &svcs {
-                println!(
-                    "{:<38} {:<20} {:<12} {:<14} {}",
-                    svc.id,
-                    svc.name,
-                    svc.namespace,
-                    svc.spec.service_type,
-                    svc.cluster_ip.as_deref().unwrap_or("-")
+                let ports: Vec<String> = svc
+                    .spec
+                    .ports
+                    .iter()
+                    .map(|p| match p.node_port {
+                        Some(node_port) => format!("{}:{}", p.port, node_port),
+                        None => p.port.to_string(),
+                    })
+                    .collect();
+                table.push_aged(
+                    vec![
+                        svc.id.clone(),
+                        svc.name.clone(),
+                        svc.namespace.clone(),
+                        svc.spec.service_type.to_string(),
+                        svc.cluster_ip.clone().unwrap_or_else(|| "-".to_string()),
+                        ports.join(","),
+                    ],
+                    svc.created_at,
                 );
             }
-            if svcs.is_empty() {
-                println!("No services found in namespace '{}'", namespace);
-            }
+            (
+                table,
+                format!("No services found in namespace '{}'", namespace),
+            )
         }
         "deployments" | "deployment" | "deploy" => {
             let url = format!("{}/api/v1/namespaces/{}/deployments", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let deploys: Vec<Deployment> = resp.json().await?;
-            println!(
-                "{:<38} {:<20} {:<12} {:<10} {:<10}",
-                "ID", "NAME", "NAMESPACE", "REPLICAS", "READY"
-            );
-            for d in &deploys {
-                println!(
-                    "{:<38} {:<20} {:<12} {:<10} {:<10}",
-                    d.id, d.name, d.namespace, d.spec.replicas, d.status.ready_replicas
-                );
+            let deploys: Vec<Deployment> = fetch_list(client, &url, chunk_size).await?;
+            let mut headers = vec!["ID", "NAME", "NAMESPACE", "REPLICAS", "READY"];
+            if wide {
+                headers.extend(["UP-TO-DATE", "AVAILABLE"]);
             }
-            if deploys.is_empty() {
-                println!("No deployments found in namespace '{}'", namespace);
+            let mut table = Table::new(&headers)
+                .numeric(&["REPLICAS", "READY", "UP-TO-DATE", "AVAILABLE"])
+                .with_age();
+            for d in &deploys {
+                let mut cells = vec![
+                    d.id.clone(),
+                    d.name.clone(),
+                    d.namespace.clone(),
+                    d.spec.replicas.to_string(),
+                    d.status.ready_replicas.to_string(),
+                ];
+                if wide {
+                    cells.push(d.status.updated_replicas.to_string());
+                    cells.push(d.status.available_replicas.to_string());
+                }
+                table.push_aged(cells, d.created_at);
             }
+            (
+                table,
+                format!("No deployments found in namespace '{}'", namespace),
+            )
         }
         "replicasets" | "replicaset" | "rs" => {
             let url = format!("{}/api/v1/namespaces/{}/replicasets", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let items: Vec<ReplicaSet> = resp.json().await?;
-            println!(
-                "{:<38} {:<20} {:<12} {:<10} {:<10}",
-                "ID", "NAME", "NAMESPACE", "REPLICAS", "READY"
-            );
+            let items: Vec<ReplicaSet> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["ID", "NAME", "NAMESPACE", "REPLICAS", "READY"])
+                .numeric(&["REPLICAS", "READY"])
+                .with_age();
             for rs in &items {
-                println!(
-                    "{:<38} {:<20} {:<12} {:<10} {:<10}",
-                    rs.id, rs.name, rs.namespace, rs.spec.replicas, rs.status.ready_replicas
+                table.push_aged(
+                    vec![
+                        rs.id.clone(),
+                        rs.name.clone(),
+                        rs.namespace.clone(),
+                        rs.spec.replicas.to_string(),
+                        rs.status.ready_replicas.to_string(),
+                    ],
+                    rs.created_at,
                 );
             }
-            if items.is_empty() {
-                println!("No replicasets found in namespace '{}'", namespace);
-            }
+            (
+                table,
+                format!("No replicasets found in namespace '{}'", namespace),
+            )
         }
         "daemonsets" | "daemonset" | "ds" => {
             let url = format!("{}/api/v1/namespaces/{}/daemonsets", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let items: Vec<DaemonSet> = resp.json().await?;
-            println!(
-                "{:<38} {:<20} {:<12} {:<10} {:<10}",
-                "ID", "NAME", "NAMESPACE", "DESIRED", "READY"
-            );
+            let items: Vec<DaemonSet> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["ID", "NAME", "NAMESPACE", "DESIRED", "READY"])
+                .numeric(&["DESIRED", "READY"])
+                .with_age();
             for ds in &items {
-                println!(
-                    "{:<38} {:<20} {:<12} {:<10} {:<10}",
-                    ds.id,
-                    ds.name,
-                    ds.namespace,
-                    ds.status.desired_number_scheduled,
-                    ds.status.number_ready
+                table.push_aged(
+                    vec![
+                        ds.id.clone(),
+                        ds.name.clone(),
+                        ds.namespace.clone(),
+                        ds.status.desired_number_scheduled.to_string(),
+                        ds.status.number_ready.to_string(),
+                    ],
+                    ds.created_at,
                 );
             }
-            if items.is_empty() {
-                println!("No daemonsets found in namespace '{}'", namespace);
-            }
+            (
+                table,
+                format!("No daemonsets found in namespace '{}'", namespace),
+            )
         }
         "jobs" | "job" => {
             let url = format!("{}/api/v1/namespaces/{}/jobs", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let items: Vec<Job> = resp.json().await?;
-            println!(
-                "{:<38} {:<20} {:<12} {:<10} {:<10}",
-                "ID", "NAME", "NAMESPACE", "STATUS", "SUCCEEDED"
-            );
+            let items: Vec<Job> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["ID", "NAME", "NAMESPACE", "STATUS", "SUCCEEDED"])
+                .numeric(&["SUCCEEDED"])
+                .status("STATUS")
+                .with_age();
             for j in &items {
-                println!(
-                    "{:<38} {:<20} {:<12} {:<10} {:<10}",
-                    j.id, j.name, j.namespace, j.status.condition, j.status.succeeded
+                table.push_aged(
+                    vec![
+                        j.id.clone(),
+                        j.name.clone(),
+                        j.namespace.clone(),
+                        j.status.condition.to_string(),
+                        j.status.succeeded.to_string(),
+                    ],
+                    j.created_at,
                 );
             }
-            if items.is_empty() {
-                println!("No jobs found in namespace '{}'", namespace);
-            }
+            (table, format!("No jobs found in namespace '{}'", namespace))
         }
         "cronjobs" | "cronjob" | "cj" => {
             let url = format!("{}/api/v1/namespaces/{}/cronjobs", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let items: Vec<CronJob> = resp.json().await?;
-            println!(
-                "{:<38} {:<20} {:<12} {:<15} SUSPEND",
-                "ID", "NAME", "NAMESPACE", "SCHEDULE"
-            );
+            let items: Vec<CronJob> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&[
+                "ID",
+                "NAME",
+                "NAMESPACE",
+                "SCHEDULE",
+                "SUSPEND",
+                "ACTIVE",
+                "LAST SCHEDULE",
+            ])
+            .numeric(&["ACTIVE"])
+            .with_age();
             for cj in &items {
-                println!(
-                    "{:<38} {:<20} {:<12} {:<15} {}",
-                    cj.id, cj.name, cj.namespace, cj.spec.schedule, cj.spec.suspend
+                table.push_aged(
+                    vec![
+                        cj.id.clone(),
+                        cj.name.clone(),
+                        cj.namespace.clone(),
+                        cj.spec.schedule.clone(),
+                        cj.spec.suspend.to_string(),
+                        cj.status.active.len().to_string(),
+                        cj.status
+                            .last_schedule_time
+                            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_else(|| "<none>".to_string()),
+                    ],
+                    cj.created_at,
                 );
             }
-            if items.is_empty() {
-                println!("No cronjobs found in namespace '{}'", namespace);
-            }
+            (
+                table,
+                format!("No cronjobs found in namespace '{}'", namespace),
+            )
         }
         "hpa" | "horizontalpodautoscalers" | "horizontalpodautoscaler" => {
             let url = format!("{}/api/v1/namespaces/{}/hpa", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let items: Vec<HorizontalPodAutoscaler> = resp.json().await?;
-            println!(
-                "{:<38} {:<20} {:<12} {:<8} {:<8} {:<10}",
-                "ID", "NAME", "NAMESPACE", "MIN", "MAX", "CURRENT"
-            );
+            let items: Vec<HorizontalPodAutoscaler> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&[
+                "ID",
+                "NAME",
+                "NAMESPACE",
+                "TARGETS",
+                "MIN",
+                "MAX",
+                "CURRENT",
+            ])
+            .numeric(&["MIN", "MAX", "CURRENT"])
+            .with_age();
             for h in &items {
-                println!(
-                    "{:<38} {:<20} {:<12} {:<8} {:<8} {:<10}",
-                    h.id,
-                    h.name,
-                    h.namespace,
-                    h.spec.min_replicas,
-                    h.spec.max_replicas,
-                    h.status.current_replicas
+                table.push_aged(
+                    vec![
+                        h.id.clone(),
+                        h.name.clone(),
+                        h.namespace.clone(),
+                        hpa_targets(h),
+                        h.spec.min_replicas.to_string(),
+                        h.spec.max_replicas.to_string(),
+                        h.status.current_replicas.to_string(),
+                    ],
+                    h.created_at,
                 );
             }
-            if items.is_empty() {
-                println!("No HPAs found in namespace '{}'", namespace);
-            }
+            (table, format!("No HPAs found in namespace '{}'", namespace))
         }
         "configmaps" | "configmap" | "cm" => {
             let url = format!("{}/api/v1/namespaces/{}/configmaps", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let cms: Vec<ConfigMap> = resp.json().await?;
-            println!("{:<38} {:<20} {:<12} KEYS", "ID", "NAME", "NAMESPACE");
+            let cms: Vec<ConfigMap> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["ID", "NAME", "NAMESPACE", "KEYS"])
+                .numeric(&["KEYS"])
+                .with_age();
             for cm in &cms {
-                println!(
-                    "{:<38} {:<20} {:<12} {}",
-                    cm.id,
-                    cm.name,
-                    cm.namespace,
-                    cm.data.len()
+                table.push_aged(
+                    vec![
+                        cm.id.clone(),
+                        cm.name.clone(),
+                        cm.namespace.clone(),
+                        cm.data.len().to_string(),
+                    ],
+                    cm.created_at,
                 );
             }
-            if cms.is_empty() {
-                println!("No configmaps found in namespace '{}'", namespace);
-            }
+            (
+                table,
+                format!("No configmaps found in namespace '{}'", namespace),
+            )
         }
         "secrets" | "secret" => {
             let url = format!("{}/api/v1/namespaces/{}/secrets", base, namespace);
-            let resp = client.get(&url).send().await?;
-            let secrets: Vec<Secret> = resp.json().await?;
-            println!("{:<38} {:<20} {:<12} KEYS", "ID", "NAME", "NAMESPACE");
+            let secrets: Vec<Secret> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["ID", "NAME", "NAMESPACE", "KEYS"])
+                .numeric(&["KEYS"])
+                .with_age();
             for s in &secrets {
-                println!(
-                    "{:<38} {:<20} {:<12} {}",
-                    s.id,
-                    s.name,
-                    s.namespace,
-                    s.data.len()
+                table.push_aged(
+                    vec![
+                        s.id.clone(),
+                        s.name.clone(),
+                        s.namespace.clone(),
+                        s.data.len().to_string(),
+                    ],
+                    s.created_at,
+                );
+            }
+            (
+                table,
+                format!("No secrets found in namespace '{}'", namespace),
+            )
+        }
+        "limitranges" | "limitrange" | "limits" => {
+            let url = format!("{}/api/v1/namespaces/{}/limitranges", base, namespace);
+            let ranges: Vec<LimitRange> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&[
+                "NAME",
+                "NAMESPACE",
+                "DEFAULT-REQUEST",
+                "DEFAULT-LIMIT",
+                "MIN",
+                "MAX",
+            ])
+            .with_age();
+            for lr in &ranges {
+                table.push_aged(
+                    vec![
+                        lr.name.clone(),
+                        lr.namespace.clone(),
+                        format_resources(&lr.default_request),
+                        format_resources(&lr.default_limit),
+                        format_resources(&lr.min),
+                        format_resources(&lr.max),
+                    ],
+                    lr.created_at,
                 );
             }
-            if secrets.is_empty() {
-                println!("No secrets found in namespace '{}'", namespace);
+            (
+                table,
+                format!("No limit ranges found in namespace '{}'", namespace),
+            )
+        }
+        "poddisruptionbudgets" | "poddisruptionbudget" | "pdb" => {
+            let url = format!(
+                "{}/api/v1/namespaces/{}/poddisruptionbudgets",
+                base, namespace
+            );
+            let budgets: Vec<PodDisruptionBudget> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&[
+                "NAME",
+                "NAMESPACE",
+                "MIN-AVAILABLE",
+                "MAX-UNAVAILABLE",
+                "HEALTHY",
+                "ALLOWED",
+            ])
+            .numeric(&["MIN-AVAILABLE", "MAX-UNAVAILABLE", "HEALTHY", "ALLOWED"])
+            .with_age();
+            let or_dash = |v: Option<u32>| v.map_or("-".to_string(), |v| v.to_string());
+            for pdb in &budgets {
+                table.push_aged(
+                    vec![
+                        pdb.name.clone(),
+                        pdb.namespace.clone(),
+                        or_dash(pdb.min_available),
+                        or_dash(pdb.max_unavailable),
+                        format!(
+                            "{}/{}",
+                            pdb.status.current_healthy, pdb.status.expected_pods
+                        ),
+                        pdb.status.disruptions_allowed.to_string(),
+                    ],
+                    pdb.created_at,
+                );
             }
+            (
+                table,
+                format!(
+                    "No pod disruption budgets found in namespace '{}'",
+                    namespace
+                ),
+            )
         }
         "namespaces" | "namespace" | "ns" => {
             let url = format!("{}/api/v1/namespaces", base);
-            let resp = client.get(&url).send().await?;
-            let nss: Vec<Namespace> = resp.json().await?;
-            println!("{:<20} CREATED", "NAME");
+            let nss: Vec<Namespace> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["NAME", "STATUS"]).status("STATUS").with_age();
             for ns in &nss {
-                println!(
-                    "{:<20} {}",
-                    ns.name,
-                    ns.created_at.format("%Y-%m-%d %H:%M:%S")
+                table.push_aged(vec![ns.name.clone(), ns.phase.to_string()], ns.created_at);
+            }
+            (table, "No namespaces found".to_string())
+        }
+        "priorityclasses" | "priorityclass" | "pc" => {
+            let url = format!("{}/api/v1/priorityclasses", base);
+            let items: Vec<PriorityClass> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["NAME", "VALUE", "GLOBAL-DEFAULT"])
+                .numeric(&["VALUE"])
+                .with_age();
+            for pc in &items {
+                table.push_aged(
+                    vec![
+                        pc.name.clone(),
+                        pc.value.to_string(),
+                        pc.global_default.to_string(),
+                    ],
+                    pc.created_at,
                 );
             }
+            (table, "No priority classes found".to_string())
         }
         "vpcs" | "vpc" => {
             let url = format!("{}/api/v1/vpcs", base);
-            let resp = client.get(&url).send().await?;
-            let vpcs: Vec<Vpc> = resp.json().await?;
-            println!(
-                "{:<6} {:<20} {:<12} {:<18} CREATED",
-                "VPC-ID", "NAME", "STATUS", "CIDR"
-            );
+            let vpcs: Vec<Vpc> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["VPC-ID", "NAME", "STATUS", "CIDR"])
+                .numeric(&["VPC-ID"])
+                .status("STATUS")
+                .with_age();
             for vpc in &vpcs {
-                println!(
-                    "{:<6} {:<20} {:<12} {:<18} {}",
-                    vpc.vpc_id,
-                    vpc.name,
-                    vpc.status,
-                    vpc.ipv4_cidr,
-                    vpc.created_at.format("%Y-%m-%d %H:%M:%S")
+                table.push_aged(
+                    vec![
+                        vpc.vpc_id.to_string(),
+                        vpc.name.clone(),
+                        vpc.status.to_string(),
+                        vpc.ipv4_cidr.clone(),
+                    ],
+                    vpc.created_at,
                 );
             }
-            if vpcs.is_empty() {
-                println!("No VPCs found");
-            }
+            (table, "No VPCs found".to_string())
         }
         "vpc-peerings" | "vpc-peering" | "peerings" | "peering" => {
             let url = format!("{}/api/v1/vpc-peerings", base);
-            let resp = client.get(&url).send().await?;
-            let peerings: Vec<VpcPeering> = resp.json().await?;
-            println!(
-                "{:<20} {:<16} {:<16} {:<15} {:<10} CREATED",
-                "NAME", "VPC-A", "VPC-B", "DIRECTION", "STATUS"
-            );
+            let peerings: Vec<VpcPeering> = fetch_list(client, &url, chunk_size).await?;
+            let mut table = Table::new(&["NAME", "VPC-A", "VPC-B", "DIRECTION", "STATUS"])
+                .status("STATUS")
+                .with_age();
             for p in &peerings {
-                println!(
-                    "{:<20} {:<16} {:<16} {:<15} {:<10} {}",
-                    p.name,
-                    p.vpc_a,
-                    p.vpc_b,
-                    p.direction,
-                    p.status,
-                    p.created_at.format("%Y-%m-%d %H:%M:%S")
+                table.push_aged(
+                    vec![
+                        p.name.clone(),
+                        p.vpc_a.clone(),
+                        p.vpc_b.clone(),
+                        p.direction.to_string(),
+                        p.status.to_string(),
+                    ],
+                    p.created_at,
                 );
             }
-            if peerings.is_empty() {
-                println!("No VPC peerings found");
-            }
+            (table, "No VPC peerings found".to_string())
         }
         other => {
             eprintln!(
-                "Unknown resource type: {}. Supported: pods, services, deployments, replicasets, daemonsets, jobs, cronjobs, hpa, configmaps, secrets, namespaces, vpcs, vpc-peerings",
+                "Unknown resource type: {}. Supported: pods, services, deployments, replicasets, daemonsets, jobs, cronjobs, hpa, configmaps, secrets, limitranges, poddisruptionbudgets, namespaces, priorityclasses, vpcs, vpc-peerings",
                 other
             );
             std::process::exit(1);
         }
+    };
+    let is_empty = table.is_empty();
+    table.print(table_options)?;
+    if is_empty {
+        println!("{}", empty);
+    }
+    Ok(())
+}
+
+/// Fetch one object (or a whole collection with `-o`) and print it in full.
+async fn get_raw(
+    client: &reqwest::Client,
+    base: &str,
+    resource: &str,
+    name: Option<&str>,
+    namespace: &str,
+    output: Option<&str>,
+    chunk_size: usize,
+) -> anyhow::Result<()> {
+    let Some((kind, segment, namespaced)) = resource_path(resource) else {
+        eprintln!("Unknown resource type: {}", resource);
+        std::process::exit(1);
+    };
+    let url = resource_url(base, segment, namespaced, namespace, name);
+
+    let value = match name {
+        Some(_) => fetch_json(client, &url).await?,
+        None => Some(serde_json::Value::Array(
+            fetch_list(client, &url, chunk_size).await?,
+        )),
+    };
+    let Some(value) = value else {
+        match (name, namespaced) {
+            (Some(name), true) => eprintln!(
+                "Error: {} \"{}\" not found in namespace \"{}\"",
+                kind, name, namespace
+            ),
+            (Some(name), false) => eprintln!("Error: {} \"{}\" not found", kind, name),
+            (None, _) => eprintln!("Error: {} not found", segment),
+        }
+        std::process::exit(1);
+    };
+
+    match output {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&value)?),
+        Some(_) => print!("{}", serde_yaml::to_string(&value)?),
+        None if kind == "pod" => print_pod(&serde_json::from_value(value)?),
+        None => print!("{}", serde_yaml::to_string(&value)?),
     }
     Ok(())
 }
+
+/// Full detail view of a single pod.
+fn print_pod(pod: &Pod) {
+    println!("Name:         {}", pod.name);
+    println!("ID:           {}", pod.id);
+    println!("Namespace:    {}", pod.namespace);
+    println!("Status:       {}", pod.status);
+    println!(
+        "Message:      {}",
+        pod.status_message.as_deref().unwrap_or("-")
+    );
+    println!("Node:         {}", pod.node_name.as_deref().unwrap_or("-"));
+    println!("Restarts:     {}", pod.restart_count);
+    println!("Owner:        {}", pod.owner_ref.as_deref().unwrap_or("-"));
+    println!(
+        "Created:      {}",
+        pod.created_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    println!("Containers:");
+    for c in &pod.spec.containers {
+        println!("  {}:", c.name);
+        println!("    Image:    {}", c.image);
+        if !c.command.is_empty() {
+            println!("    Command:  {:?}", c.command);
+        }
+        if !c.args.is_empty() {
+            println!("    Args:     {:?}", c.args);
+        }
+        if c.resources.cpu_millis > 0 || c.resources.memory_bytes > 0 {
+            println!(
+                "    Requests: cpu={}m memory={}",
+                c.resources.cpu_millis, c.resources.memory_bytes
+            );
+        }
+        if c.limits.cpu_millis > 0 || c.limits.memory_bytes > 0 {
+            println!("    Limits:   {}", format_resources(&c.limits));
+        }
+    }
+}
+
+/// `cpu=100m,memory=67108864` for the set fields of `r`, `-` if none is set.
+fn format_resources(r: &ResourceRequirements) -> String {
+    let mut parts = Vec::new();
+    if r.cpu_millis > 0 {
+        parts.push(format!("cpu={}m", r.cpu_millis));
+    }
+    if r.memory_bytes > 0 {
+        parts.push(format!("memory={}", r.memory_bytes));
+    }
+    if parts.is_empty() {
+        "-".to_string()
+    } else {
+        parts.join(",")
+    }
+}
+
+/// `current%/target%` per configured metric, `<unknown>` before the first
+/// usage sample arrives.
+fn hpa_targets(hpa: &HorizontalPodAutoscaler) -> String {
+    let metric = |name: &str, current: Option<u32>, target: Option<u32>| {
+        target.map(|target| match current {
+            Some(current) => format!("{}: {}%/{}%", name, current, target),
+            None => format!("{}: <unknown>/{}%", name, target),
+        })
+    };
+    let targets: Vec<String> = [
+        metric(
+            "cpu",
+            hpa.status.current_cpu_utilization_percent,
+            hpa.spec.metrics.cpu_utilization_percent,
+        ),
+        metric(
+            "memory",
+            hpa.status.current_memory_utilization_percent,
+            hpa.spec.metrics.memory_utilization_percent,
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if targets.is_empty() {
+        "<none>".to_string()
+    } else {
+        targets.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Serve a single canned HTTP response and return the base URL.
+    async fn serve_once(status: &'static str, body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut sock, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = sock.read(&mut buf).await;
+            let resp = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            sock.write_all(resp.as_bytes()).await.unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_hpa_targets() {
+        let mut hpa: HorizontalPodAutoscaler = serde_json::from_value(serde_json::json!({
+            "id": "hpa-id",
+            "name": "web-hpa",
+            "namespace": "default",
+            "spec": {
+                "target_deployment": "web",
+                "min_replicas": 1,
+                "max_replicas": 5,
+                "metrics": { "cpu_utilization_percent": 80, "memory_utilization_percent": 60 },
+            },
+            "created_at": "2024-02-25T00:00:00Z",
+        }))
+        .unwrap();
+        assert_eq!(
+            hpa_targets(&hpa),
+            "cpu: <unknown>/80%, memory: <unknown>/60%"
+        );
+        hpa.status.current_cpu_utilization_percent = Some(93);
+        hpa.spec.metrics.memory_utilization_percent = None;
+        assert_eq!(hpa_targets(&hpa), "cpu: 93%/80%");
+    }
+
+    #[test]
+    fn test_resource_url() {
+        let (kind, segment, namespaced) = resource_path("po").unwrap();
+        assert_eq!(kind, "pod");
+        assert_eq!(
+            resource_url("http://s", segment, namespaced, "prod", Some("web-0")),
+            "http://s/api/v1/namespaces/prod/pods/web-0"
+        );
+        let (_, segment, namespaced) = resource_path("ns").unwrap();
+        assert_eq!(
+            resource_url("http://s", segment, namespaced, "prod", Some("prod")),
+            "http://s/api/v1/namespaces/prod"
+        );
+        assert!(resource_path("widgets").is_none());
+    }
+
+    #[test]
+    fn test_completion_offers_resource_types() {
+        for &name in RESOURCE_TYPES.iter().filter(|&&r| r != "vpc-peerings") {
+            assert!(resource_path(name).is_some(), "{}", name);
+        }
+        let mut script = Vec::new();
+        clap_complete::generate(
+            clap_complete::Shell::Bash,
+            &mut <Cli as clap::CommandFactory>::command(),
+            "k3rsctl",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("poddisruptionbudgets"));
+        assert!(script.contains("vpc-peerings"));
+
+        // Names outside the list still parse, for the command to report.
+        let cli = Cli::try_parse_from(["k3rsctl", "get", "widgets"]).unwrap();
+        assert!(matches!(cli.command, Commands::Get { ref resource, .. } if resource == "widgets"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_existing_object() {
+        let base = serve_once("200 OK", r#"{"name":"web-0","restart_count":2}"#).await;
+        let client = reqwest::Client::new();
+        let value = fetch_json(
+            &client,
+            &format!("{}/api/v1/namespaces/default/pods/web-0", base),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(value["name"], "web-0");
+        assert_eq!(value["restart_count"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_missing_object_is_none() {
+        let base = serve_once("404 Not Found", "pod 'ghost' not found").await;
+        let client = reqwest::Client::new();
+        let value = fetch_json(
+            &client,
+            &format!("{}/api/v1/namespaces/default/pods/ghost", base),
+        )
+        .await
+        .unwrap();
+        assert!(value.is_none());
+    }
+
+    /// Serve a three-page list of numbers, two per page, checking that every
+    /// request carries the limit and the previous page's token.
+    async fn serve_pages() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut sock, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let n = sock.read(&mut buf).await.unwrap();
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                assert!(path.contains("limit=2"), "{}", path);
+                let (body, next) = if path.contains("continue=p3") {
+                    ("[5]", None)
+                } else if path.contains("continue=p2") {
+                    ("[3,4]", Some("p3"))
+                } else {
+                    ("[1,2]", Some("p2"))
+                };
+                let header = next
+                    .map(|t| format!("{}: {}\r\n", pkg_constants::state::CONTINUE_HEADER, t))
+                    .unwrap_or_default();
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n{}content-length: {}\r\nconnection: close\r\n\r\n{}",
+                    header,
+                    body.len(),
+                    body
+                );
+                sock.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_fetch_list_follows_continue_tokens() {
+        let base = serve_pages().await;
+        let client = reqwest::Client::new();
+        let items: Vec<u32> = fetch_list(&client, &format!("{}/api/v1/namespaces", base), 2)
+            .await
+            .unwrap();
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_get_name_and_output_flags_parse() {
+        let cli = Cli::try_parse_from([
+            "k3rsctl", "get", "pod", "my-pod", "-n", "prod", "-o", "json",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Get {
+                resource,
+                name,
+                namespace,
+                output,
+                watch,
+                chunk_size,
+                sort_by,
+            } => {
+                assert_eq!(resource, "pod");
+                assert!(!watch);
+                assert_eq!(chunk_size, pkg_constants::state::DEFAULT_LIST_CHUNK_SIZE);
+                assert_eq!(name.as_deref(), Some("my-pod"));
+                assert_eq!(namespace, "prod");
+                assert_eq!(output.as_deref(), Some("json"));
+                assert!(sort_by.is_none());
+            }
+            _ => panic!("expected get command"),
+        }
+
+        let cli = Cli::try_parse_from(["k3rsctl", "get", "pods"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Get {
+                name: None,
+                output: None,
+                ..
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["k3rsctl", "get", "pods", "--chunk-size", "50"]).unwrap();
+        assert!(matches!(cli.command, Commands::Get { chunk_size: 50, .. }));
+
+        let cli = Cli::try_parse_from(["k3rsctl", "get", "pods", "-w"]).unwrap();
+        assert!(matches!(cli.command, Commands::Get { watch: true, .. }));
+        assert!(Cli::try_parse_from(["k3rsctl", "get", "pods", "web", "--watch"]).is_err());
+    }
+}
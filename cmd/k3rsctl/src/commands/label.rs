@@ -0,0 +1,155 @@
+use super::get::{resource_path, resource_url};
+
+/// One label argument: `key=value` sets it, `key-` removes it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelChange {
+    Set(String, String),
+    Remove(String),
+}
+
+/// Parse a `key=value` or `key-` argument. Key and value syntax is left to
+/// the server.
+pub fn parse_label(s: &str) -> Result<LabelChange, String> {
+    if let Some((key, value)) = s.split_once('=') {
+        if key.is_empty() {
+            return Err(format!("label '{}' has no key", s));
+        }
+        return Ok(LabelChange::Set(key.to_string(), value.to_string()));
+    }
+    match s.strip_suffix('-') {
+        Some(key) if !key.is_empty() => Ok(LabelChange::Remove(key.to_string())),
+        _ => Err(format!(
+            "invalid label '{}': expected key=value to set it or key- to remove it",
+            s
+        )),
+    }
+}
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    resource: &str,
+    name: &str,
+    labels: &[LabelChange],
+    namespace: &str,
+) -> anyhow::Result<()> {
+    let (kind, url) = label_url(base, resource, name, namespace)?;
+    let resp = client
+        .patch(&url)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            pkg_types::patch::MERGE_PATCH_CONTENT_TYPE,
+        )
+        .body(label_patch(labels).to_string())
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        super::print_api_error(resp).await;
+        std::process::exit(1);
+    }
+    println!("{}/{} labeled", kind, name);
+    Ok(())
+}
+
+/// The kind and URL of a labelable object. Nodes are not in the generic
+/// resource table: they are registered, not applied.
+fn label_url(
+    base: &str,
+    resource: &str,
+    name: &str,
+    namespace: &str,
+) -> anyhow::Result<(&'static str, String)> {
+    if matches!(resource, "nodes" | "node" | "no") {
+        return Ok(("node", format!("{}/api/v1/nodes/{}", base, name)));
+    }
+    match resource_path(resource) {
+        Some((kind @ ("pod" | "deployment" | "namespace"), segment, namespaced)) => Ok((
+            kind,
+            resource_url(base, segment, namespaced, namespace, Some(name)),
+        )),
+        Some((kind, _, _)) => anyhow::bail!("{} objects cannot be labeled", kind),
+        None => anyhow::bail!("unknown resource type '{}'", resource),
+    }
+}
+
+/// A merge patch of the object's labels: removals are `null`.
+fn label_patch(labels: &[LabelChange]) -> serde_json::Value {
+    let labels: serde_json::Map<String, serde_json::Value> = labels
+        .iter()
+        .map(|change| match change {
+            LabelChange::Set(key, value) => (key.clone(), value.clone().into()),
+            LabelChange::Remove(key) => (key.clone(), serde_json::Value::Null),
+        })
+        .collect();
+    serde_json::json!({ "labels": labels })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+
+    #[test]
+    fn test_parse_label() {
+        assert_eq!(
+            parse_label("app=web"),
+            Ok(LabelChange::Set("app".to_string(), "web".to_string()))
+        );
+        // An empty value is a value; a trailing `-` after `=` is part of it.
+        assert_eq!(
+            parse_label("canary="),
+            Ok(LabelChange::Set("canary".to_string(), String::new()))
+        );
+        assert_eq!(
+            parse_label("tier=front-"),
+            Ok(LabelChange::Set("tier".to_string(), "front-".to_string()))
+        );
+        assert_eq!(
+            parse_label("k3rs.io/zone-"),
+            Ok(LabelChange::Remove("k3rs.io/zone".to_string()))
+        );
+        assert!(parse_label("app").is_err());
+        assert!(parse_label("-").is_err());
+        assert!(parse_label("=web").is_err());
+    }
+
+    #[test]
+    fn test_label_args() {
+        let cli = Cli::try_parse_from([
+            "k3rsctl",
+            "label",
+            "deploy",
+            "web",
+            "tier=front",
+            "canary-",
+            "-n",
+            "prod",
+        ])
+        .unwrap();
+        let Commands::Label {
+            resource,
+            name,
+            labels,
+            namespace,
+        } = cli.command
+        else {
+            panic!("expected label command");
+        };
+        assert_eq!(
+            label_patch(&labels),
+            serde_json::json!({ "labels": { "tier": "front", "canary": null } })
+        );
+        let (kind, url) = label_url("http://api", &resource, &name, &namespace).unwrap();
+        assert_eq!(kind, "deployment");
+        assert_eq!(url, "http://api/api/v1/namespaces/prod/deployments/web");
+        assert_eq!(
+            label_url("http://api", "node", "worker-1", &namespace).unwrap(),
+            ("node", "http://api/api/v1/nodes/worker-1".to_string())
+        );
+        assert!(label_url("http://api", "configmap", "cfg", "default").is_err());
+
+        assert!(Cli::try_parse_from(["k3rsctl", "label", "node", "worker-1"]).is_err());
+        assert!(Cli::try_parse_from(["k3rsctl", "label", "node", "worker-1", "zone"]).is_err());
+    }
+}
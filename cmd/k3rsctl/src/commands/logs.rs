@@ -1,38 +1,211 @@
+use std::io::Write;
+
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+
+/// Parse a relative duration such as `30s`, `5m`, `1h30m` or `2d`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty duration".to_string());
+    }
+
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let n: i64 = digits
+            .parse()
+            .map_err(|_| format!("invalid duration '{}': expected a number before '{}'", s, c))?;
+        digits.clear();
+        let part = match c {
+            's' => Duration::try_seconds(n),
+            'm' => Duration::try_minutes(n),
+            'h' => Duration::try_hours(n),
+            'd' => Duration::try_days(n),
+            _ => {
+                return Err(format!(
+                    "invalid duration '{}': unknown unit '{}' (use s, m, h or d)",
+                    s, c
+                ));
+            }
+        };
+        total = part
+            .and_then(|p| total.checked_add(&p))
+            .ok_or_else(|| format!("duration '{}' is too large", s))?;
+    }
+    if !digits.is_empty() {
+        return Err(format!(
+            "invalid duration '{}': missing unit after '{}' (use s, m, h or d)",
+            s, digits
+        ));
+    }
+    Ok(total)
+}
+
+/// Build the query string for the server's pod logs endpoint.
+fn log_query(
+    follow: bool,
+    since: Option<DateTime<Utc>>,
+    timestamps: bool,
+    container: Option<&str>,
+) -> String {
+    let mut params = vec![
+        format!("follow={}", follow),
+        format!("timestamps={}", timestamps),
+    ];
+    if let Some(since) = since {
+        params.push(format!(
+            "since={}",
+            since.to_rfc3339_opts(SecondsFormat::Nanos, true)
+        ));
+    }
+    if let Some(container) = container {
+        params.push(format!("container={}", container));
+    }
+    params.join("&")
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn handle(
     client: &reqwest::Client,
     base: &str,
     pod_id: &str,
     namespace: &str,
     follow: bool,
+    since: Option<Duration>,
+    timestamps: bool,
+    container: Option<&str>,
 ) -> anyhow::Result<()> {
+    let since = since.map(|d| Utc::now() - d);
     let url = format!(
-        "{}/api/v1/namespaces/{}/pods/{}/logs",
-        base, namespace, pod_id
+        "{}/api/v1/namespaces/{}/pods/{}/logs?{}",
+        base,
+        namespace,
+        pod_id,
+        log_query(follow, since, timestamps, container)
     );
 
-    loop {
-        let resp = client.get(&url).send().await?;
-        if resp.status().is_success() {
-            let body: serde_json::Value = resp.json().await?;
-            if let Some(logs) = body.get("logs").and_then(|l| l.as_array()) {
-                for line in logs {
-                    println!("{}", line.as_str().unwrap_or(""));
-                }
+    if follow {
+        return follow_logs(client, &url, pod_id, namespace).await;
+    }
+
+    let resp = client.get(&url).send().await?;
+    if resp.status().is_success() {
+        let body: serde_json::Value = resp.json().await?;
+        if let Some(logs) = body.get("logs").and_then(|l| l.as_array()) {
+            for line in logs {
+                println!("{}", line.as_str().unwrap_or(""));
             }
-        } else if resp.status().as_u16() == 404 {
-            eprintln!("Pod {} not found in namespace {}", pod_id, namespace);
-            break;
-        } else {
-            eprintln!("Failed to get logs: {}", resp.status());
-            break;
         }
+    } else if resp.status().as_u16() == 404 {
+        eprintln!("Pod {} not found in namespace {}", pod_id, namespace);
+    } else {
+        super::print_api_error(resp).await;
+    }
+    Ok(())
+}
 
-        if !follow {
-            break;
-        }
+/// Print the server's chunked log stream as it arrives, until the stream ends.
+async fn follow_logs(
+    client: &reqwest::Client,
+    url: &str,
+    pod_id: &str,
+    namespace: &str,
+) -> anyhow::Result<()> {
+    let mut resp = client.get(url).send().await?;
+    if resp.status().as_u16() == 404 {
+        eprintln!("Pod {} not found in namespace {}", pod_id, namespace);
+        return Ok(());
+    }
+    if !resp.status().is_success() {
+        super::print_api_error(resp).await;
+        return Ok(());
+    }
 
-        // Poll every 2 seconds in follow mode
-        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    let mut stdout = std::io::stdout();
+    while let Some(chunk) = resp.chunk().await? {
+        stdout.write_all(&chunk)?;
+        stdout.flush()?;
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::seconds(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::minutes(5)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::hours(2)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::days(1)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::minutes(90)));
+    }
+
+    #[test]
+    fn test_parse_duration_rejects_malformed() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("99999999999999999d").is_err());
+    }
+
+    #[test]
+    fn test_log_query() {
+        assert_eq!(
+            log_query(false, None, false, None),
+            "follow=false&timestamps=false"
+        );
+        let since = DateTime::parse_from_rfc3339("2024-05-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            log_query(true, Some(since), true, None),
+            "follow=true&timestamps=true&since=2024-05-01T12:00:00.000000000Z"
+        );
+        assert_eq!(
+            log_query(false, None, false, Some("sidecar")),
+            "follow=false&timestamps=false&container=sidecar"
+        );
+    }
+
+    #[test]
+    fn test_logs_flags_parse() {
+        let cli =
+            Cli::try_parse_from(["k3rsctl", "logs", "web-0", "--since", "5m", "--timestamps"])
+                .unwrap();
+        match cli.command {
+            Commands::Logs {
+                pod_id,
+                since,
+                timestamps,
+                follow,
+                container,
+                ..
+            } => {
+                assert_eq!(pod_id, "web-0");
+                assert_eq!(since, Some(Duration::minutes(5)));
+                assert!(timestamps);
+                assert!(!follow);
+                assert_eq!(container, None);
+            }
+            _ => panic!("expected logs command"),
+        }
+
+        let cli = Cli::try_parse_from(["k3rsctl", "logs", "web-0", "-c", "sidecar"]).unwrap();
+        match cli.command {
+            Commands::Logs { container, .. } => assert_eq!(container.as_deref(), Some("sidecar")),
+            _ => panic!("expected logs command"),
+        }
+
+        assert!(Cli::try_parse_from(["k3rsctl", "logs", "web-0", "--since", "soon"]).is_err());
+    }
+}
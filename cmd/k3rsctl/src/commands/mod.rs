@@ -1,34 +1,103 @@
 pub mod apply;
 pub mod backup;
 pub mod cluster;
+pub mod config;
+pub mod cp;
+pub mod debug;
 pub mod delete;
 pub mod describe;
 pub mod doctor;
+pub mod edit;
 pub mod exec;
 pub mod get;
+pub mod label;
 pub mod logs;
 pub mod node;
+pub mod patch;
+pub mod port_forward;
+pub mod rollout;
 pub mod runtime;
+pub mod scale;
+pub mod table;
+pub mod token;
+pub mod top;
+pub mod watch;
 
 use crate::cli::*;
+use crate::config::Connection;
+use pkg_types::error::{ApiError, ErrorReason};
+use table::TableOptions;
+
+/// How often an update is resent after losing a resource version conflict.
+const MAX_UPDATE_ATTEMPTS: usize = 3;
 
 /// Dispatch a CLI command to the appropriate handler.
-pub async fn dispatch(cli: &Cli, client: &reqwest::Client) -> anyhow::Result<()> {
-    let base = cli.server.trim_end_matches('/');
+pub async fn dispatch(
+    cli: &Cli,
+    conn: &Connection,
+    client: &reqwest::Client,
+) -> anyhow::Result<()> {
+    let base = conn.server.trim_end_matches('/');
+    let table = TableOptions::new(cli.no_color, None);
 
     match &cli.command {
         Commands::Cluster { action } => cluster::handle(client, base, action).await,
-        Commands::Node { action } => node::handle(client, base, action).await,
+        Commands::Node { action } => node::handle(client, base, action, cli.no_color).await,
         Commands::Get {
             resource,
             namespace,
-        } => get::handle(client, base, resource, namespace).await,
+            watch: true,
+            ..
+        } => watch::handle(client, base, resource, namespace).await,
+        Commands::Get {
+            resource,
+            name,
+            namespace,
+            output,
+            watch: false,
+            chunk_size,
+            sort_by,
+        } => {
+            get::handle(
+                client,
+                base,
+                resource,
+                name.as_deref(),
+                namespace,
+                output.as_deref(),
+                *chunk_size,
+                &TableOptions::new(cli.no_color, sort_by.as_deref()),
+            )
+            .await
+        }
         Commands::Describe {
             resource,
             name,
             namespace,
         } => describe::handle(client, base, resource, name, namespace).await,
-        Commands::Apply { file, namespace } => apply::handle(client, base, file, namespace).await,
+        Commands::Edit {
+            resource,
+            name,
+            namespace,
+        } => edit::handle(client, base, resource, name, namespace).await,
+        Commands::Apply {
+            file,
+            namespace,
+            dry_run,
+        } => apply::handle(client, base, file, namespace, *dry_run).await,
+        Commands::Patch {
+            resource,
+            name,
+            patch,
+            patch_type,
+            namespace,
+        } => patch::handle(client, base, resource, name, patch, *patch_type, namespace).await,
+        Commands::Label {
+            resource,
+            name,
+            labels,
+            namespace,
+        } => label::handle(client, base, resource, name, labels, namespace).await,
         Commands::Delete {
             resource,
             id,
@@ -49,31 +118,176 @@ pub async fn dispatch(cli: &Cli, client: &reqwest::Client) -> anyhow::Result<()>
             pod_id,
             namespace,
             follow,
-        } => logs::handle(client, base, pod_id, namespace, *follow).await,
+            since,
+            timestamps,
+            container,
+        } => {
+            logs::handle(
+                client,
+                base,
+                pod_id,
+                namespace,
+                *follow,
+                *since,
+                *timestamps,
+                container.as_deref(),
+            )
+            .await
+        }
         Commands::Exec {
             pod_id,
             command,
             namespace,
-            interactive,
+            stdin,
+            tty,
+            container,
         } => {
             exec::handle(
-                &cli.server,
-                &cli.token,
+                &conn.server,
+                &conn.token,
                 pod_id,
                 command,
                 namespace,
-                *interactive,
+                *stdin,
+                *tty,
+                container.as_deref(),
             )
             .await
         }
+        Commands::Cp {
+            src,
+            dest,
+            namespace,
+            container,
+        } => cp::handle(client, base, src, dest, namespace, container.as_deref()).await,
+        Commands::PortForward {
+            pod,
+            ports,
+            namespace,
+        } => port_forward::handle(&conn.server, &conn.token, pod, ports, namespace).await,
         Commands::Doctor { fix } => doctor::handle(client, base, *fix).await,
-        Commands::Runtime { action } => runtime::handle(client, &cli.server, action).await,
-        Commands::Backup { action } => backup::handle_backup(client, base, action).await,
+        Commands::Scale {
+            resource,
+            name,
+            replicas,
+            current_replicas,
+            namespace,
+        } => {
+            scale::handle(
+                client,
+                base,
+                resource,
+                name,
+                *replicas,
+                *current_replicas,
+                namespace,
+            )
+            .await
+        }
+        Commands::Top { action } => top::handle(client, base, action, &table).await,
+        Commands::Rollout { action } => rollout::handle(client, base, action, &table).await,
+        Commands::Runtime { action } => runtime::handle(client, &conn.server, action).await,
+        Commands::Token { action } => token::handle(client, base, action, &table).await,
+        Commands::Debug { action } => debug::handle(client, base, action).await,
+        Commands::Backup { action } => backup::handle_backup(client, base, action, &table).await,
         Commands::Restore {
             from,
             dry_run,
             force,
         } => backup::handle_restore(client, base, from, *dry_run, *force).await,
-        Commands::Pm { .. } => unreachable!("handled before dispatch"),
+        Commands::Pm { .. } | Commands::Config { .. } | Commands::Completion { .. } => {
+            unreachable!("handled before dispatch")
+        }
+    }
+}
+
+/// Read the [`ApiError`] out of a failed response.
+pub(crate) async fn api_error(resp: reqwest::Response) -> ApiError {
+    let status = resp.status().as_u16();
+    let body = resp.text().await.unwrap_or_default();
+    ApiError::from_response(status, &body)
+}
+
+/// `Error from server (<reason>): <message>`, then one indented line per
+/// failing field.
+pub(crate) fn render_error(err: &ApiError) -> String {
+    let mut out = format!("Error from server ({}): {}", err.reason, err.message);
+    for detail in &err.details {
+        out.push_str(&format!("\n  {}: {}", detail.field, detail.message));
+    }
+    out
+}
+
+/// The resource version of the object at `url`, or 0 if there is none.
+pub(crate) async fn current_resource_version(
+    client: &reqwest::Client,
+    url: &str,
+) -> anyhow::Result<u64> {
+    let resp = client.get(url).send().await?;
+    if resp.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(0);
+    }
+    if !resp.status().is_success() {
+        anyhow::bail!("{}", render_error(&api_error(resp).await));
+    }
+    let obj: serde_json::Value = resp.json().await?;
+    Ok(obj["resource_version"].as_u64().unwrap_or(0))
+}
+
+/// The resource version to retry with when `err` rejected an update because
+/// the object changed since it was read.
+pub(crate) fn conflict_version(err: &ApiError) -> Option<u64> {
+    if err.reason != ErrorReason::Conflict {
+        return None;
+    }
+    err.current.as_ref()?["resource_version"].as_u64()
+}
+
+/// Print a failed response's error to stderr.
+pub(crate) async fn print_api_error(resp: reqwest::Response) {
+    eprintln!("{}", render_error(&api_error(resp).await));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pkg_types::error::FieldError;
+
+    #[test]
+    fn test_conflict_version() {
+        let current = serde_json::json!({ "name": "web", "resource_version": 12 });
+        let err = ApiError::version_conflict("deployment", "web", 9, Some(current));
+        assert_eq!(conflict_version(&err), Some(12));
+        assert_eq!(
+            conflict_version(&ApiError::version_conflict("deployment", "web", 9, None)),
+            None
+        );
+        assert_eq!(
+            conflict_version(&ApiError::not_found("deployment", "web", None)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_render_error() {
+        let body = serde_json::to_string(&ApiError::invalid(
+            "pod",
+            "web",
+            vec![
+                FieldError::new("spec.containers[0].image", "must not be empty"),
+                FieldError::new("spec.containers[0].resources.cpu_millis", "exceeds 500"),
+            ],
+        ))
+        .unwrap();
+        assert_eq!(
+            render_error(&ApiError::from_response(422, &body)),
+            "Error from server (Invalid): pod 'web' is invalid\n  \
+             spec.containers[0].image: must not be empty\n  \
+             spec.containers[0].resources.cpu_millis: exceeds 500"
+        );
+        assert_eq!(
+            render_error(&ApiError::from_response(404, "")),
+            "Error from server (NotFound): request failed with status 404"
+        );
     }
 }
@@ -1,51 +1,144 @@
+use super::table::{Table, TableOptions};
 use crate::cli::NodeAction;
-use pkg_types::node::Node;
+use pkg_types::node::{DrainReport, Node, Taint};
+use pkg_types::pod::TaintEffect;
+
+/// One `node taint` argument.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaintChange {
+    Add(Taint),
+    /// Remove the taints with `key`: the one with `effect`, or all of them.
+    Remove {
+        key: String,
+        effect: Option<TaintEffect>,
+    },
+}
+
+/// Parse `key=value:Effect` or `key:Effect` to add a taint, and the same
+/// with a trailing `-` (the value may be left out) or a bare `key-` to
+/// remove one.
+pub fn parse_taint(s: &str) -> Result<TaintChange, String> {
+    if let Some(spec) = s.strip_suffix('-') {
+        let (key, effect) = match spec.split_once(':') {
+            Some((key, effect)) => (key, Some(effect.parse::<TaintEffect>()?)),
+            None => (spec, None),
+        };
+        let key = key.split_once('=').map_or(key, |(key, _)| key);
+        if key.is_empty() {
+            return Err(format!("taint '{}' has no key", s));
+        }
+        return Ok(TaintChange::Remove {
+            key: key.to_string(),
+            effect,
+        });
+    }
+    let Some((key, effect)) = s.split_once(':') else {
+        return Err(format!(
+            "invalid taint '{}': expected key=value:Effect, key:Effect, or key- to remove it",
+            s
+        ));
+    };
+    let (key, value) = key.split_once('=').unwrap_or((key, ""));
+    if key.is_empty() {
+        return Err(format!("taint '{}' has no key", s));
+    }
+    Ok(TaintChange::Add(Taint {
+        key: key.to_string(),
+        value: value.to_string(),
+        effect: effect.parse()?,
+    }))
+}
 
 pub async fn handle(
     client: &reqwest::Client,
     base: &str,
     action: &NodeAction,
+    no_color: bool,
 ) -> anyhow::Result<()> {
     match action {
-        NodeAction::List => {
+        NodeAction::List { output, sort_by } => {
+            let wide = match output.as_deref() {
+                None => false,
+                Some("wide") => true,
+                Some(other) => {
+                    eprintln!("Unsupported output format: {} (use wide)", other);
+                    std::process::exit(1);
+                }
+            };
             let url = format!("{}/api/v1/nodes", base);
             let resp = client.get(&url).send().await?;
             if !resp.status().is_success() {
-                eprintln!("Error: server returned {}", resp.status());
+                super::print_api_error(resp).await;
                 std::process::exit(1);
             }
             let nodes: Vec<Node> = resp.json().await?;
-            println!("{:<38} {:<16} {:<10} REGISTERED", "ID", "NAME", "STATUS");
+            let mut headers = vec!["ID", "NAME", "STATUS"];
+            if wide {
+                headers.extend(["CPU", "MEMORY", "CONTAINERS", "VERSION", "CONDITIONS"]);
+            }
+            let mut table = Table::new(&headers)
+                .numeric(&["CONTAINERS"])
+                .status("STATUS")
+                .with_age();
             for node in &nodes {
-                println!(
-                    "{:<38} {:<16} {:<10} {}",
-                    node.id,
-                    node.name,
-                    node.status,
-                    node.registered_at.format("%Y-%m-%d %H:%M:%S")
-                );
+                let mut cells = vec![node.id.clone(), node.name.clone(), node.status.to_string()];
+                if wide {
+                    cells.extend([
+                        usage_column(
+                            node.usage.cpu_millis,
+                            node.capacity.cpu_millis,
+                            format_millicores,
+                        ),
+                        usage_column(
+                            node.usage.memory_bytes,
+                            node.capacity.memory_bytes,
+                            format_bytes,
+                        ),
+                        node.running_containers.to_string(),
+                        node.agent_version
+                            .clone()
+                            .unwrap_or_else(|| "-".to_string()),
+                        conditions_column(node),
+                    ]);
+                }
+                table.push_aged(cells, node.registered_at);
             }
-            if nodes.is_empty() {
+            let is_empty = table.is_empty();
+            table.print(&TableOptions::new(no_color, sort_by.as_deref()))?;
+            if is_empty {
                 println!("(no nodes registered)");
             }
         }
-        NodeAction::Drain { name } => {
-            let url = format!("{}/api/v1/nodes/{}/drain", base, name);
-            let resp = client.post(&url).send().await?;
-            if resp.status().is_success() {
-                let body: serde_json::Value = resp.json().await?;
-                println!(
-                    "Node {} drained ({} pods evicted)",
-                    name,
-                    body.get("evicted_pods")
-                        .and_then(|v| v.as_u64())
-                        .unwrap_or(0)
+        NodeAction::Drain {
+            name,
+            timeout,
+            wait_for_reschedule,
+            force,
+        } => {
+            let mut url = format!(
+                "{}/api/v1/nodes/{}/drain?wait_for_reschedule={}&force={}",
+                base, name, wait_for_reschedule, force
+            );
+            if let Some(secs) = timeout {
+                url.push_str(&format!("&timeout_seconds={}", secs));
+            }
+            if *force {
+                eprintln!(
+                    "Warning: --force evicts pods even when their PodDisruptionBudgets do not allow it"
                 );
-            } else {
-                eprintln!("Failed to drain node {}: {}", name, resp.status());
-                if let Ok(text) = resp.text().await {
-                    eprintln!("  {}", text);
-                }
+            }
+            println!("Draining node {}...", name);
+            let resp = client.post(&url).send().await?;
+            if !resp.status().is_success() {
+                super::print_api_error(resp).await;
+                std::process::exit(1);
+            }
+            let report: DrainReport = resp.json().await?;
+            for line in drain_lines(&report) {
+                println!("{}", line);
+            }
+            if !report.complete {
+                std::process::exit(1);
             }
         }
         NodeAction::Cordon { name } => {
@@ -54,7 +147,7 @@ pub async fn handle(
             if resp.status().is_success() {
                 println!("Node {} cordoned", name);
             } else {
-                eprintln!("Failed to cordon node {}: {}", name, resp.status());
+                super::print_api_error(resp).await;
             }
         }
         NodeAction::Uncordon { name } => {
@@ -63,9 +156,268 @@ pub async fn handle(
             if resp.status().is_success() {
                 println!("Node {} uncordoned", name);
             } else {
-                eprintln!("Failed to uncordon node {}: {}", name, resp.status());
+                super::print_api_error(resp).await;
+            }
+        }
+        NodeAction::Taint { name, taints } => {
+            let url = format!("{}/api/v1/nodes/{}/taints", base, name);
+            for change in taints {
+                let (req, done) = match change {
+                    TaintChange::Add(taint) => (
+                        client.put(&url).json(&serde_json::json!({
+                            "key": taint.key,
+                            "value": taint.value,
+                            "effect": taint.effect.to_string(),
+                        })),
+                        format!("tainted {}", taint),
+                    ),
+                    TaintChange::Remove { key, effect } => {
+                        let mut url = reqwest::Url::parse(&url)?;
+                        url.query_pairs_mut().append_pair("key", key);
+                        if let Some(effect) = effect {
+                            url.query_pairs_mut()
+                                .append_pair("effect", &effect.to_string());
+                        }
+                        let removed = match effect {
+                            Some(effect) => format!("{}:{}", key, effect),
+                            None => key.clone(),
+                        };
+                        (client.delete(url), format!("untainted {}", removed))
+                    }
+                };
+                let resp = req.send().await?;
+                if !resp.status().is_success() {
+                    super::print_api_error(resp).await;
+                    std::process::exit(1);
+                }
+                println!("node/{} {}", name, done);
             }
         }
     }
     Ok(())
 }
+
+/// One line per pod of a drain report, then a summary line.
+fn drain_lines(report: &DrainReport) -> Vec<String> {
+    let mut lines: Vec<String> = report
+        .pods
+        .iter()
+        .map(|p| match p.message {
+            Some(ref msg) => format!("pod/{}/{} {} ({})", p.namespace, p.name, p.state, msg),
+            None => format!("pod/{}/{} {}", p.namespace, p.name, p.state),
+        })
+        .collect();
+    lines.push(if report.complete {
+        format!("Node {} drained", report.node)
+    } else {
+        format!("Node {} not fully drained: timed out", report.node)
+    });
+    lines
+}
+
+/// The node's pressure conditions, e.g. `DiskPressure,MemoryPressure`, or
+/// `-` when none hold.
+fn conditions_column(node: &Node) -> String {
+    if node.conditions.is_empty() {
+        return "-".to_string();
+    }
+    node.conditions
+        .iter()
+        .map(|c| c.condition_type.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// `used/capacity` for a wide-output column, e.g. `1.5/4` cores.
+fn usage_column(used: u64, capacity: u64, fmt: fn(u64) -> String) -> String {
+    format!("{}/{}", fmt(used), fmt(capacity))
+}
+
+fn format_millicores(m: u64) -> String {
+    if m.is_multiple_of(1000) {
+        format!("{}", m / 1000)
+    } else {
+        format!("{}m", m)
+    }
+}
+
+fn format_bytes(b: u64) -> String {
+    const GIB: f64 = (1u64 << 30) as f64;
+    const MIB: f64 = (1u64 << 20) as f64;
+    if b as f64 >= GIB {
+        format!("{:.1}Gi", b as f64 / GIB)
+    } else {
+        format!("{}Mi", (b as f64 / MIB).round() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+
+    #[test]
+    fn test_usage_columns() {
+        assert_eq!(usage_column(1500, 4000, format_millicores), "1500m/4");
+        assert_eq!(usage_column(3 << 29, 8 << 30, format_bytes), "1.5Gi/8.0Gi");
+        assert_eq!(format_bytes(256 << 20), "256Mi");
+    }
+
+    #[test]
+    fn test_conditions_column() {
+        let mut node: Node = serde_json::from_value(serde_json::json!({
+            "id": "n1", "name": "node-a", "address": "10.0.0.5", "agent_api_port": 10250,
+            "status": "Ready", "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:00:00Z", "labels": {},
+        }))
+        .unwrap();
+        assert_eq!(conditions_column(&node), "-");
+        node.conditions = serde_json::from_value(serde_json::json!([
+            { "type": "DiskPressure", "message": "disk 93% used", "since": "2024-02-25T00:00:00Z" },
+            { "type": "MemoryPressure", "message": "memory 96% used", "since": "2024-02-25T00:00:00Z" },
+        ]))
+        .unwrap();
+        assert_eq!(conditions_column(&node), "DiskPressure,MemoryPressure");
+    }
+
+    #[test]
+    fn test_drain_lines() {
+        let report: DrainReport = serde_json::from_value(serde_json::json!({
+            "node": "node-a",
+            "pods": [
+                { "namespace": "default", "name": "logs-node-a", "state": "skipped",
+                  "message": "managed by a DaemonSet" },
+                { "namespace": "default", "name": "web-a", "state": "awaiting_reschedule" },
+                { "namespace": "default", "name": "api-a", "state": "blocked",
+                  "message": "waiting on PDB api (3/3 required available)" },
+            ],
+            "complete": false,
+        }))
+        .unwrap();
+        assert_eq!(
+            drain_lines(&report),
+            vec![
+                "pod/default/logs-node-a skipped (managed by a DaemonSet)",
+                "pod/default/web-a awaiting reschedule",
+                "pod/default/api-a blocked (waiting on PDB api (3/3 required available))",
+                "Node node-a not fully drained: timed out",
+            ]
+        );
+
+        let cli = Cli::try_parse_from([
+            "k3rsctl",
+            "node",
+            "drain",
+            "node-a",
+            "--timeout",
+            "60",
+            "--wait-for-reschedule",
+            "--force",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Node {
+                action: NodeAction::Drain {
+                    timeout: Some(60),
+                    wait_for_reschedule: true,
+                    force: true,
+                    ..
+                }
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_taint() {
+        let add = |key: &str, value: &str, effect| {
+            Ok(TaintChange::Add(Taint {
+                key: key.to_string(),
+                value: value.to_string(),
+                effect,
+            }))
+        };
+        let remove = |key: &str, effect| {
+            Ok(TaintChange::Remove {
+                key: key.to_string(),
+                effect,
+            })
+        };
+        assert_eq!(
+            parse_taint("dedicated=gpu:NoSchedule"),
+            add("dedicated", "gpu", TaintEffect::NoSchedule)
+        );
+        assert_eq!(
+            parse_taint("k3rs.io/maintenance:NoExecute"),
+            add("k3rs.io/maintenance", "", TaintEffect::NoExecute)
+        );
+        // A value may end in `-` only when an effect follows it.
+        assert_eq!(
+            parse_taint("tier=front-:PreferNoSchedule"),
+            add("tier", "front-", TaintEffect::PreferNoSchedule)
+        );
+        assert_eq!(parse_taint("dedicated-"), remove("dedicated", None));
+        assert_eq!(
+            parse_taint("dedicated:NoExecute-"),
+            remove("dedicated", Some(TaintEffect::NoExecute))
+        );
+        assert_eq!(
+            parse_taint("dedicated=gpu:NoSchedule-"),
+            remove("dedicated", Some(TaintEffect::NoSchedule))
+        );
+
+        assert!(parse_taint("dedicated=gpu").is_err());
+        assert!(parse_taint("dedicated=gpu:NoRun").is_err());
+        assert!(parse_taint("dedicated:noschedule-").is_err());
+        assert!(parse_taint(":NoSchedule").is_err());
+        assert!(parse_taint("-").is_err());
+
+        let taint = "dedicated=gpu:NoSchedule";
+        let Ok(TaintChange::Add(parsed)) = parse_taint(taint) else {
+            panic!("expected a taint");
+        };
+        assert_eq!(parsed.to_string(), taint);
+    }
+
+    #[test]
+    fn test_node_taint_args() {
+        let cli = Cli::try_parse_from([
+            "k3rsctl",
+            "node",
+            "taint",
+            "worker-1",
+            "dedicated=gpu:NoSchedule",
+            "maintenance-",
+        ])
+        .unwrap();
+        let Commands::Node {
+            action: NodeAction::Taint { name, taints },
+        } = cli.command
+        else {
+            panic!("expected node taint command");
+        };
+        assert_eq!(name, "worker-1");
+        assert_eq!(taints.len(), 2);
+        assert!(matches!(
+            taints[1],
+            TaintChange::Remove { effect: None, .. }
+        ));
+
+        assert!(Cli::try_parse_from(["k3rsctl", "node", "taint", "worker-1"]).is_err());
+        assert!(
+            Cli::try_parse_from(["k3rsctl", "node", "taint", "worker-1", "gpu=yes:Never"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_node_list_wide_flag_parses() {
+        let cli = Cli::try_parse_from(["k3rsctl", "node", "list", "-o", "wide"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Node {
+                action: NodeAction::List { output: Some(ref o), .. }
+            } if o == "wide"
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+use super::get::{resource_path, resource_url};
+use crate::cli::PatchType;
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    resource: &str,
+    name: &str,
+    patch: &str,
+    patch_type: PatchType,
+    namespace: &str,
+) -> anyhow::Result<()> {
+    let Some((kind, segment, namespaced)) = resource_path(resource) else {
+        anyhow::bail!("unknown resource type '{}'", resource);
+    };
+    let body = patch_body(patch)?;
+    let url = resource_url(base, segment, namespaced, namespace, Some(name));
+    let resp = client
+        .patch(&url)
+        .header(reqwest::header::CONTENT_TYPE, content_type(patch_type))
+        .body(body)
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        super::print_api_error(resp).await;
+        std::process::exit(1);
+    }
+    println!("{}/{} patched", kind, name);
+    Ok(())
+}
+
+/// The patch document, checked to be a JSON object before it is sent.
+fn patch_body(patch: &str) -> anyhow::Result<String> {
+    let value: serde_json::Value = serde_json::from_str(patch)
+        .map_err(|e| anyhow::anyhow!("--patch is not valid JSON: {}", e))?;
+    if !value.is_object() {
+        anyhow::bail!("--patch must be a JSON object");
+    }
+    Ok(value.to_string())
+}
+
+fn content_type(patch_type: PatchType) -> &'static str {
+    match patch_type {
+        PatchType::Merge => pkg_types::patch::PatchType::Merge.content_type(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+
+    #[test]
+    fn test_patch_args() {
+        let cli = Cli::try_parse_from([
+            "k3rsctl",
+            "patch",
+            "deployment",
+            "web",
+            "-p",
+            r#"{"spec":{"replicas":3}}"#,
+            "--type",
+            "merge",
+        ])
+        .unwrap();
+        let Commands::Patch {
+            resource,
+            name,
+            patch,
+            patch_type,
+            namespace,
+        } = cli.command
+        else {
+            panic!("expected patch command");
+        };
+        assert_eq!((resource.as_str(), name.as_str()), ("deployment", "web"));
+        assert_eq!(patch_body(&patch).unwrap(), r#"{"spec":{"replicas":3}}"#);
+        assert_eq!(content_type(patch_type), "application/merge-patch+json");
+        assert_eq!(namespace, "default");
+
+        assert!(Cli::try_parse_from(["k3rsctl", "patch", "deploy", "web"]).is_err());
+        assert!(
+            Cli::try_parse_from([
+                "k3rsctl", "patch", "deploy", "web", "-p", "{}", "--type", "json"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_patch_body_must_be_an_object() {
+        assert!(patch_body("{\"spec\":").is_err());
+        assert!(patch_body("[1]").is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! `k3rsctl port-forward` — tunnel local TCP ports to ports in a pod.
+//!
+//! Each accepted local connection gets its own WebSocket to the server's
+//! pod port-forward endpoint, which the pod's agent connects to the
+//! container port. Binary frames carry the bytes; a text frame carries the
+//! reason the remote side gave up, and any close from the remote side closes
+//! the local connection straight away.
+
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+const BUFFER_SIZE: usize = 16 * 1024;
+
+/// A local port and the pod port it forwards to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortMapping {
+    /// 0 picks a free local port.
+    pub local: u16,
+    pub remote: u16,
+}
+
+/// Parse a `pod/<name>` or bare `<name>` argument into the pod name.
+pub fn parse_pod(s: &str) -> Result<String, String> {
+    match s.split_once('/') {
+        Some(("pod" | "pods" | "po", name)) if !name.is_empty() => Ok(name.to_string()),
+        Some((kind, _)) => Err(format!(
+            "unsupported resource type '{}': port-forward only targets pods",
+            kind
+        )),
+        None if !s.is_empty() => Ok(s.to_string()),
+        None => Err("expected pod/<name>".to_string()),
+    }
+}
+
+/// Parse `LOCAL:REMOTE`, `PORT` (same on both sides) or `:REMOTE` (any free
+/// local port).
+pub fn parse_port_mapping(s: &str) -> Result<PortMapping, String> {
+    let port = |p: &str| {
+        p.parse::<u16>()
+            .map_err(|_| format!("invalid port '{}' in '{}'", p, s))
+    };
+    let (local, remote) = match s.split_once(':') {
+        Some(("", remote)) => (0, port(remote)?),
+        Some((local, remote)) => (port(local)?, port(remote)?),
+        None => {
+            let p = port(s)?;
+            (p, p)
+        }
+    };
+    if remote == 0 {
+        return Err(format!("remote port must not be 0 in '{}'", s));
+    }
+    Ok(PortMapping { local, remote })
+}
+
+pub async fn handle(
+    server: &str,
+    token: &str,
+    pod: &str,
+    ports: &[PortMapping],
+    namespace: &str,
+) -> anyhow::Result<()> {
+    let ws_base = server
+        .trim_end_matches('/')
+        .replace("http://", "ws://")
+        .replace("https://", "wss://");
+
+    let mut listeners = Vec::with_capacity(ports.len());
+    for mapping in ports {
+        let listener = TcpListener::bind(("127.0.0.1", mapping.local))
+            .await
+            .map_err(|e| anyhow::anyhow!("unable to listen on port {}: {}", mapping.local, e))?;
+        let addr = listener.local_addr()?;
+        println!("Forwarding from {} -> {}", addr, mapping.remote);
+        listeners.push((listener, *mapping));
+    }
+
+    for (listener, mapping) in listeners {
+        let url = format!(
+            "{}/api/v1/namespaces/{}/pods/{}/portforward?port={}",
+            ws_base, namespace, pod, mapping.remote
+        );
+        tokio::spawn(accept_loop(listener, url, token.to_string(), mapping));
+    }
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+/// Forward every connection accepted on `listener` over its own WebSocket.
+async fn accept_loop(listener: TcpListener, url: String, token: String, mapping: PortMapping) {
+    loop {
+        let (conn, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("error accepting connection on {}: {}", mapping.local, e);
+                continue;
+            }
+        };
+        println!("Handling connection for {}", mapping.remote);
+        let request = super::exec::ws_request(&url, &token);
+        tokio::spawn(async move {
+            match tokio_tungstenite::connect_async(request).await {
+                Ok((ws, _)) => {
+                    let (ws_tx, ws_rx) = ws.split();
+                    if let Some(error) = relay(ws_tx, ws_rx, conn).await {
+                        eprintln!("error forwarding port {}: {}", mapping.remote, error);
+                    }
+                }
+                // Dropping `conn` closes the local socket.
+                Err(e) => eprintln!(
+                    "error forwarding port {}: {}",
+                    mapping.remote,
+                    super::exec::ws_error(&e)
+                ),
+            }
+        });
+    }
+}
+
+/// Relay bytes between `conn` and the WebSocket halves until either side
+/// closes. Returns the error the remote side reported, if any.
+async fn relay<Tx, Rx, S>(mut ws_tx: Tx, mut ws_rx: Rx, conn: S) -> Option<String>
+where
+    Tx: Sink<Message> + Unpin,
+    Rx: Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+    S: AsyncRead + AsyncWrite,
+{
+    let (mut reader, mut writer) = tokio::io::split(conn);
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    let mut error = None;
+    loop {
+        tokio::select! {
+            read = reader.read(&mut buf) => match read {
+                Ok(n) if n > 0 => {
+                    if ws_tx.send(Message::Binary(buf[..n].to_vec().into())).await.is_err() {
+                        break;
+                    }
+                }
+                // The local client hung up.
+                _ => {
+                    let _ = ws_tx.send(Message::Close(None)).await;
+                    break;
+                }
+            },
+            msg = ws_rx.next() => match msg {
+                Some(Ok(Message::Binary(data))) => {
+                    if writer.write_all(&data).await.is_err() {
+                        let _ = ws_tx.send(Message::Close(None)).await;
+                        break;
+                    }
+                }
+                Some(Ok(Message::Text(text))) => error = Some(text.to_string()),
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Err(e)) => {
+                    error.get_or_insert_with(|| e.to_string());
+                    break;
+                }
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    let _ = writer.shutdown().await;
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+    use tokio::net::TcpStream;
+
+    /// A WebSocket server standing in for the server and agent: each
+    /// connection's binary frames are echoed back, or, with `fail` set, it
+    /// reports an error and closes as an agent does when the pod port
+    /// refuses the connection.
+    async fn fake_endpoint(fail: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((conn, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut ws = tokio_tungstenite::accept_async(conn).await.unwrap();
+                    if fail {
+                        let _ = ws.send(Message::Text("connection refused".into())).await;
+                        let _ = ws.send(Message::Close(None)).await;
+                        return;
+                    }
+                    while let Some(Ok(msg)) = ws.next().await {
+                        match msg {
+                            Message::Binary(data) => {
+                                ws.send(Message::Binary(data)).await.unwrap();
+                            }
+                            Message::Close(_) => break,
+                            _ => {}
+                        }
+                    }
+                });
+            }
+        });
+        format!("ws://{}/portforward", addr)
+    }
+
+    /// A local listener forwarding to `url` as `handle` sets one up.
+    async fn forwarder(url: String) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mapping = PortMapping {
+            local: addr.port(),
+            remote: 80,
+        };
+        tokio::spawn(accept_loop(listener, url, "token".into(), mapping));
+        addr
+    }
+
+    #[test]
+    fn test_parse_pod() {
+        assert_eq!(parse_pod("pod/web-0").unwrap(), "web-0");
+        assert_eq!(parse_pod("pods/web-0").unwrap(), "web-0");
+        assert_eq!(parse_pod("web-0").unwrap(), "web-0");
+        assert!(parse_pod("deployment/web").is_err());
+        assert!(parse_pod("pod/").is_err());
+    }
+
+    #[test]
+    fn test_parse_port_mapping() {
+        let mapping = |local, remote| PortMapping { local, remote };
+        assert_eq!(parse_port_mapping("8080:80").unwrap(), mapping(8080, 80));
+        assert_eq!(parse_port_mapping("5432").unwrap(), mapping(5432, 5432));
+        assert_eq!(parse_port_mapping(":80").unwrap(), mapping(0, 80));
+        assert!(parse_port_mapping("8080:0").is_err());
+        assert!(parse_port_mapping("8080:http").is_err());
+        assert!(parse_port_mapping("70000").is_err());
+    }
+
+    #[test]
+    fn test_port_forward_args_parse() {
+        let cli = Cli::try_parse_from([
+            "k3rsctl",
+            "port-forward",
+            "pod/web-0",
+            "8080:80",
+            "9090",
+            "-n",
+            "prod",
+        ])
+        .unwrap();
+        let Commands::PortForward {
+            pod,
+            ports,
+            namespace,
+        } = cli.command
+        else {
+            panic!("expected port-forward");
+        };
+        assert_eq!(pod, "web-0");
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports[1].remote, 9090);
+        assert_eq!(namespace, "prod");
+        assert!(Cli::try_parse_from(["k3rsctl", "port-forward", "web-0"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connections_are_relayed_independently() {
+        let addr = forwarder(fake_endpoint(false).await).await;
+
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        for (conn, payload) in [(&mut first, &b"first"[..]), (&mut second, &b"second"[..])] {
+            conn.write_all(payload).await.unwrap();
+            let mut echoed = vec![0u8; payload.len()];
+            conn.read_exact(&mut echoed).await.unwrap();
+            assert_eq!(echoed, payload);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_error_closes_local_socket() {
+        let addr = forwarder(fake_endpoint(true).await).await;
+
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        let mut buf = [0u8; 16];
+        let read = tokio::time::timeout(std::time::Duration::from_secs(5), conn.read(&mut buf))
+            .await
+            .expect("local socket was not closed");
+        assert_eq!(read.unwrap(), 0);
+    }
+}
@@ -0,0 +1,297 @@
+use super::table::{Table, TableOptions};
+use crate::cli::RolloutAction;
+use pkg_types::deployment::{
+    Deployment, DeploymentConditionType, DeploymentRevision, DeploymentRollback,
+};
+
+use super::get::fetch_json;
+
+/// Parse a `deployment/<name>` argument into the deployment name.
+pub fn parse_deployment(s: &str) -> Result<String, String> {
+    match s.split_once('/') {
+        Some(("deployment" | "deployments" | "deploy", name)) if !name.is_empty() => {
+            Ok(name.to_string())
+        }
+        Some((kind, _)) => Err(format!(
+            "unsupported resource type '{}': rollout only manages deployments",
+            kind
+        )),
+        None => Err(format!("expected deployment/<name>, got '{}'", s)),
+    }
+}
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    action: &RolloutAction,
+    table_options: &TableOptions,
+) -> anyhow::Result<()> {
+    match action {
+        RolloutAction::History {
+            deployment,
+            namespace,
+        } => {
+            let url = format!(
+                "{}/api/v1/namespaces/{}/deployments/{}/revisions",
+                base, namespace, deployment
+            );
+            let resp = client.get(&url).send().await?;
+            if !resp.status().is_success() {
+                super::print_api_error(resp).await;
+                std::process::exit(1);
+            }
+            let revisions: Vec<DeploymentRevision> = resp.json().await?;
+            println!("deployment/{}", deployment);
+            let mut table = Table::new(&["REVISION", "IMAGES", "CHANGE-CAUSE"]);
+            for r in &revisions {
+                let revision = if r.current {
+                    format!("{} *", r.revision)
+                } else {
+                    r.revision.to_string()
+                };
+                table.push(vec![
+                    revision,
+                    r.images.join(","),
+                    r.change_cause
+                        .clone()
+                        .unwrap_or_else(|| "<none>".to_string()),
+                ]);
+            }
+            table.print(table_options)?;
+        }
+        RolloutAction::Undo {
+            deployment,
+            to_revision,
+            namespace,
+        } => {
+            let url = format!(
+                "{}/api/v1/namespaces/{}/deployments/{}/rollback",
+                base, namespace, deployment
+            );
+            let resp = client
+                .post(&url)
+                .json(&DeploymentRollback {
+                    to_revision: *to_revision,
+                })
+                .send()
+                .await?;
+            if !resp.status().is_success() {
+                super::print_api_error(resp).await;
+                std::process::exit(1);
+            }
+            let body: serde_json::Value = resp.json().await?;
+            let revision = body["revision"].as_u64().unwrap_or_default();
+            if body["status"] == "unchanged" {
+                println!(
+                    "deployment/{} skipped rollback (already at the template of revision {})",
+                    deployment, revision
+                );
+            } else {
+                println!(
+                    "deployment/{} rolled back to revision {}",
+                    deployment, revision
+                );
+            }
+        }
+        RolloutAction::Status {
+            deployment,
+            namespace,
+            timeout,
+        } => {
+            let url = format!(
+                "{}/api/v1/namespaces/{}/deployments/{}",
+                base, namespace, deployment
+            );
+            let deadline =
+                tokio::time::Instant::now() + timeout.to_std().unwrap_or(std::time::Duration::ZERO);
+            let mut last = String::new();
+            loop {
+                let Some(value) = fetch_json(client, &url).await? else {
+                    eprintln!(
+                        "Error: deployment \"{}\" not found in namespace \"{}\"",
+                        deployment, namespace
+                    );
+                    std::process::exit(1);
+                };
+                let deploy: Deployment = serde_json::from_value(value)?;
+                match rollout_state(&deploy) {
+                    RolloutState::Complete => {
+                        println!("deployment \"{}\" successfully rolled out", deployment);
+                        return Ok(());
+                    }
+                    RolloutState::Failed(msg) => {
+                        eprintln!(
+                            "error: deployment \"{}\" rollout failed: {}",
+                            deployment, msg
+                        );
+                        std::process::exit(1);
+                    }
+                    RolloutState::Progressing(msg) => {
+                        if msg != last {
+                            println!(
+                                "Waiting for deployment \"{}\" rollout to finish: {}...",
+                                deployment, msg
+                            );
+                            last = msg;
+                        }
+                    }
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    eprintln!(
+                        "error: timed out waiting for deployment \"{}\" to roll out",
+                        deployment
+                    );
+                    std::process::exit(1);
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum RolloutState {
+    Complete,
+    Progressing(String),
+    Failed(String),
+}
+
+fn rollout_state(deploy: &Deployment) -> RolloutState {
+    if deploy.observed_generation < deploy.generation {
+        return RolloutState::Progressing(
+            "waiting for the controller to observe the new spec".to_string(),
+        );
+    }
+    let status = &deploy.status;
+    let desired = deploy.spec.replicas;
+    if let Some(c) = status.condition(DeploymentConditionType::Progressing) {
+        if !c.status {
+            return RolloutState::Failed(format!("{}: {}", c.reason, c.message));
+        }
+        if c.reason == "NewReplicaSetAvailable" {
+            return RolloutState::Complete;
+        }
+    }
+    if status.updated_replicas < desired {
+        RolloutState::Progressing(format!(
+            "{} out of {} new replicas have been updated",
+            status.updated_replicas, desired
+        ))
+    } else if status.replicas > status.updated_replicas {
+        RolloutState::Progressing(format!(
+            "{} old replicas are pending termination",
+            status.replicas - status.updated_replicas
+        ))
+    } else {
+        RolloutState::Progressing(format!(
+            "{} of {} updated replicas are available",
+            status.available_replicas, desired
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+
+    fn deployment(generation: u64, observed: u64) -> Deployment {
+        serde_json::from_value(serde_json::json!({
+            "id": "web-id",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "replicas": 3,
+                "template": { "containers": [{ "name": "web", "image": "nginx" }] },
+            },
+            "generation": generation,
+            "observed_generation": observed,
+            "created_at": chrono::Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_deployment() {
+        assert_eq!(parse_deployment("deployment/web").unwrap(), "web");
+        assert_eq!(parse_deployment("deploy/web").unwrap(), "web");
+        assert!(parse_deployment("web").is_err());
+        assert!(parse_deployment("deployment/").is_err());
+        assert!(parse_deployment("pod/web").is_err());
+    }
+
+    #[test]
+    fn test_rollout_flags_parse() {
+        let cli = Cli::try_parse_from([
+            "k3rsctl",
+            "rollout",
+            "undo",
+            "deployment/web",
+            "--to-revision",
+            "2",
+            "-n",
+            "prod",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Rollout {
+                action: RolloutAction::Undo {
+                    ref deployment,
+                    to_revision: Some(2),
+                    ref namespace,
+                }
+            } if deployment == "web" && namespace == "prod"
+        ));
+
+        let cli = Cli::try_parse_from(["k3rsctl", "rollout", "status", "deployment/web"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Rollout {
+                action: RolloutAction::Status { timeout, .. }
+            } if timeout == chrono::Duration::minutes(5)
+        ));
+    }
+
+    #[test]
+    fn test_rollout_state() {
+        assert!(matches!(
+            rollout_state(&deployment(2, 1)),
+            RolloutState::Progressing(_)
+        ));
+
+        let mut deploy = deployment(2, 2);
+        deploy.status.replicas = 4;
+        deploy.status.updated_replicas = 2;
+        assert_eq!(
+            rollout_state(&deploy),
+            RolloutState::Progressing("2 out of 3 new replicas have been updated".to_string())
+        );
+        deploy.status.updated_replicas = 3;
+        assert_eq!(
+            rollout_state(&deploy),
+            RolloutState::Progressing("1 old replicas are pending termination".to_string())
+        );
+
+        deploy.status.set_condition(
+            DeploymentConditionType::Progressing,
+            true,
+            "NewReplicaSetAvailable",
+            String::new(),
+        );
+        assert_eq!(rollout_state(&deploy), RolloutState::Complete);
+
+        deploy.status.set_condition(
+            DeploymentConditionType::Progressing,
+            false,
+            "ProgressDeadlineExceeded",
+            "no progress".to_string(),
+        );
+        assert_eq!(
+            rollout_state(&deploy),
+            RolloutState::Failed("ProgressDeadlineExceeded: no progress".to_string())
+        );
+    }
+}
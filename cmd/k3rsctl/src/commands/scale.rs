@@ -0,0 +1,125 @@
+use pkg_types::scale::ScaleRequest;
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    resource: &str,
+    name: &str,
+    replicas: u32,
+    current_replicas: Option<u32>,
+    namespace: &str,
+) -> anyhow::Result<()> {
+    let (kind, path) = match resource {
+        "deployments" | "deployment" | "deploy" => ("deployment", "deployments"),
+        "replicasets" | "replicaset" | "rs" => ("replicaset", "replicasets"),
+        other => {
+            eprintln!(
+                "Unknown resource type for scale: {}. Supported: deployment, replicaset",
+                other
+            );
+            std::process::exit(1);
+        }
+    };
+    let object_url = format!("{}/api/v1/namespaces/{}/{}/{}", base, namespace, path, name);
+    let mut version = super::current_resource_version(client, &object_url).await?;
+    let mut attempt = 1;
+    let resp = loop {
+        let resp = client
+            .put(format!("{}/scale", object_url))
+            .json(&ScaleRequest {
+                replicas: replicas.into(),
+                current_replicas,
+                resource_version: Some(version),
+            })
+            .send()
+            .await?;
+        if resp.status().is_success() {
+            break resp;
+        }
+        let err = super::api_error(resp).await;
+        match super::conflict_version(&err) {
+            Some(current) if attempt < super::MAX_UPDATE_ATTEMPTS => {
+                version = current;
+                attempt += 1;
+            }
+            _ => {
+                eprintln!("{}", super::render_error(&err));
+                std::process::exit(1);
+            }
+        }
+    };
+    let warnings: Vec<String> = resp
+        .headers()
+        .get_all(reqwest::header::WARNING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .map(String::from)
+        .collect();
+    print!("{}", scaled_output(kind, name, &warnings));
+    Ok(())
+}
+
+/// `<kind>/<name> scaled`, preceded by the server's warnings with their
+/// `299 - "..."` framing removed.
+fn scaled_output(kind: &str, name: &str, warnings: &[String]) -> String {
+    let mut out = String::new();
+    for w in warnings {
+        let text = w
+            .strip_prefix("299 - ")
+            .map(|t| t.trim_matches('"'))
+            .unwrap_or(w);
+        out.push_str(&format!("Warning: {}\n", text));
+    }
+    out.push_str(&format!("{}/{} scaled\n", kind, name));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use clap::Parser;
+
+    #[test]
+    fn test_scaled_output() {
+        assert_eq!(
+            scaled_output("deployment", "web", &[]),
+            "deployment/web scaled\n"
+        );
+        let warnings = vec![
+            "299 - \"deployment 'web' is managed by HorizontalPodAutoscaler 'web-hpa'\""
+                .to_string(),
+        ];
+        assert_eq!(
+            scaled_output("deployment", "web", &warnings),
+            "Warning: deployment 'web' is managed by HorizontalPodAutoscaler 'web-hpa'\n\
+             deployment/web scaled\n"
+        );
+    }
+
+    #[test]
+    fn test_scale_flags_parse() {
+        let cli = Cli::try_parse_from([
+            "k3rsctl",
+            "scale",
+            "deployment",
+            "web",
+            "--replicas",
+            "5",
+            "--current-replicas",
+            "3",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Scale {
+                ref resource,
+                ref name,
+                replicas: 5,
+                current_replicas: Some(3),
+                ..
+            } if resource == "deployment" && name == "web"
+        ));
+        assert!(Cli::try_parse_from(["k3rsctl", "scale", "deployment", "web"]).is_err());
+    }
+}
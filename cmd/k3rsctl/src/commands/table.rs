@@ -0,0 +1,412 @@
+//! Tables printed by list commands: columns as wide as their widest cell,
+//! numbers right-aligned, an optional AGE column, states colored on a
+//! terminal, and rows ordered by `--sort-by`.
+
+use chrono::{DateTime, Utc};
+use crossterm::style::{Color, Stylize};
+use std::io::IsTerminal;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Cells wider than this are cut short with an ellipsis. The last column
+/// is never cut: nothing after it needs aligning.
+pub const MAX_COLUMN_WIDTH: usize = 48;
+
+/// Space between two columns.
+const COLUMN_GAP: &str = "   ";
+
+/// How tables are printed, from the command line.
+#[derive(Debug, Clone, Default)]
+pub struct TableOptions {
+    /// Color the state columns.
+    pub color: bool,
+    /// Header of the column to order rows by.
+    pub sort_by: Option<String>,
+}
+
+impl TableOptions {
+    /// Colors are used when stdout is a terminal, unless `--no-color` is
+    /// given or `NO_COLOR` is set.
+    pub fn new(no_color: bool, sort_by: Option<&str>) -> Self {
+        let color =
+            !no_color && std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+        Self {
+            color,
+            sort_by: sort_by.map(str::to_string),
+        }
+    }
+}
+
+struct Column {
+    header: &'static str,
+    numeric: bool,
+}
+
+struct Row {
+    cells: Vec<String>,
+    created_at: Option<DateTime<Utc>>,
+}
+
+/// Rows of text under fixed headers, sized when rendered.
+pub struct Table {
+    columns: Vec<Column>,
+    /// Column whose cells are colored by the state they name.
+    status: Option<usize>,
+    /// Rows end with an AGE column.
+    age: bool,
+    rows: Vec<Row>,
+}
+
+impl Table {
+    pub fn new(headers: &[&'static str]) -> Self {
+        Self {
+            columns: headers
+                .iter()
+                .map(|&header| Column {
+                    header,
+                    numeric: false,
+                })
+                .collect(),
+            status: None,
+            age: false,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Right-align the columns `headers` and sort them as numbers.
+    pub fn numeric(mut self, headers: &[&str]) -> Self {
+        for column in &mut self.columns {
+            if headers.contains(&column.header) {
+                column.numeric = true;
+            }
+        }
+        self
+    }
+
+    /// Color the cells of column `header` by the state they name.
+    pub fn status(mut self, header: &str) -> Self {
+        self.status = self.columns.iter().position(|c| c.header == header);
+        self
+    }
+
+    /// End every row with an AGE column, from the creation time given to
+    /// [`Table::push_aged`].
+    pub fn with_age(mut self) -> Self {
+        self.age = true;
+        self
+    }
+
+    pub fn push(&mut self, cells: Vec<String>) {
+        debug_assert_eq!(cells.len(), self.columns.len());
+        self.rows.push(Row {
+            cells,
+            created_at: None,
+        });
+    }
+
+    pub fn push_aged(&mut self, cells: Vec<String>, created_at: DateTime<Utc>) {
+        debug_assert_eq!(cells.len(), self.columns.len());
+        self.rows.push(Row {
+            cells,
+            created_at: Some(created_at),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Order the rows by `column`, named by its header in any case and
+    /// without a parenthesized unit (`cpu` for `CPU(cores)`). Numbers sort
+    /// by value, AGE youngest first, anything else as text; equal rows keep
+    /// their order.
+    pub fn sort_by(&mut self, column: &str) -> Result<(), String> {
+        if self.age && column.eq_ignore_ascii_case("age") {
+            self.rows
+                .sort_by_key(|row| std::cmp::Reverse(row.created_at));
+            return Ok(());
+        }
+        let Some(i) = self.columns.iter().position(|c| {
+            c.header.eq_ignore_ascii_case(column)
+                || c.header
+                    .split_once('(')
+                    .is_some_and(|(name, _)| name.eq_ignore_ascii_case(column))
+        }) else {
+            let mut headers: Vec<&str> = self.columns.iter().map(|c| c.header).collect();
+            if self.age {
+                headers.push("AGE");
+            }
+            return Err(format!(
+                "cannot sort by '{}': no such column (use one of {})",
+                column,
+                headers.join(", ")
+            ));
+        };
+        if self.columns[i].numeric {
+            self.rows.sort_by(|a, b| {
+                leading_number(&a.cells[i])
+                    .partial_cmp(&leading_number(&b.cells[i]))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        } else {
+            self.rows.sort_by(|a, b| a.cells[i].cmp(&b.cells[i]));
+        }
+        Ok(())
+    }
+
+    /// The header line and one line per row, ages taken at `now`.
+    pub fn render(&self, color: bool, now: DateTime<Utc>) -> Vec<String> {
+        let mut headers: Vec<&str> = self.columns.iter().map(|c| c.header).collect();
+        let mut numeric: Vec<bool> = self.columns.iter().map(|c| c.numeric).collect();
+        if self.age {
+            headers.push("AGE");
+            numeric.push(false);
+        }
+        let last = headers.len() - 1;
+        let rows: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let mut cells: Vec<String> = row
+                    .cells
+                    .iter()
+                    .enumerate()
+                    .map(|(i, cell)| {
+                        if i == last {
+                            cell.clone()
+                        } else {
+                            truncate(cell, MAX_COLUMN_WIDTH)
+                        }
+                    })
+                    .collect();
+                if self.age {
+                    cells.push(row.created_at.map_or("-".to_string(), |t| age(t, now)));
+                }
+                cells
+            })
+            .collect();
+
+        let mut widths: Vec<usize> = headers.iter().map(|h| h.width()).collect();
+        for cells in &rows {
+            for (width, cell) in widths.iter_mut().zip(cells) {
+                *width = (*width).max(cell.width());
+            }
+        }
+
+        let line = |cells: &[&str], paint: bool| {
+            let padded: Vec<String> = cells
+                .iter()
+                .enumerate()
+                .map(|(i, &cell)| {
+                    let fill = " ".repeat(widths[i] - cell.width());
+                    let text = if paint && self.status == Some(i) {
+                        colored(cell)
+                    } else {
+                        cell.to_string()
+                    };
+                    if numeric[i] {
+                        fill + &text
+                    } else if i == last {
+                        text
+                    } else {
+                        text + &fill
+                    }
+                })
+                .collect();
+            padded.join(COLUMN_GAP)
+        };
+
+        let mut lines = vec![line(&headers, false)];
+        for cells in &rows {
+            let cells: Vec<&str> = cells.iter().map(String::as_str).collect();
+            lines.push(line(&cells, color));
+        }
+        lines
+    }
+
+    /// Sort as `options` ask and print to stdout.
+    pub fn print(mut self, options: &TableOptions) -> anyhow::Result<()> {
+        if let Some(column) = &options.sort_by {
+            self.sort_by(column).map_err(anyhow::Error::msg)?;
+        }
+        for line in self.render(options.color, Utc::now()) {
+            println!("{}", line);
+        }
+        Ok(())
+    }
+}
+
+/// How long ago `created` was, in its largest whole unit: `45s`, `5m`,
+/// `2h`, `3d`, `1y`. A time in the future is `0s` old.
+pub fn age(created: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let secs = (now - created).num_seconds().max(0);
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 60 * 60 => format!("{}m", s / 60),
+        s if s < 24 * 60 * 60 => format!("{}h", s / (60 * 60)),
+        s if s < 365 * 24 * 60 * 60 => format!("{}d", s / (24 * 60 * 60)),
+        s => format!("{}y", s / (365 * 24 * 60 * 60)),
+    }
+}
+
+/// `s` cut to at most `max` display columns, ending in an ellipsis when
+/// anything was cut.
+pub fn truncate(s: &str, max: usize) -> String {
+    if s.width() <= max {
+        return s.to_string();
+    }
+    let mut out = String::new();
+    let mut width = 0;
+    for ch in s.chars() {
+        let w = ch.width().unwrap_or(0);
+        if width + w > max.saturating_sub(1) {
+            break;
+        }
+        out.push(ch);
+        width += w;
+    }
+    out.push('…');
+    out
+}
+
+/// The number a numeric cell starts with: `3` of `3/5`, `250` of `250m`.
+/// Cells like `-` have none and sort first.
+fn leading_number(cell: &str) -> Option<f64> {
+    let end = cell
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(cell.len());
+    cell[..end].parse().ok()
+}
+
+/// `state` in the color of what it means: green when all is well, red when
+/// something failed, yellow while waiting.
+fn colored(state: &str) -> String {
+    let name = state.split(':').next().unwrap_or(state);
+    let color = match name {
+        "Running" | "Ready" | "Active" | "Succeeded" | "Complete" | "Bound" => Color::Green,
+        "Failed" | "CrashLoopBackOff" | "OOMKilled" | "NotReady" | "Unknown" | "Lost" => Color::Red,
+        "Pending" | "Scheduled" | "ContainerCreating" | "Init" | "Terminating" => Color::Yellow,
+        _ => return state.to_string(),
+    };
+    state.with(color).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands, NodeAction};
+    use chrono::TimeZone;
+    use clap::Parser;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_widths_fit_wide_characters() {
+        let mut table = Table::new(&["NAME", "STATUS", "RESTARTS"])
+            .numeric(&["RESTARTS"])
+            .status("STATUS");
+        table.push(vec!["web-0".into(), "Running".into(), "0".into()]);
+        table.push(vec!["ウェブ-1".into(), "Pending".into(), "12".into()]);
+        table.push(vec!["数据库".into(), "CrashLoopBackOff".into(), "3".into()]);
+        assert_eq!(
+            table.render(false, at(0)),
+            vec![
+                "NAME       STATUS             RESTARTS",
+                "web-0      Running                   0",
+                "ウェブ-1   Pending                  12",
+                "数据库     CrashLoopBackOff          3",
+            ]
+        );
+
+        // Color codes do not count toward a column's width.
+        let colored = table.render(true, at(0));
+        assert!(colored[1].contains("\u{1b}["));
+        assert!(colored[1].ends_with("                   0"));
+    }
+
+    #[test]
+    fn test_long_cells_are_truncated() {
+        let long = "x".repeat(MAX_COLUMN_WIDTH + 10);
+        let mut table = Table::new(&["NAME", "IMAGE"]);
+        table.push(vec![long.clone(), long.clone()]);
+        let lines = table.render(false, at(0));
+        let name = lines[1].split(COLUMN_GAP).next().unwrap();
+        assert_eq!(name.width(), MAX_COLUMN_WIDTH);
+        assert!(name.ends_with('…'));
+        // The last column is left whole.
+        assert!(lines[1].ends_with(&long));
+
+        assert_eq!(truncate("数据库服务", 6), "数据…");
+        assert_eq!(truncate("short", 6), "short");
+    }
+
+    #[test]
+    fn test_age() {
+        assert_eq!(age(at(0), at(45)), "45s");
+        assert_eq!(age(at(0), at(5 * 60 + 59)), "5m");
+        assert_eq!(age(at(0), at(2 * 3600)), "2h");
+        assert_eq!(age(at(0), at(3 * 86400 + 7200)), "3d");
+        assert_eq!(age(at(0), at(400 * 86400)), "1y");
+        assert_eq!(age(at(60), at(0)), "0s");
+
+        let mut table = Table::new(&["NAME"]).with_age();
+        table.push_aged(vec!["web".into()], at(0));
+        assert_eq!(
+            table.render(false, at(300)),
+            vec!["NAME   AGE", "web    5m"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let mut table = Table::new(&["NAME", "CPU(cores)"])
+            .numeric(&["CPU(cores)"])
+            .with_age();
+        table.push_aged(vec!["b".into(), "250m".into()], at(10));
+        table.push_aged(vec!["c".into(), "1200m".into()], at(30));
+        table.push_aged(vec!["a".into(), "-".into()], at(20));
+        let names = |table: &Table| -> Vec<String> {
+            table.render(false, at(60))[1..]
+                .iter()
+                .map(|l| l[..1].to_string())
+                .collect()
+        };
+
+        table.sort_by("name").unwrap();
+        assert_eq!(names(&table), ["a", "b", "c"]);
+        // Numbers by value, not as text; `-` first.
+        table.sort_by("cpu").unwrap();
+        assert_eq!(names(&table), ["a", "b", "c"]);
+        table.sort_by("CPU(cores)").unwrap();
+        assert_eq!(names(&table), ["a", "b", "c"]);
+        table.sort_by("AGE").unwrap();
+        assert_eq!(names(&table), ["c", "a", "b"]);
+
+        let err = table.sort_by("memory").unwrap_err();
+        assert!(err.contains("NAME, CPU(cores), AGE"), "{}", err);
+    }
+
+    #[test]
+    fn test_sort_by_and_no_color_flags_parse() {
+        let cli = Cli::try_parse_from(["k3rsctl", "get", "pods", "--sort-by", "age"]).unwrap();
+        assert!(!cli.no_color);
+        assert!(matches!(
+            cli.command,
+            Commands::Get { sort_by: Some(ref column), .. } if column == "age"
+        ));
+
+        let cli =
+            Cli::try_parse_from(["k3rsctl", "node", "list", "--sort-by", "name", "--no-color"])
+                .unwrap();
+        assert!(cli.no_color);
+        assert!(matches!(
+            cli.command,
+            Commands::Node {
+                action: NodeAction::List { sort_by: Some(ref column), .. }
+            } if column == "name"
+        ));
+
+        assert!(!TableOptions::new(true, None).color);
+    }
+}
@@ -0,0 +1,181 @@
+use super::table::{Table, TableOptions};
+use crate::cli::TopAction;
+use pkg_types::metrics::{NodeMetrics, PodMetrics};
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    action: &TopAction,
+    table_options: &TableOptions,
+) -> anyhow::Result<()> {
+    let table = match action {
+        TopAction::Pods { namespace } => {
+            let url = format!("{}/api/v1/namespaces/{}/podmetrics", base, namespace);
+            let resp = client.get(&url).send().await?;
+            if !resp.status().is_success() {
+                super::print_api_error(resp).await;
+                std::process::exit(1);
+            }
+            let mut pods: Vec<PodMetrics> = resp.json().await?;
+            if pods.is_empty() {
+                println!("No usage reported for pods in namespace {}", namespace);
+                return Ok(());
+            }
+            pod_table(&mut pods)
+        }
+        TopAction::Nodes => {
+            let url = format!("{}/api/v1/nodemetrics", base);
+            let resp = client.get(&url).send().await?;
+            if !resp.status().is_success() {
+                super::print_api_error(resp).await;
+                std::process::exit(1);
+            }
+            let mut nodes: Vec<NodeMetrics> = resp.json().await?;
+            if nodes.is_empty() {
+                println!("(no nodes registered)");
+                return Ok(());
+            }
+            node_table(&mut nodes)
+        }
+    };
+    table.print(table_options)
+}
+
+/// Table of pod usage, busiest CPU first.
+fn pod_table(pods: &mut [PodMetrics]) -> Table {
+    pods.sort_by(|a, b| b.cpu_millis.cmp(&a.cpu_millis).then(a.name.cmp(&b.name)));
+    let mut table = Table::new(&["NAME", "NODE", "CPU(cores)", "MEMORY(bytes)"])
+        .numeric(&["CPU(cores)", "MEMORY(bytes)"]);
+    for pod in pods.iter() {
+        table.push(vec![
+            pod.name.clone(),
+            pod.node_name.clone(),
+            format!("{}m", pod.cpu_millis),
+            format_mebibytes(pod.memory_bytes),
+        ]);
+    }
+    table
+}
+
+/// Table of node usage, busiest CPU first.
+fn node_table(nodes: &mut [NodeMetrics]) -> Table {
+    nodes.sort_by(|a, b| b.cpu_millis.cmp(&a.cpu_millis).then(a.name.cmp(&b.name)));
+    let mut table = Table::new(&[
+        "NAME",
+        "CPU(cores)",
+        "CPU%",
+        "MEMORY(bytes)",
+        "MEMORY%",
+        "PODS",
+    ])
+    .numeric(&["CPU(cores)", "CPU%", "MEMORY(bytes)", "MEMORY%", "PODS"]);
+    for node in nodes.iter() {
+        table.push(vec![
+            node.name.clone(),
+            format!("{}m", node.cpu_millis),
+            percent(node.cpu_millis, node.cpu_capacity_millis),
+            format_mebibytes(node.memory_bytes),
+            percent(node.memory_bytes, node.memory_capacity_bytes),
+            node.pods.to_string(),
+        ]);
+    }
+    table
+}
+
+fn format_mebibytes(bytes: u64) -> String {
+    format!("{}Mi", (bytes as f64 / (1u64 << 20) as f64).round() as u64)
+}
+
+/// `used` as a whole percentage of `capacity`; `-` when the capacity is unknown.
+fn percent(used: u64, capacity: u64) -> String {
+    match (used * 100).checked_div(capacity) {
+        Some(pct) => format!("{}%", pct),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{Cli, Commands};
+    use chrono::Utc;
+    use clap::Parser;
+
+    fn pod(name: &str, cpu_millis: u64, memory_bytes: u64) -> PodMetrics {
+        PodMetrics {
+            namespace: "default".into(),
+            name: name.into(),
+            node_name: "node-a".into(),
+            cpu_millis,
+            memory_bytes,
+            timestamp: Utc::now(),
+        }
+    }
+
+    fn node(name: &str, cpu_millis: u64, memory_bytes: u64, pods: u32) -> NodeMetrics {
+        NodeMetrics {
+            name: name.into(),
+            cpu_millis,
+            memory_bytes,
+            cpu_capacity_millis: 4000,
+            memory_capacity_bytes: 8 << 30,
+            pods,
+            pods_cpu_millis: 0,
+            pods_memory_bytes: 0,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_pod_table_sorted_by_cpu() {
+        let mut pods = vec![
+            pod("idle-0", 0, 12 << 20),
+            pod("web-0", 250, 64 << 20),
+            pod("api-0", 1200, 300 << 20),
+            pod("cache-0", 250, 1 << 30),
+        ];
+        assert_eq!(
+            pod_table(&mut pods).render(false, Utc::now()),
+            vec![
+                "NAME      NODE     CPU(cores)   MEMORY(bytes)",
+                "api-0     node-a        1200m           300Mi",
+                "cache-0   node-a         250m          1024Mi",
+                "web-0     node-a         250m            64Mi",
+                "idle-0    node-a           0m            12Mi",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_node_table_sorted_by_cpu() {
+        let mut nodes = vec![
+            node("node-a", 500, 2 << 30, 3),
+            node("node-b", 3000, 6 << 30, 7),
+        ];
+        nodes.push(NodeMetrics {
+            cpu_capacity_millis: 0,
+            memory_capacity_bytes: 0,
+            ..node("node-c", 100, 1 << 20, 0)
+        });
+        assert_eq!(
+            node_table(&mut nodes).render(false, Utc::now()),
+            vec![
+                "NAME     CPU(cores)   CPU%   MEMORY(bytes)   MEMORY%   PODS",
+                "node-b        3000m    75%          6144Mi       75%      7",
+                "node-a         500m    12%          2048Mi       25%      3",
+                "node-c         100m      -             1Mi         -      0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_top_pods_namespace_flag_parses() {
+        let cli = Cli::try_parse_from(["k3rsctl", "top", "pods", "-n", "dev"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Top {
+                action: TopAction::Pods { ref namespace }
+            } if namespace == "dev"
+        ));
+    }
+}
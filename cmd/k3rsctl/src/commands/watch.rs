@@ -0,0 +1,423 @@
+//! `k3rsctl get <resource> --watch`: print the current list, then stream
+//! ADDED / MODIFIED / DELETED lines from the server's `/api/v1/watch` SSE feed.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::get::{resource_path, resource_url};
+
+/// A state-store mutation as sent on the watch stream.
+#[derive(Debug, Clone, Deserialize)]
+struct StoreEvent {
+    seq: u64,
+    /// `Put` or `Delete`.
+    event_type: String,
+    key: String,
+    #[serde(default)]
+    value: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Added => write!(f, "ADDED"),
+            Change::Modified => write!(f, "MODIFIED"),
+            Change::Deleted => write!(f, "DELETED"),
+        }
+    }
+}
+
+/// Turns raw store events into object changes, remembering the last seq
+/// seen so a reconnect resumes without gaps or repeats.
+struct WatchTracker {
+    last_seq: u64,
+    known: HashMap<String, Value>,
+}
+
+impl WatchTracker {
+    fn new(seq: u64, initial: impl IntoIterator<Item = (String, Value)>) -> Self {
+        Self {
+            last_seq: seq,
+            known: initial.into_iter().collect(),
+        }
+    }
+
+    /// Replace what is known with a fresh list served at `seq`, returning how
+    /// each object changed since the last event seen.
+    fn resync(&mut self, seq: u64, objects: BTreeMap<String, Value>) -> Vec<(Change, Value)> {
+        let mut changes = Vec::new();
+        let mut old = std::mem::take(&mut self.known);
+        for (key, obj) in objects {
+            match old.remove(&key) {
+                None => changes.push((Change::Added, obj.clone())),
+                Some(prev) if prev != obj => changes.push((Change::Modified, obj.clone())),
+                Some(_) => {}
+            }
+            self.known.insert(key, obj);
+        }
+        let mut deleted: Vec<(String, Value)> = old.into_iter().collect();
+        deleted.sort_by(|a, b| a.0.cmp(&b.0));
+        changes.extend(deleted.into_iter().map(|(_, obj)| (Change::Deleted, obj)));
+        self.last_seq = seq;
+        changes
+    }
+
+    /// Apply one event. Returns the change and the object's latest value, or
+    /// `None` for replays (seq already seen) and no-op updates.
+    fn apply(&mut self, event: StoreEvent) -> Option<(Change, Value)> {
+        if event.seq <= self.last_seq {
+            return None;
+        }
+        self.last_seq = event.seq;
+
+        if event.event_type == "Delete" {
+            return self
+                .known
+                .remove(&event.key)
+                .map(|old| (Change::Deleted, old));
+        }
+        let value: Value = serde_json::from_slice(event.value.as_deref()?).ok()?;
+        match self.known.insert(event.key, value.clone()) {
+            None => Some((Change::Added, value)),
+            // Watching from the list's seq can replay a change the list already
+            // reflected; it carries the same object and is not printed again.
+            Some(old) if old == value => None,
+            Some(_) => Some((Change::Modified, value)),
+        }
+    }
+}
+
+/// Accumulates SSE bytes and yields the `data` payload of each complete message.
+#[derive(Default)]
+struct SseParser {
+    buf: String,
+}
+
+impl SseParser {
+    fn push(&mut self, chunk: &str) -> Vec<String> {
+        self.buf.push_str(&chunk.replace("\r\n", "\n"));
+        let mut messages = Vec::new();
+        while let Some(end) = self.buf.find("\n\n") {
+            let block: String = self.buf.drain(..end + 2).collect();
+            let data: Vec<&str> = block
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|d| d.strip_prefix(' ').unwrap_or(d))
+                .collect();
+            if !data.is_empty() {
+                messages.push(data.join("\n"));
+            }
+        }
+        messages
+    }
+}
+
+/// Store-key prefix holding the objects of a resource collection.
+fn store_prefix(segment: &str, namespaced: bool, namespace: &str) -> String {
+    if namespaced {
+        format!("/registry/{}/{}/", segment, namespace)
+    } else {
+        format!("/registry/{}/", segment)
+    }
+}
+
+const HEADER: &str = "EVENT      NAME                           NAMESPACE    STATUS";
+
+/// One table row for an object, with a short kind-specific status column.
+fn row(kind: &str, obj: &Value) -> String {
+    let str_at = |ptr: &str| obj.pointer(ptr).and_then(Value::as_str).unwrap_or("");
+    let num_at = |ptr: &str| obj.pointer(ptr).and_then(Value::as_u64).unwrap_or(0);
+    let status = match kind {
+        "pod" => match obj.pointer("/node_name").and_then(Value::as_str) {
+            Some(node) => format!("{} on {}", str_at("/status"), node),
+            None => str_at("/status").to_string(),
+        },
+        "deployment" | "replicaset" => format!(
+            "{}/{} ready",
+            num_at("/status/ready_replicas"),
+            num_at("/spec/replicas")
+        ),
+        "service" => format!(
+            "{} {}",
+            str_at("/spec/service_type"),
+            obj.pointer("/cluster_ip")
+                .and_then(Value::as_str)
+                .unwrap_or("<none>")
+        ),
+        "job" => format!("{} succeeded", num_at("/status/succeeded")),
+        _ => obj
+            .pointer("/status")
+            .and_then(Value::as_str)
+            .unwrap_or("-")
+            .to_string(),
+    };
+    let namespace = match str_at("/namespace") {
+        "" => "-",
+        ns => ns,
+    };
+    format!("{:<30} {:<12} {}", str_at("/name"), namespace, status)
+}
+
+pub async fn handle(
+    client: &reqwest::Client,
+    base: &str,
+    resource: &str,
+    namespace: &str,
+) -> anyhow::Result<()> {
+    let Some((kind, segment, namespaced)) = resource_path(resource) else {
+        eprintln!("Unknown resource type: {}", resource);
+        std::process::exit(1);
+    };
+
+    let url = resource_url(base, segment, namespaced, namespace, None);
+    let prefix = store_prefix(segment, namespaced, namespace);
+    let (seq, initial) = list(client, &url, &prefix).await?;
+    println!("{}", HEADER);
+    for obj in initial.values() {
+        println!("{:<10} {}", "", row(kind, obj));
+    }
+    let mut tracker = WatchTracker::new(seq, initial);
+
+    loop {
+        match stream_once(client, base, kind, &prefix, &mut tracker).await {
+            // The server no longer has the events we missed: relist, and
+            // report what changed in between.
+            Ok(StreamEnd::Gone) => match list(client, &url, &prefix).await {
+                Ok((seq, objects)) => {
+                    for (change, obj) in tracker.resync(seq, objects) {
+                        println!("{:<10} {}", change.to_string(), row(kind, &obj));
+                    }
+                    continue;
+                }
+                Err(e) => eprintln!("relist failed: {} — retrying", e),
+            },
+            Ok(StreamEnd::Closed) => {}
+            Err(e) => eprintln!("watch interrupted: {} — reconnecting", e),
+        }
+        tokio::time::sleep(Duration::from_millis(
+            pkg_constants::timings::WATCH_RECONNECT_DELAY_MS,
+        ))
+        .await;
+    }
+}
+
+/// The collection at `url` keyed by store key, plus the event-log seq it was
+/// served at.
+async fn list(
+    client: &reqwest::Client,
+    url: &str,
+    prefix: &str,
+) -> anyhow::Result<(u64, BTreeMap<String, Value>)> {
+    let resp = client.get(url).send().await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("{}", super::render_error(&super::api_error(resp).await));
+    }
+    let seq = resp
+        .headers()
+        .get(pkg_constants::state::WATCH_SEQ_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    let items: Vec<Value> = resp.json().await?;
+    let objects = items
+        .into_iter()
+        .map(|obj| {
+            let name = obj.get("name").and_then(Value::as_str).unwrap_or_default();
+            (format!("{}{}", prefix, name), obj)
+        })
+        .collect();
+    Ok((seq, objects))
+}
+
+/// Why [`stream_once`] returned.
+#[derive(Debug, PartialEq, Eq)]
+enum StreamEnd {
+    /// The server closed the stream; resume from the last seq.
+    Closed,
+    /// The last seq is no longer buffered (410); relist first.
+    Gone,
+}
+
+/// Follow the watch stream from the tracker's last seq until it ends.
+async fn stream_once(
+    client: &reqwest::Client,
+    base: &str,
+    kind: &str,
+    prefix: &str,
+    tracker: &mut WatchTracker,
+) -> anyhow::Result<StreamEnd> {
+    let url = format!(
+        "{}/api/v1/watch?prefix={}&seq={}",
+        base, prefix, tracker.last_seq
+    );
+    let resp = client.get(&url).send().await?;
+    if resp.status() == reqwest::StatusCode::GONE {
+        return Ok(StreamEnd::Gone);
+    }
+    let mut resp = resp.error_for_status()?;
+    let mut parser = SseParser::default();
+    while let Some(chunk) = resp.chunk().await? {
+        for data in parser.push(&String::from_utf8_lossy(&chunk)) {
+            let Ok(event) = serde_json::from_str::<StoreEvent>(&data) else {
+                continue;
+            };
+            // A prefix like `/registry/pods/default/` also matches keys of
+            // nested collections; only direct children are objects.
+            if event.key[prefix.len().min(event.key.len())..].contains('/') {
+                tracker.last_seq = tracker.last_seq.max(event.seq);
+                continue;
+            }
+            if let Some((change, obj)) = tracker.apply(event) {
+                println!("{:<10} {}", change.to_string(), row(kind, &obj));
+            }
+        }
+    }
+    Ok(StreamEnd::Closed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn put(seq: u64, key: &str, obj: Value) -> StoreEvent {
+        StoreEvent {
+            seq,
+            event_type: "Put".to_string(),
+            key: key.to_string(),
+            value: Some(serde_json::to_vec(&obj).unwrap()),
+        }
+    }
+
+    fn pod(name: &str, status: &str) -> Value {
+        serde_json::json!({ "name": name, "namespace": "default", "status": status })
+    }
+
+    #[test]
+    fn test_tracker_resumes_from_seq_without_duplicates() {
+        let key = "/registry/pods/default/web";
+        let mut initial = HashMap::new();
+        initial.insert(key.to_string(), pod("web", "Pending"));
+        let mut tracker = WatchTracker::new(10, initial);
+
+        // Replayed event at or before the list's seq is ignored.
+        assert!(tracker.apply(put(9, key, pod("web", "Running"))).is_none());
+        // Change already reflected in the list is not reported again.
+        assert!(tracker.apply(put(11, key, pod("web", "Pending"))).is_none());
+
+        let (change, obj) = tracker.apply(put(12, key, pod("web", "Running"))).unwrap();
+        assert_eq!(change, Change::Modified);
+        assert_eq!(obj["status"], "Running");
+
+        let other = "/registry/pods/default/db";
+        let (change, _) = tracker.apply(put(13, other, pod("db", "Pending"))).unwrap();
+        assert_eq!(change, Change::Added);
+        assert_eq!(tracker.last_seq, 13);
+
+        // After a disconnect the server replays from last_seq; the overlap is dropped.
+        assert!(tracker.apply(put(12, key, pod("web", "Running"))).is_none());
+        assert!(
+            tracker
+                .apply(put(13, other, pod("db", "Pending")))
+                .is_none()
+        );
+
+        let delete = StoreEvent {
+            seq: 14,
+            event_type: "Delete".to_string(),
+            key: key.to_string(),
+            value: None,
+        };
+        let (change, obj) = tracker.apply(delete).unwrap();
+        assert_eq!(change, Change::Deleted);
+        assert_eq!(obj["name"], "web");
+    }
+
+    #[test]
+    fn test_resync_reports_changes_since_last_event() {
+        let mut initial = HashMap::new();
+        for name in ["a", "b", "c"] {
+            initial.insert(
+                format!("/registry/pods/default/{}", name),
+                pod(name, "Running"),
+            );
+        }
+        let mut tracker = WatchTracker::new(40, initial);
+
+        // The server restarted: `b` changed, `c` is gone and `d` is new.
+        let mut relisted = BTreeMap::new();
+        relisted.insert("/registry/pods/default/a".to_string(), pod("a", "Running"));
+        relisted.insert("/registry/pods/default/b".to_string(), pod("b", "Failed"));
+        relisted.insert("/registry/pods/default/d".to_string(), pod("d", "Pending"));
+        let changes: Vec<(Change, String)> = tracker
+            .resync(3, relisted)
+            .into_iter()
+            .map(|(change, obj)| (change, obj["name"].as_str().unwrap().to_string()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Change::Modified, "b".to_string()),
+                (Change::Added, "d".to_string()),
+                (Change::Deleted, "c".to_string()),
+            ]
+        );
+        // Watching resumes from the new list's seq, even though it is lower.
+        assert_eq!(tracker.last_seq, 3);
+        assert!(
+            tracker
+                .apply(put(4, "/registry/pods/default/c", pod("c", "Pending")))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_messages() {
+        let mut parser = SseParser::default();
+        assert!(parser.push("id: 1\ndata: {\"seq\":").is_empty());
+        let msgs = parser.push("1}\n\n: keep-alive\n\nid: 2\ndata: x\n\n");
+        assert_eq!(msgs, vec!["{\"seq\":1}".to_string(), "x".to_string()]);
+    }
+
+    #[test]
+    fn test_store_keys_map_to_rows() {
+        assert_eq!(
+            store_prefix("pods", true, "default"),
+            "/registry/pods/default/"
+        );
+        assert_eq!(
+            store_prefix("namespaces", false, "default"),
+            "/registry/namespaces/"
+        );
+
+        let pod_row = row(
+            "pod",
+            &serde_json::json!({
+                "name": "web", "namespace": "default", "status": "Running", "node_name": "node-a"
+            }),
+        );
+        assert!(pod_row.starts_with("web "));
+        assert!(pod_row.ends_with("default      Running on node-a"));
+
+        let deploy_row = row(
+            "deployment",
+            &serde_json::json!({
+                "name": "api", "namespace": "prod",
+                "spec": { "replicas": 3 }, "status": { "ready_replicas": 2 }
+            }),
+        );
+        assert!(deploy_row.ends_with("prod         2/3 ready"));
+
+        let ns_row = row("namespace", &serde_json::json!({ "name": "prod" }));
+        assert!(ns_row.ends_with("-            -"));
+    }
+}
@@ -0,0 +1,326 @@
+//! `~/.k3rs/config.yaml`: named contexts, each with a server, token,
+//! default namespace and CA certificate, and the one in use. A setting is
+//! taken from its flag first, then `K3RS_SERVER`/`K3RS_TOKEN`, then the
+//! current context, then the built-in default.
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+
+/// Variable with the server URL, used when `--server` is not given.
+pub const SERVER_ENV: &str = "K3RS_SERVER";
+/// Variable with the token, used when `--token` is not given.
+pub const TOKEN_ENV: &str = "K3RS_TOKEN";
+
+/// `~/.k3rs/config.yaml`.
+pub fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .expect("could not determine home directory")
+        .join(".k3rs")
+        .join("config.yaml")
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_context: Option<String>,
+    #[serde(default)]
+    pub contexts: Vec<Context>,
+}
+
+/// One cluster and the credentials to reach it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Context {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Namespace for commands run without `-n`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// PEM file of the CA that signed the server's certificate. Without
+    /// one, the server's certificate is not verified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<String>,
+}
+
+impl Config {
+    /// Read the config at `path`; an empty one if there is no file.
+    pub fn load(path: &Path) -> Result<Config> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let data = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_yaml::from_str(&data).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Write the config to `path`, readable by its owner only since it
+    /// holds tokens (write to `.tmp`, then rename).
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create directory {}", dir.display()))?;
+        }
+        let tmp = path.with_extension("yaml.tmp");
+        let _ = fs::remove_file(&tmp);
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&tmp)
+            .with_context(|| format!("failed to create {}", tmp.display()))?;
+        file.write_all(serde_yaml::to_string(self)?.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp, path).with_context(|| format!("failed to write {}", path.display()))?;
+        // A file from before the rename kept its mode; the new one is ours.
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        Ok(())
+    }
+
+    pub fn context(&self, name: &str) -> Option<&Context> {
+        self.contexts.iter().find(|c| c.name == name)
+    }
+
+    /// Add `update`, or set the fields it has on the context of the same
+    /// name and keep the others.
+    pub fn set_context(&mut self, update: Context) {
+        let Some(context) = self.contexts.iter_mut().find(|c| c.name == update.name) else {
+            self.contexts.push(update);
+            return;
+        };
+        let Context {
+            name: _,
+            server,
+            token,
+            namespace,
+            ca_cert,
+        } = update;
+        context.server = server.or(context.server.take());
+        context.token = token.or(context.token.take());
+        context.namespace = namespace.or(context.namespace.take());
+        context.ca_cert = ca_cert.or(context.ca_cert.take());
+    }
+
+    /// Make `name` the current context.
+    pub fn use_context(&mut self, name: &str) -> Result<()> {
+        if self.context(name).is_none() {
+            anyhow::bail!("no context named '{}'", name);
+        }
+        self.current_context = Some(name.to_string());
+        Ok(())
+    }
+
+    /// The current context; an error when it names a context that is not
+    /// in the file.
+    pub fn current(&self) -> Result<Option<&Context>> {
+        let Some(name) = &self.current_context else {
+            return Ok(None);
+        };
+        match self.context(name) {
+            Some(context) => Ok(Some(context)),
+            None => anyhow::bail!(
+                "current context '{}' is not defined (run `k3rsctl config use-context` or `k3rsctl config set-context {}`)",
+                name,
+                name
+            ),
+        }
+    }
+}
+
+/// A server and token set outside the config file: by flags or by
+/// environment variables.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub server: Option<String>,
+    pub token: Option<String>,
+}
+
+impl Overrides {
+    /// `K3RS_SERVER` and `K3RS_TOKEN`; empty ones count as unset.
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+        Self {
+            server: var(SERVER_ENV),
+            token: var(TOKEN_ENV),
+        }
+    }
+}
+
+/// Where to reach the server and how to authenticate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connection {
+    pub server: String,
+    pub token: String,
+    pub ca_cert: Option<String>,
+}
+
+/// Settings from `flags`, else `env`, else `context`, else the defaults.
+/// The context's CA certificate is only used for the context's server.
+pub fn resolve(flags: &Overrides, env: &Overrides, context: Option<&Context>) -> Connection {
+    let context_server = flags.server.is_none() && env.server.is_none();
+    let server = flags
+        .server
+        .clone()
+        .or_else(|| env.server.clone())
+        .or_else(|| context.and_then(|c| c.server.clone()))
+        .unwrap_or_else(|| pkg_constants::network::DEFAULT_API_ADDR.to_string());
+    let token = flags
+        .token
+        .clone()
+        .or_else(|| env.token.clone())
+        .or_else(|| context.and_then(|c| c.token.clone()))
+        .unwrap_or_else(|| pkg_constants::auth::DEFAULT_ADMIN_TOKEN.to_string());
+    Connection {
+        server,
+        token,
+        ca_cert: context
+            .filter(|_| context_server)
+            .and_then(|c| c.ca_cert.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prod() -> Context {
+        Context {
+            name: "prod".into(),
+            server: Some("https://prod:6443".into()),
+            token: Some("ctx-token".into()),
+            namespace: Some("web".into()),
+            ca_cert: Some("/etc/k3rs/ca.pem".into()),
+        }
+    }
+
+    fn overrides(server: Option<&str>, token: Option<&str>) -> Overrides {
+        Overrides {
+            server: server.map(str::to_string),
+            token: token.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let none = Overrides::default();
+        let ctx = prod();
+
+        let conn = resolve(&none, &none, None);
+        assert_eq!(conn.server, pkg_constants::network::DEFAULT_API_ADDR);
+        assert_eq!(conn.token, pkg_constants::auth::DEFAULT_ADMIN_TOKEN);
+        assert_eq!(conn.ca_cert, None);
+
+        let conn = resolve(&none, &none, Some(&ctx));
+        assert_eq!(conn.server, "https://prod:6443");
+        assert_eq!(conn.token, "ctx-token");
+        assert_eq!(conn.ca_cert.as_deref(), Some("/etc/k3rs/ca.pem"));
+
+        let env = overrides(Some("https://env:6443"), None);
+        let conn = resolve(&none, &env, Some(&ctx));
+        assert_eq!(conn.server, "https://env:6443");
+        assert_eq!(conn.token, "ctx-token");
+        assert_eq!(conn.ca_cert, None);
+
+        let flags = overrides(Some("https://flag:6443"), Some("flag-token"));
+        let env = overrides(Some("https://env:6443"), Some("env-token"));
+        let conn = resolve(&flags, &env, Some(&ctx));
+        assert_eq!(conn.server, "https://flag:6443");
+        assert_eq!(conn.token, "flag-token");
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let dir = std::env::temp_dir().join(format!("k3rsctl-config-{}", std::process::id()));
+        let path = dir.join("config.yaml");
+        let mut config = Config::default();
+        config.set_context(prod());
+        config.set_context(Context {
+            name: "dev".into(),
+            server: Some("http://127.0.0.1:6443".into()),
+            ..Default::default()
+        });
+        config.use_context("dev").unwrap();
+        config.save(&path).unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(Config::load(&path).unwrap(), config);
+
+        // Only the given fields of an existing context change.
+        config.set_context(Context {
+            name: "prod".into(),
+            namespace: Some("api".into()),
+            ..Default::default()
+        });
+        let updated = config.context("prod").unwrap();
+        assert_eq!(updated.namespace.as_deref(), Some("api"));
+        assert_eq!(updated.token.as_deref(), Some("ctx-token"));
+        config.save(&path).unwrap();
+        assert_eq!(Config::load(&path).unwrap(), config);
+
+        assert_eq!(
+            Config::load(&dir.join("missing.yaml")).unwrap(),
+            Config::default()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_context() {
+        let mut config = Config::default();
+        assert!(config.current().unwrap().is_none());
+        config.set_context(prod());
+
+        let err = config.use_context("staging").unwrap_err();
+        assert_eq!(err.to_string(), "no context named 'staging'");
+        assert!(config.current_context.is_none());
+
+        config.current_context = Some("staging".into());
+        let err = config.current().unwrap_err();
+        assert!(
+            err.to_string().contains("'staging' is not defined"),
+            "{}",
+            err
+        );
+
+        config.use_context("prod").unwrap();
+        assert_eq!(config.current().unwrap(), Some(&prod()));
+    }
+
+    #[test]
+    fn test_context_namespace_is_the_default() {
+        use crate::cli::{Cli, Commands, TokenAction, with_default_namespace};
+        use clap::{CommandFactory, FromArgMatches};
+
+        let command = with_default_namespace(Cli::command(), "web");
+        let parse = |args: &[&str]| {
+            Cli::from_arg_matches(&command.clone().try_get_matches_from(args).unwrap()).unwrap()
+        };
+        assert!(matches!(
+            parse(&["k3rsctl", "get", "pods"]).command,
+            Commands::Get { ref namespace, .. } if namespace == "web"
+        ));
+        assert!(matches!(
+            parse(&["k3rsctl", "get", "pods", "-n", "prod"]).command,
+            Commands::Get { ref namespace, .. } if namespace == "prod"
+        ));
+        // A token's scope has no default to replace.
+        assert!(matches!(
+            parse(&["k3rsctl", "token", "create", "--role", "readonly"]).command,
+            Commands::Token {
+                action: TokenAction::Create {
+                    namespace: None,
+                    ..
+                }
+            }
+        ));
+    }
+}
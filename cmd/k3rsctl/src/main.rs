@@ -1,8 +1,9 @@
 mod cli;
 mod commands;
+mod config;
 mod pm;
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use cli::Commands;
 
 #[tokio::main]
@@ -13,23 +14,65 @@ async fn main() -> anyhow::Result<()> {
                 .add_directive(tracing::level_filters::LevelFilter::INFO.into()),
         )
         .init();
-    let cli = cli::Cli::parse();
 
-    // Handle PM commands before building HTTP client (PM is local-only)
-    if let Commands::Pm { action } = &cli.command {
-        return pm::handle(action).await;
+    let config_path = config::config_path();
+    let config = config::Config::load(&config_path)?;
+    let mut command = cli::Cli::command();
+    // A missing current context is reported once a command needs a server,
+    // so `config use-context` can still fix it.
+    if let Some(namespace) = config
+        .current()
+        .ok()
+        .flatten()
+        .and_then(|c| c.namespace.clone())
+    {
+        // Parsing happens once per run, so the default can live that long.
+        command = cli::with_default_namespace(command, Box::leak(namespace.into_boxed_str()));
     }
+    let cli = cli::Cli::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit());
+
+    // Handle PM, config and completion commands before building the HTTP
+    // client (they are local-only)
+    match &cli.command {
+        Commands::Pm { action } => return pm::handle(action).await,
+        Commands::Config { action } => {
+            let table = commands::table::TableOptions::new(cli.no_color, None);
+            return commands::config::handle(action, &config_path, &table);
+        }
+        Commands::Completion { shell } => {
+            clap_complete::generate(
+                *shell,
+                &mut cli::Cli::command(),
+                "k3rsctl",
+                &mut std::io::stdout(),
+            );
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let flags = config::Overrides {
+        server: cli.server.clone(),
+        token: cli.token.clone(),
+    };
+    let conn = config::resolve(&flags, &config::Overrides::from_env(), config.current()?);
 
     let mut headers = reqwest::header::HeaderMap::new();
-    let auth_value = format!("Bearer {}", cli.token);
+    let auth_value = format!("Bearer {}", conn.token);
     let mut auth_header = reqwest::header::HeaderValue::from_str(&auth_value)?;
     auth_header.set_sensitive(true);
     headers.insert(reqwest::header::AUTHORIZATION, auth_header);
 
-    let client = reqwest::Client::builder()
-        .danger_accept_invalid_certs(true)
-        .default_headers(headers)
-        .build()?;
+    let mut builder = reqwest::Client::builder().default_headers(headers);
+    builder = match &conn.ca_cert {
+        Some(path) => {
+            let pem = std::fs::read(path)
+                .map_err(|e| anyhow::anyhow!("failed to read CA certificate {}: {}", path, e))?;
+            builder.tls_certs_merge(reqwest::Certificate::from_pem_bundle(&pem)?)
+        }
+        None => builder.danger_accept_invalid_certs(true),
+    };
+    let client = builder.build()?;
 
-    commands::dispatch(&cli, &client).await
+    commands::dispatch(&cli, &conn, &client).await
 }
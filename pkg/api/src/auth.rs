@@ -1,118 +1,508 @@
+//! Bearer-token authentication and role-based authorization of API requests.
+//!
+//! A token resolves to an [`AuthUser`]: the bootstrap admin token to the
+//! admin role, a node's agent token (see [`node_bearer`]) to the node role,
+//! and `<id>.<secret>` to the role and namespace of the [`ApiToken`] stored
+//! at `/registry/tokens/<id>`. The join token is not one of them: it is only
+//! good for `POST /register`. Tokens are looked up on every request, so
+//! deleting one revokes it at once.
+//!
+//! [`node_bearer`]: pkg_types::rbac::node_bearer
+
+use std::collections::HashMap;
+
 use axum::{
-    extract::{Request, State},
-    http::{StatusCode, header},
+    extract::{Query, Request, State},
+    http::{Method, Uri, header},
     middleware::Next,
     response::Response,
 };
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::namespace::NAMESPACED_RESOURCES;
+use pkg_types::rbac::{ApiToken, TokenRole, bearer_token, parse_node_bearer, secrets_match};
 use serde::{Deserialize, Serialize};
-use tracing::{info, warn};
+use tracing::{debug, warn};
 
 use crate::AppState;
-// Removed: use std::sync::Arc;
-// Removed: use pkg_types::rbac::{Role, RoleBinding, Subject, SubjectKind};
+use crate::error::ApiResult;
 
-/// Information about the authenticated entity.
+/// The identity a request was authenticated as.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthUser {
     pub name: String,
-    pub token: String,
+    pub role: TokenRole,
+    /// Namespace the identity is confined to; `None` for cluster-wide.
+    pub namespace: Option<String>,
 }
 
-/// Middleware: Authenticates the request using a Bearer token.
-/// Currently, we validate against the global `join_token`. In a real system,
-/// this would look up ServiceAccount tokens or user tokens in SlateDB.
-pub async fn auth_middleware(
-    State(state): State<AppState>,
-    mut req: Request,
-    next: Next,
-) -> Result<Response, StatusCode> {
-    let auth_header = req.headers().get(header::AUTHORIZATION);
-
-    let token = match auth_header {
-        Some(value) => {
-            let value_str = value.to_str().map_err(|_| StatusCode::UNAUTHORIZED)?;
-            if !value_str.starts_with("Bearer ") {
-                return Err(StatusCode::UNAUTHORIZED);
+impl AuthUser {
+    /// Whether the identity may perform `attrs`.
+    pub fn can(&self, attrs: &RequestAttributes) -> bool {
+        let in_scope = match &self.namespace {
+            Some(scope) => attrs.namespace.as_deref() == Some(scope.as_str()),
+            None => true,
+        };
+        in_scope && self.role.allows(attrs.verb, &attrs.resource)
+    }
+}
+
+/// What a request does, in the terms roles are written in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestAttributes {
+    /// `get`, `watch`, `create`, `update` or `delete`.
+    pub verb: &'static str,
+    /// Resource such as `pods`, or `pods/exec` for a subresource.
+    pub resource: String,
+    /// Namespace of the objects touched; `None` for cluster-scoped objects
+    /// and for requests across all namespaces.
+    pub namespace: Option<String>,
+}
+
+impl RequestAttributes {
+    pub fn from_request(method: &Method, uri: &Uri) -> Self {
+        let verb = match *method {
+            Method::GET | Method::HEAD => "get",
+            Method::POST => "create",
+            Method::PUT | Method::PATCH => "update",
+            Method::DELETE => "delete",
+            _ => "",
+        };
+        let path = uri.path().trim_end_matches('/');
+        let parts: Vec<&str> = path
+            .strip_prefix("/api/v1/")
+            .map(|rest| rest.split('/').collect())
+            .unwrap_or_default();
+        let attrs = |verb, resource: String, namespace: Option<&str>| Self {
+            verb,
+            resource,
+            namespace: namespace.map(str::to_string),
+        };
+        let with_sub = |resource: &str, sub: Option<&&str>| match sub {
+            Some(sub) => format!("{}/{}", resource, sub),
+            None => resource.to_string(),
+        };
+
+        match parts.as_slice() {
+            ["watch"] => {
+                let prefix = Query::<HashMap<String, String>>::try_from_uri(uri)
+                    .ok()
+                    .and_then(|q| q.0.get("prefix").cloned())
+                    .unwrap_or_default();
+                let (resource, namespace) = watched_keys(&prefix);
+                attrs("watch", resource, namespace.as_deref())
+            }
+            ["namespaces"] => attrs(verb, "namespaces".into(), None),
+            ["namespaces", ns] => attrs(verb, "namespaces".into(), Some(ns)),
+            ["namespaces", ns, resource, rest @ ..] => {
+                attrs(verb, with_sub(resource, rest.get(1)), Some(ns))
+            }
+            // Cluster-scoped node subresources, e.g. /api/v1/nodes/{name}/taints,
+            // which the generic delete below would read as a namespace.
+            ["nodes", rest @ ..] => attrs(verb, with_sub("nodes", rest.get(1)), None),
+            // Generic delete: /api/v1/{resource_type}/{ns}/{name}.
+            [resource, ns, _] if *method == Method::DELETE => {
+                attrs(verb, resource.to_string(), Some(ns))
             }
-            value_str.trim_start_matches("Bearer ").to_string()
+            [resource, rest @ ..] => attrs(verb, with_sub(resource, rest.get(1)), None),
+            [] => attrs(verb, "*".into(), None),
         }
-        None => return Err(StatusCode::UNAUTHORIZED),
-    };
+    }
+}
 
-    // Very simplified auth check:
-    // If it's the join token, map it to a cluster-admin identity.
-    if token == state.join_token {
-        let user = AuthUser {
-            name: "admin".to_string(), // For demo purposes
-            token,
-        };
-        // Inject the authenticated user into the request extensions
-        req.extensions_mut().insert(user);
-        Ok(next.run(req).await)
-    } else {
-        warn!("Invalid Bearer token provided");
-        Err(StatusCode::UNAUTHORIZED)
+impl std::fmt::Display for RequestAttributes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.verb, self.resource)?;
+        match &self.namespace {
+            Some(ns) => write!(f, " in namespace '{}'", ns),
+            None => write!(f, " cluster-wide"),
+        }
     }
 }
 
-/// Extracts the action (verb) from the HTTP method.
-fn action_from_method(method: &axum::http::Method) -> &'static str {
-    match *method {
-        axum::http::Method::GET => "get",
-        axum::http::Method::POST => "create",
-        axum::http::Method::PUT | axum::http::Method::PATCH => "update",
-        axum::http::Method::DELETE => "delete",
-        _ => "",
+/// Resource and namespace covered by a watch on store `prefix`. Only a prefix
+/// that ends on a whole path segment narrows the watch: `/registry/pods/dev`
+/// also matches namespace `dev2`, so it counts as all pods.
+fn watched_keys(prefix: &str) -> (String, Option<String>) {
+    let Some(rest) = prefix.strip_prefix("/registry/") else {
+        return ("*".into(), None);
+    };
+    let segments: Vec<&str> = rest.split('/').collect();
+    match segments.as_slice() {
+        [resource, ns, _, ..]
+            if !ns.is_empty()
+                && (NAMESPACED_RESOURCES.contains(resource) || *resource == "namespaces") =>
+        {
+            (resource.to_string(), Some(ns.to_string()))
+        }
+        [resource, _, ..] if !resource.is_empty() => (resource.to_string(), None),
+        _ => ("*".into(), None),
     }
 }
 
-/// Simplistic mapping from an API path to a resource type.
-fn resource_from_path(path: &str) -> String {
-    let parts: Vec<&str> = path.split('/').collect();
-    if parts.len() >= 4 && parts[1] == "api" && parts[2] == "v1" {
-        // e.g. /api/v1/namespaces -> namespaces
-        // e.g. /api/v1/namespaces/default/pods -> pods
-        if parts.len() >= 6 {
-            return parts[5].to_string(); // pods, services, etc.
-        } else if parts.len() >= 4 {
-            return parts[3].to_string(); // namespaces, nodes
+fn unauthorized(message: &str) -> ApiError {
+    ApiError::new(ErrorReason::Unauthorized, message)
+}
+
+/// The identity `token` stands for, or `None` if it is unknown or revoked.
+pub async fn resolve_token(state: &AppState, token: &str) -> anyhow::Result<Option<AuthUser>> {
+    if secrets_match(token, &state.admin_token) {
+        return Ok(Some(AuthUser {
+            name: "admin".to_string(),
+            role: TokenRole::Admin,
+            namespace: None,
+        }));
+    }
+    if let Some((node_name, secret)) = parse_node_bearer(token) {
+        let expected = crate::handlers::register::agent_token(state, node_name).await?;
+        if !expected.is_some_and(|expected| secrets_match(secret, &expected)) {
+            return Ok(None);
         }
+        return Ok(Some(AuthUser {
+            name: format!("system:node:{}", node_name),
+            role: TokenRole::Node,
+            namespace: None,
+        }));
+    }
+    let Some((id, secret)) = token.split_once('.') else {
+        return Ok(None);
+    };
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Ok(None);
     }
-    "*".to_string() // Fallback
+    let Some(data) = state.store.get(&format!("/registry/tokens/{}", id)).await? else {
+        return Ok(None);
+    };
+    let stored: ApiToken = serde_json::from_slice(&data)?;
+    if !secrets_match(secret, &stored.secret) {
+        return Ok(None);
+    }
+    Ok(Some(AuthUser {
+        name: format!("token:{}", stored.name),
+        role: stored.role,
+        namespace: stored.namespace,
+    }))
 }
 
-/// Middleware: Checks if the authenticated user has RBAC permission for the action.
-/// (Simplified implementation).
-pub async fn rbac_middleware(
-    State(_state): State<AppState>,
-    req: Request,
+/// Middleware: resolve the request's bearer token to an [`AuthUser`].
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> ApiResult<Response> {
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(bearer_token)
+        .ok_or_else(|| unauthorized("missing bearer token"))?;
+    let Some(user) = resolve_token(&state, token).await? else {
+        warn!("Invalid Bearer token provided");
+        return Err(unauthorized("invalid or revoked bearer token").into());
+    };
+    req.extensions_mut().insert(user);
+    Ok(next.run(req).await)
+}
+
+/// Middleware: check the authenticated user's role and namespace against
+/// what the request does, before the handler runs.
+pub async fn rbac_middleware(req: Request, next: Next) -> ApiResult<Response> {
     let user = req
         .extensions()
         .get::<AuthUser>()
-        .ok_or(StatusCode::UNAUTHORIZED)?;
-
-    let path = req.uri().path();
-    let method = req.method();
-    let action = action_from_method(method);
-    let resource = resource_from_path(path);
-
-    info!(
-        "RBAC check: user={} action={} resource={} path={}",
-        user.name, action, resource, path
+        .ok_or_else(|| unauthorized("request is not authenticated"))?;
+    let attrs = RequestAttributes::from_request(req.method(), req.uri());
+    debug!(
+        "RBAC check: user={} role={} {} path={}",
+        user.name,
+        user.role,
+        attrs,
+        req.uri().path()
     );
-
-    // Hardcode cluster-admin bypass for simplicty, assuming the `admin` uses the join_token.
-    // In a full implementation, we would query prefix `/registry/rbac/rolebindings/`
-    // and check the rules within referenced `/registry/rbac/roles/`.
-    if user.name == "admin" {
+    if user.can(&attrs) {
         return Ok(next.run(req).await);
     }
 
     warn!(
-        "RBAC denied: user={} action={} resource={}",
-        user.name, action, resource
+        "RBAC denied: user={} role={} {}",
+        user.name, user.role, attrs
     );
-    Err(StatusCode::FORBIDDEN)
+    let scope = user
+        .namespace
+        .as_ref()
+        .map(|ns| format!(", namespace '{}'", ns))
+        .unwrap_or_default();
+    Err(ApiError::new(
+        ErrorReason::Forbidden,
+        format!(
+            "{} (role {}{}) is missing permission: {}",
+            user.name, user.role, scope, attrs
+        ),
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::test_state;
+    use axum::{Router, http::StatusCode, middleware, routing::any};
+    use chrono::Utc;
+
+    fn attrs(method: Method, uri: &str) -> RequestAttributes {
+        RequestAttributes::from_request(&method, &uri.parse().unwrap())
+    }
+
+    #[test]
+    fn test_request_attributes() {
+        let cases = [
+            (
+                Method::GET,
+                "/api/v1/namespaces/dev/pods",
+                "get",
+                "pods",
+                Some("dev"),
+            ),
+            (
+                Method::PUT,
+                "/api/v1/namespaces/dev/pods/web/status",
+                "update",
+                "pods/status",
+                Some("dev"),
+            ),
+            (
+                Method::POST,
+                "/api/v1/namespaces/dev/deployments/web/scale",
+                "create",
+                "deployments/scale",
+                Some("dev"),
+            ),
+            (
+                Method::GET,
+                "/api/v1/namespaces/dev",
+                "get",
+                "namespaces",
+                Some("dev"),
+            ),
+            (Method::GET, "/api/v1/namespaces", "get", "namespaces", None),
+            (
+                Method::DELETE,
+                "/api/v1/configmaps/dev/cfg",
+                "delete",
+                "configmaps",
+                Some("dev"),
+            ),
+            (Method::GET, "/api/v1/pods", "get", "pods", None),
+            (
+                Method::PUT,
+                "/api/v1/nodes/worker-1/heartbeat",
+                "update",
+                "nodes/heartbeat",
+                None,
+            ),
+            (
+                Method::DELETE,
+                "/api/v1/nodes/worker-1/taints",
+                "delete",
+                "nodes/taints",
+                None,
+            ),
+            (
+                Method::POST,
+                "/api/v1/cluster/backup",
+                "create",
+                "cluster",
+                None,
+            ),
+            (
+                Method::GET,
+                "/api/v1/watch?prefix=/registry/pods/dev/",
+                "watch",
+                "pods",
+                Some("dev"),
+            ),
+            (
+                Method::GET,
+                "/api/v1/watch?prefix=%2Fregistry%2Fpods%2Fdev",
+                "watch",
+                "pods",
+                None,
+            ),
+            (Method::GET, "/api/v1/watch", "watch", "*", None),
+        ];
+        for (method, uri, verb, resource, namespace) in cases {
+            assert_eq!(
+                attrs(method, uri),
+                RequestAttributes {
+                    verb,
+                    resource: resource.to_string(),
+                    namespace: namespace.map(str::to_string),
+                },
+                "{}",
+                uri
+            );
+        }
+    }
+
+    async fn store_token(state: &AppState, id: &str, role: TokenRole, ns: Option<&str>) -> String {
+        let token = ApiToken {
+            id: id.to_string(),
+            name: id.to_string(),
+            role,
+            namespace: ns.map(str::to_string),
+            secret: format!("{}-secret", id),
+            created_at: Utc::now(),
+        };
+        state
+            .store
+            .put(
+                &format!("/registry/tokens/{}", id),
+                &serde_json::to_vec(&token).unwrap(),
+            )
+            .await
+            .unwrap();
+        token.bearer()
+    }
+
+    /// Serve every API path with 200 behind the auth and RBAC middleware.
+    async fn serve(state: AppState) -> String {
+        let app = Router::new()
+            .route("/api/v1/{*path}", any(|| async { StatusCode::OK }))
+            .route_layer(middleware::from_fn(rbac_middleware))
+            .route_layer(middleware::from_fn_with_state(
+                state.clone(),
+                auth_middleware,
+            ))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        base
+    }
+
+    async fn call(base: &str, token: &str, method: Method, path: &str) -> reqwest::Response {
+        reqwest::Client::new()
+            .request(method, format!("{}{}", base, path))
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_role_allow_deny_matrix() {
+        let state = test_state("rbac-matrix").await;
+        let readonly = store_token(&state, "ro", TokenRole::Readonly, None).await;
+        let dev_readonly = store_token(&state, "devro", TokenRole::Readonly, Some("dev")).await;
+        let editor = store_token(&state, "ed", TokenRole::Editor, Some("dev")).await;
+        // Named like a node, to catch node paths read as namespaced ones.
+        let node_ns_admin = store_token(&state, "nsadm", TokenRole::Admin, Some("worker-1")).await;
+        let admin = state.admin_token.clone();
+        state
+            .store
+            .put("/registry/agenttokens/worker-1", b"agent-secret")
+            .await
+            .unwrap();
+        let node = pkg_types::rbac::node_bearer("worker-1", "agent-secret");
+        let join = state.join_token.clone();
+        let base = serve(state).await;
+
+        let get = Method::GET;
+        let post = Method::POST;
+        let put = Method::PUT;
+        let del = Method::DELETE;
+        // (case, method, path, expected status per token)
+        type Case<'a> = (&'a str, &'a Method, &'a str, &'a [(&'a str, u16)]);
+        #[rustfmt::skip]
+        let matrix: &[Case] = &[
+            ("read pods", &get, "/api/v1/namespaces/dev/pods",
+                &[(&admin, 200), (&readonly, 200), (&dev_readonly, 200), (&editor, 200), (&node, 200), (&join, 401)]),
+            ("read pods elsewhere", &get, "/api/v1/namespaces/prod/pods",
+                &[(&admin, 200), (&readonly, 200), (&dev_readonly, 403), (&editor, 403), (&node, 200)]),
+            ("read all pods", &get, "/api/v1/pods",
+                &[(&admin, 200), (&readonly, 200), (&dev_readonly, 403), (&editor, 403), (&node, 200)]),
+            ("read secrets", &get, "/api/v1/namespaces/dev/secrets/db",
+                &[(&admin, 200), (&readonly, 403), (&dev_readonly, 403), (&editor, 200), (&node, 200), (&join, 401)]),
+            ("create deployment", &post, "/api/v1/namespaces/dev/deployments",
+                &[(&admin, 200), (&readonly, 403), (&dev_readonly, 403), (&editor, 200), (&node, 403)]),
+            ("create deployment elsewhere", &post, "/api/v1/namespaces/prod/deployments",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 403)]),
+            ("generic delete", &del, "/api/v1/configmaps/dev/cfg",
+                &[(&admin, 200), (&readonly, 403), (&editor, 200), (&node, 403)]),
+            ("exec", &get, "/api/v1/namespaces/dev/pods/web/exec",
+                &[(&admin, 200), (&readonly, 403), (&editor, 200), (&node, 403)]),
+            ("copy from pod", &get, "/api/v1/namespaces/dev/pods/web/archive?path=/etc",
+                &[(&admin, 200), (&readonly, 403), (&editor, 200), (&node, 403)]),
+            ("port-forward", &get, "/api/v1/namespaces/dev/pods/web/portforward?port=80",
+                &[(&admin, 200), (&readonly, 403), (&editor, 200), (&node, 403)]),
+            ("pod status", &put, "/api/v1/namespaces/dev/pods/web/status",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 200), (&join, 401)]),
+            ("heartbeat", &put, "/api/v1/nodes/worker-1/heartbeat",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 200), (&join, 401)]),
+            ("agent tunnel", &get, "/api/v1/nodes/worker-1/tunnel",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 200)]),
+            ("cordon", &post, "/api/v1/nodes/worker-1/cordon",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 403), (&node_ns_admin, 403)]),
+            ("remove taint", &del, "/api/v1/nodes/worker-1/taints",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 403), (&node_ns_admin, 403)]),
+            ("delete namespace", &del, "/api/v1/namespaces/dev",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 403)]),
+            ("backup", &post, "/api/v1/cluster/backup",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 403)]),
+            ("mint token", &post, "/api/v1/tokens",
+                &[(&admin, 200), (&readonly, 403), (&editor, 403), (&node, 403)]),
+            ("watch namespace", &get, "/api/v1/watch?prefix=/registry/pods/dev/",
+                &[(&admin, 200), (&readonly, 200), (&dev_readonly, 200), (&editor, 200), (&node, 200)]),
+            ("watch everything", &get, "/api/v1/watch",
+                &[(&admin, 200), (&readonly, 403), (&dev_readonly, 403), (&editor, 403), (&node, 403)]),
+        ];
+        for (case, method, path, expected) in matrix {
+            for (token, status) in *expected {
+                let resp = call(&base, token, (*method).clone(), path).await;
+                assert_eq!(resp.status().as_u16(), *status, "{} with {}", case, token);
+            }
+        }
+
+        // An agent token only stands for its own node's registration.
+        let stale = pkg_types::rbac::node_bearer("worker-1", "old-secret");
+        let other = pkg_types::rbac::node_bearer("worker-2", "agent-secret");
+        for token in [&stale, &other] {
+            let resp = call(&base, token, Method::GET, "/api/v1/pods").await;
+            assert_eq!(resp.status(), 401, "{}", token);
+        }
+
+        // The denial is a structured error naming the missing permission.
+        let resp = call(&base, &dev_readonly, post, "/api/v1/namespaces/dev/pods").await;
+        let err: ApiError = resp.json().await.unwrap();
+        assert_eq!(err.reason, ErrorReason::Forbidden);
+        assert_eq!(
+            err.message,
+            "token:devro (role readonly, namespace 'dev') is missing permission: create pods in namespace 'dev'"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoked_token_is_rejected_immediately() {
+        let state = test_state("rbac-revoke").await;
+        let token = store_token(&state, "ci", TokenRole::Admin, None).await;
+        let base = serve(state.clone()).await;
+
+        let resp = call(&base, &token, Method::GET, "/api/v1/nodes").await;
+        assert_eq!(resp.status(), 200);
+
+        state.store.delete("/registry/tokens/ci").await.unwrap();
+        let resp = call(&base, &token, Method::GET, "/api/v1/nodes").await;
+        assert_eq!(resp.status(), 401);
+        let err: ApiError = resp.json().await.unwrap();
+        assert_eq!(err.reason, ErrorReason::Unauthorized);
+
+        // A wrong secret for a live id, or no token at all.
+        let other = store_token(&state, "ops", TokenRole::Admin, None).await;
+        let wrong = other.replace("ops-secret", "guess");
+        let resp = call(&base, &wrong, Method::GET, "/api/v1/nodes").await;
+        assert_eq!(resp.status(), 401);
+        let resp = reqwest::get(format!("{}/api/v1/nodes", base))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 401);
+    }
 }
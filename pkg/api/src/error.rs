@@ -0,0 +1,152 @@
+//! Error responses. Handlers return [`ApiResult`] and `?` out of failures as
+//! an [`ErrorResponse`], so every error they send is an [`ApiError`] body.
+//! [`error_body_middleware`] is only a fallback for the error responses no
+//! handler builds: extractor rejections and the router's own 404s and 405s.
+
+use axum::{
+    Json,
+    body::Body,
+    extract::Request,
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use pkg_types::error::ApiError;
+use tracing::warn;
+
+/// Largest plain error body [`error_body_middleware`] reads into a message.
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// An [`ApiError`] on its way out of a handler.
+#[derive(Debug)]
+pub struct ErrorResponse(pub ApiError);
+
+pub type ApiResult<T = Response> = Result<T, ErrorResponse>;
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.0.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, Json(self.0)).into_response()
+    }
+}
+
+impl From<ApiError> for ErrorResponse {
+    fn from(err: ApiError) -> Self {
+        Self(err)
+    }
+}
+
+/// Store and other unexpected failures.
+impl From<anyhow::Error> for ErrorResponse {
+    fn from(err: anyhow::Error) -> Self {
+        warn!("Request failed: {:#}", err);
+        Self(ApiError::internal(format!("{:#}", err)))
+    }
+}
+
+/// Stored objects that no longer deserialize, or responses that do not
+/// serialize.
+impl From<serde_json::Error> for ErrorResponse {
+    fn from(err: serde_json::Error) -> Self {
+        warn!("Request failed: {}", err);
+        Self(ApiError::internal(format!("serialization failed: {}", err)))
+    }
+}
+
+/// Rewrite error responses that are not JSON into an [`ApiError`] body, with
+/// the generic reason for their status and their text as the message.
+///
+/// Handlers should not rely on this: a failure they return should already be
+/// an [`ErrorResponse`] with a specific reason.
+pub async fn error_body_middleware(req: Request, next: Next) -> Response {
+    let resp = next.run(req).await;
+    let status = resp.status();
+    if !(status.is_client_error() || status.is_server_error()) {
+        return resp;
+    }
+    let is_json = resp
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if is_json {
+        return resp;
+    }
+
+    let (mut parts, body) = resp.into_parts();
+    let text = axum::body::to_bytes(body, MAX_ERROR_BODY)
+        .await
+        .map(|b| String::from_utf8_lossy(&b).into_owned())
+        .unwrap_or_default();
+    let text = match text.trim() {
+        "" => status.canonical_reason().unwrap_or_default().to_string(),
+        _ => text,
+    };
+    let err = ApiError::from_response(status.as_u16(), &text);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    let body = serde_json::to_vec(&err).unwrap_or_default();
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, middleware, routing::get};
+
+    #[tokio::test]
+    async fn test_plain_errors_get_json_bodies() {
+        let app = Router::new()
+            .route(
+                "/text",
+                get(|| async { (StatusCode::CONFLICT, "name is taken") }),
+            )
+            .route("/bare", get(|| async { StatusCode::NOT_FOUND }))
+            .route(
+                "/typed",
+                get(|| async {
+                    ApiResult::<()>::Err(
+                        ApiError::already_exists("pod", "web", Some("prod")).into(),
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(error_body_middleware));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        let call = |path: &str| {
+            let url = format!("{}{}", base, path);
+            async move {
+                let resp = reqwest::get(url).await.unwrap();
+                let status = resp.status().as_u16();
+                (status, resp.json::<serde_json::Value>().await.unwrap())
+            }
+        };
+
+        let (status, body) = call("/text").await;
+        assert_eq!(status, 409);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": 409, "reason": "Conflict", "message": "name is taken", "details": [],
+            })
+        );
+        let (_, body) = call("/bare").await;
+        assert_eq!(body["reason"], "NotFound");
+        assert_eq!(body["message"], "Not Found");
+        let (status, body) = call("/typed").await;
+        assert_eq!(status, 409);
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": 409,
+                "reason": "AlreadyExists",
+                "message": "pod 'web' already exists in namespace 'prod'",
+                "details": [],
+            })
+        );
+    }
+}
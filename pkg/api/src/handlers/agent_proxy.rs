@@ -0,0 +1,694 @@
+//! Reaching the agent that runs a pod, for the requests the server proxies
+//! to it (exec, logs, archive, port-forward). Clients only ever talk to the
+//! API server; it resolves `pod.node_name` to the node's agent API and
+//! forwards the request there with the node's agent token.
+//!
+//! An agent with a tunnel up (see `pkg_tunnel`) is reached over it, so nodes
+//! behind NAT work; otherwise the server dials the node's address.
+//!
+//! WebSocket sessions are opened to the agent before the client's request is
+//! upgraded, so an agent that cannot be reached is a `502` naming the node
+//! instead of a session that dies straight away. Once relaying, both sides
+//! are pinged and a side that has been silent for too long ends the session,
+//! so connections left half-open by a vanished peer are cleaned up.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::ws::{CloseFrame, Message, WebSocket},
+    http::{Method, Request, header},
+};
+use futures_util::{SinkExt, StreamExt};
+use hyper::body::Incoming;
+use hyper_util::rt::TokioIo;
+use pkg_constants::timings::{
+    AGENT_CONNECT_TIMEOUT_SECS, PROXY_IDLE_TIMEOUT_SECS, PROXY_PING_INTERVAL_SECS,
+};
+use pkg_tunnel::TunnelRegistry;
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::node::Node;
+use pkg_types::pod::Pod;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, http::HeaderValue};
+use tracing::{debug, error, warn};
+
+use crate::AppState;
+use crate::error::{ApiResult, ErrorResponse};
+
+/// Largest error body relayed from an agent.
+const MAX_ERROR_BODY: usize = 64 * 1024;
+
+/// A byte stream to an agent API: a TCP connection or a tunnel stream.
+pub(crate) trait AgentIo: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> AgentIo for T {}
+
+/// A WebSocket to an agent.
+pub(crate) type AgentSocket = tokio_tungstenite::WebSocketStream<Box<dyn AgentIo>>;
+
+/// A pod's container and the agent that runs it.
+pub(crate) struct AgentTarget {
+    /// Empty when the request is for the agent itself.
+    pub container_id: String,
+    pub node: Node,
+    /// Bearer token the agent API expects.
+    pub token: Option<String>,
+    tunnels: Arc<TunnelRegistry>,
+}
+
+impl AgentTarget {
+    /// `http://` base URL of the node's agent API.
+    pub fn base_url(&self) -> String {
+        format!("http://{}:{}", self.node.address, self.node.agent_api_port)
+    }
+
+    /// `502 Bad Gateway` naming the node whose agent could not be reached.
+    pub fn unreachable(&self, err: impl std::fmt::Display) -> ErrorResponse {
+        warn!("Agent on node {} unreachable: {}", self.node.name, err);
+        ApiError::new(
+            ErrorReason::BadGateway,
+            format!(
+                "failed to reach the agent on node '{}': {}",
+                self.node.name, err
+            ),
+        )
+        .into()
+    }
+
+    /// `502 Bad Gateway` relaying an error the agent answered with.
+    pub async fn failed(&self, resp: hyper::Response<Incoming>) -> ErrorResponse {
+        let status = resp.status();
+        let body = axum::body::to_bytes(Body::new(resp.into_body()), MAX_ERROR_BODY)
+            .await
+            .unwrap_or_default();
+        self.agent_error(status, &body)
+    }
+
+    fn agent_error(&self, status: impl std::fmt::Display, body: &[u8]) -> ErrorResponse {
+        ApiError::new(
+            ErrorReason::BadGateway,
+            format!(
+                "agent on node '{}' returned {}: {}",
+                self.node.name,
+                status,
+                String::from_utf8_lossy(body).trim()
+            ),
+        )
+        .into()
+    }
+
+    /// A connection to the agent API: a stream over the node's tunnel when
+    /// it has one up, else a TCP connection to its address.
+    async fn dial(&self) -> ApiResult<Box<dyn AgentIo>> {
+        if let Some(stream) = self.tunnels.open(&self.node.name) {
+            debug!("Reaching node {} over its tunnel", self.node.name);
+            return Ok(Box::new(stream));
+        }
+        let addr = (self.node.address.as_str(), self.node.agent_api_port);
+        let connect = tokio::net::TcpStream::connect(addr);
+        match tokio::time::timeout(Duration::from_secs(AGENT_CONNECT_TIMEOUT_SECS), connect).await {
+            Ok(Ok(conn)) => Ok(Box::new(conn)),
+            Ok(Err(e)) => Err(self.unreachable(e)),
+            Err(_) => {
+                Err(self.unreachable(format!("no answer within {}s", AGENT_CONNECT_TIMEOUT_SECS)))
+            }
+        }
+    }
+
+    /// Send `method url` to the agent. Both bodies stream: only reaching the
+    /// agent is bounded, as followed logs and archives last as long as they
+    /// last.
+    pub async fn send(
+        &self,
+        method: Method,
+        url: &reqwest::Url,
+        body: Body,
+    ) -> ApiResult<hyper::Response<Incoming>> {
+        let conn = self.dial().await?;
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(conn))
+            .await
+            .map_err(|e| self.unreachable(e))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                debug!("Agent connection ended: {}", e);
+            }
+        });
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        let mut req = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(header::HOST, url.authority());
+        if let Some(token) = &self.token {
+            req = req.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let req = req.body(body).map_err(|e| {
+            error!("Invalid agent request {}: {}", url, e);
+            ApiError::internal(format!("invalid agent request {}: {}", url, e))
+        })?;
+        sender
+            .send_request(req)
+            .await
+            .map_err(|e| self.unreachable(e))
+    }
+
+    /// Open a WebSocket to `path` (with query) on the agent, or the error to
+    /// send the client instead.
+    pub async fn connect_websocket(&self, path: &str) -> ApiResult<AgentSocket> {
+        let url = format!(
+            "ws://{}:{}{}",
+            self.node.address, self.node.agent_api_port, path
+        );
+        debug!("Opening agent WebSocket {}", url);
+        let mut request = url.into_client_request().map_err(|e| self.unreachable(e))?;
+        if let Some(token) = &self.token
+            && let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", token))
+        {
+            request
+                .headers_mut()
+                .insert(tungstenite::http::header::AUTHORIZATION, value);
+        }
+        let conn = self.dial().await?;
+        let handshake = tokio_tungstenite::client_async(request, conn);
+        match tokio::time::timeout(Duration::from_secs(AGENT_CONNECT_TIMEOUT_SECS), handshake).await
+        {
+            Ok(Ok((socket, _))) => Ok(socket),
+            Ok(Err(tungstenite::Error::Http(resp))) => {
+                Err(self.agent_error(resp.status(), resp.body().as_deref().unwrap_or_default()))
+            }
+            Ok(Err(e)) => Err(self.unreachable(e)),
+            Err(_) => {
+                Err(self.unreachable(format!("no answer within {}s", AGENT_CONNECT_TIMEOUT_SECS)))
+            }
+        }
+    }
+}
+
+/// The data of an agent response body, ending quietly at the first error
+/// (an agent going away mid-stream ends a followed log rather than failing
+/// it).
+pub(crate) fn body_stream(
+    resp: hyper::Response<Incoming>,
+) -> impl futures_util::Stream<Item = Result<Bytes, axum::Error>> + Send {
+    Body::new(resp.into_body())
+        .into_data_stream()
+        .take_while(|chunk| futures_util::future::ready(chunk.is_ok()))
+}
+
+/// Resolve `container` (the first one when `None`) of pod `ns/pod_name` to
+/// its runtime ID and the agent running it, or the error to send if that
+/// fails.
+pub(crate) async fn locate_container(
+    state: &AppState,
+    ns: &str,
+    pod_name: &str,
+    container: Option<&str>,
+) -> ApiResult<AgentTarget> {
+    let pod_key = format!("/registry/pods/{}/{}", ns, pod_name);
+    let Some(data) = state.store.get(&pod_key).await? else {
+        return Err(ApiError::not_found("pod", pod_name, Some(ns)).into());
+    };
+    let pod: Pod = serde_json::from_slice(&data)?;
+
+    let Some(container_id) = pod.select_container_id(container) else {
+        return Err(ApiError::new(
+            ErrorReason::BadRequest,
+            format!(
+                "container {} is not valid for pod {}",
+                container.unwrap_or("<first>"),
+                pod_name
+            ),
+        )
+        .into());
+    };
+
+    let Some(ref node_name) = pod.node_name else {
+        return Err(ApiError::new(
+            ErrorReason::BadRequest,
+            format!("pod '{}' is not scheduled to a node", pod_name),
+        )
+        .into());
+    };
+
+    // pod.node_name stores the human-readable node name (set by the
+    // scheduler), which matches the registry key /registry/nodes/{name}.
+    let mut target = locate_node(state, node_name).await?;
+    target.container_id = container_id;
+    Ok(target)
+}
+
+/// Resolve node `node_name` to its agent, for requests about the agent
+/// itself rather than a container (`container_id` is empty), or the error
+/// to send if that fails.
+pub(crate) async fn locate_node(state: &AppState, node_name: &str) -> ApiResult<AgentTarget> {
+    let node_key = format!("/registry/nodes/{}", node_name);
+    let Some(data) = state.store.get(&node_key).await? else {
+        return Err(ApiError::not_found("node", node_name, None).into());
+    };
+    let node: Node = serde_json::from_slice(&data)?;
+    let token = super::register::agent_token(state, &node.name).await?;
+    Ok(AgentTarget {
+        container_id: String::new(),
+        node,
+        token,
+        tunnels: state.tunnels.clone(),
+    })
+}
+
+fn to_agent(msg: Message) -> Option<tungstenite::Message> {
+    Some(match msg {
+        Message::Text(t) => tungstenite::Message::Text(t.as_str().into()),
+        Message::Binary(b) => tungstenite::Message::Binary(b),
+        Message::Ping(p) => tungstenite::Message::Ping(p),
+        Message::Pong(p) => tungstenite::Message::Pong(p),
+        Message::Close(_) => return None,
+    })
+}
+
+/// The agent's close frame, passed on so its reason (an exec's exit status)
+/// reaches the client.
+fn close_to_client(frame: Option<tungstenite::protocol::CloseFrame>) -> Option<CloseFrame> {
+    frame.map(|frame| CloseFrame {
+        code: frame.code.into(),
+        reason: frame.reason.as_str().into(),
+    })
+}
+
+fn to_client(msg: tungstenite::Message) -> Option<Message> {
+    Some(match msg {
+        tungstenite::Message::Text(t) => Message::Text(t.as_str().into()),
+        tungstenite::Message::Binary(b) => Message::Binary(b),
+        tungstenite::Message::Ping(p) => Message::Ping(p),
+        tungstenite::Message::Pong(p) => Message::Pong(p),
+        tungstenite::Message::Close(_) | tungstenite::Message::Frame(_) => return None,
+    })
+}
+
+/// Relay frames between the client's upgraded WebSocket and the agent's
+/// until either side closes, errors or goes quiet for longer than
+/// `PROXY_IDLE_TIMEOUT_SECS`. The side that is still there gets a close.
+pub(crate) async fn relay_websocket(client: WebSocket, agent: AgentSocket) {
+    let (mut client_tx, mut client_rx) = client.split();
+    let (mut agent_tx, mut agent_rx) = agent.split();
+    let idle = Duration::from_secs(PROXY_IDLE_TIMEOUT_SECS);
+    let keepalive = || {
+        let period = Duration::from_secs(PROXY_PING_INTERVAL_SECS);
+        tokio::time::interval_at(tokio::time::Instant::now() + period, period)
+    };
+
+    // Each direction pings the peer it writes to; the pongs show up on the
+    // other direction's reader, which tracks when its peer was last heard.
+    let mut client_to_agent = tokio::spawn(async move {
+        let mut ping = keepalive();
+        let mut seen = Instant::now();
+        loop {
+            tokio::select! {
+                msg = client_rx.next() => {
+                    let Some(Ok(msg)) = msg else { break };
+                    seen = Instant::now();
+                    let Some(msg) = to_agent(msg) else { break };
+                    if agent_tx.send(msg).await.is_err() {
+                        return;
+                    }
+                }
+                _ = ping.tick() => {
+                    if seen.elapsed() > idle {
+                        warn!("Proxied session: client silent for {:?}, closing", idle);
+                        break;
+                    }
+                    if agent_tx.send(tungstenite::Message::Ping(Default::default())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+        let _ = agent_tx.send(tungstenite::Message::Close(None)).await;
+    });
+
+    let mut agent_to_client = tokio::spawn(async move {
+        let mut ping = keepalive();
+        let mut seen = Instant::now();
+        let mut close = None;
+        loop {
+            tokio::select! {
+                msg = agent_rx.next() => {
+                    let Some(Ok(msg)) = msg else { break };
+                    seen = Instant::now();
+                    if let tungstenite::Message::Close(frame) = msg {
+                        close = close_to_client(frame);
+                        break;
+                    }
+                    let Some(msg) = to_client(msg) else { break };
+                    if client_tx.send(msg).await.is_err() {
+                        return;
+                    }
+                }
+                _ = ping.tick() => {
+                    if seen.elapsed() > idle {
+                        warn!("Proxied session: agent silent for {:?}, closing", idle);
+                        break;
+                    }
+                    if client_tx.send(Message::Ping(Default::default())).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+        let _ = client_tx.send(Message::Close(close)).await;
+    });
+
+    // The close sent by the finished direction normally makes the other peer
+    // close too; don't wait on a peer that never does.
+    let grace = Duration::from_secs(PROXY_PING_INTERVAL_SECS.min(5));
+    tokio::select! {
+        _ = &mut client_to_agent => {
+            debug!("Proxied session: client side ended");
+            let _ = tokio::time::timeout(grace, &mut agent_to_client).await;
+        }
+        _ = &mut agent_to_client => {
+            debug!("Proxied session: agent side ended");
+            let _ = tokio::time::timeout(grace, &mut client_to_agent).await;
+        }
+    }
+    client_to_agent.abort();
+    agent_to_client.abort();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::test_state;
+    use axum::{
+        Router,
+        extract::{Path as AxumPath, ws::WebSocketUpgrade},
+        http::{HeaderMap, StatusCode},
+        response::IntoResponse,
+        routing::get,
+    };
+    use pkg_types::exec::ExecExit;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::sync::mpsc;
+    use tokio_tungstenite::MaybeTlsStream;
+
+    const TOKEN: &str = "agent-secret";
+
+    /// Stand-in for an agent: `/exec/{id}` greets with the container ID and
+    /// echoes binary frames until it gets `exit`, which it answers by closing
+    /// with exit status 3, reporting on `closed` when the session ends;
+    /// `/containers/{id}/logs` answers with one line. Both want the token.
+    async fn fake_agent(closed: mpsc::UnboundedSender<()>) -> u16 {
+        let authorized = |headers: &HeaderMap| {
+            headers
+                .get(axum::http::header::AUTHORIZATION)
+                .is_some_and(|v| v == format!("Bearer {}", TOKEN).as_str())
+        };
+        let agent = Router::new()
+            .route(
+                "/exec/{id}",
+                get(
+                    move |AxumPath(id): AxumPath<String>,
+                          headers: HeaderMap,
+                          ws: WebSocketUpgrade| async move {
+                        if !authorized(&headers) {
+                            return StatusCode::UNAUTHORIZED.into_response();
+                        }
+                        ws.on_upgrade(move |mut socket| async move {
+                            let _ = socket.send(Message::Text(id.into())).await;
+                            while let Some(Ok(msg)) = socket.next().await {
+                                match msg {
+                                    Message::Binary(data) if data == "exit" => {
+                                        let exit = ExecExit { code: 3 };
+                                        let frame = CloseFrame {
+                                            code: axum::extract::ws::close_code::NORMAL,
+                                            reason: exit.encode().into(),
+                                        };
+                                        let _ = socket.send(Message::Close(Some(frame))).await;
+                                        break;
+                                    }
+                                    Message::Binary(data) => {
+                                        let _ = socket.send(Message::Binary(data)).await;
+                                    }
+                                    Message::Close(_) => break,
+                                    _ => {}
+                                }
+                            }
+                            let _ = closed.send(());
+                        })
+                    },
+                ),
+            )
+            .route(
+                "/containers/{id}/logs",
+                get(move |headers: HeaderMap| async move {
+                    if authorized(&headers) {
+                        (StatusCode::OK, "hello\n")
+                    } else {
+                        (StatusCode::UNAUTHORIZED, "missing agent token")
+                    }
+                }),
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, agent).await.ok() });
+        port
+    }
+
+    /// A server with pod `default/web-0` on `node-a`, whose agent API
+    /// listens on `agent_port`. Returns its address, the container ID and
+    /// its tunnels.
+    async fn server(
+        label: &str,
+        agent_port: u16,
+    ) -> (std::net::SocketAddr, String, Arc<TunnelRegistry>) {
+        let state = test_state(label).await;
+        let node = serde_json::json!({
+            "id": "n1",
+            "name": "node-a",
+            "address": "127.0.0.1",
+            "agent_api_port": agent_port,
+            "status": "Ready",
+            "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:00:00Z",
+            "labels": {}
+        });
+        let pod: Pod = serde_json::from_value(serde_json::json!({
+            "id": "pod-1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": "Running",
+            "node_name": "node-a",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": { "containers": [{ "name": "nginx", "image": "nginx:alpine" }] }
+        }))
+        .unwrap();
+        let store = &state.store;
+        store
+            .put(
+                "/registry/nodes/node-a",
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+        store
+            .put(
+                "/registry/pods/default/web-0",
+                &serde_json::to_vec(&pod).unwrap(),
+            )
+            .await
+            .unwrap();
+        store
+            .put("/registry/agenttokens/node-a", TOKEN.as_bytes())
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route(
+                "/api/v1/namespaces/{ns}/pods/{pod_name}/exec",
+                get(super::super::exec::exec_into_pod),
+            )
+            .route(
+                "/api/v1/namespaces/{ns}/pods/{pod_name}/logs",
+                get(super::super::resources::pod_logs),
+            )
+            .route(
+                "/api/v1/nodes/{name}/tunnel",
+                get(super::super::tunnel::node_tunnel),
+            )
+            .with_state(state.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        (addr, pod.select_container_id(None).unwrap(), state.tunnels)
+    }
+
+    /// A port nothing listens on.
+    async fn closed_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    async fn next_message(
+        ws: &mut tokio_tungstenite::WebSocketStream<MaybeTlsStream<TcpStream>>,
+    ) -> tungstenite::Message {
+        tokio::time::timeout(Duration::from_secs(5), ws.next())
+            .await
+            .expect("no frame from the proxy")
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Open a session to pod `web-0` and check it reaches its agent.
+    async fn exec_round_trip(addr: std::net::SocketAddr, container_id: &str) {
+        let url = format!(
+            "ws://{}/api/v1/namespaces/default/pods/web-0/exec?cmd=sh",
+            addr
+        );
+        let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        assert_eq!(
+            next_message(&mut ws).await,
+            tungstenite::Message::Text(container_id.into())
+        );
+        ws.send(tungstenite::Message::Binary(b"ping"[..].into()))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_message(&mut ws).await,
+            tungstenite::Message::Binary(b"ping"[..].into())
+        );
+        ws.close(None).await.unwrap();
+    }
+
+    async fn logs_round_trip(addr: std::net::SocketAddr) {
+        let resp = reqwest::get(format!(
+            "http://{}/api/v1/namespaces/default/pods/web-0/logs",
+            addr
+        ))
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(body["logs"][0], "hello");
+    }
+
+    #[tokio::test]
+    async fn test_exec_is_relayed_to_the_pods_agent() {
+        let (closed_tx, mut closed_rx) = mpsc::unbounded_channel();
+        let (addr, container_id, _) = server("proxy-exec", fake_agent(closed_tx).await).await;
+
+        exec_round_trip(addr, &container_id).await;
+        // Closing the client ends the agent's side of the session too.
+        tokio::time::timeout(Duration::from_secs(5), closed_rx.recv())
+            .await
+            .expect("agent session left open");
+    }
+
+    #[tokio::test]
+    async fn test_exit_status_is_relayed_to_the_client() {
+        let (closed_tx, _closed_rx) = mpsc::unbounded_channel();
+        let (addr, container_id, _) = server("proxy-exit", fake_agent(closed_tx).await).await;
+
+        let url = format!(
+            "ws://{}/api/v1/namespaces/default/pods/web-0/exec?cmd=false",
+            addr
+        );
+        let (mut ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        assert_eq!(
+            next_message(&mut ws).await,
+            tungstenite::Message::Text(container_id.into())
+        );
+        ws.send(tungstenite::Message::Binary(b"exit"[..].into()))
+            .await
+            .unwrap();
+        let tungstenite::Message::Close(Some(frame)) = next_message(&mut ws).await else {
+            panic!("session closed without an exit status");
+        };
+        assert_eq!(ExecExit::decode(&frame.reason), Some(ExecExit { code: 3 }));
+    }
+
+    #[tokio::test]
+    async fn test_logs_are_relayed_to_the_pods_agent() {
+        let (closed_tx, _closed_rx) = mpsc::unbounded_channel();
+        let (addr, _, _) = server("proxy-logs", fake_agent(closed_tx).await).await;
+
+        logs_round_trip(addr).await;
+    }
+
+    #[tokio::test]
+    async fn test_agent_is_reached_over_its_tunnel() {
+        let (closed_tx, _closed_rx) = mpsc::unbounded_channel();
+        let agent_port = fake_agent(closed_tx).await;
+        // The node's address is not dialable: only the tunnel reaches it.
+        let (addr, container_id, tunnels) = server("proxy-tunnel", closed_port().await).await;
+
+        let url = format!("ws://{}/api/v1/nodes/node-a/tunnel", addr);
+        tokio::spawn(pkg_tunnel::agent::run(
+            move || {
+                let mut request = url.as_str().into_client_request().unwrap();
+                request.headers_mut().insert(
+                    pkg_tunnel::AGENT_TOKEN_HEADER,
+                    HeaderValue::from_static(TOKEN),
+                );
+                Some(request)
+            },
+            ([127, 0, 0, 1], agent_port).into(),
+        ));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !tunnels.is_connected("node-a") {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("tunnel never came up");
+
+        exec_round_trip(addr, &container_id).await;
+        logs_round_trip(addr).await;
+        assert_eq!(tunnels.stats()[0].opened_streams, 2);
+    }
+
+    #[tokio::test]
+    async fn test_tunnel_needs_the_nodes_agent_token() {
+        let (addr, _, tunnels) = server("proxy-tunnel-auth", closed_port().await).await;
+
+        let mut request = format!("ws://{}/api/v1/nodes/node-a/tunnel", addr)
+            .into_client_request()
+            .unwrap();
+        request.headers_mut().insert(
+            pkg_tunnel::AGENT_TOKEN_HEADER,
+            HeaderValue::from_static("someone-else"),
+        );
+        let err = tokio_tungstenite::connect_async(request).await.unwrap_err();
+        let tungstenite::Error::Http(resp) = err else {
+            panic!("expected an HTTP error response, got {}", err);
+        };
+        assert_eq!(resp.status(), 401);
+        assert!(!tunnels.is_connected("node-a"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_node_is_bad_gateway() {
+        let (addr, _, _) = server("proxy-unreachable", closed_port().await).await;
+        let base = format!("{}/api/v1/namespaces/default/pods/web-0", addr);
+
+        let err = tokio_tungstenite::connect_async(format!("ws://{}/exec?cmd=sh", base))
+            .await
+            .unwrap_err();
+        let tungstenite::Error::Http(resp) = err else {
+            panic!("expected an HTTP error response, got {}", err);
+        };
+        assert_eq!(resp.status(), 502);
+        let body: ApiError = serde_json::from_slice(resp.body().as_deref().unwrap()).unwrap();
+        assert_eq!(body.reason, ErrorReason::BadGateway);
+        assert!(body.message.contains("node-a"), "{}", body.message);
+
+        let resp = reqwest::get(format!("http://{}/logs", base)).await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::BAD_GATEWAY);
+        let body: ApiError = resp.json().await.unwrap();
+        assert_eq!(body.reason, ErrorReason::BadGateway);
+        assert!(body.message.contains("node-a"), "{}", body.message);
+    }
+}
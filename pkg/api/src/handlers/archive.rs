@@ -0,0 +1,89 @@
+use axum::{
+    body::Body,
+    extract::{Path as AxumPath, Query, State},
+    http::Method,
+    response::IntoResponse,
+};
+use pkg_types::error::ApiError;
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use super::agent_proxy::{AgentTarget, locate_container};
+use crate::AppState;
+use crate::error::ApiResult;
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveQuery {
+    /// Absolute path inside the container.
+    pub path: String,
+    /// Container to copy from or to; defaults to the pod's first container.
+    pub container: Option<String>,
+}
+
+/// The agent archive endpoint for the container `query` picks in pod
+/// `ns/pod_name`, or the error to send if there is none.
+async fn agent_archive_url(
+    state: &AppState,
+    ns: &str,
+    pod_name: &str,
+    query: &ArchiveQuery,
+) -> ApiResult<(reqwest::Url, AgentTarget)> {
+    let target = locate_container(state, ns, pod_name, query.container.as_deref()).await?;
+
+    let url = reqwest::Url::parse_with_params(
+        &format!(
+            "{}/containers/{}/archive",
+            target.base_url(),
+            target.container_id
+        ),
+        [("path", &query.path)],
+    )
+    .map_err(|e| ApiError::internal(e.to_string()))?;
+    Ok((url, target))
+}
+
+/// GET /api/v1/namespaces/:ns/pods/:name/archive?path= — stream `path` out
+/// of the pod's container as a tar archive, relayed from its agent.
+pub async fn get_pod_archive(
+    State(state): State<AppState>,
+    AxumPath((ns, pod_name)): AxumPath<(String, String)>,
+    Query(query): Query<ArchiveQuery>,
+) -> ApiResult {
+    let (url, target) = agent_archive_url(&state, &ns, &pod_name, &query).await?;
+    debug!("Proxying archive read {}/{} → {}", ns, pod_name, url);
+
+    let resp = target.send(Method::GET, &url, Body::empty()).await?;
+    if !resp.status().is_success() {
+        warn!("Archive read from {}/{} failed", ns, pod_name);
+        return Err(target.failed(resp).await);
+    }
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "application/x-tar")],
+        Body::new(resp.into_body()),
+    )
+        .into_response())
+}
+
+/// PUT /api/v1/namespaces/:ns/pods/:name/archive?path= — unpack the tar
+/// archive in the body at `path` in the pod's container. The body is
+/// streamed to the agent; the response carries the bytes written.
+pub async fn put_pod_archive(
+    State(state): State<AppState>,
+    AxumPath((ns, pod_name)): AxumPath<(String, String)>,
+    Query(query): Query<ArchiveQuery>,
+    body: Body,
+) -> ApiResult {
+    let (url, target) = agent_archive_url(&state, &ns, &pod_name, &query).await?;
+    debug!("Proxying archive write {}/{} → {}", ns, pod_name, url);
+
+    let resp = target.send(Method::PUT, &url, body).await?;
+    if !resp.status().is_success() {
+        warn!("Archive write to {}/{} failed", ns, pod_name);
+        return Err(target.failed(resp).await);
+    }
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, "application/json")],
+        Body::new(resp.into_body()),
+    )
+        .into_response())
+}
@@ -1,14 +1,17 @@
+use anyhow::Context;
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
 use chrono::Utc;
 use flate2::Compression;
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
 use pkg_types::backup::{BACKUP_VERSION, BackupEntry, BackupFile, BackupPki, BackupStatus};
+use pkg_types::error::{ApiError, ErrorReason};
 use std::io::{Read, Write};
 use std::sync::atomic::Ordering;
 use tracing::{info, warn};
 
 use crate::AppState;
+use crate::error::{ApiResult, ErrorResponse};
 
 // ─────────────────────────────────────────────────────────────────────────────
 // Core backup / restore helpers
@@ -112,64 +115,51 @@ pub async fn backup_status(State(state): State<AppState>) -> impl IntoResponse {
 
 /// `POST /api/v1/cluster/backup`
 /// Snapshot the cluster state and stream the gzip backup as a download.
-pub async fn create_backup_handler(State(state): State<AppState>) -> axum::response::Response {
+pub async fn create_backup_handler(State(state): State<AppState>) -> ApiResult {
     if state.restore_in_progress.load(Ordering::SeqCst) {
-        return (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({"error": "restore in progress"})),
-        )
-            .into_response();
+        return Err(restore_in_progress().into());
     }
 
-    match create_backup_bytes(&state).await {
-        Ok((backup, compressed)) => {
-            let filename = format!(
-                "backup-{}.k3rs-backup.json.gz",
-                backup.created_at.format("%Y%m%d-%H%M%S")
-            );
-            info!(
-                "Backup created on-demand: {} keys → {}",
-                backup.key_count, filename
-            );
-
-            // Persist last-backup metadata to store
-            let st = BackupStatus {
-                last_backup_at: Some(backup.created_at),
-                last_backup_file: Some(filename.clone()),
-                key_count: Some(backup.key_count),
-                status: "success".to_string(),
-            };
-            if let Ok(data) = serde_json::to_vec(&st) {
-                let _ = state.store.put("/registry/_backup/last", &data).await;
-            }
-
-            let mut headers = axum::http::HeaderMap::new();
-            headers.insert(
-                axum::http::header::CONTENT_TYPE,
-                "application/gzip".parse().unwrap(),
-            );
-            headers.insert(
-                axum::http::header::CONTENT_DISPOSITION,
-                format!("attachment; filename=\"{}\"", filename)
-                    .parse()
-                    .unwrap(),
-            );
-            headers.insert(
-                axum::http::header::CONTENT_LENGTH,
-                compressed.len().to_string().parse().unwrap(),
-            );
-
-            (StatusCode::OK, headers, compressed).into_response()
-        }
-        Err(e) => {
-            warn!("On-demand backup failed: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"error": e.to_string()})),
-            )
-                .into_response()
-        }
+    let (backup, compressed) = create_backup_bytes(&state)
+        .await
+        .context("on-demand backup failed")?;
+    let filename = format!(
+        "backup-{}.k3rs-backup.json.gz",
+        backup.created_at.format("%Y%m%d-%H%M%S")
+    );
+    info!(
+        "Backup created on-demand: {} keys → {}",
+        backup.key_count, filename
+    );
+
+    // Persist last-backup metadata to store
+    let st = BackupStatus {
+        last_backup_at: Some(backup.created_at),
+        last_backup_file: Some(filename.clone()),
+        key_count: Some(backup.key_count),
+        status: "success".to_string(),
+    };
+    if let Ok(data) = serde_json::to_vec(&st) {
+        let _ = state.store.put("/registry/_backup/last", &data).await;
     }
+
+    let mut headers = axum::http::HeaderMap::new();
+    headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        "application/gzip".parse().unwrap(),
+    );
+    headers.insert(
+        axum::http::header::CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{}\"", filename)
+            .parse()
+            .unwrap(),
+    );
+    headers.insert(
+        axum::http::header::CONTENT_LENGTH,
+        compressed.len().to_string().parse().unwrap(),
+    );
+
+    Ok((StatusCode::OK, headers, compressed).into_response())
 }
 
 /// `POST /api/v1/cluster/restore`
@@ -178,12 +168,13 @@ pub async fn create_backup_handler(State(state): State<AppState>) -> axum::respo
 pub async fn restore_cluster_handler(
     State(state): State<AppState>,
     body: axum::body::Bytes,
-) -> (StatusCode, Json<serde_json::Value>) {
+) -> ApiResult<Json<serde_json::Value>> {
     if !state.is_leader.load(Ordering::SeqCst) {
-        return (
-            StatusCode::FORBIDDEN,
-            Json(serde_json::json!({"error": "restore can only be triggered on the leader"})),
-        );
+        return Err(ApiError::new(
+            ErrorReason::Forbidden,
+            "restore can only be triggered on the leader",
+        )
+        .into());
     }
     do_restore(&state, &body, false).await
 }
@@ -193,7 +184,7 @@ pub async fn restore_cluster_handler(
 pub async fn restore_dry_run_handler(
     State(state): State<AppState>,
     body: axum::body::Bytes,
-) -> (StatusCode, Json<serde_json::Value>) {
+) -> ApiResult<Json<serde_json::Value>> {
     do_restore(&state, &body, true).await
 }
 
@@ -205,25 +196,18 @@ async fn do_restore(
     state: &AppState,
     data: &[u8],
     dry_run: bool,
-) -> (StatusCode, Json<serde_json::Value>) {
+) -> ApiResult<Json<serde_json::Value>> {
     // Parse
-    let backup = match parse_backup_bytes(data) {
-        Ok(b) => b,
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(serde_json::json!({"error": format!("Failed to parse backup: {}", e)})),
-            );
-        }
-    };
+    let backup = parse_backup_bytes(data).map_err(|e| {
+        ApiError::new(
+            ErrorReason::BadRequest,
+            format!("Failed to parse backup: {}", e),
+        )
+    })?;
 
     // Validate
-    if let Err(e) = validate_backup(&backup) {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(serde_json::json!({"error": format!("Invalid backup: {}", e)})),
-        );
-    }
+    validate_backup(&backup)
+        .map_err(|e| ApiError::new(ErrorReason::BadRequest, format!("Invalid backup: {}", e)))?;
 
     // Dry-run: return info without touching the store
     if dry_run {
@@ -231,16 +215,13 @@ async fn do_restore(
             "Restore dry-run OK: {} entries, backup created at {}",
             backup.key_count, backup.created_at
         );
-        return (
-            StatusCode::OK,
-            Json(serde_json::json!({
-                "dry_run": true,
-                "would_restore": backup.key_count,
-                "backup_created_at": backup.created_at,
-                "backup_version": backup.version,
-                "cluster_name": backup.cluster_name,
-            })),
-        );
+        return Ok(Json(serde_json::json!({
+            "dry_run": true,
+            "would_restore": backup.key_count,
+            "backup_created_at": backup.created_at,
+            "backup_version": backup.version,
+            "cluster_name": backup.cluster_name,
+        })));
     }
 
     // ── Live restore ─────────────────────────────────────────────────────────
@@ -276,14 +257,11 @@ async fn do_restore(
                 .await;
 
             info!("Cluster restore completed: {} entries imported", imported);
-            (
-                StatusCode::OK,
-                Json(serde_json::json!({
-                    "status": "completed",
-                    "imported": imported,
-                    "backup_created_at": backup.created_at,
-                })),
-            )
+            Ok(Json(serde_json::json!({
+                "status": "completed",
+                "imported": imported,
+                "backup_created_at": backup.created_at,
+            })))
         }
         Err(e) => {
             warn!("Cluster restore failed: {}", e);
@@ -291,10 +269,7 @@ async fn do_restore(
                 .store
                 .put("/registry/_restore/status", b"failed")
                 .await;
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({"error": format!("Restore failed: {}", e)})),
-            )
+            Err(ApiError::internal(format!("Restore failed: {}", e)).into())
         }
     }
 }
@@ -333,11 +308,15 @@ pub async fn restore_guard_middleware(
 ) -> axum::response::Response {
     let path = request.uri().path().to_string();
     if state.restore_in_progress.load(Ordering::SeqCst) && !path.contains("/restore") {
-        return (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({"error": "cluster restore in progress"})),
-        )
-            .into_response();
+        return ErrorResponse(restore_in_progress()).into_response();
     }
     next.run(request).await
 }
+
+/// 503 while a cluster restore is replacing the store.
+fn restore_in_progress() -> ApiError {
+    ApiError::new(
+        ErrorReason::ServiceUnavailable,
+        "cluster restore in progress",
+    )
+}
@@ -0,0 +1,252 @@
+//! Node certificate renewal and the expiry gauge.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use chrono::{DateTime, Duration, Utc};
+use pkg_constants::timings::{CERT_RENEWAL_MAX_SKEW_SECS, NODE_CERT_VALIDITY_DAYS};
+use pkg_controllers::events;
+use pkg_metrics::MetricsRegistry;
+use pkg_pki::cert::{renewal_message, verify_signature};
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::event::{CLUSTER_EVENT_NAMESPACE, Event};
+use pkg_types::node::{CertificateRenewalRequest, CertificateRenewalResponse, Node};
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::error::ApiResult;
+
+/// Gauge of the seconds until each node's certificate expires, labeled by node.
+pub const NODE_CERT_EXPIRY_METRIC: &str = "k3rs_node_certificate_expiry_seconds";
+
+/// POST /api/v1/nodes/{name}/certificate/renew — issue a node a fresh
+/// certificate from the cluster CA.
+///
+/// The node authenticates with the certificate it holds rather than a
+/// bearer token: the certificate must have been issued to it by this CA and
+/// not have expired yet, and the request must be signed with its key at a
+/// timestamp close to the server's clock.
+pub async fn renew_certificate(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+    Json(req): Json<CertificateRenewalRequest>,
+) -> ApiResult<Json<CertificateRenewalResponse>> {
+    let rejected = |reason: String| {
+        warn!(
+            "Rejected certificate renewal for node {}: {}",
+            node_name, reason
+        );
+        ApiError::new(
+            ErrorReason::Unauthorized,
+            format!(
+                "certificate renewal for node '{}' rejected: {}",
+                node_name, reason
+            ),
+        )
+    };
+
+    let now = Utc::now();
+    if let Err(e) = state.ca.verify_node_cert(&req.certificate, &node_name, now) {
+        return Err(rejected(e.to_string()).into());
+    }
+    if (now - req.timestamp).num_seconds().abs() > CERT_RENEWAL_MAX_SKEW_SECS {
+        return Err(rejected(format!(
+            "request timestamp {} is more than {}s from the server's clock",
+            req.timestamp, CERT_RENEWAL_MAX_SKEW_SECS
+        ))
+        .into());
+    }
+    let message = renewal_message(&node_name, req.timestamp);
+    if !verify_signature(&req.certificate, &message, &req.signature).unwrap_or(false) {
+        return Err(rejected("signature does not match the certificate".to_string()).into());
+    }
+
+    let key = format!("/registry/nodes/{}", node_name);
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("node", &node_name, None).into());
+    };
+    let mut node: Node = serde_json::from_slice(&data)?;
+
+    let validity = Duration::days(NODE_CERT_VALIDITY_DAYS);
+    let (certificate, private_key) = state.ca.issue_node_cert(&node_name, validity)?;
+    let expires_at = now + validity;
+    node.certificate_expires_at = Some(expires_at);
+    state.store.put(&key, &serde_json::to_vec(&node)?).await?;
+
+    info!(
+        "Renewed certificate for node {} (valid until {})",
+        node_name, expires_at
+    );
+    events::record(
+        &state.store,
+        Event::normal(
+            "node",
+            CLUSTER_EVENT_NAMESPACE,
+            &node_name,
+            "CertificateRenewed",
+            format!("Certificate renewed, valid until {}", expires_at),
+        ),
+    )
+    .await;
+
+    Ok(Json(CertificateRenewalResponse {
+        certificate,
+        private_key,
+        server_ca: state.ca.ca_cert_pem().to_string(),
+        expires_at,
+    }))
+}
+
+/// Set [`NODE_CERT_EXPIRY_METRIC`] for every node that reported when its
+/// certificate expires; already expired certificates go negative.
+pub fn update_expiry_gauge(metrics: &MetricsRegistry, nodes: &[Node], now: DateTime<Utc>) {
+    metrics.gauge_clear_labeled(NODE_CERT_EXPIRY_METRIC);
+    for node in nodes {
+        if let Some(expires_at) = node.certificate_expires_at {
+            metrics.gauge_set_with(
+                NODE_CERT_EXPIRY_METRIC,
+                &[("node", &node.name)],
+                (expires_at - now).num_seconds(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::register::register_node;
+    use crate::handlers::testing::test_state;
+    use pkg_types::node::NodeRegistrationRequest;
+
+    async fn register(state: &AppState) {
+        let req: NodeRegistrationRequest = serde_json::from_value(serde_json::json!({
+            "token": "test-token",
+            "node_name": "node-a",
+            "address": "10.0.0.5",
+        }))
+        .unwrap();
+        let _ = register_node(State(state.clone()), Json(req)).await;
+    }
+
+    fn signed(cert_pem: &str, key_pem: &str, at: DateTime<Utc>) -> CertificateRenewalRequest {
+        CertificateRenewalRequest {
+            certificate: cert_pem.to_string(),
+            timestamp: at,
+            signature: pkg_pki::cert::sign(key_pem, &renewal_message("node-a", at)).unwrap(),
+        }
+    }
+
+    async fn renew(
+        state: &AppState,
+        req: CertificateRenewalRequest,
+    ) -> ApiResult<Json<CertificateRenewalResponse>> {
+        renew_certificate(State(state.clone()), Path("node-a".to_string()), Json(req)).await
+    }
+
+    #[tokio::test]
+    async fn test_renewal_with_a_short_lived_cert() {
+        let state = test_state("cert-renew").await;
+        register(&state).await;
+        let now = Utc::now();
+        let (cert_pem, key_pem) = state
+            .ca
+            .issue_node_cert("node-a", Duration::minutes(10))
+            .unwrap();
+
+        let Json(resp) = renew(&state, signed(&cert_pem, &key_pem, now))
+            .await
+            .unwrap();
+        let not_after = pkg_pki::cert::not_after(&resp.certificate).unwrap();
+        assert!(not_after > now + Duration::days(NODE_CERT_VALIDITY_DAYS - 1));
+        assert!((not_after - resp.expires_at).num_seconds().abs() <= 1);
+        // The new pair is usable for the next renewal.
+        state
+            .ca
+            .verify_node_cert(&resp.certificate, "node-a", now)
+            .unwrap();
+        let data = state
+            .store
+            .get("/registry/nodes/node-a")
+            .await
+            .unwrap()
+            .unwrap();
+        let node: Node = serde_json::from_slice(&data).unwrap();
+        assert_eq!(node.certificate_expires_at, Some(resp.expires_at));
+
+        // A signature from another key, or for a stale timestamp.
+        let (_, other_key) = state
+            .ca
+            .issue_node_cert("node-a", Duration::minutes(10))
+            .unwrap();
+        let err = renew(&state, signed(&cert_pem, &other_key, now))
+            .await
+            .unwrap_err();
+        assert_eq!(err.0.reason, ErrorReason::Unauthorized);
+        let stale = now - Duration::seconds(CERT_RENEWAL_MAX_SKEW_SECS + 60);
+        let err = renew(&state, signed(&cert_pem, &key_pem, stale))
+            .await
+            .unwrap_err();
+        assert_eq!(err.0.reason, ErrorReason::Unauthorized);
+    }
+
+    #[tokio::test]
+    async fn test_expired_cert_is_rejected() {
+        let state = test_state("cert-expired").await;
+        register(&state).await;
+        let now = Utc::now();
+        let (cert_pem, key_pem) = state
+            .ca
+            .issue_node_cert_between("node-a", now - Duration::hours(2), now - Duration::hours(1))
+            .unwrap();
+
+        let err = renew(&state, signed(&cert_pem, &key_pem, now))
+            .await
+            .unwrap_err();
+        assert_eq!(err.0.code, 401);
+        assert!(err.0.message.contains("expired"));
+
+        // Neither is another node's certificate accepted.
+        let (other_cert, other_key) = state
+            .ca
+            .issue_node_cert("node-b", Duration::minutes(10))
+            .unwrap();
+        let err = renew(&state, signed(&other_cert, &other_key, now))
+            .await
+            .unwrap_err();
+        assert!(err.0.message.contains("not issued to node 'node-a'"));
+    }
+
+    #[test]
+    fn test_expiry_gauge() {
+        let metrics = MetricsRegistry::new();
+        metrics.register_gauge(
+            NODE_CERT_EXPIRY_METRIC,
+            "Seconds until node certificates expire",
+        );
+        let now = Utc::now();
+        let node = |name: &str, expires_at: Option<DateTime<Utc>>| {
+            let mut node: Node = serde_json::from_value(serde_json::json!({
+                "id": name, "name": name, "address": "10.0.0.5", "agent_api_port": 10250,
+                "status": "Ready", "registered_at": now, "last_heartbeat": now, "labels": {},
+            }))
+            .unwrap();
+            node.certificate_expires_at = expires_at;
+            node
+        };
+        update_expiry_gauge(
+            &metrics,
+            &[
+                node("node-a", Some(now + Duration::days(30))),
+                node("node-b", Some(now - Duration::hours(1))),
+                node("node-c", None),
+            ],
+            now,
+        );
+        let text = metrics.render();
+        assert!(text.contains(r#"k3rs_node_certificate_expiry_seconds{node="node-a"} 2592000"#));
+        assert!(text.contains(r#"k3rs_node_certificate_expiry_seconds{node="node-b"} -3600"#));
+        assert!(!text.contains("node-c"));
+    }
+}
@@ -1,8 +1,18 @@
-use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use pkg_types::error::ApiError;
+use pkg_types::lease::{LeaderInfo, Lease};
 use pkg_types::node::{ClusterInfo, Node};
+use std::sync::atomic::Ordering;
 use tracing::info;
 
 use crate::AppState;
+use crate::error::ApiResult;
+use crate::pagination::{Page, PageQuery, list_page};
 
 /// GET /api/v1/cluster/info — return cluster metadata.
 pub async fn cluster_info(State(state): State<AppState>) -> impl IntoResponse {
@@ -25,7 +35,8 @@ pub async fn cluster_info(State(state): State<AppState>) -> impl IntoResponse {
     let info = ClusterInfo {
         endpoint: format!("http://{}", state.listen_addr),
         version: "v0.1.0+k3rs".to_string(),
-        state_store: "SlateDB (local)".to_string(),
+        state_store: state.store.describe(),
+        state_store_location: Some(state.store.location()),
         node_count: nodes.len(),
         cluster_id,
     };
@@ -33,22 +44,45 @@ pub async fn cluster_info(State(state): State<AppState>) -> impl IntoResponse {
     (StatusCode::OK, Json(info)).into_response()
 }
 
+/// The leader lease as stored, if any server has taken it.
+pub(crate) async fn leader_lease(state: &AppState) -> anyhow::Result<Option<Lease>> {
+    let data = state
+        .store
+        .get(pkg_constants::state::LEADER_LEASE_KEY)
+        .await?;
+    Ok(data.map(|data| serde_json::from_slice(&data)).transpose()?)
+}
+
+/// GET /api/v1/cluster/leader — which server holds the leader lease.
+pub async fn cluster_leader(State(state): State<AppState>) -> ApiResult<Json<LeaderInfo>> {
+    let lease = leader_lease(&state).await?;
+    Ok(Json(LeaderInfo {
+        leader: lease.as_ref().map(|l| l.holder_id.clone()),
+        acquired_at: lease.as_ref().map(|l| l.acquired_at),
+        renewed_at: lease.as_ref().map(|l| l.renew_at),
+        expired: lease.as_ref().is_some_and(|l| l.is_expired()),
+        server_id: state.server_id.clone(),
+        is_leader: state.is_leader.load(Ordering::Relaxed),
+    }))
+}
+
 /// GET /api/v1/nodes — list all registered nodes.
-pub async fn list_nodes(State(state): State<AppState>) -> impl IntoResponse {
+pub async fn list_nodes(
+    State(state): State<AppState>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<Node>> {
     info!("Serving node list request");
+    list_page(&state.store, "/registry/nodes/", &page, |_| true).await
+}
 
-    let entries = match state.store.list_prefix("/registry/nodes/").await {
-        Ok(e) => e,
-        Err(e) => {
-            tracing::error!("Failed to list nodes: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to list nodes").into_response();
-        }
+/// GET /api/v1/nodes/:name — fetch a single node.
+pub async fn get_node(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> ApiResult<Json<Node>> {
+    let key = format!("/registry/nodes/{}", name);
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("node", &name, None).into());
     };
-
-    let nodes: Vec<Node> = entries
-        .into_iter()
-        .filter_map(|(_key, value)| serde_json::from_slice::<Node>(&value).ok())
-        .collect();
-
-    (StatusCode::OK, Json(nodes)).into_response()
+    Ok(Json(serde_json::from_slice(&data)?))
 }
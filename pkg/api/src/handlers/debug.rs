@@ -0,0 +1,181 @@
+//! Runtime debugging: the log filter of this server, and of a node's agent
+//! through it, changed without a restart.
+
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    body::Body,
+    extract::{Path, State},
+    http::{Method, StatusCode, header},
+    response::IntoResponse,
+};
+use pkg_metrics::log_filter::LogFilter;
+use pkg_types::config::LogLevel;
+use pkg_types::error::{ApiError, ErrorReason};
+use tracing::info;
+
+use super::agent_proxy::locate_node;
+use crate::AppState;
+use crate::error::ApiResult;
+
+fn log_filter(state: &AppState) -> Result<&Arc<LogFilter>, ApiError> {
+    state.log_filter.as_ref().ok_or_else(|| {
+        ApiError::new(
+            ErrorReason::ServiceUnavailable,
+            "this server's log filter cannot be changed",
+        )
+    })
+}
+
+/// GET /api/v1/debug/loglevel — the server's log filter.
+pub async fn get_log_level(State(state): State<AppState>) -> ApiResult<Json<LogLevel>> {
+    let filter = log_filter(&state)?.current();
+    Ok(Json(LogLevel { filter }))
+}
+
+/// PUT /api/v1/debug/loglevel — replace the server's log filter. An invalid
+/// filter is rejected and the current one stays.
+pub async fn put_log_level(
+    State(state): State<AppState>,
+    Json(level): Json<LogLevel>,
+) -> ApiResult<Json<LogLevel>> {
+    let log_filter = log_filter(&state)?;
+    log_filter
+        .set(Some(&level.filter))
+        .map_err(|e| ApiError::new(ErrorReason::BadRequest, e))?;
+    info!("Log filter set to {}", level.filter);
+    Ok(Json(LogLevel {
+        filter: log_filter.current(),
+    }))
+}
+
+/// GET /api/v1/nodes/:name/debug/loglevel — the node agent's log filter.
+pub async fn get_node_log_level(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+) -> ApiResult {
+    relay(&state, &node_name, Method::GET, Body::empty()).await
+}
+
+/// PUT /api/v1/nodes/:name/debug/loglevel — replace the node agent's log
+/// filter.
+pub async fn put_node_log_level(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+    Json(level): Json<LogLevel>,
+) -> ApiResult {
+    let body = Body::from(serde_json::to_vec(&level)?);
+    relay(&state, &node_name, Method::PUT, body).await
+}
+
+/// Send the request to the agent's `/debug/loglevel`, relaying its answer.
+/// A filter the agent rejects stays a `400`.
+async fn relay(state: &AppState, node_name: &str, method: Method, body: Body) -> ApiResult {
+    let target = locate_node(state, node_name).await?;
+    let url = reqwest::Url::parse(&format!("{}/debug/loglevel", target.base_url()))
+        .map_err(|e| ApiError::internal(e.to_string()))?;
+    let resp = target.send(method, &url, body).await?;
+    if !(resp.status().is_success() || resp.status() == StatusCode::BAD_REQUEST) {
+        return Err(target.failed(resp).await);
+    }
+    Ok((
+        resp.status(),
+        [(header::CONTENT_TYPE, "application/json")],
+        Body::new(resp.into_body()),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{api_error, body_text, test_state};
+    use axum::{Router, routing::get};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_invalid_filter_is_rejected() {
+        let (_layer, filter) = LogFilter::new(Some("info"), &[]).unwrap();
+        let mut state = test_state("debug-loglevel").await;
+        state.log_filter = Some(Arc::new(filter));
+
+        let resp = put_log_level(
+            State(state.clone()),
+            Json(LogLevel {
+                filter: "pkg_scheduler=loud".to_string(),
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(api_error(resp).await.reason, ErrorReason::BadRequest);
+        let Json(current) = get_log_level(State(state.clone())).await.unwrap();
+        assert_eq!(current.filter, "info");
+
+        let Json(set) = put_log_level(
+            State(state.clone()),
+            Json(LogLevel {
+                filter: "pkg_scheduler=debug,info".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(set.filter, "pkg_scheduler=debug,info");
+    }
+
+    #[tokio::test]
+    async fn test_node_log_level_is_relayed_to_its_agent() {
+        let agent = Router::new().route(
+            "/debug/loglevel",
+            get(|| async { Json(serde_json::json!({ "filter": "warn" })) }).put(|| async {
+                let err = ApiError::new(ErrorReason::BadRequest, "invalid log filter");
+                (StatusCode::BAD_REQUEST, Json(err))
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, agent).await.ok() });
+
+        let state = test_state("debug-node-loglevel").await;
+        let node = serde_json::json!({
+            "id": "n1",
+            "name": "node-a",
+            "address": "127.0.0.1",
+            "agent_api_port": port,
+            "status": "Ready",
+            "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:00:00Z",
+            "labels": {}
+        });
+        state
+            .store
+            .put(
+                "/registry/nodes/node-a",
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let resp = get_node_log_level(State(state.clone()), Path("node-a".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(body_text(resp).await, r#"{"filter":"warn"}"#);
+
+        let resp = put_node_log_level(
+            State(state.clone()),
+            Path("node-a".to_string()),
+            Json(LogLevel {
+                filter: "x=loud".to_string(),
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(api_error(resp).await.reason, ErrorReason::BadRequest);
+
+        let resp = get_node_log_level(State(state), Path("node-b".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -1,23 +1,34 @@
+use anyhow::Context;
 use axum::{
     Json,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
-use pkg_types::node::{Node, NodeStatus};
+use chrono::Utc;
+use pkg_controllers::disruption::{self, EvictionBlocked};
+use pkg_controllers::events;
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::event::{CLUSTER_EVENT_NAMESPACE, Event};
+use pkg_types::node::{DrainPodState, DrainReport, DrainedPod, Node, NodeStatus};
 use pkg_types::pod::{Pod, PodStatus};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use tracing::{info, warn};
 
 use crate::AppState;
+use crate::error::ApiResult;
+use crate::handlers::resources::terminate_pod;
 
 /// POST /api/v1/nodes/:name/cordon — mark a node as unschedulable.
 pub async fn cordon_node(
     State(state): State<AppState>,
     Path(node_name): Path<String>,
-) -> impl IntoResponse {
+) -> ApiResult {
     info!("Cordon request for node: {}", node_name);
 
-    match find_and_update_node(&state, &node_name, |node| {
+    find_and_update_node(&state, &node_name, |node| {
         node.unschedulable = true;
         // Add unschedulable taint
         if !node
@@ -32,58 +43,84 @@ pub async fn cordon_node(
             });
         }
     })
-    .await
-    {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(serde_json::json!({"status": "cordoned"})),
-        )
-            .into_response(),
-        Err(e) => {
-            warn!("Cordon failed: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
-        }
-    }
+    .await?;
+    record_node_event(&state, &node_name, "Cordoned", "Node marked unschedulable").await;
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({"status": "cordoned"})),
+    )
+        .into_response())
 }
 
 /// POST /api/v1/nodes/:name/uncordon — mark a node as schedulable again.
 pub async fn uncordon_node(
     State(state): State<AppState>,
     Path(node_name): Path<String>,
-) -> impl IntoResponse {
+) -> ApiResult {
     info!("Uncordon request for node: {}", node_name);
 
-    match find_and_update_node(&state, &node_name, |node| {
+    find_and_update_node(&state, &node_name, |node| {
         node.unschedulable = false;
         node.status = NodeStatus::Ready;
         node.taints
             .retain(|t| t.key != "node.kubernetes.io/unschedulable");
     })
-    .await
-    {
-        Ok(_) => (
-            StatusCode::OK,
-            Json(serde_json::json!({"status": "uncordoned"})),
-        )
-            .into_response(),
-        Err(e) => {
-            warn!("Uncordon failed: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
-        }
-    }
+    .await?;
+    record_node_event(&state, &node_name, "Uncordoned", "Node marked schedulable").await;
+    Ok((
+        StatusCode::OK,
+        Json(serde_json::json!({"status": "uncordoned"})),
+    )
+        .into_response())
+}
+
+/// Query parameters for `POST /api/v1/nodes/:name/drain`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DrainQuery {
+    /// How long to wait for the evicted pods, in seconds (default
+    /// `DRAIN_TIMEOUT_SECS`). Zero returns as soon as the evictions are issued.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+    /// Also wait until every evicted ReplicaSet pod has a Running
+    /// replacement on another node.
+    #[serde(default)]
+    pub wait_for_reschedule: bool,
+    /// Evict even when a PodDisruptionBudget does not allow it.
+    #[serde(default)]
+    pub force: bool,
 }
 
-/// POST /api/v1/nodes/:name/drain — cordon + evict all pods from the node.
+/// A pod the drain is responsible for.
+struct Tracked {
+    /// Position of the pod in the report.
+    index: usize,
+    key: String,
+    pod: Pod,
+    /// Owning ReplicaSet, when the drain waits for a replacement.
+    replicaset: Option<String>,
+    evicted: bool,
+    /// ID of the pod that replaced this one.
+    replaced_by: Option<String>,
+}
+
+/// POST /api/v1/nodes/:name/drain — cordon the node, evict its pods and
+/// wait for them to stop.
+///
+/// DaemonSet pods are left in place. Pods are evicted lowest priority first;
+/// evictions that fail or that a PodDisruptionBudget blocks are retried
+/// until the timeout, giving controllers time to bring replacements up
+/// elsewhere. `force` overrides the budgets. The report lists every pod
+/// with the state it reached.
 pub async fn drain_node(
     State(state): State<AppState>,
     Path(node_name): Path<String>,
-) -> impl IntoResponse {
+    Query(query): Query<DrainQuery>,
+) -> ApiResult {
     info!("Drain request for node: {}", node_name);
 
     // Step 1: Cordon the node
-    if let Err(e) = find_and_update_node(&state, &node_name, |node| {
+    find_and_update_node(&state, &node_name, |node| {
         node.unschedulable = true;
-        node.status = NodeStatus::NotReady;
         if !node
             .taints
             .iter()
@@ -96,53 +133,333 @@ pub async fn drain_node(
             });
         }
     })
-    .await
-    {
-        warn!("Drain failed during cordon: {}", e);
-        return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-    }
-
-    // Step 2: Evict all pods on this node
-    let mut evicted = 0u32;
-    // Scan all namespaces for pods on this node
-    let entries = match state.store.list_prefix("/registry/pods/").await {
-        Ok(e) => e,
-        Err(e) => {
-            warn!("Drain: failed to list pods: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to list pods").into_response();
+    .await?;
+
+    // Step 2: Work out which pods to evict, and in which order
+    let started = Utc::now();
+    let (mut report, mut tracked) = drain_plan(&state, &node_name, query.wait_for_reschedule)
+        .await
+        .context("listing pods to drain")?;
+
+    // Step 3: Evict, then follow the pods until done or out of time
+    let timeout = Duration::from_secs(
+        query
+            .timeout_seconds
+            .unwrap_or(pkg_constants::timings::DRAIN_TIMEOUT_SECS),
+    );
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        advance_drain(
+            &state,
+            &node_name,
+            started,
+            query.force,
+            &mut report,
+            &mut tracked,
+        )
+        .await
+        .context("checking drained pods")?;
+        report.complete = report.pods.iter().all(|p| p.state.is_final());
+        if report.complete || tokio::time::Instant::now() >= deadline {
+            break;
         }
+        tokio::time::sleep(Duration::from_millis(
+            pkg_constants::timings::DRAIN_POLL_INTERVAL_MS,
+        ))
+        .await;
+    }
+
+    let evicted = tracked.iter().filter(|t| t.evicted).count();
+    let message = if report.complete {
+        format!("Node drained, {} pods evicted", evicted)
+    } else {
+        format!(
+            "Drain timed out after {}s, {} pods evicted",
+            timeout.as_secs(),
+            evicted
+        )
     };
+    info!("Drain of node {}: {}", node_name, message);
+    record_node_event(&state, &node_name, "Drained", message).await;
+    Ok((StatusCode::OK, Json(report)).into_response())
+}
 
-    for (key, value) in entries {
-        if let Ok(mut pod) = serde_json::from_slice::<Pod>(&value)
-            && pod.node_name.as_deref() == Some(&node_name)
+/// The pods bound to `node`, in eviction order: lowest priority first, then
+/// by namespace and name. DaemonSet pods and static pod mirrors are
+/// reported as skipped.
+async fn drain_plan(
+    state: &AppState,
+    node: &str,
+    wait_for_reschedule: bool,
+) -> anyhow::Result<(DrainReport, Vec<Tracked>)> {
+    let owner_ids = |prefix: &'static str| async move {
+        anyhow::Ok(
+            state
+                .store
+                .list_prefix(prefix)
+                .await?
+                .into_iter()
+                .filter_map(|(_, v)| serde_json::from_slice::<serde_json::Value>(&v).ok())
+                .filter_map(|v| Some(v.get("id")?.as_str()?.to_string()))
+                .collect::<HashSet<String>>(),
+        )
+    };
+    let daemonsets = owner_ids("/registry/daemonsets/").await?;
+    let replicasets = owner_ids("/registry/replicasets/").await?;
+
+    let mut pods: Vec<(String, Pod)> = state
+        .store
+        .list_prefix("/registry/pods/")
+        .await?
+        .into_iter()
+        .filter_map(|(k, v)| Some((k, serde_json::from_slice::<Pod>(&v).ok()?)))
+        .filter(|(_, p)| p.node_name.as_deref() == Some(node))
+        .collect();
+    pods.sort_by(|(_, a), (_, b)| {
+        (a.spec.priority, &a.namespace, &a.name).cmp(&(b.spec.priority, &b.namespace, &b.name))
+    });
+
+    let mut report = DrainReport {
+        node: node.to_string(),
+        pods: Vec::new(),
+        complete: false,
+    };
+    let mut tracked = Vec::new();
+    for (key, pod) in pods {
+        let owner = pod.owner_ref.as_deref();
+        let skip_reason = if pod.mirror {
+            Some("static pod mirror")
+        } else if owner.is_some_and(|id| daemonsets.contains(id)) {
+            Some("managed by a DaemonSet")
+        } else {
+            None
+        };
+        report.pods.push(DrainedPod {
+            namespace: pod.namespace.clone(),
+            name: pod.name.clone(),
+            state: if skip_reason.is_some() {
+                DrainPodState::Skipped
+            } else {
+                DrainPodState::Evicting
+            },
+            message: skip_reason.map(str::to_string),
+        });
+        if skip_reason.is_some() {
+            continue;
+        }
+        tracked.push(Tracked {
+            index: report.pods.len() - 1,
+            key,
+            replicaset: owner
+                .filter(|id| wait_for_reschedule && replicasets.contains(*id))
+                .map(str::to_string),
+            pod,
+            evicted: false,
+            replaced_by: None,
+        });
+    }
+    Ok((report, tracked))
+}
+
+/// One pass over the drained pods: (re)try pending evictions and move each
+/// pod's state forward from what is in the store now.
+async fn advance_drain(
+    state: &AppState,
+    node: &str,
+    started: chrono::DateTime<Utc>,
+    force: bool,
+    report: &mut DrainReport,
+    tracked: &mut [Tracked],
+) -> anyhow::Result<()> {
+    let credited: HashSet<String> = tracked
+        .iter()
+        .filter_map(|t| t.replaced_by.clone())
+        .collect();
+    let mut replacements: HashMap<String, Vec<Pod>> = HashMap::new();
+    for t in tracked.iter_mut() {
+        let entry = &mut report.pods[t.index];
+        if !t.evicted {
+            let eviction = evict(
+                state,
+                &t.pod.namespace,
+                &t.pod.name,
+                format!("Evicted from node {} (drain)", node),
+                force,
+            )
+            .await;
+            match eviction {
+                Eviction::Done(Ok(_)) => {
+                    t.evicted = true;
+                    entry.state = DrainPodState::Evicting;
+                    entry.message = None;
+                }
+                Eviction::Done(Err(e)) => {
+                    entry.message = Some(format!("eviction failed: {}", e.0.message));
+                    continue;
+                }
+                Eviction::Blocked(blocked) => {
+                    entry.state = DrainPodState::Blocked;
+                    entry.message = Some(if blocked.is_unsatisfiable() {
+                        format!(
+                            "waiting on {}; the budget can never allow an eviction, drain with force to override it",
+                            blocked
+                        )
+                    } else {
+                        format!("waiting on {}", blocked)
+                    });
+                    continue;
+                }
+            }
+        }
+
+        if entry.state == DrainPodState::Evicting {
+            let gone = match state.store.get(&t.key).await? {
+                Some(data) => serde_json::from_slice::<Pod>(&data)
+                    .map(|p| p.id != t.pod.id)
+                    .unwrap_or(true),
+                None => true,
+            };
+            if gone {
+                entry.state = match t.replicaset {
+                    Some(_) => DrainPodState::AwaitingReschedule,
+                    None => DrainPodState::Terminated,
+                };
+            }
+        }
+
+        if let Some(ref rs) = t.replicaset
+            && entry.state == DrainPodState::AwaitingReschedule
         {
-            info!("Evicting pod {} from node {}", pod.name, node_name);
-            pod.node_name = None;
-            pod.status = PodStatus::Pending;
-            if let Ok(data) = serde_json::to_vec(&pod) {
-                let _ = state.store.put(&key, &data).await;
-                evicted += 1;
+            if !replacements.contains_key(rs) {
+                let mut found = running_replacements(state, rs, node, started).await?;
+                found.retain(|p| !credited.contains(&p.id));
+                replacements.insert(rs.clone(), found);
+            }
+            // Each replacement stands in for one evicted pod.
+            if let Some(pods) = replacements.get_mut(rs)
+                && !pods.is_empty()
+            {
+                let replacement = pods.remove(0);
+                entry.state = DrainPodState::Rescheduled;
+                t.replaced_by = Some(replacement.id.clone());
+                entry.message = Some(format!(
+                    "replaced by {} on {}",
+                    replacement.name,
+                    replacement.node_name.unwrap_or_default()
+                ));
             }
         }
     }
+    Ok(())
+}
 
-    info!(
-        "Drain complete for node {}: {} pods evicted",
-        node_name, evicted
-    );
-    (
-        StatusCode::OK,
-        Json(serde_json::json!({
-            "status": "drained",
-            "evicted_pods": evicted
-        })),
+/// Pods of ReplicaSet `rs` created since the drain began and Running on
+/// another node, oldest first.
+async fn running_replacements(
+    state: &AppState,
+    rs: &str,
+    node: &str,
+    started: chrono::DateTime<Utc>,
+) -> anyhow::Result<Vec<Pod>> {
+    let mut pods: Vec<Pod> = state
+        .store
+        .list_prefix("/registry/pods/")
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice::<Pod>(&v).ok())
+        .filter(|p| {
+            p.owner_ref.as_deref() == Some(rs)
+                && p.created_at >= started
+                && p.status == PodStatus::Running
+                && p.node_name.as_deref().is_some_and(|n| n != node)
+        })
+        .collect();
+    pods.sort_by_key(|p| p.created_at);
+    Ok(pods)
+}
+
+/// POST /api/v1/namespaces/:ns/pods/:name/eviction — stop a pod so that its
+/// controller replaces it elsewhere. 429 while a PodDisruptionBudget does
+/// not allow it.
+pub async fn evict_pod(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+) -> ApiResult {
+    let key = format!("/registry/pods/{}/{}", ns, name);
+    if state.store.get(&key).await?.is_none() {
+        return Err(ApiError::not_found("pod", &name, Some(&ns)).into());
+    }
+    let message = "Evicted via the eviction API".to_string();
+    match evict(&state, &ns, &name, message, false).await {
+        Eviction::Done(result) => result,
+        Eviction::Blocked(blocked) => Err(ApiError::new(
+            ErrorReason::TooManyRequests,
+            format!("cannot evict pod '{}': waiting on {}", name, blocked),
+        )
+        .into()),
+    }
+}
+
+/// Outcome of [`evict`].
+enum Eviction {
+    /// Handed to `terminate_pod`; this is its response.
+    Done(ApiResult),
+    /// A disruption budget does not allow it.
+    Blocked(EvictionBlocked),
+}
+
+/// Evict a pod: check the disruption budgets covering it (unless `force`),
+/// then stop it like a delete would (see `terminate_pod`) and record why.
+async fn evict(state: &AppState, ns: &str, name: &str, message: String, force: bool) -> Eviction {
+    let key = format!("/registry/pods/{}/{}", ns, name);
+    let pod = match state.store.get(&key).await {
+        Ok(data) => data.and_then(|d| serde_json::from_slice::<Pod>(&d).ok()),
+        Err(e) => return Eviction::Done(Err(e.into())),
+    };
+    let mut event = Event::normal("pod", ns, name, "Evicted", message);
+    if let Some(ref pod) = pod {
+        match disruption::check_eviction(&state.store, pod).await {
+            Ok(Ok(())) => {}
+            Ok(Err(blocked)) if force => {
+                warn!("Evicting pod {}/{} despite {} (forced)", ns, name, blocked);
+                event = Event::warning(
+                    "pod",
+                    ns,
+                    name,
+                    "Evicted",
+                    format!("{}, overriding {}", event.message, blocked),
+                );
+            }
+            Ok(Err(blocked)) => return Eviction::Blocked(blocked),
+            Err(e) => {
+                return Eviction::Done(Err(e.context("checking disruption budgets").into()));
+            }
+        }
+    }
+
+    let result = terminate_pod(state, ns, name, false).await;
+    if result.is_ok() {
+        info!("Evicting pod {}/{}: {}", ns, name, event.message);
+        events::record(&state.store, event).await;
+    }
+    Eviction::Done(result)
+}
+
+async fn record_node_event(
+    state: &AppState,
+    node_name: &str,
+    reason: &str,
+    message: impl Into<String>,
+) {
+    events::record(
+        &state.store,
+        Event::normal("node", CLUSTER_EVENT_NAMESPACE, node_name, reason, message),
     )
-        .into_response()
+    .await;
 }
 
 /// Helper: find a node by name, apply a mutation, and persist it.
-async fn find_and_update_node<F>(state: &AppState, node_name: &str, mutate: F) -> anyhow::Result<()>
+async fn find_and_update_node<F>(state: &AppState, node_name: &str, mutate: F) -> ApiResult<()>
 where
     F: FnOnce(&mut Node),
 {
@@ -159,5 +476,340 @@ where
         }
     }
 
-    Err(anyhow::anyhow!("Node not found: {}", node_name))
+    Err(ApiError::not_found("node", node_name, None).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{api_error, body_text, test_state};
+
+    async fn put(state: &AppState, key: &str, value: serde_json::Value) {
+        state
+            .store
+            .put(key, &serde_json::to_vec(&value).unwrap())
+            .await
+            .unwrap();
+    }
+
+    async fn seed_node(state: &AppState) {
+        put(
+            state,
+            "/registry/nodes/node-a",
+            serde_json::json!({
+                "id": "n1",
+                "name": "node-a",
+                "address": "10.0.0.5",
+                "agent_api_port": 10250,
+                "status": "Ready",
+                "registered_at": "2024-02-25T00:00:00Z",
+                "last_heartbeat": "2024-02-25T00:00:00Z",
+                "labels": {}
+            }),
+        )
+        .await;
+    }
+
+    async fn seed_pod(
+        state: &AppState,
+        name: &str,
+        node: &str,
+        owner: Option<&str>,
+        priority: i32,
+    ) {
+        put(
+            state,
+            &format!("/registry/pods/default/{}", name),
+            serde_json::json!({
+                "id": format!("{}-id", name),
+                "name": name,
+                "namespace": "default",
+                "spec": { "containers": [], "priority": priority },
+                "status": "Running",
+                "node_name": node,
+                "owner_ref": owner,
+                "created_at": Utc::now(),
+            }),
+        )
+        .await;
+    }
+
+    async fn stored_pod(state: &AppState, name: &str) -> Option<Pod> {
+        let data = state
+            .store
+            .get(&format!("/registry/pods/default/{}", name))
+            .await
+            .unwrap()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    async fn drain(state: &AppState, query: DrainQuery) -> DrainReport {
+        let resp = drain_node(
+            State(state.clone()),
+            Path("node-a".to_string()),
+            Query(query),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        serde_json::from_str(&body_text(resp).await).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_drain_skips_daemonset_pods_and_evicts_by_priority() {
+        let state = test_state("drain-order").await;
+        seed_node(&state).await;
+        put(
+            &state,
+            "/registry/daemonsets/default/logs",
+            serde_json::json!({ "id": "ds-1", "name": "logs" }),
+        )
+        .await;
+        seed_pod(&state, "web", "node-a", None, 10).await;
+        seed_pod(&state, "logs-node-a", "node-a", Some("ds-1"), 0).await;
+        seed_pod(&state, "batch", "node-a", None, 0).await;
+        seed_pod(&state, "api", "node-a", None, 10).await;
+        seed_pod(&state, "elsewhere", "node-b", None, 0).await;
+        put(
+            &state,
+            "/registry/pods/default/dns-node-a",
+            serde_json::json!({
+                "id": "static-default-dns",
+                "name": "dns-node-a",
+                "namespace": "default",
+                "spec": { "containers": [] },
+                "status": "Running",
+                "node_name": "node-a",
+                "mirror": true,
+                "created_at": Utc::now(),
+            }),
+        )
+        .await;
+
+        let report = drain(
+            &state,
+            DrainQuery {
+                timeout_seconds: Some(0),
+                wait_for_reschedule: false,
+                force: false,
+            },
+        )
+        .await;
+        let order: Vec<(&str, DrainPodState)> = report
+            .pods
+            .iter()
+            .map(|p| (p.name.as_str(), p.state))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("batch", DrainPodState::Evicting),
+                ("dns-node-a", DrainPodState::Skipped),
+                ("logs-node-a", DrainPodState::Skipped),
+                ("api", DrainPodState::Evicting),
+                ("web", DrainPodState::Evicting),
+            ]
+        );
+        assert!(!report.complete);
+
+        // Evicted pods are left for the agent to stop; the rest is untouched.
+        for name in ["batch", "api", "web"] {
+            assert_eq!(
+                stored_pod(&state, name).await.unwrap().status,
+                PodStatus::Terminating
+            );
+        }
+        for name in ["logs-node-a", "dns-node-a", "elsewhere"] {
+            assert_eq!(
+                stored_pod(&state, name).await.unwrap().status,
+                PodStatus::Running
+            );
+        }
+        let node: Node = serde_json::from_slice(
+            &state
+                .store
+                .get("/registry/nodes/node-a")
+                .await
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap();
+        assert!(node.unschedulable);
+    }
+
+    #[tokio::test]
+    async fn test_drain_waits_for_replacements() {
+        let state = test_state("drain-reschedule").await;
+        seed_node(&state).await;
+        put(
+            &state,
+            "/registry/replicasets/default/web-1",
+            serde_json::json!({ "id": "rs-1", "name": "web-1" }),
+        )
+        .await;
+        seed_pod(&state, "web-a", "node-a", Some("rs-1"), 0).await;
+        seed_pod(&state, "web-b", "node-b", Some("rs-1"), 0).await;
+        seed_pod(&state, "bare", "node-a", None, 0).await;
+
+        // Stand-in for the agent and the ReplicaSet controller.
+        let background = state.clone();
+        tokio::spawn(async move {
+            loop {
+                if stored_pod(&background, "web-a")
+                    .await
+                    .is_some_and(|p| p.status == PodStatus::Terminating)
+                {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            for name in ["web-a", "bare"] {
+                background
+                    .store
+                    .delete(&format!("/registry/pods/default/{}", name))
+                    .await
+                    .unwrap();
+            }
+            seed_pod(&background, "web-c", "node-b", Some("rs-1"), 0).await;
+        });
+
+        let report = drain(
+            &state,
+            DrainQuery {
+                timeout_seconds: Some(10),
+                wait_for_reschedule: true,
+                force: false,
+            },
+        )
+        .await;
+        assert!(report.complete);
+        assert_eq!(report.pods[0].name, "bare");
+        assert_eq!(report.pods[0].state, DrainPodState::Terminated);
+        assert_eq!(report.pods[1].name, "web-a");
+        assert_eq!(report.pods[1].state, DrainPodState::Rescheduled);
+        // The pod that was already running elsewhere does not count.
+        assert_eq!(
+            report.pods[1].message.as_deref(),
+            Some("replaced by web-c on node-b")
+        );
+    }
+
+    async fn seed_budget(state: &AppState, min_available: u32) {
+        put(
+            state,
+            "/registry/poddisruptionbudgets/default/web",
+            serde_json::json!({
+                "name": "web",
+                "namespace": "default",
+                "min_available": min_available,
+                "created_at": Utc::now(),
+            }),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_drain_retries_evictions_blocked_by_budget() {
+        let state = test_state("drain-pdb").await;
+        seed_node(&state).await;
+        seed_budget(&state, 1).await;
+        seed_pod(&state, "web-a", "node-a", Some("rs-1"), 0).await;
+        seed_pod(&state, "web-b", "node-b", Some("rs-1"), 0).await;
+        let mut starting = stored_pod(&state, "web-b").await.unwrap();
+        starting.status = PodStatus::ContainerCreating;
+        put(
+            &state,
+            "/registry/pods/default/web-b",
+            serde_json::to_value(&starting).unwrap(),
+        )
+        .await;
+
+        // web-a is the only ready pod.
+        let resp = evict_pod(
+            State(state.clone()),
+            Path(("default".to_string(), "web-a".to_string())),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(
+            api_error(resp).await.message,
+            "cannot evict pod 'web-a': waiting on PDB web (1/1 required available)"
+        );
+        let query = || DrainQuery {
+            timeout_seconds: Some(0),
+            wait_for_reschedule: false,
+            force: false,
+        };
+        let report = drain(&state, query()).await;
+        assert_eq!(report.pods[0].state, DrainPodState::Blocked);
+        assert_eq!(
+            report.pods[0].message.as_deref(),
+            Some("waiting on PDB web (1/1 required available)")
+        );
+        assert!(!report.complete);
+
+        // Stand-in for the controller and agents: web-b comes up, and web-a
+        // goes away once evicted.
+        let background = state.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            seed_pod(&background, "web-b", "node-b", Some("rs-1"), 0).await;
+            loop {
+                if stored_pod(&background, "web-a")
+                    .await
+                    .is_some_and(|p| p.status == PodStatus::Terminating)
+                {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            background
+                .store
+                .delete("/registry/pods/default/web-a")
+                .await
+                .unwrap();
+        });
+        let report = drain(
+            &state,
+            DrainQuery {
+                timeout_seconds: Some(10),
+                ..query()
+            },
+        )
+        .await;
+        assert!(report.complete);
+        assert_eq!(report.pods[0].state, DrainPodState::Terminated);
+    }
+
+    #[tokio::test]
+    async fn test_drain_force_overrides_budget() {
+        let state = test_state("drain-pdb-force").await;
+        seed_node(&state).await;
+        seed_budget(&state, 1).await;
+        seed_pod(&state, "web-a", "node-a", Some("rs-1"), 0).await;
+
+        let mut query = DrainQuery {
+            timeout_seconds: Some(0),
+            wait_for_reschedule: false,
+            force: false,
+        };
+        let report = drain(&state, query.clone()).await;
+        assert_eq!(report.pods[0].state, DrainPodState::Blocked);
+        assert!(
+            report.pods[0]
+                .message
+                .as_deref()
+                .unwrap()
+                .contains("can never allow an eviction")
+        );
+
+        query.force = true;
+        let report = drain(&state, query).await;
+        assert_eq!(report.pods[0].state, DrainPodState::Evicting);
+        assert_eq!(
+            stored_pod(&state, "web-a").await.unwrap().status,
+            PodStatus::Terminating
+        );
+    }
 }
@@ -0,0 +1,153 @@
+use axum::{
+    Json,
+    extract::{Path as AxumPath, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::event::Event;
+use serde::Deserialize;
+
+use crate::AppState;
+use crate::error::ApiResult;
+
+#[derive(Debug, Deserialize)]
+pub struct EventQuery {
+    /// Involved object as `{kind}/{name}`, e.g. `pod/web-0`.
+    #[serde(default)]
+    pub involved: Option<String>,
+}
+
+/// GET /api/v1/namespaces/:ns/events?involved={kind}/{name}
+///
+/// Returns the buffered object events for a namespace in the order they
+/// happened, optionally limited to a single involved object.
+pub async fn list_object_events(
+    State(state): State<AppState>,
+    AxumPath(ns): AxumPath<String>,
+    Query(query): Query<EventQuery>,
+) -> ApiResult {
+    let prefix = match query.involved.as_deref() {
+        Some(involved) => match involved.split_once('/') {
+            Some((kind, name)) if !kind.is_empty() && !name.is_empty() => {
+                format!("/events/{}/{}/{}", ns, kind.to_lowercase(), name)
+            }
+            _ => {
+                return Err(ApiError::new(
+                    ErrorReason::BadRequest,
+                    format!("invalid involved object '{}': expected kind/name", involved),
+                )
+                .into());
+            }
+        },
+        None => format!("/events/{}/", ns),
+    };
+    let exact = query.involved.is_some();
+
+    let events: Vec<Event> = state
+        .store
+        .event_log
+        .events_since(0)
+        .await
+        .into_iter()
+        .filter(|e| {
+            if exact {
+                e.key == prefix
+            } else {
+                e.key.starts_with(&prefix)
+            }
+        })
+        .filter_map(|e| serde_json::from_slice(e.value.as_deref()?).ok())
+        .collect();
+
+    Ok((StatusCode::OK, Json(events)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{body_text, test_state};
+    use pkg_controllers::events::record;
+    use pkg_state::watch::EventType as WatchEventType;
+
+    #[tokio::test]
+    async fn test_list_object_events_filters_by_involved_object() {
+        let state = test_state("events-filter").await;
+        record(
+            &state.store,
+            Event::normal("pod", "default", "web-0", "Scheduled", "assigned to node-a"),
+        )
+        .await;
+        record(
+            &state.store,
+            Event::normal(
+                "pod",
+                "default",
+                "web-01",
+                "Scheduled",
+                "assigned to node-b",
+            ),
+        )
+        .await;
+        record(
+            &state.store,
+            Event::normal("pod", "other", "web-0", "Scheduled", "other namespace"),
+        )
+        .await;
+        record(
+            &state.store,
+            Event::warning("pod", "default", "web-0", "Failed", "exit code 1"),
+        )
+        .await;
+        // Non-object entries share the log and must be skipped.
+        state
+            .store
+            .event_log
+            .emit(
+                WatchEventType::Put,
+                "/events/backup/success".to_string(),
+                Some(b"backup.json.gz".to_vec()),
+            )
+            .await;
+
+        let resp = list_object_events(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Query(EventQuery {
+                involved: Some("Pod/web-0".to_string()),
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let events: Vec<Event> = serde_json::from_str(&body_text(resp).await).unwrap();
+        let reasons: Vec<&str> = events.iter().map(|e| e.reason.as_str()).collect();
+        assert_eq!(reasons, ["Scheduled", "Failed"]);
+        assert!(
+            events
+                .iter()
+                .all(|e| e.namespace == "default" && e.name == "web-0")
+        );
+
+        let resp = list_object_events(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Query(EventQuery { involved: None }),
+        )
+        .await
+        .into_response();
+        let events: Vec<Event> = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(events.len(), 3);
+
+        let resp = list_object_events(
+            State(state),
+            AxumPath("default".to_string()),
+            Query(EventQuery {
+                involved: Some("web-0".to_string()),
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
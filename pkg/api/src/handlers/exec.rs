@@ -1,23 +1,18 @@
-use axum::{
-    extract::{
-        Path as AxumPath, Query, State,
-        ws::{Message, WebSocket, WebSocketUpgrade},
-    },
-    http::StatusCode,
-    response::IntoResponse,
-};
-use futures_util::{SinkExt, StreamExt};
-use pkg_types::node::Node;
-use pkg_types::pod::Pod;
+use axum::extract::{Path as AxumPath, Query, State, ws::WebSocketUpgrade};
 use serde::Deserialize;
-use tracing::{error, info, warn};
+use tracing::info;
 
+use super::agent_proxy::{locate_container, relay_websocket};
 use crate::AppState;
+use crate::error::ApiResult;
 
 #[derive(Debug, Deserialize)]
 pub struct ExecQuery {
     #[serde(default)]
     pub cmd: String,
+    /// Container to exec into; defaults to the pod's first container.
+    #[serde(default)]
+    pub container: Option<String>,
     /// If true, request a PTY on the agent side (raw byte tunnel).
     #[serde(default)]
     pub tty: bool,
@@ -32,58 +27,12 @@ pub async fn exec_into_pod(
     Query(query): Query<ExecQuery>,
     State(state): State<AppState>,
     ws: WebSocketUpgrade,
-) -> impl IntoResponse {
+) -> ApiResult {
     info!("Exec request for pod {}/{}", ns, pod_name);
 
-    // 1. Find the pod in the state store
-    let pod_key = format!("/registry/pods/{}/{}", ns, pod_name);
-    info!("Looking up pod with key: {}", pod_key);
-    let pod: Pod = match state.store.get(&pod_key).await {
-        Ok(Some(data)) => match serde_json::from_slice(&data) {
-            Ok(p) => p,
-            Err(e) => {
-                error!("Failed to deserialize pod: {}", e);
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-            }
-        },
-        Ok(None) => {
-            warn!("Pod not found in store: {}", pod_key);
-            return StatusCode::NOT_FOUND.into_response();
-        }
-        Err(e) => {
-            error!("Store error during pod lookup: {}", e);
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
-
-    // 2. Find the node where the pod is running
-    let node_name = match &pod.node_name {
-        Some(name) => name,
-        None => return (StatusCode::BAD_REQUEST, "Pod is not scheduled to a node").into_response(),
-    };
-
-    // pod.node_name now stores the human-readable node name (set by the
-    // scheduler), which matches the registry key /registry/nodes/{name}.
-    let node_key = format!("/registry/nodes/{}", node_name);
-    let node: Node = match state.store.get(&node_key).await {
-        Ok(Some(data)) => match serde_json::from_slice(&data) {
-            Ok(n) => n,
-            Err(e) => {
-                error!("Failed to deserialize node: {}", e);
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-            }
-        },
-        Ok(None) => {
-            warn!("Node {} not found in registry", node_name);
-            return (StatusCode::NOT_FOUND, "Node not found").into_response();
-        }
-        Err(e) => {
-            error!("Store error during node lookup: {}", e);
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-    };
+    let target = locate_container(&state, &ns, &pod_name, query.container.as_deref()).await?;
 
-    // Build agent URL with cmd and tty query params.
+    // Build agent path with cmd and tty query params.
     let encoded_cmd: String = query
         .cmd
         .chars()
@@ -101,97 +50,13 @@ pub async fn exec_into_pod(
     if query.tty {
         params.push("tty=true".to_string());
     }
-    let agent_url = if params.is_empty() {
-        format!(
-            "ws://{}:{}/exec/{}",
-            node.address, node.agent_api_port, pod.id
-        )
-    } else {
-        format!(
-            "ws://{}:{}/exec/{}?{}",
-            node.address,
-            node.agent_api_port,
-            pod.id,
-            params.join("&")
-        )
-    };
-
-    // 3. Upgrade and proxy
-    ws.on_upgrade(move |socket| proxy_to_agent(socket, agent_url))
-}
-
-async fn proxy_to_agent(mut client_socket: WebSocket, agent_url: String) {
-    info!("Proxying exec session to agent: {}", agent_url);
-
-    // Connect to the agent node's WebSocket API
-    let (agent_socket, _) = match tokio_tungstenite::connect_async(&agent_url).await {
-        Ok(conn) => conn,
-        Err(e) => {
-            error!("Failed to connect to agent WebSocket: {}", e);
-            let _ = client_socket
-                .send(Message::Text(
-                    format!("Error: Failed to connect to agent: {}\r\n", e).into(),
-                ))
-                .await;
-            return;
-        }
-    };
-
-    let (mut client_sender, mut client_receiver) = client_socket.split();
-    let (mut agent_sender, mut agent_receiver) = agent_socket.split();
-
-    // Client -> Agent
-    let client_to_agent = tokio::spawn(async move {
-        while let Some(Ok(msg)) = client_receiver.next().await {
-            // Map axum message to tungstenite message
-            let t_msg = match msg {
-                Message::Text(t) => {
-                    tokio_tungstenite::tungstenite::Message::Text(t.as_str().into())
-                }
-                Message::Binary(b) => tokio_tungstenite::tungstenite::Message::Binary(b),
-                Message::Ping(p) => tokio_tungstenite::tungstenite::Message::Ping(p),
-                Message::Pong(p) => tokio_tungstenite::tungstenite::Message::Pong(p),
-                Message::Close(_) => break,
-            };
-            if agent_sender.send(t_msg).await.is_err() {
-                break;
-            }
-        }
-    });
-
-    // Agent -> Client
-    let agent_to_client = tokio::spawn(async move {
-        while let Some(Ok(msg)) = agent_receiver.next().await {
-            // Map tungstenite message to axum message
-            let a_msg = match msg {
-                tokio_tungstenite::tungstenite::Message::Text(t) => {
-                    Message::Text(t.as_str().into())
-                }
-                tokio_tungstenite::tungstenite::Message::Binary(b) => Message::Binary(b),
-                tokio_tungstenite::tungstenite::Message::Ping(p) => Message::Ping(p),
-                tokio_tungstenite::tungstenite::Message::Pong(p) => Message::Pong(p),
-                // Forward Close so the client's read loop exits cleanly.
-                // Without this the proxy drops the sender half while the
-                // receiver half stays open, leaving the client blocked forever.
-                tokio_tungstenite::tungstenite::Message::Close(_) => {
-                    let _ = client_sender.send(Message::Close(None)).await;
-                    break;
-                }
-                _ => break,
-            };
-            if client_sender.send(a_msg).await.is_err() {
-                break;
-            }
-        }
-    });
-
-    // Wait for either to finish
-    tokio::select! {
-        _ = client_to_agent => {
-            warn!("Exec proxy: client connection closed");
-        },
-        _ = agent_to_client => {
-            warn!("Exec proxy: agent connection closed");
-        },
+    let mut agent_path = format!("/exec/{}", target.container_id);
+    if !params.is_empty() {
+        agent_path = format!("{}?{}", agent_path, params.join("&"));
     }
+
+    // Reach the agent before upgrading, so a node that is down is answered
+    // with a 502 rather than a WebSocket that closes straight away.
+    let agent = target.connect_websocket(&agent_path).await?;
+    Ok(ws.on_upgrade(move |socket| relay_websocket(socket, agent)))
 }
@@ -1,49 +1,467 @@
+use anyhow::Context;
 use axum::{
     Json,
+    body::Bytes,
     extract::{Path, State},
     http::StatusCode,
     response::IntoResponse,
 };
 use chrono::Utc;
-use pkg_types::node::{Node, NodeStatus};
+use pkg_controllers::events;
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::event::{CLUSTER_EVENT_NAMESPACE, Event};
+use pkg_types::metrics::PodMetrics;
+use pkg_types::node::{Node, NodeConditionType, NodeHeartbeat, NodeStatus, Taint};
+use pkg_types::pod::TaintEffect;
+use std::collections::HashSet;
 use tracing::{info, warn};
 
 use crate::AppState;
+use crate::error::ApiResult;
 
 /// PUT /api/v1/nodes/:name/heartbeat — update node heartbeat timestamp.
+///
+/// The body is an optional `NodeHeartbeat` with live usage; agents that
+/// predate it send an empty body, which only refreshes liveness.
 pub async fn node_heartbeat(
     State(state): State<AppState>,
     Path(node_name): Path<String>,
-) -> impl IntoResponse {
-    // Find the node by name
-    let entries = match state.store.list_prefix("/registry/nodes/").await {
-        Ok(e) => e,
-        Err(e) => {
-            warn!("Failed to list nodes for heartbeat: {}", e);
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    body: Bytes,
+) -> ApiResult {
+    let report = if body.is_empty() {
+        None
+    } else {
+        match serde_json::from_slice::<NodeHeartbeat>(&body) {
+            Ok(hb) => Some(hb),
+            Err(e) => {
+                warn!("Invalid heartbeat body from {}: {}", node_name, e);
+                return Err(ApiError::new(
+                    ErrorReason::BadRequest,
+                    format!("invalid heartbeat: {}", e),
+                )
+                .into());
+            }
         }
     };
 
+    // Find the node by name
+    let entries = state
+        .store
+        .list_prefix("/registry/nodes/")
+        .await
+        .context("listing nodes for heartbeat")?;
+
     for (key, value) in entries {
         if let Ok(mut node) = serde_json::from_slice::<Node>(&value)
             && node.name == node_name
         {
             node.last_heartbeat = Utc::now();
             node.status = NodeStatus::Ready;
-            match serde_json::to_vec(&node) {
-                Ok(data) => {
-                    if let Err(e) = state.store.put(&key, &data).await {
-                        warn!("Failed to update heartbeat: {}", e);
-                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-                    }
-                    return (StatusCode::OK, Json(serde_json::json!({"status": "ok"})))
-                        .into_response();
-                }
-                Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            let mut changed = Vec::new();
+            if let Some(ref hb) = report {
+                hb.apply_to(&mut node);
+                changed = sync_pressure_taints(&mut node);
             }
+            state
+                .store
+                .put(&key, &serde_json::to_vec(&node)?)
+                .await
+                .context("updating heartbeat")?;
+            for (condition, holds) in changed {
+                record_pressure_event(&state, &node, condition, holds).await;
+            }
+            if let Some(ref hb) = report
+                && let Err(e) = record_pod_metrics(&state, &node_name, hb).await
+            {
+                warn!("Failed to record pod metrics from {}: {}", node_name, e);
+            }
+            return Ok((StatusCode::OK, Json(serde_json::json!({"status": "ok"}))).into_response());
         }
     }
 
     info!("Heartbeat for unknown node: {}", node_name);
-    StatusCode::NOT_FOUND.into_response()
+    Err(ApiError::not_found("node", &node_name, None).into())
+}
+
+/// Keep a `NoSchedule` taint on the node for each pressure condition it
+/// reports, and drop it once the condition clears. Returns the conditions
+/// whose taint was added (true) or removed (false).
+fn sync_pressure_taints(node: &mut Node) -> Vec<(NodeConditionType, bool)> {
+    let mut changed = Vec::new();
+    for condition in NodeConditionType::ALL {
+        let key = condition.taint_key();
+        let holds = node
+            .conditions
+            .iter()
+            .any(|c| c.condition_type == condition);
+        let tainted = node.taints.iter().any(|t| t.key == key);
+        if holds && !tainted {
+            node.taints.push(Taint {
+                key: key.to_string(),
+                value: "true".to_string(),
+                effect: TaintEffect::NoSchedule,
+            });
+            changed.push((condition, true));
+        } else if !holds && tainted {
+            node.taints.retain(|t| t.key != key);
+            changed.push((condition, false));
+        }
+    }
+    changed
+}
+
+async fn record_pressure_event(
+    state: &AppState,
+    node: &Node,
+    condition: NodeConditionType,
+    holds: bool,
+) {
+    let event = if holds {
+        let message = node
+            .conditions
+            .iter()
+            .find(|c| c.condition_type == condition)
+            .map_or_else(String::new, |c| c.message.clone());
+        warn!("Node {} has {}: {}", node.name, condition, message);
+        Event::warning(
+            "node",
+            CLUSTER_EVENT_NAMESPACE,
+            &node.name,
+            &condition.to_string(),
+            format!("{}; tainted {}:NoSchedule", message, condition.taint_key()),
+        )
+    } else {
+        info!("Node {} no longer has {}", node.name, condition);
+        Event::normal(
+            "node",
+            CLUSTER_EVENT_NAMESPACE,
+            &node.name,
+            &format!("No{}", condition),
+            format!(
+                "{} cleared; removed taint {}",
+                condition,
+                condition.taint_key()
+            ),
+        )
+    };
+    events::record(&state.store, event).await;
+}
+
+/// Store the per-pod usage from a heartbeat, and drop samples this node
+/// reported earlier for pods it no longer runs.
+async fn record_pod_metrics(
+    state: &AppState,
+    node_name: &str,
+    hb: &NodeHeartbeat,
+) -> anyhow::Result<()> {
+    let mut reported = HashSet::new();
+    for usage in &hb.pods {
+        let metrics = PodMetrics {
+            namespace: usage.namespace.clone(),
+            name: usage.name.clone(),
+            node_name: node_name.to_string(),
+            cpu_millis: usage.cpu_millis,
+            memory_bytes: usage.memory_bytes,
+            timestamp: hb.timestamp,
+        };
+        let key = PodMetrics::key(&usage.namespace, &usage.name);
+        state
+            .store
+            .put(&key, &serde_json::to_vec(&metrics)?)
+            .await?;
+        reported.insert(key);
+    }
+    for (key, value) in state.store.list_prefix("/registry/metrics/pods/").await? {
+        if reported.contains(&key) {
+            continue;
+        }
+        if let Ok(metrics) = serde_json::from_slice::<PodMetrics>(&value)
+            && metrics.node_name == node_name
+        {
+            state.store.delete(&key).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::test_state;
+    use pkg_types::metrics::PodUsage;
+
+    async fn stored_node(state: &AppState) -> Node {
+        let data = state
+            .store
+            .get("/registry/nodes/node-a")
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    async fn seed_node(state: &AppState) {
+        let node: Node = serde_json::from_value(serde_json::json!({
+            "id": "n1",
+            "name": "node-a",
+            "address": "10.0.0.5",
+            "agent_api_port": 10250,
+            "status": "NotReady",
+            "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:00:00Z",
+            "labels": {}
+        }))
+        .unwrap();
+        state
+            .store
+            .put(
+                "/registry/nodes/node-a",
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_body_updates_usage() {
+        let state = test_state("heartbeat-usage").await;
+        seed_node(&state).await;
+        let hb = NodeHeartbeat {
+            cpu_millis: 750,
+            memory_bytes: 2 << 30,
+            running_containers: 3,
+            agent_version: "0.1.0".to_string(),
+            timestamp: Utc::now(),
+            pods: vec![
+                PodUsage {
+                    namespace: "default".to_string(),
+                    name: "web-0".to_string(),
+                    cpu_millis: 300,
+                    memory_bytes: 128 << 20,
+                },
+                PodUsage {
+                    namespace: "default".to_string(),
+                    name: "web-1".to_string(),
+                    cpu_millis: 450,
+                    memory_bytes: 256 << 20,
+                },
+            ],
+            conditions: Vec::new(),
+        };
+        let resp = node_heartbeat(
+            State(state.clone()),
+            Path("node-a".to_string()),
+            Bytes::from(serde_json::to_vec(&hb).unwrap()),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let node = stored_node(&state).await;
+        assert_eq!(node.status, NodeStatus::Ready);
+        assert_eq!(node.usage.cpu_millis, 750);
+        assert_eq!(node.running_containers, 3);
+        assert_eq!(node.agent_version.as_deref(), Some("0.1.0"));
+
+        let data = state
+            .store
+            .get(&PodMetrics::key("default", "web-1"))
+            .await
+            .unwrap()
+            .unwrap();
+        let metrics: PodMetrics = serde_json::from_slice(&data).unwrap();
+        assert_eq!(metrics.cpu_millis, 450);
+        assert_eq!(metrics.node_name, "node-a");
+
+        // A pod missing from the next report loses its sample.
+        let hb = NodeHeartbeat {
+            pods: hb.pods[..1].to_vec(),
+            ..hb
+        };
+        node_heartbeat(
+            State(state.clone()),
+            Path("node-a".to_string()),
+            Bytes::from(serde_json::to_vec(&hb).unwrap()),
+        )
+        .await
+        .unwrap();
+        let keys: Vec<String> = state
+            .store
+            .list_prefix("/registry/metrics/pods/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec![PodMetrics::key("default", "web-0")]);
+    }
+
+    #[tokio::test]
+    async fn test_empty_heartbeat_only_refreshes_liveness() {
+        let state = test_state("heartbeat-empty").await;
+        seed_node(&state).await;
+        let resp = node_heartbeat(
+            State(state.clone()),
+            Path("node-a".to_string()),
+            Bytes::new(),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let node = stored_node(&state).await;
+        assert_eq!(node.status, NodeStatus::Ready);
+        assert!(
+            node.last_heartbeat
+                > "2024-02-25T00:00:00Z"
+                    .parse::<chrono::DateTime<Utc>>()
+                    .unwrap()
+        );
+        assert_eq!(node.usage.cpu_millis, 0);
+        assert_eq!(node.agent_version, None);
+
+        let resp = node_heartbeat(
+            State(state),
+            Path("node-a".to_string()),
+            Bytes::from_static(b"{not json"),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    fn report(conditions: &[NodeConditionType]) -> Bytes {
+        let hb = NodeHeartbeat {
+            cpu_millis: 0,
+            memory_bytes: 0,
+            running_containers: 0,
+            agent_version: "0.1.0".to_string(),
+            timestamp: Utc::now(),
+            pods: Vec::new(),
+            conditions: conditions
+                .iter()
+                .map(|c| pkg_types::node::NodeCondition {
+                    condition_type: *c,
+                    message: "disk 93% used".to_string(),
+                    since: Utc::now(),
+                })
+                .collect(),
+        };
+        Bytes::from(serde_json::to_vec(&hb).unwrap())
+    }
+
+    fn taint_keys(node: &Node) -> Vec<&str> {
+        node.taints.iter().map(|t| t.key.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_pressure_conditions_taint_the_node() {
+        let state = test_state("heartbeat-pressure").await;
+        seed_node(&state).await;
+        let beat = |conditions: &[NodeConditionType]| {
+            node_heartbeat(
+                State(state.clone()),
+                Path("node-a".to_string()),
+                report(conditions),
+            )
+        };
+
+        beat(&[NodeConditionType::DiskPressure]).await.unwrap();
+        let node = stored_node(&state).await;
+        assert_eq!(taint_keys(&node), vec!["node.k3rs/disk-pressure"]);
+        assert_eq!(
+            node.conditions[0].condition_type,
+            NodeConditionType::DiskPressure
+        );
+
+        // Still under pressure: the taint is not added twice.
+        beat(&[
+            NodeConditionType::DiskPressure,
+            NodeConditionType::MemoryPressure,
+        ])
+        .await
+        .unwrap();
+        let node = stored_node(&state).await;
+        assert_eq!(
+            taint_keys(&node),
+            vec!["node.k3rs/disk-pressure", "node.k3rs/memory-pressure"]
+        );
+
+        beat(&[NodeConditionType::MemoryPressure]).await.unwrap();
+        let node = stored_node(&state).await;
+        assert_eq!(taint_keys(&node), vec!["node.k3rs/memory-pressure"]);
+
+        beat(&[]).await.unwrap();
+        let node = stored_node(&state).await;
+        assert!(node.taints.is_empty());
+        assert!(node.conditions.is_empty());
+
+        let reasons: Vec<String> = state
+            .store
+            .event_log
+            .events_since(0)
+            .await
+            .into_iter()
+            .filter_map(|e| serde_json::from_slice::<Event>(e.value.as_deref()?).ok())
+            .map(|e| e.reason)
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "DiskPressure",
+                "MemoryPressure",
+                "NoDiskPressure",
+                "NoMemoryPressure"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_avoids_nodes_under_pressure() {
+        let state = test_state("heartbeat-pressure-scheduling").await;
+        seed_node(&state).await;
+        let pod = |tolerations: serde_json::Value| -> pkg_types::pod::Pod {
+            serde_json::from_value(serde_json::json!({
+                "id": "p1", "name": "web-0", "namespace": "default", "status": "Pending",
+                "created_at": Utc::now(),
+                "spec": {
+                    "containers": [{ "name": "app", "image": "nginx" }],
+                    "tolerations": tolerations,
+                },
+            }))
+            .unwrap()
+        };
+        let scheduler = pkg_scheduler::Scheduler::new();
+        let beat = |conditions: &[NodeConditionType]| {
+            node_heartbeat(
+                State(state.clone()),
+                Path("node-a".to_string()),
+                report(conditions),
+            )
+        };
+
+        beat(&[NodeConditionType::DiskPressure]).await.unwrap();
+        let nodes = [stored_node(&state).await];
+        assert_eq!(
+            scheduler.schedule(&pod(serde_json::json!([])), &nodes, &[]),
+            None
+        );
+        let tolerant = pod(serde_json::json!([
+            { "key": "node.k3rs/disk-pressure", "operator": "Exists" }
+        ]));
+        assert_eq!(
+            scheduler.schedule(&tolerant, &nodes, &[]).as_deref(),
+            Some("node-a")
+        );
+
+        // Recovered: schedulable again.
+        beat(&[]).await.unwrap();
+        let nodes = [stored_node(&state).await];
+        assert_eq!(
+            scheduler
+                .schedule(&pod(serde_json::json!([])), &nodes, &[])
+                .as_deref(),
+            Some("node-a")
+        );
+    }
 }
@@ -1,13 +1,16 @@
+use anyhow::Context;
 use axum::{
     Json,
     extract::{Path as AxumPath, State},
     http::StatusCode,
     response::IntoResponse,
 };
+use pkg_types::error::{ApiError, ErrorReason};
 use serde::{Deserialize, Serialize};
 use tracing::info;
 
 use crate::AppState;
+use crate::error::{ApiResult, ErrorResponse};
 
 /// Image metadata reported by agents.
 /// Mirrors `pkg_container::image::ImageInfo` but defined locally
@@ -49,7 +52,7 @@ pub async fn report_node_images(
     State(state): State<AppState>,
     AxumPath(node_name): AxumPath<String>,
     Json(mut images): Json<Vec<ImageInfo>>,
-) -> impl IntoResponse {
+) -> ApiResult<StatusCode> {
     info!(
         "Node {} reporting {} cached images",
         node_name,
@@ -63,15 +66,12 @@ pub async fn report_node_images(
 
     // Store in state
     let key = format!("/registry/images/{}", node_name);
-    match serde_json::to_vec(&images) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
-            }
-            StatusCode::OK.into_response()
-        }
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
-    }
+    state
+        .store
+        .put(&key, &serde_json::to_vec(&images)?)
+        .await
+        .context("storing node images")?;
+    Ok(StatusCode::OK)
 }
 
 /// Pull an image from a registry.
@@ -81,28 +81,30 @@ pub struct PullImageRequest {
     pub image: String,
 }
 
-pub async fn pull_image(Json(req): Json<PullImageRequest>) -> impl IntoResponse {
+pub async fn pull_image(Json(req): Json<PullImageRequest>) -> ErrorResponse {
     info!(
         "Pull image request: {} — not available on control plane",
         req.image
     );
-    (
-        StatusCode::NOT_IMPLEMENTED,
+    ApiError::new(
+        ErrorReason::NotImplemented,
         "Image pull is handled by the Agent node. \
          The server (control plane) does not pull or cache images.",
     )
+    .into()
 }
 
 /// Delete a cached image by ID.
 /// Image management is handled by the Agent.
-pub async fn delete_image(AxumPath(image_id): AxumPath<String>) -> impl IntoResponse {
+pub async fn delete_image(AxumPath(image_id): AxumPath<String>) -> ErrorResponse {
     info!(
         "Delete image request: {} — not available on control plane",
         image_id
     );
-    (
-        StatusCode::NOT_IMPLEMENTED,
+    ApiError::new(
+        ErrorReason::NotImplemented,
         "Image delete is handled by the Agent node. \
          The server (control plane) does not manage local images.",
     )
+    .into()
 }
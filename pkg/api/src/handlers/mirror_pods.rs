@@ -0,0 +1,236 @@
+//! Mirror pods: read-only records of the static pods an agent runs from its
+//! local manifest directory, so they show up alongside the other pods.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use pkg_types::pod::Pod;
+use pkg_types::validate::validate_container_id;
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+use crate::AppState;
+use crate::error::ApiResult;
+use crate::handlers::resources::reject_if_terminating;
+
+/// PUT /api/v1/nodes/:name/mirror-pods — replace the node's mirror pods with
+/// the static pods it reports running.
+///
+/// Each reported pod is stored as a mirror bound to the node, unless its ID
+/// is not a valid container ID or a pod that is not one of the node's
+/// mirrors already has its name. The node's mirrors it no longer reports
+/// are deleted.
+pub async fn put_mirror_pods(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+    Json(pods): Json<Vec<Pod>>,
+) -> ApiResult {
+    let mut reported = HashSet::new();
+    for mut pod in pods {
+        pod.mirror = true;
+        pod.node_name = Some(node_name.clone());
+        if let Err(e) = validate_container_id(&pod.id) {
+            warn!(
+                "Not mirroring static pod {}/{} of {}: {}",
+                pod.namespace, pod.name, node_name, e
+            );
+            continue;
+        }
+        let key = format!("/registry/pods/{}/{}", pod.namespace, pod.name);
+        reported.insert(key.clone());
+
+        let stored = match state.store.get(&key).await? {
+            Some(data) => Some(serde_json::from_slice::<Pod>(&data)?),
+            None => None,
+        };
+        match stored {
+            Some(stored) if !is_mirror_on(&stored, &node_name) => {
+                warn!(
+                    "Not mirroring static pod {}/{} of {}: the name is taken",
+                    pod.namespace, pod.name, node_name
+                );
+                continue;
+            }
+            Some(stored) if unchanged(&stored, &pod) => continue,
+            Some(stored) => pod.created_at = stored.created_at,
+            None => {
+                if reject_if_terminating(&state, &pod.namespace).await.is_err() {
+                    continue;
+                }
+                info!(
+                    "Mirroring static pod {}/{} of {}",
+                    pod.namespace, pod.name, node_name
+                );
+            }
+        }
+        state.store.put(&key, &serde_json::to_vec(&pod)?).await?;
+    }
+
+    for (key, value) in state.store.list_prefix("/registry/pods/").await? {
+        if reported.contains(&key) {
+            continue;
+        }
+        if let Ok(pod) = serde_json::from_slice::<Pod>(&value)
+            && is_mirror_on(&pod, &node_name)
+        {
+            info!(
+                "Removing mirror pod {}/{}: {} no longer runs it",
+                pod.namespace, pod.name, node_name
+            );
+            state.store.delete(&key).await?;
+        }
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+fn is_mirror_on(pod: &Pod, node_name: &str) -> bool {
+    pod.mirror && pod.node_name.as_deref() == Some(node_name)
+}
+
+/// Whether storing `reported` over `stored` would change what is shown.
+fn unchanged(stored: &Pod, reported: &Pod) -> bool {
+    stored.id == reported.id
+        && stored.status == reported.status
+        && stored.status_message == reported.status_message
+        && stored.labels == reported.labels
+        && serde_json::to_value(&stored.spec).ok() == serde_json::to_value(&reported.spec).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::resources::{ApplyQuery, DeletePodQuery, delete_pod};
+    use crate::handlers::testing::{api_error, test_state};
+    use axum::extract::Query;
+    use pkg_types::error::ErrorReason;
+    use pkg_types::pod::PodStatus;
+
+    fn mirror(name: &str, image: &str) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": format!("static-default-{}", name),
+            "name": format!("{}-node-a", name),
+            "namespace": "default",
+            "spec": { "containers": [{ "name": "app", "image": image }] },
+            "status": "Running",
+            "created_at": chrono::Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    async fn report(state: &AppState, node: &str, pods: Vec<Pod>) {
+        let resp = put_mirror_pods(State(state.clone()), Path(node.to_string()), Json(pods))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    }
+
+    async fn stored(state: &AppState, name: &str) -> Option<Pod> {
+        let data = state
+            .store
+            .get(&format!("/registry/pods/default/{}", name))
+            .await
+            .unwrap()?;
+        Some(serde_json::from_slice(&data).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_mirrors_follow_the_reported_static_pods() {
+        let state = test_state("mirror-reconcile").await;
+        report(
+            &state,
+            "node-a",
+            vec![mirror("dns", "coredns:1"), mirror("ingress", "pingora:1")],
+        )
+        .await;
+        let dns = stored(&state, "dns-node-a").await.unwrap();
+        assert!(dns.mirror);
+        assert_eq!(dns.node_name.as_deref(), Some("node-a"));
+        assert_eq!(dns.status, PodStatus::Running);
+        let (version, created_at) = (dns.resource_version, dns.created_at);
+
+        // Unchanged pods are not rewritten; changed ones are, removed ones go.
+        report(&state, "node-a", vec![mirror("dns", "coredns:1")]).await;
+        assert_eq!(
+            stored(&state, "dns-node-a").await.unwrap().resource_version,
+            version
+        );
+        assert!(stored(&state, "ingress-node-a").await.is_none());
+        report(&state, "node-a", vec![mirror("dns", "coredns:2")]).await;
+        let dns = stored(&state, "dns-node-a").await.unwrap();
+        assert_eq!(dns.spec.containers[0].image, "coredns:2");
+        assert_eq!(dns.created_at, created_at);
+
+        // Another node's report leaves node-a's mirrors alone.
+        report(&state, "node-b", vec![]).await;
+        assert!(stored(&state, "dns-node-a").await.is_some());
+
+        // A pod whose ID could not name a container is not mirrored.
+        let mut bad = mirror("bad", "busybox:1");
+        bad.id = "../../etc".to_string();
+        report(&state, "node-a", vec![mirror("dns", "coredns:2"), bad]).await;
+        assert!(stored(&state, "bad-node-a").await.is_none());
+        assert!(stored(&state, "dns-node-a").await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_mirror_never_replaces_a_regular_pod() {
+        let state = test_state("mirror-taken").await;
+        let mut regular = mirror("web", "nginx:1");
+        regular.mirror = false;
+        regular.id = "regular-id".to_string();
+        state
+            .store
+            .put(
+                "/registry/pods/default/web-node-a",
+                &serde_json::to_vec(&regular).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        report(&state, "node-a", vec![mirror("web", "nginx:2")]).await;
+        report(&state, "node-a", vec![]).await;
+        let web = stored(&state, "web-node-a").await.unwrap();
+        assert!(!web.mirror);
+        assert_eq!(web.id, "regular-id");
+    }
+
+    #[tokio::test]
+    async fn test_mirrors_are_read_only_and_deleted_at_once() {
+        let state = test_state("mirror-readonly").await;
+        report(&state, "node-a", vec![mirror("dns", "coredns:1")]).await;
+
+        let mut edit = mirror("dns", "coredns:9");
+        edit.mirror = false;
+        let resp = crate::handlers::resources::apply_pod(
+            State(state.clone()),
+            Path(("default".to_string(), "dns-node-a".to_string())),
+            Query(ApplyQuery::default()),
+            Json(edit),
+        )
+        .await
+        .into_response();
+        assert_eq!(api_error(resp).await.reason, ErrorReason::Forbidden);
+        assert_eq!(
+            stored(&state, "dns-node-a").await.unwrap().spec.containers[0].image,
+            "coredns:1"
+        );
+
+        // No Terminating step: the agent must not stop the static pod.
+        let resp = delete_pod(
+            State(state.clone()),
+            Path(("default".to_string(), "dns-node-a".to_string())),
+            Query(DeletePodQuery::default()),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(stored(&state, "dns-node-a").await.is_none());
+
+        // The next report brings it back.
+        report(&state, "node-a", vec![mirror("dns", "coredns:1")]).await;
+        assert!(stored(&state, "dns-node-a").await.unwrap().mirror);
+    }
+}
@@ -1,13 +1,30 @@
+pub mod agent_proxy;
+pub mod archive;
 pub mod backup;
+pub mod certificates;
 pub mod cluster;
+pub mod debug;
 pub mod drain;
 pub mod endpoints;
+pub mod events;
 pub mod exec;
 pub mod heartbeat;
 pub mod images;
+pub mod mirror_pods;
+pub mod patch;
+pub mod port_forward;
 pub mod processes;
 pub mod register;
 pub mod resources;
+pub mod rollout;
 pub mod runtime;
+pub mod scale;
+pub mod taints;
+pub mod tokens;
+pub mod tunnel;
+pub mod usage;
 pub mod vpc;
 pub mod watch;
+
+#[cfg(test)]
+pub(crate) mod testing;
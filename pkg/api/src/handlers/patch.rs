@@ -0,0 +1,764 @@
+//! PATCH: partial updates. The patch is applied to the stored object and the
+//! result goes through the same path as an apply (`PUT`) of the whole object,
+//! so admission, defaulting and the resource version check are shared.
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::IntoResponse,
+};
+use pkg_state::client::{MAX_UPDATE_ATTEMPTS, RESOURCE_VERSION_FIELD};
+use pkg_types::error::{ApiError, ErrorReason, FieldError};
+use pkg_types::node::Node;
+use pkg_types::patch::{MERGE_PATCH_CONTENT_TYPE, PatchType, immutable_changes, merge_patch};
+use pkg_types::validate::Validate;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+use crate::AppState;
+use crate::error::ApiResult;
+use crate::handlers::resources::{self, ApplyQuery};
+
+/// Apply the patch in `body` to the object stored at `key` and hand the
+/// result to `apply`. Fields fixed at creation may not change. A patch that
+/// sets `resource_version` only applies to that version; otherwise it is
+/// re-applied to the new object when another write lands in between.
+#[allow(clippy::too_many_arguments)]
+async fn patch<T, F, Fut, R>(
+    state: &AppState,
+    headers: &HeaderMap,
+    body: &[u8],
+    key: &str,
+    kind: &str,
+    ns: Option<&str>,
+    name: &str,
+    apply: F,
+) -> ApiResult
+where
+    T: DeserializeOwned,
+    F: Fn(T) -> Fut,
+    Fut: std::future::Future<Output = R>,
+    R: IntoResponse,
+{
+    let patch = merge_patch_body(headers, body)?;
+    let pinned = patch
+        .get(RESOURCE_VERSION_FIELD)
+        .is_some_and(|v| !v.is_null());
+
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let Some(data) = state.store.get(key).await? else {
+            return Err(ApiError::not_found(kind, name, ns).into());
+        };
+        let stored: serde_json::Value = serde_json::from_slice(&data)?;
+        let mut patched = stored.clone();
+        merge_patch(&mut patched, &patch);
+        let changed = immutable_changes(&stored, &patched);
+        if !changed.is_empty() {
+            return Err(ApiError::invalid(kind, name, changed).into());
+        }
+        let obj: T = serde_json::from_value(patched).map_err(|e| {
+            ApiError::new(
+                ErrorReason::Invalid,
+                format!("{} '{}' is invalid after patching: {}", kind, name, e),
+            )
+        })?;
+        let resp = apply(obj).await.into_response();
+        if resp.status() == StatusCode::CONFLICT && !pinned && attempts < MAX_UPDATE_ATTEMPTS {
+            continue;
+        }
+        return Ok(resp);
+    }
+}
+
+/// The JSON merge patch in `body`, after checking the request says that is
+/// what it is.
+fn merge_patch_body(headers: &HeaderMap, body: &[u8]) -> Result<serde_json::Value, ApiError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    match PatchType::from_content_type(content_type) {
+        Some(PatchType::Merge) => {}
+        Some(PatchType::StrategicMerge) => {
+            return Err(ApiError::new(
+                ErrorReason::UnsupportedMediaType,
+                format!(
+                    "strategic merge patches are not supported; send a JSON merge patch ({})",
+                    MERGE_PATCH_CONTENT_TYPE
+                ),
+            ));
+        }
+        None => {
+            return Err(ApiError::new(
+                ErrorReason::UnsupportedMediaType,
+                format!(
+                    "unsupported patch content type '{}'; send a JSON merge patch ({})",
+                    content_type, MERGE_PATCH_CONTENT_TYPE
+                ),
+            ));
+        }
+    }
+    serde_json::from_slice(body).map_err(|e| {
+        ApiError::new(
+            ErrorReason::BadRequest,
+            format!("patch is not valid JSON: {}", e),
+        )
+    })
+}
+
+/// PATCH /api/v1/nodes/:name — a merge patch of the node's labels. The rest
+/// of a node is kept by its agent and the server, and taints have their own
+/// endpoint, so `labels` is the only field a patch may set.
+pub async fn patch_node(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> ApiResult {
+    let patch = merge_patch_body(&headers, &body)?;
+    let Some(fields) = patch.as_object() else {
+        return Err(ApiError::new(
+            ErrorReason::BadRequest,
+            "a node patch must be a JSON object",
+        )
+        .into());
+    };
+    let refused: Vec<FieldError> = fields
+        .keys()
+        .filter(|k| *k != "labels")
+        .map(|k| FieldError::new(k, "cannot be patched; only labels can"))
+        .collect();
+    if !refused.is_empty() {
+        return Err(ApiError::invalid("node", &name, refused).into());
+    }
+
+    let key = format!("/registry/nodes/{}", name);
+    let mut rejected = None;
+    let node = state
+        .store
+        .update::<Node, _>(&key, |node| {
+            let mut patched = serde_json::json!({ "labels": node.labels });
+            merge_patch(&mut patched, &patch);
+            let labels = match patched.get("labels").cloned() {
+                None => HashMap::new(),
+                Some(labels) => match serde_json::from_value(labels) {
+                    Ok(labels) => labels,
+                    Err(_) => {
+                        rejected = Some(vec![FieldError::new(
+                            "labels",
+                            "must map keys to string values",
+                        )]);
+                        return false;
+                    }
+                },
+            };
+            let errors = Node {
+                labels: labels.clone(),
+                taints: Vec::new(),
+                ..node.clone()
+            }
+            .validate();
+            if !errors.is_empty() {
+                rejected = Some(errors);
+                return false;
+            }
+            if labels == node.labels {
+                return false;
+            }
+            node.labels = labels;
+            true
+        })
+        .await?;
+    if let Some(errors) = rejected {
+        return Err(ApiError::invalid("node", &name, errors).into());
+    }
+    match node {
+        Some(node) => Ok(Json(node).into_response()),
+        None => Err(ApiError::not_found("node", &name, None).into()),
+    }
+}
+
+pub async fn patch_namespace(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/namespaces/{}", name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "namespace",
+        None,
+        &name,
+        |ns| {
+            resources::apply_namespace(
+                State(state.clone()),
+                Path(name.clone()),
+                Query(query.clone()),
+                Json(ns),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_pod(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/pods/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "pod",
+        Some(&ns),
+        &name,
+        |pod| {
+            resources::apply_pod(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(pod),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_service(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/services/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "service",
+        Some(&ns),
+        &name,
+        |svc| {
+            resources::apply_service(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(svc),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_deployment(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/deployments/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "deployment",
+        Some(&ns),
+        &name,
+        |deploy| {
+            resources::apply_deployment(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(deploy),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_configmap(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/configmaps/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "configmap",
+        Some(&ns),
+        &name,
+        |cm| {
+            resources::apply_configmap(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(cm),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_secret(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/secrets/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "secret",
+        Some(&ns),
+        &name,
+        |secret| {
+            resources::apply_secret(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(secret),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_replicaset(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/replicasets/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "replicaset",
+        Some(&ns),
+        &name,
+        |rs| {
+            resources::apply_replicaset(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(rs),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_daemonset(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/daemonsets/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "daemonset",
+        Some(&ns),
+        &name,
+        |ds| {
+            resources::apply_daemonset(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(ds),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_job(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/jobs/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "job",
+        Some(&ns),
+        &name,
+        |job| {
+            resources::apply_job(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(job),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_cronjob(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/cronjobs/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "cronjob",
+        Some(&ns),
+        &name,
+        |cj| {
+            resources::apply_cronjob(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(cj),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_hpa(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/hpa/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "hpa",
+        Some(&ns),
+        &name,
+        |hpa| {
+            resources::apply_hpa(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(hpa),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_limit_range(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/limitranges/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "limitrange",
+        Some(&ns),
+        &name,
+        |range| {
+            resources::apply_limit_range(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(range),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_pod_disruption_budget(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/poddisruptionbudgets/{}/{}", ns, name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "poddisruptionbudget",
+        Some(&ns),
+        &name,
+        |pdb| {
+            resources::apply_pod_disruption_budget(
+                State(state.clone()),
+                Path((ns.clone(), name.clone())),
+                Query(query.clone()),
+                Json(pdb),
+            )
+        },
+    )
+    .await
+}
+
+pub async fn patch_priority_class(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<ApplyQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    let key = format!("/registry/priorityclasses/{}", name);
+    patch(
+        &state,
+        &headers,
+        &body,
+        &key,
+        "priorityclass",
+        None,
+        &name,
+        |pc| {
+            resources::apply_priority_class(
+                State(state.clone()),
+                Path(name.clone()),
+                Query(query.clone()),
+                Json(pc),
+            )
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{api_error, body_text, test_state};
+    use axum::response::Response;
+    use pkg_types::deployment::Deployment;
+
+    fn merge_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            MERGE_PATCH_CONTENT_TYPE.parse().unwrap(),
+        );
+        headers
+    }
+
+    async fn create_web(state: &AppState) -> Deployment {
+        let deploy = serde_json::from_value(serde_json::json!({
+            "id": "", "name": "web", "namespace": "", "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "replicas": 1,
+                "selector": { "app": "web" },
+                "template": { "containers": [{ "name": "web", "image": "nginx:1" }] }
+            }
+        }))
+        .unwrap();
+        let resp = resources::create_deployment(
+            State(state.clone()),
+            Path("default".to_string()),
+            Json(deploy),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        serde_json::from_str(&body_text(resp).await).unwrap()
+    }
+
+    async fn send(state: &AppState, headers: HeaderMap, body: serde_json::Value) -> Response {
+        patch_deployment(
+            State(state.clone()),
+            Path(("default".to_string(), "web".to_string())),
+            Query(ApplyQuery::default()),
+            headers,
+            Bytes::from(body.to_string()),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn test_patch_merges_into_stored_object() {
+        let state = test_state("patch-merge").await;
+        let created = create_web(&state).await;
+
+        let resp = send(
+            &state,
+            merge_headers(),
+            serde_json::json!({ "spec": { "replicas": 3 } }),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let patched: Deployment = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(patched.spec.replicas, 3);
+        assert_eq!(patched.id, created.id);
+        assert_eq!(patched.spec.template.containers[0].image, "nginx:1");
+        assert!(patched.resource_version > created.resource_version);
+
+        // Pinned to a version that is no longer current.
+        let resp = send(
+            &state,
+            merge_headers(),
+            serde_json::json!({
+                "resource_version": created.resource_version,
+                "spec": { "replicas": 5 }
+            }),
+        )
+        .await;
+        assert_eq!(api_error(resp).await.reason, ErrorReason::Conflict);
+    }
+
+    #[tokio::test]
+    async fn test_patch_rejects_immutable_fields_and_other_formats() {
+        let state = test_state("patch-guard").await;
+        let created = create_web(&state).await;
+
+        let resp = send(
+            &state,
+            merge_headers(),
+            serde_json::json!({ "namespace": "prod", "created_at": null }),
+        )
+        .await;
+        let err = api_error(resp).await;
+        assert_eq!(err.reason, ErrorReason::Invalid);
+        assert_eq!(
+            err.details,
+            [
+                FieldError::new("namespace", "field is immutable"),
+                FieldError::new("created_at", "field is immutable"),
+            ]
+        );
+
+        let resp = send(
+            &state,
+            merge_headers(),
+            serde_json::json!({ "spec": { "replicas": "three" } }),
+        )
+        .await;
+        assert_eq!(api_error(resp).await.reason, ErrorReason::Invalid);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            "application/strategic-merge-patch+json".parse().unwrap(),
+        );
+        let resp = send(&state, headers, serde_json::json!({})).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let resp = send(&state, HeaderMap::new(), serde_json::json!({})).await;
+        assert_eq!(
+            api_error(resp).await.reason,
+            ErrorReason::UnsupportedMediaType
+        );
+
+        // Nothing was written.
+        let data = state
+            .store
+            .get("/registry/deployments/default/web")
+            .await
+            .unwrap()
+            .unwrap();
+        let stored: Deployment = serde_json::from_slice(&data).unwrap();
+        assert_eq!(stored.resource_version, created.resource_version);
+        assert_eq!(stored.namespace, "default");
+    }
+
+    #[tokio::test]
+    async fn test_patch_node_labels() {
+        let state = test_state("patch-node").await;
+        let node = serde_json::json!({
+            "id": "n1", "name": "node-a", "address": "10.0.0.5", "agent_api_port": 10250,
+            "status": "Ready", "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:00:00Z", "labels": { "zone": "a", "disk": "hdd" },
+        });
+        state
+            .store
+            .put(
+                "/registry/nodes/node-a",
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+        let send = |body: serde_json::Value| {
+            patch_node(
+                State(state.clone()),
+                Path("node-a".to_string()),
+                merge_headers(),
+                Bytes::from(body.to_string()),
+            )
+        };
+
+        let resp = send(serde_json::json!({ "labels": { "disk": "ssd", "zone": null } }))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let node: Node = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(
+            node.labels,
+            HashMap::from([("disk".to_string(), "ssd".to_string())])
+        );
+
+        let resp = send(serde_json::json!({ "labels": { "gpu!": "yes" }, "address": "x" }))
+            .await
+            .into_response();
+        let err = api_error(resp).await;
+        assert_eq!(
+            err.details,
+            [FieldError::new(
+                "address",
+                "cannot be patched; only labels can"
+            )]
+        );
+        let resp = send(serde_json::json!({ "labels": { "gpu!": "yes" } }))
+            .await
+            .into_response();
+        let err = api_error(resp).await;
+        assert_eq!(err.reason, ErrorReason::Invalid);
+        assert_eq!(err.details[0].field, "labels.gpu!");
+    }
+}
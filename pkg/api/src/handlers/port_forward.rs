@@ -0,0 +1,36 @@
+use axum::extract::{Path as AxumPath, Query, State, ws::WebSocketUpgrade};
+use serde::Deserialize;
+use tracing::info;
+
+use super::agent_proxy::{locate_container, relay_websocket};
+use crate::AppState;
+use crate::error::ApiResult;
+
+#[derive(Debug, Deserialize)]
+pub struct PortForwardQuery {
+    /// Port inside the pod to connect to.
+    pub port: u16,
+}
+
+/// GET /api/v1/namespaces/:ns/pods/:name/portforward?port= — WebSocket
+/// carrying one TCP connection to `port` in the pod, proxied to its agent.
+///
+/// Binary frames hold the connection's bytes; a text frame from the agent
+/// reports why the connection could not be made or was dropped.
+pub async fn port_forward_to_pod(
+    AxumPath((ns, pod_name)): AxumPath<(String, String)>,
+    Query(query): Query<PortForwardQuery>,
+    State(state): State<AppState>,
+    ws: WebSocketUpgrade,
+) -> ApiResult {
+    info!(
+        "Port-forward request for pod {}/{} port {}",
+        ns, pod_name, query.port
+    );
+
+    let target = locate_container(&state, &ns, &pod_name, None).await?;
+
+    let agent_path = format!("/portforward/{}?port={}", target.container_id, query.port);
+    let agent = target.connect_websocket(&agent_path).await?;
+    Ok(ws.on_upgrade(move |socket| relay_websocket(socket, agent)))
+}
@@ -1,15 +1,62 @@
-use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use anyhow::Context;
+use axum::{Json, extract::State};
 use chrono::Utc;
+use pkg_controllers::events;
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::event::{CLUSTER_EVENT_NAMESPACE, Event};
 use pkg_types::node::{Node, NodeRegistrationRequest, NodeRegistrationResponse, NodeStatus};
 use pkg_types::pod::ResourceRequirements;
+use std::fmt;
+use std::str::FromStr;
 use tracing::{info, warn};
 
 use crate::AppState;
+use crate::error::ApiResult;
 
+/// What to do when a node name that is already registered registers again
+/// from a different address.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AddressConflictPolicy {
+    /// Refuse with 409 until the old node is deleted.
+    #[default]
+    Reject,
+    /// Move the node to the new address and record a warning event.
+    TakeOver,
+}
+
+impl fmt::Display for AddressConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reject => write!(f, "reject"),
+            Self::TakeOver => write!(f, "takeover"),
+        }
+    }
+}
+
+impl FromStr for AddressConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "takeover" | "take-over" => Ok(Self::TakeOver),
+            other => Err(format!(
+                "unknown node address conflict policy '{}' (expected reject or takeover)",
+                other
+            )),
+        }
+    }
+}
+
+/// POST /register — join a node to the cluster.
+///
+/// Registration is keyed on the node name: a node that registers again (e.g.
+/// after an agent restart) keeps its id, taints and pod bindings, gets its
+/// address, capacity and labels updated and is issued fresh certificates.
 pub async fn register_node(
     State(state): State<AppState>,
     Json(payload): Json<NodeRegistrationRequest>,
-) -> impl IntoResponse {
+) -> ApiResult<Json<NodeRegistrationResponse>> {
     info!(
         "Received registration request for node: {}",
         payload.node_name
@@ -21,33 +68,54 @@ pub async fn register_node(
             "Node {} attempted to register without a token",
             payload.node_name
         );
-        return (StatusCode::UNAUTHORIZED, "Missing join token").into_response();
+        return Err(ApiError::new(ErrorReason::Unauthorized, "missing join token").into());
     }
 
     if payload.token != state.join_token {
         warn!("Node {} provided an invalid join token", payload.node_name);
-        return (StatusCode::FORBIDDEN, "Invalid join token").into_response();
+        return Err(ApiError::new(ErrorReason::Unauthorized, "invalid join token").into());
     }
 
-    // Issue a real certificate via the CA
-    let (cert_pem, key_pem) = match state.ca.issue_node_cert(&payload.node_name) {
-        Ok(pair) => pair,
-        Err(e) => {
-            tracing::error!("Failed to issue certificate: {}", e);
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Certificate generation failed",
+    // Check if node already exists
+    let key = format!("/registry/nodes/{}", payload.node_name);
+    let existing_node = state
+        .store
+        .get(&key)
+        .await
+        .context("reading node")?
+        .and_then(|data| serde_json::from_slice::<Node>(&data).ok());
+
+    let moved_from = existing_node
+        .as_ref()
+        .filter(|n| n.address != payload.address)
+        .map(|n| n.address.clone());
+    if let Some(ref old) = moved_from {
+        if state.address_conflict == AddressConflictPolicy::Reject {
+            warn!(
+                "Node {} tried to register from {}, but is registered from {}",
+                payload.node_name, payload.address, old
+            );
+            return Err(ApiError::new(
+                ErrorReason::Conflict,
+                format!(
+                    "node '{}' is already registered from {}; delete it first or set the server's node address conflict policy to takeover",
+                    payload.node_name, old
+                ),
             )
-                .into_response();
+            .into());
         }
-    };
+        warn!(
+            "Node {} taken over from {} by {}",
+            payload.node_name, old, payload.address
+        );
+    }
 
-    // Check if node already exists
-    let key = format!("/registry/nodes/{}", payload.node_name);
-    let existing_node = match state.store.get(&key).await {
-        Ok(Some(data)) => serde_json::from_slice::<Node>(&data).ok(),
-        _ => None,
-    };
+    // Issue a real certificate via the CA
+    let validity = chrono::Duration::days(pkg_constants::timings::NODE_CERT_VALIDITY_DAYS);
+    let (cert_pem, key_pem) = state
+        .ca
+        .issue_node_cert(&payload.node_name, validity)
+        .context("issuing certificate")?;
 
     let now = Utc::now();
     let node_id = if let Some(ref existing) = existing_node {
@@ -69,6 +137,19 @@ pub async fn register_node(
         format!("{}:{}", host, port)
     });
 
+    // A node keeps its pod CIDR across re-registrations; a new one takes the
+    // lowest block of the cluster CIDR no other node holds.
+    let pod_cidr = match existing_node
+        .as_ref()
+        .and_then(|n| n.pod_cidr.clone())
+        .filter(|cidr| state.cluster_cidr.contains(cidr))
+    {
+        Some(cidr) => Some(cidr),
+        None => free_pod_cidr(&state, &payload.node_name)
+            .await
+            .context("listing nodes")?,
+    };
+
     let node = if let Some(mut existing) = existing_node {
         info!("Updating existing node: {}", payload.node_name);
         existing.status = NodeStatus::Ready;
@@ -76,8 +157,13 @@ pub async fn register_node(
         existing.address = payload.address.clone();
         existing.agent_api_port = agent_api_port;
         existing.labels.extend(payload.labels.clone());
+        if let Some(capacity) = payload.capacity.clone() {
+            existing.capacity = capacity;
+        }
         existing.wg_public_key = payload.wg_public_key.clone();
         existing.wg_endpoint = wg_endpoint;
+        existing.certificate_expires_at = Some(now + validity);
+        existing.pod_cidr = pod_cidr.clone();
         existing
     } else {
         Node {
@@ -95,24 +181,67 @@ pub async fn register_node(
             unschedulable: false,
             wg_public_key: payload.wg_public_key.clone(),
             wg_endpoint,
+            usage: ResourceRequirements::default(),
+            running_containers: 0,
+            agent_version: None,
+            certificate_expires_at: Some(now + validity),
+            pod_cidr: pod_cidr.clone(),
+            conditions: Vec::new(),
         }
     };
 
-    match serde_json::to_vec(&node) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                tracing::error!("Failed to persist node: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to persist node")
-                    .into_response();
-            }
-        }
-        Err(e) => {
-            tracing::error!("Failed to serialize node: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response();
-        }
-    }
+    // A fresh agent API token on every registration; the old one stops
+    // working as soon as the agent has the new one.
+    let agent_token = uuid::Uuid::new_v4().simple().to_string();
+    state
+        .store
+        .put(&agent_token_key(&payload.node_name), agent_token.as_bytes())
+        .await
+        .context("persisting agent token")?;
+    state
+        .store
+        .put(&key, &serde_json::to_vec(&node)?)
+        .await
+        .context("persisting node")?;
 
     info!("Node {} registered with id {}", payload.node_name, node_id);
+    let event = match moved_from {
+        Some(old) => Event::warning(
+            "node",
+            CLUSTER_EVENT_NAMESPACE,
+            &payload.node_name,
+            "AddressTakeover",
+            format!(
+                "Node re-registered from {}, taking over from {}",
+                payload.address, old
+            ),
+        ),
+        None => Event::normal(
+            "node",
+            CLUSTER_EVENT_NAMESPACE,
+            &payload.node_name,
+            "Registered",
+            format!("Node registered from {}", payload.address),
+        ),
+    };
+    events::record(&state.store, event).await;
+    if pod_cidr.is_none() {
+        warn!(
+            "No pod CIDR left in {} for node {}",
+            state.cluster_cidr, payload.node_name
+        );
+        events::record(
+            &state.store,
+            Event::warning(
+                "node",
+                CLUSTER_EVENT_NAMESPACE,
+                &payload.node_name,
+                "PodCIDRNotAvailable",
+                format!("No free pod CIDR left in {}", state.cluster_cidr),
+            ),
+        )
+        .await;
+    }
 
     let response = NodeRegistrationResponse {
         node_id,
@@ -120,7 +249,251 @@ pub async fn register_node(
         private_key: key_pem,
         server_ca: state.ca.ca_cert_pem().to_string(),
         agent_api_port: pkg_constants::network::DEFAULT_AGENT_API_PORT,
+        pod_cidr,
+        agent_token: Some(agent_token),
     };
 
-    (StatusCode::OK, Json(response)).into_response()
+    Ok(Json(response))
+}
+
+/// Store key of the token node `node_name`'s agent API accepts. It is kept
+/// out of the node object, which everyone who can read nodes sees.
+fn agent_token_key(node_name: &str) -> String {
+    format!("/registry/agenttokens/{}", node_name)
+}
+
+/// The token to present on node `node_name`'s agent API, or `None` for a
+/// node that has not registered since agent tokens were introduced.
+pub(crate) async fn agent_token(
+    state: &AppState,
+    node_name: &str,
+) -> anyhow::Result<Option<String>> {
+    Ok(state
+        .store
+        .get(&agent_token_key(node_name))
+        .await?
+        .map(|data| String::from_utf8_lossy(&data).into_owned()))
+}
+
+/// The lowest pod CIDR not held by a node other than `node_name`, or `None`
+/// when the cluster CIDR is used up.
+async fn free_pod_cidr(state: &AppState, node_name: &str) -> anyhow::Result<Option<String>> {
+    let taken: Vec<String> = state
+        .store
+        .list_prefix("/registry/nodes/")
+        .await?
+        .iter()
+        .filter_map(|(_, v)| serde_json::from_slice::<Node>(v).ok())
+        .filter(|n| n.name != node_name)
+        .filter_map(|n| n.pod_cidr)
+        .collect();
+    Ok(state.cluster_cidr.assign(taken.iter().map(String::as_str)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{api_error, body_text, test_state};
+    use crate::pod_cidrs::ClusterCidr;
+    use axum::http::StatusCode;
+    use axum::response::{IntoResponse, Response};
+
+    fn request(address: &str, cpu: u64) -> NodeRegistrationRequest {
+        serde_json::from_value(serde_json::json!({
+            "token": "test-token",
+            "node_name": "node-a",
+            "address": address,
+            "labels": { "zone": "a" },
+            "capacity": { "cpu_millis": cpu, "memory_bytes": 1 << 30 },
+        }))
+        .unwrap()
+    }
+
+    async fn register(state: &AppState, req: NodeRegistrationRequest) -> Response {
+        register_node(State(state.clone()), Json(req))
+            .await
+            .into_response()
+    }
+
+    async fn node_id(resp: Response) -> String {
+        assert_eq!(resp.status(), StatusCode::OK);
+        serde_json::from_str::<NodeRegistrationResponse>(&body_text(resp).await)
+            .unwrap()
+            .node_id
+    }
+
+    async fn stored_node(state: &AppState) -> Node {
+        let data = state
+            .store
+            .get("/registry/nodes/node-a")
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reregistration_keeps_node_id_and_pods() {
+        let state = test_state("register-again").await;
+        let first = node_id(register(&state, request("10.0.0.5", 2000)).await).await;
+
+        let mut node = stored_node(&state).await;
+        node.unschedulable = true;
+        state
+            .store
+            .put(
+                "/registry/nodes/node-a",
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+        let pod = serde_json::json!({
+            "id": "p1", "name": "web", "namespace": "default",
+            "spec": { "containers": [] }, "status": "Running",
+            "node_name": "node-a", "created_at": Utc::now(),
+        });
+        state
+            .store
+            .put(
+                "/registry/pods/default/web",
+                &serde_json::to_vec(&pod).unwrap(),
+            )
+            .await
+            .unwrap();
+        let seeded = state.store.get("/registry/pods/default/web").await.unwrap();
+
+        // The agent restarts with more CPU.
+        let second = node_id(register(&state, request("10.0.0.5", 4000)).await).await;
+        assert_eq!(first, second);
+        let node = stored_node(&state).await;
+        assert_eq!(node.capacity.cpu_millis, 4000);
+        assert!(node.unschedulable);
+        // Not rewritten: still at the version it was stored with.
+        assert_eq!(
+            state.store.get("/registry/pods/default/web").await.unwrap(),
+            seeded
+        );
+
+        let mut bad = request("10.0.0.5", 4000);
+        bad.token = "wrong".to_string();
+        assert_eq!(
+            register(&state, bad).await.status(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registration_issues_agent_token() {
+        let state = test_state("register-agent-token").await;
+        let issued = |resp: Response| async move {
+            assert_eq!(resp.status(), StatusCode::OK);
+            serde_json::from_str::<NodeRegistrationResponse>(&body_text(resp).await)
+                .unwrap()
+                .agent_token
+                .unwrap()
+        };
+
+        let first = issued(register(&state, request("10.0.0.5", 2000)).await).await;
+        assert_eq!(
+            agent_token(&state, "node-a").await.unwrap().as_deref(),
+            Some(first.as_str())
+        );
+        // Not readable from the node object.
+        let node = state.store.get("/registry/nodes/node-a").await.unwrap();
+        assert!(!String::from_utf8(node.unwrap()).unwrap().contains(&first));
+
+        // Registering again replaces it.
+        let second = issued(register(&state, request("10.0.0.5", 2000)).await).await;
+        assert_ne!(first, second);
+        assert_eq!(
+            agent_token(&state, "node-a").await.unwrap().as_deref(),
+            Some(second.as_str())
+        );
+        assert_eq!(agent_token(&state, "node-b").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_address_conflict_policy() {
+        let mut state = test_state("register-conflict").await;
+        let id = node_id(register(&state, request("10.0.0.5", 2000)).await).await;
+
+        let resp = register(&state, request("10.0.0.9", 2000)).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert!(
+            api_error(resp)
+                .await
+                .message
+                .contains("already registered from 10.0.0.5")
+        );
+        assert_eq!(stored_node(&state).await.address, "10.0.0.5");
+
+        state.address_conflict = AddressConflictPolicy::TakeOver;
+        let taken = node_id(register(&state, request("10.0.0.9", 2000)).await).await;
+        assert_eq!(taken, id);
+        assert_eq!(stored_node(&state).await.address, "10.0.0.9");
+        let takeovers = state
+            .store
+            .event_log
+            .events_since(0)
+            .await
+            .into_iter()
+            .filter_map(|e| serde_json::from_slice::<Event>(e.value.as_deref()?).ok())
+            .filter(|e| e.reason == "AddressTakeover")
+            .count();
+        assert_eq!(takeovers, 1);
+
+        assert_eq!(
+            "take-over".parse::<AddressConflictPolicy>(),
+            Ok(AddressConflictPolicy::TakeOver)
+        );
+        assert!("allow".parse::<AddressConflictPolicy>().is_err());
+    }
+
+    async fn pod_cidr_of(state: &AppState, name: &str, address: &str) -> Option<String> {
+        let mut req = request(address, 2000);
+        req.node_name = name.to_string();
+        let resp = register(state, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        serde_json::from_str::<NodeRegistrationResponse>(&body_text(resp).await)
+            .unwrap()
+            .pod_cidr
+    }
+
+    #[tokio::test]
+    async fn test_pod_cidr_assignment() {
+        let state = test_state("register-pod-cidr").await;
+        let a = pod_cidr_of(&state, "node-a", "10.0.0.5").await;
+        let b = pod_cidr_of(&state, "node-b", "10.0.0.6").await;
+        assert_eq!(a.as_deref(), Some("10.42.0.0/24"));
+        assert_eq!(b.as_deref(), Some("10.42.1.0/24"));
+        assert_eq!(stored_node(&state).await.pod_cidr, a);
+
+        // Re-registering keeps the block, even once a lower one is free.
+        state.store.delete("/registry/nodes/node-a").await.unwrap();
+        assert_eq!(pod_cidr_of(&state, "node-b", "10.0.0.6").await, b);
+        assert_eq!(
+            pod_cidr_of(&state, "node-c", "10.0.0.7").await.as_deref(),
+            Some("10.42.0.0/24")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pod_cidr_exhausted() {
+        let mut state = test_state("register-pod-cidr-full").await;
+        state.cluster_cidr = ClusterCidr::new("10.42.0.0/24", 24).unwrap();
+        assert!(pod_cidr_of(&state, "node-a", "10.0.0.5").await.is_some());
+
+        // The node still joins, without a pod CIDR.
+        assert_eq!(pod_cidr_of(&state, "node-b", "10.0.0.6").await, None);
+        let unavailable = state
+            .store
+            .event_log
+            .events_since(0)
+            .await
+            .into_iter()
+            .filter_map(|e| serde_json::from_slice::<Event>(e.value.as_deref()?).ok())
+            .filter(|e| e.reason == "PodCIDRNotAvailable" && e.name == "node-b")
+            .count();
+        assert_eq!(unavailable, 1);
+    }
 }
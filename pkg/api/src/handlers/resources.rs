@@ -1,3 +1,4 @@
+use anyhow::Context;
 use axum::{
     Json,
     extract::{Path as AxumPath, Query, State},
@@ -5,11 +6,20 @@ use axum::{
     response::IntoResponse,
 };
 use chrono::Utc;
+use pkg_controllers::events;
+use pkg_state::client::{PutOutcome, resource_version};
+use pkg_types::apply::Apply;
+use pkg_types::defaults::Defaults;
+use pkg_types::error::{ApiError, ErrorReason, FieldError};
+use pkg_types::event::Event;
+use pkg_types::validate::Validate;
 use serde::Deserialize;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 use crate::AppState;
+use crate::error::{ApiResult, ErrorResponse};
+use crate::pagination::{Page, PageQuery, list_page};
 
 /// Query parameters for listing resources.
 #[derive(Debug, Deserialize)]
@@ -42,21 +52,26 @@ fn parse_field_selector(selector: &str) -> Vec<(&str, &str)> {
         .collect()
 }
 
-/// Apply a parsed field selector to a mutable pod list in-place.
-fn apply_pod_field_selector(pods: &mut Vec<pkg_types::pod::Pod>, selector: &str) {
-    for (field, value) in parse_field_selector(selector) {
+/// A predicate for the pods a field selector matches. Unsupported fields are
+/// logged once and ignored.
+fn pod_field_filter(selector: Option<&str>) -> impl Fn(&pkg_types::pod::Pod) -> bool {
+    let mut node_name = None;
+    let mut namespace = None;
+    for (field, value) in selector.map(parse_field_selector).unwrap_or_default() {
         match field {
-            "spec.nodeName" => {
-                pods.retain(|p| p.node_name.as_deref() == Some(value));
-            }
-            "metadata.namespace" => {
-                pods.retain(|p| p.namespace == value);
-            }
+            "spec.nodeName" => node_name = Some(value.to_string()),
+            "metadata.namespace" => namespace = Some(value.to_string()),
             unknown => {
                 warn!("Unsupported fieldSelector field '{}' — ignored", unknown);
             }
         }
     }
+    move |pod| {
+        node_name
+            .as_deref()
+            .is_none_or(|n| pod.node_name.as_deref() == Some(n))
+            && namespace.as_deref().is_none_or(|ns| pod.namespace == ns)
+    }
 }
 
 // ============================================================
@@ -66,37 +81,166 @@ fn apply_pod_field_selector(pods: &mut Vec<pkg_types::pod::Pod>, selector: &str)
 pub async fn create_namespace(
     State(state): State<AppState>,
     Json(mut ns): Json<pkg_types::namespace::Namespace>,
-) -> impl IntoResponse {
-    ns.created_at = Utc::now();
+) -> ApiResult {
     let key = format!("/registry/namespaces/{}", ns.name);
-    match serde_json::to_vec(&ns) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create namespace: {}", e);
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to create namespace",
-                )
-                    .into_response();
-            }
-            info!("Created namespace: {}", ns.name);
-            (StatusCode::CREATED, Json(ns)).into_response()
+    reject_resource_version("namespace", &ns.name, ns.resource_version)?;
+    admit(&state, "namespace", &ns.name, None, &ns).await?;
+    ns.created_at = Utc::now();
+    let data = serde_json::to_vec(&ns)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => ns.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("namespace", &ns.name, None).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created namespace: {}", ns.name);
+    Ok((StatusCode::CREATED, Json(ns)).into_response())
 }
 
-pub async fn list_namespaces(State(state): State<AppState>) -> impl IntoResponse {
-    let entries = state
-        .store
-        .list_prefix("/registry/namespaces/")
+pub async fn list_namespaces(
+    State(state): State<AppState>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::namespace::Namespace>> {
+    list_page(&state.store, "/registry/namespaces/", &page, |_| true).await
+}
+
+/// DELETE /api/v1/namespaces/:ns
+///
+/// Marks the namespace `Terminating` and deletes everything in it. Pods that
+/// may be running are stopped by their agents first; until they are gone the
+/// namespace stays `Terminating` (202) and the NamespaceController removes it
+/// afterwards. A namespace that empties right away is removed at once (204).
+pub async fn delete_namespace(
+    State(state): State<AppState>,
+    AxumPath(name): AxumPath<String>,
+) -> ApiResult {
+    use pkg_types::namespace::{Namespace, NamespacePhase};
+
+    if pkg_constants::network::SEED_NAMESPACES.contains(&name.as_str()) {
+        return Err(ApiError::new(
+            ErrorReason::Forbidden,
+            format!("namespace '{}' cannot be deleted", name),
+        )
+        .into());
+    }
+
+    let key = format!("/registry/namespaces/{}", name);
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("namespace", &name, None).into());
+    };
+    let mut ns: Namespace = serde_json::from_slice(&data)?;
+
+    if ns.phase != NamespacePhase::Terminating {
+        ns.phase = NamespacePhase::Terminating;
+        state
+            .store
+            .put(&key, &serde_json::to_vec(&ns)?)
+            .await
+            .with_context(|| format!("marking namespace {} Terminating", name))?;
+        info!("Namespace {} is Terminating", name);
+    }
+
+    let remaining = pkg_controllers::namespace::purge_namespace(&state.store, &name)
         .await
-        .unwrap_or_default();
-    let namespaces: Vec<pkg_types::namespace::Namespace> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(namespaces)).into_response()
+        .with_context(|| format!("emptying namespace {}", name))?;
+    if remaining > 0 {
+        info!("Namespace {}: waiting for {} pods to stop", name, remaining);
+        return Ok((StatusCode::ACCEPTED, Json(ns)).into_response());
+    }
+
+    state.store.delete(&key).await?;
+    info!("Deleted namespace {}", name);
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Reject creating objects in a namespace that is being deleted (409).
+pub(crate) async fn reject_if_terminating(state: &AppState, ns: &str) -> ApiResult<()> {
+    let key = format!("/registry/namespaces/{}", ns);
+    let Some(data) = state.store.get(&key).await? else {
+        return Ok(());
+    };
+    match serde_json::from_slice::<pkg_types::namespace::Namespace>(&data) {
+        Ok(namespace) if namespace.phase == pkg_types::namespace::NamespacePhase::Terminating => {
+            Err(ApiError::new(
+                ErrorReason::Conflict,
+                format!(
+                    "namespace '{}' is being terminated; no new objects can be created in it",
+                    ns
+                ),
+            )
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reject changes to the mirror of a static pod stored at `key` (403); its
+/// node owns it, and the pod is changed through its manifest there.
+async fn reject_if_mirror(state: &AppState, key: &str) -> ApiResult<()> {
+    let Some(data) = state.store.get(key).await? else {
+        return Ok(());
+    };
+    match serde_json::from_slice::<pkg_types::pod::Pod>(&data) {
+        Ok(pod) if pod.mirror => Err(ApiError::new(
+            ErrorReason::Forbidden,
+            format!(
+                "pod '{}' mirrors a static pod on node {}; edit its manifest on the node instead",
+                pod.name,
+                pod.node_name.as_deref().unwrap_or("<none>")
+            ),
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// Admission validation of `obj` (see [`Validate`]) and, for namespaced
+/// kinds, that its namespace `ns` exists. Every problem is reported at once,
+/// as a 422 Invalid with one detail per field. Callers fill in defaults
+/// (see [`Defaults`]) first, so what is validated is what gets stored.
+pub(crate) async fn admit<T: Validate>(
+    state: &AppState,
+    kind: &str,
+    name: &str,
+    ns: Option<&str>,
+    obj: &T,
+) -> ApiResult<()> {
+    let mut details = Vec::new();
+    if let Some(ns) = ns
+        && state
+            .store
+            .get(&format!("/registry/namespaces/{}", ns))
+            .await?
+            .is_none()
+    {
+        details.push(FieldError::new(
+            "namespace",
+            format!("namespace '{}' does not exist", ns),
+        ));
+    }
+    details.extend(obj.validate());
+    if details.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::invalid(kind, name, details).into())
+    }
+}
+
+/// Reject a create that carries a resource version: the caller read the
+/// object earlier and meant to update it.
+pub(crate) fn reject_resource_version(kind: &str, name: &str, version: u64) -> ApiResult<()> {
+    if version == 0 {
+        return Ok(());
+    }
+    Err(ApiError::invalid(
+        kind,
+        name,
+        vec![FieldError::new(
+            "resource_version",
+            "must not be set when creating an object; use PUT to update it",
+        )],
+    )
+    .into())
 }
 
 // ============================================================
@@ -107,10 +251,17 @@ pub async fn create_pod(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut pod): Json<pkg_types::pod::Pod>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/pods/{}/{}", ns, pod.name);
+    reject_resource_version("pod", &pod.name, pod.resource_version)?;
+    pod.default_fields();
+    admit(&state, "pod", &pod.name, Some(&ns), &pod).await?;
     pod.id = Uuid::new_v4().to_string();
     pod.namespace = ns.clone();
     pod.status = pkg_types::pod::PodStatus::Pending;
+    // Only agents create mirrors, through their node's mirror-pods endpoint
+    pod.mirror = false;
     pod.created_at = Utc::now();
 
     // Block pod creation in Terminating/Deleted VPCs
@@ -120,14 +271,37 @@ pub async fn create_pod(
         && let Ok(vpc) = serde_json::from_slice::<pkg_types::vpc::Vpc>(&vpc_data)
         && vpc.status != pkg_types::vpc::VpcStatus::Active
     {
-        return (
-            StatusCode::FORBIDDEN,
+        return Err(ApiError::new(
+            ErrorReason::Forbidden,
             format!(
                 "VPC '{}' is {} — cannot create new pods",
                 vpc_name, vpc.status
             ),
         )
-            .into_response();
+        .into());
+    }
+
+    // Resolve the pod's PriorityClass into a numeric priority
+    resolve_pod_priority(&state, &mut pod.spec).await?;
+
+    let limits = pkg_controllers::admission::apply_limit_ranges(&state.store, &mut pod)
+        .await
+        .context("applying limit ranges")?;
+    if let Err(details) = limits {
+        return Err(ApiError::invalid("pod", &pod.name, details).into());
+    }
+    let volumes = pkg_controllers::admission::pin_to_volumes(&state.store, &mut pod)
+        .await
+        .context("looking up claim volumes")?;
+    if let Err(msg) = volumes {
+        let details = vec![FieldError::new("spec.volumes", msg)];
+        return Err(ApiError::invalid("pod", &pod.name, details).into());
+    }
+    let quota = pkg_controllers::quota::admit_pod(&state.store, &pod)
+        .await
+        .context("checking resource quotas")?;
+    if let Err(msg) = quota {
+        return Err(ApiError::new(ErrorReason::QuotaExceeded, msg).into());
     }
 
     // Schedule the pod if scheduler is available
@@ -141,41 +315,59 @@ pub async fn create_pod(
             .into_iter()
             .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
             .collect();
-        if let Some(node_name) = scheduler.schedule(&pod, &nodes) {
+        let scheduled_pods: Vec<pkg_types::pod::Pod> = state
+            .store
+            .list_prefix("/registry/pods/")
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(_, v)| serde_json::from_slice::<pkg_types::pod::Pod>(&v).ok())
+            .filter(|p| p.node_name.is_some())
+            .collect();
+        if let Some(node_name) = scheduler.schedule(&pod, &nodes, &scheduled_pods) {
             pod.node_name = Some(node_name);
             pod.status = pkg_types::pod::PodStatus::Scheduled;
         }
     }
+    let scheduling_event = match pod.node_name {
+        Some(ref node_name) => Event::normal(
+            "pod",
+            &ns,
+            &pod.name,
+            "Scheduled",
+            format!("Successfully assigned {}/{} to {}", ns, pod.name, node_name),
+        ),
+        None if state.scheduler.is_some() => Event::warning(
+            "pod",
+            &ns,
+            &pod.name,
+            "FailedScheduling",
+            "no node satisfies the pod's resource, affinity and taint constraints",
+        ),
+        None => Event::normal("pod", &ns, &pod.name, "Created", "Pod created"),
+    };
 
-    let key = format!("/registry/pods/{}/{}", ns, pod.name);
-    match serde_json::to_vec(&pod) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create pod: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create pod").into_response();
-            }
-            info!("Created pod {}/{} (id={})", ns, pod.name, pod.id);
-            (StatusCode::CREATED, Json(pod)).into_response()
+    let data = serde_json::to_vec(&pod)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => pod.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("pod", &pod.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created pod {}/{} (id={})", ns, pod.name, pod.id);
+    events::record(&state.store, scheduling_event).await;
+    Ok((StatusCode::CREATED, Json(pod)).into_response())
 }
 
 pub async fn list_pods(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Query(query): Query<ListQuery>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::pod::Pod>> {
     let prefix = format!("/registry/pods/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let mut pods: Vec<pkg_types::pod::Pod> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    if let Some(ref selector) = query.field_selector {
-        apply_pod_field_selector(&mut pods, selector);
-    }
-    (StatusCode::OK, Json(pods)).into_response()
+    let keep = pod_field_filter(query.field_selector.as_deref());
+    list_page(&state.store, &prefix, &page, keep).await
 }
 
 /// GET /api/v1/pods — cluster-wide pod list with optional fieldSelector.
@@ -189,43 +381,31 @@ pub async fn list_pods(
 pub async fn list_all_pods(
     State(state): State<AppState>,
     Query(query): Query<ListQuery>,
-) -> impl IntoResponse {
-    let entries = state
-        .store
-        .list_prefix("/registry/pods/")
-        .await
-        .unwrap_or_default();
-    let mut pods: Vec<pkg_types::pod::Pod> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice::<pkg_types::pod::Pod>(&v).ok())
-        .collect();
-    if let Some(ref selector) = query.field_selector {
-        apply_pod_field_selector(&mut pods, selector);
-    }
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::pod::Pod>> {
+    let keep = pod_field_filter(query.field_selector.as_deref());
+    let pods = list_page(&state.store, "/registry/pods/", &page, keep).await?;
     debug!(
         "list_all_pods: fieldSelector={:?} → {} pods",
         query.field_selector,
-        pods.len()
+        pods.items.len()
     );
-    (StatusCode::OK, Json(pods)).into_response()
+    Ok(pods)
 }
 
 /// GET /api/v1/nodes/:name/pods — list all pods assigned to a specific node (across all namespaces).
 pub async fn list_node_pods(
     State(state): State<AppState>,
     AxumPath(node_name): AxumPath<String>,
-) -> impl IntoResponse {
-    let entries = state
-        .store
-        .list_prefix("/registry/pods/")
-        .await
-        .unwrap_or_default();
-    let pods: Vec<pkg_types::pod::Pod> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice::<pkg_types::pod::Pod>(&v).ok())
-        .filter(|p| p.node_name.as_deref() == Some(node_name.as_str()))
-        .collect();
-    (StatusCode::OK, Json(pods)).into_response()
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::pod::Pod>> {
+    list_page(
+        &state.store,
+        "/registry/pods/",
+        &page,
+        |p: &pkg_types::pod::Pod| p.node_name.as_deref() == Some(node_name.as_str()),
+    )
+    .await
 }
 
 pub async fn get_pod(
@@ -233,63 +413,184 @@ pub async fn get_pod(
     AxumPath((ns, pod_name)): AxumPath<(String, String)>,
 ) -> impl IntoResponse {
     let key = format!("/registry/pods/{}/{}", ns, pod_name);
-    match state.store.get(&key).await {
-        Ok(Some(data)) => {
-            if let Ok(pod) = serde_json::from_slice::<pkg_types::pod::Pod>(&data) {
-                return (StatusCode::OK, Json(pod)).into_response();
-            }
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
-        }
-        Ok(None) => StatusCode::NOT_FOUND.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    get_object::<pkg_types::pod::Pod>(&state, &key, "pod", &pod_name, Some(&ns)).await
+}
+
+/// Query parameters for deleting a pod.
+#[derive(Debug, Default, Deserialize)]
+pub struct DeletePodQuery {
+    /// Remove the record now instead of waiting for the agent to stop the
+    /// containers. The agent sets this once termination has finished.
+    #[serde(default)]
+    pub force: bool,
 }
 
+/// DELETE /api/v1/namespaces/:ns/pods/:name[?force=true]
 pub async fn delete_pod(
     State(state): State<AppState>,
     AxumPath((ns, pod_name)): AxumPath<(String, String)>,
-) -> impl IntoResponse {
+    Query(query): Query<DeletePodQuery>,
+) -> ApiResult {
+    terminate_pod(&state, &ns, &pod_name, query.force).await
+}
+
+/// Delete a pod, gracefully when it may have running containers.
+///
+/// A pod bound to a node that may have started its containers is marked
+/// `Terminating` (202); its agent stops the containers within the grace
+/// period and then force-deletes the record. Anything else — unbound,
+/// finished, a static pod's mirror, or `force` — is removed right away (204).
+pub(crate) async fn terminate_pod(
+    state: &AppState,
+    ns: &str,
+    pod_name: &str,
+    force: bool,
+) -> ApiResult {
+    use pkg_types::pod::PodStatus;
+
     let key = format!("/registry/pods/{}/{}", ns, pod_name);
-    match state.store.delete(&key).await {
-        Ok(_) => {
-            info!("Deleted pod {}/{}", ns, pod_name);
-            StatusCode::NO_CONTENT.into_response()
+    let pod = state
+        .store
+        .get(&key)
+        .await?
+        .and_then(|d| serde_json::from_slice::<pkg_types::pod::Pod>(&d).ok());
+
+    // A mirror goes right away: its node keeps running the static pod.
+    if let Some(mut pod) = pod
+        && !force
+        && !pod.mirror
+        && pod.node_name.is_some()
+        && matches!(
+            pod.status,
+            PodStatus::Scheduled
+                | PodStatus::ContainerCreating
+                | PodStatus::Running
+                | PodStatus::Terminating
+        )
+    {
+        if pod.status != PodStatus::Terminating {
+            pod.status = PodStatus::Terminating;
+            state
+                .store
+                .put(&key, &serde_json::to_vec(&pod)?)
+                .await
+                .with_context(|| format!("marking pod {}/{} Terminating", ns, pod_name))?;
+            info!("Pod {}/{} is Terminating", ns, pod_name);
+            events::record(
+                &state.store,
+                Event::normal(
+                    "pod",
+                    ns,
+                    pod_name,
+                    "Killing",
+                    format!(
+                        "Stopping containers (grace period {}s)",
+                        pod.spec.termination_grace_period_seconds.unwrap_or(
+                            pkg_constants::runtime::DEFAULT_TERMINATION_GRACE_PERIOD_SECS
+                        )
+                    ),
+                ),
+            )
+            .await;
         }
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        return Ok((StatusCode::ACCEPTED, Json(pod)).into_response());
     }
+
+    state.store.delete(&key).await?;
+    info!("Deleted pod {}/{}", ns, pod_name);
+    Ok(StatusCode::NO_CONTENT.into_response())
 }
 
 pub async fn update_pod_status(
     State(state): State<AppState>,
     AxumPath((ns, pod_name)): AxumPath<(String, String)>,
-    Json(status): Json<pkg_types::pod::PodStatus>,
-) -> impl IntoResponse {
+    Json(update): Json<pkg_types::pod::PodStatusUpdate>,
+) -> ApiResult {
+    use pkg_types::pod::PodStatusUpdate;
+
     debug!(
-        "DEBUG: update_pod_status hit for {}/{} with status {:?}",
-        ns, pod_name, status
+        "DEBUG: update_pod_status hit for {}/{} with {:?}",
+        ns, pod_name, update
     );
     let key = format!("/registry/pods/{}/{}", ns, pod_name);
-    match state.store.get(&key).await {
-        Ok(Some(data)) => match serde_json::from_slice::<pkg_types::pod::Pod>(&data) {
-            Ok(mut pod) => {
-                pod.status = status;
-                if let Ok(new_data) = serde_json::to_vec(&pod) {
-                    if let Err(e) = state.store.put(&key, &new_data).await {
-                        warn!("Failed to update pod status: {}", e);
-                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-                    }
-                    info!("Updated pod status {}/{} to {:?}", ns, pod_name, pod.status);
-                    return (StatusCode::OK, Json(pod)).into_response();
-                }
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("pod", &pod_name, Some(&ns)).into());
+    };
+    let mut pod: pkg_types::pod::Pod = serde_json::from_slice(&data)?;
+    // A late report from a pod being torn down must not bring it back.
+    if pod.status == pkg_types::pod::PodStatus::Terminating {
+        return Ok((StatusCode::OK, Json(pod)).into_response());
+    }
+    let changed = match update {
+        PodStatusUpdate::Status(status) => {
+            let changed = pod.status != status;
+            // A bare phase change makes the old reason stale.
+            if changed {
+                pod.status_message = None;
+                pod.ready = false;
             }
-            Err(e) => {
-                warn!("Failed to deserialize pod {}/{}: {}", ns, pod_name, e);
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            pod.status = status;
+            changed
+        }
+        PodStatusUpdate::Detailed {
+            status,
+            status_message,
+            restart_count,
+            ready,
+            pod_ip,
+            container_statuses,
+        } => {
+            let changed = pod.status != status || pod.status_message != status_message;
+            if pod.status != status {
+                pod.ready = false;
             }
-        },
-        Ok(None) => StatusCode::NOT_FOUND.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            pod.status = status;
+            pod.status_message = status_message;
+            if let Some(count) = restart_count {
+                pod.restart_count = count;
+            }
+            if let Some(ready) = ready
+                && pod.status == pkg_types::pod::PodStatus::Running
+            {
+                pod.ready = ready;
+            }
+            if pod_ip.is_some() {
+                pod.pod_ip = pod_ip;
+            }
+            if let Some(statuses) = container_statuses {
+                pod.container_statuses = statuses;
+            }
+            changed
+        }
+    };
+    state
+        .store
+        .put(&key, &serde_json::to_vec(&pod)?)
+        .await
+        .context("updating pod status")?;
+    info!("Updated pod status {}/{} to {:?}", ns, pod_name, pod.status);
+    if changed {
+        events::record(&state.store, pod_status_event(&pod)).await;
+    }
+    Ok((StatusCode::OK, Json(pod)).into_response())
+}
+
+/// Event recorded when the agent reports a new pod phase.
+fn pod_status_event(pod: &pkg_types::pod::Pod) -> Event {
+    use pkg_types::pod::PodStatus;
+    if let Some(msg) = pod.status_message.as_deref()
+        && msg.starts_with(pkg_types::pod::CRASH_LOOP_BACK_OFF)
+    {
+        return Event::warning("pod", &pod.namespace, &pod.name, "BackOff", msg);
+    }
+    let reason = pod.status.to_string();
+    let message = match pod.status_message {
+        Some(ref msg) => format!("Pod is {}: {}", pod.status, msg),
+        None => format!("Pod is {}", pod.status),
+    };
+    match pod.status {
+        PodStatus::Failed => Event::warning("pod", &pod.namespace, &pod.name, &reason, message),
+        _ => Event::normal("pod", &pod.namespace, &pod.name, &reason, message),
     }
 }
 
@@ -303,45 +604,108 @@ pub async fn update_pod_vpc(
     State(state): State<AppState>,
     AxumPath((ns, pod_name)): AxumPath<(String, String)>,
     Json(vpc_info): Json<PodVpcUpdate>,
-) -> impl IntoResponse {
+) -> ApiResult {
     let key = format!("/registry/pods/{}/{}", ns, pod_name);
-    match state.store.get(&key).await {
-        Ok(Some(data)) => match serde_json::from_slice::<pkg_types::pod::Pod>(&data) {
-            Ok(mut pod) => {
-                pod.ghost_ipv6 = Some(vpc_info.ghost_ipv6);
-                pod.vpc_name = Some(vpc_info.vpc_name);
-                if let Ok(new_data) = serde_json::to_vec(&pod) {
-                    if let Err(e) = state.store.put(&key, &new_data).await {
-                        warn!("Failed to update pod VPC info: {}", e);
-                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-                    }
-                    info!(
-                        "Updated pod VPC info {}/{}: ghost_ipv6={:?}",
-                        ns, pod_name, pod.ghost_ipv6
-                    );
-                    return (StatusCode::OK, Json(pod)).into_response();
-                }
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
-            }
-            Err(e) => {
-                warn!("Failed to deserialize pod {}/{}: {}", ns, pod_name, e);
-                StatusCode::INTERNAL_SERVER_ERROR.into_response()
-            }
-        },
-        Ok(None) => StatusCode::NOT_FOUND.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("pod", &pod_name, Some(&ns)).into());
+    };
+    let mut pod: pkg_types::pod::Pod = serde_json::from_slice(&data)?;
+    pod.ghost_ipv6 = Some(vpc_info.ghost_ipv6);
+    pod.vpc_name = Some(vpc_info.vpc_name);
+    state
+        .store
+        .put(&key, &serde_json::to_vec(&pod)?)
+        .await
+        .context("updating pod VPC info")?;
+    info!(
+        "Updated pod VPC info {}/{}: ghost_ipv6={:?}",
+        ns, pod_name, pod.ghost_ipv6
+    );
+    Ok((StatusCode::OK, Json(pod)).into_response())
 }
 
 // ============================================================
 // Services
 // ============================================================
 
+/// Give every port of a NodePort service a node port: explicit ones must be
+/// free and in range, ports the stored service (`existing`) already had are
+/// kept, and the rest are allocated. Other service types take no node ports.
+async fn assign_node_ports(
+    state: &AppState,
+    ns: &str,
+    name: &str,
+    svc: &mut pkg_types::service::Service,
+    existing: Option<&pkg_types::service::Service>,
+) -> ApiResult<()> {
+    use crate::node_ports::{NodePortAllocator, NodePortError};
+    use pkg_types::service::ServiceType;
+
+    if !matches!(svc.spec.service_type, ServiceType::NodePort) {
+        if let Some(p) = svc.spec.ports.iter().find(|p| p.node_port.is_some()) {
+            return Err(ApiError::new(
+                ErrorReason::BadRequest,
+                format!(
+                    "port '{}' sets node_port, but service type is {}",
+                    p.name, svc.spec.service_type
+                ),
+            )
+            .into());
+        }
+        return Ok(());
+    }
+
+    if let Some(existing) =
+        existing.filter(|e| matches!(e.spec.service_type, ServiceType::NodePort))
+    {
+        for port in svc.spec.ports.iter_mut().filter(|p| p.node_port.is_none()) {
+            port.node_port = existing
+                .spec
+                .ports
+                .iter()
+                .find(|p| p.port == port.port)
+                .and_then(|p| p.node_port);
+        }
+    }
+
+    let own_key = format!("/registry/services/{}/{}", ns, name);
+    let used = state
+        .store
+        .list_prefix("/registry/services/")
+        .await
+        .context("listing services for node port allocation")?
+        .into_iter()
+        .filter(|(k, _)| *k != own_key)
+        .filter_map(|(_, v)| serde_json::from_slice::<pkg_types::service::Service>(&v).ok())
+        .flat_map(|s| s.spec.ports.into_iter().filter_map(|p| p.node_port))
+        .collect::<Vec<_>>();
+    let mut allocator = NodePortAllocator::new(state.node_port_range, used);
+    let to_error = |e: NodePortError| {
+        let reason = match e {
+            NodePortError::OutOfRange { .. } => ErrorReason::BadRequest,
+            NodePortError::InUse(_) | NodePortError::Exhausted(_) => ErrorReason::Conflict,
+        };
+        ErrorResponse(ApiError::new(reason, e.to_string()))
+    };
+    for port in svc.spec.ports.iter().filter_map(|p| p.node_port) {
+        allocator.reserve(port).map_err(to_error)?;
+    }
+    for port in svc.spec.ports.iter_mut().filter(|p| p.node_port.is_none()) {
+        port.node_port = Some(allocator.allocate().map_err(to_error)?);
+    }
+    Ok(())
+}
+
 pub async fn create_service(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut svc): Json<pkg_types::service::Service>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/services/{}/{}", ns, svc.name);
+    reject_resource_version("service", &svc.name, svc.resource_version)?;
+    svc.default_fields();
+    admit(&state, "service", &svc.name, Some(&ns), &svc).await?;
     svc.id = Uuid::new_v4().to_string();
     svc.namespace = ns.clone();
     svc.created_at = Utc::now();
@@ -349,6 +713,8 @@ pub async fn create_service(
     if svc.vpc.is_none() {
         svc.vpc = Some(pkg_constants::network::DEFAULT_VPC_NAME.to_string());
     }
+    let name = svc.name.clone();
+    assign_node_ports(&state, &ns, &name, &mut svc, None).await?;
     // Assign a cluster IP (simple increment for now)
     if svc.cluster_ip.is_none() {
         svc.cluster_ip = Some(format!(
@@ -358,31 +724,38 @@ pub async fn create_service(
         ));
     }
 
-    let key = format!("/registry/services/{}/{}", ns, svc.name);
-    match serde_json::to_vec(&svc) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create service: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created service {}/{}", ns, svc.name);
-            (StatusCode::CREATED, Json(svc)).into_response()
+    let data = serde_json::to_vec(&svc)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => svc.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("service", &svc.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created service {}/{}", ns, svc.name);
+    events::record(
+        &state.store,
+        Event::normal(
+            "service",
+            &ns,
+            &svc.name,
+            "ClusterIPAllocated",
+            format!(
+                "Allocated cluster IP {}",
+                svc.cluster_ip.as_deref().unwrap_or("<none>")
+            ),
+        ),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(svc)).into_response())
 }
 
 pub async fn list_services(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::service::Service>> {
     let prefix = format!("/registry/services/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let svcs: Vec<pkg_types::service::Service> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(svcs)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -393,36 +766,34 @@ pub async fn create_deployment(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut deploy): Json<pkg_types::deployment::Deployment>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/deployments/{}/{}", ns, deploy.name);
+    reject_resource_version("deployment", &deploy.name, deploy.resource_version)?;
+    deploy.default_fields();
+    admit(&state, "deployment", &deploy.name, Some(&ns), &deploy).await?;
     deploy.id = Uuid::new_v4().to_string();
     deploy.namespace = ns.clone();
     deploy.created_at = Utc::now();
 
-    let key = format!("/registry/deployments/{}/{}", ns, deploy.name);
-    match serde_json::to_vec(&deploy) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create deployment: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created deployment {}/{}", ns, deploy.name);
-            (StatusCode::CREATED, Json(deploy)).into_response()
+    let data = serde_json::to_vec(&deploy)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => deploy.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("deployment", &deploy.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created deployment {}/{}", ns, deploy.name);
+    Ok((StatusCode::CREATED, Json(deploy)).into_response())
 }
 
 pub async fn list_deployments(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::deployment::Deployment>> {
     let prefix = format!("/registry/deployments/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let deploys: Vec<pkg_types::deployment::Deployment> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(deploys)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -433,35 +804,33 @@ pub async fn create_configmap(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut cm): Json<pkg_types::configmap::ConfigMap>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/configmaps/{}/{}", ns, cm.name);
+    reject_resource_version("configmap", &cm.name, cm.resource_version)?;
+    admit(&state, "configmap", &cm.name, Some(&ns), &cm).await?;
     cm.id = Uuid::new_v4().to_string();
     cm.namespace = ns.clone();
     cm.created_at = Utc::now();
 
-    let key = format!("/registry/configmaps/{}/{}", ns, cm.name);
-    match serde_json::to_vec(&cm) {
-        Ok(data) => {
-            if let Err(_e) = state.store.put(&key, &data).await {
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created configmap {}/{}", ns, cm.name);
-            (StatusCode::CREATED, Json(cm)).into_response()
+    let data = serde_json::to_vec(&cm)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => cm.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("configmap", &cm.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created configmap {}/{}", ns, cm.name);
+    Ok((StatusCode::CREATED, Json(cm)).into_response())
 }
 
 pub async fn list_configmaps(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::configmap::ConfigMap>> {
     let prefix = format!("/registry/configmaps/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let cms: Vec<pkg_types::configmap::ConfigMap> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(cms)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -472,35 +841,33 @@ pub async fn create_secret(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut secret): Json<pkg_types::secret::Secret>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/secrets/{}/{}", ns, secret.name);
+    reject_resource_version("secret", &secret.name, secret.resource_version)?;
+    admit(&state, "secret", &secret.name, Some(&ns), &secret).await?;
     secret.id = Uuid::new_v4().to_string();
     secret.namespace = ns.clone();
     secret.created_at = Utc::now();
 
-    let key = format!("/registry/secrets/{}/{}", ns, secret.name);
-    match serde_json::to_vec(&secret) {
-        Ok(data) => {
-            if let Err(_e) = state.store.put(&key, &data).await {
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created secret {}/{}", ns, secret.name);
-            (StatusCode::CREATED, Json(secret)).into_response()
+    let data = serde_json::to_vec(&secret)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => secret.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("secret", &secret.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created secret {}/{}", ns, secret.name);
+    Ok((StatusCode::CREATED, Json(secret)).into_response())
 }
 
 pub async fn list_secrets(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::secret::Secret>> {
     let prefix = format!("/registry/secrets/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let secrets: Vec<pkg_types::secret::Secret> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(secrets)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -510,7 +877,11 @@ pub async fn list_secrets(
 pub async fn delete_resource(
     State(state): State<AppState>,
     AxumPath((resource_type, ns, name)): AxumPath<(String, String, String)>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    if resource_type == "pods" {
+        return terminate_pod(&state, &ns, &name, false).await;
+    }
+
     let key = format!("/registry/{}/{}/{}", resource_type, ns, name);
 
     // Before deleting, read the resource to get its ID for cascading
@@ -548,20 +919,16 @@ pub async fn delete_resource(
     }
 
     // Delete the resource itself
-    match state.store.delete(&key).await {
-        Ok(_) => {
-            if cascade_count > 0 {
-                info!(
-                    "Deleted {}/{}/{} (cascade: {} resources)",
-                    resource_type, ns, name, cascade_count
-                );
-            } else {
-                info!("Deleted {}/{}/{}", resource_type, ns, name);
-            }
-            StatusCode::NO_CONTENT.into_response()
-        }
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    state.store.delete(&key).await?;
+    if cascade_count > 0 {
+        info!(
+            "Deleted {}/{}/{} (cascade: {} resources)",
+            resource_type, ns, name, cascade_count
+        );
+    } else {
+        info!("Deleted {}/{}/{}", resource_type, ns, name);
     }
+    Ok(StatusCode::NO_CONTENT.into_response())
 }
 
 /// Delete all ReplicaSets owned by a deployment, and their owned Pods.
@@ -605,7 +972,7 @@ async fn cascade_delete_owned_pods(state: &AppState, ns: &str, owner_id: &str) -
 }
 
 // ============================================================
-// Deployments — GET single & PUT update
+// Deployments — GET single
 // ============================================================
 
 pub async fn get_deployment(
@@ -613,47 +980,14 @@ pub async fn get_deployment(
     AxumPath((ns, deploy_name)): AxumPath<(String, String)>,
 ) -> impl IntoResponse {
     let key = format!("/registry/deployments/{}/{}", ns, deploy_name);
-    match state.store.get(&key).await {
-        Ok(Some(data)) => {
-            if let Ok(deploy) = serde_json::from_slice::<pkg_types::deployment::Deployment>(&data) {
-                return (StatusCode::OK, Json(deploy)).into_response();
-            }
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
-        }
-        Ok(None) => StatusCode::NOT_FOUND.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
-}
-
-pub async fn update_deployment(
-    State(state): State<AppState>,
-    AxumPath((ns, deploy_name)): AxumPath<(String, String)>,
-    Json(mut deploy): Json<pkg_types::deployment::Deployment>,
-) -> impl IntoResponse {
-    let key = format!("/registry/deployments/{}/{}", ns, deploy_name);
-    match state.store.get(&key).await {
-        Ok(Some(existing_data)) => {
-            if let Ok(existing) =
-                serde_json::from_slice::<pkg_types::deployment::Deployment>(&existing_data)
-            {
-                deploy.id = existing.id;
-                deploy.namespace = ns.clone();
-                deploy.created_at = existing.created_at;
-                deploy.generation = existing.generation + 1;
-                if let Ok(data) = serde_json::to_vec(&deploy) {
-                    if let Err(e) = state.store.put(&key, &data).await {
-                        warn!("Failed to update deployment: {}", e);
-                        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-                    }
-                    info!("Updated deployment {}/{}", ns, deploy_name);
-                    return (StatusCode::OK, Json(deploy)).into_response();
-                }
-            }
-            StatusCode::INTERNAL_SERVER_ERROR.into_response()
-        }
-        Ok(None) => StatusCode::NOT_FOUND.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-    }
+    get_object::<pkg_types::deployment::Deployment>(
+        &state,
+        &key,
+        "deployment",
+        &deploy_name,
+        Some(&ns),
+    )
+    .await
 }
 
 // ============================================================
@@ -664,36 +998,34 @@ pub async fn create_replicaset(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut rs): Json<pkg_types::replicaset::ReplicaSet>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/replicasets/{}/{}", ns, rs.name);
+    reject_resource_version("replicaset", &rs.name, rs.resource_version)?;
+    rs.default_fields();
+    admit(&state, "replicaset", &rs.name, Some(&ns), &rs).await?;
     rs.id = Uuid::new_v4().to_string();
     rs.namespace = ns.clone();
     rs.created_at = Utc::now();
 
-    let key = format!("/registry/replicasets/{}/{}", ns, rs.name);
-    match serde_json::to_vec(&rs) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create replicaset: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created replicaset {}/{}", ns, rs.name);
-            (StatusCode::CREATED, Json(rs)).into_response()
+    let data = serde_json::to_vec(&rs)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => rs.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("replicaset", &rs.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created replicaset {}/{}", ns, rs.name);
+    Ok((StatusCode::CREATED, Json(rs)).into_response())
 }
 
 pub async fn list_replicasets(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::replicaset::ReplicaSet>> {
     let prefix = format!("/registry/replicasets/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let items: Vec<pkg_types::replicaset::ReplicaSet> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(items)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -704,36 +1036,34 @@ pub async fn create_daemonset(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut ds): Json<pkg_types::daemonset::DaemonSet>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/daemonsets/{}/{}", ns, ds.name);
+    reject_resource_version("daemonset", &ds.name, ds.resource_version)?;
+    ds.default_fields();
+    admit(&state, "daemonset", &ds.name, Some(&ns), &ds).await?;
     ds.id = Uuid::new_v4().to_string();
     ds.namespace = ns.clone();
     ds.created_at = Utc::now();
 
-    let key = format!("/registry/daemonsets/{}/{}", ns, ds.name);
-    match serde_json::to_vec(&ds) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create daemonset: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created daemonset {}/{}", ns, ds.name);
-            (StatusCode::CREATED, Json(ds)).into_response()
+    let data = serde_json::to_vec(&ds)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => ds.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("daemonset", &ds.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created daemonset {}/{}", ns, ds.name);
+    Ok((StatusCode::CREATED, Json(ds)).into_response())
 }
 
 pub async fn list_daemonsets(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::daemonset::DaemonSet>> {
     let prefix = format!("/registry/daemonsets/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let items: Vec<pkg_types::daemonset::DaemonSet> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(items)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -744,36 +1074,34 @@ pub async fn create_job(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut job): Json<pkg_types::job::Job>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/jobs/{}/{}", ns, job.name);
+    reject_resource_version("job", &job.name, job.resource_version)?;
+    job.default_fields();
+    admit(&state, "job", &job.name, Some(&ns), &job).await?;
     job.id = Uuid::new_v4().to_string();
     job.namespace = ns.clone();
     job.created_at = Utc::now();
 
-    let key = format!("/registry/jobs/{}/{}", ns, job.name);
-    match serde_json::to_vec(&job) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create job: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created job {}/{}", ns, job.name);
-            (StatusCode::CREATED, Json(job)).into_response()
+    let data = serde_json::to_vec(&job)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => job.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("job", &job.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created job {}/{}", ns, job.name);
+    Ok((StatusCode::CREATED, Json(job)).into_response())
 }
 
 pub async fn list_jobs(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::job::Job>> {
     let prefix = format!("/registry/jobs/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let items: Vec<pkg_types::job::Job> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(items)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -784,36 +1112,34 @@ pub async fn create_cronjob(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut cj): Json<pkg_types::job::CronJob>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/cronjobs/{}/{}", ns, cj.name);
+    reject_resource_version("cronjob", &cj.name, cj.resource_version)?;
+    cj.default_fields();
+    admit(&state, "cronjob", &cj.name, Some(&ns), &cj).await?;
     cj.id = Uuid::new_v4().to_string();
     cj.namespace = ns.clone();
     cj.created_at = Utc::now();
 
-    let key = format!("/registry/cronjobs/{}/{}", ns, cj.name);
-    match serde_json::to_vec(&cj) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create cronjob: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created cronjob {}/{}", ns, cj.name);
-            (StatusCode::CREATED, Json(cj)).into_response()
+    let data = serde_json::to_vec(&cj)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => cj.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("cronjob", &cj.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created cronjob {}/{}", ns, cj.name);
+    Ok((StatusCode::CREATED, Json(cj)).into_response())
 }
 
 pub async fn list_cronjobs(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::job::CronJob>> {
     let prefix = format!("/registry/cronjobs/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let items: Vec<pkg_types::job::CronJob> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(items)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -824,36 +1150,33 @@ pub async fn create_hpa(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut hpa): Json<pkg_types::hpa::HorizontalPodAutoscaler>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/hpa/{}/{}", ns, hpa.name);
+    reject_resource_version("hpa", &hpa.name, hpa.resource_version)?;
+    admit(&state, "hpa", &hpa.name, Some(&ns), &hpa).await?;
     hpa.id = Uuid::new_v4().to_string();
     hpa.namespace = ns.clone();
     hpa.created_at = Utc::now();
 
-    let key = format!("/registry/hpa/{}/{}", ns, hpa.name);
-    match serde_json::to_vec(&hpa) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create HPA: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created HPA {}/{}", ns, hpa.name);
-            (StatusCode::CREATED, Json(hpa)).into_response()
+    let data = serde_json::to_vec(&hpa)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => hpa.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("hpa", &hpa.name, Some(&ns)).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created HPA {}/{}", ns, hpa.name);
+    Ok((StatusCode::CREATED, Json(hpa)).into_response())
 }
 
 pub async fn list_hpas(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::hpa::HorizontalPodAutoscaler>> {
     let prefix = format!("/registry/hpa/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let items: Vec<pkg_types::hpa::HorizontalPodAutoscaler> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(items)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -867,24 +1190,104 @@ pub struct PodLogResponse {
     pub logs: Vec<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PodLogQuery {
+    /// Container to read; defaults to the pod's first container.
+    pub container: Option<String>,
+    /// Stream new lines as they are written instead of returning a snapshot.
+    #[serde(default)]
+    pub follow: bool,
+    /// Only return lines written at or after this RFC3339 time.
+    pub since: Option<chrono::DateTime<Utc>>,
+    /// Only return the last N lines of the existing log.
+    pub tail: Option<usize>,
+    /// Keep the RFC3339 timestamp prefix on each line.
+    #[serde(default)]
+    pub timestamps: bool,
+}
+
+impl PodLogQuery {
+    /// Query string forwarded to the agent's log endpoint.
+    fn agent_query(&self) -> String {
+        let mut params = vec![
+            format!("follow={}", self.follow),
+            format!("timestamps={}", self.timestamps),
+        ];
+        if let Some(since) = self.since {
+            params.push(format!(
+                "since={}",
+                since.to_rfc3339_opts(chrono::SecondsFormat::Nanos, true)
+            ));
+        }
+        if let Some(tail) = self.tail {
+            params.push(format!("tail={}", tail));
+        }
+        params.join("&")
+    }
+}
+
+/// GET /api/v1/namespaces/:ns/pods/:name/logs — fetch logs from the pod's agent.
+///
+/// `container` picks one of the pod's containers (default: the first). `since`, `tail` and `timestamps` are forwarded to the agent, which applies
+/// them. Without `follow` the agent's output is collected into a
+/// `PodLogResponse`. With `follow=true` the agent's chunked `text/plain`
+/// stream is relayed as-is.
 pub async fn pod_logs(
     State(state): State<AppState>,
     AxumPath((ns, pod_name)): AxumPath<(String, String)>,
-) -> impl IntoResponse {
-    let key = format!("/registry/pods/{}/{}", ns, pod_name);
-    match state.store.get(&key).await {
-        Ok(Some(_)) => {
-            // Pod logs are available from the Agent node where the pod is running.
-            // The server (control plane) does not have access to container logs.
-            (
-                StatusCode::NOT_IMPLEMENTED,
-                "Pod logs are available from the Agent node where the pod is running.",
-            )
-                .into_response()
-        }
-        Ok(None) => StatusCode::NOT_FOUND.into_response(),
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    Query(query): Query<PodLogQuery>,
+) -> ApiResult {
+    let target =
+        super::agent_proxy::locate_container(&state, &ns, &pod_name, query.container.as_deref())
+            .await?;
+
+    let agent_url = reqwest::Url::parse(&format!(
+        "{}/containers/{}/logs?{}",
+        target.base_url(),
+        target.container_id,
+        query.agent_query()
+    ))
+    .map_err(|e| ApiError::internal(e.to_string()))?;
+    debug!("Proxying pod logs {}/{} → {}", ns, pod_name, agent_url);
+
+    let resp = target
+        .send(
+            axum::http::Method::GET,
+            &agent_url,
+            axum::body::Body::empty(),
+        )
+        .await?;
+    if !resp.status().is_success() {
+        return Err(target.failed(resp).await);
+    }
+
+    if query.follow {
+        return Ok((
+            [(
+                axum::http::header::CONTENT_TYPE,
+                "text/plain; charset=utf-8",
+            )],
+            axum::body::Body::from_stream(super::agent_proxy::body_stream(resp)),
+        )
+            .into_response());
     }
+
+    let body = axum::body::Body::new(resp.into_body());
+    let text = axum::body::to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| target.unreachable(format!("failed to read logs: {}", e)))?;
+    Ok((
+        StatusCode::OK,
+        Json(PodLogResponse {
+            pod_name,
+            namespace: ns,
+            logs: String::from_utf8_lossy(&text)
+                .lines()
+                .map(String::from)
+                .collect(),
+        }),
+    )
+        .into_response())
 }
 
 // ============================================================
@@ -895,74 +1298,176 @@ pub async fn create_resource_quota(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut quota): Json<pkg_types::quota::ResourceQuota>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/resourcequotas/{}/{}", ns, quota.name);
+    admit(&state, "resourcequota", &quota.name, Some(&ns), &quota).await?;
     quota.namespace = ns.clone();
+    quota.used = Default::default();
     quota.created_at = Utc::now();
 
-    let key = format!("/registry/resourcequotas/{}/{}", ns, quota.name);
-    match serde_json::to_vec(&quota) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create resource quota: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created resource quota {}/{}", ns, quota.name);
-            (StatusCode::CREATED, Json(quota)).into_response()
-        }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
+    let data = serde_json::to_vec(&quota)?;
+    if let PutOutcome::Conflict(_) = state.store.create(&key, &data).await? {
+        return Err(ApiError::already_exists("resourcequota", &quota.name, Some(&ns)).into());
     }
+    info!("Created resource quota {}/{}", ns, quota.name);
+    Ok((StatusCode::CREATED, Json(quota)).into_response())
 }
 
+/// Quotas are listed with `used` computed from the namespace's current pods.
 pub async fn list_resource_quotas(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
-    let prefix = format!("/registry/resourcequotas/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let items: Vec<pkg_types::quota::ResourceQuota> = entries
+) -> ApiResult<Json<Vec<pkg_types::quota::ResourceQuota>>> {
+    let items = pkg_controllers::quota::quota_status(&state.store, &ns)
+        .await
+        .context("computing quota usage")?;
+    Ok(Json(items))
+}
+
+/// GET /api/v1/namespaces/{ns}/resourcequotas/{name} — used vs hard.
+pub async fn get_resource_quota(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> ApiResult<Json<pkg_types::quota::ResourceQuota>> {
+    pkg_controllers::quota::quota_status(&state.store, &ns)
+        .await
+        .context("computing quota usage")?
         .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(items)).into_response()
+        .find(|q| q.name == name)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("resourcequota", &name, Some(&ns)).into())
 }
 
 // ============================================================
-// Network Policies
+// Limit Ranges
+// ============================================================
+
+pub async fn create_limit_range(
+    State(state): State<AppState>,
+    AxumPath(ns): AxumPath<String>,
+    Json(mut range): Json<pkg_types::limit_range::LimitRange>,
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/limitranges/{}/{}", ns, range.name);
+    reject_resource_version("limitrange", &range.name, range.resource_version)?;
+    admit(&state, "limitrange", &range.name, Some(&ns), &range).await?;
+    range.namespace = ns.clone();
+    range.created_at = Utc::now();
+
+    let data = serde_json::to_vec(&range)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => range.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("limitrange", &range.name, Some(&ns)).into());
+        }
+    }
+    info!("Created limit range {}/{}", ns, range.name);
+    Ok((StatusCode::CREATED, Json(range)).into_response())
+}
+
+pub async fn list_limit_ranges(
+    State(state): State<AppState>,
+    AxumPath(ns): AxumPath<String>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::limit_range::LimitRange>> {
+    let prefix = format!("/registry/limitranges/{}/", ns);
+    list_page(&state.store, &prefix, &page, |_| true).await
+}
+
+pub async fn get_limit_range(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/limitranges/{}/{}", ns, name);
+    get_object::<pkg_types::limit_range::LimitRange>(&state, &key, "limitrange", &name, Some(&ns))
+        .await
+}
+
+// ============================================================
+// Pod Disruption Budgets
+// ============================================================
+
+pub async fn create_pod_disruption_budget(
+    State(state): State<AppState>,
+    AxumPath(ns): AxumPath<String>,
+    Json(mut pdb): Json<pkg_types::pdb::PodDisruptionBudget>,
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/poddisruptionbudgets/{}/{}", ns, pdb.name);
+    reject_resource_version("poddisruptionbudget", &pdb.name, pdb.resource_version)?;
+    admit(&state, "poddisruptionbudget", &pdb.name, Some(&ns), &pdb).await?;
+    pdb.namespace = ns.clone();
+    pdb.status = Default::default();
+    pdb.created_at = Utc::now();
+
+    let data = serde_json::to_vec(&pdb)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => pdb.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(
+                ApiError::already_exists("poddisruptionbudget", &pdb.name, Some(&ns)).into(),
+            );
+        }
+    }
+    info!("Created pod disruption budget {}/{}", ns, pdb.name);
+    Ok((StatusCode::CREATED, Json(pdb)).into_response())
+}
+
+/// Budgets are listed with `status` computed from the namespace's current pods.
+pub async fn list_pod_disruption_budgets(
+    State(state): State<AppState>,
+    AxumPath(ns): AxumPath<String>,
+) -> ApiResult<Json<Vec<pkg_types::pdb::PodDisruptionBudget>>> {
+    let items = pkg_controllers::disruption::budget_status(&state.store, &ns)
+        .await
+        .context("computing disruption budget status")?;
+    Ok(Json(items))
+}
+
+pub async fn get_pod_disruption_budget(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> ApiResult<Json<pkg_types::pdb::PodDisruptionBudget>> {
+    pkg_controllers::disruption::budget_status(&state.store, &ns)
+        .await
+        .context("computing disruption budget status")?
+        .into_iter()
+        .find(|b| b.name == name)
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("poddisruptionbudget", &name, Some(&ns)).into())
+}
+
+// ============================================================
+// Network Policies
 // ============================================================
 
 pub async fn create_network_policy(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut policy): Json<pkg_types::network_policy::NetworkPolicy>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/networkpolicies/{}/{}", ns, policy.name);
+    admit(&state, "networkpolicy", &policy.name, Some(&ns), &policy).await?;
     policy.namespace = ns.clone();
     policy.created_at = Utc::now();
 
-    let key = format!("/registry/networkpolicies/{}/{}", ns, policy.name);
-    match serde_json::to_vec(&policy) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create network policy: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!("Created network policy {}/{}", ns, policy.name);
-            (StatusCode::CREATED, Json(policy)).into_response()
-        }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
+    let data = serde_json::to_vec(&policy)?;
+    if let PutOutcome::Conflict(_) = state.store.create(&key, &data).await? {
+        return Err(ApiError::already_exists("networkpolicy", &policy.name, Some(&ns)).into());
     }
+    info!("Created network policy {}/{}", ns, policy.name);
+    Ok((StatusCode::CREATED, Json(policy)).into_response())
 }
 
 pub async fn list_network_policies(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
-) -> impl IntoResponse {
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::network_policy::NetworkPolicy>> {
     let prefix = format!("/registry/networkpolicies/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let items: Vec<pkg_types::network_policy::NetworkPolicy> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(items)).into_response()
+    list_page(&state.store, &prefix, &page, |_| true).await
 }
 
 // ============================================================
@@ -973,55 +1478,1911 @@ pub async fn create_pvc(
     State(state): State<AppState>,
     AxumPath(ns): AxumPath<String>,
     Json(mut pvc): Json<pkg_types::volume::PersistentVolumeClaim>,
-) -> impl IntoResponse {
+) -> ApiResult {
+    reject_if_terminating(&state, &ns).await?;
+    let key = format!("/registry/pvcs/{}/{}", ns, pvc.name);
+    admit(&state, "pvc", &pvc.name, Some(&ns), &pvc).await?;
     pvc.id = Uuid::new_v4().to_string();
     pvc.namespace = ns.clone();
-    pvc.phase = pkg_types::volume::PVCPhase::Pending; // Start as Pending — reconciler will bind
+    // Bound by the VolumeController once it has provisioned a volume
+    pvc.phase = pkg_types::volume::PVCPhase::Pending;
+    pvc.volume_name = None;
     pvc.created_at = Utc::now();
 
-    let key = format!("/registry/pvcs/{}/{}", ns, pvc.name);
-    match serde_json::to_vec(&pvc) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create PVC: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed").into_response();
-            }
-            info!(
-                "Created PVC {}/{} ({}) — phase: Pending",
-                ns, pvc.name, pvc.id
-            );
+    let data = serde_json::to_vec(&pvc)?;
+    if let PutOutcome::Conflict(_) = state.store.create(&key, &data).await? {
+        return Err(ApiError::already_exists("pvc", &pvc.name, Some(&ns)).into());
+    }
+    info!(
+        "Created PVC {}/{} ({}) — phase: Pending",
+        ns, pvc.name, pvc.id
+    );
 
-            // Background bind: simulate volume provisioner binding after a short delay
-            let store = state.store.clone();
-            let bind_key = key.clone();
-            let mut bind_pvc = pvc.clone();
-            tokio::spawn(async move {
-                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
-                bind_pvc.phase = pkg_types::volume::PVCPhase::Bound;
-                if let Ok(data) = serde_json::to_vec(&bind_pvc) {
-                    if let Err(e) = store.put(&bind_key, &data).await {
-                        tracing::warn!("Failed to bind PVC {}: {}", bind_pvc.id, e);
-                    } else {
-                        tracing::info!("PVC {} bound successfully", bind_pvc.id);
-                    }
-                }
-            });
+    Ok((StatusCode::CREATED, Json(pvc)).into_response())
+}
+
+pub async fn list_pvcs(
+    State(state): State<AppState>,
+    AxumPath(ns): AxumPath<String>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::volume::PersistentVolumeClaim>> {
+    let prefix = format!("/registry/pvcs/{}/", ns);
+    list_page(&state.store, &prefix, &page, |_| true).await
+}
 
-            (StatusCode::CREATED, Json(pvc)).into_response()
+pub async fn get_pvc(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/pvcs/{}/{}", ns, name);
+    get_object::<pkg_types::volume::PersistentVolumeClaim>(&state, &key, "pvc", &name, Some(&ns))
+        .await
+}
+
+// ============================================================
+// Persistent Volumes (cluster-scoped, created by the provisioner)
+// ============================================================
+
+pub async fn list_persistent_volumes(
+    State(state): State<AppState>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::volume::PersistentVolume>> {
+    list_page(&state.store, "/registry/persistentvolumes/", &page, |_| {
+        true
+    })
+    .await
+}
+
+pub async fn get_persistent_volume(
+    State(state): State<AppState>,
+    AxumPath(name): AxumPath<String>,
+) -> impl IntoResponse {
+    let key = format!("/registry/persistentvolumes/{}", name);
+    get_object::<pkg_types::volume::PersistentVolume>(&state, &key, "persistentvolume", &name, None)
+        .await
+}
+
+/// DELETE /api/v1/persistentvolumes/:name — drop a `Released` volume. Its
+/// data stays on the node; a volume still bound to a claim is a `409`.
+pub async fn delete_persistent_volume(
+    State(state): State<AppState>,
+    AxumPath(name): AxumPath<String>,
+) -> ApiResult<StatusCode> {
+    let key = format!("/registry/persistentvolumes/{}", name);
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("persistentvolume", &name, None).into());
+    };
+    let volume: pkg_types::volume::PersistentVolume = serde_json::from_slice(&data)?;
+    if volume.phase == pkg_types::volume::PVPhase::Bound {
+        return Err(ApiError::new(
+            ErrorReason::Conflict,
+            format!(
+                "persistentvolume '{}' is bound to claim {}/{}; delete the claim instead",
+                name, volume.claim_ref.namespace, volume.claim_ref.name
+            ),
+        )
+        .into());
+    }
+    state.store.delete(&key).await?;
+    info!(
+        "Deleted persistent volume {}; its data at {}:{} is kept",
+        name, volume.node_name, volume.path
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============================================================
+// Priority Classes (cluster-scoped)
+// ============================================================
+
+pub async fn create_priority_class(
+    State(state): State<AppState>,
+    Json(mut pc): Json<pkg_types::priority_class::PriorityClass>,
+) -> ApiResult {
+    let key = format!("/registry/priorityclasses/{}", pc.name);
+    reject_resource_version("priorityclass", &pc.name, pc.resource_version)?;
+    admit(&state, "priorityclass", &pc.name, None, &pc).await?;
+    pc.created_at = Utc::now();
+
+    let data = serde_json::to_vec(&pc)?;
+    match state.store.create(&key, &data).await? {
+        PutOutcome::Stored(stored) => pc.resource_version = resource_version(&stored),
+        PutOutcome::Conflict(_) => {
+            return Err(ApiError::already_exists("priorityclass", &pc.name, None).into());
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
     }
+    info!("Created priority class {} (value={})", pc.name, pc.value);
+    Ok((StatusCode::CREATED, Json(pc)).into_response())
 }
 
-pub async fn list_pvcs(
+pub async fn list_priority_classes(
     State(state): State<AppState>,
-    AxumPath(ns): AxumPath<String>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<pkg_types::priority_class::PriorityClass>> {
+    list_page(&state.store, "/registry/priorityclasses/", &page, |_| true).await
+}
+
+pub async fn delete_priority_class(
+    State(state): State<AppState>,
+    AxumPath(name): AxumPath<String>,
+) -> ApiResult<StatusCode> {
+    let key = format!("/registry/priorityclasses/{}", name);
+    state.store.delete(&key).await?;
+    info!("Deleted priority class {}", name);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Resolve `spec.priority_class_name` into `spec.priority`.
+///
+/// A named class must exist; pods without a class pick up the value of the
+/// `global_default` class if one is defined, otherwise keep their explicit priority.
+async fn resolve_pod_priority(
+    state: &AppState,
+    spec: &mut pkg_types::pod::PodSpec,
+) -> ApiResult<()> {
+    use pkg_types::priority_class::PriorityClass;
+
+    if let Some(ref class_name) = spec.priority_class_name {
+        let key = format!("/registry/priorityclasses/{}", class_name);
+        let Some(data) = state.store.get(&key).await? else {
+            return Err(ApiError::new(
+                ErrorReason::BadRequest,
+                format!("PriorityClass '{}' not found", class_name),
+            )
+            .into());
+        };
+        spec.priority = serde_json::from_slice::<PriorityClass>(&data)?.value;
+        return Ok(());
+    }
+
+    if spec.priority == 0 {
+        let entries = state
+            .store
+            .list_prefix("/registry/priorityclasses/")
+            .await
+            .unwrap_or_default();
+        if let Some(pc) = entries
+            .into_iter()
+            .filter_map(|(_, v)| serde_json::from_slice::<PriorityClass>(&v).ok())
+            .find(|pc| pc.global_default)
+        {
+            spec.priority = pc.value;
+        }
+    }
+    Ok(())
+}
+
+// ============================================================
+// Single-object GET
+// ============================================================
+
+/// Fetch and decode the object stored at `key`. A missing key becomes a 404
+/// naming the object (`pod 'web' not found in namespace 'default'`).
+async fn get_object<T>(
+    state: &AppState,
+    key: &str,
+    kind: &str,
+    name: &str,
+    ns: Option<&str>,
+) -> ApiResult
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    let data = state
+        .store
+        .get(key)
+        .await?
+        .ok_or_else(|| ApiError::not_found(kind, name, ns))?;
+    let obj: T = serde_json::from_slice(&data)?;
+    Ok((StatusCode::OK, Json(obj)).into_response())
+}
+
+pub async fn get_namespace(
+    State(state): State<AppState>,
+    AxumPath(name): AxumPath<String>,
 ) -> impl IntoResponse {
-    let prefix = format!("/registry/pvcs/{}/", ns);
-    let entries = state.store.list_prefix(&prefix).await.unwrap_or_default();
-    let items: Vec<pkg_types::volume::PersistentVolumeClaim> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(items)).into_response()
+    let key = format!("/registry/namespaces/{}", name);
+    get_object::<pkg_types::namespace::Namespace>(&state, &key, "namespace", &name, None).await
+}
+
+pub async fn get_service(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/services/{}/{}", ns, name);
+    get_object::<pkg_types::service::Service>(&state, &key, "service", &name, Some(&ns)).await
+}
+
+pub async fn get_configmap(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/configmaps/{}/{}", ns, name);
+    get_object::<pkg_types::configmap::ConfigMap>(&state, &key, "configmap", &name, Some(&ns)).await
+}
+
+pub async fn get_secret(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/secrets/{}/{}", ns, name);
+    get_object::<pkg_types::secret::Secret>(&state, &key, "secret", &name, Some(&ns)).await
+}
+
+pub async fn get_replicaset(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/replicasets/{}/{}", ns, name);
+    get_object::<pkg_types::replicaset::ReplicaSet>(&state, &key, "replicaset", &name, Some(&ns))
+        .await
+}
+
+pub async fn get_daemonset(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/daemonsets/{}/{}", ns, name);
+    get_object::<pkg_types::daemonset::DaemonSet>(&state, &key, "daemonset", &name, Some(&ns)).await
+}
+
+pub async fn get_job(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/jobs/{}/{}", ns, name);
+    get_object::<pkg_types::job::Job>(&state, &key, "job", &name, Some(&ns)).await
+}
+
+pub async fn get_cronjob(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/cronjobs/{}/{}", ns, name);
+    get_object::<pkg_types::job::CronJob>(&state, &key, "cronjob", &name, Some(&ns)).await
+}
+
+pub async fn get_hpa(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+) -> impl IntoResponse {
+    let key = format!("/registry/hpa/{}/{}", ns, name);
+    get_object::<pkg_types::hpa::HorizontalPodAutoscaler>(&state, &key, "hpa", &name, Some(&ns))
+        .await
+}
+
+pub async fn get_priority_class(
+    State(state): State<AppState>,
+    AxumPath(name): AxumPath<String>,
+) -> impl IntoResponse {
+    let key = format!("/registry/priorityclasses/{}", name);
+    get_object::<pkg_types::priority_class::PriorityClass>(
+        &state,
+        &key,
+        "priorityclass",
+        &name,
+        None,
+    )
+    .await
+}
+
+// ============================================================
+// Server-side apply — PUT create-or-update
+// ============================================================
+
+/// Query parameters of the apply and patch endpoints.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApplyQuery {
+    /// `dryRun=All`: admit the object and return it as it would be stored,
+    /// without storing it.
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: Option<DryRun>,
+}
+
+/// The `dryRun` values the server understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum DryRun {
+    All,
+}
+
+/// Store `obj` at `key`: delegate to `create` when nothing is stored yet,
+/// otherwise replace the stored object while keeping its server-assigned
+/// fields (see `pkg_types::apply`). An update must carry the resource version
+/// of the stored object; a stale or missing one is rejected with 409 and the
+/// current object. With `dry_run` the object goes through the same checks
+/// and is returned as it would be stored, but nothing is written.
+#[allow(clippy::too_many_arguments)]
+async fn upsert<T, F, Fut>(
+    state: &AppState,
+    key: String,
+    kind: &str,
+    ns: Option<&str>,
+    name: &str,
+    dry_run: bool,
+    mut obj: T,
+    create: F,
+) -> ApiResult
+where
+    T: Apply + Defaults + Validate + serde::Serialize + serde::de::DeserializeOwned,
+    F: FnOnce(T) -> Fut,
+    Fut: std::future::Future<Output = axum::response::Response>,
+{
+    let body_name = obj.name_mut();
+    if body_name.is_empty() {
+        *body_name = name.to_string();
+    } else if body_name.as_str() != name {
+        return Err(ApiError::new(
+            ErrorReason::BadRequest,
+            format!(
+                "Manifest name '{}' does not match URL name '{}'",
+                body_name, name
+            ),
+        )
+        .into());
+    }
+
+    let Some(data) = state.store.get(&key).await? else {
+        if !dry_run {
+            return Ok(create(obj).await);
+        }
+        if let Some(ns) = ns {
+            reject_if_terminating(state, ns).await?;
+        }
+        reject_resource_version(kind, name, *obj.resource_version_mut())?;
+        obj.default_fields();
+        admit(state, kind, name, ns, &obj).await?;
+        if let (Some(ns), Some(field)) = (ns, obj.namespace_mut()) {
+            *field = ns.to_string();
+        }
+        return Ok((StatusCode::CREATED, Json(obj)).into_response());
+    };
+    let sent = *obj.resource_version_mut();
+    let stored = resource_version(&data);
+    if sent != stored {
+        return Err(version_conflict(kind, name, sent, Some(&data)));
+    }
+    obj.default_fields();
+    admit(state, kind, name, ns, &obj).await?;
+    obj.retain_server_fields(serde_json::from_slice(&data)?);
+    if dry_run {
+        return Ok((StatusCode::OK, Json(obj)).into_response());
+    }
+    match state
+        .store
+        .put_if_version(&key, &serde_json::to_vec(&obj)?, stored)
+        .await?
+    {
+        PutOutcome::Stored(data) => {
+            info!("Applied {}", key);
+            let obj: T = serde_json::from_slice(&data)?;
+            Ok((StatusCode::OK, Json(obj)).into_response())
+        }
+        PutOutcome::Conflict(current) => {
+            Err(version_conflict(kind, name, sent, current.as_deref()))
+        }
+    }
+}
+
+/// 409 for an update that was not made against the stored object.
+pub(crate) fn version_conflict(
+    kind: &str,
+    name: &str,
+    sent: u64,
+    current: Option<&[u8]>,
+) -> ErrorResponse {
+    let current = current.and_then(|data| serde_json::from_slice(data).ok());
+    ApiError::version_conflict(kind, name, sent, current).into()
+}
+
+pub async fn apply_namespace(
+    State(state): State<AppState>,
+    AxumPath(name): AxumPath<String>,
+    Query(query): Query<ApplyQuery>,
+    Json(ns): Json<pkg_types::namespace::Namespace>,
+) -> impl IntoResponse {
+    let key = format!("/registry/namespaces/{}", name);
+    upsert(
+        &state,
+        key,
+        "namespace",
+        None,
+        &name,
+        query.dry_run.is_some(),
+        ns,
+        |ns| async {
+            create_namespace(State(state.clone()), Json(ns))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_pod(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(mut pod): Json<pkg_types::pod::Pod>,
+) -> ApiResult {
+    resolve_pod_priority(&state, &mut pod.spec).await?;
+    let key = format!("/registry/pods/{}/{}", ns, name);
+    reject_if_mirror(&state, &key).await?;
+    upsert(
+        &state,
+        key,
+        "pod",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        pod,
+        |pod| async {
+            create_pod(State(state.clone()), AxumPath(ns.clone()), Json(pod))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_service(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(mut svc): Json<pkg_types::service::Service>,
+) -> ApiResult {
+    let key = format!("/registry/services/{}/{}", ns, name);
+    let existing = state
+        .store
+        .get(&key)
+        .await?
+        .and_then(|d| serde_json::from_slice(&d).ok());
+    assign_node_ports(&state, &ns, &name, &mut svc, existing.as_ref()).await?;
+    upsert(
+        &state,
+        key,
+        "service",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        svc,
+        |svc| async {
+            create_service(State(state.clone()), AxumPath(ns.clone()), Json(svc))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_deployment(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(deploy): Json<pkg_types::deployment::Deployment>,
+) -> impl IntoResponse {
+    let key = format!("/registry/deployments/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "deployment",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        deploy,
+        |deploy| async {
+            create_deployment(State(state.clone()), AxumPath(ns.clone()), Json(deploy))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_configmap(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(cm): Json<pkg_types::configmap::ConfigMap>,
+) -> impl IntoResponse {
+    let key = format!("/registry/configmaps/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "configmap",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        cm,
+        |cm| async {
+            create_configmap(State(state.clone()), AxumPath(ns.clone()), Json(cm))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_secret(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(secret): Json<pkg_types::secret::Secret>,
+) -> impl IntoResponse {
+    let key = format!("/registry/secrets/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "secret",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        secret,
+        |secret| async {
+            create_secret(State(state.clone()), AxumPath(ns.clone()), Json(secret))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_replicaset(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(rs): Json<pkg_types::replicaset::ReplicaSet>,
+) -> impl IntoResponse {
+    let key = format!("/registry/replicasets/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "replicaset",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        rs,
+        |rs| async {
+            create_replicaset(State(state.clone()), AxumPath(ns.clone()), Json(rs))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_daemonset(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(ds): Json<pkg_types::daemonset::DaemonSet>,
+) -> impl IntoResponse {
+    let key = format!("/registry/daemonsets/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "daemonset",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        ds,
+        |ds| async {
+            create_daemonset(State(state.clone()), AxumPath(ns.clone()), Json(ds))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_job(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(job): Json<pkg_types::job::Job>,
+) -> impl IntoResponse {
+    let key = format!("/registry/jobs/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "job",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        job,
+        |job| async {
+            create_job(State(state.clone()), AxumPath(ns.clone()), Json(job))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_cronjob(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(cj): Json<pkg_types::job::CronJob>,
+) -> impl IntoResponse {
+    let key = format!("/registry/cronjobs/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "cronjob",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        cj,
+        |cj| async {
+            create_cronjob(State(state.clone()), AxumPath(ns.clone()), Json(cj))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_hpa(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(hpa): Json<pkg_types::hpa::HorizontalPodAutoscaler>,
+) -> impl IntoResponse {
+    let key = format!("/registry/hpa/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "hpa",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        hpa,
+        |hpa| async {
+            create_hpa(State(state.clone()), AxumPath(ns.clone()), Json(hpa))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_limit_range(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(range): Json<pkg_types::limit_range::LimitRange>,
+) -> impl IntoResponse {
+    let key = format!("/registry/limitranges/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "limitrange",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        range,
+        |range| async {
+            create_limit_range(State(state.clone()), AxumPath(ns.clone()), Json(range))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_pod_disruption_budget(
+    State(state): State<AppState>,
+    AxumPath((ns, name)): AxumPath<(String, String)>,
+    Query(query): Query<ApplyQuery>,
+    Json(pdb): Json<pkg_types::pdb::PodDisruptionBudget>,
+) -> impl IntoResponse {
+    let key = format!("/registry/poddisruptionbudgets/{}/{}", ns, name);
+    upsert(
+        &state,
+        key,
+        "poddisruptionbudget",
+        Some(&ns),
+        &name,
+        query.dry_run.is_some(),
+        pdb,
+        |pdb| async {
+            create_pod_disruption_budget(State(state.clone()), AxumPath(ns.clone()), Json(pdb))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+pub async fn apply_priority_class(
+    State(state): State<AppState>,
+    AxumPath(name): AxumPath<String>,
+    Query(query): Query<ApplyQuery>,
+    Json(pc): Json<pkg_types::priority_class::PriorityClass>,
+) -> impl IntoResponse {
+    let key = format!("/registry/priorityclasses/{}", name);
+    upsert(
+        &state,
+        key,
+        "priorityclass",
+        None,
+        &name,
+        query.dry_run.is_some(),
+        pc,
+        |pc| async {
+            create_priority_class(State(state.clone()), Json(pc))
+                .await
+                .into_response()
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{api_error, body_text, test_state};
+
+    fn sample_pod() -> pkg_types::pod::Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": "pod-1",
+            "name": "web-0",
+            "namespace": "default",
+            "status": "Running",
+            "status_message": "started",
+            "node_name": "node-a",
+            "owner_ref": "rs-1",
+            "restart_count": 3,
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": { "containers": [{ "name": "nginx", "image": "nginx:alpine" }] }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_pod_returns_full_object() {
+        let state = test_state("get-pod").await;
+        let pod = sample_pod();
+        state
+            .store
+            .put(
+                "/registry/pods/default/web-0",
+                &serde_json::to_vec(&pod).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let resp = get_pod(
+            State(state),
+            AxumPath(("default".to_string(), "web-0".to_string())),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let got: pkg_types::pod::Pod = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(got.name, "web-0");
+        assert_eq!(got.spec.containers[0].image, "nginx:alpine");
+        assert_eq!(got.status_message.as_deref(), Some("started"));
+        assert_eq!(got.node_name.as_deref(), Some("node-a"));
+        assert_eq!(got.owner_ref.as_deref(), Some("rs-1"));
+        assert_eq!(got.restart_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_get_missing_objects_return_named_404() {
+        let state = test_state("get-missing").await;
+
+        let resp = get_pod(
+            State(state.clone()),
+            AxumPath(("default".to_string(), "ghost".to_string())),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": 404,
+                "reason": "NotFound",
+                "message": "pod 'ghost' not found in namespace 'default'",
+                "details": [],
+            })
+        );
+
+        let resp = get_priority_class(State(state), AxumPath("ghost".to_string()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            api_error(resp).await.message,
+            "priorityclass 'ghost' not found"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_existing_object_is_rejected() {
+        let state = test_state("create-existing").await;
+        let create = || {
+            create_pod(
+                State(state.clone()),
+                AxumPath("default".to_string()),
+                Json(sample_pod()),
+            )
+        };
+        assert_eq!(create().await.into_response().status(), StatusCode::CREATED);
+
+        let resp = create().await.into_response();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let body: serde_json::Value = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": 409,
+                "reason": "AlreadyExists",
+                "message": "pod 'web-0' already exists in namespace 'default'",
+                "details": [],
+            })
+        );
+
+        // Racing creates of a new name: exactly one is stored.
+        let racing = || {
+            let mut pod = sample_pod();
+            pod.name = "web-1".to_string();
+            create_pod(
+                State(state.clone()),
+                AxumPath("default".to_string()),
+                Json(pod),
+            )
+        };
+        let (a, b) = tokio::join!(racing(), racing());
+        let mut statuses = [a.into_response().status(), b.into_response().status()];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::CREATED, StatusCode::CONFLICT]);
+    }
+
+    fn deployment(selector: serde_json::Value, image: &str) -> pkg_types::deployment::Deployment {
+        serde_json::from_value(serde_json::json!({
+            "id": "",
+            "name": "web",
+            "namespace": "",
+            "created_at": "2024-02-25T00:00:00Z",
+            "spec": {
+                "replicas": 2,
+                "selector": selector,
+                "template": { "containers": [{ "name": "web", "image": image }] }
+            }
+        }))
+        .unwrap()
+    }
+
+    /// The `(field, message)` details of an Invalid error response.
+    async fn invalid_fields(resp: axum::response::Response) -> Vec<String> {
+        let err = api_error(resp).await;
+        assert_eq!(err.reason, ErrorReason::Invalid, "{}", err);
+        err.details.into_iter().map(|d| d.field).collect()
+    }
+
+    #[tokio::test]
+    async fn test_create_rejects_invalid_manifests() {
+        let state = test_state("create-invalid").await;
+
+        let mut pod = sample_pod();
+        pod.spec.containers.push(
+            serde_json::from_value(serde_json::json!({ "name": "nginx", "image": "" })).unwrap(),
+        );
+        let resp = create_pod(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(pod),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            invalid_fields(resp).await,
+            ["spec.containers[1].image", "spec.containers[1].name"]
+        );
+
+        // The namespace must exist.
+        let resp = create_pod(
+            State(state.clone()),
+            AxumPath("missing".to_string()),
+            Json(sample_pod()),
+        )
+        .await
+        .into_response();
+        let err = api_error(resp).await;
+        assert_eq!(
+            err.details,
+            [FieldError::new(
+                "namespace",
+                "namespace 'missing' does not exist"
+            )]
+        );
+
+        let resp = create_deployment(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(deployment(serde_json::json!({}), "nginx")),
+        )
+        .await
+        .into_response();
+        assert_eq!(invalid_fields(resp).await, ["spec.selector"]);
+
+        let svc: pkg_types::service::Service = serde_json::from_value(serde_json::json!({
+            "id": "", "name": "Web", "namespace": "", "created_at": Utc::now(),
+            "spec": {
+                "service_type": "ClusterIP",
+                "ports": [{ "name": "http", "port": 0, "target_port": 8080 }]
+            }
+        }))
+        .unwrap();
+        let resp = create_service(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(svc),
+        )
+        .await
+        .into_response();
+        assert_eq!(invalid_fields(resp).await, ["name", "spec.ports[0].port"]);
+
+        let cj: pkg_types::job::CronJob = serde_json::from_value(serde_json::json!({
+            "id": "", "name": "nightly", "namespace": "", "created_at": Utc::now(),
+            "spec": {
+                "schedule": "0 25 * * *",
+                "job_template": { "template": { "containers": [{ "name": "run", "image": "busybox" }] } }
+            }
+        }))
+        .unwrap();
+        let resp = create_cronjob(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(cj),
+        )
+        .await
+        .into_response();
+        assert_eq!(invalid_fields(resp).await, ["spec.schedule"]);
+
+        assert!(
+            state
+                .store
+                .list_prefix("/registry/pods/")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            state
+                .store
+                .get("/registry/deployments/default/web")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_dry_run_stores_nothing() {
+        let state = test_state("apply-dry-run").await;
+        let key = "/registry/deployments/default/web";
+        let apply = |deploy, dry_run: bool| {
+            apply_deployment(
+                State(state.clone()),
+                AxumPath(("default".to_string(), "web".to_string())),
+                Query(ApplyQuery {
+                    dry_run: dry_run.then_some(DryRun::All),
+                }),
+                Json(deploy),
+            )
+        };
+        let selector = serde_json::json!({ "app": "web" });
+
+        // A create returns the object it would have made.
+        let resp = apply(deployment(selector.clone(), "nginx:1"), true)
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let would_be: pkg_types::deployment::Deployment =
+            serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(would_be.namespace, "default");
+        assert_eq!(would_be.spec.template.containers[0].image, "nginx:1");
+        assert!(state.store.get(key).await.unwrap().is_none());
+
+        // Invalid manifests are rejected as they would be for real.
+        let resp = apply(deployment(selector.clone(), ""), true)
+            .await
+            .into_response();
+        assert_eq!(
+            invalid_fields(resp).await,
+            ["spec.template.containers[0].image"]
+        );
+
+        let resp = apply(deployment(selector.clone(), "nginx:1"), false)
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let stored: pkg_types::deployment::Deployment =
+            serde_json::from_str(&body_text(resp).await).unwrap();
+
+        // An update returns the merged object and leaves the stored one.
+        let mut update = deployment(selector.clone(), "nginx:2");
+        update.resource_version = stored.resource_version;
+        let resp = apply(update.clone(), true).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let would_be: pkg_types::deployment::Deployment =
+            serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(would_be.id, stored.id);
+        assert_eq!(would_be.spec.template.containers[0].image, "nginx:2");
+        let data = state.store.get(key).await.unwrap().unwrap();
+        assert_eq!(resource_version(&data), stored.resource_version);
+
+        // Updates are validated too.
+        update.spec.selector.clear();
+        let resp = apply(update, false).await.into_response();
+        assert_eq!(invalid_fields(resp).await, ["spec.selector"]);
+    }
+
+    #[tokio::test]
+    async fn test_minimal_manifests_are_stored_with_defaults() {
+        use pkg_types::pod::{ImagePullPolicy, RestartPolicy};
+        use pkg_types::service::{Protocol, ServiceType};
+        let state = test_state("defaults").await;
+        let stored = |key: &'static str| {
+            let store = state.store.clone();
+            async move { store.get(key).await.unwrap().unwrap() }
+        };
+
+        let pod = serde_json::from_value(serde_json::json!({
+            "id": "", "name": "web", "namespace": "", "status": "Pending",
+            "created_at": Utc::now(),
+            "spec": { "containers": [{ "name": "web", "image": "nginx" }] }
+        }))
+        .unwrap();
+        create_pod(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(pod),
+        )
+        .await
+        .unwrap();
+        let pod: pkg_types::pod::Pod =
+            serde_json::from_slice(&stored("/registry/pods/default/web").await).unwrap();
+        assert_eq!(pod.namespace, "default");
+        assert_eq!(pod.spec.restart_policy, RestartPolicy::Always);
+        assert_eq!(
+            pod.spec.containers[0].image_pull_policy,
+            Some(ImagePullPolicy::Always)
+        );
+
+        let svc = serde_json::from_value(serde_json::json!({
+            "id": "", "name": "web", "namespace": "", "created_at": Utc::now(),
+            "spec": { "selector": { "app": "web" }, "ports": [{ "port": 80 }] }
+        }))
+        .unwrap();
+        create_service(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(svc),
+        )
+        .await
+        .unwrap();
+        let svc: pkg_types::service::Service =
+            serde_json::from_slice(&stored("/registry/services/default/web").await).unwrap();
+        assert!(matches!(svc.spec.service_type, ServiceType::ClusterIP));
+        let port = &svc.spec.ports[0];
+        assert_eq!(
+            (port.protocol, port.port, port.target_port),
+            (Protocol::TCP, 80, 80)
+        );
+
+        // Omitted replicas default to 1; an explicit 0 is kept.
+        let mut deploy = deployment(serde_json::json!({ "app": "web" }), "nginx:1.27");
+        deploy.spec.replicas = 0;
+        let mut manifest = serde_json::to_value(&deploy).unwrap();
+        manifest["spec"].as_object_mut().unwrap().remove("replicas");
+        create_deployment(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(serde_json::from_value(manifest).unwrap()),
+        )
+        .await
+        .unwrap();
+        let web: pkg_types::deployment::Deployment =
+            serde_json::from_slice(&stored("/registry/deployments/default/web").await).unwrap();
+        assert_eq!(web.spec.replicas, 1);
+        assert_eq!(
+            web.spec.template.containers[0].image_pull_policy,
+            Some(ImagePullPolicy::IfNotPresent)
+        );
+        deploy.name = "idle".to_string();
+        create_deployment(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(deploy),
+        )
+        .await
+        .unwrap();
+        let idle: pkg_types::deployment::Deployment =
+            serde_json::from_slice(&stored("/registry/deployments/default/idle").await).unwrap();
+        assert_eq!(idle.spec.replicas, 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_applies_one_wins() {
+        use pkg_types::configmap::ConfigMap;
+
+        let state = test_state("apply-conflict").await;
+        let cm = |value: &str, version: u64| -> ConfigMap {
+            serde_json::from_value(serde_json::json!({
+                "id": "",
+                "name": "cfg",
+                "namespace": "default",
+                "data": { "mode": value },
+                "created_at": Utc::now(),
+                "resource_version": version,
+            }))
+            .unwrap()
+        };
+        let apply = |cm: ConfigMap| {
+            apply_configmap(
+                State(state.clone()),
+                AxumPath(("default".to_string(), "cfg".to_string())),
+                Query(ApplyQuery::default()),
+                Json(cm),
+            )
+        };
+
+        // Creating with a resource version is refused.
+        let err = api_error(apply(cm("a", 3)).await.into_response()).await;
+        assert_eq!(err.reason, ErrorReason::Invalid);
+        assert_eq!(err.details[0].field, "resource_version");
+
+        let resp = apply(cm("a", 0)).await.into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let read: ConfigMap = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert!(read.resource_version > 0);
+
+        // Two updates from the same read: exactly one lands.
+        let (first, second) = tokio::join!(
+            apply(cm("b", read.resource_version)),
+            apply(cm("c", read.resource_version)),
+        );
+        let (first, second) = (first.into_response(), second.into_response());
+        let loser = match (first.status(), second.status()) {
+            (StatusCode::OK, StatusCode::CONFLICT) => second,
+            (StatusCode::CONFLICT, StatusCode::OK) => first,
+            other => panic!("expected one winner, got {:?}", other),
+        };
+        let err = api_error(loser).await;
+        assert_eq!(err.reason, ErrorReason::Conflict);
+        let current: ConfigMap = serde_json::from_value(err.current.unwrap()).unwrap();
+        assert!(current.resource_version > read.resource_version);
+
+        // The loser retries on top of the winner's write.
+        let resp = apply(cm("d", current.resource_version))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let stored: ConfigMap = serde_json::from_slice(
+            &state
+                .store
+                .get("/registry/configmaps/default/cfg")
+                .await
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(stored.data["mode"], "d");
+        assert_eq!(stored.id, read.id);
+
+        // An update that does not say which version it read is refused too.
+        let err = api_error(apply(cm("e", 0)).await.into_response()).await;
+        assert_eq!(err.reason, ErrorReason::Conflict);
+        assert_eq!(
+            err.message,
+            "configmap 'cfg' already exists; send the resource_version you read to update it"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_running_pod_terminates_before_removal() {
+        use pkg_types::pod::{Pod, PodStatus, PodStatusUpdate};
+
+        let state = test_state("delete-pod").await;
+        let key = "/registry/pods/default/web-0";
+        state
+            .store
+            .put(key, &serde_json::to_vec(&sample_pod()).unwrap())
+            .await
+            .unwrap();
+        let delete = |force: bool| {
+            delete_pod(
+                State(state.clone()),
+                AxumPath(("default".to_string(), "web-0".to_string())),
+                Query(DeletePodQuery { force }),
+            )
+        };
+
+        let resp = delete(false).await.into_response();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let stored: Pod =
+            serde_json::from_slice(&state.store.get(key).await.unwrap().unwrap()).unwrap();
+        assert_eq!(stored.status, PodStatus::Terminating);
+
+        // Late status reports from the agent don't revive it.
+        update_pod_status(
+            State(state.clone()),
+            AxumPath(("default".to_string(), "web-0".to_string())),
+            Json(PodStatusUpdate::Status(PodStatus::Running)),
+        )
+        .await
+        .unwrap();
+        let stored: Pod =
+            serde_json::from_slice(&state.store.get(key).await.unwrap().unwrap()).unwrap();
+        assert_eq!(stored.status, PodStatus::Terminating);
+
+        // The agent force-deletes once the containers are gone.
+        let resp = delete(true).await.into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(state.store.get(key).await.unwrap().is_none());
+
+        // A pod that never reached a node is removed immediately.
+        let mut pending = sample_pod();
+        pending.node_name = None;
+        pending.status = PodStatus::Pending;
+        state
+            .store
+            .put(key, &serde_json::to_vec(&pending).unwrap())
+            .await
+            .unwrap();
+        let resp = delete(false).await.into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(state.store.get(key).await.unwrap().is_none());
+    }
+
+    fn configmap(name: &str) -> pkg_types::configmap::ConfigMap {
+        serde_json::from_value(serde_json::json!({
+            "id": "",
+            "name": name,
+            "namespace": "",
+            "data": { "key": "value" },
+            "created_at": "2024-02-25T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_delete_namespace_cascades() {
+        use pkg_types::namespace::{Namespace, NamespacePhase};
+
+        let state = test_state("delete-ns").await;
+        let ns: Namespace = serde_json::from_value(serde_json::json!({
+            "name": "team",
+            "created_at": "2024-02-25T00:00:00Z",
+        }))
+        .unwrap();
+        create_namespace(State(state.clone()), Json(ns))
+            .await
+            .unwrap();
+        let team = || AxumPath("team".to_string());
+
+        create_configmap(State(state.clone()), team(), Json(configmap("cfg")))
+            .await
+            .unwrap();
+        let secret: pkg_types::secret::Secret = serde_json::from_value(serde_json::json!({
+            "id": "", "name": "creds", "namespace": "", "data": {},
+            "created_at": "2024-02-25T00:00:00Z",
+        }))
+        .unwrap();
+        create_secret(State(state.clone()), team(), Json(secret))
+            .await
+            .unwrap();
+        let svc: pkg_types::service::Service = serde_json::from_value(serde_json::json!({
+            "id": "", "name": "web", "namespace": "",
+            "spec": { "ports": [{ "name": "http", "port": 80, "target_port": 8080 }],
+                      "service_type": "ClusterIP" },
+            "created_at": "2024-02-25T00:00:00Z",
+        }))
+        .unwrap();
+        create_service(State(state.clone()), team(), Json(svc))
+            .await
+            .unwrap();
+        let mut pending = sample_pod();
+        pending.name = "batch".to_string();
+        pending.node_name = None;
+        create_pod(State(state.clone()), team(), Json(pending))
+            .await
+            .unwrap();
+        // Objects created by controllers go straight to the store.
+        let mut running = sample_pod();
+        running.namespace = "team".to_string();
+        for (key, data) in [
+            (
+                "/registry/pods/team/web-0",
+                serde_json::to_vec(&running).unwrap(),
+            ),
+            ("/registry/deployments/team/web", b"{}".to_vec()),
+            ("/registry/replicasets/team/web-1", b"{}".to_vec()),
+            ("/registry/configmaps/default/cfg", b"{}".to_vec()),
+        ] {
+            state.store.put(key, &data).await.unwrap();
+        }
+        let remaining = |prefix: &'static str| {
+            let store = state.store.clone();
+            async move { store.list_prefix(prefix).await.unwrap().len() }
+        };
+        assert_eq!(remaining("/registry/pods/team/").await, 2);
+
+        // The running pod is handed to its agent; everything else goes now.
+        let resp = delete_namespace(State(state.clone()), team())
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+        let stored: Namespace = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(stored.phase, NamespacePhase::Terminating);
+        for prefix in [
+            "/registry/configmaps/team/",
+            "/registry/secrets/team/",
+            "/registry/services/team/",
+            "/registry/deployments/team/",
+            "/registry/replicasets/team/",
+        ] {
+            assert_eq!(remaining(prefix).await, 0, "{}", prefix);
+        }
+        let pod: pkg_types::pod::Pod = serde_json::from_slice(
+            &state
+                .store
+                .get("/registry/pods/team/web-0")
+                .await
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(pod.status, pkg_types::pod::PodStatus::Terminating);
+        assert_eq!(remaining("/registry/pods/team/").await, 1);
+        assert_eq!(remaining("/registry/configmaps/default/").await, 1);
+
+        // Nothing new while it is Terminating, including through apply.
+        let resp = create_configmap(State(state.clone()), team(), Json(configmap("late")))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert!(api_error(resp).await.message.contains("being terminated"));
+        let resp = apply_configmap(
+            State(state.clone()),
+            AxumPath(("team".to_string(), "late".to_string())),
+            Query(ApplyQuery::default()),
+            Json(configmap("late")),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        // Once the agent has removed the pod the namespace goes too.
+        state
+            .store
+            .delete("/registry/pods/team/web-0")
+            .await
+            .unwrap();
+        let resp = delete_namespace(State(state.clone()), team())
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        assert!(
+            state
+                .store
+                .get("/registry/namespaces/team")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let resp = delete_namespace(State(state.clone()), team())
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_system_namespaces_cannot_be_deleted() {
+        let state = test_state("delete-ns-protected").await;
+        for name in ["default", "k3rs-system"] {
+            let resp = delete_namespace(State(state.clone()), AxumPath(name.to_string()))
+                .await
+                .into_response();
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", name);
+        }
+    }
+
+    fn pod_requesting(name: &str, containers: &[u64]) -> pkg_types::pod::Pod {
+        let mut pod = sample_pod();
+        pod.name = name.to_string();
+        pod.spec.containers = containers
+            .iter()
+            .enumerate()
+            .map(|(i, cpu)| {
+                serde_json::from_value(serde_json::json!({
+                    "name": format!("c{}", i),
+                    "image": "nginx:alpine",
+                    "resources": { "cpu_millis": cpu, "memory_bytes": 0 },
+                }))
+                .unwrap()
+            })
+            .collect();
+        pod
+    }
+
+    #[tokio::test]
+    async fn test_pod_creation_enforces_resource_quota() {
+        let state = test_state("quota-admission").await;
+        let quota: pkg_types::quota::ResourceQuota = serde_json::from_value(serde_json::json!({
+            "name": "compute",
+            "namespace": "default",
+            "hard": { "max_pods": 2, "max_cpu_millis": 1000 },
+            "created_at": "2024-02-25T00:00:00Z",
+        }))
+        .unwrap();
+        let resp = create_resource_quota(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(quota),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let create = |pod| {
+            create_pod(
+                State(state.clone()),
+                AxumPath("default".to_string()),
+                Json(pod),
+            )
+        };
+        // Containers are summed: 300m + 400m, then 300m more is exactly at the limit.
+        let resp = create(pod_requesting("a", &[300, 400]))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let resp = create(pod_requesting("big", &[301])).await.into_response();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let err = api_error(resp).await;
+        assert_eq!(err.reason, ErrorReason::QuotaExceeded);
+        assert_eq!(err.message, "exceeded quota 'compute': cpu=1001m > 1000m");
+        let resp = create(pod_requesting("b", &[300])).await.into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let resp = create(pod_requesting("c", &[0])).await.into_response();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            api_error(resp).await.message,
+            "exceeded quota 'compute': pods=3 > 2"
+        );
+
+        let resp = get_resource_quota(
+            State(state.clone()),
+            AxumPath(("default".to_string(), "compute".to_string())),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let status: pkg_types::quota::ResourceQuota =
+            serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(status.used.pods, 2);
+        assert_eq!(status.used.cpu_millis, 1000);
+
+        // Deleting a pod frees its share right away.
+        state
+            .store
+            .delete("/registry/pods/default/b")
+            .await
+            .unwrap();
+        let resp = create(pod_requesting("c", &[0])).await.into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_pod_creation_applies_limit_ranges() {
+        let state = test_state("limit-range-admission").await;
+        let range: pkg_types::limit_range::LimitRange = serde_json::from_value(serde_json::json!({
+            "name": "defaults",
+            "default_request": { "cpu_millis": 250 },
+            "max": { "cpu_millis": 500 },
+            "created_at": "2024-02-25T00:00:00Z",
+        }))
+        .unwrap();
+        let resp = create_limit_range(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(range),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let create = |pod| {
+            create_pod(
+                State(state.clone()),
+                AxumPath("default".to_string()),
+                Json(pod),
+            )
+        };
+        let resp = create(pod_requesting("a", &[0, 100])).await.into_response();
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let pod: pkg_types::pod::Pod = serde_json::from_str(&body_text(resp).await).unwrap();
+        let requests: Vec<u64> = pod
+            .spec
+            .containers
+            .iter()
+            .map(|c| c.resources.cpu_millis)
+            .collect();
+        assert_eq!(requests, vec![250, 100]);
+
+        let resp = create(pod_requesting("b", &[600])).await.into_response();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let err = api_error(resp).await;
+        assert_eq!(err.reason, ErrorReason::Invalid);
+        assert_eq!(err.message, "pod 'b' is invalid");
+        assert_eq!(
+            err.details,
+            vec![pkg_types::error::FieldError::new(
+                "spec.containers[0].limits.cpu_millis",
+                "container 'c0': cpu limit 600m exceeds the maximum 500m of LimitRange 'defaults'"
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pod_creation_rejects_contradictory_security_context() {
+        let state = test_state("security-context-admission").await;
+        let mut pod = pod_requesting("priv", &[0]);
+        let sc = &mut pod.spec.containers[0].security_context;
+        sc.privileged = true;
+        sc.run_as_non_root = true;
+
+        let resp = create_pod(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(pod),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let err = api_error(resp).await;
+        assert_eq!(
+            err.details,
+            vec![pkg_types::error::FieldError::new(
+                "spec.containers[0].security_context.run_as_non_root",
+                "container 'c0': run_as_non_root can't be combined with privileged"
+            )]
+        );
+        assert!(
+            state
+                .store
+                .get("/registry/pods/default/priv")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_status_update_carries_message_and_restart_count() {
+        use pkg_types::pod::{CRASH_LOOP_BACK_OFF, Pod, PodStatus, PodStatusUpdate};
+
+        let state = test_state("pod-status-update").await;
+        let key = "/registry/pods/default/web-0";
+        state
+            .store
+            .put(key, &serde_json::to_vec(&sample_pod()).unwrap())
+            .await
+            .unwrap();
+        let update = |body: serde_json::Value| {
+            let update: PodStatusUpdate = serde_json::from_value(body).unwrap();
+            update_pod_status(
+                State(state.clone()),
+                AxumPath(("default".to_string(), "web-0".to_string())),
+                Json(update),
+            )
+        };
+        let stored = || async {
+            serde_json::from_slice::<Pod>(&state.store.get(key).await.unwrap().unwrap()).unwrap()
+        };
+
+        update(serde_json::json!({
+            "status": "Scheduled",
+            "status_message": "CrashLoopBackOff: back-off 10s",
+            "restart_count": 4
+        }))
+        .await
+        .unwrap();
+        let pod = stored().await;
+        assert_eq!(pod.status, PodStatus::Scheduled);
+        assert_eq!(pod.restart_count, 4);
+        assert_eq!(pod.display_status(), CRASH_LOOP_BACK_OFF);
+
+        // The agents' bare form still works and clears the stale reason.
+        update(serde_json::json!("Running")).await.unwrap();
+        let pod = stored().await;
+        assert_eq!(pod.status, PodStatus::Running);
+        assert_eq!(pod.status_message, None);
+        assert_eq!(pod.restart_count, 4);
+        assert_eq!(pod.display_status(), "Running");
+        assert!(!pod.ready);
+
+        // Readiness reports only stick while the pod is Running.
+        update(serde_json::json!({ "status": "Running", "ready": true }))
+            .await
+            .unwrap();
+        assert!(stored().await.ready);
+        update(serde_json::json!("Scheduled")).await.unwrap();
+        assert!(!stored().await.ready);
+        update(serde_json::json!({ "status": "Scheduled", "ready": true }))
+            .await
+            .unwrap();
+        assert!(!stored().await.ready);
+
+        // A reported pod IP sticks until another one is reported.
+        update(serde_json::json!({ "status": "Running", "pod_ip": "10.42.0.2" }))
+            .await
+            .unwrap();
+        assert_eq!(stored().await.pod_ip.as_deref(), Some("10.42.0.2"));
+        update(serde_json::json!({ "status": "Running", "ready": true }))
+            .await
+            .unwrap();
+        assert_eq!(stored().await.pod_ip.as_deref(), Some("10.42.0.2"));
+
+        // Container statuses likewise, and GET serves them.
+        update(serde_json::json!({
+            "status": "Running",
+            "container_statuses": [{
+                "name": "web",
+                "image": "nginx",
+                "state": { "waiting": { "reason": "ImagePullBackOff" } },
+                "restart_count": 2
+            }]
+        }))
+        .await
+        .unwrap();
+        update(serde_json::json!({ "status": "Running", "ready": true }))
+            .await
+            .unwrap();
+        let pod = stored().await;
+        assert_eq!(pod.container_statuses.len(), 1);
+        assert_eq!(pod.container_statuses[0].restart_count, 2);
+        assert_eq!(
+            pod.container_statuses[0].state.to_string(),
+            "Waiting (ImagePullBackOff)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pod_logs_selects_container() {
+        // Stand-in for the agent's log endpoint: echoes the container ID.
+        let agent = axum::Router::new().route(
+            "/containers/{id}/logs",
+            axum::routing::get(|AxumPath(id): AxumPath<String>| async move {
+                format!("log of {}\n", id)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, agent).await.ok() });
+
+        let state = test_state("pod-logs-container").await;
+        let node = serde_json::json!({
+            "id": "n1",
+            "name": "node-a",
+            "address": "127.0.0.1",
+            "agent_api_port": port,
+            "status": "Ready",
+            "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:00:00Z",
+            "labels": {}
+        });
+        state
+            .store
+            .put(
+                "/registry/nodes/node-a",
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+        let mut pod = sample_pod();
+        let mut sidecar = pod.spec.containers[0].clone();
+        sidecar.name = "shipper".to_string();
+        pod.spec.containers.push(sidecar);
+        state
+            .store
+            .put(
+                "/registry/pods/default/web-0",
+                &serde_json::to_vec(&pod).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let logs = |container: Option<&str>| {
+            pod_logs(
+                State(state.clone()),
+                AxumPath(("default".to_string(), "web-0".to_string())),
+                Query(PodLogQuery {
+                    container: container.map(String::from),
+                    follow: false,
+                    since: None,
+                    tail: None,
+                    timestamps: false,
+                }),
+            )
+        };
+
+        let resp = logs(None).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(body["logs"][0], "log of pod-1-nginx");
+
+        let resp = logs(Some("shipper")).await.into_response();
+        let body: serde_json::Value = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(body["logs"][0], "log of pod-1-shipper");
+
+        let resp = logs(Some("missing")).await.into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_pod_logs_presents_agent_token() {
+        // Stand-in for an agent that only answers its control plane.
+        let agent = axum::Router::new().route(
+            "/containers/{id}/logs",
+            axum::routing::get(|headers: axum::http::HeaderMap| async move {
+                match headers.get(axum::http::header::AUTHORIZATION) {
+                    Some(v) if v == "Bearer agent-secret" => (StatusCode::OK, "hello\n"),
+                    _ => (StatusCode::UNAUTHORIZED, "missing agent token"),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, agent).await.ok() });
+
+        let state = test_state("pod-logs-agent-token").await;
+        let node = serde_json::json!({
+            "id": "n1",
+            "name": "node-a",
+            "address": "127.0.0.1",
+            "agent_api_port": port,
+            "status": "Ready",
+            "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:00:00Z",
+            "labels": {}
+        });
+        let put = |key: &'static str, value: Vec<u8>| {
+            let state = state.clone();
+            async move { state.store.put(key, &value).await.unwrap() }
+        };
+        put("/registry/nodes/node-a", serde_json::to_vec(&node).unwrap()).await;
+        put(
+            "/registry/pods/default/web-0",
+            serde_json::to_vec(&sample_pod()).unwrap(),
+        )
+        .await;
+
+        let logs = || {
+            pod_logs(
+                State(state.clone()),
+                AxumPath(("default".to_string(), "web-0".to_string())),
+                Query(PodLogQuery {
+                    container: None,
+                    follow: false,
+                    since: None,
+                    tail: None,
+                    timestamps: false,
+                }),
+            )
+        };
+
+        // A node that never got a token is asked without one.
+        let resp = logs().await.into_response();
+        assert_eq!(resp.status(), StatusCode::BAD_GATEWAY);
+
+        put("/registry/agenttokens/node-a", b"agent-secret".to_vec()).await;
+        let resp = logs().await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(body["logs"][0], "hello");
+    }
+
+    fn node_port_service(name: &str, node_port: Option<u16>) -> pkg_types::service::Service {
+        serde_json::from_value(serde_json::json!({
+            "id": "",
+            "name": name,
+            "namespace": "default",
+            "spec": {
+                "ports": [{ "name": "http", "port": 80, "target_port": 8080, "node_port": node_port }],
+                "service_type": "NodePort",
+            },
+            "created_at": "2024-02-25T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    async fn create_svc(
+        state: &AppState,
+        svc: pkg_types::service::Service,
+    ) -> axum::response::Response {
+        create_service(
+            State(state.clone()),
+            AxumPath("default".to_string()),
+            Json(svc),
+        )
+        .await
+        .into_response()
+    }
+
+    fn node_port_of(body: &str) -> Option<u16> {
+        let svc: pkg_types::service::Service = serde_json::from_str(body).unwrap();
+        svc.spec.ports[0].node_port
+    }
+
+    #[tokio::test]
+    async fn test_node_port_allocation() {
+        let state = test_state("node-ports").await;
+
+        let resp = create_svc(&state, node_port_service("web", None)).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let created = body_text(resp).await;
+        assert_eq!(node_port_of(&created), Some(30000));
+
+        // An explicit port someone else holds is rejected.
+        let resp = create_svc(&state, node_port_service("api", Some(30000))).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            api_error(resp).await.message,
+            "node port 30000 is already allocated"
+        );
+        let resp = create_svc(&state, node_port_service("api", Some(8080))).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // Re-applying keeps the allocated port.
+        let mut svc = node_port_service("web", None);
+        svc.resource_version = serde_json::from_str::<pkg_types::service::Service>(&created)
+            .unwrap()
+            .resource_version;
+        let resp = apply_service(
+            State(state.clone()),
+            AxumPath(("default".to_string(), "web".to_string())),
+            Query(ApplyQuery::default()),
+            Json(svc),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(node_port_of(&body_text(resp).await), Some(30000));
+
+        // Deleting the service frees its port.
+        let resp = delete_resource(
+            State(state.clone()),
+            AxumPath((
+                "services".to_string(),
+                "default".to_string(),
+                "web".to_string(),
+            )),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+        let resp = create_svc(&state, node_port_service("api", Some(30000))).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_node_port_requires_node_port_type() {
+        let state = test_state("node-port-type").await;
+        let mut svc = node_port_service("web", Some(30000));
+        svc.spec.service_type = pkg_types::service::ServiceType::ClusterIP;
+        let resp = create_svc(&state, svc).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            api_error(resp).await.message,
+            "port 'http' sets node_port, but service type is ClusterIP"
+        );
+    }
 }
@@ -0,0 +1,310 @@
+use anyhow::Context;
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use pkg_state::client::PutOutcome;
+use pkg_types::deployment::{Deployment, DeploymentRevision, DeploymentRollback};
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::replicaset::ReplicaSet;
+use tracing::info;
+
+use crate::AppState;
+use crate::error::ApiResult;
+use crate::handlers::resources::version_conflict;
+
+/// GET /api/v1/namespaces/:ns/deployments/:name/revisions — the deployment's
+/// ReplicaSets, oldest revision first.
+pub async fn list_revisions(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+) -> ApiResult<Json<Vec<DeploymentRevision>>> {
+    let (_, deploy) = load_deployment(&state, &ns, &name).await?;
+    let owned = owned_replicasets(&state, &ns, &deploy)
+        .await
+        .with_context(|| format!("listing revisions of {}/{}", ns, name))?;
+    let current = serde_json::to_value(&deploy.spec.template).ok();
+    let revisions = owned
+        .into_iter()
+        .map(|rs| DeploymentRevision {
+            revision: rs.revision,
+            current: serde_json::to_value(&rs.spec.template).ok() == current,
+            images: rs
+                .spec
+                .template
+                .containers
+                .iter()
+                .map(|c| c.image.clone())
+                .collect(),
+            replicaset: rs.name,
+            change_cause: rs.change_cause,
+            created_at: rs.created_at,
+        })
+        .collect();
+    Ok(Json(revisions))
+}
+
+/// POST /api/v1/namespaces/:ns/deployments/:name/rollback — copy the template
+/// of an earlier revision back into the deployment spec. The controller then
+/// rolls it out as the next revision.
+pub async fn rollback_deployment(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Json(req): Json<DeploymentRollback>,
+) -> ApiResult<Json<serde_json::Value>> {
+    let (key, mut deploy) = load_deployment(&state, &ns, &name).await?;
+    let owned = owned_replicasets(&state, &ns, &deploy)
+        .await
+        .with_context(|| format!("listing revisions of {}/{}", ns, name))?;
+
+    let current = serde_json::to_value(&deploy.spec.template).ok();
+    let target = match req.to_revision.filter(|r| *r > 0) {
+        Some(revision) => owned.iter().find(|rs| rs.revision == revision),
+        // Undo goes to the newest revision running a different template.
+        None => owned
+            .iter()
+            .rev()
+            .find(|rs| serde_json::to_value(&rs.spec.template).ok() != current),
+    };
+    let Some(target) = target else {
+        let available: Vec<String> = owned.iter().map(|rs| rs.revision.to_string()).collect();
+        let msg = match req.to_revision.filter(|r| *r > 0) {
+            Some(revision) => format!(
+                "deployment '{}' has no revision {} (available: {})",
+                name,
+                revision,
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            ),
+            None => format!("deployment '{}' has no previous revision", name),
+        };
+        return Err(ApiError::new(ErrorReason::NotFound, msg).into());
+    };
+
+    let revision = target.revision;
+    if serde_json::to_value(&target.spec.template).ok() == current {
+        return Ok(Json(serde_json::json!({
+            "status": "unchanged",
+            "revision": revision,
+        })));
+    }
+    deploy.spec.template = target.spec.template.clone();
+    deploy.change_cause = target.change_cause.clone();
+    deploy.generation += 1;
+
+    // The target was picked from the deployment as read; refuse to write
+    // over a change made since.
+    let data = serde_json::to_vec(&deploy)?;
+    if let PutOutcome::Conflict(current) = state
+        .store
+        .put_if_version(&key, &data, deploy.resource_version)
+        .await
+        .with_context(|| format!("rolling back deployment {}/{}", ns, name))?
+    {
+        return Err(version_conflict(
+            "deployment",
+            &name,
+            deploy.resource_version,
+            current.as_deref(),
+        ));
+    }
+    info!(
+        "Rolled back deployment {}/{} to revision {}",
+        ns, name, revision
+    );
+    Ok(Json(serde_json::json!({
+        "status": "rolled back",
+        "revision": revision,
+    })))
+}
+
+async fn load_deployment(
+    state: &AppState,
+    ns: &str,
+    name: &str,
+) -> ApiResult<(String, Deployment)> {
+    let key = format!("/registry/deployments/{}/{}", ns, name);
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("deployment", name, Some(ns)).into());
+    };
+    Ok((key, serde_json::from_slice(&data)?))
+}
+
+/// ReplicaSets owned by `deploy`, sorted by revision.
+async fn owned_replicasets(
+    state: &AppState,
+    ns: &str,
+    deploy: &Deployment,
+) -> anyhow::Result<Vec<ReplicaSet>> {
+    let prefix = format!("/registry/replicasets/{}/", ns);
+    let mut owned: Vec<ReplicaSet> = state
+        .store
+        .list_prefix(&prefix)
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice::<ReplicaSet>(&v).ok())
+        .filter(|rs| rs.owner_ref.as_deref() == Some(&deploy.id))
+        .collect();
+    owned.sort_by_key(|rs| (rs.revision, rs.created_at));
+    Ok(owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{api_error, body_text, test_state};
+    use axum::{http::StatusCode, response::IntoResponse};
+    use chrono::Utc;
+
+    fn replicaset(revision: u64, image: &str, cause: &str) -> ReplicaSet {
+        serde_json::from_value(serde_json::json!({
+            "id": format!("rs-{}", revision),
+            "name": format!("web-{}", revision),
+            "namespace": "default",
+            "spec": {
+                "replicas": if revision == 3 { 2 } else { 0 },
+                "template": { "containers": [{ "name": "web", "image": image }] },
+            },
+            "owner_ref": "web-id",
+            "template_hash": format!("hash-{}", revision),
+            "revision": revision,
+            "change_cause": cause,
+            "created_at": Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    /// A deployment updated three times, as the controller leaves it.
+    async fn seed(state: &AppState) {
+        let deploy: Deployment = serde_json::from_value(serde_json::json!({
+            "id": "web-id",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "replicas": 2,
+                "template": { "containers": [{ "name": "web", "image": "nginx:1.2" }] },
+            },
+            "generation": 3,
+            "change_cause": "bump to 1.2",
+            "created_at": Utc::now(),
+        }))
+        .unwrap();
+        let put = |key: String, data: Vec<u8>| {
+            let store = state.store.clone();
+            async move { store.put(&key, &data).await.unwrap() }
+        };
+        put(
+            "/registry/deployments/default/web".to_string(),
+            serde_json::to_vec(&deploy).unwrap(),
+        )
+        .await;
+        for (revision, image, cause) in [
+            (1, "nginx:1.0", "initial"),
+            (2, "nginx:1.1", "bump to 1.1"),
+            (3, "nginx:1.2", "bump to 1.2"),
+        ] {
+            put(
+                format!("/registry/replicasets/default/web-{}", revision),
+                serde_json::to_vec(&replicaset(revision, image, cause)).unwrap(),
+            )
+            .await;
+        }
+    }
+
+    async fn stored(state: &AppState) -> Deployment {
+        let data = state
+            .store
+            .get("/registry/deployments/default/web")
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    fn path() -> Path<(String, String)> {
+        Path(("default".to_string(), "web".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_list_revisions() {
+        let state = test_state("rollout-history").await;
+        seed(&state).await;
+
+        let resp = list_revisions(State(state), path()).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let revisions: Vec<DeploymentRevision> =
+            serde_json::from_str(&body_text(resp).await).unwrap();
+        let summary: Vec<(u64, &str, bool)> = revisions
+            .iter()
+            .map(|r| (r.revision, r.images[0].as_str(), r.current))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, "nginx:1.0", false),
+                (2, "nginx:1.1", false),
+                (3, "nginx:1.2", true),
+            ]
+        );
+        assert_eq!(revisions[1].change_cause.as_deref(), Some("bump to 1.1"));
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_template() {
+        let state = test_state("rollout-undo").await;
+        seed(&state).await;
+
+        let resp = rollback_deployment(
+            State(state.clone()),
+            path(),
+            Json(DeploymentRollback {
+                to_revision: Some(1),
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let deploy = stored(&state).await;
+        assert_eq!(deploy.spec.template.containers[0].image, "nginx:1.0");
+        assert_eq!(deploy.change_cause.as_deref(), Some("initial"));
+        assert_eq!(deploy.generation, 4);
+
+        // Without a revision, undo returns to the template just replaced.
+        let resp = rollback_deployment(State(state.clone()), path(), Json(Default::default()))
+            .await
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            stored(&state).await.spec.template.containers[0].image,
+            "nginx:1.2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rollback_to_missing_revision_is_not_found() {
+        let state = test_state("rollout-missing").await;
+        seed(&state).await;
+
+        let resp = rollback_deployment(
+            State(state.clone()),
+            path(),
+            Json(DeploymentRollback {
+                to_revision: Some(7),
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            api_error(resp).await.message,
+            "deployment 'web' has no revision 7 (available: 1, 2, 3)"
+        );
+        assert_eq!(
+            stored(&state).await.spec.template.containers[0].image,
+            "nginx:1.2"
+        );
+    }
+}
@@ -1,26 +1,26 @@
-use axum::{Json, http::StatusCode, response::IntoResponse};
+use pkg_types::error::{ApiError, ErrorReason};
 use tracing::info;
 
+use crate::error::ErrorResponse;
+
 /// GET /api/v1/runtime — runtime info is available per-agent.
-pub async fn get_runtime_info() -> impl IntoResponse {
+pub async fn get_runtime_info() -> ErrorResponse {
     info!("Runtime info requested — not available on control plane");
-    (
-        StatusCode::NOT_IMPLEMENTED,
-        Json(serde_json::json!({
-            "error": "Runtime info is available per-agent via the Agent API. \
-                      The server (control plane) does not run containers."
-        })),
+    ApiError::new(
+        ErrorReason::NotImplemented,
+        "Runtime info is available per-agent via the Agent API. \
+         The server (control plane) does not run containers.",
     )
+    .into()
 }
 
 /// PUT /api/v1/runtime/upgrade — runtime upgrade is handled per-agent.
-pub async fn upgrade_runtime() -> impl IntoResponse {
+pub async fn upgrade_runtime() -> ErrorResponse {
     info!("Runtime upgrade requested — not available on control plane");
-    (
-        StatusCode::NOT_IMPLEMENTED,
-        Json(serde_json::json!({
-            "error": "Runtime upgrade is handled per-agent via the Agent API. \
-                      The server (control plane) does not manage container runtimes."
-        })),
+    ApiError::new(
+        ErrorReason::NotImplemented,
+        "Runtime upgrade is handled per-agent via the Agent API. \
+         The server (control plane) does not manage container runtimes.",
     )
+    .into()
 }
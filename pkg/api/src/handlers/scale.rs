@@ -0,0 +1,377 @@
+use anyhow::Context;
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderValue, StatusCode, header},
+    response::IntoResponse,
+};
+use pkg_state::client::{MAX_UPDATE_ATTEMPTS, PutOutcome, resource_version};
+use pkg_types::deployment::Deployment;
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::hpa::HorizontalPodAutoscaler;
+use pkg_types::replicaset::ReplicaSet;
+use pkg_types::scale::ScaleRequest;
+use serde::{Serialize, de::DeserializeOwned};
+use tracing::info;
+
+use crate::AppState;
+use crate::error::ApiResult;
+use crate::handlers::resources::version_conflict;
+
+/// PUT /api/v1/namespaces/:ns/deployments/:name/scale — set the desired
+/// replica count and leave the rollout to the controller.
+pub async fn scale_deployment(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Json(req): Json<ScaleRequest>,
+) -> ApiResult {
+    let warnings = autoscalers_of(&state, &ns, &name)
+        .await
+        .with_context(|| format!("listing HPAs in {}", ns))?
+        .into_iter()
+        .map(|hpa| {
+            format!(
+                "deployment '{}' is managed by HorizontalPodAutoscaler '{}', which will \
+                 override the replica count",
+                name, hpa
+            )
+        })
+        .collect();
+    let key = format!("/registry/deployments/{}/{}", ns, name);
+    scale_object::<Deployment>(
+        &state,
+        &key,
+        "deployment",
+        &ns,
+        &name,
+        req,
+        |deploy| &mut deploy.spec.replicas,
+        |deploy| deploy.generation += 1,
+        |_| warnings,
+    )
+    .await
+}
+
+/// PUT /api/v1/namespaces/:ns/replicasets/:name/scale
+pub async fn scale_replicaset(
+    State(state): State<AppState>,
+    Path((ns, name)): Path<(String, String)>,
+    Json(req): Json<ScaleRequest>,
+) -> ApiResult {
+    let key = format!("/registry/replicasets/{}/{}", ns, name);
+    scale_object::<ReplicaSet>(
+        &state,
+        &key,
+        "replicaset",
+        &ns,
+        &name,
+        req,
+        |rs| &mut rs.spec.replicas,
+        |_| {},
+        |rs| match rs.owner_ref {
+            Some(_) => vec![format!(
+                "replicaset '{}' is owned by a deployment, which will override the \
+                 replica count; scale the deployment instead",
+                name
+            )],
+            None => Vec::new(),
+        },
+    )
+    .await
+}
+
+/// Names of the HPAs in `ns` targeting deployment `name`.
+async fn autoscalers_of(state: &AppState, ns: &str, name: &str) -> anyhow::Result<Vec<String>> {
+    Ok(state
+        .store
+        .list_prefix(&format!("/registry/hpa/{}/", ns))
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice::<HorizontalPodAutoscaler>(&v).ok())
+        .filter(|hpa| hpa.spec.target_deployment == name)
+        .map(|hpa| hpa.name)
+        .collect())
+}
+
+/// Validate `req` against the stored object, write the new replica count,
+/// and return the updated object. `warnings` are sent as `Warning` headers.
+#[allow(clippy::too_many_arguments)]
+async fn scale_object<T>(
+    state: &AppState,
+    key: &str,
+    kind: &str,
+    ns: &str,
+    name: &str,
+    req: ScaleRequest,
+    replicas: impl Fn(&mut T) -> &mut u32,
+    on_change: impl Fn(&mut T),
+    warnings: impl FnOnce(&T) -> Vec<String>,
+) -> ApiResult
+where
+    T: Serialize + DeserializeOwned,
+{
+    let Ok(desired) = u32::try_from(req.replicas) else {
+        return Err(ApiError::new(
+            ErrorReason::BadRequest,
+            format!(
+                "replicas must be between 0 and {}, got {}",
+                u32::MAX,
+                req.replicas
+            ),
+        )
+        .into());
+    };
+
+    let mut attempts = 0;
+    let obj: T = loop {
+        attempts += 1;
+        let Some(data) = state.store.get(key).await? else {
+            return Err(ApiError::not_found(kind, name, Some(ns)).into());
+        };
+        let stored = resource_version(&data);
+        if let Some(sent) = req.resource_version
+            && sent != stored
+        {
+            return Err(version_conflict(kind, name, sent, Some(&data)));
+        }
+        let mut obj: T = serde_json::from_slice(&data)?;
+
+        let current = *replicas(&mut obj);
+        if let Some(expected) = req.current_replicas
+            && expected != current
+        {
+            return Err(ApiError::new(
+                ErrorReason::Conflict,
+                format!(
+                    "{} '{}' has {} replicas, expected {}",
+                    kind, name, current, expected
+                ),
+            )
+            .into());
+        }
+        if desired == current {
+            break obj;
+        }
+
+        *replicas(&mut obj) = desired;
+        on_change(&mut obj);
+        let data = serde_json::to_vec(&obj)?;
+        match state
+            .store
+            .put_if_version(key, &data, stored)
+            .await
+            .with_context(|| format!("scaling {} {}", kind, name))?
+        {
+            PutOutcome::Stored(data) => {
+                info!("Scaled {} {} from {} to {}", kind, name, current, desired);
+                break serde_json::from_slice(&data)?;
+            }
+            // Written by someone else in between: scale the new copy, unless
+            // the caller pinned the version it read.
+            PutOutcome::Conflict(_)
+                if req.resource_version.is_none() && attempts < MAX_UPDATE_ATTEMPTS =>
+            {
+                continue;
+            }
+            PutOutcome::Conflict(current) => {
+                let sent = req.resource_version.unwrap_or(stored);
+                return Err(version_conflict(kind, name, sent, current.as_deref()));
+            }
+        }
+    };
+
+    let warnings = warnings(&obj);
+    let mut resp = (StatusCode::OK, Json(obj)).into_response();
+    for w in warnings {
+        // RFC 7234 warn-code 299: miscellaneous persistent warning.
+        if let Ok(value) = HeaderValue::from_str(&format!("299 - \"{}\"", w)) {
+            resp.headers_mut().append(header::WARNING, value);
+        }
+    }
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{api_error, body_text, test_state};
+    use axum::response::Response;
+    use chrono::Utc;
+
+    async fn seed(state: &AppState, hpa: bool) {
+        let deploy = serde_json::json!({
+            "id": "web-id",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "replicas": 3,
+                "template": { "containers": [{ "name": "web", "image": "nginx" }] },
+            },
+            "generation": 1,
+            "created_at": Utc::now(),
+        });
+        state
+            .store
+            .put(
+                "/registry/deployments/default/web",
+                &serde_json::to_vec(&deploy).unwrap(),
+            )
+            .await
+            .unwrap();
+        if hpa {
+            let hpa = serde_json::json!({
+                "id": "hpa-id",
+                "name": "web-hpa",
+                "namespace": "default",
+                "spec": {
+                    "target_deployment": "web",
+                    "min_replicas": 2,
+                    "max_replicas": 6,
+                    "metrics": { "cpu_utilization_percent": 80 },
+                },
+                "created_at": Utc::now(),
+            });
+            state
+                .store
+                .put(
+                    "/registry/hpa/default/web-hpa",
+                    &serde_json::to_vec(&hpa).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn scale(state: &AppState, replicas: i64, current: Option<u32>) -> Response {
+        scale_deployment(
+            State(state.clone()),
+            Path(("default".to_string(), "web".to_string())),
+            Json(ScaleRequest {
+                replicas,
+                current_replicas: current,
+                resource_version: None,
+            }),
+        )
+        .await
+        .into_response()
+    }
+
+    #[tokio::test]
+    async fn test_scale_deployment() {
+        let state = test_state("scale-ok").await;
+        seed(&state, false).await;
+
+        let resp = scale(&state, 5, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get(header::WARNING).is_none());
+        let deploy: Deployment = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(deploy.spec.replicas, 5);
+        assert_eq!(deploy.generation, 2);
+
+        let resp = scale(&state, 0, Some(5)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let deploy: Deployment = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(deploy.spec.replicas, 0);
+    }
+
+    #[tokio::test]
+    async fn test_scale_rejects_invalid_replicas() {
+        let state = test_state("scale-invalid").await;
+        seed(&state, false).await;
+
+        let resp = scale(&state, -1, None).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(body_text(resp).await.contains("got -1"));
+
+        let resp = scale_replicaset(
+            State(state.clone()),
+            Path(("default".to_string(), "missing".to_string())),
+            Json(ScaleRequest {
+                replicas: 1,
+                current_replicas: None,
+                resource_version: None,
+            }),
+        )
+        .await
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_scale_precondition_conflict() {
+        let state = test_state("scale-conflict").await;
+        seed(&state, false).await;
+
+        let resp = scale(&state, 5, Some(2)).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            api_error(resp).await.message,
+            "deployment 'web' has 3 replicas, expected 2"
+        );
+        let data = state
+            .store
+            .get("/registry/deployments/default/web")
+            .await
+            .unwrap()
+            .unwrap();
+        let deploy: Deployment = serde_json::from_slice(&data).unwrap();
+        assert_eq!(deploy.spec.replicas, 3);
+    }
+
+    #[tokio::test]
+    async fn test_scale_pinned_to_resource_version() {
+        let state = test_state("scale-version").await;
+        seed(&state, false).await;
+        let read: Deployment = serde_json::from_slice(
+            &state
+                .store
+                .get("/registry/deployments/default/web")
+                .await
+                .unwrap()
+                .unwrap(),
+        )
+        .unwrap();
+        let scale_at = |replicas, version| {
+            scale_deployment(
+                State(state.clone()),
+                Path(("default".to_string(), "web".to_string())),
+                Json(ScaleRequest {
+                    replicas,
+                    current_replicas: None,
+                    resource_version: Some(version),
+                }),
+            )
+        };
+
+        let resp = scale_at(5, read.resource_version).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // A second scale from the same read loses and gets the current object.
+        let err = api_error(scale_at(1, read.resource_version).await.into_response()).await;
+        assert_eq!(err.reason, ErrorReason::Conflict);
+        let current: Deployment = serde_json::from_value(err.current.unwrap()).unwrap();
+        assert_eq!(current.spec.replicas, 5);
+        assert!(current.resource_version > read.resource_version);
+
+        let resp = scale_at(1, current.resource_version).await.into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let deploy: Deployment = serde_json::from_str(&body_text(resp).await).unwrap();
+        assert_eq!(deploy.spec.replicas, 1);
+    }
+
+    #[tokio::test]
+    async fn test_scale_warns_about_hpa() {
+        let state = test_state("scale-hpa").await;
+        seed(&state, true).await;
+
+        let resp = scale(&state, 5, None).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let warning = resp.headers().get(header::WARNING).unwrap();
+        assert!(
+            warning
+                .to_str()
+                .unwrap()
+                .contains("HorizontalPodAutoscaler 'web-hpa'")
+        );
+    }
+}
@@ -0,0 +1,290 @@
+//! Node taints set by hand. The scheduler reads a node's taints on every
+//! cycle, so a change applies to the next pod it places; the taint eviction
+//! controller stops running pods that do not tolerate a `NoExecute` taint.
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    response::IntoResponse,
+};
+use pkg_controllers::events;
+use pkg_types::error::{ApiError, ErrorReason, FieldError};
+use pkg_types::event::{CLUSTER_EVENT_NAMESPACE, Event};
+use pkg_types::node::{Node, Taint};
+use pkg_types::pod::TaintEffect;
+use pkg_types::validate::Validate;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::AppState;
+use crate::error::ApiResult;
+
+/// Body of `PUT /api/v1/nodes/:name/taints`. The effect is checked by the
+/// handler so an unknown one is reported against its field.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaintBody {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+    pub effect: String,
+}
+
+/// Query of `DELETE /api/v1/nodes/:name/taints`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaintQuery {
+    pub key: String,
+    /// Only remove the taint with this effect; all of `key`'s when unset.
+    #[serde(default)]
+    pub effect: Option<String>,
+}
+
+/// PUT /api/v1/nodes/:name/taints — add a taint, or change the value of the
+/// one with the same key and effect. Returns the node.
+pub async fn put_node_taint(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Json(body): Json<TaintBody>,
+) -> ApiResult {
+    let effect = body.effect.parse::<TaintEffect>();
+    let taint = Taint {
+        key: body.key,
+        value: body.value,
+        effect: effect.clone().unwrap_or_default(),
+    };
+    let mut errors = taint.validate();
+    if let Err(e) = effect {
+        errors.push(FieldError::new("effect", e));
+    }
+    if !errors.is_empty() {
+        return Err(ApiError::invalid("taint", &taint.key, errors).into());
+    }
+
+    let key = format!("/registry/nodes/{}", name);
+    let node = state
+        .store
+        .update::<Node, _>(&key, |node| {
+            match node
+                .taints
+                .iter_mut()
+                .find(|t| t.key == taint.key && t.effect == taint.effect)
+            {
+                Some(t) if t.value == taint.value => false,
+                Some(t) => {
+                    t.value = taint.value.clone();
+                    true
+                }
+                None => {
+                    node.taints.push(taint.clone());
+                    true
+                }
+            }
+        })
+        .await?;
+    let Some(node) = node else {
+        return Err(ApiError::not_found("node", &name, None).into());
+    };
+    info!("Node {} tainted {}", name, taint);
+    record_taint_event(&state, &name, "Tainted", format!("Added taint {}", taint)).await;
+    Ok(Json(node).into_response())
+}
+
+/// DELETE /api/v1/nodes/:name/taints?key=..[&effect=..] — remove the taints
+/// with `key`, or only the one with `effect`. Returns the node; 404 when no
+/// taint matched.
+pub async fn delete_node_taint(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<TaintQuery>,
+) -> ApiResult {
+    let effect = match query.effect.as_deref().map(str::parse::<TaintEffect>) {
+        None => None,
+        Some(Ok(effect)) => Some(effect),
+        Some(Err(e)) => {
+            return Err(
+                ApiError::invalid("taint", &query.key, vec![FieldError::new("effect", e)]).into(),
+            );
+        }
+    };
+
+    let key = format!("/registry/nodes/{}", name);
+    let mut removed: Vec<Taint> = Vec::new();
+    let node = state
+        .store
+        .update::<Node, _>(&key, |node| {
+            let (gone, kept) = std::mem::take(&mut node.taints)
+                .into_iter()
+                .partition(|t| t.key == query.key && effect.is_none_or(|e| t.effect == e));
+            node.taints = kept;
+            removed = gone;
+            !removed.is_empty()
+        })
+        .await?;
+    let Some(node) = node else {
+        return Err(ApiError::not_found("node", &name, None).into());
+    };
+    if removed.is_empty() {
+        return Err(ApiError::new(
+            ErrorReason::NotFound,
+            format!("node '{}' has no taint '{}'", name, query.key),
+        )
+        .into());
+    }
+    for taint in &removed {
+        info!("Node {} untainted {}", name, taint);
+        record_taint_event(
+            &state,
+            &name,
+            "Untainted",
+            format!("Removed taint {}", taint),
+        )
+        .await;
+    }
+    Ok(Json(node).into_response())
+}
+
+async fn record_taint_event(state: &AppState, node_name: &str, reason: &str, message: String) {
+    events::record(
+        &state.store,
+        Event::normal("node", CLUSTER_EVENT_NAMESPACE, node_name, reason, message),
+    )
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::{api_error, test_state};
+    use axum::http::StatusCode;
+
+    async fn seed_node(state: &AppState) {
+        let node = serde_json::json!({
+            "id": "n1",
+            "name": "node-a",
+            "address": "10.0.0.5",
+            "agent_api_port": 10250,
+            "status": "Ready",
+            "registered_at": "2024-02-25T00:00:00Z",
+            "last_heartbeat": "2024-02-25T00:00:00Z",
+            "labels": {},
+        });
+        state
+            .store
+            .put(
+                "/registry/nodes/node-a",
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn put(state: &AppState, key: &str, value: &str, effect: &str) -> ApiResult {
+        put_node_taint(
+            State(state.clone()),
+            Path("node-a".to_string()),
+            Json(TaintBody {
+                key: key.to_string(),
+                value: value.to_string(),
+                effect: effect.to_string(),
+            }),
+        )
+        .await
+    }
+
+    async fn delete(state: &AppState, key: &str, effect: Option<&str>) -> ApiResult {
+        delete_node_taint(
+            State(state.clone()),
+            Path("node-a".to_string()),
+            Query(TaintQuery {
+                key: key.to_string(),
+                effect: effect.map(str::to_string),
+            }),
+        )
+        .await
+    }
+
+    async fn taints(state: &AppState) -> Vec<String> {
+        let data = state
+            .store
+            .get("/registry/nodes/node-a")
+            .await
+            .unwrap()
+            .unwrap();
+        let node: Node = serde_json::from_slice(&data).unwrap();
+        node.taints.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_put_and_delete_taints() {
+        let state = test_state("taints").await;
+        seed_node(&state).await;
+
+        put(&state, "dedicated", "gpu", "NoSchedule").await.unwrap();
+        put(&state, "dedicated", "gpu", "NoExecute").await.unwrap();
+        // Same key and effect: the value changes in place.
+        put(&state, "dedicated", "ml", "NoSchedule").await.unwrap();
+        assert_eq!(
+            taints(&state).await,
+            ["dedicated=ml:NoSchedule", "dedicated=gpu:NoExecute"]
+        );
+
+        delete(&state, "dedicated", Some("NoExecute"))
+            .await
+            .unwrap();
+        assert_eq!(taints(&state).await, ["dedicated=ml:NoSchedule"]);
+        put(&state, "maintenance", "", "PreferNoSchedule")
+            .await
+            .unwrap();
+        delete(&state, "dedicated", None).await.unwrap();
+        assert_eq!(taints(&state).await, ["maintenance:PreferNoSchedule"]);
+
+        let err = api_error(
+            delete(&state, "dedicated", None)
+                .await
+                .unwrap_err()
+                .into_response(),
+        )
+        .await;
+        assert_eq!(err.reason, ErrorReason::NotFound);
+        assert_eq!(err.message, "node 'node-a' has no taint 'dedicated'");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_taints_are_rejected() {
+        let state = test_state("taints-invalid").await;
+        seed_node(&state).await;
+
+        let resp = put(&state, "bad key", "x", "NoRun")
+            .await
+            .unwrap_err()
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let err = api_error(resp).await;
+        let fields: Vec<&str> = err.details.iter().map(|d| d.field.as_str()).collect();
+        assert_eq!(fields, ["key", "effect"]);
+        assert!(err.details[1].message.contains("NoExecute"));
+
+        let err = api_error(
+            delete(&state, "dedicated", Some("Never"))
+                .await
+                .unwrap_err()
+                .into_response(),
+        )
+        .await;
+        assert_eq!(err.reason, ErrorReason::Invalid);
+        assert!(taints(&state).await.is_empty());
+
+        let resp = put_node_taint(
+            State(state.clone()),
+            Path("missing".to_string()),
+            Json(TaintBody {
+                key: "dedicated".to_string(),
+                value: String::new(),
+                effect: "NoSchedule".to_string(),
+            }),
+        )
+        .await
+        .unwrap_err()
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+}
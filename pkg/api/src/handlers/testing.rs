@@ -0,0 +1,59 @@
+//! Shared helpers for handler unit tests.
+
+use std::sync::Arc;
+
+use crate::AppState;
+
+/// An `AppState` backed by a fresh store in a per-test temp directory, with
+/// the namespaces the server seeds on startup.
+pub(crate) async fn test_state(label: &str) -> AppState {
+    let dir = std::env::temp_dir().join(format!("k3rs-api-test-{}-{}", label, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let store = pkg_state::client::StateStore::new(&dir.to_string_lossy())
+        .await
+        .unwrap();
+    for name in pkg_constants::network::SEED_NAMESPACES {
+        let ns = pkg_types::namespace::Namespace {
+            name: name.to_string(),
+            labels: Default::default(),
+            phase: Default::default(),
+            created_at: chrono::Utc::now(),
+            resource_version: 0,
+        };
+        let key = format!("/registry/namespaces/{}", name);
+        store
+            .put(&key, &serde_json::to_vec(&ns).unwrap())
+            .await
+            .unwrap();
+    }
+    AppState {
+        store,
+        ca: Arc::new(pkg_pki::ca::ClusterCA::new().unwrap()),
+        join_token: "test-token".to_string(),
+        admin_token: "test-admin-token".to_string(),
+        listen_addr: "127.0.0.1:0".to_string(),
+        scheduler: None,
+        metrics: Arc::new(pkg_metrics::MetricsRegistry::new()),
+        backup_dir: None,
+        restore_in_progress: Default::default(),
+        is_leader: Default::default(),
+        server_id: "test-server".to_string(),
+        node_port_range: Default::default(),
+        address_conflict: Default::default(),
+        cluster_cidr: Default::default(),
+        tunnels: Default::default(),
+        log_filter: None,
+    }
+}
+
+pub(crate) async fn body_text(resp: axum::response::Response) -> String {
+    let bytes = axum::body::to_bytes(resp.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+/// The [`pkg_types::error::ApiError`] body of an error response.
+pub(crate) async fn api_error(resp: axum::response::Response) -> pkg_types::error::ApiError {
+    serde_json::from_str(&body_text(resp).await).unwrap()
+}
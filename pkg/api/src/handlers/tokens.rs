@@ -0,0 +1,181 @@
+//! API tokens: minted bound to a role (and optionally a namespace), listed
+//! without their secrets, and revoked by deleting them.
+
+use axum::{
+    Json,
+    extract::{Path as AxumPath, Query, State},
+    http::StatusCode,
+};
+use chrono::Utc;
+use pkg_types::error::{ApiError, FieldError};
+use pkg_types::rbac::{ApiToken, CreateTokenRequest, TokenRole};
+use tracing::info;
+
+use crate::AppState;
+use crate::error::ApiResult;
+use crate::pagination::{Page, PageQuery, list_page};
+
+pub async fn create_token(
+    State(state): State<AppState>,
+    Json(req): Json<CreateTokenRequest>,
+) -> ApiResult<(StatusCode, Json<ApiToken>)> {
+    let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+    let name = req.name.unwrap_or_else(|| id.clone());
+
+    let scope_error = match (req.role, &req.namespace) {
+        (TokenRole::Editor, None) => Some("an editor token must be bound to a namespace"),
+        (TokenRole::Admin | TokenRole::Node, Some(_)) => {
+            Some("admin and node tokens are cluster-wide")
+        }
+        _ => None,
+    };
+    if let Some(message) = scope_error {
+        return Err(
+            ApiError::invalid("token", &name, vec![FieldError::new("namespace", message)]).into(),
+        );
+    }
+    if let Some(ns) = &req.namespace
+        && state
+            .store
+            .get(&format!("/registry/namespaces/{}", ns))
+            .await?
+            .is_none()
+    {
+        return Err(ApiError::not_found("namespace", ns, None).into());
+    }
+
+    let token = ApiToken {
+        id: id.clone(),
+        name,
+        role: req.role,
+        namespace: req.namespace,
+        secret: uuid::Uuid::new_v4().simple().to_string(),
+        created_at: Utc::now(),
+    };
+    state
+        .store
+        .put(
+            &format!("/registry/tokens/{}", id),
+            &serde_json::to_vec(&token)?,
+        )
+        .await?;
+    info!(
+        "Created token {} ({}) with role {}{}",
+        token.id,
+        token.name,
+        token.role,
+        token
+            .namespace
+            .as_ref()
+            .map(|ns| format!(" in namespace {}", ns))
+            .unwrap_or_default()
+    );
+    Ok((StatusCode::CREATED, Json(token)))
+}
+
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<ApiToken>> {
+    let mut page =
+        list_page::<ApiToken>(&state.store, "/registry/tokens/", &page, |_| true).await?;
+    for token in &mut page.items {
+        token.secret.clear();
+    }
+    Ok(page)
+}
+
+pub async fn delete_token(
+    State(state): State<AppState>,
+    AxumPath(id): AxumPath<String>,
+) -> ApiResult<StatusCode> {
+    let key = format!("/registry/tokens/{}", id);
+    if state.store.get(&key).await?.is_none() {
+        return Err(ApiError::not_found("token", &id, None).into());
+    }
+    state.store.delete(&key).await?;
+    info!("Revoked token {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::resolve_token;
+    use crate::handlers::testing::test_state;
+    use pkg_types::error::ErrorReason;
+
+    #[tokio::test]
+    async fn test_create_list_and_revoke() {
+        let state = test_state("tokens").await;
+        state
+            .store
+            .put("/registry/namespaces/dev", b"{}")
+            .await
+            .unwrap();
+
+        let (status, Json(token)) = create_token(
+            State(state.clone()),
+            Json(CreateTokenRequest {
+                name: None,
+                role: TokenRole::Readonly,
+                namespace: Some("dev".to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(token.name, token.id);
+        let user = resolve_token(&state, &token.bearer())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user.role, TokenRole::Readonly);
+        assert_eq!(user.namespace.as_deref(), Some("dev"));
+
+        // Listing never shows the secret.
+        let listed = list_tokens(State(state.clone()), Query(PageQuery::default()))
+            .await
+            .unwrap();
+        assert_eq!(listed.items.len(), 1);
+        assert!(listed.items[0].secret.is_empty());
+
+        delete_token(State(state.clone()), AxumPath(token.id.clone()))
+            .await
+            .unwrap();
+        assert!(
+            resolve_token(&state, &token.bearer())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let err = delete_token(State(state.clone()), AxumPath(token.id))
+            .await
+            .unwrap_err();
+        assert_eq!(err.0.reason, ErrorReason::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_create_rejects_bad_scope() {
+        let state = test_state("tokens-scope").await;
+        let create = |role, namespace: Option<&str>| {
+            create_token(
+                State(state.clone()),
+                Json(CreateTokenRequest {
+                    name: Some("ci".to_string()),
+                    role,
+                    namespace: namespace.map(str::to_string),
+                }),
+            )
+        };
+        let err = create(TokenRole::Editor, None).await.unwrap_err();
+        assert_eq!(err.0.reason, ErrorReason::Invalid);
+        assert_eq!(err.0.details[0].field, "namespace");
+        let err = create(TokenRole::Admin, Some("dev")).await.unwrap_err();
+        assert_eq!(err.0.reason, ErrorReason::Invalid);
+        let err = create(TokenRole::Readonly, Some("missing"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.0.reason, ErrorReason::NotFound);
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Context;
+use axum::{
+    extract::{
+        Path, State,
+        ws::{Message, WebSocketUpgrade},
+    },
+    http::HeaderMap,
+};
+use futures_util::{SinkExt, StreamExt, future};
+use pkg_types::error::{ApiError, ErrorReason};
+use tracing::warn;
+
+use crate::AppState;
+use crate::error::ApiResult;
+use pkg_types::rbac::secrets_match;
+
+/// GET /api/v1/nodes/:name/tunnel — WebSocket an agent keeps open so the
+/// server can reach its agent API without dialing the node (e.g. behind
+/// NAT). Besides its bearer token, the agent proves it is the node by
+/// presenting that node's agent token in `x-k3rs-agent-token`.
+///
+/// Binary frames carry the multiplexed streams (see `pkg_tunnel::frame`).
+pub async fn node_tunnel(
+    State(state): State<AppState>,
+    Path(node_name): Path<String>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> ApiResult {
+    let expected = super::register::agent_token(&state, &node_name)
+        .await
+        .context("looking up agent token")?;
+    let presented = headers
+        .get(pkg_tunnel::AGENT_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok());
+    let authorized = matches!(
+        (presented, expected.as_deref()),
+        (Some(presented), Some(expected)) if secrets_match(presented, expected)
+    );
+    if !authorized {
+        warn!("Rejected tunnel for node {}: bad agent token", node_name);
+        return Err(ApiError::new(
+            ErrorReason::Unauthorized,
+            format!("a tunnel for node '{}' needs its agent token", node_name),
+        )
+        .into());
+    }
+
+    Ok(ws.on_upgrade(move |socket| async move {
+        let (tx, rx) = socket.split();
+        let tx = tx.with(|frame| future::ready(Ok::<_, axum::Error>(Message::Binary(frame))));
+        let rx = rx
+            .take_while(|msg| {
+                future::ready(matches!(msg, Ok(msg) if !matches!(msg, Message::Close(_))))
+            })
+            .filter_map(|msg| {
+                future::ready(match msg {
+                    Ok(Message::Binary(frame)) => Some(frame),
+                    _ => None,
+                })
+            });
+        state.tunnels.serve(&node_name, tx, rx).await;
+    }))
+}
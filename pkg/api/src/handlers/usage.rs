@@ -0,0 +1,160 @@
+//! Resource usage for `k3rsctl top` and the UI: the latest per-pod samples
+//! agents send with their heartbeats, and per-node totals.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use pkg_types::metrics::{NodeMetrics, PodMetrics};
+use pkg_types::node::Node;
+use std::collections::HashMap;
+
+use crate::AppState;
+use crate::error::ApiResult;
+
+/// GET /api/v1/podmetrics — the latest sample of every pod.
+pub async fn list_pod_metrics(State(state): State<AppState>) -> ApiResult<Json<Vec<PodMetrics>>> {
+    Ok(Json(pod_samples(&state, "/registry/metrics/pods/").await?))
+}
+
+/// GET /api/v1/namespaces/{ns}/podmetrics — the latest sample of every pod
+/// in a namespace.
+pub async fn list_namespaced_pod_metrics(
+    State(state): State<AppState>,
+    Path(ns): Path<String>,
+) -> ApiResult<Json<Vec<PodMetrics>>> {
+    Ok(Json(
+        pod_samples(&state, &format!("/registry/metrics/pods/{}/", ns)).await?,
+    ))
+}
+
+/// GET /api/v1/nodemetrics — usage of every node.
+pub async fn list_node_metrics(State(state): State<AppState>) -> ApiResult<Json<Vec<NodeMetrics>>> {
+    let nodes: Vec<Node> = state
+        .store
+        .list_prefix("/registry/nodes/")
+        .await?
+        .iter()
+        .filter_map(|(_, v)| serde_json::from_slice(v).ok())
+        .collect();
+    let pods = pod_samples(&state, "/registry/metrics/pods/").await?;
+    Ok(Json(node_metrics(&nodes, &pods)))
+}
+
+async fn pod_samples(state: &AppState, prefix: &str) -> anyhow::Result<Vec<PodMetrics>> {
+    Ok(state
+        .store
+        .list_prefix(prefix)
+        .await?
+        .iter()
+        .filter_map(|(_, v)| serde_json::from_slice(v).ok())
+        .collect())
+}
+
+/// Each node's heartbeat usage and capacity, with the pod samples it
+/// reported summed up.
+pub fn node_metrics(nodes: &[Node], pods: &[PodMetrics]) -> Vec<NodeMetrics> {
+    let mut by_node: HashMap<&str, (u32, u64, u64)> = HashMap::new();
+    for pod in pods {
+        let totals = by_node.entry(pod.node_name.as_str()).or_default();
+        totals.0 += 1;
+        totals.1 += pod.cpu_millis;
+        totals.2 += pod.memory_bytes;
+    }
+    nodes
+        .iter()
+        .map(|node| {
+            let (count, cpu, memory) = by_node.get(node.name.as_str()).copied().unwrap_or_default();
+            NodeMetrics {
+                name: node.name.clone(),
+                cpu_millis: node.usage.cpu_millis,
+                memory_bytes: node.usage.memory_bytes,
+                cpu_capacity_millis: node.capacity.cpu_millis,
+                memory_capacity_bytes: node.capacity.memory_bytes,
+                pods: count,
+                pods_cpu_millis: cpu,
+                pods_memory_bytes: memory,
+                timestamp: node.last_heartbeat,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::test_state;
+    use chrono::Utc;
+
+    fn node(name: &str, cpu: u64, memory: u64) -> Node {
+        let mut node: Node = serde_json::from_value(serde_json::json!({
+            "id": name, "name": name, "address": "10.0.0.5", "agent_api_port": 10250,
+            "status": "Ready", "registered_at": Utc::now(), "last_heartbeat": Utc::now(),
+            "labels": {},
+        }))
+        .unwrap();
+        node.usage.cpu_millis = cpu;
+        node.usage.memory_bytes = memory;
+        node.capacity.cpu_millis = 4000;
+        node.capacity.memory_bytes = 8 << 30;
+        node
+    }
+
+    fn sample(ns: &str, name: &str, node: &str, cpu: u64, memory: u64) -> PodMetrics {
+        PodMetrics {
+            namespace: ns.to_string(),
+            name: name.to_string(),
+            node_name: node.to_string(),
+            cpu_millis: cpu,
+            memory_bytes: memory,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_node_totals_sum_their_pods() {
+        let nodes = [node("node-a", 1500, 3 << 30), node("node-b", 100, 1 << 30)];
+        let pods = [
+            sample("default", "web-0", "node-a", 250, 100 << 20),
+            sample("dev", "api-0", "node-a", 750, 300 << 20),
+            sample("default", "gone-0", "node-c", 50, 1 << 20),
+        ];
+        let metrics = node_metrics(&nodes, &pods);
+
+        assert_eq!(metrics.len(), 2);
+        let a = &metrics[0];
+        assert_eq!((a.name.as_str(), a.pods), ("node-a", 2));
+        assert_eq!((a.pods_cpu_millis, a.pods_memory_bytes), (1000, 400 << 20));
+        assert_eq!((a.cpu_millis, a.memory_bytes), (1500, 3 << 30));
+        assert_eq!(a.cpu_capacity_millis, 4000);
+        // A node without samples still shows its own usage.
+        let b = &metrics[1];
+        assert_eq!((b.pods, b.pods_cpu_millis, b.cpu_millis), (0, 0, 100));
+    }
+
+    #[tokio::test]
+    async fn test_pod_metrics_by_namespace() {
+        let state = test_state("podmetrics").await;
+        for m in [
+            sample("default", "web-0", "node-a", 250, 1 << 20),
+            sample("dev", "api-0", "node-a", 750, 1 << 20),
+        ] {
+            state
+                .store
+                .put(
+                    &PodMetrics::key(&m.namespace, &m.name),
+                    &serde_json::to_vec(&m).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let Json(all) = list_pod_metrics(State(state.clone())).await.unwrap();
+        assert_eq!(all.len(), 2);
+        let Json(dev) = list_namespaced_pod_metrics(State(state.clone()), Path("dev".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(dev.len(), 1);
+        assert_eq!(dev[0].name, "api-0");
+    }
+}
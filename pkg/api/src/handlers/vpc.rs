@@ -1,15 +1,19 @@
 use std::net::Ipv4Addr;
 
+use anyhow::Context;
 use axum::{
     Json,
-    extract::{Path as AxumPath, State},
+    extract::{Path as AxumPath, Query, State},
     http::StatusCode,
     response::IntoResponse,
 };
 use chrono::{TimeDelta, Utc};
-use tracing::{info, warn};
+use tracing::info;
 
 use crate::AppState;
+use crate::error::ApiResult;
+use crate::pagination::{Page, PageQuery, list_page};
+use pkg_types::error::{ApiError, ErrorReason, FieldError};
 use pkg_types::vpc::{PeeringStatus, Vpc, VpcPeering, VpcStatus};
 
 /// Parse an IPv4 CIDR string like "10.0.0.0/16" into (network_u32, prefix_len).
@@ -49,13 +53,18 @@ fn cidrs_overlap(a: &str, b: &str) -> bool {
 // VPCs
 // ============================================================
 
-pub async fn create_vpc(
-    State(state): State<AppState>,
-    Json(mut vpc): Json<Vpc>,
-) -> impl IntoResponse {
+pub async fn create_vpc(State(state): State<AppState>, Json(mut vpc): Json<Vpc>) -> ApiResult {
     // Validate CIDR format
     if parse_cidr(&vpc.ipv4_cidr).is_none() {
-        return (StatusCode::BAD_REQUEST, "Invalid CIDR format").into_response();
+        return Err(ApiError::invalid(
+            "vpc",
+            &vpc.name,
+            vec![FieldError::new(
+                "ipv4_cidr",
+                format!("'{}' is not an IPv4 CIDR", vpc.ipv4_cidr),
+            )],
+        )
+        .into());
     }
 
     // Auto-allocate VpcID: scan existing VPCs for max ID, next = max + 1
@@ -72,14 +81,14 @@ pub async fn create_vpc(
     // Check CIDR overlap with existing VPCs
     for existing in &existing_vpcs {
         if cidrs_overlap(&vpc.ipv4_cidr, &existing.ipv4_cidr) {
-            return (
-                StatusCode::CONFLICT,
+            return Err(ApiError::new(
+                ErrorReason::Conflict,
                 format!(
                     "CIDR {} overlaps with VPC '{}' ({})",
                     vpc.ipv4_cidr, existing.name, existing.ipv4_cidr
                 ),
             )
-                .into_response();
+            .into());
         }
     }
 
@@ -109,96 +118,59 @@ pub async fn create_vpc(
     vpc.deleted_at = None;
 
     let key = format!("/registry/vpcs/{}", vpc.name);
-    match serde_json::to_vec(&vpc) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create VPC: {}", e);
-                return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create VPC").into_response();
-            }
-            info!("Created VPC: {} (id={})", vpc.name, vpc.vpc_id);
-            (StatusCode::CREATED, Json(vpc)).into_response()
-        }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
-    }
-}
-
-pub async fn list_vpcs(State(state): State<AppState>) -> impl IntoResponse {
-    let entries = state
+    state
         .store
-        .list_prefix("/registry/vpcs/")
+        .put(&key, &serde_json::to_vec(&vpc)?)
         .await
-        .unwrap_or_default();
-    let vpcs: Vec<Vpc> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(vpcs)).into_response()
+        .context("creating VPC")?;
+    info!("Created VPC: {} (id={})", vpc.name, vpc.vpc_id);
+    Ok((StatusCode::CREATED, Json(vpc)).into_response())
+}
+
+pub async fn list_vpcs(
+    State(state): State<AppState>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<Vpc>> {
+    list_page(&state.store, "/registry/vpcs/", &page, |_| true).await
 }
 
 pub async fn get_vpc(
     State(state): State<AppState>,
     AxumPath(name): AxumPath<String>,
-) -> impl IntoResponse {
+) -> ApiResult<Json<Vpc>> {
     let key = format!("/registry/vpcs/{}", name);
-    match state.store.get(&key).await {
-        Ok(Some(data)) => match serde_json::from_slice::<Vpc>(&data) {
-            Ok(vpc) => (StatusCode::OK, Json(vpc)).into_response(),
-            Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Deserialization failed").into_response(),
-        },
-        Ok(None) => (StatusCode::NOT_FOUND, "VPC not found").into_response(),
-        Err(e) => {
-            warn!("Failed to get VPC: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Store error").into_response()
-        }
-    }
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("vpc", &name, None).into());
+    };
+    Ok(Json(serde_json::from_slice(&data)?))
 }
 
 pub async fn delete_vpc(
     State(state): State<AppState>,
     AxumPath(name): AxumPath<String>,
-) -> impl IntoResponse {
-    if name == "default" {
-        return (StatusCode::BAD_REQUEST, "Cannot delete the default VPC").into_response();
+) -> ApiResult<Json<Vpc>> {
+    if name == pkg_constants::network::DEFAULT_VPC_NAME {
+        return Err(ApiError::new(ErrorReason::BadRequest, "cannot delete the default VPC").into());
     }
 
     let key = format!("/registry/vpcs/{}", name);
-    match state.store.get(&key).await {
-        Ok(Some(data)) => {
-            let mut vpc: Vpc = match serde_json::from_slice(&data) {
-                Ok(v) => v,
-                Err(_) => {
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Deserialization failed")
-                        .into_response();
-                }
-            };
-
-            if vpc.status == VpcStatus::Terminating || vpc.status == VpcStatus::Deleted {
-                return (StatusCode::OK, Json(vpc)).into_response();
-            }
-
-            // Transition to Terminating — no new pods, existing continue
-            vpc.status = VpcStatus::Terminating;
-            match serde_json::to_vec(&vpc) {
-                Ok(updated) => {
-                    if let Err(e) = state.store.put(&key, &updated).await {
-                        warn!("Failed to update VPC status: {}", e);
-                        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update VPC")
-                            .into_response();
-                    }
-                    info!("VPC '{}' marked as Terminating (draining)", name);
-                    (StatusCode::OK, Json(vpc)).into_response()
-                }
-                Err(_) => {
-                    (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response()
-                }
-            }
-        }
-        Ok(None) => (StatusCode::NOT_FOUND, "VPC not found").into_response(),
-        Err(e) => {
-            warn!("Failed to get VPC for deletion: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, "Store error").into_response()
-        }
+    let Some(data) = state.store.get(&key).await? else {
+        return Err(ApiError::not_found("vpc", &name, None).into());
+    };
+    let mut vpc: Vpc = serde_json::from_slice(&data)?;
+    if vpc.status == VpcStatus::Terminating || vpc.status == VpcStatus::Deleted {
+        return Ok(Json(vpc));
     }
+
+    // Transition to Terminating — no new pods, existing continue
+    vpc.status = VpcStatus::Terminating;
+    state
+        .store
+        .put(&key, &serde_json::to_vec(&vpc)?)
+        .await
+        .context("updating VPC status")?;
+    info!("VPC '{}' marked as Terminating (draining)", name);
+    Ok(Json(vpc))
 }
 
 // ============================================================
@@ -208,33 +180,18 @@ pub async fn delete_vpc(
 pub async fn create_vpc_peering(
     State(state): State<AppState>,
     Json(mut peering): Json<VpcPeering>,
-) -> impl IntoResponse {
+) -> ApiResult {
     // Validate both VPCs exist
-    let vpc_a_key = format!("/registry/vpcs/{}", peering.vpc_a);
-    let vpc_b_key = format!("/registry/vpcs/{}", peering.vpc_b);
-
-    match (
-        state.store.get(&vpc_a_key).await,
-        state.store.get(&vpc_b_key).await,
-    ) {
-        (Ok(Some(_)), Ok(Some(_))) => {}
-        (Ok(None), _) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                format!("VPC '{}' not found", peering.vpc_a),
-            )
-                .into_response();
-        }
-        (_, Ok(None)) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                format!("VPC '{}' not found", peering.vpc_b),
-            )
-                .into_response();
-        }
-        (Err(e), _) | (_, Err(e)) => {
-            warn!("Failed to validate VPCs for peering: {}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Store error").into_response();
+    for vpc in [&peering.vpc_a, &peering.vpc_b] {
+        if state
+            .store
+            .get(&format!("/registry/vpcs/{}", vpc))
+            .await?
+            .is_none()
+        {
+            return Err(
+                ApiError::new(ErrorReason::BadRequest, format!("VPC '{}' not found", vpc)).into(),
+            );
         }
     }
 
@@ -242,56 +199,35 @@ pub async fn create_vpc_peering(
     peering.created_at = Utc::now();
 
     let key = format!("/registry/vpc-peerings/{}", peering.name);
-    match serde_json::to_vec(&peering) {
-        Ok(data) => {
-            if let Err(e) = state.store.put(&key, &data).await {
-                warn!("Failed to create VPC peering: {}", e);
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to create VPC peering",
-                )
-                    .into_response();
-            }
-            info!(
-                "Created VPC peering: {} ({} <-> {})",
-                peering.name, peering.vpc_a, peering.vpc_b
-            );
-            (StatusCode::CREATED, Json(peering)).into_response()
-        }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Serialization failed").into_response(),
-    }
-}
-
-pub async fn list_vpc_peerings(State(state): State<AppState>) -> impl IntoResponse {
-    let entries = state
+    state
         .store
-        .list_prefix("/registry/vpc-peerings/")
+        .put(&key, &serde_json::to_vec(&peering)?)
         .await
-        .unwrap_or_default();
-    let peerings: Vec<VpcPeering> = entries
-        .into_iter()
-        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
-        .collect();
-    (StatusCode::OK, Json(peerings)).into_response()
+        .context("creating VPC peering")?;
+    info!(
+        "Created VPC peering: {} ({} <-> {})",
+        peering.name, peering.vpc_a, peering.vpc_b
+    );
+    Ok((StatusCode::CREATED, Json(peering)).into_response())
+}
+
+pub async fn list_vpc_peerings(
+    State(state): State<AppState>,
+    Query(page): Query<PageQuery>,
+) -> ApiResult<Page<VpcPeering>> {
+    list_page(&state.store, "/registry/vpc-peerings/", &page, |_| true).await
 }
 
 pub async fn delete_vpc_peering(
     State(state): State<AppState>,
     AxumPath(name): AxumPath<String>,
-) -> impl IntoResponse {
+) -> ApiResult<StatusCode> {
     let key = format!("/registry/vpc-peerings/{}", name);
-    match state.store.delete(&key).await {
-        Ok(_) => {
-            info!("Deleted VPC peering: {}", name);
-            StatusCode::NO_CONTENT.into_response()
-        }
-        Err(e) => {
-            warn!("Failed to delete VPC peering: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to delete VPC peering",
-            )
-                .into_response()
-        }
-    }
+    state
+        .store
+        .delete(&key)
+        .await
+        .context("deleting VPC peering")?;
+    info!("Deleted VPC peering: {}", name);
+    Ok(StatusCode::NO_CONTENT)
 }
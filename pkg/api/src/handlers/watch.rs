@@ -1,12 +1,15 @@
 use axum::{
     Json,
-    extract::{Query, State},
+    extract::{Query, Request, State},
+    http::{HeaderMap, HeaderValue},
+    middleware::Next,
     response::{
-        IntoResponse,
+        IntoResponse, Response,
         sse::{Event, KeepAlive, Sse},
     },
 };
 use pkg_state::watch::WatchEvent;
+use pkg_types::error::{ApiError, ErrorReason};
 use serde::Deserialize;
 use std::convert::Infallible;
 use tokio_stream::StreamExt;
@@ -14,33 +17,64 @@ use tokio_stream::wrappers::BroadcastStream;
 use tracing::info;
 
 use crate::AppState;
+use crate::error::ApiResult;
 
 #[derive(Debug, Deserialize)]
 pub struct WatchQuery {
     #[serde(default)]
     pub prefix: Option<String>,
-    #[serde(default)]
+    /// Resume after this seq (`from` is accepted as an alias).
+    #[serde(default, alias = "from")]
     pub seq: Option<u64>,
 }
 
 /// GET /api/v1/watch — SSE endpoint streaming watch events.
+///
+/// Each SSE message carries the event's `seq` as its id, so a client that
+/// reconnects with `?seq=<last seen>` (or a `Last-Event-ID` header) receives
+/// exactly the events it missed. If some of them have already been dropped
+/// from the event buffer the request fails with 410 `Gone`, and the client
+/// relists and watches from the list's `x-k3rs-watch-seq`. Without a seq,
+/// whatever is still buffered is replayed.
 pub async fn watch_events(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(query): Query<WatchQuery>,
-) -> Sse<impl tokio_stream::Stream<Item = Result<Event, Infallible>>> {
+) -> ApiResult<Sse<impl tokio_stream::Stream<Item = Result<Event, Infallible>>>> {
     let prefix = query.prefix.unwrap_or_default();
-    let from_seq = query.seq.unwrap_or(0);
+    let resume_seq = query.seq.or_else(|| {
+        headers
+            .get("last-event-id")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+    });
 
     info!(
-        "Watch subscription: prefix='{}', from_seq={}",
-        prefix, from_seq
+        "Watch subscription: prefix='{}', from_seq={:?}",
+        prefix, resume_seq
     );
 
-    // First send any buffered events since from_seq
-    let buffered = state.store.event_log.events_since(from_seq).await;
-
+    // Subscribe before reading the buffer so nothing emitted in between is lost;
+    // live events already covered by the buffer are skipped by seq below.
     let rx = state.store.event_log.subscribe();
-    let stream = BroadcastStream::new(rx);
+    let buffered = match resume_seq {
+        Some(seq) => state
+            .store
+            .event_log
+            .replay(seq)
+            .await
+            .map_err(|c| {
+                ApiError::new(
+                    ErrorReason::Gone,
+                    format!(
+                        "events after seq {} are no longer available (oldest buffered: {}, current: {}); relist and watch from the list's seq",
+                        c.from_seq, c.oldest, c.current
+                    ),
+                )
+            })?,
+        None => state.store.event_log.events_since(0).await,
+    };
+    let replayed_to = buffered.last().map_or(resume_seq.unwrap_or(0), |e| e.seq);
 
     let prefix_clone = prefix.clone();
 
@@ -49,28 +83,45 @@ pub async fn watch_events(
         buffered
             .into_iter()
             .filter(move |e| prefix.is_empty() || e.key.starts_with(&prefix))
-            .map(|e| {
-                let data = serde_json::to_string(&e).unwrap_or_default();
-                Ok::<_, Infallible>(Event::default().data(data))
-            }),
+            .map(|e| Ok::<_, Infallible>(sse_event(&e))),
     );
 
-    let live_stream = stream.filter_map(move |result| match result {
-        Ok(event) => {
-            if (prefix_clone.is_empty() || event.key.starts_with(&prefix_clone))
-                && let Ok(data) = serde_json::to_string(&event)
-            {
-                return Some(Ok::<_, Infallible>(Event::default().data(data)));
-            }
-
-            None
-        }
-        Err(_) => None,
-    });
+    // A subscriber that falls behind the broadcast channel has lost events:
+    // the stream ends there, and the client resumes from its last seq.
+    let live_stream = BroadcastStream::new(rx)
+        .map_while(|result| result.ok())
+        .filter_map(move |event| {
+            (event.seq > replayed_to
+                && (prefix_clone.is_empty() || event.key.starts_with(&prefix_clone)))
+            .then(|| Ok::<_, Infallible>(sse_event(&event)))
+        });
 
     let combined = buffered_stream.chain(live_stream);
 
-    Sse::new(combined).keep_alive(KeepAlive::default())
+    Ok(Sse::new(combined).keep_alive(KeepAlive::default()))
+}
+
+fn sse_event(event: &WatchEvent) -> Event {
+    Event::default()
+        .id(event.seq.to_string())
+        .data(serde_json::to_string(event).unwrap_or_default())
+}
+
+/// Middleware: stamp responses with the event-log sequence number read before
+/// the handler ran. A client that lists objects and then watches from this seq
+/// sees every later change (possibly re-seeing one already in the list).
+pub async fn watch_seq_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let seq = state.store.event_log.current_seq().await;
+    let mut response = next.run(req).await;
+    response.headers_mut().insert(
+        pkg_constants::state::WATCH_SEQ_HEADER,
+        HeaderValue::from(seq),
+    );
+    response
 }
 
 /// GET /api/v1/events — Returns recent buffered events as JSON (no streaming).
@@ -93,3 +144,102 @@ pub async fn list_events(
 
     Json(events)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::test_state;
+    use axum::{Router, routing::get};
+
+    /// Serve the watch endpoint and return the base URL.
+    async fn serve(state: AppState) -> String {
+        let app = Router::new()
+            .route("/api/v1/watch", get(watch_events))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+        base
+    }
+
+    /// Read `count` events off a watch stream.
+    async fn read_events(mut resp: reqwest::Response, count: usize) -> Vec<WatchEvent> {
+        let mut text = String::new();
+        let mut events = Vec::new();
+        while events.len() < count {
+            let chunk = resp.chunk().await.unwrap().expect("stream ended early");
+            text.push_str(&String::from_utf8_lossy(&chunk));
+            while let Some(end) = text.find("\n\n") {
+                let block: String = text.drain(..end + 2).collect();
+                events.extend(
+                    block
+                        .lines()
+                        .filter_map(|l| l.strip_prefix("data:"))
+                        .filter_map(|d| serde_json::from_str::<WatchEvent>(d.trim()).ok()),
+                );
+            }
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_watch_resumes_from_seq_with_prefix() {
+        // Seqs 1 and 2 are the seeded namespaces.
+        let state = test_state("watch-resume").await;
+        for key in [
+            "/registry/pods/default/a",
+            "/registry/services/default/web",
+            "/registry/pods/default/b",
+            "/registry/pods/default/c",
+        ] {
+            state.store.put(key, b"{}").await.unwrap();
+        }
+        let base = serve(state.clone()).await;
+
+        let resp = reqwest::get(format!(
+            "{}/api/v1/watch?prefix=/registry/pods/&from=3",
+            base
+        ))
+        .await
+        .unwrap();
+        assert_eq!(resp.status(), 200);
+        // Buffered pod events after seq 3, then a live one; never the service.
+        let store = state.store.clone();
+        tokio::spawn(async move {
+            store
+                .put("/registry/services/default/db", b"{}")
+                .await
+                .unwrap();
+            store.delete("/registry/pods/default/a").await.unwrap();
+        });
+        let events = read_events(resp, 3).await;
+        let seen: Vec<(u64, &str)> = events.iter().map(|e| (e.seq, e.key.as_str())).collect();
+        assert_eq!(
+            seen,
+            [
+                (5, "/registry/pods/default/b"),
+                (6, "/registry/pods/default/c"),
+                (8, "/registry/pods/default/a"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_from_compacted_seq_is_gone() {
+        let state = test_state("watch-gone").await;
+        state
+            .store
+            .put("/registry/pods/default/a", b"{}")
+            .await
+            .unwrap();
+        let base = serve(state).await;
+
+        // A seq newer than the log, as a client would hold after a restart.
+        let resp = reqwest::get(format!("{}/api/v1/watch?seq=99", base))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 410);
+        let err: ApiError = resp.json().await.unwrap();
+        assert_eq!(err.reason, ErrorReason::Gone);
+    }
+}
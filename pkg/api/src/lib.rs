@@ -1,5 +1,11 @@
 pub mod auth;
+pub mod error;
 pub mod handlers;
+pub mod local_path;
+pub mod metrics;
+pub mod node_ports;
+pub mod pagination;
+pub mod pod_cidrs;
 pub mod request_id;
 pub mod server;
 
@@ -14,7 +20,10 @@ use pkg_state::client::StateStore;
 pub struct AppState {
     pub store: StateStore,
     pub ca: Arc<ClusterCA>,
+    /// Registers nodes; as a bearer token it carries the node role.
     pub join_token: String,
+    /// Bootstrap bearer token with the admin role.
+    pub admin_token: String,
     pub listen_addr: String,
     pub scheduler: Option<Arc<Scheduler>>,
     pub metrics: Arc<pkg_metrics::MetricsRegistry>,
@@ -24,4 +33,16 @@ pub struct AppState {
     pub restore_in_progress: Arc<AtomicBool>,
     /// Set to true when this server holds the leader lease.
     pub is_leader: Arc<AtomicBool>,
+    /// This server's identity in leader election.
+    pub server_id: String,
+    /// Ports NodePort services are allocated from.
+    pub node_port_range: node_ports::NodePortRange,
+    /// How a node name re-registering from a new address is handled.
+    pub address_conflict: handlers::register::AddressConflictPolicy,
+    /// Address space node pod CIDRs are assigned from.
+    pub cluster_cidr: pod_cidrs::ClusterCidr,
+    /// Tunnels agents keep open to this server, for reaching them behind NAT.
+    pub tunnels: Arc<pkg_tunnel::TunnelRegistry>,
+    /// This server's log filter (None = not changeable at runtime).
+    pub log_filter: Option<Arc<pkg_metrics::log_filter::LogFilter>>,
 }
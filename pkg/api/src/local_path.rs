@@ -0,0 +1,69 @@
+//! The server's side of the local-path provisioner: the VolumeController
+//! has a node's agent create and remove volume directories, reaching it the
+//! way proxied pod requests do (over its tunnel when it has one up).
+
+use async_trait::async_trait;
+use axum::body::{Body, Bytes};
+use axum::http::Method;
+use pkg_controllers::volume::VolumeProvisioner;
+use pkg_types::volume::{LocalVolume, LocalVolumeRequest};
+
+use crate::AppState;
+use crate::handlers::agent_proxy::locate_node;
+
+/// Largest agent reply read.
+const MAX_REPLY: usize = 64 * 1024;
+
+/// [`VolumeProvisioner`] that calls the agent API's `/volumes/{name}`.
+pub struct AgentVolumeProvisioner {
+    state: AppState,
+}
+
+impl AgentVolumeProvisioner {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    /// Send `method /volumes/{name}` to the node's agent. Returns the body
+    /// of a successful reply.
+    async fn call(
+        &self,
+        node: &str,
+        method: Method,
+        name: &str,
+        body: Body,
+    ) -> anyhow::Result<Bytes> {
+        let target = locate_node(&self.state, node)
+            .await
+            .map_err(|e| anyhow::anyhow!(e.0.message))?;
+        let url = reqwest::Url::parse(&format!("{}/volumes/{}", target.base_url(), name))?;
+        let resp = target
+            .send(method, &url, body)
+            .await
+            .map_err(|e| anyhow::anyhow!(e.0.message))?;
+        if !resp.status().is_success() {
+            anyhow::bail!("{}", target.failed(resp).await.0.message);
+        }
+        Ok(axum::body::to_bytes(Body::new(resp.into_body()), MAX_REPLY).await?)
+    }
+}
+
+#[async_trait]
+impl VolumeProvisioner for AgentVolumeProvisioner {
+    async fn create(
+        &self,
+        node: &str,
+        name: &str,
+        request: &LocalVolumeRequest,
+    ) -> anyhow::Result<String> {
+        let body = Body::from(serde_json::to_vec(request)?);
+        let reply = self.call(node, Method::PUT, name, body).await?;
+        let volume: LocalVolume = serde_json::from_slice(&reply)?;
+        Ok(volume.path)
+    }
+
+    async fn delete(&self, node: &str, name: &str) -> anyhow::Result<()> {
+        self.call(node, Method::DELETE, name, Body::empty()).await?;
+        Ok(())
+    }
+}
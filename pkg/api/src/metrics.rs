@@ -0,0 +1,327 @@
+//! Server metrics: the series the API server exports on `GET /metrics`, the
+//! middleware counting API requests, and the scrape handler.
+//!
+//! Gauges describing cluster state (pods, nodes, certificates) are computed
+//! from the store on each scrape; the scheduler keeps its own counts, which
+//! are copied in at the same time.
+
+use axum::{
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use pkg_metrics::MetricsRegistry;
+use pkg_types::node::Node;
+use pkg_types::pod::Pod;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+
+use crate::AppState;
+use crate::handlers::{certificates, cluster};
+
+/// API requests served, labeled by method and status code.
+pub const API_REQUESTS_METRIC: &str = "k3rs_api_requests_total";
+/// Nodes, unlabeled in total and labeled by status.
+pub const NODES_METRIC: &str = "k3rs_nodes_total";
+/// Pods, unlabeled in total and labeled by namespace and status.
+pub const PODS_METRIC: &str = "k3rs_pods_total";
+/// Pods the scheduler tried to place on a node.
+pub const SCHEDULING_ATTEMPTS_METRIC: &str = "k3rs_scheduler_attempts_total";
+/// Scheduling attempts that found no eligible node.
+pub const SCHEDULING_FAILURES_METRIC: &str = "k3rs_scheduler_failures_total";
+/// Whether this server holds the leader lease.
+pub const LEADER_METRIC: &str = "k3rs_leader_status";
+/// The server holding the leader lease, as a `leader` label set to 1.
+pub const LEADER_INFO_METRIC: &str = "k3rs_leader_info";
+/// Whether each node's agent tunnel is up.
+pub const TUNNEL_CONNECTED_METRIC: &str = "k3rs_tunnel_connected";
+/// Streams open over each node's agent tunnel.
+pub const TUNNEL_ACTIVE_STREAMS_METRIC: &str = "k3rs_tunnel_streams_active";
+/// Streams opened over each node's agent tunnel.
+pub const TUNNEL_OPENED_STREAMS_METRIC: &str = "k3rs_tunnel_streams_opened_total";
+
+/// Register every series the server exports.
+pub fn register(metrics: &MetricsRegistry) {
+    metrics.register_counter(API_REQUESTS_METRIC, "Total API requests served");
+    metrics.register_counter(
+        "k3rs_controller_reconcile_total",
+        "Total controller reconciliation cycles",
+    );
+    metrics.register_counter(
+        pkg_controllers::node::NODE_NOT_READY_METRIC,
+        "Total times a node went NotReady after missing heartbeats",
+    );
+    metrics.register_counter(
+        SCHEDULING_ATTEMPTS_METRIC,
+        "Total pods the scheduler tried to place",
+    );
+    metrics.register_counter(
+        SCHEDULING_FAILURES_METRIC,
+        "Total scheduling attempts that found no eligible node",
+    );
+    metrics.register_counter(
+        TUNNEL_OPENED_STREAMS_METRIC,
+        "Total streams opened over each node's agent tunnel",
+    );
+    metrics.register_gauge(NODES_METRIC, "Total registered nodes");
+    metrics.register_gauge(PODS_METRIC, "Total pods in the cluster");
+    metrics.register_gauge(
+        certificates::NODE_CERT_EXPIRY_METRIC,
+        "Seconds until each node's certificate expires",
+    );
+    metrics.register_gauge(
+        LEADER_METRIC,
+        "Whether this server is the leader (1=leader, 0=follower)",
+    );
+    metrics.register_gauge(
+        LEADER_INFO_METRIC,
+        "The server holding the leader lease, as its leader label",
+    );
+    metrics.register_gauge(
+        TUNNEL_CONNECTED_METRIC,
+        "Whether each node's agent tunnel is connected (1=up, 0=down)",
+    );
+    metrics.register_gauge(
+        TUNNEL_ACTIVE_STREAMS_METRIC,
+        "Streams currently open over each node's agent tunnel",
+    );
+}
+
+/// Count every request by method and response status.
+pub async fn metrics_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let method = req.method().to_string();
+    let response = next.run(req).await;
+    state.metrics.counter_inc_with(
+        API_REQUESTS_METRIC,
+        &[("method", &method), ("code", response.status().as_str())],
+    );
+    response
+}
+
+/// Handler for `GET /metrics` — renders Prometheus text exposition format.
+/// Public, so Prometheus can scrape it without a token.
+pub async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    // Update gauge values from live state
+    let nodes: Vec<Node> = list(&state, "/registry/nodes/").await;
+    let mut nodes_by_status: BTreeMap<String, i64> = BTreeMap::new();
+    for node in &nodes {
+        *nodes_by_status.entry(node.status.to_string()).or_default() += 1;
+    }
+    state.metrics.gauge_set(NODES_METRIC, nodes.len() as i64);
+    state.metrics.gauge_clear_labeled(NODES_METRIC);
+    for (status, count) in &nodes_by_status {
+        state
+            .metrics
+            .gauge_set_with(NODES_METRIC, &[("status", status)], *count);
+    }
+    certificates::update_expiry_gauge(&state.metrics, &nodes, Utc::now());
+
+    // Per-namespace/status breakdown of pods
+    let pods: Vec<Pod> = list(&state, "/registry/pods/").await;
+    let mut pods_by_status: BTreeMap<(String, String), i64> = BTreeMap::new();
+    for pod in &pods {
+        *pods_by_status
+            .entry((pod.namespace.clone(), pod.status.to_string()))
+            .or_default() += 1;
+    }
+    state.metrics.gauge_set(PODS_METRIC, pods.len() as i64);
+    state.metrics.gauge_clear_labeled(PODS_METRIC);
+    for ((ns, status), count) in &pods_by_status {
+        state.metrics.gauge_set_with(
+            PODS_METRIC,
+            &[("namespace", ns), ("status", status)],
+            *count,
+        );
+    }
+
+    if let Some(scheduler) = &state.scheduler {
+        state
+            .metrics
+            .counter_set(SCHEDULING_ATTEMPTS_METRIC, scheduler.attempts());
+        state
+            .metrics
+            .counter_set(SCHEDULING_FAILURES_METRIC, scheduler.failures());
+    }
+    state.metrics.gauge_set(
+        LEADER_METRIC,
+        state.is_leader.load(Ordering::Relaxed) as i64,
+    );
+    state.metrics.gauge_clear_labeled(LEADER_INFO_METRIC);
+    if let Ok(Some(lease)) = cluster::leader_lease(&state).await
+        && !lease.is_expired()
+    {
+        state
+            .metrics
+            .gauge_set_with(LEADER_INFO_METRIC, &[("leader", &lease.holder_id)], 1);
+    }
+    for tunnel in state.tunnels.stats() {
+        let node = [("node", tunnel.node.as_str())];
+        state
+            .metrics
+            .gauge_set_with(TUNNEL_CONNECTED_METRIC, &node, tunnel.connected as i64);
+        state.metrics.gauge_set_with(
+            TUNNEL_ACTIVE_STREAMS_METRIC,
+            &node,
+            tunnel.active_streams as i64,
+        );
+        state
+            .metrics
+            .counter_set_with(TUNNEL_OPENED_STREAMS_METRIC, &node, tunnel.opened_streams);
+    }
+
+    (
+        StatusCode::OK,
+        [(
+            header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        state.metrics.render(),
+    )
+}
+
+/// Every object under `prefix` that decodes as `T`; a store error yields none.
+async fn list<T: serde::de::DeserializeOwned>(state: &AppState, prefix: &str) -> Vec<T> {
+    state
+        .store
+        .list_prefix(prefix)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|(_, v)| serde_json::from_slice(v).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::test_state;
+    use axum::{Router, middleware, routing::get};
+    use pkg_scheduler::Scheduler;
+    use std::sync::Arc;
+
+    fn node(name: &str, status: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": name, "name": name, "address": "10.0.0.5", "agent_api_port": 10250,
+            "status": status, "registered_at": Utc::now(), "last_heartbeat": Utc::now(),
+            "labels": {},
+        })
+    }
+
+    fn pod(name: &str, status: &str) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": name, "name": name, "namespace": "default", "status": status,
+            "created_at": Utc::now(),
+            "spec": { "containers": [{ "name": "app", "image": "nginx" }] },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scrape_after_operations() {
+        let mut state = test_state("metrics-scrape").await;
+        state.scheduler = Some(Arc::new(Scheduler::new()));
+        register(&state.metrics);
+
+        let ready: Node = serde_json::from_value(node("node-a", "Ready")).unwrap();
+        let not_ready: Node = serde_json::from_value(node("node-b", "NotReady")).unwrap();
+        for n in [&ready, &not_ready] {
+            let key = format!("/registry/nodes/{}", n.name);
+            state
+                .store
+                .put(&key, &serde_json::to_vec(n).unwrap())
+                .await
+                .unwrap();
+        }
+        for p in [pod("web-1", "Running"), pod("web-2", "Pending")] {
+            let key = format!("/registry/pods/default/{}", p.name);
+            state
+                .store
+                .put(&key, &serde_json::to_vec(&p).unwrap())
+                .await
+                .unwrap();
+        }
+        let lease = pkg_types::lease::Lease {
+            id: "controller-leader".to_string(),
+            holder_id: "server-b".to_string(),
+            acquired_at: Utc::now(),
+            renew_at: Utc::now(),
+            ttl_seconds: 15,
+        };
+        state
+            .store
+            .put(
+                pkg_constants::state::LEADER_LEASE_KEY,
+                &serde_json::to_vec(&lease).unwrap(),
+            )
+            .await
+            .unwrap();
+        // One pod placed, one that fits nowhere.
+        let scheduler = state.scheduler.clone().unwrap();
+        assert!(
+            scheduler
+                .schedule(&pod("web-1", "Pending"), std::slice::from_ref(&ready), &[])
+                .is_some()
+        );
+        assert!(
+            scheduler
+                .schedule(&pod("web-2", "Pending"), &[not_ready], &[])
+                .is_none()
+        );
+
+        let app = Router::new()
+            .route("/metrics", get(metrics_handler))
+            .route("/api/v1/pods", get(|| async { StatusCode::OK }))
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                metrics_middleware,
+            ))
+            .with_state(state);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.ok() });
+
+        let client = reqwest::Client::new();
+        for path in ["/api/v1/pods", "/api/v1/pods", "/api/v1/missing"] {
+            client
+                .get(format!("{}{}", base, path))
+                .send()
+                .await
+                .unwrap();
+        }
+        // No Authorization header needed.
+        let resp = client
+            .get(format!("{}/metrics", base))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let text = resp.text().await.unwrap();
+
+        for series in [
+            r#"k3rs_api_requests_total{code="200",method="GET"} 2"#,
+            r#"k3rs_api_requests_total{code="404",method="GET"} 1"#,
+            "k3rs_nodes_total 2",
+            r#"k3rs_nodes_total{status="NotReady"} 1"#,
+            r#"k3rs_nodes_total{status="Ready"} 1"#,
+            "k3rs_pods_total 2",
+            r#"k3rs_pods_total{namespace="default",status="Pending"} 1"#,
+            r#"k3rs_pods_total{namespace="default",status="Running"} 1"#,
+            "k3rs_scheduler_attempts_total 2",
+            "k3rs_scheduler_failures_total 1",
+            "k3rs_leader_status 0",
+            r#"k3rs_leader_info{leader="server-b"} 1"#,
+        ] {
+            assert!(
+                text.lines().any(|line| line == series),
+                "missing {}",
+                series
+            );
+        }
+    }
+}
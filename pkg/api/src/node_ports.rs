@@ -0,0 +1,184 @@
+//! NodePort allocation for Services of type `NodePort`.
+//!
+//! Node ports are recorded only on the stored Services, so the allocator is
+//! rebuilt from them for every request and deleting a Service frees its ports.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+
+/// Inclusive range node ports are allocated from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodePortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl NodePortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        (self.start..=self.end).contains(&port)
+    }
+}
+
+impl Default for NodePortRange {
+    fn default() -> Self {
+        Self {
+            start: pkg_constants::network::DEFAULT_NODE_PORT_RANGE_START,
+            end: pkg_constants::network::DEFAULT_NODE_PORT_RANGE_END,
+        }
+    }
+}
+
+impl fmt::Display for NodePortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+impl FromStr for NodePortRange {
+    type Err = String;
+
+    /// Parse `start-end`, e.g. `30000-32767`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once('-')
+            .ok_or_else(|| format!("node port range '{}' must be start-end", s))?;
+        let parse = |p: &str| {
+            p.trim()
+                .parse::<u16>()
+                .map_err(|_| format!("invalid port '{}' in node port range", p))
+        };
+        let (start, end) = (parse(start)?, parse(end)?);
+        if start == 0 || start > end {
+            return Err(format!("node port range '{}' is empty", s));
+        }
+        Ok(Self { start, end })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodePortError {
+    OutOfRange { port: u16, range: NodePortRange },
+    InUse(u16),
+    Exhausted(NodePortRange),
+}
+
+impl fmt::Display for NodePortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodePortError::OutOfRange { port, range } => write!(
+                f,
+                "node port {} is outside the node port range {}",
+                port, range
+            ),
+            NodePortError::InUse(port) => write!(f, "node port {} is already allocated", port),
+            NodePortError::Exhausted(range) => {
+                write!(f, "no free node ports left in range {}", range)
+            }
+        }
+    }
+}
+
+impl std::error::Error for NodePortError {}
+
+#[derive(Debug, Clone)]
+pub struct NodePortAllocator {
+    range: NodePortRange,
+    used: BTreeSet<u16>,
+}
+
+impl NodePortAllocator {
+    /// An allocator with `used` already taken.
+    pub fn new(range: NodePortRange, used: impl IntoIterator<Item = u16>) -> Self {
+        Self {
+            range,
+            used: used.into_iter().collect(),
+        }
+    }
+
+    /// Claim a specific port.
+    pub fn reserve(&mut self, port: u16) -> Result<(), NodePortError> {
+        if !self.range.contains(port) {
+            return Err(NodePortError::OutOfRange {
+                port,
+                range: self.range,
+            });
+        }
+        if !self.used.insert(port) {
+            return Err(NodePortError::InUse(port));
+        }
+        Ok(())
+    }
+
+    /// Claim the lowest free port.
+    pub fn allocate(&mut self) -> Result<u16, NodePortError> {
+        let port = (self.range.start..=self.range.end)
+            .find(|p| !self.used.contains(p))
+            .ok_or(NodePortError::Exhausted(self.range))?;
+        self.used.insert(port);
+        Ok(port)
+    }
+
+    pub fn release(&mut self, port: u16) {
+        self.used.remove(&port);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL: NodePortRange = NodePortRange {
+        start: 30000,
+        end: 30002,
+    };
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!("30000-32767".parse(), Ok(NodePortRange::default()));
+        assert_eq!(
+            "31000 - 31010".parse(),
+            Ok(NodePortRange {
+                start: 31000,
+                end: 31010
+            })
+        );
+        for bad in ["30000", "32767-30000", "0-10", "a-b", "30000-70000"] {
+            assert!(bad.parse::<NodePortRange>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_allocate_until_exhausted() {
+        let mut alloc = NodePortAllocator::new(SMALL, [30001]);
+        assert_eq!(alloc.allocate(), Ok(30000));
+        assert_eq!(alloc.allocate(), Ok(30002));
+        assert_eq!(alloc.allocate(), Err(NodePortError::Exhausted(SMALL)));
+    }
+
+    #[test]
+    fn test_reserve_explicit_port() {
+        let mut alloc = NodePortAllocator::new(SMALL, [30001]);
+        assert_eq!(alloc.reserve(30001), Err(NodePortError::InUse(30001)));
+        assert_eq!(
+            alloc.reserve(8080),
+            Err(NodePortError::OutOfRange {
+                port: 8080,
+                range: SMALL
+            })
+        );
+        assert_eq!(alloc.reserve(30002), Ok(()));
+        assert_eq!(alloc.reserve(30002), Err(NodePortError::InUse(30002)));
+        assert_eq!(alloc.allocate(), Ok(30000));
+    }
+
+    #[test]
+    fn test_release_and_reuse() {
+        let mut alloc = NodePortAllocator::new(SMALL, [30000, 30001, 30002]);
+        assert!(alloc.allocate().is_err());
+        alloc.release(30001);
+        assert_eq!(alloc.allocate(), Ok(30001));
+        alloc.release(30000);
+        assert_eq!(alloc.reserve(30000), Ok(()));
+    }
+}
@@ -0,0 +1,321 @@
+//! Chunked list responses. A list request with `?limit=N` gets at most `N`
+//! objects in store-key order (namespace/name) and, when more may follow, a
+//! [`CONTINUE_HEADER`] whose value is passed back as `?continue=` to resume
+//! right after the last object served. Without `limit` the whole collection
+//! is returned, as before.
+
+use axum::{
+    Json,
+    http::HeaderValue,
+    response::{IntoResponse, Response},
+};
+use pkg_constants::state::CONTINUE_HEADER;
+use pkg_state::client::StateStore;
+use pkg_types::error::{ApiError, ErrorReason};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::error::ApiResult;
+
+/// Most keys read from the store per scan while filling a page.
+const MAX_SCAN_BATCH: usize = 1000;
+
+/// `limit` / `continue` query parameters of list endpoints.
+#[derive(Debug, Default, Deserialize)]
+pub struct PageQuery {
+    /// Most objects to return; absent or 0 returns everything.
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Token from the previous page's [`CONTINUE_HEADER`].
+    #[serde(rename = "continue", default)]
+    pub continue_token: Option<String>,
+}
+
+/// One page of a list: served as a JSON array, with the token for the next
+/// page in [`CONTINUE_HEADER`].
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub continue_token: Option<String>,
+}
+
+impl<T: Serialize> IntoResponse for Page<T> {
+    fn into_response(self) -> Response {
+        let mut resp = Json(self.items).into_response();
+        if let Some(token) = self.continue_token
+            && let Ok(value) = HeaderValue::from_str(&token)
+        {
+            resp.headers_mut().insert(CONTINUE_HEADER, value);
+        }
+        resp
+    }
+}
+
+/// List the objects under `prefix` that deserialize as `T` and pass `keep`,
+/// one page at a time as asked for by `query`. Pages are filled by scanning
+/// the store from the token's key, so a page is only short when the
+/// collection is exhausted; the last full page may still carry a token that
+/// then yields an empty page if the remaining objects are all filtered out.
+pub async fn list_page<T: DeserializeOwned>(
+    store: &StateStore,
+    prefix: &str,
+    query: &PageQuery,
+    keep: impl Fn(&T) -> bool,
+) -> ApiResult<Page<T>> {
+    let mut cursor = match query.continue_token.as_deref() {
+        Some(token) => Some(decode_token(token, prefix)?),
+        None => None,
+    };
+    let limit = match query.limit {
+        Some(limit) if limit > 0 => limit,
+        // A token without a limit still resumes where it left off.
+        _ => usize::MAX,
+    };
+
+    let mut items = Vec::new();
+    loop {
+        let batch = store
+            .list_prefix_after(prefix, cursor.as_deref(), limit.min(MAX_SCAN_BATCH))
+            .await?;
+        let exhausted = batch.len() < limit.min(MAX_SCAN_BATCH);
+        for (key, value) in batch {
+            if let Ok(item) = serde_json::from_slice::<T>(&value)
+                && keep(&item)
+            {
+                items.push(item);
+            }
+            cursor = Some(key);
+            if items.len() == limit {
+                break;
+            }
+        }
+        if items.len() == limit || exhausted {
+            break;
+        }
+    }
+
+    let continue_token = match cursor {
+        Some(last)
+            if items.len() == limit
+                && !store
+                    .list_prefix_after(prefix, Some(&last), 1)
+                    .await?
+                    .is_empty() =>
+        {
+            Some(encode_token(&last))
+        }
+        _ => None,
+    };
+    Ok(Page {
+        items,
+        continue_token,
+    })
+}
+
+/// The token is the hex-encoded store key of the last object served.
+fn encode_token(key: &str) -> String {
+    key.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// The store key a token resumes after. Tokens from another collection are
+/// rejected, since resuming one would skip or repeat arbitrary objects.
+fn decode_token(token: &str, prefix: &str) -> ApiResult<String> {
+    let invalid = || ApiError::new(ErrorReason::BadRequest, "invalid continue token");
+    if !token.len().is_multiple_of(2) {
+        return Err(invalid().into());
+    }
+    let bytes = (0..token.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(token.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(invalid)?;
+    match String::from_utf8(bytes) {
+        Ok(key) if key.starts_with(prefix) => Ok(key),
+        _ => Err(invalid().into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::testing::test_state;
+    use pkg_types::pod::Pod;
+    use std::collections::HashSet;
+
+    async fn seed_pods(store: &StateStore, count: usize) {
+        for i in 0..count {
+            let ns = ["default", "prod", "staging"][i % 3];
+            let name = format!("web-{:04}", i);
+            let pod = serde_json::json!({
+                "id": format!("{}-id", name),
+                "name": name,
+                "namespace": ns,
+                "spec": { "containers": [] },
+                "status": "Running",
+                "node_name": if i % 2 == 0 { "worker-1" } else { "worker-2" },
+                "created_at": "2024-02-25T00:00:00Z",
+            });
+            store
+                .put(
+                    &format!("/registry/pods/{}/{}", ns, name),
+                    &serde_json::to_vec(&pod).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Page through `prefix` `limit` objects at a time, returning every pod
+    /// seen and the number of pages it took.
+    async fn read_all(
+        store: &StateStore,
+        prefix: &str,
+        limit: usize,
+        keep: impl Fn(&Pod) -> bool + Copy,
+    ) -> (Vec<Pod>, usize) {
+        let mut query = PageQuery {
+            limit: Some(limit),
+            continue_token: None,
+        };
+        let (mut pods, mut pages) = (Vec::new(), 0);
+        loop {
+            let page = list_page(store, prefix, &query, keep).await.unwrap();
+            pages += 1;
+            assert!(page.items.len() <= limit);
+            pods.extend(page.items);
+            match page.continue_token {
+                Some(token) => query.continue_token = Some(token),
+                None => return (pods, pages),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pages_cover_every_pod_once() {
+        let state = test_state("paging").await;
+        seed_pods(&state.store, 350).await;
+
+        let (pods, pages) = read_all(&state.store, "/registry/pods/", 40, |_| true).await;
+        assert_eq!(pods.len(), 350);
+        assert_eq!(pages, 9);
+        let keys: Vec<String> = pods
+            .iter()
+            .map(|p| format!("{}/{}", p.namespace, p.name))
+            .collect();
+        assert_eq!(keys.iter().collect::<HashSet<_>>().len(), 350);
+        // In stable namespace/name order.
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+
+        // An exact multiple of the limit ends without an empty extra page.
+        let (pods, pages) = read_all(&state.store, "/registry/pods/prod/", 39, |_| true).await;
+        assert_eq!((pods.len(), pages), (117, 3));
+
+        // Filtered pages are still filled up to the limit.
+        let (pods, _) = read_all(&state.store, "/registry/pods/", 25, |p: &Pod| {
+            p.node_name.as_deref() == Some("worker-1")
+        })
+        .await;
+        assert_eq!(pods.len(), 175);
+        assert!(
+            pods.iter()
+                .all(|p| p.node_name.as_deref() == Some("worker-1"))
+        );
+
+        // Without a limit everything comes back at once, with no token.
+        let page = list_page::<Pod>(
+            &state.store,
+            "/registry/pods/",
+            &PageQuery::default(),
+            |_| true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.items.len(), 350);
+        assert!(page.continue_token.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stale_token_after_deletions() {
+        let state = test_state("paging-stale").await;
+        seed_pods(&state.store, 300).await;
+        let mut query = PageQuery {
+            limit: Some(100),
+            continue_token: None,
+        };
+        let first = list_page::<Pod>(&state.store, "/registry/pods/", &query, |_| true)
+            .await
+            .unwrap();
+        let last = first.items.last().unwrap();
+        let seen: HashSet<String> = first.items.iter().map(|p| p.name.clone()).collect();
+
+        // The pod the token points at, and others on both sides, go away.
+        for pod in [&first.items[0], &first.items[50], last] {
+            let key = format!("/registry/pods/{}/{}", pod.namespace, pod.name);
+            state.store.delete(&key).await.unwrap();
+        }
+        let after = format!("/registry/pods/{}/{}", last.namespace, last.name);
+        let next = state
+            .store
+            .list_prefix_after("/registry/pods/", Some(&after), 1)
+            .await
+            .unwrap();
+        state.store.delete(&next[0].0).await.unwrap();
+
+        // The rest resumes after the deleted key: nothing repeats, and only
+        // the deleted pod is missing.
+        query.continue_token = first.continue_token;
+        let mut rest = Vec::new();
+        loop {
+            let page = list_page::<Pod>(&state.store, "/registry/pods/", &query, |_| true)
+                .await
+                .unwrap();
+            rest.extend(page.items);
+            match page.continue_token {
+                Some(token) => query.continue_token = Some(token),
+                None => break,
+            }
+        }
+        assert_eq!(rest.len(), 199);
+        assert!(rest.iter().all(|p| !seen.contains(&p.name)));
+    }
+
+    #[tokio::test]
+    async fn test_continue_header_and_invalid_token() {
+        let state = test_state("paging-header").await;
+        seed_pods(&state.store, 5).await;
+        let query = PageQuery {
+            limit: Some(2),
+            continue_token: None,
+        };
+        let resp = list_page::<Pod>(&state.store, "/registry/pods/", &query, |_| true)
+            .await
+            .unwrap()
+            .into_response();
+        let token = resp.headers()[CONTINUE_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        // A token is only good for the collection it was issued for.
+        let query = PageQuery {
+            limit: Some(2),
+            continue_token: Some(token),
+        };
+        let err = list_page::<Pod>(&state.store, "/registry/services/", &query, |_| true)
+            .await
+            .unwrap_err();
+        assert_eq!(err.0.reason, ErrorReason::BadRequest);
+    }
+
+    #[test]
+    fn test_token_round_trip() {
+        let token = encode_token("/registry/pods/default/web-1");
+        assert_eq!(
+            decode_token(&token, "/registry/pods/").unwrap(),
+            "/registry/pods/default/web-1"
+        );
+        // Another collection's token, or garbage.
+        assert!(decode_token(&token, "/registry/services/").is_err());
+        assert!(decode_token("zz", "/registry/pods/").is_err());
+        assert!(decode_token("abc", "/registry/pods/").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! Per-node pod CIDRs carved out of the cluster CIDR.
+//!
+//! Pod CIDRs are recorded only on the stored Nodes, so the free blocks are
+//! worked out from them at each registration and deleting a Node frees its
+//! block.
+
+use std::fmt;
+use std::net::Ipv4Addr;
+
+/// The cluster's pod address space and the block size each node gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClusterCidr {
+    network: u32,
+    prefix_len: u8,
+    node_prefix_len: u8,
+}
+
+impl Default for ClusterCidr {
+    fn default() -> Self {
+        Self::new(
+            pkg_constants::network::DEFAULT_POD_CIDR,
+            pkg_constants::network::DEFAULT_NODE_CIDR_MASK_SIZE,
+        )
+        .expect("default cluster CIDR is valid")
+    }
+}
+
+impl fmt::Display for ClusterCidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", Ipv4Addr::from(self.network), self.prefix_len)
+    }
+}
+
+impl ClusterCidr {
+    /// Parse an IPv4 `cidr` such as `10.42.0.0/16`, split into blocks with
+    /// `node_prefix_len` bits of prefix.
+    pub fn new(cidr: &str, node_prefix_len: u8) -> Result<Self, String> {
+        let (network, prefix_len) = parse_cidr(cidr)?;
+        if node_prefix_len < prefix_len || node_prefix_len > 30 {
+            return Err(format!(
+                "node CIDR mask size {} must be between {} and 30 for cluster CIDR {}",
+                node_prefix_len, prefix_len, cidr
+            ));
+        }
+        Ok(Self {
+            network,
+            prefix_len,
+            node_prefix_len,
+        })
+    }
+
+    /// Number of node blocks in the cluster CIDR.
+    pub fn capacity(&self) -> u64 {
+        1u64 << (self.node_prefix_len - self.prefix_len)
+    }
+
+    /// The lowest block not in `taken`. Entries that are not blocks of this
+    /// cluster CIDR are ignored.
+    pub fn assign<'a>(&self, taken: impl IntoIterator<Item = &'a str>) -> Option<String> {
+        let taken: Vec<u64> = taken
+            .into_iter()
+            .filter_map(|cidr| self.block_index(cidr))
+            .collect();
+        (0..self.capacity())
+            .find(|i| !taken.contains(i))
+            .map(|i| self.block(i))
+    }
+
+    /// Whether `cidr` is one of this cluster CIDR's node blocks.
+    pub fn contains(&self, cidr: &str) -> bool {
+        self.block_index(cidr).is_some()
+    }
+
+    fn block(&self, index: u64) -> String {
+        let size = 1u64 << (32 - self.node_prefix_len);
+        let start = self.network as u64 + index * size;
+        format!("{}/{}", Ipv4Addr::from(start as u32), self.node_prefix_len)
+    }
+
+    fn block_index(&self, cidr: &str) -> Option<u64> {
+        let (start, prefix_len) = parse_cidr(cidr).ok()?;
+        if prefix_len != self.node_prefix_len {
+            return None;
+        }
+        let offset = start.checked_sub(self.network)? as u64;
+        let index = offset >> (32 - self.node_prefix_len);
+        (index < self.capacity()).then_some(index)
+    }
+}
+
+/// Network address and prefix length of an IPv4 CIDR. Host bits must be zero.
+fn parse_cidr(cidr: &str) -> Result<(u32, u8), String> {
+    let (ip, prefix) = cidr
+        .split_once('/')
+        .ok_or_else(|| format!("CIDR '{}' must be address/prefix", cidr))?;
+    let ip: Ipv4Addr = ip
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid IPv4 address in CIDR '{}'", cidr))?;
+    let prefix_len: u8 = prefix
+        .trim()
+        .parse()
+        .ok()
+        .filter(|p| *p <= 32)
+        .ok_or_else(|| format!("invalid prefix length in CIDR '{}'", cidr))?;
+    let host_mask = u32::MAX.checked_shr(prefix_len as u32).unwrap_or(0);
+    if u32::from(ip) & host_mask != 0 {
+        return Err(format!("CIDR '{}' has host bits set", cidr));
+    }
+    Ok((u32::from(ip), prefix_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cluster_cidr() {
+        let cidr = ClusterCidr::default();
+        assert_eq!(cidr.to_string(), "10.42.0.0/16");
+        assert_eq!(cidr.capacity(), 256);
+        assert_eq!(ClusterCidr::new("10.0.0.0/8", 16).unwrap().capacity(), 256);
+        for (bad, mask) in [
+            ("10.42.0.0", 24),
+            ("10.42.0.1/16", 24),
+            ("10.42.0.0/33", 24),
+            ("fd00::/64", 80),
+            ("10.42.0.0/24", 16),
+            ("10.42.0.0/16", 31),
+        ] {
+            assert!(ClusterCidr::new(bad, mask).is_err(), "{}/{}", bad, mask);
+        }
+    }
+
+    #[test]
+    fn test_assign_lowest_free_block() {
+        let cidr = ClusterCidr::default();
+        assert_eq!(cidr.assign([]).as_deref(), Some("10.42.0.0/24"));
+        assert_eq!(
+            cidr.assign(["10.42.0.0/24", "10.42.2.0/24"]).as_deref(),
+            Some("10.42.1.0/24")
+        );
+        // Blocks of another cluster CIDR don't count.
+        assert_eq!(
+            cidr.assign(["10.43.0.0/24", "10.42.0.0/25"]).as_deref(),
+            Some("10.42.0.0/24")
+        );
+        assert!(cidr.contains("10.42.255.0/24"));
+        assert!(!cidr.contains("10.43.0.0/24"));
+    }
+
+    #[test]
+    fn test_assign_exhausted() {
+        let cidr = ClusterCidr::new("10.42.0.0/23", 24).unwrap();
+        assert_eq!(cidr.assign(["10.42.0.0/24", "10.42.1.0/24"]), None);
+    }
+}
@@ -4,16 +4,23 @@ use axum::{
 };
 use chrono::Utc;
 use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::{Arc, atomic::AtomicBool};
 use tokio::net::TcpListener;
-use tracing::{info, warn};
+use tracing::info;
 
 use crate::AppState;
 use crate::auth::{auth_middleware, rbac_middleware};
+use crate::error::error_body_middleware;
+use crate::handlers::register::AddressConflictPolicy;
 use crate::handlers::{
-    backup, cluster, drain, endpoints, exec, heartbeat, images, processes, register, resources,
-    vpc, watch,
+    archive, backup, certificates, cluster, debug, drain, endpoints, events, exec, heartbeat,
+    images, mirror_pods, patch, port_forward, processes, register, resources, rollout, scale,
+    taints, tokens, tunnel, usage, vpc, watch,
 };
+use crate::local_path::AgentVolumeProvisioner;
+use crate::node_ports::NodePortRange;
+use crate::pod_cidrs::ClusterCidr;
 use crate::request_id::request_id_middleware;
 
 use pkg_controllers::backup::BackupController;
@@ -24,21 +31,35 @@ use pkg_controllers::endpoint::EndpointController;
 use pkg_controllers::eviction::EvictionController;
 use pkg_controllers::hpa::HPAController;
 use pkg_controllers::job::JobController;
+use pkg_controllers::manager::ControllerManager;
+use pkg_controllers::namespace::NamespaceController;
 use pkg_controllers::node::NodeController;
 use pkg_controllers::replicaset::ReplicaSetController;
 use pkg_controllers::restore_watcher::RestoreWatcher;
+use pkg_controllers::scheduling::SchedulingController;
+use pkg_controllers::taint_eviction::TaintEvictionController;
+use pkg_controllers::volume::{VolumeController, VolumeProvisioner};
 use pkg_controllers::vpc::VpcController;
 use pkg_metrics::MetricsRegistry;
+use pkg_metrics::log_filter::LogFilter;
 use pkg_pki::ca::ClusterCA;
 use pkg_scheduler::Scheduler;
+use pkg_state::backend::BackendConfig;
 use pkg_state::client::StateStore;
+use pkg_state::encryption::SecretCipher;
 use pkg_state::leader::LeaderElection;
 
 /// Server configuration passed from the binary's CLI.
 pub struct ServerConfig {
     pub addr: SocketAddr,
-    pub data_dir: String,
+    /// Where cluster state is kept (SlateDB under the data dir by default).
+    pub state_store: BackendConfig,
+    /// File holding the key Secret data is encrypted with at rest; created
+    /// on first start. `K3RS_SECRETS_ENCRYPTION_KEY` overrides it.
+    pub secrets_key_file: String,
     pub join_token: String,
+    /// Bootstrap token with the admin role.
+    pub admin_token: String,
     pub node_name: String,
     pub server_id: String,
     /// Directory where automated backups are written (None = disabled).
@@ -47,27 +68,39 @@ pub struct ServerConfig {
     pub backup_interval_secs: u64,
     /// Number of backup files to retain (default 5).
     pub backup_retention: usize,
+    /// This server's log filter, for the debug API.
+    pub log_filter: Arc<LogFilter>,
+    /// Places pending pods. Built by the caller, which changes its strategy
+    /// when the config file is reloaded.
+    pub scheduler: Arc<Scheduler>,
+    /// Failed/Succeeded pods kept per ReplicaSet (default 5).
+    pub failed_pod_retention: usize,
+    /// Ports NodePort services are allocated from (default 30000-32767).
+    pub node_port_range: NodePortRange,
+    /// How a node name re-registering from a new address is handled
+    /// (default: reject).
+    pub address_conflict: AddressConflictPolicy,
+    /// Address space node pod CIDRs are assigned from (default 10.42.0.0/16
+    /// in /24 blocks).
+    pub cluster_cidr: ClusterCidr,
+    /// Heartbeat staleness before a node is marked NotReady (default 30s).
+    pub node_not_ready_grace: std::time::Duration,
+    /// Heartbeat staleness before a lost node's pods are evicted (default 5m).
+    pub pod_eviction_timeout: std::time::Duration,
 }
 
 pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
     // Initialize core subsystems
-    let store = StateStore::new(&config.data_dir).await?;
+    let store = StateStore::open(&config.state_store)
+        .await?
+        .with_secret_cipher(SecretCipher::load(Path::new(&config.secrets_key_file))?);
     let ca = ClusterCA::new()?;
-    let scheduler = Arc::new(Scheduler::new());
+    let scheduler = config.scheduler.clone();
+    info!("Scheduler strategy: {}", scheduler.config().strategy);
 
     // Initialize metrics registry
     let metrics = Arc::new(MetricsRegistry::new());
-    metrics.register_counter("k3rs_api_requests_total", "Total API requests served");
-    metrics.register_counter(
-        "k3rs_controller_reconcile_total",
-        "Total controller reconciliation cycles",
-    );
-    metrics.register_gauge("k3rs_nodes_total", "Total registered nodes");
-    metrics.register_gauge("k3rs_pods_total", "Total pods in the cluster");
-    metrics.register_gauge(
-        "k3rs_leader_status",
-        "Whether this server is the leader (1=leader, 0=follower)",
-    );
+    crate::metrics::register(&metrics);
 
     // Restore / leader flags shared across AppState clones
     let restore_in_progress = Arc::new(AtomicBool::new(false));
@@ -77,12 +110,19 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
         store: store.clone(),
         ca: Arc::new(ca),
         join_token: config.join_token,
+        admin_token: config.admin_token,
         listen_addr: config.addr.to_string(),
         scheduler: Some(scheduler.clone()),
-        metrics,
+        metrics: metrics.clone(),
         backup_dir: config.backup_dir.clone(),
         restore_in_progress: restore_in_progress.clone(),
         is_leader: is_leader.clone(),
+        server_id: config.server_id.clone(),
+        node_port_range: config.node_port_range,
+        address_conflict: config.address_conflict,
+        cluster_cidr: config.cluster_cidr,
+        tunnels: Arc::new(pkg_tunnel::TunnelRegistry::new()),
+        log_filter: Some(config.log_filter.clone()),
     };
 
     // Seed default namespaces
@@ -111,33 +151,45 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
     let ctrl_backup_dir = config.backup_dir.clone();
     let ctrl_backup_interval = config.backup_interval_secs;
     let ctrl_backup_retention = config.backup_retention;
+    let ctrl_failed_pod_retention = config.failed_pod_retention;
     let ctrl_ca_cert_pem = state.ca.ca_cert_pem().to_string();
-    let ctrl_is_leader = is_leader.clone();
-
-    tokio::spawn(async move {
-        let mut rx = leader_rx;
-        loop {
-            // Wait until we become leader
-            while !*rx.borrow() {
-                if rx.changed().await.is_err() {
-                    return;
-                }
-            }
-
-            ctrl_is_leader.store(true, std::sync::atomic::Ordering::SeqCst);
-            info!("Starting controllers (leader mode)");
+    let ctrl_metrics = metrics.clone();
+    let ctrl_not_ready_grace = config.node_not_ready_grace;
+    let ctrl_eviction_timeout = config.pod_eviction_timeout;
+    let ctrl_provisioner: Arc<dyn VolumeProvisioner> =
+        Arc::new(AgentVolumeProvisioner::new(state.clone()));
 
+    let manager = ControllerManager::new(
+        move |stop| {
             let mut handles = vec![
-                NodeController::new(ctrl_store.clone()).start(),
-                DeploymentController::new(ctrl_store.clone()).start(),
-                ReplicaSetController::new(ctrl_store.clone(), ctrl_scheduler.clone()).start(),
-                DaemonSetController::new(ctrl_store.clone()).start(),
-                JobController::new(ctrl_store.clone(), ctrl_scheduler.clone()).start(),
-                CronJobController::new(ctrl_store.clone()).start(),
-                HPAController::new(ctrl_store.clone()).start(),
-                EvictionController::new(ctrl_store.clone()).start(),
-                VpcController::new(ctrl_store.clone()).start(),
-                EndpointController::new(ctrl_store.clone()).start(),
+                NodeController::new(
+                    ctrl_store.clone(),
+                    ctrl_metrics.clone(),
+                    ctrl_not_ready_grace,
+                )
+                .start(stop.clone()),
+                DeploymentController::new(ctrl_store.clone()).start(stop.clone()),
+                ReplicaSetController::new(
+                    ctrl_store.clone(),
+                    ctrl_scheduler.clone(),
+                    ctrl_failed_pod_retention,
+                )
+                .start(stop.clone()),
+                DaemonSetController::new(ctrl_store.clone(), ctrl_scheduler.clone())
+                    .start(stop.clone()),
+                JobController::new(ctrl_store.clone(), ctrl_scheduler.clone()).start(stop.clone()),
+                SchedulingController::new(ctrl_store.clone(), ctrl_scheduler.clone())
+                    .start(stop.clone()),
+                CronJobController::new(ctrl_store.clone()).start(stop.clone()),
+                HPAController::new(ctrl_store.clone()).start(stop.clone()),
+                EvictionController::new(ctrl_store.clone(), ctrl_eviction_timeout)
+                    .start(stop.clone()),
+                TaintEvictionController::new(ctrl_store.clone()).start(stop.clone()),
+                VpcController::new(ctrl_store.clone()).start(stop.clone()),
+                EndpointController::new(ctrl_store.clone()).start(stop.clone()),
+                NamespaceController::new(ctrl_store.clone()).start(stop.clone()),
+                VolumeController::new(ctrl_store.clone(), ctrl_provisioner.clone())
+                    .start(stop.clone()),
             ];
 
             // Start BackupController if a backup directory is configured
@@ -149,33 +201,38 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
                     ctrl_backup_retention,
                     ctrl_ca_cert_pem.clone(),
                 );
-                handles.push(bctl.start());
-            }
-
-            // Wait until we lose leadership
-            while *rx.borrow() {
-                if rx.changed().await.is_err() {
-                    return;
-                }
+                handles.push(bctl.start(stop.clone()));
             }
-
-            ctrl_is_leader.store(false, std::sync::atomic::Ordering::SeqCst);
-            warn!("Lost leadership — stopping controllers");
-            for h in handles {
-                h.abort();
-            }
-        }
-    });
+            handles
+        },
+        is_leader.clone(),
+    );
+    let _controllers = manager.run(leader_rx);
 
     // Protected API routes
     let api_routes = Router::new()
         // Phase 1: nodes
         .route("/api/v1/nodes", get(cluster::list_nodes))
+        .route(
+            "/api/v1/nodes/{name}",
+            get(cluster::get_node).patch(patch::patch_node),
+        )
+        .route(
+            "/api/v1/nodes/{name}/taints",
+            put(taints::put_node_taint).delete(taints::delete_node_taint),
+        )
         // Phase 2: heartbeat
         .route(
             "/api/v1/nodes/{name}/heartbeat",
             put(heartbeat::node_heartbeat),
         )
+        // Read-only records of the static pods each agent runs
+        .route(
+            "/api/v1/nodes/{name}/mirror-pods",
+            put(mirror_pods::put_mirror_pods),
+        )
+        // Reverse tunnel agents keep open for reaching their API
+        .route("/api/v1/nodes/{name}/tunnel", get(tunnel::node_tunnel))
         // Phase 2: watch stream
         // Cluster-wide pod listing with optional ?fieldSelector=spec.nodeName=<name>
         .route("/api/v1/pods", get(resources::list_all_pods))
@@ -188,11 +245,27 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
             "/api/v1/namespaces/{ns}/pods/{pod_name}/exec",
             get(exec::exec_into_pod),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/pods/{pod_name}/portforward",
+            get(port_forward::port_forward_to_pod),
+        )
         // Phase 2: namespaces
         .route(
             "/api/v1/namespaces",
             post(resources::create_namespace).get(resources::list_namespaces),
         )
+        .route(
+            "/api/v1/namespaces/{ns}",
+            get(resources::get_namespace)
+                .put(resources::apply_namespace)
+                .patch(patch::patch_namespace)
+                .delete(resources::delete_namespace),
+        )
+        // Object events, filterable by ?involved={kind}/{name}
+        .route(
+            "/api/v1/namespaces/{ns}/events",
+            get(events::list_object_events),
+        )
         // Phase 2: pods
         .route(
             "/api/v1/namespaces/{ns}/pods",
@@ -200,7 +273,10 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
         )
         .route(
             "/api/v1/namespaces/{ns}/pods/{pod_name}",
-            get(resources::get_pod).delete(resources::delete_pod),
+            get(resources::get_pod)
+                .put(resources::apply_pod)
+                .patch(patch::patch_pod)
+                .delete(resources::delete_pod),
         )
         .route(
             "/api/v1/namespaces/{ns}/pods/{pod_name}/status",
@@ -216,11 +292,22 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
             "/api/v1/namespaces/{ns}/pods/{pod_name}/logs",
             get(resources::pod_logs),
         )
+        // Copying files in and out of a pod's container (k3rsctl cp)
+        .route(
+            "/api/v1/namespaces/{ns}/pods/{pod_name}/archive",
+            get(archive::get_pod_archive).put(archive::put_pod_archive),
+        )
         // Phase 2: services
         .route(
             "/api/v1/namespaces/{ns}/services",
             post(resources::create_service).get(resources::list_services),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/services/{name}",
+            get(resources::get_service)
+                .put(resources::apply_service)
+                .patch(patch::patch_service),
+        )
         // Phase 2: deployments
         .route(
             "/api/v1/namespaces/{ns}/deployments",
@@ -229,18 +316,44 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
         // Phase 4: deployment CRUD
         .route(
             "/api/v1/namespaces/{ns}/deployments/{deploy_name}",
-            get(resources::get_deployment).put(resources::update_deployment),
+            get(resources::get_deployment)
+                .put(resources::apply_deployment)
+                .patch(patch::patch_deployment),
+        )
+        .route(
+            "/api/v1/namespaces/{ns}/deployments/{name}/revisions",
+            get(rollout::list_revisions),
+        )
+        .route(
+            "/api/v1/namespaces/{ns}/deployments/{name}/rollback",
+            post(rollout::rollback_deployment),
+        )
+        .route(
+            "/api/v1/namespaces/{ns}/deployments/{name}/scale",
+            put(scale::scale_deployment),
         )
         // Phase 2: configmaps
         .route(
             "/api/v1/namespaces/{ns}/configmaps",
             post(resources::create_configmap).get(resources::list_configmaps),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/configmaps/{name}",
+            get(resources::get_configmap)
+                .put(resources::apply_configmap)
+                .patch(patch::patch_configmap),
+        )
         // Phase 2: secrets
         .route(
             "/api/v1/namespaces/{ns}/secrets",
             post(resources::create_secret).get(resources::list_secrets),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/secrets/{name}",
+            get(resources::get_secret)
+                .put(resources::apply_secret)
+                .patch(patch::patch_secret),
+        )
         // Phase 3: endpoints
         .route(
             "/api/v1/namespaces/{ns}/endpoints",
@@ -256,35 +369,102 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
             "/api/v1/namespaces/{ns}/replicasets",
             post(resources::create_replicaset).get(resources::list_replicasets),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/replicasets/{name}",
+            get(resources::get_replicaset)
+                .put(resources::apply_replicaset)
+                .patch(patch::patch_replicaset),
+        )
+        .route(
+            "/api/v1/namespaces/{ns}/replicasets/{name}/scale",
+            put(scale::scale_replicaset),
+        )
         // Phase 4: daemonsets
         .route(
             "/api/v1/namespaces/{ns}/daemonsets",
             post(resources::create_daemonset).get(resources::list_daemonsets),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/daemonsets/{name}",
+            get(resources::get_daemonset)
+                .put(resources::apply_daemonset)
+                .patch(patch::patch_daemonset),
+        )
         // Phase 4: jobs
         .route(
             "/api/v1/namespaces/{ns}/jobs",
             post(resources::create_job).get(resources::list_jobs),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/jobs/{name}",
+            get(resources::get_job)
+                .put(resources::apply_job)
+                .patch(patch::patch_job),
+        )
         // Phase 4: cronjobs
         .route(
             "/api/v1/namespaces/{ns}/cronjobs",
             post(resources::create_cronjob).get(resources::list_cronjobs),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/cronjobs/{name}",
+            get(resources::get_cronjob)
+                .put(resources::apply_cronjob)
+                .patch(patch::patch_cronjob),
+        )
         // Phase 4: hpa
         .route(
             "/api/v1/namespaces/{ns}/hpa",
             post(resources::create_hpa).get(resources::list_hpas),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/hpa/{name}",
+            get(resources::get_hpa)
+                .put(resources::apply_hpa)
+                .patch(patch::patch_hpa),
+        )
         // Phase 5: node drain/cordon/uncordon
+        .route(
+            "/api/v1/nodes/{name}/debug/loglevel",
+            get(debug::get_node_log_level).put(debug::put_node_log_level),
+        )
         .route("/api/v1/nodes/{name}/cordon", post(drain::cordon_node))
         .route("/api/v1/nodes/{name}/uncordon", post(drain::uncordon_node))
         .route("/api/v1/nodes/{name}/drain", post(drain::drain_node))
+        .route(
+            "/api/v1/namespaces/{ns}/pods/{name}/eviction",
+            post(drain::evict_pod),
+        )
         // Phase 5: resource quotas
         .route(
             "/api/v1/namespaces/{ns}/resourcequotas",
             post(resources::create_resource_quota).get(resources::list_resource_quotas),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/resourcequotas/{name}",
+            get(resources::get_resource_quota),
+        )
+        .route(
+            "/api/v1/namespaces/{ns}/limitranges",
+            post(resources::create_limit_range).get(resources::list_limit_ranges),
+        )
+        .route(
+            "/api/v1/namespaces/{ns}/limitranges/{name}",
+            get(resources::get_limit_range)
+                .put(resources::apply_limit_range)
+                .patch(patch::patch_limit_range),
+        )
+        .route(
+            "/api/v1/namespaces/{ns}/poddisruptionbudgets",
+            post(resources::create_pod_disruption_budget)
+                .get(resources::list_pod_disruption_budgets),
+        )
+        .route(
+            "/api/v1/namespaces/{ns}/poddisruptionbudgets/{name}",
+            get(resources::get_pod_disruption_budget)
+                .put(resources::apply_pod_disruption_budget)
+                .patch(patch::patch_pod_disruption_budget),
+        )
         // Phase 5: network policies
         .route(
             "/api/v1/namespaces/{ns}/networkpolicies",
@@ -295,6 +475,18 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
             "/api/v1/namespaces/{ns}/pvcs",
             post(resources::create_pvc).get(resources::list_pvcs),
         )
+        .route(
+            "/api/v1/namespaces/{ns}/pvcs/{name}",
+            get(resources::get_pvc),
+        )
+        .route(
+            "/api/v1/persistentvolumes",
+            get(resources::list_persistent_volumes),
+        )
+        .route(
+            "/api/v1/persistentvolumes/{name}",
+            get(resources::get_persistent_volume).delete(resources::delete_persistent_volume),
+        )
         // Phase 6: backup / restore
         .route(
             "/api/v1/cluster/backup",
@@ -309,8 +501,20 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
             "/api/v1/cluster/restore/dry-run",
             post(backup::restore_dry_run_handler),
         )
+        // Runtime log filter
+        .route(
+            "/api/v1/debug/loglevel",
+            get(debug::get_log_level).put(debug::put_log_level),
+        )
         // Cluster: process list
         .route("/api/v1/processes", get(processes::list_processes))
+        // Resource usage reported with node heartbeats
+        .route("/api/v1/podmetrics", get(usage::list_pod_metrics))
+        .route(
+            "/api/v1/namespaces/{ns}/podmetrics",
+            get(usage::list_namespaced_pod_metrics),
+        )
+        .route("/api/v1/nodemetrics", get(usage::list_node_metrics))
         // Runtime management
         .route(
             "/api/v1/runtime",
@@ -342,20 +546,39 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
             "/api/v1/vpc-peerings/{name}",
             delete(vpc::delete_vpc_peering),
         )
+        // Priority classes (cluster-scoped)
+        .route(
+            "/api/v1/priorityclasses",
+            post(resources::create_priority_class).get(resources::list_priority_classes),
+        )
+        .route(
+            "/api/v1/priorityclasses/{name}",
+            get(resources::get_priority_class)
+                .put(resources::apply_priority_class)
+                .patch(patch::patch_priority_class)
+                .delete(resources::delete_priority_class),
+        )
+        // API tokens
+        .route(
+            "/api/v1/tokens",
+            post(tokens::create_token).get(tokens::list_tokens),
+        )
+        .route("/api/v1/tokens/{id}", delete(tokens::delete_token))
         // Phase 2: generic delete
         .route(
             "/api/v1/{resource_type}/{ns}/{name}",
             delete(resources::delete_resource),
         )
-        // Restore-guard: return 503 on all routes while a restore is running
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
-            backup::restore_guard_middleware,
+            watch::watch_seq_middleware,
         ))
+        // Restore-guard: return 503 on all routes while a restore is running
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
-            rbac_middleware,
+            backup::restore_guard_middleware,
         ))
+        .route_layer(middleware::from_fn(rbac_middleware))
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -365,14 +588,25 @@ pub async fn start_server(config: ServerConfig) -> anyhow::Result<()> {
     let app = Router::new()
         // Phase 1: registration + cluster info (unprotected)
         .route("/register", post(register::register_node))
+        // Authenticated by the node's current certificate, not a token
+        .route(
+            "/api/v1/nodes/{name}/certificate/renew",
+            post(certificates::renew_certificate),
+        )
         .route("/api/v1/cluster/info", get(cluster::cluster_info))
+        .route("/api/v1/cluster/leader", get(cluster::cluster_leader))
         // Phase 6: Prometheus metrics endpoint (unprotected)
-        .route("/metrics", get(metrics_handler))
+        .route("/metrics", get(crate::metrics::metrics_handler))
         .merge(api_routes)
         .fallback(|req: axum::http::Request<axum::body::Body>| async move {
             info!("No route matched for {} {}", req.method(), req.uri().path());
             axum::http::StatusCode::NOT_FOUND
         })
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::metrics::metrics_middleware,
+        ))
+        .layer(middleware::from_fn(error_body_middleware))
         .layer(middleware::from_fn(request_id_middleware))
         .with_state(state);
 
@@ -392,7 +626,9 @@ async fn seed_default_namespaces(store: &StateStore) -> anyhow::Result<()> {
             let ns = pkg_types::namespace::Namespace {
                 name: name.to_string(),
                 labels: std::collections::HashMap::new(),
+                phase: Default::default(),
                 created_at: Utc::now(),
+                resource_version: 0,
             };
             let data = serde_json::to_vec(&ns)?;
             store.put(&key, &data).await?;
@@ -434,6 +670,12 @@ async fn seed_master_node(store: &StateStore, name: &str) -> anyhow::Result<()>
             unschedulable: false,
             wg_public_key: None,
             wg_endpoint: None,
+            usage: pkg_types::pod::ResourceRequirements::default(),
+            running_containers: 0,
+            agent_version: None,
+            certificate_expires_at: None,
+            pod_cidr: None,
+            conditions: Vec::new(),
         };
         let data = serde_json::to_vec(&node)?;
         store.put(&key, &data).await?;
@@ -472,36 +714,3 @@ async fn seed_default_vpc(store: &StateStore) -> anyhow::Result<()> {
     }
     Ok(())
 }
-
-/// Handler for `GET /metrics` — renders Prometheus text exposition format.
-async fn metrics_handler(
-    axum::extract::State(state): axum::extract::State<AppState>,
-) -> impl axum::response::IntoResponse {
-    // Update gauge values from live state
-    let node_count = state
-        .store
-        .list_prefix("/registry/nodes/")
-        .await
-        .map(|e| e.len() as i64)
-        .unwrap_or(0);
-    let pod_count = state
-        .store
-        .list_prefix("/registry/pods/")
-        .await
-        .map(|e| e.len() as i64)
-        .unwrap_or(0);
-
-    state.metrics.gauge_set("k3rs_nodes_total", node_count);
-    state.metrics.gauge_set("k3rs_pods_total", pod_count);
-    state.metrics.counter_inc("k3rs_api_requests_total");
-
-    let body = state.metrics.render();
-    (
-        axum::http::StatusCode::OK,
-        [(
-            axum::http::header::CONTENT_TYPE,
-            "text/plain; version=0.0.4; charset=utf-8",
-        )],
-        body,
-    )
-}
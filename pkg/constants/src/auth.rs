@@ -3,5 +3,8 @@
 /// Default join token used in development. Change before production.
 pub const DEFAULT_JOIN_TOKEN: &str = "demo-token-123";
 
+/// Default bootstrap admin token used in development. Change before production.
+pub const DEFAULT_ADMIN_TOKEN: &str = "demo-admin-token-123";
+
 /// Default node name when not specified via CLI or config.
 pub const DEFAULT_NODE_NAME: &str = "node-1";
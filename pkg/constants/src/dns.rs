@@ -6,6 +6,14 @@ pub const QTYPE_A: u16 = 1;
 /// DNS query type: AAAA record (IPv6).
 pub const QTYPE_AAAA: u16 = 28;
 
+/// DNS query type: SRV record (service location).
+pub const QTYPE_SRV: u16 = 33;
+
+/// TTL of answers built from Endpoints (headless Services, per-pod names and
+/// SRV records). Kept short since endpoints change much more often than
+/// ClusterIPs.
+pub const ENDPOINT_RECORD_TTL_SECS: u32 = 5;
+
 /// NAT64 well-known prefix (first 12 bytes): `64:ff9b::/96` (RFC 6052).
 pub const NAT64_PREFIX: [u8; 12] = [0x00, 0x64, 0xff, 0x9b, 0, 0, 0, 0, 0, 0, 0, 0];
 
@@ -15,6 +15,15 @@ pub const DEFAULT_AGENT_API_PORT: u16 = 10250;
 /// Default service proxy / kube-proxy port.
 pub const DEFAULT_SERVICE_PROXY_PORT: u16 = 10256;
 
+/// Default ingress proxy port (agent-side, external HTTP traffic).
+pub const DEFAULT_INGRESS_PORT: u16 = 8080;
+
+/// First port of the default NodePort range.
+pub const DEFAULT_NODE_PORT_RANGE_START: u16 = 30000;
+
+/// Last port of the default NodePort range.
+pub const DEFAULT_NODE_PORT_RANGE_END: u16 = 32767;
+
 /// Default embedded DNS server port.
 /// Avoids 5353 which is the well-known mDNS port used by Avahi/systemd-resolved.
 pub const DEFAULT_DNS_PORT: u16 = 10053;
@@ -91,9 +100,13 @@ pub const UTUN_PEER_IP: &str = "10.254.254.2";
 /// pfctl anchor name for k3rs NAT rules (isolated from user rules).
 pub const PFCTL_ANCHOR: &str = "com.k3rs.nat";
 
-/// Default pod CIDR routed through the utun device.
+/// Default pod CIDR: routed through the utun device on macOS, and split into
+/// per-node ranges for bridge networking on Linux.
 pub const DEFAULT_POD_CIDR: &str = "10.42.0.0/16";
 
+/// Prefix length of the pod range each node gets from the cluster CIDR.
+pub const DEFAULT_NODE_CIDR_MASK_SIZE: u8 = 24;
+
 // ─── Pod netns ────────────────────────────────────────────────────
 
 /// Interface name assigned inside the container network namespace.
@@ -105,6 +118,15 @@ pub const NETKIT_HOST_PREFIX: &str = "nk-";
 /// Netkit temporary peer name prefix (moved into netns, then renamed to GUEST_IFACE).
 pub const NETKIT_PEER_PREFIX: &str = "nktmp-";
 
+/// Linux bridge the veth pairs of bridge-networked pods are attached to.
+pub const POD_BRIDGE_NAME: &str = "k3rs-br0";
+
+/// Veth host-side device name prefix (followed by truncated pod ID).
+pub const VETH_HOST_PREFIX: &str = "veth-";
+
+/// Veth temporary peer name prefix (moved into netns, then renamed to GUEST_IFACE).
+pub const VETH_PEER_PREFIX: &str = "vtmp-";
+
 /// Link-local IPv4 gateway used inside pod netns for NAT64 / SIIT routing.
 pub const POD_IPV4_GATEWAY: &str = "169.254.1.1";
 
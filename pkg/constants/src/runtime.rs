@@ -12,12 +12,55 @@ pub const DEFAULT_RUNTIME: &str = "youki";
 /// All OCI runtimes supported by the installer.
 pub const SUPPORTED_RUNTIMES: &[&str] = &["youki", "crun"];
 
+/// Parent cgroup of OCI containers; each container runs in
+/// `<parent>/<container id>` under the cgroup v2 mount.
+pub const CONTAINER_CGROUP_PARENT: &str = "/k3rs";
+
+/// CFS period in microseconds the CPU quota of a container's limit is
+/// expressed against.
+pub const CPU_CFS_PERIOD_US: u64 = 100_000;
+
+/// Maximum number of processes in a container with resource limits.
+pub const CONTAINER_PIDS_LIMIT: i64 = 4096;
+
+/// Time a pod's containers get between SIGTERM and SIGKILL when the pod
+/// sets no grace period (seconds). Agents override it with
+/// `termination-grace-period`.
+pub const DEFAULT_TERMINATION_GRACE_PERIOD_SECS: u64 = 30;
+
+/// How often a stopping container is checked for exit during its grace period (milliseconds).
+pub const STOP_POLL_INTERVAL_MS: u64 = 100;
+
+/// How often a started container's process is checked for its exit status (milliseconds).
+pub const EXIT_POLL_INTERVAL_MS: u64 = 100;
+
+/// How long a container that stopped is given for its exit status to be
+/// collected before it is reported as unknown (milliseconds).
+pub const EXIT_STATUS_WAIT_MS: u64 = 1000;
+
+/// How often the agent reaps orphaned container processes nobody waits on
+/// (seconds).
+pub const ORPHAN_REAP_INTERVAL_SECS: u64 = 5;
+
+/// Default heartbeat staleness before a node is marked NotReady (seconds).
+/// It is marked Unknown after twice as long. Servers override it with
+/// `node-not-ready-grace-period`.
+pub const DEFAULT_NODE_NOT_READY_GRACE_SECS: u64 = 30;
+
+/// Default heartbeat staleness before a lost node's pods are evicted
+/// (seconds). Servers override it with `pod-eviction-timeout`.
+pub const DEFAULT_POD_EVICTION_TIMEOUT_SECS: u64 = 300;
+
 /// Default vCPU count for micro-VMs.
 pub const DEFAULT_CPU_COUNT: u32 = 1;
 
 /// Default memory size in MiB for micro-VMs.
 pub const DEFAULT_MEMORY_MB: u64 = 256;
 
+/// Smallest memory size in MiB a micro-VM is booted with, whatever the
+/// container's limit; the guest kernel and k3rs-init need room of their own.
+pub const MIN_VM_MEMORY_MB: u64 = 128;
+
 /// Version of Firecracker to download when not found in PATH.
 pub const FIRECRACKER_VERSION: &str = "1.14.2";
 
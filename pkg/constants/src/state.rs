@@ -8,3 +8,23 @@ pub const LEADER_LEASE_TTL_SECS: u64 = 15;
 
 /// The lease is renewed every `TTL / LEADER_RENEW_INTERVAL_DIVISOR` seconds.
 pub const LEADER_RENEW_INTERVAL_DIVISOR: u64 = 3;
+
+/// A leader that has not renewed its lease for this long steps down, in
+/// seconds. Shorter than the TTL, so its controllers stop before another
+/// server may take over.
+pub const LEADER_RENEW_DEADLINE_SECS: u64 = 10;
+
+/// How long a leader that stepped down waits for its controllers to finish
+/// their current pass before aborting them, in seconds.
+pub const LEADER_STOP_GRACE_SECS: u64 = 3;
+
+/// Response header carrying the event-log sequence number observed before a
+/// request was served. `k3rsctl get --watch` resumes its stream from it.
+pub const WATCH_SEQ_HEADER: &str = "x-k3rs-watch-seq";
+
+/// Response header of a list page after which more objects follow; its value
+/// is sent back as the `continue` query parameter to fetch the next page.
+pub const CONTINUE_HEADER: &str = "x-k3rs-continue";
+
+/// Objects `k3rsctl get` asks for per list request.
+pub const DEFAULT_LIST_CHUNK_SIZE: usize = 500;
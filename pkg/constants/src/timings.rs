@@ -9,18 +9,32 @@ pub const HEARTBEAT_TIMEOUT_SECS: u64 = 5;
 /// Reconnect loop idle sleep when already connected (seconds).
 pub const RECONNECT_IDLE_SECS: u64 = 5;
 
+/// How often the server and agent check their config file for changes (seconds).
+pub const CONFIG_RELOAD_INTERVAL_SECS: u64 = 5;
+
 /// DeploymentController reconciliation interval (seconds).
 pub const DEPLOYMENT_CHECK_INTERVAL_SECS: u64 = 10;
 
 /// EvictionController check interval (seconds).
 pub const EVICTION_CHECK_INTERVAL_SECS: u64 = 30;
 
-/// Grace period before evicting pods from an Unknown node (seconds).
-pub const EVICTION_GRACE_PERIOD_SECS: u64 = 300;
+/// TaintEvictionController check interval (seconds). Node writes and
+/// running toleration timers wake it sooner.
+pub const TAINT_EVICTION_CHECK_INTERVAL_SECS: u64 = 30;
 
 /// HPAController reconciliation interval (seconds).
 pub const HPA_CHECK_INTERVAL_SECS: u64 = 30;
 
+/// Minimum time between an HPA's last scale and a scale-down (seconds).
+pub const HPA_SCALE_DOWN_STABILIZATION_SECS: u64 = 300;
+
+/// Pod usage samples older than this are ignored by the HPA (seconds).
+pub const POD_METRICS_MAX_AGE_SECS: u64 = 60;
+
+/// VolumeController reconciliation interval (seconds). Claim and pod writes
+/// wake it sooner.
+pub const VOLUME_CHECK_INTERVAL_SECS: u64 = 15;
+
 /// CronJobController reconciliation interval (seconds).
 pub const CRONJOB_CHECK_INTERVAL_SECS: u64 = 30;
 
@@ -39,6 +53,9 @@ pub const DEFAULT_BACKUP_INTERVAL_SECS: u64 = 3600;
 /// Default number of backup files to retain.
 pub const DEFAULT_BACKUP_RETENTION: usize = 5;
 
+/// Default number of Failed/Succeeded pods kept per ReplicaSet.
+pub const DEFAULT_FAILED_POD_RETENTION: usize = 5;
+
 /// Systemd restart delay (seconds).
 pub const SYSTEMD_RESTART_SECS: u64 = 5;
 
@@ -47,12 +64,6 @@ pub const SYSTEMD_RESTART_SECS: u64 = 5;
 /// NodeController health-check interval (seconds).
 pub const NODE_CHECK_INTERVAL_SECS: u64 = 15;
 
-/// Heartbeat staleness threshold before marking a node NotReady (seconds).
-pub const NODE_NOT_READY_THRESHOLD_SECS: u64 = 30;
-
-/// Heartbeat staleness threshold before marking a node Unknown (seconds).
-pub const NODE_UNKNOWN_THRESHOLD_SECS: u64 = 60;
-
 /// DaemonSetController reconciliation interval (seconds).
 pub const DAEMONSET_CHECK_INTERVAL_SECS: u64 = 15;
 
@@ -62,12 +73,18 @@ pub const JOB_CHECK_INTERVAL_SECS: u64 = 10;
 /// ReplicaSetController reconciliation interval (seconds).
 pub const REPLICASET_CHECK_INTERVAL_SECS: u64 = 10;
 
+/// SchedulingController pending-pod scan interval (seconds).
+pub const SCHEDULING_CHECK_INTERVAL_SECS: u64 = 5;
+
 /// EndpointController reconciliation interval (seconds).
 pub const ENDPOINT_CHECK_INTERVAL_SECS: u64 = 10;
 
 /// VpcController reconciliation interval (seconds).
 pub const VPC_CHECK_INTERVAL_SECS: u64 = 15;
 
+/// NamespaceController interval for finishing namespace deletions (seconds).
+pub const NAMESPACE_CHECK_INTERVAL_SECS: u64 = 5;
+
 /// RestoreWatcher poll interval (seconds).
 pub const RESTORE_WATCHER_INTERVAL_SECS: u64 = 5;
 
@@ -76,9 +93,33 @@ pub const RESTORE_WATCHER_INTERVAL_SECS: u64 = 5;
 /// Agent pod sync interval (seconds).
 pub const POD_SYNC_INTERVAL_SECS: u64 = 5;
 
+/// Agent route sync interval (seconds).
+pub const ROUTE_SYNC_INTERVAL_SECS: u64 = 10;
+
+/// Delay before the first restart of a crashed container (seconds); doubles per restart.
+pub const CRASH_LOOP_BACKOFF_BASE_SECS: u64 = 10;
+
+/// Upper bound on the crash-loop restart delay (seconds).
+pub const CRASH_LOOP_BACKOFF_MAX_SECS: u64 = 300;
+
+/// A container that stays up this long after a restart resets its backoff (seconds).
+pub const CRASH_LOOP_BACKOFF_RESET_SECS: u64 = 600;
+
+/// Poll interval when following a container log file (milliseconds).
+pub const LOG_FOLLOW_POLL_MS: u64 = 250;
+
 /// Agent image report interval (seconds).
 pub const IMAGE_REPORT_INTERVAL_SECS: u64 = 30;
 
+/// Agent image garbage collection interval (seconds).
+pub const IMAGE_GC_INTERVAL_SECS: u64 = 300;
+
+/// Agent orphan garbage collection interval (seconds).
+pub const ORPHAN_GC_INTERVAL_SECS: u64 = 60;
+
+/// Agent static pod manifest directory poll interval (seconds).
+pub const STATIC_POD_SYNC_INTERVAL_SECS: u64 = 20;
+
 // ─── VM / container timeouts ────────────────────────────────────
 
 /// Timeout for VM exec commands over IPC (seconds).
@@ -87,5 +128,78 @@ pub const VM_EXEC_TIMEOUT_SECS: u64 = 30;
 /// Timeout for vsock connection establishment (seconds).
 pub const VSOCK_CONNECT_TIMEOUT_SECS: u64 = 5;
 
+/// How often the service proxy probes each endpoint (seconds).
+pub const ENDPOINT_PROBE_INTERVAL_SECS: u64 = 5;
+
+/// Timeout of one endpoint probe (seconds).
+pub const ENDPOINT_PROBE_TIMEOUT_SECS: u64 = 2;
+
+/// Consecutive failed probes before an endpoint stops receiving traffic.
+pub const ENDPOINT_PROBE_FAILURE_THRESHOLD: u32 = 2;
+
+/// Consecutive good probes before a failed endpoint receives traffic again.
+pub const ENDPOINT_PROBE_SUCCESS_THRESHOLD: u32 = 1;
+
+/// Default idle time before a ClientIP session affinity pin expires (seconds).
+pub const DEFAULT_SESSION_AFFINITY_TIMEOUT_SECS: u64 = 10800;
+
+/// How long `POST /nodes/{name}/drain` waits for evicted pods by default (seconds).
+pub const DRAIN_TIMEOUT_SECS: u64 = 300;
+
+/// How often a drain checks on the pods it evicted (milliseconds).
+pub const DRAIN_POLL_INTERVAL_MS: u64 = 500;
+
+/// Timeout for the server connecting to an agent it proxies a request to (seconds).
+pub const AGENT_CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// How often each side of a proxied exec/port-forward WebSocket is pinged (seconds).
+pub const PROXY_PING_INTERVAL_SECS: u64 = 30;
+
+/// Silence after which one side of a proxied WebSocket is considered gone (seconds).
+pub const PROXY_IDLE_TIMEOUT_SECS: u64 = 90;
+
+/// Timeout for an agent opening its tunnel to the server (seconds).
+pub const TUNNEL_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// How often each end of an agent tunnel pings the other (seconds).
+pub const TUNNEL_PING_INTERVAL_SECS: u64 = 15;
+
+/// Silence after which an agent tunnel is considered dead (seconds).
+pub const TUNNEL_IDLE_TIMEOUT_SECS: u64 = 45;
+
 /// VPC deletion cooldown period (seconds).
 pub const VPC_DELETION_COOLDOWN_SECS: i64 = 300;
+
+// ─── Node certificates ──────────────────────────────────────────
+
+/// How long a node certificate issued at registration or renewal is valid (days).
+pub const NODE_CERT_VALIDITY_DAYS: i64 = 365;
+
+/// Default remaining validity below which an agent renews its certificate (days).
+pub const CERT_RENEW_BEFORE_DAYS: i64 = 30;
+
+/// How often the agent checks its certificate's expiry (seconds).
+pub const CERT_CHECK_INTERVAL_SECS: u64 = 86400;
+
+/// Largest clock difference accepted on a signed renewal request (seconds).
+pub const CERT_RENEWAL_MAX_SKEW_SECS: i64 = 300;
+
+// ─── State store ────────────────────────────────────────────────
+
+/// Timeout for connecting to an etcd endpoint of the state store (seconds).
+pub const ETCD_CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// Timeout for one etcd state store request (seconds).
+pub const ETCD_REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/// Timeout for connecting to the object store SlateDB keeps state in (seconds).
+pub const OBJECT_STORE_CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// How long the startup check of the object store may retry before the
+/// server gives up (seconds).
+pub const OBJECT_STORE_PROBE_TIMEOUT_SECS: u64 = 15;
+
+// ─── CLI ────────────────────────────────────────────────────────
+
+/// Delay before `k3rsctl get --watch` reopens a dropped watch stream (milliseconds).
+pub const WATCH_RECONNECT_DELAY_MS: u64 = 1000;
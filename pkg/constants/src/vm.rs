@@ -12,6 +12,27 @@ pub const DEFAULT_VM_HOSTNAME: &str = "k3rs-guest";
 /// Byte prefix that switches vsock exec into streaming PTY mode.
 pub const VSOCK_STREAM_PREFIX: u8 = 0x01;
 
+/// Byte prefix that turns a vsock exec connection into a TCP forward to a
+/// guest port: `0x02 <port>\n`, answered by `OK\n` or `ERR <reason>\n`.
+pub const VSOCK_FORWARD_PREFIX: u8 = 0x02;
+
+/// Byte prefix that runs a vsock exec with the host's stdin: after the
+/// command line the host sends stdin as frames, `length (4, BE) | bytes`,
+/// and a zero-length frame for EOF. Output streams back without a PTY.
+pub const VSOCK_STDIN_PREFIX: u8 = 0x03;
+
+/// Largest stdin frame k3rs-init accepts.
+pub const VSOCK_STDIN_MAX_FRAME: usize = 64 * 1024;
+
+/// Trailer k3rs-init writes after a command's output on a vsock exec
+/// connection, one-shot or streaming, just before closing it:
+/// `\x02exit:<code>\n`. A command killed by a signal reports `128 + signal`.
+pub const VSOCK_EXIT_TRAILER: &[u8] = b"\x02exit:";
+
+/// Exit code of `k3rs-vmm exec` when the exec itself failed (VM not running,
+/// IPC or vsock error) rather than the command, as with `docker exec`.
+pub const VMM_EXEC_FAILED: i32 = 125;
+
 // ─── Guest filesystem paths ─────────────────────────────────────
 
 /// Kernel binary filename inside the kernel directory.
@@ -31,3 +52,22 @@ pub const GUEST_ROOTFS_MOUNT: &str = "/mnt/rootfs";
 
 /// eBPF filesystem pin directory for VPC programs.
 pub const BPFFS_PIN_DIR: &str = "/sys/fs/bpf/k3rs_vpc";
+
+// ─── Guest log forwarding ───────────────────────────────────────
+
+/// vsock port k3rs-init serves the entrypoint's stdout/stderr on. The host
+/// connects to it and reads a stream of log frames:
+/// `tag (1) | unix nanos (8, BE) | length (4, BE) | line`.
+pub const VSOCK_LOG_PORT: u32 = 5556;
+
+/// Frame tag of a line the entrypoint wrote to stdout.
+pub const LOG_FRAME_STDOUT: u8 = 1;
+
+/// Frame tag of a line the entrypoint wrote to stderr.
+pub const LOG_FRAME_STDERR: u8 = 2;
+
+/// Size of a log frame header.
+pub const LOG_FRAME_HEADER_LEN: usize = 13;
+
+/// Longest line carried by one frame; longer lines are split.
+pub const LOG_FRAME_MAX_LINE: usize = 16 * 1024;
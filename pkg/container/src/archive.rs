@@ -0,0 +1,318 @@
+//! Copying files in and out of containers as tar streams (`k3rsctl cp`).
+//!
+//! Both directions run `tar` inside the container through the backend's
+//! `spawn_exec`, so they work wherever exec does, as long as the image ships
+//! a `tar`. An archive holds a single top-level entry named after the last
+//! component of the container path: downloads pack `<path>` relative to its
+//! parent directory, and uploads are unpacked into the parent of `<path>`.
+
+use std::pin::Pin;
+
+use anyhow::{Context, Result, anyhow, bail};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Child;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+
+/// A tar archive read out of a container, in chunks.
+pub type ArchiveStream = Pin<Box<dyn Stream<Item = std::io::Result<Vec<u8>>> + Send>>;
+
+const TAR_BLOCK: usize = 512;
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Split an absolute container path into the directory `tar` runs in and the
+/// entry it packs or unpacks there. `/` itself is the entry `.` of `/`.
+pub fn split_container_path(path: &str) -> Result<(String, String)> {
+    if !path.starts_with('/') {
+        bail!("container path must be absolute: {}", path);
+    }
+    let trimmed = path.trim_end_matches('/');
+    let Some((parent, name)) = trimmed.rsplit_once('/') else {
+        return Ok(("/".to_string(), ".".to_string()));
+    };
+    if name == "." || name == ".." {
+        bail!("container path must not end in '{}': {}", name, path);
+    }
+    let parent = if parent.is_empty() { "/" } else { parent };
+    Ok((parent.to_string(), name.to_string()))
+}
+
+/// `tar` writing `path` to stdout. The entry is passed as `./<name>` so a
+/// name starting with `-` isn't taken for an option.
+pub fn tar_create_command(path: &str) -> Result<Vec<String>> {
+    let (parent, name) = split_container_path(path)?;
+    Ok(vec![
+        "tar".to_string(),
+        "cf".to_string(),
+        "-".to_string(),
+        "-C".to_string(),
+        parent,
+        format!("./{}", name),
+    ])
+}
+
+/// `tar` unpacking stdin into the parent directory of `path`, keeping modes.
+pub fn tar_extract_command(path: &str) -> Result<Vec<String>> {
+    let (parent, _) = split_container_path(path)?;
+    Ok(vec![
+        "tar".to_string(),
+        "xpf".to_string(),
+        "-".to_string(),
+        "-C".to_string(),
+        parent,
+    ])
+}
+
+/// Whether a failed exec of `tar` means the image has no `tar` binary:
+/// exit 127 from a shell or nsenter, or the runtime's "not found" message.
+pub fn is_tar_missing(code: Option<i32>, stderr: &str) -> bool {
+    code == Some(127)
+        || stderr.contains("failed to execute tar")
+        || (stderr.contains("tar") && stderr.contains("not found"))
+}
+
+fn tar_error(path: &str, code: Option<i32>, stderr: &str) -> anyhow::Error {
+    if is_tar_missing(code, stderr) {
+        return anyhow!(
+            "the container has no tar binary, which copying files needs; \
+             add one to the image (e.g. busybox)"
+        );
+    }
+    let code = code.map_or_else(|| "signal".to_string(), |c| c.to_string());
+    anyhow!("tar failed for {} (exit {}): {}", path, code, stderr.trim())
+}
+
+async fn read_all(pipe: Option<impl AsyncRead + Unpin>) -> String {
+    let mut out = Vec::new();
+    if let Some(mut pipe) = pipe {
+        let _ = pipe.read_to_end(&mut out).await;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Stream the archive written by a [`tar_create_command`] child.
+///
+/// The stream is held back until tar has written its first header block or
+/// exited, so a missing path or a missing tar comes back as an error here
+/// instead of as a truncated archive. A failure after that point ends the
+/// stream with an error item. The child's stdin is kept open until tar is
+/// done: exec bridges that see stdin close may hang up early.
+pub async fn read_archive(mut child: Child, path: &str) -> Result<ArchiveStream> {
+    let mut stdout = child.stdout.take().context("tar stdout is not piped")?;
+    let stderr = tokio::spawn(read_all(child.stderr.take()));
+
+    let mut head = Vec::with_capacity(CHUNK_SIZE);
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    while head.len() < TAR_BLOCK {
+        let n = stdout.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+
+    // A short or all-zero first block means nothing was packed: tar either
+    // failed or, for an empty directory, wrote only the end-of-archive marker.
+    if head.len() < TAR_BLOCK || head[..TAR_BLOCK].iter().all(|&b| b == 0) {
+        stdout.read_to_end(&mut head).await?;
+        let status = child.wait().await?;
+        let stderr = stderr.await.unwrap_or_default();
+        if !status.success() {
+            return Err(tar_error(path, status.code(), &stderr));
+        }
+        if head.len() < TAR_BLOCK {
+            let output = format!("{}{}", String::from_utf8_lossy(&head), stderr);
+            return Err(tar_error(path, None, &output));
+        }
+        return Ok(Box::pin(tokio_stream::once(Ok(head))));
+    }
+
+    let (tx, rx) = tokio::sync::mpsc::channel(8);
+    let path = path.to_string();
+    tokio::spawn(async move {
+        let mut chunk = head;
+        loop {
+            if tx.send(Ok(chunk)).await.is_err() {
+                // The reader went away; don't leave tar blocked on a full pipe.
+                let _ = child.kill().await;
+                return;
+            }
+            chunk = match stdout.read(&mut buf).await {
+                Ok(0) => break,
+                Ok(n) => buf[..n].to_vec(),
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    let _ = child.kill().await;
+                    return;
+                }
+            };
+        }
+        let result = match child.wait().await {
+            Ok(status) if status.success() => return,
+            Ok(status) => tar_error(&path, status.code(), &stderr.await.unwrap_or_default()),
+            Err(e) => e.into(),
+        };
+        let _ = tx
+            .send(Err(std::io::Error::other(result.to_string())))
+            .await;
+    });
+    Ok(Box::pin(ReceiverStream::new(rx)))
+}
+
+/// Feed `archive` to a [`tar_extract_command`] child and wait for it to
+/// unpack. Returns the number of archive bytes written.
+pub async fn write_archive<S, B, E>(mut child: Child, path: &str, archive: S) -> Result<u64>
+where
+    S: Stream<Item = std::result::Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: std::fmt::Display,
+{
+    let mut stdin = child.stdin.take().context("tar stdin is not piped")?;
+    let stdout = tokio::spawn(read_all(child.stdout.take()));
+    let stderr = tokio::spawn(read_all(child.stderr.take()));
+
+    let mut archive = std::pin::pin!(archive);
+    let mut written = 0u64;
+    while let Some(chunk) = archive.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                let _ = child.kill().await;
+                bail!("reading archive for {}: {}", path, e);
+            }
+        };
+        // A write error means tar exited early; its status says why.
+        if stdin.write_all(chunk.as_ref()).await.is_err() {
+            break;
+        }
+        written += chunk.as_ref().len() as u64;
+    }
+    drop(stdin);
+
+    let status = child.wait().await?;
+    if !status.success() {
+        let output = format!(
+            "{}{}",
+            stderr.await.unwrap_or_default(),
+            stdout.await.unwrap_or_default()
+        );
+        return Err(tar_error(path, status.code(), &output));
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+    use std::os::unix::fs::{PermissionsExt, symlink};
+    use std::path::Path;
+    use std::process::Stdio;
+
+    /// A local `tar` child standing in for an exec into a container whose
+    /// root is the host filesystem.
+    fn spawn_tar(args: &[String]) -> Child {
+        tokio::process::Command::new(&args[0])
+            .args(&args[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_split_container_path() {
+        let split = |p: &str| split_container_path(p).unwrap();
+        assert_eq!(split("/etc/nginx"), ("/etc".into(), "nginx".into()));
+        assert_eq!(split("/etc/nginx/"), ("/etc".into(), "nginx".into()));
+        assert_eq!(split("/data"), ("/".into(), "data".into()));
+        assert_eq!(split("/"), ("/".into(), ".".into()));
+        assert!(split_container_path("etc/nginx").is_err());
+        assert!(split_container_path("/etc/..").is_err());
+    }
+
+    #[test]
+    fn test_tar_commands() {
+        assert_eq!(
+            tar_create_command("/var/log/app").unwrap().join(" "),
+            "tar cf - -C /var/log ./app"
+        );
+        assert_eq!(
+            tar_extract_command("/tmp/upload").unwrap().join(" "),
+            "tar xpf - -C /tmp"
+        );
+    }
+
+    #[test]
+    fn test_tar_missing() {
+        assert!(is_tar_missing(Some(127), ""));
+        assert!(is_tar_missing(
+            Some(1),
+            "nsenter: failed to execute tar: No such file or directory"
+        ));
+        assert!(!is_tar_missing(
+            Some(2),
+            "tar: ./missing: Cannot stat: No such file or directory"
+        ));
+        let err = tar_error("/x", Some(127), "").to_string();
+        assert!(err.contains("busybox"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_binary_file_and_symlink() {
+        let src = temp_dir("src").join("payload");
+        std::fs::create_dir_all(src.join("sub")).unwrap();
+        let binary: Vec<u8> = (0..=255u8).cycle().take(200_000).collect();
+        std::fs::write(src.join("sub/blob.bin"), &binary).unwrap();
+        std::fs::set_permissions(src.join("sub/blob.bin"), PermissionsExt::from_mode(0o750))
+            .unwrap();
+        symlink("sub/blob.bin", src.join("link")).unwrap();
+
+        let path = src.to_str().unwrap();
+        let child = spawn_tar(&tar_create_command(path).unwrap());
+        let stream = read_archive(child, path).await.unwrap();
+
+        // Unpacking to a path with the same last component recreates the tree.
+        let dest = temp_dir("dest").join("payload");
+        let dest_path = dest.to_str().unwrap();
+        let child = spawn_tar(&tar_extract_command(dest_path).unwrap());
+        let written = write_archive(child, dest_path, stream).await.unwrap();
+
+        assert!(written >= binary.len() as u64);
+        assert_eq!(std::fs::read(dest.join("sub/blob.bin")).unwrap(), binary);
+        let mode = std::fs::metadata(dest.join("sub/blob.bin"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o750);
+        assert_eq!(
+            std::fs::read_link(dest.join("link")).unwrap(),
+            Path::new("sub/blob.bin")
+        );
+
+        let _ = std::fs::remove_dir_all(src.parent().unwrap());
+        let _ = std::fs::remove_dir_all(dest.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_missing_path_is_an_error() {
+        let dir = temp_dir("missing");
+        let path = format!("{}/nope", dir.display());
+        let child = spawn_tar(&tar_create_command(&path).unwrap());
+        let err = read_archive(child, &path).await.err().unwrap().to_string();
+        assert!(err.contains("tar failed"), "{}", err);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_missing_tar_suggests_busybox() {
+        let child = spawn_tar(&[
+            "sh".to_string(),
+            "-c".to_string(),
+            "exec tar-not-installed cf -".to_string(),
+        ]);
+        let err = read_archive(child, "/etc").await.err().unwrap().to_string();
+        assert!(err.contains("busybox"), "{}", err);
+    }
+}
@@ -1,8 +1,73 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use dashmap::DashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use crate::logs::{
+    LogOptions, LogRotation, LogStream, read_log_file, spawn_log_relay, tail_log_file,
+};
 use crate::state::ContainerStateInfo;
+use pkg_types::pod::ResourceRequirements;
+
+/// A byte stream to a port inside a container, from
+/// [`RuntimeBackend::port_forward`].
+pub trait ForwardStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin {}
+
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin> ForwardStream for T {}
+
+/// Outcome of [`RuntimeBackend::exec`]. A command that ran and failed is
+/// still `Ok`, with a non-zero `exit_code`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecResult {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i32,
+}
+
+impl ExecResult {
+    pub(crate) fn from_output(output: &std::process::Output) -> Self {
+        Self {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            exit_code: exit_code(output.status),
+        }
+    }
+
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+/// Exit code of a finished process, `128 + signal` if it was killed.
+pub fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    match status.code() {
+        Some(code) => code,
+        None => 128 + status.signal().unwrap_or(0),
+    }
+}
+
+/// Poll `pid`, a child of this process, until it exits, reap it and return
+/// its exit code. Fails if `pid` is not a child of this process.
+async fn wait_pid(pid: u32) -> Result<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    let interval = Duration::from_millis(pkg_constants::runtime::EXIT_POLL_INTERVAL_MS);
+    let _claim = crate::reaper::claim(pid);
+    let mut status = 0;
+    loop {
+        match unsafe { libc::waitpid(pid as libc::pid_t, &mut status, libc::WNOHANG) } {
+            0 => tokio::time::sleep(interval).await,
+            -1 => {
+                let err = std::io::Error::last_os_error();
+                if err.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(anyhow::anyhow!("waitpid({}) failed: {}", pid, err));
+                }
+            }
+            _ => return Ok(exit_code(std::process::ExitStatus::from_raw(status))),
+        }
+    }
+}
 
 /// Pluggable runtime backend trait.
 /// Implementations: Virtualization (macOS), OCI (youki/crun on Linux).
@@ -19,6 +84,19 @@ pub trait RuntimeBackend: Send + Sync + 'static {
     /// Create a container from an OCI bundle directory (OCI) or image name (Docker).
     async fn create(&self, id: &str, bundle: &Path) -> Result<()>;
 
+    /// Create a container sized to `resources` (its effective limits). VM
+    /// backends boot the microVM with a matching vCPU count and memory; the
+    /// default ignores them.
+    async fn create_with_resources(
+        &self,
+        id: &str,
+        bundle: &Path,
+        resources: &ResourceRequirements,
+    ) -> Result<()> {
+        let _ = resources;
+        self.create(id, bundle).await
+    }
+
     /// Create a container directly from an image reference (Docker shortcut).
     /// Default implementation delegates to create() — Docker overrides this.
     async fn create_from_image(&self, id: &str, image: &str, command: &[String]) -> Result<()> {
@@ -31,8 +109,29 @@ pub trait RuntimeBackend: Send + Sync + 'static {
     /// Start a created container.
     async fn start(&self, id: &str) -> Result<()>;
 
-    /// Stop a running container (SIGTERM → SIGKILL).
-    async fn stop(&self, id: &str) -> Result<()>;
+    /// Create and start a container in the foreground, wait for it to exit and
+    /// return its exit code. Used for init containers.
+    async fn run(&self, id: &str, bundle: &Path) -> Result<i32> {
+        let _ = bundle;
+        Err(anyhow::anyhow!(
+            "running container {} to completion is not supported by the {} backend",
+            id,
+            self.name()
+        ))
+    }
+
+    /// Wait for a started container to exit and return its exit code.
+    async fn wait(&self, id: &str) -> Result<i32> {
+        Err(anyhow::anyhow!(
+            "exit status of container {} is not reported by the {} backend",
+            id,
+            self.name()
+        ))
+    }
+
+    /// Stop a running container: SIGTERM, then SIGKILL if it is still
+    /// running after `grace` (immediately when `grace` is zero).
+    async fn stop(&self, id: &str, grace: Duration) -> Result<()>;
 
     /// Delete a stopped container.
     async fn delete(&self, id: &str) -> Result<()>;
@@ -40,14 +139,43 @@ pub trait RuntimeBackend: Send + Sync + 'static {
     /// List running container IDs managed by k3rs.
     async fn list(&self) -> Result<Vec<String>>;
 
-    /// Get logs from a container.
-    async fn logs(&self, id: &str, tail: usize) -> Result<Vec<String>>;
+    /// Get logs from a container, filtered by `opts`.
+    async fn logs(&self, id: &str, opts: &LogOptions) -> Result<Vec<String>>;
+
+    /// Path of the file this backend writes the container's stdout/stderr to,
+    /// if it keeps one. Used by the default `logs_stream`.
+    fn log_file(&self, id: &str) -> Option<PathBuf> {
+        let _ = id;
+        None
+    }
+
+    /// Stream a container's log lines, optionally following new output.
+    ///
+    /// The default implementation tails `log_file()`; backends without a log
+    /// file fall back to a one-shot snapshot from `logs()`. VM backends have
+    /// no stream of their own: both relay the output k3rs-init forwards over
+    /// vsock into their log file as it arrives, so tailing it follows the
+    /// guest live.
+    async fn logs_stream(&self, id: &str, follow: bool, opts: &LogOptions) -> Result<LogStream> {
+        match self.log_file(id) {
+            Some(path) => Ok(tail_log_file(path, follow, opts.clone())),
+            None => {
+                let lines = self.logs(id, opts).await?;
+                Ok(Box::pin(tokio_stream::iter(lines)))
+            }
+        }
+    }
 
-    /// Execute a command inside a running container.
-    async fn exec(&self, id: &str, command: &[&str]) -> Result<String>;
+    /// Execute a command inside a running container and collect its output
+    /// and exit code. `Err` means the command could not be run at all.
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecResult>;
 
     /// Spawn a command inside a running container and return the child process handle.
     /// Used for non-interactive WebSocket sessions.
+    ///
+    /// The child's stdin, stdout and stderr are piped. Bytes written to stdin
+    /// reach the command's stdin, and dropping it is the command's EOF; this
+    /// is the path for any exec that needs input.
     async fn spawn_exec(
         &self,
         id: &str,
@@ -64,6 +192,16 @@ pub trait RuntimeBackend: Send + Sync + 'static {
         None
     }
 
+    /// Open a TCP connection to `port` on the container's loopback
+    /// interface, as a process inside the container would.
+    async fn port_forward(&self, id: &str, port: u16) -> Result<Box<dyn ForwardStream>> {
+        let _ = (id, port);
+        anyhow::bail!(
+            "port-forward is not supported by the {} backend",
+            self.name()
+        )
+    }
+
     /// Query the real OCI runtime state of a container.
     /// Runs `<runtime> state <id>` and parses the JSON output.
     async fn state(&self, id: &str) -> Result<ContainerStateInfo> {
@@ -95,6 +233,10 @@ pub struct OciBackend {
     log_dir: PathBuf,
     /// Root directory for runtime state (--root flag)
     state_dir: PathBuf,
+    /// Tasks copying each container's stdout FIFO into its timestamped log file
+    log_relays: DashMap<String, tokio::task::JoinHandle<()>>,
+    /// Rotation of the log files the relays write
+    log_rotation: LogRotation,
 }
 
 impl OciBackend {
@@ -164,9 +306,17 @@ impl OciBackend {
             runtime_version: version,
             log_dir: data_dir.join("logs"),
             state_dir: data_dir.join("state"),
+            log_relays: DashMap::new(),
+            log_rotation: LogRotation::default(),
         }
     }
 
+    /// Rotate container log files per `rotation` instead of the default.
+    pub fn with_log_rotation(mut self, rotation: LogRotation) -> Self {
+        self.log_rotation = rotation;
+        self
+    }
+
     /// Get the log directory for a container.
     pub fn container_log_dir(&self, id: &str) -> PathBuf {
         self.log_dir.join(id)
@@ -177,6 +327,11 @@ impl OciBackend {
         self.log_dir.join(id).join("stdout.log")
     }
 
+    /// Get the FIFO the container's stdout/stderr are connected to.
+    pub fn container_stdout_fifo(&self, id: &str) -> PathBuf {
+        self.log_dir.join(id).join("stdout.fifo")
+    }
+
     /// Get the PID file path for a container.
     pub fn container_pid_file(&self, id: &str) -> PathBuf {
         self.log_dir.join(id).join("container.pid")
@@ -197,6 +352,60 @@ impl OciBackend {
         Ok(dir)
     }
 
+    /// Create the container's stdout FIFO if it does not exist yet.
+    fn ensure_stdout_fifo(&self, id: &str) -> Result<PathBuf> {
+        let fifo = self.container_stdout_fifo(id);
+        if !fifo.exists() {
+            let c_path = std::ffi::CString::new(fifo.to_string_lossy().as_bytes())?;
+            if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+        }
+        Ok(fifo)
+    }
+
+    /// Start relaying the container's stdout FIFO into its log file, unless a
+    /// relay is already running. Called on create and again when containers
+    /// are rediscovered after an agent restart.
+    fn attach_log_relay(&self, id: &str) -> Result<()> {
+        if self
+            .log_relays
+            .get(id)
+            .is_some_and(|relay| !relay.is_finished())
+        {
+            return Ok(());
+        }
+        let fifo = self.container_stdout_fifo(id);
+        // Holding a write end as well keeps the reader from seeing EOF
+        // between container writes.
+        #[cfg(target_os = "linux")]
+        let receiver = tokio::net::unix::pipe::OpenOptions::new()
+            .read_write(true)
+            .open_receiver(&fifo)?;
+        #[cfg(not(target_os = "linux"))]
+        let receiver = tokio::net::unix::pipe::OpenOptions::new().open_receiver(&fifo)?;
+        let relay = spawn_log_relay(receiver, self.container_log_path(id), self.log_rotation);
+        self.log_relays.insert(id.to_string(), relay);
+        Ok(())
+    }
+
+    /// Stdout/stderr handles for a container process.
+    ///
+    /// The container writes to a FIFO that the agent relays into the log
+    /// file with a timestamp per line. The container's end is opened
+    /// read-write so it never sees EPIPE while the agent (and with it the
+    /// relay) restarts — output just waits in the pipe buffer.
+    fn container_stdio(&self, id: &str) -> Result<(std::fs::File, std::fs::File)> {
+        let fifo = self.ensure_stdout_fifo(id)?;
+        self.attach_log_relay(id)?;
+        let stdout_file = std::fs::File::options()
+            .read(true)
+            .write(true)
+            .open(&fifo)?;
+        let stderr_file = stdout_file.try_clone()?;
+        Ok((stdout_file, stderr_file))
+    }
+
     fn get_version(path: &str) -> String {
         std::process::Command::new(path)
             .arg("--version")
@@ -264,12 +473,7 @@ impl RuntimeBackend for OciBackend {
         // Ensure the log directory exists
         self.ensure_log_dir(id)?;
 
-        // Open the stdout log file for the container process
-        let stdout_file = std::fs::File::options()
-            .create(true)
-            .append(true)
-            .open(&log_path)?;
-        let stderr_file = stdout_file.try_clone()?;
+        let (stdout_file, stderr_file) = self.container_stdio(id)?;
 
         let mut command = self.cmd();
         command
@@ -289,9 +493,11 @@ impl RuntimeBackend for OciBackend {
         let status = command.status().await?;
 
         if !status.success() {
-            let stderr = tokio::fs::read_to_string(&log_path)
+            let stderr = tokio::fs::read_to_string(&runtime_log)
                 .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
+                .ok()
+                .filter(|s| !s.trim().is_empty())
+                .unwrap_or_else(|| "Unknown error".to_string());
             anyhow::bail!(
                 "[{}] create failed for {}: {}",
                 self.runtime_name,
@@ -310,6 +516,45 @@ impl RuntimeBackend for OciBackend {
         Ok(())
     }
 
+    async fn run(&self, id: &str, bundle: &Path) -> Result<i32> {
+        tracing::info!("[{}] run container: {}", self.runtime_name, id);
+
+        self.ensure_log_dir(id)?;
+        std::fs::create_dir_all(&self.state_dir)?;
+        let pid_file = self.container_pid_file(id);
+        let runtime_log = self.container_log_dir(id).join("runtime.log");
+        let (stdout_file, stderr_file) = self.container_stdio(id)?;
+
+        // Without --detach the runtime stays in the foreground and exits with
+        // the container's exit code.
+        let status = self
+            .cmd()
+            .args([
+                "--log",
+                &runtime_log.to_string_lossy(),
+                "run",
+                "--bundle",
+                &bundle.to_string_lossy(),
+                "--pid-file",
+                &pid_file.to_string_lossy(),
+                id,
+            ])
+            .stdin(std::process::Stdio::null())
+            .stdout(stdout_file)
+            .stderr(stderr_file)
+            .status()
+            .await?;
+
+        let code = exit_code(status);
+        tracing::info!(
+            "[{}] container {} exited with code {}",
+            self.runtime_name,
+            id,
+            code
+        );
+        Ok(code)
+    }
+
     async fn start(&self, id: &str) -> Result<()> {
         tracing::info!("[{}] start container: {}", self.runtime_name, id);
         let output = self.cmd().args(["start", id]).output().await?;
@@ -337,15 +582,51 @@ impl RuntimeBackend for OciBackend {
         Ok(())
     }
 
-    async fn stop(&self, id: &str) -> Result<()> {
-        tracing::info!("[{}] stop container: {}", self.runtime_name, id);
+    async fn wait(&self, id: &str) -> Result<i32> {
+        let pid = self
+            .read_pid(id)
+            .ok_or_else(|| anyhow::anyhow!("[{}] no PID recorded for {}", self.runtime_name, id))?;
+        let code = wait_pid(pid).await?;
+        tracing::info!(
+            "[{}] container {} exited with code {}",
+            self.runtime_name,
+            id,
+            code
+        );
+        Ok(code)
+    }
 
-        // Send SIGTERM first
-        let _ = self.cmd().args(["kill", id, "SIGTERM"]).output().await;
+    async fn stop(&self, id: &str, grace: Duration) -> Result<()> {
+        tracing::info!(
+            "[{}] stop container: {} (grace={}s)",
+            self.runtime_name,
+            id,
+            grace.as_secs()
+        );
 
-        // Wait briefly then force kill
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-        let _ = self.cmd().args(["kill", id, "SIGKILL"]).output().await;
+        let outcome = crate::stop::graceful_stop(
+            grace,
+            async {
+                let _ = self.cmd().args(["kill", id, "SIGTERM"]).output().await;
+            },
+            || async {
+                self.state(id)
+                    .await
+                    .is_ok_and(|s| s.status == "running" || s.status == "paused")
+            },
+            async {
+                let _ = self.cmd().args(["kill", id, "SIGKILL"]).output().await;
+            },
+        )
+        .await;
+        if outcome == crate::stop::StopOutcome::Killed {
+            tracing::warn!(
+                "[{}] container {} still running after {}s grace, sent SIGKILL",
+                self.runtime_name,
+                id,
+                grace.as_secs()
+            );
+        }
 
         Ok(())
     }
@@ -364,6 +645,10 @@ impl RuntimeBackend for OciBackend {
             );
         }
 
+        if let Some((_, relay)) = self.log_relays.remove(id) {
+            relay.abort();
+        }
+
         // Clean up log/pid files
         let log_dir = self.container_log_dir(id);
         if log_dir.exists() {
@@ -381,25 +666,23 @@ impl RuntimeBackend for OciBackend {
             .filter(|l| !l.trim().is_empty())
             .map(|l| l.trim().to_string())
             .collect();
+
+        // Reattach log relays for containers that outlived a previous agent.
+        for id in &ids {
+            if self.container_stdout_fifo(id).exists()
+                && let Err(e) = self.attach_log_relay(id)
+            {
+                tracing::warn!("[{}] log relay for {}: {}", self.runtime_name, id, e);
+            }
+        }
         Ok(ids)
     }
 
-    async fn logs(&self, id: &str, tail: usize) -> Result<Vec<String>> {
-        // Read from the container's stdout log file.
+    async fn logs(&self, id: &str, opts: &LogOptions) -> Result<Vec<String>> {
+        // Read from the container's stdout log file and those rotated out of it.
         let log_path = self.container_log_path(id);
-        match tokio::fs::read_to_string(&log_path).await {
-            Ok(content) => {
-                let lines: Vec<String> = content
-                    .lines()
-                    .rev()
-                    .take(tail)
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>()
-                    .into_iter()
-                    .rev()
-                    .collect();
-                Ok(lines)
-            }
+        match read_log_file(log_path.clone(), opts.clone()).await {
+            Ok(lines) => Ok(lines),
             Err(_) => Ok(vec![format!(
                 "[{}] No logs available for container {} (log path: {})",
                 self.runtime_name,
@@ -409,7 +692,11 @@ impl RuntimeBackend for OciBackend {
         }
     }
 
-    async fn exec(&self, id: &str, command: &[&str]) -> Result<String> {
+    fn log_file(&self, id: &str) -> Option<PathBuf> {
+        Some(self.container_log_path(id))
+    }
+
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecResult> {
         tracing::info!(
             "[{}] exec in container {}: {:?}",
             self.runtime_name,
@@ -447,14 +734,7 @@ impl RuntimeBackend for OciBackend {
                 .args(&args)
                 .output()
                 .await?;
-            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-            return if output.status.success() {
-                Ok(stdout)
-            } else {
-                anyhow::bail!("[nsenter] exec failed in {}: {}{}", id, stdout, stderr)
-            };
+            return Ok(ExecResult::from_output(&output));
         }
 
         // Fallback: youki/crun exec (requires elevated privileges or root).
@@ -462,20 +742,7 @@ impl RuntimeBackend for OciBackend {
         args.extend_from_slice(command);
 
         let output = self.cmd().args(&args).output().await?;
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-        if output.status.success() {
-            Ok(stdout)
-        } else {
-            anyhow::bail!(
-                "[{}] exec failed in {}: {}{}",
-                self.runtime_name,
-                id,
-                stdout,
-                stderr
-            )
-        }
+        Ok(ExecResult::from_output(&output))
     }
 
     async fn spawn_exec(
@@ -553,6 +820,23 @@ impl RuntimeBackend for OciBackend {
         Some(self.runtime_path.clone())
     }
 
+    #[cfg(target_os = "linux")]
+    async fn port_forward(&self, id: &str, port: u16) -> Result<Box<dyn ForwardStream>> {
+        let pid = self
+            .read_pid(id)
+            .ok_or_else(|| anyhow::anyhow!("container {} is not running", id))?;
+        // Joining the namespace taints the thread, so use one of its own.
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(crate::linux::netns::connect_in_netns(pid, port));
+        });
+        let stream = rx.await?.map_err(|e| {
+            anyhow::anyhow!("connecting to port {} in container {}: {}", port, id, e)
+        })?;
+        stream.set_nonblocking(true)?;
+        Ok(Box::new(tokio::net::TcpStream::from_std(stream)?))
+    }
+
     async fn state(&self, id: &str) -> Result<ContainerStateInfo> {
         let output = self.cmd().args(["state", id]).output().await?;
 
@@ -597,6 +881,53 @@ impl RuntimeBackend for OciBackend {
                 .and_then(|v| v.as_str())
                 .unwrap_or("")
                 .to_string(),
+            vcpus: None,
+            memory_mb: None,
+            usage: None,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{stub_runtime, temp_dir};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// An OCI runtime whose `exec <id> <command...>` runs the command on
+    /// the host, with the stdio it was given.
+    const EXEC_ON_HOST: &str = "#!/bin/sh\n\
+         while [ \"$1\" != exec ]; do [ $# -gt 0 ] || exit 0; shift; done\n\
+         shift 2\n\
+         exec \"$@\"\n";
+
+    #[tokio::test]
+    async fn test_spawn_exec_pipes_stdin_to_the_command() {
+        let dir = temp_dir("stdin");
+        let runtime = stub_runtime(&dir, EXEC_ON_HOST);
+        let backend = OciBackend::new(runtime.to_str().unwrap(), &dir);
+
+        let mut child = backend
+            .spawn_exec("c1", &["wc", "-c"], false)
+            .await
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        let writer = tokio::spawn(async move {
+            stdin.write_all(&vec![b'x'; 100_000]).await.unwrap();
+            // Dropping stdin is the command's EOF.
+        });
+        let mut output = String::new();
+        child
+            .stdout
+            .take()
+            .unwrap()
+            .read_to_string(&mut output)
+            .await
+            .unwrap();
+        writer.await.unwrap();
+
+        assert_eq!(output.trim(), "100000");
+        assert!(child.wait().await.unwrap().success());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
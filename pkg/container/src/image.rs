@@ -1,128 +1,381 @@
 use anyhow::Result;
-use oci_client::{Client, Reference, client::ClientConfig, manifest::ImageIndexEntry};
+use dashmap::DashMap;
+use oci_client::{
+    Client, Reference,
+    client::{ClientConfig, ClientProtocol},
+    errors::OciDistributionError,
+    manifest::{ImageIndexEntry, OciDescriptor, OciImageManifest, OciManifest},
+    secrets::RegistryAuth,
+};
+use pkg_types::pod::ImagePullPolicy;
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use tracing::info;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::layers::{self, LayerCache};
+use crate::pull;
+use crate::registry_auth::{self, AuthenticationRequired, RegistryKeychain};
+use crate::rootfs::RootfsManager;
 
 /// Manages OCI image pulling and layer caching.
 pub struct ImageManager {
     /// Root directory for image storage: `<data_dir>/images/`
     images_dir: PathBuf,
-    /// OCI registry client
+    /// OCI registry client for anonymous pulls
     client: Client,
+    /// Registries spoken to over plain HTTP
+    insecure_registries: Vec<String>,
+    /// Layer blobs shared by all images: `<data_dir>/blobs/`
+    layer_cache: Arc<LayerCache>,
+    /// Pulls that went to a registry, for the agent's metrics
+    stats: PullStats,
+    /// One slot per image directory, by image ID. Entries are never removed:
+    /// a caller must find the slot that an in-flight pull holds.
+    slots: DashMap<String, Arc<PullSlot>>,
+}
+
+/// Serializes the pulls of one image. Fetching holds the lock for writing;
+/// extracting from the cached image holds it for reading, so the layers a
+/// rootfs is built from are never swapped underneath it.
+#[derive(Default)]
+struct PullSlot {
+    lock: RwLock<()>,
+    /// Pulls of the image that completed, so a caller that waited on one
+    /// uses its result instead of fetching again.
+    fetched: AtomicU64,
+}
+
+/// Counts of image pulls that went to a registry; cache hits are not pulls.
+#[derive(Debug, Default)]
+pub struct PullStats {
+    pulled: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl PullStats {
+    /// Pulls attempted.
+    pub fn pulled(&self) -> u64 {
+        self.pulled.load(Ordering::Relaxed)
+    }
+
+    /// Pulls that failed.
+    pub fn failed(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Count a pull; it is counted as failed unless
+    /// [`PullAttempt::succeeded`] is called before the guard is dropped.
+    fn attempt(&self) -> PullAttempt<'_> {
+        self.pulled.fetch_add(1, Ordering::Relaxed);
+        PullAttempt {
+            stats: self,
+            succeeded: false,
+        }
+    }
+}
+
+/// An in-progress pull, see [`PullStats::attempt`].
+struct PullAttempt<'a> {
+    stats: &'a PullStats,
+    succeeded: bool,
+}
+
+impl PullAttempt<'_> {
+    fn succeeded(mut self) {
+        self.succeeded = true;
+    }
+}
+
+impl Drop for PullAttempt<'_> {
+    fn drop(&mut self) {
+        if !self.succeeded {
+            self.stats.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
 }
 
 impl ImageManager {
     pub fn new(data_dir: &Path) -> Self {
         let images_dir = data_dir.join("images");
-        let config = ClientConfig {
-            protocol: oci_client::client::ClientProtocol::HttpsExcept(vec![
-                pkg_constants::network::LOCAL_REGISTRY.to_string(),
-            ]),
-            // Always resolve to linux/<host_arch> — container images are Linux-based,
-            // even when running on macOS (VirtualizationBackend boots a Linux microVM).
-            platform_resolver: Some(Box::new(linux_platform_resolver)),
-            ..Default::default()
-        };
-        let client = Client::new(config);
-        Self { images_dir, client }
+        let insecure_registries = vec![pkg_constants::network::LOCAL_REGISTRY.to_string()];
+        let client = Client::new(client_config(&insecure_registries));
+        Self {
+            images_dir,
+            client,
+            insecure_registries,
+            layer_cache: Arc::new(LayerCache::new(data_dir)),
+            stats: PullStats::default(),
+            slots: DashMap::new(),
+        }
     }
 
-    /// Pull an image from a registry. Returns the path to the image directory.
+    fn slot(&self, image_id: &str) -> Arc<PullSlot> {
+        self.slots.entry(image_id.to_string()).or_default().clone()
+    }
+
+    /// Registry pulls made so far.
+    pub fn stats(&self) -> &PullStats {
+        &self.stats
+    }
+
+    /// Pull an image from a registry under `policy`. Returns the path to the
+    /// image directory.
     /// Layout: `<images_dir>/<image_hash>/` containing manifest.json + layer blobs.
-    pub async fn pull(&self, image_ref: &str) -> Result<PathBuf> {
+    ///
+    /// `image@sha256:<digest>` references are pinned: the registry's manifest
+    /// must hash to that digest or the pull fails.
+    ///
+    /// Private registries are authenticated with the `keychain` entry for the
+    /// image's registry host, falling back to the node credentials file.
+    ///
+    /// Concurrent pulls of one image wait for a single fetch and share it,
+    /// whatever their policy.
+    pub async fn pull(
+        &self,
+        image_ref: &str,
+        policy: ImagePullPolicy,
+        keychain: &RegistryKeychain,
+    ) -> Result<PathBuf> {
+        self.pull_layers(image_ref, policy, keychain, None).await
+    }
+
+    /// Pull an image like [`pull`](Self::pull) and extract it into
+    /// `<container_dir>/rootfs`. Each layer is extracted as soon as it and
+    /// the layers below it are downloaded, while later layers keep
+    /// downloading. Returns the image directory and the rootfs path.
+    pub async fn pull_and_extract(
+        &self,
+        image_ref: &str,
+        policy: ImagePullPolicy,
+        keychain: &RegistryKeychain,
+        container_dir: &Path,
+    ) -> Result<(PathBuf, PathBuf)> {
+        let rootfs = container_dir.join("rootfs");
+        tokio::fs::create_dir_all(&rootfs).await?;
+        let image_dir = self
+            .pull_layers(image_ref, policy, keychain, Some(&rootfs))
+            .await?;
+        info!("Rootfs extracted to {}", rootfs.display());
+        Ok((image_dir, rootfs))
+    }
+
+    /// Shared body of [`pull`](Self::pull) and
+    /// [`pull_and_extract`](Self::pull_and_extract): fetch what `policy`
+    /// requires, extracting every layer into `rootfs` when given.
+    async fn pull_layers(
+        &self,
+        image_ref: &str,
+        policy: ImagePullPolicy,
+        keychain: &RegistryKeychain,
+        rootfs: Option<&Path>,
+    ) -> Result<PathBuf> {
         let reference: Reference = image_ref
             .parse()
             .map_err(|e| anyhow::anyhow!("Invalid image reference '{}': {}", image_ref, e))?;
 
-        info!("Pulling image: {}", reference);
-
         // Create image directory using a hash of the reference
-        let image_hash = format!("{:x}", md5_hash(image_ref));
-        let image_dir = self.images_dir.join(&image_hash);
-
-        // Check if already pulled — manifest must exist AND at least one layer file.
-        // An empty/partial cache (e.g. from a crashed download) is treated as a miss.
+        let id = image_id(image_ref);
+        let image_dir = self.images_dir.join(&id);
         let layers_dir = image_dir.join("layers");
-        let has_layers = layers_dir.exists()
-            && std::fs::read_dir(&layers_dir)
-                .map(|mut rd| {
-                    rd.any(|e| {
-                        e.ok()
-                            .and_then(|e| e.path().extension().map(|x| x == "gz"))
-                            .unwrap_or(false)
-                    })
-                })
-                .unwrap_or(false);
 
-        if image_dir.join("manifest.json").exists() && has_layers {
+        let slot = self.slot(&id);
+        let fetched = slot.fetched.load(Ordering::Acquire);
+        let guard = slot.lock.write().await;
+        let cached = is_complete(&image_dir).await;
+        // Someone else fetched the image while this pull waited for it.
+        let shared = cached && slot.fetched.load(Ordering::Acquire) != fetched;
+
+        if shared || !pull::needs_fetch(image_ref, policy, cached)? {
+            let _guard = guard.downgrade();
             info!(
                 "Image {} already cached at {}",
                 image_ref,
                 image_dir.display()
             );
+            if let Some(rootfs) = rootfs {
+                RootfsManager::extract_layers(&image_dir, rootfs).await?;
+            }
+            mark_used(&image_dir).await;
             return Ok(image_dir);
         }
 
-        tokio::fs::create_dir_all(&image_dir).await?;
-
-        // Pull platform-resolved image manifest (auto-resolves multi-arch ImageIndex)
-        let auth = oci_client::secrets::RegistryAuth::Anonymous;
+        info!("Pulling image: {} (policy {})", reference, policy);
+        let attempt = self.stats.attempt();
+        // Drop what an interrupted pull left behind; its blobs are still in
+        // the layer cache.
+        if !cached {
+            remove_if_present(tokio::fs::remove_dir_all(&layers_dir).await)?;
+        }
+        tokio::fs::create_dir_all(&layers_dir).await?;
 
-        let (img_manifest, _digest) = self
-            .client
-            .pull_image_manifest(&reference, &auth)
+        // Authenticated pulls get their own client: the shared one caches
+        // tokens per repository, which would leak one pod's access to another.
+        let registry = reference.resolve_registry().to_string();
+        let node_keychain = RegistryKeychain::load_file(&registry_auth::node_credentials_path())
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to pull manifest for {}: {}", image_ref, e))?;
+            .unwrap_or_else(|e| {
+                warn!("Ignoring node registry credentials: {:#}", e);
+                RegistryKeychain::default()
+            });
+        let credentials = keychain
+            .get(&registry)
+            .or_else(|| node_keychain.get(&registry));
+        let authed_client;
+        let (client, auth) = match credentials {
+            Some(creds) => {
+                info!("Using registry credentials for {}", registry);
+                authed_client = Client::new(client_config(&self.insecure_registries));
+                (
+                    &authed_client,
+                    RegistryAuth::Basic(creds.username.clone(), creds.password().to_string()),
+                )
+            }
+            None => (&self.client, RegistryAuth::Anonymous),
+        };
 
-        // Save manifest
-        let manifest_json = serde_json::to_string_pretty(&img_manifest)?;
-        tokio::fs::write(image_dir.join("manifest.json"), &manifest_json).await?;
+        let img_manifest = fetch_manifest(client, &auth, &reference, image_ref).await?;
 
-        // Pull each layer blob
-        let layers_dir = image_dir.join("layers");
-        tokio::fs::create_dir_all(&layers_dir).await?;
+        // Layers already on disk, by digest — only an `Always` re-pull has any.
+        let cached_manifest: Option<OciImageManifest> = if cached {
+            tokio::fs::read(image_dir.join("manifest.json"))
+                .await
+                .ok()
+                .and_then(|data| serde_json::from_slice(&data).ok())
+        } else {
+            None
+        };
+        let cached_layers: Vec<String> = cached_manifest
+            .as_ref()
+            .map(|m| m.layers.iter().map(|l| l.digest.clone()).collect())
+            .unwrap_or_default();
+        let fetched_layers: Vec<String> = img_manifest
+            .layers
+            .iter()
+            .map(|l| l.digest.clone())
+            .collect();
+        let changed = pull::changed_layers(&cached_layers, &fetched_layers);
+        let config_changed = cached_manifest
+            .as_ref()
+            .is_none_or(|m| m.config.digest != img_manifest.config.digest);
+
+        if changed.is_empty() && !config_changed && cached_layers.len() == fetched_layers.len() {
+            info!("Image {} is up to date", image_ref);
+            slot.fetched.fetch_add(1, Ordering::Release);
+            if let Some(rootfs) = rootfs {
+                RootfsManager::extract_layers(&image_dir, rootfs).await?;
+            }
+            attempt.succeeded();
+            mark_used(&image_dir).await;
+            return Ok(image_dir);
+        }
 
-        for (i, layer) in img_manifest.layers.iter().enumerate() {
+        // Layers are about to be replaced: until the marker is written again
+        // the image must not be used, even if this pull is interrupted.
+        remove_if_present(tokio::fs::remove_file(image_dir.join(PULL_COMPLETE_MARKER)).await)?;
+
+        // Download changed layers into the blob cache, a few at a time, and
+        // link (and extract) them in layer order as they arrive. Unchanged
+        // layers are already in `layers_dir`.
+        let total = img_manifest.layers.len();
+        let fetch = |i: usize, layer: OciDescriptor| {
+            let (client, reference, cache) =
+                (client.clone(), reference.clone(), self.layer_cache.clone());
+            let changed = changed.contains(&i);
             let layer_path = layers_dir.join(format!("layer_{}.tar.gz", i));
-            if layer_path.exists() {
-                info!("  Layer {} already cached", i);
-                continue;
+            async move {
+                if !changed {
+                    return Ok(());
+                }
+                let hit = cache
+                    .get_or_fetch(&layer.digest, &layer_path, || async {
+                        info!(
+                            "  Pulling layer {}/{}: {} ({})",
+                            i + 1,
+                            total,
+                            layer.digest,
+                            format_size(layer.size as u64),
+                        );
+                        let mut data = Vec::new();
+                        client
+                            .pull_blob(&reference, &layer, &mut data)
+                            .await
+                            .map_err(|e| {
+                                anyhow::anyhow!("Failed to pull layer {}: {}", layer.digest, e)
+                            })?;
+                        Ok(data)
+                    })
+                    .await?;
+                if hit {
+                    info!(
+                        "  Layer {}/{}: {} already in blob cache",
+                        i + 1,
+                        total,
+                        layer.digest
+                    );
+                } else {
+                    info!(
+                        "  Downloaded layer {}/{}: {} bytes",
+                        i + 1,
+                        total,
+                        layer.size
+                    );
+                }
+                Ok(())
             }
+        };
+        let apply = |i: usize, ()| {
+            let layer_path = layers_dir.join(format!("layer_{}.tar.gz", i));
+            async move {
+                if let Some(rootfs) = rootfs {
+                    info!("  Extracting layer {}/{}", i + 1, total);
+                    RootfsManager::extract_layer(&layer_path, rootfs).await?;
+                }
+                Ok(())
+            }
+        };
+        layers::ordered_pipeline(
+            img_manifest.layers.clone(),
+            layers::MAX_CONCURRENT_DOWNLOADS,
+            fetch,
+            apply,
+        )
+        .await?;
+
+        // Drop layers the new manifest no longer has
+        for i in fetched_layers.len()..cached_layers.len() {
+            let _ = tokio::fs::remove_file(layers_dir.join(format!("layer_{}.tar.gz", i))).await;
+        }
 
-            info!(
-                "  Pulling layer {}/{}: {} ({})",
-                i + 1,
-                img_manifest.layers.len(),
-                layer.digest,
-                format_size(layer.size as u64),
-            );
-
-            let mut layer_data = Vec::new();
-            self.client
-                .pull_blob(&reference, layer, &mut layer_data)
+        // Also pull the config blob
+        if config_changed {
+            let mut config_data = Vec::new();
+            client
+                .pull_blob(&reference, &img_manifest.config, &mut config_data)
                 .await
-                .map_err(|e| anyhow::anyhow!("Failed to pull layer {}: {}", layer.digest, e))?;
-
-            tokio::fs::write(&layer_path, &layer_data).await?;
+                .map_err(|e| anyhow::anyhow!("Failed to pull config: {}", e))?;
+            tokio::fs::write(image_dir.join("config.json"), &config_data).await?;
         }
 
-        // Also pull the config blob
-        let mut config_data = Vec::new();
-        self.client
-            .pull_blob(&reference, &img_manifest.config, &mut config_data)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to pull config: {}", e))?;
-        tokio::fs::write(image_dir.join("config.json"), &config_data).await?;
+        // Save the manifest and then the marker last, so an interrupted pull
+        // is never mistaken for a complete one.
+        let manifest_json = serde_json::to_string_pretty(&img_manifest)?;
+        tokio::fs::write(image_dir.join("manifest.json"), &manifest_json).await?;
+        mark_complete(&image_dir).await?;
+        slot.fetched.fetch_add(1, Ordering::Release);
 
         info!("Image {} pulled to {}", image_ref, image_dir.display());
+        attempt.succeeded();
+        mark_used(&image_dir).await;
         Ok(image_dir)
     }
 
-    /// Get the cached image directory, if it exists.
+    /// Get the cached image directory, if the image was fully pulled.
     pub fn get_cached(&self, image_ref: &str) -> Option<PathBuf> {
-        let image_hash = format!("{:x}", md5_hash(image_ref));
-        let image_dir = self.images_dir.join(&image_hash);
-        if image_dir.join("manifest.json").exists() {
+        let image_dir = self.images_dir.join(image_id(image_ref));
+        if image_dir.join(PULL_COMPLETE_MARKER).exists() {
             Some(image_dir)
         } else {
             None
@@ -188,6 +441,16 @@ impl ImageManager {
                 }
             }
 
+            // Last pull or container start from this image
+            let last_used = match tokio::fs::metadata(path.join(LAST_USED_MARKER)).await {
+                Ok(meta) => meta.modified().ok(),
+                Err(_) => tokio::fs::metadata(&manifest_path)
+                    .await
+                    .ok()
+                    .and_then(|m| m.modified().ok()),
+            }
+            .map(chrono::DateTime::<chrono::Utc>::from);
+
             let hash = path
                 .file_name()
                 .unwrap_or_default()
@@ -203,6 +466,7 @@ impl ImageManager {
                 architecture,
                 os,
                 created,
+                last_used,
             });
         }
 
@@ -210,12 +474,36 @@ impl ImageManager {
         Ok(images)
     }
 
+    /// Layer digests in the manifests of the cached images. Their blobs are
+    /// kept even where an image holds a copy rather than a link (its
+    /// layers on another filesystem than the blob cache).
+    async fn referenced_layers(&self) -> HashSet<String> {
+        let mut digests = HashSet::new();
+        let Ok(mut entries) = tokio::fs::read_dir(&self.images_dir).await else {
+            return digests;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(data) = tokio::fs::read(entry.path().join("manifest.json")).await else {
+                continue;
+            };
+            if let Ok(manifest) = serde_json::from_slice::<OciImageManifest>(&data) {
+                digests.extend(manifest.layers.into_iter().map(|l| l.digest));
+            }
+        }
+        digests
+    }
+
     /// Delete a cached image by its hash ID.
     pub async fn delete_image(&self, image_id: &str) -> Result<()> {
+        let slot = self.slot(image_id);
+        let _guard = slot.lock.write().await;
         let image_dir = self.images_dir.join(image_id);
         if image_dir.exists() {
             tokio::fs::remove_dir_all(&image_dir).await?;
             info!("Deleted cached image: {}", image_id);
+            self.layer_cache
+                .prune(&self.referenced_layers().await)
+                .await;
         } else {
             anyhow::bail!("Image {} not found", image_id);
         }
@@ -234,6 +522,49 @@ pub struct ImageInfo {
     pub architecture: String,
     pub os: String,
     pub created: String,
+    /// When a container last started from this image.
+    #[serde(default)]
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Cache directory name (and image ID) of an image reference.
+pub fn image_id(image_ref: &str) -> String {
+    format!("{:x}", md5_hash(image_ref))
+}
+
+/// File in an image directory whose mtime records the image's last use.
+const LAST_USED_MARKER: &str = "last_used";
+
+/// File in an image directory written once every layer, the config and the
+/// manifest are in place. An image directory without it is a pull that was
+/// interrupted (or predates the marker) and is fetched again.
+const PULL_COMPLETE_MARKER: &str = "pulled";
+
+async fn is_complete(image_dir: &Path) -> bool {
+    tokio::fs::try_exists(image_dir.join(PULL_COMPLETE_MARKER))
+        .await
+        .unwrap_or(false)
+}
+
+async fn mark_complete(image_dir: &Path) -> Result<()> {
+    tokio::fs::write(image_dir.join(PULL_COMPLETE_MARKER), b"").await?;
+    Ok(())
+}
+
+/// The result of removing a file or directory, where one that is already
+/// gone is not an error.
+fn remove_if_present(result: std::io::Result<()>) -> Result<()> {
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Record that a container is being created from the image in `image_dir`.
+async fn mark_used(image_dir: &Path) {
+    if let Err(e) = tokio::fs::write(image_dir.join(LAST_USED_MARKER), b"").await {
+        warn!("Failed to mark image {} used: {}", image_dir.display(), e);
+    }
 }
 
 /// Simple hash for image reference → directory name.
@@ -256,6 +587,69 @@ fn format_size(bytes: u64) -> String {
     }
 }
 
+/// Registry client settings: plain HTTP for `insecure_registries`, and
+/// linux/<host_arch> manifests from multi-arch images.
+fn client_config(insecure_registries: &[String]) -> ClientConfig {
+    ClientConfig {
+        protocol: ClientProtocol::HttpsExcept(insecure_registries.to_vec()),
+        // Always resolve to linux/<host_arch> — container images are Linux-based,
+        // even when running on macOS (VirtualizationBackend boots a Linux microVM).
+        platform_resolver: Some(Box::new(linux_platform_resolver)),
+        ..Default::default()
+    }
+}
+
+/// Fetch the linux image manifest for `reference`, checking the digest
+/// of the top-level manifest (or image index) against a pinned reference.
+///
+/// An anonymous pull the registry refuses fails with
+/// [`AuthenticationRequired`].
+async fn fetch_manifest(
+    client: &Client,
+    auth: &RegistryAuth,
+    reference: &Reference,
+    image_ref: &str,
+) -> Result<OciImageManifest> {
+    let manifest_err = |e: OciDistributionError| match e {
+        OciDistributionError::UnauthorizedError { .. }
+        | OciDistributionError::AuthenticationFailure(_)
+            if matches!(auth, RegistryAuth::Anonymous) =>
+        {
+            anyhow::Error::new(AuthenticationRequired {
+                registry: reference.resolve_registry().to_string(),
+            })
+        }
+        e => anyhow::anyhow!("Failed to pull manifest for {}: {}", image_ref, e),
+    };
+
+    let (manifest, digest) = client
+        .pull_manifest(reference, auth)
+        .await
+        .map_err(manifest_err)?;
+    pull::verify_digest(image_ref, &digest)?;
+
+    match manifest {
+        OciManifest::Image(img_manifest) => Ok(img_manifest),
+        OciManifest::ImageIndex(index) => {
+            // Resolve the multi-arch index to this node's platform
+            let platform_digest = linux_platform_resolver(&index.manifests).ok_or_else(|| {
+                anyhow::anyhow!("Image {} has no linux platform manifest", image_ref)
+            })?;
+            let platform_ref = reference.clone_with_digest(platform_digest);
+            match client
+                .pull_manifest(&platform_ref, auth)
+                .await
+                .map_err(manifest_err)?
+            {
+                (OciManifest::Image(img_manifest), _) => Ok(img_manifest),
+                (OciManifest::ImageIndex(_), _) => {
+                    anyhow::bail!("Image {} resolved to a nested image index", image_ref)
+                }
+            }
+        }
+    }
+}
+
 /// Platform resolver that picks the first `linux/<host_arch>` entry from an
 /// OCI Image Index. This ensures images resolve correctly on macOS (where the
 /// host OS is `darwin`) since all container images target Linux.
@@ -287,3 +681,327 @@ fn linux_platform_resolver(manifests: &[ImageIndexEntry]) -> Option<String> {
 
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry_auth::RegistryCredentials;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const TOKEN: &str = "stub-token";
+    const MANIFEST: &str = r#"{
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "mediaType": "application/vnd.oci.image.config.v1+json",
+            "digest": "sha256:c0ffee",
+            "size": 2
+        },
+        "layers": []
+    }"#;
+
+    /// A registry on localhost that only serves manifests to bearer tokens,
+    /// handed out by its token endpoint for `basic`. Records the
+    /// `Authorization` header of every request as `<path> <header>`.
+    async fn stub_registry(basic: String) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (realm, log) = (format!("http://{}/token", host), seen.clone());
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match conn.read(&mut chunk).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&buf).to_string();
+                let path = request.split(' ').nth(1).unwrap_or("").to_string();
+                let auth = request
+                    .lines()
+                    .find_map(|l| {
+                        l.split_once(':')
+                            .filter(|(k, _)| k.eq_ignore_ascii_case("authorization"))
+                            .map(|(_, v)| v.trim().to_string())
+                    })
+                    .unwrap_or_default();
+                log.lock().unwrap().push(format!("{} {}", path, auth));
+
+                let (status, headers, body) = if path == "/v2/" {
+                    (
+                        "401 Unauthorized",
+                        format!(
+                            "WWW-Authenticate: Bearer realm=\"{}\",service=\"stub\"\r\n",
+                            realm
+                        ),
+                        String::new(),
+                    )
+                } else if path.starts_with("/token") && auth == basic {
+                    (
+                        "200 OK",
+                        "Content-Type: application/json\r\n".to_string(),
+                        format!(r#"{{"token":"{}"}}"#, TOKEN),
+                    )
+                } else if path.starts_with("/v2/app/manifests/")
+                    && auth == format!("Bearer {}", TOKEN)
+                {
+                    (
+                        "200 OK",
+                        "Content-Type: application/vnd.oci.image.manifest.v1+json\r\n".to_string(),
+                        MANIFEST.to_string(),
+                    )
+                } else {
+                    ("401 Unauthorized", String::new(), String::new())
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                );
+                let _ = conn.write_all(response.as_bytes()).await;
+            }
+        });
+        (host, seen)
+    }
+
+    /// An anonymous registry on localhost serving `app:v1`, one layer and its
+    /// config. Counts the requests for each path; manifests are slow to
+    /// answer, so concurrent pulls overlap.
+    async fn counting_registry() -> (String, Arc<Mutex<HashMap<String, usize>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+        let counts = Arc::new(Mutex::new(HashMap::new()));
+        let layer = b"layer bytes".to_vec();
+        let config = b"{}".to_vec();
+        let manifest = serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": layers::sha256_digest(&config),
+                "size": config.len()
+            },
+            "layers": [{
+                "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                "digest": layers::sha256_digest(&layer),
+                "size": layer.len()
+            }]
+        })
+        .to_string();
+        let log = counts.clone();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let (log, layer, config, manifest) =
+                    (log.clone(), layer.clone(), config.clone(), manifest.clone());
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match conn.read(&mut chunk).await {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&buf).to_string();
+                    let path = request.split(' ').nth(1).unwrap_or("").to_string();
+                    *log.lock().unwrap().entry(path.clone()).or_insert(0) += 1;
+
+                    let (status, content_type, body) = if path == "/v2/" {
+                        ("200 OK", "application/json", b"{}".to_vec())
+                    } else if path.starts_with("/v2/app/manifests/") {
+                        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                        (
+                            "200 OK",
+                            "application/vnd.oci.image.manifest.v1+json",
+                            manifest.into_bytes(),
+                        )
+                    } else if path == format!("/v2/app/blobs/{}", layers::sha256_digest(&layer)) {
+                        ("200 OK", "application/octet-stream", layer)
+                    } else if path == format!("/v2/app/blobs/{}", layers::sha256_digest(&config)) {
+                        ("200 OK", "application/octet-stream", config)
+                    } else {
+                        ("404 Not Found", "text/plain", Vec::new())
+                    };
+                    let head = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        status,
+                        content_type,
+                        body.len()
+                    );
+                    let _ = conn.write_all(head.as_bytes()).await;
+                    let _ = conn.write_all(&body).await;
+                });
+            }
+        });
+        (host, counts)
+    }
+
+    fn manager(host: &str, label: &str) -> ImageManager {
+        let insecure_registries = vec![host.to_string()];
+        let data_dir = crate::test_util::temp_dir(label);
+        ImageManager {
+            images_dir: data_dir.join("images"),
+            client: Client::new(client_config(&insecure_registries)),
+            insecure_registries,
+            layer_cache: Arc::new(LayerCache::new(&data_dir)),
+            stats: PullStats::default(),
+            slots: DashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_token_exchange_with_pull_secret() {
+        let creds = RegistryCredentials::new("robot", "s3cret");
+        let (host, seen) = stub_registry(creds.basic_header()).await;
+        let mgr = manager(&host, "token");
+        let image_ref = format!("{}/app:v1", host);
+        let reference: Reference = image_ref.parse().unwrap();
+
+        let auth = RegistryAuth::Basic(creds.username.clone(), creds.password().to_string());
+        let authed = Client::new(client_config(&mgr.insecure_registries));
+        let manifest = fetch_manifest(&authed, &auth, &reference, &image_ref)
+            .await
+            .unwrap();
+        assert_eq!(manifest.config.digest, "sha256:c0ffee");
+
+        // Basic credentials go to the token endpoint only; the registry
+        // itself sees the bearer token.
+        let seen = seen.lock().unwrap().clone();
+        assert!(
+            seen.iter()
+                .any(|r| r.starts_with("/token") && r.ends_with("Basic cm9ib3Q6czNjcmV0"))
+        );
+        assert!(
+            seen.iter()
+                .any(|r| r == "/v2/app/manifests/v1 Bearer stub-token")
+        );
+        assert!(
+            !seen
+                .iter()
+                .any(|r| r.starts_with("/v2/app") && r.contains("Basic"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_pull_requires_authentication() {
+        let (host, _) = stub_registry("Basic cm9ib3Q6czNjcmV0".to_string()).await;
+        let mgr = manager(&host, "anonymous");
+        let image_ref = format!("{}/app:v1", host);
+        let reference: Reference = image_ref.parse().unwrap();
+
+        let err = fetch_manifest(
+            &mgr.client,
+            &RegistryAuth::Anonymous,
+            &reference,
+            &image_ref,
+        )
+        .await
+        .unwrap_err();
+        let required = err.downcast_ref::<AuthenticationRequired>().unwrap();
+        assert_eq!(required.registry, host);
+    }
+
+    #[tokio::test]
+    async fn test_failed_pull_is_counted() {
+        let (host, _) = stub_registry("Basic cm9ib3Q6czNjcmV0".to_string()).await;
+        let mgr = manager(&host, "counted");
+        let image_ref = format!("{}/app:counted", host);
+
+        assert!(
+            mgr.pull(
+                &image_ref,
+                ImagePullPolicy::Always,
+                &RegistryKeychain::default()
+            )
+            .await
+            .is_err()
+        );
+        assert_eq!(mgr.stats().pulled(), 1);
+        assert_eq!(mgr.stats().failed(), 1);
+
+        mgr.stats.attempt().succeeded();
+        assert_eq!(mgr.stats().pulled(), 2);
+        assert_eq!(mgr.stats().failed(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_pulls_fetch_once() {
+        let (host, counts) = counting_registry().await;
+        let mgr = Arc::new(manager(&host, "concurrent"));
+        let image_ref = format!("{}/app:v1", host);
+
+        let pulls: Vec<_> = (0..5)
+            .map(|_| {
+                let (mgr, image_ref) = (mgr.clone(), image_ref.clone());
+                tokio::spawn(async move {
+                    mgr.pull(
+                        &image_ref,
+                        ImagePullPolicy::Always,
+                        &RegistryKeychain::default(),
+                    )
+                    .await
+                    .unwrap()
+                })
+            })
+            .collect();
+        let image_dir = mgr.images_dir.join(image_id(&image_ref));
+        for pull in pulls {
+            assert_eq!(pull.await.unwrap(), image_dir);
+        }
+
+        let counts = counts.lock().unwrap().clone();
+        assert_eq!(counts.get("/v2/app/manifests/v1"), Some(&1));
+        assert_eq!(
+            counts.keys().filter(|p| p.contains("/blobs/")).count(),
+            2,
+            "{:?}",
+            counts
+        );
+        assert!(
+            counts
+                .iter()
+                .all(|(p, n)| !p.contains("/blobs/") || *n == 1)
+        );
+        assert_eq!(mgr.stats().pulled(), 1);
+        assert_eq!(mgr.get_cached(&image_ref), Some(image_dir));
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_pull_is_redone() {
+        let (host, counts) = counting_registry().await;
+        let mgr = manager(&host, "interrupted");
+        let image_ref = format!("{}/app:v1", host);
+        let keychain = RegistryKeychain::default();
+
+        let image_dir = mgr
+            .pull(&image_ref, ImagePullPolicy::IfNotPresent, &keychain)
+            .await
+            .unwrap();
+        // A crash mid-pull: the marker is gone and a stray layer is left.
+        std::fs::remove_file(image_dir.join(PULL_COMPLETE_MARKER)).unwrap();
+        std::fs::write(image_dir.join("layers/layer_1.tar.gz"), b"partial").unwrap();
+        assert_eq!(mgr.get_cached(&image_ref), None);
+
+        mgr.pull(&image_ref, ImagePullPolicy::IfNotPresent, &keychain)
+            .await
+            .unwrap();
+        assert_eq!(counts.lock().unwrap().get("/v2/app/manifests/v1"), Some(&2));
+        assert!(image_dir.join(PULL_COMPLETE_MARKER).exists());
+        assert!(image_dir.join("layers/layer_0.tar.gz").exists());
+        assert!(!image_dir.join("layers/layer_1.tar.gz").exists());
+
+        // Complete now: no registry round trip.
+        mgr.pull(&image_ref, ImagePullPolicy::IfNotPresent, &keychain)
+            .await
+            .unwrap();
+        assert_eq!(counts.lock().unwrap().get("/v2/app/manifests/v1"), Some(&2));
+        assert_eq!(mgr.stats().pulled(), 2);
+    }
+}
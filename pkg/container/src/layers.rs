@@ -0,0 +1,438 @@
+//! Content-addressed layer blobs shared by all images on the node, and the
+//! download pipeline that fills them.
+//!
+//! Blobs live at `<data_dir>/blobs/sha256/<hex>`; an image's
+//! `layers/layer_<i>.tar.gz` is a hard link to its blob, so a base layer used
+//! by many images is downloaded and stored once. Blobs no image links to any
+//! more are removed by [`LayerCache::prune`].
+
+use anyhow::Result;
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::future::Future;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{Mutex, Semaphore};
+use tracing::{info, warn};
+
+/// Layer downloads in flight per image pull.
+pub const MAX_CONCURRENT_DOWNLOADS: usize = 4;
+
+/// `sha256:<hex>` digest of `data`.
+pub fn sha256_digest(data: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(data))
+}
+
+/// Blob store keyed by digest.
+pub struct LayerCache {
+    dir: PathBuf,
+    /// One lock per digest being fetched, so concurrent pulls of images
+    /// sharing a layer download it once.
+    locks: DashMap<String, Arc<Mutex<()>>>,
+}
+
+impl LayerCache {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            dir: data_dir.join("blobs").join("sha256"),
+            locks: DashMap::new(),
+        }
+    }
+
+    /// Path of the blob for `digest`. Only `sha256:<64 hex>` digests are
+    /// accepted, which also keeps registry-supplied names out of the path.
+    pub fn blob_path(&self, digest: &str) -> Result<PathBuf> {
+        match digest.strip_prefix("sha256:") {
+            Some(hex) if hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                Ok(self.dir.join(hex.to_ascii_lowercase()))
+            }
+            _ => anyhow::bail!("unsupported layer digest {}", digest),
+        }
+    }
+
+    /// Make `dest` refer to the blob for `digest` (see [`link_blob`]),
+    /// running `download` only if it is not cached. The downloaded bytes
+    /// must hash to `digest`. Returns whether it was a cache hit.
+    ///
+    /// The digest's lock is held until `dest` is linked, so [`prune`]
+    /// cannot remove the blob in between.
+    ///
+    /// [`prune`]: Self::prune
+    pub async fn get_or_fetch<F, Fut>(&self, digest: &str, dest: &Path, download: F) -> Result<bool>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<u8>>>,
+    {
+        let path = self.blob_path(digest)?;
+        let lock = self
+            .locks
+            .entry(digest.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone();
+        let result = async {
+            let _guard = lock.lock().await;
+            let hit = self.fetch_locked(digest, &path, download).await?;
+            link_blob(&path, dest).await?;
+            Ok(hit)
+        }
+        .await;
+        // Forget the lock once no other pull is waiting on it: the map
+        // holds one reference and this pull the other.
+        self.locks
+            .remove_if(digest, |_, l| Arc::strong_count(l) == 2);
+        result
+    }
+
+    /// Download the blob for `digest` to `path` unless it is there already.
+    /// Called with the digest's lock held.
+    async fn fetch_locked<F, Fut>(&self, digest: &str, path: &Path, download: F) -> Result<bool>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<u8>>>,
+    {
+        if tokio::fs::try_exists(path).await.unwrap_or(false) {
+            return Ok(true);
+        }
+
+        let data = download().await?;
+        let actual = sha256_digest(&data);
+        if actual != digest {
+            anyhow::bail!("layer digest mismatch: expected {}, got {}", digest, actual);
+        }
+
+        // Write beside the blob and rename, so a crash never leaves a
+        // truncated blob under its digest.
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, &data).await?;
+        tokio::fs::rename(&partial, path).await?;
+        Ok(false)
+    }
+
+    /// Delete blobs no image directory links to, other than those of the
+    /// `referenced` digests (layers an image holds a copy of) and those a
+    /// pull is fetching or linking right now. Returns the bytes freed.
+    pub async fn prune(&self, referenced: &HashSet<String>) -> u64 {
+        let mut freed = 0;
+        let Ok(mut entries) = tokio::fs::read_dir(&self.dir).await else {
+            return 0;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let digest = format!("sha256:{}", entry.file_name().to_string_lossy());
+            if referenced.contains(&digest) {
+                continue;
+            }
+            let Some(lock) = self.locks.get(&digest).map(|l| l.clone()) else {
+                freed += remove_if_unlinked(&entry.path()).await;
+                continue;
+            };
+            // Held through the removal, so a pull waiting on it downloads
+            // the blob again rather than linking a removed one.
+            if let Ok(_guard) = lock.try_lock() {
+                freed += remove_if_unlinked(&entry.path()).await;
+            }
+        }
+        freed
+    }
+}
+
+async fn remove_if_unlinked(path: &Path) -> u64 {
+    match tokio::fs::metadata(path).await {
+        Ok(meta) if meta.is_file() && meta.nlink() == 1 => {
+            if let Err(e) = tokio::fs::remove_file(path).await {
+                warn!("Failed to remove layer blob {}: {}", path.display(), e);
+                return 0;
+            }
+            info!("Removed unused layer blob {}", path.display());
+            meta.len()
+        }
+        _ => 0,
+    }
+}
+
+/// Make `dest` refer to the blob at `blob`: a hard link, or a copy when the
+/// two are on different filesystems.
+pub async fn link_blob(blob: &Path, dest: &Path) -> Result<()> {
+    if let Ok(meta) = tokio::fs::metadata(dest).await {
+        let blob_meta = tokio::fs::metadata(blob).await?;
+        if meta.ino() == blob_meta.ino() && meta.dev() == blob_meta.dev() {
+            return Ok(());
+        }
+        tokio::fs::remove_file(dest).await?;
+    }
+    if tokio::fs::hard_link(blob, dest).await.is_err() {
+        tokio::fs::copy(blob, dest).await?;
+    }
+    Ok(())
+}
+
+/// Run `fetch` for every item with at most `limit` in flight, and hand the
+/// results to `apply` strictly in item order: item `i` is applied as soon as
+/// it and every item before it are fetched, while later fetches continue in
+/// the background. The first error stops the pipeline and cancels the
+/// fetches still running.
+pub async fn ordered_pipeline<T, R, Fetch, FetchFut, Apply, ApplyFut>(
+    items: Vec<T>,
+    limit: usize,
+    fetch: Fetch,
+    mut apply: Apply,
+) -> Result<()>
+where
+    Fetch: Fn(usize, T) -> FetchFut,
+    FetchFut: Future<Output = Result<R>> + Send + 'static,
+    R: Send + 'static,
+    Apply: FnMut(usize, R) -> ApplyFut,
+    ApplyFut: Future<Output = Result<()>>,
+{
+    let permits = Arc::new(Semaphore::new(limit.max(1)));
+    let handles: Vec<_> = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let permits = permits.clone();
+            let fetched = fetch(i, item);
+            tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await?;
+                fetched.await
+            })
+        })
+        .collect();
+
+    let mut result = Ok(());
+    let mut handles = handles.into_iter().enumerate();
+    for (i, handle) in handles.by_ref() {
+        result = match handle.await {
+            Ok(Ok(value)) => apply(i, value).await,
+            Ok(Err(e)) => Err(e),
+            Err(e) => Err(e.into()),
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+    for (_, handle) in handles {
+        handle.abort();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_dir;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cache_hit_skips_download() {
+        let dir = temp_dir("hit");
+        let cache = LayerCache::new(&dir);
+        let blob = b"alpine base layer".to_vec();
+        let digest = sha256_digest(&blob);
+        let downloads = AtomicUsize::new(0);
+        let download = || async {
+            downloads.fetch_add(1, Ordering::SeqCst);
+            Ok(blob.clone())
+        };
+
+        let (first, second) = (dir.join("layer_0.tar.gz"), dir.join("layer_1.tar.gz"));
+        assert!(!cache.get_or_fetch(&digest, &first, download).await.unwrap());
+        assert_eq!(std::fs::read(&first).unwrap(), blob);
+        assert!(
+            cache
+                .get_or_fetch(&digest, &second, download)
+                .await
+                .unwrap()
+        );
+        assert_eq!(std::fs::read(&second).unwrap(), blob);
+        assert_eq!(downloads.load(Ordering::SeqCst), 1);
+        // Both are links to the one blob.
+        let path = cache.blob_path(&digest).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().nlink(), 3);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_download_is_rejected() {
+        let dir = temp_dir("corrupt");
+        let cache = LayerCache::new(&dir);
+        let digest = sha256_digest(b"expected");
+        let err = cache
+            .get_or_fetch(&digest, &dir.join("layer_0.tar.gz"), || async {
+                Ok(b"tampered".to_vec())
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("layer digest mismatch"));
+        assert!(!cache.blob_path(&digest).unwrap().exists());
+        assert!(cache.blob_path("sha256:../../etc").is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_pulls_download_once() {
+        let dir = temp_dir("concurrent");
+        let cache = Arc::new(LayerCache::new(&dir));
+        let blob = b"shared layer".to_vec();
+        let digest = sha256_digest(&blob);
+        let downloads = Arc::new(AtomicUsize::new(0));
+
+        let pulls: Vec<_> = (0..8)
+            .map(|i| {
+                let (cache, digest, blob, downloads) = (
+                    cache.clone(),
+                    digest.clone(),
+                    blob.clone(),
+                    downloads.clone(),
+                );
+                let dest = dir.join(format!("image-{}.tar.gz", i));
+                tokio::spawn(async move {
+                    cache
+                        .get_or_fetch(&digest, &dest, || async move {
+                            downloads.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(20)).await;
+                            Ok(blob)
+                        })
+                        .await
+                        .unwrap();
+                    dest
+                })
+            })
+            .collect();
+        for pull in pulls {
+            assert_eq!(std::fs::read(pull.await.unwrap()).unwrap(), blob);
+        }
+        assert_eq!(downloads.load(Ordering::SeqCst), 1);
+        // Locks go once no pull needs them.
+        assert!(cache.locks.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_applies_in_order() {
+        // Later layers finish downloading first.
+        let delays = vec![60u64, 40, 20, 0, 10];
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let mut applied = Vec::new();
+
+        ordered_pipeline(
+            delays,
+            2,
+            |i, delay| {
+                let (in_flight, peak) = (in_flight.clone(), peak.clone());
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    Ok(i)
+                }
+            },
+            |i, fetched| {
+                assert_eq!(i, fetched);
+                applied.push(fetched);
+                async { Ok(()) }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(applied, vec![0, 1, 2, 3, 4]);
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_stops_at_first_error() {
+        let mut applied = Vec::new();
+        let err = ordered_pipeline(
+            vec![0, 1, 2],
+            4,
+            |i, _| async move {
+                if i == 1 {
+                    anyhow::bail!("layer {} failed", i)
+                }
+                Ok(i)
+            },
+            |i, _| {
+                applied.push(i);
+                async { Ok(()) }
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.to_string(), "layer 1 failed");
+        assert_eq!(applied, vec![0]);
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_linked_blobs() {
+        let dir = temp_dir("prune");
+        let cache = LayerCache::new(&dir);
+        let image_layer = dir.join("layer_0.tar.gz");
+        cache
+            .get_or_fetch(&sha256_digest(b"kept"), &image_layer, || async {
+                Ok(b"kept".to_vec())
+            })
+            .await
+            .unwrap();
+        let kept = cache.blob_path(&sha256_digest(b"kept")).unwrap();
+        // Linking again is a no-op.
+        link_blob(&kept, &image_layer).await.unwrap();
+        let orphan_layer = dir.join("orphan.tar.gz");
+        cache
+            .get_or_fetch(&sha256_digest(b"orphan"), &orphan_layer, || async {
+                Ok(b"orphan".to_vec())
+            })
+            .await
+            .unwrap();
+        std::fs::remove_file(&orphan_layer).unwrap();
+        let orphan = cache.blob_path(&sha256_digest(b"orphan")).unwrap();
+
+        assert_eq!(cache.prune(&HashSet::new()).await, 6);
+        assert!(kept.exists());
+        assert!(!orphan.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_prune_skips_pulls_in_flight_and_copies() {
+        let dir = temp_dir("prune-in-flight");
+        let cache = LayerCache::new(&dir);
+        let digest = sha256_digest(b"layer");
+        let layer = dir.join("layer_0.tar.gz");
+        cache
+            .get_or_fetch(&digest, &layer, || async { Ok(b"layer".to_vec()) })
+            .await
+            .unwrap();
+        // As if the image held a copy instead of a link.
+        std::fs::remove_file(&layer).unwrap();
+        let blob = cache.blob_path(&digest).unwrap();
+
+        // A pull holding the digest's lock is about to link it...
+        let lock = cache
+            .locks
+            .entry(digest.clone())
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone();
+        let guard = lock.lock().await;
+        assert_eq!(cache.prune(&HashSet::new()).await, 0);
+        assert!(blob.exists());
+        drop(guard);
+
+        // ...and an image's manifest still lists it.
+        let referenced = HashSet::from([digest.clone()]);
+        assert_eq!(cache.prune(&referenced).await, 0);
+        assert!(blob.exists());
+        assert_eq!(cache.prune(&HashSet::new()).await, 5);
+        assert!(!blob.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,11 +1,21 @@
+pub mod archive;
 pub mod backend;
 pub mod image;
 pub mod installer;
 pub mod kernel;
+pub mod layers;
+pub mod logs;
+pub mod pull;
+pub mod reaper;
+pub mod registry_auth;
 pub mod rootfs;
 pub mod runtime;
 pub mod state;
+pub mod stop;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
 pub mod vm_utils;
+pub mod volume;
 
 #[cfg(target_os = "macos")]
 pub mod macos;
@@ -2,8 +2,8 @@
 //!
 //! Firecracker exposes an HTTP/1.1 API over a Unix domain socket.
 //! This module provides a lightweight client without requiring hyper or
-//! reqwest-unix — Firecracker's API is simple enough (PUT-only, JSON body)
-//! that raw HTTP is straightforward.
+//! reqwest-unix — Firecracker's API is simple enough (JSON PUTs to configure
+//! and boot, GETs to read state back) that raw HTTP is straightforward.
 
 use anyhow::{Context, Result};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
@@ -22,11 +22,27 @@ impl FcApiClient {
     }
 
     /// Send a PUT request to the Firecracker API and return the response body.
+    async fn put(&self, path: &str, body: &serde_json::Value) -> Result<String> {
+        self.request("PUT", path, Some(body)).await
+    }
+
+    /// Send a GET request to the Firecracker API and parse the JSON response.
+    async fn get(&self, path: &str) -> Result<serde_json::Value> {
+        let body = self.request("GET", path, None).await?;
+        serde_json::from_str(&body).with_context(|| format!("GET {}: invalid JSON", path))
+    }
+
+    /// Send a request and return the response body; non-2xx is an error.
     ///
     /// Parses the HTTP response incrementally (headers → Content-Length → body)
     /// instead of relying on `shutdown()` + `read_to_end()`, which can race with
     /// Firecracker's micro_http server and cause connection resets.
-    async fn put(&self, path: &str, body: &serde_json::Value) -> Result<String> {
+    async fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<String> {
         let mut stream = UnixStream::connect(&self.socket_path)
             .await
             .with_context(|| {
@@ -36,29 +52,39 @@ impl FcApiClient {
                 )
             })?;
 
-        let body_str = serde_json::to_string(body)?;
-
-        let request = format!(
-            "PUT {path} HTTP/1.1\r\n\
-             Host: localhost\r\n\
-             Accept: application/json\r\n\
-             Content-Type: application/json\r\n\
-             Content-Length: {}\r\n\
-             \r\n\
-             {body_str}",
-            body_str.len(),
-        );
+        let request = match body {
+            Some(body) => {
+                let body_str = serde_json::to_string(body)?;
+                format!(
+                    "{method} {path} HTTP/1.1\r\n\
+                     Host: localhost\r\n\
+                     Accept: application/json\r\n\
+                     Content-Type: application/json\r\n\
+                     Content-Length: {}\r\n\
+                     \r\n\
+                     {body_str}",
+                    body_str.len(),
+                )
+            }
+            None => format!(
+                "{method} {path} HTTP/1.1\r\n\
+                 Host: localhost\r\n\
+                 Accept: application/json\r\n\
+                 \r\n"
+            ),
+        };
 
         stream.write_all(request.as_bytes()).await?;
 
         let (status_code, response_body) = Self::read_http_response(&mut stream)
             .await
-            .with_context(|| format!("PUT {}", path))?;
+            .with_context(|| format!("{} {}", method, path))?;
 
         if !(200..300).contains(&status_code) {
             anyhow::bail!(
-                "Firecracker API error: HTTP {} for PUT {} — {}",
+                "Firecracker API error: HTTP {} for {} {} — {}",
                 status_code,
+                method,
                 path,
                 response_body.trim()
             );
@@ -233,27 +259,91 @@ impl FcApiClient {
         Ok(())
     }
 
-    /// Get the full VM configuration (GET /machine-config).
+    /// Get the machine configuration (GET /machine-config).
     pub async fn get_machine_config(&self) -> Result<serde_json::Value> {
-        let mut stream = UnixStream::connect(&self.socket_path).await?;
-
-        let request = "GET /machine-config HTTP/1.1\r\n\
-                       Host: localhost\r\n\
-                       Accept: application/json\r\n\
-                       \r\n";
+        self.get("/machine-config").await
+    }
 
-        stream.write_all(request.as_bytes()).await?;
+    /// Get the full VM configuration (GET /vm/config): boot source, drives,
+    /// network interfaces and vsock as they were configured before boot.
+    pub async fn get_vm_config(&self) -> Result<serde_json::Value> {
+        self.get("/vm/config").await
+    }
 
-        let (_status, body) = Self::read_http_response(&mut stream).await?;
-        Ok(serde_json::from_str(&body).unwrap_or(serde_json::json!({})))
+    /// Describe the instance (GET /).
+    pub async fn describe_instance(&self) -> Result<InstanceInfo> {
+        Ok(serde_json::from_value(self.get("/").await?)?)
     }
 }
 
+/// Response of `GET /`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct InstanceInfo {
+    #[serde(default)]
+    pub id: String,
+    /// `Not started`, `Running` or `Paused`.
+    pub state: String,
+    #[serde(default)]
+    pub vmm_version: String,
+}
+
 #[cfg(test)]
 mod tests {
+    use super::super::testing::MockFcApi;
+    use crate::test_util::temp_dir;
+
     #[test]
     fn test_api_client_new() {
         let client = super::FcApiClient::new("/tmp/test.sock");
         assert_eq!(client.socket_path, "/tmp/test.sock");
     }
+
+    #[tokio::test]
+    async fn test_put_and_get() {
+        let dir = temp_dir("put-get");
+        let socket = dir.join("api.sock");
+        let mock = MockFcApi::start(&socket);
+        mock.respond(
+            "GET",
+            "/",
+            200,
+            r#"{"id":"vm","state":"Running","vmm_version":"1.10.1"}"#,
+        );
+
+        let client = super::FcApiClient::new(&socket.to_string_lossy());
+        client.set_machine_config(2, 512).await.unwrap();
+        let info = client.describe_instance().await.unwrap();
+        assert_eq!(info.state, "Running");
+        assert_eq!(info.vmm_version, "1.10.1");
+
+        let requests = mock.requests();
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].path, "/machine-config");
+        assert_eq!(
+            requests[0].body,
+            serde_json::json!({ "vcpu_count": 2, "mem_size_mib": 512 })
+        );
+        assert_eq!(requests[1].method, "GET");
+        assert_eq!(requests[1].path, "/");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_error_status_is_reported() {
+        let dir = temp_dir("error");
+        let socket = dir.join("api.sock");
+        let mock = MockFcApi::start(&socket);
+        mock.respond(
+            "PUT",
+            "/actions",
+            400,
+            r#"{"fault_message":"Cannot start microvm that was already started"}"#,
+        );
+
+        let client = super::FcApiClient::new(&socket.to_string_lossy());
+        let err = client.start_instance().await.unwrap_err().to_string();
+        assert!(err.contains("HTTP 400 for PUT /actions"), "{}", err);
+        assert!(err.contains("already started"), "{}", err);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
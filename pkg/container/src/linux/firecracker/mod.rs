@@ -8,8 +8,13 @@
 //! - **virtio-blk**: ext4 rootfs image created via `mkfs.ext4 -d` (no root required)
 //! - **virtio-net**: TAP device per VM with /30 subnet + iptables NAT; guest IP
 //!   configured via kernel `ip=` boot parameter
-//! - **serial console**: stdout/stderr streaming to host log file (`console=ttyS0`)
-//! - **vsock**: host ↔ guest exec channel (port 5555) via Firecracker vsock UDS
+//! - **serial console**: kernel and k3rs-init messages to a host file (`console=ttyS0`)
+//! - **vsock**: host ↔ guest exec channel (port 5555) and the container's
+//!   stdout/stderr (port 5556) via Firecracker vsock UDS
+//!
+//! State is read back from the Firecracker API (`GET /`), and after an agent
+//! restart running VMs are re-adopted by scanning the data directory for API
+//! sockets that still answer (`GET /vm/config` restores CID, TAP and rootfs).
 //!
 //! ## Boot flow
 //!
@@ -18,11 +23,26 @@
 //! 3. Boot: kernel loads with `root=/dev/vda init=/sbin/k3rs-init ip=...`
 //! 4. k3rs-init reads `/config.json` and execs the container entrypoint
 //!
+//! ## Logs
+//!
+//! k3rs-init forwards the entrypoint's output on `VSOCK_LOG_PORT`. Once the
+//! VM boots, a relay task connects to it (retrying until k3rs-init listens)
+//! and appends each line to `{id}-output.log` in the OCI log format; that
+//! file is what `logs()` reads and `logs_stream` follows.
+//!
 //! ## Exec
 //!
-//! Exec connects directly to the Firecracker vsock UDS at
-//! `{vsock_uds_path}_{5555}` — no intermediary helper needed (unlike the
-//! macOS VZ backend which requires k3rs-vmm).
+//! Exec connects directly to the Firecracker vsock UDS and sends
+//! `CONNECT 5555` — no intermediary helper needed (unlike the macOS VZ
+//! backend which requires k3rs-vmm). The request is k3rs-init's exec
+//! protocol: NUL-separated argv and `\n`, prefixed with
+//! `VSOCK_STREAM_PREFIX` for a PTY session. Port-forwards use the same
+//! port with `VSOCK_FORWARD_PREFIX`; k3rs-init then dials the guest port.
+//!
+//! A session without a PTY uses `VSOCK_STDIN_PREFIX`: its stdin goes to
+//! k3rs-init in frames, ending with an EOF frame. The `socat` handed to the
+//! agent connects to a per-session relay socket, which frames what it sends
+//! and turns its half-close into the EOF frame.
 //!
 //! ## Requirements
 //! - Linux with `/dev/kvm` access
@@ -35,23 +55,32 @@ pub mod installer;
 pub mod jailer;
 pub mod network;
 pub mod rootfs;
+#[cfg(test)]
+mod testing;
 
-use crate::backend::RuntimeBackend;
+use crate::backend::{ExecResult, ForwardStream, RuntimeBackend};
 use crate::kernel::KernelManager;
+use crate::logs::LogOptions;
 use crate::state::ContainerStateInfo;
+use crate::vm_utils::VmConfig;
 use anyhow::{Context, Result};
 use api::FcApiClient;
 use async_trait::async_trait;
 use installer::FcInstaller;
 use rootfs::{FcRootfsManager, FcRootfsMode};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 
 use pkg_constants::runtime::{DEFAULT_CPU_COUNT, DEFAULT_MEMORY_MB, FC_GUEST_CID};
-use pkg_constants::vm::VSOCK_EXEC_PORT;
+use pkg_constants::vm::{
+    VSOCK_EXEC_PORT, VSOCK_FORWARD_PREFIX, VSOCK_LOG_PORT, VSOCK_STDIN_PREFIX, VSOCK_STREAM_PREFIX,
+};
+use pkg_types::guest_log::{FrameDecoder, format_frame};
+use pkg_types::pod::ResourceRequirements;
 
 /// VPC boot parameters passed through to the guest kernel cmdline.
 /// When present, the VM does its own SIIT translation and the host TAP is pure IPv6.
@@ -85,6 +114,9 @@ struct FcInstance {
     log_path: PathBuf,
     /// Guest CID for vsock
     guest_cid: u32,
+    /// vCPUs and memory the VM boots with; unknown for VMs re-adopted
+    /// from a PID file alone
+    vm_config: Option<VmConfig>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -116,6 +148,8 @@ pub struct FirecrackerBackend {
     kernel_manager: KernelManager,
     /// Counter for unique guest CIDs
     next_cid: Arc<tokio::sync::Mutex<u32>>,
+    /// Guest output relay task per VM
+    log_relays: std::sync::Mutex<HashMap<String, tokio::task::JoinHandle<()>>>,
     /// Cached version string
     version_string: String,
 }
@@ -180,6 +214,7 @@ impl FirecrackerBackend {
             instances: Arc::new(RwLock::new(HashMap::new())),
             kernel_manager,
             next_cid: Arc::new(tokio::sync::Mutex::new(FC_GUEST_CID)),
+            log_relays: Default::default(),
             version_string: format!(
                 "firecracker-{}",
                 pkg_constants::runtime::FIRECRACKER_VERSION
@@ -201,6 +236,11 @@ impl FirecrackerBackend {
         self.data_dir.join(format!("{}.log", id))
     }
 
+    /// Container stdout/stderr relayed from k3rs-init.
+    fn output_log_path(&self, id: &str) -> PathBuf {
+        self.data_dir.join(format!("{}-output.log", id))
+    }
+
     fn rootfs_dir(&self, id: &str) -> PathBuf {
         self.data_dir.join(format!("{}-rootfs", id))
     }
@@ -213,6 +253,11 @@ impl FirecrackerBackend {
         self.data_dir.join(format!("{}.pid", id))
     }
 
+    /// Relay socket of the `n`th exec session with stdin.
+    fn exec_relay_path(&self, id: &str, n: u64) -> PathBuf {
+        self.data_dir.join(format!("{}-exec-{}.sock", id, n))
+    }
+
     // ─── CID allocation ──────────────────────────────────────────────
 
     async fn allocate_cid(&self) -> u32 {
@@ -222,6 +267,15 @@ impl FirecrackerBackend {
         current
     }
 
+    /// Keep `cid` (held by a re-adopted VM) from being handed out again. The
+    /// CID also picks the TAP subnet, so reuse would clash on both.
+    async fn reserve_cid(&self, cid: u32) {
+        let mut next = self.next_cid.lock().await;
+        if cid >= *next {
+            *next = cid + 1;
+        }
+    }
+
     // ─── Rootfs preparation ──────────────────────────────────────────
 
     /// Prepare the rootfs with k3rs-init + config.json, then create
@@ -232,9 +286,10 @@ impl FirecrackerBackend {
         id: &str,
         command: &[String],
         env: &[String],
+        privileges: &serde_json::Map<String, serde_json::Value>,
     ) -> Result<FcRootfsMode> {
         // Inject k3rs-init and write config.json (same as VZ backend)
-        Self::inject_init_and_config(rootfs_dir, id, command, env).await?;
+        Self::inject_init_and_config(rootfs_dir, id, command, env, privileges).await?;
 
         // Always ext4 — Firecracker only supports virtio-blk root devices.
         let img_path = self.rootfs_img_path(id);
@@ -250,6 +305,7 @@ impl FirecrackerBackend {
         id: &str,
         command: &[String],
         env: &[String],
+        privileges: &serde_json::Map<String, serde_json::Value>,
     ) -> Result<()> {
         // Create required guest directories
         for dir in &[
@@ -312,13 +368,19 @@ impl FirecrackerBackend {
             command.iter().map(|s| s.as_str()).collect()
         };
 
+        // k3rs-init runs the entrypoint as the bundle's user, with its
+        // rlimits and noNewPrivileges.
+        let mut process = serde_json::json!({
+            "args": args,
+            "env": all_env,
+            "cwd": "/"
+        });
+        if let Some(process) = process.as_object_mut() {
+            process.extend(privileges.clone());
+        }
         let config = serde_json::json!({
             "ociVersion": "1.0.0",
-            "process": {
-                "args": args,
-                "env": all_env,
-                "cwd": "/"
-            },
+            "process": process,
             "hostname": id
         });
 
@@ -327,11 +389,14 @@ impl FirecrackerBackend {
             .await
             .with_context(|| format!("write config.json to {}", config_dest.display()))?;
 
-        // Write /etc/resolv.conf so the guest can resolve DNS names.
+        // The runtime writes the cluster resolv.conf while preparing the
+        // bundle; fall back to public resolvers if the rootfs has none.
         let resolv_dest = rootfs_dir.join("etc/resolv.conf");
-        tokio::fs::write(&resolv_dest, "nameserver 8.8.8.8\nnameserver 8.8.4.4\n")
-            .await
-            .ok();
+        if crate::vm_utils::needs_resolv_conf(&resolv_dest).await {
+            tokio::fs::write(&resolv_dest, "nameserver 8.8.8.8\nnameserver 8.8.4.4\n")
+                .await
+                .ok();
+        }
 
         Ok(())
     }
@@ -408,13 +473,14 @@ impl FirecrackerBackend {
         id: &str,
         rootfs_mode: &FcRootfsMode,
         guest_cid: u32,
+        vm_config: &VmConfig,
         tap_name: Option<&str>,
         vpc_params: Option<&VpcBootParams>,
     ) -> Result<()> {
         let api = FcApiClient::new(&self.api_socket_path(id).to_string_lossy());
 
         // 1. Machine config
-        api.set_machine_config(DEFAULT_CPU_COUNT, DEFAULT_MEMORY_MB)
+        api.set_machine_config(vm_config.cpu_count, vm_config.memory_mb)
             .await?;
 
         // 2. Boot source — ext4 root device via virtio-blk.
@@ -472,10 +538,12 @@ impl FirecrackerBackend {
         let boot_elapsed = boot_start.elapsed();
 
         tracing::info!(
-            "[fc] VM {} booted in {:?} (cid={}, kernel={}, rootfs={:?})",
+            "[fc] VM {} booted in {:?} (cid={}, cpus={}, mem={}MB, kernel={}, rootfs={:?})",
             id,
             boot_elapsed,
             guest_cid,
+            vm_config.cpu_count,
+            vm_config.memory_mb,
             self.kernel_path.display(),
             rootfs_mode
         );
@@ -491,28 +559,37 @@ impl FirecrackerBackend {
         Ok(())
     }
 
-    /// Stop a VM: graceful shutdown via API, then SIGTERM/SIGKILL fallback.
-    async fn stop_vm(&self, id: &str, instance: &FcInstance) -> Result<()> {
-        // Try graceful shutdown via API
-        let api_socket = &instance.api_socket;
-        if api_socket.exists() {
-            let api = FcApiClient::new(&api_socket.to_string_lossy());
-            if api.send_ctrl_alt_del().await.is_ok() {
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            }
-        }
-
-        // Signal-based fallback
+    /// Stop a VM: Ctrl+Alt+Del via the API (SIGTERM if unavailable), then
+    /// SIGKILL if the VMM is still alive after `grace`.
+    async fn stop_vm(&self, id: &str, instance: &FcInstance, grace: Duration) -> Result<()> {
         if let Some(pid) = instance.fc_pid {
-            let _ = tokio::process::Command::new("kill")
-                .args(["-TERM", &pid.to_string()])
-                .output()
-                .await;
-            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-            let _ = tokio::process::Command::new("kill")
-                .args(["-KILL", &pid.to_string()])
-                .output()
-                .await;
+            let outcome = crate::stop::graceful_stop(
+                grace,
+                async {
+                    // Ask the guest to shut down via the API; SIGTERM the VMM
+                    // if the API socket is gone.
+                    let api_socket = &instance.api_socket;
+                    let requested = api_socket.exists()
+                        && FcApiClient::new(&api_socket.to_string_lossy())
+                            .send_ctrl_alt_del()
+                            .await
+                            .is_ok();
+                    if !requested {
+                        crate::stop::signal_pid(pid, libc::SIGTERM);
+                    }
+                },
+                || std::future::ready(crate::stop::pid_alive(pid)),
+                async { crate::stop::signal_pid(pid, libc::SIGKILL) },
+            )
+            .await;
+            if outcome == crate::stop::StopOutcome::Killed {
+                tracing::warn!(
+                    "[fc] VM {} still running after {}s grace, killed (pid={})",
+                    id,
+                    grace.as_secs(),
+                    pid
+                );
+            }
         }
 
         // Stop virtiofsd if running
@@ -537,8 +614,114 @@ impl FirecrackerBackend {
         Ok(())
     }
 
-    /// Restore VMs from PID files after agent restart.
-    async fn restore_from_pid_files(&self, discovered: &mut std::collections::HashSet<String>) {
+    /// Status of a VM per its Firecracker API (`GET /`), or `None` if the API
+    /// socket does not answer.
+    async fn api_status(api_socket: &Path) -> Option<&'static str> {
+        let client = FcApiClient::new(&api_socket.to_string_lossy());
+        let info = tokio::time::timeout(Duration::from_secs(2), client.describe_instance())
+            .await
+            .ok()?
+            .ok()?;
+        Some(match info.state.as_str() {
+            "Running" => "running",
+            "Paused" => "paused",
+            _ => "created",
+        })
+    }
+
+    fn read_pid_file(&self, id: &str) -> Option<u32> {
+        std::fs::read_to_string(self.pid_file_path(id))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+    }
+
+    /// Rebuild a VM's instance state from its `GET /vm/config` response.
+    fn restored_instance(&self, id: &str, config: &serde_json::Value) -> FcInstance {
+        let image_path = config["drives"]
+            .as_array()
+            .and_then(|drives| drives.iter().find(|d| d["is_root_device"] == true))
+            .and_then(|d| d["path_on_host"].as_str())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.rootfs_img_path(id));
+        let tap_name = config["network-interfaces"]
+            .as_array()
+            .and_then(|ifaces| ifaces.first())
+            .and_then(|i| i["host_dev_name"].as_str())
+            .map(str::to_string);
+        let vsock_uds = config["vsock"]["uds_path"]
+            .as_str()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.vsock_uds_path(id));
+
+        FcInstance {
+            rootfs_mode: FcRootfsMode::Ext4 { image_path },
+            rootfs_dir: self.rootfs_dir(id),
+            fc_pid: self.read_pid_file(id),
+            api_socket: self.api_socket_path(id),
+            vsock_uds,
+            tap_name,
+            state: FcVmState::Running,
+            log_path: self.log_path(id),
+            guest_cid: config["vsock"]["guest_cid"].as_u64().unwrap_or(0) as u32,
+            vm_config: Some(VmConfig {
+                cpu_count: config["machine-config"]["vcpu_count"]
+                    .as_u64()
+                    .map_or(DEFAULT_CPU_COUNT, |n| n as u32),
+                memory_mb: config["machine-config"]["mem_size_mib"]
+                    .as_u64()
+                    .unwrap_or(DEFAULT_MEMORY_MB),
+            }),
+        }
+    }
+
+    /// Re-adopt running VMs whose API socket (`{id}.sock`) still answers
+    /// after an agent restart.
+    async fn restore_from_api_sockets(&self, discovered: &mut HashSet<String>) {
+        let mut dir = match tokio::fs::read_dir(&self.data_dir).await {
+            Ok(d) => d,
+            Err(_) => return,
+        };
+
+        while let Ok(Some(entry)) = dir.next_entry().await {
+            let file_name = entry.file_name();
+            let name = file_name.to_string_lossy();
+            let Some(vm_id) = name.strip_suffix(".sock") else {
+                continue;
+            };
+            if vm_id.ends_with("-vsock") || self.instances.read().await.contains_key(vm_id) {
+                continue;
+            }
+
+            let api_socket = entry.path();
+            if Self::api_status(&api_socket).await != Some("running") {
+                continue;
+            }
+            let config = FcApiClient::new(&api_socket.to_string_lossy())
+                .get_vm_config()
+                .await
+                .unwrap_or_default();
+            let instance = self.restored_instance(vm_id, &config);
+            self.reserve_cid(instance.guest_cid).await;
+
+            tracing::info!(
+                "[fc] restored VM {} from API socket (pid={:?}, cid={}, tap={:?})",
+                vm_id,
+                instance.fc_pid,
+                instance.guest_cid,
+                instance.tap_name
+            );
+            discovered.insert(vm_id.to_string());
+            self.start_log_relay(vm_id, instance.vsock_uds.clone());
+            self.instances
+                .write()
+                .await
+                .insert(vm_id.to_string(), instance);
+        }
+    }
+
+    /// Restore VMs from PID files after agent restart. Catches VMs whose API
+    /// socket is gone but whose VMM is still alive.
+    async fn restore_from_pid_files(&self, discovered: &mut HashSet<String>) {
         let mut dir = match tokio::fs::read_dir(&self.data_dir).await {
             Ok(d) => d,
             Err(_) => return,
@@ -591,8 +774,10 @@ impl FirecrackerBackend {
                             state: FcVmState::Running,
                             log_path: self.log_path(&vm_id),
                             guest_cid: 0, // Unknown after restart
+                            vm_config: None,
                         },
                     );
+                    self.start_log_relay(&vm_id, self.vsock_uds_path(&vm_id));
                 }
             } else {
                 tracing::info!(
@@ -623,48 +808,228 @@ impl FirecrackerBackend {
                 .vsock_uds
                 .clone()
         };
+        vsock_dial(&vsock_uds, port).await
+    }
 
-        let mut stream = tokio::net::UnixStream::connect(&vsock_uds)
-            .await
-            .with_context(|| {
-                format!("failed to connect to vsock UDS at {}", vsock_uds.display())
-            })?;
-
-        // CONNECT handshake
-        stream
-            .write_all(format!("CONNECT {}\n", port).as_bytes())
-            .await?;
-
-        let mut ok_buf = vec![0u8; 64];
-        let n = tokio::time::timeout(std::time::Duration::from_secs(5), stream.read(&mut ok_buf))
-            .await
-            .context("vsock CONNECT handshake timeout (5s)")?
-            .context("reading vsock CONNECT response")?;
-
-        let response = String::from_utf8_lossy(&ok_buf[..n]);
-        if !response.starts_with("OK ") {
-            anyhow::bail!("vsock CONNECT to port {} failed: {}", port, response.trim());
+    // ─── Guest output ────────────────────────────────────────────────
+
+    /// Relay the guest's forwarded output into the VM's output log for as
+    /// long as its vsock UDS exists, reconnecting whenever k3rs-init is not
+    /// (yet) listening. Replaces any relay already running for `id`.
+    fn start_log_relay(&self, id: &str, vsock_uds: PathBuf) {
+        let path = self.output_log_path(id);
+        let booted_at = chrono::Utc::now();
+        let handle = tokio::spawn(async move {
+            while tokio::fs::metadata(&vsock_uds).await.is_ok() {
+                // Refused until k3rs-init has started the entrypoint.
+                if let Ok(stream) = vsock_dial(&vsock_uds, VSOCK_LOG_PORT).await {
+                    tracing::info!("[fc] relaying guest output to {}", path.display());
+                    if let Err(e) = relay_guest_output(stream, &path, booted_at).await {
+                        tracing::warn!("[fc] guest log relay stopped: {}", e);
+                    }
+                }
+                tokio::time::sleep(LOG_RELAY_RECONNECT_DELAY).await;
+            }
+        });
+        if let Some(old) = self
+            .log_relays
+            .lock()
+            .unwrap()
+            .insert(id.to_string(), handle)
+        {
+            old.abort();
         }
+    }
 
-        Ok(stream)
+    fn stop_log_relay(&self, id: &str) {
+        if let Some(handle) = self.log_relays.lock().unwrap().remove(id) {
+            handle.abort();
+        }
     }
 
     /// Execute a one-shot command via Firecracker vsock.
     ///
     /// Connects host→guest on VSOCK_EXEC_PORT and uses the k3rs-init
-    /// protocol: `cmd\0arg1\0arg2\n`.
-    async fn exec_via_vsock(&self, id: &str, command: &[&str]) -> Result<String> {
+    /// protocol: `cmd\0arg1\0arg2\n`. k3rs-init sends the combined output,
+    /// then the exit status trailer.
+    async fn exec_via_vsock(&self, id: &str, command: &[&str]) -> Result<ExecResult> {
         let mut stream = self.vsock_connect(id, VSOCK_EXEC_PORT).await?;
-
-        // Send command: cmd\0arg1\0arg2\n
-        let payload = format!("{}\n", command.join("\0"));
-        stream.write_all(payload.as_bytes()).await?;
+        stream.write_all(&exec_payload(command, None)).await?;
 
         // Read response until EOF
         let mut output = Vec::new();
         stream.read_to_end(&mut output).await?;
 
-        Ok(String::from_utf8_lossy(&output).to_string())
+        let (output, code) = crate::vm_utils::split_exit_trailer(&output);
+        Ok(ExecResult {
+            stdout: String::from_utf8_lossy(output).to_string(),
+            stderr: String::new(),
+            // A k3rs-init too old to report a status.
+            exit_code: code.unwrap_or(0),
+        })
+    }
+
+    /// Start `command` in k3rs-init's stdin mode. The returned connection
+    /// takes stdin frames and yields the output, then the exit trailer.
+    async fn stdin_session(&self, id: &str, command: &[&str]) -> Result<tokio::net::UnixStream> {
+        let mut stream = self.vsock_connect(id, VSOCK_EXEC_PORT).await?;
+        stream
+            .write_all(&exec_payload(command, Some(VSOCK_STDIN_PREFIX)))
+            .await?;
+        Ok(stream)
+    }
+
+    /// Exec without a PTY: `socat` on a relay socket that frames its input
+    /// for the guest session. See [`relay_stdin_session`].
+    async fn spawn_stdin_exec(&self, id: &str, command: &[&str]) -> Result<tokio::process::Child> {
+        static RELAYS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+        let guest = self.stdin_session(id, command).await?;
+        let relay_path = self.exec_relay_path(
+            id,
+            RELAYS.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        );
+        let _ = std::fs::remove_file(&relay_path);
+        let listener = tokio::net::UnixListener::bind(&relay_path)
+            .with_context(|| format!("binding exec relay at {}", relay_path.display()))?;
+
+        // After its input ends socat waits `-t` seconds for the output
+        // before giving up on it; the guest closing the relay ends it sooner.
+        let child = tokio::process::Command::new("socat")
+            .args([
+                "-t",
+                STDIN_EXEC_LINGER_SECS,
+                "STDIO",
+                &format!("UNIX-CONNECT:{}", relay_path.display()),
+            ])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn();
+        let child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                let _ = std::fs::remove_file(&relay_path);
+                return Err(anyhow::Error::new(e)
+                    .context("failed to spawn socat for vsock bridge (is socat installed?)"));
+            }
+        };
+
+        tokio::spawn(async move {
+            let accepted = tokio::time::timeout(
+                Duration::from_secs(pkg_constants::timings::VSOCK_CONNECT_TIMEOUT_SECS),
+                listener.accept(),
+            )
+            .await;
+            let _ = std::fs::remove_file(&relay_path);
+            match accepted {
+                Ok(Ok((client, _))) => relay_stdin_session(client, guest).await,
+                _ => tracing::warn!("[fc] socat never connected to {}", relay_path.display()),
+            }
+        });
+        Ok(child)
+    }
+}
+
+/// Delay between attempts to reach the guest's log port.
+const LOG_RELAY_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// Open a host→guest vsock connection via the Firecracker vsock UDS.
+///
+/// The `OK` reply is read a byte at a time: the guest may start sending
+/// right behind it, and those bytes belong to the caller.
+async fn vsock_dial(vsock_uds: &Path, port: u32) -> Result<tokio::net::UnixStream> {
+    let mut stream = tokio::net::UnixStream::connect(vsock_uds)
+        .await
+        .with_context(|| format!("failed to connect to vsock UDS at {}", vsock_uds.display()))?;
+
+    // CONNECT handshake
+    stream
+        .write_all(format!("CONNECT {}\n", port).as_bytes())
+        .await?;
+
+    let handshake = async {
+        let mut response = Vec::new();
+        loop {
+            let byte = stream.read_u8().await?;
+            if byte == b'\n' || response.len() >= 64 {
+                return std::io::Result::Ok(response);
+            }
+            response.push(byte);
+        }
+    };
+    let response = tokio::time::timeout(Duration::from_secs(5), handshake)
+        .await
+        .context("vsock CONNECT handshake timeout (5s)")?
+        .context("reading vsock CONNECT response")?;
+
+    let response = String::from_utf8_lossy(&response);
+    if !response.starts_with("OK ") {
+        anyhow::bail!("vsock CONNECT to port {} failed: {}", port, response.trim());
+    }
+
+    Ok(stream)
+}
+
+/// Append the frames k3rs-init sends on `stream` to `path` until the guest
+/// closes the connection.
+async fn relay_guest_output(
+    mut stream: tokio::net::UnixStream,
+    path: &Path,
+    booted_at: chrono::DateTime<chrono::Utc>,
+) -> Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("open {}", path.display()))?;
+    let mut decoder = FrameDecoder::default();
+    let mut buf = vec![0u8; 16 * 1024];
+    loop {
+        let n = stream.read(&mut buf).await.context("vsock read")?;
+        if n == 0 {
+            return Ok(());
+        }
+        decoder.feed(&buf[..n]);
+        while let Some(frame) = decoder.next_frame().map_err(anyhow::Error::msg)? {
+            let line = format_frame(&frame, booted_at, chrono::Utc::now());
+            file.write_all(line.as_bytes()).await?;
+        }
+    }
+}
+
+/// How long socat keeps a session with stdin open after its input ended,
+/// waiting for the rest of the output.
+const STDIN_EXEC_LINGER_SECS: &str = "86400";
+
+/// Relay one exec session between `client` and the k3rs-init connection
+/// `guest`: client bytes go out as stdin frames, its half-close as the EOF
+/// frame, and the guest's output comes back untouched. Ends when the guest
+/// closes.
+async fn relay_stdin_session(client: tokio::net::UnixStream, guest: tokio::net::UnixStream) {
+    let (mut client_read, mut client_write) = client.into_split();
+    let (mut guest_read, mut guest_write) = guest.into_split();
+
+    let output = async {
+        let _ = tokio::io::copy(&mut guest_read, &mut client_write).await;
+        let _ = client_write.shutdown().await;
+    };
+    let input = async {
+        let mut buf = vec![0u8; 16 * 1024];
+        loop {
+            let n = client_read.read(&mut buf).await.unwrap_or(0);
+            let frame = crate::vm_utils::stdin_frame(&buf[..n]);
+            if guest_write.write_all(&frame).await.is_err() || n == 0 {
+                break;
+            }
+        }
+        // Output can still be on its way.
+        std::future::pending::<()>().await
+    };
+    tokio::select! {
+        _ = output => {}
+        _ = input => {}
     }
 }
 
@@ -696,13 +1061,17 @@ impl RuntimeBackend for FirecrackerBackend {
 
         // Parse entrypoint + env from bundle config.json
         let (command, env) = crate::vm_utils::parse_bundle_config(bundle);
+        let privileges = crate::vm_utils::parse_bundle_privileges(bundle);
 
         // Prepare rootfs (inject k3rs-init, create ext4 or start virtiofsd)
-        let rootfs_mode = self.prepare_rootfs(&rootfs_dir, id, &command, &env).await?;
+        let rootfs_mode = self
+            .prepare_rootfs(&rootfs_dir, id, &command, &env, &privileges)
+            .await?;
 
         let guest_cid = self.allocate_cid().await;
         let log_path = self.log_path(id);
         tokio::fs::write(&log_path, "").await?;
+        tokio::fs::write(self.output_log_path(id), "").await?;
 
         self.instances.write().await.insert(
             id.to_string(),
@@ -716,6 +1085,7 @@ impl RuntimeBackend for FirecrackerBackend {
                 state: FcVmState::Created,
                 log_path,
                 guest_cid,
+                vm_config: Some(VmConfig::default()),
             },
         );
 
@@ -723,13 +1093,38 @@ impl RuntimeBackend for FirecrackerBackend {
         Ok(())
     }
 
+    async fn create_with_resources(
+        &self,
+        id: &str,
+        bundle: &Path,
+        resources: &ResourceRequirements,
+    ) -> Result<()> {
+        let vm_config =
+            VmConfig::for_limits(resources, &VmConfig::default(), &VmConfig::node_capacity());
+        self.create(id, bundle).await?;
+        if let Some(inst) = self.instances.write().await.get_mut(id) {
+            inst.vm_config = Some(vm_config);
+        }
+        tracing::info!(
+            "[fc] VM {} sized to {} vCPU, {}MB (limits: {}m CPU, {} bytes)",
+            id,
+            vm_config.cpu_count,
+            vm_config.memory_mb,
+            resources.cpu_millis,
+            resources.memory_bytes
+        );
+        Ok(())
+    }
+
     async fn create_from_image(&self, id: &str, image: &str, command: &[String]) -> Result<()> {
         tracing::info!("[fc] create_from_image: id={} image={}", id, image);
 
         let rootfs_dir = self.rootfs_dir(id);
         tokio::fs::create_dir_all(&rootfs_dir).await?;
 
-        let rootfs_mode = self.prepare_rootfs(&rootfs_dir, id, command, &[]).await?;
+        let rootfs_mode = self
+            .prepare_rootfs(&rootfs_dir, id, command, &[], &serde_json::Map::new())
+            .await?;
 
         let guest_cid = self.allocate_cid().await;
         let log_path = self.log_path(id);
@@ -738,6 +1133,7 @@ impl RuntimeBackend for FirecrackerBackend {
             format!("[fc] VM for image {} (cmd: {:?})\n", image, command),
         )
         .await?;
+        tokio::fs::write(self.output_log_path(id), "").await?;
 
         self.instances.write().await.insert(
             id.to_string(),
@@ -751,6 +1147,7 @@ impl RuntimeBackend for FirecrackerBackend {
                 state: FcVmState::Created,
                 log_path,
                 guest_cid,
+                vm_config: Some(VmConfig::default()),
             },
         );
 
@@ -767,12 +1164,16 @@ impl RuntimeBackend for FirecrackerBackend {
             );
         }
 
-        let (rootfs_mode, guest_cid) = {
+        let (rootfs_mode, guest_cid, vm_config) = {
             let instances = self.instances.read().await;
             let inst = instances
                 .get(id)
                 .ok_or_else(|| anyhow::anyhow!("VM {} not found — call create() first", id))?;
-            (inst.rootfs_mode.clone(), inst.guest_cid)
+            (
+                inst.rootfs_mode.clone(),
+                inst.guest_cid,
+                inst.vm_config.unwrap_or_default(),
+            )
         };
 
         // 1. Spawn Firecracker process
@@ -789,7 +1190,14 @@ impl RuntimeBackend for FirecrackerBackend {
 
         // 3. Configure via REST API and boot
         if let Err(e) = self
-            .configure_and_boot(id, &rootfs_mode, guest_cid, tap_name.as_deref(), None)
+            .configure_and_boot(
+                id,
+                &rootfs_mode,
+                guest_cid,
+                &vm_config,
+                tap_name.as_deref(),
+                None,
+            )
             .await
         {
             // Check if Firecracker is still alive for better diagnostics
@@ -836,13 +1244,14 @@ impl RuntimeBackend for FirecrackerBackend {
             inst.state = FcVmState::Running;
             inst.fc_pid = Some(pid);
             inst.tap_name = tap_name;
+            self.start_log_relay(id, inst.vsock_uds.clone());
         }
 
         Ok(())
     }
 
-    async fn stop(&self, id: &str) -> Result<()> {
-        tracing::info!("[fc] stop VM: {}", id);
+    async fn stop(&self, id: &str, grace: Duration) -> Result<()> {
+        tracing::info!("[fc] stop VM: {} (grace={}s)", id, grace.as_secs());
 
         let instance = {
             let instances = self.instances.read().await;
@@ -850,7 +1259,7 @@ impl RuntimeBackend for FirecrackerBackend {
         };
 
         if let Some(inst) = instance {
-            self.stop_vm(id, &inst).await?;
+            self.stop_vm(id, &inst, grace).await?;
         }
 
         let mut instances = self.instances.write().await;
@@ -865,7 +1274,8 @@ impl RuntimeBackend for FirecrackerBackend {
     async fn delete(&self, id: &str) -> Result<()> {
         tracing::info!("[fc] delete VM: {}", id);
 
-        self.stop(id).await.ok();
+        self.stop(id, Duration::ZERO).await.ok();
+        self.stop_log_relay(id);
 
         let removed = self.instances.write().await.remove(id);
         if let Some(inst) = removed {
@@ -899,6 +1309,7 @@ impl RuntimeBackend for FirecrackerBackend {
         let _ = tokio::fs::remove_dir_all(self.rootfs_dir(id)).await;
         let _ = tokio::fs::remove_file(self.rootfs_img_path(id)).await;
         let _ = tokio::fs::remove_file(self.log_path(id)).await;
+        let _ = tokio::fs::remove_file(self.output_log_path(id)).await;
         let _ = tokio::fs::remove_file(self.pid_file_path(id)).await;
         let _ = tokio::fs::remove_file(self.api_socket_path(id)).await;
         let _ = tokio::fs::remove_file(self.vsock_uds_path(id)).await;
@@ -908,7 +1319,7 @@ impl RuntimeBackend for FirecrackerBackend {
     }
 
     async fn list(&self) -> Result<Vec<String>> {
-        let mut ids: std::collections::HashSet<String> = {
+        let mut ids: HashSet<String> = {
             let instances = self.instances.read().await;
             instances
                 .iter()
@@ -917,32 +1328,27 @@ impl RuntimeBackend for FirecrackerBackend {
                 .collect()
         };
 
-        // PID file scan + liveness check
+        // API socket scan, then PID file scan + liveness check
+        self.restore_from_api_sockets(&mut ids).await;
         self.restore_from_pid_files(&mut ids).await;
 
         Ok(ids.into_iter().collect())
     }
 
-    async fn logs(&self, id: &str, tail: usize) -> Result<Vec<String>> {
-        let log_path = self.log_path(id);
-        match tokio::fs::read_to_string(&log_path).await {
-            Ok(content) => {
-                let lines: Vec<String> = content
-                    .lines()
-                    .rev()
-                    .take(tail)
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>()
-                    .into_iter()
-                    .rev()
-                    .collect();
-                Ok(lines)
-            }
+    async fn logs(&self, id: &str, opts: &LogOptions) -> Result<Vec<String>> {
+        match crate::logs::read_log_file(self.output_log_path(id), opts.clone()).await {
+            Ok(lines) => Ok(lines),
             Err(_) => Ok(vec![format!("[fc] no logs for VM {}", id)]),
         }
     }
 
-    async fn exec(&self, id: &str, command: &[&str]) -> Result<String> {
+    /// The relay appends the container's stdout and stderr here as k3rs-init
+    /// forwards them, so following it streams the guest's output live.
+    fn log_file(&self, id: &str) -> Option<PathBuf> {
+        Some(self.output_log_path(id))
+    }
+
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecResult> {
         tracing::info!("[fc] exec in VM {}: {:?}", id, command);
 
         // Verify the VM is running
@@ -972,6 +1378,10 @@ impl RuntimeBackend for FirecrackerBackend {
     ) -> Result<tokio::process::Child> {
         tracing::info!("[fc] spawn_exec in VM {} tty={}: {:?}", id, tty, command);
 
+        if !tty {
+            return self.spawn_stdin_exec(id, command).await;
+        }
+
         let vsock_uds = {
             let instances = self.instances.read().await;
             instances
@@ -981,16 +1391,6 @@ impl RuntimeBackend for FirecrackerBackend {
                 .clone()
         };
 
-        // Build the exec payload
-        let cmd_args: Vec<&str> = if command.is_empty() {
-            vec!["/bin/sh"]
-        } else {
-            command.to_vec()
-        };
-
-        let prefix = if tty { "\x01" } else { "" };
-        let payload = format!("{}{}\n", prefix, cmd_args.join("\0"));
-
         // Use socat to connect to the main vsock UDS.
         let mut child = tokio::process::Command::new("socat")
             .args(["STDIO", &format!("UNIX-CONNECT:{}", vsock_uds.display())])
@@ -1029,30 +1429,85 @@ impl RuntimeBackend for FirecrackerBackend {
 
         // Send the exec payload after handshake completes
         if let Some(ref mut stdin) = child.stdin {
-            stdin.write_all(payload.as_bytes()).await?;
+            stdin
+                .write_all(&exec_payload(command, Some(VSOCK_STREAM_PREFIX)))
+                .await?;
         }
 
         Ok(child)
     }
 
-    async fn state(&self, id: &str) -> Result<ContainerStateInfo> {
-        // In-memory check (fast path)
-        {
-            let instances = self.instances.read().await;
-            if let Some(inst) = instances.get(id) {
-                let status = match inst.state {
-                    FcVmState::Created => "created",
-                    FcVmState::Running => "running",
-                    FcVmState::Stopped => "stopped",
+    async fn port_forward(&self, id: &str, port: u16) -> Result<Box<dyn ForwardStream>> {
+        let mut stream = self.vsock_connect(id, VSOCK_EXEC_PORT).await?;
+        stream.write_all(&forward_payload(port)).await?;
+
+        // Byte by byte: whatever follows the status line is the port's data.
+        let mut line = Vec::new();
+        let read_status = async {
+            loop {
+                match stream.read_u8().await? {
+                    b'\n' => return Ok::<_, std::io::Error>(()),
+                    b if line.len() < 256 => line.push(b),
+                    _ => return Err(std::io::ErrorKind::InvalidData.into()),
                 }
-                .to_string();
-                return Ok(ContainerStateInfo {
-                    id: id.to_string(),
-                    status,
-                    pid: inst.fc_pid.unwrap_or(0),
-                    bundle: inst.rootfs_dir.to_string_lossy().to_string(),
-                });
             }
+        };
+        tokio::time::timeout(
+            Duration::from_secs(pkg_constants::timings::VSOCK_CONNECT_TIMEOUT_SECS),
+            read_status,
+        )
+        .await
+        .context("vsock port-forward handshake timeout")?
+        .context("reading vsock port-forward status")?;
+
+        let status = String::from_utf8_lossy(&line);
+        if status != "OK" {
+            anyhow::bail!(
+                "connecting to port {} in VM {}: {}",
+                port,
+                id,
+                status.strip_prefix("ERR ").unwrap_or(&status)
+            );
+        }
+        Ok(Box::new(stream))
+    }
+
+    async fn state(&self, id: &str) -> Result<ContainerStateInfo> {
+        // Known VM: a booted one reports its state through the API
+        let instance = self.instances.read().await.get(id).cloned();
+        if let Some(inst) = instance {
+            let status = match inst.state {
+                FcVmState::Created => "created",
+                FcVmState::Running => match Self::api_status(&inst.api_socket).await {
+                    Some(status) => status,
+                    None if inst.fc_pid.is_some_and(crate::stop::pid_alive) => "running",
+                    None => "stopped",
+                },
+                FcVmState::Stopped => "stopped",
+            }
+            .to_string();
+            return Ok(ContainerStateInfo {
+                id: id.to_string(),
+                status,
+                pid: inst.fc_pid.unwrap_or(0),
+                bundle: inst.rootfs_dir.to_string_lossy().to_string(),
+                vcpus: inst.vm_config.map(|c| c.cpu_count),
+                memory_mb: inst.vm_config.map(|c| c.memory_mb),
+                usage: None,
+            });
+        }
+
+        // API socket check (post-restart recovery)
+        if let Some(status) = Self::api_status(&self.api_socket_path(id)).await {
+            return Ok(ContainerStateInfo {
+                id: id.to_string(),
+                status: status.to_string(),
+                pid: self.read_pid_file(id).unwrap_or(0),
+                bundle: self.rootfs_dir(id).to_string_lossy().to_string(),
+                vcpus: None,
+                memory_mb: None,
+                usage: None,
+            });
         }
 
         // PID file check (post-restart recovery)
@@ -1067,6 +1522,9 @@ impl RuntimeBackend for FirecrackerBackend {
                     status: "running".to_string(),
                     pid,
                     bundle: self.rootfs_dir(id).to_string_lossy().to_string(),
+                    vcpus: None,
+                    memory_mb: None,
+                    usage: None,
                 });
             }
         }
@@ -1075,14 +1533,38 @@ impl RuntimeBackend for FirecrackerBackend {
     }
 }
 
+/// A k3rs-init exec request: NUL-separated argv terminated by `\n`, with
+/// `prefix` in front to pick a mode other than one-shot:
+/// [`VSOCK_STREAM_PREFIX`] for a PTY, [`VSOCK_STDIN_PREFIX`] for stdin.
+fn exec_payload(command: &[&str], prefix: Option<u8>) -> Vec<u8> {
+    let args: &[&str] = if command.is_empty() {
+        &["/bin/sh"]
+    } else {
+        command
+    };
+    let mut payload = Vec::new();
+    payload.extend(prefix);
+    payload.extend_from_slice(args.join("\0").as_bytes());
+    payload.push(b'\n');
+    payload
+}
+
+/// k3rs-init request to forward the connection to a guest TCP port.
+fn forward_payload(port: u16) -> Vec<u8> {
+    let mut payload = vec![VSOCK_FORWARD_PREFIX];
+    payload.extend_from_slice(format!("{}\n", port).as_bytes());
+    payload
+}
+
 #[cfg(test)]
 mod tests {
+    use super::testing::MockFcApi;
     use super::*;
+    use crate::test_util::temp_dir;
 
-    #[test]
-    fn test_path_helpers() {
-        let backend = FirecrackerBackend {
-            data_dir: PathBuf::from("/tmp/test-vms"),
+    fn test_backend(data_dir: &Path) -> FirecrackerBackend {
+        FirecrackerBackend {
+            data_dir: data_dir.to_path_buf(),
             kernel_path: PathBuf::from("/tmp/vmlinux"),
             initrd_path: None,
             firecracker_bin: PathBuf::from("/usr/local/bin/firecracker"),
@@ -1090,11 +1572,17 @@ mod tests {
             instances: Arc::new(RwLock::new(HashMap::new())),
             kernel_manager: KernelManager::with_dir(Path::new("/tmp/test")),
             next_cid: Arc::new(tokio::sync::Mutex::new(FC_GUEST_CID)),
+            log_relays: Default::default(),
             version_string: format!(
                 "firecracker-{}",
                 pkg_constants::runtime::FIRECRACKER_VERSION
             ),
-        };
+        }
+    }
+
+    #[test]
+    fn test_path_helpers() {
+        let backend = test_backend(Path::new("/tmp/test-vms"));
 
         assert_eq!(
             backend.api_socket_path("vm-001"),
@@ -1130,4 +1618,422 @@ mod tests {
             assert_eq!(second, 4);
         }
     }
+
+    #[tokio::test]
+    async fn test_configure_and_boot_sequence() {
+        let dir = temp_dir("boot");
+        let backend = test_backend(&dir);
+        let mock = MockFcApi::start(&backend.api_socket_path("vm-1"));
+        let image_path = backend.rootfs_img_path("vm-1");
+
+        backend
+            .configure_and_boot(
+                "vm-1",
+                &FcRootfsMode::Ext4 {
+                    image_path: image_path.clone(),
+                },
+                7,
+                &VmConfig {
+                    cpu_count: 2,
+                    memory_mb: 1024,
+                },
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+
+        let requests = mock.requests();
+        let paths: Vec<&str> = requests.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "/machine-config",
+                "/boot-source",
+                "/drives/rootfs",
+                "/vsock",
+                "/actions"
+            ]
+        );
+        assert!(requests.iter().all(|r| r.method == "PUT"));
+        assert_eq!(
+            requests[0].body,
+            serde_json::json!({ "vcpu_count": 2, "mem_size_mib": 1024 })
+        );
+
+        let boot_args = requests[1].body["boot_args"].as_str().unwrap();
+        assert!(boot_args.contains("console=ttyS0"), "{}", boot_args);
+        assert!(boot_args.contains("root=/dev/vda rw"), "{}", boot_args);
+        assert!(boot_args.contains("init=/sbin/k3rs-init"), "{}", boot_args);
+        assert_eq!(requests[1].body["kernel_image_path"], "/tmp/vmlinux");
+        assert_eq!(
+            requests[2].body["path_on_host"],
+            image_path.to_string_lossy().as_ref()
+        );
+        assert_eq!(requests[2].body["is_root_device"], true);
+        assert_eq!(requests[3].body["guest_cid"], 7);
+        assert_eq!(
+            requests[3].body["uds_path"],
+            backend.vsock_uds_path("vm-1").to_string_lossy().as_ref()
+        );
+        assert_eq!(requests[4].body["action_type"], "InstanceStart");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_configure_and_boot_with_network() {
+        let dir = temp_dir("boot-net");
+        let backend = test_backend(&dir);
+        let mock = MockFcApi::start(&backend.api_socket_path("vm-1"));
+        let rootfs = FcRootfsMode::Ext4 {
+            image_path: backend.rootfs_img_path("vm-1"),
+        };
+        let vpc = VpcBootParams {
+            guest_ipv4: "10.0.1.5".to_string(),
+            ghost_ipv6: "fd00::1:5".to_string(),
+            vpc_id: 1,
+            vpc_cidr: "10.0.1.0/24".to_string(),
+            gw_mac: "02:00:00:00:00:01".to_string(),
+        };
+
+        backend
+            .configure_and_boot(
+                "vm-1",
+                &rootfs,
+                4,
+                &VmConfig::default(),
+                Some("tap-vm-1"),
+                Some(&vpc),
+            )
+            .await
+            .unwrap();
+
+        let requests = mock.requests();
+        let boot_args = requests[1].body["boot_args"].as_str().unwrap();
+        assert!(boot_args.contains("k3rs.ipv4=10.0.1.5"), "{}", boot_args);
+        assert!(!boot_args.contains(" ip="), "{}", boot_args);
+        // The NIC is attached before the instance starts.
+        let n = requests.len();
+        assert_eq!(requests[n - 2].path, "/network-interfaces/eth0");
+        assert_eq!(requests[n - 2].body["host_dev_name"], "tap-vm-1");
+        assert_eq!(requests[n - 1].path, "/actions");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_configure_and_boot_stops_at_api_error() {
+        let dir = temp_dir("boot-err");
+        let backend = test_backend(&dir);
+        let mock = MockFcApi::start(&backend.api_socket_path("vm-1"));
+        mock.respond(
+            "PUT",
+            "/boot-source",
+            400,
+            r#"{"fault_message":"The kernel file cannot be opened"}"#,
+        );
+        let rootfs = FcRootfsMode::Ext4 {
+            image_path: backend.rootfs_img_path("vm-1"),
+        };
+
+        let err = backend
+            .configure_and_boot("vm-1", &rootfs, 3, &VmConfig::default(), None, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("kernel file"), "{}", err);
+        assert!(mock.requests().iter().all(|r| r.path != "/actions"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_exec_payload() {
+        assert_eq!(exec_payload(&["ls", "-l", "/"], None), b"ls\0-l\0/\n");
+        assert_eq!(
+            exec_payload(&[], Some(VSOCK_STREAM_PREFIX)),
+            b"\x01/bin/sh\n"
+        );
+        assert_eq!(
+            exec_payload(&["wc", "-c"], Some(VSOCK_STDIN_PREFIX)),
+            b"\x03wc\0-c\n"
+        );
+        assert_eq!(forward_payload(8080), b"\x028080\n");
+    }
+
+    #[tokio::test]
+    async fn test_exec_over_vsock() {
+        let dir = temp_dir("exec");
+        let backend = test_backend(&dir);
+        let vsock_uds = backend.vsock_uds_path("vm-1");
+
+        // Stand-in for Firecracker's vsock muxer and k3rs-init behind it.
+        let listener = tokio::net::UnixListener::bind(&vsock_uds).unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = tokio::io::BufReader::new(stream);
+            let mut line = Vec::new();
+            tokio::io::AsyncBufReadExt::read_until(&mut stream, b'\n', &mut line)
+                .await
+                .unwrap();
+            assert_eq!(line, format!("CONNECT {}\n", VSOCK_EXEC_PORT).as_bytes());
+            stream
+                .get_mut()
+                .write_all(b"OK 1073741824\n")
+                .await
+                .unwrap();
+            line.clear();
+            tokio::io::AsyncBufReadExt::read_until(&mut stream, b'\n', &mut line)
+                .await
+                .unwrap();
+            let reply = format!(
+                "ran {:?}\n\x02exit:3\n",
+                String::from_utf8_lossy(&line).trim_end()
+            );
+            stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+        });
+
+        let mut instance = backend.restored_instance("vm-1", &serde_json::Value::Null);
+        instance.state = FcVmState::Running;
+        backend
+            .instances
+            .write()
+            .await
+            .insert("vm-1".to_string(), instance);
+
+        let out = backend
+            .exec_via_vsock("vm-1", &["echo", "hi"])
+            .await
+            .unwrap();
+        assert_eq!(out.stdout, "ran \"echo\\0hi\"\n");
+        assert_eq!(out.exit_code, 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Stand-in for Firecracker's vsock muxer and k3rs-init running `wc -c`
+    /// in stdin mode: counts the bytes in the frames up to the EOF frame.
+    fn fake_stdin_guest(vsock_uds: &Path) {
+        let listener = tokio::net::UnixListener::bind(vsock_uds).unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = tokio::io::BufReader::new(stream);
+            let mut line = Vec::new();
+            tokio::io::AsyncBufReadExt::read_until(&mut stream, b'\n', &mut line)
+                .await
+                .unwrap();
+            stream
+                .get_mut()
+                .write_all(b"OK 1073741826\n")
+                .await
+                .unwrap();
+            line.clear();
+            tokio::io::AsyncBufReadExt::read_until(&mut stream, b'\n', &mut line)
+                .await
+                .unwrap();
+            assert_eq!(line, b"\x03wc\0-c\n");
+            let mut count = 0;
+            loop {
+                let len = stream.read_u32().await.unwrap() as usize;
+                if len == 0 {
+                    break;
+                }
+                let mut frame = vec![0u8; len];
+                stream.read_exact(&mut frame).await.unwrap();
+                count += len;
+            }
+            let reply = format!("{}\n\x02exit:0\n", count);
+            stream.get_mut().write_all(reply.as_bytes()).await.unwrap();
+        });
+    }
+
+    #[tokio::test]
+    async fn test_stdin_session_over_vsock() {
+        let dir = temp_dir("stdin");
+        let backend = test_backend(&dir);
+        fake_stdin_guest(&backend.vsock_uds_path("vm-1"));
+        let mut instance = backend.restored_instance("vm-1", &serde_json::Value::Null);
+        instance.state = FcVmState::Running;
+        backend
+            .instances
+            .write()
+            .await
+            .insert("vm-1".to_string(), instance);
+
+        // `client` is where socat would be.
+        let guest = backend.stdin_session("vm-1", &["wc", "-c"]).await.unwrap();
+        let (mut client, relay_end) = tokio::net::UnixStream::pair().unwrap();
+        let relay = tokio::spawn(relay_stdin_session(relay_end, guest));
+
+        client.write_all(&vec![b'x'; 100_000]).await.unwrap();
+        client.shutdown().await.unwrap();
+        let mut output = String::new();
+        client.read_to_string(&mut output).await.unwrap();
+        relay.await.unwrap();
+        assert_eq!(output, "100000\n\x02exit:0\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Stand-in for Firecracker's vsock muxer with k3rs-init forwarding to
+    /// an echo server on the guest's port 8080 and refusing other ports.
+    fn fake_forwarding_guest(vsock_uds: &Path) {
+        let listener = tokio::net::UnixListener::bind(vsock_uds).unwrap();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut stream = tokio::io::BufReader::new(stream);
+                    let mut line = Vec::new();
+                    tokio::io::AsyncBufReadExt::read_until(&mut stream, b'\n', &mut line)
+                        .await
+                        .unwrap();
+                    stream
+                        .get_mut()
+                        .write_all(b"OK 1073741825\n")
+                        .await
+                        .unwrap();
+                    line.clear();
+                    tokio::io::AsyncBufReadExt::read_until(&mut stream, b'\n', &mut line)
+                        .await
+                        .unwrap();
+                    if line != forward_payload(8080) {
+                        let _ = stream
+                            .get_mut()
+                            .write_all(b"ERR Connection refused (os error 111)\n")
+                            .await;
+                        return;
+                    }
+                    stream.get_mut().write_all(b"OK\n").await.unwrap();
+                    let (mut reader, mut writer) = tokio::io::split(stream);
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_port_forward_over_vsock() {
+        let dir = temp_dir("forward");
+        let backend = test_backend(&dir);
+        fake_forwarding_guest(&backend.vsock_uds_path("vm-1"));
+        let mut instance = backend.restored_instance("vm-1", &serde_json::Value::Null);
+        instance.state = FcVmState::Running;
+        backend
+            .instances
+            .write()
+            .await
+            .insert("vm-1".to_string(), instance);
+
+        let mut stream = backend.port_forward("vm-1", 8080).await.unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply).await.unwrap();
+        assert_eq!(&reply, b"ping");
+
+        let err = backend.port_forward("vm-1", 9090).await.err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "connecting to port 9090 in VM vm-1: Connection refused (os error 111)"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_guest_output_relayed_to_log() {
+        let dir = temp_dir("output");
+        let backend = test_backend(&dir);
+        let vsock_uds = backend.vsock_uds_path("vm-1");
+
+        // k3rs-init sends its first frame right behind the muxer's `OK`.
+        let listener = tokio::net::UnixListener::bind(&vsock_uds).unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = tokio::io::BufReader::new(stream);
+            let mut line = Vec::new();
+            tokio::io::AsyncBufReadExt::read_until(&mut stream, b'\n', &mut line)
+                .await
+                .unwrap();
+            assert_eq!(line, format!("CONNECT {}\n", VSOCK_LOG_PORT).as_bytes());
+            let mut reply = b"OK 1073741824\n".to_vec();
+            for (tag, text) in [(1u8, "hello"), (2, "oops")] {
+                reply.push(tag);
+                reply.extend_from_slice(&0u64.to_be_bytes());
+                reply.extend_from_slice(&(text.len() as u32).to_be_bytes());
+                reply.extend_from_slice(text.as_bytes());
+            }
+            stream.get_mut().write_all(&reply).await.unwrap();
+            // Hold the connection so the relay doesn't dial again.
+            std::future::pending::<()>().await;
+        });
+
+        backend.start_log_relay("vm-1", vsock_uds);
+        let path = backend.log_file("vm-1").unwrap();
+        let mut lines = Vec::new();
+        for _ in 0..50 {
+            lines = crate::logs::read_log_file(path.clone(), LogOptions::default())
+                .await
+                .unwrap_or_default();
+            if lines.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+        assert!(lines[0].ends_with("hello"), "{:?}", lines);
+        assert!(lines[1].ends_with("oops"), "{:?}", lines);
+
+        backend.stop_log_relay("vm-1");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_list_restores_vms_from_api_sockets() {
+        let dir = temp_dir("restore");
+        let backend = test_backend(&dir);
+        let running = MockFcApi::start(&backend.api_socket_path("vm-1"));
+        running.respond("GET", "/", 200, r#"{"id":"vm-1","state":"Running"}"#);
+        running.respond(
+            "GET",
+            "/vm/config",
+            200,
+            &serde_json::json!({
+                "drives": [{
+                    "drive_id": "rootfs",
+                    "path_on_host": "/var/vms/vm-1-rootfs.ext4",
+                    "is_root_device": true
+                }],
+                "network-interfaces": [{ "iface_id": "eth0", "host_dev_name": "tap-vm-1" }],
+                "vsock": { "guest_cid": 9, "uds_path": "/var/vms/vm-1-vsock.sock" },
+                "machine-config": { "vcpu_count": 2, "mem_size_mib": 2048 }
+            })
+            .to_string(),
+        );
+        let pid = std::process::id();
+        std::fs::write(backend.pid_file_path("vm-1"), format!("{}\n", pid)).unwrap();
+        let not_started = MockFcApi::start(&backend.api_socket_path("vm-2"));
+        not_started.respond("GET", "/", 200, r#"{"id":"vm-2","state":"Not started"}"#);
+        // A socket nobody listens on any more
+        drop(std::os::unix::net::UnixListener::bind(backend.api_socket_path("vm-3")).unwrap());
+
+        assert_eq!(backend.list().await.unwrap(), ["vm-1"]);
+
+        {
+            let instances = backend.instances.read().await;
+            let vm = &instances["vm-1"];
+            assert_eq!(vm.guest_cid, 9);
+            assert_eq!(vm.tap_name.as_deref(), Some("tap-vm-1"));
+            assert_eq!(vm.fc_pid, Some(pid));
+            assert_eq!(vm.vsock_uds, Path::new("/var/vms/vm-1-vsock.sock"));
+            let FcRootfsMode::Ext4 { image_path } = &vm.rootfs_mode else {
+                panic!("restored VM should use an ext4 rootfs");
+            };
+            assert_eq!(image_path, Path::new("/var/vms/vm-1-rootfs.ext4"));
+        }
+        // The restored CID is not handed out again.
+        assert_eq!(backend.allocate_cid().await, 10);
+
+        let state = backend.state("vm-1").await.unwrap();
+        assert_eq!(state.status, "running");
+        assert_eq!((state.vcpus, state.memory_mb), (Some(2), Some(2048)));
+        running.respond("GET", "/", 200, r#"{"id":"vm-1","state":"Paused"}"#);
+        assert_eq!(backend.state("vm-1").await.unwrap().status, "paused");
+        assert_eq!(backend.state("vm-2").await.unwrap().status, "created");
+        assert!(backend.state("vm-3").await.is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
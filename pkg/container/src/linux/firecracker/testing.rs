@@ -0,0 +1,111 @@
+//! Stand-in for the Firecracker API socket, for tests that exercise the
+//! configure/boot sequence without KVM.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixListener;
+
+/// A request the mock received.
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    /// JSON body; `Null` for GETs.
+    pub body: serde_json::Value,
+}
+
+type Responses = HashMap<(String, String), (u16, String)>;
+
+/// Records every request and answers `204 No Content` unless a response was
+/// set with [`MockFcApi::respond`].
+pub struct MockFcApi {
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+    responses: Arc<Mutex<Responses>>,
+}
+
+impl MockFcApi {
+    /// Listen on `socket`. Must be called inside a Tokio runtime.
+    pub fn start(socket: &Path) -> Self {
+        let _ = std::fs::remove_file(socket);
+        let listener = UnixListener::bind(socket).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let responses = Arc::new(Mutex::new(Responses::new()));
+
+        let (reqs, resps) = (requests.clone(), responses.clone());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let Some(request) = read_request(&mut stream).await else {
+                    continue;
+                };
+                let (status, body) = resps
+                    .lock()
+                    .unwrap()
+                    .get(&(request.method.clone(), request.path.clone()))
+                    .cloned()
+                    .unwrap_or((204, String::new()));
+                reqs.lock().unwrap().push(request);
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        Self {
+            requests,
+            responses,
+        }
+    }
+
+    /// Answer `method path` with `status` and a JSON `body`.
+    pub fn respond(&self, method: &str, path: &str, status: u16, body: &str) {
+        self.responses.lock().unwrap().insert(
+            (method.to_string(), path.to_string()),
+            (status, body.to_string()),
+        );
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+async fn read_request(stream: &mut tokio::net::UnixStream) -> Option<MockRequest> {
+    let mut buf = Vec::new();
+    let mut tmp = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut tmp).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&tmp[..n]);
+        let Some(hdr_end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            continue;
+        };
+        let headers = String::from_utf8_lossy(&buf[..hdr_end]).to_string();
+        let content_length: usize = headers
+            .lines()
+            .find_map(|l| {
+                l.to_ascii_lowercase()
+                    .strip_prefix("content-length:")
+                    .map(|v| v.trim().parse().unwrap_or(0))
+            })
+            .unwrap_or(0);
+        if buf.len() < hdr_end + 4 + content_length {
+            continue;
+        }
+
+        let mut request_line = headers.lines().next()?.split_whitespace();
+        let method = request_line.next()?.to_string();
+        let path = request_line.next()?.to_string();
+        let body = &buf[hdr_end + 4..hdr_end + 4 + content_length];
+        let body = serde_json::from_slice(body).unwrap_or(serde_json::Value::Null);
+        return Some(MockRequest { method, path, body });
+    }
+}
@@ -1 +1,2 @@
 pub mod firecracker;
+pub mod netns;
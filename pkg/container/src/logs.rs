@@ -0,0 +1,1065 @@
+//! Container log streaming.
+//!
+//! Every backend persists container stdout/stderr to a per-container log file
+//! (OCI: relayed from the container's stdout FIFO by [`spawn_log_relay`]; VMs:
+//! the virtio-console output written by the VMM). [`tail_log_file`] turns such
+//! a file into a line stream that can optionally keep following new writes,
+//! which the agent exposes over HTTP.
+//!
+//! The relay rotates the file per [`LogRotation`]: `stdout.log` is moved to
+//! `stdout.log.1` (optionally gzipped to `stdout.log.1.gz`) and older files
+//! shift up by one. Reading back covers the rotated files too, from the end,
+//! so a short `tail` of a large log stays cheap.
+//!
+//! Lines written by the relay carry an RFC3339 timestamp prefix
+//! (`2024-01-01T00:00:00.000000000Z message`). Lines without one — VM console
+//! output, or logs written before timestamps were recorded — are passed
+//! through unchanged and sort as the Unix epoch when filtering by time.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// A stream of log lines (without trailing newlines).
+pub type LogStream = Pin<Box<dyn Stream<Item = String> + Send>>;
+
+/// Number of lines buffered between the tailing task and the consumer.
+const LOG_STREAM_BUFFER: usize = 256;
+
+/// Filters applied when reading container logs back.
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    /// Only return lines written at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only return the last N lines of the existing log.
+    pub tail: Option<usize>,
+    /// Keep the RFC3339 timestamp prefix in the output.
+    pub timestamps: bool,
+}
+
+/// Prefix a log line with its write time.
+pub fn format_log_line(ts: DateTime<Utc>, line: &str) -> String {
+    format!(
+        "{} {}",
+        ts.to_rfc3339_opts(SecondsFormat::Nanos, true),
+        line
+    )
+}
+
+/// Split a stored log line into its timestamp and message.
+/// Lines without a valid RFC3339 prefix are returned whole with no timestamp.
+pub fn parse_log_line(line: &str) -> (Option<DateTime<Utc>>, &str) {
+    if let Some((prefix, rest)) = line.split_once(' ')
+        && let Ok(ts) = DateTime::parse_from_rfc3339(prefix)
+    {
+        return (Some(ts.with_timezone(&Utc)), rest);
+    }
+    (None, line)
+}
+
+impl LogOptions {
+    /// Whether a stored line passes the `since` filter.
+    fn admits(&self, line: &str) -> bool {
+        match self.since {
+            Some(since) => parse_log_line(line).0.unwrap_or(DateTime::UNIX_EPOCH) >= since,
+            None => true,
+        }
+    }
+
+    /// Render a stored line for output, stripping the timestamp unless requested.
+    fn render(&self, line: String) -> String {
+        if self.timestamps {
+            return line;
+        }
+        match parse_log_line(&line) {
+            (Some(_), message) => message.to_string(),
+            (None, _) => line,
+        }
+    }
+
+    /// Apply `since`, then `tail`, then timestamp stripping to stored lines.
+    pub fn apply<I>(&self, lines: I) -> Vec<String>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let mut kept: Vec<String> = lines
+            .into_iter()
+            .map(Into::into)
+            .filter(|l| self.admits(l))
+            .collect();
+        if let Some(tail) = self.tail
+            && kept.len() > tail
+        {
+            kept.drain(..kept.len() - tail);
+        }
+        kept.into_iter().map(|l| self.render(l)).collect()
+    }
+}
+
+/// When a container's log file is rotated, and what is kept of the old ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogRotation {
+    /// Size in bytes past which the active file is rotated.
+    pub max_bytes: u64,
+    /// Rotated files kept next to the active one (`stdout.log.1` is the
+    /// newest); older ones are deleted.
+    pub max_files: usize,
+    /// Gzip rotated files (`stdout.log.1.gz`).
+    pub compress: bool,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self {
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            compress: false,
+        }
+    }
+}
+
+/// Path of the `n`th file rotated out of `path` (1 is the newest).
+pub fn rotated_log_path(path: &Path, n: usize, compressed: bool) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    if compressed {
+        name.push(".gz");
+    }
+    PathBuf::from(name)
+}
+
+/// The files rotated out of `path`, newest first, compressed or not.
+pub fn rotated_log_files(path: &Path) -> Vec<PathBuf> {
+    (1..)
+        .map_while(|n| {
+            [false, true]
+                .into_iter()
+                .map(|compressed| rotated_log_path(path, n, compressed))
+                .find(|p| p.exists())
+        })
+        .collect()
+}
+
+/// Appends lines to a log file, rotating it once it would grow past
+/// `max_bytes`.
+pub struct RotatingLogWriter {
+    path: PathBuf,
+    rotation: LogRotation,
+    file: tokio::fs::File,
+    size: u64,
+}
+
+impl RotatingLogWriter {
+    /// Open `path` for appending, creating it if needed.
+    pub async fn open(path: PathBuf, rotation: LogRotation) -> std::io::Result<Self> {
+        let file = open_append(&path).await?;
+        let size = file.metadata().await?.len();
+        Ok(Self {
+            path,
+            rotation,
+            file,
+            size,
+        })
+    }
+
+    /// Append one newline-terminated line. A line that does not fit in what
+    /// is left of the file goes to a fresh one; a line larger than
+    /// `max_bytes` is still written whole.
+    pub async fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.rotation.max_bytes {
+            self.rotate().await?;
+        }
+        self.file.write_all(line).await?;
+        self.file.flush().await?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        let (path, rotation) = (self.path.clone(), self.rotation);
+        tokio::task::spawn_blocking(move || rotate_log_files(&path, rotation))
+            .await
+            .map_err(std::io::Error::other)??;
+        self.file = open_append(&self.path).await?;
+        self.size = self.file.metadata().await?.len();
+        Ok(())
+    }
+}
+
+async fn open_append(path: &Path) -> std::io::Result<tokio::fs::File> {
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+}
+
+/// Move `path` to `path.1`, shifting older rotated files up by one and
+/// deleting those past `max_files`.
+fn rotate_log_files(path: &Path, rotation: LogRotation) -> std::io::Result<()> {
+    if rotation.max_files == 0 {
+        return remove_if_exists(path);
+    }
+    for compressed in [false, true] {
+        remove_if_exists(&rotated_log_path(path, rotation.max_files, compressed))?;
+    }
+    for n in (1..rotation.max_files).rev() {
+        for compressed in [false, true] {
+            match std::fs::rename(
+                rotated_log_path(path, n, compressed),
+                rotated_log_path(path, n + 1, compressed),
+            ) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+    }
+    let newest = rotated_log_path(path, 1, false);
+    std::fs::rename(path, &newest)?;
+    if rotation.compress {
+        gzip_log_file(&newest)?;
+    }
+    Ok(())
+}
+
+/// Replace `path` with `path.gz`. The compressed file only appears once it
+/// is complete, so a reader always finds one of the two whole.
+fn gzip_log_file(path: &Path) -> std::io::Result<()> {
+    let mut gz = path.as_os_str().to_owned();
+    gz.push(".gz");
+    let gz = PathBuf::from(gz);
+    let mut tmp = gz.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut input = std::fs::File::open(path)?;
+    let mut encoder = GzEncoder::new(std::fs::File::create(&tmp)?, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    std::fs::rename(&tmp, &gz)?;
+    std::fs::remove_file(path)
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Copy a container's output into `path`, prefixing every line with the time
+/// it was read and rotating the file per `rotation`. Runs until `reader`
+/// reaches EOF or the task is aborted.
+pub fn spawn_log_relay<R>(
+    reader: R,
+    path: PathBuf,
+    rotation: LogRotation,
+) -> tokio::task::JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut writer = match RotatingLogWriter::open(path.clone(), rotation).await {
+            Ok(w) => w,
+            Err(e) => {
+                tracing::warn!("log relay: cannot open {}: {}", path.display(), e);
+                return;
+            }
+        };
+
+        let mut reader = tokio::io::BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf).await {
+                Ok(0) => return,
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::debug!("log relay for {} stopped: {}", path.display(), e);
+                    return;
+                }
+            }
+            if buf.last() == Some(&b'\n') {
+                buf.pop();
+            }
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+            let mut line = format_log_line(Utc::now(), &String::from_utf8_lossy(&buf));
+            line.push('\n');
+            if let Err(e) = writer.write_line(line.as_bytes()).await {
+                tracing::warn!("log relay: write to {} failed: {}", path.display(), e);
+                return;
+            }
+        }
+    })
+}
+
+/// Block size of [`ReverseLines`] reads.
+const REVERSE_READ_BLOCK: usize = 64 * 1024;
+
+/// The lines of a file before offset `end`, last first. Reads backwards a
+/// block at a time, so only the lines taken so far are held in memory.
+struct ReverseLines {
+    file: std::fs::File,
+    /// Start of the part of the file not read yet.
+    pos: u64,
+    /// Bytes read but not returned: the end of the line before `pos`.
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl ReverseLines {
+    fn new(mut file: std::fs::File, end: u64) -> std::io::Result<Self> {
+        // The newline ending the last line does not start an empty one.
+        let mut pos = end;
+        if end > 0 {
+            let mut last = [0u8];
+            file.seek(SeekFrom::Start(end - 1))?;
+            file.read_exact(&mut last)?;
+            if last[0] == b'\n' {
+                pos -= 1;
+            }
+        }
+        Ok(Self {
+            file,
+            pos,
+            buf: Vec::new(),
+            done: end == 0,
+        })
+    }
+}
+
+impl Iterator for ReverseLines {
+    type Item = std::io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(i) = self.buf.iter().rposition(|b| *b == b'\n') {
+                let line = self.buf.split_off(i + 1);
+                self.buf.truncate(i);
+                return Some(Ok(line_string(line)));
+            }
+            if self.pos == 0 {
+                if self.done {
+                    return None;
+                }
+                self.done = true;
+                return Some(Ok(line_string(std::mem::take(&mut self.buf))));
+            }
+            let n = self.pos.min(REVERSE_READ_BLOCK as u64);
+            self.pos -= n;
+            let mut block = vec![0u8; n as usize];
+            if let Err(e) = self
+                .file
+                .seek(SeekFrom::Start(self.pos))
+                .and_then(|_| self.file.read_exact(&mut block))
+            {
+                self.done = true;
+                return Some(Err(e));
+            }
+            block.append(&mut self.buf);
+            self.buf = block;
+        }
+    }
+}
+
+fn line_string(mut line: Vec<u8>) -> String {
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8_lossy(&line).into_owned()
+}
+
+/// The lines of a gzipped rotated file, last first. It is decompressed
+/// whole, which rotation bounds to about `max_bytes`.
+fn gzip_lines_rev(
+    file: std::fs::File,
+) -> std::io::Result<impl Iterator<Item = std::io::Result<String>>> {
+    let mut data = Vec::new();
+    GzDecoder::new(file).read_to_end(&mut data)?;
+    if data.last() == Some(&b'\n') {
+        data.pop();
+    }
+    let lines: Vec<_> = if data.is_empty() {
+        Vec::new()
+    } else {
+        data.split(|b| *b == b'\n')
+            .map(|line| Ok(line_string(line.to_vec())))
+            .collect()
+    };
+    Ok(lines.into_iter().rev())
+}
+
+/// Read back a log file and the files rotated out of it, filtered by
+/// `opts`, in write order.
+///
+/// Files are read from the end, so a `tail` only costs the lines it
+/// returns plus one block.
+pub async fn read_log_file(path: PathBuf, opts: LogOptions) -> std::io::Result<Vec<String>> {
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&path)?;
+        let end = file.metadata()?.len();
+        read_backlog(&path, &file, end, &opts)
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// The lines of `active` (the file open at `path`) before `end` and of the
+/// files rotated out of it, filtered by `opts`, in write order.
+fn read_backlog(
+    path: &Path,
+    active: &std::fs::File,
+    end: u64,
+    opts: &LogOptions,
+) -> std::io::Result<Vec<String>> {
+    let limit = opts.tail.unwrap_or(usize::MAX);
+    let mut kept = Vec::new();
+    let mut full = limit == 0
+        || take_lines(
+            ReverseLines::new(active.try_clone()?, end)?,
+            opts,
+            limit,
+            &mut kept,
+        )?;
+
+    let active = active.metadata()?;
+    for rotated in rotated_log_files(path) {
+        if full {
+            break;
+        }
+        let file = match std::fs::File::open(&rotated) {
+            Ok(file) => file,
+            // Rotated away meanwhile; what follows is older still.
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e),
+        };
+        full = if rotated.extension().is_some_and(|ext| ext == "gz") {
+            take_lines(gzip_lines_rev(file)?, opts, limit, &mut kept)?
+        } else if same_file(&file.metadata()?, &active) {
+            // The active file, rotated since it was opened.
+            continue;
+        } else {
+            let end = file.metadata()?.len();
+            take_lines(ReverseLines::new(file, end)?, opts, limit, &mut kept)?
+        };
+    }
+
+    kept.reverse();
+    Ok(kept.into_iter().map(|line| opts.render(line)).collect())
+}
+
+/// Move the lines `opts` admits from `lines` (last first) to `kept` until it
+/// holds `limit`. Returns whether it does.
+fn take_lines(
+    lines: impl Iterator<Item = std::io::Result<String>>,
+    opts: &LogOptions,
+    limit: usize,
+    kept: &mut Vec<String>,
+) -> std::io::Result<bool> {
+    for line in lines {
+        let line = line?;
+        if opts.admits(&line) {
+            kept.push(line);
+            if kept.len() >= limit {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+fn same_file(a: &std::fs::Metadata, b: &std::fs::Metadata) -> bool {
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// Offset just past the last newline before `end`, where following the
+/// file starts: a line still being written is not part of the backlog.
+fn complete_lines_end(file: &mut std::fs::File, end: u64) -> std::io::Result<u64> {
+    let mut pos = end;
+    let mut block = vec![0u8; REVERSE_READ_BLOCK];
+    while pos > 0 {
+        let n = pos.min(REVERSE_READ_BLOCK as u64) as usize;
+        pos -= n as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut block[..n])?;
+        if let Some(i) = block[..n].iter().rposition(|b| *b == b'\n') {
+            return Ok(pos + i as u64 + 1);
+        }
+    }
+    Ok(0)
+}
+
+/// Stream the lines of a log file.
+///
+/// Emits the existing content first, the files rotated out of it included,
+/// filtered by `opts.since` and `opts.tail`. With `follow`, the open file
+/// is then polled and newly appended lines are emitted as they become
+/// complete (still subject to `since`). When the file is rotated the rest
+/// of the old one is read before moving on to the new one; a truncated
+/// file is re-read from the start. The stream ends when the consumer is
+/// dropped. Without `follow`, the stream ends at EOF (a trailing line
+/// without a newline is still emitted).
+pub fn tail_log_file(path: PathBuf, follow: bool, opts: LogOptions) -> LogStream {
+    tail_log_file_with_interval(
+        path,
+        follow,
+        opts,
+        Duration::from_millis(pkg_constants::timings::LOG_FOLLOW_POLL_MS),
+    )
+}
+
+fn tail_log_file_with_interval(
+    path: PathBuf,
+    follow: bool,
+    opts: LogOptions,
+    poll: Duration,
+) -> LogStream {
+    let (tx, rx) = tokio::sync::mpsc::channel::<String>(LOG_STREAM_BUFFER);
+
+    tokio::spawn(async move {
+        let backlog_path = path.clone();
+        let backlog_opts = opts.clone();
+        let opened = tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(&backlog_path)?;
+            let len = file.metadata()?.len();
+            let end = if follow {
+                complete_lines_end(&mut file, len)?
+            } else {
+                len
+            };
+            let lines = read_backlog(&backlog_path, &file, end, &backlog_opts)?;
+            Ok::<_, std::io::Error>((file, end, lines))
+        })
+        .await
+        .map_err(std::io::Error::other)
+        .and_then(|opened| opened);
+
+        let (mut file, mut offset) = match opened {
+            Ok((file, end, lines)) => {
+                for line in lines {
+                    if tx.send(line).await.is_err() {
+                        return;
+                    }
+                }
+                if !follow {
+                    return;
+                }
+                (tokio::fs::File::from_std(file), end)
+            }
+            // The file may not exist yet while the container is starting:
+            // all it gets is new output.
+            Err(e) if follow && e.kind() == std::io::ErrorKind::NotFound => loop {
+                if tx.is_closed() {
+                    return;
+                }
+                tokio::time::sleep(poll).await;
+                if let Ok(file) = tokio::fs::File::open(&path).await {
+                    break (file, 0);
+                }
+            },
+            Err(e) => {
+                tracing::debug!("log tail of {} stopped: {}", path.display(), e);
+                return;
+            }
+        };
+
+        // `tail` only limits the backlog present when the stream opened.
+        let opts = LogOptions { tail: None, ..opts };
+        let mut partial: Vec<u8> = Vec::new();
+        loop {
+            if tx.is_closed() {
+                return;
+            }
+            tokio::time::sleep(poll).await;
+
+            let mut lines = match read_appended(&mut file, &mut offset, &mut partial).await {
+                Ok(lines) => lines,
+                Err(e) => {
+                    tracing::debug!("log tail of {} stopped: {}", path.display(), e);
+                    return;
+                }
+            };
+            if let Some(next) = rotated_to(&path, &file).await {
+                // Nothing more is written to the old file once it is
+                // renamed, so what is left of it is read now.
+                if let Ok(rest) = read_appended(&mut file, &mut offset, &mut partial).await {
+                    lines.extend(rest);
+                }
+                if !partial.is_empty() {
+                    lines.push(line_string(std::mem::take(&mut partial)));
+                }
+                file = next;
+                offset = 0;
+            }
+            for line in opts.apply(lines) {
+                if tx.send(line).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    Box::pin(ReceiverStream::new(rx))
+}
+
+/// The file now at `path` if it is no longer `file`, i.e. `file` was
+/// rotated out. `None` while nothing is there yet.
+async fn rotated_to(path: &Path, file: &tokio::fs::File) -> Option<tokio::fs::File> {
+    let current = tokio::fs::metadata(path).await.ok()?;
+    let open = file.metadata().await.ok()?;
+    if same_file(&current, &open) {
+        return None;
+    }
+    tokio::fs::File::open(path).await.ok()
+}
+
+/// Read bytes appended to `file` since `offset` and return the complete
+/// lines. Bytes after the last newline are kept in `partial` for the next
+/// call.
+async fn read_appended(
+    file: &mut tokio::fs::File,
+    offset: &mut u64,
+    partial: &mut Vec<u8>,
+) -> std::io::Result<Vec<String>> {
+    let len = file.metadata().await?.len();
+
+    if len < *offset {
+        // Truncated — start over.
+        *offset = 0;
+        partial.clear();
+    }
+    if len == *offset {
+        return Ok(Vec::new());
+    }
+
+    file.seek(SeekFrom::Start(*offset)).await?;
+    let mut buf = Vec::with_capacity((len - *offset) as usize);
+    let read = (&mut *file)
+        .take(len - *offset)
+        .read_to_end(&mut buf)
+        .await?;
+    *offset += read as u64;
+    partial.extend_from_slice(&buf);
+
+    let mut lines = Vec::new();
+    while let Some(pos) = partial.iter().position(|b| *b == b'\n') {
+        let mut line: Vec<u8> = partial.drain(..=pos).collect();
+        line.pop();
+        lines.push(line_string(line));
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tokio_stream::StreamExt;
+
+    fn temp_log(label: &str) -> PathBuf {
+        crate::test_util::temp_dir(label).join("stdout.log")
+    }
+
+    fn append(path: &PathBuf, data: &str) {
+        let mut f = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        f.write_all(data.as_bytes()).unwrap();
+    }
+
+    async fn next_line(stream: &mut LogStream) -> Option<String> {
+        tokio::time::timeout(Duration::from_secs(5), stream.next())
+            .await
+            .expect("timed out waiting for log line")
+    }
+
+    #[tokio::test]
+    async fn test_tail_without_follow_reads_to_eof() {
+        let path = temp_log("nofollow");
+        append(&path, "one\ntwo\r\nthree");
+
+        let lines: Vec<String> = tail_log_file(path, false, LogOptions::default())
+            .collect()
+            .await;
+        assert_eq!(lines, vec!["one", "two", "three"]);
+    }
+
+    #[tokio::test]
+    async fn test_follow_yields_appended_lines() {
+        let path = temp_log("follow");
+        append(&path, "first\n");
+
+        let mut stream = tail_log_file_with_interval(
+            path.clone(),
+            true,
+            LogOptions::default(),
+            Duration::from_millis(10),
+        );
+        assert_eq!(next_line(&mut stream).await.as_deref(), Some("first"));
+
+        append(&path, "second\nthi");
+        assert_eq!(next_line(&mut stream).await.as_deref(), Some("second"));
+
+        // The partial line is only emitted once its newline arrives.
+        append(&path, "rd\n");
+        assert_eq!(next_line(&mut stream).await.as_deref(), Some("third"));
+    }
+
+    #[tokio::test]
+    async fn test_follow_waits_for_missing_file_and_handles_truncation() {
+        let path = temp_log("truncate");
+        let mut stream = tail_log_file_with_interval(
+            path.clone(),
+            true,
+            LogOptions::default(),
+            Duration::from_millis(10),
+        );
+
+        append(&path, "before rotation\n");
+        assert_eq!(
+            next_line(&mut stream).await.as_deref(),
+            Some("before rotation")
+        );
+
+        std::fs::write(&path, "").unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        append(&path, "after\n");
+        assert_eq!(next_line(&mut stream).await.as_deref(), Some("after"));
+    }
+
+    fn ts(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_format_and_parse_round_trip() {
+        let at = ts("2024-05-01T12:00:00.123456789Z");
+        let line = format_log_line(at, "hello world");
+        assert_eq!(line, "2024-05-01T12:00:00.123456789Z hello world");
+        assert_eq!(parse_log_line(&line), (Some(at), "hello world"));
+    }
+
+    #[test]
+    fn test_parse_passes_through_unprefixed_lines() {
+        assert_eq!(parse_log_line("plain output"), (None, "plain output"));
+        assert_eq!(
+            parse_log_line("not-a-date message"),
+            (None, "not-a-date message")
+        );
+        assert_eq!(parse_log_line(""), (None, ""));
+    }
+
+    #[test]
+    fn test_apply_strips_timestamps_by_default() {
+        let lines = vec![
+            "legacy line".to_string(),
+            format_log_line(ts("2024-05-01T12:00:00Z"), "new line"),
+        ];
+        assert_eq!(
+            LogOptions::default().apply(lines.clone()),
+            vec!["legacy line", "new line"]
+        );
+
+        let keep = LogOptions {
+            timestamps: true,
+            ..Default::default()
+        };
+        assert_eq!(keep.apply(lines.clone()), lines);
+    }
+
+    #[test]
+    fn test_apply_since_treats_unprefixed_as_epoch() {
+        let lines = vec![
+            "legacy line".to_string(),
+            format_log_line(ts("2024-05-01T11:00:00Z"), "old"),
+            format_log_line(ts("2024-05-01T12:00:00Z"), "boundary"),
+            format_log_line(ts("2024-05-01T13:00:00Z"), "recent"),
+        ];
+        let opts = LogOptions {
+            since: Some(ts("2024-05-01T12:00:00Z")),
+            ..Default::default()
+        };
+        assert_eq!(opts.apply(lines.clone()), vec!["boundary", "recent"]);
+
+        let epoch = LogOptions {
+            since: Some(DateTime::UNIX_EPOCH),
+            ..Default::default()
+        };
+        assert_eq!(epoch.apply(lines).len(), 4);
+    }
+
+    #[test]
+    fn test_apply_tail_after_since() {
+        let lines: Vec<String> = (0..5)
+            .map(|i| format_log_line(ts(&format!("2024-05-01T12:00:0{}Z", i)), &i.to_string()))
+            .collect();
+        let opts = LogOptions {
+            since: Some(ts("2024-05-01T12:00:01Z")),
+            tail: Some(2),
+            timestamps: false,
+        };
+        assert_eq!(opts.apply(lines.clone()), vec!["3", "4"]);
+
+        let all = LogOptions {
+            tail: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(all.apply(lines).len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_relay_prefixes_each_line() {
+        let path = temp_log("relay");
+        let before = Utc::now();
+        spawn_log_relay(
+            &b"alpha\r\nbeta\ngamma"[..],
+            path.clone(),
+            LogRotation::default(),
+        )
+        .await
+        .unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let parsed: Vec<(Option<DateTime<Utc>>, &str)> =
+            content.lines().map(parse_log_line).collect();
+        let messages: Vec<&str> = parsed.iter().map(|(_, m)| *m).collect();
+        assert_eq!(messages, vec!["alpha", "beta", "gamma"]);
+        assert!(parsed.iter().all(|(t, _)| t.is_some_and(|t| t >= before)));
+    }
+
+    #[tokio::test]
+    async fn test_tail_applies_options_to_backlog() {
+        let path = temp_log("options");
+        append(&path, "legacy\n");
+        for i in 0..3 {
+            append(
+                &path,
+                &format!("{}\n", format_log_line(Utc::now(), &format!("line {}", i))),
+            );
+        }
+
+        let opts = LogOptions {
+            tail: Some(2),
+            ..Default::default()
+        };
+        let lines: Vec<String> = tail_log_file(path.clone(), false, opts).collect().await;
+        assert_eq!(lines, vec!["line 1", "line 2"]);
+
+        let opts = LogOptions {
+            since: Some(Utc::now() - chrono::Duration::hours(1)),
+            timestamps: true,
+            ..Default::default()
+        };
+        let lines: Vec<String> = tail_log_file(path, false, opts).collect().await;
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|l| parse_log_line(l).0.is_some()));
+    }
+
+    fn rotation(max_bytes: u64, max_files: usize, compress: bool) -> LogRotation {
+        LogRotation {
+            max_bytes,
+            max_files,
+            compress,
+        }
+    }
+
+    async fn write_lines(writer: &mut RotatingLogWriter, lines: std::ops::Range<usize>) {
+        for i in lines {
+            writer
+                .write_line(format!("line {:04}\n", i).as_bytes())
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writer_rotates_at_size_boundary() {
+        let path = temp_log("rotate-boundary");
+        // Each line is 10 bytes: ten of them fill the file exactly.
+        let mut writer = RotatingLogWriter::open(path.clone(), rotation(100, 2, false))
+            .await
+            .unwrap();
+        write_lines(&mut writer, 0..10).await;
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 100);
+        assert!(rotated_log_files(&path).is_empty());
+
+        write_lines(&mut writer, 10..11).await;
+        let rotated = rotated_log_files(&path);
+        assert_eq!(rotated, vec![rotated_log_path(&path, 1, false)]);
+        assert_eq!(std::fs::metadata(&rotated[0]).unwrap().len(), 100);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line 0010\n");
+
+        // Only `max_files` rotated files are kept: the oldest lines go.
+        write_lines(&mut writer, 11..40).await;
+        assert_eq!(
+            rotated_log_files(&path),
+            vec![
+                rotated_log_path(&path, 1, false),
+                rotated_log_path(&path, 2, false)
+            ]
+        );
+        let oldest = std::fs::read_to_string(rotated_log_path(&path, 2, false)).unwrap();
+        assert!(oldest.starts_with("line 0010\n"), "{}", oldest);
+
+        // A line larger than the limit is written whole to a fresh file.
+        let long = format!("{}\n", "x".repeat(150));
+        writer.write_line(long.as_bytes()).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), long);
+    }
+
+    #[tokio::test]
+    async fn test_writer_resumes_size_of_existing_file() {
+        let path = temp_log("rotate-reopen");
+        append(&path, &"a".repeat(95));
+        let mut writer = RotatingLogWriter::open(path.clone(), rotation(100, 1, false))
+            .await
+            .unwrap();
+        write_lines(&mut writer, 0..1).await;
+        assert_eq!(rotated_log_files(&path).len(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line 0000\n");
+    }
+
+    #[tokio::test]
+    async fn test_tail_spans_rotation_point() {
+        for compress in [false, true] {
+            let path = temp_log(&format!("rotate-tail-{}", compress));
+            let mut writer = RotatingLogWriter::open(path.clone(), rotation(100, 3, compress))
+                .await
+                .unwrap();
+            write_lines(&mut writer, 0..25).await;
+            // line 20..24 are active, 10..19 in .1, 0..9 in .2
+            let rotated = rotated_log_files(&path);
+            assert_eq!(rotated.len(), 2);
+            assert_eq!(
+                rotated[0].extension().is_some_and(|ext| ext == "gz"),
+                compress
+            );
+
+            let opts = LogOptions {
+                tail: Some(8),
+                ..Default::default()
+            };
+            let lines = read_log_file(path.clone(), opts.clone()).await.unwrap();
+            let expected: Vec<String> = (17..25).map(|i| format!("line {:04}", i)).collect();
+            assert_eq!(lines, expected);
+
+            // The stream's backlog reads the same lines.
+            let streamed: Vec<String> = tail_log_file(path.clone(), false, opts).collect().await;
+            assert_eq!(streamed, expected);
+
+            let all = read_log_file(path, LogOptions::default()).await.unwrap();
+            assert_eq!(all.len(), 25);
+            assert_eq!(all[0], "line 0000");
+        }
+    }
+
+    #[test]
+    fn test_reverse_reader_on_multi_mb_file() {
+        let path = temp_log("reverse-large");
+        let mut content = String::new();
+        for i in 0..200_000 {
+            content.push_str(&format!("entry {:06} {}\n", i, "-".repeat(i % 40)));
+        }
+        // A line longer than a read block, and a last line without a newline.
+        content.push_str(&"y".repeat(3 * REVERSE_READ_BLOCK / 2));
+        content.push_str("\r\nlast");
+        std::fs::write(&path, &content).unwrap();
+        assert!(content.len() > 5 * 1024 * 1024);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let end = file.metadata().unwrap().len();
+        let lines: Vec<String> = ReverseLines::new(file, end)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let mut expected: Vec<&str> = content.lines().collect();
+        expected.reverse();
+        assert_eq!(lines.len(), expected.len());
+        assert_eq!(lines, expected);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let tail = read_backlog(
+            &path,
+            &file,
+            end,
+            &LogOptions {
+                tail: Some(3),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            tail[0],
+            format!("entry 199999 {}", "-".repeat(199_999 % 40))
+        );
+        assert_eq!(tail[2], "last");
+
+        let mut file = std::fs::File::open(&path).unwrap();
+        assert_eq!(
+            complete_lines_end(&mut file, end).unwrap(),
+            end - "last".len() as u64
+        );
+    }
+
+    #[test]
+    fn test_reverse_reader_edge_cases() {
+        let path = temp_log("reverse-edges");
+        let read = |content: &str| -> Vec<String> {
+            std::fs::write(&path, content).unwrap();
+            let file = std::fs::File::open(&path).unwrap();
+            ReverseLines::new(file, content.len() as u64)
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+        assert!(read("").is_empty());
+        assert_eq!(read("\n"), vec![""]);
+        assert_eq!(read("a\n\nb"), vec!["b", "", "a"]);
+        assert_eq!(read("a\r\nb\n"), vec!["b", "a"]);
+    }
+
+    #[tokio::test]
+    async fn test_follow_survives_rotation() {
+        let path = temp_log("rotate-follow");
+        let mut writer = RotatingLogWriter::open(path.clone(), rotation(100, 1, true))
+            .await
+            .unwrap();
+        write_lines(&mut writer, 0..3).await;
+
+        let mut stream = tail_log_file_with_interval(
+            path.clone(),
+            true,
+            LogOptions {
+                tail: Some(1),
+                ..Default::default()
+            },
+            Duration::from_millis(10),
+        );
+        assert_eq!(next_line(&mut stream).await.as_deref(), Some("line 0002"));
+
+        // Each batch is written in one go, so its rotation happens between
+        // two polls: the end of the old file still comes before the new one.
+        for batch in [3..12, 12..25] {
+            write_lines(&mut writer, batch.clone()).await;
+            for i in batch {
+                assert_eq!(next_line(&mut stream).await, Some(format!("line {:04}", i)));
+            }
+        }
+        assert_eq!(rotated_log_files(&path).len(), 1);
+    }
+}
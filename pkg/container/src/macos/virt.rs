@@ -38,11 +38,13 @@ use std::collections::HashMap;
 use std::os::unix::io::OwnedFd;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
-use crate::backend::RuntimeBackend;
+use crate::backend::{ExecResult, RuntimeBackend};
 use crate::kernel::KernelManager;
-use crate::state::ContainerStateInfo;
+use crate::logs::LogOptions;
+use crate::state::{ContainerStateInfo, VmUsage};
 
 use pkg_constants::paths::DATA_DIR;
 
@@ -51,24 +53,10 @@ const GUEST_INIT_PATH: &str = pkg_constants::vm::GUEST_INIT_PATH;
 /// Standard config.json path inside guest rootfs (read by k3rs-init).
 const GUEST_CONFIG_PATH: &str = pkg_constants::vm::GUEST_CONFIG_PATH;
 use pkg_constants::runtime::{DEFAULT_CPU_COUNT, DEFAULT_MEMORY_MB};
+use pkg_types::pod::ResourceRequirements;
 
-/// Per-VM resource configuration.
-#[derive(Debug, Clone)]
-pub struct VmConfig {
-    /// Number of vCPUs
-    pub cpu_count: u32,
-    /// Memory in megabytes
-    pub memory_mb: u64,
-}
-
-impl Default for VmConfig {
-    fn default() -> Self {
-        Self {
-            cpu_count: DEFAULT_CPU_COUNT,
-            memory_mb: DEFAULT_MEMORY_MB,
-        }
-    }
-}
+/// Re-export VmConfig from shared vm_utils.
+pub use crate::vm_utils::VmConfig;
 
 /// VM instance state tracking.
 #[derive(Debug, Clone)]
@@ -79,6 +67,9 @@ struct VmInstance {
     vmm_pid: Option<u32>,
     state: VmState,
     log_path: PathBuf,
+    /// vCPUs and memory the VM boots with; unknown for VMs re-adopted
+    /// after an agent restart
+    vm_config: Option<VmConfig>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -102,7 +93,7 @@ pub struct VirtualizationBackend {
     kernel_path: PathBuf,
     /// Path to the initrd image (k3rs-init runs as PID 1 from initrd)
     initrd_path: Option<PathBuf>,
-    /// Per-VM resource configuration
+    /// Sizing for VMs created without resource limits
     vm_config: VmConfig,
     /// Active VM instances (in-memory, repopulated on discovery)
     instances: Arc<RwLock<HashMap<String, VmInstance>>>,
@@ -181,10 +172,17 @@ impl VirtualizationBackend {
         Ok(backend)
     }
 
+    /// The container's output, forwarded by k3rs-init over vsock and written
+    /// by k3rs-vmm in the OCI backend's log format.
     fn log_path(&self, id: &str) -> PathBuf {
         self.data_dir.join(format!("{}.log", id))
     }
 
+    /// Kernel, k3rs-init and k3rs-vmm messages, for debugging a VM.
+    fn console_log_path(&self, id: &str) -> PathBuf {
+        self.data_dir.join(format!("{}.console.log", id))
+    }
+
     fn rootfs_dir(&self, id: &str) -> PathBuf {
         self.data_dir.join(format!("{}-rootfs", id))
     }
@@ -207,13 +205,16 @@ impl VirtualizationBackend {
     ///  1. Ensuring required guest directories exist.
     ///  2. Injecting `k3rs-init` as `/sbin/k3rs-init` in the rootfs (avoids
     ///     overwriting the container's own `/sbin/k3rs-init`).
-    ///  3. Writing `/config.json` (read by k3rs-init to find the entrypoint).
+    ///  3. Writing `/config.json` (read by k3rs-init to find the entrypoint,
+    ///     and the user, rlimits and noNewPrivileges in `privileges`), with a
+    ///     `network` section asking for DHCP on eth0.
     async fn prepare_rootfs(
         &self,
         rootfs: &Path,
         id: &str,
         command: &[String],
         env: &[String],
+        privileges: &serde_json::Map<String, serde_json::Value>,
     ) -> Result<()> {
         // ── 1. Required guest directories ─────────────────────────────────────
         for dir in &[
@@ -289,14 +290,23 @@ impl VirtualizationBackend {
             command.iter().map(|s| s.as_str()).collect()
         };
 
+        let mut process = serde_json::json!({
+            "args": args,
+            "env": all_env,
+            "cwd": "/"
+        });
+        if let Some(process) = process.as_object_mut() {
+            process.extend(privileges.clone());
+        }
+        // eth0 leases from the NAT DHCP server, and k3rs-init writes the
+        // lease's DNS servers to /etc/resolv.conf unless the runtime already
+        // put the cluster one there. VPC boot params on the kernel cmdline
+        // take precedence.
         let config = serde_json::json!({
             "ociVersion": "1.0.0",
-            "process": {
-                "args": args,
-                "env": all_env,
-                "cwd": "/"
-            },
-            "hostname": id
+            "process": process,
+            "hostname": id,
+            "network": { "dhcp": true }
         });
 
         tokio::fs::write(&config_dest, serde_json::to_string_pretty(&config)?)
@@ -305,12 +315,6 @@ impl VirtualizationBackend {
 
         tracing::debug!("[virt] config.json written to {}", config_dest.display());
 
-        // ── 4. Write /etc/resolv.conf (DNS fallback) ──────────────────────
-        let resolv_dest = rootfs.join("etc/resolv.conf");
-        tokio::fs::write(&resolv_dest, "nameserver 8.8.8.8\nnameserver 8.8.4.4\n")
-            .await
-            .ok();
-
         Ok(())
     }
 
@@ -328,37 +332,18 @@ impl VirtualizationBackend {
         &self,
         id: &str,
         rootfs_dir: &Path,
+        vm_config: &VmConfig,
         vpc_config: Option<&VmNetworkConfig>,
     ) -> Result<Option<u32>> {
-        let log_path = self.log_path(id);
         let vmm = which_vmm().await.ok_or_else(|| {
             anyhow::anyhow!("k3rs-vmm not found — build with `cargo build -p k3rs-vmm --release`")
         })?;
 
-        let log_file = std::fs::File::create(&log_path)?;
+        let log_file = std::fs::File::create(self.console_log_path(id))?;
         let stderr_file = log_file.try_clone()?;
 
         let mut cmd = std::process::Command::new(&vmm);
-        let mut boot_args = vec![
-            "boot".to_string(),
-            "--kernel".to_string(),
-            self.kernel_path.to_string_lossy().to_string(),
-            "--rootfs".to_string(),
-            rootfs_dir.to_string_lossy().to_string(),
-            "--cpus".to_string(),
-            self.vm_config.cpu_count.to_string(),
-            "--memory".to_string(),
-            self.vm_config.memory_mb.to_string(),
-            "--id".to_string(),
-            id.to_string(),
-            "--log".to_string(),
-            log_path.to_string_lossy().to_string(),
-            "--foreground".to_string(),
-        ];
-        if let Some(ref initrd) = self.initrd_path {
-            boot_args.push("--initrd".to_string());
-            boot_args.push(initrd.to_string_lossy().to_string());
-        }
+        let mut boot_args = self.boot_args(id, rootfs_dir, vm_config);
 
         // Create socketpair for VPC networking (macOS only)
         #[cfg(target_os = "macos")]
@@ -489,14 +474,41 @@ impl VirtualizationBackend {
             "[virt] VM {} booted (pid={}, cpus={}, mem={}MB, rootfs={}, vpc={})",
             id,
             pid,
-            self.vm_config.cpu_count,
-            self.vm_config.memory_mb,
+            vm_config.cpu_count,
+            vm_config.memory_mb,
             rootfs_dir.display(),
             vpc_config.is_some()
         );
         Ok(Some(pid))
     }
 
+    /// `k3rs-vmm boot` arguments for a VM, before any VPC networking flags.
+    fn boot_args(&self, id: &str, rootfs_dir: &Path, vm_config: &VmConfig) -> Vec<String> {
+        let mut args = vec![
+            "boot".to_string(),
+            "--kernel".to_string(),
+            self.kernel_path.to_string_lossy().to_string(),
+            "--rootfs".to_string(),
+            rootfs_dir.to_string_lossy().to_string(),
+            "--cpus".to_string(),
+            vm_config.cpu_count.to_string(),
+            "--memory".to_string(),
+            vm_config.memory_mb.to_string(),
+            "--id".to_string(),
+            id.to_string(),
+            "--log".to_string(),
+            self.console_log_path(id).to_string_lossy().to_string(),
+            "--stdout-log".to_string(),
+            self.log_path(id).to_string_lossy().to_string(),
+            "--foreground".to_string(),
+        ];
+        if let Some(ref initrd) = self.initrd_path {
+            args.push("--initrd".to_string());
+            args.push(initrd.to_string_lossy().to_string());
+        }
+        args
+    }
+
     /// Take the host-side network socket for a VM (used by the userspace switch).
     /// Returns `None` if the VM was booted without VPC networking.
     #[cfg(target_os = "macos")]
@@ -515,40 +527,58 @@ impl VirtualizationBackend {
             .insert(id.to_string(), config);
     }
 
-    /// Stop a VM via k3rs-vmm, falling back to SIGTERM/SIGKILL.
+    /// Stop a VM via k3rs-vmm (or SIGTERM), then SIGKILL if the VMM is still
+    /// alive after `grace`.
     ///
     /// Cleans up the PID file regardless of how the stop occurred so that
     /// `restore_from_pid_files()` never re-discovers a stopped VM.
-    async fn stop_vm(&self, id: &str, pid: Option<u32>) -> Result<()> {
-        if let Some(vmm) = which_vmm().await {
-            let out = tokio::process::Command::new(&vmm)
-                .args(["stop", "--id", id])
-                .output()
-                .await;
-            if let Ok(o) = out {
-                if o.status.success() {
-                    tracing::info!("[virt] VM {} stopped via k3rs-vmm", id);
-                    let _ = std::fs::remove_file(self.pid_file_path(id));
-                    return Ok(());
+    async fn stop_vm(&self, id: &str, pid: Option<u32>, grace: Duration) -> Result<()> {
+        let term = async {
+            // `k3rs-vmm stop` signals the VMM, which asks the guest to shut down.
+            if let Some(vmm) = which_vmm().await {
+                let out = tokio::process::Command::new(&vmm)
+                    .args(["stop", "--id", id])
+                    .output()
+                    .await;
+                if let Ok(o) = out {
+                    if o.status.success() {
+                        tracing::info!("[virt] VM {} stop requested via k3rs-vmm", id);
+                        return;
+                    }
+                    tracing::warn!(
+                        "[virt] k3rs-vmm stop: {}",
+                        String::from_utf8_lossy(&o.stderr)
+                    );
                 }
-                tracing::warn!(
-                    "[virt] k3rs-vmm stop: {}",
-                    String::from_utf8_lossy(&o.stderr)
-                );
             }
-        }
+            if let Some(pid) = pid {
+                crate::stop::signal_pid(pid, libc::SIGTERM);
+            }
+        };
 
-        if let Some(pid) = pid {
-            let _ = tokio::process::Command::new("kill")
-                .args(["-TERM", &pid.to_string()])
-                .output()
+        match pid {
+            Some(pid) => {
+                let outcome = crate::stop::graceful_stop(
+                    grace,
+                    term,
+                    || std::future::ready(crate::stop::pid_alive(pid)),
+                    async { crate::stop::signal_pid(pid, libc::SIGKILL) },
+                )
                 .await;
-            tokio::time::sleep(std::time::Duration::from_secs(3)).await;
-            let _ = tokio::process::Command::new("kill")
-                .args(["-KILL", &pid.to_string()])
-                .output()
-                .await;
-            tracing::info!("[virt] VM {} killed (pid={})", id, pid);
+                match outcome {
+                    crate::stop::StopOutcome::Exited => {
+                        tracing::info!("[virt] VM {} stopped (pid={})", id, pid)
+                    }
+                    crate::stop::StopOutcome::Killed => tracing::warn!(
+                        "[virt] VM {} still running after {}s grace, killed (pid={})",
+                        id,
+                        grace.as_secs(),
+                        pid
+                    ),
+                }
+            }
+            // No PID to watch: the best we can do is ask.
+            None => term.await,
         }
 
         // Always clean up the PID file, even if stop was a no-op (VM already dead).
@@ -557,7 +587,10 @@ impl VirtualizationBackend {
     }
 
     /// Execute a command via k3rs-vmm IPC → vsock → guest k3rs-init.
-    async fn exec_via_vmm(&self, id: &str, command: &[&str]) -> Result<String> {
+    ///
+    /// `k3rs-vmm exec` exits with the command's own exit code, or
+    /// `VMM_EXEC_FAILED` when it could not run it.
+    async fn exec_via_vmm(&self, id: &str, command: &[&str]) -> Result<ExecResult> {
         let vmm = which_vmm()
             .await
             .ok_or_else(|| anyhow::anyhow!("k3rs-vmm not found"))?;
@@ -576,14 +609,15 @@ impl VirtualizationBackend {
             .await
             .context("failed to exec via k3rs-vmm")?;
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-
-        if output.status.success() {
-            Ok(stdout)
-        } else {
-            anyhow::bail!("k3rs-vmm exec failed: {}{}", stdout.trim(), stderr.trim())
+        let result = ExecResult::from_output(&output);
+        if result.exit_code == pkg_constants::vm::VMM_EXEC_FAILED {
+            anyhow::bail!(
+                "k3rs-vmm exec failed: {}{}",
+                result.stdout.trim(),
+                result.stderr.trim()
+            )
         }
+        Ok(result)
     }
 
     /// Check if a guest kernel is available.
@@ -600,8 +634,9 @@ impl VirtualizationBackend {
     ///
     /// 1. Scan all `{data_dir}/*.pid` files (written by `boot_vm()` at start).
     /// 2. Read the stored PID.
-    /// 3. Call `kill(pid, 0)` — existence-only check, **no signal sent**.
-    ///    Returns 0 if the process is alive, -1 (ESRCH) if it is gone.
+    /// 3. Call `kill(pid, 0)` — existence-only check, **no signal sent** —
+    ///    and check the process is still `k3rs-vmm boot --id <id>`, not
+    ///    something that reused the PID (`is_vmm_boot`).
     /// 4. Alive → add to `discovered` set + rebuild `VmInstance` entry.
     ///    Dead  → remove the stale PID file so it is never re-read.
     ///
@@ -639,13 +674,9 @@ impl VirtualizationBackend {
                 }
             };
 
-            // kill(pid, 0): sends no signal; returns 0 if the process exists,
-            // -1 with ESRCH if it does not.  This is the POSIX-approved way
-            // to check process liveness from the host.
-            //
-            // SAFETY: pid comes from our own PID file (written atomically by
-            // boot_vm).  Signal 0 cannot harm the target process.
-            let alive = unsafe { libc::kill(pid as libc::pid_t, 0) == 0 };
+            // kill(pid, 0) plus a look at the process's arguments, so a PID
+            // recycled by an unrelated process is not taken for the VM.
+            let alive = is_vmm_boot(pid, &vm_id);
 
             if alive {
                 discovered.insert(vm_id.clone());
@@ -666,6 +697,7 @@ impl VirtualizationBackend {
                             vmm_pid: Some(pid),
                             state: VmState::Running,
                             log_path: self.log_path(&vm_id),
+                            vm_config: None,
                         },
                     );
                 }
@@ -715,9 +747,11 @@ impl RuntimeBackend for VirtualizationBackend {
 
         // Extract entrypoint + env from the OCI bundle's config.json
         let (command, env) = parse_bundle_config(bundle);
+        let privileges = parse_bundle_privileges(bundle);
 
         // Inject k3rs-init and write /config.json into the rootfs
-        self.prepare_rootfs(&rootfs_dir, id, &command, &env).await?;
+        self.prepare_rootfs(&rootfs_dir, id, &command, &env, &privileges)
+            .await?;
 
         let log_path = self.log_path(id);
         tokio::fs::write(&log_path, "").await?;
@@ -729,6 +763,7 @@ impl RuntimeBackend for VirtualizationBackend {
                 vmm_pid: None,
                 state: VmState::Created,
                 log_path,
+                vm_config: Some(self.vm_config),
             },
         );
 
@@ -739,6 +774,31 @@ impl RuntimeBackend for VirtualizationBackend {
         Ok(())
     }
 
+    /// Create a container whose VM is sized to its limits, falling back to
+    /// the backend's default sizing for fields left at zero.
+    async fn create_with_resources(
+        &self,
+        id: &str,
+        bundle: &Path,
+        resources: &ResourceRequirements,
+    ) -> Result<()> {
+        let vm_config =
+            VmConfig::for_limits(resources, &self.vm_config, &VmConfig::node_capacity());
+        self.create(id, bundle).await?;
+        if let Some(inst) = self.instances.write().await.get_mut(id) {
+            inst.vm_config = Some(vm_config);
+        }
+        tracing::info!(
+            "[virt] VM {} sized to {} vCPU, {}MB (limits: {}m CPU, {} bytes)",
+            id,
+            vm_config.cpu_count,
+            vm_config.memory_mb,
+            resources.cpu_millis,
+            resources.memory_bytes
+        );
+        Ok(())
+    }
+
     /// Create from an image reference (direct shortcut — bypasses image pull).
     async fn create_from_image(&self, id: &str, image: &str, command: &[String]) -> Result<()> {
         tracing::info!("[virt] create_from_image: id={} image={}", id, image);
@@ -746,7 +806,8 @@ impl RuntimeBackend for VirtualizationBackend {
         let rootfs_dir = self.rootfs_dir(id);
         tokio::fs::create_dir_all(&rootfs_dir).await?;
 
-        self.prepare_rootfs(&rootfs_dir, id, command, &[]).await?;
+        self.prepare_rootfs(&rootfs_dir, id, command, &[], &serde_json::Map::new())
+            .await?;
 
         let log_path = self.log_path(id);
         tokio::fs::write(
@@ -762,6 +823,7 @@ impl RuntimeBackend for VirtualizationBackend {
                 vmm_pid: None,
                 state: VmState::Created,
                 log_path,
+                vm_config: Some(self.vm_config),
             },
         );
         Ok(())
@@ -777,13 +839,15 @@ impl RuntimeBackend for VirtualizationBackend {
             );
         }
 
-        let rootfs_dir = {
+        let (rootfs_dir, vm_config) = {
             let instances = self.instances.read().await;
-            instances
+            let inst = instances
                 .get(id)
-                .ok_or_else(|| anyhow::anyhow!("VM {} not found — call create() first", id))?
-                .rootfs_dir
-                .clone()
+                .ok_or_else(|| anyhow::anyhow!("VM {} not found — call create() first", id))?;
+            (
+                inst.rootfs_dir.clone(),
+                inst.vm_config.unwrap_or(self.vm_config),
+            )
         };
 
         let boot_start = std::time::Instant::now();
@@ -792,7 +856,9 @@ impl RuntimeBackend for VirtualizationBackend {
         let vpc_config = self.pending_vpc_configs.write().await.remove(id);
         #[cfg(not(target_os = "macos"))]
         let vpc_config: Option<VmNetworkConfig> = None;
-        let pid = self.boot_vm(id, &rootfs_dir, vpc_config.as_ref()).await?;
+        let pid = self
+            .boot_vm(id, &rootfs_dir, &vm_config, vpc_config.as_ref())
+            .await?;
         let boot_elapsed = boot_start.elapsed();
 
         tracing::info!(
@@ -819,15 +885,15 @@ impl RuntimeBackend for VirtualizationBackend {
         Ok(())
     }
 
-    async fn stop(&self, id: &str) -> Result<()> {
-        tracing::info!("[virt] stop VM: {}", id);
+    async fn stop(&self, id: &str, grace: Duration) -> Result<()> {
+        tracing::info!("[virt] stop VM: {} (grace={}s)", id, grace.as_secs());
 
         let pid = {
             let instances = self.instances.read().await;
             instances.get(id).and_then(|i| i.vmm_pid)
         };
 
-        self.stop_vm(id, pid).await?;
+        self.stop_vm(id, pid, grace).await?;
 
         let mut instances = self.instances.write().await;
         if let Some(inst) = instances.get_mut(id) {
@@ -841,7 +907,7 @@ impl RuntimeBackend for VirtualizationBackend {
     async fn delete(&self, id: &str) -> Result<()> {
         tracing::info!("[virt] delete VM: {}", id);
 
-        self.stop(id).await.ok(); // stop_vm() removes the PID file
+        self.stop(id, Duration::ZERO).await.ok(); // stop_vm() removes the PID file
 
         let removed = self.instances.write().await.remove(id);
         if let Some(inst) = removed {
@@ -852,6 +918,7 @@ impl RuntimeBackend for VirtualizationBackend {
         // Best-effort cleanup for named paths (handles post-restart recovery cases)
         let _ = tokio::fs::remove_dir_all(self.rootfs_dir(id)).await;
         let _ = tokio::fs::remove_file(self.log_path(id)).await;
+        let _ = tokio::fs::remove_file(self.console_log_path(id)).await;
         // PID file: stop_vm() already removed it; this is a safety net.
         let _ = tokio::fs::remove_file(self.pid_file_path(id)).await;
 
@@ -911,6 +978,7 @@ impl RuntimeBackend for VirtualizationBackend {
                                 vmm_pid,
                                 state: VmState::Running,
                                 log_path: self.log_path(&vm_id),
+                                vm_config: None,
                             },
                         );
                     }
@@ -921,26 +989,20 @@ impl RuntimeBackend for VirtualizationBackend {
         Ok(ids.into_iter().collect())
     }
 
-    async fn logs(&self, id: &str, tail: usize) -> Result<Vec<String>> {
-        let log_path = self.log_path(id);
-        match tokio::fs::read_to_string(&log_path).await {
-            Ok(content) => {
-                let lines: Vec<String> = content
-                    .lines()
-                    .rev()
-                    .take(tail)
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>()
-                    .into_iter()
-                    .rev()
-                    .collect();
-                Ok(lines)
-            }
+    async fn logs(&self, id: &str, opts: &LogOptions) -> Result<Vec<String>> {
+        match crate::logs::read_log_file(self.log_path(id), opts.clone()).await {
+            Ok(lines) => Ok(lines),
             Err(_) => Ok(vec![format!("[virt] no logs for VM {}", id)]),
         }
     }
 
-    async fn exec(&self, id: &str, command: &[&str]) -> Result<String> {
+    /// The VMM writes the guest's virtio-console (the container's stdout and
+    /// stderr) to this file, so following it streams the guest console live.
+    fn log_file(&self, id: &str) -> Option<PathBuf> {
+        Some(self.log_path(id))
+    }
+
+    async fn exec(&self, id: &str, command: &[&str]) -> Result<ExecResult> {
         tracing::info!("[virt] exec in VM {}: {:?}", id, command);
 
         // Verify the VM is running (check in-memory OR via k3rs-vmm state)
@@ -965,7 +1027,7 @@ impl RuntimeBackend for VirtualizationBackend {
 
     /// Spawn an interactive exec session.
     ///
-    /// Spawns `k3rs-vmm exec --id <id> [--tty] -- <cmd>` as a subprocess and
+    /// Spawns `k3rs-vmm exec --id <id> --tty|--stdin -- <cmd>` as a subprocess and
     /// returns the child handle so the WebSocket exec handler gets piped I/O.
     ///
     /// When `tty=true`, `--tty` is passed to k3rs-vmm exec, which switches it
@@ -989,10 +1051,10 @@ impl RuntimeBackend for VirtualizationBackend {
             command.to_vec()
         };
 
+        // Without a PTY, `--stdin` passes the child's stdin on to the guest
+        // command, which sees EOF when it is dropped.
         let mut args: Vec<&str> = vec!["exec", "--id", id];
-        if tty {
-            args.push("--tty");
-        }
+        args.push(if tty { "--tty" } else { "--stdin" });
         args.push("--");
         args.extend_from_slice(&cmd_args);
 
@@ -1009,57 +1071,55 @@ impl RuntimeBackend for VirtualizationBackend {
 
     /// Query the runtime state of a VM.
     ///
-    /// First checks in-memory state, then falls back to `k3rs-vmm state --id`
-    /// so the agent's `discover_running_containers()` works after restarts.
+    /// Tracked VMs answer from memory, with uptime and usage from
+    /// `k3rs-vmm state --id --json` while running. Untracked ones (after an
+    /// agent restart) come entirely from it, so the agent's
+    /// `discover_running_containers()` works after restarts.
     async fn state(&self, id: &str) -> Result<ContainerStateInfo> {
         // In-memory check (fast path)
-        {
+        let tracked = {
             let instances = self.instances.read().await;
-            if let Some(inst) = instances.get(id) {
-                let status = match inst.state {
+            instances.get(id).map(|inst| ContainerStateInfo {
+                id: id.to_string(),
+                status: match inst.state {
                     VmState::Created => "created",
                     VmState::Running => "running",
                     VmState::Stopped => "stopped",
                 }
-                .to_string();
-                return Ok(ContainerStateInfo {
-                    id: id.to_string(),
-                    status,
-                    pid: inst.vmm_pid.unwrap_or(0),
-                    bundle: inst.rootfs_dir.to_string_lossy().to_string(),
-                });
+                .to_string(),
+                pid: inst.vmm_pid.unwrap_or(0),
+                bundle: inst.rootfs_dir.to_string_lossy().to_string(),
+                vcpus: inst.vm_config.map(|c| c.cpu_count),
+                memory_mb: inst.vm_config.map(|c| c.memory_mb),
+                usage: None,
+            })
+        };
+        if let Some(mut info) = tracked {
+            if info.status == "running"
+                && let Some(status) = query_vmm_state(id).await
+            {
+                info.usage = status.usage();
             }
+            return Ok(info);
         }
 
         // k3rs-vmm state query (for post-restart recovery)
-        if let Some(vmm) = which_vmm().await {
-            let out = tokio::process::Command::new(&vmm)
-                .args(["state", "--id", id])
-                .output()
-                .await;
-
-            if let Ok(o) = out {
-                let stdout = String::from_utf8_lossy(&o.stdout);
-                // Output: "state=running" or "state=not_found"
-                if let Some(val) = stdout.lines().find_map(|l| l.strip_prefix("state=")) {
-                    let status = if val.trim() == "running" {
-                        "running"
-                    } else {
-                        "stopped"
-                    };
-                    let pid = if status == "running" {
-                        vmm_pid_for(id).await
-                    } else {
-                        0
-                    };
-                    return Ok(ContainerStateInfo {
-                        id: id.to_string(),
-                        status: status.to_string(),
-                        pid,
-                        bundle: self.rootfs_dir(id).to_string_lossy().to_string(),
-                    });
-                }
-            }
+        if let Some(status) = query_vmm_state(id).await {
+            let running = status.state == "running";
+            let pid = match status.pid {
+                Some(pid) => pid,
+                None if running => vmm_boot_pid(id).unwrap_or(0),
+                None => 0,
+            };
+            return Ok(ContainerStateInfo {
+                id: id.to_string(),
+                status: if running { "running" } else { "stopped" }.to_string(),
+                pid,
+                bundle: self.rootfs_dir(id).to_string_lossy().to_string(),
+                vcpus: status.cpus,
+                memory_mb: status.memory_mb,
+                usage: status.usage(),
+            });
         }
 
         anyhow::bail!("VM {} not found (not tracked and k3rs-vmm unavailable)", id)
@@ -1178,26 +1238,55 @@ async fn ensure_vmm_entitlements(vmm_path: &str) {
 /// 2. User-local (`~/.k3rs/bin/k3rs-init`)
 /// 3. Cargo build output for aarch64 and x86_64 musl targets
 // find_k3rs_init() and parse_bundle_config() moved to crate::vm_utils
-use crate::vm_utils::{find_k3rs_init, parse_bundle_config};
+use crate::vm_utils::{
+    find_k3rs_init, is_vmm_boot, parse_bundle_config, parse_bundle_privileges, vmm_boot_pid,
+};
+
+/// One VM as printed by `k3rs-vmm state --json`. The usage fields are
+/// absent when the VM is not running or its boot process did not answer.
+#[derive(Debug, serde::Deserialize)]
+struct VmmStatus {
+    /// `running`, `stale` or `not_found`.
+    state: String,
+    pid: Option<u32>,
+    uptime_secs: Option<u64>,
+    cpu_usage_usec: Option<u64>,
+    rss_bytes: Option<u64>,
+    cpus: Option<u32>,
+    memory_mb: Option<u64>,
+}
 
-/// Get the PID of the k3rs-vmm boot process for a given VM ID.
-async fn vmm_pid_for(id: &str) -> u32 {
-    let out = tokio::process::Command::new("pgrep")
-        .args(["-f", &format!("k3rs-vmm boot.*--id {}", id)])
-        .output()
-        .await;
-
-    match out {
-        Ok(o) if o.status.success() => String::from_utf8_lossy(&o.stdout)
-            .trim()
-            .lines()
-            .next()
-            .and_then(|s| s.parse::<u32>().ok())
-            .unwrap_or(0),
-        _ => 0,
+impl VmmStatus {
+    fn usage(&self) -> Option<VmUsage> {
+        Some(VmUsage {
+            uptime_secs: self.uptime_secs?,
+            cpu_usage_usec: self.cpu_usage_usec?,
+            rss_bytes: self.rss_bytes?,
+        })
     }
 }
 
+/// Find the JSON line in `k3rs-vmm state --json` output; k3rs-vmm's own
+/// log lines share stdout with it.
+fn parse_vmm_state(stdout: &str) -> Option<VmmStatus> {
+    stdout
+        .lines()
+        .rev()
+        .find_map(|l| serde_json::from_str(l.trim()).ok())
+}
+
+/// Run `k3rs-vmm state --id <id> --json`; `None` if k3rs-vmm is unavailable
+/// or printed nothing usable.
+async fn query_vmm_state(id: &str) -> Option<VmmStatus> {
+    let vmm = which_vmm().await?;
+    let out = tokio::process::Command::new(&vmm)
+        .args(["state", "--id", id, "--json"])
+        .output()
+        .await
+        .ok()?;
+    parse_vmm_state(&String::from_utf8_lossy(&out.stdout))
+}
+
 // ─── Tests ───────────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -1213,6 +1302,87 @@ mod tests {
 
     // parse_bundle_config tests moved to vm_utils module
 
+    #[test]
+    fn test_parse_vmm_state() {
+        let out = concat!(
+            "2026-01-01T00:00:00Z INFO removed stale IPC socket\n",
+            r#"{"id":"vm-1","state":"running","pid":4242,"uptime_secs":90,"cpu_usage_usec":1500000,"rss_bytes":67108864,"cpus":2,"memory_mb":512}"#,
+            "\n"
+        );
+        let status = parse_vmm_state(out).unwrap();
+        assert_eq!(status.state, "running");
+        assert_eq!(status.pid, Some(4242));
+        assert_eq!((status.cpus, status.memory_mb), (Some(2), Some(512)));
+        assert_eq!(
+            status.usage(),
+            Some(VmUsage {
+                uptime_secs: 90,
+                cpu_usage_usec: 1_500_000,
+                rss_bytes: 64 << 20,
+            })
+        );
+
+        let status = parse_vmm_state(r#"{"id":"vm-2","state":"not_found"}"#).unwrap();
+        assert_eq!(status.state, "not_found");
+        assert_eq!(status.usage(), None);
+        assert!(parse_vmm_state("state=running\n").is_none());
+    }
+
+    #[cfg(target_os = "macos")]
+    #[tokio::test]
+    async fn test_create_with_resources_sizes_boot() {
+        let tmp = PathBuf::from("/tmp/k3rs-virt-sizing-test");
+        let _ = tokio::fs::create_dir_all(&tmp).await;
+        let backend = VirtualizationBackend::new(&tmp).await.unwrap();
+        let bundle = tmp.join("bundle");
+        tokio::fs::create_dir_all(bundle.join("rootfs"))
+            .await
+            .unwrap();
+
+        let resources = ResourceRequirements {
+            cpu_millis: 1500,
+            memory_bytes: 512 * 1024 * 1024,
+        };
+        backend
+            .create_with_resources("vm-sized", &bundle, &resources)
+            .await
+            .unwrap();
+        backend
+            .create_with_resources("vm-unsized", &bundle, &ResourceRequirements::default())
+            .await
+            .unwrap();
+
+        let state = backend.state("vm-sized").await.unwrap();
+        assert_eq!((state.vcpus, state.memory_mb), (Some(2), Some(512)));
+        let state = backend.state("vm-unsized").await.unwrap();
+        assert_eq!(
+            (state.vcpus, state.memory_mb),
+            (Some(DEFAULT_CPU_COUNT), Some(DEFAULT_MEMORY_MB))
+        );
+
+        let config = backend.instances.read().await["vm-sized"]
+            .vm_config
+            .unwrap();
+        let args = backend.boot_args("vm-sized", &bundle.join("rootfs"), &config);
+        let flag = |name: &str| {
+            let i = args.iter().position(|a| a == name).unwrap();
+            args[i + 1].clone()
+        };
+        assert_eq!(flag("--cpus"), "2");
+        assert_eq!(flag("--memory"), "512");
+        assert_eq!(flag("--id"), "vm-sized");
+        assert_eq!(
+            flag("--stdout-log"),
+            tmp.join("vm-sized.log").to_string_lossy()
+        );
+        assert_eq!(
+            flag("--log"),
+            tmp.join("vm-sized.console.log").to_string_lossy()
+        );
+
+        let _ = tokio::fs::remove_dir_all(&tmp).await;
+    }
+
     #[cfg(target_os = "macos")]
     #[tokio::test]
     async fn test_backend_new() {
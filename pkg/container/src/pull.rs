@@ -0,0 +1,107 @@
+//! Registry decisions for [`ImageManager::pull`](crate::image::ImageManager::pull):
+//! whether the pull policy lets the node use its cache, which layers changed
+//! upstream, and `image@sha256:<digest>` pinning.
+
+use anyhow::Result;
+use pkg_types::pod::ImagePullPolicy;
+
+/// Whether pulling `image_ref` under `policy` must contact the registry.
+///
+/// `Never` without a cached copy is an error: the pod cannot start until the
+/// image is loaded onto the node some other way.
+pub fn needs_fetch(image_ref: &str, policy: ImagePullPolicy, cached: bool) -> Result<bool> {
+    match policy {
+        ImagePullPolicy::IfNotPresent => Ok(!cached),
+        ImagePullPolicy::Always => Ok(true),
+        ImagePullPolicy::Never if cached => Ok(false),
+        ImagePullPolicy::Never => anyhow::bail!(
+            "Image {} is not present on the node and imagePullPolicy is Never",
+            image_ref
+        ),
+    }
+}
+
+/// The digest an `image@sha256:<digest>` reference is pinned to.
+pub fn pinned_digest(image_ref: &str) -> Option<&str> {
+    image_ref.rsplit_once('@').map(|(_, digest)| digest)
+}
+
+/// Reject a manifest whose digest differs from the one `image_ref` pins.
+/// Unpinned references accept any digest.
+pub fn verify_digest(image_ref: &str, digest: &str) -> Result<()> {
+    match pinned_digest(image_ref) {
+        Some(expected) if expected != digest => anyhow::bail!(
+            "Manifest digest mismatch for {}: expected {}, got {}",
+            image_ref,
+            expected,
+            digest
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Indices of the `fetched` layers that differ from the `cached` layer at the
+/// same position, by digest.
+pub fn changed_layers(cached: &[String], fetched: &[String]) -> Vec<usize> {
+    fetched
+        .iter()
+        .enumerate()
+        .filter(|(i, digest)| cached.get(*i) != Some(digest))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digests(ds: &[&str]) -> Vec<String> {
+        ds.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn test_if_not_present_uses_cache() {
+        assert!(needs_fetch("nginx", ImagePullPolicy::IfNotPresent, false).unwrap());
+        assert!(!needs_fetch("nginx", ImagePullPolicy::IfNotPresent, true).unwrap());
+    }
+
+    #[test]
+    fn test_always_contacts_registry() {
+        assert!(needs_fetch("nginx", ImagePullPolicy::Always, false).unwrap());
+        assert!(needs_fetch("nginx", ImagePullPolicy::Always, true).unwrap());
+    }
+
+    #[test]
+    fn test_never_requires_cached_image() {
+        assert!(!needs_fetch("nginx", ImagePullPolicy::Never, true).unwrap());
+        let err = needs_fetch("nginx", ImagePullPolicy::Never, false).unwrap_err();
+        assert!(err.to_string().contains("not present on the node"));
+    }
+
+    #[test]
+    fn test_changed_layers_compares_digests() {
+        let cached = digests(&["sha256:a", "sha256:b", "sha256:c"]);
+        assert!(changed_layers(&cached, &cached).is_empty());
+        // Top layer rebuilt, one layer added.
+        let fetched = digests(&["sha256:a", "sha256:b", "sha256:x", "sha256:y"]);
+        assert_eq!(changed_layers(&cached, &fetched), vec![2, 3]);
+        // Nothing cached: every layer is fetched.
+        assert_eq!(changed_layers(&[], &fetched), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_digest_pinning() {
+        let pinned = "registry.local/app@sha256:abc";
+        assert_eq!(pinned_digest(pinned), Some("sha256:abc"));
+        assert_eq!(pinned_digest("registry.local:5000/app:v1"), None);
+
+        verify_digest(pinned, "sha256:abc").unwrap();
+        verify_digest("registry.local/app:v1", "sha256:anything").unwrap();
+        let err = verify_digest(pinned, "sha256:def").unwrap_err();
+        assert!(err.to_string().contains("digest mismatch"));
+        assert!(
+            err.to_string()
+                .contains("expected sha256:abc, got sha256:def")
+        );
+    }
+}
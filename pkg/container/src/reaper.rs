@@ -0,0 +1,145 @@
+//! Reaping of orphaned container processes.
+//!
+//! The OCI runtime exits once a container is created, so its init would be
+//! reparented to PID 1 and its exit status lost. The agent makes itself the
+//! child subreaper once at startup ([`become_subreaper`]) so these processes
+//! are reparented to it instead. `OciBackend::wait` then claims the init of a
+//! container it waits on ([`claim`]) and collects its status; [`start`] reaps
+//! every other orphan that exits, so none are left as zombies.
+//!
+//! Only zombies from a nested PID namespace count as orphans: the agent's
+//! own children share its namespace and are reaped by whoever spawned them.
+//! An orphan is also left for one pass, so a waiter that has yet to claim a
+//! container that exited right away still gets its status.
+
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Pids whose exit status a waiter collects; never reaped here.
+static CLAIMED: LazyLock<Mutex<HashSet<u32>>> = LazyLock::new(Default::default);
+
+/// Make this process the subreaper of its descendants. Call once, at
+/// startup; it affects the whole process.
+pub fn become_subreaper() -> std::io::Result<()> {
+    #[cfg(target_os = "linux")]
+    if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Keeps `pid` away from the orphan reaper while held.
+pub struct Claim(u32);
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        CLAIMED.lock().unwrap().remove(&self.0);
+    }
+}
+
+/// Claim `pid`, a child of this process, for a waiter to reap.
+pub fn claim(pid: u32) -> Claim {
+    CLAIMED.lock().unwrap().insert(pid);
+    Claim(pid)
+}
+
+/// Reap exited orphans every
+/// [`ORPHAN_REAP_INTERVAL_SECS`](pkg_constants::runtime::ORPHAN_REAP_INTERVAL_SECS).
+pub fn start() -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(
+            pkg_constants::runtime::ORPHAN_REAP_INTERVAL_SECS,
+        ));
+        let mut seen = HashSet::new();
+        loop {
+            interval.tick().await;
+            seen = reap_orphans(&seen);
+        }
+    })
+}
+
+/// Reap the unclaimed orphans that were already zombies in `seen`, the
+/// previous pass. Returns the zombies left for the next pass.
+pub fn reap_orphans(seen: &HashSet<u32>) -> HashSet<u32> {
+    let mut pending = HashSet::new();
+    for pid in orphaned_zombies() {
+        if CLAIMED.lock().unwrap().contains(&pid) {
+            continue;
+        }
+        if !seen.contains(&pid) {
+            pending.insert(pid);
+            continue;
+        }
+        let mut status = 0;
+        if unsafe { libc::waitpid(pid as libc::pid_t, &mut status, libc::WNOHANG) } > 0 {
+            tracing::debug!("Reaped orphaned process {}", pid);
+        }
+    }
+    pending
+}
+
+/// Zombie children of this process that ran in a nested PID namespace.
+#[cfg(target_os = "linux")]
+fn orphaned_zombies() -> Vec<u32> {
+    let me = std::process::id();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| {
+            let Ok(stat) = std::fs::read_to_string(format!("/proc/{}/stat", pid)) else {
+                return false;
+            };
+            // `pid (comm) state ppid ...`; comm may itself contain spaces.
+            let mut fields = stat
+                .rsplit_once(')')
+                .map(|(_, rest)| rest.split_whitespace())
+                .into_iter()
+                .flatten();
+            fields.next() == Some("Z") && fields.next() == Some(me.to_string().as_str())
+        })
+        .filter(|pid| {
+            std::fs::read_to_string(format!("/proc/{}/status", pid))
+                .ok()
+                .and_then(|status| {
+                    let line = status.lines().find(|l| l.starts_with("NSpid:"))?;
+                    Some(line.split_whitespace().count() > 2)
+                })
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn orphaned_zombies() -> Vec<u32> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_own_children_are_left_to_their_waiter() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .spawn()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        // Two passes: enough for an unclaimed orphan to be reaped.
+        let seen = reap_orphans(&HashSet::new());
+        reap_orphans(&seen);
+        assert_eq!(child.wait().unwrap().code(), Some(3));
+    }
+
+    #[test]
+    fn test_claim_is_released_on_drop() {
+        let claim = claim(u32::MAX);
+        assert!(CLAIMED.lock().unwrap().contains(&u32::MAX));
+        drop(claim);
+        assert!(!CLAIMED.lock().unwrap().contains(&u32::MAX));
+    }
+}
@@ -0,0 +1,253 @@
+//! Registry credentials for private image pulls.
+//!
+//! Credentials come from the Secrets a pod names in `image_pull_secrets`
+//! (a `.dockerconfigjson` key holding `{"auths": {<registry>: ...}}`), with a
+//! node-wide file of the same shape as the fallback. The registry client trades
+//! them for a bearer token (Docker registry v2 token auth) on first use.
+//! Passwords never appear in `Debug` output or logs.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use pkg_types::secret::Secret;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Secret data key holding a Docker `config.json`.
+pub const DOCKER_CONFIG_KEY: &str = ".dockerconfigjson";
+
+/// Node-wide fallback credentials file, in `.dockerconfigjson` format.
+pub fn node_credentials_path() -> std::path::PathBuf {
+    Path::new(pkg_constants::paths::CONFIG_DIR).join("registry-auth.json")
+}
+
+/// A username and password for one registry.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RegistryCredentials {
+    pub username: String,
+    password: String,
+}
+
+impl RegistryCredentials {
+    pub fn new(username: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    /// `Authorization` header value presented to the token endpoint.
+    pub fn basic_header(&self) -> String {
+        let pair = format!("{}:{}", self.username, self.password);
+        format!("Basic {}", STANDARD.encode(pair))
+    }
+}
+
+impl std::fmt::Debug for RegistryCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegistryCredentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Credentials by registry host.
+#[derive(Debug, Clone, Default)]
+pub struct RegistryKeychain {
+    auths: HashMap<String, RegistryCredentials>,
+}
+
+#[derive(Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, DockerAuth>,
+}
+
+#[derive(Deserialize)]
+struct DockerAuth {
+    /// base64 of `username:password`.
+    #[serde(default)]
+    auth: Option<String>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+impl RegistryKeychain {
+    /// Parse a Docker `config.json` (`{"auths": {...}}`).
+    pub fn from_docker_config(json: &[u8]) -> Result<Self> {
+        let config: DockerConfig =
+            serde_json::from_slice(json).context("invalid docker config json")?;
+        let mut auths = HashMap::new();
+        for (registry, entry) in config.auths {
+            let creds = match (entry.auth, entry.username, entry.password) {
+                (Some(auth), _, _) => {
+                    let decoded = STANDARD
+                        .decode(auth.trim())
+                        .ok()
+                        .and_then(|b| String::from_utf8(b).ok())
+                        .with_context(|| format!("invalid auth entry for {}", registry))?;
+                    let (user, pass) = decoded
+                        .split_once(':')
+                        .with_context(|| format!("invalid auth entry for {}", registry))?;
+                    RegistryCredentials::new(user, pass)
+                }
+                (None, Some(user), Some(pass)) => RegistryCredentials::new(user, pass),
+                _ => anyhow::bail!("no credentials in auth entry for {}", registry),
+            };
+            auths.insert(normalize_registry(&registry), creds);
+        }
+        Ok(Self { auths })
+    }
+
+    /// Parse an image pull Secret. Secret values are base64-encoded.
+    pub fn from_secret(secret: &Secret) -> Result<Self> {
+        let encoded = secret
+            .data
+            .get(DOCKER_CONFIG_KEY)
+            .with_context(|| format!("secret {} has no {} key", secret.name, DOCKER_CONFIG_KEY))?;
+        let json = STANDARD
+            .decode(encoded.trim())
+            .with_context(|| format!("secret {} is not valid base64", secret.name))?;
+        Self::from_docker_config(&json)
+            .with_context(|| format!("secret {} is not a registry credential", secret.name))
+    }
+
+    /// Load a credentials file. A missing file is an empty keychain.
+    pub async fn load_file(path: &Path) -> Result<Self> {
+        match tokio::fs::read(path).await {
+            Ok(data) => Self::from_docker_config(&data)
+                .with_context(|| format!("failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Add `other`'s registries; entries already present win.
+    pub fn merge(&mut self, other: RegistryKeychain) {
+        for (registry, creds) in other.auths {
+            self.auths.entry(registry).or_insert(creds);
+        }
+    }
+
+    /// Credentials for a registry host.
+    pub fn get(&self, registry: &str) -> Option<&RegistryCredentials> {
+        self.auths.get(&normalize_registry(registry))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.auths.is_empty()
+    }
+}
+
+/// Reduce a config key (`https://ghcr.io/v2/`, `index.docker.io`) to the host
+/// it names, folding Docker Hub's aliases into `docker.io`.
+fn normalize_registry(registry: &str) -> String {
+    let host = registry
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let host = host.split('/').next().unwrap_or(host).to_ascii_lowercase();
+    match host.as_str() {
+        "index.docker.io" | "registry-1.docker.io" => "docker.io".to_string(),
+        _ => host,
+    }
+}
+
+/// A registry refused the pull and no credentials were configured for it.
+#[derive(Debug)]
+pub struct AuthenticationRequired {
+    pub registry: String,
+}
+
+impl std::fmt::Display for AuthenticationRequired {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "registry {} requires authentication", self.registry)
+    }
+}
+
+impl std::error::Error for AuthenticationRequired {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(data: &[(&str, &str)]) -> Secret {
+        Secret {
+            id: "s1".to_string(),
+            name: "regcred".to_string(),
+            namespace: "default".to_string(),
+            data: data
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            created_at: chrono::Utc::now(),
+            resource_version: 0,
+        }
+    }
+
+    #[test]
+    fn test_basic_header_construction() {
+        let creds = RegistryCredentials::new("robot", "s3cret");
+        // base64("robot:s3cret")
+        assert_eq!(creds.basic_header(), "Basic cm9ib3Q6czNjcmV0");
+        let debug = format!("{:?}", creds);
+        assert!(debug.contains("robot"));
+        assert!(!debug.contains("s3cret"));
+    }
+
+    #[test]
+    fn test_keychain_from_docker_config() {
+        let json = br#"{"auths": {
+            "https://ghcr.io/v2/": {"auth": "cm9ib3Q6czNjcmV0"},
+            "harbor.corp:8443": {"username": "ci", "password": "pw"},
+            "index.docker.io": {"auth": "aHViOmh1Yg=="}
+        }}"#;
+        let keychain = RegistryKeychain::from_docker_config(json).unwrap();
+        assert_eq!(
+            keychain.get("ghcr.io"),
+            Some(&RegistryCredentials::new("robot", "s3cret"))
+        );
+        assert_eq!(keychain.get("harbor.corp:8443").unwrap().password(), "pw");
+        assert_eq!(
+            keychain.get("registry-1.docker.io").unwrap().username,
+            "hub"
+        );
+        assert!(keychain.get("quay.io").is_none());
+
+        let err = RegistryKeychain::from_docker_config(br#"{"auths": {"x.io": {}}}"#);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_keychain_from_secret_and_merge() {
+        let config = STANDARD.encode(r#"{"auths": {"ghcr.io": {"auth": "YTpi"}}}"#);
+        let mut keychain =
+            RegistryKeychain::from_secret(&secret(&[(DOCKER_CONFIG_KEY, &config)])).unwrap();
+        assert_eq!(keychain.get("ghcr.io").unwrap().username, "a");
+
+        // The node fallback only fills in registries the pod has no secret for.
+        let node = RegistryKeychain::from_docker_config(
+            br#"{"auths": {"ghcr.io": {"auth": "bm9kZTpu"}, "quay.io": {"auth": "bm9kZTpu"}}}"#,
+        )
+        .unwrap();
+        keychain.merge(node);
+        assert_eq!(keychain.get("ghcr.io").unwrap().username, "a");
+        assert_eq!(keychain.get("quay.io").unwrap().username, "node");
+
+        assert!(RegistryKeychain::from_secret(&secret(&[("token", "abc")])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_missing_node_credentials_file_is_empty() {
+        let path = std::env::temp_dir().join("k3rs-no-such-registry-auth.json");
+        let keychain = RegistryKeychain::load_file(&path).await.unwrap();
+        assert!(keychain.is_empty());
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::Result;
+use pkg_types::pod::{Capabilities, ResourceRequirements, SecurityContext};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use tracing::info;
+use tracing::{debug, info};
 
 /// Check if we are running as root.
 fn is_root() -> bool {
@@ -95,6 +97,169 @@ pub fn parse_image_workdir(image_dir: &Path) -> Option<String> {
         .map(String::from)
 }
 
+/// Parse the user the image runs as (`User`) from OCI image config.json.
+pub fn parse_image_user(image_dir: &Path) -> Option<String> {
+    let config_path = image_dir.join("config.json");
+    let data = std::fs::read_to_string(&config_path).ok()?;
+    let v: serde_json::Value = serde_json::from_str(&data).ok()?;
+    let config = v.get("config").or_else(|| v.get("Config"))?;
+    config
+        .get("User")
+        .and_then(|u| u.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+}
+
+/// Resolve an image `User` — `uid`, `name`, `uid:gid`, `name:group` and
+/// the mixed forms — to a UID and GID, looking names up in the rootfs's
+/// `/etc/passwd` and `/etc/group`. Without a group the user's primary
+/// group is used, or 0 for a UID the passwd file doesn't list.
+pub fn resolve_image_user(user: &str, rootfs: &Path) -> Result<(u32, u32)> {
+    let (user_part, group_part) = match user.split_once(':') {
+        Some((u, g)) => (u, Some(g)),
+        None => (user, None),
+    };
+    let passwd = std::fs::read_to_string(rootfs.join("etc/passwd")).unwrap_or_default();
+    // name:password:uid:gid:gecos:home:shell
+    let mut users = passwd.lines().filter_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        let uid = fields.nth(1)?.parse::<u32>().ok()?;
+        let gid = fields.next()?.parse::<u32>().ok()?;
+        Some((name, uid, gid))
+    });
+    let (uid, primary_gid) = match user_part.parse::<u32>() {
+        Ok(uid) => (uid, users.find(|u| u.1 == uid).map(|u| u.2).unwrap_or(0)),
+        Err(_) => users
+            .find(|u| u.0 == user_part)
+            .map(|u| (u.1, u.2))
+            .ok_or_else(|| {
+                anyhow::anyhow!("user `{}` not found in the image's /etc/passwd", user_part)
+            })?,
+    };
+    let gid = match group_part {
+        None => primary_gid,
+        Some(group) => match group.parse::<u32>() {
+            Ok(gid) => gid,
+            Err(_) => {
+                // name:password:gid:members
+                let groups = std::fs::read_to_string(rootfs.join("etc/group")).unwrap_or_default();
+                groups
+                    .lines()
+                    .find_map(|line| {
+                        let mut fields = line.split(':');
+                        (fields.next()? == group).then(|| fields.nth(1)?.parse::<u32>().ok())?
+                    })
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("group `{}` not found in the image's /etc/group", group)
+                    })?
+            }
+        },
+    };
+    Ok((uid, gid))
+}
+
+/// Capabilities of an unprivileged container before the security
+/// context's `drop` and `add` are applied.
+const DEFAULT_CAPABILITIES: &[&str] = &[
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_FSETID",
+    "CAP_FOWNER",
+    "CAP_MKNOD",
+    "CAP_NET_RAW",
+    "CAP_NET_ADMIN",
+    "CAP_SYS_ADMIN", // Added for youki compatibility
+    "CAP_SETGID",
+    "CAP_SETUID",
+    "CAP_SETFCAP",
+    "CAP_SETPCAP",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_SYS_CHROOT",
+    "CAP_KILL",
+    "CAP_AUDIT_WRITE",
+];
+
+/// Every capability the kernel knows, for privileged containers.
+const ALL_CAPABILITIES: &[&str] = &[
+    "CAP_CHOWN",
+    "CAP_DAC_OVERRIDE",
+    "CAP_DAC_READ_SEARCH",
+    "CAP_FOWNER",
+    "CAP_FSETID",
+    "CAP_KILL",
+    "CAP_SETGID",
+    "CAP_SETUID",
+    "CAP_SETPCAP",
+    "CAP_LINUX_IMMUTABLE",
+    "CAP_NET_BIND_SERVICE",
+    "CAP_NET_BROADCAST",
+    "CAP_NET_ADMIN",
+    "CAP_NET_RAW",
+    "CAP_IPC_LOCK",
+    "CAP_IPC_OWNER",
+    "CAP_SYS_MODULE",
+    "CAP_SYS_RAWIO",
+    "CAP_SYS_CHROOT",
+    "CAP_SYS_PTRACE",
+    "CAP_SYS_PACCT",
+    "CAP_SYS_ADMIN",
+    "CAP_SYS_BOOT",
+    "CAP_SYS_NICE",
+    "CAP_SYS_RESOURCE",
+    "CAP_SYS_TIME",
+    "CAP_SYS_TTY_CONFIG",
+    "CAP_MKNOD",
+    "CAP_LEASE",
+    "CAP_AUDIT_WRITE",
+    "CAP_AUDIT_CONTROL",
+    "CAP_SETFCAP",
+    "CAP_MAC_OVERRIDE",
+    "CAP_MAC_ADMIN",
+    "CAP_SYSLOG",
+    "CAP_WAKE_ALARM",
+    "CAP_BLOCK_SUSPEND",
+    "CAP_AUDIT_READ",
+    "CAP_PERFMON",
+    "CAP_BPF",
+    "CAP_CHECKPOINT_RESTORE",
+];
+
+/// The container's capability set: every capability when privileged,
+/// otherwise the defaults minus `drop` plus `add`.
+pub fn container_capabilities(security: &SecurityContext) -> Vec<String> {
+    if security.privileged {
+        return ALL_CAPABILITIES.iter().map(|c| c.to_string()).collect();
+    }
+    let drop: Vec<String> = security
+        .capabilities
+        .drop
+        .iter()
+        .map(|c| Capabilities::normalize(c))
+        .collect();
+    let mut caps: Vec<String> = if drop.iter().any(|c| c == "ALL") {
+        Vec::new()
+    } else {
+        DEFAULT_CAPABILITIES
+            .iter()
+            .map(|c| c.to_string())
+            .filter(|c| !drop.contains(c))
+            .collect()
+    };
+    for cap in security
+        .capabilities
+        .add
+        .iter()
+        .map(|c| Capabilities::normalize(c))
+    {
+        if !caps.contains(&cap) {
+            caps.push(cap);
+        }
+    }
+    caps
+}
+
 /// Container networking mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum NetworkMode {
@@ -105,6 +270,231 @@ pub enum NetworkMode {
     Isolated,
 }
 
+/// Name prefix of an OCI whiteout entry.
+const WHITEOUT_PREFIX: &str = ".wh.";
+/// Whiteout entry that hides everything lower layers put in its directory.
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+/// Symlinks followed while resolving one path inside a rootfs before giving
+/// up, as the kernel's `MAXSYMLINKS`.
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Where `path` is inside `rootfs`, resolving the directories on the way as
+/// the container would see them: symlinks among them are followed, but with
+/// `rootfs` as `/`, so neither an absolute target nor `..` leads out of it.
+/// The last component is not followed, so a symlink there is the path
+/// itself. Only the normal components of `path` count.
+///
+/// Layers are extracted by root, so writing or deleting through a symlink a
+/// layer planted (`etc -> /etc`) would reach the host's files.
+fn resolve_in_rootfs(rootfs: &Path, path: &Path) -> Result<PathBuf> {
+    use std::collections::VecDeque;
+    use std::ffi::OsString;
+    use std::path::Component;
+
+    let mut parts: VecDeque<OsString> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_os_string()),
+            _ => None,
+        })
+        .collect();
+    let Some(name) = parts.pop_back() else {
+        return Ok(rootfs.to_path_buf());
+    };
+
+    let mut resolved = PathBuf::new();
+    let mut hops = 0;
+    while let Some(part) = parts.pop_front() {
+        if part == ".." {
+            resolved.pop();
+            continue;
+        }
+        let candidate = rootfs.join(&resolved).join(&part);
+        match std::fs::symlink_metadata(&candidate) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                hops += 1;
+                if hops > MAX_SYMLINK_HOPS {
+                    anyhow::bail!("too many levels of symlinks in `{}`", path.display());
+                }
+                let target = std::fs::read_link(&candidate)?;
+                if target.is_absolute() {
+                    resolved.clear();
+                }
+                for component in target.components().rev() {
+                    match component {
+                        Component::Normal(name) => parts.push_front(name.to_os_string()),
+                        Component::ParentDir => parts.push_front("..".into()),
+                        _ => {}
+                    }
+                }
+            }
+            _ => resolved.push(part),
+        }
+    }
+    Ok(rootfs.join(resolved).join(name))
+}
+
+/// Remove a file, symlink or directory tree. Missing paths are fine.
+fn remove_path(path: &Path) -> Result<()> {
+    let result = match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => std::fs::remove_dir_all(path),
+        Ok(_) => std::fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(anyhow::anyhow!(
+            "failed to apply whiteout `{}`: {e}",
+            path.display()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Apply an opaque whiteout: delete the contents of `dir` that came from
+/// lower layers, keeping anything the current layer (`written`) put there.
+fn clear_opaque_dir(dir: &Path, written: &HashSet<PathBuf>) -> Result<()> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if written.iter().any(|w| w.starts_with(&path)) {
+            // A directory this layer wrote into may still hold lower files.
+            if std::fs::symlink_metadata(&path)?.is_dir() {
+                clear_opaque_dir(&path, written)?;
+            }
+        } else {
+            remove_path(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Where extraction records the image's file ownership when it cannot chown
+/// (not running as root): `<container_dir>/rootfs.owners`, one
+/// `<uid>:<gid> <path>` line per entry, in layer order — the last line for a
+/// path wins.
+pub fn ownership_record_path(rootfs: &Path) -> PathBuf {
+    rootfs.with_extension("owners")
+}
+
+/// Create a character/block device or FIFO entry. Device nodes need root and
+/// are skipped otherwise; the container runtime provides the standard ones in
+/// `/dev` regardless.
+fn create_special_file(header: &tar::Header, dest: &Path, as_root: bool) -> Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let kind = header.entry_type();
+    let file_type = match kind {
+        tar::EntryType::Char => libc::S_IFCHR,
+        tar::EntryType::Block => libc::S_IFBLK,
+        _ => libc::S_IFIFO,
+    };
+    if file_type != libc::S_IFIFO && !as_root {
+        debug!(
+            "Skipping device node {} (not running as root)",
+            dest.display()
+        );
+        return Ok(());
+    }
+
+    let mode = header.mode()? & 0o7777;
+    let major = header.device_major()?.unwrap_or(0);
+    let minor = header.device_minor()?.unwrap_or(0);
+    let c_path = std::ffi::CString::new(dest.as_os_str().as_bytes())?;
+    let dev = libc::makedev(major as _, minor as _);
+    if unsafe { libc::mknod(c_path.as_ptr(), file_type | mode as libc::mode_t, dev) } != 0 {
+        return Err(anyhow::anyhow!(
+            "failed to create `{}`: {}",
+            dest.display(),
+            std::io::Error::last_os_error()
+        ));
+    }
+    // mknod's mode was filtered through the umask.
+    std::fs::set_permissions(dest, std::os::unix::fs::PermissionsExt::from_mode(mode))?;
+    if as_root {
+        let uid = header.uid().unwrap_or(0) as u32;
+        let gid = header.gid().unwrap_or(0) as u32;
+        set_ownership(dest, kind, uid, gid, mode)?;
+    }
+    Ok(())
+}
+
+/// chown an extracted entry (the link itself for symlinks). chown clears
+/// setuid/setgid, so those bits of `mode` are restored afterwards.
+fn set_ownership(dest: &Path, kind: tar::EntryType, uid: u32, gid: u32, mode: u32) -> Result<()> {
+    std::os::unix::fs::lchown(dest, Some(uid), Some(gid))
+        .map_err(|e| anyhow::anyhow!("failed to chown `{}`: {e}", dest.display()))?;
+    if !kind.is_symlink() && mode & 0o6000 != 0 {
+        std::fs::set_permissions(
+            dest,
+            std::os::unix::fs::PermissionsExt::from_mode(mode & 0o7777),
+        )?;
+    }
+    Ok(())
+}
+
+/// Replace the rootfs's `/etc/resolv.conf` with `contents`. A symlink left
+/// there by the image is removed rather than written through, since its
+/// target would resolve on the host.
+pub async fn write_resolv_conf(rootfs: &Path, contents: &str) -> Result<()> {
+    let etc = rootfs.join("etc");
+    if tokio::fs::symlink_metadata(&etc)
+        .await
+        .is_ok_and(|m| m.file_type().is_symlink())
+    {
+        anyhow::bail!("`{}` is a symlink", etc.display());
+    }
+    tokio::fs::create_dir_all(&etc).await?;
+    let path = etc.join("resolv.conf");
+    if tokio::fs::symlink_metadata(&path).await.is_ok() {
+        tokio::fs::remove_file(&path).await?;
+    }
+    tokio::fs::write(&path, contents).await?;
+    Ok(())
+}
+
+/// Cgroup v2 path a container runs in, relative to the cgroup mount.
+pub fn container_cgroup_path(container_id: &str) -> String {
+    format!(
+        "{}/{}",
+        pkg_constants::runtime::CONTAINER_CGROUP_PARENT,
+        container_id
+    )
+}
+
+/// OCI `linux.resources` for a container's limits, or `None` when it has
+/// none. CPU becomes a CFS quota per [`CPU_CFS_PERIOD_US`] and memory a hard
+/// limit with swap equal to it, so the container can't swap. Limited
+/// containers also get a PID cap.
+///
+/// [`CPU_CFS_PERIOD_US`]: pkg_constants::runtime::CPU_CFS_PERIOD_US
+pub fn linux_resources(limits: &ResourceRequirements) -> Option<serde_json::Value> {
+    use pkg_constants::runtime::{CONTAINER_PIDS_LIMIT, CPU_CFS_PERIOD_US};
+
+    if limits.cpu_millis == 0 && limits.memory_bytes == 0 {
+        return None;
+    }
+    let mut resources = serde_json::json!({ "pids": { "limit": CONTAINER_PIDS_LIMIT } });
+    if limits.cpu_millis > 0 {
+        resources["cpu"] = serde_json::json!({
+            "quota": limits.cpu_millis * CPU_CFS_PERIOD_US / 1000,
+            "period": CPU_CFS_PERIOD_US,
+        });
+    }
+    if limits.memory_bytes > 0 {
+        // OCI `swap` is memory plus swap.
+        resources["memory"] = serde_json::json!({
+            "limit": limits.memory_bytes,
+            "swap": limits.memory_bytes,
+        });
+    }
+    Some(resources)
+}
+
 /// Manages rootfs extraction from OCI image layers.
 pub struct RootfsManager;
 
@@ -113,7 +503,13 @@ impl RootfsManager {
     pub async fn extract(image_dir: &Path, container_dir: &Path) -> Result<PathBuf> {
         let rootfs = container_dir.join("rootfs");
         tokio::fs::create_dir_all(&rootfs).await?;
+        Self::extract_layers(image_dir, &rootfs).await?;
+        info!("Rootfs extracted to {}", rootfs.display());
+        Ok(rootfs)
+    }
 
+    /// Extract every layer of a pulled image into `rootfs`, bottom layer first.
+    pub async fn extract_layers(image_dir: &Path, rootfs: &Path) -> Result<()> {
         let layers_dir = image_dir.join("layers");
         if !layers_dir.exists() {
             anyhow::bail!("No layers directory found at {}", layers_dir.display());
@@ -127,7 +523,15 @@ impl RootfsManager {
                 layers.push(path);
             }
         }
-        layers.sort();
+        // By layer index: a plain sort would put layer_10 before layer_2.
+        layers.sort_by_key(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix("layer_"))
+                .and_then(|n| n.split('.').next())
+                .and_then(|n| n.parse::<usize>().ok())
+                .unwrap_or(usize::MAX)
+        });
 
         info!("Extracting {} layers to {}", layers.len(), rootfs.display());
 
@@ -138,117 +542,175 @@ impl RootfsManager {
                 layers.len(),
                 layer_path.display()
             );
+            Self::extract_layer(layer_path, rootfs).await?;
+        }
+        Ok(())
+    }
 
-            let layer_path = layer_path.clone();
-            let rootfs_clone = rootfs.clone();
-
-            tokio::task::spawn_blocking(move || -> Result<()> {
-                let file = std::fs::File::open(&layer_path)?;
-                let decoder = flate2::read::GzDecoder::new(file);
-                let mut archive = tar::Archive::new(decoder);
-                archive.set_preserve_permissions(true);
-                archive.set_overwrite(true);
-
-                // Hard link entries (EntryType::Link) can appear before the file they point to
-                // within the same tar. Collect them and retry after all regular entries are done.
-                let mut deferred_hard_links: Vec<(PathBuf, PathBuf)> = Vec::new();
-
-                for entry in archive.entries()? {
-                    let mut entry = entry?;
-                    let entry_path = entry.path()?.into_owned();
-
-                    // Skip whiteout files (OCI layer deletion markers) — they start with ".wh."
-                    if let Some(name) = entry_path.file_name().and_then(|n| n.to_str())
-                        && name.starts_with(".wh.")
-                    {
+    /// Extract one gzipped layer tarball on top of `rootfs`. Layers must be
+    /// applied in order, each after the ones below it.
+    pub async fn extract_layer(layer_path: &Path, rootfs: &Path) -> Result<()> {
+        let layer_path = layer_path.to_path_buf();
+        let rootfs = rootfs.to_path_buf();
+
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let file = std::fs::File::open(&layer_path)?;
+            let decoder = flate2::read::GzDecoder::new(file);
+            let mut archive = tar::Archive::new(decoder);
+            archive.set_preserve_permissions(true);
+            archive.set_overwrite(true);
+            archive.set_preserve_mtime(true);
+            // Only root can chown; otherwise ownership is written to the
+            // record beside the rootfs instead.
+            let as_root = is_root();
+            let mut owners = String::new();
+
+            // Hard link entries (EntryType::Link) can appear before the file they point to
+            // within the same tar. Collect them and retry after all regular entries are done.
+            let mut deferred_hard_links: Vec<(PathBuf, PathBuf)> = Vec::new();
+            // Paths this layer wrote, which an opaque whiteout later in the
+            // same tar must leave alone.
+            let mut written: HashSet<PathBuf> = HashSet::new();
+
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                let entry_path = entry.path()?.into_owned();
+
+                // Resolved inside the rootfs, so neither `..`, an absolute
+                // path nor a symlinked directory from a layer leads out of it.
+                let dest = resolve_in_rootfs(&rootfs, &entry_path)?;
+
+                // Whiteouts (OCI layer deletion markers) delete what lower
+                // layers put there: `.wh..wh..opq` empties its directory,
+                // `.wh.<name>` removes `<name>`.
+                if let Some(name) = entry_path.file_name().and_then(|n| n.to_str())
+                    && let Some(target) = name.strip_prefix(WHITEOUT_PREFIX)
+                {
+                    let Some(dir) = dest.parent() else {
                         continue;
+                    };
+                    if name == OPAQUE_WHITEOUT {
+                        clear_opaque_dir(dir, &written)?;
+                    } else if !target.is_empty() && target != "." && target != ".." {
+                        remove_path(&dir.join(target))?;
                     }
+                    continue;
+                }
+                written.insert(dest.clone());
+
+                // Always defer hard links. The tar crate's unpack() for EntryType::Link
+                // uses relative paths from the current working directory, which fails
+                // when extracting to a nested rootfs. We also need to ensure the source
+                // file exists, so we collect them and link them in a second pass using
+                // absolute paths, resolved then since later entries can change them.
+                if entry.header().entry_type() == tar::EntryType::Link
+                    && let Some(link_name) = entry.header().link_name()?
+                {
+                    deferred_hard_links.push((link_name.into_owned(), entry_path));
+                    continue;
+                }
 
-                    // Calculate safe destination path, avoiding absolute path traversal.
-                    let mut dest = rootfs_clone.to_path_buf();
-                    for component in entry_path.components() {
-                        if let std::path::Component::Normal(c) = component {
-                            dest.push(c);
-                        }
-                    }
-
-                    // Always defer hard links. The tar crate's unpack() for EntryType::Link
-                    // uses relative paths from the current working directory, which fails
-                    // when extracting to a nested rootfs. We also need to ensure the source
-                    // file exists, so we collect them and link them in a second pass using
-                    // absolute paths.
-                    if entry.header().entry_type() == tar::EntryType::Link
-                        && let Some(link_name) = entry.header().link_name()?
-                    {
-                        let mut link_src = rootfs_clone.to_path_buf();
-                        for component in link_name.components() {
-                            if let std::path::Component::Normal(c) = component {
-                                link_src.push(c);
-                            }
-                        }
-                        deferred_hard_links.push((link_src, dest));
-                        continue;
-                    }
+                let kind = entry.header().entry_type();
+                let uid = entry.header().uid().unwrap_or(0) as u32;
+                let gid = entry.header().gid().unwrap_or(0) as u32;
+                if !as_root {
+                    owners.push_str(&format!("{}:{} {}\n", uid, gid, entry_path.display()));
+                }
 
-                    // If an existing non-directory file blocks the unpack, remove it first.
-                    // This handles the case where a previous layer wrote a file with
-                    // restrictive permissions (e.g. 0555) that the tar crate cannot overwrite.
-                    if dest.exists() && !dest.is_dir() {
+                // Clear whatever a lower layer left at this path, without
+                // following symlinks: a symlink is removed, never written
+                // through. A directory is only replaced by a non-directory.
+                // This also handles a previous layer's file with restrictive
+                // permissions (e.g. 0555) that the tar crate cannot overwrite.
+                if let Ok(existing) = std::fs::symlink_metadata(&dest) {
+                    if existing.is_file() {
                         // Best-effort chmod to make it writable, then remove.
                         let _ = std::fs::set_permissions(
                             &dest,
                             std::os::unix::fs::PermissionsExt::from_mode(0o644),
                         );
+                    }
+                    if !existing.is_dir() {
                         let _ = std::fs::remove_file(&dest);
+                    } else if !kind.is_dir() {
+                        let _ = std::fs::remove_dir_all(&dest);
                     }
+                }
 
-                    // Ensure parent directory exists.
-                    if let Some(parent) = dest.parent() {
-                        let _ = std::fs::create_dir_all(parent);
-                    }
+                // Ensure parent directory exists.
+                if let Some(parent) = dest.parent() {
+                    let _ = std::fs::create_dir_all(parent);
+                }
 
-                    if let Err(e) = entry.unpack(&dest) {
-                        // EEXIST on directories is fine — the dir already exists.
-                        // AlreadyExists can also surface for hard-linked entries on some kernels.
-                        if e.kind() == std::io::ErrorKind::AlreadyExists {
-                            continue;
-                        }
-                        return Err(anyhow::anyhow!(
-                            "failed to unpack `{}`: {e}",
-                            dest.display()
-                        ));
-                    }
+                // The tar crate would write device nodes and FIFOs out as
+                // empty regular files.
+                if matches!(
+                    kind,
+                    tar::EntryType::Char | tar::EntryType::Block | tar::EntryType::Fifo
+                ) {
+                    create_special_file(entry.header(), &dest, as_root)?;
+                    continue;
                 }
 
-                // Retry deferred hard links now that all regular files are on disk.
-                for (link_src, dest) in deferred_hard_links {
-                    if dest.exists() {
-                        continue; // a later layer or earlier iteration already wrote it
+                if let Err(e) = entry.unpack(&dest) {
+                    // EEXIST on directories is fine — the dir already exists.
+                    // AlreadyExists can also surface for hard-linked entries on some kernels.
+                    if e.kind() == std::io::ErrorKind::AlreadyExists {
+                        continue;
                     }
-                    if link_src.exists() {
-                        // Ensure parent directory exists for the hard link.
-                        if let Some(parent) = dest.parent() {
-                            let _ = std::fs::create_dir_all(parent);
-                        }
-
-                        std::fs::hard_link(&link_src, &dest).map_err(|e| {
-                            anyhow::anyhow!(
-                                "failed to hard link `{}` -> `{}`: {e}",
-                                link_src.display(),
-                                dest.display()
-                            )
-                        })?;
+                    return Err(anyhow::anyhow!(
+                        "failed to unpack `{}`: {e}",
+                        dest.display()
+                    ));
+                }
+
+                if as_root {
+                    set_ownership(&dest, kind, uid, gid, entry.header().mode().unwrap_or(0))?;
+                }
+            }
+
+            // Retry deferred hard links now that all regular files are on disk.
+            for (link_name, entry_path) in deferred_hard_links {
+                let link_src = resolve_in_rootfs(&rootfs, &link_name)?;
+                let dest = resolve_in_rootfs(&rootfs, &entry_path)?;
+                // If link_src still doesn't exist it wasn't in this layer set — skip.
+                if std::fs::symlink_metadata(&link_src).is_err() {
+                    continue;
+                }
+                match std::fs::symlink_metadata(&dest) {
+                    Ok(existing) if existing.is_dir() => continue,
+                    // A file or symlink from a lower layer is replaced.
+                    Ok(_) => {
+                        let _ = std::fs::remove_file(&dest);
                     }
-                    // If link_src still doesn't exist it wasn't in this layer set — skip.
+                    Err(_) => {}
+                }
+                // Ensure parent directory exists for the hard link.
+                if let Some(parent) = dest.parent() {
+                    let _ = std::fs::create_dir_all(parent);
                 }
 
-                Ok(())
-            })
-            .await??;
-        }
+                std::fs::hard_link(&link_src, &dest).map_err(|e| {
+                    anyhow::anyhow!(
+                        "failed to hard link `{}` -> `{}`: {e}",
+                        link_src.display(),
+                        dest.display()
+                    )
+                })?;
+            }
 
-        info!("Rootfs extracted to {}", rootfs.display());
-        Ok(rootfs)
+            if !owners.is_empty() {
+                use std::io::Write;
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(ownership_record_path(&rootfs))?
+                    .write_all(owners.as_bytes())?;
+            }
+
+            Ok(())
+        })
+        .await?
     }
 
     /// Generate OCI config.json — backward-compatible wrapper.
@@ -266,18 +728,35 @@ impl RootfsManager {
             None,
             None,
             NetworkMode::default(),
+            &[],
+            &ResourceRequirements::default(),
+            &SecurityContext::default(),
         )
     }
 
-    /// Full config generation with image config support and network mode.
+    /// Full config generation with image config support, network mode,
+    /// the pod's volume mounts, the container's resource limits and its
+    /// security context.
+    ///
+    /// As root the container gets its own cgroup (see
+    /// [`container_cgroup_path`]) with the limits applied; rootless runtimes
+    /// can't write the cgroup tree, so there the limits are left out.
+    ///
+    /// The process runs as the security context's user, else the image's
+    /// `User` (resolved against `rootfs_path`), else root. Fails when that
+    /// is root and `run_as_non_root` is set.
+    #[allow(clippy::too_many_arguments)]
     pub fn generate_config_full(
         container_id: &str,
-        _rootfs_path: &Path,
+        rootfs_path: &Path,
         command: &[String],
         env_vars: &std::collections::HashMap<String, String>,
         image_dir: Option<&Path>,
         working_dir: Option<&str>,
         network_mode: NetworkMode,
+        volume_mounts: &[crate::volume::BindMount],
+        resources: &ResourceRequirements,
+        security: &SecurityContext,
     ) -> Result<String> {
         let _network_mode = network_mode;
         // Resolve command: pod spec > image entrypoint+cmd > /bin/sh
@@ -339,24 +818,21 @@ impl RootfsManager {
             .map(|(k, v)| format!("{}={}", k, v))
             .collect();
 
-        let default_caps = serde_json::json!([
-            "CAP_CHOWN",
-            "CAP_DAC_OVERRIDE",
-            "CAP_FSETID",
-            "CAP_FOWNER",
-            "CAP_MKNOD",
-            "CAP_NET_RAW",
-            "CAP_NET_ADMIN",
-            "CAP_SYS_ADMIN", // Added for youki compatibility
-            "CAP_SETGID",
-            "CAP_SETUID",
-            "CAP_SETFCAP",
-            "CAP_SETPCAP",
-            "CAP_NET_BIND_SERVICE",
-            "CAP_SYS_CHROOT",
-            "CAP_KILL",
-            "CAP_AUDIT_WRITE"
-        ]);
+        let (image_uid, image_gid) = match image_dir.and_then(parse_image_user) {
+            Some(user) => resolve_image_user(&user, rootfs_path)?,
+            None => (0, 0),
+        };
+        let uid = security.run_as_user.unwrap_or(image_uid);
+        let gid = security.run_as_group.unwrap_or(image_gid);
+        if uid == 0 && security.run_as_non_root {
+            anyhow::bail!(
+                "container {} would run as root, but its security context sets run_as_non_root",
+                container_id
+            );
+        }
+
+        let caps = serde_json::json!(container_capabilities(security));
+        let privileged = security.privileged;
 
         // Build namespaces
         let mut namespaces = vec![
@@ -403,17 +879,24 @@ impl RootfsManager {
             vec![]
         };
 
-        let mut linux_val = serde_json::json!({
-            "namespaces": namespaces,
-            "maskedPaths": [
-                "/proc/acpi", "/proc/asound", "/proc/kcore", "/proc/keys",
-                "/proc/latency_stats", "/proc/timer_list", "/proc/timer_stats",
-                "/proc/sched_debug", "/sys/firmware", "/proc/scsi"
-            ],
-            "readonlyPaths": [
-                "/proc/bus", "/proc/fs", "/proc/irq", "/proc/sysrq-trigger"
-            ]
-        });
+        let mut linux_val = serde_json::json!({ "namespaces": namespaces });
+        // Privileged containers see the host's /proc and /sys unmasked.
+        if !privileged {
+            linux_val["maskedPaths"] = serde_json::json!([
+                "/proc/acpi",
+                "/proc/asound",
+                "/proc/kcore",
+                "/proc/keys",
+                "/proc/latency_stats",
+                "/proc/timer_list",
+                "/proc/timer_stats",
+                "/proc/sched_debug",
+                "/sys/firmware",
+                "/proc/scsi"
+            ]);
+            linux_val["readonlyPaths"] =
+                serde_json::json!(["/proc/bus", "/proc/fs", "/proc/irq", "/proc/sysrq-trigger"]);
+        }
 
         // Add UID/GID mappings only for rootless mode (user namespace)
         if !uid_mappings.is_empty() {
@@ -421,12 +904,31 @@ impl RootfsManager {
             linux_val["gidMappings"] = serde_json::json!(gid_mappings);
         }
 
+        if is_root() {
+            linux_val["cgroupsPath"] = serde_json::json!(container_cgroup_path(container_id));
+            if let Some(limits) = linux_resources(resources) {
+                linux_val["resources"] = limits;
+            }
+            if privileged {
+                linux_val["resources"]["devices"] =
+                    serde_json::json!([{ "allow": true, "access": "rwm" }]);
+            }
+        }
+
         let linux = linux_val;
 
+        // Privileged containers get the host's devices and a writable /sys.
+        let dev = if privileged {
+            serde_json::json!({ "destination": "/dev", "type": "bind", "source": "/dev",
+              "options": ["rbind", "rprivate", "rw"] })
+        } else {
+            serde_json::json!({ "destination": "/dev", "type": "tmpfs", "source": "tmpfs",
+              "options": ["nosuid", "strictatime", "mode=755", "size=65536k"] })
+        };
+        let rw = if privileged { "rw" } else { "ro" };
         let mut mounts = vec![
             serde_json::json!({ "destination": "/proc", "type": "proc", "source": "proc" }),
-            serde_json::json!({ "destination": "/dev", "type": "tmpfs", "source": "tmpfs",
-              "options": ["nosuid", "strictatime", "mode=755", "size=65536k"] }),
+            dev,
             serde_json::json!({ "destination": "/dev/pts", "type": "devpts", "source": "devpts",
               "options": ["nosuid", "noexec", "newinstance", "ptmxmode=0666", "mode=0620"] }),
             serde_json::json!({ "destination": "/dev/shm", "type": "tmpfs", "source": "shm",
@@ -434,7 +936,7 @@ impl RootfsManager {
             serde_json::json!({ "destination": "/dev/mqueue", "type": "mqueue", "source": "mqueue",
               "options": ["nosuid", "noexec", "nodev"] }),
             serde_json::json!({ "destination": "/sys", "type": "sysfs", "source": "sysfs",
-              "options": ["nosuid", "noexec", "nodev", "ro"] }),
+              "options": ["nosuid", "noexec", "nodev", rw] }),
             serde_json::json!({ "destination": "/tmp", "type": "tmpfs", "source": "tmpfs",
               "options": ["nosuid", "nodev", "mode=1777", "size=65536k"] }),
             serde_json::json!({ "destination": "/run", "type": "tmpfs", "source": "tmpfs",
@@ -447,29 +949,32 @@ impl RootfsManager {
                 "destination": "/sys/fs/cgroup",
                 "type": "cgroup",
                 "source": "cgroup",
-                "options": ["nosuid", "noexec", "nodev", "relatime", "ro"]
+                "options": ["nosuid", "noexec", "nodev", "relatime", rw]
             }));
         }
 
+        // Pod volumes last, so they can shadow image paths
+        mounts.extend(volume_mounts.iter().map(|m| m.to_oci()));
+
         let config = serde_json::json!({
             "ociVersion": "1.0.2",
             "process": {
                 "terminal": false,
-                "user": { "uid": 0, "gid": 0 },
+                "user": { "uid": uid, "gid": gid },
                 "args": cmd,
                 "env": env,
                 "cwd": cwd,
                 "capabilities": {
-                    "bounding": default_caps,
-                    "effective": default_caps,
-                    "inheritable": default_caps,
-                    "permitted": default_caps,
-                    "ambient": default_caps,
+                    "bounding": caps,
+                    "effective": caps,
+                    "inheritable": caps,
+                    "permitted": caps,
+                    "ambient": caps,
                 },
                 "rlimits": [{ "type": "RLIMIT_NOFILE", "hard": 1024u64, "soft": 1024u64 }],
-                "noNewPrivileges": true
+                "noNewPrivileges": security.no_new_privileges.unwrap_or(!privileged)
             },
-            "root": { "path": "rootfs", "readonly": false },
+            "root": { "path": "rootfs", "readonly": security.read_only_root_filesystem },
             "hostname": container_id,
             "mounts": mounts,
             "linux": linux
@@ -622,6 +1127,9 @@ mod tests {
             None,
             None,
             NetworkMode::Host,
+            &[],
+            &ResourceRequirements::default(),
+            &SecurityContext::default(),
         )
         .unwrap();
         let config: serde_json::Value = serde_json::from_str(&config_str).unwrap();
@@ -644,6 +1152,9 @@ mod tests {
             None,
             None,
             NetworkMode::Isolated,
+            &[],
+            &ResourceRequirements::default(),
+            &SecurityContext::default(),
         )
         .unwrap();
         let config: serde_json::Value = serde_json::from_str(&config_str).unwrap();
@@ -665,11 +1176,300 @@ mod tests {
             None,
             Some("/app"),
             NetworkMode::default(),
+            &[],
+            &ResourceRequirements::default(),
+            &SecurityContext::default(),
         )
         .unwrap();
         let config: serde_json::Value = serde_json::from_str(&config_str).unwrap();
         assert_eq!(config["process"]["cwd"], "/app");
     }
+
+    #[test]
+    fn test_generate_config_volume_mounts() {
+        let volumes = [
+            crate::volume::BindMount {
+                source: "/var/lib/k3rs/volumes/p1/cache".into(),
+                destination: "/cache".to_string(),
+                read_only: false,
+            },
+            crate::volume::BindMount {
+                source: "/etc/ssl/certs".into(),
+                destination: "/etc/ssl/certs".to_string(),
+                read_only: true,
+            },
+        ];
+        let config_str = RootfsManager::generate_config_full(
+            "vol-test",
+            Path::new("/tmp/rootfs"),
+            &[],
+            &HashMap::new(),
+            None,
+            None,
+            NetworkMode::default(),
+            &volumes,
+            &ResourceRequirements::default(),
+            &SecurityContext::default(),
+        )
+        .unwrap();
+        let config: serde_json::Value = serde_json::from_str(&config_str).unwrap();
+        let mounts = config["mounts"].as_array().unwrap();
+
+        let cache = mounts
+            .iter()
+            .find(|m| m["destination"] == "/cache")
+            .unwrap();
+        assert_eq!(cache["type"], "bind");
+        assert_eq!(cache["source"], "/var/lib/k3rs/volumes/p1/cache");
+        assert_eq!(
+            cache["options"],
+            serde_json::json!(["rbind", "rprivate", "rw"])
+        );
+
+        let certs = mounts
+            .iter()
+            .find(|m| m["destination"] == "/etc/ssl/certs")
+            .unwrap();
+        assert_eq!(certs["options"][2], "ro");
+        // Volumes come after the default filesystems.
+        assert_eq!(mounts.last().unwrap()["destination"], "/etc/ssl/certs");
+    }
+
+    #[test]
+    fn test_linux_resources() {
+        let limits = |cpu_millis, memory_bytes| ResourceRequirements {
+            cpu_millis,
+            memory_bytes,
+        };
+        assert_eq!(linux_resources(&limits(0, 0)), None);
+
+        assert_eq!(
+            linux_resources(&limits(500, 256 << 20)).unwrap(),
+            serde_json::json!({
+                "cpu": { "quota": 50_000, "period": 100_000 },
+                "memory": { "limit": 268_435_456u64, "swap": 268_435_456u64 },
+                "pids": { "limit": 4096 },
+            })
+        );
+
+        // More than one core, no memory limit.
+        let cpu_only = linux_resources(&limits(2500, 0)).unwrap();
+        assert_eq!(cpu_only["cpu"]["quota"], 250_000);
+        assert!(cpu_only.get("memory").is_none());
+
+        let memory_only = linux_resources(&limits(0, 64 << 20)).unwrap();
+        assert!(memory_only.get("cpu").is_none());
+        assert_eq!(memory_only["memory"]["limit"], 64u64 << 20);
+        assert_eq!(memory_only["pids"]["limit"], 4096);
+    }
+
+    #[test]
+    fn test_generate_config_resources() {
+        let config_str = RootfsManager::generate_config_full(
+            "web-0-app",
+            Path::new("/tmp/rootfs"),
+            &[],
+            &HashMap::new(),
+            None,
+            None,
+            NetworkMode::default(),
+            &[],
+            &ResourceRequirements {
+                cpu_millis: 250,
+                memory_bytes: 128 << 20,
+            },
+            &SecurityContext::default(),
+        )
+        .unwrap();
+        let config: serde_json::Value = serde_json::from_str(&config_str).unwrap();
+        if is_root() {
+            assert_eq!(config["linux"]["cgroupsPath"], "/k3rs/web-0-app");
+            assert_eq!(config["linux"]["resources"]["cpu"]["quota"], 25_000);
+            assert_eq!(
+                config["linux"]["resources"]["memory"]["limit"],
+                128u64 << 20
+            );
+        } else {
+            assert!(config["linux"].get("resources").is_none());
+        }
+    }
+
+    /// An image dir whose config runs as `user`, and a rootfs with an
+    /// nginx-style `/etc/passwd` and `/etc/group`.
+    fn user_fixture(label: &str, user: &str) -> (PathBuf, PathBuf) {
+        let base = crate::test_util::temp_dir(label);
+        let (image_dir, rootfs) = (base.join("image"), base.join("rootfs"));
+        std::fs::create_dir_all(&image_dir).unwrap();
+        std::fs::create_dir_all(rootfs.join("etc")).unwrap();
+        let config = serde_json::json!({ "config": { "User": user, "Cmd": ["nginx"] } });
+        std::fs::write(image_dir.join("config.json"), config.to_string()).unwrap();
+        std::fs::write(
+            rootfs.join("etc/passwd"),
+            "root:x:0:0:root:/root:/bin/sh\nnginx:x:101:101:nginx:/var/cache/nginx:/sbin/nologin\n",
+        )
+        .unwrap();
+        std::fs::write(
+            rootfs.join("etc/group"),
+            "root:x:0:\nadm:x:4:nginx\nnginx:x:101:\n",
+        )
+        .unwrap();
+        (image_dir, rootfs)
+    }
+
+    #[test]
+    fn test_image_user_fallback() {
+        let (image_dir, rootfs) = user_fixture("image-user", "nginx");
+        assert_eq!(parse_image_user(&image_dir).as_deref(), Some("nginx"));
+        assert_eq!(parse_image_user(Path::new("/nonexistent")), None);
+
+        let resolve = |user: &str| resolve_image_user(user, &rootfs).ok();
+        assert_eq!(resolve("nginx"), Some((101, 101)));
+        assert_eq!(resolve("101"), Some((101, 101)));
+        assert_eq!(resolve("nginx:adm"), Some((101, 4)));
+        assert_eq!(resolve("nginx:0"), Some((101, 0)));
+        assert_eq!(resolve("1000:1000"), Some((1000, 1000)));
+        // A UID the passwd file doesn't list runs with group 0.
+        assert_eq!(resolve("12345"), Some((12345, 0)));
+        assert_eq!(resolve("ghost"), None);
+        assert_eq!(resolve("nginx:staff"), None);
+        // No passwd file: numeric forms still work.
+        assert_eq!(
+            resolve_image_user("65534:65534", Path::new("/nonexistent")).ok(),
+            Some((65534, 65534))
+        );
+
+        let config = |security: &SecurityContext| -> serde_json::Value {
+            let config_str = RootfsManager::generate_config_full(
+                "nginx",
+                &rootfs,
+                &[],
+                &HashMap::new(),
+                Some(&image_dir),
+                None,
+                NetworkMode::default(),
+                &[],
+                &ResourceRequirements::default(),
+                security,
+            )
+            .unwrap();
+            serde_json::from_str(&config_str).unwrap()
+        };
+        let user = &config(&SecurityContext::default())["process"]["user"];
+        assert_eq!(
+            (user["uid"].clone(), user["gid"].clone()),
+            (101.into(), 101.into())
+        );
+        // The security context wins over the image.
+        let user = &config(&SecurityContext {
+            run_as_user: Some(2000),
+            ..Default::default()
+        })["process"]["user"];
+        assert_eq!(
+            (user["uid"].clone(), user["gid"].clone()),
+            (2000.into(), 101.into())
+        );
+    }
+
+    #[test]
+    fn test_generate_config_security_context() {
+        let config = |security: SecurityContext| {
+            RootfsManager::generate_config_full(
+                "sc-test",
+                Path::new("/tmp/rootfs"),
+                &[],
+                &HashMap::new(),
+                None,
+                None,
+                NetworkMode::default(),
+                &[],
+                &ResourceRequirements::default(),
+                &security,
+            )
+            .map(|c| serde_json::from_str::<serde_json::Value>(&c).unwrap())
+        };
+        let caps = |config: &serde_json::Value| -> Vec<String> {
+            config["process"]["capabilities"]["bounding"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|c| c.as_str().unwrap().to_string())
+                .collect()
+        };
+
+        // Defaults: root, the default capabilities, writable root.
+        let default = config(SecurityContext::default()).unwrap();
+        assert_eq!(default["process"]["user"]["uid"], 0);
+        assert_eq!(caps(&default).len(), DEFAULT_CAPABILITIES.len());
+        assert_eq!(default["process"]["noNewPrivileges"], true);
+        assert_eq!(default["root"]["readonly"], false);
+        assert!(default["linux"]["maskedPaths"].is_array());
+
+        // Non-root, read-only, minimal capabilities.
+        let locked = config(SecurityContext {
+            run_as_user: Some(1000),
+            run_as_group: Some(3000),
+            run_as_non_root: true,
+            read_only_root_filesystem: true,
+            capabilities: Capabilities {
+                add: vec!["net_bind_service".to_string()],
+                drop: vec!["ALL".to_string()],
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(locked["process"]["user"]["uid"], 1000);
+        assert_eq!(locked["process"]["user"]["gid"], 3000);
+        assert_eq!(locked["root"]["readonly"], true);
+        assert_eq!(caps(&locked), ["CAP_NET_BIND_SERVICE"]);
+        assert_eq!(
+            locked["process"]["capabilities"]["ambient"],
+            serde_json::json!(["CAP_NET_BIND_SERVICE"])
+        );
+
+        // Dropping single capabilities keeps the rest.
+        let dropped = config(SecurityContext {
+            capabilities: Capabilities {
+                add: vec!["CAP_SYS_PTRACE".to_string()],
+                drop: vec!["NET_RAW".to_string(), "CAP_SYS_ADMIN".to_string()],
+            },
+            no_new_privileges: Some(false),
+            ..Default::default()
+        })
+        .unwrap();
+        let dropped_caps = caps(&dropped);
+        assert_eq!(dropped_caps.len(), DEFAULT_CAPABILITIES.len() - 1);
+        assert!(!dropped_caps.contains(&"CAP_NET_RAW".to_string()));
+        assert!(dropped_caps.contains(&"CAP_SYS_PTRACE".to_string()));
+        assert_eq!(dropped["process"]["noNewPrivileges"], false);
+
+        // Privileged: every capability, host devices, nothing masked.
+        let privileged = config(SecurityContext {
+            privileged: true,
+            capabilities: Capabilities {
+                add: vec![],
+                drop: vec!["ALL".to_string()],
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(caps(&privileged).len(), ALL_CAPABILITIES.len());
+        assert_eq!(privileged["process"]["noNewPrivileges"], false);
+        assert!(privileged["linux"].get("maskedPaths").is_none());
+        assert!(privileged["linux"].get("readonlyPaths").is_none());
+        let mounts = privileged["mounts"].as_array().unwrap();
+        let dev = mounts.iter().find(|m| m["destination"] == "/dev").unwrap();
+        assert_eq!(dev["type"], "bind");
+        let sys = mounts.iter().find(|m| m["destination"] == "/sys").unwrap();
+        assert_eq!(sys["options"][3], "rw");
+
+        // run_as_non_root without a user to run as.
+        let err = config(SecurityContext {
+            run_as_non_root: true,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("run_as_non_root"), "{}", err);
+    }
 }
 #[cfg(test)]
 use flate2::Compression;
@@ -801,3 +1601,453 @@ async fn test_extract_hard_links_deferred() {
     }
     let _ = std::fs::remove_dir_all(&tmp_base);
 }
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_extract_layers_in_index_order() {
+    let tmp_base = std::env::temp_dir().join(format!(
+        "k3rs-test-layer-order-{}",
+        chrono::Utc::now().timestamp_millis()
+    ));
+    let _ = std::fs::remove_dir_all(&tmp_base);
+    let image_dir = tmp_base.join("image");
+    let layers_dir = image_dir.join("layers");
+    std::fs::create_dir_all(&layers_dir).unwrap();
+
+    // Every layer rewrites the same file; the top layer (10) must win.
+    for i in 0..11 {
+        let file = std::fs::File::create(layers_dir.join(format!("layer_{}.tar.gz", i))).unwrap();
+        let mut tar = Builder::new(GzEncoder::new(file, Compression::default()));
+        let data = format!("layer {}", i);
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "etc/version", data.as_bytes())
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    let rootfs = RootfsManager::extract(&image_dir, &tmp_base.join("container"))
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(rootfs.join("etc/version")).unwrap(),
+        "layer 10"
+    );
+    let _ = std::fs::remove_dir_all(&tmp_base);
+}
+
+/// Write `layers_dir/layer_<index>.tar.gz` holding `entries`: paths ending
+/// in `/` are directories, the rest files with the given contents.
+#[cfg(test)]
+fn write_test_layer(layers_dir: &Path, index: usize, entries: &[(&str, &str)]) {
+    let file = std::fs::File::create(layers_dir.join(format!("layer_{}.tar.gz", index))).unwrap();
+    let mut tar = Builder::new(GzEncoder::new(file, Compression::default()));
+    for (path, data) in entries {
+        let mut header = Header::new_gnu();
+        if path.ends_with('/') {
+            header.set_entry_type(EntryType::Directory);
+            header.set_mode(0o755);
+            header.set_size(0);
+            tar.append_data(&mut header, path, &[][..]).unwrap();
+        } else {
+            header.set_mode(0o644);
+            header.set_size(data.len() as u64);
+            tar.append_data(&mut header, path, data.as_bytes()).unwrap();
+        }
+    }
+    tar.into_inner().unwrap().finish().unwrap();
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_extract_whiteouts() {
+    let tmp_base = std::env::temp_dir().join(format!(
+        "k3rs-test-whiteout-{}",
+        chrono::Utc::now().timestamp_millis()
+    ));
+    let _ = std::fs::remove_dir_all(&tmp_base);
+    let image_dir = tmp_base.join("image");
+    let layers_dir = image_dir.join("layers");
+    std::fs::create_dir_all(&layers_dir).unwrap();
+
+    write_test_layer(
+        &layers_dir,
+        0,
+        &[
+            ("etc/hostname", "base"),
+            ("var/cache/apt/pkgcache.bin", "cache"),
+            ("var/cache/apt/archives/lock", ""),
+            ("usr/share/doc/", ""),
+            ("usr/share/doc/bash/README", "docs"),
+            ("app/", ""),
+            ("app/old.py", "old"),
+            ("app/lib/", ""),
+            ("app/lib/util.py", "old util"),
+        ],
+    );
+    write_test_layer(
+        &layers_dir,
+        1,
+        &[
+            // File and directory whiteouts.
+            ("var/cache/apt/.wh.pkgcache.bin", ""),
+            ("usr/share/.wh.doc", ""),
+            // This layer's own files come before the opaque marker: they
+            // must survive it, while the lower layer's `app/` contents go.
+            ("app/lib/", ""),
+            ("app/lib/new.py", "new util"),
+            ("app/main.py", "new"),
+            ("app/.wh..wh..opq", ""),
+        ],
+    );
+    write_test_layer(&layers_dir, 2, &[("var/cache/apt/pkgcache.bin", "again")]);
+
+    let rootfs = RootfsManager::extract(&image_dir, &tmp_base.join("container"))
+        .await
+        .unwrap();
+
+    let read = |p: &str| std::fs::read_to_string(rootfs.join(p)).ok();
+    assert_eq!(read("etc/hostname").as_deref(), Some("base"));
+    // Whited out in layer 1, recreated in layer 2.
+    assert_eq!(read("var/cache/apt/pkgcache.bin").as_deref(), Some("again"));
+    assert!(rootfs.join("var/cache/apt/archives/lock").exists());
+    assert!(!rootfs.join("usr/share/doc").exists());
+    assert!(rootfs.join("usr/share").is_dir());
+
+    let mut app: Vec<String> = walk(&rootfs.join("app"));
+    app.sort();
+    assert_eq!(app, vec!["lib", "lib/new.py", "main.py"]);
+
+    // No whiteout markers leak into the rootfs.
+    assert!(walk(&rootfs).iter().all(|p| !p.contains(".wh.")));
+    let _ = std::fs::remove_dir_all(&tmp_base);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_whiteouts_stay_inside_the_rootfs() {
+    let tmp_base = std::env::temp_dir().join(format!(
+        "k3rs-test-whiteout-escape-{}",
+        chrono::Utc::now().timestamp_millis()
+    ));
+    let _ = std::fs::remove_dir_all(&tmp_base);
+    let image_dir = tmp_base.join("image");
+    let layers_dir = image_dir.join("layers");
+    std::fs::create_dir_all(&layers_dir).unwrap();
+    // Stands in for the host's /etc.
+    let host = tmp_base.join("host");
+    std::fs::create_dir_all(host.join("etc")).unwrap();
+    std::fs::write(host.join("etc/passwd"), "root:x:0:0").unwrap();
+    std::fs::write(host.join("etc/shadow"), "root:*").unwrap();
+
+    write_test_layer(&layers_dir, 0, &[("etc/", ""), ("etc/passwd", "image")]);
+    let file = std::fs::File::create(layers_dir.join("layer_1.tar.gz")).unwrap();
+    let mut tar = Builder::new(GzEncoder::new(file, Compression::default()));
+    // Symlinked directories, one absolute and one climbing out.
+    let mut header = test_header(EntryType::Symlink, 0o777, 0, 0);
+    tar.append_link(&mut header, "evil", host.join("etc"))
+        .unwrap();
+    let mut header = test_header(EntryType::Symlink, 0o777, 0, 0);
+    tar.append_link(&mut header, "up", "../../../../../../../..")
+        .unwrap();
+    for marker in [
+        "evil/.wh.passwd".to_string(),
+        "evil/.wh..wh..opq".to_string(),
+        format!("up{}/.wh.shadow", host.join("etc").display()),
+    ] {
+        let mut header = test_header(EntryType::Regular, 0o644, 0, 0);
+        tar.append_data(&mut header, marker, &[][..]).unwrap();
+    }
+    tar.into_inner().unwrap().finish().unwrap();
+
+    let rootfs = RootfsManager::extract(&image_dir, &tmp_base.join("container"))
+        .await
+        .unwrap();
+
+    // Resolved inside the rootfs: `evil` is the rootfs's own view of that
+    // path (absent) and `up` stops at the rootfs root.
+    assert_eq!(
+        std::fs::read_to_string(host.join("etc/passwd")).unwrap(),
+        "root:x:0:0"
+    );
+    assert_eq!(
+        std::fs::read_to_string(host.join("etc/shadow")).unwrap(),
+        "root:*"
+    );
+    assert_eq!(
+        std::fs::read_to_string(rootfs.join("etc/passwd")).unwrap(),
+        "image"
+    );
+    let _ = std::fs::remove_dir_all(&tmp_base);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_extract_writes_stay_inside_the_rootfs() {
+    let tmp_base = std::env::temp_dir().join(format!(
+        "k3rs-test-write-escape-{}",
+        chrono::Utc::now().timestamp_millis()
+    ));
+    let _ = std::fs::remove_dir_all(&tmp_base);
+    let image_dir = tmp_base.join("image");
+    let layers_dir = image_dir.join("layers");
+    std::fs::create_dir_all(&layers_dir).unwrap();
+    // Stands in for the host's /etc.
+    let host = tmp_base.join("host");
+    std::fs::create_dir_all(host.join("etc")).unwrap();
+    std::fs::write(host.join("etc/passwd"), "root:x:0:0").unwrap();
+
+    write_test_layer(
+        &layers_dir,
+        0,
+        &[("usr/lib/", ""), ("etc/", ""), ("etc/passwd", "image")],
+    );
+    let file = std::fs::File::create(layers_dir.join("layer_1.tar.gz")).unwrap();
+    let mut tar = Builder::new(GzEncoder::new(file, Compression::default()));
+    let mut header = test_header(EntryType::Symlink, 0o777, 0, 0);
+    tar.append_link(&mut header, "evil", host.join("etc"))
+        .unwrap();
+    // A merged-/usr style link, as real images have.
+    let mut header = test_header(EntryType::Symlink, 0o777, 0, 0);
+    tar.append_link(&mut header, "lib", "usr/lib").unwrap();
+    let data = b"pwned";
+    for path in ["evil/passwd", "evil/cron", "lib/libc.so"] {
+        let mut header = test_header(EntryType::Regular, 0o644, 0, 0);
+        header.set_size(data.len() as u64);
+        tar.append_data(&mut header, path, &data[..]).unwrap();
+    }
+    let mut header = test_header(EntryType::Link, 0o644, 0, 0);
+    tar.append_link(&mut header, "evil/shadow", "etc/passwd")
+        .unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+
+    let rootfs = RootfsManager::extract(&image_dir, &tmp_base.join("container"))
+        .await
+        .unwrap();
+
+    // Nothing was written through the symlink to the host...
+    assert_eq!(
+        std::fs::read_to_string(host.join("etc/passwd")).unwrap(),
+        "root:x:0:0"
+    );
+    assert!(!host.join("etc/cron").exists());
+    assert!(!host.join("etc/shadow").exists());
+    // ...but to where the link points inside the rootfs.
+    let inside = rootfs.join(host.join("etc").strip_prefix("/").unwrap());
+    assert_eq!(std::fs::read(inside.join("cron")).unwrap(), data);
+    assert_eq!(std::fs::read(inside.join("shadow")).unwrap(), b"image");
+    assert_eq!(std::fs::read(rootfs.join("usr/lib/libc.so")).unwrap(), data);
+    let _ = std::fs::remove_dir_all(&tmp_base);
+}
+
+#[cfg(test)]
+#[test]
+fn test_resolve_in_rootfs() {
+    let tmp_base = std::env::temp_dir().join(format!(
+        "k3rs-test-resolve-{}",
+        chrono::Utc::now().timestamp_millis()
+    ));
+    let _ = std::fs::remove_dir_all(&tmp_base);
+    let rootfs = tmp_base.join("rootfs");
+    std::fs::create_dir_all(rootfs.join("usr/lib")).unwrap();
+    std::os::unix::fs::symlink("usr/lib", rootfs.join("lib")).unwrap();
+    std::os::unix::fs::symlink("/", rootfs.join("root-link")).unwrap();
+    std::os::unix::fs::symlink("../../..", rootfs.join("usr/lib/up")).unwrap();
+    std::os::unix::fs::symlink("loop", rootfs.join("loop")).unwrap();
+
+    let resolve = |p: &str| resolve_in_rootfs(&rootfs, Path::new(p)).unwrap();
+    // Relative and absolute links resolve inside the rootfs...
+    assert_eq!(resolve("lib/libc.so"), rootfs.join("usr/lib/libc.so"));
+    assert_eq!(resolve("root-link/etc/passwd"), rootfs.join("etc/passwd"));
+    assert_eq!(resolve("lib/up/etc/passwd"), rootfs.join("etc/passwd"));
+    // ...the last component is left alone...
+    assert_eq!(resolve("lib"), rootfs.join("lib"));
+    assert_eq!(resolve("/../etc/./x"), rootfs.join("etc/x"));
+    // ...and a link cycle is an error.
+    assert!(resolve_in_rootfs(&rootfs, Path::new("loop/x")).is_err());
+    let _ = std::fs::remove_dir_all(&tmp_base);
+}
+
+/// Every path under `dir`, relative to it.
+#[cfg(test)]
+fn walk(dir: &Path) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(current) = stack.pop() {
+        for entry in std::fs::read_dir(&current).unwrap() {
+            let path = entry.unwrap().path();
+            paths.push(
+                path.strip_prefix(dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned(),
+            );
+            if path.is_dir() {
+                stack.push(path);
+            }
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+fn test_header(kind: EntryType, mode: u32, uid: u64, gid: u64) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(kind);
+    header.set_mode(mode);
+    header.set_uid(uid);
+    header.set_gid(gid);
+    header.set_mtime(1_600_000_000);
+    header.set_size(0);
+    header
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_extract_preserves_metadata() {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    let tmp_base = std::env::temp_dir().join(format!(
+        "k3rs-test-metadata-{}",
+        chrono::Utc::now().timestamp_millis()
+    ));
+    let _ = std::fs::remove_dir_all(&tmp_base);
+    let image_dir = tmp_base.join("image");
+    let layers_dir = image_dir.join("layers");
+    std::fs::create_dir_all(&layers_dir).unwrap();
+
+    let file = std::fs::File::create(layers_dir.join("layer_0.tar.gz")).unwrap();
+    let mut tar = Builder::new(GzEncoder::new(file, Compression::default()));
+    let mut header = test_header(EntryType::Directory, 0o700, 999, 999);
+    tar.append_data(&mut header, "var/lib/postgresql/", &[][..])
+        .unwrap();
+    let data = b"#!/bin/sh\n";
+    let mut header = test_header(EntryType::Regular, 0o4755, 0, 0);
+    header.set_size(data.len() as u64);
+    tar.append_data(&mut header, "usr/bin/su", &data[..])
+        .unwrap();
+    let mut header = test_header(EntryType::Regular, 0o640, 101, 102);
+    header.set_size(data.len() as u64);
+    tar.append_data(&mut header, "etc/nginx/nginx.conf", &data[..])
+        .unwrap();
+    // Symlink chain: sh -> busybox -> ../../usr/bin/su
+    let mut header = test_header(EntryType::Symlink, 0o777, 0, 0);
+    tar.append_link(&mut header, "bin/sh", "busybox").unwrap();
+    let mut header = test_header(EntryType::Symlink, 0o777, 0, 0);
+    tar.append_link(&mut header, "bin/busybox", "../usr/bin/su")
+        .unwrap();
+    // Becomes a symlink in the next layer.
+    let mut header = test_header(EntryType::Regular, 0o644, 0, 0);
+    header.set_size(data.len() as u64);
+    tar.append_data(&mut header, "etc/localtime", &data[..])
+        .unwrap();
+    // A symlink pointing outside the rootfs, replaced by a file next layer.
+    let mut header = test_header(EntryType::Symlink, 0o777, 0, 0);
+    tar.append_link(&mut header, "etc/resolv.conf", "/etc/hostname")
+        .unwrap();
+    let mut header = test_header(EntryType::Char, 0o666, 0, 0);
+    header.set_device_major(1).unwrap();
+    header.set_device_minor(3).unwrap();
+    tar.append_data(&mut header, "dev/null", &[][..]).unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+
+    let file = std::fs::File::create(layers_dir.join("layer_1.tar.gz")).unwrap();
+    let mut tar = Builder::new(GzEncoder::new(file, Compression::default()));
+    let mut header = test_header(EntryType::Symlink, 0o777, 0, 0);
+    tar.append_link(&mut header, "etc/localtime", "/usr/share/zoneinfo/UTC")
+        .unwrap();
+    let resolv = b"nameserver 10.43.0.10\n";
+    let mut header = test_header(EntryType::Regular, 0o644, 0, 0);
+    header.set_size(resolv.len() as u64);
+    tar.append_data(&mut header, "etc/resolv.conf", &resolv[..])
+        .unwrap();
+    tar.into_inner().unwrap().finish().unwrap();
+
+    let host_hostname = std::fs::read("/etc/hostname").ok();
+    let rootfs = RootfsManager::extract(&image_dir, &tmp_base.join("container"))
+        .await
+        .unwrap();
+
+    // Symlinks are kept as links, not followed.
+    assert_eq!(
+        std::fs::read_link(rootfs.join("bin/sh")).unwrap(),
+        Path::new("busybox")
+    );
+    assert_eq!(
+        std::fs::read_to_string(rootfs.join("bin/sh")).unwrap(),
+        "#!/bin/sh\n"
+    );
+    assert_eq!(
+        std::fs::read_link(rootfs.join("etc/localtime")).unwrap(),
+        Path::new("/usr/share/zoneinfo/UTC")
+    );
+    // The file replaced the symlink instead of writing through it.
+    let resolv_meta = std::fs::symlink_metadata(rootfs.join("etc/resolv.conf")).unwrap();
+    assert!(resolv_meta.is_file());
+    assert_eq!(std::fs::read("/etc/hostname").ok(), host_hostname);
+
+    let su = std::fs::metadata(rootfs.join("usr/bin/su")).unwrap();
+    assert_eq!(su.mode() & 0o7777, 0o4755);
+    assert_eq!(su.mtime(), 1_600_000_000);
+
+    let conf = std::fs::metadata(rootfs.join("etc/nginx/nginx.conf")).unwrap();
+    let pgdata = std::fs::metadata(rootfs.join("var/lib/postgresql")).unwrap();
+    if is_root() {
+        assert_eq!((conf.uid(), conf.gid()), (101, 102));
+        assert_eq!((pgdata.uid(), pgdata.gid()), (999, 999));
+        let null = std::fs::metadata(rootfs.join("dev/null")).unwrap();
+        assert!(null.file_type().is_char_device());
+        assert_eq!(null.rdev(), libc::makedev(1, 3));
+        assert!(!ownership_record_path(&rootfs).exists());
+    } else {
+        assert_eq!(conf.uid(), host_uid());
+        assert!(!rootfs.join("dev/null").exists());
+        let record = std::fs::read_to_string(ownership_record_path(&rootfs)).unwrap();
+        assert!(record.contains("101:102 etc/nginx/nginx.conf\n"));
+        assert!(record.contains("999:999 var/lib/postgresql/\n"));
+    }
+    let _ = std::fs::remove_dir_all(&tmp_base);
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_write_resolv_conf() {
+    let tmp_base = std::env::temp_dir().join(format!(
+        "k3rs-test-resolv-{}",
+        chrono::Utc::now().timestamp_millis()
+    ));
+    let _ = std::fs::remove_dir_all(&tmp_base);
+    let rootfs = tmp_base.join("rootfs");
+    std::fs::create_dir_all(rootfs.join("etc")).unwrap();
+    let outside = tmp_base.join("host-resolv.conf");
+    std::fs::write(&outside, "nameserver 192.168.1.1\n").unwrap();
+    std::os::unix::fs::symlink(&outside, rootfs.join("etc/resolv.conf")).unwrap();
+
+    let conf = "nameserver fd6b:3372::53\noptions ndots:5\n";
+    write_resolv_conf(&rootfs, conf).await.unwrap();
+    let meta = std::fs::symlink_metadata(rootfs.join("etc/resolv.conf")).unwrap();
+    assert!(meta.is_file());
+    assert_eq!(
+        std::fs::read_to_string(rootfs.join("etc/resolv.conf")).unwrap(),
+        conf
+    );
+    // The symlink target is untouched.
+    assert_eq!(
+        std::fs::read_to_string(&outside).unwrap(),
+        "nameserver 192.168.1.1\n"
+    );
+
+    // A rootfs without /etc gets one.
+    let bare = tmp_base.join("bare");
+    std::fs::create_dir_all(&bare).unwrap();
+    write_resolv_conf(&bare, conf).await.unwrap();
+    assert!(bare.join("etc/resolv.conf").is_file());
+
+    // An /etc symlink is refused.
+    let linked = tmp_base.join("linked");
+    std::fs::create_dir_all(&linked).unwrap();
+    std::os::unix::fs::symlink(&tmp_base, linked.join("etc")).unwrap();
+    assert!(write_resolv_conf(&linked, conf).await.is_err());
+    let _ = std::fs::remove_dir_all(&tmp_base);
+}
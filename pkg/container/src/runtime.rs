@@ -1,13 +1,21 @@
 use anyhow::Result;
+use dashmap::DashMap;
+use pkg_types::error::{ApiError, ErrorReason};
+use pkg_types::pod::{ImagePullPolicy, ResourceRequirements, SecurityContext};
+use pkg_types::validate::validate_container_id;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tracing::{error, info};
+use std::time::Duration;
+use tracing::{debug, error, info};
 
-use crate::backend::{OciBackend, RuntimeBackend};
+use crate::backend::{ExecResult, OciBackend, RuntimeBackend};
 use crate::image::ImageManager;
+use crate::logs::{LogOptions, LogRotation};
+use crate::registry_auth::RegistryKeychain;
 use crate::rootfs::RootfsManager;
 use crate::state::{ContainerState, ContainerStateInfo, ContainerStore};
+use crate::volume::{self, BindMount};
 
 /// Runtime info for tracking which backend a pod is using.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -33,6 +41,10 @@ pub struct ContainerRuntime {
     /// Lazily initialized on first use so the in-memory instance map persists
     /// across create → start → stop → delete calls.
     vm_backend: tokio::sync::OnceCell<Arc<dyn RuntimeBackend>>,
+    /// Rotation of container log files written by OCI backends.
+    log_rotation: LogRotation,
+    /// Exit status of each started container, once its waiter has collected it.
+    exits: DashMap<String, tokio::sync::watch::Receiver<Option<i32>>>,
 }
 
 impl ContainerRuntime {
@@ -41,7 +53,10 @@ impl ContainerRuntime {
     /// Detection priority:
     /// - macOS: VirtualizationBackend (Apple Virtualization.framework microVM)
     /// - Linux: OCI (youki/crun in PATH) → auto-download
-    pub async fn new(data_dir: Option<&str>) -> Result<Self> {
+    ///
+    /// `log_rotation` applies to the log files of OCI containers; VM
+    /// consoles are written by the VMM and not rotated.
+    pub async fn new(data_dir: Option<&str>, log_rotation: LogRotation) -> Result<Self> {
         let data_dir = PathBuf::from(
             data_dir.unwrap_or(&format!("{}/runtime", pkg_constants::paths::DATA_DIR)),
         );
@@ -76,7 +91,7 @@ impl ContainerRuntime {
         // (e.g. "youki") rather than always showing "vm".
         #[cfg(target_os = "linux")]
         let backend: Arc<dyn RuntimeBackend> = {
-            match OciBackend::detect(&data_dir) {
+            match OciBackend::detect(&data_dir).map(|b| b.with_log_rotation(log_rotation)) {
                 Ok(oci) => {
                     info!("Using OCI runtime: {} ({})", oci.name(), oci.version());
                     Arc::new(oci)
@@ -86,7 +101,8 @@ impl ContainerRuntime {
                     info!("No OCI runtime found — attempting auto-download...");
                     match crate::installer::RuntimeInstaller::ensure_runtime(None).await {
                         Ok(path) => {
-                            let oci = OciBackend::new(&path.to_string_lossy(), &data_dir);
+                            let oci = OciBackend::new(&path.to_string_lossy(), &data_dir)
+                                .with_log_rotation(log_rotation);
                             info!(
                                 "Using auto-downloaded runtime: {} ({})",
                                 oci.name(),
@@ -131,8 +147,15 @@ impl ContainerRuntime {
                 }
             }
         };
-        let image_manager = ImageManager::new(&data_dir);
+        Ok(Self::with_backend(backend, data_dir, log_rotation))
+    }
 
+    /// Create a container runtime on an already chosen backend.
+    pub fn with_backend(
+        backend: Arc<dyn RuntimeBackend>,
+        data_dir: PathBuf,
+        log_rotation: LogRotation,
+    ) -> Self {
         // Pre-populate the VM backend cache if the default backend is already a VM backend,
         // so all code paths share the same instance.
         let vm_backend = tokio::sync::OnceCell::new();
@@ -140,13 +163,20 @@ impl ContainerRuntime {
             let _ = vm_backend.set(backend.clone());
         }
 
-        Ok(Self {
+        Self {
+            image_manager: ImageManager::new(&data_dir),
             backend,
-            image_manager,
             data_dir,
             store: ContainerStore::new(),
             vm_backend,
-        })
+            log_rotation,
+            exits: DashMap::new(),
+        }
+    }
+
+    /// Root of the runtime's on-disk state (images, bundles, logs, volumes).
+    pub fn data_dir(&self) -> &std::path::Path {
+        &self.data_dir
     }
 
     /// The name of the active runtime backend.
@@ -187,7 +217,17 @@ impl ContainerRuntime {
 
     // ─── Image Operations ───────────────────────────────────────────
 
-    pub async fn pull_image(&self, image: &str) -> Result<()> {
+    /// Counts of image pulls that went to a registry.
+    pub fn image_pull_stats(&self) -> &crate::image::PullStats {
+        self.image_manager.stats()
+    }
+
+    pub async fn pull_image(
+        &self,
+        image: &str,
+        policy: ImagePullPolicy,
+        keychain: &RegistryKeychain,
+    ) -> Result<()> {
         if self.backend.handles_images() {
             info!(
                 "Skipping OCI image pull (handled by {} backend)",
@@ -195,7 +235,7 @@ impl ContainerRuntime {
             );
             return Ok(());
         }
-        self.image_manager.pull(image).await?;
+        self.image_manager.pull(image, policy, keychain).await?;
         Ok(())
     }
 
@@ -204,15 +244,284 @@ impl ContainerRuntime {
     /// Create a container from an OCI image.
     ///
     /// Full pipeline: pull image → extract rootfs → generate config.json → create via backend.
-    /// Accepts optional environment variables from the pod's `ContainerSpec`.
+    /// Accepts optional environment variables and volume mounts from the pod's `ContainerSpec`.
+    /// `pull_policy` decides whether the image is fetched or taken from the cache;
+    /// `keychain` holds the pod's registry credentials. `namespace` is the
+    /// pod's namespace, for the DNS search path in `/etc/resolv.conf`.
+    /// `resources` are the container's effective limits: OCI runtimes enforce
+    /// them through the container's cgroup, VM backends size the microVM
+    /// from them. `security` is the container's security context.
+    ///
+    /// Fails if `id` is not a valid container ID, or if a container with
+    /// that ID is still live (not stopped or exited): its state is never
+    /// reused.
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_container(
         &self,
         id: &str,
+        namespace: &str,
         image: &str,
         command: &[String],
         env: &HashMap<String, String>,
+        mounts: &[BindMount],
+        resources: &ResourceRequirements,
+        security: &SecurityContext,
+        pull_policy: ImagePullPolicy,
+        keychain: &RegistryKeychain,
         runtime_name: Option<&str>,
     ) -> Result<()> {
+        validate_container_id(id)?;
+        let backend = self.select_backend(runtime_name).await?;
+
+        info!(
+            "Creating container: id={}, image={}, backend={}",
+            id,
+            image,
+            backend.name()
+        );
+
+        let container_dir = self.data_dir.join("containers").join(id);
+        let log_path = self.data_dir.join("logs").join(id).join("stdout.log");
+
+        Self::remove_stale(&backend, id).await?;
+
+        let mut empty_dirs = Vec::new();
+        if backend.handles_images() {
+            // Backend handles images internally (e.g. Docker)
+            backend.create_from_image(id, image, command).await?;
+        } else {
+            let in_vm = backend.name() == "vm";
+            empty_dirs = self
+                .prepare_bundle(
+                    id,
+                    namespace,
+                    image,
+                    command,
+                    env,
+                    mounts,
+                    resources,
+                    security,
+                    pull_policy,
+                    keychain,
+                    in_vm,
+                )
+                .await?
+                .1;
+            backend
+                .create_with_resources(id, &container_dir, resources)
+                .await?;
+        }
+
+        self.store.track(
+            id,
+            image,
+            backend.name(),
+            &container_dir.to_string_lossy(),
+            &log_path.to_string_lossy(),
+        );
+        self.store.set_empty_dirs(
+            id,
+            empty_dirs.iter().map(|d| d.display().to_string()).collect(),
+        );
+
+        info!(
+            "Container {} created successfully via {}",
+            id,
+            backend.name()
+        );
+        Ok(())
+    }
+
+    /// Run a container to completion and return its exit code (init containers).
+    ///
+    /// Builds the bundle like `create_container`, runs it in the foreground
+    /// and records the exit code. The caller removes the container with
+    /// `cleanup_container` afterwards; `emptyDir` data it wrote stays for the
+    /// pod's app containers.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn run_container(
+        &self,
+        id: &str,
+        namespace: &str,
+        image: &str,
+        command: &[String],
+        env: &HashMap<String, String>,
+        mounts: &[BindMount],
+        resources: &ResourceRequirements,
+        security: &SecurityContext,
+        pull_policy: ImagePullPolicy,
+        keychain: &RegistryKeychain,
+        runtime_name: Option<&str>,
+    ) -> Result<i32> {
+        validate_container_id(id)?;
+        let backend = self.select_backend(runtime_name).await?;
+        if backend.handles_images() {
+            anyhow::bail!(
+                "running container {} to completion is not supported by the {} backend",
+                id,
+                backend.name()
+            );
+        }
+        info!(
+            "Running container to completion: id={}, image={}, backend={}",
+            id,
+            image,
+            backend.name()
+        );
+
+        Self::remove_stale(&backend, id).await?;
+        let (container_dir, _) = self
+            .prepare_bundle(
+                id,
+                namespace,
+                image,
+                command,
+                env,
+                mounts,
+                resources,
+                security,
+                pull_policy,
+                keychain,
+                false,
+            )
+            .await?;
+        let log_path = self.data_dir.join("logs").join(id).join("stdout.log");
+        self.store.track(
+            id,
+            image,
+            backend.name(),
+            &container_dir.to_string_lossy(),
+            &log_path.to_string_lossy(),
+        );
+
+        let code = backend.run(id, &container_dir).await?;
+        self.store.set_exit_code(id, code);
+        self.store.update_state(id, ContainerState::Stopped);
+        Ok(code)
+    }
+
+    /// Clear what is left of a previous container with the same ID (e.g.
+    /// from a failed run) before creating it again. A container that is
+    /// still live is an `AlreadyExists` [`ApiError`] instead.
+    ///
+    /// [`ApiError`]: pkg_types::error::ApiError
+    async fn remove_stale(backend: &Arc<dyn RuntimeBackend>, id: &str) -> Result<()> {
+        let Ok(state) = backend.state(id).await else {
+            return Ok(());
+        };
+        if state.status != "stopped" && state.status != "exited" {
+            return Err(ApiError::new(
+                ErrorReason::AlreadyExists,
+                format!(
+                    "container '{}' already exists and is {} on the {} backend",
+                    id,
+                    state.status,
+                    backend.name()
+                ),
+            )
+            .into());
+        }
+        info!(
+            "Container {} exists in stopped/exited state, cleaning up first...",
+            id
+        );
+        let _ = backend.delete(id).await;
+        Ok(())
+    }
+
+    /// Pull image → extract rootfs → create volumes → write config.json and
+    /// `/etc/resolv.conf`. Returns the bundle directory and the `emptyDir`
+    /// directories mounted.
+    ///
+    /// VM guests cannot see host bind mounts: there each `emptyDir` becomes a
+    /// plain directory in the guest rootfs, and `hostPath` is not supported.
+    /// Containers resolve through the cluster DNS VIP, VM guests through the
+    /// link-local DNS proxy address.
+    #[allow(clippy::too_many_arguments)]
+    async fn prepare_bundle(
+        &self,
+        id: &str,
+        namespace: &str,
+        image: &str,
+        command: &[String],
+        env: &HashMap<String, String>,
+        mounts: &[BindMount],
+        resources: &ResourceRequirements,
+        security: &SecurityContext,
+        pull_policy: ImagePullPolicy,
+        keychain: &RegistryKeychain,
+        in_vm: bool,
+    ) -> Result<(PathBuf, Vec<PathBuf>)> {
+        let container_dir = self.data_dir.join("containers").join(id);
+        let (image_dir, rootfs_path) = self
+            .image_manager
+            .pull_and_extract(image, pull_policy, keychain, &container_dir)
+            .await?;
+        let (bind_mounts, empty_dirs) = if in_vm {
+            let root = volume::volumes_root(&self.data_dir);
+            for mount in mounts {
+                if mount.source.starts_with(&root) {
+                    let dest = rootfs_path.join(mount.destination.trim_start_matches('/'));
+                    tokio::fs::create_dir_all(dest).await?;
+                } else {
+                    tracing::warn!(
+                        "Container {}: hostPath mount {} is not supported in VMs, skipping",
+                        id,
+                        mount.destination
+                    );
+                }
+            }
+            (&[][..], Vec::new())
+        } else {
+            (
+                mounts,
+                volume::create_empty_dirs(&self.data_dir, mounts).await?,
+            )
+        };
+        let config_json = RootfsManager::generate_config_full(
+            id,
+            &rootfs_path,
+            command,
+            env,
+            Some(&image_dir),
+            None,
+            crate::rootfs::NetworkMode::default(),
+            bind_mounts,
+            resources,
+            security,
+        )?;
+        tokio::fs::write(container_dir.join("config.json"), &config_json).await?;
+        let nameserver = if in_vm {
+            pkg_constants::network::DNS_PROXY_IPV4
+        } else {
+            pkg_constants::network::DNS_VIP
+        };
+        crate::rootfs::write_resolv_conf(
+            &rootfs_path,
+            &pkg_network::resolv::pod_resolv_conf(nameserver, namespace),
+        )
+        .await?;
+        Ok((container_dir, empty_dirs))
+    }
+
+    /// Host directory backing a pod's `emptyDir` volume.
+    pub fn empty_dir_path(&self, pod_id: &str, volume: &str) -> PathBuf {
+        volume::empty_dir_path(&self.data_dir, pod_id, volume)
+    }
+
+    /// Delete all of a pod's `emptyDir` data, mounted or not.
+    pub async fn remove_pod_volumes(&self, pod_id: &str) -> Result<()> {
+        validate_container_id(pod_id)?;
+        let dir = volume::volumes_root(&self.data_dir).join(pod_id);
+        if dir.exists() {
+            tokio::fs::remove_dir_all(&dir).await?;
+        }
+        Ok(())
+    }
+
+    /// Backend for a new container: the one named by the pod's `runtime`
+    /// field, or the default.
+    async fn select_backend(&self, runtime_name: Option<&str>) -> Result<Arc<dyn RuntimeBackend>> {
         // macOS: always use VM backend — OCI runtimes are not supported.
         #[cfg(target_os = "macos")]
         let backend = {
@@ -248,7 +557,9 @@ impl ContainerRuntime {
 
                 // Try PATH first; auto-download if not found.
                 match OciBackend::with_name(effective_name, &self.data_dir) {
-                    Ok(b) => Arc::new(b) as Arc<dyn RuntimeBackend>,
+                    Ok(b) => {
+                        Arc::new(b.with_log_rotation(self.log_rotation)) as Arc<dyn RuntimeBackend>
+                    }
                     Err(_) => {
                         info!(
                             "OCI runtime {} not found in PATH or install dir — attempting auto-download...",
@@ -261,10 +572,10 @@ impl ContainerRuntime {
                         .map_err(|e| {
                             anyhow::anyhow!("Auto-download failed for {}: {}", effective_name, e)
                         })?;
-                        Arc::new(OciBackend::new(
-                            &downloaded.to_string_lossy(),
-                            &self.data_dir,
-                        )) as Arc<dyn RuntimeBackend>
+                        Arc::new(
+                            OciBackend::new(&downloaded.to_string_lossy(), &self.data_dir)
+                                .with_log_rotation(self.log_rotation),
+                        ) as Arc<dyn RuntimeBackend>
                     }
                 }
             } else {
@@ -275,66 +586,7 @@ impl ContainerRuntime {
             self.backend.clone()
         };
 
-        info!(
-            "Creating container: id={}, image={}, backend={}",
-            id,
-            image,
-            backend.name()
-        );
-
-        let container_dir = self.data_dir.join("containers").join(id);
-        let log_path = self.data_dir.join("logs").join(id).join("stdout.log");
-
-        // Best-effort cleanup in case old state exists (e.g. from a previous failed run)
-        // to avoid "container already exists" errors.
-        if let Ok(state) = backend.state(id).await
-            && (state.status == "stopped" || state.status == "exited")
-        {
-            info!(
-                "Container {} exists in stopped/exited state, cleaning up first...",
-                id
-            );
-            let _ = backend.delete(id).await;
-        }
-
-        if backend.handles_images() {
-            // Backend handles images internally (e.g. Docker)
-            backend.create_from_image(id, image, command).await?;
-        } else {
-            // Pull image → extract rootfs → create bundle → create via backend
-            let image_dir = self.image_manager.pull(image).await?;
-
-            tokio::fs::create_dir_all(&container_dir).await?;
-
-            let rootfs_path = RootfsManager::extract(&image_dir, &container_dir).await?;
-            let config_json = RootfsManager::generate_config_full(
-                id,
-                &rootfs_path,
-                command,
-                env,
-                Some(&image_dir),
-                None,
-                crate::rootfs::NetworkMode::default(),
-            )?;
-            tokio::fs::write(container_dir.join("config.json"), &config_json).await?;
-
-            backend.create(id, &container_dir).await?;
-        }
-
-        self.store.track(
-            id,
-            image,
-            backend.name(),
-            &container_dir.to_string_lossy(),
-            &log_path.to_string_lossy(),
-        );
-
-        info!(
-            "Container {} created successfully via {}",
-            id,
-            backend.name()
-        );
-        Ok(())
+        Ok(backend)
     }
 
     /// Get or lazily initialize the cached VM backend.
@@ -372,7 +624,7 @@ impl ContainerRuntime {
             } else if (entry.runtime_name == "youki" || entry.runtime_name == "crun")
                 && let Ok(oci) = OciBackend::with_name(&entry.runtime_name, &self.data_dir)
             {
-                return Arc::new(oci);
+                return Arc::new(oci.with_log_rotation(self.log_rotation));
             }
         }
         // Fallback to default backend
@@ -381,17 +633,35 @@ impl ContainerRuntime {
 
     /// Start a created container.
     pub async fn start_container(&self, id: &str) -> Result<()> {
+        validate_container_id(id)?;
         let backend = self.get_backend_for_container(id).await;
         backend.start(id).await?;
         self.store.update_state(id, ContainerState::Running);
         info!("Container {} started via {}", id, backend.name());
+
+        // Collect the exit status in the background; a backend that can't
+        // report it drops the sender and leaves the code unknown.
+        let (tx, rx) = tokio::sync::watch::channel(None);
+        self.exits.insert(id.to_string(), rx);
+        let store = self.store.clone();
+        let id = id.to_string();
+        tokio::spawn(async move {
+            match backend.wait(&id).await {
+                Ok(code) => {
+                    store.set_exit_code(&id, code);
+                    let _ = tx.send(Some(code));
+                }
+                Err(e) => debug!("No exit status for container {}: {}", id, e),
+            }
+        });
         Ok(())
     }
 
-    /// Stop and delete a container.
-    pub async fn stop_container(&self, id: &str) -> Result<()> {
+    /// Stop and delete a container, giving it `grace` to exit after SIGTERM.
+    pub async fn stop_container(&self, id: &str, grace: Duration) -> Result<()> {
+        validate_container_id(id)?;
         let backend = self.get_backend_for_container(id).await;
-        backend.stop(id).await?;
+        backend.stop(id, grace).await?;
         backend.delete(id).await?;
         self.store.update_state(id, ContainerState::Stopped);
         info!(
@@ -447,6 +717,10 @@ impl ContainerRuntime {
         );
         let ids = backend.list().await.unwrap_or_default();
         for id in ids {
+            if let Err(e) = validate_container_id(&id) {
+                tracing::warn!("Skipping container from {}: {}", backend.name(), e);
+                continue;
+            }
             match backend.state(&id).await {
                 Ok(state_info) => {
                     if state_info.status == "running" || state_info.status == "created" {
@@ -484,14 +758,28 @@ impl ContainerRuntime {
         }
     }
 
-    /// Get logs from a container.
-    pub async fn container_logs(&self, id: &str, tail: usize) -> Result<Vec<String>> {
+    /// Get logs from a container, filtered by `opts`.
+    pub async fn container_logs(&self, id: &str, opts: &LogOptions) -> Result<Vec<String>> {
+        validate_container_id(id)?;
+        let backend = self.get_backend_for_container(id).await;
+        backend.logs(id, opts).await
+    }
+
+    /// Stream a container's log lines; with `follow`, keep yielding new output.
+    pub async fn container_logs_stream(
+        &self,
+        id: &str,
+        follow: bool,
+        opts: &LogOptions,
+    ) -> Result<crate::logs::LogStream> {
+        validate_container_id(id)?;
         let backend = self.get_backend_for_container(id).await;
-        backend.logs(id, tail).await
+        backend.logs_stream(id, follow, opts).await
     }
 
     /// Execute a command inside a running container.
-    pub async fn exec_in_container(&self, id: &str, command: &[&str]) -> Result<String> {
+    pub async fn exec_in_container(&self, id: &str, command: &[&str]) -> Result<ExecResult> {
+        validate_container_id(id)?;
         let backend = self.get_backend_for_container(id).await;
         backend.exec(id, command).await
     }
@@ -504,36 +792,109 @@ impl ContainerRuntime {
         command: &[&str],
         tty: bool,
     ) -> Result<tokio::process::Child> {
+        validate_container_id(id)?;
         let backend = self.get_backend_for_container(id).await;
         backend.spawn_exec(id, command, tty).await
     }
 
+    /// Open a TCP connection to `port` on a running container's loopback
+    /// interface: from inside its network namespace for OCI containers,
+    /// through k3rs-init over vsock for microVMs.
+    pub async fn port_forward(
+        &self,
+        id: &str,
+        port: u16,
+    ) -> Result<Box<dyn crate::backend::ForwardStream>> {
+        validate_container_id(id)?;
+        let backend = self.get_backend_for_container(id).await;
+        backend.port_forward(id, port).await
+    }
+
+    /// Stream `path` out of a running container as a tar archive.
+    pub async fn copy_from_container(
+        &self,
+        id: &str,
+        path: &str,
+    ) -> Result<crate::archive::ArchiveStream> {
+        let command = crate::archive::tar_create_command(path)?;
+        let command: Vec<&str> = command.iter().map(String::as_str).collect();
+        let child = self.spawn_exec_in_container(id, &command, false).await?;
+        crate::archive::read_archive(child, path).await
+    }
+
+    /// Unpack a tar archive into the parent directory of `path` inside a
+    /// running container. Returns the number of archive bytes written.
+    pub async fn copy_into_container<S, B, E>(
+        &self,
+        id: &str,
+        path: &str,
+        archive: S,
+    ) -> Result<u64>
+    where
+        S: tokio_stream::Stream<Item = std::result::Result<B, E>>,
+        B: AsRef<[u8]>,
+        E: std::fmt::Display,
+    {
+        // A guest exec can report tar's status only in-band, after its output
+        // (see `ExitTrailerFilter`), which `write_archive` does not look for:
+        // a failed extraction would pass for a success.
+        if self.backend_name_for(id) == "vm" {
+            anyhow::bail!("copying into microVM containers is not supported yet");
+        }
+        let command = crate::archive::tar_extract_command(path)?;
+        let command: Vec<&str> = command.iter().map(String::as_str).collect();
+        let child = self.spawn_exec_in_container(id, &command, false).await?;
+        crate::archive::write_archive(child, path, archive).await
+    }
+
     /// Return the main process PID of a running container.
     ///
     /// Reads the pid file that the OCI runtime wrote at `create` time.
-    /// Returns `None` if the pid file is missing or unparseable.
+    /// Returns `None` for an invalid ID, or if the pid file is missing or
+    /// unparseable.
     pub fn container_pid(&self, id: &str) -> Option<u32> {
+        validate_container_id(id).ok()?;
         let pid_file = self.data_dir.join("logs").join(id).join("container.pid");
         std::fs::read_to_string(&pid_file)
             .ok()
             .and_then(|s| s.trim().parse().ok())
     }
 
+    /// Exit code of a stopped container, if the backend reported one.
+    pub async fn container_exit_code(&self, id: &str) -> Option<i32> {
+        if let Some(code) = self.store.get(id).and_then(|entry| entry.exit_code) {
+            return Some(code);
+        }
+        // The runtime can report a container stopped just before its
+        // waiter has reaped it.
+        let mut rx = self.exits.get(id)?.clone();
+        let wait = Duration::from_millis(pkg_constants::runtime::EXIT_STATUS_WAIT_MS);
+        *tokio::time::timeout(wait, rx.wait_for(Option::is_some))
+            .await
+            .ok()?
+            .ok()?
+    }
+
     /// Query the real OCI runtime state of a container.
     pub async fn container_state(&self, id: &str) -> Result<ContainerStateInfo> {
+        validate_container_id(id)?;
         let backend = self.get_backend_for_container(id).await;
         backend.state(id).await
     }
 
     /// Full cleanup: stop + delete + remove from store + cleanup container dir.
     pub async fn cleanup_container(&self, id: &str) -> Result<()> {
+        validate_container_id(id)?;
         let backend = self.get_backend_for_container(id).await;
-        // Best-effort stop and delete
-        let _ = backend.stop(id).await;
+        // Best-effort stop and delete; nothing here is worth waiting for
+        let _ = backend.stop(id, Duration::ZERO).await;
         let _ = backend.delete(id).await;
 
-        // Remove from store
-        self.store.remove(id);
+        self.exits.remove(id);
+        // Remove from store, then any emptyDir no other container still mounts
+        if let Some(entry) = self.store.remove(id) {
+            volume::release_empty_dirs(&self.store, &entry.empty_dirs).await;
+        }
 
         // Clean up container directory
         let container_dir = self.data_dir.join("containers").join(id);
@@ -597,3 +958,63 @@ impl ContainerRuntime {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{stub_runtime, temp_dir};
+    use std::path::Path;
+
+    /// An OCI runtime that answers `state <id>` with `<dir>/<id>.state`
+    /// (failing when there is none) and records `delete` as `<dir>/<id>.deleted`.
+    fn stub_backend(dir: &Path) -> Arc<dyn RuntimeBackend> {
+        let path = stub_runtime(
+            dir,
+            "#!/bin/sh\n\
+             dir=$(dirname \"$0\")\n\
+             while [ $# -gt 0 ]; do\n\
+               case \"$1\" in\n\
+                 state) exec cat \"$dir/$2.state\" 2>/dev/null ;;\n\
+                 delete) touch \"$dir/$3.deleted\"; exit 0 ;;\n\
+                 *) shift ;;\n\
+               esac\n\
+             done\n",
+        );
+        Arc::new(OciBackend::new(path.to_str().unwrap(), dir))
+    }
+
+    fn set_state(dir: &Path, id: &str, status: &str) {
+        let state = serde_json::json!({ "id": id, "status": status, "pid": 42 });
+        std::fs::write(dir.join(format!("{}.state", id)), state.to_string()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_live_duplicate_is_rejected() {
+        let dir = temp_dir("duplicate");
+        let backend = stub_backend(&dir);
+
+        for status in ["created", "running"] {
+            set_state(&dir, "pod-app", status);
+            let err = ContainerRuntime::remove_stale(&backend, "pod-app")
+                .await
+                .unwrap_err();
+            let err = err.downcast_ref::<ApiError>().expect("an ApiError");
+            assert_eq!(err.reason, ErrorReason::AlreadyExists);
+            assert!(err.message.contains(status), "{}", err.message);
+        }
+        assert!(!dir.join("pod-app.deleted").exists());
+
+        // What a stopped container left behind is cleared instead.
+        set_state(&dir, "pod-app", "stopped");
+        ContainerRuntime::remove_stale(&backend, "pod-app")
+            .await
+            .unwrap();
+        assert!(dir.join("pod-app.deleted").exists());
+
+        // Nothing to clear.
+        ContainerRuntime::remove_stale(&backend, "pod-new")
+            .await
+            .unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
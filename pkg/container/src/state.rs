@@ -56,6 +56,9 @@ pub struct ContainerEntry {
     pub started_at: Option<DateTime<Utc>>,
     /// When the container finished (transitioned to Stopped/Failed).
     pub finished_at: Option<DateTime<Utc>>,
+    /// Host directories of the `emptyDir` volumes this container mounts.
+    #[serde(default)]
+    pub empty_dirs: Vec<String>,
 }
 
 /// Concurrent in-memory container state store.
@@ -96,6 +99,7 @@ impl ContainerStore {
             created_at: Utc::now(),
             started_at: None,
             finished_at: None,
+            empty_dirs: Vec::new(),
         };
         self.containers.insert(id.to_string(), entry);
     }
@@ -133,6 +137,13 @@ impl ContainerStore {
         }
     }
 
+    /// Record the `emptyDir` directories a container mounts.
+    pub fn set_empty_dirs(&self, id: &str, dirs: Vec<String>) {
+        if let Some(mut entry) = self.containers.get_mut(id) {
+            entry.empty_dirs = dirs;
+        }
+    }
+
     /// Get a snapshot of a container's entry.
     pub fn get(&self, id: &str) -> Option<ContainerEntry> {
         self.containers.get(id).map(|e| e.clone())
@@ -148,6 +159,14 @@ impl ContainerStore {
         self.containers.remove(id).map(|(_, e)| e)
     }
 
+    /// Number of tracked containers currently in the `Running` state.
+    pub fn running_count(&self) -> usize {
+        self.containers
+            .iter()
+            .filter(|e| e.value().state == ContainerState::Running)
+            .count()
+    }
+
     /// Number of tracked containers.
     pub fn len(&self) -> usize {
         self.containers.len()
@@ -181,6 +200,27 @@ pub struct ContainerStateInfo {
     /// Bundle path.
     #[serde(default)]
     pub bundle: String,
+    /// vCPUs of the container's microVM (VM backends only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcpus: Option<u32>,
+    /// Memory of the container's microVM in MB (VM backends only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_mb: Option<u64>,
+    /// Uptime and host-side usage of the container's microVM (VM backends
+    /// that can report it).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<VmUsage>,
+}
+
+/// What a running microVM costs the host, measured on its VMM process.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct VmUsage {
+    /// Seconds since the VM started.
+    pub uptime_secs: u64,
+    /// User + system CPU time of the VMM process.
+    pub cpu_usage_usec: u64,
+    /// Resident memory of the VMM process.
+    pub rss_bytes: u64,
 }
 
 // ─── Tests ─────────────────────────────────────────────────────
@@ -233,6 +273,19 @@ mod tests {
         assert!(entry.finished_at.is_some());
     }
 
+    #[test]
+    fn test_running_count() {
+        let store = ContainerStore::new();
+        for id in ["a", "b", "c"] {
+            store.track(id, "alpine:latest", "crun", "/tmp/bundle", "/tmp/log");
+        }
+        assert_eq!(store.running_count(), 0);
+        store.update_state("a", ContainerState::Running);
+        store.update_state("b", ContainerState::Running);
+        store.update_state("b", ContainerState::Stopped);
+        assert_eq!(store.running_count(), 1);
+    }
+
     #[test]
     fn test_failed_state() {
         let store = ContainerStore::new();
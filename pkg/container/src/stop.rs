@@ -0,0 +1,188 @@
+//! Graceful stop: SIGTERM, wait for the workload to exit, SIGKILL on timeout.
+
+use std::time::Duration;
+
+/// How a graceful stop ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopOutcome {
+    /// The workload exited on its own within the grace period.
+    Exited,
+    /// The grace period ran out (or was zero) and SIGKILL was sent.
+    Killed,
+}
+
+/// Ask a workload to stop and escalate only if it doesn't.
+///
+/// Awaits `term`, then polls `is_running` every
+/// [`STOP_POLL_INTERVAL_MS`](pkg_constants::runtime::STOP_POLL_INTERVAL_MS)
+/// until it reports `false` or `grace` elapses, and only then awaits `kill`.
+/// A zero grace period skips `term` and kills straight away.
+pub async fn graceful_stop<T, R, F, K>(
+    grace: Duration,
+    term: T,
+    mut is_running: R,
+    kill: K,
+) -> StopOutcome
+where
+    T: Future<Output = ()>,
+    R: FnMut() -> F,
+    F: Future<Output = bool>,
+    K: Future<Output = ()>,
+{
+    if !is_running().await {
+        return StopOutcome::Exited;
+    }
+    if grace.is_zero() {
+        kill.await;
+        return StopOutcome::Killed;
+    }
+
+    term.await;
+    let poll = Duration::from_millis(pkg_constants::runtime::STOP_POLL_INTERVAL_MS);
+    let deadline = tokio::time::Instant::now() + grace;
+    loop {
+        if !is_running().await {
+            return StopOutcome::Exited;
+        }
+        let now = tokio::time::Instant::now();
+        if now >= deadline {
+            break;
+        }
+        tokio::time::sleep(poll.min(deadline - now)).await;
+    }
+    kill.await;
+    StopOutcome::Killed
+}
+
+/// Send `signal` to a host process, ignoring errors (it may already be gone).
+pub fn signal_pid(pid: u32, signal: libc::c_int) {
+    unsafe {
+        libc::kill(pid as libc::pid_t, signal);
+    }
+}
+
+/// Whether a host process is still running: it exists (`kill(pid, 0)`, no
+/// signal sent) and, where `/proc` tells, is not a zombie waiting to be
+/// reaped, which has exited as far as a stop is concerned.
+pub fn pid_alive(pid: u32) -> bool {
+    if unsafe { libc::kill(pid as libc::pid_t, 0) } != 0 {
+        return false;
+    }
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => !matches!(proc_state(&stat), Some('Z' | 'X')),
+        Err(_) => true,
+    }
+}
+
+/// The state letter in a `/proc/<pid>/stat` line. It follows the command
+/// name, which is in parentheses and may itself contain `)`.
+fn proc_state(stat: &str) -> Option<char> {
+    stat.rsplit_once(')')?.1.trim_start().chars().next()
+}
+
+/// [`graceful_stop`] for a plain host process such as a VMM.
+pub async fn stop_pid(pid: u32, grace: Duration) -> StopOutcome {
+    graceful_stop(
+        grace,
+        async { signal_pid(pid, libc::SIGTERM) },
+        || std::future::ready(pid_alive(pid)),
+        async { signal_pid(pid, libc::SIGKILL) },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tokio::process::{Child, Command};
+
+    fn spawn_sh(script: &str) -> Child {
+        Command::new("sh").args(["-c", script]).spawn().unwrap()
+    }
+
+    /// Stop a child process; `try_wait` reaps it so exit is seen immediately.
+    async fn stop_child(child: &mut Child, grace: Duration, term_sent: &AtomicBool) -> StopOutcome {
+        let pid = child.id().unwrap();
+        graceful_stop(
+            grace,
+            async {
+                term_sent.store(true, Ordering::SeqCst);
+                signal_pid(pid, libc::SIGTERM);
+            },
+            || std::future::ready(matches!(child.try_wait(), Ok(None))),
+            async { signal_pid(pid, libc::SIGKILL) },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_exits_on_sigterm_without_kill() {
+        let mut child = spawn_sh("trap 'exit 0' TERM; while :; do sleep 0.05; done");
+        // Give the shell a moment to install its trap.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let term_sent = AtomicBool::new(false);
+        let started = std::time::Instant::now();
+
+        let outcome = stop_child(&mut child, Duration::from_secs(10), &term_sent).await;
+
+        assert_eq!(outcome, StopOutcome::Exited);
+        assert!(term_sent.load(Ordering::SeqCst));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(child.wait().await.unwrap().code(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_ignored_sigterm_is_killed_after_grace() {
+        let mut child = spawn_sh("trap '' TERM; while :; do sleep 0.05; done");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let term_sent = AtomicBool::new(false);
+        let started = std::time::Instant::now();
+
+        let outcome = stop_child(&mut child, Duration::from_millis(500), &term_sent).await;
+
+        assert_eq!(outcome, StopOutcome::Killed);
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(child.wait().await.unwrap().signal(), Some(libc::SIGKILL));
+    }
+
+    #[test]
+    fn test_proc_state() {
+        assert_eq!(proc_state("42 (sleep) S 1 42 42 0"), Some('S'));
+        assert_eq!(proc_state("42 (a) b) Z 1 42 42 0"), Some('Z'));
+        assert_eq!(proc_state("garbage"), None);
+    }
+
+    #[tokio::test]
+    async fn test_unreaped_exit_is_not_alive() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "trap 'exit 0' TERM; while :; do sleep 0.05; done"])
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(pid_alive(pid));
+        let started = std::time::Instant::now();
+
+        // Nothing reaps the child while it stops, so it lingers as a zombie.
+        let outcome = stop_pid(pid, Duration::from_secs(10)).await;
+
+        assert_eq!(outcome, StopOutcome::Exited);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(child.wait().unwrap().code(), Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_zero_grace_kills_immediately() {
+        let mut child = spawn_sh("trap 'exit 0' TERM; while :; do sleep 0.05; done");
+        let term_sent = AtomicBool::new(false);
+
+        let outcome = stop_child(&mut child, Duration::ZERO, &term_sent).await;
+
+        assert_eq!(outcome, StopOutcome::Killed);
+        assert!(!term_sent.load(Ordering::SeqCst));
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(child.wait().await.unwrap().signal(), Some(libc::SIGKILL));
+    }
+}
@@ -0,0 +1,29 @@
+//! Fixtures shared by the tests of this crate and of the crates built on it,
+//! which enable them with the `test-util` feature.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// A fresh, empty directory under the system temp dir, unique to this call.
+pub fn temp_dir(name: &str) -> PathBuf {
+    static NEXT: AtomicU32 = AtomicU32::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "k3rs-test-{}-{}-{}",
+        name,
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Install `script` as `dir/stub-runtime`, an executable standing in for
+/// the OCI runtime (or any other binary a test spawns). Returns its path.
+pub fn stub_runtime(dir: &Path, script: &str) -> PathBuf {
+    let path = dir.join("stub-runtime");
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
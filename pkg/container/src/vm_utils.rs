@@ -3,11 +3,82 @@
 //! Contains functions and types used by both platform-specific backends:
 //! - `find_k3rs_init()`: Locate the k3rs-init binary for guest injection
 //! - `parse_bundle_config()`: Parse OCI bundle config.json for entrypoint/env
+//! - `parse_bundle_privileges()`: The user, rlimits and noNewPrivileges k3rs-init applies
 //! - `VmNetworkConfig`: VPC networking parameters for a VM
+//! - `needs_resolv_conf()`: Whether the guest rootfs still needs a resolv.conf
+//! - `VmConfig`: vCPU/memory sizing of a VM, derived from container limits
+//! - `vmm_boot_pid()` / `is_vmm_boot()`: Find and verify k3rs-vmm boot processes
+//! - `split_exit_trailer()` / `ExitTrailerFilter`: The exit status k3rs-init
+//!   appends to an exec connection
+//! - `stdin_frame()`: Framing of the stdin a host passes to a guest exec
 
 use std::path::{Path, PathBuf};
 
 use pkg_constants::paths::DATA_DIR;
+use pkg_constants::runtime::{DEFAULT_CPU_COUNT, DEFAULT_MEMORY_MB, MIN_VM_MEMORY_MB};
+use pkg_constants::vm::{VSOCK_EXIT_TRAILER, VSOCK_STDIN_MAX_FRAME};
+use pkg_types::pod::ResourceRequirements;
+
+/// Per-VM resource configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VmConfig {
+    /// Number of vCPUs
+    pub cpu_count: u32,
+    /// Memory in megabytes
+    pub memory_mb: u64,
+}
+
+impl Default for VmConfig {
+    fn default() -> Self {
+        Self {
+            cpu_count: DEFAULT_CPU_COUNT,
+            memory_mb: DEFAULT_MEMORY_MB,
+        }
+    }
+}
+
+impl VmConfig {
+    /// Size a VM for a container's `limits`: CPU millis rounded up to whole
+    /// vCPUs, memory bytes rounded up to MiB (at least `MIN_VM_MEMORY_MB`).
+    /// A zero field falls back to `defaults`; the result never exceeds
+    /// `capacity`.
+    pub fn for_limits(
+        limits: &ResourceRequirements,
+        defaults: &VmConfig,
+        capacity: &VmConfig,
+    ) -> Self {
+        let cpu_count = match limits.cpu_millis {
+            0 => defaults.cpu_count,
+            millis => u32::try_from(millis.div_ceil(1000)).unwrap_or(u32::MAX),
+        };
+        let memory_mb = match limits.memory_bytes {
+            0 => defaults.memory_mb,
+            bytes => bytes.div_ceil(1024 * 1024).max(MIN_VM_MEMORY_MB),
+        };
+        Self {
+            cpu_count: cpu_count.clamp(1, capacity.cpu_count.max(1)),
+            memory_mb: memory_mb.min(capacity.memory_mb),
+        }
+    }
+
+    /// CPUs and physical memory of this node.
+    pub fn node_capacity() -> Self {
+        let cpu_count = std::thread::available_parallelism()
+            .map(|n| n.get() as u32)
+            .unwrap_or(DEFAULT_CPU_COUNT);
+        let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        let memory_mb = if pages > 0 && page_size > 0 {
+            (pages as u64 * page_size as u64) / (1024 * 1024)
+        } else {
+            u64::MAX
+        };
+        Self {
+            cpu_count,
+            memory_mb,
+        }
+    }
+}
 
 /// VPC networking parameters for a VM (passed to k3rs-vmm as CLI args on macOS,
 /// or to the guest kernel cmdline on Linux/Firecracker).
@@ -54,6 +125,15 @@ pub(crate) fn find_k3rs_init() -> Option<PathBuf> {
     None
 }
 
+/// Whether `resolv_conf` (a path in the guest rootfs) is missing or empty.
+/// Symlinks count as present; writing through them would hit the host.
+pub(crate) async fn needs_resolv_conf(resolv_conf: &Path) -> bool {
+    match tokio::fs::symlink_metadata(resolv_conf).await {
+        Ok(meta) => meta.is_file() && meta.len() == 0,
+        Err(_) => true,
+    }
+}
+
 fn try_path(s: &str) -> Option<PathBuf> {
     let p = PathBuf::from(s);
     if p.exists() { Some(p) } else { None }
@@ -92,10 +172,249 @@ pub(crate) fn parse_bundle_config(bundle: &Path) -> (Vec<String>, Vec<String>) {
     (command, env)
 }
 
+/// The `process` fields of an OCI bundle's config.json that k3rs-init
+/// applies before exec: `user`, `rlimits` and `noNewPrivileges`. Copied
+/// as-is into the guest config.json; empty when the bundle has none.
+pub(crate) fn parse_bundle_privileges(bundle: &Path) -> serde_json::Map<String, serde_json::Value> {
+    let mut privileges = serde_json::Map::new();
+    let Some(v) = std::fs::read_to_string(bundle.join("config.json"))
+        .ok()
+        .and_then(|d| serde_json::from_str::<serde_json::Value>(&d).ok())
+    else {
+        return privileges;
+    };
+    for key in ["user", "rlimits", "noNewPrivileges"] {
+        if let Some(value) = v["process"].get(key) {
+            privileges.insert(key.to_string(), value.clone());
+        }
+    }
+    privileges
+}
+
+/// PID of the running `k3rs-vmm boot` for `id`, from the pidfile it keeps
+/// at `{DATA_DIR}/runtime/vms/vmm-{id}.pid`. k3rs-vmm owns that file and
+/// removes it when stale; this only reads it.
+pub fn vmm_boot_pid(id: &str) -> Option<u32> {
+    let path = format!("{}/runtime/vms/vmm-{}.pid", DATA_DIR, id);
+    let pid = std::fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    is_vmm_boot(pid, id).then_some(pid)
+}
+
+/// Whether `pid` is alive and is `k3rs-vmm boot` for exactly `id`. Guards
+/// against a PID reused by another process since it was recorded, and
+/// against a VM whose ID only starts with `id`.
+pub fn is_vmm_boot(pid: u32, id: &str) -> bool {
+    // Signal 0 checks existence without signalling.
+    let alive = unsafe { libc::kill(pid as libc::pid_t, 0) == 0 };
+    alive && process_args(pid).is_some_and(|args| is_vmm_boot_args(&args, id))
+}
+
+fn is_vmm_boot_args(args: &[String], id: &str) -> bool {
+    // An interpreter may come first (`sh .../k3rs-vmm boot ...`).
+    let Some(bin) = args
+        .iter()
+        .position(|a| Path::new(a).file_name().is_some_and(|n| n == "k3rs-vmm"))
+    else {
+        return false;
+    };
+    let rest = &args[bin + 1..];
+    rest.first().is_some_and(|a| a == "boot")
+        && (rest.windows(2).any(|w| w[0] == "--id" && w[1] == id)
+            || rest.iter().any(|a| a.strip_prefix("--id=") == Some(id)))
+}
+
+/// Arguments of a running process, via `sysctl(KERN_PROCARGS2)`.
+#[cfg(target_os = "macos")]
+fn process_args(pid: u32) -> Option<Vec<String>> {
+    let mut mib = [libc::CTL_KERN, libc::KERN_PROCARGS2, pid as libc::c_int];
+    let mut size: libc::size_t = 0;
+    let null = std::ptr::null_mut();
+    if unsafe { libc::sysctl(mib.as_mut_ptr(), 3, null, &mut size, null, 0) } != 0 {
+        return None;
+    }
+    let mut buf = vec![0u8; size];
+    let ptr = buf.as_mut_ptr().cast();
+    if unsafe { libc::sysctl(mib.as_mut_ptr(), 3, ptr, &mut size, null, 0) } != 0 {
+        return None;
+    }
+    buf.truncate(size);
+    parse_procargs2(&buf)
+}
+
+/// Split a `KERN_PROCARGS2` buffer: `argc (i32) | exec path | NUL padding |
+/// argv... | env...`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_procargs2(buf: &[u8]) -> Option<Vec<String>> {
+    let argc = i32::from_ne_bytes(buf.get(..4)?.try_into().ok()?) as usize;
+    let rest = &buf[4..];
+    let path_end = rest.iter().position(|&b| b == 0)?;
+    let start = path_end + rest[path_end..].iter().position(|&b| b != 0)?;
+    let args: Vec<String> = rest[start..]
+        .split(|&b| b == 0)
+        .take(argc)
+        .map(|a| String::from_utf8_lossy(a).into_owned())
+        .collect();
+    (args.len() == argc).then_some(args)
+}
+
+/// Arguments of a running process, from `/proc/<pid>/cmdline`.
+#[cfg(not(target_os = "macos"))]
+fn process_args(pid: u32) -> Option<Vec<String>> {
+    let raw = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let raw = raw.strip_suffix(b"\0").unwrap_or(&raw);
+    Some(
+        raw.split(|&b| b == 0)
+            .map(|a| String::from_utf8_lossy(a).into_owned())
+            .collect(),
+    )
+}
+
+/// Split the `\x02exit:<code>\n` trailer k3rs-init writes after a command's
+/// output off the end of `output`. Output without one is returned whole.
+pub fn split_exit_trailer(output: &[u8]) -> (&[u8], Option<i32>) {
+    let Some(start) = output
+        .windows(VSOCK_EXIT_TRAILER.len())
+        .rposition(|w| w == VSOCK_EXIT_TRAILER)
+    else {
+        return (output, None);
+    };
+    let code = output[start + VSOCK_EXIT_TRAILER.len()..]
+        .strip_suffix(b"\n")
+        .and_then(|digits| std::str::from_utf8(digits).ok())
+        .and_then(|digits| digits.parse().ok());
+    match code {
+        Some(code) => (&output[..start], Some(code)),
+        None => (output, None),
+    }
+}
+
+/// Strips the exit status trailer from a streamed exec session. Bytes that
+/// could still turn out to be the start of the trailer are held back until
+/// more arrive.
+#[derive(Debug, Default)]
+pub struct ExitTrailerFilter {
+    pending: Vec<u8>,
+}
+
+impl ExitTrailerFilter {
+    /// Longest trailer: the marker, an `i32` and the newline.
+    const MAX_LEN: usize = VSOCK_EXIT_TRAILER.len() + 11 + 1;
+
+    /// Take in `chunk` and return what can be passed on now.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+        let keep = match self
+            .pending
+            .iter()
+            .rposition(|&b| b == VSOCK_EXIT_TRAILER[0])
+        {
+            Some(start) if Self::may_be_trailer(&self.pending[start..]) => start,
+            _ => self.pending.len(),
+        };
+        let rest = self.pending.split_off(keep);
+        std::mem::replace(&mut self.pending, rest)
+    }
+
+    /// The session ended: the held-back bytes that were not the trailer, and
+    /// the exit code if they were.
+    pub fn finish(self) -> (Vec<u8>, Option<i32>) {
+        let (output, code) = split_exit_trailer(&self.pending);
+        (output.to_vec(), code)
+    }
+
+    /// Whether `tail` is the trailer, or the start of one.
+    fn may_be_trailer(tail: &[u8]) -> bool {
+        if tail.len() > Self::MAX_LEN {
+            return false;
+        }
+        let marker = tail.len().min(VSOCK_EXIT_TRAILER.len());
+        if tail[..marker] != VSOCK_EXIT_TRAILER[..marker] {
+            return false;
+        }
+        let code = &tail[marker..];
+        let digits = code.strip_suffix(b"\n").unwrap_or(code);
+        digits.iter().all(|b| b.is_ascii_digit() || *b == b'-')
+    }
+}
+
+/// `bytes` as one stdin frame of a `VSOCK_STDIN_PREFIX` exec:
+/// `length (4, BE) | bytes`. An empty frame is the EOF.
+pub fn stdin_frame(bytes: &[u8]) -> Vec<u8> {
+    debug_assert!(bytes.len() <= VSOCK_STDIN_MAX_FRAME);
+    let mut frame = (bytes.len() as u32).to_be_bytes().to_vec();
+    frame.extend_from_slice(bytes);
+    frame
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const MIB: u64 = 1024 * 1024;
+
+    fn limits(cpu_millis: u64, memory_bytes: u64) -> ResourceRequirements {
+        ResourceRequirements {
+            cpu_millis,
+            memory_bytes,
+        }
+    }
+
+    #[test]
+    fn test_vm_config_for_limits() {
+        let node = VmConfig {
+            cpu_count: 8,
+            memory_mb: 16384,
+        };
+        let sized = |cpu, mem| VmConfig::for_limits(&limits(cpu, mem), &VmConfig::default(), &node);
+
+        assert_eq!(
+            sized(2000, 4096 * MIB),
+            VmConfig {
+                cpu_count: 2,
+                memory_mb: 4096
+            }
+        );
+        // Partial cores and MiB round up.
+        assert_eq!(sized(1500, 300 * MIB + 1).cpu_count, 2);
+        assert_eq!(sized(1500, 300 * MIB + 1).memory_mb, 301);
+        assert_eq!(sized(100, 512 * MIB).cpu_count, 1);
+        // Tiny memory limits still leave the guest room to boot.
+        assert_eq!(sized(1000, 16 * MIB).memory_mb, MIN_VM_MEMORY_MB);
+        // Capped at the node.
+        assert_eq!(
+            sized(64_000, 1 << 40),
+            VmConfig {
+                cpu_count: 8,
+                memory_mb: 16384
+            }
+        );
+    }
+
+    #[test]
+    fn test_vm_config_zero_limits_use_defaults() {
+        let node = VmConfig::node_capacity();
+        assert!(node.cpu_count >= 1);
+        let defaults = VmConfig {
+            cpu_count: 2,
+            memory_mb: 512,
+        };
+        assert_eq!(
+            VmConfig::for_limits(&limits(0, 0), &defaults, &node),
+            VmConfig {
+                cpu_count: 2.min(node.cpu_count),
+                memory_mb: 512.min(node.memory_mb)
+            }
+        );
+        assert_eq!(
+            VmConfig::for_limits(&limits(0, 1024 * MIB), &VmConfig::default(), &node).cpu_count,
+            DEFAULT_CPU_COUNT
+        );
+        assert_eq!(
+            VmConfig::for_limits(&limits(3000, 0), &VmConfig::default(), &node).memory_mb,
+            DEFAULT_MEMORY_MB.min(node.memory_mb)
+        );
+    }
+
     #[test]
     fn test_parse_bundle_config_missing() {
         let (cmd, env) = parse_bundle_config(Path::new("/nonexistent"));
@@ -125,4 +444,123 @@ mod tests {
 
         let _ = std::fs::remove_dir_all(&tmp);
     }
+
+    #[test]
+    fn test_parse_bundle_privileges() {
+        let tmp = std::env::temp_dir().join("k3rs-vm-utils-privileges-test");
+        std::fs::create_dir_all(&tmp).unwrap();
+        assert!(parse_bundle_privileges(&tmp.join("missing")).is_empty());
+
+        let cfg = serde_json::json!({
+            "process": {
+                "args": ["/app"],
+                "user": { "uid": 1000, "gid": 1000 },
+                "rlimits": [{ "type": "RLIMIT_NOFILE", "hard": 1024, "soft": 1024 }],
+                "noNewPrivileges": true,
+                "terminal": false
+            }
+        });
+        std::fs::write(tmp.join("config.json"), cfg.to_string()).unwrap();
+
+        let privileges = parse_bundle_privileges(&tmp);
+        assert_eq!(privileges.len(), 3);
+        assert_eq!(privileges["user"], cfg["process"]["user"]);
+        assert_eq!(privileges["rlimits"], cfg["process"]["rlimits"]);
+        assert_eq!(privileges["noNewPrivileges"], true);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_is_vmm_boot_args() {
+        let args = |line: &str| line.split(' ').map(str::to_string).collect::<Vec<_>>();
+        assert!(is_vmm_boot_args(
+            &args("./target/release/k3rs-vmm boot --id c1"),
+            "c1"
+        ));
+        assert!(is_vmm_boot_args(
+            &args("k3rs-vmm boot --cpus 2 --id=c1"),
+            "c1"
+        ));
+        // `pgrep -f "k3rs-vmm boot.*--id c1"` would take c10's boot for c1.
+        assert!(!is_vmm_boot_args(&args("k3rs-vmm boot --id c10"), "c1"));
+        assert!(!is_vmm_boot_args(&args("k3rs-vmm exec --id c1 ls"), "c1"));
+        assert!(!is_vmm_boot_args(&args("less k3rs-vmm.log --id c1"), "c1"));
+    }
+
+    #[test]
+    fn test_parse_procargs2() {
+        let mut buf = 2i32.to_ne_bytes().to_vec();
+        buf.extend_from_slice(b"/opt/k3rs-vmm\0\0\0k3rs-vmm\0boot\0PATH=/bin\0");
+        assert_eq!(parse_procargs2(&buf).unwrap(), ["k3rs-vmm", "boot"]);
+        buf[0] = 5;
+        assert_eq!(parse_procargs2(&buf), None);
+    }
+
+    #[test]
+    fn test_is_vmm_boot_checks_live_process() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = crate::test_util::temp_dir("vmm-boot");
+        let bin = tmp.join("k3rs-vmm");
+        std::fs::write(&bin, "#!/bin/sh\nsleep 30\n").unwrap();
+        std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let mut child = std::process::Command::new(&bin)
+            .args(["boot", "--id", "c1"])
+            .spawn()
+            .unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let pid = child.id();
+        assert!(is_vmm_boot(pid, "c1"));
+        assert!(!is_vmm_boot(pid, "c"));
+        assert!(!is_vmm_boot(std::process::id(), "c1"));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!is_vmm_boot(pid, "c1"));
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_split_exit_trailer() {
+        assert_eq!(
+            split_exit_trailer(b"out\n\x02exit:2\n"),
+            (&b"out\n"[..], Some(2))
+        );
+        assert_eq!(split_exit_trailer(b"out\n"), (&b"out\n"[..], None));
+        assert_eq!(
+            split_exit_trailer(b"\x02exit:\n"),
+            (&b"\x02exit:\n"[..], None)
+        );
+    }
+
+    #[test]
+    fn test_exit_trailer_filter() {
+        let mut filter = ExitTrailerFilter::default();
+        let mut out = filter.push(b"a\x02b\r\n\x02exi");
+        assert_eq!(out, b"a\x02b\r\n");
+        out.extend(filter.push(b"t:13"));
+        out.extend(filter.push(b"0\n"));
+        let (rest, code) = filter.finish();
+        out.extend(rest);
+        assert_eq!(out, b"a\x02b\r\n");
+        assert_eq!(code, Some(130));
+
+        // Something that only starts like the trailer is output.
+        let mut filter = ExitTrailerFilter::default();
+        let mut out = filter.push(b"\x02ex");
+        out.extend(filter.push(b"tra"));
+        let (rest, code) = filter.finish();
+        out.extend(rest);
+        assert_eq!(out, b"\x02extra");
+        assert_eq!(code, None);
+    }
+
+    #[test]
+    fn test_stdin_frame() {
+        assert_eq!(stdin_frame(b"hello"), b"\0\0\0\x05hello");
+        assert_eq!(stdin_frame(&[b'x'; 300])[..4], [0, 0, 1, 44]);
+        assert_eq!(stdin_frame(b""), b"\0\0\0\0");
+    }
 }
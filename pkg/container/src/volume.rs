@@ -0,0 +1,143 @@
+//! Pod volumes on the node: `emptyDir` directories and bind mounts.
+//!
+//! The agent resolves each container's volume mounts against the pod's
+//! volumes into [`BindMount`]s. `emptyDir` volumes live under
+//! `<data_dir>/volumes/<pod_id>/<volume>`, are shared by the pod's
+//! containers, and are removed once no tracked container mounts them.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::state::ContainerStore;
+
+/// A host directory mounted into a container.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BindMount {
+    /// Path on the host.
+    pub source: PathBuf,
+    /// Absolute path inside the container.
+    pub destination: String,
+    pub read_only: bool,
+}
+
+impl BindMount {
+    /// The OCI `config.json` mounts entry for this bind mount.
+    pub fn to_oci(&self) -> serde_json::Value {
+        serde_json::json!({
+            "destination": self.destination,
+            "type": "bind",
+            "source": self.source.to_string_lossy(),
+            "options": ["rbind", "rprivate", if self.read_only { "ro" } else { "rw" }]
+        })
+    }
+}
+
+/// Root of all `emptyDir` volumes under a runtime data dir.
+pub fn volumes_root(data_dir: &Path) -> PathBuf {
+    data_dir.join("volumes")
+}
+
+/// Directory backing a pod's `emptyDir` volume.
+pub fn empty_dir_path(data_dir: &Path, pod_id: &str, volume: &str) -> PathBuf {
+    volumes_root(data_dir).join(pod_id).join(volume)
+}
+
+/// Create the `emptyDir` sources among `mounts`. Returns their paths.
+pub async fn create_empty_dirs(data_dir: &Path, mounts: &[BindMount]) -> Result<Vec<PathBuf>> {
+    let root = volumes_root(data_dir);
+    let mut dirs = Vec::new();
+    for mount in mounts.iter().filter(|m| m.source.starts_with(&root)) {
+        tokio::fs::create_dir_all(&mount.source).await?;
+        if !dirs.contains(&mount.source) {
+            dirs.push(mount.source.clone());
+        }
+    }
+    Ok(dirs)
+}
+
+/// Delete the `emptyDir` directories no tracked container mounts any more,
+/// along with the pod directory once it is empty.
+pub async fn release_empty_dirs(store: &ContainerStore, dirs: &[String]) {
+    for dir in dirs {
+        if store.list().iter().any(|e| e.empty_dirs.contains(dir)) {
+            continue;
+        }
+        let path = Path::new(dir);
+        if let Err(e) = tokio::fs::remove_dir_all(path).await
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!("Failed to remove emptyDir {}: {}", dir, e);
+        }
+        // Only succeeds once the pod's last volume is gone.
+        if let Some(pod_dir) = path.parent() {
+            let _ = tokio::fs::remove_dir(pod_dir).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bind_mount_oci_entry() {
+        let mount = BindMount {
+            source: PathBuf::from("/var/lib/k3rs/volumes/p1/cache"),
+            destination: "/cache".to_string(),
+            read_only: true,
+        };
+        let entry = mount.to_oci();
+        assert_eq!(entry["type"], "bind");
+        assert_eq!(entry["source"], "/var/lib/k3rs/volumes/p1/cache");
+        assert_eq!(entry["destination"], "/cache");
+        assert_eq!(
+            entry["options"],
+            serde_json::json!(["rbind", "rprivate", "ro"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_dir_lifecycle() {
+        let data_dir = crate::test_util::temp_dir("empty-dir");
+        let cache = empty_dir_path(&data_dir, "p1", "cache");
+        let mounts = [
+            BindMount {
+                source: cache.clone(),
+                destination: "/cache".to_string(),
+                read_only: false,
+            },
+            // hostPath sources are left alone.
+            BindMount {
+                source: PathBuf::from("/etc/hosts"),
+                destination: "/etc/hosts".to_string(),
+                read_only: true,
+            },
+        ];
+
+        let dirs = create_empty_dirs(&data_dir, &mounts).await.unwrap();
+        assert_eq!(dirs, vec![cache.clone()]);
+        assert!(cache.is_dir());
+        std::fs::write(cache.join("data"), "x").unwrap();
+
+        // Two containers of the pod share the volume.
+        let store = ContainerStore::new();
+        let dirs: Vec<String> = dirs.iter().map(|d| d.display().to_string()).collect();
+        for id in ["p1-web", "p1-sidecar"] {
+            store.track(id, "alpine", "crun", "/tmp/bundle", "/tmp/log");
+            store.set_empty_dirs(id, dirs.clone());
+        }
+
+        // Still mounted by the sidecar: the data survives.
+        store.remove("p1-web");
+        release_empty_dirs(&store, &dirs).await;
+        assert!(cache.join("data").exists());
+
+        // Last user gone: the volume and the pod directory are deleted.
+        store.remove("p1-sidecar");
+        release_empty_dirs(&store, &dirs).await;
+        assert!(!cache.exists());
+        assert!(!volumes_root(&data_dir).join("p1").exists());
+
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+}
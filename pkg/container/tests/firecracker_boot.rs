@@ -0,0 +1,89 @@
+//! Full microVM boot through the Firecracker backend.
+//!
+//! Needs Linux with `/dev/kvm`, a guest kernel (`scripts/build-kernel.sh`),
+//! k3rs-init (`scripts/build-k3rs-init.sh`), `mkfs.ext4` and a static
+//! `busybox` on the host for the guest rootfs. Firecracker itself is
+//! downloaded on first use. Run with:
+//!   cargo test -p pkg-container --test firecracker_boot -- --ignored
+
+#![cfg(target_os = "linux")]
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use pkg_container::backend::RuntimeBackend;
+use pkg_container::linux::firecracker::FirecrackerBackend;
+use pkg_container::logs::LogOptions;
+
+/// Bundle whose rootfs is a static busybox with its applets under /bin.
+fn busybox_bundle(dir: &Path) -> PathBuf {
+    let busybox = std::process::Command::new("which")
+        .arg("busybox")
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| PathBuf::from(String::from_utf8_lossy(&o.stdout).trim()))
+        .expect("busybox not found in PATH");
+
+    let bundle = dir.join("bundle");
+    let bin = bundle.join("rootfs/bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::copy(&busybox, bin.join("busybox")).unwrap();
+    for applet in ["sh", "echo", "sleep", "cat"] {
+        std::os::unix::fs::symlink("busybox", bin.join(applet)).unwrap();
+    }
+    let config = serde_json::json!({
+        "process": {
+            "args": ["/bin/sh", "-c", "echo hello-from-firecracker; sleep 300"],
+            "env": ["PATH=/bin"]
+        }
+    });
+    std::fs::write(bundle.join("config.json"), config.to_string()).unwrap();
+    bundle
+}
+
+#[tokio::test]
+#[ignore = "requires /dev/kvm, a guest kernel and k3rs-init"]
+async fn test_boot_exec_and_rediscover() {
+    let dir = std::env::temp_dir().join(format!("k3rs-fc-boot-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let bundle = busybox_bundle(&dir);
+    let id = "fc-boot-test";
+
+    let backend = FirecrackerBackend::new(&dir).await.unwrap();
+    backend.create(id, &bundle).await.unwrap();
+    backend.start(id).await.unwrap();
+
+    // k3rs-init forwards the container's stdout over vsock.
+    let mut booted = false;
+    for _ in 0..60 {
+        let logs = backend.logs(id, &LogOptions::default()).await.unwrap();
+        if logs.iter().any(|l| l.contains("hello-from-firecracker")) {
+            booted = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    assert!(booted, "guest output never reached the container log");
+
+    let out = backend.exec(id, &["/bin/echo", "exec-ok"]).await.unwrap();
+    assert!(out.stdout.contains("exec-ok"), "{:?}", out);
+    assert_eq!(out.exit_code, 0);
+    let out = backend.exec(id, &["/bin/false"]).await.unwrap();
+    assert_eq!(out.exit_code, 1);
+
+    // A fresh backend (an agent restart) finds the VM through its API socket.
+    let restarted = FirecrackerBackend::new(&dir).await.unwrap();
+    assert!(restarted.list().await.unwrap().iter().any(|v| v == id));
+    assert_eq!(restarted.state(id).await.unwrap().status, "running");
+    let out = restarted
+        .exec(id, &["/bin/cat", "/config.json"])
+        .await
+        .unwrap();
+    assert!(out.stdout.contains("hello-from-firecracker"), "{:?}", out);
+
+    restarted.delete(id).await.unwrap();
+    assert!(restarted.state(id).await.is_err());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,95 @@
+//! Pod admission shared by the API server and the workload controllers:
+//! security context checks, then LimitRange defaults and bounds, so that the
+//! defaulted requests are what the ResourceQuota check counts. Pods using
+//! bound PersistentVolumeClaims are pinned to their volumes' node.
+
+use pkg_state::client::StateStore;
+use pkg_types::error::FieldError;
+use pkg_types::event::Event;
+use pkg_types::limit_range::LimitRange;
+use pkg_types::pod::Pod;
+
+/// Apply the LimitRanges of the pod's namespace, in name order (see
+/// [`LimitRange::apply`]). The inner `Err` lists every failed constraint.
+pub async fn apply_limit_ranges(
+    store: &StateStore,
+    pod: &mut Pod,
+) -> anyhow::Result<Result<(), Vec<FieldError>>> {
+    let mut ranges: Vec<LimitRange> = store
+        .list_prefix(&format!("/registry/limitranges/{}/", pod.namespace))
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
+        .collect();
+    ranges.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut errors = Vec::new();
+    for range in &ranges {
+        if let Err(e) = range.apply(&mut pod.spec) {
+            errors.extend(e);
+        }
+    }
+    Ok(if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    })
+}
+
+/// Pin the pod to the node holding the volumes of its bound claims (see
+/// [`pkg_scheduler::pin_to_volume_nodes`]). The inner `Err` says why no
+/// node can hold them all.
+pub async fn pin_to_volumes(
+    store: &StateStore,
+    pod: &mut Pod,
+) -> anyhow::Result<Result<(), String>> {
+    if pod.spec.claim_names().next().is_none() {
+        return Ok(Ok(()));
+    }
+    let claim_nodes = crate::volume::claim_nodes(store, &pod.namespace).await?;
+    Ok(pkg_scheduler::pin_to_volume_nodes(pod, &claim_nodes))
+}
+
+/// Admission for a pod a controller creates on behalf of its `kind` object
+/// `owner`: security contexts, LimitRanges, volume pinning, then
+/// ResourceQuotas. A
+/// rejection is recorded as a `FailedCreate` event on the owner. Returns
+/// whether the pod may be created.
+pub async fn admit_owned_pod(
+    store: &StateStore,
+    kind: &str,
+    owner: &str,
+    pod: &mut Pod,
+) -> anyhow::Result<bool> {
+    let checked = match pod.spec.validate_security_contexts() {
+        Ok(()) => apply_limit_ranges(store, pod).await?,
+        Err(errors) => Err(errors),
+    };
+    let verdict = match checked {
+        Ok(()) => match pin_to_volumes(store, pod).await? {
+            Ok(()) => crate::quota::admit_pod(store, pod).await?,
+            Err(message) => Err(message),
+        },
+        Err(errors) => Err(errors
+            .into_iter()
+            .map(|e| e.message)
+            .collect::<Vec<_>>()
+            .join("; ")),
+    };
+    match verdict {
+        Ok(()) => Ok(true),
+        Err(message) => {
+            crate::events::record(
+                store,
+                Event::warning(
+                    kind,
+                    &pod.namespace,
+                    owner,
+                    "FailedCreate",
+                    format!("Error creating pod {}: {}", pod.name, message),
+                ),
+            )
+            .await;
+            Ok(false)
+        }
+    }
+}
@@ -6,6 +6,7 @@ use pkg_state::watch::EventType;
 use pkg_types::backup::{BACKUP_VERSION, BackupEntry, BackupFile, BackupPki, BackupStatus};
 use std::io::Write;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info, warn};
 
 /// Controller that periodically snapshots cluster state to disk.
@@ -37,7 +38,7 @@ impl BackupController {
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "BackupController started (interval={}s, dir={}, retention={})",
@@ -59,7 +60,10 @@ impl BackupController {
             interval.tick().await;
 
             loop {
-                interval.tick().await;
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
                 match self.run_backup().await {
                     Ok(filename) => {
                         info!("BackupController: backup written to {}", filename);
@@ -172,7 +176,7 @@ impl BackupController {
         }
 
         // Sort newest first
-        files.sort_by(|a, b| b.1.cmp(&a.1));
+        files.sort_by_key(|f| std::cmp::Reverse(f.1));
 
         // Delete files beyond retention
         for (path, _) in files.into_iter().skip(self.retention) {
@@ -1,12 +1,18 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use pkg_state::client::StateStore;
-use pkg_types::job::{CronJob, Job, JobStatus};
+use pkg_types::cron::Schedule;
+use pkg_types::job::{ConcurrencyPolicy, CronJob, Job, JobCondition, JobReference, JobStatus};
+use pkg_types::pod::Pod;
 use std::time::Duration;
-use tracing::{info, warn};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
-/// Controller that creates Jobs on a cron schedule.
-/// Supports simple cron expressions: `*/N` for every N minutes and exact minute values.
+/// Missed runs beyond which catching up is logged as a warning.
+const MISSED_RUNS_WARN_THRESHOLD: usize = 100;
+
+/// Controller that creates Jobs on a cron schedule, honoring the concurrency
+/// policy, starting deadline and history limits.
 pub struct CronJobController {
     store: StateStore,
     check_interval: Duration,
@@ -22,7 +28,7 @@ impl CronJobController {
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "CronJobController started (interval={}s)",
@@ -32,18 +38,20 @@ impl CronJobController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
-                        if let Err(e) = self.reconcile().await {
+                        if let Err(e) = self.reconcile(Utc::now()).await {
                             warn!("CronJobController reconcile error: {}", e);
                         }
                     }
                     result = event_rx.recv() => {
                         match result {
                             Ok(ref event)
-                                if event.key.starts_with("/registry/cronjobs/") =>
+                                if event.key.starts_with("/registry/cronjobs/")
+                                    || event.key.starts_with("/registry/jobs/") =>
                             {
                                 while event_rx.try_recv().is_ok() {}
-                                if let Err(e) = self.reconcile().await {
+                                if let Err(e) = self.reconcile(Utc::now()).await {
                                     warn!("CronJobController reconcile error: {}", e);
                                 }
                                 while event_rx.try_recv().is_ok() {}
@@ -51,7 +59,7 @@ impl CronJobController {
                             }
                             Ok(_) => {}
                             Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
-                                if let Err(e) = self.reconcile().await {
+                                if let Err(e) = self.reconcile(Utc::now()).await {
                                     warn!("CronJobController reconcile error: {}", e);
                                 }
                                 interval.reset();
@@ -64,7 +72,7 @@ impl CronJobController {
         })
     }
 
-    async fn reconcile(&self) -> anyhow::Result<()> {
+    async fn reconcile(&self, now: DateTime<Utc>) -> anyhow::Result<()> {
         let ns_entries = self.store.list_prefix("/registry/namespaces/").await?;
         for (ns_key, _) in ns_entries {
             let ns = ns_key
@@ -74,122 +82,461 @@ impl CronJobController {
             if ns.is_empty() {
                 continue;
             }
-            self.reconcile_namespace(&ns).await?;
+            self.reconcile_namespace(&ns, now).await?;
         }
         Ok(())
     }
 
-    async fn reconcile_namespace(&self, ns: &str) -> anyhow::Result<()> {
+    async fn reconcile_namespace(&self, ns: &str, now: DateTime<Utc>) -> anyhow::Result<()> {
         let cj_prefix = format!("/registry/cronjobs/{}/", ns);
         let cj_entries = self.store.list_prefix(&cj_prefix).await?;
-        let now = Utc::now();
 
         for (cj_key, cj_value) in cj_entries {
             let mut cj: CronJob = match serde_json::from_slice(&cj_value) {
                 Ok(c) => c,
                 Err(_) => continue,
             };
+            let before = serde_json::to_value(&cj.status).ok();
 
-            // Skip suspended CronJobs
-            if cj.spec.suspend {
-                continue;
+            let mut owned = self.owned_jobs(ns, &cj).await?;
+            cj.status.active = owned
+                .iter()
+                .filter(|(_, job)| job.status.condition == JobCondition::Running)
+                .map(|(_, job)| JobReference {
+                    id: job.id.clone(),
+                    name: job.name.clone(),
+                })
+                .collect();
+
+            if !cj.spec.suspend {
+                match Schedule::parse(&cj.spec.schedule) {
+                    Ok(schedule) => {
+                        if let Some(scheduled) = due_run(&cj, &schedule, now) {
+                            self.run(ns, &mut cj, &mut owned, scheduled, now).await?;
+                        }
+                    }
+                    Err(e) => warn!("CronJob {}/{}: invalid schedule: {}", ns, cj.name, e),
+                }
             }
 
-            // Check if schedule is due
-            let should_run = match &cj.status.last_schedule_time {
-                Some(last) => is_schedule_due(&cj.spec.schedule, last, &now),
-                None => true, // Never ran before
-            };
+            self.prune_history(ns, &cj, &owned).await?;
 
-            if should_run {
-                // Create a new Job
-                let job_id = Uuid::new_v4().to_string();
-                let job = Job {
-                    id: job_id.clone(),
-                    name: format!("{}-{}", cj.name, now.format("%Y%m%d%H%M%S")),
-                    namespace: ns.to_string(),
-                    spec: cj.spec.job_template.clone(),
-                    status: JobStatus::default(),
-                    owner_ref: Some(cj.id.clone()),
-                    created_at: now,
-                };
-
-                let job_key = format!("/registry/jobs/{}/{}", ns, job.name);
-                let data = serde_json::to_vec(&job)?;
-                self.store.put(&job_key, &data).await?;
+            if serde_json::to_value(&cj.status).ok() != before {
+                self.store
+                    .update(&cj_key, |stored: &mut CronJob| {
+                        stored.status = cj.status.clone();
+                        true
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
 
-                info!(
-                    "CronJob {}: spawned job {} (schedule={})",
-                    cj.name, job.name, cj.spec.schedule
-                );
+    /// Start the Job for the run scheduled at `scheduled`, subject to the
+    /// concurrency policy.
+    async fn run(
+        &self,
+        ns: &str,
+        cj: &mut CronJob,
+        owned: &mut Vec<(String, Job)>,
+        scheduled: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        if !cj.status.active.is_empty() {
+            match cj.spec.concurrency_policy {
+                ConcurrencyPolicy::Allow => {}
+                ConcurrencyPolicy::Forbid => {
+                    // Retried on later passes while the deadline allows.
+                    debug!(
+                        "CronJob {}/{}: skipping run at {}, {} job(s) still active",
+                        ns,
+                        cj.name,
+                        scheduled,
+                        cj.status.active.len()
+                    );
+                    return Ok(());
+                }
+                ConcurrencyPolicy::Replace => {
+                    for active in std::mem::take(&mut cj.status.active) {
+                        delete_job(&self.store, ns, &active.id, &active.name).await?;
+                        owned.retain(|(_, job)| job.id != active.id);
+                        info!(
+                            "CronJob {}/{}: replaced active job {}",
+                            ns, cj.name, active.name
+                        );
+                    }
+                }
+            }
+        }
 
-                // Update CronJob status
-                cj.status.last_schedule_time = Some(now);
-                cj.status.active_jobs.push(job_id);
+        let job = Job {
+            id: Uuid::new_v4().to_string(),
+            name: format!("{}-{}", cj.name, scheduled.format("%Y%m%d%H%M")),
+            namespace: ns.to_string(),
+            spec: cj.spec.job_template.clone(),
+            status: JobStatus::default(),
+            owner_ref: Some(cj.id.clone()),
+            created_at: now,
+            resource_version: 0,
+        };
+        let job_key = format!("/registry/jobs/{}/{}", ns, job.name);
+        // A run is started once, even if the status update below was lost.
+        if self.store.get(&job_key).await?.is_none() {
+            self.store.put(&job_key, &serde_json::to_vec(&job)?).await?;
+            info!(
+                "CronJob {}/{}: started job {} for {} (schedule={})",
+                ns, cj.name, job.name, scheduled, cj.spec.schedule
+            );
+            cj.status.active.push(JobReference {
+                id: job.id.clone(),
+                name: job.name.clone(),
+            });
+            owned.push((job_key, job));
+        }
+        cj.status.last_schedule_time = Some(scheduled);
+        Ok(())
+    }
 
-                let data = serde_json::to_vec(&cj)?;
-                self.store.put(&cj_key, &data).await?;
-            }
+    /// Jobs owned by `cj`, with their keys.
+    async fn owned_jobs(&self, ns: &str, cj: &CronJob) -> anyhow::Result<Vec<(String, Job)>> {
+        Ok(self
+            .store
+            .list_prefix(&format!("/registry/jobs/{}/", ns))
+            .await?
+            .into_iter()
+            .filter_map(|(k, v)| {
+                let job: Job = serde_json::from_slice(&v).ok()?;
+                (job.owner_ref.as_deref() == Some(&cj.id)).then_some((k, job))
+            })
+            .collect())
+    }
 
-            // Clean up completed jobs from active list
-            let mut still_active = Vec::new();
-            for job_id in &cj.status.active_jobs {
-                let job_key = format!("/registry/jobs/{}/{}", ns, job_id);
-                let is_running = self
-                    .store
-                    .get(&job_key)
-                    .await
-                    .ok()
-                    .flatten()
-                    .and_then(|data| serde_json::from_slice::<Job>(&data).ok())
-                    .is_some_and(|job| {
-                        job.status.condition == pkg_types::job::JobCondition::Running
-                    });
-                if is_running {
-                    still_active.push(job_id.clone());
-                }
+    /// Delete finished Jobs beyond the history limits, oldest first.
+    async fn prune_history(
+        &self,
+        ns: &str,
+        cj: &CronJob,
+        owned: &[(String, Job)],
+    ) -> anyhow::Result<()> {
+        for (condition, limit) in [
+            (
+                JobCondition::Complete,
+                cj.spec.successful_jobs_history_limit,
+            ),
+            (JobCondition::Failed, cj.spec.failed_jobs_history_limit),
+        ] {
+            let mut finished: Vec<&Job> = owned
+                .iter()
+                .map(|(_, job)| job)
+                .filter(|job| job.status.condition == condition)
+                .collect();
+            if finished.len() <= limit as usize {
+                continue;
             }
-            if still_active.len() != cj.status.active_jobs.len() {
-                cj.status.active_jobs = still_active;
-                let data = serde_json::to_vec(&cj)?;
-                self.store.put(&cj_key, &data).await?;
+            finished.sort_by_key(|job| job.status.completion_time.unwrap_or(job.created_at));
+            let excess = finished.len() - limit as usize;
+            for job in &finished[..excess] {
+                delete_job(&self.store, ns, &job.id, &job.name).await?;
+                info!(
+                    "CronJob {}/{}: removed {} job {} (history limit {})",
+                    ns, cj.name, condition, job.name, limit
+                );
             }
         }
         Ok(())
     }
 }
 
-/// Simple cron schedule checker.
-/// Supports: `*/N * * * *` (every N minutes) and `M * * * *` (at minute M).
-/// Only parses the minute field for MVP.
-fn is_schedule_due(
-    schedule: &str,
-    last_run: &chrono::DateTime<chrono::Utc>,
-    now: &chrono::DateTime<chrono::Utc>,
-) -> bool {
-    let parts: Vec<&str> = schedule.split_whitespace().collect();
-    if parts.is_empty() {
-        return false;
-    }
-
-    let minute_field = parts[0];
-    let elapsed_minutes = now.signed_duration_since(*last_run).num_minutes();
-
-    if minute_field == "*" {
-        // Every minute
-        elapsed_minutes >= 1
-    } else if let Some(interval) = minute_field.strip_prefix("*/") {
-        // Every N minutes
-        if let Ok(n) = interval.parse::<i64>() {
-            elapsed_minutes >= n
-        } else {
-            false
+/// The scheduled time of the run that should start now, if any: the most
+/// recent scheduled time since the last run (or creation), skipping it when
+/// it is older than the starting deadline. Earlier missed runs are dropped
+/// rather than started as a backlog.
+fn due_run(cj: &CronJob, schedule: &Schedule, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut since = cj.status.last_schedule_time.unwrap_or(cj.created_at);
+    if let Some(deadline) = cj.spec.starting_deadline_seconds {
+        since = since.max(now - chrono::Duration::seconds(deadline as i64));
+    }
+    let mut latest = None;
+    let mut missed = 0;
+    let mut t = since;
+    while let Some(next) = schedule.next_after(t).filter(|next| *next <= now) {
+        latest = Some(next);
+        missed += 1;
+        t = next;
+    }
+    if missed > MISSED_RUNS_WARN_THRESHOLD {
+        warn!(
+            "CronJob {}/{}: missed {} scheduled runs, starting only the latest; \
+             set starting_deadline_seconds to bound catch-up",
+            cj.namespace, cj.name, missed
+        );
+    }
+    latest
+}
+
+/// Delete a Job and the pods it owns.
+async fn delete_job(store: &StateStore, ns: &str, id: &str, name: &str) -> anyhow::Result<()> {
+    for (pod_key, value) in store
+        .list_prefix(&format!("/registry/pods/{}/", ns))
+        .await?
+    {
+        if serde_json::from_slice::<Pod>(&value)
+            .is_ok_and(|pod| pod.owner_ref.as_deref() == Some(id))
+        {
+            store.delete(&pod_key).await?;
+        }
+    }
+    store
+        .delete(&format!("/registry/jobs/{}/{}", ns, name))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_pod, test_store};
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    async fn seed_cronjob(store: &StateStore, policy: &str, extra: serde_json::Value) {
+        let mut spec = serde_json::json!({
+            "schedule": "*/10 * * * *",
+            "job_template": { "template": { "containers": [] } },
+            "concurrency_policy": policy,
+        });
+        spec.as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        let cj = serde_json::json!({
+            "id": "backup-id",
+            "name": "backup",
+            "namespace": "default",
+            "spec": spec,
+            "created_at": at("2024-03-01T09:55:00Z"),
+        });
+        store
+            .put(
+                "/registry/cronjobs/default/backup",
+                &serde_json::to_vec(&cj).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn stored_cronjob(store: &StateStore) -> CronJob {
+        let data = store
+            .get("/registry/cronjobs/default/backup")
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    async fn jobs(store: &StateStore) -> Vec<Job> {
+        let mut jobs: Vec<Job> = store
+            .list_prefix("/registry/jobs/default/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, v)| serde_json::from_slice(&v).unwrap())
+            .collect();
+        jobs.sort_by(|a, b| a.name.cmp(&b.name));
+        jobs
+    }
+
+    fn names(jobs: &[Job]) -> Vec<&str> {
+        jobs.iter().map(|j| j.name.as_str()).collect()
+    }
+
+    async fn finish_job(store: &StateStore, name: &str, condition: JobCondition) {
+        let key = format!("/registry/jobs/default/{}", name);
+        let mut job: Job =
+            serde_json::from_slice(&store.get(&key).await.unwrap().unwrap()).unwrap();
+        job.status.condition = condition;
+        store
+            .put(&key, &serde_json::to_vec(&job).unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_creates_job_per_schedule() {
+        let store = test_store("cronjob-schedule").await;
+        seed_cronjob(&store, "Allow", serde_json::json!({})).await;
+        let ctrl = CronJobController::new(store.clone());
+
+        ctrl.reconcile_namespace("default", at("2024-03-01T09:59:00Z"))
+            .await
+            .unwrap();
+        assert!(jobs(&store).await.is_empty());
+
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:00:30Z"))
+            .await
+            .unwrap();
+        // A second pass in the same minute does not start another run.
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:00:45Z"))
+            .await
+            .unwrap();
+        let created = jobs(&store).await;
+        assert_eq!(names(&created), vec!["backup-202403011000"]);
+        assert_eq!(created[0].owner_ref.as_deref(), Some("backup-id"));
+
+        let cj = stored_cronjob(&store).await;
+        assert_eq!(
+            cj.status.last_schedule_time,
+            Some(at("2024-03-01T10:00:00Z"))
+        );
+        assert_eq!(cj.status.active.len(), 1);
+        assert_eq!(cj.status.active[0].name, "backup-202403011000");
+
+        // Allow: the next run starts alongside the one still active.
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:10:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(stored_cronjob(&store).await.status.active.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_forbid_skips_overlapping_run() {
+        let store = test_store("cronjob-forbid").await;
+        seed_cronjob(&store, "Forbid", serde_json::json!({})).await;
+        let ctrl = CronJobController::new(store.clone());
+
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:00:00Z"))
+            .await
+            .unwrap();
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:10:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(names(&jobs(&store).await), vec!["backup-202403011000"]);
+        assert_eq!(
+            stored_cronjob(&store).await.status.last_schedule_time,
+            Some(at("2024-03-01T10:00:00Z"))
+        );
+
+        // Once the first run finishes, the skipped slot is picked up.
+        finish_job(&store, "backup-202403011000", JobCondition::Complete).await;
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:11:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(
+            names(&jobs(&store).await),
+            vec!["backup-202403011000", "backup-202403011010"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_deletes_active_job() {
+        let store = test_store("cronjob-replace").await;
+        seed_cronjob(&store, "Replace", serde_json::json!({})).await;
+        let ctrl = CronJobController::new(store.clone());
+
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:00:00Z"))
+            .await
+            .unwrap();
+        let first = jobs(&store).await.remove(0);
+        seed_pod(&store, "backup-pod", "worker-1", Some(&first.id)).await;
+
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:10:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(names(&jobs(&store).await), vec!["backup-202403011010"]);
+        assert!(
+            store
+                .get("/registry/pods/default/backup-pod")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let cj = stored_cronjob(&store).await;
+        assert_eq!(cj.status.active.len(), 1);
+        assert_eq!(cj.status.active[0].name, "backup-202403011010");
+    }
+
+    #[tokio::test]
+    async fn test_missed_runs_start_once_within_deadline() {
+        let store = test_store("cronjob-missed").await;
+        seed_cronjob(&store, "Allow", serde_json::json!({})).await;
+        let ctrl = CronJobController::new(store.clone());
+
+        // Down for an hour: only the latest slot runs, no backlog.
+        ctrl.reconcile_namespace("default", at("2024-03-01T11:05:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(names(&jobs(&store).await), vec!["backup-202403011100"]);
+
+        let store = test_store("cronjob-deadline").await;
+        seed_cronjob(
+            &store,
+            "Allow",
+            serde_json::json!({ "starting_deadline_seconds": 120 }),
+        )
+        .await;
+        let ctrl = CronJobController::new(store.clone());
+
+        // The 11:00 slot is more than two minutes old.
+        ctrl.reconcile_namespace("default", at("2024-03-01T11:05:00Z"))
+            .await
+            .unwrap();
+        assert!(jobs(&store).await.is_empty());
+        ctrl.reconcile_namespace("default", at("2024-03-01T11:11:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(names(&jobs(&store).await), vec!["backup-202403011110"]);
+    }
+
+    #[tokio::test]
+    async fn test_suspended_cronjob_does_not_run() {
+        let store = test_store("cronjob-suspend").await;
+        seed_cronjob(&store, "Allow", serde_json::json!({ "suspend": true })).await;
+        let ctrl = CronJobController::new(store.clone());
+
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:00:00Z"))
+            .await
+            .unwrap();
+        assert!(jobs(&store).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_history_limits_prune_oldest_jobs() {
+        let store = test_store("cronjob-history").await;
+        seed_cronjob(
+            &store,
+            "Allow",
+            serde_json::json!({
+                "successful_jobs_history_limit": 2,
+                "failed_jobs_history_limit": 1,
+            }),
+        )
+        .await;
+        let ctrl = CronJobController::new(store.clone());
+
+        for (minute, condition) in [
+            ("00", JobCondition::Complete),
+            ("10", JobCondition::Failed),
+            ("20", JobCondition::Complete),
+            ("30", JobCondition::Failed),
+            ("40", JobCondition::Complete),
+        ] {
+            ctrl.reconcile_namespace("default", at(&format!("2024-03-01T10:{}:00Z", minute)))
+                .await
+                .unwrap();
+            finish_job(&store, &format!("backup-2024030110{}", minute), condition).await;
         }
-    } else if let Ok(target_minute) = minute_field.parse::<u32>() {
-        // At specific minute — check if we crossed that minute boundary
-        let current_minute = now.format("%M").to_string().parse::<u32>().unwrap_or(0);
-        current_minute == target_minute && elapsed_minutes >= 1
-    } else {
-        false
+        ctrl.reconcile_namespace("default", at("2024-03-01T10:45:00Z"))
+            .await
+            .unwrap();
+        assert_eq!(
+            names(&jobs(&store).await),
+            vec![
+                "backup-202403011020",
+                "backup-202403011030",
+                "backup-202403011040"
+            ]
+        );
+        assert!(stored_cronjob(&store).await.status.active.is_empty());
     }
 }
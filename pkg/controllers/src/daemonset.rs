@@ -1,31 +1,37 @@
 use chrono::Utc;
+use pkg_scheduler::Scheduler;
 use pkg_state::client::StateStore;
 use pkg_types::daemonset::DaemonSet;
-use pkg_types::node::{Node, NodeStatus};
+use pkg_types::node::Node;
 use pkg_types::pod::{Pod, PodStatus};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::{info, warn};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 /// Controller that ensures exactly one Pod runs on each eligible node
-/// for every DaemonSet.
+/// for every DaemonSet. Eligibility is the scheduler's node filtering; the
+/// pods are bound directly, bypassing node selection.
 pub struct DaemonSetController {
     store: StateStore,
+    scheduler: Arc<Scheduler>,
     check_interval: Duration,
 }
 
 impl DaemonSetController {
-    pub fn new(store: StateStore) -> Self {
+    pub fn new(store: StateStore, scheduler: Arc<Scheduler>) -> Self {
         Self {
             store,
+            scheduler,
             check_interval: Duration::from_secs(
                 pkg_constants::timings::DAEMONSET_CHECK_INTERVAL_SECS,
             ),
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "DaemonSetController started (interval={}s)",
@@ -35,6 +41,7 @@ impl DaemonSetController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
                         if let Err(e) = self.reconcile().await {
                             warn!("DaemonSetController reconcile error: {}", e);
@@ -99,86 +106,108 @@ impl DaemonSetController {
                 Ok(d) => d,
                 Err(_) => continue,
             };
+            let before = serde_json::to_value(&ds.status).ok();
+            // The pod as it would be created, to check node eligibility
+            let template = daemon_pod(ns, &ds, None);
 
-            // Filter eligible nodes (Ready + matching node_selector)
-            let eligible_nodes: Vec<&Node> = nodes
-                .iter()
-                .filter(|n| {
-                    if n.status != NodeStatus::Ready {
-                        return false;
-                    }
-                    // Check node selector
-                    ds.spec
-                        .node_selector
-                        .iter()
-                        .all(|(k, v)| n.labels.get(k).is_some_and(|nv| nv == v))
-                })
-                .collect();
-
-            // Get pods owned by this DaemonSet
+            // Get pods owned by this DaemonSet, grouped by node. Finished or
+            // unbound (evicted) pods are removed and replaced.
             let pod_prefix = format!("/registry/pods/{}/", ns);
             let pod_entries = self.store.list_prefix(&pod_prefix).await?;
-            let owned_pods: Vec<(String, Pod)> = pod_entries
-                .into_iter()
-                .filter_map(|(k, v)| {
-                    let pod: Pod = serde_json::from_slice(&v).ok()?;
-                    if pod.owner_ref.as_deref() == Some(&ds.id) {
-                        Some((k, pod))
-                    } else {
-                        None
+            let mut pods_by_node: HashMap<String, Vec<(String, Pod)>> = HashMap::new();
+            for (key, value) in pod_entries {
+                let Ok(pod) = serde_json::from_slice::<Pod>(&value) else {
+                    continue;
+                };
+                if pod.owner_ref.as_deref() != Some(&ds.id) {
+                    continue;
+                }
+                match pod.node_name.clone() {
+                    Some(node_name)
+                        if !matches!(pod.status, PodStatus::Succeeded | PodStatus::Failed) =>
+                    {
+                        pods_by_node.entry(node_name).or_default().push((key, pod));
+                    }
+                    _ => {
+                        self.store.delete(&key).await?;
+                        info!("DaemonSet {}: removed finished pod {}", ds.name, pod.name);
                     }
-                })
-                .collect();
-
-            // Build set of node IDs that already have a pod
-            let nodes_with_pods: HashMap<String, String> = owned_pods
-                .iter()
-                .filter_map(|(key, pod)| {
-                    pod.node_name.as_ref().map(|nid| (nid.clone(), key.clone()))
-                })
-                .collect();
-
-            // Create pods on nodes that don't have one
-            let mut created = 0u32;
-            for node in &eligible_nodes {
-                if !nodes_with_pods.contains_key(&node.name) {
-                    self.create_pod_on_node(ns, &ds, node).await?;
-                    created += 1;
-                    info!("DaemonSet {}: created pod on node {}", ds.name, node.name);
                 }
             }
 
-            // Delete pods on nodes that are no longer eligible
-            let eligible_names: std::collections::HashSet<&str> =
-                eligible_nodes.iter().map(|n| n.name.as_str()).collect();
-            for (pod_key, pod) in &owned_pods {
-                if let Some(nid) = &pod.node_name
-                    && !eligible_names.contains(nid.as_str())
-                {
+            let mut desired = 0u32;
+            let mut current = 0u32;
+            let mut ready = 0u32;
+            for node in &nodes {
+                let mut pods = pods_by_node.remove(&node.name).unwrap_or_default();
+                // A running pod's requests are already in the node's allocation.
+                if !self.scheduler.passes_node_filters(node, &template) {
+                    for (pod_key, pod) in &pods {
+                        self.store.delete(pod_key).await?;
+                        info!(
+                            "DaemonSet {}: removed pod {} from ineligible node {}",
+                            ds.name, pod.name, node.name
+                        );
+                    }
+                    continue;
+                }
+                desired += 1;
+
+                if pods.is_empty() {
+                    if self.scheduler.is_node_eligible(node, &template) {
+                        if let Some(pod) = self.create_pod_on_node(ns, &ds, node).await? {
+                            current += 1;
+                            info!(
+                                "DaemonSet {}: created pod {} on node {}",
+                                ds.name, pod.name, node.name
+                            );
+                        }
+                    } else {
+                        debug!(
+                            "DaemonSet {}: node {} lacks resources for its pod",
+                            ds.name, node.name
+                        );
+                    }
+                    continue;
+                }
+
+                // Exactly one per node: keep the oldest.
+                pods.sort_by_key(|(_, p)| p.created_at);
+                for (pod_key, pod) in &pods[1..] {
                     self.store.delete(pod_key).await?;
+                    info!(
+                        "DaemonSet {}: removed duplicate pod {} on node {}",
+                        ds.name, pod.name, node.name
+                    );
+                }
+                current += 1;
+                if pods[0].1.is_ready() {
+                    ready += 1;
+                }
+            }
+
+            // Pods on nodes that no longer exist
+            for (node_name, pods) in pods_by_node {
+                for (pod_key, pod) in pods {
+                    self.store.delete(&pod_key).await?;
                     info!(
                         "DaemonSet {}: removed orphan pod {} from node {}",
-                        ds.name, pod.name, nid
+                        ds.name, pod.name, node_name
                     );
                 }
             }
 
-            // Update status
-            ds.status.desired_number_scheduled = eligible_nodes.len() as u32;
-            ds.status.current_number_scheduled = eligible_nodes.len() as u32;
-            let ready_count = owned_pods
-                .iter()
-                .filter(|(_, p)| {
-                    matches!(p.status, PodStatus::Running | PodStatus::Scheduled)
-                        && p.node_name
-                            .as_ref()
-                            .is_some_and(|nid| eligible_names.contains(nid.as_str()))
-                })
-                .count() as u32
-                + created;
-            ds.status.number_ready = ready_count.min(ds.status.desired_number_scheduled);
-            let data = serde_json::to_vec(&ds)?;
-            self.store.put(&ds_key, &data).await?;
+            ds.status.desired_number_scheduled = desired;
+            ds.status.current_number_scheduled = current;
+            ds.status.number_ready = ready;
+            if serde_json::to_value(&ds.status).ok() != before {
+                self.store
+                    .update(&ds_key, |stored: &mut DaemonSet| {
+                        stored.status = ds.status.clone();
+                        true
+                    })
+                    .await?;
+            }
         }
         Ok(())
     }
@@ -188,28 +217,215 @@ impl DaemonSetController {
         ns: &str,
         ds: &DaemonSet,
         node: &Node,
-    ) -> anyhow::Result<Pod> {
-        let pod_id = Uuid::new_v4().to_string();
-        let pod = Pod {
-            id: pod_id.clone(),
-            name: format!("{}-{}", ds.name, &node.name),
-            namespace: ns.to_string(),
-            spec: ds.spec.template.clone(),
-            status: PodStatus::Scheduled,
-            status_message: None,
-            container_id: None,
-            node_name: Some(node.name.clone()),
-            labels: ds.spec.node_selector.clone(),
-            owner_ref: Some(ds.id.clone()),
-            restart_count: 0,
-            runtime_info: None,
-            ghost_ipv6: None,
-            vpc_name: None,
-            created_at: Utc::now(),
-        };
+    ) -> anyhow::Result<Option<Pod>> {
+        let mut pod = daemon_pod(ns, ds, Some(&node.name));
+        if !crate::admission::admit_owned_pod(&self.store, "daemonset", &ds.name, &mut pod).await? {
+            return Ok(None);
+        }
         let key = format!("/registry/pods/{}/{}", ns, pod.name);
         let data = serde_json::to_vec(&pod)?;
         self.store.put(&key, &data).await?;
-        Ok(pod)
+        Ok(Some(pod))
+    }
+}
+
+/// The DaemonSet's pod, bound directly to `node` when given. The node
+/// selector becomes node affinity so the scheduler's filters apply it.
+fn daemon_pod(ns: &str, ds: &DaemonSet, node: Option<&str>) -> Pod {
+    let pod_id = Uuid::new_v4().to_string();
+    let mut spec = ds.spec.template.clone();
+    spec.node_affinity.extend(ds.spec.node_selector.clone());
+    Pod {
+        id: pod_id,
+        name: format!("{}-{}", ds.name, node.unwrap_or_default()),
+        namespace: ns.to_string(),
+        spec,
+        status: PodStatus::Scheduled,
+        status_message: None,
+        container_id: None,
+        node_name: node.map(str::to_string),
+        labels: ds.spec.node_selector.clone(),
+        owner_ref: Some(ds.id.clone()),
+        restart_count: 0,
+        runtime_info: None,
+        ghost_ipv6: None,
+        vpc_name: None,
+        pod_ip: None,
+        ready: false,
+        mirror: false,
+        container_statuses: Vec::new(),
+        created_at: Utc::now(),
+        resource_version: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_node, stored_node, test_store};
+    use pkg_types::node::{NodeStatus, Taint};
+    use pkg_types::pod::TaintEffect;
+
+    async fn seed_daemonset(store: &StateStore, spec: serde_json::Value) {
+        let mut full = serde_json::json!({ "template": { "containers": [] } });
+        full.as_object_mut()
+            .unwrap()
+            .extend(spec.as_object().unwrap().clone());
+        let ds = serde_json::json!({
+            "id": "agent-id",
+            "name": "agent",
+            "namespace": "default",
+            "spec": full,
+            "created_at": Utc::now(),
+        });
+        store
+            .put(
+                "/registry/daemonsets/default/agent",
+                &serde_json::to_vec(&ds).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn update_node(store: &StateStore, name: &str, f: impl FnOnce(&mut Node)) {
+        let mut node = stored_node(store, name).await;
+        f(&mut node);
+        store
+            .put(
+                &format!("/registry/nodes/{}", name),
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    /// Nodes that have the DaemonSet's pod, sorted.
+    async fn pod_nodes(store: &StateStore) -> Vec<String> {
+        let mut nodes: Vec<String> = store
+            .list_prefix("/registry/pods/default/")
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|(_, v)| serde_json::from_slice::<Pod>(&v).ok())
+            .filter(|p| p.owner_ref.as_deref() == Some("agent-id"))
+            .filter_map(|p| p.node_name)
+            .collect();
+        nodes.sort();
+        nodes
+    }
+
+    async fn status(store: &StateStore) -> (u32, u32, u32) {
+        let data = store
+            .get("/registry/daemonsets/default/agent")
+            .await
+            .unwrap()
+            .unwrap();
+        let ds: DaemonSet = serde_json::from_slice(&data).unwrap();
+        (
+            ds.status.desired_number_scheduled,
+            ds.status.current_number_scheduled,
+            ds.status.number_ready,
+        )
+    }
+
+    fn controller(store: &StateStore) -> DaemonSetController {
+        DaemonSetController::new(store.clone(), Arc::new(Scheduler::new()))
+    }
+
+    #[tokio::test]
+    async fn test_follows_nodes_added_and_removed() {
+        let store = test_store("ds-nodes").await;
+        seed_node(&store, "worker-1").await;
+        seed_node(&store, "worker-2").await;
+        seed_daemonset(&store, serde_json::json!({})).await;
+        let ctrl = controller(&store);
+
+        ctrl.reconcile_namespace("default").await.unwrap();
+        assert_eq!(pod_nodes(&store).await, vec!["worker-1", "worker-2"]);
+
+        seed_node(&store, "worker-3").await;
+        ctrl.reconcile_namespace("default").await.unwrap();
+        assert_eq!(
+            pod_nodes(&store).await,
+            vec!["worker-1", "worker-2", "worker-3"]
+        );
+
+        store.delete("/registry/nodes/worker-1").await.unwrap();
+        ctrl.reconcile_namespace("default").await.unwrap();
+        assert_eq!(pod_nodes(&store).await, vec!["worker-2", "worker-3"]);
+        assert_eq!(status(&store).await, (2, 2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_untolerated_taint_removes_pod() {
+        let store = test_store("ds-taints").await;
+        seed_node(&store, "worker-1").await;
+        seed_node(&store, "worker-2").await;
+        seed_daemonset(
+            &store,
+            serde_json::json!({
+                "template": {
+                    "containers": [],
+                    "tolerations": [{ "key": "gpu", "operator": "Exists" }],
+                },
+            }),
+        )
+        .await;
+        let ctrl = controller(&store);
+        ctrl.reconcile_namespace("default").await.unwrap();
+
+        let taint = |key: &str| Taint {
+            key: key.to_string(),
+            value: "true".to_string(),
+            effect: TaintEffect::NoSchedule,
+        };
+        update_node(&store, "worker-1", |n| n.taints.push(taint("gpu"))).await;
+        update_node(&store, "worker-2", |n| n.taints.push(taint("maintenance"))).await;
+        ctrl.reconcile_namespace("default").await.unwrap();
+        assert_eq!(pod_nodes(&store).await, vec!["worker-1"]);
+        assert_eq!(status(&store).await, (1, 1, 0));
+
+        // Taint lifted: the pod comes back.
+        update_node(&store, "worker-2", |n| n.taints.clear()).await;
+        ctrl.reconcile_namespace("default").await.unwrap();
+        assert_eq!(pod_nodes(&store).await, vec!["worker-1", "worker-2"]);
+    }
+
+    #[tokio::test]
+    async fn test_status_counts_eligible_and_ready() {
+        let store = test_store("ds-status").await;
+        for name in ["worker-1", "worker-2", "worker-3"] {
+            seed_node(&store, name).await;
+            update_node(&store, name, |n| {
+                n.labels.insert("role".to_string(), "edge".to_string());
+            })
+            .await;
+        }
+        seed_node(&store, "other").await;
+        seed_daemonset(
+            &store,
+            serde_json::json!({ "node_selector": { "role": "edge" } }),
+        )
+        .await;
+        let ctrl = controller(&store);
+
+        ctrl.reconcile_namespace("default").await.unwrap();
+        assert_eq!(status(&store).await, (3, 3, 0));
+
+        let key = "/registry/pods/default/agent-worker-1";
+        let mut pod: Pod = serde_json::from_slice(&store.get(key).await.unwrap().unwrap()).unwrap();
+        pod.status = PodStatus::Running;
+        store
+            .put(key, &serde_json::to_vec(&pod).unwrap())
+            .await
+            .unwrap();
+        ctrl.reconcile_namespace("default").await.unwrap();
+        assert_eq!(status(&store).await, (3, 3, 1));
+
+        // A node that goes NotReady is no longer counted.
+        update_node(&store, "worker-3", |n| n.status = NodeStatus::NotReady).await;
+        ctrl.reconcile_namespace("default").await.unwrap();
+        assert_eq!(status(&store).await, (2, 2, 1));
+        assert_eq!(pod_nodes(&store).await, vec!["worker-1", "worker-2"]);
     }
 }
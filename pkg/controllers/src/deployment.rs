@@ -1,8 +1,10 @@
 use chrono::Utc;
-use pkg_state::client::StateStore;
-use pkg_types::deployment::{Deployment, DeploymentStrategy};
+use pkg_state::client::{PutOutcome, StateStore};
+use pkg_types::deployment::{Deployment, DeploymentConditionType, DeploymentStrategy};
+use pkg_types::event::Event;
 use pkg_types::replicaset::{ReplicaSet, ReplicaSetSpec, ReplicaSetStatus};
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 use uuid::Uuid;
 
@@ -23,7 +25,7 @@ impl DeploymentController {
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "DeploymentController started (interval={}s)",
@@ -33,6 +35,7 @@ impl DeploymentController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
                         if let Err(e) = self.reconcile().await {
                             warn!("DeploymentController reconcile error: {}", e);
@@ -111,113 +114,151 @@ impl DeploymentController {
                 .collect();
 
             // Find the RS matching the current template
-            let current_rs = owned_rs
+            let mut current_rs = owned_rs
                 .iter()
-                .find(|(_, rs)| rs.template_hash == template_hash);
+                .find(|(_, rs)| rs.template_hash == template_hash)
+                .cloned();
+            let next_revision = owned_rs
+                .iter()
+                .map(|(_, rs)| rs.revision)
+                .max()
+                .unwrap_or(0)
+                + 1;
+
+            // A template that was current before becomes the latest revision again
+            if let Some((rs_key, rs)) = current_rs.as_mut()
+                && rs.revision + 1 < next_revision
+            {
+                let updated = self
+                    .store
+                    .update(rs_key, |stored: &mut ReplicaSet| {
+                        stored.revision = next_revision;
+                        if deploy.change_cause.is_some() {
+                            stored.change_cause = deploy.change_cause.clone();
+                        }
+                        true
+                    })
+                    .await?;
+                if let Some(updated) = updated {
+                    *rs = updated;
+                }
+            }
+
+            // Old ReplicaSets that still have replicas, oldest first
+            let mut old_rs: Vec<(String, ReplicaSet)> = owned_rs
+                .iter()
+                .filter(|(_, rs)| rs.template_hash != template_hash && rs.spec.replicas > 0)
+                .cloned()
+                .collect();
+            old_rs.sort_by_key(|(_, rs)| (rs.revision, rs.created_at));
 
             match &deploy.spec.strategy {
                 DeploymentStrategy::RollingUpdate {
                     max_surge,
-                    max_unavailable: _,
+                    max_unavailable,
                 } => {
-                    let max_surge = *max_surge;
+                    let (new_replicas, new_available) = current_rs
+                        .as_ref()
+                        .map(|(_, rs)| (rs.spec.replicas, available(rs)))
+                        .unwrap_or((0, 0));
+                    let old: Vec<(u32, u32)> = old_rs
+                        .iter()
+                        .map(|(_, rs)| (rs.spec.replicas, available(rs)))
+                        .collect();
+                    let step = rolling_update_step(
+                        deploy.spec.replicas,
+                        *max_surge,
+                        *max_unavailable,
+                        (new_replicas, new_available),
+                        &old,
+                    );
 
-                    if let Some((rs_key, rs)) = current_rs {
-                        // Current RS exists — make sure it has the right replica count
-                        if rs.spec.replicas != deploy.spec.replicas {
-                            let mut rs = rs.clone();
-                            rs.spec.replicas = deploy.spec.replicas;
-                            let data = serde_json::to_vec(&rs)?;
-                            self.store.put(rs_key, &data).await?;
+                    match &current_rs {
+                        Some((rs_key, rs)) => {
+                            if step.new_replicas != rs.spec.replicas {
+                                self.scale_replicaset(&deploy, rs_key, rs, step.new_replicas)
+                                    .await?;
+                            }
+                        }
+                        None => {
+                            let rs = self
+                                .create_replicaset(
+                                    ns,
+                                    &deploy,
+                                    &template_hash,
+                                    step.new_replicas,
+                                    next_revision,
+                                )
+                                .await?;
                             info!(
-                                "Deployment {}: scaled RS {} to {}",
-                                deploy.name, rs.name, deploy.spec.replicas
+                                "Deployment {}: created new RS {} (hash={})",
+                                deploy.name, rs.name, template_hash
                             );
                         }
-
-                        // Scale down any old RS to 0
-                        for (old_key, mut old_rs) in owned_rs.into_iter() {
-                            if old_rs.template_hash != template_hash && old_rs.spec.replicas > 0 {
-                                old_rs.spec.replicas = 0;
-                                let data = serde_json::to_vec(&old_rs)?;
-                                self.store.put(&old_key, &data).await?;
-                            }
-                        }
-                    } else {
-                        // Create new ReplicaSet
-                        let rs = self
-                            .create_replicaset(
-                                ns,
-                                &deploy,
-                                &template_hash,
-                                deploy.spec.replicas.min(max_surge),
-                            )
-                            .await?;
-                        info!(
-                            "Deployment {}: created new RS {} (hash={})",
-                            deploy.name, rs.name, template_hash
-                        );
-
-                        // Scale down old ReplicaSets
-                        for (old_key, mut old_rs) in owned_rs {
-                            if old_rs.template_hash != template_hash && old_rs.spec.replicas > 0 {
-                                old_rs.spec.replicas =
-                                    old_rs.spec.replicas.saturating_sub(max_surge.max(1));
-                                let data = serde_json::to_vec(&old_rs)?;
-                                self.store.put(&old_key, &data).await?;
-                                info!(
-                                    "Deployment {}: scaling down old RS {} to {}",
-                                    deploy.name, old_rs.name, old_rs.spec.replicas
-                                );
-                            }
+                    }
+                    for ((old_key, old), replicas) in old_rs.iter().zip(step.old_replicas) {
+                        if replicas != old.spec.replicas {
+                            self.scale_replicaset(&deploy, old_key, old, replicas)
+                                .await?;
                         }
                     }
                 }
                 DeploymentStrategy::Recreate => {
-                    if let Some((rs_key, rs)) = current_rs {
-                        if rs.spec.replicas != deploy.spec.replicas {
-                            let mut rs = rs.clone();
-                            rs.spec.replicas = deploy.spec.replicas;
-                            let data = serde_json::to_vec(&rs)?;
-                            self.store.put(rs_key, &data).await?;
-                        }
+                    // Scale all old RS to 0 first
+                    let old_pods_remain = owned_rs
+                        .iter()
+                        .any(|(_, rs)| rs.template_hash != template_hash && rs.status.replicas > 0);
+                    for (old_key, old) in &old_rs {
+                        self.scale_replicaset(&deploy, old_key, old, 0).await?;
+                    }
+                    // Then run the new RS at full scale once their pods are gone
+                    let replicas = if old_rs.is_empty() && !old_pods_remain {
+                        deploy.spec.replicas
                     } else {
-                        // Scale all old RS to 0 first
-                        for (old_key, mut old_rs) in owned_rs.into_iter() {
-                            if old_rs.spec.replicas > 0 {
-                                old_rs.spec.replicas = 0;
-                                let data = serde_json::to_vec(&old_rs)?;
-                                self.store.put(&old_key, &data).await?;
+                        0
+                    };
+                    match &current_rs {
+                        Some((rs_key, rs)) => {
+                            if rs.spec.replicas != replicas {
+                                self.scale_replicaset(&deploy, rs_key, rs, replicas).await?;
                             }
                         }
-                        // Then create new RS at full scale
-                        self.create_replicaset(ns, &deploy, &template_hash, deploy.spec.replicas)
+                        None => {
+                            self.create_replicaset(
+                                ns,
+                                &deploy,
+                                &template_hash,
+                                replicas,
+                                next_revision,
+                            )
                             .await?;
-                        info!("Deployment {}: recreated with new RS", deploy.name);
+                            info!("Deployment {}: recreated with new RS", deploy.name);
+                        }
                     }
                 }
                 DeploymentStrategy::BlueGreen => {
                     // Blue/Green: deploy new version at full scale alongside old,
                     // then cut over by scaling old to 0
-                    if let Some((rs_key, rs)) = current_rs {
+                    if let Some((rs_key, rs)) = &current_rs {
                         if rs.spec.replicas != deploy.spec.replicas {
-                            let mut rs = rs.clone();
-                            rs.spec.replicas = deploy.spec.replicas;
-                            let data = serde_json::to_vec(&rs)?;
-                            self.store.put(rs_key, &data).await?;
+                            self.set_replicas(rs_key, deploy.spec.replicas).await?;
                         }
                         // Scale down old ReplicaSets (cutover)
-                        for (old_key, mut old_rs) in owned_rs.into_iter() {
+                        for (old_key, old_rs) in &owned_rs {
                             if old_rs.template_hash != template_hash && old_rs.spec.replicas > 0 {
-                                old_rs.spec.replicas = 0;
-                                let data = serde_json::to_vec(&old_rs)?;
-                                self.store.put(&old_key, &data).await?;
+                                self.set_replicas(old_key, 0).await?;
                             }
                         }
                     } else {
                         // Create new "green" RS at full scale
-                        self.create_replicaset(ns, &deploy, &template_hash, deploy.spec.replicas)
-                            .await?;
+                        self.create_replicaset(
+                            ns,
+                            &deploy,
+                            &template_hash,
+                            deploy.spec.replicas,
+                            next_revision,
+                        )
+                        .await?;
                         info!(
                             "Deployment {}: blue/green — new version deployed",
                             deploy.name
@@ -230,17 +271,20 @@ impl DeploymentController {
                         .ceil()
                         .max(1.0) as u32;
 
-                    if let Some((rs_key, rs)) = current_rs {
+                    if let Some((rs_key, rs)) = &current_rs {
                         if rs.spec.replicas != deploy.spec.replicas {
-                            let mut rs = rs.clone();
-                            rs.spec.replicas = deploy.spec.replicas;
-                            let data = serde_json::to_vec(&rs)?;
-                            self.store.put(rs_key, &data).await?;
+                            self.set_replicas(rs_key, deploy.spec.replicas).await?;
                         }
                     } else {
                         // Create canary RS with limited replicas
-                        self.create_replicaset(ns, &deploy, &template_hash, canary_replicas)
-                            .await?;
+                        self.create_replicaset(
+                            ns,
+                            &deploy,
+                            &template_hash,
+                            canary_replicas,
+                            next_revision,
+                        )
+                        .await?;
                         info!(
                             "Deployment {}: canary — {} replicas ({}% traffic)",
                             deploy.name, canary_replicas, weight
@@ -251,29 +295,29 @@ impl DeploymentController {
 
             // Update deployment status from owned ReplicaSets
             let rs_prefix = format!("/registry/replicasets/{}/", ns);
-            let rs_entries = self.store.list_prefix(&rs_prefix).await?;
-            let mut ready = 0u32;
-            let mut available = 0u32;
-            let mut updated = 0u32;
-            for (_, v) in rs_entries {
-                if let Ok(rs) = serde_json::from_slice::<ReplicaSet>(&v) {
-                    if rs.owner_ref.as_deref() != Some(&deploy.id) {
-                        continue;
-                    }
-                    ready += rs.status.ready_replicas;
-                    available += rs.status.available_replicas;
-                    if rs.template_hash == template_hash {
-                        updated += rs.status.ready_replicas;
-                    }
-                }
-            }
-
-            deploy.status.ready_replicas = ready;
-            deploy.status.available_replicas = available;
-            deploy.status.updated_replicas = updated;
+            let mut owned_rs: Vec<(String, ReplicaSet)> = self
+                .store
+                .list_prefix(&rs_prefix)
+                .await?
+                .into_iter()
+                .filter_map(|(k, v)| {
+                    let rs: ReplicaSet = serde_json::from_slice(&v).ok()?;
+                    (rs.owner_ref.as_deref() == Some(&deploy.id)).then_some((k, rs))
+                })
+                .collect();
+            self.prune_history(&deploy, &template_hash, &mut owned_rs)
+                .await?;
+            update_status(&mut deploy, &template_hash, &owned_rs);
             deploy.observed_generation = deploy.generation;
-            let data = serde_json::to_vec(&deploy)?;
-            self.store.put(&key, &data).await?;
+            // A spec applied meanwhile is kept; its new generation stays
+            // unobserved until the next pass.
+            self.store
+                .update(&key, |stored: &mut Deployment| {
+                    stored.status = deploy.status.clone();
+                    stored.observed_generation = deploy.observed_generation;
+                    true
+                })
+                .await?;
         }
         Ok(())
     }
@@ -284,6 +328,7 @@ impl DeploymentController {
         deploy: &Deployment,
         template_hash: &str,
         replicas: u32,
+        revision: u64,
     ) -> anyhow::Result<ReplicaSet> {
         let rs_id = Uuid::new_v4().to_string();
         let rs = ReplicaSet {
@@ -302,13 +347,258 @@ impl DeploymentController {
             status: ReplicaSetStatus::default(),
             owner_ref: Some(deploy.id.clone()),
             template_hash: template_hash.to_string(),
+            revision,
+            change_cause: deploy.change_cause.clone(),
             created_at: Utc::now(),
+            resource_version: 0,
         };
         let key = format!("/registry/replicasets/{}/{}", ns, rs.name);
         let data = serde_json::to_vec(&rs)?;
-        self.store.put(&key, &data).await?;
+        if let PutOutcome::Conflict(_) = self.store.create(&key, &data).await? {
+            anyhow::bail!("replicaset {}/{} already exists", ns, rs.name);
+        }
+        self.record_scaling(deploy, &rs.name, replicas).await;
         Ok(rs)
     }
+
+    async fn scale_replicaset(
+        &self,
+        deploy: &Deployment,
+        rs_key: &str,
+        rs: &ReplicaSet,
+        replicas: u32,
+    ) -> anyhow::Result<()> {
+        self.set_replicas(rs_key, replicas).await?;
+        info!(
+            "Deployment {}: scaled RS {} to {}",
+            deploy.name, rs.name, replicas
+        );
+        self.record_scaling(deploy, &rs.name, replicas).await;
+        Ok(())
+    }
+
+    /// Set the replica count of the ReplicaSet at `rs_key`, keeping whatever
+    /// else changed on it since it was read.
+    async fn set_replicas(&self, rs_key: &str, replicas: u32) -> anyhow::Result<()> {
+        self.store
+            .update(rs_key, |stored: &mut ReplicaSet| {
+                if stored.spec.replicas == replicas {
+                    return false;
+                }
+                stored.spec.replicas = replicas;
+                true
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Delete scaled-down old ReplicaSets beyond the revision history limit,
+    /// oldest revisions first.
+    async fn prune_history(
+        &self,
+        deploy: &Deployment,
+        template_hash: &str,
+        owned_rs: &mut Vec<(String, ReplicaSet)>,
+    ) -> anyhow::Result<()> {
+        let mut retired: Vec<&(String, ReplicaSet)> = owned_rs
+            .iter()
+            .filter(|(_, rs)| {
+                rs.template_hash != template_hash
+                    && rs.spec.replicas == 0
+                    && rs.status.replicas == 0
+            })
+            .collect();
+        let limit = deploy.spec.revision_history_limit as usize;
+        if retired.len() <= limit {
+            return Ok(());
+        }
+        retired.sort_by_key(|(_, rs)| std::cmp::Reverse((rs.revision, rs.created_at)));
+        let doomed: Vec<String> = retired[limit..].iter().map(|(k, _)| k.clone()).collect();
+        for key in &doomed {
+            self.store.delete(key).await?;
+            info!(
+                "Deployment {}: removed old RS {} (revision history limit {})",
+                deploy.name,
+                key.rsplit('/').next().unwrap_or_default(),
+                limit
+            );
+        }
+        owned_rs.retain(|(k, _)| !doomed.contains(k));
+        Ok(())
+    }
+
+    async fn record_scaling(&self, deploy: &Deployment, rs_name: &str, replicas: u32) {
+        crate::events::record(
+            &self.store,
+            Event::normal(
+                "deployment",
+                &deploy.namespace,
+                &deploy.name,
+                "ScalingReplicaSet",
+                format!("Scaled replica set {} to {}", rs_name, replicas),
+            ),
+        )
+        .await;
+    }
+}
+
+/// Available pods of a ReplicaSet, never more than it is scaled to.
+fn available(rs: &ReplicaSet) -> u32 {
+    rs.status.available_replicas.min(rs.spec.replicas)
+}
+
+/// Replica counts after one rolling-update step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RolloutStep {
+    pub new_replicas: u32,
+    /// In the order the old ReplicaSets were given.
+    pub old_replicas: Vec<u32>,
+}
+
+/// One step of a rolling update. `new` and each of `old` (oldest first) are
+/// `(replicas, available pods)`.
+///
+/// The new ReplicaSet grows while the total stays within `desired +
+/// max_surge`; old ones shrink while at least `desired - max_unavailable`
+/// pods stay available, dropping pods that are not available first.
+pub(crate) fn rolling_update_step(
+    desired: u32,
+    max_surge: u32,
+    max_unavailable: u32,
+    new: (u32, u32),
+    old: &[(u32, u32)],
+) -> RolloutStep {
+    // With neither surge nor unavailability the rollout could never move
+    let max_unavailable = if max_surge == 0 && max_unavailable == 0 {
+        1
+    } else {
+        max_unavailable
+    }
+    .min(desired);
+    let mut old_replicas: Vec<u32> = old.iter().map(|(replicas, _)| *replicas).collect();
+
+    // Scale up the new ReplicaSet
+    let total = new.0 + old_replicas.iter().sum::<u32>();
+    let max_total = desired + max_surge;
+    let new_replicas = if new.0 >= desired {
+        desired
+    } else {
+        new.0 + max_total.saturating_sub(total).min(desired - new.0)
+    };
+
+    // Scale down old pods that are not available: it costs no availability
+    let min_available = desired - max_unavailable;
+    let all = new_replicas + old_replicas.iter().sum::<u32>();
+    let new_unavailable = new_replicas.saturating_sub(new.1);
+    let mut budget = all
+        .saturating_sub(min_available)
+        .saturating_sub(new_unavailable);
+    for (replicas, (_, avail)) in old_replicas.iter_mut().zip(old) {
+        let cut = replicas.saturating_sub(*avail).min(budget);
+        *replicas -= cut;
+        budget -= cut;
+    }
+
+    // Then available ones, down to the availability floor
+    let available_total = new.1.min(new_replicas)
+        + old_replicas
+            .iter()
+            .zip(old)
+            .map(|(replicas, (_, avail))| (*avail).min(*replicas))
+            .sum::<u32>();
+    let mut budget = available_total.saturating_sub(min_available);
+    for replicas in old_replicas.iter_mut() {
+        let cut = (*replicas).min(budget);
+        *replicas -= cut;
+        budget -= cut;
+    }
+
+    RolloutStep {
+        new_replicas,
+        old_replicas,
+    }
+}
+
+/// Most pods below the desired count the strategy allows unavailable.
+fn max_unavailable(deploy: &Deployment) -> u32 {
+    match deploy.spec.strategy {
+        DeploymentStrategy::RollingUpdate {
+            max_surge,
+            max_unavailable,
+        } if max_surge == 0 && max_unavailable == 0 => 1,
+        DeploymentStrategy::RollingUpdate {
+            max_unavailable, ..
+        } => max_unavailable,
+        _ => 0,
+    }
+    .min(deploy.spec.replicas)
+}
+
+/// Recompute replica counts and conditions from the owned ReplicaSets.
+fn update_status(deploy: &mut Deployment, template_hash: &str, owned_rs: &[(String, ReplicaSet)]) {
+    let status = &mut deploy.status;
+    status.replicas = owned_rs.iter().map(|(_, rs)| rs.status.replicas).sum();
+    status.ready_replicas = owned_rs
+        .iter()
+        .map(|(_, rs)| rs.status.ready_replicas)
+        .sum();
+    status.available_replicas = owned_rs
+        .iter()
+        .map(|(_, rs)| rs.status.available_replicas)
+        .sum();
+    let current = owned_rs
+        .iter()
+        .map(|(_, rs)| rs)
+        .find(|rs| rs.template_hash == template_hash);
+    status.updated_replicas = current.map(|rs| rs.status.replicas).unwrap_or(0);
+
+    let desired = deploy.spec.replicas;
+    let min_available = desired - max_unavailable(deploy);
+    let status = &mut deploy.status;
+    if status.available_replicas >= min_available {
+        status.set_condition(
+            DeploymentConditionType::Available,
+            true,
+            "MinimumReplicasAvailable",
+            format!(
+                "{} of {} replicas available",
+                status.available_replicas, desired
+            ),
+        );
+    } else {
+        status.set_condition(
+            DeploymentConditionType::Available,
+            false,
+            "MinimumReplicasUnavailable",
+            format!(
+                "{} of {} replicas available, {} required",
+                status.available_replicas, desired, min_available
+            ),
+        );
+    }
+
+    let rs_name = current.map(|rs| rs.name.as_str()).unwrap_or_default();
+    let complete = status.updated_replicas == desired
+        && status.replicas == desired
+        && current.is_some_and(|rs| rs.status.available_replicas >= desired);
+    if complete {
+        status.set_condition(
+            DeploymentConditionType::Progressing,
+            true,
+            "NewReplicaSetAvailable",
+            format!("ReplicaSet {} has successfully progressed", rs_name),
+        );
+    } else {
+        status.set_condition(
+            DeploymentConditionType::Progressing,
+            true,
+            "ReplicaSetUpdated",
+            format!(
+                "ReplicaSet {} is progressing: {} of {} updated",
+                rs_name, status.updated_replicas, desired
+            ),
+        );
+    }
 }
 
 /// Compute a simple hash of the deployment template for change detection.
@@ -321,3 +611,236 @@ fn compute_template_hash(deploy: &Deployment) -> String {
     }
     format!("{:016x}", hash)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_store;
+
+    /// Roll `old` pods over to a new template, with every pod becoming
+    /// available before the next step. Returns `(new, old)` per step.
+    fn simulate(desired: u32, max_surge: u32, max_unavailable: u32) -> Vec<(u32, u32)> {
+        let (mut new, mut old) = ((0, 0), (desired, desired));
+        let mut steps = Vec::new();
+        while new != (desired, desired) || old.0 > 0 {
+            let step = rolling_update_step(desired, max_surge, max_unavailable, new, &[old]);
+            // Bounds hold before pods catch up
+            assert!(step.new_replicas + step.old_replicas[0] <= desired + max_surge);
+            assert!(
+                new.1.min(step.new_replicas) + old.1.min(step.old_replicas[0])
+                    >= desired - max_unavailable
+            );
+            new = (step.new_replicas, step.new_replicas);
+            old = (step.old_replicas[0], step.old_replicas[0]);
+            steps.push((new.0, old.0));
+            assert!(steps.len() <= 2 * desired as usize, "rollout stalled");
+        }
+        steps
+    }
+
+    #[test]
+    fn test_rolling_update_step_sequence() {
+        assert_eq!(simulate(4, 1, 1), vec![(1, 3), (2, 2), (3, 1), (4, 0)]);
+        // Surge only: a new pod must be available before an old one goes
+        assert_eq!(simulate(2, 1, 0), vec![(1, 2), (1, 1), (2, 1), (2, 0)]);
+        // Unavailability only: replace in place
+        assert_eq!(simulate(2, 0, 1), vec![(0, 1), (1, 1), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn test_rolling_update_waits_for_available_pods() {
+        // One surge pod created, one old pod removed — then nothing until
+        // new pods become available.
+        let step = rolling_update_step(4, 1, 1, (1, 0), &[(3, 3)]);
+        assert_eq!(step.new_replicas, 2);
+        assert_eq!(step.old_replicas, vec![3]);
+        let step = rolling_update_step(4, 1, 1, (2, 0), &[(3, 3)]);
+        assert_eq!((step.new_replicas, step.old_replicas[0]), (2, 3));
+
+        // Old pods that are not available go first; the available one stays
+        // to keep 3 of 4 available.
+        let step = rolling_update_step(4, 1, 1, (2, 2), &[(1, 1), (2, 0)]);
+        assert_eq!(step.new_replicas, 2);
+        assert_eq!(step.old_replicas, vec![1, 0]);
+    }
+
+    async fn seed_deployment(store: &StateStore, image: &str, history: u32) {
+        seed_deployment_with_cause(store, image, history, None).await;
+    }
+
+    async fn seed_deployment_with_cause(
+        store: &StateStore,
+        image: &str,
+        history: u32,
+        change_cause: Option<&str>,
+    ) {
+        let deploy: Deployment = serde_json::from_value(serde_json::json!({
+            "id": "web-id",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "replicas": 4,
+                "template": { "containers": [{ "name": "web", "image": image }] },
+                "strategy": { "type": "RollingUpdate", "max_surge": 1, "max_unavailable": 1 },
+                "revision_history_limit": history,
+            },
+            "change_cause": change_cause,
+            "created_at": Utc::now(),
+        }))
+        .unwrap();
+        store
+            .put(
+                "/registry/deployments/default/web",
+                &serde_json::to_vec(&deploy).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn owned(store: &StateStore) -> Vec<(String, ReplicaSet)> {
+        let mut rs: Vec<(String, ReplicaSet)> = store
+            .list_prefix("/registry/replicasets/default/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(k, v)| (k, serde_json::from_slice(&v).unwrap()))
+            .collect();
+        rs.sort_by_key(|(_, rs)| rs.revision);
+        rs
+    }
+
+    /// Stand in for the ReplicaSet controller: every pod comes up available.
+    async fn pods_become_available(store: &StateStore) {
+        for (key, mut rs) in owned(store).await {
+            let n = rs.spec.replicas;
+            rs.status = ReplicaSetStatus {
+                replicas: n,
+                ready_replicas: n,
+                available_replicas: n,
+            };
+            store
+                .put(&key, &serde_json::to_vec(&rs).unwrap())
+                .await
+                .unwrap();
+        }
+    }
+
+    async fn stored_deployment(store: &StateStore) -> Deployment {
+        let data = store
+            .get("/registry/deployments/default/web")
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rollout_scales_replicasets_and_prunes_history() {
+        let store = test_store("deployment-rollout").await;
+        let ctrl = DeploymentController::new(store.clone());
+
+        seed_deployment(&store, "nginx:1.0", 1).await;
+        ctrl.reconcile_namespace("default").await.unwrap();
+        pods_become_available(&store).await;
+        ctrl.reconcile_namespace("default").await.unwrap();
+        let deploy = stored_deployment(&store).await;
+        assert_eq!(deploy.status.available_replicas, 4);
+        let progressing = deploy
+            .status
+            .condition(DeploymentConditionType::Progressing)
+            .unwrap();
+        assert_eq!(progressing.reason, "NewReplicaSetAvailable");
+
+        for (revision, image) in [(2, "nginx:1.1"), (3, "nginx:1.2")] {
+            seed_deployment(&store, image, 1).await;
+            let mut steps = Vec::new();
+            for _ in 0..4 {
+                ctrl.reconcile_namespace("default").await.unwrap();
+                let rs = owned(&store).await;
+                let new = &rs.last().unwrap().1;
+                assert_eq!(new.revision, revision);
+                let old: u32 = rs[..rs.len() - 1]
+                    .iter()
+                    .map(|(_, r)| r.spec.replicas)
+                    .sum();
+                steps.push((new.spec.replicas, old));
+
+                let deploy = stored_deployment(&store).await;
+                let available = deploy
+                    .status
+                    .condition(DeploymentConditionType::Available)
+                    .unwrap();
+                assert!(available.status);
+                pods_become_available(&store).await;
+            }
+            assert_eq!(steps, vec![(1, 3), (2, 2), (3, 1), (4, 0)]);
+        }
+
+        // Revision 1 is pruned; revision 2 is kept for rollback.
+        ctrl.reconcile_namespace("default").await.unwrap();
+        let revisions: Vec<u64> = owned(&store)
+            .await
+            .iter()
+            .map(|(_, rs)| rs.revision)
+            .collect();
+        assert_eq!(revisions, vec![2, 3]);
+        let deploy = stored_deployment(&store).await;
+        assert_eq!(deploy.status.updated_replicas, 4);
+        assert_eq!(
+            deploy
+                .status
+                .condition(DeploymentConditionType::Progressing)
+                .unwrap()
+                .reason,
+            "NewReplicaSetAvailable"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revisions_record_change_cause() {
+        let store = test_store("deployment-revisions").await;
+        let ctrl = DeploymentController::new(store.clone());
+
+        let updates = [
+            ("nginx:1.0", "initial"),
+            ("nginx:1.1", "bump to 1.1"),
+            ("nginx:1.2", "bump to 1.2"),
+        ];
+        for (image, cause) in updates {
+            seed_deployment_with_cause(&store, image, 10, Some(cause)).await;
+            for _ in 0..5 {
+                ctrl.reconcile_namespace("default").await.unwrap();
+                pods_become_available(&store).await;
+            }
+        }
+        let history: Vec<(u64, String, Option<String>)> = owned(&store)
+            .await
+            .into_iter()
+            .map(|(_, rs)| {
+                (
+                    rs.revision,
+                    rs.spec.template.containers[0].image.clone(),
+                    rs.change_cause,
+                )
+            })
+            .collect();
+        assert_eq!(
+            history,
+            updates
+                .iter()
+                .zip(1..)
+                .map(|((image, cause), rev)| (rev, image.to_string(), Some(cause.to_string())))
+                .collect::<Vec<_>>()
+        );
+
+        // Restoring the first template reuses its ReplicaSet as revision 4.
+        seed_deployment_with_cause(&store, "nginx:1.0", 10, Some("initial")).await;
+        ctrl.reconcile_namespace("default").await.unwrap();
+        let rs = owned(&store).await;
+        assert_eq!(rs.len(), 3);
+        let (_, current) = rs.last().unwrap();
+        assert_eq!(current.revision, 4);
+        assert_eq!(current.spec.template.containers[0].image, "nginx:1.0");
+        assert_eq!(current.change_cause.as_deref(), Some("initial"));
+    }
+}
@@ -0,0 +1,80 @@
+//! PodDisruptionBudget checks for voluntary evictions. Like quota usage,
+//! budget status is computed from the stored pods on each check.
+
+use pkg_state::client::StateStore;
+use pkg_types::pdb::{PdbStatus, PodDisruptionBudget};
+use pkg_types::pod::Pod;
+
+/// An eviction a disruption budget does not allow right now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvictionBlocked {
+    pub budget: String,
+    pub status: PdbStatus,
+}
+
+impl EvictionBlocked {
+    /// The budget requires every pod it expects to stay ready, so no
+    /// eviction can ever satisfy it (e.g. one replica, `min_available: 1`).
+    pub fn is_unsatisfiable(&self) -> bool {
+        self.status.desired_healthy >= self.status.expected_pods
+    }
+}
+
+impl std::fmt::Display for EvictionBlocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PDB {} ({})", self.budget, self.status.summary())
+    }
+}
+
+async fn namespace_pods(store: &StateStore, ns: &str) -> anyhow::Result<Vec<Pod>> {
+    Ok(store
+        .list_prefix(&format!("/registry/pods/{}/", ns))
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
+        .collect())
+}
+
+/// The PodDisruptionBudgets of namespace `ns`, sorted by name, with
+/// `status` filled in.
+pub async fn budget_status(
+    store: &StateStore,
+    ns: &str,
+) -> anyhow::Result<Vec<PodDisruptionBudget>> {
+    let mut budgets: Vec<PodDisruptionBudget> = store
+        .list_prefix(&format!("/registry/poddisruptionbudgets/{}/", ns))
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
+        .collect();
+    if budgets.is_empty() {
+        return Ok(budgets);
+    }
+    budgets.sort_by(|a, b| a.name.cmp(&b.name));
+    let pods = namespace_pods(store, ns).await?;
+    for budget in &mut budgets {
+        budget.status = budget.compute_status(&pods);
+    }
+    Ok(budgets)
+}
+
+/// Whether `pod` may be evicted without breaking a budget that covers it.
+/// Evicting a pod that is not ready takes nothing away, so it is always
+/// allowed.
+pub async fn check_eviction(
+    store: &StateStore,
+    pod: &Pod,
+) -> anyhow::Result<Result<(), EvictionBlocked>> {
+    if !pod.is_ready() {
+        return Ok(Ok(()));
+    }
+    for budget in budget_status(store, &pod.namespace).await? {
+        if budget.selects(pod) && budget.status.disruptions_allowed == 0 {
+            return Ok(Err(EvictionBlocked {
+                budget: budget.name,
+                status: budget.status,
+            }));
+        }
+    }
+    Ok(Ok(()))
+}
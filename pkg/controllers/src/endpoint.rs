@@ -1,13 +1,14 @@
 use chrono::Utc;
 use pkg_state::client::StateStore;
 use pkg_types::endpoint::{Endpoint, EndpointAddress, EndpointPort};
-use pkg_types::pod::{Pod, PodStatus};
+use pkg_types::pod::Pod;
 use pkg_types::service::Service;
 use std::collections::HashMap;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
-/// Controller that auto-generates Endpoints from Running pods matched by
+/// Controller that auto-generates Endpoints from ready pods matched by
 /// Service selectors. Uses Ghost IPv6 addresses when available.
 pub struct EndpointController {
     store: StateStore,
@@ -24,7 +25,7 @@ impl EndpointController {
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "EndpointController started (interval={}s)",
@@ -34,6 +35,7 @@ impl EndpointController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
                         if let Err(e) = self.reconcile().await {
                             warn!("EndpointController reconcile error: {}", e);
@@ -76,13 +78,13 @@ impl EndpointController {
                 continue;
             }
 
-            // Find Running pods matching this service's selector, namespace, and VPC
+            // Find ready pods matching this service's selector, namespace, and VPC
             let svc_vpc = svc.vpc.as_deref().unwrap_or("default");
             let matching_pods: Vec<&Pod> = pods
                 .iter()
                 .filter(|p| {
                     p.namespace == svc.namespace
-                        && p.status == PodStatus::Running
+                        && p.is_ready()
                         && matches_selector(&p.labels, &svc.spec.selector)
                         && p.vpc_name
                             .as_deref()
@@ -95,12 +97,13 @@ impl EndpointController {
             let addresses: Vec<EndpointAddress> = matching_pods
                 .iter()
                 .filter_map(|p| {
-                    // Use Ghost IPv6 if available, fallback to K3RS_POD_IP-style guest IPv4
-                    let ip = p.ghost_ipv6.clone()?;
+                    // Ghost IPv6 for VPC pods, the bridge address for the rest
+                    let ip = p.ghost_ipv6.clone().or_else(|| p.pod_ip.clone())?;
                     Some(EndpointAddress {
                         ip,
                         node_name: p.node_name.clone(),
                         pod_id: Some(p.id.clone()),
+                        hostname: Some(p.name.clone()),
                     })
                 })
                 .collect();
@@ -152,3 +155,61 @@ fn matches_selector(labels: &HashMap<String, String>, selector: &HashMap<String,
         .iter()
         .all(|(k, v)| labels.get(k).is_some_and(|lv| lv == v))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_store;
+
+    async fn put<T: serde::Serialize>(store: &StateStore, key: &str, value: &T) {
+        store
+            .put(key, &serde_json::to_vec(value).unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_addresses_prefer_ghost_ipv6_then_pod_ip() {
+        let store = test_store("endpoint-addresses").await;
+        let svc: Service = serde_json::from_value(serde_json::json!({
+            "id": "svc-1", "name": "web", "namespace": "default",
+            "spec": {
+                "selector": { "app": "web" },
+                "ports": [{ "name": "http", "port": 80, "target_port": 8080 }],
+                "service_type": "ClusterIP",
+            },
+            "created_at": Utc::now(),
+        }))
+        .unwrap();
+        put(&store, "/registry/services/default/web", &svc).await;
+        for (name, ghost_ipv6, pod_ip) in [
+            ("vpc-0", Some("fd6b:3372::1:2"), None),
+            ("bridge-0", None, Some("10.42.0.2")),
+            ("pending-0", None, None),
+        ] {
+            let pod: Pod = serde_json::from_value(serde_json::json!({
+                "id": format!("{}-id", name), "name": name, "namespace": "default",
+                "labels": { "app": "web" }, "spec": { "containers": [] },
+                "status": "Running", "node_name": "node-a", "created_at": Utc::now(),
+                "ghost_ipv6": ghost_ipv6, "pod_ip": pod_ip,
+            }))
+            .unwrap();
+            put(&store, &format!("/registry/pods/default/{}", name), &pod).await;
+        }
+
+        EndpointController::new(store.clone())
+            .reconcile()
+            .await
+            .unwrap();
+
+        let data = store
+            .get("/registry/endpoints/default/svc-1")
+            .await
+            .unwrap()
+            .unwrap();
+        let endpoint: Endpoint = serde_json::from_slice(&data).unwrap();
+        let mut ips: Vec<&str> = endpoint.addresses.iter().map(|a| a.ip.as_str()).collect();
+        ips.sort();
+        assert_eq!(ips, ["10.42.0.2", "fd6b:3372::1:2"]);
+    }
+}
@@ -0,0 +1,20 @@
+use pkg_state::client::StateStore;
+use pkg_state::watch::EventType;
+use pkg_types::event::Event;
+use tracing::warn;
+
+/// Emit an object event (see [`Event`]) into the store's in-memory event log.
+///
+/// Shared by controllers and API handlers so both file events under the same
+/// `/events/{ns}/{kind}/{name}` keys.
+pub async fn record(store: &StateStore, event: Event) {
+    match serde_json::to_vec(&event) {
+        Ok(data) => {
+            store
+                .event_log
+                .emit(EventType::Put, event.key(), Some(data))
+                .await
+        }
+        Err(e) => warn!("Failed to serialize event {}: {}", event.key(), e),
+    }
+}
@@ -1,13 +1,18 @@
+use chrono::{DateTime, Utc};
 use pkg_state::client::StateStore;
+use pkg_types::event::Event;
 use pkg_types::node::{Node, NodeStatus};
 use pkg_types::pod::{Pod, PodStatus};
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
 /// Controller that watches for failed nodes and reschedules their pods.
 ///
-/// When a node stays in `Unknown` state past the eviction grace period,
-/// all pods on that node are reset to `Pending` for rescheduling.
+/// When a node has been out of `Ready` for longer than the eviction grace
+/// period, its pods are taken off it. Pods owned by a ReplicaSet (or another
+/// controller) are marked `Failed` so the owner creates replacements; bare
+/// pods have nothing to recreate them and are reset to `Pending` instead.
 pub struct EvictionController {
     store: StateStore,
     check_interval: Duration,
@@ -15,18 +20,20 @@ pub struct EvictionController {
 }
 
 impl EvictionController {
-    pub fn new(store: StateStore) -> Self {
+    /// `grace_period` is how long a node may go without a heartbeat before
+    /// its pods are evicted.
+    pub fn new(store: StateStore, grace_period: Duration) -> Self {
         Self {
             store,
             check_interval: Duration::from_secs(
                 pkg_constants::timings::EVICTION_CHECK_INTERVAL_SECS,
             ),
-            grace_period: Duration::from_secs(pkg_constants::timings::EVICTION_GRACE_PERIOD_SECS),
+            grace_period,
         }
     }
 
     /// Start the controller loop as a background task.
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "EvictionController started (interval={}s, grace={}s)",
@@ -37,8 +44,9 @@ impl EvictionController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
-                        if let Err(e) = self.reconcile().await {
+                        if let Err(e) = self.reconcile(Utc::now()).await {
                             warn!("EvictionController reconcile error: {}", e);
                         }
                     }
@@ -48,7 +56,7 @@ impl EvictionController {
                                 if event.key.starts_with("/registry/nodes/") =>
                             {
                                 while event_rx.try_recv().is_ok() {}
-                                if let Err(e) = self.reconcile().await {
+                                if let Err(e) = self.reconcile(Utc::now()).await {
                                     warn!("EvictionController reconcile error: {}", e);
                                 }
                                 while event_rx.try_recv().is_ok() {}
@@ -56,7 +64,7 @@ impl EvictionController {
                             }
                             Ok(_) => {}
                             Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
-                                if let Err(e) = self.reconcile().await {
+                                if let Err(e) = self.reconcile(Utc::now()).await {
                                     warn!("EvictionController reconcile error: {}", e);
                                 }
                                 interval.reset();
@@ -69,10 +77,10 @@ impl EvictionController {
         })
     }
 
-    /// One pass: find nodes in Unknown state past grace period and evict their pods.
-    async fn reconcile(&self) -> anyhow::Result<()> {
+    /// One pass at time `now`: find nodes that stopped heartbeating more than
+    /// the grace period ago and evict their pods.
+    async fn reconcile(&self, now: DateTime<Utc>) -> anyhow::Result<()> {
         let node_entries = self.store.list_prefix("/registry/nodes/").await?;
-        let now = chrono::Utc::now();
 
         let mut failed_node_names: Vec<String> = Vec::new();
 
@@ -91,7 +99,7 @@ impl EvictionController {
                 continue;
             }
 
-            if node.status == NodeStatus::Unknown {
+            if node.status != NodeStatus::Ready {
                 let age = now
                     .signed_duration_since(node.last_heartbeat)
                     .to_std()
@@ -99,8 +107,9 @@ impl EvictionController {
 
                 if age >= self.grace_period {
                     info!(
-                        "Node {} has been Unknown for {}s (grace={}s) — evicting pods",
+                        "Node {} has been {} for {}s (grace={}s) — evicting pods",
                         node.name,
+                        node.status,
                         age.as_secs(),
                         self.grace_period.as_secs()
                     );
@@ -116,28 +125,180 @@ impl EvictionController {
         // Evict pods from failed nodes
         let pod_entries = self.store.list_prefix("/registry/pods/").await?;
         for (key, value) in pod_entries {
-            let mut pod: Pod = match serde_json::from_slice(&value) {
+            let pod: Pod = match serde_json::from_slice(&value) {
                 Ok(p) => p,
                 Err(_) => continue,
             };
 
             if let Some(ref node_name) = pod.node_name
                 && failed_node_names.contains(node_name)
-                && pod.status != PodStatus::Pending
-                && pod.status != PodStatus::Succeeded
-                && pod.status != PodStatus::Failed
+                && evictable(&pod.status)
             {
+                // The agent that would finish the deletion is gone, and a
+                // static pod only runs where its manifest is.
+                if pod.status == PodStatus::Terminating || pod.mirror {
+                    info!(
+                        "Deleting {} pod {} (node {} lost)",
+                        if pod.mirror { "mirror" } else { "terminating" },
+                        pod.name,
+                        node_name
+                    );
+                    self.store.delete(&key).await?;
+                    continue;
+                }
+                let message = format!("Node {} lost: no heartbeat", node_name);
+                let mut evicted = false;
+                self.store
+                    .update(&key, |stored: &mut Pod| {
+                        // Rescheduled, finished or being deleted since it was listed.
+                        evicted = stored.node_name.as_ref() == Some(node_name)
+                            && evictable(&stored.status)
+                            && stored.status != PodStatus::Terminating;
+                        if !evicted {
+                            return false;
+                        }
+                        stored.node_name = None;
+                        stored.container_statuses.clear();
+                        if stored.owner_ref.is_some() {
+                            stored.status = PodStatus::Failed;
+                            stored.status_message = Some(message.clone());
+                        } else {
+                            stored.status = PodStatus::Pending;
+                            stored.status_message = None;
+                        }
+                        true
+                    })
+                    .await?;
+                if !evicted {
+                    continue;
+                }
                 info!(
-                    "Evicting pod {} (was on failed node {})",
+                    "Evicted pod {} (was on failed node {})",
                     pod.name, node_name
                 );
-                pod.node_name = None;
-                pod.status = PodStatus::Pending;
-                let data = serde_json::to_vec(&pod)?;
-                self.store.put(&key, &data).await?;
+                crate::events::record(
+                    &self.store,
+                    Event::warning("pod", &pod.namespace, &pod.name, "NodeLost", message),
+                )
+                .await;
             }
         }
 
         Ok(())
     }
 }
+
+/// Whether a pod in `status` still occupies its node.
+fn evictable(status: &PodStatus) -> bool {
+    !matches!(
+        status,
+        PodStatus::Pending | PodStatus::Succeeded | PodStatus::Failed
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_node, seed_pod, stored_node, stored_pod, test_store};
+
+    async fn mark_node(store: &StateStore, name: &str, status: NodeStatus) {
+        let mut node = stored_node(store, name).await;
+        node.status = status;
+        store
+            .put(
+                &format!("/registry/nodes/{}", name),
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    fn default_grace() -> Duration {
+        Duration::from_secs(pkg_constants::runtime::DEFAULT_POD_EVICTION_TIMEOUT_SECS)
+    }
+
+    #[tokio::test]
+    async fn test_lost_node_pods_evicted_after_grace_period() {
+        let store = test_store("evict-lost").await;
+        let beat = seed_node(&store, "worker-1").await;
+        seed_pod(&store, "web-abc", "worker-1", Some("rs-1")).await;
+        seed_pod(&store, "bare", "worker-1", None).await;
+        seed_pod(&store, "stopping", "worker-1", Some("rs-1")).await;
+        let mut stopping = stored_pod(&store, "stopping").await;
+        stopping.status = PodStatus::Terminating;
+        store
+            .put(
+                "/registry/pods/default/stopping",
+                &serde_json::to_vec(&stopping).unwrap(),
+            )
+            .await
+            .unwrap();
+        mark_node(&store, "worker-1", NodeStatus::Unknown).await;
+        let ctrl = EvictionController::new(store.clone(), default_grace());
+
+        // Still within the grace period: nothing moves.
+        ctrl.reconcile(beat + chrono::Duration::seconds(120))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_pod(&store, "web-abc").await.node_name.as_deref(),
+            Some("worker-1")
+        );
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(301))
+            .await
+            .unwrap();
+        let owned = stored_pod(&store, "web-abc").await;
+        assert_eq!(owned.status, PodStatus::Failed);
+        assert_eq!(owned.node_name, None);
+        assert!(owned.status_message.unwrap().contains("worker-1"));
+
+        let bare = stored_pod(&store, "bare").await;
+        assert_eq!(bare.status, PodStatus::Pending);
+        assert_eq!(bare.node_name, None);
+
+        // A pod whose deletion was pending on the lost agent is removed.
+        assert!(
+            store
+                .get("/registry/pods/default/stopping")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recovered_node_keeps_its_pods() {
+        let store = test_store("evict-recovered").await;
+        let beat = seed_node(&store, "worker-1").await;
+        seed_pod(&store, "web-abc", "worker-1", Some("rs-1")).await;
+        let ctrl = EvictionController::new(store.clone(), default_grace());
+
+        // The node was flagged NotReady but heartbeats resumed before eviction.
+        mark_node(&store, "worker-1", NodeStatus::NotReady).await;
+        mark_node(&store, "worker-1", NodeStatus::Ready).await;
+        ctrl.reconcile(beat + chrono::Duration::seconds(600))
+            .await
+            .unwrap();
+
+        let pod = stored_pod(&store, "web-abc").await;
+        assert_eq!(pod.status, PodStatus::Running);
+        assert_eq!(pod.node_name.as_deref(), Some("worker-1"));
+    }
+
+    #[tokio::test]
+    async fn test_configured_eviction_timeout() {
+        let store = test_store("evict-timeout").await;
+        let beat = seed_node(&store, "worker-1").await;
+        seed_pod(&store, "web-abc", "worker-1", Some("rs-1")).await;
+        mark_node(&store, "worker-1", NodeStatus::Unknown).await;
+        let ctrl = EvictionController::new(store.clone(), Duration::from_secs(60));
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(61))
+            .await
+            .unwrap();
+        let pod = stored_pod(&store, "web-abc").await;
+        assert_eq!(pod.status, PodStatus::Failed);
+        assert_eq!(pod.node_name, None);
+    }
+}
@@ -1,13 +1,20 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use pkg_state::client::StateStore;
 use pkg_types::deployment::Deployment;
 use pkg_types::hpa::HorizontalPodAutoscaler;
-use pkg_types::pod::{Pod, PodStatus};
+use pkg_types::metrics::PodMetrics;
+use pkg_types::pod::{Pod, PodStatus, ResourceRequirements};
+use std::collections::HashMap;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
+/// Utilization within this fraction of the target does not rescale.
+const SCALE_TOLERANCE: f64 = 0.1;
+
 /// Horizontal Pod Autoscaler controller.
-/// Scales deployment replicas based on average CPU/memory utilization.
+/// Scales deployment replicas based on average CPU/memory utilization, as
+/// reported per pod in node heartbeats.
 pub struct HPAController {
     store: StateStore,
     check_interval: Duration,
@@ -21,7 +28,7 @@ impl HPAController {
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "HPAController started (interval={}s)",
@@ -31,6 +38,7 @@ impl HPAController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
                         if let Err(e) = self.reconcile().await {
                             warn!("HPAController reconcile error: {}", e);
@@ -104,7 +112,7 @@ impl HPAController {
                     continue;
                 }
             };
-            let mut deploy: Deployment = match serde_json::from_slice(&deploy_data) {
+            let deploy: Deployment = match serde_json::from_slice(&deploy_data) {
                 Ok(d) => d,
                 Err(_) => continue,
             };
@@ -144,58 +152,35 @@ impl HPAController {
                 .collect();
 
             let current_replicas = deploy.spec.replicas;
-            let pod_count = running_pods.len() as u64;
-            let mut desired_replicas = current_replicas;
-
-            // Compute average CPU utilization
-            if let Some(target_cpu) = hpa.spec.metrics.cpu_utilization_percent
-                && pod_count > 0
-            {
-                let total_cpu_requested: u64 = running_pods
-                    .iter()
-                    .flat_map(|p| p.spec.containers.iter())
-                    .map(|c| c.resources.cpu_millis)
-                    .sum();
-
-                // Simulate current utilization (in production, agents report real metrics)
-                let avg_util = if total_cpu_requested > 0 { 70u32 } else { 0 };
-
-                hpa.status.current_cpu_utilization_percent = Some(avg_util);
-
-                if avg_util > target_cpu && current_replicas < hpa.spec.max_replicas {
-                    desired_replicas = (current_replicas + 1).min(hpa.spec.max_replicas);
-                } else if avg_util < target_cpu.saturating_sub(10)
-                    && current_replicas > hpa.spec.min_replicas
-                {
-                    desired_replicas = (current_replicas - 1).max(hpa.spec.min_replicas);
-                }
-            }
+            let metrics = self.pod_metrics(ns).await?;
+            let cpu_util = utilization(&running_pods, &metrics, |m| m.cpu_millis, |r| r.cpu_millis);
+            let mem_util = utilization(
+                &running_pods,
+                &metrics,
+                |m| m.memory_bytes,
+                |r| r.memory_bytes,
+            );
+            hpa.status.current_cpu_utilization_percent = cpu_util;
+            hpa.status.current_memory_utilization_percent = mem_util;
 
-            // Compute average memory utilization
-            if let Some(target_mem) = hpa.spec.metrics.memory_utilization_percent
-                && pod_count > 0
-            {
-                let avg_mem_util = 60u32; // Simulated baseline
-                hpa.status.current_memory_utilization_percent = Some(avg_mem_util);
-
-                if avg_mem_util > target_mem && desired_replicas < hpa.spec.max_replicas {
-                    desired_replicas = (desired_replicas + 1).min(hpa.spec.max_replicas);
-                } else if avg_mem_util < target_mem.saturating_sub(10)
-                    && desired_replicas > hpa.spec.min_replicas
-                {
-                    desired_replicas = (desired_replicas - 1).max(hpa.spec.min_replicas);
-                }
-            }
+            let desired_replicas =
+                recommend(&hpa, current_replicas, cpu_util, mem_util, Utc::now());
 
             // Apply scaling
             if desired_replicas != current_replicas {
-                deploy.spec.replicas = desired_replicas;
-                deploy.generation += 1;
-                let deploy_data = serde_json::to_vec(&deploy)?;
-                self.store.put(&deploy_key, &deploy_data).await?;
+                self.store
+                    .update(&deploy_key, |stored: &mut Deployment| {
+                        if stored.spec.replicas == desired_replicas {
+                            return false;
+                        }
+                        stored.spec.replicas = desired_replicas;
+                        stored.generation += 1;
+                        true
+                    })
+                    .await?;
                 info!(
-                    "HPA {}: scaled deployment {} from {} to {} replicas",
-                    hpa.name, deploy.name, current_replicas, desired_replicas
+                    "HPA {}: scaled deployment {} from {} to {} replicas (cpu {:?}%, memory {:?}%)",
+                    hpa.name, deploy.name, current_replicas, desired_replicas, cpu_util, mem_util
                 );
                 hpa.status.last_scale_time = Some(Utc::now());
             }
@@ -203,9 +188,270 @@ impl HPAController {
             // Update HPA status
             hpa.status.current_replicas = current_replicas;
             hpa.status.desired_replicas = desired_replicas;
-            let data = serde_json::to_vec(&hpa)?;
-            self.store.put(&hpa_key, &data).await?;
+            self.store
+                .update(&hpa_key, |stored: &mut HorizontalPodAutoscaler| {
+                    stored.status = hpa.status.clone();
+                    true
+                })
+                .await?;
         }
         Ok(())
     }
+
+    /// Fresh usage samples of the pods in `ns`, by pod name.
+    async fn pod_metrics(&self, ns: &str) -> anyhow::Result<HashMap<String, PodMetrics>> {
+        let max_age =
+            chrono::Duration::seconds(pkg_constants::timings::POD_METRICS_MAX_AGE_SECS as i64);
+        let now = Utc::now();
+        Ok(self
+            .store
+            .list_prefix(&format!("/registry/metrics/pods/{}/", ns))
+            .await?
+            .into_iter()
+            .filter_map(|(_, v)| serde_json::from_slice::<PodMetrics>(&v).ok())
+            .filter(|m| now - m.timestamp <= max_age)
+            .map(|m| (m.name.clone(), m))
+            .collect())
+    }
+}
+
+/// Average utilization of `pods` as a percentage of their requests, over the
+/// pods that have both a usage sample and a non-zero request. `None` when no
+/// pod qualifies.
+fn utilization(
+    pods: &[Pod],
+    metrics: &HashMap<String, PodMetrics>,
+    used: impl Fn(&PodMetrics) -> u64,
+    requested: impl Fn(&ResourceRequirements) -> u64,
+) -> Option<u32> {
+    let (mut total_used, mut total_requested) = (0u64, 0u64);
+    for pod in pods {
+        let Some(m) = metrics.get(&pod.name) else {
+            continue;
+        };
+        let request: u64 = pod
+            .spec
+            .containers
+            .iter()
+            .map(|c| requested(&c.resources))
+            .sum();
+        if request == 0 {
+            continue;
+        }
+        total_used += used(m);
+        total_requested += request;
+    }
+    (total_requested > 0).then(|| (total_used * 100 / total_requested) as u32)
+}
+
+/// Replica count that brings `utilization` to `target`:
+/// `ceil(current * utilization / target)`, or `current` when the ratio is
+/// within the tolerance.
+fn replicas_for(current: u32, utilization: u32, target: u32) -> u32 {
+    if target == 0 {
+        return current;
+    }
+    let ratio = utilization as f64 / target as f64;
+    if (ratio - 1.0).abs() <= SCALE_TOLERANCE {
+        return current;
+    }
+    (current as f64 * ratio).ceil() as u32
+}
+
+/// Desired replicas for `hpa`: the largest per-metric recommendation,
+/// clamped to `[min_replicas, max_replicas]`. Scaling down waits until the
+/// last scale is older than the stabilization window. A deployment scaled to
+/// zero is left alone.
+fn recommend(
+    hpa: &HorizontalPodAutoscaler,
+    current: u32,
+    cpu_util: Option<u32>,
+    mem_util: Option<u32>,
+    now: DateTime<Utc>,
+) -> u32 {
+    if current == 0 {
+        return 0;
+    }
+    let targets = &hpa.spec.metrics;
+    let desired = [
+        cpu_util.zip(targets.cpu_utilization_percent),
+        mem_util.zip(targets.memory_utilization_percent),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|(util, target)| replicas_for(current, util, target))
+    .max()
+    .unwrap_or(current)
+    .clamp(
+        hpa.spec.min_replicas,
+        hpa.spec.max_replicas.max(hpa.spec.min_replicas),
+    );
+
+    let window =
+        chrono::Duration::seconds(pkg_constants::timings::HPA_SCALE_DOWN_STABILIZATION_SECS as i64);
+    let recently_scaled = hpa.status.last_scale_time.is_some_and(|t| now - t < window);
+    if desired < current && recently_scaled {
+        return current;
+    }
+    desired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_store;
+
+    fn hpa(min: u32, max: u32, last_scale: Option<DateTime<Utc>>) -> HorizontalPodAutoscaler {
+        serde_json::from_value(serde_json::json!({
+            "id": "hpa-id",
+            "name": "web-hpa",
+            "namespace": "default",
+            "spec": {
+                "target_deployment": "web",
+                "min_replicas": min,
+                "max_replicas": max,
+                "metrics": { "cpu_utilization_percent": 50 },
+            },
+            "status": {
+                "current_replicas": 0,
+                "desired_replicas": 0,
+                "last_scale_time": last_scale,
+            },
+            "created_at": Utc::now(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_replicas_for_ratio() {
+        assert_eq!(replicas_for(2, 100, 50), 4);
+        assert_eq!(replicas_for(3, 70, 50), 5);
+        assert_eq!(replicas_for(4, 10, 50), 1);
+        // Within 10% of the target: no change.
+        assert_eq!(replicas_for(4, 54, 50), 4);
+    }
+
+    #[test]
+    fn test_recommend_clamps_to_min_max() {
+        let now = Utc::now();
+        assert_eq!(recommend(&hpa(1, 5, None), 3, Some(400), None, now), 5);
+        assert_eq!(recommend(&hpa(2, 5, None), 3, Some(1), None, now), 2);
+        // Without metrics the count only moves into the range.
+        assert_eq!(recommend(&hpa(2, 5, None), 1, None, None, now), 2);
+        assert_eq!(recommend(&hpa(2, 5, None), 4, None, None, now), 4);
+        assert_eq!(recommend(&hpa(2, 5, None), 0, Some(400), None, now), 0);
+    }
+
+    #[test]
+    fn test_recommend_stabilizes_scale_down() {
+        let now = Utc::now();
+        let recent = hpa(1, 10, Some(now - chrono::Duration::minutes(2)));
+        assert_eq!(recommend(&recent, 4, Some(10), None, now), 4);
+        // Scaling up is never held back.
+        assert_eq!(recommend(&recent, 4, Some(100), None, now), 8);
+        let old = hpa(1, 10, Some(now - chrono::Duration::minutes(6)));
+        assert_eq!(recommend(&old, 4, Some(10), None, now), 1);
+    }
+
+    async fn put<T: serde::Serialize>(store: &StateStore, key: &str, value: &T) {
+        store
+            .put(key, &serde_json::to_vec(value).unwrap())
+            .await
+            .unwrap();
+    }
+
+    /// A deployment with `replicas` running pods requesting 200m CPU each,
+    /// each reporting `cpu_millis` of usage.
+    async fn seed(store: &StateStore, replicas: u32, cpu_millis: u64) {
+        let deploy: Deployment = serde_json::from_value(serde_json::json!({
+            "id": "web-id",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "replicas": replicas,
+                "template": { "containers": [{ "name": "web", "image": "nginx" }] },
+            },
+            "created_at": Utc::now(),
+        }))
+        .unwrap();
+        put(store, "/registry/deployments/default/web", &deploy).await;
+        put(
+            store,
+            "/registry/replicasets/default/web-1",
+            &serde_json::json!({
+                "id": "rs-id",
+                "name": "web-1",
+                "namespace": "default",
+                "spec": {
+                    "replicas": replicas,
+                    "template": { "containers": [{ "name": "web", "image": "nginx" }] },
+                },
+                "owner_ref": "web-id",
+                "created_at": Utc::now(),
+            }),
+        )
+        .await;
+        for i in 0..replicas {
+            let name = format!("web-{}", i);
+            put(
+                store,
+                &format!("/registry/pods/default/{}", name),
+                &serde_json::json!({
+                    "id": format!("{}-id", name),
+                    "name": name,
+                    "namespace": "default",
+                    "spec": { "containers": [{
+                        "name": "web",
+                        "image": "nginx",
+                        "resources": { "cpu_millis": 200, "memory_bytes": 0 },
+                    }] },
+                    "status": "Running",
+                    "owner_ref": "rs-id",
+                    "created_at": Utc::now(),
+                }),
+            )
+            .await;
+            let metrics = PodMetrics {
+                namespace: "default".to_string(),
+                name: name.clone(),
+                node_name: "node-a".to_string(),
+                cpu_millis,
+                memory_bytes: 0,
+                timestamp: Utc::now(),
+            };
+            put(store, &PodMetrics::key("default", &name), &metrics).await;
+        }
+    }
+
+    async fn stored<T: serde::de::DeserializeOwned>(store: &StateStore, key: &str) -> T {
+        serde_json::from_slice(&store.get(key).await.unwrap().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_scales_on_reported_usage() {
+        let store = test_store("hpa-scale-up").await;
+        // 2 pods at 180m of 200m requested: 90% against a 50% target.
+        seed(&store, 2, 180).await;
+        put(&store, "/registry/hpa/default/web-hpa", &hpa(1, 10, None)).await;
+
+        let ctrl = HPAController::new(store.clone());
+        ctrl.reconcile_namespace("default").await.unwrap();
+
+        let deploy: Deployment = stored(&store, "/registry/deployments/default/web").await;
+        assert_eq!(deploy.spec.replicas, 4);
+        let hpa: HorizontalPodAutoscaler = stored(&store, "/registry/hpa/default/web-hpa").await;
+        assert_eq!(hpa.status.current_replicas, 2);
+        assert_eq!(hpa.status.desired_replicas, 4);
+        assert_eq!(hpa.status.current_cpu_utilization_percent, Some(90));
+        assert!(hpa.status.last_scale_time.is_some());
+
+        // Load drops right after: the scale-down waits out the window.
+        seed(&store, 4, 20).await;
+        ctrl.reconcile_namespace("default").await.unwrap();
+        let deploy: Deployment = stored(&store, "/registry/deployments/default/web").await;
+        assert_eq!(deploy.spec.replicas, 4);
+        let hpa: HorizontalPodAutoscaler = stored(&store, "/registry/hpa/default/web-hpa").await;
+        assert_eq!(hpa.status.current_cpu_utilization_percent, Some(10));
+        assert_eq!(hpa.status.desired_replicas, 4);
+    }
 }
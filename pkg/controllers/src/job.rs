@@ -1,15 +1,19 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use pkg_scheduler::Scheduler;
 use pkg_state::client::StateStore;
+use pkg_types::event::Event;
 use pkg_types::job::{Job, JobCondition};
-use pkg_types::pod::{Pod, PodStatus};
+use pkg_types::pod::{Pod, PodStatus, RestartPolicy};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 use uuid::Uuid;
 
-/// Controller that manages Job lifecycle — creates Pods to completion.
+/// Controller that manages Job lifecycle — creates Pods to completion,
+/// retrying failures up to `backoff_limit` and enforcing
+/// `active_deadline_seconds`.
 pub struct JobController {
     store: StateStore,
     scheduler: Arc<Scheduler>,
@@ -25,7 +29,7 @@ impl JobController {
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "JobController started (interval={}s)",
@@ -35,8 +39,9 @@ impl JobController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
-                        if let Err(e) = self.reconcile().await {
+                        if let Err(e) = self.reconcile(Utc::now()).await {
                             warn!("JobController reconcile error: {}", e);
                         }
                     }
@@ -47,7 +52,7 @@ impl JobController {
                                     || event.key.starts_with("/registry/pods/") =>
                             {
                                 while event_rx.try_recv().is_ok() {}
-                                if let Err(e) = self.reconcile().await {
+                                if let Err(e) = self.reconcile(Utc::now()).await {
                                     warn!("JobController reconcile error: {}", e);
                                 }
                                 while event_rx.try_recv().is_ok() {}
@@ -55,7 +60,7 @@ impl JobController {
                             }
                             Ok(_) => {}
                             Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
-                                if let Err(e) = self.reconcile().await {
+                                if let Err(e) = self.reconcile(Utc::now()).await {
                                     warn!("JobController reconcile error: {}", e);
                                 }
                                 interval.reset();
@@ -68,7 +73,7 @@ impl JobController {
         })
     }
 
-    async fn reconcile(&self) -> anyhow::Result<()> {
+    async fn reconcile(&self, now: DateTime<Utc>) -> anyhow::Result<()> {
         let ns_entries = self.store.list_prefix("/registry/namespaces/").await?;
         for (ns_key, _) in ns_entries {
             let ns = ns_key
@@ -78,12 +83,12 @@ impl JobController {
             if ns.is_empty() {
                 continue;
             }
-            self.reconcile_namespace(&ns).await?;
+            self.reconcile_namespace(&ns, now).await?;
         }
         Ok(())
     }
 
-    async fn reconcile_namespace(&self, ns: &str) -> anyhow::Result<()> {
+    async fn reconcile_namespace(&self, ns: &str, now: DateTime<Utc>) -> anyhow::Result<()> {
         let job_prefix = format!("/registry/jobs/{}/", ns);
         let job_entries = self.store.list_prefix(&job_prefix).await?;
 
@@ -93,6 +98,16 @@ impl JobController {
             .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
             .collect();
 
+        // Bound pods, for anti-affinity; new pods are appended as they are placed
+        let mut scheduled_pods: Vec<Pod> = self
+            .store
+            .list_prefix("/registry/pods/")
+            .await?
+            .into_iter()
+            .filter_map(|(_, v)| serde_json::from_slice::<Pod>(&v).ok())
+            .filter(|p| p.node_name.is_some())
+            .collect();
+
         for (job_key, job_value) in job_entries {
             let mut job: Job = match serde_json::from_slice(&job_value) {
                 Ok(j) => j,
@@ -105,11 +120,10 @@ impl JobController {
             {
                 continue;
             }
+            let before = serde_json::to_value(&job.status).ok();
 
             // Set start time if not set
-            if job.status.start_time.is_none() {
-                job.status.start_time = Some(Utc::now());
-            }
+            let start_time = *job.status.start_time.get_or_insert(now);
 
             // Get owned pods
             let pod_prefix = format!("/registry/pods/{}/", ns);
@@ -127,18 +141,7 @@ impl JobController {
                 .collect();
 
             // Count pod states
-            let active = owned_pods
-                .iter()
-                .filter(|(_, p)| {
-                    matches!(
-                        p.status,
-                        PodStatus::Pending
-                            | PodStatus::Scheduled
-                            | PodStatus::ContainerCreating
-                            | PodStatus::Running
-                    )
-                })
-                .count() as u32;
+            let active = owned_pods.iter().filter(|(_, p)| is_active(p)).count() as u32;
             let succeeded = owned_pods
                 .iter()
                 .filter(|(_, p)| p.status == PodStatus::Succeeded)
@@ -152,29 +155,104 @@ impl JobController {
             job.status.succeeded = succeeded;
             job.status.failed = failed;
 
+            let deadline_passed = job
+                .spec
+                .active_deadline_seconds
+                .is_some_and(|secs| now >= start_time + chrono::Duration::seconds(secs as i64));
+
             // Check completion
             if succeeded >= job.spec.completions {
                 job.status.condition = JobCondition::Complete;
-                job.status.completion_time = Some(Utc::now());
+                job.status.completion_time = Some(now);
+                job.status.active = 0;
+                self.terminate_pods(&owned_pods).await?;
                 info!("Job {}: completed ({} succeeded)", job.name, succeeded);
-            } else if failed >= job.spec.backoff_limit {
-                job.status.condition = JobCondition::Failed;
-                info!(
-                    "Job {}: failed (backoff limit {} reached)",
-                    job.name, job.spec.backoff_limit
+            } else if failed > job.spec.backoff_limit {
+                let message = format!(
+                    "Job has reached the specified backoff limit ({} failed pods)",
+                    failed
                 );
+                self.fail(&mut job, &owned_pods, "BackoffLimitExceeded", message)
+                    .await?;
+            } else if deadline_passed {
+                let message = format!(
+                    "Job was active longer than specified deadline ({}s)",
+                    job.spec.active_deadline_seconds.unwrap_or_default()
+                );
+                self.fail(&mut job, &owned_pods, "DeadlineExceeded", message)
+                    .await?;
             } else if active < job.spec.parallelism && (active + succeeded) < job.spec.completions {
-                // Need to create more pods
+                // Need to create more pods; failed ones are retried here
                 let to_create =
                     (job.spec.parallelism - active).min(job.spec.completions - active - succeeded);
                 for _ in 0..to_create {
-                    self.create_job_pod(ns, &job, &nodes).await?;
-                    info!("Job {}: created new pod", job.name);
+                    let Some(pod) = self
+                        .create_job_pod(ns, &job, &nodes, &scheduled_pods)
+                        .await?
+                    else {
+                        break;
+                    };
+                    info!("Job {}: created pod {}", job.name, pod.name);
+                    job.status.active += 1;
+                    if pod.node_name.is_some() {
+                        scheduled_pods.push(pod);
+                    }
                 }
             }
 
-            let data = serde_json::to_vec(&job)?;
-            self.store.put(&job_key, &data).await?;
+            if serde_json::to_value(&job.status).ok() != before {
+                self.store
+                    .update(&job_key, |stored: &mut Job| {
+                        stored.status = job.status.clone();
+                        true
+                    })
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark `job` Failed and kill its remaining pods.
+    async fn fail(
+        &self,
+        job: &mut Job,
+        owned_pods: &[(String, Pod)],
+        reason: &str,
+        message: String,
+    ) -> anyhow::Result<()> {
+        job.status.condition = JobCondition::Failed;
+        job.status.active = 0;
+        self.terminate_pods(owned_pods).await?;
+        job.status.reason = Some(reason.to_string());
+        job.status.message = Some(message.clone());
+        info!("Job {}: failed ({}: {})", job.name, reason, message);
+        crate::events::record(
+            &self.store,
+            Event::warning("job", &job.namespace, &job.name, reason, message),
+        )
+        .await;
+        Ok(())
+    }
+
+    /// Stop the active pods in `pods`: those bound to a node are marked
+    /// `Terminating` for their agent to stop gracefully, the rest deleted.
+    async fn terminate_pods(&self, pods: &[(String, Pod)]) -> anyhow::Result<()> {
+        for (key, pod) in pods.iter().filter(|(_, p)| is_active(p)) {
+            if pod.node_name.is_some() && pod.status != PodStatus::Pending {
+                self.store
+                    .update(key, |stored: &mut Pod| {
+                        // Finished or already stopping since it was listed.
+                        if !is_active(stored) {
+                            return false;
+                        }
+                        stored.status = PodStatus::Terminating;
+                        true
+                    })
+                    .await?;
+            } else {
+                self.store.delete(key).await?;
+            }
+            info!("Job: stopping pod {}", pod.name);
         }
         Ok(())
     }
@@ -184,13 +262,19 @@ impl JobController {
         ns: &str,
         job: &Job,
         nodes: &[pkg_types::node::Node],
-    ) -> anyhow::Result<Pod> {
+        scheduled_pods: &[Pod],
+    ) -> anyhow::Result<Option<Pod>> {
         let pod_id = Uuid::new_v4().to_string();
+        let mut spec = job.spec.template.clone();
+        // Job pods must be able to finish; retries are counted here, not by the agent.
+        if spec.restart_policy == RestartPolicy::Always {
+            spec.restart_policy = RestartPolicy::Never;
+        }
         let mut pod = Pod {
             id: pod_id.clone(),
             name: format!("{}-{}", job.name, &pod_id[..8]),
             namespace: ns.to_string(),
-            spec: job.spec.template.clone(),
+            spec,
             status: PodStatus::Pending,
             status_message: None,
             container_id: None,
@@ -201,10 +285,18 @@ impl JobController {
             runtime_info: None,
             ghost_ipv6: None,
             vpc_name: None,
+            pod_ip: None,
+            ready: false,
+            mirror: false,
+            container_statuses: Vec::new(),
             created_at: Utc::now(),
+            resource_version: 0,
         };
+        if !crate::admission::admit_owned_pod(&self.store, "job", &job.name, &mut pod).await? {
+            return Ok(None);
+        }
 
-        if let Some(node_name) = self.scheduler.schedule(&pod, nodes) {
+        if let Some(node_name) = self.scheduler.schedule(&pod, nodes, scheduled_pods) {
             pod.node_name = Some(node_name);
             pod.status = PodStatus::Scheduled;
         }
@@ -212,6 +304,202 @@ impl JobController {
         let key = format!("/registry/pods/{}/{}", ns, pod.name);
         let data = serde_json::to_vec(&pod)?;
         self.store.put(&key, &data).await?;
-        Ok(pod)
+        Ok(Some(pod))
+    }
+}
+
+/// A pod that is still working toward the Job's completions.
+fn is_active(pod: &Pod) -> bool {
+    matches!(
+        pod.status,
+        PodStatus::Pending
+            | PodStatus::Scheduled
+            | PodStatus::ContainerCreating
+            | PodStatus::Running
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::test_store;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    async fn seed_job(store: &StateStore, spec: serde_json::Value) {
+        let mut full = serde_json::json!({ "template": { "containers": [] } });
+        full.as_object_mut()
+            .unwrap()
+            .extend(spec.as_object().unwrap().clone());
+        let job = serde_json::json!({
+            "id": "batch-id",
+            "name": "batch",
+            "namespace": "default",
+            "spec": full,
+            "created_at": at("2024-03-01T10:00:00Z"),
+        });
+        store
+            .put(
+                "/registry/jobs/default/batch",
+                &serde_json::to_vec(&job).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn stored_job(store: &StateStore) -> Job {
+        let data = store
+            .get("/registry/jobs/default/batch")
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    async fn job_pods(store: &StateStore) -> Vec<(String, Pod)> {
+        let mut pods: Vec<(String, Pod)> = store
+            .list_prefix("/registry/pods/default/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(k, v)| (k, serde_json::from_slice(&v).unwrap()))
+            .collect();
+        pods.sort_by_key(|(_, p)| p.created_at);
+        pods
+    }
+
+    async fn set_status(store: &StateStore, key: &str, status: PodStatus) {
+        let mut pod: Pod = serde_json::from_slice(&store.get(key).await.unwrap().unwrap()).unwrap();
+        if status != PodStatus::Pending {
+            pod.node_name = Some("worker-1".to_string());
+        }
+        pod.status = status;
+        store
+            .put(key, &serde_json::to_vec(&pod).unwrap())
+            .await
+            .unwrap();
+    }
+
+    /// Keys of the owned pods with `status`.
+    async fn with_status(store: &StateStore, status: PodStatus) -> Vec<String> {
+        job_pods(store)
+            .await
+            .into_iter()
+            .filter(|(_, p)| p.status == status)
+            .map(|(k, _)| k)
+            .collect()
+    }
+
+    fn controller(store: &StateStore) -> JobController {
+        JobController::new(store.clone(), Arc::new(Scheduler::new()))
+    }
+
+    #[tokio::test]
+    async fn test_runs_to_completions_within_parallelism() {
+        let store = test_store("job-parallel").await;
+        seed_job(
+            &store,
+            serde_json::json!({ "completions": 3, "parallelism": 2 }),
+        )
+        .await;
+        let ctrl = controller(&store);
+        let now = at("2024-03-01T10:00:00Z");
+
+        ctrl.reconcile_namespace("default", now).await.unwrap();
+        let pods = job_pods(&store).await;
+        assert_eq!(pods.len(), 2);
+        assert!(
+            pods.iter()
+                .all(|(_, p)| p.owner_ref.as_deref() == Some("batch-id"))
+        );
+        assert_eq!(stored_job(&store).await.status.active, 2);
+
+        // One finishes: only one more is needed to reach three.
+        set_status(&store, &pods[0].0, PodStatus::Succeeded).await;
+        ctrl.reconcile_namespace("default", now).await.unwrap();
+        assert_eq!(job_pods(&store).await.len(), 3);
+        let job = stored_job(&store).await;
+        assert_eq!((job.status.active, job.status.succeeded), (2, 1));
+
+        for key in with_status(&store, PodStatus::Pending).await {
+            set_status(&store, &key, PodStatus::Succeeded).await;
+        }
+        ctrl.reconcile_namespace("default", now).await.unwrap();
+        let job = stored_job(&store).await;
+        assert_eq!(job.status.condition, JobCondition::Complete);
+        assert_eq!((job.status.active, job.status.succeeded), (0, 3));
+        assert_eq!(job.status.completion_time, Some(now));
+        assert_eq!(job_pods(&store).await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_pods_retried_until_backoff_limit() {
+        let store = test_store("job-backoff").await;
+        seed_job(&store, serde_json::json!({ "backoff_limit": 1 })).await;
+        let ctrl = controller(&store);
+        let now = at("2024-03-01T10:00:00Z");
+
+        ctrl.reconcile_namespace("default", now).await.unwrap();
+        let first = job_pods(&store).await.remove(0).0;
+        set_status(&store, &first, PodStatus::Failed).await;
+
+        // One retry is allowed.
+        ctrl.reconcile_namespace("default", now).await.unwrap();
+        assert_eq!(with_status(&store, PodStatus::Pending).await.len(), 1);
+        let job = stored_job(&store).await;
+        assert_eq!(job.status.condition, JobCondition::Running);
+        assert_eq!(job.status.failed, 1);
+
+        let retry = with_status(&store, PodStatus::Pending).await.remove(0);
+        set_status(&store, &retry, PodStatus::Failed).await;
+        ctrl.reconcile_namespace("default", now).await.unwrap();
+        let job = stored_job(&store).await;
+        assert_eq!(job.status.condition, JobCondition::Failed);
+        assert_eq!(job.status.reason.as_deref(), Some("BackoffLimitExceeded"));
+        assert_eq!(job.status.failed, 2);
+        assert_eq!(job_pods(&store).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_active_deadline_kills_pods() {
+        let store = test_store("job-deadline").await;
+        seed_job(
+            &store,
+            serde_json::json!({
+                "completions": 2,
+                "parallelism": 2,
+                "active_deadline_seconds": 60,
+            }),
+        )
+        .await;
+        let ctrl = controller(&store);
+        let start = at("2024-03-01T10:00:00Z");
+
+        ctrl.reconcile_namespace("default", start).await.unwrap();
+        let pods = job_pods(&store).await;
+        set_status(&store, &pods[0].0, PodStatus::Running).await;
+
+        ctrl.reconcile_namespace("default", start + chrono::Duration::seconds(59))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_job(&store).await.status.condition,
+            JobCondition::Running
+        );
+
+        ctrl.reconcile_namespace("default", start + chrono::Duration::seconds(60))
+            .await
+            .unwrap();
+        let job = stored_job(&store).await;
+        assert_eq!(job.status.condition, JobCondition::Failed);
+        assert_eq!(job.status.reason.as_deref(), Some("DeadlineExceeded"));
+        assert_eq!(job.status.active, 0);
+        // The running pod stops gracefully; the unbound one is just removed.
+        let left = job_pods(&store).await;
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].0, pods[0].0);
+        assert_eq!(left[0].1.status, PodStatus::Terminating);
     }
 }
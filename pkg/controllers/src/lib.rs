@@ -1,12 +1,24 @@
+pub mod admission;
 pub mod backup;
 pub mod cronjob;
 pub mod daemonset;
 pub mod deployment;
+pub mod disruption;
 pub mod endpoint;
+pub mod events;
 pub mod eviction;
 pub mod hpa;
 pub mod job;
+pub mod manager;
+pub mod namespace;
 pub mod node;
+pub mod quota;
 pub mod replicaset;
 pub mod restore_watcher;
+pub mod scheduling;
+pub mod taint_eviction;
+pub mod volume;
 pub mod vpc;
+
+#[cfg(test)]
+pub(crate) mod testing;
@@ -0,0 +1,201 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use pkg_constants::state::LEADER_STOP_GRACE_SECS;
+
+/// Starts a set of controllers that stop once the token is cancelled.
+type StartFn = dyn Fn(&CancellationToken) -> Vec<JoinHandle<()>> + Send + Sync;
+
+/// Runs the leader-only controllers (the scheduler among them) while this
+/// server holds the leader lease, and stops them when it steps down.
+pub struct ControllerManager {
+    start: Box<StartFn>,
+    /// Set while the controllers run; the API gates leader-only routes on it.
+    is_leader: Arc<AtomicBool>,
+    stop_grace: Duration,
+}
+
+impl ControllerManager {
+    pub fn new(
+        start: impl Fn(&CancellationToken) -> Vec<JoinHandle<()>> + Send + Sync + 'static,
+        is_leader: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            start: Box::new(start),
+            is_leader,
+            stop_grace: Duration::from_secs(LEADER_STOP_GRACE_SECS),
+        }
+    }
+
+    /// Follow `leader` as a background task: start the controllers when it
+    /// turns true, stop them when it turns false or the election ends.
+    pub fn run(self, mut leader: watch::Receiver<bool>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                // Wait until we become leader
+                if leader.wait_for(|is_leader| *is_leader).await.is_err() {
+                    return;
+                }
+                self.is_leader.store(true, Ordering::SeqCst);
+                info!("Starting controllers (leader mode)");
+                let stop = CancellationToken::new();
+                let handles = (self.start)(&stop);
+
+                // Wait until we lose leadership, or nothing renews it any more
+                let ended = leader.wait_for(|is_leader| !*is_leader).await.is_err();
+                self.is_leader.store(false, Ordering::SeqCst);
+                warn!("Lost leadership — stopping controllers");
+                self.stop(stop, handles).await;
+                if ended {
+                    return;
+                }
+            }
+        })
+    }
+
+    /// Cancel the controllers and wait for them to finish their current
+    /// pass, aborting any still running after the grace period.
+    async fn stop(&self, stop: CancellationToken, handles: Vec<JoinHandle<()>>) {
+        stop.cancel();
+        let deadline = tokio::time::Instant::now() + self.stop_grace;
+        for mut handle in handles {
+            if tokio::time::timeout_at(deadline, &mut handle)
+                .await
+                .is_err()
+            {
+                warn!("Controller did not stop within the grace period; aborting it");
+                handle.abort();
+            }
+        }
+        info!("Controllers stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pkg_state::backend::{SlateDbBackend, StateBackend};
+    use pkg_state::client::StateStore;
+    use pkg_state::leader::LeaderElection;
+    use std::sync::Mutex;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Stands in for each server's scheduler: counts how many act at once.
+    #[derive(Clone, Default)]
+    struct Schedulers {
+        acting: Arc<AtomicUsize>,
+        most_at_once: Arc<AtomicUsize>,
+        started: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    impl Schedulers {
+        fn start(&self, server: &'static str, stop: &CancellationToken) -> Vec<JoinHandle<()>> {
+            let this = self.clone();
+            let stop = stop.clone();
+            this.started.lock().unwrap().push(server);
+            vec![tokio::spawn(async move {
+                let now = this.acting.fetch_add(1, Ordering::SeqCst) + 1;
+                this.most_at_once.fetch_max(now, Ordering::SeqCst);
+                stop.cancelled().await;
+                this.acting.fetch_sub(1, Ordering::SeqCst);
+            })]
+        }
+    }
+
+    async fn wait_until(what: &str, timeout: Duration, check: impl Fn() -> bool) {
+        tokio::time::timeout(timeout, async {
+            while !check() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("timed out waiting until {}", what));
+    }
+
+    #[tokio::test]
+    async fn test_controllers_follow_leadership() {
+        let (tx, rx) = watch::channel(false);
+        let is_leader = Arc::new(AtomicBool::new(false));
+        let schedulers = Schedulers::default();
+        let started = schedulers.clone();
+        let manager = ControllerManager::new(
+            move |stop| started.start("server-a", stop),
+            is_leader.clone(),
+        )
+        .run(rx);
+
+        let acting = || schedulers.acting.load(Ordering::SeqCst);
+        tx.send(true).unwrap();
+        wait_until("started", Duration::from_secs(2), || acting() == 1).await;
+        assert!(is_leader.load(Ordering::SeqCst));
+
+        tx.send(false).unwrap();
+        wait_until("stopped", Duration::from_secs(2), || acting() == 0).await;
+        assert!(!is_leader.load(Ordering::SeqCst));
+
+        tx.send(true).unwrap();
+        wait_until("restarted", Duration::from_secs(2), || acting() == 1).await;
+        // The election going away stops the controllers too.
+        drop(tx);
+        wait_until("stopped again", Duration::from_secs(2), || acting() == 0).await;
+        manager.await.unwrap();
+        assert_eq!(schedulers.started.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_follower_takes_over_when_leader_stops_renewing() {
+        let dir = std::env::temp_dir().join(format!(
+            "k3rs-controllers-test-manager-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        let shared: Arc<dyn StateBackend> =
+            Arc::new(SlateDbBackend::open(&dir.to_string_lossy()).await.unwrap());
+        let ttl = Duration::from_millis(600);
+        let schedulers = Schedulers::default();
+
+        let server = |name: &'static str| {
+            let shared = shared.clone();
+            let schedulers = schedulers.clone();
+            async move {
+                let store = StateStore::with_backend(shared).await.unwrap();
+                let (election, leader) = LeaderElection::new(store, name.to_string())
+                    .with_timings(ttl, Duration::from_millis(100), Duration::from_millis(300))
+                    .start();
+                let is_leader = Arc::new(AtomicBool::new(false));
+                ControllerManager::new(move |stop| schedulers.start(name, stop), is_leader.clone())
+                    .run(leader);
+                (election, is_leader)
+            }
+        };
+
+        let (election_a, a_leads) = server("server-a").await;
+        wait_until("server-a leads", Duration::from_secs(2), || {
+            a_leads.load(Ordering::SeqCst)
+        })
+        .await;
+        let (_election_b, b_leads) = server("server-b").await;
+        // B follows for as long as A renews.
+        tokio::time::sleep(ttl * 2).await;
+        assert!(!b_leads.load(Ordering::SeqCst));
+
+        election_a.abort();
+        wait_until("server-b takes over", ttl * 4, || {
+            b_leads.load(Ordering::SeqCst)
+        })
+        .await;
+        assert!(!a_leads.load(Ordering::SeqCst));
+
+        assert_eq!(schedulers.acting.load(Ordering::SeqCst), 1);
+        assert_eq!(schedulers.most_at_once.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            *schedulers.started.lock().unwrap(),
+            vec!["server-a", "server-b"]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use pkg_state::client::StateStore;
+use pkg_types::event::Event;
+use pkg_types::namespace::{NAMESPACED_RESOURCES, Namespace, NamespacePhase};
+use pkg_types::pod::{Pod, PodStatus};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Controller that finishes namespace deletion: empties `Terminating`
+/// namespaces and removes their record once nothing is left in them.
+pub struct NamespaceController {
+    store: StateStore,
+    check_interval: Duration,
+}
+
+impl NamespaceController {
+    pub fn new(store: StateStore) -> Self {
+        Self {
+            store,
+            check_interval: Duration::from_secs(
+                pkg_constants::timings::NAMESPACE_CHECK_INTERVAL_SECS,
+            ),
+        }
+    }
+
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(
+                "NamespaceController started (interval={}s)",
+                self.check_interval.as_secs()
+            );
+            let mut event_rx = self.store.event_log.subscribe();
+            let mut interval = tokio::time::interval(self.check_interval);
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = interval.tick() => {
+                        if let Err(e) = self.reconcile().await {
+                            warn!("NamespaceController reconcile error: {}", e);
+                        }
+                    }
+                    result = event_rx.recv() => {
+                        match result {
+                            Ok(ref event)
+                                if event.key.starts_with("/registry/namespaces/")
+                                    || event.key.starts_with("/registry/pods/") =>
+                            {
+                                while event_rx.try_recv().is_ok() {}
+                                if let Err(e) = self.reconcile().await {
+                                    warn!("NamespaceController reconcile error: {}", e);
+                                }
+                                while event_rx.try_recv().is_ok() {}
+                                interval.reset();
+                            }
+                            Ok(_) => {}
+                            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                                if let Err(e) = self.reconcile().await {
+                                    warn!("NamespaceController reconcile error: {}", e);
+                                }
+                                interval.reset();
+                            }
+                            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    async fn reconcile(&self) -> anyhow::Result<()> {
+        for (key, value) in self.store.list_prefix("/registry/namespaces/").await? {
+            let Ok(ns) = serde_json::from_slice::<Namespace>(&value) else {
+                continue;
+            };
+            if ns.phase != NamespacePhase::Terminating {
+                continue;
+            }
+            // Purged again on every pass: a controller may have created
+            // objects in the namespace since the last one.
+            if purge_namespace(&self.store, &ns.name).await? == 0 {
+                self.store.delete(&key).await?;
+                info!("Deleted namespace {}", ns.name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Delete everything in namespace `ns`. Pods whose containers may be running
+/// are marked `Terminating` so their agent stops them and then removes them;
+/// everything else, static pod mirrors included, is deleted right away. Returns how many objects are left.
+pub async fn purge_namespace(store: &StateStore, ns: &str) -> anyhow::Result<usize> {
+    let mut remaining = 0;
+    for resource in NAMESPACED_RESOURCES {
+        let prefix = format!("/registry/{}/{}/", resource, ns);
+        for (key, value) in store.list_prefix(&prefix).await? {
+            if *resource == "pods"
+                && let Ok(pod) = serde_json::from_slice::<Pod>(&value)
+                && !pod.mirror
+                && pod.node_name.is_some()
+                && matches!(
+                    pod.status,
+                    PodStatus::Scheduled
+                        | PodStatus::ContainerCreating
+                        | PodStatus::Running
+                        | PodStatus::Terminating
+                )
+            {
+                let mut stopping = false;
+                if pod.status != PodStatus::Terminating {
+                    store
+                        .update(&key, |stored: &mut Pod| {
+                            stopping = stored.status != PodStatus::Terminating;
+                            stored.status = PodStatus::Terminating;
+                            stopping
+                        })
+                        .await?;
+                }
+                if stopping {
+                    crate::events::record(
+                        store,
+                        Event::normal(
+                            "pod",
+                            ns,
+                            &pod.name,
+                            "Killing",
+                            format!("Stopping containers: namespace {} is being deleted", ns),
+                        ),
+                    )
+                    .await;
+                }
+                remaining += 1;
+                continue;
+            }
+            store.delete(&key).await?;
+        }
+    }
+    Ok(remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_pod, stored_pod, test_store};
+    use chrono::Utc;
+
+    async fn seed_namespace(store: &StateStore, name: &str, phase: NamespacePhase) {
+        let ns = Namespace {
+            name: name.to_string(),
+            labels: Default::default(),
+            phase,
+            created_at: Utc::now(),
+            resource_version: 0,
+        };
+        store
+            .put(
+                &format!("/registry/namespaces/{}", name),
+                &serde_json::to_vec(&ns).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_terminating_namespace_removed_once_pods_are_gone() {
+        let store = test_store("ns-finalize").await;
+        seed_namespace(&store, "default", NamespacePhase::Terminating).await;
+        seed_namespace(&store, "other", NamespacePhase::Active).await;
+        seed_pod(&store, "web", "worker-1", None).await;
+        for key in [
+            "/registry/configmaps/default/cfg",
+            "/registry/services/default/web",
+            "/registry/configmaps/other/cfg",
+        ] {
+            store.put(key, b"{}").await.unwrap();
+        }
+        let controller = NamespaceController::new(store.clone());
+
+        // The running pod has to be stopped by its agent first.
+        controller.reconcile().await.unwrap();
+        assert_eq!(
+            stored_pod(&store, "web").await.status,
+            PodStatus::Terminating
+        );
+        assert!(
+            store
+                .get("/registry/configmaps/default/cfg")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            store
+                .get("/registry/namespaces/default")
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // The agent removed it: the namespace goes too, the other one stays.
+        store.delete("/registry/pods/default/web").await.unwrap();
+        controller.reconcile().await.unwrap();
+        assert!(
+            store
+                .get("/registry/namespaces/default")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            store
+                .get("/registry/namespaces/other")
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            store
+                .get("/registry/configmaps/other/cfg")
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+}
@@ -1,34 +1,49 @@
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use pkg_metrics::MetricsRegistry;
 use pkg_state::client::StateStore;
+use pkg_types::event::{CLUSTER_EVENT_NAMESPACE, Event};
+use pkg_types::metrics::PodMetrics;
 use pkg_types::node::{Node, NodeStatus};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
+/// Counter bumped every time a node leaves `Ready` because its heartbeat went stale.
+pub const NODE_NOT_READY_METRIC: &str = "k3rs_node_not_ready_total";
+
 /// Background controller that monitors node health based on heartbeat timestamps.
-/// Transitions nodes: Ready → NotReady (30s stale) → Unknown (60s stale).
+/// Transitions nodes: Ready → NotReady (stale for the grace period, 30s by
+/// default) → Unknown (stale for twice that), and back to Ready as soon as
+/// heartbeats resume. Also drops pod usage samples
+/// whose pod is gone or whose node stopped reporting them.
 pub struct NodeController {
     store: StateStore,
+    metrics: Arc<MetricsRegistry>,
     check_interval: Duration,
     not_ready_threshold: Duration,
     unknown_threshold: Duration,
 }
 
 impl NodeController {
-    pub fn new(store: StateStore) -> Self {
+    /// `not_ready_grace` is how long a node may go without a heartbeat
+    /// before it is marked NotReady.
+    pub fn new(
+        store: StateStore,
+        metrics: Arc<MetricsRegistry>,
+        not_ready_grace: Duration,
+    ) -> Self {
         Self {
             store,
+            metrics,
             check_interval: Duration::from_secs(pkg_constants::timings::NODE_CHECK_INTERVAL_SECS),
-            not_ready_threshold: Duration::from_secs(
-                pkg_constants::timings::NODE_NOT_READY_THRESHOLD_SECS,
-            ),
-            unknown_threshold: Duration::from_secs(
-                pkg_constants::timings::NODE_UNKNOWN_THRESHOLD_SECS,
-            ),
+            not_ready_threshold: not_ready_grace,
+            unknown_threshold: not_ready_grace * 2,
         }
     }
 
     /// Start the controller loop as a background task.
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "NodeController started (interval={}s)",
@@ -36,59 +51,281 @@ impl NodeController {
             );
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
-                interval.tick().await;
-                if let Err(e) = self.reconcile().await {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = interval.tick() => {}
+                }
+                if let Err(e) = self.reconcile(Utc::now()).await {
                     warn!("NodeController reconcile error: {}", e);
                 }
             }
         })
     }
 
-    /// One pass: check all nodes and update stale ones.
-    async fn reconcile(&self) -> anyhow::Result<()> {
+    /// One pass at time `now`: check all nodes and update stale ones.
+    async fn reconcile(&self, now: DateTime<Utc>) -> anyhow::Result<()> {
         let entries = self.store.list_prefix("/registry/nodes/").await?;
-        let now = Utc::now();
 
         for (key, value) in entries {
-            let mut node: Node = match serde_json::from_slice(&value) {
+            let node: Node = match serde_json::from_slice(&value) {
                 Ok(n) => n,
                 Err(_) => continue,
             };
+            if self.status_at(&node, now).0 == node.status {
+                continue;
+            }
 
-            let age = now
-                .signed_duration_since(node.last_heartbeat)
-                .to_std()
-                .unwrap_or_default();
+            // Re-derived from the stored node, so a heartbeat or taint change
+            // landing in between is kept rather than overwritten.
+            let mut change = None;
+            self.store
+                .update(&key, |stored: &mut Node| {
+                    let (new_status, age) = self.status_at(stored, now);
+                    change = (stored.status != new_status)
+                        .then(|| (stored.status.clone(), new_status.clone(), age));
+                    stored.status = new_status;
+                    change.is_some()
+                })
+                .await?;
+            let Some((old_status, new_status, age)) = change else {
+                continue;
+            };
 
-            let is_master = node.labels.contains_key("node-role.kubernetes.io/master")
-                || node
-                    .labels
-                    .contains_key("node-role.kubernetes.io/control-plane");
-
-            let new_status = if is_master {
-                // The master node runs alongside the server and doesn't send heartbeats.
-                NodeStatus::Ready
-            } else if age >= self.unknown_threshold {
-                NodeStatus::Unknown
-            } else if age >= self.not_ready_threshold {
-                NodeStatus::NotReady
+            info!(
+                "Node {} status: {} → {} (last heartbeat {}s ago)",
+                node.name,
+                old_status,
+                new_status,
+                age.as_secs()
+            );
+            let event = if new_status == NodeStatus::Ready {
+                Event::normal(
+                    "node",
+                    CLUSTER_EVENT_NAMESPACE,
+                    &node.name,
+                    "NodeReady",
+                    "Heartbeats resumed",
+                )
             } else {
-                NodeStatus::Ready
+                if old_status == NodeStatus::Ready {
+                    self.metrics.counter_inc(NODE_NOT_READY_METRIC);
+                }
+                Event::warning(
+                    "node",
+                    CLUSTER_EVENT_NAMESPACE,
+                    &node.name,
+                    &format!("Node{}", new_status),
+                    format!("No heartbeat for {}s", age.as_secs()),
+                )
             };
+            crate::events::record(&self.store, event).await;
+        }
+        self.prune_pod_metrics(now).await
+    }
 
-            if node.status != new_status {
-                info!(
-                    "Node {} status: {} → {} (last heartbeat {}s ago)",
-                    node.name,
-                    node.status,
-                    new_status,
-                    age.as_secs()
-                );
-                node.status = new_status;
-                let data = serde_json::to_vec(&node)?;
-                self.store.put(&key, &data).await?;
+    /// The status `node` should have at `now`, and how long ago its last
+    /// heartbeat was.
+    fn status_at(&self, node: &Node, now: DateTime<Utc>) -> (NodeStatus, Duration) {
+        let age = now
+            .signed_duration_since(node.last_heartbeat)
+            .to_std()
+            .unwrap_or_default();
+
+        let is_master = node.labels.contains_key("node-role.kubernetes.io/master")
+            || node
+                .labels
+                .contains_key("node-role.kubernetes.io/control-plane");
+
+        let status = if is_master {
+            // The master node runs alongside the server and doesn't send heartbeats.
+            NodeStatus::Ready
+        } else if age >= self.unknown_threshold {
+            NodeStatus::Unknown
+        } else if age >= self.not_ready_threshold {
+            NodeStatus::NotReady
+        } else {
+            NodeStatus::Ready
+        };
+        (status, age)
+    }
+
+    /// Delete usage samples for deleted pods, and samples older than the
+    /// NotReady threshold — a node that stopped heartbeating no longer
+    /// replaces them, so they would show stale usage forever.
+    async fn prune_pod_metrics(&self, now: DateTime<Utc>) -> anyhow::Result<()> {
+        for (key, value) in self.store.list_prefix("/registry/metrics/pods/").await? {
+            let Ok(sample) = serde_json::from_slice::<PodMetrics>(&value) else {
+                continue;
+            };
+            let pod_key = format!("/registry/pods/{}/{}", sample.namespace, sample.name);
+            let age = now
+                .signed_duration_since(sample.timestamp)
+                .to_std()
+                .unwrap_or_default();
+            if age >= self.not_ready_threshold || self.store.get(&pod_key).await?.is_none() {
+                self.store.delete(&key).await?;
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_node, stored_node, test_store};
+
+    fn controller(store: StateStore) -> NodeController {
+        with_grace(
+            store,
+            pkg_constants::runtime::DEFAULT_NODE_NOT_READY_GRACE_SECS,
+        )
+    }
+
+    fn with_grace(store: StateStore, secs: u64) -> NodeController {
+        let metrics = Arc::new(MetricsRegistry::new());
+        metrics.register_counter(NODE_NOT_READY_METRIC, "test");
+        NodeController::new(store, metrics, Duration::from_secs(secs))
+    }
+
+    #[tokio::test]
+    async fn test_stale_heartbeat_marks_not_ready_then_unknown() {
+        let store = test_store("node-stale").await;
+        let beat = seed_node(&store, "worker-1").await;
+        let ctrl = controller(store.clone());
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(10))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_node(&store, "worker-1").await.status,
+            NodeStatus::Ready
+        );
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(31))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_node(&store, "worker-1").await.status,
+            NodeStatus::NotReady
+        );
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(61))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_node(&store, "worker-1").await.status,
+            NodeStatus::Unknown
+        );
+        // NotReady → Unknown is the same outage; it is counted once.
+        assert!(
+            ctrl.metrics
+                .render()
+                .contains(&format!("{} 1", NODE_NOT_READY_METRIC))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_configured_grace_period() {
+        let store = test_store("node-grace").await;
+        let beat = seed_node(&store, "worker-1").await;
+        let ctrl = with_grace(store.clone(), 10);
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(11))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_node(&store, "worker-1").await.status,
+            NodeStatus::NotReady
+        );
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(21))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_node(&store, "worker-1").await.status,
+            NodeStatus::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resumed_heartbeat_recovers_node() {
+        let store = test_store("node-recover").await;
+        let beat = seed_node(&store, "worker-1").await;
+        let ctrl = controller(store.clone());
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(40))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_node(&store, "worker-1").await.status,
+            NodeStatus::NotReady
+        );
+
+        // A heartbeat lands within the grace window; the next pass flips it back.
+        let mut node = stored_node(&store, "worker-1").await;
+        node.last_heartbeat = beat + chrono::Duration::seconds(45);
+        store
+            .put(
+                "/registry/nodes/worker-1",
+                &serde_json::to_vec(&node).unwrap(),
+            )
+            .await
+            .unwrap();
+        ctrl.reconcile(beat + chrono::Duration::seconds(50))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_node(&store, "worker-1").await.status,
+            NodeStatus::Ready
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prunes_stale_pod_metrics() {
+        let store = test_store("node-prune-metrics").await;
+        let beat = seed_node(&store, "worker-1").await;
+        let ctrl = controller(store.clone());
+
+        let sample = |name: &str, at: DateTime<Utc>| PodMetrics {
+            namespace: "default".into(),
+            name: name.into(),
+            node_name: "worker-1".into(),
+            cpu_millis: 100,
+            memory_bytes: 1 << 20,
+            timestamp: at,
+        };
+        for (name, at, pod_exists) in [
+            ("live", beat, true),
+            ("deleted", beat, false),
+            ("stale", beat - chrono::Duration::seconds(60), true),
+        ] {
+            let m = sample(name, at);
+            store
+                .put(
+                    &PodMetrics::key("default", name),
+                    &serde_json::to_vec(&m).unwrap(),
+                )
+                .await
+                .unwrap();
+            if pod_exists {
+                store
+                    .put(&format!("/registry/pods/default/{}", name), b"{}")
+                    .await
+                    .unwrap();
+            }
+        }
+
+        ctrl.reconcile(beat + chrono::Duration::seconds(5))
+            .await
+            .unwrap();
+        let left: Vec<String> = store
+            .list_prefix("/registry/metrics/pods/")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(left, vec![PodMetrics::key("default", "live")]);
+    }
+}
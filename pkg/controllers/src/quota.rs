@@ -0,0 +1,125 @@
+//! ResourceQuota admission. A pod may only be created while every quota in
+//! its namespace stays within its hard limits once the pod is counted. Usage
+//! is computed from the stored pods on each check, so deleting a pod frees
+//! its share right away.
+
+use pkg_state::client::StateStore;
+use pkg_types::pod::Pod;
+use pkg_types::quota::{QuotaUsage, ResourceQuota};
+
+/// The ResourceQuotas of namespace `ns`, sorted by name.
+async fn quotas(store: &StateStore, ns: &str) -> anyhow::Result<Vec<ResourceQuota>> {
+    let mut quotas: Vec<ResourceQuota> = store
+        .list_prefix(&format!("/registry/resourcequotas/{}/", ns))
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
+        .collect();
+    quotas.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(quotas)
+}
+
+/// Current usage of namespace `ns`, see [`QuotaUsage::of`].
+pub async fn namespace_usage(store: &StateStore, ns: &str) -> anyhow::Result<QuotaUsage> {
+    let pods: Vec<Pod> = store
+        .list_prefix(&format!("/registry/pods/{}/", ns))
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
+        .collect();
+    Ok(QuotaUsage::of(&pods))
+}
+
+/// The ResourceQuotas of namespace `ns` with `used` filled in.
+pub async fn quota_status(store: &StateStore, ns: &str) -> anyhow::Result<Vec<ResourceQuota>> {
+    let mut quotas = quotas(store, ns).await?;
+    if !quotas.is_empty() {
+        let usage = namespace_usage(store, ns).await?;
+        for quota in &mut quotas {
+            quota.used = usage.clone();
+        }
+    }
+    Ok(quotas)
+}
+
+/// Whether `pod` may be created in its namespace. The inner `Err` names the
+/// quota it would exceed, e.g. `exceeded quota 'compute': pods=11 > 10`.
+pub async fn admit_pod(store: &StateStore, pod: &Pod) -> anyhow::Result<Result<(), String>> {
+    let quotas = quotas(store, &pod.namespace).await?;
+    if quotas.is_empty() {
+        return Ok(Ok(()));
+    }
+    let mut usage = namespace_usage(store, &pod.namespace).await?;
+    usage.add(pod);
+    for quota in &quotas {
+        if let Some(exceeded) = quota.exceeded(&usage) {
+            return Ok(Err(format!(
+                "exceeded quota '{}': {}",
+                quota.name, exceeded
+            )));
+        }
+    }
+    Ok(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_pod, stored_pod, test_store};
+    use chrono::Utc;
+    use pkg_types::quota::QuotaLimits;
+
+    async fn seed_quota(store: &StateStore, max_pods: u32) {
+        let quota = ResourceQuota {
+            name: "pods".to_string(),
+            namespace: "default".to_string(),
+            hard: QuotaLimits {
+                max_pods: Some(max_pods),
+                max_cpu_millis: None,
+                max_memory_bytes: None,
+            },
+            used: QuotaUsage::default(),
+            created_at: Utc::now(),
+        };
+        store
+            .put(
+                "/registry/resourcequotas/default/pods",
+                &serde_json::to_vec(&quota).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_admit_pod_counts_live_pods() {
+        let store = test_store("quota-admit").await;
+        seed_pod(&store, "a", "worker-1", None).await;
+        let mut new = stored_pod(&store, "a").await;
+        new.name = "b".to_string();
+        // No quota: anything goes.
+        assert_eq!(admit_pod(&store, &new).await.unwrap(), Ok(()));
+
+        seed_quota(&store, 1).await;
+        assert_eq!(
+            admit_pod(&store, &new).await.unwrap(),
+            Err("exceeded quota 'pods': pods=2 > 1".to_string())
+        );
+        assert_eq!(
+            quota_status(&store, "default").await.unwrap()[0].used.pods,
+            1
+        );
+
+        // Deleting frees the quota immediately.
+        let mut a = stored_pod(&store, "a").await;
+        a.status = pkg_types::pod::PodStatus::Terminating;
+        store
+            .put("/registry/pods/default/a", &serde_json::to_vec(&a).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(admit_pod(&store, &new).await.unwrap(), Ok(()));
+        assert_eq!(
+            quota_status(&store, "default").await.unwrap()[0].used.pods,
+            0
+        );
+    }
+}
@@ -5,6 +5,7 @@ use pkg_types::pod::{Pod, PodStatus};
 use pkg_types::replicaset::ReplicaSet;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 use uuid::Uuid;
 
@@ -17,21 +18,24 @@ use uuid::Uuid;
 pub struct ReplicaSetController {
     store: StateStore,
     scheduler: Arc<Scheduler>,
+    /// Failed/Succeeded pods kept per ReplicaSet before being deleted.
+    failed_pod_retention: usize,
     check_interval: Duration,
 }
 
 impl ReplicaSetController {
-    pub fn new(store: StateStore, scheduler: Arc<Scheduler>) -> Self {
+    pub fn new(store: StateStore, scheduler: Arc<Scheduler>, failed_pod_retention: usize) -> Self {
         Self {
             store,
             scheduler,
+            failed_pod_retention,
             check_interval: Duration::from_secs(
                 pkg_constants::timings::REPLICASET_CHECK_INTERVAL_SECS,
             ),
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "ReplicaSetController started (interval={}s)",
@@ -41,6 +45,7 @@ impl ReplicaSetController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
                         if let Err(e) = self.reconcile().await {
                             warn!("ReplicaSetController reconcile error: {}", e);
@@ -100,34 +105,58 @@ impl ReplicaSetController {
             .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
             .collect();
 
+        // Bound pods, for anti-affinity; new pods are appended as they are placed
+        let mut scheduled_pods: Vec<Pod> = self
+            .store
+            .list_prefix("/registry/pods/")
+            .await?
+            .into_iter()
+            .filter_map(|(_, v)| serde_json::from_slice::<Pod>(&v).ok())
+            .filter(|p| p.node_name.is_some())
+            .collect();
+
         for (rs_key, rs_value) in rs_entries {
             let mut rs: ReplicaSet = match serde_json::from_slice(&rs_value) {
                 Ok(r) => r,
                 Err(_) => continue,
             };
 
+            let before = serde_json::to_value(&rs.status).ok();
+
             // Find pods owned by this RS
             let pod_prefix = format!("/registry/pods/{}/", ns);
             let pod_entries = self.store.list_prefix(&pod_prefix).await?;
-            let owned_pods: Vec<(String, Pod)> = pod_entries
-                .into_iter()
-                .filter_map(|(k, v)| {
-                    let pod: Pod = serde_json::from_slice(&v).ok()?;
-                    if pod.owner_ref.as_deref() == Some(&rs.id) {
-                        Some((k, pod))
-                    } else {
-                        None
-                    }
-                })
-                .collect();
+            // Failed and Succeeded pods (crashed, evicted from a lost node)
+            // and pods being deleted no longer count toward the desired
+            // replicas and get replaced.
+            let mut active_pods: Vec<(String, Pod)> = Vec::new();
+            let mut finished_pods: Vec<(String, Pod)> = Vec::new();
+            for (key, value) in pod_entries {
+                let Ok(pod) = serde_json::from_slice::<Pod>(&value) else {
+                    continue;
+                };
+                if pod.owner_ref.as_deref() != Some(&rs.id) {
+                    continue;
+                }
+                match pod.status {
+                    PodStatus::Failed | PodStatus::Succeeded => finished_pods.push((key, pod)),
+                    PodStatus::Terminating => {}
+                    _ => active_pods.push((key, pod)),
+                }
+            }
 
-            let current_count = owned_pods.len() as u32;
+            let current_count = active_pods.len() as u32;
 
             if current_count < rs.spec.replicas {
                 // Scale up — create missing pods
                 let to_create = rs.spec.replicas - current_count;
                 for i in 0..to_create {
-                    let pod = self.create_pod(ns, &rs, &nodes, i + current_count).await?;
+                    let Some(pod) = self
+                        .create_pod(ns, &rs, &nodes, &scheduled_pods, i + current_count)
+                        .await?
+                    else {
+                        break;
+                    };
                     info!(
                         "RS {}: created pod {} ({}/{})",
                         rs.name,
@@ -135,48 +164,47 @@ impl ReplicaSetController {
                         current_count + i + 1,
                         rs.spec.replicas
                     );
+                    if pod.node_name.is_some() {
+                        scheduled_pods.push(pod.clone());
+                    }
+                    active_pods.push((String::new(), pod));
                 }
             } else if current_count > rs.spec.replicas {
                 // Scale down — delete excess pods (newest first)
                 let to_delete = (current_count - rs.spec.replicas) as usize;
-                let mut pods_to_delete: Vec<(String, Pod)> = owned_pods;
-                pods_to_delete.sort_by_key(|b| std::cmp::Reverse(b.1.created_at));
-                for (pod_key, pod) in pods_to_delete.into_iter().take(to_delete) {
+                active_pods.sort_by_key(|b| std::cmp::Reverse(b.1.created_at));
+                for (pod_key, pod) in active_pods.drain(..to_delete) {
                     self.store.delete(&pod_key).await?;
                     info!("RS {}: deleted pod {}", rs.name, pod.name);
                 }
             }
 
-            // Recount pods for status update
-            let pod_prefix = format!("/registry/pods/{}/", ns);
-            let pod_entries = self.store.list_prefix(&pod_prefix).await?;
-            let mut replicas = 0u32;
-            let mut ready = 0u32;
-            let mut available = 0u32;
-            for (_, v) in pod_entries {
-                if let Ok(pod) = serde_json::from_slice::<Pod>(&v) {
-                    if pod.owner_ref.as_deref() != Some(&rs.id) {
-                        continue;
-                    }
-                    replicas += 1;
-                    match pod.status {
-                        PodStatus::Running => {
-                            ready += 1;
-                            available += 1;
-                        }
-                        PodStatus::Scheduled => {
-                            ready += 1;
-                        }
-                        _ => {}
-                    }
+            // Keep only the most recent finished pods, for debugging
+            if finished_pods.len() > self.failed_pod_retention {
+                finished_pods.sort_by_key(|b| std::cmp::Reverse(b.1.created_at));
+                for (pod_key, pod) in finished_pods.drain(self.failed_pod_retention..) {
+                    self.store.delete(&pod_key).await?;
+                    info!(
+                        "RS {}: garbage-collected {} pod {}",
+                        rs.name, pod.status, pod.name
+                    );
                 }
             }
 
-            rs.status.replicas = replicas;
-            rs.status.ready_replicas = ready;
-            rs.status.available_replicas = available;
-            let data = serde_json::to_vec(&rs)?;
-            self.store.put(&rs_key, &data).await?;
+            rs.status.replicas = active_pods.len() as u32;
+            // Ready once Running and passing its readiness probe
+            rs.status.ready_replicas =
+                active_pods.iter().filter(|(_, p)| p.is_ready()).count() as u32;
+            rs.status.available_replicas = rs.status.ready_replicas;
+            if serde_json::to_value(&rs.status).ok() != before {
+                // Only the status is ours; keep whatever else changed since.
+                self.store
+                    .update(&rs_key, |stored: &mut ReplicaSet| {
+                        stored.status = rs.status.clone();
+                        true
+                    })
+                    .await?;
+            }
         }
         Ok(())
     }
@@ -186,8 +214,9 @@ impl ReplicaSetController {
         ns: &str,
         rs: &ReplicaSet,
         nodes: &[pkg_types::node::Node],
+        scheduled_pods: &[Pod],
         _index: u32,
-    ) -> anyhow::Result<Pod> {
+    ) -> anyhow::Result<Option<Pod>> {
         let pod_id = Uuid::new_v4().to_string();
         let mut pod = Pod {
             id: pod_id.clone(),
@@ -204,11 +233,20 @@ impl ReplicaSetController {
             runtime_info: None,
             ghost_ipv6: None,
             vpc_name: None,
+            pod_ip: None,
+            ready: false,
+            mirror: false,
+            container_statuses: Vec::new(),
             created_at: Utc::now(),
+            resource_version: 0,
         };
+        if !crate::admission::admit_owned_pod(&self.store, "replicaset", &rs.name, &mut pod).await?
+        {
+            return Ok(None);
+        }
 
         // Schedule the pod
-        if let Some(node_name) = self.scheduler.schedule(&pod, nodes) {
+        if let Some(node_name) = self.scheduler.schedule(&pod, nodes, scheduled_pods) {
             pod.node_name = Some(node_name);
             pod.status = PodStatus::Scheduled;
         }
@@ -216,6 +254,201 @@ impl ReplicaSetController {
         let key = format!("/registry/pods/{}/{}", ns, pod.name);
         let data = serde_json::to_vec(&pod)?;
         self.store.put(&key, &data).await?;
-        Ok(pod)
+        Ok(Some(pod))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_pod, stored_pod, test_store};
+
+    async fn seed_replicaset(store: &StateStore, replicas: u32) {
+        let rs = serde_json::json!({
+            "id": "rs-1",
+            "name": "web-1",
+            "namespace": "default",
+            "spec": {
+                "replicas": replicas,
+                "template": { "containers": [] },
+            },
+            "created_at": Utc::now(),
+        });
+        store
+            .put(
+                "/registry/replicasets/default/web-1",
+                &serde_json::to_vec(&rs).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    /// A pod owned by the ReplicaSet, created `age_secs` ago.
+    async fn seed_owned(store: &StateStore, name: &str, status: PodStatus, age_secs: i64) {
+        seed_pod(store, name, "worker-1", Some("rs-1")).await;
+        let mut pod = stored_pod(store, name).await;
+        pod.status = status;
+        pod.created_at = Utc::now() - chrono::Duration::seconds(age_secs);
+        store
+            .put(
+                &format!("/registry/pods/default/{}", name),
+                &serde_json::to_vec(&pod).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn owned_pods(store: &StateStore) -> Vec<Pod> {
+        let mut pods: Vec<Pod> = store
+            .list_prefix("/registry/pods/default/")
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|(_, v)| serde_json::from_slice::<Pod>(&v).ok())
+            .filter(|p| p.owner_ref.as_deref() == Some("rs-1"))
+            .collect();
+        pods.sort_by_key(|p| p.created_at);
+        pods
+    }
+
+    async fn stored_rs(store: &StateStore) -> ReplicaSet {
+        let data = store
+            .get("/registry/replicasets/default/web-1")
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    fn controller(store: &StateStore, retention: usize) -> ReplicaSetController {
+        ReplicaSetController::new(store.clone(), Arc::new(Scheduler::new()), retention)
+    }
+
+    #[tokio::test]
+    async fn test_finished_pods_are_replaced() {
+        let store = test_store("rs-replace").await;
+        seed_replicaset(&store, 3).await;
+        seed_owned(&store, "a", PodStatus::Running, 40).await;
+        seed_owned(&store, "b", PodStatus::Running, 30).await;
+        seed_owned(&store, "c", PodStatus::Failed, 20).await;
+        seed_owned(&store, "d", PodStatus::Succeeded, 10).await;
+
+        controller(&store, 5)
+            .reconcile_namespace("default")
+            .await
+            .unwrap();
+        let pods = owned_pods(&store).await;
+        assert_eq!(pods.len(), 5);
+        let pending = pods
+            .iter()
+            .filter(|p| p.status == PodStatus::Pending)
+            .count();
+        assert_eq!(pending, 1);
+
+        let rs = stored_rs(&store).await;
+        assert_eq!(rs.status.replicas, 3);
+        assert_eq!(rs.status.ready_replicas, 2);
+    }
+
+    #[tokio::test]
+    async fn test_scale_up_stops_at_quota() {
+        let store = test_store("rs-quota").await;
+        seed_replicaset(&store, 3).await;
+        seed_owned(&store, "a", PodStatus::Running, 30).await;
+        let quota = serde_json::json!({
+            "name": "pods",
+            "namespace": "default",
+            "hard": { "max_pods": 2 },
+            "created_at": Utc::now(),
+        });
+        store
+            .put(
+                "/registry/resourcequotas/default/pods",
+                &serde_json::to_vec(&quota).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        controller(&store, 5)
+            .reconcile_namespace("default")
+            .await
+            .unwrap();
+        assert_eq!(owned_pods(&store).await.len(), 2);
+        assert_eq!(stored_rs(&store).await.status.replicas, 2);
+    }
+
+    #[tokio::test]
+    async fn test_surplus_pods_deleted_newest_first() {
+        let store = test_store("rs-surplus").await;
+        seed_replicaset(&store, 2).await;
+        seed_owned(&store, "old", PodStatus::Running, 30).await;
+        seed_owned(&store, "mid", PodStatus::Running, 20).await;
+        seed_owned(&store, "new", PodStatus::Pending, 10).await;
+        seed_owned(&store, "crashed", PodStatus::Failed, 5).await;
+
+        controller(&store, 5)
+            .reconcile_namespace("default")
+            .await
+            .unwrap();
+        let names: Vec<String> = owned_pods(&store)
+            .await
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, vec!["old", "mid", "crashed"]);
+        assert_eq!(stored_rs(&store).await.status.replicas, 2);
+    }
+
+    #[tokio::test]
+    async fn test_finished_pods_garbage_collected_beyond_retention() {
+        let store = test_store("rs-gc").await;
+        seed_replicaset(&store, 1).await;
+        seed_owned(&store, "live", PodStatus::Running, 60).await;
+        for (name, age) in [("f1", 40), ("f2", 30), ("f3", 20), ("f4", 10)] {
+            seed_owned(&store, name, PodStatus::Failed, age).await;
+        }
+
+        controller(&store, 2)
+            .reconcile_namespace("default")
+            .await
+            .unwrap();
+        let names: Vec<String> = owned_pods(&store)
+            .await
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, vec!["live", "f3", "f4"]);
+    }
+
+    #[tokio::test]
+    async fn test_ready_replicas_count_only_ready_pods() {
+        let store = test_store("rs-ready").await;
+        seed_replicaset(&store, 3).await;
+        seed_owned(&store, "running", PodStatus::Running, 30).await;
+        seed_owned(&store, "scheduled", PodStatus::Scheduled, 20).await;
+        seed_owned(&store, "probing", PodStatus::Running, 10).await;
+        let mut probing = stored_pod(&store, "probing").await;
+        probing.spec.containers = serde_json::from_value(serde_json::json!([{
+            "name": "web",
+            "image": "nginx",
+            "readiness_probe": { "http_get": { "path": "/", "port": 80 } },
+        }]))
+        .unwrap();
+        store
+            .put(
+                "/registry/pods/default/probing",
+                &serde_json::to_vec(&probing).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        controller(&store, 5)
+            .reconcile_namespace("default")
+            .await
+            .unwrap();
+        let rs = stored_rs(&store).await;
+        assert_eq!(rs.status.replicas, 3);
+        assert_eq!(rs.status.ready_replicas, 1);
+        assert_eq!(rs.status.available_replicas, 1);
     }
 }
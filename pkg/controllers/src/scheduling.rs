@@ -0,0 +1,155 @@
+use pkg_scheduler::Scheduler;
+use pkg_state::client::{PutOutcome, StateStore};
+use pkg_types::node::Node;
+use pkg_types::pod::{Pod, PodStatus};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Controller that binds unscheduled pods to nodes.
+///
+/// Pods can be left `Pending` without a node — created while no node had
+/// capacity, or reset by the EvictionController. Each pass hands the whole
+/// pending queue to `Scheduler::schedule_batch`, so higher-priority pods
+/// claim capacity first.
+pub struct SchedulingController {
+    store: StateStore,
+    scheduler: Arc<Scheduler>,
+    check_interval: Duration,
+}
+
+impl SchedulingController {
+    pub fn new(store: StateStore, scheduler: Arc<Scheduler>) -> Self {
+        Self {
+            store,
+            scheduler,
+            check_interval: Duration::from_secs(
+                pkg_constants::timings::SCHEDULING_CHECK_INTERVAL_SECS,
+            ),
+        }
+    }
+
+    /// Start the controller loop as a background task.
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(
+                "SchedulingController started (interval={}s)",
+                self.check_interval.as_secs()
+            );
+            let mut event_rx = self.store.event_log.subscribe();
+            let mut interval = tokio::time::interval(self.check_interval);
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = interval.tick() => {
+                        if let Err(e) = self.reconcile().await {
+                            warn!("SchedulingController reconcile error: {}", e);
+                        }
+                    }
+                    result = event_rx.recv() => {
+                        match result {
+                            Ok(ref event)
+                                if event.key.starts_with("/registry/pods/")
+                                    || event.key.starts_with("/registry/nodes/") =>
+                            {
+                                while event_rx.try_recv().is_ok() {}
+                                if let Err(e) = self.reconcile().await {
+                                    warn!("SchedulingController reconcile error: {}", e);
+                                }
+                                while event_rx.try_recv().is_ok() {}
+                                interval.reset();
+                            }
+                            Ok(_) => {}
+                            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {
+                                if let Err(e) = self.reconcile().await {
+                                    warn!("SchedulingController reconcile error: {}", e);
+                                }
+                                interval.reset();
+                            }
+                            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// One pass: schedule every pending pod that has no node assigned.
+    async fn reconcile(&self) -> anyhow::Result<()> {
+        let pod_entries = self.store.list_prefix("/registry/pods/").await?;
+        let mut pending: HashMap<String, (String, Pod)> = HashMap::new();
+        let mut scheduled: Vec<Pod> = Vec::new();
+        for (key, value) in pod_entries {
+            let pod: Pod = match serde_json::from_slice(&value) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+            if pod.node_name.is_some() {
+                scheduled.push(pod);
+            } else if pod.status == PodStatus::Pending {
+                pending.insert(pod.id.clone(), (key, pod));
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let node_entries = self.store.list_prefix("/registry/nodes/").await?;
+        let nodes: Vec<Node> = node_entries
+            .into_iter()
+            .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
+            .collect();
+
+        // Pods created before their claims were bound are pinned now.
+        let mut stuck = Vec::new();
+        for (pod_id, (_, pod)) in pending.iter_mut() {
+            if let Err(message) = crate::admission::pin_to_volumes(&self.store, pod).await? {
+                debug!(
+                    "Pod {}/{} remains pending: {}",
+                    pod.namespace, pod.name, message
+                );
+                stuck.push(pod_id.clone());
+            }
+        }
+        for pod_id in stuck {
+            pending.remove(&pod_id);
+        }
+
+        let queue: Vec<Pod> = pending.values().map(|(_, p)| p.clone()).collect();
+        for (pod_id, node_name) in self.scheduler.schedule_batch(&queue, &nodes, &scheduled) {
+            let Some((key, mut pod)) = pending.remove(&pod_id) else {
+                continue;
+            };
+            let Some(node_name) = node_name else {
+                debug!(
+                    "Pod {}/{} (priority={}) remains pending",
+                    pod.namespace, pod.name, pod.spec.priority
+                );
+                continue;
+            };
+            info!(
+                "Bound pending pod {}/{} (priority={}) → node {}",
+                pod.namespace, pod.name, pod.spec.priority, node_name
+            );
+            pod.node_name = Some(node_name);
+            pod.status = PodStatus::Scheduled;
+            let data = serde_json::to_vec(&pod)?;
+            // A pod deleted or changed since it was listed is left for the next pass.
+            if let PutOutcome::Conflict(_) = self
+                .store
+                .put_if_version(&key, &data, pod.resource_version)
+                .await?
+            {
+                debug!(
+                    "Pod {}/{} changed while being scheduled; retrying next pass",
+                    pod.namespace, pod.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,454 @@
+use chrono::{DateTime, Utc};
+use pkg_scheduler::tolerates;
+use pkg_state::client::StateStore;
+use pkg_types::event::Event;
+use pkg_types::node::{Node, NodeStatus, Taint};
+use pkg_types::pod::{Pod, PodStatus, TaintEffect};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// A pod that tolerates a `NoExecute` taint for a limited time.
+struct PendingEviction {
+    /// `namespace/name`, for logging.
+    pod: String,
+    due: DateTime<Utc>,
+}
+
+/// Controller that evicts pods from nodes with `NoExecute` taints.
+///
+/// A pod that does not tolerate one of its node's `NoExecute` taints is
+/// evicted on the next pass. One whose toleration sets `toleration_seconds`
+/// is evicted once that long has passed since the controller first saw the
+/// taint on the pod's node; the timer is dropped when the taint goes away, so
+/// a taint that is removed and added again starts a new one. Evicted pods are
+/// marked `Terminating` for their agent to stop, as a delete does, and their
+/// owners replace them elsewhere.
+pub struct TaintEvictionController {
+    store: StateStore,
+    check_interval: Duration,
+    /// Running timers, by pod ID and the taint they count down for.
+    pending: HashMap<(String, Taint), PendingEviction>,
+}
+
+impl TaintEvictionController {
+    pub fn new(store: StateStore) -> Self {
+        Self {
+            store,
+            check_interval: Duration::from_secs(
+                pkg_constants::timings::TAINT_EVICTION_CHECK_INTERVAL_SECS,
+            ),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Start the controller loop as a background task. Node writes trigger a
+    /// pass right away; between them it wakes when the next timer runs out,
+    /// or after the check interval.
+    pub fn start(mut self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(
+                "TaintEvictionController started (interval={}s)",
+                self.check_interval.as_secs()
+            );
+            let mut event_rx = self.store.event_log.subscribe();
+            let mut next_pass = tokio::time::Instant::now();
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = tokio::time::sleep_until(next_pass) => {}
+                    result = event_rx.recv() => match result {
+                        Ok(ref event) if event.key.starts_with("/registry/nodes/") => {
+                            while event_rx.try_recv().is_ok() {}
+                        }
+                        Ok(_) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+                let now = Utc::now();
+                let mut wait = self.check_interval;
+                match self.reconcile(now).await {
+                    Ok(Some(due)) => {
+                        wait = wait.min((due - now).to_std().unwrap_or_default());
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("TaintEvictionController reconcile error: {}", e),
+                }
+                next_pass = tokio::time::Instant::now() + wait;
+            }
+        })
+    }
+
+    /// One pass at time `now`: evict the pods that do not tolerate their
+    /// node's `NoExecute` taints or whose toleration has run out, and start
+    /// or cancel timers. Returns when the earliest remaining timer runs out.
+    async fn reconcile(&mut self, now: DateTime<Utc>) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let mut no_execute: HashMap<String, Vec<Taint>> = HashMap::new();
+        for (_, value) in self.store.list_prefix("/registry/nodes/").await? {
+            let Ok(node) = serde_json::from_slice::<Node>(&value) else {
+                continue;
+            };
+            // A lost node's agent cannot stop pods; the EvictionController
+            // moves them off it instead.
+            if node.status == NodeStatus::Unknown {
+                continue;
+            }
+            let taints: Vec<Taint> = node
+                .taints
+                .into_iter()
+                .filter(|t| t.effect == TaintEffect::NoExecute)
+                .collect();
+            if !taints.is_empty() {
+                no_execute.insert(node.name, taints);
+            }
+        }
+
+        let mut live: HashSet<(String, Taint)> = HashSet::new();
+        let mut next_due: Option<DateTime<Utc>> = None;
+        for (key, value) in self.store.list_prefix("/registry/pods/").await? {
+            let Ok(pod) = serde_json::from_slice::<Pod>(&value) else {
+                continue;
+            };
+            let Some(taints) = pod.node_name.as_deref().and_then(|n| no_execute.get(n)) else {
+                continue;
+            };
+            // A static pod only runs where its manifest is.
+            if pod.mirror
+                || !matches!(
+                    pod.status,
+                    PodStatus::Scheduled | PodStatus::ContainerCreating | PodStatus::Running
+                )
+            {
+                continue;
+            }
+            let node_name = pod.node_name.clone().unwrap_or_default();
+
+            if let Some(taint) = taints.iter().find(|t| !tolerates(&pod, t)) {
+                let message = format!(
+                    "Node {} has taint {} that the pod does not tolerate",
+                    node_name, taint
+                );
+                self.evict(&key, pod, message).await?;
+                continue;
+            }
+
+            let mut timers = Vec::new();
+            let mut expired = None;
+            for taint in taints {
+                let Some(seconds) = toleration_seconds(&pod, taint) else {
+                    continue;
+                };
+                let Some(due) = self.timer(now, &pod, taint, seconds) else {
+                    continue;
+                };
+                timers.push((pod.id.clone(), taint.clone()));
+                if due <= now {
+                    expired.get_or_insert((taint, seconds));
+                } else {
+                    next_due = Some(next_due.map_or(due, |next| next.min(due)));
+                }
+            }
+            match expired {
+                Some((taint, seconds)) => {
+                    let message = format!(
+                        "Node {} has taint {}; the pod tolerated it for {}s",
+                        node_name, taint, seconds
+                    );
+                    let id = pod.id.clone();
+                    self.evict(&key, pod, message).await?;
+                    self.pending.retain(|(pod_id, _), _| *pod_id != id);
+                }
+                None => live.extend(timers),
+            }
+        }
+
+        // Timers whose taint is gone, or whose pod left the node or stopped.
+        self.pending.retain(|key, pending| {
+            let keep = live.contains(key);
+            if !keep {
+                info!(
+                    "Cancelled eviction of pod {} for taint {}",
+                    pending.pod, key.1
+                );
+            }
+            keep
+        });
+        Ok(next_due)
+    }
+
+    /// When the pod's toleration of `taint` runs out, starting its timer at
+    /// `now` if it has none. `None` if that is too far away to represent.
+    fn timer(
+        &mut self,
+        now: DateTime<Utc>,
+        pod: &Pod,
+        taint: &Taint,
+        seconds: u64,
+    ) -> Option<DateTime<Utc>> {
+        let key = (pod.id.clone(), taint.clone());
+        if let Some(pending) = self.pending.get(&key) {
+            return Some(pending.due);
+        }
+        let due = i64::try_from(seconds)
+            .ok()
+            .and_then(chrono::TimeDelta::try_seconds)
+            .and_then(|d| now.checked_add_signed(d))?;
+        info!(
+            "Pod {}/{} will be evicted in {}s unless taint {} is removed",
+            pod.namespace, pod.name, seconds, taint
+        );
+        self.pending.insert(
+            key,
+            PendingEviction {
+                pod: format!("{}/{}", pod.namespace, pod.name),
+                due,
+            },
+        );
+        Some(due)
+    }
+
+    /// Mark the pod `Terminating`; its agent stops it and removes the record.
+    /// A pod deleted, rescheduled or stopped since it was listed is left alone.
+    async fn evict(&self, key: &str, pod: Pod, message: String) -> anyhow::Result<()> {
+        let mut evicted = false;
+        self.store
+            .update(key, |stored: &mut Pod| {
+                evicted = stored.id == pod.id
+                    && stored.node_name == pod.node_name
+                    && matches!(
+                        stored.status,
+                        PodStatus::Scheduled | PodStatus::ContainerCreating | PodStatus::Running
+                    );
+                if evicted {
+                    stored.status = PodStatus::Terminating;
+                }
+                evicted
+            })
+            .await?;
+        if !evicted {
+            return Ok(());
+        }
+        info!("Evicting pod {}/{}: {}", pod.namespace, pod.name, message);
+        crate::events::record(
+            &self.store,
+            Event::warning("pod", &pod.namespace, &pod.name, "TaintEviction", message),
+        )
+        .await;
+        Ok(())
+    }
+}
+
+/// How long the pod tolerates `taint`: the shortest `toleration_seconds`
+/// among its tolerations that match it, or `None` when none of them sets
+/// one and the taint is tolerated for good.
+fn toleration_seconds(pod: &Pod, taint: &Taint) -> Option<u64> {
+    pod.spec
+        .tolerations
+        .iter()
+        .filter(|t| t.matches(taint))
+        .filter_map(|t| t.toleration_seconds)
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_node, seed_pod, stored_node, stored_pod, test_store};
+
+    async fn set_taints(store: &StateStore, node: &str, taints: &[&str]) {
+        let mut stored = stored_node(store, node).await;
+        stored.taints = taints
+            .iter()
+            .map(|t| {
+                let (key, effect) = t.split_once(':').unwrap();
+                Taint {
+                    key: key.to_string(),
+                    value: String::new(),
+                    effect: effect.parse().unwrap(),
+                }
+            })
+            .collect();
+        store
+            .put(
+                &format!("/registry/nodes/{}", node),
+                &serde_json::to_vec(&stored).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn set_tolerations(store: &StateStore, pod: &str, tolerations: serde_json::Value) {
+        let mut stored = stored_pod(store, pod).await;
+        stored.spec.tolerations = serde_json::from_value(tolerations).unwrap();
+        store
+            .put(
+                &format!("/registry/pods/default/{}", pod),
+                &serde_json::to_vec(&stored).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_untolerated_no_execute_evicts_immediately() {
+        let store = test_store("taint-evict-now").await;
+        let now = seed_node(&store, "worker-1").await;
+        seed_node(&store, "worker-2").await;
+        seed_pod(&store, "web", "worker-1", Some("rs-1")).await;
+        seed_pod(&store, "agent", "worker-1", Some("ds-1")).await;
+        set_tolerations(
+            &store,
+            "agent",
+            serde_json::json!([{ "key": "maintenance", "operator": "Exists" }]),
+        )
+        .await;
+        seed_pod(&store, "other", "worker-2", Some("rs-1")).await;
+        set_taints(&store, "worker-1", &["maintenance:NoExecute"]).await;
+        set_taints(&store, "worker-2", &["maintenance:NoSchedule"]).await;
+        let mut ctrl = TaintEvictionController::new(store.clone());
+
+        assert_eq!(ctrl.reconcile(now).await.unwrap(), None);
+        assert_eq!(
+            stored_pod(&store, "web").await.status,
+            PodStatus::Terminating
+        );
+        // Tolerated without a time limit, or only kept from being scheduled.
+        assert_eq!(stored_pod(&store, "agent").await.status, PodStatus::Running);
+        assert_eq!(stored_pod(&store, "other").await.status, PodStatus::Running);
+
+        let evictions: Vec<Event> = store
+            .event_log
+            .events_since(0)
+            .await
+            .into_iter()
+            .filter(|e| e.key.starts_with("/events/"))
+            .filter_map(|e| serde_json::from_slice::<Event>(e.value.as_deref()?).ok())
+            .filter(|e| e.reason == "TaintEviction")
+            .collect();
+        assert_eq!(evictions.len(), 1);
+        assert_eq!(evictions[0].name, "web");
+        assert!(evictions[0].message.contains("maintenance:NoExecute"));
+    }
+
+    #[tokio::test]
+    async fn test_eviction_keeps_concurrent_changes() {
+        let store = test_store("taint-evict-race").await;
+        seed_node(&store, "worker-1").await;
+        seed_pod(&store, "web", "worker-1", Some("rs-1")).await;
+        seed_pod(&store, "api", "worker-1", Some("rs-1")).await;
+        let ctrl = TaintEvictionController::new(store.clone());
+
+        // Deleted after the pass listed it: not brought back.
+        let web = stored_pod(&store, "web").await;
+        store.delete("/registry/pods/default/web").await.unwrap();
+        ctrl.evict("/registry/pods/default/web", web, "gone".to_string())
+            .await
+            .unwrap();
+        assert!(
+            store
+                .get("/registry/pods/default/web")
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Finished in between: its status is kept.
+        let api = stored_pod(&store, "api").await;
+        let mut finished = api.clone();
+        finished.status = PodStatus::Succeeded;
+        store
+            .put(
+                "/registry/pods/default/api",
+                &serde_json::to_vec(&finished).unwrap(),
+            )
+            .await
+            .unwrap();
+        ctrl.evict("/registry/pods/default/api", api, "done".to_string())
+            .await
+            .unwrap();
+        assert_eq!(stored_pod(&store, "api").await.status, PodStatus::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_toleration_seconds_delays_eviction() {
+        let store = test_store("taint-evict-delay").await;
+        let now = seed_node(&store, "worker-1").await;
+        seed_pod(&store, "web", "worker-1", Some("rs-1")).await;
+        // The shortest matching toleration wins.
+        set_tolerations(
+            &store,
+            "web",
+            serde_json::json!([
+                { "key": "maintenance", "operator": "Exists", "toleration_seconds": 300 },
+                { "key": "maintenance", "operator": "Exists", "toleration_seconds": 60 },
+            ]),
+        )
+        .await;
+        set_taints(&store, "worker-1", &["maintenance:NoExecute"]).await;
+        let mut ctrl = TaintEvictionController::new(store.clone());
+
+        let due = now + chrono::Duration::seconds(60);
+        assert_eq!(ctrl.reconcile(now).await.unwrap(), Some(due));
+        // The timer runs from when the taint was first seen, not each pass.
+        assert_eq!(
+            ctrl.reconcile(now + chrono::Duration::seconds(59))
+                .await
+                .unwrap(),
+            Some(due)
+        );
+        assert_eq!(stored_pod(&store, "web").await.status, PodStatus::Running);
+
+        assert_eq!(ctrl.reconcile(due).await.unwrap(), None);
+        assert_eq!(
+            stored_pod(&store, "web").await.status,
+            PodStatus::Terminating
+        );
+        assert!(ctrl.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_removing_taint_cancels_eviction() {
+        let store = test_store("taint-evict-cancel").await;
+        let now = seed_node(&store, "worker-1").await;
+        seed_pod(&store, "web", "worker-1", Some("rs-1")).await;
+        set_tolerations(
+            &store,
+            "web",
+            serde_json::json!([
+                { "key": "maintenance", "operator": "Exists", "toleration_seconds": 60 },
+            ]),
+        )
+        .await;
+        set_taints(&store, "worker-1", &["maintenance:NoExecute"]).await;
+        let mut ctrl = TaintEvictionController::new(store.clone());
+        ctrl.reconcile(now).await.unwrap();
+
+        set_taints(&store, "worker-1", &[]).await;
+        assert_eq!(
+            ctrl.reconcile(now + chrono::Duration::seconds(30))
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(ctrl.pending.is_empty());
+
+        // Added back after the first deadline: a new timer starts.
+        set_taints(&store, "worker-1", &["maintenance:NoExecute"]).await;
+        let readded = now + chrono::Duration::seconds(90);
+        assert_eq!(
+            ctrl.reconcile(readded).await.unwrap(),
+            Some(readded + chrono::Duration::seconds(60))
+        );
+        assert_eq!(stored_pod(&store, "web").await.status, PodStatus::Running);
+
+        ctrl.reconcile(readded + chrono::Duration::seconds(60))
+            .await
+            .unwrap();
+        assert_eq!(
+            stored_pod(&store, "web").await.status,
+            PodStatus::Terminating
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Shared helpers for controller unit tests.
+
+use chrono::{DateTime, Utc};
+use pkg_state::client::StateStore;
+use pkg_types::node::Node;
+use pkg_types::pod::Pod;
+
+/// A fresh store in a per-test temp directory.
+pub(crate) async fn test_store(label: &str) -> StateStore {
+    let dir = std::env::temp_dir().join(format!(
+        "k3rs-controllers-test-{}-{}",
+        label,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    StateStore::new(&dir.to_string_lossy()).await.unwrap()
+}
+
+/// Store a Ready worker node and return its last heartbeat time.
+pub(crate) async fn seed_node(store: &StateStore, name: &str) -> DateTime<Utc> {
+    let now = Utc::now();
+    let node: Node = serde_json::from_value(serde_json::json!({
+        "id": format!("{}-id", name),
+        "name": name,
+        "address": "10.0.0.2",
+        "agent_api_port": 10250,
+        "status": "Ready",
+        "registered_at": now,
+        "last_heartbeat": now,
+        "labels": {},
+    }))
+    .unwrap();
+    store
+        .put(
+            &format!("/registry/nodes/{}", name),
+            &serde_json::to_vec(&node).unwrap(),
+        )
+        .await
+        .unwrap();
+    now
+}
+
+pub(crate) async fn stored_node(store: &StateStore, name: &str) -> Node {
+    let data = store
+        .get(&format!("/registry/nodes/{}", name))
+        .await
+        .unwrap()
+        .unwrap();
+    serde_json::from_slice(&data).unwrap()
+}
+
+/// Store a Running pod bound to `node`, optionally owned by `owner_ref`.
+pub(crate) async fn seed_pod(store: &StateStore, name: &str, node: &str, owner_ref: Option<&str>) {
+    let pod: Pod = serde_json::from_value(serde_json::json!({
+        "id": format!("{}-id", name),
+        "name": name,
+        "namespace": "default",
+        "spec": { "containers": [] },
+        "status": "Running",
+        "node_name": node,
+        "labels": {},
+        "owner_ref": owner_ref,
+        "created_at": Utc::now(),
+    }))
+    .unwrap();
+    store
+        .put(
+            &format!("/registry/pods/default/{}", name),
+            &serde_json::to_vec(&pod).unwrap(),
+        )
+        .await
+        .unwrap();
+}
+
+pub(crate) async fn stored_pod(store: &StateStore, name: &str) -> Pod {
+    let data = store
+        .get(&format!("/registry/pods/default/{}", name))
+        .await
+        .unwrap()
+        .unwrap();
+    serde_json::from_slice(&data).unwrap()
+}
@@ -0,0 +1,664 @@
+//! Dynamic provisioning for PersistentVolumeClaims of the `local-path`
+//! storage class (the default): a directory on one node per claim.
+//!
+//! A `Pending` claim gets its volume on the node running the first pod that
+//! uses it, or on the next ready node in turn when no pod has been placed
+//! yet. The node's agent creates the directory; the controller then records
+//! a PersistentVolume bound to the claim and marks the claim `Bound`. From
+//! then on, pods using the claim are pinned to that node (see
+//! [`claim_nodes`]). When the claim is deleted, its reclaim policy decides
+//! the volume's fate: `Delete` has the agent remove the directory and drops
+//! the volume, `Retain` keeps both and leaves the volume `Released`.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use pkg_state::client::{PutOutcome, StateStore};
+use pkg_types::event::{CLUSTER_EVENT_NAMESPACE, Event};
+use pkg_types::node::{Node, NodeStatus};
+use pkg_types::pod::{Pod, TaintEffect};
+use pkg_types::volume::{
+    ClaimRef, LOCAL_PATH_STORAGE_CLASS, LocalVolumeRequest, PVCPhase, PVPhase, PersistentVolume,
+    PersistentVolumeClaim, ReclaimPolicy,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Creates and removes the directories of local-path volumes on nodes.
+#[async_trait]
+pub trait VolumeProvisioner: Send + Sync {
+    /// Create the directory of volume `name` on `node`. Returns its path
+    /// there.
+    async fn create(
+        &self,
+        node: &str,
+        name: &str,
+        request: &LocalVolumeRequest,
+    ) -> anyhow::Result<String>;
+
+    /// Remove the directory of volume `name` from `node`, data included.
+    async fn delete(&self, node: &str, name: &str) -> anyhow::Result<()>;
+}
+
+/// Binds `local-path` claims to volumes it provisions, and reclaims the
+/// volumes of deleted claims.
+pub struct VolumeController {
+    store: StateStore,
+    provisioner: Arc<dyn VolumeProvisioner>,
+    check_interval: Duration,
+    /// Rotation over the ready nodes, for claims no pod uses yet.
+    next_node: usize,
+}
+
+impl VolumeController {
+    pub fn new(store: StateStore, provisioner: Arc<dyn VolumeProvisioner>) -> Self {
+        Self {
+            store,
+            provisioner,
+            check_interval: Duration::from_secs(pkg_constants::timings::VOLUME_CHECK_INTERVAL_SECS),
+            next_node: 0,
+        }
+    }
+
+    /// Start the controller loop as a background task. Claim and pod
+    /// writes trigger a pass, so a claim binds as soon as its first pod is
+    /// placed.
+    pub fn start(mut self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            info!(
+                "VolumeController started (interval={}s)",
+                self.check_interval.as_secs()
+            );
+            let mut event_rx = self.store.event_log.subscribe();
+            let mut interval = tokio::time::interval(self.check_interval);
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = interval.tick() => {}
+                    result = event_rx.recv() => match result {
+                        Ok(ref event)
+                            if event.key.starts_with("/registry/pvcs/")
+                                || event.key.starts_with("/registry/pods/") =>
+                        {
+                            while event_rx.try_recv().is_ok() {}
+                            interval.reset();
+                        }
+                        Ok(_) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+                if let Err(e) = self.reconcile().await {
+                    warn!("VolumeController reconcile error: {}", e);
+                }
+            }
+        })
+    }
+
+    /// One pass: provision and bind every pending `local-path` claim that
+    /// can be placed, then reclaim the volumes whose claim is gone.
+    async fn reconcile(&mut self) -> anyhow::Result<()> {
+        let claims: Vec<(String, PersistentVolumeClaim)> = self
+            .store
+            .list_prefix("/registry/pvcs/")
+            .await?
+            .into_iter()
+            .filter_map(|(k, v)| Some((k, serde_json::from_slice(&v).ok()?)))
+            .collect();
+        let volumes: Vec<(String, PersistentVolume)> = self
+            .store
+            .list_prefix("/registry/persistentvolumes/")
+            .await?
+            .into_iter()
+            .filter_map(|(k, v)| Some((k, serde_json::from_slice(&v).ok()?)))
+            .collect();
+
+        let pending: Vec<&(String, PersistentVolumeClaim)> = claims
+            .iter()
+            .filter(|(_, c)| c.phase == PVCPhase::Pending && c.is_local_path())
+            .collect();
+        if !pending.is_empty() {
+            let pods: Vec<Pod> = self
+                .store
+                .list_prefix("/registry/pods/")
+                .await?
+                .into_iter()
+                .filter_map(|(_, v)| serde_json::from_slice(&v).ok())
+                .collect();
+            let mut nodes: Vec<Node> = self
+                .store
+                .list_prefix("/registry/nodes/")
+                .await?
+                .into_iter()
+                .filter_map(|(_, v)| serde_json::from_slice::<Node>(&v).ok())
+                .filter(accepts_volumes)
+                .collect();
+            nodes.sort_by(|a, b| a.name.cmp(&b.name));
+
+            for (key, claim) in pending {
+                // A volume left over from a pass that stopped before binding.
+                let volume = match volumes.iter().find(|(_, v)| v.claim_ref.id == claim.id) {
+                    Some((_, volume)) => volume.clone(),
+                    None => {
+                        let Some(node) = self.pick_node(claim, &pods, &nodes) else {
+                            continue;
+                        };
+                        match self.provision(claim, &node).await {
+                            Ok(volume) => volume,
+                            Err(e) => {
+                                warn!(
+                                    "Failed to provision a volume for claim {}/{} on node {}: {:#}",
+                                    claim.namespace, claim.name, node, e
+                                );
+                                crate::events::record(
+                                    &self.store,
+                                    Event::warning(
+                                        "pvc",
+                                        &claim.namespace,
+                                        &claim.name,
+                                        "ProvisioningFailed",
+                                        format!(
+                                            "Failed to provision a volume on node {}: {:#}",
+                                            node, e
+                                        ),
+                                    ),
+                                )
+                                .await;
+                                continue;
+                            }
+                        }
+                    }
+                };
+                self.bind(key, claim, &volume).await?;
+            }
+        }
+
+        for (key, volume) in &volumes {
+            if volume.phase != PVPhase::Bound
+                || claims.iter().any(|(_, c)| c.id == volume.claim_ref.id)
+            {
+                continue;
+            }
+            self.reclaim(key, volume).await?;
+        }
+        Ok(())
+    }
+
+    /// The node for a claim's volume: that of the oldest placed pod using
+    /// the claim, else the next ready node in turn. `None` with no node to
+    /// use; the claim then stays `Pending`.
+    fn pick_node(
+        &mut self,
+        claim: &PersistentVolumeClaim,
+        pods: &[Pod],
+        nodes: &[Node],
+    ) -> Option<String> {
+        let consumer = pods
+            .iter()
+            .filter(|p| p.namespace == claim.namespace && p.node_name.is_some())
+            .filter(|p| p.spec.claim_names().any(|c| c == claim.name))
+            .min_by_key(|p| p.created_at);
+        if let Some(pod) = consumer {
+            return pod.node_name.clone();
+        }
+        if nodes.is_empty() {
+            return None;
+        }
+        let node = nodes[self.next_node % nodes.len()].name.clone();
+        self.next_node = self.next_node.wrapping_add(1);
+        Some(node)
+    }
+
+    /// Have `node`'s agent create the claim's directory and record the
+    /// volume.
+    async fn provision(
+        &self,
+        claim: &PersistentVolumeClaim,
+        node: &str,
+    ) -> anyhow::Result<PersistentVolume> {
+        let name = volume_name(claim);
+        let request = LocalVolumeRequest {
+            claim: format!("{}/{}", claim.namespace, claim.name),
+            capacity_bytes: claim.requested_bytes,
+        };
+        let path = self.provisioner.create(node, &name, &request).await?;
+        let volume = PersistentVolume {
+            name: name.clone(),
+            storage_class: LOCAL_PATH_STORAGE_CLASS.to_string(),
+            capacity_bytes: claim.requested_bytes,
+            access_modes: claim.access_modes.clone(),
+            reclaim_policy: claim.reclaim_policy,
+            node_name: node.to_string(),
+            path,
+            claim_ref: ClaimRef {
+                namespace: claim.namespace.clone(),
+                name: claim.name.clone(),
+                id: claim.id.clone(),
+            },
+            phase: PVPhase::Bound,
+            created_at: Utc::now(),
+        };
+        let key = format!("/registry/persistentvolumes/{}", name);
+        if let PutOutcome::Conflict(existing) = self
+            .store
+            .create(&key, &serde_json::to_vec(&volume)?)
+            .await?
+        {
+            // Another pass recorded the claim's volume first: use that one.
+            let existing: PersistentVolume = match existing {
+                Some(data) => serde_json::from_slice(&data)?,
+                None => anyhow::bail!("volume {} changed while being recorded", name),
+            };
+            if existing.claim_ref.id != claim.id {
+                anyhow::bail!("volume {} belongs to another claim", name);
+            }
+            return Ok(existing);
+        }
+        info!(
+            "Provisioned volume {} for claim {}/{} at {}:{}",
+            name, claim.namespace, claim.name, node, volume.path
+        );
+        Ok(volume)
+    }
+
+    /// Mark the claim `Bound` to `volume`, unless it changed in the meantime.
+    async fn bind(
+        &self,
+        key: &str,
+        claim: &PersistentVolumeClaim,
+        volume: &PersistentVolume,
+    ) -> anyhow::Result<()> {
+        let bound = self
+            .store
+            .update::<PersistentVolumeClaim, _>(key, |c| {
+                if c.id != claim.id || c.phase != PVCPhase::Pending {
+                    return false;
+                }
+                c.phase = PVCPhase::Bound;
+                c.volume_name = Some(volume.name.clone());
+                true
+            })
+            .await?;
+        if bound.is_some_and(|c| c.volume_name.as_deref() == Some(volume.name.as_str())) {
+            info!(
+                "Bound claim {}/{} to volume {}",
+                claim.namespace, claim.name, volume.name
+            );
+            crate::events::record(
+                &self.store,
+                Event::normal(
+                    "pvc",
+                    &claim.namespace,
+                    &claim.name,
+                    "ProvisioningSucceeded",
+                    format!(
+                        "Successfully provisioned volume {} on node {}",
+                        volume.name, volume.node_name
+                    ),
+                ),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Apply the reclaim policy of a volume whose claim was deleted. A
+    /// directory that cannot be removed yet is retried on the next pass.
+    async fn reclaim(&self, key: &str, volume: &PersistentVolume) -> anyhow::Result<()> {
+        match volume.reclaim_policy {
+            ReclaimPolicy::Delete => {
+                if let Err(e) = self
+                    .provisioner
+                    .delete(&volume.node_name, &volume.name)
+                    .await
+                {
+                    warn!(
+                        "Failed to delete volume {} on node {}: {:#}",
+                        volume.name, volume.node_name, e
+                    );
+                    crate::events::record(
+                        &self.store,
+                        Event::warning(
+                            "persistentvolume",
+                            CLUSTER_EVENT_NAMESPACE,
+                            &volume.name,
+                            "VolumeFailedDelete",
+                            format!("Failed to delete the volume's data: {:#}", e),
+                        ),
+                    )
+                    .await;
+                    return Ok(());
+                }
+                self.store.delete(key).await?;
+                info!(
+                    "Deleted volume {} of claim {}/{}",
+                    volume.name, volume.claim_ref.namespace, volume.claim_ref.name
+                );
+            }
+            ReclaimPolicy::Retain => {
+                // Left alone if another pass released it or it was rebound.
+                let mut released = false;
+                self.store
+                    .update::<PersistentVolume, _>(key, |v| {
+                        released =
+                            v.claim_ref.id == volume.claim_ref.id && v.phase == PVPhase::Bound;
+                        if released {
+                            v.phase = PVPhase::Released;
+                        }
+                        released
+                    })
+                    .await?;
+                if !released {
+                    return Ok(());
+                }
+                info!(
+                    "Released volume {} of claim {}/{}; its data on node {} is retained",
+                    volume.name,
+                    volume.claim_ref.namespace,
+                    volume.claim_ref.name,
+                    volume.node_name
+                );
+                crate::events::record(
+                    &self.store,
+                    Event::normal(
+                        "persistentvolume",
+                        CLUSTER_EVENT_NAMESPACE,
+                        &volume.name,
+                        "VolumeReleased",
+                        format!(
+                            "Claim {}/{} was deleted; the data at {}:{} is retained",
+                            volume.claim_ref.namespace,
+                            volume.claim_ref.name,
+                            volume.node_name,
+                            volume.path
+                        ),
+                    ),
+                )
+                .await;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Node of the volume bound to each claim of `namespace`, by claim name:
+/// what [`pkg_scheduler::pin_to_volume_nodes`] pins pods to.
+pub async fn claim_nodes(
+    store: &StateStore,
+    namespace: &str,
+) -> anyhow::Result<HashMap<String, String>> {
+    let claims: HashMap<String, String> = store
+        .list_prefix(&format!("/registry/pvcs/{}/", namespace))
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice::<PersistentVolumeClaim>(&v).ok())
+        .filter_map(|c| Some((c.volume_name?, c.name)))
+        .collect();
+    if claims.is_empty() {
+        return Ok(HashMap::new());
+    }
+    Ok(store
+        .list_prefix("/registry/persistentvolumes/")
+        .await?
+        .into_iter()
+        .filter_map(|(_, v)| serde_json::from_slice::<PersistentVolume>(&v).ok())
+        .filter(|v| v.phase == PVPhase::Bound && v.claim_ref.namespace == namespace)
+        .filter_map(|v| Some((claims.get(&v.name)?.clone(), v.node_name)))
+        .collect())
+}
+
+/// Name of the volume provisioned for a claim.
+fn volume_name(claim: &PersistentVolumeClaim) -> String {
+    format!("pvc-{}", claim.id)
+}
+
+/// Whether a node can be given volumes no pod asked for yet: Ready,
+/// schedulable and without taints that keep pods off it.
+fn accepts_volumes(node: &Node) -> bool {
+    node.status == NodeStatus::Ready
+        && !node.unschedulable
+        && node
+            .taints
+            .iter()
+            .all(|t| t.effect == TaintEffect::PreferNoSchedule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{seed_node, seed_pod, stored_node, stored_pod, test_store};
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Records what it was asked to do; fails while `fail` is set.
+    #[derive(Default)]
+    struct FakeProvisioner {
+        fail: AtomicBool,
+        created: Mutex<Vec<(String, String)>>,
+        deleted: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait]
+    impl VolumeProvisioner for FakeProvisioner {
+        async fn create(
+            &self,
+            node: &str,
+            name: &str,
+            _request: &LocalVolumeRequest,
+        ) -> anyhow::Result<String> {
+            if self.fail.load(Ordering::SeqCst) {
+                anyhow::bail!("agent unreachable");
+            }
+            self.created
+                .lock()
+                .unwrap()
+                .push((node.to_string(), name.to_string()));
+            Ok(format!("/data/local-path/{}", name))
+        }
+
+        async fn delete(&self, node: &str, name: &str) -> anyhow::Result<()> {
+            if self.fail.load(Ordering::SeqCst) {
+                anyhow::bail!("agent unreachable");
+            }
+            self.deleted
+                .lock()
+                .unwrap()
+                .push((node.to_string(), name.to_string()));
+            Ok(())
+        }
+    }
+
+    async fn seed_claim(
+        store: &StateStore,
+        name: &str,
+        storage_class: Option<&str>,
+        reclaim_policy: ReclaimPolicy,
+    ) {
+        let claim: PersistentVolumeClaim = serde_json::from_value(serde_json::json!({
+            "id": format!("{}-id", name),
+            "name": name,
+            "namespace": "default",
+            "storage_class": storage_class,
+            "requested_bytes": 1_000_000_000u64,
+            "reclaim_policy": reclaim_policy,
+            "created_at": Utc::now(),
+        }))
+        .unwrap();
+        store
+            .put(
+                &format!("/registry/pvcs/default/{}", name),
+                &serde_json::to_vec(&claim).unwrap(),
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn stored_claim(store: &StateStore, name: &str) -> PersistentVolumeClaim {
+        let data = store
+            .get(&format!("/registry/pvcs/default/{}", name))
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::from_slice(&data).unwrap()
+    }
+
+    async fn stored_volume(store: &StateStore, name: &str) -> Option<PersistentVolume> {
+        let data = store
+            .get(&format!("/registry/persistentvolumes/{}", name))
+            .await
+            .unwrap()?;
+        Some(serde_json::from_slice(&data).unwrap())
+    }
+
+    fn controller(store: &StateStore) -> (VolumeController, Arc<FakeProvisioner>) {
+        let provisioner = Arc::new(FakeProvisioner::default());
+        (
+            VolumeController::new(store.clone(), provisioner.clone()),
+            provisioner,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_claim_binds_on_first_consumers_node() {
+        let store = test_store("volume-bind").await;
+        seed_node(&store, "worker-1").await;
+        seed_node(&store, "worker-2").await;
+        seed_claim(&store, "data", None, ReclaimPolicy::Delete).await;
+        seed_pod(&store, "db", "worker-2", None).await;
+        let mut db = stored_pod(&store, "db").await;
+        db.spec.volumes = serde_json::from_value(serde_json::json!([
+            { "name": "data", "source": { "type": "persistentVolumeClaim", "claim_name": "data" } },
+        ]))
+        .unwrap();
+        store
+            .put(
+                "/registry/pods/default/db",
+                &serde_json::to_vec(&db).unwrap(),
+            )
+            .await
+            .unwrap();
+        let (mut ctrl, provisioner) = controller(&store);
+
+        // The agent cannot be reached: the claim waits for the next pass.
+        provisioner.fail.store(true, Ordering::SeqCst);
+        ctrl.reconcile().await.unwrap();
+        assert_eq!(stored_claim(&store, "data").await.phase, PVCPhase::Pending);
+        assert!(stored_volume(&store, "pvc-data-id").await.is_none());
+
+        provisioner.fail.store(false, Ordering::SeqCst);
+        ctrl.reconcile().await.unwrap();
+        let claim = stored_claim(&store, "data").await;
+        assert_eq!(claim.phase, PVCPhase::Bound);
+        assert_eq!(claim.volume_name.as_deref(), Some("pvc-data-id"));
+        let volume = stored_volume(&store, "pvc-data-id").await.unwrap();
+        assert_eq!(volume.node_name, "worker-2");
+        assert_eq!(volume.path, "/data/local-path/pvc-data-id");
+        assert_eq!(volume.phase, PVPhase::Bound);
+        assert_eq!(volume.claim_ref.id, "data-id");
+
+        // Bound claims are left alone, and pin their pods.
+        ctrl.reconcile().await.unwrap();
+        assert_eq!(provisioner.created.lock().unwrap().len(), 1);
+        assert_eq!(
+            claim_nodes(&store, "default").await.unwrap(),
+            HashMap::from([("data".to_string(), "worker-2".to_string())])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unused_claims_rotate_over_ready_nodes() {
+        let store = test_store("volume-rotate").await;
+        seed_node(&store, "worker-1").await;
+        seed_node(&store, "worker-2").await;
+        seed_node(&store, "worker-3").await;
+        let mut cordoned = stored_node(&store, "worker-3").await;
+        cordoned.unschedulable = true;
+        store
+            .put(
+                "/registry/nodes/worker-3",
+                &serde_json::to_vec(&cordoned).unwrap(),
+            )
+            .await
+            .unwrap();
+        seed_claim(&store, "a", Some("local-path"), ReclaimPolicy::Delete).await;
+        seed_claim(&store, "b", Some(""), ReclaimPolicy::Delete).await;
+        seed_claim(&store, "c", None, ReclaimPolicy::Delete).await;
+        seed_claim(&store, "fast", Some("fast-ssd"), ReclaimPolicy::Delete).await;
+        let (mut ctrl, provisioner) = controller(&store);
+
+        ctrl.reconcile().await.unwrap();
+        let nodes: Vec<String> = provisioner
+            .created
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(node, _)| node.clone())
+            .collect();
+        assert_eq!(nodes, ["worker-1", "worker-2", "worker-1"]);
+        // Another storage class is someone else's to provision.
+        assert_eq!(stored_claim(&store, "fast").await.phase, PVCPhase::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_volume_recorded_first_is_kept() {
+        let store = test_store("volume-race").await;
+        seed_claim(&store, "data", None, ReclaimPolicy::Delete).await;
+        let claim = stored_claim(&store, "data").await;
+        let (ctrl, _) = controller(&store);
+
+        let first = ctrl.provision(&claim, "worker-1").await.unwrap();
+        // A second pass racing the first ends up with the recorded volume.
+        let second = ctrl.provision(&claim, "worker-2").await.unwrap();
+        assert_eq!(second.node_name, "worker-1");
+        assert_eq!(
+            stored_volume(&store, "pvc-data-id")
+                .await
+                .unwrap()
+                .node_name,
+            first.node_name
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleted_claims_follow_reclaim_policy() {
+        let store = test_store("volume-reclaim").await;
+        seed_node(&store, "worker-1").await;
+        seed_claim(&store, "scratch", None, ReclaimPolicy::Delete).await;
+        seed_claim(&store, "keep", None, ReclaimPolicy::Retain).await;
+        let (mut ctrl, provisioner) = controller(&store);
+        ctrl.reconcile().await.unwrap();
+        store
+            .delete("/registry/pvcs/default/scratch")
+            .await
+            .unwrap();
+        store.delete("/registry/pvcs/default/keep").await.unwrap();
+
+        // Removing the data fails: the volume stays until it succeeds.
+        provisioner.fail.store(true, Ordering::SeqCst);
+        ctrl.reconcile().await.unwrap();
+        assert_eq!(
+            stored_volume(&store, "pvc-scratch-id").await.unwrap().phase,
+            PVPhase::Bound
+        );
+        assert_eq!(
+            stored_volume(&store, "pvc-keep-id").await.unwrap().phase,
+            PVPhase::Released
+        );
+
+        provisioner.fail.store(false, Ordering::SeqCst);
+        ctrl.reconcile().await.unwrap();
+        assert!(stored_volume(&store, "pvc-scratch-id").await.is_none());
+        assert_eq!(
+            *provisioner.deleted.lock().unwrap(),
+            [("worker-1".to_string(), "pvc-scratch-id".to_string())]
+        );
+        // Retained data is never removed, and its volume no longer pins.
+        assert_eq!(
+            stored_volume(&store, "pvc-keep-id").await.unwrap().phase,
+            PVPhase::Released
+        );
+        assert!(claim_nodes(&store, "default").await.unwrap().is_empty());
+    }
+}
@@ -2,6 +2,7 @@ use chrono::Utc;
 use pkg_state::client::StateStore;
 use pkg_types::vpc::{Vpc, VpcStatus};
 use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
 /// Controller that ensures the default VPC exists and reconciles VPC state.
@@ -18,7 +19,7 @@ impl VpcController {
         }
     }
 
-    pub fn start(self) -> tokio::task::JoinHandle<()> {
+    pub fn start(self, stop: CancellationToken) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             info!(
                 "VpcController started (interval={}s)",
@@ -28,6 +29,7 @@ impl VpcController {
             let mut interval = tokio::time::interval(self.check_interval);
             loop {
                 tokio::select! {
+                    _ = stop.cancelled() => break,
                     _ = interval.tick() => {
                         if let Err(e) = self.reconcile().await {
                             warn!("VpcController reconcile error: {}", e);
@@ -1,6 +1,11 @@
+pub mod log_filter;
+
 use std::collections::BTreeMap;
 use std::sync::RwLock;
-use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+
+/// Label pairs identifying one series of a metric, sorted by label name.
+type LabelSet = Vec<(String, String)>;
 
 /// A lightweight, thread-safe metrics registry that renders in Prometheus text exposition format.
 pub struct MetricsRegistry {
@@ -11,12 +16,18 @@ pub struct MetricsRegistry {
 /// Monotonically increasing counter.
 pub struct Counter {
     value: AtomicU64,
+    /// Set once the unlabeled series has been written to.
+    unlabeled_used: AtomicBool,
+    series: RwLock<BTreeMap<LabelSet, AtomicU64>>,
     help: String,
 }
 
 /// Value that can go up or down.
 pub struct Gauge {
     value: AtomicI64,
+    /// Set once the unlabeled series has been written to.
+    unlabeled_used: AtomicBool,
+    series: RwLock<BTreeMap<LabelSet, AtomicI64>>,
     help: String,
 }
 
@@ -33,6 +44,8 @@ impl MetricsRegistry {
         let mut counters = self.counters.write().unwrap();
         counters.entry(name.to_string()).or_insert_with(|| Counter {
             value: AtomicU64::new(0),
+            unlabeled_used: AtomicBool::new(false),
+            series: RwLock::new(BTreeMap::new()),
             help: help.to_string(),
         });
     }
@@ -42,38 +55,95 @@ impl MetricsRegistry {
         let mut gauges = self.gauges.write().unwrap();
         gauges.entry(name.to_string()).or_insert_with(|| Gauge {
             value: AtomicI64::new(0),
+            unlabeled_used: AtomicBool::new(false),
+            series: RwLock::new(BTreeMap::new()),
             help: help.to_string(),
         });
     }
 
     /// Increment a counter by 1.
     pub fn counter_inc(&self, name: &str) {
-        let counters = self.counters.read().unwrap();
-        if let Some(c) = counters.get(name) {
-            c.value.fetch_add(1, Ordering::Relaxed);
-        }
+        self.counter_add(name, 1);
     }
 
     /// Increment a counter by a given amount.
     pub fn counter_add(&self, name: &str, val: u64) {
         let counters = self.counters.read().unwrap();
         if let Some(c) = counters.get(name) {
+            c.unlabeled_used.store(true, Ordering::Relaxed);
             c.value.fetch_add(val, Ordering::Relaxed);
         }
     }
 
+    /// Set a counter to a total kept elsewhere, e.g. by a component that
+    /// does not depend on this registry. The total must never go down.
+    pub fn counter_set(&self, name: &str, val: u64) {
+        let counters = self.counters.read().unwrap();
+        if let Some(c) = counters.get(name) {
+            c.unlabeled_used.store(true, Ordering::Relaxed);
+            c.value.store(val, Ordering::Relaxed);
+        }
+    }
+
+    /// Increment the series of a counter identified by `labels` by 1.
+    pub fn counter_inc_with(&self, name: &str, labels: &[(&str, &str)]) {
+        self.counter_add_with(name, labels, 1);
+    }
+
+    /// Increment the series of a counter identified by `labels` by a given amount.
+    pub fn counter_add_with(&self, name: &str, labels: &[(&str, &str)], val: u64) {
+        let counters = self.counters.read().unwrap();
+        if let Some(c) = counters.get(name) {
+            with_series(&c.series, labels, |v: &AtomicU64| {
+                v.fetch_add(val, Ordering::Relaxed);
+            });
+        }
+    }
+
+    /// Set the series of a counter identified by `labels` to a total kept
+    /// elsewhere. The total must never go down.
+    pub fn counter_set_with(&self, name: &str, labels: &[(&str, &str)], val: u64) {
+        let counters = self.counters.read().unwrap();
+        if let Some(c) = counters.get(name) {
+            with_series(&c.series, labels, |v: &AtomicU64| {
+                v.store(val, Ordering::Relaxed);
+            });
+        }
+    }
+
     /// Set a gauge to a specific value.
     pub fn gauge_set(&self, name: &str, val: i64) {
         let gauges = self.gauges.read().unwrap();
         if let Some(g) = gauges.get(name) {
+            g.unlabeled_used.store(true, Ordering::Relaxed);
             g.value.store(val, Ordering::Relaxed);
         }
     }
 
+    /// Set the series of a gauge identified by `labels` to a specific value.
+    pub fn gauge_set_with(&self, name: &str, labels: &[(&str, &str)], val: i64) {
+        let gauges = self.gauges.read().unwrap();
+        if let Some(g) = gauges.get(name) {
+            with_series(&g.series, labels, |v: &AtomicI64| {
+                v.store(val, Ordering::Relaxed);
+            });
+        }
+    }
+
+    /// Drop all labeled series of a gauge, e.g. before re-populating it from a
+    /// fresh snapshot so label sets that disappeared are not rendered stale.
+    pub fn gauge_clear_labeled(&self, name: &str) {
+        let gauges = self.gauges.read().unwrap();
+        if let Some(g) = gauges.get(name) {
+            g.series.write().unwrap().clear();
+        }
+    }
+
     /// Increment a gauge by 1.
     pub fn gauge_inc(&self, name: &str) {
         let gauges = self.gauges.read().unwrap();
         if let Some(g) = gauges.get(name) {
+            g.unlabeled_used.store(true, Ordering::Relaxed);
             g.value.fetch_add(1, Ordering::Relaxed);
         }
     }
@@ -82,11 +152,15 @@ impl MetricsRegistry {
     pub fn gauge_dec(&self, name: &str) {
         let gauges = self.gauges.read().unwrap();
         if let Some(g) = gauges.get(name) {
+            g.unlabeled_used.store(true, Ordering::Relaxed);
             g.value.fetch_sub(1, Ordering::Relaxed);
         }
     }
 
     /// Render all metrics in Prometheus text exposition format.
+    ///
+    /// The unlabeled series is rendered unless the metric has only ever been
+    /// written with labels; labeled series follow in label order.
     pub fn render(&self) -> String {
         let mut output = String::new();
 
@@ -95,11 +169,22 @@ impl MetricsRegistry {
         for (name, counter) in counters.iter() {
             output.push_str(&format!("# HELP {} {}\n", name, counter.help));
             output.push_str(&format!("# TYPE {} counter\n", name));
-            output.push_str(&format!(
-                "{} {}\n",
-                name,
-                counter.value.load(Ordering::Relaxed)
-            ));
+            let series = counter.series.read().unwrap();
+            if series.is_empty() || counter.unlabeled_used.load(Ordering::Relaxed) {
+                output.push_str(&format!(
+                    "{} {}\n",
+                    name,
+                    counter.value.load(Ordering::Relaxed)
+                ));
+            }
+            for (labels, value) in series.iter() {
+                output.push_str(&format!(
+                    "{}{} {}\n",
+                    name,
+                    format_labels(labels),
+                    value.load(Ordering::Relaxed)
+                ));
+            }
         }
 
         // Gauges
@@ -107,11 +192,22 @@ impl MetricsRegistry {
         for (name, gauge) in gauges.iter() {
             output.push_str(&format!("# HELP {} {}\n", name, gauge.help));
             output.push_str(&format!("# TYPE {} gauge\n", name));
-            output.push_str(&format!(
-                "{} {}\n",
-                name,
-                gauge.value.load(Ordering::Relaxed)
-            ));
+            let series = gauge.series.read().unwrap();
+            if series.is_empty() || gauge.unlabeled_used.load(Ordering::Relaxed) {
+                output.push_str(&format!(
+                    "{} {}\n",
+                    name,
+                    gauge.value.load(Ordering::Relaxed)
+                ));
+            }
+            for (labels, value) in series.iter() {
+                output.push_str(&format!(
+                    "{}{} {}\n",
+                    name,
+                    format_labels(labels),
+                    value.load(Ordering::Relaxed)
+                ));
+            }
         }
 
         output
@@ -123,3 +219,180 @@ impl Default for MetricsRegistry {
         Self::new()
     }
 }
+
+/// Run `f` on the series for `labels`, creating it (zeroed) on first use.
+fn with_series<V: Default>(
+    series: &RwLock<BTreeMap<LabelSet, V>>,
+    labels: &[(&str, &str)],
+    f: impl FnOnce(&V),
+) {
+    let key = label_set(labels);
+    {
+        let map = series.read().unwrap();
+        if let Some(v) = map.get(&key) {
+            f(v);
+            return;
+        }
+    }
+    let mut map = series.write().unwrap();
+    f(map.entry(key).or_default());
+}
+
+/// Normalize label pairs into a sorted, owned label set.
+fn label_set(labels: &[(&str, &str)]) -> LabelSet {
+    let mut set: LabelSet = labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    set.sort();
+    set
+}
+
+/// Render a label set as `{k1="v1",k2="v2"}`.
+fn format_labels(labels: &LabelSet) -> String {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// Escape a label value per the Prometheus text format (`\\`, `\"`, `\n`).
+fn escape_label_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlabeled_render_unchanged() {
+        let registry = MetricsRegistry::new();
+        registry.register_counter("k3rs_requests_total", "Total requests");
+        registry.register_gauge("k3rs_nodes_total", "Total nodes");
+        registry.counter_inc("k3rs_requests_total");
+        registry.gauge_set("k3rs_nodes_total", 3);
+
+        assert_eq!(
+            registry.render(),
+            "# HELP k3rs_requests_total Total requests\n\
+             # TYPE k3rs_requests_total counter\n\
+             k3rs_requests_total 1\n\
+             # HELP k3rs_nodes_total Total nodes\n\
+             # TYPE k3rs_nodes_total gauge\n\
+             k3rs_nodes_total 3\n"
+        );
+    }
+
+    #[test]
+    fn test_labeled_series_sorted() {
+        let registry = MetricsRegistry::new();
+        registry.register_gauge("k3rs_pods_total", "Pods by namespace and status");
+        registry.gauge_set_with(
+            "k3rs_pods_total",
+            &[("status", "Running"), ("namespace", "default")],
+            2,
+        );
+        registry.gauge_set_with(
+            "k3rs_pods_total",
+            &[("namespace", "default"), ("status", "Pending")],
+            1,
+        );
+
+        let out = registry.render();
+        assert_eq!(
+            out,
+            "# HELP k3rs_pods_total Pods by namespace and status\n\
+             # TYPE k3rs_pods_total gauge\n\
+             k3rs_pods_total{namespace=\"default\",status=\"Pending\"} 1\n\
+             k3rs_pods_total{namespace=\"default\",status=\"Running\"} 2\n"
+        );
+    }
+
+    #[test]
+    fn test_labeled_and_unlabeled_both_rendered() {
+        let registry = MetricsRegistry::new();
+        registry.register_counter("k3rs_reconcile_total", "Reconciles");
+        registry.counter_add("k3rs_reconcile_total", 5);
+        registry.counter_inc_with("k3rs_reconcile_total", &[("controller", "job")]);
+
+        let out = registry.render();
+        assert!(out.contains("k3rs_reconcile_total 5\n"));
+        assert!(out.contains("k3rs_reconcile_total{controller=\"job\"} 1\n"));
+    }
+
+    #[test]
+    fn test_label_value_escaping() {
+        let registry = MetricsRegistry::new();
+        registry.register_counter("k3rs_errors_total", "Errors");
+        registry.counter_inc_with("k3rs_errors_total", &[("msg", "say \"hi\"\\now\nbye")]);
+
+        let out = registry.render();
+        assert!(out.contains(r#"k3rs_errors_total{msg="say \"hi\"\\now\nbye"} 1"#));
+    }
+
+    #[test]
+    fn test_concurrent_labeled_increments() {
+        let registry = MetricsRegistry::new();
+        registry.register_counter("k3rs_hits_total", "Hits");
+
+        std::thread::scope(|s| {
+            for t in 0..8 {
+                let registry = &registry;
+                s.spawn(move || {
+                    let ns = if t % 2 == 0 { "even" } else { "odd" };
+                    for _ in 0..1000 {
+                        registry.counter_inc_with("k3rs_hits_total", &[("ns", ns)]);
+                    }
+                });
+            }
+        });
+
+        let out = registry.render();
+        assert!(out.contains("k3rs_hits_total{ns=\"even\"} 4000\n"));
+        assert!(out.contains("k3rs_hits_total{ns=\"odd\"} 4000\n"));
+        assert!(!out.contains("k3rs_hits_total 0\n"));
+    }
+
+    #[test]
+    fn test_gauge_clear_labeled() {
+        let registry = MetricsRegistry::new();
+        registry.register_gauge("k3rs_pods_total", "Pods");
+        registry.gauge_set("k3rs_pods_total", 1);
+        registry.gauge_set_with("k3rs_pods_total", &[("status", "Pending")], 1);
+        registry.gauge_clear_labeled("k3rs_pods_total");
+        registry.gauge_set_with("k3rs_pods_total", &[("status", "Running")], 1);
+
+        let out = registry.render();
+        assert!(out.contains("k3rs_pods_total 1\n"));
+        assert!(!out.contains("Pending"));
+        assert!(out.contains("k3rs_pods_total{status=\"Running\"} 1\n"));
+    }
+
+    #[test]
+    fn test_counter_set() {
+        let registry = MetricsRegistry::new();
+        registry.register_counter("k3rs_image_pulls_total", "Pulls");
+        registry.counter_set("k3rs_image_pulls_total", 7);
+        registry.counter_inc("k3rs_image_pulls_total");
+        assert!(registry.render().contains("k3rs_image_pulls_total 8\n"));
+    }
+
+    #[test]
+    fn test_unregistered_metric_ignored() {
+        let registry = MetricsRegistry::new();
+        registry.counter_inc_with("missing", &[("a", "b")]);
+        registry.gauge_set_with("missing", &[("a", "b")], 1);
+        assert_eq!(registry.render(), "");
+    }
+}
@@ -0,0 +1,147 @@
+//! The log filter of a running server or agent, changeable without a restart
+//! through the debug API and on config file reload.
+
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+/// The filter layer to install at the bottom of the subscriber.
+pub type LogFilterLayer = reload::Layer<EnvFilter, Registry>;
+
+/// `RUST_LOG`, at info and above.
+pub fn default_filter() -> EnvFilter {
+    EnvFilter::from_default_env().add_directive(tracing::level_filters::LevelFilter::INFO.into())
+}
+
+/// Parse a filter in `RUST_LOG` syntax, e.g. `pkg_container=debug,info`.
+pub fn parse(directives: &str) -> Result<EnvFilter, String> {
+    if directives.trim().is_empty() {
+        return Err("log filter is empty".to_string());
+    }
+    EnvFilter::try_new(directives)
+        .map_err(|e| format!("invalid log filter '{}': {}", directives, e))
+}
+
+/// Swaps the filter of the installed [`LogFilterLayer`].
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Directives added to every filter, over what was asked for.
+    pinned: &'static [&'static str],
+}
+
+impl LogFilter {
+    /// A filter layer starting from `directives` ([`default_filter`] when
+    /// `None`), and its control. `pinned` directives are added to every
+    /// filter the layer gets.
+    pub fn new(
+        directives: Option<&str>,
+        pinned: &'static [&'static str],
+    ) -> Result<(LogFilterLayer, Self), String> {
+        let filter = build(directives, pinned)?;
+        let (layer, handle) = reload::Layer::new(filter);
+        Ok((layer, Self { handle, pinned }))
+    }
+
+    /// Check `directives` without applying them.
+    pub fn validate(&self, directives: Option<&str>) -> Result<(), String> {
+        build(directives, self.pinned).map(|_| ())
+    }
+
+    /// Replace the filter with `directives` (the default when `None`). An
+    /// invalid filter is an error and the current one stays.
+    pub fn set(&self, directives: Option<&str>) -> Result<(), String> {
+        let filter = build(directives, self.pinned)?;
+        self.handle.reload(filter).map_err(|e| e.to_string())
+    }
+
+    /// The filter in effect.
+    pub fn current(&self) -> String {
+        self.handle
+            .with_current(|filter| filter.to_string())
+            .unwrap_or_default()
+    }
+}
+
+fn build(directives: Option<&str>, pinned: &[&str]) -> Result<EnvFilter, String> {
+    let mut filter = match directives {
+        Some(directives) => parse(directives)?,
+        None => default_filter(),
+    };
+    for directive in pinned {
+        filter = filter.add_directive(directive.parse().map_err(|e| format!("{}", e))?);
+    }
+    Ok(filter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Capture {
+        fn text(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_filters() {
+        assert!(parse("info").is_ok());
+        assert!(parse("pkg_container=debug,info").is_ok());
+        assert!(parse("").is_err());
+        assert!(parse("pkg_container=loud").is_err());
+        assert!(parse("info,[span{=debug").is_err());
+    }
+
+    #[test]
+    fn test_invalid_filter_keeps_current() {
+        let (_layer, filter) = LogFilter::new(Some("warn"), &["oci_client=info"]).unwrap();
+        assert_eq!(filter.current(), "oci_client=info,warn");
+
+        assert!(filter.set(Some("pkg_container=loud")).is_err());
+        assert_eq!(filter.current(), "oci_client=info,warn");
+
+        filter.set(Some("pkg_container=debug,info")).unwrap();
+        assert_eq!(filter.current(), "pkg_container=debug,oci_client=info,info");
+    }
+
+    #[test]
+    fn test_debug_module_emits_after_change() {
+        let (layer, filter) = LogFilter::new(Some("info"), &[]).unwrap();
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::registry().with(layer).with(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(move || writer.clone()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: "pkg_container", "before the change");
+            tracing::info!(target: "pkg_proxy", "info still shows");
+            filter.set(Some("pkg_container=debug,info")).unwrap();
+            tracing::debug!(target: "pkg_container", "after the change");
+            tracing::debug!(target: "pkg_proxy", "other modules stay at info");
+        });
+
+        let text = capture.text();
+        assert!(!text.contains("before the change"));
+        assert!(text.contains("info still shows"));
+        assert!(text.contains("after the change"));
+        assert!(!text.contains("other modules stay at info"));
+    }
+}
@@ -1,122 +1,167 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::net::Ipv4Addr;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU32, Ordering};
 use tokio::sync::RwLock;
 use tracing::info;
 
 /// Lightweight CNI-like Pod network manager.
 ///
-/// Allocates IP addresses from a configurable CIDR block (e.g. 10.42.0.0/16)
-/// and maintains a mapping of pod_id → allocated IP.
+/// Allocates IP addresses from a configurable CIDR block (e.g. 10.42.0.0/24)
+/// and maintains a mapping of pod_id → allocated IP. The first host address
+/// is the gateway; released addresses are handed out again, lowest first.
 pub struct PodNetwork {
     /// Base IP as a u32 (e.g. 10.42.0.0 → 0x0A2A0000)
     base_ip: u32,
     /// Subnet mask bit count
-    _prefix_len: u8,
-    /// Maximum number of addresses in this CIDR
+    prefix_len: u8,
+    /// Number of addresses in this CIDR, network and broadcast included
     max_hosts: u32,
-    /// Next IP offset to allocate (starts from 2 to skip network + gateway)
-    next_offset: AtomicU32,
-    /// pod_id → allocated IP string
-    allocations: Arc<RwLock<HashMap<String, String>>>,
+    allocations: Arc<RwLock<Allocations>>,
+}
+
+#[derive(Default)]
+struct Allocations {
+    /// pod_id → allocated offset from the base IP
+    by_pod: HashMap<String, u32>,
+    /// Offsets in use
+    used: BTreeSet<u32>,
 }
 
 impl PodNetwork {
-    /// Create a new PodNetwork with the given CIDR block (e.g. "10.42.0.0/16").
+    /// Create a new PodNetwork with the given CIDR block (e.g. "10.42.0.0/24").
     pub fn new(cidr: &str) -> anyhow::Result<Self> {
-        let parts: Vec<&str> = cidr.split('/').collect();
-        if parts.len() != 2 {
-            return Err(anyhow::anyhow!("Invalid CIDR format: {}", cidr));
+        let (ip_str, prefix) = cidr
+            .split_once('/')
+            .ok_or_else(|| anyhow::anyhow!("Invalid CIDR format: {}", cidr))?;
+        let prefix_len: u8 = prefix
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid prefix length: {}", prefix))?;
+        if prefix_len > 30 {
+            return Err(anyhow::anyhow!(
+                "CIDR {} is too small for a gateway and pods",
+                cidr
+            ));
         }
-
-        let ip_str = parts[0];
-        let prefix_len: u8 = parts[1]
+        let ip: Ipv4Addr = ip_str
             .parse()
-            .map_err(|_| anyhow::anyhow!("Invalid prefix length: {}", parts[1]))?;
-
-        let octets: Vec<u8> = ip_str
-            .split('.')
-            .map(|o| o.parse::<u8>())
-            .collect::<Result<Vec<_>, _>>()
             .map_err(|_| anyhow::anyhow!("Invalid IP: {}", ip_str))?;
 
-        if octets.len() != 4 {
-            return Err(anyhow::anyhow!("Invalid IP: {}", ip_str));
-        }
-
-        let base_ip = (octets[0] as u32) << 24
-            | (octets[1] as u32) << 16
-            | (octets[2] as u32) << 8
-            | (octets[3] as u32);
-
-        let max_hosts = 1u32.checked_shl(32 - prefix_len as u32).unwrap_or(0);
+        let max_hosts = 1u32 << (32 - prefix_len as u32);
+        // Mask off host bits so "10.42.0.7/24" still means 10.42.0.0/24
+        let base_ip = u32::from(ip) & !(max_hosts - 1);
 
         info!(
             "PodNetwork initialized: CIDR={}, max_hosts={}",
             cidr,
-            max_hosts.saturating_sub(2)
+            max_hosts - 3
         );
 
         Ok(Self {
             base_ip,
-            _prefix_len: prefix_len,
+            prefix_len,
             max_hosts,
-            next_offset: AtomicU32::new(2), // Skip .0 (network) and .1 (gateway)
-            allocations: Arc::new(RwLock::new(HashMap::new())),
+            allocations: Arc::new(RwLock::new(Allocations::default())),
         })
     }
 
+    /// Network address and prefix length, e.g. `10.42.0.0/24`.
+    pub fn cidr(&self) -> String {
+        format!("{}/{}", Ipv4Addr::from(self.base_ip), self.prefix_len)
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// The first host address, kept for the node's bridge.
+    pub fn gateway(&self) -> String {
+        Ipv4Addr::from(self.base_ip + 1).to_string()
+    }
+
     /// Allocate an IP for the given pod. Returns the allocated IP as a dotted-decimal string.
     pub async fn allocate_ip(&self, pod_id: &str) -> anyhow::Result<String> {
-        // Check if already allocated
-        {
-            let map = self.allocations.read().await;
-            if let Some(ip) = map.get(pod_id) {
-                return Ok(ip.clone());
-            }
+        let mut allocs = self.allocations.write().await;
+        if let Some(&offset) = allocs.by_pod.get(pod_id) {
+            return Ok(self.ip_at(offset));
         }
 
-        let offset = self.next_offset.fetch_add(1, Ordering::Relaxed);
-        if offset >= self.max_hosts {
+        // Skip .0 (network), .1 (gateway) and the broadcast address
+        let offset = (2..self.max_hosts - 1)
+            .find(|o| !allocs.used.contains(o))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "PodNetwork {} exhausted: no more IPs available",
+                    self.cidr()
+                )
+            })?;
+        allocs.used.insert(offset);
+        allocs.by_pod.insert(pod_id.to_string(), offset);
+
+        let ip = self.ip_at(offset);
+        info!("PodNetwork: allocated {} → {}", pod_id, ip);
+        Ok(ip)
+    }
+
+    /// Record an address a pod already holds, e.g. one reported before an
+    /// agent restart, so it is not handed to another pod.
+    pub async fn reserve(&self, pod_id: &str, ip: &str) -> anyhow::Result<()> {
+        let addr: Ipv4Addr = ip
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid IP: {}", ip))?;
+        let offset = u32::from(addr).wrapping_sub(self.base_ip);
+        if offset < 2 || offset >= self.max_hosts - 1 {
             return Err(anyhow::anyhow!(
-                "PodNetwork exhausted: no more IPs available"
+                "{} is not a pod address in {}",
+                ip,
+                self.cidr()
             ));
         }
 
-        let ip_u32 = self.base_ip + offset;
-        let ip = format!(
-            "{}.{}.{}.{}",
-            (ip_u32 >> 24) & 0xFF,
-            (ip_u32 >> 16) & 0xFF,
-            (ip_u32 >> 8) & 0xFF,
-            ip_u32 & 0xFF,
-        );
-
-        let mut map = self.allocations.write().await;
-        map.insert(pod_id.to_string(), ip.clone());
-
-        info!("PodNetwork: allocated {} → {}", pod_id, ip);
-        Ok(ip)
+        let mut allocs = self.allocations.write().await;
+        if allocs.by_pod.get(pod_id) == Some(&offset) {
+            return Ok(());
+        }
+        if allocs.used.contains(&offset) {
+            return Err(anyhow::anyhow!("{} is already allocated", ip));
+        }
+        if let Some(previous) = allocs.by_pod.insert(pod_id.to_string(), offset) {
+            allocs.used.remove(&previous);
+        }
+        allocs.used.insert(offset);
+        Ok(())
     }
 
     /// Release a pod's IP allocation.
     pub async fn release_ip(&self, pod_id: &str) {
-        let mut map = self.allocations.write().await;
-        if let Some(ip) = map.remove(pod_id) {
-            info!("PodNetwork: released {} (was {})", pod_id, ip);
+        let mut allocs = self.allocations.write().await;
+        if let Some(offset) = allocs.by_pod.remove(pod_id) {
+            allocs.used.remove(&offset);
+            info!(
+                "PodNetwork: released {} (was {})",
+                pod_id,
+                self.ip_at(offset)
+            );
         }
     }
 
     /// Look up a pod's allocated IP.
     pub async fn get_pod_ip(&self, pod_id: &str) -> Option<String> {
-        let map = self.allocations.read().await;
-        map.get(pod_id).cloned()
+        let allocs = self.allocations.read().await;
+        allocs.by_pod.get(pod_id).map(|&o| self.ip_at(o))
     }
 
     /// Dump all current allocations.
     pub async fn list_allocations(&self) -> HashMap<String, String> {
-        let map = self.allocations.read().await;
-        map.clone()
+        let allocs = self.allocations.read().await;
+        allocs
+            .by_pod
+            .iter()
+            .map(|(pod, &o)| (pod.clone(), self.ip_at(o)))
+            .collect()
+    }
+
+    fn ip_at(&self, offset: u32) -> String {
+        Ipv4Addr::from(self.base_ip + offset).to_string()
     }
 }
 
@@ -128,11 +173,8 @@ mod tests {
     async fn test_allocate_ip() {
         let net = PodNetwork::new("10.42.0.0/16").unwrap();
         let ip = net.allocate_ip("pod-1").await.unwrap();
-        assert!(
-            ip.starts_with("10.42."),
-            "IP should be in 10.42.x.x range, got {}",
-            ip
-        );
+        assert_eq!(ip, "10.42.0.2");
+        assert_eq!(net.gateway(), "10.42.0.1");
     }
 
     #[tokio::test]
@@ -172,4 +214,50 @@ mod tests {
         let ip2 = net.allocate_ip("pod-1").await.unwrap();
         assert_eq!(ip1, ip2, "Allocating same pod_id should return same IP");
     }
+
+    #[tokio::test]
+    async fn test_released_ip_is_reused() {
+        let net = PodNetwork::new("10.42.3.0/24").unwrap();
+        for pod in ["a", "b", "c"] {
+            net.allocate_ip(pod).await.unwrap();
+        }
+        net.release_ip("b").await;
+        assert_eq!(net.allocate_ip("d").await.unwrap(), "10.42.3.3");
+        assert_eq!(net.allocate_ip("e").await.unwrap(), "10.42.3.5");
+    }
+
+    #[tokio::test]
+    async fn test_exhaustion() {
+        // A /29 has 8 addresses: network, gateway, broadcast and 5 for pods.
+        let net = PodNetwork::new("10.42.0.8/29").unwrap();
+        for i in 0..5 {
+            net.allocate_ip(&format!("pod-{}", i)).await.unwrap();
+        }
+        assert_eq!(net.get_pod_ip("pod-4").await.as_deref(), Some("10.42.0.14"));
+        assert!(net.allocate_ip("pod-5").await.is_err());
+        net.release_ip("pod-0").await;
+        assert_eq!(net.allocate_ip("pod-5").await.unwrap(), "10.42.0.10");
+    }
+
+    #[tokio::test]
+    async fn test_reserve_existing_ip() {
+        let net = PodNetwork::new("10.42.0.0/24").unwrap();
+        net.reserve("pod-1", "10.42.0.2").await.unwrap();
+        net.reserve("pod-1", "10.42.0.2").await.unwrap();
+        assert!(net.reserve("pod-2", "10.42.0.2").await.is_err());
+        assert!(net.reserve("pod-2", "10.42.0.1").await.is_err());
+        assert!(net.reserve("pod-2", "10.42.1.2").await.is_err());
+        assert_eq!(net.allocate_ip("pod-2").await.unwrap(), "10.42.0.3");
+    }
+
+    #[test]
+    fn test_invalid_cidrs() {
+        for bad in ["10.42.0.0", "10.42.0/24", "10.42.0.0/31", "10.42.0.0/x"] {
+            assert!(PodNetwork::new(bad).is_err(), "{}", bad);
+        }
+        assert_eq!(
+            PodNetwork::new("10.42.7.9/24").unwrap().cidr(),
+            "10.42.7.0/24"
+        );
+    }
 }
@@ -1,29 +1,71 @@
 use std::collections::{HashMap, HashSet};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::net::UdpSocket;
 use tokio::sync::RwLock;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
-use pkg_constants::dns::{NAT64_PREFIX, QTYPE_A, QTYPE_AAAA, UPSTREAM_DNS_TIMEOUT_SECS};
+use pkg_constants::dns::{
+    ENDPOINT_RECORD_TTL_SECS, NAT64_PREFIX, QTYPE_A, QTYPE_AAAA, QTYPE_SRV,
+    UPSTREAM_DNS_TIMEOUT_SECS,
+};
 use pkg_constants::network::{DEFAULT_VPC_ID, GHOST_VERSION, PLATFORM_PREFIX};
 
 /// FQDN → (ClusterIP, vpc_name, vpc_id) shared map.
 type VpcRecords = Arc<RwLock<HashMap<String, (String, String, u16)>>>;
 
+use pkg_types::endpoint::{Endpoint, EndpointAddress};
+use pkg_types::service::Service;
 use pkg_types::vpc::{PeeringDirection, PeeringStatus, VpcPeering};
 
+/// Response codes used in answers built here.
+const RCODE_NOERROR: u8 = 0;
+const RCODE_NXDOMAIN: u8 = 3;
+
+/// Largest response sent over UDP; answers past it are dropped and the
+/// response marked truncated.
+const MAX_UDP_RESPONSE: usize = 512;
+
+/// One SRV answer: where a named port of a Service can be reached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SrvRecord {
+    port: u16,
+    target: String,
+}
+
+/// Records derived from Services and their Endpoints. Rebuilt on every sync
+/// and swapped in whole, so a query sees either the old or the new set,
+/// never a half-updated one.
+#[derive(Debug, Default)]
+struct EndpointRecords {
+    /// Headless service and per-pod FQDN → (service VPC, endpoint addresses).
+    addresses: HashMap<String, (String, Vec<IpAddr>)>,
+    /// `_<port>._<proto>.<svc>.<ns>.svc.cluster.local` → (service VPC, answers).
+    srv: HashMap<String, (String, Vec<SrvRecord>)>,
+    /// Start offset of the next multi-record answer, for round-robin order.
+    rotation: AtomicUsize,
+}
+
 /// Upstream forwarding timeout.
 const UPSTREAM_TIMEOUT: std::time::Duration =
     std::time::Duration::from_secs(UPSTREAM_DNS_TIMEOUT_SECS);
 
 /// Lightweight embedded DNS server with DNS64 support.
 ///
-/// **Internal** (`*.svc.cluster.local`):
+/// **Internal** (`<svc>.<ns>.svc.cluster.local`, or the `<svc>.<ns>` and
+/// `<svc>.<ns>.svc` short forms of a known service):
 ///   - AAAA queries → Ghost IPv6 (constructed from ClusterIP + VPC ID)
 ///   - A queries → ClusterIP (backward compat)
+///   - headless Services (`cluster_ip: "None"`) → the ready endpoint
+///     addresses, rotated per query; each endpoint also answers as
+///     `<hostname>.<svc>.<ns>.svc.cluster.local`
+///   - SRV queries for `_<port>._<proto>.<svc>.<ns>.svc.cluster.local` →
+///     one record per named port target
+///   - unknown names under `cluster.local` → NXDOMAIN
 ///
-/// **External** (all other domains):
+/// **External** (all other domains), forwarded to the upstream resolvers in
+/// order until one answers:
 ///   - AAAA queries → forward upstream as A, synthesize AAAA via `64:ff9b::/96` (DNS64)
 ///   - A queries → forward upstream, relay response as-is
 pub struct DnsServer {
@@ -35,14 +77,16 @@ pub struct DnsServer {
     vpc_members: Arc<RwLock<HashMap<String, String>>>,
     /// Directed peering pairs: (src_vpc, dst_vpc) — src can resolve dst's services
     peered_vpcs: Arc<RwLock<HashSet<(String, String)>>>,
+    /// Headless, per-pod and SRV records built from Endpoints
+    endpoint_records: Arc<RwLock<EndpointRecords>>,
     listen_addr: SocketAddr,
     domain_suffix: String,
     /// Ghost IPv6 platform prefix (e.g., 0xfd6b3372).
     platform_prefix: u32,
     /// Cluster ID for Ghost IPv6 construction.
     cluster_id: u32,
-    /// Upstream DNS resolver for forwarding external queries.
-    upstream: SocketAddr,
+    /// Upstream DNS resolvers for forwarding external queries, tried in order.
+    upstreams: Arc<Vec<SocketAddr>>,
 }
 
 impl DnsServer {
@@ -53,11 +97,14 @@ impl DnsServer {
             vpc_records: Arc::new(RwLock::new(HashMap::new())),
             vpc_members: Arc::new(RwLock::new(HashMap::new())),
             peered_vpcs: Arc::new(RwLock::new(HashSet::new())),
+            endpoint_records: Arc::new(RwLock::new(EndpointRecords::default())),
             listen_addr,
             domain_suffix: pkg_constants::dns::DNS_DOMAIN_SUFFIX.to_string(),
             platform_prefix: PLATFORM_PREFIX,
             cluster_id: 0,
-            upstream: pkg_constants::dns::DEFAULT_UPSTREAM_DNS.parse().unwrap(),
+            upstreams: Arc::new(vec![
+                pkg_constants::dns::DEFAULT_UPSTREAM_DNS.parse().unwrap(),
+            ]),
         }
     }
 
@@ -67,20 +114,29 @@ impl DnsServer {
         self.cluster_id = cluster_id;
     }
 
-    /// Set the upstream DNS resolver for forwarding external queries.
-    pub fn set_upstream(&mut self, addr: SocketAddr) {
-        self.upstream = addr;
+    /// Address of the primary listener.
+    pub fn listen_addr(&self) -> SocketAddr {
+        self.listen_addr
+    }
+
+    /// Set the upstream DNS resolvers for forwarding external queries; later
+    /// ones are tried when earlier ones fail or time out. An empty list keeps
+    /// the current resolvers.
+    pub fn set_upstreams(&mut self, addrs: Vec<SocketAddr>) {
+        if !addrs.is_empty() {
+            self.upstreams = Arc::new(addrs);
+        }
     }
 
     /// Update DNS records from a list of Services.
-    pub async fn update_records(&self, services: &[pkg_types::service::Service]) {
+    pub async fn update_records(&self, services: &[Service]) {
         let mut new_records = HashMap::new();
 
         for svc in services {
-            if let Some(ref cluster_ip) = svc.cluster_ip {
+            if let Some(cluster_ip) = svc.virtual_ip() {
                 // <service-name>.<namespace>.svc.cluster.local
                 let fqdn = format!("{}.{}.{}", svc.name, svc.namespace, self.domain_suffix);
-                new_records.insert(fqdn, cluster_ip.clone());
+                new_records.insert(fqdn, cluster_ip.to_string());
             }
         }
 
@@ -97,21 +153,21 @@ impl DnsServer {
     /// the source pod's VPC membership. Also updates the vpc_members map.
     pub async fn update_records_vpc(
         &self,
-        services: &[pkg_types::service::Service],
+        services: &[Service],
         ip_to_vpc: &HashMap<String, String>,
         vpc_name_to_id: &HashMap<String, u16>,
     ) {
         let mut new_vpc_records = HashMap::new();
 
         for svc in services {
-            if let Some(ref cluster_ip) = svc.cluster_ip {
+            if let Some(cluster_ip) = svc.virtual_ip() {
                 let fqdn = format!("{}.{}.{}", svc.name, svc.namespace, self.domain_suffix);
                 let svc_vpc = svc.vpc.as_deref().unwrap_or("default").to_string();
                 let vpc_id = vpc_name_to_id
                     .get(&svc_vpc)
                     .copied()
                     .unwrap_or(DEFAULT_VPC_ID);
-                new_vpc_records.insert(fqdn, (cluster_ip.clone(), svc_vpc, vpc_id));
+                new_vpc_records.insert(fqdn, (cluster_ip.to_string(), svc_vpc, vpc_id));
             }
         }
 
@@ -132,6 +188,106 @@ impl DnsServer {
         );
     }
 
+    /// Update headless service, per-pod and SRV records from Services and
+    /// their Endpoints.
+    ///
+    /// A headless Service resolves to the addresses of its ready endpoints,
+    /// and each endpoint to `<hostname>.<svc>.<ns>.svc.cluster.local`. Named
+    /// ports get SRV records pointing at those per-pod names for headless
+    /// Services, and at the Service name and port otherwise.
+    pub async fn update_endpoints(&self, services: &[Service], endpoints: &[Endpoint]) {
+        let new_records = Self::build_endpoint_records(services, endpoints, &self.domain_suffix);
+        let (names, srv_names) = (new_records.addresses.len(), new_records.srv.len());
+        *self.endpoint_records.write().await = new_records;
+        info!(
+            "DNS endpoint records updated: {} names, {} SRV names",
+            names, srv_names
+        );
+    }
+
+    fn build_endpoint_records(
+        services: &[Service],
+        endpoints: &[Endpoint],
+        domain_suffix: &str,
+    ) -> EndpointRecords {
+        let mut records = EndpointRecords::default();
+
+        for svc in services {
+            let headless = svc.is_headless();
+            if !headless && svc.virtual_ip().is_none() {
+                continue;
+            }
+            let fqdn = format!("{}.{}.{}", svc.name, svc.namespace, domain_suffix);
+            let svc_vpc = svc.vpc.as_deref().unwrap_or("default").to_string();
+            let endpoint = endpoints
+                .iter()
+                .find(|ep| ep.service_id == svc.id && ep.namespace == svc.namespace);
+            let backends: Vec<(String, IpAddr)> = endpoint
+                .map(|ep| {
+                    ep.addresses
+                        .iter()
+                        .filter_map(|addr| {
+                            let ip = addr.ip.parse().ok()?;
+                            Some((Self::pod_label(addr, ip), ip))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            if headless {
+                // Listed even without endpoints, so the name answers NODATA
+                // rather than NXDOMAIN while the pods start.
+                let ips = backends.iter().map(|(_, ip)| *ip).collect();
+                records
+                    .addresses
+                    .insert(fqdn.clone(), (svc_vpc.clone(), ips));
+                for (label, ip) in &backends {
+                    records
+                        .addresses
+                        .entry(format!("{}.{}", label, fqdn))
+                        .or_insert_with(|| (svc_vpc.clone(), Vec::new()))
+                        .1
+                        .push(*ip);
+                }
+            }
+
+            for port in svc.spec.ports.iter().filter(|p| !p.name.is_empty()) {
+                let proto = endpoint
+                    .and_then(|ep| ep.ports.iter().find(|p| p.name == port.name))
+                    .map_or_else(|| "tcp".to_string(), |p| p.protocol.to_ascii_lowercase());
+                let owner = format!("_{}._{}.{}", port.name.to_ascii_lowercase(), proto, fqdn);
+                let answers = if headless {
+                    backends
+                        .iter()
+                        .map(|(label, _)| SrvRecord {
+                            port: port.target_port,
+                            target: format!("{}.{}", label, fqdn),
+                        })
+                        .collect()
+                } else {
+                    vec![SrvRecord {
+                        port: port.port,
+                        target: fqdn.clone(),
+                    }]
+                };
+                records.srv.insert(owner, (svc_vpc.clone(), answers));
+            }
+        }
+
+        records
+    }
+
+    /// DNS label of an endpoint: its hostname, or the address with `.` and
+    /// `:` replaced by `-` when it has none (or one that is not a valid label).
+    fn pod_label(addr: &EndpointAddress, ip: IpAddr) -> String {
+        addr.hostname
+            .as_deref()
+            .filter(|h| !h.is_empty() && h.len() <= 63 && !h.contains('.'))
+            .map(str::to_string)
+            .unwrap_or_else(|| ip.to_string().replace(['.', ':'], "-"))
+            .to_ascii_lowercase()
+    }
+
     /// Update the set of peered VPC pairs from the latest peerings list.
     ///
     /// Bidirectional peerings insert both `(a,b)` and `(b,a)`.
@@ -178,9 +334,10 @@ impl DnsServer {
         let vpc_records = self.vpc_records.clone();
         let vpc_members = self.vpc_members.clone();
         let peered_vpcs = self.peered_vpcs.clone();
+        let endpoint_records = self.endpoint_records.clone();
         let platform_prefix = self.platform_prefix;
         let cluster_id = self.cluster_id;
-        let upstream = self.upstream;
+        let upstreams = self.upstreams.clone();
         let domain_suffix = self.domain_suffix.clone();
 
         tokio::spawn(async move {
@@ -194,10 +351,11 @@ impl DnsServer {
                             &vpc_records,
                             &vpc_members,
                             &peered_vpcs,
+                            &endpoint_records,
                             &src,
                             platform_prefix,
                             cluster_id,
-                            upstream,
+                            &upstreams,
                             &domain_suffix,
                         )
                         .await
@@ -214,8 +372,8 @@ impl DnsServer {
         });
 
         info!(
-            "DNS server is running (DNS64 enabled, upstream={})",
-            self.upstream
+            "DNS server is running (DNS64 enabled, upstreams={:?})",
+            self.upstreams
         );
         Ok(())
     }
@@ -230,9 +388,10 @@ impl DnsServer {
         let vpc_records = self.vpc_records.clone();
         let vpc_members = self.vpc_members.clone();
         let peered_vpcs = self.peered_vpcs.clone();
+        let endpoint_records = self.endpoint_records.clone();
         let platform_prefix = self.platform_prefix;
         let cluster_id = self.cluster_id;
-        let upstream = self.upstream;
+        let upstreams = self.upstreams.clone();
         let domain_suffix = self.domain_suffix.clone();
 
         tokio::spawn(async move {
@@ -246,10 +405,11 @@ impl DnsServer {
                             &vpc_records,
                             &vpc_members,
                             &peered_vpcs,
+                            &endpoint_records,
                             &src,
                             platform_prefix,
                             cluster_id,
-                            upstream,
+                            &upstreams,
                             &domain_suffix,
                         )
                         .await
@@ -275,6 +435,9 @@ impl DnsServer {
     ///
     /// - A queries for internal services → ClusterIP (backward compat)
     /// - AAAA queries for internal services → Ghost IPv6 (from ClusterIP + VPC ID)
+    /// - headless, per-pod and SRV names → answers from the Endpoints
+    /// - other types for internal services → empty answer (NODATA)
+    /// - unknown or denied names under the cluster domain → NXDOMAIN
     /// - A queries for external domains → forward upstream
     /// - AAAA queries for external domains → DNS64 (forward as A, synthesize AAAA)
     #[allow(clippy::too_many_arguments)]
@@ -284,10 +447,11 @@ impl DnsServer {
         vpc_records: &VpcRecords,
         vpc_members: &Arc<RwLock<HashMap<String, String>>>,
         peered_vpcs: &Arc<RwLock<HashSet<(String, String)>>>,
+        endpoint_records: &Arc<RwLock<EndpointRecords>>,
         src: &SocketAddr,
         platform_prefix: u32,
         cluster_id: u32,
-        upstream: SocketAddr,
+        upstreams: &[SocketAddr],
         domain_suffix: &str,
     ) -> Option<Vec<u8>> {
         // Minimum DNS query: 12-byte header + at least 1 byte question
@@ -296,7 +460,7 @@ impl DnsServer {
         }
 
         let txn_id = &query[0..2];
-        let name = Self::parse_dns_name(query, 12)?;
+        let name = Self::parse_dns_name(query, 12)?.to_ascii_lowercase();
 
         // Parse QTYPE from the question section
         let name_end = Self::find_name_end(query, 12)?;
@@ -305,27 +469,54 @@ impl DnsServer {
         }
         let qtype = u16::from_be_bytes([query[name_end], query[name_end + 1]]);
         let q_end = name_end + 4; // QTYPE (2) + QCLASS (2)
+        let question = &query[12..q_end];
+
+        let fqdn = match Self::qualify(&name, domain_suffix) {
+            // A short form that names no service is left to the upstreams.
+            Some(fqdn)
+                if records.read().await.contains_key(&fqdn)
+                    || vpc_records.read().await.contains_key(&fqdn)
+                    || endpoint_records.read().await.addresses.contains_key(&fqdn) =>
+            {
+                Some(fqdn)
+            }
+            Some(_) => None,
+            None => Self::is_cluster_name(&name, domain_suffix).then(|| name.clone()),
+        };
 
-        let is_internal = name.ends_with(domain_suffix);
-
-        if is_internal {
+        if let Some(fqdn) = fqdn {
             // Determine the source pod's VPC (if any)
-            let src_ip = src.ip().to_string();
+            let src_ip = src.ip().to_canonical().to_string();
             let members = vpc_members.read().await;
             let src_vpc = members.get(&src_ip).cloned();
             drop(members);
 
+            if let Some(response) = Self::resolve_endpoints(
+                txn_id,
+                question,
+                qtype,
+                &fqdn,
+                &src_vpc,
+                endpoint_records,
+                peered_vpcs,
+            )
+            .await
+            {
+                return Some(response);
+            }
+
             // Resolve internal service (returns ClusterIP + VPC ID)
-            let (ip_str, vpc_id) =
-                Self::resolve_internal(&name, &src_vpc, records, vpc_records, peered_vpcs).await?;
+            let Some((ip_str, vpc_id)) =
+                Self::resolve_internal(&fqdn, &src_vpc, records, vpc_records, peered_vpcs).await
+            else {
+                return Some(Self::build_empty_response(txn_id, question, RCODE_NXDOMAIN));
+            };
 
             let octets: Vec<u8> = ip_str.split('.').filter_map(|o| o.parse().ok()).collect();
             if octets.len() != 4 {
                 return None;
             }
 
-            let question = &query[12..q_end];
-
             match qtype {
                 QTYPE_A => Self::build_a_response(txn_id, question, &octets),
                 QTYPE_AAAA => {
@@ -333,23 +524,48 @@ impl DnsServer {
                         Self::construct_ghost_ipv6(platform_prefix, cluster_id, vpc_id, &octets);
                     Self::build_aaaa_response(txn_id, question, &ghost)
                 }
-                _ => None,
+                _ => Some(Self::build_empty_response(txn_id, question, RCODE_NOERROR)),
             }
         } else {
             // External domain — forward upstream
             match qtype {
                 QTYPE_AAAA => {
                     // DNS64: forward as A query, synthesize AAAA from response
-                    Self::dns64_forward(query, name_end, q_end, upstream).await
+                    Self::dns64_forward(query, name_end, q_end, upstreams).await
                 }
                 _ => {
                     // Forward as-is (A queries, etc.)
-                    Self::forward_upstream(query, upstream).await
+                    Self::forward_upstream(query, upstreams).await
                 }
             }
         }
     }
 
+    /// The full service name for `<svc>.<ns>.svc.cluster.local` and its short
+    /// forms `<svc>.<ns>` and `<svc>.<ns>.svc`; `None` for anything else.
+    fn qualify(name: &str, domain_suffix: &str) -> Option<String> {
+        let labels: Vec<&str> = name.split('.').collect();
+        let short_suffix = domain_suffix.split('.').next().unwrap_or_default();
+        match labels.as_slice() {
+            [svc, ns] if !svc.is_empty() && !ns.is_empty() => {
+                Some(format!("{}.{}.{}", svc, ns, domain_suffix))
+            }
+            [svc, ns, suffix] if *suffix == short_suffix => {
+                Some(format!("{}.{}.{}", svc, ns, domain_suffix))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `name` lies under the cluster domain (`cluster.local` for the
+    /// default `svc.cluster.local` suffix), i.e. only this server can answer it.
+    fn is_cluster_name(name: &str, domain_suffix: &str) -> bool {
+        let cluster_domain = domain_suffix
+            .split_once('.')
+            .map_or(domain_suffix, |(_, rest)| rest);
+        name == cluster_domain || name.ends_with(&format!(".{}", cluster_domain))
+    }
+
     // ─── Internal Resolution ────────────────────────────────────────
 
     /// Resolve an internal service name with VPC-scoped access control.
@@ -390,6 +606,57 @@ impl DnsServer {
         }
     }
 
+    /// Answer `name` from the Endpoints-derived records: headless Service
+    /// and per-pod addresses of the queried family, or SRV records. Known
+    /// names without records of the queried type get NODATA, names outside
+    /// the source's VPC and its peers NXDOMAIN. `None` when `name` is not
+    /// such a record.
+    async fn resolve_endpoints(
+        txn_id: &[u8],
+        question: &[u8],
+        qtype: u16,
+        name: &str,
+        src_vpc: &Option<String>,
+        endpoint_records: &Arc<RwLock<EndpointRecords>>,
+        peered_vpcs: &Arc<RwLock<HashSet<(String, String)>>>,
+    ) -> Option<Vec<u8>> {
+        let records = endpoint_records.read().await;
+        let (svc_vpc, rdata): (&str, Vec<Vec<u8>>) =
+            if let Some((svc_vpc, ips)) = records.addresses.get(name) {
+                let rdata = ips
+                    .iter()
+                    .filter_map(|ip| match (qtype, ip) {
+                        (QTYPE_A, IpAddr::V4(v4)) => Some(v4.octets().to_vec()),
+                        (QTYPE_AAAA, IpAddr::V6(v6)) => Some(v6.octets().to_vec()),
+                        _ => None,
+                    })
+                    .collect();
+                (svc_vpc, rdata)
+            } else if let Some((svc_vpc, srv)) = records.srv.get(name) {
+                let rdata = if qtype == QTYPE_SRV {
+                    srv.iter().map(|r| Self::srv_rdata(r, srv.len())).collect()
+                } else {
+                    Vec::new()
+                };
+                (svc_vpc, rdata)
+            } else {
+                return None;
+            };
+
+        if let Some(source_vpc) = src_vpc
+            && source_vpc != svc_vpc
+            && !peered_vpcs
+                .read()
+                .await
+                .contains(&(source_vpc.clone(), svc_vpc.to_string()))
+        {
+            return Some(Self::build_empty_response(txn_id, question, RCODE_NXDOMAIN));
+        }
+
+        let rotate = records.rotation.fetch_add(1, Ordering::Relaxed);
+        Some(Self::build_answers(txn_id, question, qtype, &rdata, rotate))
+    }
+
     // ─── Ghost IPv6 Construction ────────────────────────────────────
 
     /// Construct a Ghost IPv6 address from ClusterIP + VPC metadata.
@@ -423,6 +690,82 @@ impl DnsServer {
 
     // ─── DNS Response Builders ──────────────────────────────────────
 
+    /// Build an answerless response with the given RCODE: NXDOMAIN for
+    /// unknown names, NOERROR (NODATA) for a known name without records of
+    /// the queried type.
+    fn build_empty_response(txn_id: &[u8], question: &[u8], rcode: u8) -> Vec<u8> {
+        let mut r = Vec::with_capacity(12 + question.len());
+        // Header
+        r.extend_from_slice(txn_id);
+        r.extend_from_slice(&[0x85, 0x80 | rcode]); // Flags: authoritative response, RCODE
+        r.extend_from_slice(&[0x00, 0x01]); // QDCOUNT: 1
+        r.extend_from_slice(&[0x00, 0x00]); // ANCOUNT: 0
+        r.extend_from_slice(&[0x00, 0x00]); // NSCOUNT: 0
+        r.extend_from_slice(&[0x00, 0x00]); // ARCOUNT: 0
+        // Question section
+        r.extend_from_slice(question);
+        r
+    }
+
+    /// Build a response with one `qtype` record per RDATA, starting at
+    /// `rotate % len` so that clients taking the first answer spread over
+    /// the backends. Records that would overflow a UDP response are left out
+    /// and the TC bit set. No RDATA gives NODATA.
+    fn build_answers(
+        txn_id: &[u8],
+        question: &[u8],
+        qtype: u16,
+        rdata: &[Vec<u8>],
+        rotate: usize,
+    ) -> Vec<u8> {
+        if rdata.is_empty() {
+            return Self::build_empty_response(txn_id, question, RCODE_NOERROR);
+        }
+        let mut r = Vec::with_capacity(MAX_UDP_RESPONSE);
+        // Header
+        r.extend_from_slice(txn_id);
+        r.extend_from_slice(&[0x85, 0x80]); // Flags: authoritative response, no error
+        r.extend_from_slice(&[0x00, 0x01]); // QDCOUNT: 1
+        r.extend_from_slice(&[0x00, 0x00]); // ANCOUNT: set below
+        r.extend_from_slice(&[0x00, 0x00]); // NSCOUNT: 0
+        r.extend_from_slice(&[0x00, 0x00]); // ARCOUNT: 0
+        // Question section
+        r.extend_from_slice(question);
+        // Answer section
+        let mut count: u16 = 0;
+        for i in 0..rdata.len() {
+            let data = &rdata[(rotate + i) % rdata.len()];
+            if r.len() + 12 + data.len() > MAX_UDP_RESPONSE {
+                r[2] |= 0x02; // TC
+                break;
+            }
+            r.extend_from_slice(&[0xC0, 0x0C]); // Name pointer to question
+            r.extend_from_slice(&qtype.to_be_bytes());
+            r.extend_from_slice(&[0x00, 0x01]); // Class: IN
+            r.extend_from_slice(&ENDPOINT_RECORD_TTL_SECS.to_be_bytes());
+            r.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            r.extend_from_slice(data);
+            count += 1;
+        }
+        r[6..8].copy_from_slice(&count.to_be_bytes());
+        r
+    }
+
+    /// SRV RDATA for one of `total` equally weighted targets.
+    fn srv_rdata(record: &SrvRecord, total: usize) -> Vec<u8> {
+        let weight = (100 / total.max(1)).max(1) as u16;
+        let mut d = Vec::with_capacity(8 + record.target.len());
+        d.extend_from_slice(&[0x00, 0x00]); // Priority: 0
+        d.extend_from_slice(&weight.to_be_bytes());
+        d.extend_from_slice(&record.port.to_be_bytes());
+        for label in record.target.split('.') {
+            d.push(label.len() as u8);
+            d.extend_from_slice(label.as_bytes());
+        }
+        d.push(0);
+        d
+    }
+
     /// Build a DNS response with an A record (4-byte IPv4 RDATA).
     fn build_a_response(txn_id: &[u8], question: &[u8], ip: &[u8]) -> Option<Vec<u8>> {
         let mut r = Vec::with_capacity(64);
@@ -475,7 +818,7 @@ impl DnsServer {
         query: &[u8],
         name_end: usize,
         q_end: usize,
-        upstream: SocketAddr,
+        upstreams: &[SocketAddr],
     ) -> Option<Vec<u8>> {
         // Build A query from the original AAAA query
         let mut a_query = query.to_vec();
@@ -483,7 +826,7 @@ impl DnsServer {
         a_query[name_end + 1] = 0x01; // QTYPE = A
 
         // Forward to upstream
-        let response = Self::forward_upstream(&a_query, upstream).await?;
+        let response = Self::forward_upstream(&a_query, upstreams).await?;
 
         // Extract first A record IP from upstream response
         let ipv4 = Self::extract_a_record_ip(&response)?;
@@ -497,16 +840,37 @@ impl DnsServer {
         Self::build_aaaa_response(&query[0..2], &query[12..q_end], &aaaa)
     }
 
-    /// Forward a raw DNS query to an upstream resolver and return the response.
-    async fn forward_upstream(query: &[u8], upstream: SocketAddr) -> Option<Vec<u8>> {
-        let sock = UdpSocket::bind("0.0.0.0:0").await.ok()?;
-        sock.send_to(query, upstream).await.ok()?;
+    /// Forward a raw DNS query to the upstream resolvers in order and return
+    /// the first response. A resolver that errors or times out is skipped.
+    async fn forward_upstream(query: &[u8], upstreams: &[SocketAddr]) -> Option<Vec<u8>> {
+        for upstream in upstreams {
+            let bind: SocketAddr = if upstream.is_ipv6() {
+                "[::]:0".parse().unwrap()
+            } else {
+                "0.0.0.0:0".parse().unwrap()
+            };
+            let Ok(sock) = UdpSocket::bind(bind).await else {
+                continue;
+            };
+            // Connected, so only the upstream's replies are accepted and a
+            // closed port fails fast instead of waiting out the timeout.
+            if let Err(e) = sock.connect(upstream).await {
+                debug!("DNS upstream {} unreachable: {}", upstream, e);
+                continue;
+            }
+            if let Err(e) = sock.send(query).await {
+                debug!("DNS upstream {} unreachable: {}", upstream, e);
+                continue;
+            }
 
-        let mut buf = [0u8; 512];
-        match tokio::time::timeout(UPSTREAM_TIMEOUT, sock.recv_from(&mut buf)).await {
-            Ok(Ok((len, _))) => Some(buf[..len].to_vec()),
-            _ => None,
+            let mut buf = [0u8; 512];
+            match tokio::time::timeout(UPSTREAM_TIMEOUT, sock.recv(&mut buf)).await {
+                Ok(Ok(len)) => return Some(buf[..len].to_vec()),
+                Ok(Err(e)) => debug!("DNS upstream {} failed: {}", upstream, e),
+                Err(_) => debug!("DNS upstream {} timed out", upstream),
+            }
         }
+        None
     }
 
     /// Extract the first A record's IPv4 address from a DNS response.
@@ -621,3 +985,347 @@ impl DnsServer {
         Some(offset)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, namespace: &str, cluster_ip: &str) -> pkg_types::service::Service {
+        serde_json::from_value(serde_json::json!({
+            "id": format!("svc-{}", name),
+            "name": name,
+            "namespace": namespace,
+            "spec": {
+                "ports": [{ "name": "http", "port": 80, "target_port": 8080 }],
+                "service_type": "ClusterIP",
+            },
+            "cluster_ip": cluster_ip,
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        let mut q = vec![0xAB, 0xCD, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        for label in name.split('.') {
+            q.push(label.len() as u8);
+            q.extend_from_slice(label.as_bytes());
+        }
+        q.push(0);
+        q.extend_from_slice(&qtype.to_be_bytes());
+        q.extend_from_slice(&[0x00, 0x01]);
+        q
+    }
+
+    async fn ask(server: &DnsServer, name: &str, qtype: u16) -> Option<Vec<u8>> {
+        DnsServer::handle_dns_query(
+            &query(name, qtype),
+            &server.records,
+            &server.vpc_records,
+            &server.vpc_members,
+            &server.peered_vpcs,
+            &server.endpoint_records,
+            &"10.42.0.9:40000".parse().unwrap(),
+            server.platform_prefix,
+            server.cluster_id,
+            &server.upstreams,
+            &server.domain_suffix,
+        )
+        .await
+    }
+
+    fn rcode(response: &[u8]) -> u8 {
+        response[3] & 0x0F
+    }
+
+    /// Endpoints of `svc` with one ready address per `(pod name, ip)`.
+    fn endpoints(svc: &Service, pods: &[(&str, &str)]) -> Endpoint {
+        let addresses: Vec<_> = pods
+            .iter()
+            .map(|(name, ip)| serde_json::json!({ "ip": ip, "hostname": name }))
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "id": svc.id,
+            "service_id": svc.id,
+            "service_name": svc.name,
+            "namespace": svc.namespace,
+            "addresses": addresses,
+            "ports": [{ "name": "http", "port": 8080 }],
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    /// `(type, RDATA)` of every answer record.
+    fn answers(response: &[u8]) -> Vec<(u16, Vec<u8>)> {
+        let ancount = u16::from_be_bytes([response[6], response[7]]);
+        let mut offset = DnsServer::find_name_end(response, 12).unwrap() + 4;
+        (0..ancount)
+            .map(|_| {
+                offset = DnsServer::find_name_end(response, offset).unwrap();
+                let rtype = u16::from_be_bytes([response[offset], response[offset + 1]]);
+                let len = u16::from_be_bytes([response[offset + 8], response[offset + 9]]) as usize;
+                offset += 10;
+                let rdata = response[offset..offset + len].to_vec();
+                offset += len;
+                (rtype, rdata)
+            })
+            .collect()
+    }
+
+    fn ipv6(ip: &str) -> Vec<u8> {
+        ip.parse::<std::net::Ipv6Addr>().unwrap().octets().to_vec()
+    }
+
+    /// A UDP resolver that answers every A query with `ip`.
+    async fn fake_upstream(ip: [u8; 4]) -> SocketAddr {
+        let sock = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = sock.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, src)) = sock.recv_from(&mut buf).await {
+                let q = &buf[..len];
+                let q_end = DnsServer::find_name_end(q, 12).unwrap() + 4;
+                let reply = DnsServer::build_a_response(&q[0..2], &q[12..q_end], &ip).unwrap();
+                let _ = sock.send_to(&reply, src).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_record_updates() {
+        let server = DnsServer::new("127.0.0.1:0".parse().unwrap());
+        server
+            .update_records(&[
+                service("web", "default", "10.43.0.10"),
+                service("db", "prod", "10.43.0.20"),
+            ])
+            .await;
+
+        for name in [
+            "web.default.svc.cluster.local",
+            "WEB.Default.svc.cluster.local",
+            "web.default.svc",
+            "web.default",
+        ] {
+            let response = ask(&server, name, QTYPE_A).await.unwrap();
+            assert_eq!(rcode(&response), 0, "{}", name);
+            assert_eq!(
+                DnsServer::extract_a_record_ip(&response),
+                Some([10, 43, 0, 10]),
+                "{}",
+                name
+            );
+        }
+        let response = ask(&server, "db.prod", QTYPE_AAAA).await.unwrap();
+        assert_eq!(&response[response.len() - 4..], &[10, 43, 0, 20]);
+
+        // Known name, no records of that type.
+        let response = ask(&server, "web.default", 16).await.unwrap();
+        assert_eq!((rcode(&response), response[7]), (0, 0));
+
+        // Unknown names in the cluster domain are NXDOMAIN, not forwarded.
+        for name in [
+            "web.prod.svc.cluster.local",
+            "web.default.default.svc.cluster.local",
+        ] {
+            let response = ask(&server, name, QTYPE_A).await.unwrap();
+            assert_eq!(rcode(&response), RCODE_NXDOMAIN, "{}", name);
+        }
+
+        // Records follow the service list.
+        server
+            .update_records(&[service("db", "prod", "10.43.0.20")])
+            .await;
+        let response = ask(&server, "web.default.svc.cluster.local", QTYPE_A)
+            .await
+            .unwrap();
+        assert_eq!(rcode(&response), RCODE_NXDOMAIN);
+    }
+
+    #[tokio::test]
+    async fn test_upstream_forwarding_fallback() {
+        // Nothing listens on the first upstream.
+        let dead = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let live = fake_upstream([93, 184, 216, 34]).await;
+        let mut server = DnsServer::new("127.0.0.1:0".parse().unwrap());
+        server.set_upstreams(vec![dead, live]);
+        server
+            .update_records(&[service("web", "default", "10.43.0.10")])
+            .await;
+
+        let response = ask(&server, "example.com", QTYPE_A).await.unwrap();
+        assert_eq!(
+            DnsServer::extract_a_record_ip(&response),
+            Some([93, 184, 216, 34])
+        );
+        // A short form that is not a service goes upstream too.
+        let response = ask(&server, "example.default", QTYPE_A).await.unwrap();
+        assert_eq!(
+            DnsServer::extract_a_record_ip(&response),
+            Some([93, 184, 216, 34])
+        );
+        // DNS64 synthesis from the upstream's A record.
+        let response = ask(&server, "example.com", QTYPE_AAAA).await.unwrap();
+        assert_eq!(
+            &response[response.len() - 16..],
+            &[
+                0, 0x64, 0xff, 0x9b, 0, 0, 0, 0, 0, 0, 0, 0, 93, 184, 216, 34
+            ]
+        );
+
+        server.set_upstreams(vec![dead]);
+        assert_eq!(ask(&server, "example.com", QTYPE_A).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_headless_service_answers() {
+        let server = DnsServer::new("127.0.0.1:0".parse().unwrap());
+        let db = service("db", "prod", "None");
+        let eps = endpoints(
+            &db,
+            &[
+                ("db-0", "fd6b:3372::a"),
+                ("db-1", "fd6b:3372::b"),
+                ("db-2", "10.42.0.7"),
+            ],
+        );
+        server.update_records(std::slice::from_ref(&db)).await;
+        server.update_endpoints(&[db], &[eps]).await;
+
+        // Every ready address of the queried family, in rotating order.
+        let first = ask(&server, "db.prod.svc.cluster.local", QTYPE_AAAA)
+            .await
+            .unwrap();
+        let first = answers(&first);
+        assert_eq!(first.len(), 2);
+        assert!(first.iter().all(|(rtype, _)| *rtype == QTYPE_AAAA));
+        let mut ips: Vec<Vec<u8>> = first.iter().map(|(_, ip)| ip.clone()).collect();
+        ips.sort();
+        assert_eq!(ips, vec![ipv6("fd6b:3372::a"), ipv6("fd6b:3372::b")]);
+        let second = answers(&ask(&server, "db.prod", QTYPE_AAAA).await.unwrap());
+        assert_eq!(second.len(), 2);
+        assert_ne!(first[0], second[0], "answer order rotates per query");
+
+        let response = ask(&server, "db.prod.svc", QTYPE_A).await.unwrap();
+        assert_eq!(answers(&response), vec![(QTYPE_A, vec![10, 42, 0, 7])]);
+
+        // Each endpoint by its hostname.
+        let response = ask(&server, "db-1.db.prod.svc.cluster.local", QTYPE_AAAA)
+            .await
+            .unwrap();
+        assert_eq!(answers(&response), vec![(QTYPE_AAAA, ipv6("fd6b:3372::b"))]);
+        let response = ask(&server, "db-1.db.prod.svc.cluster.local", QTYPE_A)
+            .await
+            .unwrap();
+        assert_eq!((rcode(&response), answers(&response).len()), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_srv_records() {
+        let server = DnsServer::new("127.0.0.1:0".parse().unwrap());
+        let db = service("db", "prod", "None");
+        let web = service("web", "default", "10.43.0.10");
+        let db_eps = endpoints(&db, &[("db-0", "fd6b:3372::a"), ("db-1", "fd6b:3372::b")]);
+        let web_eps = endpoints(&web, &[("web-0", "fd6b:3372::c")]);
+        server.update_records(&[db.clone(), web.clone()]).await;
+        server
+            .update_endpoints(&[db, web], &[db_eps, web_eps])
+            .await;
+
+        // Headless: one target per pod, on the pod's port.
+        let response = ask(&server, "_http._tcp.db.prod.svc.cluster.local", QTYPE_SRV)
+            .await
+            .unwrap();
+        let mut srv: Vec<(u16, u16, u16, String)> = answers(&response)
+            .into_iter()
+            .map(|(rtype, d)| {
+                assert_eq!(rtype, QTYPE_SRV);
+                (
+                    u16::from_be_bytes([d[0], d[1]]),
+                    u16::from_be_bytes([d[2], d[3]]),
+                    u16::from_be_bytes([d[4], d[5]]),
+                    DnsServer::parse_dns_name(&d, 6).unwrap(),
+                )
+            })
+            .collect();
+        srv.sort();
+        assert_eq!(
+            srv,
+            vec![
+                (0, 50, 8080, "db-0.db.prod.svc.cluster.local".to_string()),
+                (0, 50, 8080, "db-1.db.prod.svc.cluster.local".to_string()),
+            ]
+        );
+
+        // ClusterIP: the service name on the service port.
+        let response = ask(
+            &server,
+            "_http._tcp.web.default.svc.cluster.local",
+            QTYPE_SRV,
+        )
+        .await
+        .unwrap();
+        let srv = answers(&response);
+        assert_eq!(srv.len(), 1);
+        assert_eq!(&srv[0].1[..6], &[0, 0, 0, 100, 0, 80]);
+        assert_eq!(
+            DnsServer::parse_dns_name(&srv[0].1, 6).as_deref(),
+            Some("web.default.svc.cluster.local")
+        );
+
+        // Known SRV name, other type: NODATA. Unknown port: NXDOMAIN.
+        let response = ask(&server, "_http._tcp.web.default.svc.cluster.local", QTYPE_A)
+            .await
+            .unwrap();
+        assert_eq!((rcode(&response), answers(&response).len()), (0, 0));
+        let response = ask(
+            &server,
+            "_grpc._tcp.web.default.svc.cluster.local",
+            QTYPE_SRV,
+        )
+        .await
+        .unwrap();
+        assert_eq!(rcode(&response), RCODE_NXDOMAIN);
+    }
+
+    #[tokio::test]
+    async fn test_headless_without_endpoints() {
+        let server = DnsServer::new("127.0.0.1:0".parse().unwrap());
+        let db = service("db", "prod", "None");
+        server
+            .update_endpoints(std::slice::from_ref(&db), &[endpoints(&db, &[])])
+            .await;
+
+        // The service exists but has no ready pods: NODATA, not NXDOMAIN.
+        for (name, qtype) in [
+            ("db.prod.svc.cluster.local", QTYPE_AAAA),
+            ("db.prod", QTYPE_A),
+            ("_http._tcp.db.prod.svc.cluster.local", QTYPE_SRV),
+        ] {
+            let response = ask(&server, name, qtype).await.unwrap();
+            assert_eq!(
+                (rcode(&response), answers(&response).len()),
+                (RCODE_NOERROR, 0),
+                "{}",
+                name
+            );
+        }
+        for name in [
+            "cache.prod.svc.cluster.local",
+            "db-0.db.prod.svc.cluster.local",
+        ] {
+            let response = ask(&server, name, QTYPE_AAAA).await.unwrap();
+            assert_eq!(rcode(&response), RCODE_NXDOMAIN, "{}", name);
+        }
+
+        // Without endpoints at all the name still exists.
+        server.update_endpoints(&[db], &[]).await;
+        let response = ask(&server, "db.prod", QTYPE_AAAA).await.unwrap();
+        assert_eq!(rcode(&response), RCODE_NOERROR);
+    }
+}
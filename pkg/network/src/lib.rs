@@ -1,5 +1,6 @@
 pub mod cni;
 pub mod dns;
+pub mod resolv;
 pub mod wireguard;
 
 #[cfg(target_os = "linux")]
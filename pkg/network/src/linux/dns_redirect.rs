@@ -0,0 +1,113 @@
+//! NAT rules that send pod DNS traffic on port 53 to the agent's DNS port.
+//!
+//! Pods and VM guests always use port 53 (resolv.conf has no port field), but
+//! the agent's DNS server listens on an unprivileged port. When the agent
+//! cannot bind the DNS VIP on port 53 itself, DNAT forwards it to the VIP on
+//! the DNS port; VM guests on Linux reach the link-local DNS proxy address,
+//! which is redirected to the DNS port on whichever host address received it.
+//!
+//! Rules are checked with `-C` before being added, so calls are idempotent.
+
+use anyhow::Result;
+use pkg_constants::network::{DNS_PROXY_IPV4, DNS_VIP};
+use tracing::{info, warn};
+
+/// `(binary, rule)` pairs for the `nat` table's `PREROUTING` chain. The VIP
+/// rule is only needed when the agent could not listen on `[DNS_VIP]:53`.
+pub fn redirect_rules(dns_port: u16, redirect_vip: bool) -> Vec<(&'static str, Vec<String>)> {
+    let mut rules = Vec::new();
+    if redirect_vip {
+        rules.push((
+            "ip6tables",
+            vec![
+                "-d".to_string(),
+                format!("{}/128", DNS_VIP),
+                "-p".to_string(),
+                "udp".to_string(),
+                "--dport".to_string(),
+                "53".to_string(),
+                "-j".to_string(),
+                "DNAT".to_string(),
+                "--to-destination".to_string(),
+                format!("[{}]:{}", DNS_VIP, dns_port),
+            ],
+        ));
+    }
+    rules.push((
+        "iptables",
+        vec![
+            "-d".to_string(),
+            format!("{}/32", DNS_PROXY_IPV4),
+            "-p".to_string(),
+            "udp".to_string(),
+            "--dport".to_string(),
+            "53".to_string(),
+            "-j".to_string(),
+            "REDIRECT".to_string(),
+            "--to-ports".to_string(),
+            dns_port.to_string(),
+        ],
+    ));
+    rules
+}
+
+/// Install [`redirect_rules`]. Failures are logged per rule; the error is
+/// returned only if no rule could be installed.
+pub async fn ensure_dns_redirect(dns_port: u16, redirect_vip: bool) -> Result<()> {
+    let mut installed = 0;
+    let rules = redirect_rules(dns_port, redirect_vip);
+    for (binary, rule) in &rules {
+        let run = |op: &'static str| {
+            tokio::process::Command::new(binary)
+                .args(["-t", "nat", op, "PREROUTING"])
+                .args(rule)
+                .output()
+        };
+        if run("-C").await.is_ok_and(|o| o.status.success()) {
+            installed += 1;
+            continue;
+        }
+        match run("-A").await {
+            Ok(o) if o.status.success() => {
+                info!("[dns] {} {}", binary, rule.join(" "));
+                installed += 1;
+            }
+            Ok(o) => warn!(
+                "[dns] {} redirect failed: {} (may need root)",
+                binary,
+                String::from_utf8_lossy(&o.stderr).trim()
+            ),
+            Err(e) => warn!("[dns] {} redirect failed: {}", binary, e),
+        }
+    }
+    if installed == 0 {
+        anyhow::bail!("no DNS redirect rule could be installed");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redirect_rules() {
+        let rules = redirect_rules(10053, true);
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].0, "ip6tables");
+        assert_eq!(
+            rules[0].1.join(" "),
+            "-d fd6b:3372::53/128 -p udp --dport 53 -j DNAT --to-destination [fd6b:3372::53]:10053"
+        );
+        assert_eq!(rules[1].0, "iptables");
+        assert_eq!(
+            rules[1].1.join(" "),
+            "-d 169.254.0.53/32 -p udp --dport 53 -j REDIRECT --to-ports 10053"
+        );
+
+        // The agent owns the VIP on port 53: only VM guests are redirected.
+        let rules = redirect_rules(10053, false);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].0, "iptables");
+    }
+}
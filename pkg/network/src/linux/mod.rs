@@ -1,2 +1,4 @@
 pub mod bridge;
+pub mod dns_redirect;
 pub mod netns;
+pub mod veth;
@@ -8,7 +8,7 @@
 
 use anyhow::Result;
 use pkg_constants::network::{
-    BRIDGE_GATEWAY_IPV6, DNS_NDOTS, DNS_VIP, GUEST_IFACE, NETKIT_HOST_PREFIX, NETKIT_PEER_PREFIX,
+    BRIDGE_GATEWAY_IPV6, DNS_VIP, GUEST_IFACE, NETKIT_HOST_PREFIX, NETKIT_PEER_PREFIX,
     POD_IPV4_GATEWAY,
 };
 use tracing::{info, warn};
@@ -40,7 +40,7 @@ impl PodNetworkConfig {
 }
 
 /// Run an `ip` command, returning an error on failure (with stderr context).
-async fn run_ip(args: &[&str]) -> Result<()> {
+pub(crate) async fn run_ip(args: &[&str]) -> Result<()> {
     let output = tokio::process::Command::new("ip")
         .args(args)
         .output()
@@ -53,7 +53,7 @@ async fn run_ip(args: &[&str]) -> Result<()> {
 }
 
 /// Run a command inside the container's network namespace via nsenter.
-async fn nsenter_run(pid: u32, args: &[&str]) -> Result<()> {
+pub(crate) async fn nsenter_run(pid: u32, args: &[&str]) -> Result<()> {
     let pid_str = pid.to_string();
     let mut cmd_args = vec!["-t", &pid_str, "-n", "--"];
     cmd_args.extend_from_slice(args);
@@ -74,29 +74,6 @@ async fn nsenter_run(pid: u32, args: &[&str]) -> Result<()> {
     Ok(())
 }
 
-/// Run a command inside the container's network + mount namespaces via nsenter.
-/// Needed for operations that write to the container's filesystem (e.g. /etc/resolv.conf).
-async fn nsenter_run_with_mount(pid: u32, args: &[&str]) -> Result<()> {
-    let pid_str = pid.to_string();
-    let mut cmd_args = vec!["-t", &pid_str, "-n", "-m", "--"];
-    cmd_args.extend_from_slice(args);
-
-    let output = tokio::process::Command::new("nsenter")
-        .args(&cmd_args)
-        .output()
-        .await?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!(
-            "nsenter -t {} -n -m -- {} failed: {}",
-            pid,
-            args.join(" "),
-            stderr.trim()
-        );
-    }
-    Ok(())
-}
-
 /// Create netkit pair (L2 mode), move peer into container netns, configure
 /// host-side gateway + host route, and set up IPv6/IPv4 inside the container.
 pub async fn setup_pod_network(config: &PodNetworkConfig) -> Result<()> {
@@ -230,25 +207,8 @@ pub async fn setup_pod_network(config: &PodNetworkConfig) -> Result<()> {
         );
     }
 
-    // 9. Write /etc/resolv.conf pointing to the k3rs DNS VIP (on k3rs0 dummy device)
-    //    Uses mount namespace (-m) so we write to the container's filesystem, not the host's.
-    let resolv_content = format!("nameserver {}\noptions ndots:{}\n", DNS_VIP, DNS_NDOTS);
-    if let Err(e) = nsenter_run_with_mount(
-        pid,
-        &[
-            "sh",
-            "-c",
-            &format!("echo '{}' > /etc/resolv.conf", resolv_content.trim()),
-        ],
-    )
-    .await
-    {
-        warn!(
-            "[netns:{}] Failed to write resolv.conf: {}",
-            &config.pod_id[..8.min(config.pod_id.len())],
-            e
-        );
-    }
+    // /etc/resolv.conf pointing at the DNS VIP is written into the rootfs
+    // by the container runtime before the container starts.
 
     info!(
         "[netns:{}] Pod network configured: eth0={} + {}, dns={}",
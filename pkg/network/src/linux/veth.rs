@@ -0,0 +1,182 @@
+//! Bridge networking for pods — an alternative to the netkit / Ghost IPv6
+//! path for nodes that run without the k3rs-vpc daemon.
+//!
+//! The node's pod CIDR lives on the `k3rs-br0` bridge, whose address is the
+//! pods' default gateway. Each pod gets a veth pair: the host end is attached
+//! to the bridge, the peer is moved into the container netns as `eth0` with
+//! the pod's IPv4 address. Egress leaving the node is masqueraded.
+
+use anyhow::Result;
+use pkg_constants::network::{GUEST_IFACE, POD_BRIDGE_NAME, VETH_HOST_PREFIX, VETH_PEER_PREFIX};
+use tracing::{info, warn};
+
+use super::netns::{nsenter_run, run_ip};
+
+/// Veth configuration for a single pod.
+pub struct VethConfig {
+    /// Pod identifier (used to derive veth device names).
+    pub pod_id: String,
+    /// Address from the node's pod CIDR.
+    pub pod_ip: String,
+    /// Prefix length of the node's pod CIDR.
+    pub prefix_len: u8,
+    /// Bridge address, used as the pod's default gateway.
+    pub gateway: String,
+    /// PID of the container's init process.
+    pub container_pid: u32,
+}
+
+/// Host-side veth name for a pod.
+pub fn veth_host_name(pod_id: &str) -> String {
+    format!("{}{}", VETH_HOST_PREFIX, &pod_id[..8.min(pod_id.len())])
+}
+
+fn veth_peer_name(pod_id: &str) -> String {
+    format!("{}{}", VETH_PEER_PREFIX, &pod_id[..8.min(pod_id.len())])
+}
+
+/// Create the pod bridge with `gateway/prefix_len` on it and enable IPv4
+/// forwarding. Idempotent.
+pub async fn ensure_pod_bridge(gateway: &str, prefix_len: u8) -> Result<()> {
+    if let Err(e) = run_ip(&["link", "add", POD_BRIDGE_NAME, "type", "bridge"]).await
+        && !e.to_string().contains("File exists")
+    {
+        return Err(e);
+    }
+    let gateway_cidr = format!("{}/{}", gateway, prefix_len);
+    if let Err(e) = run_ip(&["addr", "add", &gateway_cidr, "dev", POD_BRIDGE_NAME]).await
+        && !e.to_string().contains("File exists")
+    {
+        return Err(e);
+    }
+    run_ip(&["link", "set", POD_BRIDGE_NAME, "up"]).await?;
+
+    if let Err(e) = tokio::fs::write("/proc/sys/net/ipv4/ip_forward", "1").await {
+        warn!(
+            "[veth] Failed to enable IPv4 forwarding: {} (may need root)",
+            e
+        );
+    }
+    info!("[veth] {} ready ({})", POD_BRIDGE_NAME, gateway_cidr);
+    Ok(())
+}
+
+/// `nat` `POSTROUTING` rule masquerading pod traffic that leaves the node.
+/// Traffic between pods on the bridge keeps its source address.
+pub fn masquerade_rule(pod_cidr: &str) -> Vec<String> {
+    [
+        "-s",
+        pod_cidr,
+        "!",
+        "-o",
+        POD_BRIDGE_NAME,
+        "-j",
+        "MASQUERADE",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
+/// Install [`masquerade_rule`], checking with `-C` first so it is added once.
+pub async fn ensure_masquerade(pod_cidr: &str) -> Result<()> {
+    let rule = masquerade_rule(pod_cidr);
+    let run = |op: &'static str| {
+        tokio::process::Command::new("iptables")
+            .args(["-t", "nat", op, "POSTROUTING"])
+            .args(&rule)
+            .output()
+    };
+    if run("-C").await.is_ok_and(|o| o.status.success()) {
+        return Ok(());
+    }
+    let output = run("-A").await?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "iptables masquerade for {} failed: {}",
+            pod_cidr,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    info!("[veth] iptables -t nat POSTROUTING {}", rule.join(" "));
+    Ok(())
+}
+
+/// Create a veth pair, attach the host end to the pod bridge and configure
+/// the peer as `eth0` inside the container netns with the pod's address and
+/// a default route via the bridge.
+pub async fn setup_pod_veth(config: &VethConfig) -> Result<()> {
+    let host = veth_host_name(&config.pod_id);
+    let peer = veth_peer_name(&config.pod_id);
+    let pid = config.container_pid;
+
+    info!(
+        "[veth:{}] Setting up pod network: veth={}, ip={}, pid={}",
+        &config.pod_id[..8.min(config.pod_id.len())],
+        host,
+        config.pod_ip,
+        pid
+    );
+
+    // 1. veth pair; the host end joins the bridge
+    run_ip(&["link", "add", &host, "type", "veth", "peer", "name", &peer]).await?;
+    run_ip(&["link", "set", &host, "master", POD_BRIDGE_NAME]).await?;
+    run_ip(&["link", "set", &host, "up"]).await?;
+
+    // 2. Move the peer into the container netns
+    run_ip(&["link", "set", &peer, "netns", &pid.to_string()]).await?;
+
+    // 3. Inside netns: rename peer → eth0, assign the pod IP, bring links up
+    nsenter_run(pid, &["ip", "link", "set", &peer, "name", GUEST_IFACE]).await?;
+    let pod_cidr = format!("{}/{}", config.pod_ip, config.prefix_len);
+    nsenter_run(pid, &["ip", "addr", "add", &pod_cidr, "dev", GUEST_IFACE]).await?;
+    nsenter_run(pid, &["ip", "link", "set", "lo", "up"]).await?;
+    nsenter_run(pid, &["ip", "link", "set", GUEST_IFACE, "up"]).await?;
+
+    // 4. Default route via the bridge
+    nsenter_run(
+        pid,
+        &["ip", "route", "add", "default", "via", &config.gateway],
+    )
+    .await?;
+
+    info!(
+        "[veth:{}] Pod network configured: eth0={}, gateway={}",
+        &config.pod_id[..8.min(config.pod_id.len())],
+        pod_cidr,
+        config.gateway
+    );
+    Ok(())
+}
+
+/// Delete the host end of a pod's veth pair; the kernel removes the peer.
+pub async fn teardown_pod_veth(pod_id: &str) {
+    let host = veth_host_name(pod_id);
+    let _ = tokio::process::Command::new("ip")
+        .args(["link", "delete", &host])
+        .output()
+        .await;
+    info!("[veth:{}] {} removed", &pod_id[..8.min(pod_id.len())], host);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masquerade_rule() {
+        assert_eq!(
+            masquerade_rule("10.42.1.0/24").join(" "),
+            "-s 10.42.1.0/24 ! -o k3rs-br0 -j MASQUERADE"
+        );
+    }
+
+    #[test]
+    fn test_device_names_fit_ifnamsiz() {
+        let id = "0b7c6f1e-8c1d-4e55-9d47-0a6f1d2c3b4a";
+        assert_eq!(veth_host_name(id), "veth-0b7c6f1e");
+        assert_eq!(veth_peer_name(id), "vtmp-0b7c6f1e");
+        // Linux interface names are at most 15 bytes.
+        assert!(veth_host_name(id).len() <= 15);
+    }
+}
@@ -0,0 +1,120 @@
+//! `/etc/resolv.conf` handling: reading the host's upstream resolvers and
+//! rendering the file pods get.
+
+use std::net::{IpAddr, SocketAddr};
+
+use pkg_constants::dns::{DEFAULT_UPSTREAM_DNS, DNS_DOMAIN_SUFFIX};
+use pkg_constants::network::{DNS_NDOTS, DNS_PROXY_IPV4, DNS_VIP};
+
+/// Host resolver configuration the agent forwards external queries to.
+pub const HOST_RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// Nameserver addresses listed in a resolv.conf, in order.
+pub fn parse_nameservers(contents: &str) -> Vec<IpAddr> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => fields.next(),
+                _ => None,
+            }
+        })
+        // Drop scoped IPv6 zones (`fe80::1%eth0`); the zone is not routable here.
+        .filter_map(|addr| addr.split('%').next()?.parse().ok())
+        .collect()
+}
+
+/// Upstream resolvers for the embedded DNS server, taken from the host's
+/// resolv.conf. The pod nameserver addresses are skipped so that a host
+/// pointed at the cluster DNS does not forward queries back to itself. Falls
+/// back to [`DEFAULT_UPSTREAM_DNS`] when nothing usable is listed.
+pub fn host_upstreams(contents: &str) -> Vec<SocketAddr> {
+    let own: [IpAddr; 2] = [
+        DNS_VIP.parse().expect("invalid DNS_VIP"),
+        DNS_PROXY_IPV4.parse().expect("invalid DNS_PROXY_IPV4"),
+    ];
+    let upstreams: Vec<SocketAddr> = parse_nameservers(contents)
+        .into_iter()
+        .filter(|ip| !own.contains(ip))
+        .map(|ip| SocketAddr::new(ip, 53))
+        .collect();
+    if upstreams.is_empty() {
+        vec![
+            DEFAULT_UPSTREAM_DNS
+                .parse()
+                .expect("invalid DEFAULT_UPSTREAM_DNS"),
+        ]
+    } else {
+        upstreams
+    }
+}
+
+/// Read [`host_upstreams`] from [`HOST_RESOLV_CONF`].
+pub fn load_host_upstreams() -> Vec<SocketAddr> {
+    host_upstreams(&std::fs::read_to_string(HOST_RESOLV_CONF).unwrap_or_default())
+}
+
+/// The resolv.conf of a pod in `namespace`: the cluster DNS at `nameserver`,
+/// with search domains so `<svc>` and `<svc>.<ns>` resolve like in Kubernetes.
+pub fn pod_resolv_conf(nameserver: &str, namespace: &str) -> String {
+    let cluster_domain = DNS_DOMAIN_SUFFIX
+        .strip_prefix("svc.")
+        .unwrap_or(DNS_DOMAIN_SUFFIX);
+    format!(
+        "nameserver {}\nsearch {}.{} {} {}\noptions ndots:{}\n",
+        nameserver, namespace, DNS_DOMAIN_SUFFIX, DNS_DOMAIN_SUFFIX, cluster_domain, DNS_NDOTS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nameservers() {
+        let conf = "# generated by NetworkManager\n\
+                    search lan\n\
+                    nameserver 192.168.1.1\n\
+                    nameserver   fe80::1%eth0\n\
+                    nameserver not-an-ip\n\
+                    options edns0\n\
+                    nameserver 2001:4860:4860::8888\n";
+        assert_eq!(
+            parse_nameservers(conf),
+            vec![
+                "192.168.1.1".parse::<IpAddr>().unwrap(),
+                "fe80::1".parse().unwrap(),
+                "2001:4860:4860::8888".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_host_upstreams_skip_cluster_dns() {
+        let conf = format!(
+            "nameserver {}\nnameserver 127.0.0.53\nnameserver {}\n",
+            DNS_VIP, DNS_PROXY_IPV4
+        );
+        assert_eq!(
+            host_upstreams(&conf),
+            vec!["127.0.0.53:53".parse::<SocketAddr>().unwrap()]
+        );
+        // Nothing usable: the default upstream.
+        assert_eq!(
+            host_upstreams(&format!("nameserver {}\n", DNS_VIP)),
+            vec![DEFAULT_UPSTREAM_DNS.parse::<SocketAddr>().unwrap()]
+        );
+        assert_eq!(host_upstreams("").len(), 1);
+    }
+
+    #[test]
+    fn test_pod_resolv_conf() {
+        assert_eq!(
+            pod_resolv_conf("fd6b:3372::53", "prod"),
+            "nameserver fd6b:3372::53\n\
+             search prod.svc.cluster.local svc.cluster.local cluster.local\n\
+             options ndots:5\n"
+        );
+    }
+}
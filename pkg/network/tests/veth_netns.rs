@@ -0,0 +1,78 @@
+//! Bridge + veth setup against a real network namespace.
+//!
+//! Needs root on Linux with `ip`, `nsenter`, `unshare` and `iptables`, and
+//! creates the `k3rs-br0` bridge on the host. Run with:
+//!   sudo -E cargo test -p pkg-network --test veth_netns -- --ignored
+
+#![cfg(target_os = "linux")]
+
+use pkg_network::cni::PodNetwork;
+use pkg_network::linux::veth::{self, VethConfig};
+
+/// Output of `ip <args>` run inside the netns of `pid`.
+async fn ip_in(pid: u32, args: &[&str]) -> String {
+    let output = tokio::process::Command::new("nsenter")
+        .args(["-t", &pid.to_string(), "-n", "--", "ip"])
+        .args(args)
+        .output()
+        .await
+        .unwrap();
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[tokio::test]
+#[ignore = "requires root on Linux"]
+async fn test_pod_veth_in_netns() {
+    let ipam = PodNetwork::new("10.42.250.0/24").unwrap();
+    veth::ensure_pod_bridge(&ipam.gateway(), ipam.prefix_len())
+        .await
+        .unwrap();
+    veth::ensure_masquerade(&ipam.cidr()).await.unwrap();
+
+    // A process in its own network namespace stands in for a container.
+    let mut sleeper = tokio::process::Command::new("unshare")
+        .args(["-n", "sleep", "30"])
+        .spawn()
+        .unwrap();
+    let pid = sleeper.id().unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let pod_id = "vethtest-0000";
+    let pod_ip = ipam.allocate_ip(pod_id).await.unwrap();
+    veth::setup_pod_veth(&VethConfig {
+        pod_id: pod_id.to_string(),
+        pod_ip: pod_ip.clone(),
+        prefix_len: ipam.prefix_len(),
+        gateway: ipam.gateway(),
+        container_pid: pid,
+    })
+    .await
+    .unwrap();
+
+    let addrs = ip_in(pid, &["-4", "addr", "show", "eth0"]).await;
+    assert!(addrs.contains(&format!("{}/24", pod_ip)), "{}", addrs);
+    let routes = ip_in(pid, &["route"]).await;
+    assert!(
+        routes.contains(&format!("default via {}", ipam.gateway())),
+        "{}",
+        routes
+    );
+    let ping = tokio::process::Command::new("nsenter")
+        .args(["-t", &pid.to_string(), "-n", "--"])
+        .args(["ping", "-c", "1", "-W", "2", &ipam.gateway()])
+        .status()
+        .await
+        .unwrap();
+    assert!(ping.success(), "pod cannot reach its gateway");
+
+    veth::teardown_pod_veth(pod_id).await;
+    ipam.release_ip(pod_id).await;
+    let links = tokio::process::Command::new("ip")
+        .args(["link", "show", &veth::veth_host_name(pod_id)])
+        .status()
+        .await
+        .unwrap();
+    assert!(!links.success(), "host veth still present");
+
+    let _ = sleeper.kill().await;
+}
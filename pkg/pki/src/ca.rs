@@ -1,6 +1,16 @@
-use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair, KeyUsagePurpose};
+use chrono::{DateTime, Duration, Utc};
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+    KeyUsagePurpose,
+};
+use rustls::pki_types::{ServerName, UnixTime};
+use rustls::server::{ParsedCertificate, WebPkiClientVerifier};
+use rustls::{CertificateError, RootCertStore};
+use std::sync::Arc;
 use tracing::info;
 
+use crate::cert;
+
 /// Internal cluster Certificate Authority.
 /// Generates a self-signed root CA and issues per-node TLS certificates.
 pub struct ClusterCA {
@@ -37,10 +47,28 @@ impl ClusterCA {
         })
     }
 
-    /// Issue a TLS certificate for a node, signed by this CA.
-    /// Returns `(cert_pem, private_key_pem)`.
-    pub fn issue_node_cert(&self, node_name: &str) -> anyhow::Result<(String, String)> {
-        info!("Issuing certificate for node: {}", node_name);
+    /// Issue a TLS certificate for a node, signed by this CA and valid for
+    /// `validity` from now. Returns `(cert_pem, private_key_pem)`.
+    pub fn issue_node_cert(
+        &self,
+        node_name: &str,
+        validity: Duration,
+    ) -> anyhow::Result<(String, String)> {
+        let now = Utc::now();
+        self.issue_node_cert_between(node_name, now, now + validity)
+    }
+
+    /// Issue a node certificate valid from `not_before` until `not_after`.
+    pub fn issue_node_cert_between(
+        &self,
+        node_name: &str,
+        not_before: DateTime<Utc>,
+        not_after: DateTime<Utc>,
+    ) -> anyhow::Result<(String, String)> {
+        info!(
+            "Issuing certificate for node: {} (valid until {})",
+            node_name, not_after
+        );
 
         let mut params = CertificateParams::default();
         params
@@ -53,6 +81,12 @@ impl ClusterCA {
         params
             .subject_alt_names
             .push(rcgen::SanType::DnsName(node_name.try_into()?));
+        params.extended_key_usages = vec![
+            ExtendedKeyUsagePurpose::ClientAuth,
+            ExtendedKeyUsagePurpose::ServerAuth,
+        ];
+        params.not_before = to_offset(not_before)?;
+        params.not_after = to_offset(not_after)?;
 
         let node_key = KeyPair::generate()?;
         let node_cert = params.signed_by(&node_key, &self.ca_cert, &self.ca_key_pair)?;
@@ -60,8 +94,94 @@ impl ClusterCA {
         Ok((node_cert.pem(), node_key.serialize_pem()))
     }
 
+    /// Check that `cert_pem` is a certificate this CA issued to `node_name`
+    /// and that it is valid at `now`.
+    pub fn verify_node_cert(
+        &self,
+        cert_pem: &str,
+        node_name: &str,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let der = cert::parse_pem(cert_pem)?;
+        let mut roots = RootCertStore::empty();
+        roots.add(self.ca_cert.der().clone())?;
+        let verifier = WebPkiClientVerifier::builder_with_provider(
+            Arc::new(roots),
+            Arc::new(rustls::crypto::aws_lc_rs::default_provider()),
+        )
+        .build()?;
+        let at = UnixTime::since_unix_epoch(std::time::Duration::from_secs(
+            now.timestamp().max(0) as u64
+        ));
+        if let Err(e) = verifier.verify_client_cert(&der, &[], at) {
+            return Err(match e {
+                rustls::Error::InvalidCertificate(
+                    CertificateError::Expired | CertificateError::ExpiredContext { .. },
+                ) => match cert::not_after(cert_pem) {
+                    Ok(not_after) => anyhow::anyhow!("certificate expired at {}", not_after),
+                    Err(_) => anyhow::anyhow!("certificate has expired"),
+                },
+                // Another CA with the same name shows as a bad signature.
+                rustls::Error::InvalidCertificate(
+                    CertificateError::UnknownIssuer | CertificateError::BadSignature,
+                ) => {
+                    anyhow::anyhow!("certificate was not issued by this cluster's CA")
+                }
+                e => anyhow::anyhow!("invalid certificate: {}", e),
+            });
+        }
+
+        let parsed = ParsedCertificate::try_from(&der)?;
+        let name = ServerName::try_from(node_name)
+            .map_err(|_| anyhow::anyhow!("invalid node name '{}'", node_name))?;
+        rustls::client::verify_server_name(&parsed, &name)
+            .map_err(|_| anyhow::anyhow!("certificate was not issued to node '{}'", node_name))
+    }
+
     /// Return the CA certificate PEM so agents can verify server identity.
     pub fn ca_cert_pem(&self) -> &str {
         &self.ca_cert_pem
     }
 }
+
+fn to_offset(at: DateTime<Utc>) -> anyhow::Result<time::OffsetDateTime> {
+    Ok(time::OffsetDateTime::from_unix_timestamp(at.timestamp())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_node_cert() {
+        let ca = ClusterCA::new().unwrap();
+        let now = Utc::now();
+        let (cert_pem, key_pem) = ca.issue_node_cert("node-a", Duration::days(2)).unwrap();
+        ca.verify_node_cert(&cert_pem, "node-a", now).unwrap();
+        let not_after = cert::not_after(&cert_pem).unwrap();
+        assert!((not_after - (now + Duration::days(2))).num_seconds().abs() <= 1);
+
+        let err = ca.verify_node_cert(&cert_pem, "node-b", now).unwrap_err();
+        assert!(err.to_string().contains("not issued to node 'node-b'"));
+        let err = ca
+            .verify_node_cert(&cert_pem, "node-a", now + Duration::days(3))
+            .unwrap_err();
+        assert!(err.to_string().contains("expired"));
+
+        // Another cluster's CA (e.g. from before a server restart).
+        let other = ClusterCA::new().unwrap();
+        let err = other
+            .verify_node_cert(&cert_pem, "node-a", now)
+            .unwrap_err();
+        assert!(err.to_string().contains("not issued by this cluster's CA"));
+
+        // Signatures made with the node key verify against the certificate.
+        let message = cert::renewal_message("node-a", now);
+        let signature = cert::sign(&key_pem, &message).unwrap();
+        assert!(cert::verify_signature(&cert_pem, &message, &signature).unwrap());
+        let later = cert::renewal_message("node-a", now + Duration::seconds(1));
+        assert!(!cert::verify_signature(&cert_pem, &later, &signature).unwrap());
+        let (other_cert, _) = ca.issue_node_cert("node-a", Duration::days(2)).unwrap();
+        assert!(!cert::verify_signature(&other_cert, &message, &signature).unwrap());
+    }
+}
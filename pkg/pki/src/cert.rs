@@ -0,0 +1,166 @@
+//! Reading node certificates and proving possession of their keys.
+//!
+//! A node renews its certificate by signing [`renewal_message`] with its
+//! current key; the server checks the signature against the public key of
+//! the certificate the node presents.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rustls::SignatureScheme;
+use rustls::pki_types::CertificateDer;
+
+/// Schemes a node key may sign with, in order of preference.
+const SIGNATURE_SCHEMES: &[SignatureScheme] = &[
+    SignatureScheme::ECDSA_NISTP256_SHA256,
+    SignatureScheme::ECDSA_NISTP384_SHA384,
+    SignatureScheme::ED25519,
+    SignatureScheme::RSA_PSS_SHA256,
+];
+
+/// The first certificate in a PEM bundle.
+pub fn parse_pem(cert_pem: &str) -> anyhow::Result<CertificateDer<'static>> {
+    rustls_pemfile::certs(&mut cert_pem.as_bytes())
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("no certificate in PEM"))?
+        .map_err(|e| anyhow::anyhow!("invalid certificate PEM: {}", e))
+}
+
+/// When the certificate stops being valid.
+pub fn not_after(cert_pem: &str) -> anyhow::Result<DateTime<Utc>> {
+    let der = parse_pem(cert_pem)?;
+    Ok(tbs_fields(&der)?.1)
+}
+
+/// What a node signs to renew its certificate: bound to the node and to the
+/// time of the request, so a captured request cannot be replayed later or
+/// for another node.
+pub fn renewal_message(node_name: &str, timestamp: DateTime<Utc>) -> Vec<u8> {
+    format!(
+        "k3rs-certificate-renewal\n{}\n{}",
+        node_name,
+        timestamp.timestamp()
+    )
+    .into_bytes()
+}
+
+/// Sign `message` with the PEM private key; the signature is hex-encoded.
+pub fn sign(key_pem: &str, message: &[u8]) -> anyhow::Result<String> {
+    let key = rustls_pemfile::private_key(&mut key_pem.as_bytes())?
+        .ok_or_else(|| anyhow::anyhow!("no private key in PEM"))?;
+    let signer = rustls::crypto::aws_lc_rs::sign::any_supported_type(&key)?
+        .choose_scheme(SIGNATURE_SCHEMES)
+        .ok_or_else(|| anyhow::anyhow!("unsupported private key type"))?;
+    Ok(to_hex(&signer.sign(message)?))
+}
+
+/// Whether `signature` (hex) over `message` was made with the key of the
+/// certificate.
+pub fn verify_signature(cert_pem: &str, message: &[u8], signature: &str) -> anyhow::Result<bool> {
+    let der = parse_pem(cert_pem)?;
+    let (public_key, _) = tbs_fields(&der)?;
+    let Some(signature) = from_hex(signature) else {
+        return Ok(false);
+    };
+    let algorithms = rustls::crypto::aws_lc_rs::default_provider()
+        .signature_verification_algorithms
+        .all;
+    Ok(algorithms.iter().any(|alg| {
+        alg.verify_signature(public_key, message, &signature)
+            .is_ok()
+    }))
+}
+
+/// The subject public key and the end of the validity of a DER certificate.
+fn tbs_fields(der: &[u8]) -> anyhow::Result<(&[u8], DateTime<Utc>)> {
+    let (_, cert, _) = read_tlv(der, 0x30)?;
+    let (_, tbs, _) = read_tlv(cert, 0x30)?;
+    let mut rest = tbs;
+    // Optional explicit version.
+    if rest.first() == Some(&0xa0) {
+        rest = read_tlv(rest, 0xa0)?.2;
+    }
+    rest = read_tlv(rest, 0x02)?.2; // serial
+    rest = read_tlv(rest, 0x30)?.2; // signature algorithm
+    rest = read_tlv(rest, 0x30)?.2; // issuer
+    let (_, validity, after_validity) = read_tlv(rest, 0x30)?;
+    let validity = read_tlv(validity, 0)?.2; // not before
+    let (tag, not_after, _) = read_tlv(validity, 0)?;
+    let not_after = parse_time(tag, not_after)?;
+
+    rest = read_tlv(after_validity, 0x30)?.2; // subject
+    let (_, spki, _) = read_tlv(rest, 0x30)?;
+    let after_algorithm = read_tlv(spki, 0x30)?.2;
+    let (_, key_bits, _) = read_tlv(after_algorithm, 0x03)?;
+    // Skip the bit string's unused-bits byte.
+    let public_key = key_bits
+        .split_first()
+        .map(|(_, key)| key)
+        .ok_or_else(|| anyhow::anyhow!("empty subject public key"))?;
+    Ok((public_key, not_after))
+}
+
+/// Read one DER element, returning its tag, contents and the bytes after it.
+/// `expected` 0 accepts any tag.
+fn read_tlv(input: &[u8], expected: u8) -> anyhow::Result<(u8, &[u8], &[u8])> {
+    let malformed = || anyhow::anyhow!("malformed certificate");
+    let (&tag, rest) = input.split_first().ok_or_else(malformed)?;
+    if expected != 0 && tag != expected {
+        return Err(malformed());
+    }
+    let (&first, mut rest) = rest.split_first().ok_or_else(malformed)?;
+    let len = if first < 0x80 {
+        first as usize
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err(malformed());
+        }
+        let (bytes, tail) = rest.split_at(count);
+        rest = tail;
+        bytes.iter().fold(0usize, |len, &b| (len << 8) | b as usize)
+    };
+    if rest.len() < len {
+        return Err(malformed());
+    }
+    let (contents, rest) = rest.split_at(len);
+    Ok((tag, contents, rest))
+}
+
+/// A UTCTime (`YYMMDDHHMMSSZ`) or GeneralizedTime (`YYYYMMDDHHMMSSZ`).
+fn parse_time(tag: u8, bytes: &[u8]) -> anyhow::Result<DateTime<Utc>> {
+    let invalid = || anyhow::anyhow!("invalid certificate time");
+    let text = std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|t| t.strip_suffix('Z'))
+        .filter(|t| t.is_ascii())
+        .ok_or_else(invalid)?;
+    let (year, rest) = match tag {
+        0x17 if text.len() == 12 => {
+            let yy: i32 = text[..2].parse().map_err(|_| invalid())?;
+            (if yy < 50 { 2000 + yy } else { 1900 + yy }, &text[2..])
+        }
+        0x18 if text.len() == 14 => (text[..4].parse().map_err(|_| invalid())?, &text[4..]),
+        _ => return Err(invalid()),
+    };
+    let fields = (0..5)
+        .map(|i| rest[i * 2..i * 2 + 2].parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    NaiveDate::from_ymd_opt(year, fields[0], fields[1])
+        .and_then(|date| date.and_hms_opt(fields[2], fields[3], fields[4]))
+        .map(|t| t.and_utc())
+        .ok_or_else(invalid)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,227 @@
+//! ClientIP session affinity for the service proxy.
+//!
+//! Each (route, client IP) pair sticks to one backend until it has been idle
+//! for the service's affinity timeout. New clients are placed by hashing
+//! their IP over the available backends.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Upper bound on remembered clients across all routes. When full, the entry
+/// closest to expiry is dropped, so a scan from many source IPs cannot grow
+/// the table without bound.
+pub const MAX_AFFINITY_ENTRIES: usize = 65_536;
+
+type Key = (String, IpAddr);
+
+struct Entry {
+    backend: String,
+    expires: Instant,
+    seq: u64,
+}
+
+pub struct AffinityTable {
+    entries: HashMap<Key, Entry>,
+    /// Entries ordered by expiry (`seq` breaks ties), for sweeping and eviction.
+    by_expiry: BTreeMap<(Instant, u64), Key>,
+    next_seq: u64,
+    max_entries: usize,
+}
+
+impl Default for AffinityTable {
+    fn default() -> Self {
+        Self::new(MAX_AFFINITY_ENTRIES)
+    }
+}
+
+impl AffinityTable {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            by_expiry: BTreeMap::new(),
+            next_seq: 0,
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The backend `client` should use for `route`, out of `candidates`.
+    ///
+    /// Keeps the remembered backend while it is still a candidate, otherwise
+    /// picks one by hashing the client IP and remembers that instead. Either
+    /// way the entry's expiry moves to `now + timeout`.
+    pub fn pick(
+        &mut self,
+        route: &str,
+        client: IpAddr,
+        candidates: &[String],
+        timeout: Duration,
+        now: Instant,
+    ) -> Option<String> {
+        if candidates.is_empty() {
+            return None;
+        }
+        self.sweep(now);
+
+        let key = (route.to_string(), client);
+        let backend = match self.remove(&key) {
+            Some(entry) if candidates.contains(&entry.backend) => entry.backend,
+            _ => {
+                let mut hasher = DefaultHasher::new();
+                route.hash(&mut hasher);
+                client.hash(&mut hasher);
+                candidates[(hasher.finish() % candidates.len() as u64) as usize].clone()
+            }
+        };
+
+        while self.entries.len() >= self.max_entries {
+            let Some((_, oldest)) = self.by_expiry.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let expires = now + timeout;
+        self.by_expiry.insert((expires, seq), key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                backend: backend.clone(),
+                expires,
+                seq,
+            },
+        );
+        Some(backend)
+    }
+
+    /// Drop entries for routes `keep` rejects (services gone or no longer sticky).
+    pub fn retain_routes(&mut self, keep: impl Fn(&str) -> bool) {
+        self.entries.retain(|(route, _), _| keep(route));
+        self.by_expiry.retain(|_, (route, _)| keep(route));
+    }
+
+    /// Drop entries idle past their timeout.
+    pub fn sweep(&mut self, now: Instant) {
+        while let Some(entry) = self.by_expiry.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let key = entry.remove();
+            self.entries.remove(&key);
+        }
+    }
+
+    fn remove(&mut self, key: &Key) -> Option<Entry> {
+        let entry = self.entries.remove(key)?;
+        self.by_expiry.remove(&(entry.expires, entry.seq));
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([10, 0, 0, last])
+    }
+
+    fn backends(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_same_client_same_backend() {
+        let mut table = AffinityTable::default();
+        let all = backends(&["10.42.0.1:80", "10.42.0.2:80", "10.42.0.3:80"]);
+        let now = Instant::now();
+        let first = table.pick("svc", ip(1), &all, TIMEOUT, now).unwrap();
+        for i in 1..20 {
+            let at = now + Duration::from_secs(i);
+            assert_eq!(
+                table.pick("svc", ip(1), &all, TIMEOUT, at),
+                Some(first.clone())
+            );
+        }
+        assert_eq!(table.len(), 1);
+        assert_eq!(table.pick("svc", ip(1), &[], TIMEOUT, now), None);
+    }
+
+    #[test]
+    fn test_failover_when_backend_removed() {
+        let mut table = AffinityTable::default();
+        let all = backends(&["10.42.0.1:80", "10.42.0.2:80"]);
+        let now = Instant::now();
+        let first = table.pick("svc", ip(1), &all, TIMEOUT, now).unwrap();
+
+        let rest: Vec<String> = all.iter().filter(|b| **b != first).cloned().collect();
+        let second = table.pick("svc", ip(1), &rest, TIMEOUT, now).unwrap();
+        assert_ne!(first, second);
+
+        // The new mapping sticks even once the old backend is back.
+        assert_eq!(
+            table.pick("svc", ip(1), &all, TIMEOUT, now),
+            Some(second.clone())
+        );
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn test_entries_expire_when_idle() {
+        let mut table = AffinityTable::default();
+        let all = backends(&["10.42.0.1:80", "10.42.0.2:80"]);
+        let now = Instant::now();
+        let hashed = table.pick("svc", ip(1), &all, TIMEOUT, now).unwrap();
+        let other: Vec<String> = all.iter().filter(|b| **b != hashed).cloned().collect();
+        table.pick("svc", ip(1), &other, TIMEOUT, now).unwrap();
+
+        // Use keeps the entry alive...
+        let later = now + TIMEOUT - Duration::from_secs(1);
+        assert_eq!(
+            table.pick("svc", ip(1), &all, TIMEOUT, later),
+            Some(other[0].clone())
+        );
+        // ...until it has been idle for the whole timeout.
+        let idle = later + TIMEOUT;
+        table.sweep(idle);
+        assert!(table.is_empty());
+        assert_eq!(table.pick("svc", ip(1), &all, TIMEOUT, idle), Some(hashed));
+    }
+
+    #[test]
+    fn test_table_is_bounded() {
+        let mut table = AffinityTable::new(2);
+        let all = backends(&["10.42.0.1:80"]);
+        let now = Instant::now();
+        for i in 0..10u8 {
+            table.pick(
+                "svc",
+                ip(i),
+                &all,
+                TIMEOUT,
+                now + Duration::from_secs(i.into()),
+            );
+            assert!(table.len() <= 2);
+        }
+        // The most recent clients are the ones kept.
+        assert_eq!(table.len(), 2);
+        assert!(table.entries.contains_key(&("svc".to_string(), ip(9))));
+        assert!(table.entries.contains_key(&("svc".to_string(), ip(8))));
+
+        table.retain_routes(|route| route != "svc");
+        assert!(table.is_empty());
+    }
+}
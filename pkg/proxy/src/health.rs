@@ -0,0 +1,318 @@
+//! Active health checking of service backends.
+//!
+//! The service proxy probes every endpoint it routes to and stops sending
+//! traffic to ones that fail `failure_threshold` probes in a row, without
+//! waiting for the control plane to drop them from the Endpoints. They come
+//! back after `success_threshold` good probes.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{info, warn};
+
+/// Probe timing and thresholds.
+#[derive(Debug, Clone, Copy)]
+pub struct HealthConfig {
+    pub interval: Duration,
+    pub timeout: Duration,
+    /// Consecutive failed probes before an endpoint is taken out.
+    pub failure_threshold: u32,
+    /// Consecutive good probes before it is put back.
+    pub success_threshold: u32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(pkg_constants::timings::ENDPOINT_PROBE_INTERVAL_SECS),
+            timeout: Duration::from_secs(pkg_constants::timings::ENDPOINT_PROBE_TIMEOUT_SECS),
+            failure_threshold: pkg_constants::timings::ENDPOINT_PROBE_FAILURE_THRESHOLD,
+            success_threshold: pkg_constants::timings::ENDPOINT_PROBE_SUCCESS_THRESHOLD,
+        }
+    }
+}
+
+/// How an endpoint is probed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase", tag = "type", content = "path")]
+pub enum Probe {
+    /// The endpoint accepts a TCP connection.
+    Tcp,
+    /// `GET <path>` answers with a 2xx or 3xx status.
+    Http(String),
+}
+
+/// Health of one endpoint, as listed by the agent's debug API.
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointHealth {
+    /// "podIP:port"
+    pub address: String,
+    pub probe: Probe,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    pub last_probe: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct HealthTable {
+    endpoints: HashMap<String, EndpointHealth>,
+}
+
+impl HealthTable {
+    /// Replace the set of probed endpoints. New ones start healthy, since
+    /// the control plane only lists ready pods; known ones keep their state.
+    pub fn set_targets(&mut self, targets: HashMap<String, Probe>) {
+        self.endpoints.retain(|addr, _| targets.contains_key(addr));
+        for (address, probe) in targets {
+            match self.endpoints.get_mut(&address) {
+                Some(health) => health.probe = probe,
+                None => {
+                    self.endpoints.insert(
+                        address.clone(),
+                        EndpointHealth {
+                            address,
+                            probe,
+                            healthy: true,
+                            consecutive_failures: 0,
+                            consecutive_successes: 0,
+                            last_probe: None,
+                            last_error: None,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    pub fn targets(&self) -> Vec<(String, Probe)> {
+        self.endpoints
+            .values()
+            .map(|h| (h.address.clone(), h.probe.clone()))
+            .collect()
+    }
+
+    /// Record a probe result for `address` and apply the thresholds.
+    pub fn record(
+        &mut self,
+        address: &str,
+        result: Result<(), String>,
+        config: &HealthConfig,
+        now: DateTime<Utc>,
+    ) {
+        // Removed while the probe was in flight.
+        let Some(health) = self.endpoints.get_mut(address) else {
+            return;
+        };
+        health.last_probe = Some(now);
+        match result {
+            Ok(()) => {
+                health.consecutive_failures = 0;
+                health.consecutive_successes += 1;
+                health.last_error = None;
+                if !health.healthy && health.consecutive_successes >= config.success_threshold {
+                    health.healthy = true;
+                    info!("Endpoint {} is healthy again", address);
+                }
+            }
+            Err(e) => {
+                health.consecutive_successes = 0;
+                health.consecutive_failures += 1;
+                if health.healthy && health.consecutive_failures >= config.failure_threshold {
+                    health.healthy = false;
+                    warn!(
+                        "Endpoint {} failed {} probes, removing it from load balancing: {}",
+                        address, health.consecutive_failures, e
+                    );
+                }
+                health.last_error = Some(e);
+            }
+        }
+    }
+
+    /// Endpoints currently taken out of load balancing.
+    pub fn down(&self) -> Vec<String> {
+        self.endpoints
+            .values()
+            .filter(|h| !h.healthy)
+            .map(|h| h.address.clone())
+            .collect()
+    }
+
+    /// All endpoints, sorted by address.
+    pub fn snapshot(&self) -> Vec<EndpointHealth> {
+        let mut all: Vec<EndpointHealth> = self.endpoints.values().cloned().collect();
+        all.sort_by(|a, b| a.address.cmp(&b.address));
+        all
+    }
+}
+
+/// Delay before probing `address` within a round, spreading endpoints evenly
+/// over the interval so hundreds of them are not probed at once.
+pub fn stagger(address: &str, interval: Duration) -> Duration {
+    let millis = interval.as_millis().max(1) as u64;
+    let mut hasher = DefaultHasher::new();
+    address.hash(&mut hasher);
+    Duration::from_millis(hasher.finish() % millis)
+}
+
+/// Run one probe against `address`.
+pub async fn probe(address: &str, probe: &Probe, timeout: Duration) -> Result<(), String> {
+    let attempt = async {
+        let mut stream = TcpStream::connect(address)
+            .await
+            .map_err(|e| format!("connect: {}", e))?;
+        let Probe::Http(path) = probe else {
+            return Ok(());
+        };
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: k3rs-proxy\r\nConnection: close\r\n\r\n",
+            path, address
+        );
+        stream
+            .write_all(request.as_bytes())
+            .await
+            .map_err(|e| format!("write: {}", e))?;
+        let mut head = [0u8; 32];
+        let n = stream
+            .read(&mut head)
+            .await
+            .map_err(|e| format!("read: {}", e))?;
+        // "HTTP/1.1 200 OK"
+        let status = std::str::from_utf8(&head[..n])
+            .ok()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| "malformed HTTP response".to_string())?;
+        if (200..400).contains(&status) {
+            Ok(())
+        } else {
+            Err(format!("HTTP status {}", status))
+        }
+    };
+    tokio::time::timeout(timeout, attempt)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {:?}", timeout)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: HealthConfig = HealthConfig {
+        interval: Duration::from_secs(5),
+        timeout: Duration::from_secs(1),
+        failure_threshold: 3,
+        success_threshold: 2,
+    };
+
+    fn table(addrs: &[&str]) -> HealthTable {
+        let mut table = HealthTable::default();
+        table.set_targets(addrs.iter().map(|a| (a.to_string(), Probe::Tcp)).collect());
+        table
+    }
+
+    #[test]
+    fn test_thresholds() {
+        let mut t = table(&["10.42.0.1:80"]);
+        let now = Utc::now();
+        let fail = || Err("connect: refused".to_string());
+
+        t.record("10.42.0.1:80", fail(), &CONFIG, now);
+        t.record("10.42.0.1:80", fail(), &CONFIG, now);
+        assert!(t.down().is_empty());
+        t.record("10.42.0.1:80", fail(), &CONFIG, now);
+        assert_eq!(t.down(), vec!["10.42.0.1:80"]);
+
+        // One good probe is not enough to come back.
+        t.record("10.42.0.1:80", Ok(()), &CONFIG, now);
+        assert_eq!(t.down().len(), 1);
+        t.record("10.42.0.1:80", fail(), &CONFIG, now);
+        t.record("10.42.0.1:80", Ok(()), &CONFIG, now);
+        t.record("10.42.0.1:80", Ok(()), &CONFIG, now);
+        assert!(t.down().is_empty());
+
+        let snapshot = t.snapshot();
+        assert!(snapshot[0].healthy);
+        assert_eq!(snapshot[0].consecutive_successes, 2);
+        assert_eq!(snapshot[0].last_error, None);
+        assert_eq!(snapshot[0].last_probe, Some(now));
+    }
+
+    #[test]
+    fn test_set_targets_keeps_known_state() {
+        let mut t = table(&["10.42.0.1:80", "10.42.0.2:80"]);
+        for _ in 0..3 {
+            t.record("10.42.0.1:80", Err("refused".into()), &CONFIG, Utc::now());
+        }
+        t.set_targets(
+            [
+                ("10.42.0.1:80".to_string(), Probe::Http("/healthz".into())),
+                ("10.42.0.3:80".to_string(), Probe::Tcp),
+            ]
+            .into(),
+        );
+        let snapshot = t.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert!(!snapshot[0].healthy);
+        assert_eq!(snapshot[0].probe, Probe::Http("/healthz".into()));
+        assert!(snapshot[1].healthy);
+        // Results for endpoints no longer listed are ignored.
+        t.record("10.42.0.2:80", Ok(()), &CONFIG, Utc::now());
+        assert_eq!(t.snapshot().len(), 2);
+    }
+
+    #[test]
+    fn test_stagger_spreads_probes() {
+        let interval = Duration::from_secs(5);
+        let delays: std::collections::HashSet<Duration> = (0..100)
+            .map(|i| stagger(&format!("10.42.0.{}:80", i), interval))
+            .collect();
+        assert!(delays.iter().all(|d| *d < interval));
+        assert!(delays.len() > 50);
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let status = if buf[..n].starts_with(b"GET /healthz ") {
+                    "200 OK"
+                } else {
+                    "503 Service Unavailable"
+                };
+                let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status);
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        let timeout = Duration::from_secs(1);
+
+        assert_eq!(probe(&addr, &Probe::Tcp, timeout).await, Ok(()));
+        assert_eq!(
+            probe(&addr, &Probe::Http("/healthz".into()), timeout).await,
+            Ok(())
+        );
+        assert_eq!(
+            probe(&addr, &Probe::Http("/ready".into()), timeout).await,
+            Err("HTTP status 503".to_string())
+        );
+
+        // Nothing listening.
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        assert!(probe(&closed, &Probe::Tcp, timeout).await.is_err());
+    }
+}
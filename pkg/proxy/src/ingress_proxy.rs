@@ -1,91 +1,187 @@
+use crate::service_proxy::{ServiceProxy, Upstreams};
 use async_trait::async_trait;
+use bytes::Bytes;
+use pingora::http::ResponseHeader;
 use pingora::prelude::*;
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{debug, info};
 
 use pkg_types::ingress::PathType;
 
+/// Body of the default backend, served when no Ingress rule matches.
+pub const DEFAULT_BACKEND_BODY: &str = "<html>\n\
+     <head><title>404 Not Found</title></head>\n\
+     <body>\n\
+     <h1>404 Not Found</h1>\n\
+     <p>No Ingress rule matches this host and path.</p>\n\
+     <hr><p>k3rs ingress</p>\n\
+     </body>\n\
+     </html>\n";
+
 /// A compiled Ingress routing rule for fast matching.
 #[derive(Debug, Clone)]
 pub struct IngressRouteRule {
+    /// Lower-cased host; empty matches any host.
     pub host: String,
     pub path: String,
     pub path_type: PathType,
-    /// Backend address as "clusterIP:port"
+    /// Service route key as "clusterIP:port"
     pub backend: String,
 }
 
+impl IngressRouteRule {
+    /// `Prefix` matches whole path segments: `/api` matches `/api` and
+    /// `/api/users` but not `/apiv2`.
+    fn matches_path(&self, path: &str) -> bool {
+        match self.path_type {
+            PathType::Exact => path == self.path,
+            PathType::Prefix => {
+                let prefix = self.path.trim_end_matches('/');
+                prefix.is_empty()
+                    || path
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            }
+        }
+    }
+}
+
+/// The rule a request for `host` + `path` is routed by.
+///
+/// Rules naming the host win over host-less ones; among those, an `Exact`
+/// match wins over prefixes and the longest prefix wins. Ties go to the
+/// earlier rule.
+pub fn find_route<'a>(
+    rules: &'a [IngressRouteRule],
+    host: &str,
+    path: &str,
+) -> Option<&'a IngressRouteRule> {
+    let mut best: Option<(&IngressRouteRule, (bool, bool, usize))> = None;
+    for rule in rules {
+        let host_ok = rule.host.is_empty() || rule.host.eq_ignore_ascii_case(host);
+        if !host_ok || !rule.matches_path(path) {
+            continue;
+        }
+        let rank = (
+            !rule.host.is_empty(),
+            matches!(rule.path_type, PathType::Exact),
+            rule.path.len(),
+        );
+        if best.is_none_or(|(_, best_rank)| rank > best_rank) {
+            best = Some((rule, rank));
+        }
+    }
+    best.map(|(rule, _)| rule)
+}
+
+/// Strip the port from a Host header value (`example.com:8080`, `[::1]:80`).
+fn host_name(host: &str) -> &str {
+    if let Some(rest) = host.strip_prefix('[') {
+        return rest.split(']').next().unwrap_or(rest);
+    }
+    host.split(':').next().unwrap_or(host)
+}
+
 /// Pingora-based Ingress Controller — routes external HTTP traffic
 /// to cluster services based on Ingress host/path rules.
 pub struct IngressProxy {
     pub rules: Arc<RwLock<Vec<IngressRouteRule>>>,
+    upstreams: Upstreams,
     pub listen_port: u16,
 }
 
 /// Pingora `ProxyHttp` handler for Ingress routing.
 struct IngressProxyHandler {
     rules: Arc<RwLock<Vec<IngressRouteRule>>>,
+    upstreams: Upstreams,
+}
+
+/// Answer with the k3rs default backend page.
+async fn respond_not_found(session: &mut Session) -> Result<()> {
+    let mut header = ResponseHeader::build(404, Some(3))?;
+    header.insert_header("Server", "k3rs-ingress")?;
+    header.insert_header("Content-Type", "text/html; charset=utf-8")?;
+    header.insert_header("Content-Length", DEFAULT_BACKEND_BODY.len().to_string())?;
+    session
+        .write_response_header(Box::new(header), false)
+        .await?;
+    session
+        .write_response_body(
+            Some(Bytes::from_static(DEFAULT_BACKEND_BODY.as_bytes())),
+            true,
+        )
+        .await
 }
 
 #[async_trait]
 impl ProxyHttp for IngressProxyHandler {
-    type CTX = ();
+    /// Route key of the matched rule's backend.
+    type CTX = Option<String>;
 
-    fn new_ctx(&self) -> Self::CTX {}
+    fn new_ctx(&self) -> Self::CTX {
+        None
+    }
 
-    async fn upstream_peer(
-        &self,
-        session: &mut Session,
-        _ctx: &mut Self::CTX,
-    ) -> Result<Box<HttpPeer>> {
-        let host = session
-            .req_header()
-            .headers
-            .get("host")
-            .and_then(|h| h.to_str().ok())
+    async fn request_filter(&self, session: &mut Session, ctx: &mut Self::CTX) -> Result<bool> {
+        let req = session.req_header();
+        let host = req
+            .uri
+            .host()
+            .or_else(|| req.headers.get("host").and_then(|h| h.to_str().ok()))
+            .map(host_name)
             .unwrap_or("")
             .to_string();
+        let path = req.uri.path().to_string();
 
-        // Strip port from host if present (e.g. "example.com:8080" → "example.com")
-        let host_name = host.split(':').next().unwrap_or(&host);
-
-        let path = session.req_header().uri.path().to_string();
-
-        let rules = self.rules.read().await;
-
-        // Find a matching rule (first match wins)
-        for rule in rules.iter() {
-            if rule.host != host_name {
-                continue;
+        let backend = {
+            let rules = self.rules.read().await;
+            find_route(&rules, &host, &path).map(|rule| rule.backend.clone())
+        };
+        match backend {
+            Some(backend) => {
+                *ctx = Some(backend);
+                Ok(false)
             }
-
-            let matched = match rule.path_type {
-                PathType::Exact => path == rule.path,
-                PathType::Prefix => path.starts_with(&rule.path),
-            };
-
-            if matched {
-                let peer = HttpPeer::new(&rule.backend, false, String::new());
-                return Ok(Box::new(peer));
+            None => {
+                debug!("Ingress: no rule for {}{}", host, path);
+                respond_not_found(session).await?;
+                Ok(true)
             }
         }
+    }
 
-        // No matching ingress rule
-        Err(pingora::Error::new(pingora::ErrorType::ConnectNoRoute))
+    async fn upstream_peer(
+        &self,
+        session: &mut Session,
+        ctx: &mut Self::CTX,
+    ) -> Result<Box<HttpPeer>> {
+        let Some(backend) = ctx.as_deref() else {
+            return Err(pingora::Error::new(pingora::ErrorType::ConnectNoRoute));
+        };
+        let source = session
+            .client_addr()
+            .and_then(|addr| addr.as_inet())
+            .map(|addr| addr.ip().to_canonical().to_string());
+        self.upstreams.select(backend, source.as_deref()).await
     }
 }
 
 impl IngressProxy {
-    /// Create a new ingress proxy on the given port.
-    pub fn new(listen_port: u16) -> Self {
+    /// Create a new ingress proxy on the given port, sending traffic through
+    /// the backends and NetworkPolicies of `services`.
+    pub fn new(listen_port: u16, services: &ServiceProxy) -> Self {
         Self {
             rules: Arc::new(RwLock::new(Vec::new())),
+            upstreams: services.upstreams(),
             listen_port,
         }
     }
 
     /// Rebuild the routing rules from Ingress + Service resources.
+    ///
+    /// Requests already matched keep their backend; new requests see the
+    /// new rules.
     pub async fn update_rules(
         &self,
         ingresses: &[pkg_types::ingress::Ingress],
@@ -96,25 +192,29 @@ impl IngressProxy {
         for ingress in ingresses {
             for rule in &ingress.spec.rules {
                 for path in &rule.http.paths {
-                    // Resolve the backend service to a ClusterIP:port
+                    // Resolve the backend service to its ClusterIP:port route
                     let backend = services
                         .iter()
                         .find(|s| {
                             s.name == path.backend.service_name && s.namespace == ingress.namespace
                         })
                         .and_then(|s| {
-                            s.cluster_ip
-                                .as_ref()
+                            s.virtual_ip()
                                 .map(|ip| format!("{}:{}", ip, path.backend.service_port))
                         });
 
                     if let Some(backend_addr) = backend {
                         new_rules.push(IngressRouteRule {
-                            host: rule.host.clone(),
+                            host: rule.host.to_ascii_lowercase(),
                             path: path.path.clone(),
                             path_type: path.path_type.clone(),
                             backend: backend_addr,
                         });
+                    } else {
+                        debug!(
+                            "Ingress {}/{}: service {} has no ClusterIP yet",
+                            ingress.namespace, ingress.name, path.backend.service_name
+                        );
                     }
                 }
             }
@@ -138,6 +238,7 @@ impl IngressProxy {
 
         let handler = IngressProxyHandler {
             rules: self.rules.clone(),
+            upstreams: self.upstreams.clone(),
         };
 
         let mut proxy = http_proxy_service(&server.configuration, handler);
@@ -153,3 +254,206 @@ impl IngressProxy {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pkg_metrics::MetricsRegistry;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn rule(host: &str, path: &str, path_type: PathType, backend: &str) -> IngressRouteRule {
+        IngressRouteRule {
+            host: host.to_string(),
+            path: path.to_string(),
+            path_type,
+            backend: backend.to_string(),
+        }
+    }
+
+    fn route<'a>(rules: &'a [IngressRouteRule], host: &str, path: &str) -> Option<&'a str> {
+        find_route(rules, host, path).map(|r| r.backend.as_str())
+    }
+
+    #[test]
+    fn test_route_precedence() {
+        let rules = vec![
+            rule("", "/", PathType::Prefix, "catch-all"),
+            rule("shop.example.com", "/", PathType::Prefix, "shop"),
+            rule("shop.example.com", "/api", PathType::Prefix, "api"),
+            rule("shop.example.com", "/api/v2", PathType::Prefix, "api-v2"),
+            rule(
+                "shop.example.com",
+                "/api/v2/health",
+                PathType::Exact,
+                "health",
+            ),
+            rule("shop.example.com", "/api", PathType::Prefix, "api-shadowed"),
+        ];
+        let cases = [
+            ("shop.example.com", "/", Some("shop")),
+            ("shop.example.com", "/cart", Some("shop")),
+            ("shop.example.com", "/api", Some("api")),
+            ("shop.example.com", "/api/users", Some("api")),
+            // Longest prefix wins regardless of rule order.
+            ("shop.example.com", "/api/v2/users", Some("api-v2")),
+            ("shop.example.com", "/api/v2/health", Some("health")),
+            ("shop.example.com", "/api/v2/health/db", Some("api-v2")),
+            // Prefixes match whole segments.
+            ("shop.example.com", "/apiv2", Some("shop")),
+            ("SHOP.example.com", "/api", Some("api")),
+            ("other.example.com", "/api", Some("catch-all")),
+        ];
+        for (host, path, expected) in cases {
+            assert_eq!(route(&rules, host, path), expected, "{}{}", host, path);
+        }
+
+        let rules = vec![
+            rule("shop.example.com", "/login", PathType::Exact, "login"),
+            rule("shop.example.com", "/static/", PathType::Prefix, "static"),
+        ];
+        assert_eq!(route(&rules, "shop.example.com", "/login"), Some("login"));
+        assert_eq!(route(&rules, "shop.example.com", "/login/"), None);
+        assert_eq!(
+            route(&rules, "shop.example.com", "/static/app.js"),
+            Some("static")
+        );
+        assert_eq!(route(&rules, "shop.example.com", "/static"), Some("static"));
+        assert_eq!(route(&rules, "other.example.com", "/login"), None);
+    }
+
+    #[test]
+    fn test_host_name_strips_port() {
+        assert_eq!(host_name("example.com"), "example.com");
+        assert_eq!(host_name("example.com:8080"), "example.com");
+        assert_eq!(host_name("[fd6b::1]:80"), "fd6b::1");
+    }
+
+    /// HTTP backend answering every request with its request line.
+    async fn echo_backend() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let body = request.lines().next().unwrap_or("").to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        port
+    }
+
+    /// Send a GET through the proxy, retrying while it starts up.
+    async fn get(port: u16, host: &str, path: &str) -> String {
+        for _ in 0..50 {
+            if let Ok(mut stream) = TcpStream::connect(("127.0.0.1", port)).await {
+                let request = format!(
+                    "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+                    path, host
+                );
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                return response;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("ingress proxy did not start on port {}", port);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_round_trip_through_service_backend() {
+        let backend_port = echo_backend().await;
+        let service: pkg_types::service::Service = serde_json::from_value(serde_json::json!({
+            "id": "svc-web",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "ports": [{ "name": "http", "port": 80, "target_port": backend_port }],
+                "service_type": "ClusterIP",
+            },
+            "cluster_ip": "10.43.0.10",
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let endpoint: pkg_types::endpoint::Endpoint = serde_json::from_value(serde_json::json!({
+            "id": "ep-web",
+            "service_id": "svc-web",
+            "service_name": "web",
+            "namespace": "default",
+            "addresses": [{ "ip": "127.0.0.1" }],
+            "ports": [],
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let ingress: pkg_types::ingress::Ingress = serde_json::from_value(serde_json::json!({
+            "id": "ing-web",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "rules": [{
+                    "host": "shop.example.com",
+                    "http": { "paths": [{
+                        "path": "/api",
+                        "backend": { "service_name": "web", "service_port": 80 },
+                    }] },
+                }],
+            },
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+
+        let services = ServiceProxy::new(0, Arc::new(MetricsRegistry::new()));
+        services
+            .update_routes(std::slice::from_ref(&service), &[endpoint], &HashMap::new())
+            .await;
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let ingress_proxy = IngressProxy::new(port, &services);
+        ingress_proxy
+            .update_rules(
+                std::slice::from_ref(&ingress),
+                std::slice::from_ref(&service),
+            )
+            .await;
+        ingress_proxy.start().await.unwrap();
+
+        let response = get(port, "shop.example.com", "/api/users?page=2").await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(
+            response.ends_with("GET /api/users?page=2 HTTP/1.1"),
+            "{}",
+            response
+        );
+
+        let response = get(port, "shop.example.com", "/cart").await;
+        assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+        assert!(response.contains("k3rs ingress"), "{}", response);
+
+        // Rule changes apply to the next request.
+        ingress_proxy.update_rules(&[], &[service]).await;
+        let response = get(port, "shop.example.com", "/api/users").await;
+        assert!(response.starts_with("HTTP/1.1 404"), "{}", response);
+    }
+}
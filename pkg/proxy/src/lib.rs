@@ -1,3 +1,6 @@
+pub mod affinity;
+pub mod health;
 pub mod ingress_proxy;
+pub mod network_policy;
 pub mod service_proxy;
 pub mod tunnel;
@@ -0,0 +1,339 @@
+//! NetworkPolicy ingress enforcement for the service proxy.
+//!
+//! Only traffic that goes through the [`ServiceProxy`](crate::service_proxy::ServiceProxy)
+//! is covered. Sources are identified by IP through the endpoints table, so a
+//! client pod that backs no Service is unknown and matches no `from` peer.
+
+use pkg_types::endpoint::Endpoint;
+use pkg_types::namespace::Namespace;
+use pkg_types::network_policy::{NetworkPolicy, NetworkPolicyPeer, PolicyType};
+use pkg_types::pod::Pod;
+use std::collections::HashMap;
+
+/// The namespace and labels of a pod, as far as the proxy knows them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PodIdentity {
+    pub namespace: String,
+    pub labels: HashMap<String, String>,
+}
+
+/// Snapshot of the policies and pod identities needed to judge connections.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyTable {
+    /// Policies with ingress semantics, by namespace.
+    policies: HashMap<String, Vec<NetworkPolicy>>,
+    namespace_labels: HashMap<String, HashMap<String, String>>,
+    /// Pod IP → identity, from endpoint addresses.
+    pods_by_ip: HashMap<String, PodIdentity>,
+}
+
+impl PolicyTable {
+    pub fn new(
+        policies: &[NetworkPolicy],
+        namespaces: &[Namespace],
+        pods: &[Pod],
+        endpoints: &[Endpoint],
+    ) -> Self {
+        let mut by_ns: HashMap<String, Vec<NetworkPolicy>> = HashMap::new();
+        for policy in policies.iter().filter(|p| applies_to_ingress(p)) {
+            by_ns
+                .entry(policy.namespace.clone())
+                .or_default()
+                .push(policy.clone());
+        }
+
+        let pods_by_id: HashMap<&str, &Pod> = pods.iter().map(|p| (p.id.as_str(), p)).collect();
+        let mut pods_by_ip = HashMap::new();
+        for ep in endpoints {
+            for addr in &ep.addresses {
+                if let Some(pod) = addr.pod_id.as_deref().and_then(|id| pods_by_id.get(id)) {
+                    pods_by_ip.insert(
+                        addr.ip.clone(),
+                        PodIdentity {
+                            namespace: pod.namespace.clone(),
+                            labels: pod.labels.clone(),
+                        },
+                    );
+                }
+            }
+        }
+
+        Self {
+            policies: by_ns,
+            namespace_labels: namespaces
+                .iter()
+                .map(|ns| (ns.name.clone(), ns.labels.clone()))
+                .collect(),
+            pods_by_ip,
+        }
+    }
+
+    /// Whether no policy constrains ingress anywhere (allow-all).
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// Whether a connection from `source_ip` to the backend pod at
+    /// `dest_ip:port` is allowed.
+    ///
+    /// A pod selected by no ingress policy accepts everything; otherwise one
+    /// rule of one selecting policy must admit the source and port. A policy
+    /// with no rules denies all ingress to the pods it selects.
+    pub fn allows(&self, source_ip: Option<&str>, dest_ip: &str, port: u16) -> bool {
+        if self.policies.is_empty() {
+            return true;
+        }
+        // Not a pod the proxy knows: nothing to evaluate against.
+        let Some(dest) = self.pods_by_ip.get(dest_ip) else {
+            return true;
+        };
+        let Some(ns_policies) = self.policies.get(&dest.namespace) else {
+            return true;
+        };
+        let mut selected = ns_policies
+            .iter()
+            .filter(|p| selector_matches(&p.pod_selector, &dest.labels))
+            .peekable();
+        if selected.peek().is_none() {
+            return true;
+        }
+
+        let source = source_ip.and_then(|ip| self.pods_by_ip.get(ip));
+        selected.any(|policy| {
+            policy.ingress.iter().any(|rule| {
+                let port_ok = rule.ports.is_empty()
+                    || rule.ports.iter().any(|p| {
+                        p.port.is_none_or(|p| p == port)
+                            && p.protocol
+                                .as_deref()
+                                .is_none_or(|proto| proto.eq_ignore_ascii_case("TCP"))
+                    });
+                let source_ok = rule.from.is_empty()
+                    || rule
+                        .from
+                        .iter()
+                        .any(|peer| self.peer_matches(peer, &policy.namespace, source));
+                port_ok && source_ok
+            })
+        })
+    }
+
+    fn peer_matches(
+        &self,
+        peer: &NetworkPolicyPeer,
+        policy_namespace: &str,
+        source: Option<&PodIdentity>,
+    ) -> bool {
+        let Some(source) = source else {
+            return false;
+        };
+        let namespace_ok = match &peer.namespace_selector {
+            Some(selector) => self
+                .namespace_labels
+                .get(&source.namespace)
+                .is_some_and(|labels| selector_matches(selector, labels)),
+            // Without a namespace selector, a pod selector is scoped to the
+            // policy's own namespace.
+            None => source.namespace == policy_namespace,
+        };
+        let pod_ok = match &peer.pod_selector {
+            Some(selector) => selector_matches(selector, &source.labels),
+            None => peer.namespace_selector.is_some(),
+        };
+        namespace_ok && pod_ok
+    }
+}
+
+/// Policies without `policy_types` still govern ingress.
+fn applies_to_ingress(policy: &NetworkPolicy) -> bool {
+    policy.policy_types.is_empty() || policy.policy_types.contains(&PolicyType::Ingress)
+}
+
+/// Whether `labels` carry every key/value in `selector`. An empty selector
+/// matches everything.
+pub fn selector_matches(
+    selector: &HashMap<String, String>,
+    labels: &HashMap<String, String>,
+) -> bool {
+    selector.iter().all(|(k, v)| labels.get(k) == Some(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn policy(ns: &str, value: serde_json::Value) -> NetworkPolicy {
+        let mut obj = serde_json::json!({
+            "name": "p",
+            "namespace": ns,
+            "created_at": "2024-01-01T00:00:00Z",
+        });
+        obj.as_object_mut()
+            .unwrap()
+            .extend(value.as_object().unwrap().clone());
+        serde_json::from_value(obj).unwrap()
+    }
+
+    /// Pods `web` (10.0.0.1, app=web) and `api` (10.0.0.2, app=api) in
+    /// `default`, `monitor` (10.0.1.1, app=prometheus) in `ops`.
+    fn table(policies: Vec<NetworkPolicy>) -> PolicyTable {
+        let pod = |id: &str, ns: &str, app: &str| -> Pod {
+            serde_json::from_value(serde_json::json!({
+                "id": id,
+                "name": id,
+                "namespace": ns,
+                "spec": { "containers": [] },
+                "status": "Running",
+                "labels": { "app": app },
+                "created_at": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap()
+        };
+        let endpoint = |ns: &str, addrs: &[(&str, &str)]| -> Endpoint {
+            serde_json::from_value(serde_json::json!({
+                "id": "ep",
+                "service_id": "svc",
+                "service_name": "svc",
+                "namespace": ns,
+                "addresses": addrs
+                    .iter()
+                    .map(|(ip, id)| serde_json::json!({ "ip": ip, "pod_id": id }))
+                    .collect::<Vec<_>>(),
+                "ports": [],
+                "created_at": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap()
+        };
+        let namespace = |name: &str, team: &str| -> Namespace {
+            serde_json::from_value(serde_json::json!({
+                "name": name,
+                "labels": { "team": team },
+                "created_at": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap()
+        };
+        PolicyTable::new(
+            &policies,
+            &[namespace("default", "dev"), namespace("ops", "sre")],
+            &[
+                pod("web", "default", "web"),
+                pod("api", "default", "api"),
+                pod("monitor", "ops", "prometheus"),
+            ],
+            &[
+                endpoint("default", &[("10.0.0.1", "web"), ("10.0.0.2", "api")]),
+                endpoint("ops", &[("10.0.1.1", "monitor")]),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_selector_matches() {
+        let pod = labels(&[("app", "web"), ("tier", "front")]);
+        assert!(selector_matches(&labels(&[]), &pod));
+        assert!(selector_matches(&labels(&[("app", "web")]), &pod));
+        assert!(!selector_matches(&labels(&[("app", "api")]), &pod));
+        assert!(!selector_matches(
+            &labels(&[("app", "web"), ("env", "prod")]),
+            &pod
+        ));
+    }
+
+    #[test]
+    fn test_no_policies_allow_all() {
+        let t = table(vec![]);
+        assert!(t.is_empty());
+        assert!(t.allows(Some("10.0.0.1"), "10.0.0.2", 80));
+        assert!(t.allows(None, "10.0.0.2", 80));
+    }
+
+    #[test]
+    fn test_policy_without_rules_denies_selected_pods() {
+        let t = table(vec![policy(
+            "default",
+            serde_json::json!({ "pod_selector": { "app": "api" } }),
+        )]);
+        assert!(!t.allows(Some("10.0.0.1"), "10.0.0.2", 80));
+        assert!(!t.allows(None, "10.0.0.2", 80));
+        // Pods the policy does not select are unaffected.
+        assert!(t.allows(Some("10.0.0.2"), "10.0.0.1", 80));
+        // Egress-only policies do not restrict ingress.
+        let t = table(vec![policy(
+            "default",
+            serde_json::json!({ "policy_types": ["Egress"] }),
+        )]);
+        assert!(t.allows(None, "10.0.0.2", 80));
+    }
+
+    #[test]
+    fn test_ingress_rule_decision_table() {
+        let t = table(vec![policy(
+            "default",
+            serde_json::json!({
+                "pod_selector": { "app": "api" },
+                "ingress": [
+                    {
+                        "from": [{ "pod_selector": { "app": "web" } }],
+                        "ports": [{ "protocol": "TCP", "port": 8080 }],
+                    },
+                    {
+                        "from": [{ "namespace_selector": { "team": "sre" } }],
+                    },
+                ],
+            }),
+        )]);
+        let cases = [
+            // (source, port, allowed)
+            (Some("10.0.0.1"), 8080, true),  // web, allowed port
+            (Some("10.0.0.1"), 9090, false), // web, other port
+            (Some("10.0.1.1"), 9090, true),  // any pod in an sre namespace
+            (Some("10.0.0.2"), 8080, false), // api itself is not web
+            (Some("10.9.9.9"), 8080, false), // unknown source
+            (None, 8080, false),
+        ];
+        for (source, port, allowed) in cases {
+            assert_eq!(
+                t.allows(source, "10.0.0.2", port),
+                allowed,
+                "{:?} -> api:{}",
+                source,
+                port
+            );
+        }
+    }
+
+    #[test]
+    fn test_pod_selector_peer_is_namespace_scoped() {
+        let t = table(vec![policy(
+            "default",
+            serde_json::json!({
+                "pod_selector": { "app": "api" },
+                "ingress": [{ "from": [{ "pod_selector": {} }] }],
+            }),
+        )]);
+        // Any pod in the policy's namespace, but not from `ops`.
+        assert!(t.allows(Some("10.0.0.1"), "10.0.0.2", 80));
+        assert!(!t.allows(Some("10.0.1.1"), "10.0.0.2", 80));
+
+        let t = table(vec![policy(
+            "default",
+            serde_json::json!({
+                "ingress": [{
+                    "from": [{
+                        "namespace_selector": { "team": "sre" },
+                        "pod_selector": { "app": "prometheus" },
+                    }],
+                }],
+            }),
+        )]);
+        assert!(t.allows(Some("10.0.1.1"), "10.0.0.1", 80));
+        assert!(!t.allows(Some("10.0.0.2"), "10.0.0.1", 80));
+    }
+}
@@ -1,12 +1,29 @@
+use crate::affinity::AffinityTable;
+use crate::health::{self, EndpointHealth, HealthConfig, HealthTable, Probe};
+use crate::network_policy::PolicyTable;
 use async_trait::async_trait;
+use chrono::Utc;
 use pingora::prelude::*;
 use pingora_load_balancing::selection::RoundRobin;
 use pingora_load_balancing::{Backend, Backends, LoadBalancer, discovery::Static};
+use pkg_metrics::MetricsRegistry;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeSet, HashMap, HashSet};
+use std::net::IpAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::RwLock;
-use tracing::info;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+pub const NETWORK_POLICY_DENIED_METRIC: &str = "k3rs_network_policy_denied_total";
+pub const SESSION_AFFINITY_ENTRIES_METRIC: &str = "k3rs_service_affinity_entries";
+pub const ENDPOINTS_DOWN_METRIC: &str = "k3rs_service_endpoints_down";
+
+/// Minimum time between log lines about denied connections.
+const DENY_LOG_INTERVAL: Duration = Duration::from_secs(10);
 
 /// A routing table entry: maps `ClusterIP:port` to a list of backend pod addresses.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -21,15 +38,207 @@ pub struct RoutingTable {
 /// based on the dynamic routing table populated from Service + Endpoint data.
 pub struct ServiceProxy {
     pub routing_table: Arc<RwLock<RoutingTable>>,
+    upstreams: Upstreams,
+    /// NodePort → `clusterIP:port` route, read by the node port listeners.
+    node_ports: Arc<RwLock<HashMap<u16, String>>>,
+    /// Accept loops of the node ports currently bound on this node.
+    node_port_listeners: std::sync::Mutex<HashMap<u16, JoinHandle<()>>>,
+    pub listen_port: u16,
+}
+
+/// Backend selection shared by the service and ingress proxies: the
+/// per-route load balancers plus the NetworkPolicy check applied to them.
+#[derive(Clone)]
+pub(crate) struct Upstreams {
     /// Per-route LoadBalancer instances built from Pingora's load-balancing crate.
     lb_table: Arc<RwLock<HashMap<String, Arc<LoadBalancer<RoundRobin>>>>>,
-    pub listen_port: u16,
+    /// NetworkPolicy ingress rules applied to every proxied connection.
+    policies: Arc<RwLock<PolicyTable>>,
+    /// Routes of ClientIP-affinity services → idle timeout of a pin.
+    affinity_routes: Arc<RwLock<HashMap<String, Duration>>>,
+    affinity: Arc<std::sync::Mutex<AffinityTable>>,
+    /// Probe results of every backend, by "podIP:port".
+    health: Arc<std::sync::Mutex<HealthTable>>,
+    metrics: Arc<MetricsRegistry>,
+    deny_log: Arc<DenyLog>,
 }
 
 /// The Pingora `ProxyHttp` handler for service proxying.
 struct ServiceProxyHandler {
     routing_table: Arc<RwLock<RoutingTable>>,
-    lb_table: Arc<RwLock<HashMap<String, Arc<LoadBalancer<RoundRobin>>>>>,
+    upstreams: Upstreams,
+}
+
+/// Rate limit for the denied-connection log: one line per
+/// [`DENY_LOG_INTERVAL`], with a count of the ones in between.
+#[derive(Default)]
+struct DenyLog {
+    last: std::sync::Mutex<Option<Instant>>,
+    suppressed: AtomicU64,
+}
+
+impl DenyLog {
+    fn record(&self, route: &str, source: Option<&str>) {
+        let mut last = self.last.lock().unwrap();
+        if last.is_some_and(|t| t.elapsed() < DENY_LOG_INTERVAL) {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        *last = Some(Instant::now());
+        let suppressed = self.suppressed.swap(0, Ordering::Relaxed);
+        warn!(
+            "NetworkPolicy denied connection from {} to {} ({} more denied since last report)",
+            source.unwrap_or("unknown"),
+            route,
+            suppressed
+        );
+    }
+}
+
+impl Upstreams {
+    /// Pick a backend for the `clusterIP:port` route that the NetworkPolicies
+    /// let `source` reach.
+    pub(crate) async fn select(&self, route: &str, source: Option<&str>) -> Result<Box<HttpPeer>> {
+        let backend = self.backend(route, source).await?;
+        Ok(Box::new(HttpPeer::new(backend, false, String::new())))
+    }
+
+    async fn backend(&self, route: &str, source: Option<&str>) -> Result<Backend> {
+        let lb = self.lb_table.read().await.get(route).cloned();
+        match lb {
+            Some(lb) => self.pick(route, &lb, source).await,
+            None => Err(pingora::Error::new(pingora::ErrorType::ConnectNoRoute)),
+        }
+    }
+
+    async fn select_backend(
+        &self,
+        route: &str,
+        lb: &LoadBalancer<RoundRobin>,
+        source: Option<&str>,
+    ) -> Result<Box<HttpPeer>> {
+        let backend = self.pick(route, lb, source).await?;
+        Ok(Box::new(HttpPeer::new(backend, false, String::new())))
+    }
+
+    async fn pick(
+        &self,
+        route: &str,
+        lb: &LoadBalancer<RoundRobin>,
+        source: Option<&str>,
+    ) -> Result<Backend> {
+        let policies = self.policies.read().await;
+        let allowed = |backend: &Backend| {
+            policies.is_empty()
+                || backend
+                    .addr
+                    .as_inet()
+                    .is_none_or(|addr| policies.allows(source, &addr.ip().to_string(), addr.port()))
+        };
+        // Backends failing their probes are skipped, unless that leaves the
+        // route with none: then they are used anyway rather than failing
+        // every connection on a probe that may be wrong.
+        let down: HashSet<String> = self.health.lock().unwrap().down().into_iter().collect();
+        let skip_down = !down.is_empty()
+            && lb
+                .backends()
+                .get_backend()
+                .iter()
+                .any(|b| !down.contains(&b.addr.to_string()));
+        let up = |backend: &Backend| !skip_down || !down.contains(&backend.addr.to_string());
+        let timeout = self.affinity_routes.read().await.get(route).copied();
+        let client = source.and_then(|s| s.parse::<IpAddr>().ok());
+        let upstream = match (timeout, client) {
+            (Some(timeout), Some(client)) => {
+                self.sticky(route, lb, client, timeout, |b| up(b) && allowed(b))
+            }
+            _ if policies.is_empty() && !skip_down => lb.select(b"", 256),
+            _ => lb.select_with(b"", 256, |backend, healthy| {
+                healthy && up(backend) && allowed(backend)
+            }),
+        };
+        if upstream.is_none() && !policies.is_empty() && lb.select(b"", 256).is_some() {
+            self.metrics.counter_inc(NETWORK_POLICY_DENIED_METRIC);
+            self.deny_log.record(route, source);
+            return Err(pingora::Error::explain(
+                pingora::ErrorType::HTTPStatus(403),
+                "denied by NetworkPolicy",
+            ));
+        }
+        upstream.ok_or_else(|| pingora::Error::new(pingora::ErrorType::ConnectNoRoute))
+    }
+
+    /// The backend `client` is pinned to for `route`, re-pinning it when that
+    /// backend is gone, failing probes or no longer allowed.
+    fn sticky(
+        &self,
+        route: &str,
+        lb: &LoadBalancer<RoundRobin>,
+        client: IpAddr,
+        timeout: Duration,
+        allowed: impl Fn(&Backend) -> bool,
+    ) -> Option<Backend> {
+        let backends = lb.backends().get_backend();
+        let candidates: Vec<&Backend> = backends
+            .iter()
+            .filter(|b| lb.backends().ready(b) && allowed(b))
+            .collect();
+        let names: Vec<String> = candidates.iter().map(|b| b.addr.to_string()).collect();
+        let (chosen, entries) = {
+            let mut table = self.affinity.lock().unwrap();
+            let chosen = table.pick(route, client, &names, timeout, Instant::now());
+            (chosen, table.len())
+        };
+        self.metrics
+            .gauge_set(SESSION_AFFINITY_ENTRIES_METRIC, entries as i64);
+        let chosen = chosen?;
+        candidates
+            .into_iter()
+            .find(|b| b.addr.to_string() == chosen)
+            .cloned()
+    }
+}
+
+/// Accept loop of one node port: forwards each TCP connection to a backend of
+/// the route the port currently maps to, like ClusterIP traffic.
+async fn serve_node_port(
+    listener: TcpListener,
+    port: u16,
+    node_ports: Arc<RwLock<HashMap<u16, String>>>,
+    upstreams: Upstreams,
+) {
+    loop {
+        let (mut inbound, peer) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Node port {}: accept failed: {}", port, e);
+                continue;
+            }
+        };
+        let Some(route) = node_ports.read().await.get(&port).cloned() else {
+            continue;
+        };
+        let upstreams = upstreams.clone();
+        tokio::spawn(async move {
+            let source = peer.ip().to_canonical().to_string();
+            let backend = match upstreams.backend(&route, Some(&source)).await {
+                Ok(backend) => backend,
+                Err(e) => {
+                    debug!("Node port {}: no backend for {}: {}", port, route, e);
+                    return;
+                }
+            };
+            let Some(addr) = backend.addr.as_inet().copied() else {
+                return;
+            };
+            match TcpStream::connect(addr).await {
+                Ok(mut outbound) => {
+                    let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                }
+                Err(e) => warn!("Node port {}: failed to connect to {}: {}", port, addr, e),
+            }
+        });
+    }
 }
 
 #[async_trait]
@@ -52,14 +261,20 @@ impl ProxyHttp for ServiceProxyHandler {
             .unwrap_or("unknown")
             .to_string();
 
-        let lb_map = self.lb_table.read().await;
+        // Source pod for NetworkPolicy checks, by IP
+        let source = session
+            .client_addr()
+            .and_then(|addr| addr.as_inet())
+            .map(|addr| addr.ip().to_canonical().to_string());
+
+        let lb_map = self.upstreams.lb_table.read().await;
 
         // Try exact match first
-        if let Some(lb) = lb_map.get(&host)
-            && let Some(upstream) = lb.select(b"", 256)
-        {
-            let peer = HttpPeer::new(upstream, false, String::new());
-            return Ok(Box::new(peer));
+        if let Some(lb) = lb_map.get(&host) {
+            return self
+                .upstreams
+                .select_backend(&host, lb, source.as_deref())
+                .await;
         }
 
         // Fallback: try without port matching (just plain host)
@@ -67,10 +282,11 @@ impl ProxyHttp for ServiceProxyHandler {
         for key in table.routes.keys() {
             if key.starts_with(&host)
                 && let Some(lb) = lb_map.get(key)
-                && let Some(upstream) = lb.select(b"", 256)
             {
-                let peer = HttpPeer::new(upstream, false, String::new());
-                return Ok(Box::new(peer));
+                return self
+                    .upstreams
+                    .select_backend(key, lb, source.as_deref())
+                    .await;
             }
         }
 
@@ -96,15 +312,48 @@ async fn build_lb(backends: &[String]) -> anyhow::Result<Arc<LoadBalancer<RoundR
 
 impl ServiceProxy {
     /// Create a new service proxy listening on the given port.
-    pub fn new(listen_port: u16) -> Self {
+    pub fn new(listen_port: u16, metrics: Arc<MetricsRegistry>) -> Self {
+        metrics.register_counter(
+            NETWORK_POLICY_DENIED_METRIC,
+            "Service connections denied by NetworkPolicy",
+        );
+        metrics.register_gauge(
+            SESSION_AFFINITY_ENTRIES_METRIC,
+            "Clients pinned to a backend by ClientIP session affinity",
+        );
+        metrics.register_gauge(
+            ENDPOINTS_DOWN_METRIC,
+            "Service endpoints taken out of load balancing by failed probes",
+        );
         Self {
             routing_table: Arc::new(RwLock::new(RoutingTable::default())),
-            lb_table: Arc::new(RwLock::new(HashMap::new())),
+            upstreams: Upstreams {
+                lb_table: Arc::new(RwLock::new(HashMap::new())),
+                policies: Arc::new(RwLock::new(PolicyTable::default())),
+                affinity_routes: Arc::new(RwLock::new(HashMap::new())),
+                affinity: Arc::new(std::sync::Mutex::new(AffinityTable::default())),
+                health: Arc::new(std::sync::Mutex::new(HealthTable::default())),
+                metrics,
+                deny_log: Arc::new(DenyLog::default()),
+            },
+            node_ports: Arc::new(RwLock::new(HashMap::new())),
+            node_port_listeners: std::sync::Mutex::new(HashMap::new()),
             listen_port,
         }
     }
 
-    /// Update the routing table from Service + Endpoint data.
+    /// Backend selection for other proxies routing to Services.
+    pub(crate) fn upstreams(&self) -> Upstreams {
+        self.upstreams.clone()
+    }
+
+    /// Replace the NetworkPolicy rules enforced on new connections.
+    pub async fn update_policies(&self, policies: PolicyTable) {
+        *self.upstreams.policies.write().await = policies;
+    }
+
+    /// Update the routing table from Service + Endpoint data, and bind or
+    /// release the node ports of NodePort services.
     ///
     /// `vpc_pod_ips` maps VPC name → set of pod IPs belonging to that VPC.
     /// When non-empty, only endpoint backends whose IP is in the same VPC as
@@ -117,15 +366,29 @@ impl ServiceProxy {
         vpc_pod_ips: &HashMap<String, HashSet<String>>,
     ) {
         let mut new_routes: HashMap<String, Vec<String>> = HashMap::new();
+        let mut new_node_ports: HashMap<u16, String> = HashMap::new();
+        let mut new_affinity: HashMap<String, Duration> = HashMap::new();
+        let mut probe_targets: HashMap<String, Probe> = HashMap::new();
         let has_vpc_info = !vpc_pod_ips.is_empty();
 
         for svc in services {
-            let cluster_ip = match &svc.cluster_ip {
-                Some(ip) => ip.clone(),
-                None => continue,
+            let Some(cluster_ip) = svc.virtual_ip() else {
+                continue;
             };
 
             let svc_vpc = svc.vpc.as_deref().unwrap_or("default");
+            let affinity_timeout =
+                (svc.spec.session_affinity.as_deref() == Some("ClientIP")).then(|| {
+                    Duration::from_secs(
+                        svc.spec.session_affinity_timeout_seconds.unwrap_or(
+                            pkg_constants::timings::DEFAULT_SESSION_AFFINITY_TIMEOUT_SECS,
+                        ),
+                    )
+                });
+            let probe = match &svc.spec.health_check_path {
+                Some(path) => Probe::Http(path.clone()),
+                None => Probe::Tcp,
+            };
 
             // Find matching endpoints for this service
             let matching_eps: Vec<&pkg_types::endpoint::Endpoint> = endpoints
@@ -142,6 +405,12 @@ impl ServiceProxy {
 
             for svc_port in &svc.spec.ports {
                 let route_key = format!("{}:{}", cluster_ip, svc_port.port);
+                if let Some(node_port) = svc_port.node_port {
+                    new_node_ports.insert(node_port, route_key.clone());
+                }
+                if let Some(timeout) = affinity_timeout {
+                    new_affinity.insert(route_key.clone(), timeout);
+                }
                 let mut backends = Vec::new();
 
                 for ep in &matching_eps {
@@ -166,6 +435,9 @@ impl ServiceProxy {
                     }
                 }
 
+                for backend in &backends {
+                    probe_targets.insert(backend.clone(), probe.clone());
+                }
                 if !backends.is_empty() {
                     new_routes.insert(route_key, backends);
                 }
@@ -191,12 +463,68 @@ impl ServiceProxy {
             *table = RoutingTable { routes: new_routes };
         }
         {
-            let mut lb_map = self.lb_table.write().await;
+            let mut lb_map = self.upstreams.lb_table.write().await;
             *lb_map = new_lb_table;
         }
+        *self.node_ports.write().await = new_node_ports;
+        let affinity_entries = {
+            let mut table = self.upstreams.affinity.lock().unwrap();
+            table.retain_routes(|route| new_affinity.contains_key(route));
+            table.len()
+        };
+        self.upstreams
+            .metrics
+            .gauge_set(SESSION_AFFINITY_ENTRIES_METRIC, affinity_entries as i64);
+        *self.upstreams.affinity_routes.write().await = new_affinity;
+        self.upstreams
+            .health
+            .lock()
+            .unwrap()
+            .set_targets(probe_targets);
+        self.sync_node_port_listeners().await;
         info!("ServiceProxy routing table updated: {} routes", route_count);
     }
 
+    /// Bind listeners for new node ports and stop those no service uses any
+    /// more. Connections already accepted on a released port keep running.
+    async fn sync_node_port_listeners(&self) {
+        let wanted: Vec<u16> = self.node_ports.read().await.keys().copied().collect();
+        let to_bind: Vec<u16> = {
+            let mut listeners = self.node_port_listeners.lock().unwrap();
+            listeners.retain(|port, task| {
+                let keep = wanted.contains(port) && !task.is_finished();
+                if !keep {
+                    task.abort();
+                    info!("ServiceProxy released node port {}", port);
+                }
+                keep
+            });
+            wanted
+                .into_iter()
+                .filter(|port| !listeners.contains_key(port))
+                .collect()
+        };
+
+        for port in to_bind {
+            match TcpListener::bind(("0.0.0.0", port)).await {
+                Ok(listener) => {
+                    let task = tokio::spawn(serve_node_port(
+                        listener,
+                        port,
+                        self.node_ports.clone(),
+                        self.upstreams.clone(),
+                    ));
+                    self.node_port_listeners.lock().unwrap().insert(port, task);
+                    info!("ServiceProxy listening on node port {}", port);
+                }
+                Err(e) => warn!(
+                    "Failed to bind node port {}: {} (retrying on next sync)",
+                    port, e
+                ),
+            }
+        }
+    }
+
     /// Load routing table from a JSON file (for cache-based startup).
     /// Returns the number of routes loaded.
     pub async fn load_from_file(&self, path: &str) -> anyhow::Result<usize> {
@@ -222,12 +550,51 @@ impl ServiceProxy {
             *table = RoutingTable { routes };
         }
         {
-            let mut lb_map = self.lb_table.write().await;
+            let mut lb_map = self.upstreams.lb_table.write().await;
             *lb_map = new_lb_table;
         }
         Ok(count)
     }
 
+    /// Probe every backend each `config.interval`, taking the ones that fail
+    /// `config.failure_threshold` probes in a row out of load balancing until
+    /// they pass `config.success_threshold`. Each endpoint is probed at a
+    /// fixed offset into the interval, so probes are spread out over it.
+    pub fn spawn_health_checks(&self, config: HealthConfig) -> JoinHandle<()> {
+        let table = self.upstreams.health.clone();
+        let metrics = self.upstreams.metrics.clone();
+        info!(
+            "Probing service endpoints every {:?} (down after {} failures, up after {} successes)",
+            config.interval, config.failure_threshold, config.success_threshold
+        );
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(config.interval);
+            loop {
+                ticker.tick().await;
+                let targets = table.lock().unwrap().targets();
+                for (address, probe) in targets {
+                    let table = table.clone();
+                    let metrics = metrics.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(health::stagger(&address, config.interval)).await;
+                        let result = health::probe(&address, &probe, config.timeout).await;
+                        let down = {
+                            let mut table = table.lock().unwrap();
+                            table.record(&address, result, &config, Utc::now());
+                            table.down().len()
+                        };
+                        metrics.gauge_set(ENDPOINTS_DOWN_METRIC, down as i64);
+                    });
+                }
+            }
+        })
+    }
+
+    /// Probe status of every backend, for the agent's debug API.
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.upstreams.health.lock().unwrap().snapshot()
+    }
+
     /// Start the Pingora-based service proxy in a background task.
     pub async fn start(&self) -> anyhow::Result<()> {
         info!(
@@ -240,7 +607,7 @@ impl ServiceProxy {
 
         let handler = ServiceProxyHandler {
             routing_table: self.routing_table.clone(),
-            lb_table: self.lb_table.clone(),
+            upstreams: self.upstreams.clone(),
         };
 
         let mut proxy = http_proxy_service(&server.configuration, handler);
@@ -256,3 +623,266 @@ impl ServiceProxy {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn round_trip(stream: &mut TcpStream, msg: &[u8]) -> Vec<u8> {
+        stream.write_all(msg).await.unwrap();
+        let mut buf = vec![0u8; msg.len()];
+        stream.read_exact(&mut buf).await.unwrap();
+        buf
+    }
+
+    #[tokio::test]
+    async fn test_node_port_forwards_to_endpoints() {
+        // TCP echo server standing in for the pod.
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend_port = backend.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = backend.accept().await {
+                tokio::spawn(async move {
+                    let (mut r, mut w) = stream.split();
+                    let _ = tokio::io::copy(&mut r, &mut w).await;
+                });
+            }
+        });
+
+        let node_port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let service: pkg_types::service::Service = serde_json::from_value(serde_json::json!({
+            "id": "svc-web",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "ports": [{
+                    "name": "http",
+                    "port": 80,
+                    "target_port": backend_port,
+                    "node_port": node_port,
+                }],
+                "service_type": "NodePort",
+            },
+            "cluster_ip": "10.43.0.10",
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let endpoint: pkg_types::endpoint::Endpoint = serde_json::from_value(serde_json::json!({
+            "id": "ep-web",
+            "service_id": "svc-web",
+            "service_name": "web",
+            "namespace": "default",
+            "addresses": [{ "ip": "127.0.0.1" }],
+            "ports": [],
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+
+        let proxy = ServiceProxy::new(0, Arc::new(MetricsRegistry::new()));
+        proxy
+            .update_routes(&[service], &[endpoint], &HashMap::new())
+            .await;
+
+        let mut stream = TcpStream::connect(("127.0.0.1", node_port)).await.unwrap();
+        assert_eq!(round_trip(&mut stream, b"ping").await, b"ping");
+
+        // Removing the service releases the port but keeps open connections.
+        proxy.update_routes(&[], &[], &HashMap::new()).await;
+        assert_eq!(round_trip(&mut stream, b"pong").await, b"pong");
+        let mut refused = false;
+        for _ in 0..50 {
+            if TcpStream::connect(("127.0.0.1", node_port)).await.is_err() {
+                refused = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(refused, "node port {} still accepting", node_port);
+    }
+
+    #[tokio::test]
+    async fn test_client_ip_affinity() {
+        let service = |affinity: bool| -> pkg_types::service::Service {
+            serde_json::from_value(serde_json::json!({
+                "id": "svc-web",
+                "name": "web",
+                "namespace": "default",
+                "spec": {
+                    "ports": [{ "name": "http", "port": 80, "target_port": 8080 }],
+                    "service_type": "ClusterIP",
+                    "session_affinity": affinity.then_some("ClientIP"),
+                },
+                "cluster_ip": "10.43.0.10",
+                "created_at": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap()
+        };
+        let endpoint = |ips: &[&str]| -> pkg_types::endpoint::Endpoint {
+            serde_json::from_value(serde_json::json!({
+                "id": "ep-web",
+                "service_id": "svc-web",
+                "service_name": "web",
+                "namespace": "default",
+                "addresses": ips.iter().map(|ip| serde_json::json!({ "ip": ip })).collect::<Vec<_>>(),
+                "ports": [],
+                "created_at": "2024-01-01T00:00:00Z",
+            }))
+            .unwrap()
+        };
+        let metrics = Arc::new(MetricsRegistry::new());
+        let proxy = ServiceProxy::new(0, metrics.clone());
+        let all = ["10.42.0.1", "10.42.0.2", "10.42.0.3"];
+        proxy
+            .update_routes(&[service(true)], &[endpoint(&all)], &HashMap::new())
+            .await;
+
+        let backend = |source: &'static str| {
+            let upstreams = proxy.upstreams();
+            async move {
+                upstreams
+                    .backend("10.43.0.10:80", Some(source))
+                    .await
+                    .unwrap()
+                    .addr
+                    .to_string()
+            }
+        };
+        let pinned = backend("10.0.0.7").await;
+        for _ in 0..10 {
+            assert_eq!(backend("10.0.0.7").await, pinned);
+        }
+        assert!(
+            metrics
+                .render()
+                .contains(&format!("{} 1", SESSION_AFFINITY_ENTRIES_METRIC))
+        );
+
+        // The pinned pod goes away: the client moves, then stays put.
+        let rest: Vec<&str> = all
+            .iter()
+            .copied()
+            .filter(|ip| !pinned.starts_with(&format!("{}:", ip)))
+            .collect();
+        proxy
+            .update_routes(&[service(true)], &[endpoint(&rest)], &HashMap::new())
+            .await;
+        let moved = backend("10.0.0.7").await;
+        assert_ne!(moved, pinned);
+        for _ in 0..10 {
+            assert_eq!(backend("10.0.0.7").await, moved);
+        }
+
+        // Without affinity the pins are dropped and requests rotate.
+        proxy
+            .update_routes(&[service(false)], &[endpoint(&all)], &HashMap::new())
+            .await;
+        assert!(proxy.upstreams.affinity.lock().unwrap().is_empty());
+        let mut seen = HashSet::new();
+        for _ in 0..6 {
+            seen.insert(backend("10.0.0.7").await);
+        }
+        assert!(seen.len() > 1);
+    }
+
+    #[tokio::test]
+    async fn test_failing_endpoint_is_taken_out() {
+        // Two pods on the same port, on different loopback addresses.
+        let listen = |ip: &'static str, port: u16| async move {
+            let listener = TcpListener::bind((ip, port)).await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let task = tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    drop(stream);
+                }
+            });
+            (port, task)
+        };
+        let (port, first) = listen("127.0.0.1", 0).await;
+        let (_, _second) = listen("127.0.0.2", port).await;
+
+        let service: pkg_types::service::Service = serde_json::from_value(serde_json::json!({
+            "id": "svc-web",
+            "name": "web",
+            "namespace": "default",
+            "spec": {
+                "ports": [{ "name": "http", "port": 80, "target_port": port }],
+                "service_type": "ClusterIP",
+            },
+            "cluster_ip": "10.43.0.10",
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let endpoint: pkg_types::endpoint::Endpoint = serde_json::from_value(serde_json::json!({
+            "id": "ep-web",
+            "service_id": "svc-web",
+            "service_name": "web",
+            "namespace": "default",
+            "addresses": [{ "ip": "127.0.0.1" }, { "ip": "127.0.0.2" }],
+            "ports": [],
+            "created_at": "2024-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let metrics = Arc::new(MetricsRegistry::new());
+        let proxy = ServiceProxy::new(0, metrics.clone());
+        proxy
+            .update_routes(&[service], &[endpoint], &HashMap::new())
+            .await;
+        let config = HealthConfig {
+            interval: Duration::from_millis(100),
+            timeout: Duration::from_millis(100),
+            failure_threshold: 2,
+            success_threshold: 1,
+        };
+        let checks = proxy.spawn_health_checks(config);
+
+        let backends = || async {
+            let mut seen = HashSet::new();
+            for _ in 0..6 {
+                let backend = proxy
+                    .upstreams
+                    .backend("10.43.0.10:80", None)
+                    .await
+                    .unwrap();
+                seen.insert(backend.addr.to_string());
+            }
+            seen
+        };
+        let dead = format!("127.0.0.1:{}", port);
+        assert!(backends().await.contains(&dead));
+
+        // The pod stops accepting connections. Within the failure threshold
+        // (plus one interval for the probe offset) traffic moves away.
+        first.abort();
+        let _ = first.await;
+        tokio::time::sleep(config.interval * (config.failure_threshold + 1) + config.interval / 2)
+            .await;
+        assert_eq!(
+            backends().await,
+            HashSet::from([format!("127.0.0.2:{}", port)])
+        );
+        let health = proxy.endpoint_health();
+        assert_eq!(health.len(), 2);
+        assert_eq!(health[0].address, dead);
+        assert!(!health[0].healthy);
+        assert!(health[0].last_error.is_some());
+        assert!(health[1].healthy);
+        assert!(
+            metrics
+                .render()
+                .contains(&format!("{} 1", ENDPOINTS_DOWN_METRIC))
+        );
+
+        // It comes back after a good probe.
+        let (_, _restarted) = listen("127.0.0.1", port).await;
+        tokio::time::sleep(config.interval * (config.success_threshold + 1) + config.interval / 2)
+            .await;
+        assert!(backends().await.contains(&dead));
+        assert!(proxy.endpoint_health().iter().all(|h| h.healthy));
+        checks.abort();
+    }
+}
@@ -1,36 +1,130 @@
-use pkg_types::node::{Node, NodeStatus};
-use pkg_types::pod::{Pod, TaintEffect, TolerationOperator};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use pkg_types::node::{HOSTNAME_LABEL, Node, NodeStatus, Taint};
+use pkg_types::pod::{Pod, PodStatus, TaintEffect};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use tracing::info;
 
-/// Round-robin scheduler with filtering for taints, tolerations,
-/// node affinity, and resource availability.
+/// How the scheduler picks among nodes that passed filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ScoringStrategy {
+    /// Rotate through eligible nodes (no scoring).
+    #[default]
+    RoundRobin,
+    /// Prefer the node with the largest free cpu/memory fraction (spread).
+    LeastAllocated,
+    /// Prefer the node with the smallest free cpu/memory fraction (bin-packing).
+    MostAllocated,
+}
+
+impl std::fmt::Display for ScoringStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScoringStrategy::RoundRobin => write!(f, "RoundRobin"),
+            ScoringStrategy::LeastAllocated => write!(f, "LeastAllocated"),
+            ScoringStrategy::MostAllocated => write!(f, "MostAllocated"),
+        }
+    }
+}
+
+impl std::str::FromStr for ScoringStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "roundrobin" => Ok(ScoringStrategy::RoundRobin),
+            "leastallocated" => Ok(ScoringStrategy::LeastAllocated),
+            "mostallocated" => Ok(ScoringStrategy::MostAllocated),
+            other => anyhow::bail!("unknown scheduling strategy '{}'", other),
+        }
+    }
+}
+
+/// Scheduler tuning knobs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    #[serde(default)]
+    pub strategy: ScoringStrategy,
+}
+
+/// Scheduler with filtering for taints, tolerations, node affinity, and
+/// resource availability, followed by a configurable scoring phase.
 pub struct Scheduler {
+    /// Changed at runtime when the server's config file is reloaded.
+    config: RwLock<SchedulerConfig>,
     round_robin_index: AtomicUsize,
+    /// Pods the scheduler tried to place, and those that fit on no node.
+    attempts: AtomicU64,
+    failures: AtomicU64,
 }
 
 impl Scheduler {
     pub fn new() -> Self {
+        Self::new_with_config(SchedulerConfig::default())
+    }
+
+    pub fn new_with_config(config: SchedulerConfig) -> Self {
         Self {
+            config: RwLock::new(config),
             round_robin_index: AtomicUsize::new(0),
+            attempts: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
         }
     }
 
+    pub fn config(&self) -> SchedulerConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Score nodes with `strategy` from the next pod on.
+    pub fn set_strategy(&self, strategy: ScoringStrategy) {
+        self.config.write().unwrap().strategy = strategy;
+    }
+
+    /// Scheduling attempts so far, one per pod passed to [`schedule`](Self::schedule).
+    pub fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    /// Attempts that found no eligible node.
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
     /// Schedule a pod to a node. Returns the node name if a suitable node is found.
-    pub fn schedule(&self, pod: &Pod, nodes: &[Node]) -> Option<String> {
+    ///
+    /// `existing_pods` are the pods already bound to nodes; they are used to
+    /// enforce `spec.pod_anti_affinity`.
+    pub fn schedule(&self, pod: &Pod, nodes: &[Node], existing_pods: &[Pod]) -> Option<String> {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
         let eligible: Vec<&Node> = nodes
             .iter()
             .filter(|n| self.is_node_eligible(n, pod))
+            .filter(|n| !violates_anti_affinity(n, pod, existing_pods))
             .collect();
 
         if eligible.is_empty() {
             info!("No eligible nodes for pod {}/{}", pod.namespace, pod.name);
+            self.failures.fetch_add(1, Ordering::Relaxed);
             return None;
         }
 
-        // Round-robin selection among eligible nodes
-        let idx = self.round_robin_index.fetch_add(1, Ordering::Relaxed) % eligible.len();
-        let selected = eligible[idx];
+        // Preference step: avoid nodes with untolerated PreferNoSchedule taints
+        // unless they are the only ones left.
+        let preferred: Vec<&Node> = eligible
+            .iter()
+            .copied()
+            .filter(|n| !has_untolerated_prefer_no_schedule(n, pod))
+            .collect();
+        let candidates = if preferred.is_empty() {
+            &eligible
+        } else {
+            &preferred
+        };
+
+        let selected = self.select_node(candidates, pod);
 
         info!(
             "Scheduled pod {}/{} → node {} ({})",
@@ -39,8 +133,79 @@ impl Scheduler {
         Some(selected.name.clone())
     }
 
-    /// Check if a node is eligible to run this pod.
-    fn is_node_eligible(&self, node: &Node, pod: &Pod) -> bool {
+    /// Schedule a queue of pending pods in priority order.
+    ///
+    /// Pods are ordered by descending `spec.priority`, falling back to
+    /// `created_at` (oldest first) for equal priorities. Each assignment is
+    /// charged against a working copy of the node's allocation so later pods
+    /// in the batch see the reduced capacity. A pod that fits nowhere is
+    /// returned with `None` and does not block the pods behind it.
+    ///
+    /// Pods placed earlier in the batch count towards anti-affinity for the
+    /// ones that follow, alongside `existing_pods`.
+    ///
+    /// Returns `(pod_id, node_name)` pairs in the order they were scheduled.
+    pub fn schedule_batch(
+        &self,
+        pods: &[Pod],
+        nodes: &[Node],
+        existing_pods: &[Pod],
+    ) -> Vec<(String, Option<String>)> {
+        let mut queue: Vec<&Pod> = pods.iter().collect();
+        queue.sort_by(|a, b| {
+            b.spec
+                .priority
+                .cmp(&a.spec.priority)
+                .then_with(|| a.created_at.cmp(&b.created_at))
+        });
+
+        let mut working: Vec<Node> = nodes.to_vec();
+        let mut placed: Vec<Pod> = existing_pods.to_vec();
+        let mut assignments = Vec::with_capacity(queue.len());
+        for pod in queue {
+            let selected = self.schedule(pod, &working, &placed);
+            if let Some(ref node_name) = selected {
+                if let Some(node) = working.iter_mut().find(|n| &n.name == node_name) {
+                    let (cpu, mem) = pod_requests(pod);
+                    node.allocated.cpu_millis += cpu;
+                    node.allocated.memory_bytes += mem;
+                }
+                let mut bound = pod.clone();
+                bound.node_name = Some(node_name.clone());
+                placed.push(bound);
+            }
+            assignments.push((pod.id.clone(), selected));
+        }
+        assignments
+    }
+
+    /// Pick one node out of the filtered set according to the configured strategy.
+    fn select_node<'a>(&self, eligible: &[&'a Node], pod: &Pod) -> &'a Node {
+        let strategy = self.config.read().unwrap().strategy;
+        match strategy {
+            ScoringStrategy::RoundRobin => {
+                let idx = self.round_robin_index.fetch_add(1, Ordering::Relaxed) % eligible.len();
+                eligible[idx]
+            }
+            strategy => eligible
+                .iter()
+                .copied()
+                .max_by_key(|n| (score_node(strategy, n, pod), Reverse(n.name.as_str())))
+                .expect("eligible is non-empty"),
+        }
+    }
+
+    /// Check if a node is eligible to run this pod: Ready, not cordoned,
+    /// matching its node affinity, with no untolerated `NoSchedule` or
+    /// `NoExecute` taint, and with room for its resource requests.
+    pub fn is_node_eligible(&self, node: &Node, pod: &Pod) -> bool {
+        self.passes_node_filters(node, pod) && fits_resources(node, pod)
+    }
+
+    /// Everything [`Scheduler::is_node_eligible`] checks except free
+    /// capacity. For a pod already bound to the node, whose requests are part
+    /// of the node's allocation.
+    pub fn passes_node_filters(&self, node: &Node, pod: &Pod) -> bool {
         // 1. Node must be Ready
         if node.status != NodeStatus::Ready {
             return false;
@@ -53,67 +218,169 @@ impl Scheduler {
 
         // 2. Check node affinity (all required labels must match)
         for (key, value) in &pod.spec.node_affinity {
-            match node.labels.get(key) {
-                Some(v) if v == value => {}
-                _ => return false,
+            if node.label(key) != Some(value.as_str()) {
+                return false;
             }
         }
 
         // 3. Check taints & tolerations
         for taint in &node.taints {
-            let tolerated = pod.spec.tolerations.iter().any(|t| {
-                if t.key != taint.key {
-                    return false;
-                }
-                match t.operator {
-                    TolerationOperator::Exists => true,
-                    TolerationOperator::Equal => t.value == taint.value,
-                }
-            });
             // If a NoSchedule taint is not tolerated, skip this node
-            if !tolerated {
+            if !tolerates(pod, taint) {
                 match taint.effect {
                     TaintEffect::NoSchedule | TaintEffect::NoExecute => return false,
-                    TaintEffect::PreferNoSchedule => {} // soft preference, don't reject
+                    TaintEffect::PreferNoSchedule => {} // soft preference, handled in schedule()
                 }
             }
         }
 
-        // 4. Check resource availability
-        let pod_cpu: u64 = pod
-            .spec
-            .containers
-            .iter()
-            .map(|c| c.resources.cpu_millis)
-            .sum();
-        let pod_mem: u64 = pod
-            .spec
-            .containers
-            .iter()
-            .map(|c| c.resources.memory_bytes)
-            .sum();
-
-        if node.capacity.cpu_millis > 0 {
-            let available_cpu = node
-                .capacity
-                .cpu_millis
-                .saturating_sub(node.allocated.cpu_millis);
-            if pod_cpu > available_cpu {
-                return false;
-            }
+        true
+    }
+}
+
+/// Whether the node has room for the pod's resource requests.
+fn fits_resources(node: &Node, pod: &Pod) -> bool {
+    let (pod_cpu, pod_mem) = pod_requests(pod);
+
+    if node.capacity.cpu_millis > 0 {
+        let available_cpu = node
+            .capacity
+            .cpu_millis
+            .saturating_sub(node.allocated.cpu_millis);
+        if pod_cpu > available_cpu {
+            return false;
         }
-        if node.capacity.memory_bytes > 0 {
-            let available_mem = node
-                .capacity
-                .memory_bytes
-                .saturating_sub(node.allocated.memory_bytes);
-            if pod_mem > available_mem {
-                return false;
+    }
+    if node.capacity.memory_bytes > 0 {
+        let available_mem = node
+            .capacity
+            .memory_bytes
+            .saturating_sub(node.allocated.memory_bytes);
+        if pod_mem > available_mem {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Whether any of the pod's tolerations matches the taint.
+pub fn tolerates(pod: &Pod, taint: &Taint) -> bool {
+    pod.spec.tolerations.iter().any(|t| t.matches(taint))
+}
+
+/// Pin the pod to the node holding its claims' volumes, by node affinity on
+/// [`HOSTNAME_LABEL`]. `claim_nodes` maps the bound claims of the pod's
+/// namespace to their volume's node; unbound claims leave the pod free, and
+/// the provisioner follows it. Fails when the volumes are on different
+/// nodes, or the pod's own affinity names another one.
+pub fn pin_to_volume_nodes(
+    pod: &mut Pod,
+    claim_nodes: &HashMap<String, String>,
+) -> Result<(), String> {
+    let mut pinned: Option<(&str, &str)> = None;
+    for claim in pod.spec.claim_names() {
+        let Some(node) = claim_nodes.get(claim) else {
+            continue;
+        };
+        match pinned {
+            Some((other, other_node)) if other_node != node => {
+                return Err(format!(
+                    "claims {} and {} are bound to volumes on different nodes ({} and {})",
+                    other, claim, other_node, node
+                ));
             }
+            Some(_) => {}
+            None => pinned = Some((claim, node)),
+        }
+    }
+    let Some((claim, node)) = pinned else {
+        return Ok(());
+    };
+    match pod.spec.node_affinity.get(HOSTNAME_LABEL) {
+        Some(wanted) if wanted != node => Err(format!(
+            "claim {} is bound to a volume on node {}, but the pod's node affinity requires {}",
+            claim, node, wanted
+        )),
+        Some(_) => Ok(()),
+        None => {
+            let node = node.to_string();
+            pod.spec
+                .node_affinity
+                .insert(HOSTNAME_LABEL.to_string(), node);
+            Ok(())
         }
+    }
+}
 
-        true
+/// Whether the node carries a PreferNoSchedule taint the pod does not tolerate.
+fn has_untolerated_prefer_no_schedule(node: &Node, pod: &Pod) -> bool {
+    node.taints
+        .iter()
+        .any(|t| matches!(t.effect, TaintEffect::PreferNoSchedule) && !tolerates(pod, t))
+}
+
+/// Whether placing the pod on this node would co-locate it with a pod that
+/// matches one of its anti-affinity selectors.
+///
+/// Only active pods in the same namespace that are bound to the node count.
+fn violates_anti_affinity(node: &Node, pod: &Pod, existing_pods: &[Pod]) -> bool {
+    if pod.spec.pod_anti_affinity.is_empty() {
+        return false;
+    }
+    existing_pods.iter().any(|other| {
+        other.id != pod.id
+            && other.namespace == pod.namespace
+            && other.node_name.as_deref() == Some(node.name.as_str())
+            && !matches!(other.status, PodStatus::Succeeded | PodStatus::Failed)
+            && pod
+                .spec
+                .pod_anti_affinity
+                .iter()
+                .any(|sel| sel.matches(&other.labels))
+    })
+}
+
+/// Score a node in `0..=100` for the given strategy, assuming the pod is placed on it.
+///
+/// Each resource with a known capacity contributes its free (LeastAllocated) or
+/// used (MostAllocated) percentage; the score is the mean over those resources.
+/// A node that reports no capacity at all scores 0.
+fn score_node(strategy: ScoringStrategy, node: &Node, pod: &Pod) -> u64 {
+    let (pod_cpu, pod_mem) = pod_requests(pod);
+    let dims = [
+        (
+            node.capacity.cpu_millis,
+            node.allocated.cpu_millis.saturating_add(pod_cpu),
+        ),
+        (
+            node.capacity.memory_bytes,
+            node.allocated.memory_bytes.saturating_add(pod_mem),
+        ),
+    ];
+
+    let mut total = 0u64;
+    let mut counted = 0u64;
+    for (capacity, used) in dims {
+        if capacity == 0 {
+            continue;
+        }
+        let used = used.min(capacity);
+        let pct = match strategy {
+            ScoringStrategy::MostAllocated => used as u128 * 100 / capacity as u128,
+            _ => (capacity - used) as u128 * 100 / capacity as u128,
+        };
+        total += pct as u64;
+        counted += 1;
     }
+    total.checked_div(counted).unwrap_or(0)
+}
+
+/// Total (cpu_millis, memory_bytes) requested by all containers of a pod.
+fn pod_requests(pod: &Pod) -> (u64, u64) {
+    pod.spec.containers.iter().fold((0, 0), |(cpu, mem), c| {
+        (cpu + c.resources.cpu_millis, mem + c.resources.memory_bytes)
+    })
 }
 
 impl Default for Scheduler {
@@ -126,7 +393,7 @@ impl Default for Scheduler {
 mod tests {
     use super::*;
     use chrono::Utc;
-    use pkg_types::pod::{ContainerSpec, PodSpec, PodStatus, ResourceRequirements};
+    use pkg_types::pod::{ContainerSpec, LabelSelector, PodSpec, ResourceRequirements};
     use std::collections::HashMap;
 
     fn make_node(name: &str, status: NodeStatus) -> Node {
@@ -148,6 +415,12 @@ mod tests {
             agent_api_port: pkg_constants::network::DEFAULT_AGENT_API_PORT,
             wg_public_key: None,
             wg_endpoint: None,
+            usage: ResourceRequirements::default(),
+            running_containers: 0,
+            agent_version: None,
+            certificate_expires_at: None,
+            pod_cidr: None,
+            conditions: Vec::new(),
         }
     }
 
@@ -157,6 +430,10 @@ mod tests {
             name: name.to_string(),
             namespace: "default".to_string(),
             vpc_name: None,
+            pod_ip: None,
+            ready: false,
+            mirror: false,
+            container_statuses: Vec::new(),
             ghost_ipv6: None,
             spec: PodSpec {
                 vpc: None,
@@ -171,11 +448,23 @@ mod tests {
                         cpu_millis: 100,
                         memory_bytes: 128_000_000,
                     },
+                    limits: ResourceRequirements::default(),
                     volume_mounts: vec![],
+                    liveness_probe: None,
+                    readiness_probe: None,
+                    image_pull_policy: Default::default(),
+                    security_context: Default::default(),
                 }],
+                init_containers: vec![],
                 node_affinity: HashMap::new(),
                 tolerations: vec![],
                 volumes: vec![],
+                priority: 0,
+                priority_class_name: None,
+                pod_anti_affinity: vec![],
+                termination_grace_period_seconds: None,
+                restart_policy: Default::default(),
+                image_pull_secrets: vec![],
             },
             status: PodStatus::Pending,
             status_message: None,
@@ -186,6 +475,7 @@ mod tests {
             restart_count: 0,
             runtime_info: None,
             created_at: Utc::now(),
+            resource_version: 0,
         }
     }
 
@@ -198,8 +488,8 @@ mod tests {
         ];
         let pod = make_pod("test-pod");
 
-        let result1 = scheduler.schedule(&pod, &nodes);
-        let result2 = scheduler.schedule(&pod, &nodes);
+        let result1 = scheduler.schedule(&pod, &nodes, &[]);
+        let result2 = scheduler.schedule(&pod, &nodes, &[]);
 
         assert!(result1.is_some());
         assert!(result2.is_some());
@@ -215,7 +505,7 @@ mod tests {
         ];
         let pod = make_pod("test-pod");
 
-        let result = scheduler.schedule(&pod, &nodes);
+        let result = scheduler.schedule(&pod, &nodes, &[]);
         assert_eq!(result, Some("node-2".to_string()));
     }
 
@@ -228,7 +518,436 @@ mod tests {
         ];
         let pod = make_pod("test-pod");
 
-        let result = scheduler.schedule(&pod, &nodes);
+        let result = scheduler.schedule(&pod, &nodes, &[]);
         assert!(result.is_none());
+        assert_eq!((scheduler.attempts(), scheduler.failures()), (1, 1));
+    }
+
+    #[test]
+    fn test_node_filters_ignore_capacity() {
+        let scheduler = Scheduler::new();
+        let mut node = make_node("node-1", NodeStatus::Ready);
+        node.allocated.cpu_millis = node.capacity.cpu_millis;
+        let pod = make_pod("test-pod");
+
+        assert!(!scheduler.is_node_eligible(&node, &pod));
+        assert!(scheduler.passes_node_filters(&node, &pod));
+
+        node.unschedulable = true;
+        assert!(!scheduler.passes_node_filters(&node, &pod));
+    }
+
+    fn make_pod_with(name: &str, priority: i32, cpu_millis: u64, age_secs: i64) -> Pod {
+        let mut pod = make_pod(name);
+        pod.spec.priority = priority;
+        pod.spec.containers[0].resources.cpu_millis = cpu_millis;
+        pod.created_at = Utc::now() - chrono::Duration::seconds(age_secs);
+        pod
+    }
+
+    #[test]
+    fn test_batch_orders_by_priority() {
+        let scheduler = Scheduler::new();
+        let nodes = vec![make_node("node-1", NodeStatus::Ready)];
+        let pods = vec![
+            make_pod_with("low", 0, 100, 30),
+            make_pod_with("high", 1000, 100, 10),
+            make_pod_with("mid", 100, 100, 20),
+        ];
+
+        let result = scheduler.schedule_batch(&pods, &nodes, &[]);
+        let order: Vec<&str> = result.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(order, vec!["high-id", "mid-id", "low-id"]);
+        assert!(result.iter().all(|(_, node)| node.is_some()));
+    }
+
+    #[test]
+    fn test_batch_equal_priority_is_fifo() {
+        let scheduler = Scheduler::new();
+        let nodes = vec![make_node("node-1", NodeStatus::Ready)];
+        let pods = vec![
+            make_pod_with("newest", 5, 100, 1),
+            make_pod_with("oldest", 5, 100, 60),
+            make_pod_with("middle", 5, 100, 30),
+        ];
+
+        let result = scheduler.schedule_batch(&pods, &nodes, &[]);
+        let order: Vec<&str> = result.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(order, vec!["oldest-id", "middle-id", "newest-id"]);
+    }
+
+    #[test]
+    fn test_batch_unschedulable_high_priority_does_not_block() {
+        let scheduler = Scheduler::new();
+        let nodes = vec![make_node("node-1", NodeStatus::Ready)];
+        let pods = vec![
+            make_pod_with("huge", 1000, 64_000, 10),
+            make_pod_with("small", 0, 100, 5),
+        ];
+
+        let result = scheduler.schedule_batch(&pods, &nodes, &[]);
+        assert_eq!(result[0], ("huge-id".to_string(), None));
+        assert_eq!(
+            result[1],
+            ("small-id".to_string(), Some("node-1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_batch_charges_capacity_in_priority_order() {
+        let scheduler = Scheduler::new();
+        let nodes = vec![make_node("node-1", NodeStatus::Ready)];
+        // node-1 has 4000m CPU: only one 3000m pod fits, and the high-priority one wins.
+        let pods = vec![
+            make_pod_with("low", 0, 3000, 60),
+            make_pod_with("high", 10, 3000, 1),
+        ];
+
+        let result = scheduler.schedule_batch(&pods, &nodes, &[]);
+        assert_eq!(
+            result[0],
+            ("high-id".to_string(), Some("node-1".to_string()))
+        );
+        assert_eq!(result[1], ("low-id".to_string(), None));
+    }
+
+    fn make_sized_node(name: &str, cpu_millis: u64, memory_bytes: u64) -> Node {
+        let mut node = make_node(name, NodeStatus::Ready);
+        node.capacity = ResourceRequirements {
+            cpu_millis,
+            memory_bytes,
+        };
+        node
+    }
+
+    fn scheduler_with(strategy: ScoringStrategy) -> Scheduler {
+        Scheduler::new_with_config(SchedulerConfig { strategy })
+    }
+
+    #[test]
+    fn test_least_allocated_prefers_larger_node() {
+        let scheduler = scheduler_with(ScoringStrategy::LeastAllocated);
+        let nodes = vec![
+            make_sized_node("small", 2000, 4_000_000_000),
+            make_sized_node("large", 16000, 32_000_000_000),
+        ];
+        let pod = make_pod("test-pod");
+
+        assert_eq!(
+            scheduler.schedule(&pod, &nodes, &[]),
+            Some("large".to_string())
+        );
+    }
+
+    #[test]
+    fn test_most_allocated_prefers_smaller_node() {
+        let scheduler = scheduler_with(ScoringStrategy::MostAllocated);
+        let nodes = vec![
+            make_sized_node("large", 16000, 32_000_000_000),
+            make_sized_node("small", 2000, 4_000_000_000),
+        ];
+        let pod = make_pod("test-pod");
+
+        assert_eq!(
+            scheduler.schedule(&pod, &nodes, &[]),
+            Some("small".to_string())
+        );
+    }
+
+    #[test]
+    fn test_strategy_changes_at_runtime() {
+        let scheduler = scheduler_with(ScoringStrategy::LeastAllocated);
+        let nodes = vec![
+            make_sized_node("large", 16000, 32_000_000_000),
+            make_sized_node("small", 2000, 4_000_000_000),
+        ];
+        let pod = make_pod("test-pod");
+        assert_eq!(
+            scheduler.schedule(&pod, &nodes, &[]),
+            Some("large".to_string())
+        );
+
+        scheduler.set_strategy(ScoringStrategy::MostAllocated);
+        assert_eq!(scheduler.config().strategy, ScoringStrategy::MostAllocated);
+        assert_eq!(
+            scheduler.schedule(&pod, &nodes, &[]),
+            Some("small".to_string())
+        );
+    }
+
+    #[test]
+    fn test_least_allocated_spreads_batch() {
+        let scheduler = scheduler_with(ScoringStrategy::LeastAllocated);
+        let nodes = vec![
+            make_sized_node("node-a", 4000, 8_000_000_000),
+            make_sized_node("node-b", 4000, 8_000_000_000),
+        ];
+        let pods: Vec<Pod> = (0..4)
+            .map(|i| make_pod_with(&format!("p{}", i), 0, 1000, 10 - i))
+            .collect();
+
+        let result = scheduler.schedule_batch(&pods, &nodes, &[]);
+        let placed: Vec<&str> = result.iter().filter_map(|(_, n)| n.as_deref()).collect();
+        assert_eq!(placed, vec!["node-a", "node-b", "node-a", "node-b"]);
+    }
+
+    #[test]
+    fn test_most_allocated_packs_batch() {
+        let scheduler = scheduler_with(ScoringStrategy::MostAllocated);
+        let nodes = vec![
+            make_sized_node("node-a", 4000, 8_000_000_000),
+            make_sized_node("node-b", 4000, 8_000_000_000),
+        ];
+        let pods: Vec<Pod> = (0..5)
+            .map(|i| make_pod_with(&format!("p{}", i), 0, 1000, 10 - i))
+            .collect();
+
+        let result = scheduler.schedule_batch(&pods, &nodes, &[]);
+        let placed: Vec<&str> = result.iter().filter_map(|(_, n)| n.as_deref()).collect();
+        // node-a fills up (4 x 1000m) before anything lands on node-b.
+        assert_eq!(
+            placed,
+            vec!["node-a", "node-a", "node-a", "node-a", "node-b"]
+        );
+    }
+
+    #[test]
+    fn test_scoring_ties_break_on_node_name() {
+        for strategy in [
+            ScoringStrategy::LeastAllocated,
+            ScoringStrategy::MostAllocated,
+        ] {
+            let scheduler = scheduler_with(strategy);
+            let nodes = vec![
+                make_sized_node("node-c", 4000, 8_000_000_000),
+                make_sized_node("node-a", 4000, 8_000_000_000),
+                make_sized_node("node-b", 4000, 8_000_000_000),
+            ];
+            let pod = make_pod("test-pod");
+            for _ in 0..3 {
+                assert_eq!(
+                    scheduler.schedule(&pod, &nodes, &[]),
+                    Some("node-a".to_string())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_strategy_from_str() {
+        assert_eq!(
+            "least-allocated".parse::<ScoringStrategy>().unwrap(),
+            ScoringStrategy::LeastAllocated
+        );
+        assert_eq!(
+            "MostAllocated".parse::<ScoringStrategy>().unwrap(),
+            ScoringStrategy::MostAllocated
+        );
+        assert_eq!(
+            "round_robin".parse::<ScoringStrategy>().unwrap(),
+            ScoringStrategy::RoundRobin
+        );
+        assert!("random".parse::<ScoringStrategy>().is_err());
+    }
+
+    fn prefer_no_schedule(key: &str) -> Taint {
+        Taint {
+            key: key.to_string(),
+            value: String::new(),
+            effect: TaintEffect::PreferNoSchedule,
+        }
+    }
+
+    #[test]
+    fn test_prefer_no_schedule_avoided_when_alternative_fits() {
+        let scheduler = Scheduler::new();
+        let mut tainted = make_node("tainted", NodeStatus::Ready);
+        tainted.taints.push(prefer_no_schedule("dedicated"));
+        let nodes = vec![tainted, make_node("clean", NodeStatus::Ready)];
+        let pod = make_pod("test-pod");
+
+        // Round-robin would alternate; the preference step must pin it to the clean node.
+        for _ in 0..4 {
+            assert_eq!(
+                scheduler.schedule(&pod, &nodes, &[]),
+                Some("clean".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn test_prefer_no_schedule_used_as_last_resort() {
+        let scheduler = Scheduler::new();
+        let mut tainted = make_node("tainted", NodeStatus::Ready);
+        tainted.taints.push(prefer_no_schedule("dedicated"));
+        let mut full = make_node("clean", NodeStatus::Ready);
+        full.allocated.cpu_millis = full.capacity.cpu_millis;
+        let nodes = vec![tainted, full];
+        let pod = make_pod("test-pod");
+
+        assert_eq!(
+            scheduler.schedule(&pod, &nodes, &[]),
+            Some("tainted".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tolerated_prefer_no_schedule_is_not_penalized() {
+        let scheduler = scheduler_with(ScoringStrategy::LeastAllocated);
+        let mut tainted = make_sized_node("tainted", 16000, 32_000_000_000);
+        tainted.taints.push(prefer_no_schedule("dedicated"));
+        let nodes = vec![tainted, make_node("clean", NodeStatus::Ready)];
+        let mut pod = make_pod("test-pod");
+        pod.spec.tolerations.push(pkg_types::pod::Toleration {
+            key: "dedicated".to_string(),
+            operator: pkg_types::pod::TolerationOperator::Exists,
+            value: String::new(),
+            effect: TaintEffect::PreferNoSchedule,
+            toleration_seconds: None,
+        });
+
+        // With the taint tolerated, the larger node wins on score.
+        assert_eq!(
+            scheduler.schedule(&pod, &nodes, &[]),
+            Some("tainted".to_string())
+        );
+    }
+
+    fn make_replica(name: &str, app: &str) -> Pod {
+        let mut pod = make_pod(name);
+        pod.labels.insert("app".to_string(), app.to_string());
+        pod.spec.pod_anti_affinity = vec![LabelSelector {
+            match_labels: HashMap::from([("app".to_string(), app.to_string())]),
+        }];
+        pod
+    }
+
+    #[test]
+    fn test_anti_affinity_spreads_replicas() {
+        let scheduler = Scheduler::new();
+        let nodes = vec![
+            make_node("node-1", NodeStatus::Ready),
+            make_node("node-2", NodeStatus::Ready),
+            make_node("node-3", NodeStatus::Ready),
+        ];
+        // An unrelated pod shifts the round-robin cursor between replicas.
+        let unrelated = make_pod("other");
+        let mut existing: Vec<Pod> = Vec::new();
+        let mut placed = Vec::new();
+        for i in 0..3 {
+            let mut replica = make_replica(&format!("web-{}", i), "web");
+            let node = scheduler
+                .schedule(&replica, &nodes, &existing)
+                .expect("replica should fit");
+            scheduler.schedule(&unrelated, &nodes, &existing);
+            replica.node_name = Some(node.clone());
+            existing.push(replica);
+            placed.push(node);
+        }
+
+        placed.sort();
+        assert_eq!(placed, vec!["node-1", "node-2", "node-3"]);
+    }
+
+    #[test]
+    fn test_anti_affinity_no_node_left() {
+        let scheduler = Scheduler::new();
+        let nodes = vec![
+            make_node("node-1", NodeStatus::Ready),
+            make_node("node-2", NodeStatus::Ready),
+        ];
+        let pods: Vec<Pod> = (0..3)
+            .map(|i| make_replica(&format!("web-{}", i), "web"))
+            .collect();
+
+        let result = scheduler.schedule_batch(&pods, &nodes, &[]);
+        let placed: Vec<Option<&str>> = result.iter().map(|(_, n)| n.as_deref()).collect();
+        assert_eq!(placed.iter().filter(|n| n.is_some()).count(), 2);
+        assert_eq!(placed[2], None);
+    }
+
+    #[test]
+    fn test_anti_affinity_ignores_other_apps_and_namespaces() {
+        let scheduler = Scheduler::new();
+        let nodes = vec![make_node("node-1", NodeStatus::Ready)];
+        let mut other_app = make_replica("db-0", "db");
+        other_app.node_name = Some("node-1".to_string());
+        let mut other_ns = make_replica("web-x", "web");
+        other_ns.namespace = "staging".to_string();
+        other_ns.node_name = Some("node-1".to_string());
+        let mut finished = make_replica("web-done", "web");
+        finished.node_name = Some("node-1".to_string());
+        finished.status = PodStatus::Succeeded;
+
+        let pod = make_replica("web-0", "web");
+        assert_eq!(
+            scheduler.schedule(&pod, &nodes, &[other_app, other_ns, finished]),
+            Some("node-1".to_string())
+        );
+    }
+
+    fn claim_volume(name: &str, claim: &str) -> pkg_types::volume::Volume {
+        pkg_types::volume::Volume {
+            name: name.to_string(),
+            source: pkg_types::volume::VolumeSource::PersistentVolumeClaim {
+                claim_name: claim.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_pin_to_volume_nodes() {
+        let scheduler = Scheduler::new();
+        let nodes = vec![
+            make_node("node-1", NodeStatus::Ready),
+            make_node("node-2", NodeStatus::Ready),
+        ];
+        let claim_nodes: HashMap<String, String> = [
+            ("data".to_string(), "node-2".to_string()),
+            ("logs".to_string(), "node-2".to_string()),
+            ("cache".to_string(), "node-1".to_string()),
+        ]
+        .into();
+
+        let mut pod = make_pod("db-0");
+        pod.spec.volumes = vec![
+            claim_volume("data", "data"),
+            claim_volume("logs", "logs"),
+            claim_volume("scratch", "unbound"),
+        ];
+        pin_to_volume_nodes(&mut pod, &claim_nodes).unwrap();
+        assert_eq!(
+            pod.spec
+                .node_affinity
+                .get(HOSTNAME_LABEL)
+                .map(String::as_str),
+            Some("node-2")
+        );
+        // Pinned on every attempt, not just when the rotation lands there.
+        for _ in 0..3 {
+            assert_eq!(
+                scheduler.schedule(&pod, &nodes, &[]).as_deref(),
+                Some("node-2")
+            );
+        }
+
+        // Only unbound claims: the pod goes anywhere.
+        let mut free = make_pod("db-1");
+        free.spec.volumes = vec![claim_volume("scratch", "unbound")];
+        pin_to_volume_nodes(&mut free, &claim_nodes).unwrap();
+        assert!(free.spec.node_affinity.is_empty());
+
+        let mut split = make_pod("db-2");
+        split.spec.volumes = vec![claim_volume("data", "data"), claim_volume("c", "cache")];
+        let err = pin_to_volume_nodes(&mut split, &claim_nodes).unwrap_err();
+        assert!(err.contains("different nodes"), "{}", err);
+
+        let mut conflicting = make_pod("db-3");
+        conflicting.spec.volumes = vec![claim_volume("data", "data")];
+        conflicting
+            .spec
+            .node_affinity
+            .insert(HOSTNAME_LABEL.to_string(), "node-1".to_string());
+        assert!(pin_to_volume_nodes(&mut conflicting, &claim_nodes).is_err());
     }
 }
@@ -0,0 +1,353 @@
+//! etcd, spoken to over its v3 JSON gateway (`POST /v3/kv/...`), which every
+//! etcd since 3.4 serves on its client port. Keys and values travel base64
+//! encoded; conditional writes are etcd transactions comparing the value.
+
+use async_trait::async_trait;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::{Value, json};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use pkg_constants::timings::{ETCD_CONNECT_TIMEOUT_SECS, ETCD_REQUEST_TIMEOUT_SECS};
+
+use super::StateBackend;
+
+/// Environment variable holding the etcd password; overrides the config.
+pub const PASSWORD_ENV: &str = "K3RS_ETCD_PASSWORD";
+
+/// How to reach an etcd cluster.
+#[derive(Debug, Clone, Default)]
+pub struct EtcdConfig {
+    /// Client URLs, e.g. `http://10.0.0.1:2379`; tried in order.
+    pub endpoints: Vec<String>,
+    /// Credentials, when etcd has authentication enabled.
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+/// An etcd cluster. Conditional writes are atomic in etcd, so several
+/// servers can share one.
+pub struct EtcdBackend {
+    config: EtcdConfig,
+    client: reqwest::Client,
+    /// Auth token from the last `authenticate`, when credentials are set.
+    token: Mutex<Option<String>>,
+}
+
+impl EtcdBackend {
+    /// Connect to the cluster in `config`, failing unless one of its
+    /// endpoints answers (and accepts the credentials).
+    pub async fn connect(config: EtcdConfig) -> anyhow::Result<Self> {
+        if config.endpoints.is_empty() {
+            anyhow::bail!("etcd state store needs at least one endpoint");
+        }
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(ETCD_CONNECT_TIMEOUT_SECS))
+            .timeout(Duration::from_secs(ETCD_REQUEST_TIMEOUT_SECS))
+            .build()?;
+        let backend = Self {
+            config,
+            client,
+            token: Mutex::new(None),
+        };
+        backend
+            .get("/registry/")
+            .await
+            .map_err(|e| anyhow::anyhow!("etcd state store unavailable: {}", e))?;
+        info!("Using etcd state store at {}", backend.location());
+        Ok(backend)
+    }
+
+    /// `POST /v3/<method>` to the first endpoint that answers. An expired
+    /// auth token is renewed once.
+    async fn call(&self, method: &str, body: Value) -> anyhow::Result<Value> {
+        match self.call_once(method, &body).await {
+            Err(CallError::Unauthenticated(_)) if self.config.username.is_some() => {
+                *self.token.lock().await = None;
+                self.call_once(method, &body).await.map_err(Into::into)
+            }
+            result => result.map_err(Into::into),
+        }
+    }
+
+    async fn call_once(&self, method: &str, body: &Value) -> Result<Value, CallError> {
+        let token = self.token().await?;
+        self.post(method, body, token.as_deref()).await
+    }
+
+    /// The auth token to send, authenticating first if there is none yet.
+    async fn token(&self) -> Result<Option<String>, CallError> {
+        let Some(name) = &self.config.username else {
+            return Ok(None);
+        };
+        let mut token = self.token.lock().await;
+        if token.is_none() {
+            let password = self.config.password.as_deref().unwrap_or("");
+            let body = json!({ "name": name, "password": password });
+            let resp = self.post("auth/authenticate", &body, None).await?;
+            let Some(issued) = resp["token"].as_str() else {
+                return Err(CallError::Failed(
+                    "etcd authenticate returned no token".to_string(),
+                ));
+            };
+            *token = Some(issued.to_string());
+        }
+        Ok(token.clone())
+    }
+
+    async fn post(
+        &self,
+        method: &str,
+        body: &Value,
+        token: Option<&str>,
+    ) -> Result<Value, CallError> {
+        let mut unreachable = Vec::new();
+        for endpoint in &self.config.endpoints {
+            let url = format!("{}/v3/{}", endpoint.trim_end_matches('/'), method);
+            let mut req = self.client.post(&url).json(body);
+            if let Some(token) = token {
+                req = req.header(reqwest::header::AUTHORIZATION, token);
+            }
+            let resp = match req.send().await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    warn!("etcd endpoint {} unreachable: {}", endpoint, e);
+                    unreachable.push(format!("{}: {}", endpoint, e));
+                    continue;
+                }
+            };
+            let status = resp.status();
+            let body: Value = resp.json().await.unwrap_or_default();
+            if status.is_success() {
+                return Ok(body);
+            }
+            let message = format!(
+                "etcd {} returned {}: {}",
+                method,
+                status,
+                body["message"].as_str().unwrap_or("no error message")
+            );
+            return Err(if status == reqwest::StatusCode::UNAUTHORIZED {
+                CallError::Unauthenticated(message)
+            } else {
+                CallError::Failed(message)
+            });
+        }
+        Err(CallError::Failed(format!(
+            "no etcd endpoint reachable ({})",
+            unreachable.join("; ")
+        )))
+    }
+}
+
+/// Why a gateway call failed.
+enum CallError {
+    /// The auth token was refused, e.g. because it expired.
+    Unauthenticated(String),
+    Failed(String),
+}
+
+impl From<CallError> for anyhow::Error {
+    fn from(e: CallError) -> Self {
+        match e {
+            CallError::Unauthenticated(message) | CallError::Failed(message) => {
+                anyhow::anyhow!(message)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StateBackend for EtcdBackend {
+    fn describe(&self) -> String {
+        "etcd".to_string()
+    }
+
+    fn location(&self) -> String {
+        self.config.endpoints.join(", ")
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let resp = self.call("kv/range", json!({ "key": encode(key) })).await?;
+        match kvs(&resp)?.into_iter().next() {
+            Some((found, value)) if found == key => Ok(Some(value)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn put(&self, key: &str, value: &[u8]) -> anyhow::Result<()> {
+        let body = json!({ "key": encode(key), "value": BASE64.encode(value) });
+        self.call("kv/put", body).await?;
+        Ok(())
+    }
+
+    async fn put_if(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> anyhow::Result<bool> {
+        let compare = match expected {
+            Some(expected) => json!({
+                "key": encode(key),
+                "target": "VALUE",
+                "result": "EQUAL",
+                "value": BASE64.encode(expected),
+            }),
+            // A key that does not exist has create revision 0.
+            None => json!({
+                "key": encode(key),
+                "target": "CREATE",
+                "result": "EQUAL",
+                "create_revision": "0",
+            }),
+        };
+        let body = json!({
+            "compare": [compare],
+            "success": [{ "request_put": { "key": encode(key), "value": BASE64.encode(value) } }],
+        });
+        let resp = self.call("kv/txn", body).await?;
+        // Proto3 JSON leaves out `false`.
+        Ok(resp["succeeded"].as_bool().unwrap_or(false))
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        self.call("kv/deleterange", json!({ "key": encode(key) }))
+            .await?;
+        Ok(())
+    }
+
+    async fn scan(
+        &self,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        // The smallest key after `start_after` is `start_after` + NUL.
+        let start = match start_after {
+            Some(key) if key >= prefix => [key.as_bytes(), b"\0"].concat(),
+            _ => prefix.as_bytes().to_vec(),
+        };
+        let body = json!({
+            "key": BASE64.encode(start),
+            "range_end": BASE64.encode(prefix_end(prefix.as_bytes())),
+            "limit": limit.unwrap_or(0).to_string(),
+            "sort_order": "ASCEND",
+            "sort_target": "KEY",
+        });
+        kvs(&self.call("kv/range", body).await?)
+    }
+
+    async fn close(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn encode(key: &str) -> String {
+    BASE64.encode(key.as_bytes())
+}
+
+/// The key-value pairs of a range response.
+fn kvs(resp: &Value) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let Some(kvs) = resp["kvs"].as_array() else {
+        return Ok(Vec::new());
+    };
+    kvs.iter()
+        .map(|kv| {
+            let field = |name: &str| -> anyhow::Result<Vec<u8>> {
+                Ok(BASE64.decode(kv[name].as_str().unwrap_or(""))?)
+            };
+            Ok((
+                String::from_utf8_lossy(&field("key")?).into_owned(),
+                field("value")?,
+            ))
+        })
+        .collect()
+}
+
+/// The range end covering every key that starts with `prefix`: the prefix
+/// with its last byte below 0xff incremented. `\0` (no end) when there is
+/// none.
+fn prefix_end(prefix: &[u8]) -> Vec<u8> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < 0xff {
+            end.push(last + 1);
+            return end;
+        }
+    }
+    vec![0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::etcd_fake::FakeEtcd;
+
+    #[test]
+    fn test_prefix_end() {
+        assert_eq!(prefix_end(b"/registry/"), b"/registry0");
+        assert_eq!(prefix_end(b"a\xff"), b"b");
+        assert_eq!(prefix_end(b"\xff\xff"), b"\0");
+        assert_eq!(prefix_end(b""), b"\0");
+    }
+
+    /// A port nothing listens on.
+    async fn closed_endpoint() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_to_the_next_endpoint() {
+        let etcd = FakeEtcd::start(None).await;
+        let backend = EtcdBackend::connect(EtcdConfig {
+            endpoints: vec![closed_endpoint().await, etcd.endpoint()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        backend.put("/registry/a", b"1").await.unwrap();
+        assert_eq!(backend.get("/registry/a").await.unwrap().unwrap(), b"1");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_cluster_is_a_clear_error() {
+        let endpoint = closed_endpoint().await;
+        let err = EtcdBackend::connect(EtcdConfig {
+            endpoints: vec![endpoint.clone()],
+            ..Default::default()
+        })
+        .await
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(err.contains("etcd state store unavailable"), "{}", err);
+        assert!(err.contains(&endpoint), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_authenticates_and_renews_its_token() {
+        let etcd = FakeEtcd::start(Some(("root", "hunter2"))).await;
+        let config = |password: &str| EtcdConfig {
+            endpoints: vec![etcd.endpoint()],
+            username: Some("root".to_string()),
+            password: Some(password.to_string()),
+        };
+
+        let err = EtcdBackend::connect(config("wrong"))
+            .await
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("authentication failed"), "{}", err);
+
+        let backend = EtcdBackend::connect(config("hunter2")).await.unwrap();
+        backend.put("/registry/a", b"1").await.unwrap();
+        // The cluster forgets the token; the backend signs in again.
+        etcd.revoke_tokens();
+        assert_eq!(backend.get("/registry/a").await.unwrap().unwrap(), b"1");
+    }
+}
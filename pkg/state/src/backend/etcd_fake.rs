@@ -0,0 +1,228 @@
+//! An in-memory stand-in for etcd's v3 JSON gateway, for tests. It serves
+//! the calls [`EtcdBackend`](super::EtcdBackend) makes with etcd's semantics:
+//! ranges in key order, a revision raised by every write, value and
+//! create-revision compares in transactions, and token authentication when
+//! credentials are set.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Default)]
+struct Cluster {
+    kvs: BTreeMap<Vec<u8>, Vec<u8>>,
+    /// Username and password every request needs a token for, if set.
+    credentials: Option<(String, String)>,
+    tokens: HashSet<String>,
+    issued: u64,
+    /// Raised by every write, as etcd's store revision is.
+    revision: u64,
+}
+
+pub(crate) struct FakeEtcd {
+    endpoint: String,
+    cluster: Arc<Mutex<Cluster>>,
+}
+
+impl FakeEtcd {
+    /// Serve a fresh, empty cluster on a local port.
+    pub async fn start(credentials: Option<(&str, &str)>) -> Self {
+        let cluster = Arc::new(Mutex::new(Cluster {
+            credentials: credentials.map(|(u, p)| (u.to_string(), p.to_string())),
+            ..Default::default()
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let shared = cluster.clone();
+        tokio::spawn(async move {
+            while let Ok((conn, _)) = listener.accept().await {
+                tokio::spawn(serve(conn, shared.clone()));
+            }
+        });
+        Self { endpoint, cluster }
+    }
+
+    pub fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    /// Forget every issued auth token, as etcd does when they expire.
+    pub fn revoke_tokens(&self) {
+        self.cluster.lock().unwrap().tokens.clear();
+    }
+}
+
+/// Answer HTTP/1.1 requests on `conn` until the client closes it.
+async fn serve(conn: TcpStream, cluster: Arc<Mutex<Cluster>>) {
+    let mut conn = BufReader::new(conn);
+    loop {
+        let mut path = String::new();
+        let mut token = None;
+        let mut length = 0;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match conn.read_line(&mut line).await {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if path.is_empty() {
+                path = line.split(' ').nth(1).unwrap_or_default().to_string();
+            } else if let Some((name, value)) = line.split_once(':') {
+                match name.to_ascii_lowercase().as_str() {
+                    "content-length" => length = value.trim().parse().unwrap_or(0),
+                    "authorization" => token = Some(value.trim().to_string()),
+                    _ => {}
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        if conn.read_exact(&mut body).await.is_err() {
+            return;
+        }
+        let body: Value = serde_json::from_slice(&body).unwrap_or_default();
+        let (status, reply) = handle(&cluster, &path, token.as_deref(), &body);
+        let reply = reply.to_string();
+        let head = format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+            status,
+            reply.len()
+        );
+        let conn = conn.get_mut();
+        if conn.write_all(head.as_bytes()).await.is_err()
+            || conn.write_all(reply.as_bytes()).await.is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn handle(
+    cluster: &Mutex<Cluster>,
+    path: &str,
+    token: Option<&str>,
+    body: &Value,
+) -> (&'static str, Value) {
+    let mut cluster = cluster.lock().unwrap();
+    if path == "/v3/auth/authenticate" {
+        let presented = (
+            body["name"].as_str().unwrap_or_default(),
+            body["password"].as_str().unwrap_or_default(),
+        );
+        return match &cluster.credentials {
+            Some((user, password)) if presented == (user.as_str(), password.as_str()) => {
+                cluster.issued += 1;
+                let token = format!("token.{}", cluster.issued);
+                cluster.tokens.insert(token.clone());
+                ("200 OK", json!({ "token": token }))
+            }
+            _ => error(
+                "400 Bad Request",
+                "etcdserver: authentication failed, invalid user ID or password",
+            ),
+        };
+    }
+    if cluster.credentials.is_some() && !token.is_some_and(|t| cluster.tokens.contains(t)) {
+        return error("401 Unauthorized", "etcdserver: invalid auth token");
+    }
+
+    let bytes = |name: &str| {
+        BASE64
+            .decode(body[name].as_str().unwrap_or_default())
+            .unwrap()
+    };
+    let header = |cluster: &Cluster| json!({ "revision": cluster.revision.to_string() });
+    match path {
+        "/v3/kv/range" => {
+            let key = bytes("key");
+            let kvs: Vec<Value> = match body["range_end"].as_str() {
+                _ if body["count_only"] == true => Vec::new(),
+                Some(_) => {
+                    let end = bytes("range_end");
+                    let end = if end == b"\0" {
+                        Bound::Unbounded
+                    } else {
+                        Bound::Excluded(end)
+                    };
+                    let limit = match body["limit"].as_str().unwrap_or("0").parse().unwrap() {
+                        0 => usize::MAX,
+                        n => n,
+                    };
+                    cluster
+                        .kvs
+                        .range((Bound::Included(key), end))
+                        .take(limit)
+                        .map(|(k, v)| kv(k, v))
+                        .collect()
+                }
+                None => cluster
+                    .kvs
+                    .get(&key)
+                    .map(|v| kv(&key, v))
+                    .into_iter()
+                    .collect(),
+            };
+            if kvs.is_empty() {
+                ("200 OK", json!({ "header": header(&cluster) }))
+            } else {
+                ("200 OK", json!({ "header": header(&cluster), "kvs": kvs }))
+            }
+        }
+        "/v3/kv/put" => {
+            cluster.kvs.insert(bytes("key"), bytes("value"));
+            cluster.revision += 1;
+            ("200 OK", json!({ "header": header(&cluster) }))
+        }
+        "/v3/kv/deleterange" => {
+            if cluster.kvs.remove(&bytes("key")).is_some() {
+                cluster.revision += 1;
+            }
+            ("200 OK", json!({ "header": header(&cluster) }))
+        }
+        "/v3/kv/txn" => {
+            let holds = body["compare"].as_array().unwrap().iter().all(|compare| {
+                let key = BASE64.decode(compare["key"].as_str().unwrap()).unwrap();
+                let current = cluster.kvs.get(&key);
+                match compare["target"].as_str().unwrap() {
+                    "VALUE" => {
+                        let value = BASE64.decode(compare["value"].as_str().unwrap()).unwrap();
+                        current == Some(&value)
+                    }
+                    "CREATE" => current.is_none(),
+                    target => panic!("unexpected compare target {}", target),
+                }
+            });
+            if !holds {
+                return ("200 OK", json!({ "header": header(&cluster) }));
+            }
+            for op in body["success"].as_array().unwrap() {
+                let put = &op["request_put"];
+                let field = |name: &str| BASE64.decode(put[name].as_str().unwrap()).unwrap();
+                cluster.kvs.insert(field("key"), field("value"));
+            }
+            cluster.revision += 1;
+            (
+                "200 OK",
+                json!({ "header": header(&cluster), "succeeded": true }),
+            )
+        }
+        other => error("404 Not Found", &format!("unknown method {}", other)),
+    }
+}
+
+fn kv(key: &[u8], value: &[u8]) -> Value {
+    json!({ "key": BASE64.encode(key), "value": BASE64.encode(value) })
+}
+
+fn error(status: &'static str, message: &str) -> (&'static str, Value) {
+    (status, json!({ "error": message, "message": message }))
+}
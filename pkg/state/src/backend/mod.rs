@@ -0,0 +1,94 @@
+//! Where [`StateStore`](crate::client::StateStore) keeps its keys.
+//!
+//! A backend is a plain ordered key-value store. Resource versions, Secret
+//! encryption and watch events are the store's business and work the same on
+//! every backend: the store stamps and seals values before they reach the
+//! backend and emits a watch event for each write it makes.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+
+pub mod etcd;
+#[cfg(test)]
+pub(crate) mod etcd_fake;
+pub mod slatedb;
+
+pub use self::etcd::{EtcdBackend, EtcdConfig};
+pub use self::slatedb::{ObjectStoreConfig, ObjectStoreProvider, SlateDbBackend};
+
+/// The operations the state store needs from its storage.
+#[async_trait]
+pub trait StateBackend: Send + Sync {
+    /// Which backend this is, for `k3rsctl cluster info`.
+    fn describe(&self) -> String;
+
+    /// Where the state lives: a directory, bucket URL or endpoints.
+    fn location(&self) -> String;
+
+    /// The value stored under `key`, if any.
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    async fn put(&self, key: &str, value: &[u8]) -> anyhow::Result<()>;
+
+    /// Store `value` only if `key` still holds `expected` (`None`: nothing).
+    /// Returns whether it was stored.
+    async fn put_if(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> anyhow::Result<bool>;
+
+    /// [`put_if`](Self::put_if) for bookkeeping that only the writes after
+    /// it rely on. May return before the value is durable, provided it
+    /// becomes durable no later than any write that follows.
+    async fn put_if_ordered(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> anyhow::Result<bool> {
+        self.put_if(key, expected, value).await
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+
+    /// Key-value pairs whose keys start with `prefix`, in key order, starting
+    /// right after `start_after` (or at the first key when `None`), at most
+    /// `limit` of them when given.
+    async fn scan(
+        &self,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, Vec<u8>)>>;
+
+    async fn close(&self) -> anyhow::Result<()>;
+}
+
+/// The backend a server keeps its state in.
+#[derive(Debug, Clone)]
+pub enum BackendConfig {
+    /// SlateDB in a local directory (the default), or in a bucket when
+    /// `object_store` is set.
+    SlateDb {
+        path: String,
+        object_store: Option<ObjectStoreConfig>,
+    },
+    /// An etcd cluster, shared by every server pointed at it.
+    Etcd(EtcdConfig),
+}
+
+impl BackendConfig {
+    /// Open the backend, failing if it cannot be reached.
+    pub async fn open(&self) -> anyhow::Result<Arc<dyn StateBackend>> {
+        Ok(match self {
+            Self::SlateDb {
+                object_store: Some(bucket),
+                ..
+            } => Arc::new(SlateDbBackend::open_bucket(bucket).await?),
+            Self::SlateDb { path, .. } => Arc::new(SlateDbBackend::open(path).await?),
+            Self::Etcd(config) => Arc::new(EtcdBackend::connect(config.clone()).await?),
+        })
+    }
+}
@@ -0,0 +1,570 @@
+use async_trait::async_trait;
+use object_store::aws::AmazonS3Builder;
+use object_store::azure::MicrosoftAzureBuilder;
+use object_store::gcp::GoogleCloudStorageBuilder;
+use object_store::{ClientOptions, ObjectStore, PutPayload, RetryConfig};
+use slatedb::Db;
+use slatedb::config::{PutOptions, WriteOptions};
+use slatedb::object_store::local::LocalFileSystem;
+use slatedb::object_store::path::Path;
+use std::ops::Bound;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::info;
+
+use pkg_constants::timings::{OBJECT_STORE_CONNECT_TIMEOUT_SECS, OBJECT_STORE_PROBE_TIMEOUT_SECS};
+
+use super::StateBackend;
+
+/// Environment variable holding the object store secret key; overrides the
+/// config.
+pub const SECRET_KEY_ENV: &str = "K3RS_OBJECT_STORE_SECRET_KEY";
+
+/// Object storage services SlateDB can keep state in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectStoreProvider {
+    S3,
+    Gcs,
+    Azure,
+    /// MinIO or another S3-compatible store at `endpoint`.
+    Minio,
+}
+
+impl FromStr for ObjectStoreProvider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "s3" => Ok(Self::S3),
+            "gcs" => Ok(Self::Gcs),
+            "azure" => Ok(Self::Azure),
+            "minio" => Ok(Self::Minio),
+            other => Err(format!(
+                "unknown object store provider '{}' (expected s3, gcs, azure or minio)",
+                other
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ObjectStoreProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::S3 => "s3",
+            Self::Gcs => "gcs",
+            Self::Azure => "azure",
+            Self::Minio => "minio",
+        })
+    }
+}
+
+/// A bucket SlateDB keeps state in, instead of a local directory.
+/// Credentials left unset are taken from the provider's usual environment
+/// variables (`AWS_ACCESS_KEY_ID`, `GOOGLE_APPLICATION_CREDENTIALS`,
+/// `AZURE_STORAGE_ACCOUNT_NAME`, ...).
+#[derive(Debug, Clone)]
+pub struct ObjectStoreConfig {
+    pub provider: ObjectStoreProvider,
+    /// Bucket (container, on Azure).
+    pub bucket: String,
+    /// Path inside the bucket the database lives under.
+    pub prefix: String,
+    pub region: Option<String>,
+    /// Service URL, for MinIO and other S3-compatible stores.
+    pub endpoint: Option<String>,
+    /// S3 access key id, or Azure storage account name.
+    pub access_key_id: Option<String>,
+    /// S3 secret access key, or Azure account key.
+    pub secret_access_key: Option<String>,
+    /// GCS service account key file.
+    pub credentials_file: Option<String>,
+}
+
+impl ObjectStoreConfig {
+    /// The bucket and prefix as a URL, e.g. `s3://k3rs-state/prod`.
+    pub fn location(&self) -> String {
+        let scheme = match self.provider {
+            ObjectStoreProvider::S3 | ObjectStoreProvider::Minio => "s3",
+            ObjectStoreProvider::Gcs => "gs",
+            ObjectStoreProvider::Azure => "az",
+        };
+        let mut location = format!("{}://{}", scheme, self.bucket);
+        let prefix = self.prefix.trim_matches('/');
+        if !prefix.is_empty() {
+            location = format!("{}/{}", location, prefix);
+        }
+        match &self.endpoint {
+            Some(endpoint) => format!("{} at {}", location, endpoint),
+            None => location,
+        }
+    }
+
+    /// A client for the bucket, retrying failed requests as `retry` says.
+    fn build(&self, retry: RetryConfig) -> anyhow::Result<Arc<dyn ObjectStore>> {
+        let allow_http = self
+            .endpoint
+            .as_deref()
+            .is_some_and(|e| e.starts_with("http://"));
+        let options = ClientOptions::new()
+            .with_connect_timeout(Duration::from_secs(OBJECT_STORE_CONNECT_TIMEOUT_SECS))
+            .with_allow_http(allow_http);
+        let invalid =
+            |e: object_store::Error| anyhow::anyhow!("invalid object store configuration: {}", e);
+        let store: Arc<dyn ObjectStore> = match self.provider {
+            ObjectStoreProvider::S3 | ObjectStoreProvider::Minio => {
+                let mut builder = AmazonS3Builder::from_env()
+                    .with_bucket_name(&self.bucket)
+                    .with_client_options(options)
+                    .with_retry(retry);
+                if let Some(region) = &self.region {
+                    builder = builder.with_region(region);
+                }
+                if let Some(endpoint) = &self.endpoint {
+                    builder = builder.with_endpoint(endpoint);
+                } else if self.provider == ObjectStoreProvider::Minio {
+                    anyhow::bail!("object store provider minio needs an endpoint");
+                }
+                if let Some(id) = &self.access_key_id {
+                    builder = builder.with_access_key_id(id);
+                }
+                if let Some(secret) = &self.secret_access_key {
+                    builder = builder.with_secret_access_key(secret);
+                }
+                Arc::new(builder.build().map_err(invalid)?)
+            }
+            ObjectStoreProvider::Gcs => {
+                if self.endpoint.is_some() {
+                    anyhow::bail!("object store provider gcs does not take an endpoint");
+                }
+                let mut builder = GoogleCloudStorageBuilder::from_env()
+                    .with_bucket_name(&self.bucket)
+                    .with_client_options(options)
+                    .with_retry(retry);
+                if let Some(path) = &self.credentials_file {
+                    builder = builder.with_service_account_path(path);
+                }
+                Arc::new(builder.build().map_err(invalid)?)
+            }
+            ObjectStoreProvider::Azure => {
+                let mut builder = MicrosoftAzureBuilder::from_env()
+                    .with_container_name(&self.bucket)
+                    .with_client_options(options)
+                    .with_retry(retry);
+                if let Some(endpoint) = &self.endpoint {
+                    builder = builder.with_endpoint(endpoint.clone());
+                }
+                if let Some(account) = &self.access_key_id {
+                    builder = builder.with_account(account);
+                }
+                if let Some(key) = &self.secret_access_key {
+                    builder = builder.with_access_key(key);
+                }
+                Arc::new(builder.build().map_err(invalid)?)
+            }
+        };
+        Ok(store)
+    }
+
+    /// What to fix when the bucket refuses the startup check.
+    fn explain(&self, err: &object_store::Error) -> String {
+        let text = err.to_string();
+        let mentions = |codes: &[&str]| codes.iter().any(|code| text.contains(code));
+        if mentions(&[
+            "AuthorizationHeaderMalformed",
+            "PermanentRedirect",
+            "301 Moved Permanently",
+        ]) {
+            format!(
+                "bucket '{}' is not in region {}; set the object store region to the bucket's",
+                self.bucket,
+                self.region.as_deref().unwrap_or("us-east-1 (the default)")
+            )
+        } else if matches!(err, object_store::Error::NotFound { .. })
+            || mentions(&["NoSuchBucket", "ContainerNotFound"])
+        {
+            format!(
+                "bucket '{}' does not exist; create it or fix the object store bucket",
+                self.bucket
+            )
+        } else if matches!(
+            err,
+            object_store::Error::PermissionDenied { .. }
+                | object_store::Error::Unauthenticated { .. }
+        ) || mentions(&[
+            "InvalidAccessKeyId",
+            "SignatureDoesNotMatch",
+            "AccessDenied",
+            "AuthenticationFailed",
+            "ExpiredToken",
+        ]) {
+            match self.provider {
+                ObjectStoreProvider::Gcs => {
+                    "credentials were refused; check the object store credentials file".to_string()
+                }
+                _ => format!(
+                    "credentials were refused; check the object store access key id and \
+                     secret access key (or {})",
+                    SECRET_KEY_ENV
+                ),
+            }
+        } else {
+            format!(
+                "could not reach {}; check the object store endpoint and the network",
+                self.endpoint.as_deref().unwrap_or("the provider")
+            )
+        }
+    }
+}
+
+/// SlateDB on a local filesystem or in a bucket. Only one server may use a
+/// database; [`StateBackend::put_if`] holds a lock over its read and write,
+/// so it is atomic against this process's other writes.
+pub struct SlateDbBackend {
+    db: Db,
+    kind: String,
+    location: String,
+    writes: Mutex<()>,
+}
+
+impl SlateDbBackend {
+    /// Open (or create) a database rooted at `path`.
+    pub async fn open(path: &str) -> anyhow::Result<Self> {
+        info!("Opening SlateDB state store at {}", path);
+
+        // Ensure the data directory exists before opening the object store
+        std::fs::create_dir_all(path)
+            .map_err(|e| anyhow::anyhow!("Failed to create data directory {}: {}", path, e))?;
+
+        let object_store = Arc::new(
+            LocalFileSystem::new_with_prefix(path)
+                .map_err(|e| anyhow::anyhow!("Failed to create local object store: {}", e))?,
+        );
+        let db = Db::open(Path::from("/"), object_store)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to open SlateDB: {}", e))?;
+        Ok(Self {
+            db,
+            kind: "SlateDB (local)".to_string(),
+            location: path.to_string(),
+            writes: Mutex::new(()),
+        })
+    }
+
+    /// Open (or create) a database in the bucket `config` names, failing
+    /// with what to fix unless the bucket can be written to.
+    pub async fn open_bucket(config: &ObjectStoreConfig) -> anyhow::Result<Self> {
+        let location = config.location();
+        info!("Opening SlateDB state store at {}", location);
+        let checked = config.build(RetryConfig {
+            max_retries: 3,
+            retry_timeout: Duration::from_secs(OBJECT_STORE_PROBE_TIMEOUT_SECS),
+            ..Default::default()
+        })?;
+        let root = Path::from(config.prefix.as_str());
+        probe(checked.as_ref(), &root).await.map_err(|e| {
+            anyhow::anyhow!(
+                "object store {} unavailable: {} ({})",
+                location,
+                config.explain(&e),
+                e
+            )
+        })?;
+        let store = config.build(RetryConfig::default())?;
+        Self::in_store(
+            store,
+            root,
+            format!("SlateDB ({})", config.provider),
+            location,
+        )
+        .await
+    }
+
+    async fn in_store(
+        store: Arc<dyn ObjectStore>,
+        root: Path,
+        kind: String,
+        location: String,
+    ) -> anyhow::Result<Self> {
+        let db = Db::open(root, store)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to open SlateDB: {}", e))?;
+        Ok(Self {
+            db,
+            kind,
+            location,
+            writes: Mutex::new(()),
+        })
+    }
+
+    /// Put `value`, returning once it is durable when `await_durable`, or
+    /// else once it is in the WAL, ahead of every later write.
+    async fn write(&self, key: &str, value: &[u8], await_durable: bool) -> anyhow::Result<()> {
+        self.db
+            .put_with_options(
+                key.as_bytes(),
+                value,
+                &PutOptions::default(),
+                &WriteOptions { await_durable },
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("SlateDB put failed: {}", e))?;
+        Ok(())
+    }
+
+    async fn write_if(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+        await_durable: bool,
+    ) -> anyhow::Result<bool> {
+        let _writing = self.writes.lock().await;
+        if self.get(key).await?.as_deref() != expected {
+            return Ok(false);
+        }
+        self.write(key, value, await_durable).await?;
+        Ok(true)
+    }
+}
+
+/// Write, read back and remove an object under `root`, so a bucket that
+/// is missing or refuses this server fails startup instead of the first
+/// write.
+async fn probe(store: &dyn ObjectStore, root: &Path) -> object_store::Result<()> {
+    let path = root.child(".k3rs-probe");
+    store
+        .put(&path, PutPayload::from_static(b"k3rs state store check"))
+        .await?;
+    store.get(&path).await?.bytes().await?;
+    store.delete(&path).await
+}
+
+#[async_trait]
+impl StateBackend for SlateDbBackend {
+    fn describe(&self) -> String {
+        self.kind.clone()
+    }
+
+    fn location(&self) -> String {
+        self.location.clone()
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        self.db
+            .get(key.as_bytes())
+            .await
+            .map(|value| value.map(|bytes| bytes.to_vec()))
+            .map_err(|e| anyhow::anyhow!("SlateDB get failed: {}", e))
+    }
+
+    async fn put(&self, key: &str, value: &[u8]) -> anyhow::Result<()> {
+        let _writing = self.writes.lock().await;
+        self.write(key, value, true).await
+    }
+
+    async fn put_if(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> anyhow::Result<bool> {
+        self.write_if(key, expected, value, true).await
+    }
+
+    async fn put_if_ordered(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+    ) -> anyhow::Result<bool> {
+        // The WAL is flushed in order, so this lands no later than the
+        // writes after it.
+        self.write_if(key, expected, value, false).await
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let _writing = self.writes.lock().await;
+        self.db
+            .delete(key.as_bytes())
+            .await
+            .map_err(|e| anyhow::anyhow!("SlateDB delete failed: {}", e))?;
+        Ok(())
+    }
+
+    async fn scan(
+        &self,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        let start = match start_after {
+            Some(key) if key >= prefix => Bound::Excluded(key.as_bytes().to_vec()),
+            _ => Bound::Included(prefix.as_bytes().to_vec()),
+        };
+        let mut iter = self
+            .db
+            .scan((start, Bound::Unbounded))
+            .await
+            .map_err(|e| anyhow::anyhow!("SlateDB scan failed: {}", e))?;
+
+        let limit = limit.unwrap_or(usize::MAX);
+        let mut results = Vec::new();
+        while results.len() < limit {
+            let Ok(Some(kv)) = iter.next().await else {
+                break;
+            };
+            if !kv.key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let key = String::from_utf8_lossy(&kv.key).to_string();
+            results.push((key, kv.value.to_vec()));
+        }
+        Ok(results)
+    }
+
+    async fn close(&self) -> anyhow::Result<()> {
+        info!("Closing SlateDB state store");
+        self.db
+            .close()
+            .await
+            .map_err(|e| anyhow::anyhow!("SlateDB close failed: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object_store::memory::InMemory;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn minio(endpoint: String) -> ObjectStoreConfig {
+        ObjectStoreConfig {
+            provider: ObjectStoreProvider::Minio,
+            bucket: "state".to_string(),
+            prefix: "cluster-a".to_string(),
+            region: Some("eu-west-1".to_string()),
+            endpoint: Some(endpoint),
+            access_key_id: Some("k3rs".to_string()),
+            secret_access_key: Some("secret".to_string()),
+            credentials_file: None,
+        }
+    }
+
+    /// An S3 endpoint answering every request with `status` and `body`.
+    async fn refusing_s3(status: &'static str, body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let _ = conn.read(&mut request).await;
+                let reply = format!(
+                    "HTTP/1.1 {}\r\ncontent-type: application/xml\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = conn.write_all(reply.as_bytes()).await;
+            }
+        });
+        endpoint
+    }
+
+    async fn open_error(config: &ObjectStoreConfig) -> String {
+        SlateDbBackend::open_bucket(config)
+            .await
+            .err()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_location() {
+        let mut config = minio("http://minio:9000".to_string());
+        assert_eq!(
+            config.location(),
+            "s3://state/cluster-a at http://minio:9000"
+        );
+        config.provider = ObjectStoreProvider::Gcs;
+        config.prefix = "/".to_string();
+        config.endpoint = None;
+        assert_eq!(config.location(), "gs://state");
+        assert!("b2".parse::<ObjectStoreProvider>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bucket_write_read_cycle() {
+        let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+        let root = Path::from("cluster-a");
+        probe(store.as_ref(), &root).await.unwrap();
+
+        let open = || {
+            SlateDbBackend::in_store(
+                store.clone(),
+                root.clone(),
+                "SlateDB (s3)".to_string(),
+                "s3://state/cluster-a".to_string(),
+            )
+        };
+        let backend = open().await.unwrap();
+        backend.put("/registry/a", b"1").await.unwrap();
+        assert_eq!(backend.get("/registry/a").await.unwrap().unwrap(), b"1");
+        assert_eq!(backend.location(), "s3://state/cluster-a");
+        backend.close().await.unwrap();
+
+        // The state is in the bucket, not in the server.
+        let reopened = open().await.unwrap();
+        assert_eq!(reopened.get("/registry/a").await.unwrap().unwrap(), b"1");
+        reopened.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_missing_bucket_is_a_clear_error() {
+        let endpoint = refusing_s3(
+            "404 Not Found",
+            "<Error><Code>NoSuchBucket</Code><Message>The specified bucket does not exist</Message></Error>",
+        )
+        .await;
+        let err = open_error(&minio(endpoint)).await;
+        assert!(err.contains("bucket 'state' does not exist"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_bad_credentials_are_a_clear_error() {
+        let endpoint = refusing_s3(
+            "403 Forbidden",
+            "<Error><Code>InvalidAccessKeyId</Code><Message>The Access Key Id you provided does not exist in our records.</Message></Error>",
+        )
+        .await;
+        let err = open_error(&minio(endpoint)).await;
+        assert!(err.contains("credentials were refused"), "{}", err);
+        assert!(err.contains(SECRET_KEY_ENV), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_region_is_a_clear_error() {
+        let endpoint = refusing_s3(
+            "400 Bad Request",
+            "<Error><Code>AuthorizationHeaderMalformed</Code><Message>the region 'eu-west-1' is wrong; expecting 'us-east-2'</Message></Error>",
+        )
+        .await;
+        let err = open_error(&minio(endpoint)).await;
+        assert!(err.contains("is not in region eu-west-1"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_endpoint_is_a_clear_error() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let err = open_error(&minio(endpoint.clone())).await;
+        assert!(err.contains("object store s3://state/cluster-a"), "{}", err);
+        assert!(
+            err.contains(&format!("could not reach {}", endpoint)),
+            "{}",
+            err
+        );
+    }
+}
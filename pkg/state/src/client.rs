@@ -1,69 +1,251 @@
-use slatedb::Db;
-use slatedb::object_store::local::LocalFileSystem;
-use slatedb::object_store::path::Path;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 use std::sync::Arc;
-use tracing::info;
+use tokio::sync::Mutex;
 
+use crate::backend::{BackendConfig, SlateDbBackend, StateBackend};
+use crate::encryption::{SECRETS_PREFIX, SecretCipher};
 use crate::watch::{EventLog, EventType};
 
-/// Persistent state store backed by SlateDB on a local filesystem.
-/// Integrates with EventLog to emit watch events on mutations.
+/// Field the store stamps into every JSON object it writes.
+pub const RESOURCE_VERSION_FIELD: &str = "resource_version";
+
+/// Key of the last resource version handed out, kept in the backend beside
+/// the registry (and so out of backups) as a decimal number.
+const VERSION_KEY: &str = "/k3rs/resource-version";
+
+/// How often [`StateStore::update`] re-reads and retries after a conflict.
+pub const MAX_UPDATE_ATTEMPTS: usize = 5;
+
+/// Outcome of [`StateStore::put_if_version`] and [`StateStore::create`].
+#[derive(Debug)]
+pub enum PutOutcome {
+    /// Written; holds the stored bytes with their new resource version.
+    Stored(Vec<u8>),
+    /// The key was changed (or deleted) since `expected` was read, or already
+    /// exists for a create; holds the currently stored value.
+    Conflict(Option<Vec<u8>>),
+}
+
+/// Persistent state store over a [`StateBackend`] (SlateDB on a local
+/// filesystem by default, or etcd). Integrates with EventLog to emit watch
+/// events on mutations.
+///
+/// Every JSON object written gets a `resource_version` from a counter that
+/// increases on each write, so a caller can tell whether the object changed
+/// since it was read (see [`StateStore::put_if_version`]). The counter lives
+/// in the backend, so servers sharing one never hand out the same version.
+///
+/// With a [`SecretCipher`] set, Secret `data` values are encrypted on the way
+/// to the backend and decrypted on the way out; callers and watchers only
+/// ever see plaintext.
 #[derive(Clone)]
 pub struct StateStore {
-    db: Db,
+    backend: Arc<dyn StateBackend>,
     pub event_log: EventLog,
+    /// Held for the whole of a write, so this server's writes and the watch
+    /// events for them happen in version order.
+    writes: Arc<Mutex<()>>,
+    secrets: Option<Arc<SecretCipher>>,
 }
 
 impl StateStore {
     /// Open (or create) a state store rooted at `path` on the local filesystem.
     pub async fn new(path: &str) -> anyhow::Result<Self> {
-        info!("Opening SlateDB state store at {}", path);
-
-        // Ensure the data directory exists before opening the object store
-        std::fs::create_dir_all(path)
-            .map_err(|e| anyhow::anyhow!("Failed to create data directory {}: {}", path, e))?;
+        Self::with_backend(Arc::new(SlateDbBackend::open(path).await?)).await
+    }
 
-        let object_store = Arc::new(
-            LocalFileSystem::new_with_prefix(path)
-                .map_err(|e| anyhow::anyhow!("Failed to create local object store: {}", e))?,
-        );
-        let db = Db::open(Path::from("/"), object_store)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to open SlateDB: {}", e))?;
+    /// Open the state store `config` describes.
+    pub async fn open(config: &BackendConfig) -> anyhow::Result<Self> {
+        Self::with_backend(config.open().await?).await
+    }
 
+    /// A state store over `backend`.
+    pub async fn with_backend(backend: Arc<dyn StateBackend>) -> anyhow::Result<Self> {
         Ok(Self {
-            db,
+            backend,
             event_log: EventLog::new(10_000),
+            writes: Arc::new(Mutex::new(())),
+            secrets: None,
         })
     }
 
-    /// Store a value under the given key. Emits a `Put` watch event.
-    pub async fn put(&self, key: &str, value: &[u8]) -> anyhow::Result<()> {
-        self.db
-            .put(key.as_bytes(), value)
-            .await
-            .map_err(|e| anyhow::anyhow!("SlateDB put failed: {}", e))?;
+    /// Which backend the state is in, e.g. `SlateDB (local)`.
+    pub fn describe(&self) -> String {
+        self.backend.describe()
+    }
+
+    /// Where the state lives, e.g. `s3://k3rs-state/prod`.
+    pub fn location(&self) -> String {
+        self.backend.location()
+    }
+
+    /// Encrypt Secret `data` values at rest with `cipher`.
+    pub fn with_secret_cipher(mut self, cipher: SecretCipher) -> Self {
+        self.secrets = Some(Arc::new(cipher));
+        self
+    }
+
+    /// Store a value under the given key, stamping JSON objects with the
+    /// next resource version, which is returned. Emits a `Put` watch event.
+    pub async fn put(&self, key: &str, value: &[u8]) -> anyhow::Result<u64> {
+        let _writing = self.writes.lock().await;
+        let next = self.next_version().await?;
+        let (value, stored) = self.seal(next, key, value)?;
+        self.backend.put(key, &stored).await?;
+        self.announce(key, &value).await;
+        Ok(next)
+    }
+
+    /// Store `value` only if the object at `key` is still at resource
+    /// version `expected`, i.e. nothing wrote it since it was read. The
+    /// backend checks the stored bytes too, so a write by another server
+    /// sharing it in between is a conflict as well.
+    pub async fn put_if_version(
+        &self,
+        key: &str,
+        value: &[u8],
+        expected: u64,
+    ) -> anyhow::Result<PutOutcome> {
+        let _writing = self.writes.lock().await;
+        let read = self.backend.get(key).await?;
+        let current = read
+            .clone()
+            .map(|stored| self.decrypt(key, stored))
+            .transpose()?;
+        if current.as_deref().map(resource_version) != Some(expected) {
+            return Ok(PutOutcome::Conflict(current));
+        }
+        let next = self.next_version().await?;
+        let (value, stored) = self.seal(next, key, value)?;
+        if !self.backend.put_if(key, read.as_deref(), &stored).await? {
+            return Ok(PutOutcome::Conflict(self.get(key).await?));
+        }
+        self.announce(key, &value).await;
+        Ok(PutOutcome::Stored(value))
+    }
+
+    /// Store `value` only if nothing is stored at `key` yet.
+    pub async fn create(&self, key: &str, value: &[u8]) -> anyhow::Result<PutOutcome> {
+        let _writing = self.writes.lock().await;
+        let next = self.next_version().await?;
+        let (value, stored) = self.seal(next, key, value)?;
+        if !self.backend.put_if(key, None, &stored).await? {
+            return Ok(PutOutcome::Conflict(self.get(key).await?));
+        }
+        self.announce(key, &value).await;
+        Ok(PutOutcome::Stored(value))
+    }
+
+    /// Read-modify-write the object at `key`: `mutate` is applied to the
+    /// stored copy, which is written back unless it returns `false`. When
+    /// another write lands in between, the object is read again and `mutate`
+    /// re-applied, up to [`MAX_UPDATE_ATTEMPTS`] times. Returns the resulting
+    /// object, or `None` if nothing is stored at `key`.
+    pub async fn update<T, F>(&self, key: &str, mut mutate: F) -> anyhow::Result<Option<T>>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnMut(&mut T) -> bool,
+    {
+        for _ in 0..MAX_UPDATE_ATTEMPTS {
+            let Some(data) = self.get(key).await? else {
+                return Ok(None);
+            };
+            let mut obj: T = serde_json::from_slice(&data)?;
+            if !mutate(&mut obj) {
+                return Ok(Some(obj));
+            }
+            let value = serde_json::to_vec(&obj)?;
+            match self
+                .put_if_version(key, &value, resource_version(&data))
+                .await?
+            {
+                PutOutcome::Stored(stored) => return Ok(Some(serde_json::from_slice(&stored)?)),
+                PutOutcome::Conflict(None) => return Ok(None),
+                PutOutcome::Conflict(Some(_)) => continue,
+            }
+        }
+        anyhow::bail!(
+            "{} kept changing; gave up after {} attempts",
+            key,
+            MAX_UPDATE_ATTEMPTS
+        )
+    }
+
+    /// Claim the next resource version by bumping the counter under
+    /// [`VERSION_KEY`] with a compare-and-set, retrying when another server
+    /// sharing the backend claimed one in between.
+    async fn next_version(&self) -> anyhow::Result<u64> {
+        loop {
+            let current = self.backend.get(VERSION_KEY).await?;
+            let last = match &current {
+                Some(value) => std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .ok_or_else(|| anyhow::anyhow!("{} is not a version", VERSION_KEY))?,
+                None => self.newest_stored_version().await?,
+            };
+            let next = last + 1;
+            if self
+                .backend
+                .put_if_ordered(VERSION_KEY, current.as_deref(), next.to_string().as_bytes())
+                .await?
+            {
+                return Ok(next);
+            }
+        }
+    }
+
+    /// The highest resource version among stored objects, to start the
+    /// counter from in a store written before it existed. Only read while
+    /// [`VERSION_KEY`] is missing, i.e. once per store.
+    async fn newest_stored_version(&self) -> anyhow::Result<u64> {
+        Ok(self
+            .list_prefix("/registry/")
+            .await?
+            .iter()
+            .map(|(_, v)| resource_version(v))
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// `value` stamped with resource version `next`, and that as it is
+    /// stored, i.e. sealed if it is a Secret.
+    fn seal(&self, next: u64, key: &str, value: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        let value = stamp(value, next);
+        let stored = match &self.secrets {
+            Some(cipher) if key.starts_with(SECRETS_PREFIX) => cipher.seal_object(key, &value)?,
+            _ => value.clone(),
+        };
+        Ok((value, stored))
+    }
+
+    async fn announce(&self, key: &str, value: &[u8]) {
         self.event_log
             .emit(EventType::Put, key.to_string(), Some(value.to_vec()))
             .await;
-        Ok(())
     }
 
     /// Retrieve the value for a key, or `None` if it does not exist.
     pub async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
-        match self.db.get(key.as_bytes()).await {
-            Ok(Some(bytes)) => Ok(Some(bytes.to_vec())),
-            Ok(None) => Ok(None),
-            Err(e) => Err(anyhow::anyhow!("SlateDB get failed: {}", e)),
+        match self.backend.get(key).await? {
+            Some(stored) => Ok(Some(self.decrypt(key, stored)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// A value as read from the backend, with Secret `data` values decrypted.
+    fn decrypt(&self, key: &str, value: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        match &self.secrets {
+            Some(cipher) if key.starts_with(SECRETS_PREFIX) => cipher.open_object(key, &value),
+            _ => Ok(value),
         }
     }
 
     /// Delete a key from the store. Emits a `Delete` watch event.
     pub async fn delete(&self, key: &str) -> anyhow::Result<()> {
-        self.db
-            .delete(key.as_bytes())
-            .await
-            .map_err(|e| anyhow::anyhow!("SlateDB delete failed: {}", e))?;
+        let _writing = self.writes.lock().await;
+        self.backend.delete(key).await?;
         self.event_log
             .emit(EventType::Delete, key.to_string(), None)
             .await;
@@ -72,25 +254,45 @@ impl StateStore {
 
     /// List all key-value pairs whose keys start with `prefix`.
     pub async fn list_prefix(&self, prefix: &str) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
-        let mut results = Vec::new();
-        let mut iter = self
-            .db
-            .scan_prefix(prefix.as_bytes())
-            .await
-            .map_err(|e| anyhow::anyhow!("SlateDB scan_prefix failed: {}", e))?;
+        self.scan(prefix, None, None).await
+    }
 
-        while let Ok(Some(kv)) = iter.next().await {
-            let key = String::from_utf8_lossy(&kv.key).to_string();
-            results.push((key, kv.value.to_vec()));
-        }
-        Ok(results)
+    /// Up to `limit` key-value pairs under `prefix`, in key order, starting
+    /// right after `start_after` (or at the first key when `None`). The key
+    /// does not have to exist, so a page can resume after a deleted object.
+    pub async fn list_prefix_after(
+        &self,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        self.scan(prefix, start_after, Some(limit)).await
+    }
+
+    async fn scan(
+        &self,
+        prefix: &str,
+        start_after: Option<&str>,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+        self.backend
+            .scan(prefix, start_after, limit)
+            .await?
+            .into_iter()
+            .map(|(key, value)| {
+                let value = self.decrypt(&key, value)?;
+                Ok((key, value))
+            })
+            .collect()
     }
 
     /// Snapshot all registry keys for backup purposes.
     /// Excludes restore metadata (`/registry/_restore/`), backup metadata
     /// (`/registry/_backup/`), and lease keys (`/registry/leases/`).
+    /// Secret values stay encrypted, so a backup only restores into a
+    /// cluster with the same encryption key.
     pub async fn snapshot(&self) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
-        let all = self.list_prefix("/registry/").await?;
+        let all = self.backend.scan("/registry/", None, None).await?;
         let filtered = all
             .into_iter()
             .filter(|(k, _)| {
@@ -104,10 +306,348 @@ impl StateStore {
 
     /// Gracefully close the state store.
     pub async fn close(self) -> anyhow::Result<()> {
-        info!("Closing SlateDB state store");
-        self.db
-            .close()
+        self.backend.close().await
+    }
+}
+
+/// The resource version of a stored value; 0 if it has none.
+pub fn resource_version(value: &[u8]) -> u64 {
+    #[derive(serde::Deserialize)]
+    struct Versioned {
+        #[serde(default)]
+        resource_version: u64,
+    }
+    serde_json::from_slice::<Versioned>(value)
+        .map(|v| v.resource_version)
+        .unwrap_or(0)
+}
+
+/// `value` with its resource version set to `version`, if it is a JSON
+/// object; anything else is stored as is.
+fn stamp(value: &[u8], version: u64) -> Vec<u8> {
+    match serde_json::from_slice::<serde_json::Value>(value) {
+        Ok(serde_json::Value::Object(mut obj)) => {
+            obj.insert(RESOURCE_VERSION_FIELD.to_string(), version.into());
+            serde_json::to_vec(&obj).unwrap_or_else(|_| value.to_vec())
+        }
+        _ => value.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::EtcdConfig;
+    use crate::backend::etcd_fake::FakeEtcd;
+
+    /// A backend to run the suite against: SlateDB in a fresh directory, or
+    /// a fresh (fake) etcd cluster.
+    enum TestBackend {
+        SlateDb(String),
+        Etcd(FakeEtcd),
+    }
+
+    impl TestBackend {
+        fn slatedb(label: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "k3rs-state-test-{}-{}",
+                label,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            Self::SlateDb(dir.to_string_lossy().to_string())
+        }
+
+        async fn etcd() -> Self {
+            Self::Etcd(FakeEtcd::start(None).await)
+        }
+
+        /// A store over the backend; opening it again sees what was written.
+        async fn open(&self) -> StateStore {
+            let config = match self {
+                Self::SlateDb(path) => BackendConfig::SlateDb {
+                    path: path.clone(),
+                    object_store: None,
+                },
+                Self::Etcd(etcd) => BackendConfig::Etcd(EtcdConfig {
+                    endpoints: vec![etcd.endpoint()],
+                    ..Default::default()
+                }),
+            };
+            StateStore::open(&config).await.unwrap()
+        }
+    }
+
+    /// Run each test against both backends, as `slatedb::<test>` and
+    /// `etcd::<test>`.
+    macro_rules! backend_tests {
+        ($($test:ident),* $(,)?) => {
+            mod slatedb {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        super::$test(super::TestBackend::slatedb(stringify!($test))).await;
+                    }
+                )*
+            }
+            mod etcd {
+                $(
+                    #[tokio::test]
+                    async fn $test() {
+                        super::$test(super::TestBackend::etcd().await).await;
+                    }
+                )*
+            }
+        };
+    }
+
+    backend_tests!(
+        test_writes_stamp_increasing_versions,
+        test_counter_starts_after_existing_objects,
+        test_concurrent_updates_one_wins,
+        test_list_prefix_after,
+        test_secrets_are_encrypted_at_rest,
+    );
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Counter {
+        n: u32,
+        #[serde(default)]
+        resource_version: u64,
+    }
+
+    async fn test_writes_stamp_increasing_versions(backend: TestBackend) {
+        let store = backend.open().await;
+        let first = store.put("/registry/a", br#"{"n":1}"#).await.unwrap();
+        let second = store.put("/registry/b", br#"{"n":2}"#).await.unwrap();
+        assert!(second > first);
+        let a = store.get("/registry/a").await.unwrap().unwrap();
+        assert_eq!(resource_version(&a), first);
+        // Non-objects are stored untouched.
+        store.put("/registry/raw", b"not json").await.unwrap();
+        assert_eq!(
+            store.get("/registry/raw").await.unwrap().unwrap(),
+            b"not json"
+        );
+
+        // A reopened store continues after the newest object.
+        store.close().await.unwrap();
+        let store = backend.open().await;
+        assert!(store.put("/registry/a", br#"{"n":3}"#).await.unwrap() > second);
+    }
+
+    async fn test_counter_starts_after_existing_objects(backend: TestBackend) {
+        // Objects written before the counter existed keep their versions...
+        let store = backend.open().await;
+        store
+            .backend
+            .put("/registry/old", br#"{"n":1,"resource_version":41}"#)
+            .await
+            .unwrap();
+        // ...and the counter picks up after the newest of them.
+        assert_eq!(store.put("/registry/a", br#"{"n":1}"#).await.unwrap(), 42);
+        assert_eq!(
+            store.backend.get(VERSION_KEY).await.unwrap().unwrap(),
+            b"42"
+        );
+        // The counter is not part of the registry, so not of backups.
+        assert!(
+            store
+                .snapshot()
+                .await
+                .unwrap()
+                .iter()
+                .all(|(k, _)| k != VERSION_KEY)
+        );
+    }
+
+    async fn test_concurrent_updates_one_wins(backend: TestBackend) {
+        let store = backend.open().await;
+        let read = store.put("/registry/c", br#"{"n":0}"#).await.unwrap();
+
+        // Two writers that both read `read`: exactly one lands.
+        let (a, b) = tokio::join!(
+            store.put_if_version("/registry/c", br#"{"n":1}"#, read),
+            store.put_if_version("/registry/c", br#"{"n":2}"#, read),
+        );
+        let outcomes = [a.unwrap(), b.unwrap()];
+        let stored = outcomes
+            .iter()
+            .filter(|o| matches!(o, PutOutcome::Stored(_)))
+            .count();
+        assert_eq!(stored, 1);
+        let Some(PutOutcome::Conflict(Some(current))) = outcomes
+            .into_iter()
+            .find(|o| matches!(o, PutOutcome::Conflict(_)))
+        else {
+            panic!("the loser should see the winner's write");
+        };
+
+        // The loser retries against what it was shown.
+        let current: Counter = serde_json::from_slice(&current).unwrap();
+        let retry = store
+            .put_if_version("/registry/c", br#"{"n":10}"#, current.resource_version)
+            .await
+            .unwrap();
+        assert!(matches!(retry, PutOutcome::Stored(_)));
+
+        // `update` re-reads on conflict, so concurrent increments all count.
+        let incr = |c: &mut Counter| {
+            c.n += 1;
+            true
+        };
+        let (a, b) = tokio::join!(
+            store.update("/registry/c", incr),
+            store.update("/registry/c", incr),
+        );
+        a.unwrap().unwrap();
+        b.unwrap().unwrap();
+        let c: Counter =
+            serde_json::from_slice(&store.get("/registry/c").await.unwrap().unwrap()).unwrap();
+        assert_eq!(c.n, 12);
+        assert!(
+            store
+                .update("/registry/missing", incr)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    async fn test_list_prefix_after(backend: TestBackend) {
+        let store = backend.open().await;
+        for key in [
+            "/registry/a/1",
+            "/registry/a/2",
+            "/registry/a/3",
+            "/registry/b/1",
+        ] {
+            store.put(key, b"{}").await.unwrap();
+        }
+        let keys = |page: Vec<(String, Vec<u8>)>| -> Vec<String> {
+            page.into_iter().map(|(k, _)| k).collect()
+        };
+
+        let first = store
+            .list_prefix_after("/registry/a/", None, 2)
+            .await
+            .unwrap();
+        assert_eq!(keys(first), ["/registry/a/1", "/registry/a/2"]);
+        // Stops at the end of the prefix, not at `limit`.
+        let rest = store
+            .list_prefix_after("/registry/a/", Some("/registry/a/2"), 10)
             .await
-            .map_err(|e| anyhow::anyhow!("SlateDB close failed: {}", e))
+            .unwrap();
+        assert_eq!(keys(rest), ["/registry/a/3"]);
+        // Resuming after a key that is gone continues with the next one.
+        store.delete("/registry/a/2").await.unwrap();
+        let rest = store
+            .list_prefix_after("/registry/a/", Some("/registry/a/2"), 10)
+            .await
+            .unwrap();
+        assert_eq!(keys(rest), ["/registry/a/3"]);
+    }
+
+    async fn test_secrets_are_encrypted_at_rest(backend: TestBackend) {
+        use crate::encryption::ENCRYPTED_PREFIX;
+
+        let store = backend.open().await;
+        let store = store.with_secret_cipher(SecretCipher::new(&[7; 32]));
+        let key = "/registry/secrets/default/db";
+
+        // A secret stored before encryption was enabled is read as it is...
+        store
+            .backend
+            .put(key, br#"{"name":"db","data":{"password":"aHVudGVyMg=="}}"#)
+            .await
+            .unwrap();
+        let value = store.get(key).await.unwrap().unwrap();
+        assert!(String::from_utf8(value).unwrap().contains("aHVudGVyMg=="));
+
+        // ...and sealed on its next write, while readers still get plaintext.
+        store
+            .put(key, br#"{"name":"db","data":{"password":"aHVudGVyMg=="}}"#)
+            .await
+            .unwrap();
+        let stored = store.backend.get(key).await.unwrap().unwrap();
+        let stored = String::from_utf8(stored).unwrap();
+        assert!(stored.contains(ENCRYPTED_PREFIX));
+        assert!(!stored.contains("aHVudGVyMg=="));
+        let listed = store.list_prefix("/registry/secrets/").await.unwrap();
+        assert!(String::from_utf8_lossy(&listed[0].1).contains("aHVudGVyMg=="));
+        let events = store.event_log.events_since(0).await;
+        let event = events.last().unwrap().value.as_ref().unwrap();
+        assert!(String::from_utf8_lossy(event).contains("aHVudGVyMg=="));
+        // Backups keep the ciphertext.
+        let snapshot = store.snapshot().await.unwrap();
+        assert!(String::from_utf8_lossy(&snapshot[0].1).contains(ENCRYPTED_PREFIX));
+
+        // A value altered on disk is refused rather than returned.
+        let tampered = stored.replacen(ENCRYPTED_PREFIX, &format!("{}AA", ENCRYPTED_PREFIX), 1);
+        store.backend.put(key, tampered.as_bytes()).await.unwrap();
+        assert!(store.get(key).await.is_err());
+
+        // Other resources are stored as they are.
+        store
+            .put("/registry/configmaps/default/db", br#"{"data":{"a":"b"}}"#)
+            .await
+            .unwrap();
+        let plain = store
+            .backend
+            .get("/registry/configmaps/default/db")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(String::from_utf8_lossy(&plain).contains(r#""a":"b""#));
+    }
+
+    #[tokio::test]
+    async fn test_servers_sharing_etcd_do_not_reuse_versions() {
+        let backend = TestBackend::etcd().await;
+        let (a, b) = (backend.open().await, backend.open().await);
+        let read = a.put("/registry/c", br#"{"n":0}"#).await.unwrap();
+
+        // `b` writes with a version above `a`'s, though it never saw it...
+        let theirs = b.put("/registry/c", br#"{"n":1}"#).await.unwrap();
+        assert!(theirs > read);
+        assert_eq!(
+            resource_version(&a.get("/registry/c").await.unwrap().unwrap()),
+            theirs
+        );
+
+        // ...so `a` writing based on its stale read is a conflict.
+        let outcome = a
+            .put_if_version("/registry/c", br#"{"n":2}"#, read)
+            .await
+            .unwrap();
+        assert!(matches!(outcome, PutOutcome::Conflict(Some(_))));
+        let outcome = a
+            .put_if_version("/registry/c", br#"{"n":2}"#, theirs)
+            .await
+            .unwrap();
+        assert!(matches!(outcome, PutOutcome::Stored(_)));
+    }
+
+    #[tokio::test]
+    async fn test_servers_sharing_etcd_hand_out_distinct_versions() {
+        let backend = TestBackend::etcd().await;
+        let (a, b) = (backend.open().await, backend.open().await);
+        let writes = |store: StateStore, name: &'static str| async move {
+            let mut versions = Vec::new();
+            for i in 0..10 {
+                let key = format!("/registry/{}/{}", name, i);
+                versions.push(store.put(&key, br#"{"n":0}"#).await.unwrap());
+            }
+            versions
+        };
+        let (ours, theirs) = tokio::join!(writes(a, "a"), writes(b, "b"));
+        // Each server's versions keep increasing...
+        assert!(ours.windows(2).all(|w| w[0] < w[1]));
+        assert!(theirs.windows(2).all(|w| w[0] < w[1]));
+        // ...and no version is handed out twice.
+        let mut all: Vec<u64> = ours.into_iter().chain(theirs).collect();
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), 20);
     }
 }
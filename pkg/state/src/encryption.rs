@@ -0,0 +1,328 @@
+//! Encryption at rest for Secret `data` values.
+//!
+//! Under [`SECRETS_PREFIX`] every value of a stored object's `data` map is
+//! sealed with ChaCha20-Poly1305 under the data-encryption key and written
+//! as `k3rs:enc:v1:<base64(nonce || ciphertext)>`. The store key and the
+//! field name are bound in as associated data, so a value cannot be moved to
+//! another secret or field. Values without the prefix are plaintext from
+//! before encryption was enabled: they are read as they are and sealed on
+//! their next write.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+use std::path::Path;
+use tracing::info;
+
+/// Store keys whose objects have their `data` values encrypted.
+pub const SECRETS_PREFIX: &str = "/registry/secrets/";
+
+/// Marks a sealed value and the format it was sealed with.
+pub const ENCRYPTED_PREFIX: &str = "k3rs:enc:v1:";
+
+/// Environment variable holding a base64 data-encryption key. When set it is
+/// used instead of the key file, e.g. for a key unwrapped by an external KMS.
+pub const KEY_ENV: &str = "K3RS_SECRETS_ENCRYPTION_KEY";
+
+/// Length of the data-encryption key in bytes.
+pub const KEY_LEN: usize = 32;
+
+/// Seals and opens Secret `data` values with the data-encryption key.
+pub struct SecretCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl SecretCipher {
+    pub fn new(key: &[u8; KEY_LEN]) -> Self {
+        Self {
+            key: LessSafeKey::new(
+                UnboundKey::new(&CHACHA20_POLY1305, key).expect("key has the cipher's length"),
+            ),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// The key from [`KEY_ENV`] if set, else the one in `path`, generating
+    /// it (readable by the owner only) on first start.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        Self::load_from(std::env::var(KEY_ENV).ok(), path)
+    }
+
+    fn load_from(env_key: Option<String>, path: &Path) -> anyhow::Result<Self> {
+        if let Some(encoded) = env_key {
+            info!("Using the secrets encryption key from {}", KEY_ENV);
+            return Ok(Self::new(&decode_key(&encoded, KEY_ENV)?));
+        }
+        match std::fs::read_to_string(path) {
+            Ok(encoded) => Ok(Self::new(&decode_key(
+                &encoded,
+                &path.display().to_string(),
+            )?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut key = [0u8; KEY_LEN];
+                SystemRandom::new()
+                    .fill(&mut key)
+                    .map_err(|_| anyhow::anyhow!("failed to generate a secrets encryption key"))?;
+                write_key_file(path, &BASE64.encode(key))?;
+                info!("Generated secrets encryption key at {}", path.display());
+                Ok(Self::new(&key))
+            }
+            Err(e) => Err(anyhow::anyhow!(
+                "Failed to read secrets encryption key {}: {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    /// `value` with every `data` entry sealed. Entries that are already
+    /// sealed (e.g. restored from a backup) are kept if this key opens them.
+    pub fn seal_object(&self, store_key: &str, value: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.map_data(store_key, value, |aad, entry| {
+            if entry.starts_with(ENCRYPTED_PREFIX) {
+                self.open(aad, entry)?;
+                return Ok(entry.to_string());
+            }
+            self.seal(aad, entry)
+        })
+    }
+
+    /// `value` with every sealed `data` entry opened. Fails if an entry was
+    /// tampered with or sealed under another key.
+    pub fn open_object(&self, store_key: &str, value: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.map_data(store_key, value, |aad, entry| {
+            if entry.starts_with(ENCRYPTED_PREFIX) {
+                self.open(aad, entry)
+            } else {
+                Ok(entry.to_string())
+            }
+        })
+    }
+
+    /// Apply `f` to the string values of the object's `data` map; other
+    /// values are returned as they are.
+    fn map_data(
+        &self,
+        store_key: &str,
+        value: &[u8],
+        f: impl Fn(&[u8], &str) -> anyhow::Result<String>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let Ok(Value::Object(mut obj)) = serde_json::from_slice::<Value>(value) else {
+            return Ok(value.to_vec());
+        };
+        let Some(Value::Object(data)) = obj.get_mut("data") else {
+            return Ok(value.to_vec());
+        };
+        for (field, entry) in data.iter_mut() {
+            if let Value::String(text) = entry {
+                let aad = format!("{}\n{}", store_key, field);
+                *text = f(aad.as_bytes(), text)
+                    .map_err(|e| anyhow::anyhow!("{} data '{}': {}", store_key, field, e))?;
+            }
+        }
+        Ok(serde_json::to_vec(&obj)?)
+    }
+
+    fn seal(&self, aad: &[u8], plaintext: &str) -> anyhow::Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| anyhow::anyhow!("failed to generate a nonce"))?;
+        let mut sealed = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            BASE64.encode([&nonce[..], &sealed].concat())
+        ))
+    }
+
+    fn open(&self, aad: &[u8], entry: &str) -> anyhow::Result<String> {
+        let failed =
+            || anyhow::anyhow!("cannot be decrypted (tampered with, or sealed under another key)");
+        let mut bytes = BASE64
+            .decode(&entry[ENCRYPTED_PREFIX.len()..])
+            .map_err(|_| failed())?;
+        if bytes.len() < NONCE_LEN {
+            return Err(failed());
+        }
+        let mut sealed = bytes.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&bytes).map_err(|_| failed())?;
+        let plaintext = self
+            .key
+            .open_in_place(nonce, Aad::from(aad), &mut sealed)
+            .map_err(|_| failed())?;
+        String::from_utf8(plaintext.to_vec()).map_err(|_| failed())
+    }
+}
+
+fn decode_key(encoded: &str, source: &str) -> anyhow::Result<[u8; KEY_LEN]> {
+    BASE64
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|key| <[u8; KEY_LEN]>::try_from(key).ok())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "secrets encryption key from {} must be {} base64-encoded bytes",
+                source,
+                KEY_LEN
+            )
+        })
+}
+
+fn write_key_file(path: &Path, contents: &str) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to create secrets encryption key {}: {}",
+            path.display(),
+            e
+        )
+    })?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "/registry/secrets/default/db";
+
+    fn secret(data: Value) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({ "name": "db", "data": data })).unwrap()
+    }
+
+    fn data(value: &[u8]) -> serde_json::Map<String, Value> {
+        serde_json::from_slice::<Value>(value).unwrap()["data"]
+            .as_object()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cipher = SecretCipher::new(&[7; KEY_LEN]);
+        let plain = secret(serde_json::json!({ "password": "aHVudGVyMg==", "user": "YWRtaW4=" }));
+        let sealed = cipher.seal_object(KEY, &plain).unwrap();
+        for entry in data(&sealed).values() {
+            assert!(entry.as_str().unwrap().starts_with(ENCRYPTED_PREFIX));
+        }
+        assert!(!String::from_utf8_lossy(&sealed).contains("aHVudGVyMg=="));
+        assert_eq!(
+            data(&cipher.open_object(KEY, &sealed).unwrap()),
+            data(&plain)
+        );
+
+        // Sealing twice gives different ciphertexts; resealing keeps them.
+        assert_ne!(
+            data(&cipher.seal_object(KEY, &plain).unwrap()),
+            data(&sealed)
+        );
+        assert_eq!(
+            data(&cipher.seal_object(KEY, &sealed).unwrap()),
+            data(&sealed)
+        );
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let cipher = SecretCipher::new(&[7; KEY_LEN]);
+        let sealed = cipher
+            .seal_object(
+                KEY,
+                &secret(serde_json::json!({ "password": "aHVudGVyMg==" })),
+            )
+            .unwrap();
+        let entry = data(&sealed)["password"].as_str().unwrap().to_string();
+
+        // A flipped ciphertext byte.
+        let mut bytes = BASE64.decode(&entry[ENCRYPTED_PREFIX.len()..]).unwrap();
+        bytes[NONCE_LEN] ^= 1;
+        let flipped = format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(bytes));
+        let err = cipher
+            .open_object(KEY, &secret(serde_json::json!({ "password": flipped })))
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("data 'password': cannot be decrypted")
+        );
+
+        // The value moved to another field or secret, or another key.
+        let moved = secret(serde_json::json!({ "token": entry }));
+        assert!(cipher.open_object(KEY, &moved).is_err());
+        assert!(
+            cipher
+                .open_object("/registry/secrets/default/other", &sealed)
+                .is_err()
+        );
+        assert!(
+            SecretCipher::new(&[8; KEY_LEN])
+                .open_object(KEY, &sealed)
+                .is_err()
+        );
+        // Nor can such a value be written.
+        assert!(cipher.seal_object(KEY, &moved).is_err());
+    }
+
+    #[test]
+    fn test_plaintext_is_read_as_is() {
+        let cipher = SecretCipher::new(&[7; KEY_LEN]);
+        let plain = secret(serde_json::json!({ "password": "aHVudGVyMg==" }));
+        assert_eq!(
+            data(&cipher.open_object(KEY, &plain).unwrap()),
+            data(&plain)
+        );
+        // Objects without a data map pass through untouched.
+        let other = br#"{"name":"db"}"#;
+        assert_eq!(cipher.seal_object(KEY, other).unwrap(), other);
+    }
+
+    #[test]
+    fn test_key_file_and_env_override() {
+        let dir = std::env::temp_dir().join(format!("k3rs-secrets-key-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("secrets-encryption.key");
+
+        let first = SecretCipher::load_from(None, &path).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // The next start reads the same key back.
+        let sealed = first
+            .seal_object(
+                KEY,
+                &secret(serde_json::json!({ "password": "aHVudGVyMg==" })),
+            )
+            .unwrap();
+        let second = SecretCipher::load_from(None, &path).unwrap();
+        assert!(second.open_object(KEY, &sealed).is_ok());
+
+        // The environment takes precedence over the file.
+        let env = SecretCipher::load_from(Some(BASE64.encode([9u8; KEY_LEN])), &path).unwrap();
+        assert!(env.open_object(KEY, &sealed).is_err());
+        assert!(SecretCipher::load_from(Some("c2hvcnQ=".to_string()), &path).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -2,12 +2,14 @@ use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::sync::watch;
+use tokio::time::Instant;
 use tracing::{info, warn};
 
-use crate::client::StateStore;
+use crate::client::{PutOutcome, StateStore, resource_version};
 
 use pkg_constants::state::{
-    LEADER_LEASE_KEY, LEADER_LEASE_TTL_SECS, LEADER_RENEW_INTERVAL_DIVISOR,
+    LEADER_LEASE_KEY, LEADER_LEASE_TTL_SECS, LEADER_RENEW_DEADLINE_SECS,
+    LEADER_RENEW_INTERVAL_DIVISOR,
 };
 
 /// A distributed lease for leader election.
@@ -27,15 +29,29 @@ impl Lease {
     }
 }
 
-/// Leader election engine using SlateDB leases.
+/// The lease version a follower last saw, and since when.
+struct Observed {
+    version: u64,
+    since: Instant,
+}
+
+/// Leader election engine using store leases.
 ///
-/// Only one server instance holds the lease at a time.
-/// The leader runs Scheduler and Controllers; followers only serve API reads.
+/// Only one server instance holds the lease at a time: every acquire and
+/// renew is a conditional write against the version read, so two servers
+/// racing for it cannot both win.
+/// The leader runs Scheduler and Controllers; followers only serve the API.
+///
+/// Expiry is judged on each server's own clock. A follower takes over once
+/// the lease has gone a full TTL without changing since it first saw it,
+/// and a leader that has not renewed within the renew deadline (shorter
+/// than the TTL) steps down first, so their controllers never overlap.
 pub struct LeaderElection {
     store: StateStore,
     server_id: String,
     ttl: Duration,
     renew_interval: Duration,
+    renew_deadline: Duration,
     leader_tx: watch::Sender<bool>,
     leader_rx: watch::Receiver<bool>,
 }
@@ -52,11 +68,25 @@ impl LeaderElection {
             server_id,
             ttl,
             renew_interval,
+            renew_deadline: Duration::from_secs(LEADER_RENEW_DEADLINE_SECS),
             leader_tx,
             leader_rx,
         }
     }
 
+    /// Override the lease TTL, renew interval and renew deadline.
+    pub fn with_timings(
+        mut self,
+        ttl: Duration,
+        renew_interval: Duration,
+        renew_deadline: Duration,
+    ) -> Self {
+        self.ttl = ttl;
+        self.renew_interval = renew_interval;
+        self.renew_deadline = renew_deadline;
+        self
+    }
+
     /// Get a receiver to observe leadership changes.
     pub fn subscribe(&self) -> watch::Receiver<bool> {
         self.leader_rx.clone()
@@ -67,57 +97,66 @@ impl LeaderElection {
         *self.leader_rx.borrow()
     }
 
-    /// Try to acquire or renew the lease. Returns true if we are the leader.
-    async fn try_acquire_or_renew(&self) -> anyhow::Result<bool> {
+    fn new_lease(&self) -> anyhow::Result<Vec<u8>> {
         let now = Utc::now();
+        Ok(serde_json::to_vec(&Lease {
+            id: "controller-leader".to_string(),
+            holder_id: self.server_id.clone(),
+            acquired_at: now,
+            renew_at: now,
+            ttl_seconds: self.ttl.as_secs(),
+        })?)
+    }
 
-        match self.store.get(LEADER_LEASE_KEY).await? {
-            Some(data) => {
-                let lease: Lease = serde_json::from_slice(&data)?;
-
-                if lease.holder_id == self.server_id {
-                    // We hold it — renew
-                    let renewed = Lease {
-                        renew_at: now,
-                        ..lease
-                    };
-                    let data = serde_json::to_vec(&renewed)?;
-                    self.store.put(LEADER_LEASE_KEY, &data).await?;
-                    Ok(true)
-                } else if lease.is_expired() {
-                    // Previous holder's lease expired — take over
-                    info!(
-                        "Lease expired (held by {}), acquiring for {}",
-                        lease.holder_id, self.server_id
-                    );
-                    let new_lease = Lease {
-                        id: "controller-leader".to_string(),
-                        holder_id: self.server_id.clone(),
-                        acquired_at: now,
-                        renew_at: now,
-                        ttl_seconds: self.ttl.as_secs(),
-                    };
-                    let data = serde_json::to_vec(&new_lease)?;
-                    self.store.put(LEADER_LEASE_KEY, &data).await?;
-                    Ok(true)
-                } else {
-                    Ok(false)
-                }
-            }
-            None => {
-                info!("No existing lease found, acquiring for {}", self.server_id);
-                let lease = Lease {
-                    id: "controller-leader".to_string(),
-                    holder_id: self.server_id.clone(),
-                    acquired_at: now,
-                    renew_at: now,
-                    ttl_seconds: self.ttl.as_secs(),
-                };
-                let data = serde_json::to_vec(&lease)?;
-                self.store.put(LEADER_LEASE_KEY, &data).await?;
-                Ok(true)
+    /// Try to acquire or renew the lease. Returns true if we are the leader.
+    async fn try_acquire_or_renew(&self, observed: &mut Option<Observed>) -> anyhow::Result<bool> {
+        let Some(data) = self.store.get(LEADER_LEASE_KEY).await? else {
+            info!("No existing lease found, acquiring for {}", self.server_id);
+            let outcome = self
+                .store
+                .create(LEADER_LEASE_KEY, &self.new_lease()?)
+                .await?;
+            return Ok(matches!(outcome, PutOutcome::Stored(_)));
+        };
+        let lease: Lease = serde_json::from_slice(&data)?;
+        let version = resource_version(&data);
+
+        if lease.holder_id == self.server_id {
+            // We hold it — renew
+            let renewed = Lease {
+                renew_at: Utc::now(),
+                ..lease
+            };
+            let outcome = self
+                .store
+                .put_if_version(LEADER_LEASE_KEY, &serde_json::to_vec(&renewed)?, version)
+                .await?;
+            return Ok(matches!(outcome, PutOutcome::Stored(_)));
+        }
+
+        let since = match observed {
+            Some(seen) if seen.version == version => seen.since,
+            _ => {
+                *observed = Some(Observed {
+                    version,
+                    since: Instant::now(),
+                });
+                return Ok(false);
             }
+        };
+        if since.elapsed() < self.ttl {
+            return Ok(false);
         }
+        // Previous holder stopped renewing — take over
+        info!(
+            "Lease expired (held by {}), acquiring for {}",
+            lease.holder_id, self.server_id
+        );
+        let outcome = self
+            .store
+            .put_if_version(LEADER_LEASE_KEY, &self.new_lease()?, version)
+            .await?;
+        Ok(matches!(outcome, PutOutcome::Stored(_)))
     }
 
     /// Start the leader election loop as a background task.
@@ -131,31 +170,197 @@ impl LeaderElection {
                 self.renew_interval.as_secs()
             );
 
+            let mut observed = None;
+            // When the last successful renewal began.
+            let mut renewed: Option<Instant> = None;
             let mut interval = tokio::time::interval(self.renew_interval);
             loop {
                 interval.tick().await;
 
-                match self.try_acquire_or_renew().await {
-                    Ok(is_leader) => {
-                        let was_leader = *self.leader_rx.borrow();
-                        if is_leader && !was_leader {
-                            info!("🏆 This server is now the LEADER ({})", self.server_id);
-                        } else if !is_leader && was_leader {
-                            warn!(
-                                "⚠️  Leadership LOST for {} — another server took over",
-                                self.server_id
-                            );
-                        }
-                        let _ = self.leader_tx.send(is_leader);
+                let started = Instant::now();
+                let budget = renewed.map_or(self.renew_deadline, |at| {
+                    self.renew_deadline.saturating_sub(at.elapsed())
+                });
+                let attempt =
+                    tokio::time::timeout(budget, self.try_acquire_or_renew(&mut observed)).await;
+                let is_leader = match attempt {
+                    Ok(Ok(true)) => {
+                        renewed = Some(started);
+                        true
                     }
-                    Err(e) => {
+                    Ok(Ok(false)) => false,
+                    Ok(Err(e)) => {
                         warn!("Leader election error: {}", e);
-                        let _ = self.leader_tx.send(false);
+                        // Keep leading until the renew deadline passes
+                        renewed.is_some_and(|at| at.elapsed() < self.renew_deadline)
                     }
+                    Err(_) => {
+                        warn!("Leader election timed out renewing the lease");
+                        false
+                    }
+                };
+                if !is_leader {
+                    renewed = None;
                 }
+
+                let was_leader = *self.leader_rx.borrow();
+                if is_leader && !was_leader {
+                    info!("🏆 This server is now the LEADER ({})", self.server_id);
+                } else if !is_leader && was_leader {
+                    warn!("⚠️  Leadership LOST for {} — stepping down", self.server_id);
+                }
+                let _ = self.leader_tx.send(is_leader);
             }
         });
 
         (handle, rx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{SlateDbBackend, StateBackend};
+    use async_trait::async_trait;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// A server's link to the shared backend, which the test can cut.
+    struct Link {
+        shared: Arc<dyn StateBackend>,
+        cut: AtomicBool,
+    }
+
+    impl Link {
+        fn check(&self) -> anyhow::Result<()> {
+            if self.cut.load(Ordering::SeqCst) {
+                anyhow::bail!("state store unreachable");
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl StateBackend for Link {
+        fn describe(&self) -> String {
+            self.shared.describe()
+        }
+
+        fn location(&self) -> String {
+            self.shared.location()
+        }
+
+        async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+            self.check()?;
+            self.shared.get(key).await
+        }
+
+        async fn put(&self, key: &str, value: &[u8]) -> anyhow::Result<()> {
+            self.check()?;
+            self.shared.put(key, value).await
+        }
+
+        async fn put_if(
+            &self,
+            key: &str,
+            expected: Option<&[u8]>,
+            value: &[u8],
+        ) -> anyhow::Result<bool> {
+            self.check()?;
+            self.shared.put_if(key, expected, value).await
+        }
+
+        async fn delete(&self, key: &str) -> anyhow::Result<()> {
+            self.check()?;
+            self.shared.delete(key).await
+        }
+
+        async fn scan(
+            &self,
+            prefix: &str,
+            start_after: Option<&str>,
+            limit: Option<usize>,
+        ) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+            self.check()?;
+            self.shared.scan(prefix, start_after, limit).await
+        }
+
+        async fn close(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    const TTL: Duration = Duration::from_millis(600);
+
+    async fn shared_backend(label: &str) -> Arc<dyn StateBackend> {
+        let dir =
+            std::env::temp_dir().join(format!("k3rs-leader-test-{}-{}", label, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        Arc::new(SlateDbBackend::open(&dir.to_string_lossy()).await.unwrap())
+    }
+
+    async fn election(backend: Arc<dyn StateBackend>, server_id: &str) -> LeaderElection {
+        let store = StateStore::with_backend(backend).await.unwrap();
+        LeaderElection::new(store, server_id.to_string()).with_timings(
+            TTL,
+            Duration::from_millis(100),
+            Duration::from_millis(300),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_only_one_server_acquires_a_free_lease() {
+        let shared = shared_backend("race").await;
+        let a = election(shared.clone(), "server-a").await;
+        let b = election(shared, "server-b").await;
+        let (mut seen_a, mut seen_b) = (None, None);
+        let (won_a, won_b) = tokio::join!(
+            a.try_acquire_or_renew(&mut seen_a),
+            b.try_acquire_or_renew(&mut seen_b)
+        );
+        assert!(won_a.unwrap() ^ won_b.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_follower_takes_over_when_leader_cannot_renew() {
+        let shared = shared_backend("takeover").await;
+        let link = Arc::new(Link {
+            shared: shared.clone(),
+            cut: AtomicBool::new(false),
+        });
+        let (_a, mut a_leads) = election(link.clone(), "server-a").await.start();
+        tokio::time::timeout(Duration::from_secs(2), a_leads.wait_for(|l| *l))
+            .await
+            .unwrap()
+            .unwrap();
+        let (_b, mut b_leads) = election(shared, "server-b").await.start();
+
+        // Never both leaders, sampled throughout.
+        let (a, b) = (a_leads.clone(), b_leads.clone());
+        let overlap = tokio::spawn(async move {
+            loop {
+                if *a.borrow() && *b.borrow() {
+                    return true;
+                }
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        });
+
+        tokio::time::sleep(TTL * 2).await;
+        assert!(!*b_leads.borrow(), "B took over a lease A keeps renewing");
+
+        link.cut.store(true, Ordering::SeqCst);
+        tokio::time::timeout(TTL * 4, b_leads.wait_for(|l| *l))
+            .await
+            .expect("B did not take over")
+            .unwrap();
+        assert!(!*a_leads.borrow());
+        assert!(!overlap.is_finished());
+        overlap.abort();
+
+        let store = StateStore::with_backend(link.shared.clone()).await.unwrap();
+        let data = store.get(LEADER_LEASE_KEY).await.unwrap().unwrap();
+        let lease: Lease = serde_json::from_slice(&data).unwrap();
+        assert_eq!(lease.holder_id, "server-b");
+    }
+}
@@ -1,3 +1,5 @@
+pub mod backend;
 pub mod client;
+pub mod encryption;
 pub mod leader;
 pub mod watch;
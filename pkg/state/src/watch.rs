@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::sync::broadcast;
@@ -31,10 +32,22 @@ pub struct EventLog {
 struct EventLogInner {
     seq: u64,
     /// Ring buffer of recent events (capped)
-    events: Vec<WatchEvent>,
+    events: VecDeque<WatchEvent>,
     max_events: usize,
 }
 
+/// The events a watch asked to resume after are no longer all buffered (or
+/// the seq is newer than any event, e.g. from before a server restart). The
+/// client has to relist and watch from the list's seq.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compacted {
+    /// Seq the client asked to resume after.
+    pub from_seq: u64,
+    /// Oldest buffered seq; a resume must start at or after `oldest - 1`.
+    pub oldest: u64,
+    pub current: u64,
+}
+
 impl EventLog {
     /// Create a new event log with the given capacity for recent events.
     pub fn new(max_events: usize) -> Self {
@@ -42,7 +55,7 @@ impl EventLog {
         Self {
             inner: Arc::new(RwLock::new(EventLogInner {
                 seq: 0,
-                events: Vec::with_capacity(max_events),
+                events: VecDeque::with_capacity(max_events),
                 max_events,
             })),
             sender,
@@ -61,9 +74,9 @@ impl EventLog {
         };
         // Ring buffer: remove oldest if at capacity
         if inner.events.len() >= inner.max_events {
-            inner.events.remove(0);
+            inner.events.pop_front();
         }
-        inner.events.push(event.clone());
+        inner.events.push_back(event.clone());
         // Broadcast to subscribers (ignore errors if no receivers)
         let _ = self.sender.send(event);
     }
@@ -84,8 +97,82 @@ impl EventLog {
             .collect()
     }
 
+    /// Every event after `from_seq`, or [`Compacted`] when some of them have
+    /// already been dropped from the buffer.
+    pub async fn replay(&self, from_seq: u64) -> Result<Vec<WatchEvent>, Compacted> {
+        let inner = self.inner.read().await;
+        let oldest = inner.events.front().map_or(inner.seq + 1, |e| e.seq);
+        if from_seq > inner.seq || from_seq + 1 < oldest {
+            return Err(Compacted {
+                from_seq,
+                oldest,
+                current: inner.seq,
+            });
+        }
+        Ok(inner
+            .events
+            .iter()
+            .filter(|e| e.seq > from_seq)
+            .cloned()
+            .collect())
+    }
+
     /// Subscribe to receive new events as they are emitted.
     pub fn subscribe(&self) -> broadcast::Receiver<WatchEvent> {
         self.sender.subscribe()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn emit_puts(log: &EventLog, keys: &[&str]) {
+        for key in keys {
+            log.emit(EventType::Put, key.to_string(), None).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_resumes_after_seq() {
+        let log = EventLog::new(10);
+        emit_puts(
+            &log,
+            &["/registry/pods/a", "/registry/pods/b", "/registry/pods/c"],
+        )
+        .await;
+
+        let seqs = |events: Vec<WatchEvent>| events.iter().map(|e| e.seq).collect::<Vec<_>>();
+        assert_eq!(seqs(log.replay(0).await.unwrap()), [1, 2, 3]);
+        assert_eq!(seqs(log.replay(1).await.unwrap()), [2, 3]);
+        // Caught up: nothing to replay, but not an error either.
+        assert!(log.replay(3).await.unwrap().is_empty());
+        // A seq the log never reached (e.g. from before a restart).
+        assert_eq!(
+            log.replay(7).await.unwrap_err(),
+            Compacted {
+                from_seq: 7,
+                oldest: 1,
+                current: 3
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replay_after_compaction() {
+        let log = EventLog::new(3);
+        emit_puts(&log, &["/a", "/b", "/c", "/d", "/e"]).await;
+
+        // Seqs 1 and 2 were dropped: resuming after 2 still sees everything
+        // that followed, resuming after 1 would miss seq 2.
+        let events = log.replay(2).await.unwrap();
+        assert_eq!(
+            events.iter().map(|e| e.key.as_str()).collect::<Vec<_>>(),
+            ["/c", "/d", "/e"]
+        );
+        let err = log.replay(1).await.unwrap_err();
+        assert_eq!((err.oldest, err.current), (3, 5));
+        assert!(log.replay(0).await.is_err());
+        assert_eq!(log.events_since(0).await.len(), 3);
+    }
+}
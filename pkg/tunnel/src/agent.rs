@@ -0,0 +1,242 @@
+//! The agent's side: keep a tunnel open to the server and connect every
+//! stream the server opens to a local address (the agent API).
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{Sink, Stream, StreamExt, future};
+use pkg_constants::timings::{BACKOFF_MAX_SECS, BACKOFF_SHIFT_CAP, TUNNEL_CONNECT_TIMEOUT_SECS};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::{self, Message, handshake::client::Request};
+use tracing::{debug, info, warn};
+
+use crate::mux::Tunnel;
+
+/// Keep a tunnel open to the server, relaying the streams it opens to
+/// `target`. `request` builds the WebSocket handshake for each attempt, or
+/// returns `None` while the agent has no credentials for one yet. Failed and
+/// lost tunnels are retried with exponential backoff; never returns.
+pub async fn run<F>(mut request: F, target: SocketAddr)
+where
+    F: FnMut() -> Option<Request>,
+{
+    let mut attempt = 0u32;
+    loop {
+        if let Some(request) = request() {
+            let url = request.uri().to_string();
+            let connect = tokio_tungstenite::connect_async(request);
+            match tokio::time::timeout(Duration::from_secs(TUNNEL_CONNECT_TIMEOUT_SECS), connect)
+                .await
+            {
+                Ok(Ok((ws, _))) => {
+                    info!("Tunnel to {} established", url);
+                    attempt = 0;
+                    serve(ws, target).await;
+                    warn!("Tunnel to {} lost, reconnecting", url);
+                }
+                Ok(Err(e)) => warn!("Tunnel to {} failed: {}", url, e),
+                Err(_) => warn!(
+                    "Tunnel to {} failed: no answer within {}s",
+                    url, TUNNEL_CONNECT_TIMEOUT_SECS
+                ),
+            }
+        }
+        tokio::time::sleep(backoff(attempt)).await;
+        attempt = attempt.saturating_add(1);
+    }
+}
+
+/// Serve one established tunnel until it closes.
+pub async fn serve<S>(ws: WebSocketStream<S>, target: SocketAddr)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (tx, rx) = frames(ws);
+    let (_tunnel, mut accepted, driver) = Tunnel::new(tx, rx);
+    let relay = tokio::spawn(async move {
+        while let Some(mut stream) = accepted.recv().await {
+            tokio::spawn(async move {
+                match TcpStream::connect(target).await {
+                    Ok(mut conn) => {
+                        let _ = tokio::io::copy_bidirectional(&mut stream, &mut conn).await;
+                    }
+                    // Dropping the stream tells the server it was refused.
+                    Err(e) => debug!("Tunnel stream to {} refused: {}", target, e),
+                }
+            });
+        }
+    });
+    driver.await;
+    relay.abort();
+}
+
+/// The tunnel frames carried by `ws`, one per binary message. The stream
+/// ends at the first close or error.
+pub fn frames<S>(
+    ws: WebSocketStream<S>,
+) -> (
+    impl Sink<Bytes, Error = tungstenite::Error> + Send,
+    impl Stream<Item = Bytes> + Send,
+)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    let (ws_tx, ws_rx) = ws.split();
+    let tx = futures_util::SinkExt::with(ws_tx, |frame: Bytes| {
+        future::ready(Ok::<_, tungstenite::Error>(Message::Binary(frame)))
+    });
+    let rx = ws_rx
+        .take_while(|msg| future::ready(matches!(msg, Ok(msg) if !msg.is_close())))
+        .filter_map(|msg| {
+            future::ready(match msg {
+                Ok(Message::Binary(frame)) => Some(frame),
+                _ => None,
+            })
+        });
+    (tx, rx)
+}
+
+/// Delay before reconnect attempt `attempt` (0-based): 1s, 2s, 4s … capped
+/// at `BACKOFF_MAX_SECS`.
+fn backoff(attempt: u32) -> Duration {
+    let secs = 1u64 << attempt.min(BACKOFF_SHIFT_CAP);
+    Duration::from_secs(secs.min(BACKOFF_MAX_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::TunnelRegistry;
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+    use tokio::task::JoinHandle;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+    /// Stand-in for the agent API: echoes every connection.
+    async fn echo_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut conn, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let (mut reader, mut writer) = conn.split();
+                    let _ = tokio::io::copy(&mut reader, &mut writer).await;
+                });
+            }
+        });
+        addr
+    }
+
+    /// Stand-in for the server's tunnel endpoint, registering every tunnel
+    /// as `node-a`. The task serving each one is sent on the channel, so a
+    /// test can drop it.
+    async fn tunnel_server(
+        registry: Arc<TunnelRegistry>,
+    ) -> (String, mpsc::UnboundedReceiver<JoinHandle<()>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/tunnel", listener.local_addr().unwrap());
+        let (served_tx, served_rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((conn, _)) = listener.accept().await {
+                let ws = tokio_tungstenite::accept_async(conn).await.unwrap();
+                let registry = registry.clone();
+                let _ = served_tx.send(tokio::spawn(async move {
+                    let (tx, rx) = frames(ws);
+                    registry.serve("node-a", tx, rx).await;
+                }));
+            }
+        });
+        (url, served_rx)
+    }
+
+    /// Start an agent tunnel to `url` relaying to `target`.
+    fn start_agent(url: String, target: SocketAddr) {
+        tokio::spawn(run(
+            move || Some(url.as_str().into_client_request().unwrap()),
+            target,
+        ));
+    }
+
+    async fn wait_until(what: &str, cond: impl Fn() -> bool) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while !cond() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("timed out waiting until {}", what));
+    }
+
+    async fn round_trip(registry: &TunnelRegistry, payload: &[u8]) {
+        let mut stream = registry.open("node-a").expect("tunnel is up");
+        stream.write_all(payload).await.unwrap();
+        let mut echoed = vec![0u8; payload.len()];
+        stream.read_exact(&mut echoed).await.unwrap();
+        assert_eq!(echoed, payload);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_streams_reach_the_agent() {
+        let registry = Arc::new(TunnelRegistry::new());
+        let (url, _served) = tunnel_server(registry.clone()).await;
+        start_agent(url, echo_server().await);
+        wait_until("the tunnel is up", || registry.is_connected("node-a")).await;
+
+        let streams: Vec<_> = (0..8)
+            .map(|i| {
+                let registry = registry.clone();
+                tokio::spawn(async move {
+                    // Large enough to span several frames.
+                    let payload = format!("stream-{}-", i).repeat(4096);
+                    round_trip(&registry, payload.as_bytes()).await;
+                })
+            })
+            .collect();
+        for stream in streams {
+            stream.await.unwrap();
+        }
+
+        wait_until("the streams are closed", || {
+            registry.stats()[0].active_streams == 0
+        })
+        .await;
+        let stats = registry.stats();
+        assert_eq!(stats.len(), 1);
+        assert!(stats[0].connected);
+        assert_eq!(stats[0].opened_streams, 8);
+    }
+
+    #[tokio::test]
+    async fn test_agent_reconnects_after_the_tunnel_drops() {
+        let registry = Arc::new(TunnelRegistry::new());
+        let (url, mut served) = tunnel_server(registry.clone()).await;
+        start_agent(url, echo_server().await);
+        wait_until("the tunnel is up", || registry.is_connected("node-a")).await;
+        round_trip(&registry, b"before").await;
+
+        // The server side of the tunnel goes away, mid-stream.
+        let mut open = registry.open("node-a").unwrap();
+        served.recv().await.unwrap().abort();
+        wait_until("the tunnel is down", || !registry.is_connected("node-a")).await;
+        let mut rest = Vec::new();
+        open.read_to_end(&mut rest).await.unwrap();
+        assert!(registry.open("node-a").is_none());
+
+        served.recv().await.expect("agent reconnected");
+        wait_until("the tunnel is back", || registry.is_connected("node-a")).await;
+        round_trip(&registry, b"after").await;
+        assert_eq!(registry.stats()[0].opened_streams, 3);
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        assert_eq!(backoff(0), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(8));
+        assert_eq!(backoff(40), Duration::from_secs(BACKOFF_MAX_SECS));
+    }
+}
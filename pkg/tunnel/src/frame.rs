@@ -0,0 +1,114 @@
+//! Wire format: every WebSocket binary message is one frame,
+//! `[kind: u8][stream: u32 big-endian][payload]`.
+//!
+//! | kind | frame   | payload          |
+//! |------|---------|------------------|
+//! | 1    | `Open`  | —                |
+//! | 2    | `Data`  | the stream bytes |
+//! | 3    | `Close` | —                |
+//! | 4    | `Ping`  | — (stream 0)     |
+//! | 5    | `Credit`| byte count (u32 big-endian) |
+//!
+//! Only the server opens streams. `Close` ends a stream in both directions.
+//! Each side sends `Ping` periodically so the other can tell a dead tunnel
+//! from an idle one.
+//!
+//! Each side may send a stream `mux::STREAM_WINDOW` bytes of `Data` up front;
+//! after that, only as many bytes as the receiver has granted back with
+//! `Credit` once its reader consumed them.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+const OPEN: u8 = 1;
+const DATA: u8 = 2;
+const CLOSE: u8 = 3;
+const PING: u8 = 4;
+const CREDIT: u8 = 5;
+
+/// Bytes before a frame's payload.
+const HEADER_LEN: usize = 5;
+
+/// One message of the multiplexing protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    /// The peer opened stream `id`.
+    Open(u32),
+    /// Bytes for stream `id`.
+    Data(u32, Bytes),
+    /// Stream `id` is finished.
+    Close(u32),
+    /// Keepalive.
+    Ping,
+    /// The peer may send this many more bytes on stream `id`.
+    Credit(u32, u32),
+}
+
+impl Frame {
+    pub fn encode(&self) -> Bytes {
+        let (kind, id, payload) = match self {
+            Self::Open(id) => (OPEN, *id, None),
+            Self::Data(id, data) => (DATA, *id, Some(data.clone())),
+            Self::Close(id) => (CLOSE, *id, None),
+            Self::Ping => (PING, 0, None),
+            Self::Credit(id, n) => (CREDIT, *id, Some(Bytes::copy_from_slice(&n.to_be_bytes()))),
+        };
+        let mut buf = BytesMut::with_capacity(HEADER_LEN + payload.as_ref().map_or(0, Bytes::len));
+        buf.put_u8(kind);
+        buf.put_u32(id);
+        if let Some(payload) = &payload {
+            buf.put_slice(payload);
+        }
+        buf.freeze()
+    }
+
+    /// The frame in `buf`, or `None` if it is not one.
+    pub fn decode(mut buf: Bytes) -> Option<Self> {
+        if buf.len() < HEADER_LEN {
+            return None;
+        }
+        let kind = buf[0];
+        let id = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]);
+        let payload = buf.split_off(HEADER_LEN);
+        match kind {
+            OPEN => Some(Self::Open(id)),
+            DATA => Some(Self::Data(id, payload)),
+            CLOSE => Some(Self::Close(id)),
+            PING => Some(Self::Ping),
+            CREDIT => Some(Self::Credit(
+                id,
+                u32::from_be_bytes(payload[..].try_into().ok()?),
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_round_trip() {
+        let frames = [
+            Frame::Open(1),
+            Frame::Data(7, Bytes::from_static(b"GET / HTTP/1.1\r\n")),
+            Frame::Data(u32::MAX, Bytes::new()),
+            Frame::Close(7),
+            Frame::Ping,
+            Frame::Credit(7, 65536),
+        ];
+        for frame in frames {
+            assert_eq!(Frame::decode(frame.encode()), Some(frame));
+        }
+    }
+
+    #[test]
+    fn test_rejects_malformed_frames() {
+        assert_eq!(Frame::decode(Bytes::from_static(&[DATA, 0, 0])), None);
+        assert_eq!(Frame::decode(Bytes::from_static(&[9, 0, 0, 0, 1])), None);
+        assert_eq!(
+            Frame::decode(Bytes::from_static(&[CREDIT, 0, 0, 0, 1, 0])),
+            None
+        );
+    }
+}
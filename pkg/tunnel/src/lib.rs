@@ -0,0 +1,25 @@
+//! Reverse tunnel between agents and the server.
+//!
+//! Agents behind NAT can't be dialed, so each agent keeps one outbound
+//! WebSocket open to the server (`GET /api/v1/nodes/{name}/tunnel`). The
+//! server multiplexes logical byte streams over it, each of which the agent
+//! connects to its own agent API; exec, logs, archive and port-forward then
+//! run over a stream exactly as over a direct TCP connection.
+//!
+//! - [`frame`]: the wire format of the multiplexing protocol.
+//! - [`mux`]: one tunnel — its streams and the task driving the transport.
+//! - [`registry`]: the server's tunnels, keyed by node name.
+//! - [`agent`]: the agent's side — connecting, reconnecting with backoff and
+//!   relaying streams to a local address.
+
+pub mod agent;
+pub mod frame;
+pub mod mux;
+pub mod registry;
+
+pub use mux::{Tunnel, TunnelStream};
+pub use registry::{TunnelRegistry, TunnelStats};
+
+/// Header an agent presents its agent API token in when opening its tunnel,
+/// next to its node bearer token in `Authorization`.
+pub const AGENT_TOKEN_HEADER: &str = "x-k3rs-agent-token";
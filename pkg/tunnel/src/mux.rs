@@ -0,0 +1,465 @@
+//! One tunnel: logical byte streams multiplexed over a message transport.
+//!
+//! Each stream is handed out as one end of an in-memory duplex pipe; a pump
+//! task per stream turns what is written into it into `Data` frames and
+//! writes the peer's `Data` frames back. Dropping or shutting down a stream
+//! closes it on both sides, and so does the peer's `Close`.
+//!
+//! Streams are flow-controlled: a pump sends only as many bytes as the peer
+//! has room for, and grants the peer more as its own reader drains the pipe.
+//! A stream nobody reads therefore holds at most `STREAM_WINDOW` bytes on
+//! the receiving side and stalls its writer, without holding up the other
+//! streams or the keepalive.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use pkg_constants::timings::{TUNNEL_IDLE_TIMEOUT_SECS, TUNNEL_PING_INTERVAL_SECS};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::sync::{Semaphore, mpsc};
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::frame::Frame;
+
+/// A stream over a tunnel; reads and writes like a TCP connection.
+pub type TunnelStream = DuplexStream;
+
+/// Bytes buffered in each direction of a stream's pipe.
+const STREAM_BUFFER: usize = 64 * 1024;
+
+/// Largest `Data` frame payload.
+const MAX_CHUNK: usize = 16 * 1024;
+
+/// Bytes of `Data` either side may send a stream before the receiver
+/// grants more with `Credit`.
+pub(crate) const STREAM_WINDOW: usize = 256 * 1024;
+
+/// Bytes a pump lets its reader consume before granting them back.
+const CREDIT_BATCH: usize = STREAM_WINDOW / 4;
+
+/// `Data` and `Close` frames queued for the transport.
+const OUTBOUND_FRAMES: usize = 64;
+
+/// Handle to a tunnel, for opening streams over it.
+#[derive(Clone)]
+pub struct Tunnel {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    /// `Open` and `Credit` frames waiting for the transport. They are few and
+    /// small, and go out ahead of `data`.
+    control: mpsc::UnboundedSender<Frame>,
+    /// `Data` and `Close` frames waiting for the transport, in stream order.
+    data: mpsc::Sender<Frame>,
+    /// Open streams.
+    streams: Mutex<HashMap<u32, StreamState>>,
+    next_id: AtomicU32,
+    /// Set, under the `streams` lock, once the transport is gone.
+    closed: AtomicBool,
+}
+
+/// The driver's side of an open stream.
+struct StreamState {
+    /// Where the peer's `Data` goes. Unbounded, but the peer may only have
+    /// `recv_window` bytes in it.
+    inbound: mpsc::UnboundedSender<Bytes>,
+    /// Bytes the peer may still send before it is granted more.
+    recv_window: usize,
+    /// Bytes the pump may still send; the peer's `Credit` adds to it.
+    send_credit: Arc<Semaphore>,
+}
+
+impl Tunnel {
+    /// A tunnel over a transport carrying one encoded frame per message:
+    /// `tx` sends them, `rx` yields them and ends when the transport closes.
+    ///
+    /// Returns the handle, the streams the peer opens, and the future that
+    /// drives the transport. The future completes when the transport closes
+    /// or the peer has been silent for `TUNNEL_IDLE_TIMEOUT_SECS`; every
+    /// stream then reads EOF.
+    pub fn new<Tx, Rx>(
+        tx: Tx,
+        rx: Rx,
+    ) -> (
+        Self,
+        mpsc::UnboundedReceiver<TunnelStream>,
+        impl Future<Output = ()> + Send + 'static,
+    )
+    where
+        Tx: Sink<Bytes> + Send + 'static,
+        Rx: Stream<Item = Bytes> + Send + 'static,
+    {
+        let (control_tx, control_rx) = mpsc::unbounded_channel();
+        let (data_tx, data_rx) = mpsc::channel(OUTBOUND_FRAMES);
+        let shared = Arc::new(Shared {
+            control: control_tx,
+            data: data_tx,
+            streams: Mutex::new(HashMap::new()),
+            next_id: AtomicU32::new(1),
+            closed: AtomicBool::new(false),
+        });
+        let (accepted_tx, accepted_rx) = mpsc::unbounded_channel();
+        let driver = drive(shared.clone(), tx, rx, control_rx, data_rx, accepted_tx);
+        (Self { shared }, accepted_rx, driver)
+    }
+
+    /// Open a stream to the peer, unless the tunnel has closed.
+    pub fn open(&self) -> Option<TunnelStream> {
+        if self.is_closed() {
+            return None;
+        }
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        // Registered before the peer hears of it, so none of its answer is
+        // missed; the stream's own data goes out after the `Open`.
+        let stream = self.shared.attach(id)?;
+        let _ = self.shared.control.send(Frame::Open(id));
+        Some(stream)
+    }
+
+    /// End every stream and refuse new ones, as when the transport closes.
+    /// For when the driver is dropped rather than run to completion.
+    pub(crate) fn shut_down(&self) {
+        self.shared.shut_down();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.load(Ordering::Acquire)
+    }
+
+    /// Streams currently open.
+    pub fn active_streams(&self) -> usize {
+        self.shared.streams.lock().unwrap().len()
+    }
+}
+
+impl Shared {
+    /// Register stream `id` and start its pump; `None` once closed.
+    fn attach(self: &Arc<Self>, id: u32) -> Option<TunnelStream> {
+        let (inbound_tx, inbound_rx) = mpsc::unbounded_channel();
+        let send_credit = Arc::new(Semaphore::new(STREAM_WINDOW));
+        {
+            let mut streams = self.streams.lock().unwrap();
+            if self.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            let state = StreamState {
+                inbound: inbound_tx,
+                recv_window: STREAM_WINDOW,
+                send_credit: send_credit.clone(),
+            };
+            streams.insert(id, state);
+        }
+        let (user, pipe) = tokio::io::duplex(STREAM_BUFFER);
+        tokio::spawn(pump(self.clone(), id, pipe, inbound_rx, send_credit));
+        Some(user)
+    }
+
+    /// End stream `id` from this side, telling the peer if it was open.
+    async fn close(&self, id: u32) {
+        let open = self.streams.lock().unwrap().remove(&id).is_some();
+        if open {
+            let _ = self.data.send(Frame::Close(id)).await;
+        }
+    }
+
+    /// Let the peer send `n` more bytes on stream `id`, which its reader
+    /// has consumed.
+    fn grant(&self, id: u32, n: usize) {
+        if let Some(stream) = self.streams.lock().unwrap().get_mut(&id) {
+            stream.recv_window += n;
+            let _ = self.control.send(Frame::Credit(id, n as u32));
+        }
+    }
+
+    /// The peer sent `data` on stream `id`; a peer sending more than it was
+    /// granted loses the stream.
+    fn receive(&self, id: u32, data: Bytes) {
+        let mut streams = self.streams.lock().unwrap();
+        let Some(stream) = streams.get_mut(&id) else {
+            return;
+        };
+        if data.len() > stream.recv_window {
+            warn!("Tunnel stream {} overran its window, closing", id);
+            streams.remove(&id);
+            let _ = self.control.send(Frame::Close(id));
+            return;
+        }
+        stream.recv_window -= data.len();
+        let _ = stream.inbound.send(data);
+    }
+
+    /// The peer granted `n` more bytes on stream `id`; more than the window
+    /// is never owed, so anything past it is ignored.
+    fn credit(&self, id: u32, n: u32) {
+        if let Some(stream) = self.streams.lock().unwrap().get(&id) {
+            let room = STREAM_WINDOW.saturating_sub(stream.send_credit.available_permits());
+            stream.send_credit.add_permits((n as usize).min(room));
+        }
+    }
+
+    /// The transport is gone: refuse new streams and end the open ones.
+    fn shut_down(&self) {
+        let mut streams = self.streams.lock().unwrap();
+        self.closed.store(true, Ordering::Release);
+        streams.clear();
+    }
+}
+
+/// Move bytes between stream `id`'s pipe and the tunnel until either side
+/// closes it.
+async fn pump(
+    shared: Arc<Shared>,
+    id: u32,
+    pipe: DuplexStream,
+    mut inbound: mpsc::UnboundedReceiver<Bytes>,
+    send_credit: Arc<Semaphore>,
+) {
+    let (mut reader, mut writer) = tokio::io::split(pipe);
+    let outbound = async {
+        let mut buf = vec![0u8; MAX_CHUNK];
+        // Until the local end shuts down or drops the stream.
+        while let Ok(n) = reader.read(&mut buf).await
+            && n > 0
+        {
+            let Ok(permits) = send_credit.acquire_many(n as u32).await else {
+                break;
+            };
+            permits.forget();
+            let data = Frame::Data(id, Bytes::copy_from_slice(&buf[..n]));
+            if shared.data.send(data).await.is_err() {
+                break;
+            }
+        }
+        shared.close(id).await;
+    };
+    let inbound = async {
+        let mut consumed = 0;
+        while let Some(data) = inbound.recv().await {
+            if writer.write_all(&data).await.is_err() {
+                shared.close(id).await;
+                return;
+            }
+            consumed += data.len();
+            if consumed >= CREDIT_BATCH {
+                shared.grant(id, consumed);
+                consumed = 0;
+            }
+        }
+        // The peer closed the stream, or the tunnel is gone. What was
+        // written stays readable.
+        let _ = writer.shutdown().await;
+    };
+    tokio::select! {
+        _ = outbound => {}
+        _ = inbound => {}
+    }
+    debug!("Tunnel stream {} closed", id);
+}
+
+/// Carry frames between the transport and the streams until the transport
+/// closes or goes quiet.
+async fn drive<Tx, Rx>(
+    shared: Arc<Shared>,
+    tx: Tx,
+    rx: Rx,
+    mut control: mpsc::UnboundedReceiver<Frame>,
+    mut data: mpsc::Receiver<Frame>,
+    accepted: mpsc::UnboundedSender<TunnelStream>,
+) where
+    Tx: Sink<Bytes>,
+    Rx: Stream<Item = Bytes>,
+{
+    let mut tx = std::pin::pin!(tx);
+    let mut rx = std::pin::pin!(rx);
+    let idle = Duration::from_secs(TUNNEL_IDLE_TIMEOUT_SECS);
+    let period = Duration::from_secs(TUNNEL_PING_INTERVAL_SECS);
+    let mut ping = tokio::time::interval_at(Instant::now() + period, period);
+    let mut seen = Instant::now();
+    loop {
+        tokio::select! {
+            // A stream's `Open` goes out before any of its data.
+            biased;
+            Some(frame) = control.recv() => {
+                if tx.send(frame.encode()).await.is_err() {
+                    break;
+                }
+            }
+            Some(frame) = data.recv() => {
+                if tx.send(frame.encode()).await.is_err() {
+                    break;
+                }
+            }
+            msg = rx.next() => {
+                let Some(msg) = msg else { break };
+                seen = Instant::now();
+                match Frame::decode(msg) {
+                    Some(Frame::Open(id)) => {
+                        if let Some(stream) = shared.attach(id) {
+                            // Nobody accepting: dropping the stream closes it.
+                            let _ = accepted.send(stream);
+                        }
+                    }
+                    Some(Frame::Data(id, bytes)) => shared.receive(id, bytes),
+                    Some(Frame::Credit(id, n)) => shared.credit(id, n),
+                    Some(Frame::Close(id)) => {
+                        shared.streams.lock().unwrap().remove(&id);
+                    }
+                    Some(Frame::Ping) => {}
+                    None => warn!("Dropping malformed tunnel frame"),
+                }
+            }
+            _ = ping.tick() => {
+                if seen.elapsed() > idle {
+                    warn!("Tunnel peer silent for {:?}, closing", idle);
+                    break;
+                }
+                if tx.send(Frame::Ping.encode()).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    shared.shut_down();
+    let _ = tx.close().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Two tunnel ends joined by in-memory channels, with their drivers
+    /// running. Sending on the returned channel ends `a`'s transport.
+    fn pair() -> (
+        Tunnel,
+        mpsc::UnboundedReceiver<TunnelStream>,
+        tokio::sync::oneshot::Sender<()>,
+    ) {
+        let (a_tx, b_rx) = mpsc::unbounded_channel::<Bytes>();
+        let (b_tx, a_rx) = mpsc::unbounded_channel::<Bytes>();
+        let sink = |tx: mpsc::UnboundedSender<Bytes>| {
+            futures_util::sink::unfold(tx, |tx, frame: Bytes| async move {
+                tx.send(frame).map_err(|_| ())?;
+                Ok::<_, ()>(tx)
+            })
+        };
+        let stream = |rx: mpsc::UnboundedReceiver<Bytes>| {
+            futures_util::stream::unfold(rx, |mut rx| async move {
+                rx.recv().await.map(|frame| (frame, rx))
+            })
+        };
+        let (cut_tx, cut_rx) = tokio::sync::oneshot::channel();
+        let (a, _, a_driver) = Tunnel::new(sink(a_tx), stream(a_rx).take_until(cut_rx));
+        let (_b, b_accepted, b_driver) = Tunnel::new(sink(b_tx), stream(b_rx));
+        tokio::spawn(a_driver);
+        tokio::spawn(b_driver);
+        (a, b_accepted, cut_tx)
+    }
+
+    #[tokio::test]
+    async fn test_streams_carry_bytes_independently() {
+        let (a, mut accepted, _cut) = pair();
+
+        let mut first = a.open().unwrap();
+        let mut second = a.open().unwrap();
+        let mut first_peer = accepted.recv().await.unwrap();
+        let mut second_peer = accepted.recv().await.unwrap();
+
+        second.write_all(b"two").await.unwrap();
+        first.write_all(b"one").await.unwrap();
+        let mut buf = [0u8; 3];
+        first_peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"one");
+        second_peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"two");
+
+        second_peer.write_all(b"back").await.unwrap();
+        let mut buf = [0u8; 4];
+        second.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"back");
+        assert_eq!(a.active_streams(), 2);
+
+        // Dropping one end closes the stream on the other.
+        drop(first);
+        let mut rest = Vec::new();
+        first_peer.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(a.active_streams(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_unread_stream_stalls_only_itself() {
+        let (a, mut accepted, _cut) = pair();
+        let mut flooded = a.open().unwrap();
+        let mut flooded_peer = accepted.recv().await.unwrap();
+        let mut other = a.open().unwrap();
+        let mut other_peer = accepted.recv().await.unwrap();
+
+        // Far more than the window and both pipes hold; nobody reads it yet.
+        let payload = vec![7u8; 4 * STREAM_WINDOW];
+        let expected = payload.len();
+        let writer = tokio::spawn(async move {
+            flooded.write_all(&payload).await.unwrap();
+            flooded
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!writer.is_finished());
+
+        other.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        other_peer.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        let mut received = vec![0u8; expected];
+        flooded_peer.read_exact(&mut received).await.unwrap();
+        assert!(received.iter().all(|&b| b == 7));
+        drop(writer.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_peer_overrunning_its_window_loses_the_stream() {
+        let (peer_tx, rx) = mpsc::unbounded_channel::<Bytes>();
+        let (tx, mut peer_rx) = mpsc::unbounded_channel::<Bytes>();
+        let sink = futures_util::sink::unfold(tx, |tx, frame: Bytes| async move {
+            tx.send(frame).map_err(|_| ())?;
+            Ok::<_, ()>(tx)
+        });
+        let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|frame| (frame, rx))
+        });
+        let (tunnel, mut accepted, driver) = Tunnel::new(sink, stream);
+        tokio::spawn(driver);
+
+        peer_tx.send(Frame::Open(1).encode()).unwrap();
+        let mut stream = accepted.recv().await.unwrap();
+        let overrun = Bytes::from(vec![0u8; STREAM_WINDOW + 1]);
+        peer_tx.send(Frame::Data(1, overrun).encode()).unwrap();
+
+        let reply = Frame::decode(peer_rx.recv().await.unwrap());
+        assert_eq!(reply, Some(Frame::Close(1)));
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(tunnel.active_streams(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_closed_transport_ends_streams() {
+        let (a, mut accepted, cut) = pair();
+        let mut stream = a.open().unwrap();
+        let _peer = accepted.recv().await.unwrap();
+
+        cut.send(()).unwrap();
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(a.is_closed());
+        assert!(a.open().is_none());
+    }
+}
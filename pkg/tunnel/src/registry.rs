@@ -0,0 +1,138 @@
+//! The server's side: the tunnel each connected agent keeps open, by node
+//! name, and per-node stream accounting.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use bytes::Bytes;
+use futures_util::{Sink, Stream};
+use tracing::info;
+
+use crate::mux::{Tunnel, TunnelStream};
+
+/// Tunnels from agents, keyed by node name.
+#[derive(Default)]
+pub struct TunnelRegistry {
+    tunnels: Mutex<HashMap<String, Registered>>,
+    /// Streams opened per node, across reconnects.
+    opened: Mutex<BTreeMap<String, u64>>,
+    next_generation: AtomicU64,
+}
+
+struct Registered {
+    /// Tells a tunnel apart from the one that replaced it.
+    generation: u64,
+    tunnel: Tunnel,
+}
+
+/// Stream accounting for one node's tunnel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TunnelStats {
+    pub node: String,
+    pub connected: bool,
+    pub active_streams: usize,
+    pub opened_streams: u64,
+}
+
+impl TunnelRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `node`'s tunnel over `tx`/`rx` until it closes. Streams are
+    /// only opened by this side, so the agent opening any is refused. A
+    /// newer tunnel from the same node takes over new streams at once; the
+    /// old one keeps its streams until its transport goes.
+    pub async fn serve<Tx, Rx>(&self, node: &str, tx: Tx, rx: Rx)
+    where
+        Tx: Sink<Bytes> + Send + 'static,
+        Rx: Stream<Item = Bytes> + Send + 'static,
+    {
+        let (tunnel, _refused, driver) = Tunnel::new(tx, rx);
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        self.tunnels.lock().unwrap().insert(
+            node.to_string(),
+            Registered {
+                generation,
+                tunnel: tunnel.clone(),
+            },
+        );
+        info!("Tunnel from node {} connected", node);
+
+        // Deregister however the driver ends, also when the task serving it
+        // is aborted.
+        let _registered = Deregister {
+            registry: self,
+            node,
+            generation,
+            tunnel,
+        };
+        driver.await;
+        info!("Tunnel from node {} disconnected", node);
+    }
+
+    /// Open a stream to `node`'s agent, if it has a tunnel up.
+    pub fn open(&self, node: &str) -> Option<TunnelStream> {
+        let tunnel = self.tunnels.lock().unwrap().get(node)?.tunnel.clone();
+        let stream = tunnel.open()?;
+        *self
+            .opened
+            .lock()
+            .unwrap()
+            .entry(node.to_string())
+            .or_default() += 1;
+        Some(stream)
+    }
+
+    pub fn is_connected(&self, node: &str) -> bool {
+        self.tunnels
+            .lock()
+            .unwrap()
+            .get(node)
+            .is_some_and(|r| !r.tunnel.is_closed())
+    }
+
+    /// Accounting for every node that has had a tunnel, sorted by name.
+    pub fn stats(&self) -> Vec<TunnelStats> {
+        let tunnels = self.tunnels.lock().unwrap();
+        let opened = self.opened.lock().unwrap();
+        let mut nodes: Vec<&String> = tunnels.keys().chain(opened.keys()).collect();
+        nodes.sort();
+        nodes.dedup();
+        nodes
+            .into_iter()
+            .map(|node| {
+                let tunnel = tunnels.get(node).map(|r| &r.tunnel);
+                TunnelStats {
+                    node: node.clone(),
+                    connected: tunnel.is_some_and(|t| !t.is_closed()),
+                    active_streams: tunnel.map_or(0, Tunnel::active_streams),
+                    opened_streams: opened.get(node).copied().unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Drops a node's registration when its tunnel ends, unless a newer tunnel
+/// replaced it, and ends the tunnel's streams.
+struct Deregister<'a> {
+    registry: &'a TunnelRegistry,
+    node: &'a str,
+    generation: u64,
+    tunnel: Tunnel,
+}
+
+impl Drop for Deregister<'_> {
+    fn drop(&mut self) {
+        self.tunnel.shut_down();
+        let mut tunnels = self.registry.tunnels.lock().unwrap();
+        if tunnels
+            .get(self.node)
+            .is_some_and(|r| r.generation == self.generation)
+        {
+            tunnels.remove(self.node);
+        }
+    }
+}
@@ -0,0 +1,473 @@
+//! Server-side apply: which fields an update replaces and which it keeps.
+//!
+//! `PUT /api/v1/namespaces/{ns}/{resource}/{name}` creates the object if it
+//! does not exist, otherwise replaces it in place. The submitted manifest
+//! carries the user's intent (spec, labels, data); everything the server or a
+//! controller assigned after creation is carried over from the stored copy.
+
+use crate::configmap::ConfigMap;
+use crate::daemonset::DaemonSet;
+use crate::deployment::Deployment;
+use crate::hpa::HorizontalPodAutoscaler;
+use crate::job::{CronJob, Job};
+use crate::limit_range::LimitRange;
+use crate::namespace::Namespace;
+use crate::pdb::PodDisruptionBudget;
+use crate::pod::Pod;
+use crate::priority_class::PriorityClass;
+use crate::replicaset::ReplicaSet;
+use crate::secret::Secret;
+use crate::service::Service;
+
+/// A resource that can be applied (created or updated) by name.
+pub trait Apply {
+    /// The object's name, as used in its registry key.
+    fn name_mut(&mut self) -> &mut String;
+
+    /// The namespace the object lives in; `None` for cluster-scoped kinds.
+    fn namespace_mut(&mut self) -> Option<&mut String>;
+
+    /// The resource version the object was read at (0 for a new object).
+    fn resource_version_mut(&mut self) -> &mut u64;
+
+    /// Copy server-assigned fields (id, timestamps, status, allocations) from
+    /// the currently stored object into `self`, which holds the applied manifest.
+    fn retain_server_fields(&mut self, existing: Self);
+}
+
+impl Apply for Pod {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.status = existing.status;
+        self.status_message = existing.status_message;
+        self.container_id = existing.container_id;
+        self.node_name = existing.node_name;
+        self.owner_ref = existing.owner_ref;
+        self.restart_count = existing.restart_count;
+        self.container_statuses = existing.container_statuses;
+        self.runtime_info = existing.runtime_info;
+        self.vpc_name = existing.vpc_name;
+        self.mirror = existing.mirror;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for Service {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.created_at = existing.created_at;
+        // Keep the allocated cluster IP and VPC unless the manifest pins them.
+        if self.cluster_ip.is_none() {
+            self.cluster_ip = existing.cluster_ip;
+        }
+        if self.vpc.is_none() {
+            self.vpc = existing.vpc;
+        }
+    }
+}
+
+impl Apply for Deployment {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        let spec_changed =
+            serde_json::to_value(&self.spec).ok() != serde_json::to_value(&existing.spec).ok();
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.status = existing.status;
+        self.observed_generation = existing.observed_generation;
+        self.generation = if spec_changed {
+            existing.generation + 1
+        } else {
+            existing.generation
+        };
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for ReplicaSet {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.status = existing.status;
+        self.owner_ref = existing.owner_ref;
+        self.template_hash = existing.template_hash;
+        self.revision = existing.revision;
+        self.change_cause = existing.change_cause;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for DaemonSet {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.status = existing.status;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for Job {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.status = existing.status;
+        self.owner_ref = existing.owner_ref;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for CronJob {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.status = existing.status;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for HorizontalPodAutoscaler {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.status = existing.status;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for ConfigMap {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for Secret {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.id = existing.id;
+        self.namespace = existing.namespace;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for Namespace {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        None
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.phase = existing.phase;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for LimitRange {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.namespace = existing.namespace;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for PodDisruptionBudget {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        Some(&mut self.namespace)
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.namespace = existing.namespace;
+        self.created_at = existing.created_at;
+    }
+}
+
+impl Apply for PriorityClass {
+    fn name_mut(&mut self) -> &mut String {
+        &mut self.name
+    }
+
+    fn namespace_mut(&mut self) -> Option<&mut String> {
+        None
+    }
+
+    fn resource_version_mut(&mut self) -> &mut u64 {
+        &mut self.resource_version
+    }
+
+    fn retain_server_fields(&mut self, existing: Self) {
+        self.created_at = existing.created_at;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deployment::DeploymentStatus;
+
+    const DEPLOYMENT: &str = r#"
+kind: Deployment
+id: "manifest-id"
+name: web
+namespace: default
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  replicas: 2
+  template:
+    containers:
+      - name: nginx
+        image: nginx:alpine
+"#;
+
+    /// Simulate two `apply` calls: the first manifest was created and then
+    /// reconciled by the controller, the second changes the replica count.
+    #[test]
+    fn test_apply_twice_updates_spec_and_keeps_status() {
+        let mut stored: Deployment = serde_yaml::from_str(DEPLOYMENT).unwrap();
+        stored.id = "server-id".to_string();
+        stored.created_at = chrono::Utc::now();
+        stored.generation = 1;
+        stored.observed_generation = 1;
+        stored.status = DeploymentStatus {
+            ready_replicas: 2,
+            available_replicas: 2,
+            updated_replicas: 2,
+            ..Default::default()
+        };
+
+        let mut applied: Deployment =
+            serde_yaml::from_str(&DEPLOYMENT.replace("replicas: 2", "replicas: 5")).unwrap();
+        applied.retain_server_fields(stored.clone());
+
+        assert_eq!(applied.spec.replicas, 5);
+        assert_eq!(applied.id, "server-id");
+        assert_eq!(applied.created_at, stored.created_at);
+        assert_eq!(applied.status.ready_replicas, 2);
+        assert_eq!(applied.status.available_replicas, 2);
+        assert_eq!(applied.status.updated_replicas, 2);
+        assert_eq!(applied.generation, 2);
+        assert_eq!(applied.observed_generation, 1);
+    }
+
+    #[test]
+    fn test_reapplying_same_spec_keeps_generation() {
+        let mut stored: Deployment = serde_yaml::from_str(DEPLOYMENT).unwrap();
+        stored.generation = 3;
+        let mut applied: Deployment = serde_yaml::from_str(DEPLOYMENT).unwrap();
+        applied.retain_server_fields(stored);
+        assert_eq!(applied.generation, 3);
+    }
+
+    #[test]
+    fn test_service_keeps_allocated_cluster_ip() {
+        let manifest = r#"
+id: ""
+name: web
+namespace: default
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  selector: { app: web }
+  ports: [{ name: http, port: 80, target_port: 8080 }]
+  service_type: ClusterIP
+"#;
+        let mut stored: Service = serde_yaml::from_str(manifest).unwrap();
+        stored.id = "svc-id".to_string();
+        stored.cluster_ip = Some("10.43.0.7".to_string());
+
+        let mut applied: Service = serde_yaml::from_str(manifest).unwrap();
+        applied.retain_server_fields(stored.clone());
+        assert_eq!(applied.id, "svc-id");
+        assert_eq!(applied.cluster_ip.as_deref(), Some("10.43.0.7"));
+
+        let mut pinned: Service = serde_yaml::from_str(manifest).unwrap();
+        pinned.cluster_ip = Some("10.43.0.9".to_string());
+        pinned.retain_server_fields(stored);
+        assert_eq!(pinned.cluster_ip.as_deref(), Some("10.43.0.9"));
+    }
+
+    #[test]
+    fn test_pod_keeps_binding_and_status() {
+        let manifest = r#"
+id: ""
+name: worker
+namespace: default
+status: Pending
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  containers:
+    - name: app
+      image: alpine:3
+"#;
+        let mut stored: Pod = serde_yaml::from_str(manifest).unwrap();
+        stored.id = "pod-id".to_string();
+        stored.node_name = Some("node-1".to_string());
+        stored.status = crate::pod::PodStatus::Running;
+        stored.restart_count = 2;
+        stored.container_statuses = vec![crate::pod::ContainerStatus {
+            name: "app".to_string(),
+            image: "alpine:3".to_string(),
+            container_id: Some("pod-id-app".to_string()),
+            state: crate::pod::ContainerState::Running {
+                started_at: stored.created_at,
+            },
+            restart_count: 2,
+            ready: true,
+        }];
+
+        let mut applied: Pod =
+            serde_yaml::from_str(&manifest.replace("alpine:3", "alpine:3.20")).unwrap();
+        applied.retain_server_fields(stored);
+        assert_eq!(applied.spec.containers[0].image, "alpine:3.20");
+        assert_eq!(applied.id, "pod-id");
+        assert_eq!(applied.node_name.as_deref(), Some("node-1"));
+        assert_eq!(applied.status, crate::pod::PodStatus::Running);
+        assert_eq!(applied.restart_count, 2);
+        assert_eq!(applied.container_statuses.len(), 1);
+    }
+}
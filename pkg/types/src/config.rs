@@ -1,4 +1,7 @@
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 
 /// Server configuration file (YAML).
 ///
@@ -7,6 +10,23 @@ use serde::{Deserialize, Serialize};
 /// port: 6443
 /// data-dir: /var/lib/k3rs/data
 /// token: my-secret-token
+/// admin-token: my-admin-token
+/// scheduler-strategy: least-allocated
+/// log-level: info,pkg_scheduler=debug
+/// # Keep state in etcd instead of SlateDB under data-dir:
+/// # state-store: etcd
+/// # etcd:
+/// #   endpoints: [http://10.0.0.5:2379, http://10.0.0.6:2379]
+/// #   username: k3rs
+/// #   password-file: /etc/k3rs/etcd-password
+/// # Or keep SlateDB in a bucket instead of under data-dir:
+/// # object-store:
+/// #   provider: s3
+/// #   bucket: k3rs-state
+/// #   prefix: prod
+/// #   region: eu-west-1
+/// #   access-key-id: AKIA...
+/// #   secret-access-key-file: /etc/k3rs/s3-secret
 /// ```
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ServerConfigFile {
@@ -14,10 +34,97 @@ pub struct ServerConfigFile {
     pub port: Option<u16>,
     #[serde(default, alias = "data-dir")]
     pub data_dir: Option<String>,
+    /// Key file Secret data is encrypted with at rest.
+    #[serde(default, alias = "secrets-key-file")]
+    pub secrets_key_file: Option<String>,
     #[serde(default)]
     pub token: Option<String>,
+    /// Bootstrap token with the admin role, for k3rsctl and the UI.
+    #[serde(default, alias = "admin-token")]
+    pub admin_token: Option<String>,
     #[serde(default, alias = "node-name")]
     pub node_name: Option<String>,
+    /// Node scoring strategy: round-robin (default), least-allocated, most-allocated.
+    #[serde(default, alias = "scheduler-strategy")]
+    pub scheduler_strategy: Option<String>,
+    /// Log filter, in `RUST_LOG` syntax (default: info).
+    #[serde(default, alias = "log-level")]
+    pub log_level: Option<String>,
+    /// NodePort allocation range as `start-end` (default: 30000-32767).
+    #[serde(default, alias = "service-node-port-range")]
+    pub service_node_port_range: Option<String>,
+    /// Re-registration of a node name from a new address: reject (default)
+    /// or takeover.
+    #[serde(default, alias = "node-address-conflict")]
+    pub node_address_conflict: Option<String>,
+    /// IPv4 range node pod CIDRs are assigned from (default: 10.42.0.0/16).
+    #[serde(default, alias = "cluster-cidr")]
+    pub cluster_cidr: Option<String>,
+    /// Prefix length of each node's pod CIDR (default: 24).
+    #[serde(default, alias = "node-cidr-mask-size")]
+    pub node_cidr_mask_size: Option<u8>,
+    /// Seconds without a heartbeat before a node is marked NotReady (default: 30).
+    #[serde(default, alias = "node-not-ready-grace-period")]
+    pub node_not_ready_grace_period: Option<u64>,
+    /// Seconds without a heartbeat before a node's pods are evicted (default: 300).
+    #[serde(default, alias = "pod-eviction-timeout")]
+    pub pod_eviction_timeout: Option<u64>,
+    /// Where cluster state is kept: slatedb (default, under data-dir) or etcd.
+    #[serde(default, alias = "state-store")]
+    pub state_store: Option<String>,
+    /// The etcd cluster, for `state-store: etcd`.
+    #[serde(default)]
+    pub etcd: Option<EtcdConfigFile>,
+    /// Bucket SlateDB keeps state in, instead of data-dir.
+    #[serde(default, alias = "object-store")]
+    pub object_store: Option<ObjectStoreConfigFile>,
+}
+
+/// `etcd` section of the server configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EtcdConfigFile {
+    /// Client URLs, e.g. `http://10.0.0.5:2379`.
+    #[serde(default)]
+    pub endpoints: Vec<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// File holding the password, instead of `password`.
+    #[serde(default, alias = "password-file")]
+    pub password_file: Option<String>,
+}
+
+/// `object-store` section of the server configuration file. Credentials
+/// left unset come from the provider's usual environment variables.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObjectStoreConfigFile {
+    /// s3, gcs, azure or minio.
+    #[serde(default)]
+    pub provider: String,
+    /// Bucket (container, on Azure).
+    #[serde(default)]
+    pub bucket: String,
+    /// Path inside the bucket, so several clusters can share one.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    /// Service URL, for MinIO and other S3-compatible stores.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// S3 access key id, or Azure storage account name.
+    #[serde(default, alias = "access-key-id")]
+    pub access_key_id: Option<String>,
+    /// S3 secret access key, or Azure account key.
+    #[serde(default, alias = "secret-access-key")]
+    pub secret_access_key: Option<String>,
+    /// File holding the secret access key, instead of `secret-access-key`.
+    #[serde(default, alias = "secret-access-key-file")]
+    pub secret_access_key_file: Option<String>,
+    /// GCS service account key file.
+    #[serde(default, alias = "credentials-file")]
+    pub credentials_file: Option<String>,
 }
 
 /// Agent configuration file (YAML).
@@ -29,7 +136,12 @@ pub struct ServerConfigFile {
 /// node-name: worker-1
 /// proxy-port: 6444
 /// service-proxy-port: 10256
+/// ingress-port: 8080
 /// dns-port: 5353
+/// image-gc-high-threshold: 85
+/// image-gc-low-threshold: 80
+/// log-level: info
+/// pod-sync-interval: 5
 /// ```
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AgentConfigFile {
@@ -43,6 +155,8 @@ pub struct AgentConfigFile {
     pub proxy_port: Option<u16>,
     #[serde(default, alias = "service-proxy-port")]
     pub service_proxy_port: Option<u16>,
+    #[serde(default, alias = "ingress-port")]
+    pub ingress_port: Option<u16>,
     #[serde(default, alias = "dns-port")]
     pub dns_port: Option<u16>,
     /// WireGuard listen port (default: 51820).
@@ -51,6 +165,74 @@ pub struct AgentConfigFile {
     /// Path to store WireGuard keys (default: /var/lib/k3rs/wireguard/).
     #[serde(default, alias = "wg-key-path")]
     pub wg_key_path: Option<String>,
+    /// Disk usage (percent) of the data dir filesystem that starts image GC (default: 85).
+    #[serde(default, alias = "image-gc-high-threshold")]
+    pub image_gc_high_threshold: Option<u8>,
+    /// Disk usage (percent) image GC frees down to (default: 80).
+    #[serde(default, alias = "image-gc-low-threshold")]
+    pub image_gc_low_threshold: Option<u8>,
+    /// Cap on the total size of cached images in bytes (default: none).
+    #[serde(default, alias = "image-gc-max-bytes")]
+    pub image_gc_max_bytes: Option<u64>,
+    /// Log filter, in `RUST_LOG` syntax (default: info).
+    #[serde(default, alias = "log-level")]
+    pub log_level: Option<String>,
+    /// Seconds between heartbeats while the server is reachable (default: 10).
+    #[serde(default, alias = "heartbeat-interval")]
+    pub heartbeat_interval: Option<u64>,
+    /// Seconds between pod syncs (default: 5).
+    #[serde(default, alias = "pod-sync-interval")]
+    pub pod_sync_interval: Option<u64>,
+    /// Seconds between route syncs (default: 10).
+    #[serde(default, alias = "route-sync-interval")]
+    pub route_sync_interval: Option<u64>,
+    /// Seconds a pod's containers get between SIGTERM and SIGKILL when the
+    /// pod sets no grace period (default: 30).
+    #[serde(default, alias = "termination-grace-period")]
+    pub termination_grace_period: Option<u64>,
+    /// Seconds a leftover container, VM or log directory no pod owns is kept
+    /// before orphan GC deletes it (default: 600).
+    #[serde(default, alias = "orphan-gc-grace-period")]
+    pub orphan_gc_grace_period: Option<u64>,
+    /// Disk usage (percent) of the data dir filesystem at which the node
+    /// reports DiskPressure (default: 90).
+    #[serde(default, alias = "disk-pressure-threshold")]
+    pub disk_pressure_threshold: Option<u8>,
+    /// Memory usage (percent) at which the node reports MemoryPressure (default: 95).
+    #[serde(default, alias = "memory-pressure-threshold")]
+    pub memory_pressure_threshold: Option<u8>,
+    /// Directory of Pod manifests the agent runs as static pods, without the
+    /// server (default: {CONFIG_DIR}/manifests).
+    #[serde(default, alias = "static-pod-dir")]
+    pub static_pod_dir: Option<String>,
+    /// Seconds between service proxy probes of each endpoint (default: 5).
+    #[serde(default, alias = "endpoint-probe-interval")]
+    pub endpoint_probe_interval: Option<u64>,
+    /// Failed probes in a row before an endpoint stops receiving traffic (default: 2).
+    #[serde(default, alias = "endpoint-probe-failure-threshold")]
+    pub endpoint_probe_failure_threshold: Option<u32>,
+    /// Good probes in a row before it receives traffic again (default: 1).
+    #[serde(default, alias = "endpoint-probe-success-threshold")]
+    pub endpoint_probe_success_threshold: Option<u32>,
+    /// Days of validity left at which the node certificate is renewed (default: 30).
+    #[serde(default, alias = "cert-renew-before-days")]
+    pub cert_renew_before_days: Option<i64>,
+    /// Pod networking: vpc (default) or bridge.
+    #[serde(default, alias = "pod-network")]
+    pub pod_network: Option<String>,
+    /// Serve the agent API without checking the server's token (default:
+    /// false). For local development only.
+    #[serde(default, alias = "insecure-agent-api")]
+    pub insecure_agent_api: Option<bool>,
+    /// Size in bytes at which a container's log file is rotated (default: 10 MiB).
+    #[serde(default, alias = "container-log-max-bytes")]
+    pub container_log_max_bytes: Option<u64>,
+    /// Rotated log files kept per container (default: 5).
+    #[serde(default, alias = "container-log-max-files")]
+    pub container_log_max_files: Option<usize>,
+    /// Gzip rotated container log files (default: false).
+    #[serde(default, alias = "container-log-compress")]
+    pub container_log_compress: Option<bool>,
 }
 
 /// VPC daemon configuration file (YAML).
@@ -89,6 +271,13 @@ pub struct VpcConfigFile {
     pub phys_iface: Option<String>,
 }
 
+/// Body of the `debug/loglevel` endpoints of the server and agent APIs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogLevel {
+    /// Log filter in `RUST_LOG` syntax, e.g. `pkg_container=debug,info`.
+    pub filter: String,
+}
+
 /// Load a YAML config file, returning the default if the file doesn't exist.
 pub fn load_config_file<T: serde::de::DeserializeOwned + Default>(path: &str) -> anyhow::Result<T> {
     let content = match std::fs::read_to_string(path) {
@@ -101,3 +290,187 @@ pub fn load_config_file<T: serde::de::DeserializeOwned + Default>(path: &str) ->
     let config: T = serde_yaml::from_str(&content)?;
     Ok(config)
 }
+
+/// Keys of a config file, by their YAML name.
+pub fn config_key(field: &str) -> String {
+    field.replace('_', "-")
+}
+
+/// A config file reloaded by [`ConfigFileWatcher::poll`].
+#[derive(Debug)]
+pub struct Reloaded<R> {
+    /// What the caller built from the new file.
+    pub value: R,
+    /// Changed keys that take effect now.
+    pub applied: Vec<String>,
+    /// Changed keys that only take effect after a restart.
+    pub restart_required: Vec<String>,
+}
+
+/// Watches a YAML config file for changes. A missing file reads as the
+/// default config, as with [`load_config_file`].
+pub struct ConfigFileWatcher<T> {
+    path: String,
+    /// Keys that can change without a restart.
+    live_keys: &'static [&'static str],
+    /// Hash of the contents last read, `None` while the file is missing.
+    seen: Option<u64>,
+    current: T,
+}
+
+impl<T: DeserializeOwned + Serialize + Default> ConfigFileWatcher<T> {
+    /// Load the file at `path`. `live_keys` lists the keys (YAML names) the
+    /// caller applies on reload.
+    pub fn open(path: &str, live_keys: &'static [&'static str]) -> anyhow::Result<Self> {
+        let content = read_if_exists(path)?;
+        let current = parse_or_default(content.as_deref())?;
+        Ok(Self {
+            path: path.to_string(),
+            live_keys,
+            seen: content.as_deref().map(content_hash),
+            current,
+        })
+    }
+
+    /// The config as last loaded.
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    /// Re-read the file. If its contents changed since the last poll, parse
+    /// it and pass it to `build`, and keep it only if both succeed; an
+    /// invalid file is reported once and the previous config stays.
+    pub fn poll<R>(
+        &mut self,
+        build: impl FnOnce(&T) -> anyhow::Result<R>,
+    ) -> anyhow::Result<Option<Reloaded<R>>> {
+        let content = read_if_exists(&self.path)?;
+        let seen = content.as_deref().map(content_hash);
+        if seen == self.seen {
+            return Ok(None);
+        }
+        self.seen = seen;
+        let config: T = parse_or_default(content.as_deref())?;
+        let value = build(&config)?;
+
+        let (applied, restart_required) = changed_keys(&self.current, &config)?
+            .into_iter()
+            .partition(|key| self.live_keys.contains(&key.as_str()));
+        self.current = config;
+        Ok(Some(Reloaded {
+            value,
+            applied,
+            restart_required,
+        }))
+    }
+}
+
+fn read_if_exists(path: &str) -> anyhow::Result<Option<String>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn parse_or_default<T: DeserializeOwned + Default>(content: Option<&str>) -> anyhow::Result<T> {
+    match content {
+        Some(content) => Ok(serde_yaml::from_str(content)?),
+        None => Ok(T::default()),
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Top-level keys (YAML names) whose values differ between two configs.
+fn changed_keys<T: Serialize>(old: &T, new: &T) -> anyhow::Result<Vec<String>> {
+    let old = serde_json::to_value(old)?;
+    let new = serde_json::to_value(new)?;
+    let (Some(old), Some(new)) = (old.as_object(), new.as_object()) else {
+        anyhow::bail!("config is not a mapping");
+    };
+    let mut keys: Vec<String> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|key| old.get(*key) != new.get(*key))
+        .map(|key| config_key(key))
+        .collect();
+    keys.sort();
+    keys.dedup();
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIVE: &[&str] = &["log-level", "scheduler-strategy"];
+
+    fn temp_config(name: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("k3rs-config-{}-{}.yaml", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn strategy(cfg: &ServerConfigFile) -> anyhow::Result<Option<String>> {
+        match cfg.scheduler_strategy.as_deref() {
+            Some("bogus") => anyhow::bail!("unknown scheduling strategy 'bogus'"),
+            other => Ok(other.map(str::to_string)),
+        }
+    }
+
+    #[test]
+    fn test_watcher_reports_applied_and_restart_keys() {
+        let path = temp_config("keys");
+        std::fs::write(&path, "port: 6443\nlog-level: info\n").unwrap();
+        let mut watcher = ConfigFileWatcher::<ServerConfigFile>::open(&path, LIVE).unwrap();
+        assert_eq!(watcher.current().port, Some(6443));
+        assert!(watcher.poll(strategy).unwrap().is_none());
+
+        std::fs::write(
+            &path,
+            "port: 7443\nlog-level: debug\nscheduler-strategy: most-allocated\n",
+        )
+        .unwrap();
+        let reloaded = watcher.poll(strategy).unwrap().unwrap();
+        assert_eq!(reloaded.value.as_deref(), Some("most-allocated"));
+        assert_eq!(reloaded.applied, vec!["log-level", "scheduler-strategy"]);
+        assert_eq!(reloaded.restart_required, vec!["port"]);
+        assert_eq!(watcher.current().log_level.as_deref(), Some("debug"));
+        assert!(watcher.poll(strategy).unwrap().is_none());
+
+        // A missing file is the default config.
+        std::fs::remove_file(&path).unwrap();
+        let reloaded = watcher.poll(strategy).unwrap().unwrap();
+        assert_eq!(reloaded.value, None);
+        assert!(watcher.current().port.is_none());
+    }
+
+    #[test]
+    fn test_watcher_keeps_previous_config_on_bad_file() {
+        let path = temp_config("bad");
+        std::fs::write(&path, "log-level: info\n").unwrap();
+        let mut watcher = ConfigFileWatcher::<ServerConfigFile>::open(&path, LIVE).unwrap();
+
+        std::fs::write(&path, "log-level: [unclosed\n").unwrap();
+        assert!(watcher.poll(strategy).is_err());
+        // Reported once, not on every poll.
+        assert!(watcher.poll(strategy).unwrap().is_none());
+        assert_eq!(watcher.current().log_level.as_deref(), Some("info"));
+
+        std::fs::write(&path, "log-level: warn\nscheduler-strategy: bogus\n").unwrap();
+        assert!(watcher.poll(strategy).is_err());
+        assert_eq!(watcher.current().log_level.as_deref(), Some("info"));
+
+        std::fs::write(&path, "log-level: warn\n").unwrap();
+        let reloaded = watcher.poll(strategy).unwrap().unwrap();
+        assert_eq!(reloaded.applied, vec!["log-level"]);
+        assert!(reloaded.restart_required.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
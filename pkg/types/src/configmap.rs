@@ -9,4 +9,8 @@ pub struct ConfigMap {
     pub namespace: String,
     pub data: HashMap<String, String>,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
 }
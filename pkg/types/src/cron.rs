@@ -0,0 +1,253 @@
+//! Cron schedule expressions, as used by `CronJob.spec.schedule`.
+//!
+//! Standard five-field syntax (`minute hour day-of-month month day-of-week`)
+//! with `*`, lists, ranges and steps (`1,15`, `9-17`, `*/5`, `10-50/10`),
+//! month and weekday names (`JAN`, `MON`), and the `@yearly`, `@monthly`,
+//! `@weekly`, `@daily`/`@midnight` and `@hourly` shorthands. Times are UTC.
+//! As in Vixie cron, when both day-of-month and day-of-week are restricted a
+//! day matching either one fires.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Day-of-month field was `*`.
+    any_day_of_month: bool,
+    /// Day-of-week field was `*`.
+    any_day_of_week: bool,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// How far ahead `next_after` looks before concluding a schedule never fires
+/// (e.g. `0 0 30 2 *`). Covers a full leap-year cycle.
+const SEARCH_DAYS: i64 = 366 * 4 + 1;
+
+impl Schedule {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = expr.trim();
+        let expanded = match expr {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other if other.starts_with('@') => {
+                return Err(format!("unknown schedule shorthand '{}'", other));
+            }
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(format!(
+                "schedule '{}' must have 5 fields (minute hour day-of-month month day-of-week), got {}",
+                expr,
+                fields.len()
+            ));
+        };
+        let mut days_of_week =
+            parse_field(dow, 0, 7, &WEEKDAY_NAMES, 0).map_err(|e| format!("day-of-week: {}", e))?;
+        // 7 is Sunday too.
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[], 0).map_err(|e| format!("minute: {}", e))?,
+            hours: parse_field(hour, 0, 23, &[], 0).map_err(|e| format!("hour: {}", e))?,
+            days_of_month: parse_field(dom, 1, 31, &[], 0)
+                .map_err(|e| format!("day-of-month: {}", e))?,
+            months: parse_field(month, 1, 12, &MONTH_NAMES, 1)
+                .map_err(|e| format!("month: {}", e))?,
+            days_of_week,
+            any_day_of_month: dom == "*",
+            any_day_of_week: dow == "*",
+        })
+    }
+
+    /// The first time strictly after `after` that matches, at minute
+    /// resolution. `None` if the schedule never fires.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        // Start at the next whole minute.
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date_naive();
+        let last = date + Duration::days(SEARCH_DAYS);
+        while date <= last {
+            if self.matches_day(date) {
+                let (from_hour, from_minute) = if date == start.date_naive() {
+                    (start.hour(), start.minute())
+                } else {
+                    (0, 0)
+                };
+                for hour in from_hour..24 {
+                    if self.hours & (1 << hour) == 0 {
+                        continue;
+                    }
+                    let first_minute = if hour == from_hour { from_minute } else { 0 };
+                    if let Some(minute) = (first_minute..60).find(|m| self.minutes & (1 << m) != 0)
+                    {
+                        return Some(Utc.from_utc_datetime(&date.and_hms_opt(hour, minute, 0)?));
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let dom = self.days_of_month & (1 << date.day()) != 0;
+        let dow = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (false, true) => dom,
+            (true, false) => dow,
+            (false, false) => dom || dow,
+        }
+    }
+}
+
+/// Parse one field into a bitmask of allowed values in `min..=max`. `names`
+/// are accepted case-insensitively in place of numbers, the first standing
+/// for `name_base`.
+fn parse_field(
+    field: &str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+    name_base: u32,
+) -> Result<u64, String> {
+    let value = |s: &str| -> Result<u32, String> {
+        let upper = s.to_ascii_uppercase();
+        let n = match names.iter().position(|n| *n == upper) {
+            Some(i) => i as u32 + name_base,
+            None => s
+                .parse::<u32>()
+                .map_err(|_| format!("invalid value '{}'", s))?,
+        };
+        if n < min || n > max {
+            return Err(format!("value {} out of range {}-{}", n, min, max));
+        }
+        Ok(n)
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("step must be at least 1".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            let (lo, hi) = (value(lo)?, value(hi)?);
+            if lo > hi {
+                return Err(format!("range {}-{} is backwards", lo, hi));
+            }
+            (lo, hi)
+        } else {
+            let n = value(range)?;
+            // `5/15` means from 5 to the end, every 15.
+            (n, if part.contains('/') { max } else { n })
+        };
+        for n in (lo..=hi).step_by(step as usize) {
+            mask |= 1 << n;
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn next(expr: &str, after: &str) -> DateTime<Utc> {
+        Schedule::parse(expr)
+            .unwrap()
+            .next_after(at(after))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(
+            next("*/5 * * * *", "2024-03-01T10:02:30Z"),
+            at("2024-03-01T10:05:00Z")
+        );
+        // Strictly after: a time on the schedule moves to the next one.
+        assert_eq!(
+            next("*/5 * * * *", "2024-03-01T10:05:00Z"),
+            at("2024-03-01T10:10:00Z")
+        );
+        assert_eq!(
+            next("30 9-17/4 * * *", "2024-03-01T17:45:00Z"),
+            at("2024-03-02T09:30:00Z")
+        );
+        assert_eq!(
+            next("@monthly", "2024-01-31T12:00:00Z"),
+            at("2024-02-01T00:00:00Z")
+        );
+        // 2024-03-01 is a Friday.
+        assert_eq!(
+            next("0 8 * * MON-FRI", "2024-03-01T09:00:00Z"),
+            at("2024-03-04T08:00:00Z")
+        );
+        assert_eq!(
+            next("0 0 * * 7", "2024-03-01T00:00:00Z"),
+            at("2024-03-03T00:00:00Z")
+        );
+        // Day-of-month and day-of-week: either matches.
+        assert_eq!(
+            next("0 0 15 * SUN", "2024-03-04T00:00:00Z"),
+            at("2024-03-10T00:00:00Z")
+        );
+        assert_eq!(
+            next("0 0 29 feb *", "2024-03-01T00:00:00Z"),
+            at("2028-02-29T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_never_firing_schedule() {
+        let s = Schedule::parse("0 0 30 2 *").unwrap();
+        assert_eq!(s.next_after(at("2024-01-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        for expr in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "* * * FOO *",
+            "@every-minute",
+        ] {
+            assert!(Schedule::parse(expr).is_err(), "{} should not parse", expr);
+        }
+        let err = Schedule::parse("0 25 * * *").unwrap_err();
+        assert_eq!(err, "hour: value 25 out of range 0-23");
+    }
+}
@@ -34,4 +34,8 @@ pub struct DaemonSet {
     #[serde(default)]
     pub status: DaemonSetStatus,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
 }
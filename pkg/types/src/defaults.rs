@@ -0,0 +1,209 @@
+//! Admission defaulting: fields a hand-written manifest may omit, filled in
+//! by the API server before validation so every stored object is fully
+//! specified. Defaults that do not depend on other fields (`replicas` 1,
+//! `protocol` TCP, `service_type` ClusterIP, `restart_policy` Always) are
+//! serde defaults on the types themselves; the ones here are derived from
+//! the rest of the object. A value the manifest sets explicitly, zero
+//! included, is never replaced.
+
+use crate::configmap::ConfigMap;
+use crate::daemonset::DaemonSet;
+use crate::deployment::Deployment;
+use crate::hpa::HorizontalPodAutoscaler;
+use crate::job::{CronJob, Job};
+use crate::limit_range::LimitRange;
+use crate::namespace::Namespace;
+use crate::network_policy::NetworkPolicy;
+use crate::pdb::PodDisruptionBudget;
+use crate::pod::{Pod, PodSpec};
+use crate::priority_class::PriorityClass;
+use crate::quota::ResourceQuota;
+use crate::replicaset::ReplicaSet;
+use crate::secret::Secret;
+use crate::service::Service;
+use crate::volume::PersistentVolumeClaim;
+
+/// A resource with fields defaulted at admission.
+pub trait Defaults {
+    /// Fill in every omitted field that has a default.
+    fn default_fields(&mut self) {}
+}
+
+/// Each container's image pull policy, from its image tag when unset.
+fn pod_spec(spec: &mut PodSpec) {
+    for c in spec
+        .init_containers
+        .iter_mut()
+        .chain(spec.containers.iter_mut())
+    {
+        c.image_pull_policy = Some(c.pull_policy());
+    }
+}
+
+impl Defaults for Pod {
+    fn default_fields(&mut self) {
+        pod_spec(&mut self.spec);
+    }
+}
+
+impl Defaults for Service {
+    fn default_fields(&mut self) {
+        for p in &mut self.spec.ports {
+            if p.target_port == 0 {
+                p.target_port = p.port;
+            }
+        }
+    }
+}
+
+impl Defaults for Deployment {
+    fn default_fields(&mut self) {
+        pod_spec(&mut self.spec.template);
+    }
+}
+
+impl Defaults for ReplicaSet {
+    fn default_fields(&mut self) {
+        pod_spec(&mut self.spec.template);
+    }
+}
+
+impl Defaults for DaemonSet {
+    fn default_fields(&mut self) {
+        pod_spec(&mut self.spec.template);
+    }
+}
+
+impl Defaults for Job {
+    fn default_fields(&mut self) {
+        pod_spec(&mut self.spec.template);
+    }
+}
+
+impl Defaults for CronJob {
+    fn default_fields(&mut self) {
+        pod_spec(&mut self.spec.job_template.template);
+    }
+}
+
+impl Defaults for Namespace {}
+impl Defaults for ConfigMap {}
+impl Defaults for Secret {}
+impl Defaults for HorizontalPodAutoscaler {}
+impl Defaults for LimitRange {}
+impl Defaults for PodDisruptionBudget {}
+impl Defaults for ResourceQuota {}
+impl Defaults for PriorityClass {}
+impl Defaults for NetworkPolicy {}
+impl Defaults for PersistentVolumeClaim {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pod::{ImagePullPolicy, RestartPolicy};
+    use crate::service::{Protocol, ServiceType};
+
+    #[test]
+    fn test_minimal_pod_defaults() {
+        let mut pod: Pod = serde_yaml::from_str(
+            r#"
+id: ""
+name: web
+namespace: ""
+status: Pending
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  containers:
+    - name: latest
+      image: nginx:latest
+    - name: untagged
+      image: registry.local:5000/nginx
+    - name: pinned
+      image: registry.local:5000/nginx:1.27
+    - name: digest
+      image: nginx@sha256:0123
+    - name: explicit
+      image: nginx
+      image_pull_policy: Never
+"#,
+        )
+        .unwrap();
+        pod.default_fields();
+        assert_eq!(pod.spec.restart_policy, RestartPolicy::Always);
+        let policies: Vec<_> = pod
+            .spec
+            .containers
+            .iter()
+            .map(|c| c.image_pull_policy)
+            .collect();
+        assert_eq!(
+            policies,
+            [
+                Some(ImagePullPolicy::Always),
+                Some(ImagePullPolicy::Always),
+                Some(ImagePullPolicy::IfNotPresent),
+                Some(ImagePullPolicy::IfNotPresent),
+                Some(ImagePullPolicy::Never),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_minimal_service_defaults() {
+        let mut svc: Service = serde_yaml::from_str(
+            r#"
+id: ""
+name: web
+namespace: ""
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  selector: { app: web }
+  ports:
+    - port: 80
+    - port: 443
+      target_port: 8443
+"#,
+        )
+        .unwrap();
+        svc.default_fields();
+        assert!(matches!(svc.spec.service_type, ServiceType::ClusterIP));
+        let ports: Vec<_> = svc
+            .spec
+            .ports
+            .iter()
+            .map(|p| (p.protocol, p.port, p.target_port))
+            .collect();
+        assert_eq!(ports, [(Protocol::TCP, 80, 80), (Protocol::TCP, 443, 8443)]);
+    }
+
+    #[test]
+    fn test_deployment_replicas_default_keeps_explicit_zero() {
+        let manifest = |replicas: &str| {
+            format!(
+                r#"
+id: ""
+name: web
+namespace: ""
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  {}
+  selector: {{ app: web }}
+  template:
+    containers: [{{ name: web, image: "nginx:1" }}]
+"#,
+                replicas
+            )
+        };
+        let mut deploy: Deployment = serde_yaml::from_str(&manifest("")).unwrap();
+        deploy.default_fields();
+        assert_eq!(deploy.spec.replicas, 1);
+        assert_eq!(
+            deploy.spec.template.containers[0].image_pull_policy,
+            Some(ImagePullPolicy::IfNotPresent)
+        );
+
+        let mut deploy: Deployment = serde_yaml::from_str(&manifest("replicas: 0")).unwrap();
+        deploy.default_fields();
+        assert_eq!(deploy.spec.replicas, 0);
+    }
+}
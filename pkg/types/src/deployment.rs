@@ -6,14 +6,19 @@ use crate::pod::PodSpec;
 
 // --- Deployment strategy ---
 
+/// How pods of an old template are replaced, as `{type: ..., ...}`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
 pub enum DeploymentStrategy {
+    /// Replace pods gradually: at most `max_surge` pods above the desired
+    /// count, and at most `max_unavailable` below it in available pods.
     RollingUpdate {
         #[serde(default = "default_max_surge")]
         max_surge: u32,
         #[serde(default = "default_max_unavailable")]
         max_unavailable: u32,
     },
+    /// Remove every old pod before creating new ones.
     Recreate,
     /// Blue/Green: deploy new version alongside old, then switch traffic atomically
     BlueGreen,
@@ -34,6 +39,12 @@ fn default_max_unavailable() -> u32 {
 fn default_canary_weight() -> u32 {
     10
 }
+fn default_revision_history_limit() -> u32 {
+    10
+}
+fn default_replicas() -> u32 {
+    1
+}
 
 impl Default for DeploymentStrategy {
     fn default() -> Self {
@@ -46,17 +57,98 @@ impl Default for DeploymentStrategy {
 
 // --- Deployment status ---
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeploymentConditionType {
+    /// A rollout is under way, or finished with `reason` NewReplicaSetAvailable.
+    Progressing,
+    /// At least `replicas - max_unavailable` pods are available.
+    Available,
+}
+
+impl std::fmt::Display for DeploymentConditionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeploymentConditionType::Progressing => write!(f, "Progressing"),
+            DeploymentConditionType::Available => write!(f, "Available"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeploymentCondition {
+    #[serde(rename = "type")]
+    pub condition_type: DeploymentConditionType,
+    pub status: bool,
+    pub reason: String,
+    #[serde(default)]
+    pub message: String,
+    /// When `status` last changed.
+    pub last_transition_time: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DeploymentStatus {
+    /// Pods of all the deployment's ReplicaSets.
+    #[serde(default)]
+    pub replicas: u32,
     pub ready_replicas: u32,
     pub available_replicas: u32,
+    /// Pods running the current template.
     pub updated_replicas: u32,
+    #[serde(default)]
+    pub conditions: Vec<DeploymentCondition>,
+}
+
+impl DeploymentStatus {
+    pub fn condition(
+        &self,
+        condition_type: DeploymentConditionType,
+    ) -> Option<&DeploymentCondition> {
+        self.conditions
+            .iter()
+            .find(|c| c.condition_type == condition_type)
+    }
+
+    /// Set a condition, keeping its transition time unless `status` changed.
+    pub fn set_condition(
+        &mut self,
+        condition_type: DeploymentConditionType,
+        status: bool,
+        reason: &str,
+        message: String,
+    ) {
+        let now = Utc::now();
+        match self
+            .conditions
+            .iter_mut()
+            .find(|c| c.condition_type == condition_type)
+        {
+            Some(c) => {
+                if c.status != status {
+                    c.last_transition_time = now;
+                }
+                c.status = status;
+                c.reason = reason.to_string();
+                c.message = message;
+            }
+            None => self.conditions.push(DeploymentCondition {
+                condition_type,
+                status,
+                reason: reason.to_string(),
+                message,
+                last_transition_time: now,
+            }),
+        }
+    }
 }
 
 // --- Deployment spec ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentSpec {
+    /// Desired pod count; 1 when omitted. An explicit 0 is kept and scales
+    /// the deployment down to nothing.
+    #[serde(default = "default_replicas")]
     pub replicas: u32,
     pub template: PodSpec,
     #[serde(default)]
@@ -64,6 +156,9 @@ pub struct DeploymentSpec {
     /// Label selector for matching pods
     #[serde(default)]
     pub selector: HashMap<String, String>,
+    /// Scaled-down ReplicaSets kept for rollback
+    #[serde(default = "default_revision_history_limit")]
+    pub revision_history_limit: u32,
 }
 
 // --- Deployment ---
@@ -73,6 +168,8 @@ pub struct Deployment {
     pub id: String,
     pub name: String,
     pub namespace: String,
+    #[serde(default)]
+    pub labels: HashMap<String, String>,
     pub spec: DeploymentSpec,
     #[serde(default)]
     pub status: DeploymentStatus,
@@ -82,5 +179,37 @@ pub struct Deployment {
     /// Last generation observed by the controller
     #[serde(default)]
     pub observed_generation: u64,
+    /// Why the template last changed; recorded on the ReplicaSet it creates
+    #[serde(default)]
+    pub change_cause: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
+}
+
+// --- Rollout history ---
+
+/// One entry of `GET .../deployments/{name}/revisions`: a ReplicaSet the
+/// deployment can be rolled back to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentRevision {
+    pub revision: u64,
+    pub replicaset: String,
+    #[serde(default)]
+    pub change_cause: Option<String>,
+    /// Container images of the revision's template
+    pub images: Vec<String>,
+    /// Whether this is the template the deployment currently runs
+    pub current: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of `POST .../deployments/{name}/rollback`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeploymentRollback {
+    /// Revision to restore; `None` or 0 means the one before the current.
+    #[serde(default)]
+    pub to_revision: Option<u64>,
 }
@@ -9,6 +9,10 @@ pub struct EndpointAddress {
     pub node_name: Option<String>,
     #[serde(default)]
     pub pod_id: Option<String>,
+    /// DNS label of the pod (its name), served as
+    /// `<hostname>.<svc>.<ns>.svc.cluster.local` for headless Services.
+    #[serde(default)]
+    pub hostname: Option<String>,
 }
 
 /// A port exposed by a backend pod.
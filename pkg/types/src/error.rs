@@ -0,0 +1,279 @@
+//! The error body the API server answers failed requests with.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Machine-readable cause of an [`ApiError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorReason {
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    /// A create named an object that is already stored.
+    AlreadyExists,
+    Conflict,
+    /// A watch asked to resume from events that are no longer buffered; the
+    /// client has to relist.
+    Gone,
+    /// The object failed validation; `details` lists every failing field.
+    Invalid,
+    /// Admitting the object would exceed a ResourceQuota.
+    QuotaExceeded,
+    /// The request body is in a format the endpoint does not accept.
+    UnsupportedMediaType,
+    TooManyRequests,
+    /// A node the request had to be relayed to could not be reached or
+    /// failed the request.
+    BadGateway,
+    ServiceUnavailable,
+    /// The endpoint exists but this server does not serve it.
+    NotImplemented,
+    InternalError,
+}
+
+impl ErrorReason {
+    /// The HTTP status code the reason is served with.
+    pub fn status(self) -> u16 {
+        match self {
+            Self::BadRequest => 400,
+            Self::Unauthorized => 401,
+            Self::Forbidden | Self::QuotaExceeded => 403,
+            Self::NotFound => 404,
+            Self::AlreadyExists | Self::Conflict => 409,
+            Self::Gone => 410,
+            Self::UnsupportedMediaType => 415,
+            Self::Invalid => 422,
+            Self::TooManyRequests => 429,
+            Self::BadGateway => 502,
+            Self::ServiceUnavailable => 503,
+            Self::NotImplemented => 501,
+            Self::InternalError => 500,
+        }
+    }
+
+    /// The generic reason for an HTTP status code.
+    pub fn from_status(code: u16) -> Self {
+        match code {
+            401 => Self::Unauthorized,
+            403 => Self::Forbidden,
+            404 => Self::NotFound,
+            409 => Self::Conflict,
+            410 => Self::Gone,
+            415 => Self::UnsupportedMediaType,
+            422 => Self::Invalid,
+            429 => Self::TooManyRequests,
+            502 => Self::BadGateway,
+            501 => Self::NotImplemented,
+            503 => Self::ServiceUnavailable,
+            400..=499 => Self::BadRequest,
+            _ => Self::InternalError,
+        }
+    }
+}
+
+impl fmt::Display for ErrorReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// One failing field of an invalid object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    /// Path of the field, e.g. `spec.containers[0].image`.
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Body of every error response:
+/// `{"code": 404, "reason": "NotFound", "message": "...", "details": []}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiError {
+    /// HTTP status code.
+    pub code: u16,
+    pub reason: ErrorReason,
+    pub message: String,
+    #[serde(default)]
+    pub details: Vec<FieldError>,
+    /// The stored object, when an update was rejected because it was not made
+    /// against the current resource version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn new(reason: ErrorReason, message: impl Into<String>) -> Self {
+        Self {
+            code: reason.status(),
+            reason,
+            message: message.into(),
+            details: Vec::new(),
+            current: None,
+        }
+    }
+
+    /// `<kind> '<name>' not found [in namespace '<ns>']`.
+    pub fn not_found(kind: &str, name: &str, ns: Option<&str>) -> Self {
+        Self::new(
+            ErrorReason::NotFound,
+            format!("{} '{}' not found{}", kind, name, in_namespace(ns)),
+        )
+    }
+
+    /// `<kind> '<name>' already exists [in namespace '<ns>']`.
+    pub fn already_exists(kind: &str, name: &str, ns: Option<&str>) -> Self {
+        Self::new(
+            ErrorReason::AlreadyExists,
+            format!("{} '{}' already exists{}", kind, name, in_namespace(ns)),
+        )
+    }
+
+    /// `<kind> '<name>' is invalid`, with one detail per failing field.
+    pub fn invalid(kind: &str, name: &str, details: Vec<FieldError>) -> Self {
+        let mut err = Self::new(
+            ErrorReason::Invalid,
+            format!("{} '{}' is invalid", kind, name),
+        );
+        err.details = details;
+        err
+    }
+
+    /// An update sent with resource version `sent` while the object is now at
+    /// another one (`sent` 0: the caller did not say which version it read).
+    /// Carries the stored object so the caller can redo its change and retry.
+    pub fn version_conflict(
+        kind: &str,
+        name: &str,
+        sent: u64,
+        current: Option<serde_json::Value>,
+    ) -> Self {
+        let message = if sent == 0 {
+            format!(
+                "{} '{}' already exists; send the resource_version you read to update it",
+                kind, name
+            )
+        } else {
+            format!(
+                "{} '{}' has been modified since resource_version {}; reapply your changes to the current object and retry",
+                kind, name, sent
+            )
+        };
+        let mut err = Self::new(ErrorReason::Conflict, message);
+        err.current = current;
+        err
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorReason::InternalError, message)
+    }
+
+    /// Serve the error with `code` instead of its reason's default status.
+    pub fn with_code(mut self, code: u16) -> Self {
+        self.code = code;
+        self
+    }
+
+    /// Decode an error response. Bodies that are not an `ApiError` (e.g.
+    /// from an older server or a proxy) become one with the generic reason
+    /// for `status` and the body text as the message.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        if let Ok(err) = serde_json::from_str::<ApiError>(body) {
+            return err;
+        }
+        let reason = ErrorReason::from_status(status);
+        let message = match body.trim() {
+            "" => format!("request failed with status {}", status),
+            text => text.to_string(),
+        };
+        Self::new(reason, message).with_code(status)
+    }
+}
+
+fn in_namespace(ns: Option<&str>) -> String {
+    ns.map(|ns| format!(" in namespace '{}'", ns))
+        .unwrap_or_default()
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.reason, self.message)?;
+        for detail in &self.details {
+            write!(f, "\n  {}: {}", detail.field, detail.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_shape() {
+        let err = ApiError::not_found("pod", "web", Some("prod"));
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "code": 404,
+                "reason": "NotFound",
+                "message": "pod 'web' not found in namespace 'prod'",
+                "details": [],
+            })
+        );
+
+        let err = ApiError::invalid(
+            "pod",
+            "web",
+            vec![
+                FieldError::new("spec.containers[0].image", "must not be empty"),
+                FieldError::new("spec.containers[1].name", "duplicate name 'app'"),
+            ],
+        );
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "code": 422,
+                "reason": "Invalid",
+                "message": "pod 'web' is invalid",
+                "details": [
+                    { "field": "spec.containers[0].image", "message": "must not be empty" },
+                    { "field": "spec.containers[1].name", "message": "duplicate name 'app'" },
+                ],
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "Invalid: pod 'web' is invalid\n  spec.containers[0].image: must not be empty\n  spec.containers[1].name: duplicate name 'app'"
+        );
+    }
+
+    #[test]
+    fn test_from_response() {
+        let body =
+            serde_json::to_string(&ApiError::already_exists("namespace", "prod", None)).unwrap();
+        let err = ApiError::from_response(409, &body);
+        assert_eq!(err.reason, ErrorReason::AlreadyExists);
+        assert_eq!(err.message, "namespace 'prod' already exists");
+
+        // Plain-text bodies keep their text under the status's reason.
+        let err = ApiError::from_response(404, "No such route\n");
+        assert_eq!(err.reason, ErrorReason::NotFound);
+        assert_eq!(err.message, "No such route");
+        let err = ApiError::from_response(502, "");
+        assert_eq!(err.code, 502);
+        assert_eq!(err.reason, ErrorReason::BadGateway);
+        assert_eq!(err.message, "request failed with status 502");
+    }
+}
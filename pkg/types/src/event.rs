@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Namespace that events about cluster-scoped objects (nodes) are filed under.
+pub const CLUSTER_EVENT_NAMESPACE: &str = "default";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EventType {
+    Normal,
+    Warning,
+}
+
+impl std::fmt::Display for EventType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventType::Normal => write!(f, "Normal"),
+            EventType::Warning => write!(f, "Warning"),
+        }
+    }
+}
+
+/// Something that happened to an object (scheduled, failed, cordoned, ...).
+///
+/// Events are ephemeral: they are emitted into the server's in-memory
+/// `EventLog` under [`Event::key`] and are not written to the state store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    /// Lowercase kind of the involved object, e.g. `pod` or `node`.
+    pub kind: String,
+    pub name: String,
+    pub namespace: String,
+    pub event_type: EventType,
+    /// Short CamelCase reason, e.g. `Scheduled` or `FailedScheduling`.
+    pub reason: String,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Event {
+    pub fn new(
+        event_type: EventType,
+        kind: &str,
+        namespace: &str,
+        name: &str,
+        reason: &str,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            namespace: namespace.to_string(),
+            event_type,
+            reason: reason.to_string(),
+            message: message.into(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    pub fn normal(
+        kind: &str,
+        namespace: &str,
+        name: &str,
+        reason: &str,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::new(EventType::Normal, kind, namespace, name, reason, message)
+    }
+
+    pub fn warning(
+        kind: &str,
+        namespace: &str,
+        name: &str,
+        reason: &str,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::new(EventType::Warning, kind, namespace, name, reason, message)
+    }
+
+    /// EventLog key: `/events/{namespace}/{kind}/{name}`.
+    pub fn key(&self) -> String {
+        format!("/events/{}/{}/{}", self.namespace, self.kind, self.name)
+    }
+}
@@ -0,0 +1,129 @@
+//! Frames of the pod exec WebSocket.
+//!
+//! Binary frames carry raw bytes: the client's stdin one way, the process's
+//! output the other. Text frames from the server are status messages. Text
+//! frames from the client are [`ExecControl`] messages when they parse as
+//! one; any other text is stdin, which is how older clients sent keystrokes.
+//! The server's close frame carries the process's [`ExecExit`] status.
+
+use serde::{Deserialize, Serialize};
+
+/// Out-of-band message from an exec client, sent as a JSON text frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecControl {
+    /// The client's terminal changed size; applied to the session's PTY.
+    Resize { cols: u16, rows: u16 },
+    /// The client's input ended; the process's stdin is closed so it sees
+    /// EOF. The session stays open for the rest of its output.
+    Eof,
+}
+
+impl ExecControl {
+    /// The text frame carrying this message.
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("exec control messages always serialize")
+    }
+}
+
+/// How the exec'd process ended, sent as the reason of the server's close
+/// frame: `exit:<code>`. A session whose status is unknown (the client went
+/// away, the process could not be started) closes without one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecExit {
+    /// Exit code, `128 + signal` for a process killed by a signal.
+    pub code: i32,
+}
+
+impl ExecExit {
+    /// The close frame reason carrying this status.
+    pub fn encode(&self) -> String {
+        format!("exit:{}", self.code)
+    }
+
+    pub fn decode(reason: &str) -> Option<Self> {
+        let code = reason.strip_prefix("exit:")?.parse().ok()?;
+        Some(ExecExit { code })
+    }
+}
+
+/// A decoded frame from an exec client.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ExecInput<'a> {
+    Stdin(&'a [u8]),
+    Control(ExecControl),
+}
+
+impl<'a> ExecInput<'a> {
+    pub fn from_binary(bytes: &'a [u8]) -> Self {
+        ExecInput::Stdin(bytes)
+    }
+
+    pub fn from_text(text: &'a str) -> Self {
+        if text.starts_with('{')
+            && let Ok(control) = serde_json::from_str(text)
+        {
+            return ExecInput::Control(control);
+        }
+        ExecInput::Stdin(text.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_round_trip() {
+        let resize = ExecControl::Resize {
+            cols: 132,
+            rows: 43,
+        };
+        let frame = resize.encode();
+        assert_eq!(frame, r#"{"type":"resize","cols":132,"rows":43}"#);
+        assert_eq!(ExecInput::from_text(&frame), ExecInput::Control(resize));
+    }
+
+    #[test]
+    fn test_eof_round_trip() {
+        let frame = ExecControl::Eof.encode();
+        assert_eq!(frame, r#"{"type":"eof"}"#);
+        assert_eq!(
+            ExecInput::from_text(&frame),
+            ExecInput::Control(ExecControl::Eof)
+        );
+    }
+
+    #[test]
+    fn test_exit_round_trip() {
+        let exit = ExecExit { code: 137 };
+        assert_eq!(exit.encode(), "exit:137");
+        assert_eq!(ExecExit::decode(&exit.encode()), Some(exit));
+        assert_eq!(ExecExit::decode(""), None);
+        assert_eq!(ExecExit::decode("exit:"), None);
+        assert_eq!(ExecExit::decode("idle timeout"), None);
+    }
+
+    #[test]
+    fn test_stdin_frames() {
+        assert_eq!(
+            ExecInput::from_binary(b"\x03{\"type\":\"resize\"}"),
+            ExecInput::Stdin(b"\x03{\"type\":\"resize\"}")
+        );
+        // Keystrokes from clients that send text frames.
+        assert_eq!(
+            ExecInput::from_text("ls -l\r"),
+            ExecInput::Stdin(b"ls -l\r")
+        );
+        // Braces that aren't a control message are still input.
+        assert_eq!(ExecInput::from_text("{"), ExecInput::Stdin(b"{"));
+        assert_eq!(
+            ExecInput::from_text(r#"{"type":"resize","cols":80}"#),
+            ExecInput::Stdin(br#"{"type":"resize","cols":80}"#)
+        );
+        assert_eq!(
+            ExecInput::from_text(r#"{"type":"detach"}"#),
+            ExecInput::Stdin(br#"{"type":"detach"}"#)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Container output k3rs-init forwards from a microVM over vsock.
+//!
+//! k3rs-init serves the entrypoint's stdout/stderr on `VSOCK_LOG_PORT` as
+//! frames of `tag (1) | unix nanos (8, BE) | length (4, BE) | line`. The VM
+//! backends decode them here and append each line to the container's log
+//! file as `<RFC3339 nanos> <line>` — the format the OCI backend's log relay
+//! writes, so the agent reads all of them alike.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use pkg_constants::vm::{LOG_FRAME_HEADER_LEN, LOG_FRAME_MAX_LINE};
+
+/// One forwarded line.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LogFrame {
+    /// `LOG_FRAME_STDOUT` or `LOG_FRAME_STDERR`.
+    pub tag: u8,
+    pub unix_nanos: u64,
+    pub line: Vec<u8>,
+}
+
+/// Splits the byte stream from the guest into frames.
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    pub fn feed(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// The next complete frame, if one has arrived. A length over
+    /// `LOG_FRAME_MAX_LINE` means the stream is out of step.
+    pub fn next_frame(&mut self) -> Result<Option<LogFrame>, String> {
+        if self.buf.len() < LOG_FRAME_HEADER_LEN {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes(self.buf[9..13].try_into().unwrap()) as usize;
+        if len > LOG_FRAME_MAX_LINE {
+            return Err(format!("log frame of {} bytes exceeds the limit", len));
+        }
+        if self.buf.len() < LOG_FRAME_HEADER_LEN + len {
+            return Ok(None);
+        }
+        let frame = LogFrame {
+            tag: self.buf[0],
+            unix_nanos: u64::from_be_bytes(self.buf[1..9].try_into().unwrap()),
+            line: self.buf[LOG_FRAME_HEADER_LEN..LOG_FRAME_HEADER_LEN + len].to_vec(),
+        };
+        self.buf.drain(..LOG_FRAME_HEADER_LEN + len);
+        Ok(Some(frame))
+    }
+}
+
+/// Render a frame as a log file line. The guest's timestamp is kept unless
+/// it predates `booted_at` — a guest clock that was never set — in which
+/// case the time it arrived is used.
+pub fn format_frame(frame: &LogFrame, booted_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let ts = DateTime::from_timestamp_nanos(frame.unix_nanos as i64);
+    let ts = if ts < booted_at { now } else { ts };
+    format!(
+        "{} {}\n",
+        ts.to_rfc3339_opts(SecondsFormat::Nanos, true),
+        String::from_utf8_lossy(&frame.line)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pkg_constants::vm::{LOG_FRAME_STDERR, LOG_FRAME_STDOUT};
+
+    /// As k3rs-init encodes `oops` on stderr at 1_700_000_000_123_456_789.
+    const FRAME: [u8; 17] = [
+        2, 0x17, 0x97, 0x9c, 0xfe, 0x3d, 0x85, 0xcd, 0x15, 0, 0, 0, 4, b'o', b'o', b'p', b's',
+    ];
+
+    #[test]
+    fn test_decode_split_frames() {
+        let mut decoder = FrameDecoder::default();
+        let mut stream = FRAME.to_vec();
+        stream.extend_from_slice(&[LOG_FRAME_STDOUT, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0]);
+        // Arrives in pieces that cut through headers and lines.
+        decoder.feed(&stream[..5]);
+        assert_eq!(decoder.next_frame(), Ok(None));
+        decoder.feed(&stream[5..15]);
+        assert_eq!(decoder.next_frame(), Ok(None));
+        decoder.feed(&stream[15..]);
+        assert_eq!(
+            decoder.next_frame(),
+            Ok(Some(LogFrame {
+                tag: LOG_FRAME_STDERR,
+                unix_nanos: 1_700_000_000_123_456_789,
+                line: b"oops".to_vec(),
+            }))
+        );
+        let empty = decoder.next_frame().unwrap().unwrap();
+        assert_eq!((empty.tag, empty.unix_nanos), (LOG_FRAME_STDOUT, 1));
+        assert!(empty.line.is_empty());
+        assert_eq!(decoder.next_frame(), Ok(None));
+    }
+
+    #[test]
+    fn test_decode_rejects_oversized_frame() {
+        let mut decoder = FrameDecoder::default();
+        decoder.feed(&[1, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+        assert!(decoder.next_frame().is_err());
+    }
+
+    #[test]
+    fn test_format_frame_like_oci_logs() {
+        let frame = LogFrame {
+            tag: LOG_FRAME_STDERR,
+            unix_nanos: 1_700_000_000_123_456_789,
+            line: b"oops".to_vec(),
+        };
+        let booted_at = DateTime::from_timestamp(1_600_000_000, 0).unwrap();
+        let now = DateTime::from_timestamp(1_800_000_000, 0).unwrap();
+        assert_eq!(
+            format_frame(&frame, booted_at, now),
+            "2023-11-14T22:13:20.123456789Z oops\n"
+        );
+
+        // An unset guest clock reads as 1970: stamped on arrival instead.
+        let frame = LogFrame {
+            unix_nanos: 5_000_000_000,
+            ..frame
+        };
+        assert_eq!(
+            format_frame(&frame, booted_at, now),
+            "2027-01-15T08:00:00.000000000Z oops\n"
+        );
+    }
+}
@@ -49,4 +49,8 @@ pub struct HorizontalPodAutoscaler {
     #[serde(default)]
     pub status: HPAStatus,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
 }
@@ -33,6 +33,11 @@ pub struct JobStatus {
     pub start_time: Option<DateTime<Utc>>,
     #[serde(default)]
     pub completion_time: Option<DateTime<Utc>>,
+    /// Why the Job failed (`BackoffLimitExceeded`, `DeadlineExceeded`).
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
 }
 
 // --- Job spec ---
@@ -46,9 +51,12 @@ pub struct JobSpec {
     /// Max pods running in parallel
     #[serde(default = "default_parallelism")]
     pub parallelism: u32,
-    /// Max failures before marking job as Failed
+    /// Failed pods retried before marking job as Failed
     #[serde(default = "default_backoff_limit")]
     pub backoff_limit: u32,
+    /// Seconds after start before the Job is failed and its pods killed
+    #[serde(default)]
+    pub active_deadline_seconds: Option<u64>,
 }
 
 fn default_completions() -> u32 {
@@ -75,27 +83,71 @@ pub struct Job {
     #[serde(default)]
     pub owner_ref: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
 }
 
 // --- CronJob ---
 
+/// What a CronJob does when a run is due while an earlier Job is still active.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum ConcurrencyPolicy {
+    /// Start the new Job alongside the running ones.
+    #[default]
+    Allow,
+    /// Skip the run.
+    Forbid,
+    /// Delete the running Jobs, then start the new one.
+    Replace,
+}
+
+/// A Job created by a CronJob.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JobReference {
+    pub id: String,
+    pub name: String,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CronJobStatus {
+    /// Scheduled time of the last Job started.
     #[serde(default)]
     pub last_schedule_time: Option<DateTime<Utc>>,
+    /// Jobs still running.
     #[serde(default)]
-    pub active_jobs: Vec<String>,
+    pub active: Vec<JobReference>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CronJobSpec {
-    /// Cron schedule string (e.g. "*/5 * * * *")
+    /// Cron schedule string (e.g. "*/5 * * * *"), see [`crate::cron`]
     pub schedule: String,
     /// Job template to create
     pub job_template: JobSpec,
     /// If true, skip execution
     #[serde(default)]
     pub suspend: bool,
+    #[serde(default)]
+    pub concurrency_policy: ConcurrencyPolicy,
+    /// A run that could not start within this many seconds of its scheduled
+    /// time is skipped. Without it, only the most recent missed run starts.
+    #[serde(default)]
+    pub starting_deadline_seconds: Option<u64>,
+    /// Completed Jobs to keep
+    #[serde(default = "default_successful_jobs_history_limit")]
+    pub successful_jobs_history_limit: u32,
+    /// Failed Jobs to keep
+    #[serde(default = "default_failed_jobs_history_limit")]
+    pub failed_jobs_history_limit: u32,
+}
+
+fn default_successful_jobs_history_limit() -> u32 {
+    3
+}
+fn default_failed_jobs_history_limit() -> u32 {
+    1
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -107,4 +159,8 @@ pub struct CronJob {
     #[serde(default)]
     pub status: CronJobStatus,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
 }
@@ -24,3 +24,18 @@ impl Lease {
         Utc::now() > expiry
     }
 }
+
+/// Response of `GET /api/v1/cluster/leader`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderInfo {
+    /// The server holding the leader lease, if any has taken it.
+    pub leader: Option<String>,
+    pub acquired_at: Option<DateTime<Utc>>,
+    pub renewed_at: Option<DateTime<Utc>>,
+    /// The lease has gone unrenewed for longer than its TTL.
+    pub expired: bool,
+    /// The server that answered.
+    pub server_id: String,
+    /// Whether the answering server is running the controllers.
+    pub is_leader: bool,
+}
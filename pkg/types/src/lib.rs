@@ -1,20 +1,33 @@
+pub mod apply;
 pub mod backup;
 pub mod config;
 pub mod configmap;
+pub mod cron;
 pub mod daemonset;
+pub mod defaults;
 pub mod deployment;
 pub mod endpoint;
+pub mod error;
+pub mod event;
+pub mod exec;
+pub mod guest_log;
 pub mod hpa;
 pub mod ingress;
 pub mod job;
 pub mod lease;
+pub mod limit_range;
+pub mod metrics;
 pub mod namespace;
 pub mod network_policy;
 pub mod node;
+pub mod patch;
+pub mod pdb;
 pub mod pod;
+pub mod priority_class;
 pub mod quota;
 pub mod rbac;
 pub mod replicaset;
+pub mod scale;
 pub mod secret;
 pub mod service;
 pub mod validate;
@@ -0,0 +1,247 @@
+use crate::error::FieldError;
+use crate::pod::{ContainerSpec, PodSpec, ResourceRequirements};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Per-container resource defaults and bounds for a namespace, applied to
+/// pods at admission. A zero field is unset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitRange {
+    pub name: String,
+    #[serde(default)]
+    pub namespace: String,
+    /// Requests for containers that request nothing. Falls back to the
+    /// container's own limit, then to `default_limit`.
+    #[serde(default)]
+    pub default_request: ResourceRequirements,
+    /// Limits for containers that set none.
+    #[serde(default)]
+    pub default_limit: ResourceRequirements,
+    /// Smallest request a container may make.
+    #[serde(default)]
+    pub min: ResourceRequirements,
+    /// Largest limit a container may have.
+    #[serde(default)]
+    pub max: ResourceRequirements,
+    pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
+}
+
+impl LimitRange {
+    /// Fill in the defaults for every container (init containers included)
+    /// that leaves a request or limit unset, then check each against `min`
+    /// and `max`. Values set explicitly are never changed. The error lists
+    /// every failed constraint, with the field it applies to.
+    pub fn apply(&self, spec: &mut PodSpec) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let all = [
+            ("init_containers", &mut spec.init_containers),
+            ("containers", &mut spec.containers),
+        ];
+        for (list, containers) in all {
+            for (i, c) in containers.iter_mut().enumerate() {
+                self.apply_defaults(c);
+                self.check(c, &format!("spec.{}[{}]", list, i), &mut errors);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn apply_defaults(&self, c: &mut ContainerSpec) {
+        let fill = |value: &mut u64, defaults: [u64; 3]| {
+            if *value == 0 {
+                *value = defaults.into_iter().find(|d| *d > 0).unwrap_or(0);
+            }
+        };
+        fill(
+            &mut c.resources.cpu_millis,
+            [
+                c.limits.cpu_millis,
+                self.default_request.cpu_millis,
+                self.default_limit.cpu_millis,
+            ],
+        );
+        fill(
+            &mut c.resources.memory_bytes,
+            [
+                c.limits.memory_bytes,
+                self.default_request.memory_bytes,
+                self.default_limit.memory_bytes,
+            ],
+        );
+        fill(
+            &mut c.limits.cpu_millis,
+            [self.default_limit.cpu_millis, 0, 0],
+        );
+        fill(
+            &mut c.limits.memory_bytes,
+            [self.default_limit.memory_bytes, 0, 0],
+        );
+    }
+
+    fn check(&self, c: &ContainerSpec, path: &str, errors: &mut Vec<FieldError>) {
+        let limits = c.effective_limits();
+        let dimensions = [
+            (
+                "cpu",
+                "cpu_millis",
+                c.resources.cpu_millis,
+                limits.cpu_millis,
+                self.min.cpu_millis,
+                self.max.cpu_millis,
+            ),
+            (
+                "memory",
+                "memory_bytes",
+                c.resources.memory_bytes,
+                limits.memory_bytes,
+                self.min.memory_bytes,
+                self.max.memory_bytes,
+            ),
+        ];
+        for (resource, field, request, limit, min, max) in dimensions {
+            let fmt = |v: u64| match resource {
+                "cpu" => format!("{}m", v),
+                _ => v.to_string(),
+            };
+            if limit < request {
+                errors.push(FieldError::new(
+                    format!("{}.limits.{}", path, field),
+                    format!(
+                        "container '{}': {} limit {} is below its request {}",
+                        c.name,
+                        resource,
+                        fmt(limit),
+                        fmt(request)
+                    ),
+                ));
+            }
+            if min > 0 && request < min {
+                errors.push(FieldError::new(
+                    format!("{}.resources.{}", path, field),
+                    format!(
+                        "container '{}': {} request {} is below the minimum {} of LimitRange '{}'",
+                        c.name,
+                        resource,
+                        fmt(request),
+                        fmt(min),
+                        self.name
+                    ),
+                ));
+            }
+            if max > 0 && limit > max {
+                errors.push(FieldError::new(
+                    format!("{}.limits.{}", path, field),
+                    format!(
+                        "container '{}': {} limit {} exceeds the maximum {} of LimitRange '{}'",
+                        c.name,
+                        resource,
+                        fmt(limit),
+                        fmt(max),
+                        self.name
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range() -> LimitRange {
+        serde_json::from_value(serde_json::json!({
+            "name": "defaults",
+            "namespace": "default",
+            "default_request": { "cpu_millis": 100, "memory_bytes": 64 },
+            "default_limit": { "cpu_millis": 500, "memory_bytes": 256 },
+            "min": { "cpu_millis": 50 },
+            "max": { "cpu_millis": 1000, "memory_bytes": 1024 },
+            "created_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn spec(containers: serde_json::Value) -> PodSpec {
+        serde_json::from_value(serde_json::json!({ "containers": containers })).unwrap()
+    }
+
+    #[test]
+    fn test_defaults_fill_only_unset_fields() {
+        let mut spec = spec(serde_json::json!([
+            { "name": "bare", "image": "nginx" },
+            {
+                "name": "explicit",
+                "image": "nginx",
+                "resources": { "cpu_millis": 200 },
+                "limits": { "memory_bytes": 512 },
+            },
+        ]));
+        range().apply(&mut spec).unwrap();
+
+        let bare = &spec.containers[0];
+        assert_eq!(bare.resources.cpu_millis, 100);
+        assert_eq!(bare.resources.memory_bytes, 64);
+        assert_eq!(bare.limits.cpu_millis, 500);
+        assert_eq!(bare.limits.memory_bytes, 256);
+
+        // Set values are kept; a missing request follows the container's limit.
+        let explicit = &spec.containers[1];
+        assert_eq!(explicit.resources.cpu_millis, 200);
+        assert_eq!(explicit.resources.memory_bytes, 512);
+        assert_eq!(explicit.limits.cpu_millis, 500);
+        assert_eq!(explicit.limits.memory_bytes, 512);
+    }
+
+    #[test]
+    fn test_min_and_max_rejected() {
+        let mut too_small = spec(serde_json::json!([
+            { "name": "web", "image": "nginx", "resources": { "cpu_millis": 10 } },
+        ]));
+        assert_eq!(
+            range().apply(&mut too_small).unwrap_err(),
+            vec![FieldError::new(
+                "spec.containers[0].resources.cpu_millis",
+                "container 'web': cpu request 10m is below the minimum 50m of LimitRange 'defaults'"
+            )]
+        );
+
+        // Every violation is reported, not just the first.
+        let mut too_big = spec(serde_json::json!([
+            { "name": "web", "image": "nginx", "limits": { "memory_bytes": 2048 } },
+            { "name": "side", "image": "nginx", "limits": { "cpu_millis": 2000 } },
+        ]));
+        assert_eq!(
+            range().apply(&mut too_big).unwrap_err(),
+            vec![
+                FieldError::new(
+                    "spec.containers[0].limits.memory_bytes",
+                    "container 'web': memory limit 2048 exceeds the maximum 1024 of LimitRange 'defaults'"
+                ),
+                FieldError::new(
+                    "spec.containers[1].limits.cpu_millis",
+                    "container 'side': cpu limit 2000m exceeds the maximum 1000m of LimitRange 'defaults'"
+                ),
+            ]
+        );
+
+        // Exactly at the bounds is fine.
+        let mut at_bounds = spec(serde_json::json!([
+            {
+                "name": "web",
+                "image": "nginx",
+                "resources": { "cpu_millis": 50 },
+                "limits": { "cpu_millis": 1000, "memory_bytes": 1024 },
+            },
+        ]));
+        assert!(range().apply(&mut at_bounds).is_ok());
+    }
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Live usage of one pod, summed over its running containers, as reported
+/// in the node heartbeat.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PodUsage {
+    pub namespace: String,
+    pub name: String,
+    pub cpu_millis: u64,
+    pub memory_bytes: u64,
+}
+
+/// Latest usage sample of a pod.
+/// Stored at `/registry/metrics/pods/<namespace>/<name>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PodMetrics {
+    pub namespace: String,
+    pub name: String,
+    /// Node that reported the sample.
+    pub node_name: String,
+    pub cpu_millis: u64,
+    pub memory_bytes: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl PodMetrics {
+    pub fn key(namespace: &str, name: &str) -> String {
+        format!("/registry/metrics/pods/{}/{}", namespace, name)
+    }
+}
+
+/// Usage of a node: what its latest heartbeat measured for the whole
+/// machine, next to the sum of the pod samples it reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeMetrics {
+    pub name: String,
+    pub cpu_millis: u64,
+    pub memory_bytes: u64,
+    pub cpu_capacity_millis: u64,
+    pub memory_capacity_bytes: u64,
+    /// Pods with a current sample on the node.
+    pub pods: u32,
+    pub pods_cpu_millis: u64,
+    pub pods_memory_bytes: u64,
+    /// Time of the heartbeat the usage came from.
+    pub timestamp: DateTime<Utc>,
+}
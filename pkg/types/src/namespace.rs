@@ -2,10 +2,58 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Registry prefixes of namespace-scoped objects (`/registry/<prefix>/<ns>/`),
+/// in the order a deleted namespace is emptied: owners come before the
+/// objects their controllers create, so nothing is recreated behind the purge.
+pub const NAMESPACED_RESOURCES: &[&str] = &[
+    "cronjobs",
+    "deployments",
+    "hpa",
+    "daemonsets",
+    "jobs",
+    "replicasets",
+    "pods",
+    "services",
+    "endpoints",
+    "ingresses",
+    "configmaps",
+    "secrets",
+    "resourcequotas",
+    "limitranges",
+    "poddisruptionbudgets",
+    "networkpolicies",
+    "pvcs",
+    "metrics/pods",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NamespacePhase {
+    #[default]
+    Active,
+    /// Deletion requested: no new objects are accepted, and the namespace
+    /// record is removed once everything in it is gone.
+    Terminating,
+}
+
+impl std::fmt::Display for NamespacePhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NamespacePhase::Active => write!(f, "Active"),
+            NamespacePhase::Terminating => write!(f, "Terminating"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Namespace {
     pub name: String,
     #[serde(default)]
     pub labels: HashMap<String, String>,
+    #[serde(default)]
+    pub phase: NamespacePhase,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
 }
@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::metrics::PodUsage;
 use crate::pod::ResourceRequirements;
 
 // --- Registration messages ---
@@ -33,6 +34,32 @@ pub struct NodeRegistrationResponse {
     pub server_ca: String,
     /// Port for the agent to listen on for its API (assigned by server)
     pub agent_api_port: u16,
+    /// IPv4 range the node gives bridge-networked pods their addresses from.
+    #[serde(default)]
+    pub pod_cidr: Option<String>,
+    /// Bearer token the control plane presents on the agent API. The agent
+    /// turns away requests without it.
+    #[serde(default)]
+    pub agent_token: Option<String>,
+}
+
+/// Body of `POST /api/v1/nodes/{name}/certificate/renew`: the node's
+/// current certificate and a signature over
+/// `pkg_pki::cert::renewal_message(name, timestamp)` made with its key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateRenewalRequest {
+    pub certificate: String,
+    pub timestamp: DateTime<Utc>,
+    /// Hex-encoded signature.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CertificateRenewalResponse {
+    pub certificate: String,
+    pub private_key: String,
+    pub server_ca: String,
+    pub expires_at: DateTime<Utc>,
 }
 
 // --- Node status ---
@@ -56,13 +83,69 @@ impl std::fmt::Display for NodeStatus {
 
 // --- Taint ---
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Taint {
     pub key: String,
+    #[serde(default)]
     pub value: String,
     pub effect: crate::pod::TaintEffect,
 }
 
+/// `key=value:Effect`, or `key:Effect` without a value.
+impl std::fmt::Display for Taint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.value.is_empty() {
+            write!(f, "{}:{}", self.key, self.effect)
+        } else {
+            write!(f, "{}={}:{}", self.key, self.value, self.effect)
+        }
+    }
+}
+
+// --- Conditions ---
+
+/// A pressure condition the agent reports on its node.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NodeConditionType {
+    /// The runtime data dir filesystem is nearly full.
+    DiskPressure,
+    /// The node is nearly out of memory.
+    MemoryPressure,
+}
+
+impl NodeConditionType {
+    pub const ALL: [NodeConditionType; 2] = [Self::DiskPressure, Self::MemoryPressure];
+
+    /// Key of the `NoSchedule` taint the server keeps on a node while the
+    /// condition holds.
+    pub fn taint_key(self) -> &'static str {
+        match self {
+            Self::DiskPressure => "node.k3rs/disk-pressure",
+            Self::MemoryPressure => "node.k3rs/memory-pressure",
+        }
+    }
+}
+
+impl std::fmt::Display for NodeConditionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeConditionType::DiskPressure => write!(f, "DiskPressure"),
+            NodeConditionType::MemoryPressure => write!(f, "MemoryPressure"),
+        }
+    }
+}
+
+/// A condition holding on a node. Only conditions that hold are listed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NodeCondition {
+    #[serde(rename = "type")]
+    pub condition_type: NodeConditionType,
+    /// What crossed the threshold, e.g. `disk 92% used`.
+    pub message: String,
+    /// When the agent first saw the condition.
+    pub since: DateTime<Utc>,
+}
+
 // --- Persisted Node object ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +175,131 @@ pub struct Node {
     /// WireGuard endpoint ("host:port") for cross-node mesh.
     #[serde(default)]
     pub wg_endpoint: Option<String>,
+    /// Live resource usage from the latest heartbeat.
+    #[serde(default)]
+    pub usage: ResourceRequirements,
+    /// Containers running on the node at the latest heartbeat.
+    #[serde(default)]
+    pub running_containers: u32,
+    /// Version of the agent binary, from the latest heartbeat.
+    #[serde(default)]
+    pub agent_version: Option<String>,
+    /// When the node's current client certificate expires.
+    #[serde(default)]
+    pub certificate_expires_at: Option<DateTime<Utc>>,
+    /// IPv4 range for the node's bridge-networked pods, carved out of the
+    /// cluster CIDR at registration.
+    #[serde(default)]
+    pub pod_cidr: Option<String>,
+    /// Pressure conditions from the latest heartbeat.
+    #[serde(default)]
+    pub conditions: Vec<NodeCondition>,
+}
+
+/// Label whose value is the node's name, for node affinity that pins a pod
+/// to one node. Nodes need not carry it: [`Node::label`] fills it in.
+pub const HOSTNAME_LABEL: &str = "k3rs.io/hostname";
+
+impl Node {
+    /// The value of label `key`. [`HOSTNAME_LABEL`] defaults to the name.
+    pub fn label(&self, key: &str) -> Option<&str> {
+        match self.labels.get(key) {
+            Some(value) => Some(value),
+            None if key == HOSTNAME_LABEL => Some(&self.name),
+            None => None,
+        }
+    }
+}
+
+// --- Heartbeat ---
+
+/// Body of `PUT /api/v1/nodes/{name}/heartbeat`. Older agents send no body.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeHeartbeat {
+    /// CPU in use across the node, in millicores.
+    pub cpu_millis: u64,
+    /// Memory in use across the node, in bytes.
+    pub memory_bytes: u64,
+    pub running_containers: u32,
+    pub agent_version: String,
+    pub timestamp: DateTime<Utc>,
+    /// Per-pod usage of the pods running on the node.
+    #[serde(default)]
+    pub pods: Vec<PodUsage>,
+    /// Pressure conditions holding on the node.
+    #[serde(default)]
+    pub conditions: Vec<NodeCondition>,
+}
+
+impl NodeHeartbeat {
+    /// Record the reported usage on the stored node.
+    pub fn apply_to(&self, node: &mut Node) {
+        node.usage = ResourceRequirements {
+            cpu_millis: self.cpu_millis,
+            memory_bytes: self.memory_bytes,
+        };
+        node.running_containers = self.running_containers;
+        node.agent_version = Some(self.agent_version.clone());
+        node.conditions = self.conditions.clone();
+    }
+}
+
+// --- Drain ---
+
+/// Result of `POST /api/v1/nodes/{name}/drain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrainReport {
+    pub node: String,
+    /// The pods that were on the node, in eviction order.
+    pub pods: Vec<DrainedPod>,
+    /// Every evicted pod reached its final state before the timeout.
+    pub complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrainedPod {
+    pub namespace: String,
+    pub name: String,
+    pub state: DrainPodState,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DrainPodState {
+    /// Left in place (DaemonSet-managed).
+    Skipped,
+    /// Not evicted yet: a disruption budget does not allow it.
+    Blocked,
+    /// Evicted; its containers are still stopping.
+    Evicting,
+    /// Stopped and removed from the node.
+    Terminated,
+    /// Stopped; waiting for its ReplicaSet to run a replacement elsewhere.
+    AwaitingReschedule,
+    /// A replacement is running on another node.
+    Rescheduled,
+}
+
+impl DrainPodState {
+    /// Whether the drain is done with the pod.
+    pub fn is_final(self) -> bool {
+        matches!(self, Self::Skipped | Self::Terminated | Self::Rescheduled)
+    }
+}
+
+impl std::fmt::Display for DrainPodState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrainPodState::Skipped => write!(f, "skipped"),
+            DrainPodState::Blocked => write!(f, "blocked"),
+            DrainPodState::Evicting => write!(f, "evicting"),
+            DrainPodState::Terminated => write!(f, "terminated"),
+            DrainPodState::AwaitingReschedule => write!(f, "awaiting reschedule"),
+            DrainPodState::Rescheduled => write!(f, "rescheduled"),
+        }
+    }
 }
 
 // --- Cluster info ---
@@ -101,7 +309,82 @@ pub struct ClusterInfo {
     pub endpoint: String,
     pub version: String,
     pub state_store: String,
+    /// Directory, bucket URL or endpoints the state store keeps state in.
+    #[serde(default)]
+    pub state_store_location: Option<String>,
     pub node_count: usize,
     #[serde(default)]
     pub cluster_id: Option<u32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODE: &str = r#"{
+        "id": "n1",
+        "name": "node-a",
+        "address": "10.0.0.5",
+        "agent_api_port": 10250,
+        "status": "Ready",
+        "registered_at": "2024-02-25T00:00:00Z",
+        "last_heartbeat": "2024-02-25T00:00:00Z",
+        "labels": {}
+    }"#;
+
+    #[test]
+    fn test_heartbeat_roundtrip() {
+        let hb = NodeHeartbeat {
+            cpu_millis: 1250,
+            memory_bytes: 3 << 30,
+            running_containers: 4,
+            agent_version: "0.1.0".to_string(),
+            timestamp: "2024-02-25T00:00:10Z".parse().unwrap(),
+            pods: vec![PodUsage {
+                namespace: "default".to_string(),
+                name: "web-0".to_string(),
+                cpu_millis: 250,
+                memory_bytes: 64 << 20,
+            }],
+            conditions: vec![NodeCondition {
+                condition_type: NodeConditionType::DiskPressure,
+                message: "disk 92% used".to_string(),
+                since: "2024-02-25T00:00:00Z".parse().unwrap(),
+            }],
+        };
+        let json = serde_json::to_value(&hb).unwrap();
+        assert_eq!(json["cpu_millis"], 1250);
+        assert_eq!(json["running_containers"], 4);
+        assert_eq!(json["timestamp"], "2024-02-25T00:00:10Z");
+        assert_eq!(json["conditions"][0]["type"], "DiskPressure");
+        let back: NodeHeartbeat = serde_json::from_value(json).unwrap();
+        assert_eq!(back.memory_bytes, 3 << 30);
+        assert_eq!(back.agent_version, "0.1.0");
+        assert_eq!(back.pods[0].cpu_millis, 250);
+        assert_eq!(back.conditions, hb.conditions);
+    }
+
+    #[test]
+    fn test_heartbeat_merges_into_stored_node() {
+        // Nodes persisted before heartbeats carried usage still decode.
+        let mut node: Node = serde_json::from_str(NODE).unwrap();
+        assert_eq!(node.usage.cpu_millis, 0);
+        assert_eq!(node.agent_version, None);
+
+        let hb = NodeHeartbeat {
+            cpu_millis: 500,
+            memory_bytes: 1 << 30,
+            running_containers: 2,
+            agent_version: "0.2.0".to_string(),
+            timestamp: Utc::now(),
+            pods: Vec::new(),
+            conditions: Vec::new(),
+        };
+        hb.apply_to(&mut node);
+        assert_eq!(node.usage.cpu_millis, 500);
+        assert_eq!(node.usage.memory_bytes, 1 << 30);
+        assert_eq!(node.running_containers, 2);
+        assert_eq!(node.agent_version.as_deref(), Some("0.2.0"));
+        assert_eq!(node.name, "node-a");
+    }
+}
@@ -0,0 +1,180 @@
+//! Partial updates: `PATCH` applies a patch document to the stored object
+//! instead of replacing it. The patch format is chosen by the request's
+//! `Content-Type`; only JSON Merge Patch (RFC 7386) is implemented.
+
+use crate::error::FieldError;
+use serde_json::Value;
+
+/// `Content-Type` of a JSON Merge Patch.
+pub const MERGE_PATCH_CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// `Content-Type` of a Kubernetes strategic merge patch.
+pub const STRATEGIC_MERGE_PATCH_CONTENT_TYPE: &str = "application/strategic-merge-patch+json";
+
+/// Fields fixed when an object is created. Cluster-scoped kinds have no
+/// `namespace`, and some kinds no `id`; only fields the stored object has
+/// are guarded.
+pub const IMMUTABLE_FIELDS: &[&str] = &["id", "name", "namespace", "created_at"];
+
+/// A patch format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchType {
+    /// RFC 7386: objects merge recursively, `null` removes a key, anything
+    /// else (arrays included) replaces the target value.
+    Merge,
+    /// Merges lists by key; recognized so it can be refused explicitly.
+    StrategicMerge,
+}
+
+impl PatchType {
+    /// The patch type named by a `Content-Type` header value, ignoring
+    /// parameters such as `charset`.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        if mime.eq_ignore_ascii_case(MERGE_PATCH_CONTENT_TYPE) {
+            Some(Self::Merge)
+        } else if mime.eq_ignore_ascii_case(STRATEGIC_MERGE_PATCH_CONTENT_TYPE) {
+            Some(Self::StrategicMerge)
+        } else {
+            None
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Merge => MERGE_PATCH_CONTENT_TYPE,
+            Self::StrategicMerge => STRATEGIC_MERGE_PATCH_CONTENT_TYPE,
+        }
+    }
+}
+
+/// Apply a JSON Merge Patch to `target` in place (RFC 7386 `MergePatch`).
+/// A patch that is not an object replaces the target outright; an object
+/// patch turns a non-object target into an object first.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let Value::Object(target) = target else {
+        unreachable!("target was just made an object");
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// The [`IMMUTABLE_FIELDS`] that differ between the stored object and the
+/// patched one, each reported against its field.
+pub fn immutable_changes(stored: &Value, patched: &Value) -> Vec<FieldError> {
+    IMMUTABLE_FIELDS
+        .iter()
+        .filter_map(|&field| {
+            let before = stored.get(field)?;
+            (patched.get(field) != Some(before))
+                .then(|| FieldError::new(field, "field is immutable"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn merged(target: Value, patch: Value) -> Value {
+        let mut target = target;
+        merge_patch(&mut target, &patch);
+        target
+    }
+
+    #[test]
+    fn test_merge_patch_objects_and_nulls() {
+        let target = json!({
+            "name": "web",
+            "labels": { "app": "web", "tier": "front" },
+            "spec": { "replicas": 1, "template": { "runtime": "youki", "vpc": "a" } },
+        });
+        let patch = json!({
+            "labels": { "tier": null, "track": "stable" },
+            "spec": { "replicas": 3, "template": { "vpc": null } },
+            "missing": null,
+        });
+        assert_eq!(
+            merged(target, patch),
+            json!({
+                "name": "web",
+                "labels": { "app": "web", "track": "stable" },
+                "spec": { "replicas": 3, "template": { "runtime": "youki" } },
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_patch_replaces_arrays() {
+        let target = json!({ "ports": [{ "port": 80 }, { "port": 443 }] });
+        let patch = json!({ "ports": [{ "port": 8080 }] });
+        assert_eq!(
+            merged(target, patch),
+            json!({ "ports": [{ "port": 8080 }] })
+        );
+    }
+
+    #[test]
+    fn test_merge_patch_type_mismatch() {
+        // An object patch over a scalar builds an object, dropping its nulls.
+        let target = json!({ "selector": "app=web" });
+        let patch = json!({ "selector": { "app": "web", "tier": null } });
+        assert_eq!(
+            merged(target, patch),
+            json!({ "selector": { "app": "web" } })
+        );
+        // A scalar patch over an object replaces it.
+        let target = json!({ "selector": { "app": "web" } });
+        assert_eq!(
+            merged(target, json!({ "selector": "app=web" })),
+            json!({ "selector": "app=web" })
+        );
+        // A non-object patch replaces the whole document.
+        assert_eq!(merged(json!({ "a": 1 }), json!([1, 2])), json!([1, 2]));
+    }
+
+    #[test]
+    fn test_immutable_changes() {
+        let stored = json!({ "id": "x", "name": "web", "namespace": "default", "spec": {} });
+        let patched = merged(
+            stored.clone(),
+            json!({ "namespace": "prod", "id": null, "spec": { "replicas": 2 } }),
+        );
+        assert_eq!(
+            immutable_changes(&stored, &patched),
+            [
+                FieldError::new("id", "field is immutable"),
+                FieldError::new("namespace", "field is immutable"),
+            ]
+        );
+        // Repeating the stored value is not a change.
+        let patched = merged(stored.clone(), json!({ "name": "web" }));
+        assert!(immutable_changes(&stored, &patched).is_empty());
+    }
+
+    #[test]
+    fn test_patch_type_from_content_type() {
+        assert_eq!(
+            PatchType::from_content_type("application/merge-patch+json; charset=utf-8"),
+            Some(PatchType::Merge)
+        );
+        assert_eq!(
+            PatchType::from_content_type(STRATEGIC_MERGE_PATCH_CONTENT_TYPE),
+            Some(PatchType::StrategicMerge)
+        );
+        assert_eq!(PatchType::from_content_type("application/json"), None);
+    }
+}
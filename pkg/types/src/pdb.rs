@@ -0,0 +1,157 @@
+use crate::pod::{LabelSelector, Pod, PodStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Limits how many of a set of pods voluntary disruptions (evictions, node
+/// drains) may take down at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PodDisruptionBudget {
+    pub name: String,
+    #[serde(default)]
+    pub namespace: String,
+    /// Pods in the namespace the budget covers; empty selects all of them.
+    #[serde(default)]
+    pub selector: LabelSelector,
+    /// Ready pods that must remain after an eviction. Takes precedence over
+    /// `max_unavailable`.
+    #[serde(default)]
+    pub min_available: Option<u32>,
+    /// Pods that may be missing or not ready at once.
+    #[serde(default)]
+    pub max_unavailable: Option<u32>,
+    /// Computed on read from the current pods.
+    #[serde(default)]
+    pub status: PdbStatus,
+    pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdbStatus {
+    /// Selected pods that are not finished or being deleted.
+    pub expected_pods: u32,
+    /// Selected pods that are ready.
+    pub current_healthy: u32,
+    /// Ready pods the budget requires.
+    pub desired_healthy: u32,
+    pub disruptions_allowed: u32,
+}
+
+impl PdbStatus {
+    /// `3/3 required available`: current vs required ready pods.
+    pub fn summary(&self) -> String {
+        format!(
+            "{}/{} required available",
+            self.current_healthy, self.desired_healthy
+        )
+    }
+}
+
+impl PodDisruptionBudget {
+    /// Whether the budget covers `pod`.
+    pub fn selects(&self, pod: &Pod) -> bool {
+        pod.namespace == self.namespace && self.selector.matches(&pod.labels)
+    }
+
+    /// The budget's status over `pods` (any namespace; only selected pods count).
+    pub fn compute_status<'a>(&self, pods: impl IntoIterator<Item = &'a Pod>) -> PdbStatus {
+        let (mut expected, mut healthy) = (0u32, 0u32);
+        for pod in pods.into_iter().filter(|p| self.selects(p)) {
+            if matches!(
+                pod.status,
+                PodStatus::Succeeded | PodStatus::Failed | PodStatus::Terminating
+            ) {
+                continue;
+            }
+            expected += 1;
+            if pod.is_ready() {
+                healthy += 1;
+            }
+        }
+        let desired = match (self.min_available, self.max_unavailable) {
+            (Some(min), _) => min,
+            (None, Some(max)) => expected.saturating_sub(max),
+            (None, None) => 0,
+        };
+        PdbStatus {
+            expected_pods: expected,
+            current_healthy: healthy,
+            desired_healthy: desired,
+            disruptions_allowed: healthy.saturating_sub(desired),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pod(name: &str, app: &str, status: &str, ready: bool) -> Pod {
+        serde_json::from_value(serde_json::json!({
+            "id": format!("{}-id", name),
+            "name": name,
+            "namespace": "default",
+            "spec": { "containers": [] },
+            "status": status,
+            "labels": { "app": app },
+            "ready": ready,
+            "created_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    fn pdb(min_available: Option<u32>, max_unavailable: Option<u32>) -> PodDisruptionBudget {
+        serde_json::from_value(serde_json::json!({
+            "name": "web",
+            "namespace": "default",
+            "selector": { "match_labels": { "app": "web" } },
+            "min_available": min_available,
+            "max_unavailable": max_unavailable,
+            "created_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_disruptions_allowed() {
+        let pods = [
+            pod("web-1", "web", "Running", true),
+            pod("web-2", "web", "Running", true),
+            pod("web-3", "web", "Running", true),
+            pod("web-4", "web", "Pending", false),
+            pod("web-5", "web", "Terminating", true),
+            pod("db-1", "db", "Running", true),
+        ];
+
+        let status = pdb(Some(2), None).compute_status(&pods);
+        assert_eq!(status.expected_pods, 4);
+        assert_eq!(status.current_healthy, 3);
+        assert_eq!(status.disruptions_allowed, 1);
+        assert_eq!(status.summary(), "3/2 required available");
+
+        // Exactly at the minimum: nothing may go.
+        assert_eq!(
+            pdb(Some(3), None).compute_status(&pods).disruptions_allowed,
+            0
+        );
+        // min_available wins over max_unavailable.
+        assert_eq!(
+            pdb(Some(3), Some(4))
+                .compute_status(&pods)
+                .disruptions_allowed,
+            0
+        );
+        // Four expected, at most two unavailable: two must stay ready.
+        let status = pdb(None, Some(2)).compute_status(&pods);
+        assert_eq!(status.desired_healthy, 2);
+        assert_eq!(status.disruptions_allowed, 1);
+        // The not-ready pod already uses up the single allowed disruption.
+        assert_eq!(
+            pdb(None, Some(1)).compute_status(&pods).disruptions_allowed,
+            0
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::FieldError;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -26,10 +27,238 @@ pub struct ContainerSpec {
     pub args: Vec<String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Requests: what the scheduler and resource quotas count.
     #[serde(default)]
     pub resources: ResourceRequirements,
+    /// Upper bounds for the runtime to enforce. A zero field defaults to the
+    /// request, see [`ContainerSpec::effective_limits`].
+    #[serde(default)]
+    pub limits: ResourceRequirements,
     #[serde(default)]
     pub volume_mounts: Vec<crate::volume::VolumeMount>,
+    /// Restart the container when this probe keeps failing.
+    #[serde(default)]
+    pub liveness_probe: Option<Probe>,
+    /// Keep the pod out of service endpoints while this probe fails.
+    #[serde(default)]
+    pub readiness_probe: Option<Probe>,
+    /// Unset means `Always` for a `:latest` or untagged image and
+    /// `IfNotPresent` otherwise (see [`ContainerSpec::pull_policy`]); the API
+    /// server fills it in at admission.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_pull_policy: Option<ImagePullPolicy>,
+    #[serde(default)]
+    pub security_context: SecurityContext,
+}
+
+impl ContainerSpec {
+    /// The container's limits, with unset fields defaulting to the request.
+    pub fn effective_limits(&self) -> ResourceRequirements {
+        let or_request = |limit: u64, request: u64| if limit > 0 { limit } else { request };
+        ResourceRequirements {
+            cpu_millis: or_request(self.limits.cpu_millis, self.resources.cpu_millis),
+            memory_bytes: or_request(self.limits.memory_bytes, self.resources.memory_bytes),
+        }
+    }
+
+    /// The image pull policy, or the one implied by the image's tag when
+    /// none is set: a moving `:latest` (or no tag) is re-pulled on every
+    /// start, a pinned tag or digest only when missing.
+    pub fn pull_policy(&self) -> ImagePullPolicy {
+        if let Some(policy) = self.image_pull_policy {
+            return policy;
+        }
+        if self.image.contains('@') {
+            return ImagePullPolicy::IfNotPresent;
+        }
+        // A ':' after the last '/' starts the tag; one before it is a port.
+        let last = self.image.rsplit('/').next().unwrap_or_default();
+        match last.split_once(':') {
+            Some((_, tag)) if tag != "latest" => ImagePullPolicy::IfNotPresent,
+            _ => ImagePullPolicy::Always,
+        }
+    }
+}
+
+/// When the agent contacts the registry for a container's image.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum ImagePullPolicy {
+    /// Pull only when the image is not cached on the node.
+    #[default]
+    IfNotPresent,
+    /// Re-resolve the manifest on every start, fetching changed layers.
+    Always,
+    /// Only use the node's cache; fail if the image is missing.
+    Never,
+}
+
+impl std::fmt::Display for ImagePullPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImagePullPolicy::IfNotPresent => write!(f, "IfNotPresent"),
+            ImagePullPolicy::Always => write!(f, "Always"),
+            ImagePullPolicy::Never => write!(f, "Never"),
+        }
+    }
+}
+
+// --- Security context ---
+
+/// User, capabilities and filesystem access of a container's process.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SecurityContext {
+    /// UID of the process. Defaults to the image's `User`, then root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as_user: Option<u32>,
+    /// GID of the process. Defaults to the group of the image's `User`,
+    /// then root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_as_group: Option<u32>,
+    /// Refuse to start the container when it would run as UID 0.
+    #[serde(default)]
+    pub run_as_non_root: bool,
+    /// Mount the container's root filesystem read-only.
+    #[serde(default)]
+    pub read_only_root_filesystem: bool,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    /// Whether the process may gain privileges through setuid binaries or
+    /// file capabilities. Defaults to `true` unless `privileged`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_new_privileges: Option<bool>,
+    /// Every capability, host devices and writable `/sys`.
+    #[serde(default)]
+    pub privileged: bool,
+}
+
+/// Changes to the default capability set. `drop` is applied first; `ALL`
+/// drops every capability. Names may omit the `CAP_` prefix.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Capabilities {
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub drop: Vec<String>,
+}
+
+impl Capabilities {
+    /// Capability name in its `CAP_`-prefixed, upper-case form.
+    pub fn normalize(name: &str) -> String {
+        let name = name.trim().to_ascii_uppercase();
+        if name == "ALL" || name.starts_with("CAP_") {
+            name
+        } else {
+            format!("CAP_{}", name)
+        }
+    }
+}
+
+impl SecurityContext {
+    /// Check for combinations that can't be honored, adding one error per
+    /// problem under `path`.
+    fn validate(&self, path: &str, container: &str, errors: &mut Vec<FieldError>) {
+        let field = |name: &str| format!("{}.security_context.{}", path, name);
+        if self.privileged && self.run_as_non_root {
+            errors.push(FieldError::new(
+                field("run_as_non_root"),
+                format!(
+                    "container '{}': run_as_non_root can't be combined with privileged",
+                    container
+                ),
+            ));
+        }
+        if self.run_as_non_root && self.run_as_user == Some(0) {
+            errors.push(FieldError::new(
+                field("run_as_user"),
+                format!(
+                    "container '{}': run_as_user 0 contradicts run_as_non_root",
+                    container
+                ),
+            ));
+        }
+        if self.privileged && self.no_new_privileges == Some(true) {
+            errors.push(FieldError::new(
+                field("no_new_privileges"),
+                format!(
+                    "container '{}': no_new_privileges can't be set on a privileged container",
+                    container
+                ),
+            ));
+        }
+        let caps = [
+            ("add", &self.capabilities.add),
+            ("drop", &self.capabilities.drop),
+        ];
+        for (list, names) in caps {
+            for (i, name) in names.iter().enumerate() {
+                let cap = Capabilities::normalize(name);
+                let valid = match cap.as_str() {
+                    // Adding everything is what `privileged` is for.
+                    "ALL" => list == "drop",
+                    _ => cap.len() > 4 && cap.chars().all(|c| c.is_ascii_uppercase() || c == '_'),
+                };
+                if !valid {
+                    errors.push(FieldError::new(
+                        field(&format!("capabilities.{}[{}]", list, i)),
+                        format!("container '{}': invalid capability '{}'", container, name),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+// --- Probes ---
+
+/// A periodic health check run by the agent against a container.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Probe {
+    #[serde(flatten)]
+    pub handler: ProbeHandler,
+    /// Seconds after the container starts before the first probe.
+    #[serde(default)]
+    pub initial_delay_seconds: u64,
+    /// Seconds between probes.
+    #[serde(default = "default_probe_period")]
+    pub period_seconds: u64,
+    /// Seconds before a single probe attempt counts as failed.
+    #[serde(default = "default_probe_timeout")]
+    pub timeout_seconds: u64,
+    /// Consecutive failures before the probe is considered failed.
+    #[serde(default = "default_probe_failure_threshold")]
+    pub failure_threshold: u32,
+}
+
+fn default_probe_period() -> u64 {
+    10
+}
+
+fn default_probe_timeout() -> u64 {
+    1
+}
+
+fn default_probe_failure_threshold() -> u32 {
+    3
+}
+
+/// How a probe checks the container.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeHandler {
+    /// `GET path` on the pod's address; a 2xx or 3xx response succeeds.
+    HttpGet {
+        #[serde(default = "default_probe_path")]
+        path: String,
+        port: u16,
+    },
+    /// Succeeds when a TCP connection to the port can be opened.
+    TcpSocket { port: u16 },
+    /// Runs a command inside the container; exit code 0 succeeds.
+    Exec { command: Vec<String> },
+}
+
+fn default_probe_path() -> String {
+    "/".to_string()
 }
 
 // --- Pod status ---
@@ -40,6 +269,9 @@ pub enum PodStatus {
     Scheduled,
     ContainerCreating,
     Running,
+    /// Deletion requested; the agent is stopping the containers within the
+    /// pod's termination grace period.
+    Terminating,
     Succeeded,
     Failed,
     Unknown,
@@ -52,6 +284,7 @@ impl std::fmt::Display for PodStatus {
             PodStatus::Scheduled => write!(f, "Scheduled"),
             PodStatus::ContainerCreating => write!(f, "ContainerCreating"),
             PodStatus::Running => write!(f, "Running"),
+            PodStatus::Terminating => write!(f, "Terminating"),
             PodStatus::Succeeded => write!(f, "Succeeded"),
             PodStatus::Failed => write!(f, "Failed"),
             PodStatus::Unknown => write!(f, "Unknown"),
@@ -59,11 +292,166 @@ impl std::fmt::Display for PodStatus {
     }
 }
 
+/// `status_message` prefix reported while a crashed container waits to be restarted.
+pub const CRASH_LOOP_BACK_OFF: &str = "CrashLoopBackOff";
+
+/// `status_message` prefix reported when a container was killed for
+/// exceeding its memory limit.
+pub const OOM_KILLED: &str = "OOMKilled";
+
+/// `status_message` prefix reported while init containers run, as `Init:<done>/<total>`.
+pub const INIT_STATUS_PREFIX: &str = "Init:";
+
+/// `ContainerState::Waiting` reason while a container is being created.
+pub const CONTAINER_CREATING: &str = "ContainerCreating";
+
+/// What one of a pod's containers is doing.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerState {
+    /// Not running yet, e.g. `ContainerCreating`, `ErrImagePull` or
+    /// `CrashLoopBackOff`.
+    Waiting {
+        reason: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+    Running {
+        started_at: DateTime<Utc>,
+    },
+    /// Exited: `Completed`, `Error` or `OOMKilled`. The exit code is unknown
+    /// when the backend could not report one.
+    Terminated {
+        exit_code: Option<i32>,
+        reason: String,
+        finished_at: DateTime<Utc>,
+    },
+}
+
+impl ContainerState {
+    /// Whether the container exited with anything but code 0.
+    pub fn failed(&self) -> bool {
+        matches!(self, ContainerState::Terminated { exit_code, .. } if *exit_code != Some(0))
+    }
+}
+
+impl std::fmt::Display for ContainerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContainerState::Waiting { reason, .. } => write!(f, "Waiting ({})", reason),
+            ContainerState::Running { .. } => write!(f, "Running"),
+            ContainerState::Terminated {
+                exit_code: Some(code),
+                reason,
+                ..
+            } => write!(f, "Terminated ({}, exit code {})", reason, code),
+            ContainerState::Terminated { reason, .. } => write!(f, "Terminated ({})", reason),
+        }
+    }
+}
+
+/// Reported state of one of a pod's app containers, in spec order.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ContainerStatus {
+    pub name: String,
+    pub image: String,
+    /// Runtime ID, once the container was created.
+    #[serde(default)]
+    pub container_id: Option<String>,
+    pub state: ContainerState,
+    /// Times this container was restarted.
+    #[serde(default)]
+    pub restart_count: u32,
+    /// Running, and passing the pod's readiness probe if it has one.
+    #[serde(default)]
+    pub ready: bool,
+}
+
+impl PodStatus {
+    /// The pod phase its containers add up to under `policy`, or `None`
+    /// when there are none to go by:
+    ///
+    /// - any container waiting: `ContainerCreating` (`Pending` is for pods
+    ///   no node has been picked for);
+    /// - a failed container the policy does not restart: `Failed`;
+    /// - every container exited with code 0 and the policy does not restart
+    ///   them: `Succeeded`;
+    /// - otherwise `Running`, restarting exited containers.
+    pub fn from_containers(policy: RestartPolicy, statuses: &[ContainerStatus]) -> Option<Self> {
+        if statuses.is_empty() {
+            return None;
+        }
+        let states = || statuses.iter().map(|s| &s.state);
+        if states().any(|s| matches!(s, ContainerState::Waiting { .. })) {
+            return Some(PodStatus::ContainerCreating);
+        }
+        if policy == RestartPolicy::Never && states().any(ContainerState::failed) {
+            return Some(PodStatus::Failed);
+        }
+        let all_completed = states().all(|s| matches!(s, ContainerState::Terminated { .. }))
+            && !states().any(ContainerState::failed);
+        if all_completed && policy != RestartPolicy::Always {
+            return Some(PodStatus::Succeeded);
+        }
+        Some(PodStatus::Running)
+    }
+}
+
+/// Body of `PUT /api/v1/namespaces/{ns}/pods/{name}/status`: either a bare
+/// status or a status with details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PodStatusUpdate {
+    Status(PodStatus),
+    Detailed {
+        status: PodStatus,
+        #[serde(default)]
+        status_message: Option<String>,
+        #[serde(default)]
+        restart_count: Option<u32>,
+        /// Result of the readiness probe.
+        #[serde(default)]
+        ready: Option<bool>,
+        /// Address the agent gave the pod; absent leaves the stored one.
+        #[serde(default)]
+        pod_ip: Option<String>,
+        /// State of each app container; absent leaves the stored ones.
+        #[serde(default)]
+        container_statuses: Option<Vec<ContainerStatus>>,
+    },
+}
+
 // --- Pod spec ---
 
+/// What the agent does when a pod's container exits.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum RestartPolicy {
+    /// Restart on every exit, including success.
+    #[default]
+    Always,
+    /// Restart on a non-zero exit; a zero exit completes the pod.
+    OnFailure,
+    /// Never restart; the exit code decides Succeeded or Failed.
+    Never,
+}
+
+impl std::fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestartPolicy::Always => write!(f, "Always"),
+            RestartPolicy::OnFailure => write!(f, "OnFailure"),
+            RestartPolicy::Never => write!(f, "Never"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PodSpec {
     pub containers: Vec<ContainerSpec>,
+    /// Containers run to completion, one at a time and in order, before
+    /// `containers` start. A failing one blocks the pod.
+    #[serde(default)]
+    pub init_containers: Vec<ContainerSpec>,
     /// Explicit runtime selection: "youki", "crun", "vm" (Apple VZ on macOS / Firecracker on Linux)
     #[serde(default)]
     pub runtime: Option<String>,
@@ -76,6 +464,82 @@ pub struct PodSpec {
     /// VPC name this pod belongs to (defaults to "default" if unset)
     #[serde(default)]
     pub vpc: Option<String>,
+    /// Scheduling priority — higher-priority pending pods are scheduled first.
+    #[serde(default)]
+    pub priority: i32,
+    /// Name of a PriorityClass; resolved into `priority` at admission.
+    #[serde(default)]
+    pub priority_class_name: Option<String>,
+    /// Hard anti-affinity: never co-locate with a pod in the same namespace
+    /// whose labels match any of these selectors.
+    #[serde(default)]
+    pub pod_anti_affinity: Vec<LabelSelector>,
+    /// Seconds between SIGTERM and SIGKILL when the pod is deleted
+    /// (agent default: 30). Zero kills immediately.
+    #[serde(default)]
+    pub termination_grace_period_seconds: Option<u64>,
+    #[serde(default)]
+    pub restart_policy: RestartPolicy,
+    /// Secrets in the pod's namespace holding registry credentials
+    /// (`.dockerconfigjson`) for pulling its images.
+    #[serde(default)]
+    pub image_pull_secrets: Vec<String>,
+}
+
+impl PodSpec {
+    /// Reject security contexts that contradict themselves, in every
+    /// container (init containers included). The error lists each problem
+    /// with the field it applies to.
+    pub fn validate_security_contexts(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        self.check_security_contexts("spec", &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// [`PodSpec::validate_security_contexts`] for a spec at `path`.
+    pub(crate) fn check_security_contexts(&self, path: &str, errors: &mut Vec<FieldError>) {
+        let all = [
+            ("init_containers", &self.init_containers),
+            ("containers", &self.containers),
+        ];
+        for (list, containers) in all {
+            for (i, c) in containers.iter().enumerate() {
+                c.security_context
+                    .validate(&format!("{}.{}[{}]", path, list, i), &c.name, errors);
+            }
+        }
+    }
+
+    /// Names of the PersistentVolumeClaims the pod's volumes use.
+    pub fn claim_names(&self) -> impl Iterator<Item = &str> {
+        self.volumes.iter().filter_map(|v| match &v.source {
+            crate::volume::VolumeSource::PersistentVolumeClaim { claim_name } => {
+                Some(claim_name.as_str())
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Equality-based label selector.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LabelSelector {
+    #[serde(default)]
+    pub match_labels: HashMap<String, String>,
+}
+
+impl LabelSelector {
+    /// Whether every `match_labels` entry is present in `labels`.
+    /// An empty selector matches everything.
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.match_labels
+            .iter()
+            .all(|(k, v)| labels.get(k) == Some(v))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +551,24 @@ pub struct Toleration {
     pub value: String,
     #[serde(default)]
     pub effect: TaintEffect,
+    /// How long the pod may keep running after a matching `NoExecute` taint
+    /// appears on its node. Unset tolerates it for good.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toleration_seconds: Option<u64>,
+}
+
+impl Toleration {
+    /// Whether this toleration matches the taint: same key, and the same
+    /// value unless the operator is `Exists`.
+    pub fn matches(&self, taint: &crate::node::Taint) -> bool {
+        if self.key != taint.key {
+            return false;
+        }
+        match self.operator {
+            TolerationOperator::Exists => true,
+            TolerationOperator::Equal => self.value == taint.value,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -96,14 +578,45 @@ pub enum TolerationOperator {
     Exists,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum TaintEffect {
     #[default]
     NoSchedule,
     PreferNoSchedule,
+    /// Also evicts running pods that do not tolerate the taint.
     NoExecute,
 }
 
+impl TaintEffect {
+    pub const ALL: [TaintEffect; 3] = [Self::NoSchedule, Self::PreferNoSchedule, Self::NoExecute];
+}
+
+impl std::fmt::Display for TaintEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaintEffect::NoSchedule => write!(f, "NoSchedule"),
+            TaintEffect::PreferNoSchedule => write!(f, "PreferNoSchedule"),
+            TaintEffect::NoExecute => write!(f, "NoExecute"),
+        }
+    }
+}
+
+impl std::str::FromStr for TaintEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|e| e.to_string() == s)
+            .ok_or_else(|| {
+                format!(
+                    "unknown taint effect '{}' (expected NoSchedule, PreferNoSchedule or NoExecute)",
+                    s
+                )
+            })
+    }
+}
+
 // --- Pod runtime info ---
 
 /// Tracks which container runtime backend is running this pod.
@@ -117,6 +630,16 @@ pub struct PodRuntimeInfo {
 
 // --- Pod ---
 
+/// Runtime container ID of a pod's container: `{pod_id}-{container_name}`.
+pub fn container_id(pod_id: &str, container_name: &str) -> String {
+    format!("{}-{}", pod_id, container_name)
+}
+
+/// Name of the mirror record of static pod `name` running on `node_name`.
+pub fn mirror_pod_name(name: &str, node_name: &str) -> String {
+    format!("{}-{}", name, node_name)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pod {
     pub id: String,
@@ -151,5 +674,361 @@ pub struct Pod {
     /// Resolved VPC name for this pod
     #[serde(default)]
     pub vpc_name: Option<String>,
+    /// IPv4 address from the node's pod CIDR, for bridge-networked pods
+    #[serde(default)]
+    pub pod_ip: Option<String>,
+    /// Whether the readiness probe passes; reset whenever the pod leaves Running.
+    #[serde(default)]
+    pub ready: bool,
+    /// Read-only record of a static pod, which its node runs from a local
+    /// manifest. Deleting the record does not stop the pod.
+    #[serde(default)]
+    pub mirror: bool,
+    /// State of each app container, reported by the agent.
+    #[serde(default)]
+    pub container_statuses: Vec<ContainerStatus>,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
+}
+
+impl Pod {
+    /// Status for listings: `CrashLoopBackOff` while a restart is being
+    /// delayed, `Init:<done>/<total>` while init containers run, `OOMKilled`
+    /// for a pod that failed on its memory limit, otherwise the phase.
+    pub fn display_status(&self) -> String {
+        match self.status_message.as_deref() {
+            Some(msg) if msg.starts_with(CRASH_LOOP_BACK_OFF) => CRASH_LOOP_BACK_OFF.to_string(),
+            Some(msg) if msg.starts_with(OOM_KILLED) && self.status == PodStatus::Failed => {
+                OOM_KILLED.to_string()
+            }
+            Some(msg)
+                if msg.starts_with(INIT_STATUS_PREFIX)
+                    && self.status == PodStatus::ContainerCreating =>
+            {
+                msg.to_string()
+            }
+            _ => self.status.to_string(),
+        }
+    }
+
+    /// Containers ready and in the spec, for a `READY` column. Pods from
+    /// agents that do not report container statuses count as all ready or
+    /// none.
+    pub fn ready_containers(&self) -> (usize, usize) {
+        let total = self.spec.containers.len();
+        let ready = if self.container_statuses.is_empty() {
+            if self.is_ready() { total } else { 0 }
+        } else {
+            self.container_statuses.iter().filter(|s| s.ready).count()
+        };
+        (ready, total)
+    }
+
+    /// Runtime IDs of the pod's containers, in spec order.
+    pub fn container_ids(&self) -> Vec<String> {
+        self.spec
+            .containers
+            .iter()
+            .map(|c| container_id(&self.id, &c.name))
+            .collect()
+    }
+
+    /// Runtime ID of the named container, or of the first container when no
+    /// name is given. `None` if the pod has no such container.
+    pub fn select_container_id(&self, name: Option<&str>) -> Option<String> {
+        let container = match name {
+            Some(name) => self.spec.containers.iter().find(|c| c.name == name)?,
+            None => self.spec.containers.first()?,
+        };
+        Some(container_id(&self.id, &container.name))
+    }
+
+    /// Total resource requests of the pod's containers.
+    pub fn requests(&self) -> ResourceRequirements {
+        self.spec
+            .containers
+            .iter()
+            .fold(ResourceRequirements::default(), |sum, c| {
+                ResourceRequirements {
+                    cpu_millis: sum.cpu_millis + c.resources.cpu_millis,
+                    memory_bytes: sum.memory_bytes + c.resources.memory_bytes,
+                }
+            })
+    }
+
+    /// Whether the pod should receive service traffic: Running, and passing
+    /// its readiness probe if any container has one.
+    pub fn is_ready(&self) -> bool {
+        self.status == PodStatus::Running
+            && (self.ready
+                || self
+                    .spec
+                    .containers
+                    .iter()
+                    .all(|c| c.readiness_probe.is_none()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POD: &str = r#"{
+        "id": "p1",
+        "name": "web-0",
+        "namespace": "default",
+        "spec": {
+            "containers": [{
+                "name": "web",
+                "image": "nginx",
+                "liveness_probe": { "http_get": { "port": 8080 }, "period_seconds": 5 },
+                "readiness_probe": { "exec": { "command": ["cat", "/tmp/ready"] } }
+            }]
+        },
+        "status": "Running",
+        "created_at": "2024-02-25T00:00:00Z"
+    }"#;
+
+    #[test]
+    fn test_probe_defaults_and_handlers() {
+        let pod: Pod = serde_json::from_str(POD).unwrap();
+        let container = &pod.spec.containers[0];
+
+        let liveness = container.liveness_probe.as_ref().unwrap();
+        assert_eq!(
+            liveness.handler,
+            ProbeHandler::HttpGet {
+                path: "/".to_string(),
+                port: 8080
+            }
+        );
+        assert_eq!(liveness.period_seconds, 5);
+        assert_eq!(liveness.initial_delay_seconds, 0);
+        assert_eq!(liveness.failure_threshold, 3);
+
+        let readiness = container.readiness_probe.as_ref().unwrap();
+        assert_eq!(readiness.period_seconds, 10);
+        assert_eq!(readiness.timeout_seconds, 1);
+
+        let json = serde_json::to_value(readiness).unwrap();
+        assert_eq!(json["exec"]["command"][1], "/tmp/ready");
+        let back: Probe = serde_json::from_value(json).unwrap();
+        assert_eq!(&back, readiness);
+    }
+
+    #[test]
+    fn test_is_ready_requires_readiness_probe_to_pass() {
+        let mut pod: Pod = serde_json::from_str(POD).unwrap();
+        assert!(!pod.ready);
+        assert!(!pod.is_ready());
+
+        pod.ready = true;
+        assert!(pod.is_ready());
+        pod.status = PodStatus::Terminating;
+        assert!(!pod.is_ready());
+
+        // Without a readiness probe a Running pod is ready as soon as it starts.
+        pod.status = PodStatus::Running;
+        pod.ready = false;
+        pod.spec.containers[0].readiness_probe = None;
+        assert!(pod.is_ready());
+    }
+
+    #[test]
+    fn test_display_status_shows_init_progress() {
+        let mut pod: Pod = serde_json::from_str(POD).unwrap();
+        assert!(pod.spec.init_containers.is_empty());
+
+        pod.status = PodStatus::ContainerCreating;
+        pod.status_message = Some(format!("{}1/2", INIT_STATUS_PREFIX));
+        assert_eq!(pod.display_status(), "Init:1/2");
+
+        // A stale message doesn't hide a later phase.
+        pod.status = PodStatus::Failed;
+        assert_eq!(pod.display_status(), "Failed");
+
+        pod.status_message = Some(format!(
+            "{}: container app exceeded its memory limit",
+            OOM_KILLED
+        ));
+        assert_eq!(pod.display_status(), "OOMKilled");
+    }
+
+    fn status(name: &str, state: ContainerState) -> ContainerStatus {
+        ContainerStatus {
+            name: name.to_string(),
+            image: "nginx".to_string(),
+            container_id: Some(container_id("p1", name)),
+            state,
+            restart_count: 0,
+            ready: false,
+        }
+    }
+
+    #[test]
+    fn test_status_from_containers() {
+        let now = Utc::now();
+        let running = || ContainerState::Running { started_at: now };
+        let exited = |code| ContainerState::Terminated {
+            exit_code: Some(code),
+            reason: if code == 0 { "Completed" } else { "Error" }.to_string(),
+            finished_at: now,
+        };
+        let waiting = ContainerState::Waiting {
+            reason: "ImagePullBackOff".to_string(),
+            message: None,
+        };
+        let from = |policy, states: Vec<ContainerState>| {
+            let statuses: Vec<_> = states.into_iter().map(|s| status("c", s)).collect();
+            PodStatus::from_containers(policy, &statuses)
+        };
+
+        assert_eq!(from(RestartPolicy::Always, vec![]), None);
+        assert_eq!(
+            from(RestartPolicy::Never, vec![running(), waiting.clone()]),
+            Some(PodStatus::ContainerCreating)
+        );
+        assert_eq!(
+            from(RestartPolicy::Always, vec![running(), running()]),
+            Some(PodStatus::Running)
+        );
+
+        // One failed container of two: restarted unless the policy is Never.
+        for (policy, expected) in [
+            (RestartPolicy::Always, PodStatus::Running),
+            (RestartPolicy::OnFailure, PodStatus::Running),
+            (RestartPolicy::Never, PodStatus::Failed),
+        ] {
+            assert_eq!(from(policy, vec![running(), exited(1)]), Some(expected));
+        }
+        // An unknown exit code is a failure.
+        let unknown = ContainerState::Terminated {
+            exit_code: None,
+            reason: "Error".to_string(),
+            finished_at: now,
+        };
+        assert_eq!(
+            from(RestartPolicy::Never, vec![unknown]),
+            Some(PodStatus::Failed)
+        );
+
+        // Everything completed.
+        for (policy, expected) in [
+            (RestartPolicy::Always, PodStatus::Running),
+            (RestartPolicy::OnFailure, PodStatus::Succeeded),
+            (RestartPolicy::Never, PodStatus::Succeeded),
+        ] {
+            assert_eq!(from(policy, vec![exited(0), exited(0)]), Some(expected));
+        }
+        assert_eq!(
+            from(RestartPolicy::OnFailure, vec![exited(0), running()]),
+            Some(PodStatus::Running)
+        );
+    }
+
+    #[test]
+    fn test_container_statuses_serialization() {
+        // Pods stored before container statuses were reported.
+        let mut pod: Pod = serde_json::from_str(POD).unwrap();
+        assert!(pod.container_statuses.is_empty());
+        assert_eq!(pod.ready_containers(), (0, 1));
+        pod.ready = true;
+        assert_eq!(pod.ready_containers(), (1, 1));
+
+        let terminated = status(
+            "web",
+            ContainerState::Terminated {
+                exit_code: Some(137),
+                reason: "OOMKilled".to_string(),
+                finished_at: "2024-02-25T00:01:00Z".parse().unwrap(),
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(&terminated).unwrap()["state"],
+            serde_json::json!({ "terminated": {
+                "exit_code": 137,
+                "reason": "OOMKilled",
+                "finished_at": "2024-02-25T00:01:00Z"
+            }})
+        );
+        pod.container_statuses = vec![terminated];
+        let json = serde_json::to_string(&pod).unwrap();
+        let back: Pod = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.container_statuses, pod.container_statuses);
+        assert_eq!(back.ready_containers(), (0, 1));
+
+        // Updates from older agents leave the stored statuses alone.
+        let update: PodStatusUpdate =
+            serde_json::from_str(r#"{"status": "Running", "ready": true}"#).unwrap();
+        assert!(matches!(
+            update,
+            PodStatusUpdate::Detailed {
+                container_statuses: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_security_context_validation() {
+        let mut pod: Pod = serde_json::from_str(POD).unwrap();
+        assert_eq!(
+            pod.spec.containers[0].security_context,
+            SecurityContext::default()
+        );
+        assert!(pod.spec.validate_security_contexts().is_ok());
+
+        let sc = &mut pod.spec.containers[0].security_context;
+        sc.run_as_user = Some(101);
+        sc.run_as_non_root = true;
+        sc.capabilities.drop = vec!["ALL".to_string()];
+        sc.capabilities.add = vec!["net_bind_service".to_string()];
+        assert!(pod.spec.validate_security_contexts().is_ok());
+
+        let sc = &mut pod.spec.containers[0].security_context;
+        sc.privileged = true;
+        sc.run_as_user = Some(0);
+        sc.capabilities.add.push("ALL".to_string());
+        sc.capabilities.drop.push("NET-RAW".to_string());
+        let fields: Vec<String> = pod
+            .spec
+            .validate_security_contexts()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "spec.containers[0].security_context.run_as_non_root",
+                "spec.containers[0].security_context.run_as_user",
+                "spec.containers[0].security_context.capabilities.add[1]",
+                "spec.containers[0].security_context.capabilities.drop[1]",
+            ]
+        );
+
+        assert_eq!(Capabilities::normalize("net_admin"), "CAP_NET_ADMIN");
+        assert_eq!(Capabilities::normalize("CAP_KILL"), "CAP_KILL");
+        assert_eq!(Capabilities::normalize("all"), "ALL");
+    }
+
+    #[test]
+    fn test_container_ids_derive_from_pod_id_and_name() {
+        let mut pod: Pod = serde_json::from_str(POD).unwrap();
+        let mut sidecar = pod.spec.containers[0].clone();
+        sidecar.name = "log-shipper".to_string();
+        pod.spec.containers.push(sidecar);
+
+        assert_eq!(container_id("p1", "web"), "p1-web");
+        assert_eq!(pod.container_ids(), ["p1-web", "p1-log-shipper"]);
+        assert_eq!(pod.select_container_id(None).as_deref(), Some("p1-web"));
+        assert_eq!(
+            pod.select_container_id(Some("log-shipper")).as_deref(),
+            Some("p1-log-shipper")
+        );
+        assert_eq!(pod.select_container_id(Some("missing")), None);
+    }
 }
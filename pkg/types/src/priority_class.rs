@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Cluster-scoped mapping from a priority class name to a scheduling priority.
+///
+/// Pods reference a class via `PodSpec::priority_class_name`; the API server
+/// resolves the name into `PodSpec::priority` at admission time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityClass {
+    pub name: String,
+    /// Higher values are scheduled first.
+    pub value: i32,
+    /// If true, pods without a `priority_class_name` receive this class's value.
+    #[serde(default)]
+    pub global_default: bool,
+    #[serde(default)]
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
+}
@@ -1,3 +1,4 @@
+use crate::pod::{Pod, PodStatus};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -31,3 +32,138 @@ pub struct QuotaUsage {
     pub cpu_millis: u64,
     pub memory_bytes: u64,
 }
+
+impl QuotaUsage {
+    /// Usage of `pods`. Pods that have finished or are being deleted no
+    /// longer count against a quota.
+    pub fn of<'a>(pods: impl IntoIterator<Item = &'a Pod>) -> Self {
+        let mut usage = Self::default();
+        for pod in pods {
+            if !matches!(
+                pod.status,
+                PodStatus::Succeeded | PodStatus::Failed | PodStatus::Terminating
+            ) {
+                usage.add(pod);
+            }
+        }
+        usage
+    }
+
+    /// Add `pod` and its requests.
+    pub fn add(&mut self, pod: &Pod) {
+        let requests = pod.requests();
+        self.pods += 1;
+        self.cpu_millis += requests.cpu_millis;
+        self.memory_bytes += requests.memory_bytes;
+    }
+}
+
+impl ResourceQuota {
+    /// The first hard limit `usage` is over, as `"pods=11 > 10"`. Usage
+    /// exactly at a limit is allowed.
+    pub fn exceeded(&self, usage: &QuotaUsage) -> Option<String> {
+        if let Some(max) = self.hard.max_pods
+            && usage.pods > max
+        {
+            return Some(format!("pods={} > {}", usage.pods, max));
+        }
+        if let Some(max) = self.hard.max_cpu_millis
+            && usage.cpu_millis > max
+        {
+            return Some(format!("cpu={}m > {}m", usage.cpu_millis, max));
+        }
+        if let Some(max) = self.hard.max_memory_bytes
+            && usage.memory_bytes > max
+        {
+            return Some(format!("memory={} > {}", usage.memory_bytes, max));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pod(status: PodStatus, containers: &[(u64, u64)]) -> Pod {
+        let containers: Vec<serde_json::Value> = containers
+            .iter()
+            .enumerate()
+            .map(|(i, (cpu, mem))| {
+                serde_json::json!({
+                    "name": format!("c{}", i),
+                    "image": "nginx",
+                    "resources": { "cpu_millis": cpu, "memory_bytes": mem },
+                })
+            })
+            .collect();
+        let mut pod: Pod = serde_json::from_value(serde_json::json!({
+            "id": "p1",
+            "name": "web",
+            "namespace": "default",
+            "spec": { "containers": containers },
+            "status": "Pending",
+            "created_at": "2026-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        pod.status = status;
+        pod
+    }
+
+    fn quota(max_pods: Option<u32>, max_cpu: Option<u64>, max_mem: Option<u64>) -> ResourceQuota {
+        ResourceQuota {
+            name: "compute".to_string(),
+            namespace: "default".to_string(),
+            hard: QuotaLimits {
+                max_pods,
+                max_cpu_millis: max_cpu,
+                max_memory_bytes: max_mem,
+            },
+            used: QuotaUsage::default(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_usage_sums_containers_of_live_pods() {
+        let pods = [
+            pod(PodStatus::Running, &[(100, 64), (250, 128)]),
+            pod(PodStatus::Pending, &[(50, 32)]),
+            pod(PodStatus::Succeeded, &[(1000, 1024)]),
+            pod(PodStatus::Terminating, &[(1000, 1024)]),
+        ];
+        let usage = QuotaUsage::of(&pods);
+        assert_eq!(usage.pods, 2);
+        assert_eq!(usage.cpu_millis, 400);
+        assert_eq!(usage.memory_bytes, 224);
+    }
+
+    #[test]
+    fn test_exceeded_each_dimension_at_boundary() {
+        let at = QuotaUsage {
+            pods: 10,
+            cpu_millis: 2000,
+            memory_bytes: 1 << 30,
+        };
+        let q = quota(Some(10), Some(2000), Some(1 << 30));
+        assert_eq!(q.exceeded(&at), None);
+
+        let over = |f: fn(&mut QuotaUsage)| {
+            let mut usage = at.clone();
+            f(&mut usage);
+            q.exceeded(&usage)
+        };
+        assert_eq!(over(|u| u.pods += 1).as_deref(), Some("pods=11 > 10"));
+        assert_eq!(
+            over(|u| u.cpu_millis += 1).as_deref(),
+            Some("cpu=2001m > 2000m")
+        );
+        assert_eq!(
+            over(|u| u.memory_bytes += 1).as_deref(),
+            Some("memory=1073741825 > 1073741824")
+        );
+
+        // Unset limits are unbounded.
+        assert_eq!(quota(None, None, None).exceeded(&at), None);
+    }
+}
@@ -1,4 +1,7 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
 // --- Policy rules ---
 
@@ -46,3 +49,304 @@ pub struct RoleBinding {
     pub role_ref: String,
     pub subjects: Vec<Subject>,
 }
+
+impl PolicyRule {
+    /// A rule over all API groups.
+    pub fn new(verbs: &[&str], resources: &[&str]) -> Self {
+        Self {
+            api_groups: vec!["*".to_string()],
+            resources: resources.iter().map(|r| r.to_string()).collect(),
+            verbs: verbs.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    /// Whether the rule grants `verb` on `resource`. A subresource such as
+    /// `pods/exec` has to be named (or covered by `*`); granting `pods` does
+    /// not grant its subresources.
+    pub fn allows(&self, verb: &str, resource: &str) -> bool {
+        let matches = |list: &[String], value: &str| list.iter().any(|v| v == "*" || v == value);
+        matches(&self.verbs, verb) && matches(&self.resources, resource)
+    }
+}
+
+// --- API tokens ---
+
+/// Namespaced resources and subresources a read-only token may read.
+const READABLE_NAMESPACED: &[&str] = &[
+    "pods",
+    "pods/logs",
+    "services",
+    "deployments",
+    "deployments/revisions",
+    "configmaps",
+    "endpoints",
+    "ingresses",
+    "replicasets",
+    "daemonsets",
+    "jobs",
+    "cronjobs",
+    "hpa",
+    "resourcequotas",
+    "limitranges",
+    "poddisruptionbudgets",
+    "networkpolicies",
+    "pvcs",
+    "events",
+    "namespaces",
+    "podmetrics",
+];
+
+/// Cluster-scoped resources a cluster-wide read-only token may read.
+const READABLE_CLUSTER: &[&str] = &[
+    "nodes",
+    "nodes/pods",
+    "persistentvolumes",
+    "priorityclasses",
+    "vpcs",
+    "vpc-peerings",
+    "images",
+    "processes",
+    "runtime",
+    "nodemetrics",
+];
+
+/// What a namespace editor may change on top of reading.
+const EDITABLE: &[&str] = &[
+    "pods",
+    "pods/archive",
+    "pods/exec",
+    "pods/eviction",
+    "pods/portforward",
+    "services",
+    "deployments",
+    "deployments/rollback",
+    "deployments/scale",
+    "configmaps",
+    "secrets",
+    "ingresses",
+    "replicasets",
+    "replicasets/scale",
+    "daemonsets",
+    "jobs",
+    "cronjobs",
+    "hpa",
+    "resourcequotas",
+    "limitranges",
+    "poddisruptionbudgets",
+    "networkpolicies",
+    "pvcs",
+];
+
+/// Built-in role an API token is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenRole {
+    /// Everything, cluster-wide.
+    Admin,
+    /// Reads everything except secrets and tokens.
+    Readonly,
+    /// Reads and writes workloads and their config; always namespace-scoped.
+    Editor,
+    /// What an agent needs: its heartbeat, pod status and the cluster state
+    /// it syncs. A node's agent token carries this role; the join token
+    /// only registers nodes.
+    Node,
+}
+
+impl TokenRole {
+    /// The rules the role grants. A namespace-scoped token additionally
+    /// only reaches objects in its namespace.
+    pub fn rules(self) -> Vec<PolicyRule> {
+        const READ: &[&str] = &["get", "watch"];
+        match self {
+            Self::Admin => vec![PolicyRule::new(&["*"], &["*"])],
+            Self::Readonly => vec![
+                PolicyRule::new(READ, READABLE_NAMESPACED),
+                PolicyRule::new(READ, READABLE_CLUSTER),
+            ],
+            Self::Editor => vec![
+                PolicyRule::new(READ, READABLE_NAMESPACED),
+                PolicyRule::new(READ, &["secrets"]),
+                PolicyRule::new(&["get", "watch", "create", "update", "delete"], EDITABLE),
+            ],
+            Self::Node => vec![
+                PolicyRule::new(
+                    READ,
+                    &[
+                        "nodes",
+                        "nodes/pods",
+                        "namespaces",
+                        "pods",
+                        "services",
+                        "endpoints",
+                        "ingresses",
+                        "networkpolicies",
+                        "configmaps",
+                        "secrets",
+                        "pvcs",
+                        "persistentvolumes",
+                        "vpcs",
+                        "vpc-peerings",
+                    ],
+                ),
+                PolicyRule::new(
+                    &["create", "update"],
+                    &["nodes/heartbeat", "nodes/images", "pods/status", "pods/vpc"],
+                ),
+                PolicyRule::new(&["get"], &["nodes/tunnel"]),
+                PolicyRule::new(&["delete"], &["pods"]),
+            ],
+        }
+    }
+
+    /// Whether the role grants `verb` on `resource`.
+    pub fn allows(self, verb: &str, resource: &str) -> bool {
+        self.rules().iter().any(|r| r.allows(verb, resource))
+    }
+}
+
+impl fmt::Display for TokenRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Admin => "admin",
+            Self::Readonly => "readonly",
+            Self::Editor => "editor",
+            Self::Node => "node",
+        })
+    }
+}
+
+impl FromStr for TokenRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(Self::Admin),
+            "readonly" => Ok(Self::Readonly),
+            "editor" => Ok(Self::Editor),
+            "node" => Ok(Self::Node),
+            other => Err(format!(
+                "unknown role '{}' (expected admin, readonly, editor or node)",
+                other
+            )),
+        }
+    }
+}
+
+/// A bearer token minted by the server, stored at `/registry/tokens/<id>`.
+/// Clients present it as `<id>.<secret>`; deleting the record revokes it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub role: TokenRole,
+    /// Namespace the token is confined to; `None` for cluster-wide.
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Only returned when the token is created.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiToken {
+    /// The value to send as `Authorization: Bearer <token>`.
+    pub fn bearer(&self) -> String {
+        format!("{}.{}", self.id, self.secret)
+    }
+}
+
+/// Prefix of the bearer token an agent presents for its node.
+const NODE_BEARER_PREFIX: &str = "node:";
+
+/// The bearer token an agent presents for node `node_name`: the agent token
+/// the node was issued at its latest registration, tagged with its name.
+pub fn node_bearer(node_name: &str, agent_token: &str) -> String {
+    format!("{}{}:{}", NODE_BEARER_PREFIX, node_name, agent_token)
+}
+
+/// Node name and agent token of a bearer token from [`node_bearer`].
+pub fn parse_node_bearer(token: &str) -> Option<(&str, &str)> {
+    token
+        .strip_prefix(NODE_BEARER_PREFIX)?
+        .rsplit_once(':')
+        .filter(|(node, secret)| !node.is_empty() && !secret.is_empty())
+}
+
+/// The token of an `Authorization: Bearer <token>` header value.
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+    authorization
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+}
+
+/// Compare secrets without stopping at the first differing byte.
+pub fn secrets_match(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Body of `POST /api/v1/tokens`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTokenRequest {
+    /// Defaults to the generated id.
+    #[serde(default)]
+    pub name: Option<String>,
+    pub role: TokenRole,
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_rules() {
+        assert!(TokenRole::Admin.allows("delete", "cluster"));
+        assert!(TokenRole::Readonly.allows("get", "pods"));
+        assert!(TokenRole::Readonly.allows("get", "pods/logs"));
+        assert!(!TokenRole::Readonly.allows("get", "secrets"));
+        assert!(!TokenRole::Readonly.allows("get", "pods/exec"));
+        assert!(!TokenRole::Readonly.allows("get", "pods/archive"));
+        assert!(TokenRole::Editor.allows("update", "pods/archive"));
+        assert!(!TokenRole::Readonly.allows("get", "pods/portforward"));
+        assert!(TokenRole::Editor.allows("get", "pods/portforward"));
+        assert!(!TokenRole::Readonly.allows("create", "pods"));
+        assert!(TokenRole::Editor.allows("update", "deployments/scale"));
+        assert!(!TokenRole::Editor.allows("delete", "namespaces"));
+        assert!(TokenRole::Node.allows("update", "pods/status"));
+        assert!(!TokenRole::Node.allows("update", "pods"));
+        assert!(!TokenRole::Node.allows("get", "tokens"));
+
+        assert_eq!("readonly".parse::<TokenRole>(), Ok(TokenRole::Readonly));
+        assert!("viewer".parse::<TokenRole>().is_err());
+        assert_eq!(
+            serde_json::to_value(TokenRole::Editor).unwrap(),
+            serde_json::json!("editor")
+        );
+    }
+
+    #[test]
+    fn test_node_bearer_round_trip() {
+        let token = node_bearer("worker-1", "abc123");
+        assert_eq!(parse_node_bearer(&token), Some(("worker-1", "abc123")));
+        assert_eq!(parse_node_bearer("node:worker-1"), None);
+        assert_eq!(parse_node_bearer("node::abc123"), None);
+        assert_eq!(parse_node_bearer("ci.abc123"), None);
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(bearer_token("Bearer abc123 "), Some("abc123"));
+        assert_eq!(bearer_token("Bearer  "), None);
+        assert_eq!(bearer_token("Basic abc123"), None);
+        assert!(secrets_match("abc123", "abc123"));
+        assert!(!secrets_match("abc123", "abc124"));
+        assert!(!secrets_match("abc123", "abc1234"));
+    }
+}
@@ -15,8 +15,14 @@ pub struct ReplicaSetStatus {
 
 // --- ReplicaSet spec ---
 
+fn default_replicas() -> u32 {
+    1
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplicaSetSpec {
+    /// Desired pod count; 1 when omitted. An explicit 0 is kept.
+    #[serde(default = "default_replicas")]
     pub replicas: u32,
     #[serde(default)]
     pub selector: HashMap<String, String>,
@@ -39,5 +45,15 @@ pub struct ReplicaSet {
     /// Template hash for tracking which spec version this RS represents
     #[serde(default)]
     pub template_hash: String,
+    /// Deployment revision: bumped each time this RS becomes the current one
+    #[serde(default)]
+    pub revision: u64,
+    /// Change cause of the deployment when this revision was created
+    #[serde(default)]
+    pub change_cause: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
 }
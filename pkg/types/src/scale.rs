@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Body of `PUT .../{deployments,replicasets}/{name}/scale`: sets only
+/// `spec.replicas`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleRequest {
+    /// Desired replica count. Signed so a negative value is rejected with a
+    /// message instead of a deserialization error.
+    pub replicas: i64,
+    /// Precondition: only scale if the object currently wants this many.
+    #[serde(default)]
+    pub current_replicas: Option<u32>,
+    /// Precondition: only scale if the object is still at this resource
+    /// version. Without it the server retries against concurrent writes.
+    #[serde(default)]
+    pub resource_version: Option<u64>,
+}
@@ -10,4 +10,8 @@ pub struct Secret {
     /// Secret data stored as base64-encoded values.
     pub data: HashMap<String, String>,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
 }
@@ -2,8 +2,9 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum ServiceType {
+    #[default]
     ClusterIP,
     NodePort,
     LoadBalancer,
@@ -19,10 +20,34 @@ impl std::fmt::Display for ServiceType {
     }
 }
 
+/// Transport protocol of a service port.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Protocol {
+    #[default]
+    TCP,
+    /// Accepted in manifests, but the service proxy only forwards TCP.
+    UDP,
+}
+
+impl std::fmt::Display for Protocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Protocol::TCP => write!(f, "TCP"),
+            Protocol::UDP => write!(f, "UDP"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServicePort {
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
+    pub protocol: Protocol,
     pub port: u16,
+    /// Container port traffic is forwarded to; 0 (unset) defaults to `port`
+    /// at admission.
+    #[serde(default)]
     pub target_port: u16,
     #[serde(default)]
     pub node_port: Option<u16>,
@@ -33,9 +58,25 @@ pub struct ServiceSpec {
     #[serde(default)]
     pub selector: HashMap<String, String>,
     pub ports: Vec<ServicePort>,
+    #[serde(default)]
     pub service_type: ServiceType,
+    /// "ClientIP" pins each client to one backend; unset or "None" balances
+    /// every connection.
+    #[serde(default)]
+    pub session_affinity: Option<String>,
+    /// How long an idle ClientIP pin lasts (default 10800).
+    #[serde(default)]
+    pub session_affinity_timeout_seconds: Option<u64>,
+    /// Path the service proxy probes with `GET` on each endpoint; unset
+    /// probes with a plain TCP connect.
+    #[serde(default)]
+    pub health_check_path: Option<String>,
 }
 
+/// `cluster_ip` value of a headless Service: no virtual IP is allocated and
+/// DNS answers with the ready endpoint addresses instead.
+pub const CLUSTER_IP_NONE: &str = "None";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Service {
     pub id: String,
@@ -47,4 +88,22 @@ pub struct Service {
     #[serde(default)]
     pub vpc: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Set by the state store on every write; send it back unchanged to
+    /// update the object.
+    #[serde(default)]
+    pub resource_version: u64,
+}
+
+impl Service {
+    pub fn is_headless(&self) -> bool {
+        self.cluster_ip.as_deref() == Some(CLUSTER_IP_NONE)
+    }
+
+    /// The virtual IP traffic to this Service is proxied through; `None`
+    /// until one is allocated, and always for headless Services.
+    pub fn virtual_ip(&self) -> Option<&str> {
+        self.cluster_ip
+            .as_deref()
+            .filter(|ip| *ip != CLUSTER_IP_NONE)
+    }
 }
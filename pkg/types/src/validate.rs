@@ -1,4 +1,28 @@
+//! Admission validation: the checks an object's own fields must pass before
+//! the API server stores it. Checks that need other objects (the namespace
+//! exists, the name is free) are made by the server.
+
+use crate::configmap::ConfigMap;
+use crate::cron::Schedule;
+use crate::daemonset::DaemonSet;
+use crate::deployment::Deployment;
+use crate::error::FieldError;
+use crate::hpa::HorizontalPodAutoscaler;
+use crate::job::{CronJob, Job, JobSpec};
+use crate::limit_range::LimitRange;
+use crate::namespace::Namespace;
+use crate::network_policy::{NetworkPolicy, NetworkPolicyPeer, NetworkPolicyPort};
+use crate::node::{Node, Taint};
+use crate::pdb::PodDisruptionBudget;
+use crate::pod::{ContainerSpec, Pod, PodSpec, ProbeHandler, ResourceRequirements};
+use crate::priority_class::PriorityClass;
+use crate::quota::ResourceQuota;
+use crate::replicaset::ReplicaSet;
+use crate::secret::Secret;
+use crate::service::Service;
+use crate::volume::PersistentVolumeClaim;
 use anyhow::{Result, bail};
+use std::collections::{HashMap, HashSet};
 
 /// Validate a Kubernetes-style resource name.
 /// Rules: lowercase `[a-z0-9-]`, max 63 chars, no leading/trailing hyphens.
@@ -24,6 +48,576 @@ pub fn validate_name(name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Longest container ID the runtime accepts. A container ID is
+/// `<pod id>-<container name>`; the longest the cluster makes belongs to a
+/// static pod, `static-<namespace>-<pod>-<container>` with three 63-character
+/// names (198 characters).
+pub const MAX_CONTAINER_ID_LEN: usize = 200;
+
+/// Validate an ID the container runtime builds file paths, socket names and
+/// command arguments from: a container ID, or the pod ID it begins with.
+/// Rules: lowercase `[a-z0-9-]`, max [`MAX_CONTAINER_ID_LEN`] chars, no
+/// leading/trailing hyphens — so it can never name another directory.
+pub fn validate_container_id(id: &str) -> Result<()> {
+    if id.is_empty() {
+        bail!("container ID must not be empty");
+    }
+    if id.len() > MAX_CONTAINER_ID_LEN {
+        bail!(
+            "container ID '{}' exceeds {} characters (got {})",
+            id,
+            MAX_CONTAINER_ID_LEN,
+            id.len()
+        );
+    }
+    if id.starts_with('-') || id.ends_with('-') {
+        bail!("container ID '{}' must not start or end with a hyphen", id);
+    }
+    if !id
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        bail!(
+            "container ID '{}' must contain only lowercase letters, digits, and hyphens [a-z0-9-]",
+            id
+        );
+    }
+    Ok(())
+}
+
+/// A resource whose fields can be checked at admission.
+pub trait Validate {
+    /// Every problem with the object, each with the path of its field.
+    /// Empty when the object is valid.
+    fn validate(&self) -> Vec<FieldError>;
+}
+
+/// A DNS-1123 label (see [`validate_name`]) at `path`.
+fn name(path: &str, value: &str, errors: &mut Vec<FieldError>) {
+    if let Err(e) = validate_name(value) {
+        errors.push(FieldError::new(path, e.to_string()));
+    }
+}
+
+/// A port number at `path`; zero is the only invalid `u16`.
+fn port(path: &str, value: u16, errors: &mut Vec<FieldError>) {
+    if value == 0 {
+        errors.push(FieldError::new(path, "must be between 1 and 65535"));
+    }
+}
+
+/// Label keys and values, as used on objects and in selectors. A key is an
+/// optional DNS subdomain prefix and `/`, then a name of up to 63
+/// alphanumerics, `-`, `_` and `.`, beginning and ending alphanumeric. A
+/// value is empty or such a name.
+fn labels(path: &str, labels: &HashMap<String, String>, errors: &mut Vec<FieldError>) {
+    let mut keys: Vec<_> = labels.keys().collect();
+    keys.sort();
+    for key in keys {
+        let at = format!("{}.{}", path, key);
+        label_key(&at, key, "label", errors);
+        label_value(&at, &labels[key], "label", errors);
+    }
+}
+
+/// A label-style key at `path`; `what` names it in the message.
+fn label_key(path: &str, key: &str, what: &str, errors: &mut Vec<FieldError>) {
+    let (prefix, label) = match key.split_once('/') {
+        Some((prefix, label)) => (Some(prefix), label),
+        None => (None, key),
+    };
+    let valid_prefix = prefix.is_none_or(|p| {
+        !p.is_empty() && p.len() <= 253 && p.split('.').all(|part| validate_name(part).is_ok())
+    });
+    if !valid_prefix || label.is_empty() || !label_name(label) {
+        errors.push(FieldError::new(
+            path,
+            format!("invalid {} key '{}'", what, key),
+        ));
+    }
+}
+
+/// A label-style value at `path`; `what` names it in the message.
+fn label_value(path: &str, value: &str, what: &str, errors: &mut Vec<FieldError>) {
+    if !value.is_empty() && !label_name(value) {
+        errors.push(FieldError::new(
+            path,
+            format!("invalid {} value '{}'", what, value),
+        ));
+    }
+}
+
+fn label_name(s: &str) -> bool {
+    s.len() <= 63
+        && s.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && s.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// ConfigMap and Secret keys: up to 253 alphanumerics, `-`, `_` and `.`.
+fn data_keys(data: &HashMap<String, String>, errors: &mut Vec<FieldError>) {
+    let mut keys: Vec<_> = data.keys().collect();
+    keys.sort();
+    for key in keys {
+        let valid = !key.is_empty()
+            && key.len() <= 253
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        if !valid {
+            errors.push(FieldError::new(
+                format!("data.{}", key),
+                format!("invalid key '{}'", key),
+            ));
+        }
+    }
+}
+
+/// Requirements `high` that must not be below `low`, each given with its
+/// field name under `path`. Zero is unset and never compared.
+fn not_below(
+    path: &str,
+    low: (&str, &ResourceRequirements),
+    high: (&str, &ResourceRequirements),
+    errors: &mut Vec<FieldError>,
+) {
+    let pairs = [
+        ("cpu_millis", low.1.cpu_millis, high.1.cpu_millis),
+        ("memory_bytes", low.1.memory_bytes, high.1.memory_bytes),
+    ];
+    for (field, lo, hi) in pairs {
+        if lo > 0 && hi > 0 && hi < lo {
+            let at = match path {
+                "" => format!("{}.{}", high.0, field),
+                _ => format!("{}.{}.{}", path, high.0, field),
+            };
+            errors.push(FieldError::new(
+                at,
+                format!("{} must not be below {} ({})", hi, low.0, lo),
+            ));
+        }
+    }
+}
+
+fn container(path: &str, c: &ContainerSpec, volumes: &HashSet<&str>, errors: &mut Vec<FieldError>) {
+    name(&format!("{}.name", path), &c.name, errors);
+    if c.image.trim().is_empty() {
+        errors.push(FieldError::new(
+            format!("{}.image", path),
+            "must not be empty",
+        ));
+    }
+    not_below(
+        path,
+        ("resources", &c.resources),
+        ("limits", &c.limits),
+        errors,
+    );
+    let probes = [
+        ("liveness_probe", &c.liveness_probe),
+        ("readiness_probe", &c.readiness_probe),
+    ];
+    for (field, probe) in probes {
+        let probe_port = match probe.as_ref().map(|p| &p.handler) {
+            Some(ProbeHandler::HttpGet { port, .. } | ProbeHandler::TcpSocket { port }) => *port,
+            _ => continue,
+        };
+        port(&format!("{}.{}.port", path, field), probe_port, errors);
+    }
+    for (i, mount) in c.volume_mounts.iter().enumerate() {
+        let at = format!("{}.volume_mounts[{}]", path, i);
+        if !volumes.contains(mount.name.as_str()) {
+            errors.push(FieldError::new(
+                format!("{}.name", at),
+                format!("no volume named '{}' in the pod", mount.name),
+            ));
+        }
+        if !mount.mount_path.starts_with('/') {
+            errors.push(FieldError::new(
+                format!("{}.mount_path", at),
+                "must be an absolute path",
+            ));
+        }
+    }
+}
+
+/// A pod spec at `path`, as in a Pod or a workload's pod template.
+fn pod_spec(path: &str, spec: &PodSpec, errors: &mut Vec<FieldError>) {
+    if spec.containers.is_empty() {
+        errors.push(FieldError::new(
+            format!("{}.containers", path),
+            "must have at least one container",
+        ));
+    }
+    let mut volumes = HashSet::new();
+    for (i, volume) in spec.volumes.iter().enumerate() {
+        let at = format!("{}.volumes[{}].name", path, i);
+        name(&at, &volume.name, errors);
+        if !volumes.insert(volume.name.as_str()) {
+            errors.push(FieldError::new(
+                at,
+                format!("duplicate name '{}'", volume.name),
+            ));
+        }
+    }
+    // Init and app containers share one namespace of names.
+    let mut names = HashSet::new();
+    let all = [
+        ("init_containers", &spec.init_containers),
+        ("containers", &spec.containers),
+    ];
+    for (list, containers) in all {
+        for (i, c) in containers.iter().enumerate() {
+            let at = format!("{}.{}[{}]", path, list, i);
+            container(&at, c, &volumes, errors);
+            if !names.insert(c.name.as_str()) {
+                errors.push(FieldError::new(
+                    format!("{}.name", at),
+                    format!("duplicate name '{}'", c.name),
+                ));
+            }
+        }
+    }
+    spec.check_security_contexts(path, errors);
+    labels(
+        &format!("{}.node_affinity", path),
+        &spec.node_affinity,
+        errors,
+    );
+    for (i, selector) in spec.pod_anti_affinity.iter().enumerate() {
+        labels(
+            &format!("{}.pod_anti_affinity[{}].match_labels", path, i),
+            &selector.match_labels,
+            errors,
+        );
+    }
+    for (i, secret) in spec.image_pull_secrets.iter().enumerate() {
+        name(
+            &format!("{}.image_pull_secrets[{}]", path, i),
+            secret,
+            errors,
+        );
+    }
+}
+
+/// A ReplicaSet-style selector: the labels its pods are created with, so an
+/// empty one would claim every pod in the namespace.
+fn workload_selector(selector: &HashMap<String, String>, errors: &mut Vec<FieldError>) {
+    if selector.is_empty() {
+        errors.push(FieldError::new(
+            "spec.selector",
+            "must have at least one label",
+        ));
+    }
+    labels("spec.selector", selector, errors);
+}
+
+fn job_spec(path: &str, spec: &JobSpec, errors: &mut Vec<FieldError>) {
+    let counts = [
+        ("completions", spec.completions),
+        ("parallelism", spec.parallelism),
+    ];
+    for (field, count) in counts {
+        if count == 0 {
+            errors.push(FieldError::new(
+                format!("{}.{}", path, field),
+                "must be at least 1",
+            ));
+        }
+    }
+    pod_spec(&format!("{}.template", path), &spec.template, errors);
+}
+
+impl Validate for Namespace {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        labels("labels", &self.labels, &mut errors);
+        errors
+    }
+}
+
+impl Validate for Pod {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        // Assigned by the server; one sent in is checked all the same.
+        if !self.id.is_empty()
+            && let Err(e) = validate_container_id(&self.id)
+        {
+            errors.push(FieldError::new("id", e.to_string()));
+        }
+        name("name", &self.name, &mut errors);
+        labels("labels", &self.labels, &mut errors);
+        pod_spec("spec", &self.spec, &mut errors);
+        errors
+    }
+}
+
+impl Validate for Service {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        labels("spec.selector", &self.spec.selector, &mut errors);
+        let mut seen = HashSet::new();
+        for (i, p) in self.spec.ports.iter().enumerate() {
+            let at = format!("spec.ports[{}]", i);
+            port(&format!("{}.port", at), p.port, &mut errors);
+            port(&format!("{}.target_port", at), p.target_port, &mut errors);
+            if p.port != 0 && !seen.insert(p.port) {
+                errors.push(FieldError::new(
+                    format!("{}.port", at),
+                    format!("duplicate port {}", p.port),
+                ));
+            }
+        }
+        errors
+    }
+}
+
+impl Validate for Deployment {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        labels("labels", &self.labels, &mut errors);
+        workload_selector(&self.spec.selector, &mut errors);
+        pod_spec("spec.template", &self.spec.template, &mut errors);
+        errors
+    }
+}
+
+impl Validate for ReplicaSet {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        workload_selector(&self.spec.selector, &mut errors);
+        pod_spec("spec.template", &self.spec.template, &mut errors);
+        errors
+    }
+}
+
+impl Validate for DaemonSet {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        labels("spec.node_selector", &self.spec.node_selector, &mut errors);
+        pod_spec("spec.template", &self.spec.template, &mut errors);
+        errors
+    }
+}
+
+impl Validate for Job {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        job_spec("spec", &self.spec, &mut errors);
+        errors
+    }
+}
+
+impl Validate for CronJob {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        if let Err(e) = Schedule::parse(&self.spec.schedule) {
+            errors.push(FieldError::new("spec.schedule", e));
+        }
+        job_spec("spec.job_template", &self.spec.job_template, &mut errors);
+        errors
+    }
+}
+
+impl Validate for HorizontalPodAutoscaler {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        name(
+            "spec.target_deployment",
+            &self.spec.target_deployment,
+            &mut errors,
+        );
+        if self.spec.min_replicas == 0 {
+            errors.push(FieldError::new("spec.min_replicas", "must be at least 1"));
+        }
+        if self.spec.max_replicas < self.spec.min_replicas {
+            errors.push(FieldError::new(
+                "spec.max_replicas",
+                format!(
+                    "{} must not be below min_replicas ({})",
+                    self.spec.max_replicas, self.spec.min_replicas
+                ),
+            ));
+        }
+        let targets = [
+            (
+                "cpu_utilization_percent",
+                self.spec.metrics.cpu_utilization_percent,
+            ),
+            (
+                "memory_utilization_percent",
+                self.spec.metrics.memory_utilization_percent,
+            ),
+        ];
+        for (field, target) in targets {
+            if target == Some(0) {
+                errors.push(FieldError::new(
+                    format!("spec.metrics.{}", field),
+                    "must be at least 1",
+                ));
+            }
+        }
+        errors
+    }
+}
+
+impl Validate for ConfigMap {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        data_keys(&self.data, &mut errors);
+        errors
+    }
+}
+
+impl Validate for Secret {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        data_keys(&self.data, &mut errors);
+        errors
+    }
+}
+
+impl Validate for LimitRange {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        // A range whose bounds cross admits no container at all.
+        not_below("", ("min", &self.min), ("max", &self.max), &mut errors);
+        not_below(
+            "",
+            ("default_request", &self.default_request),
+            ("default_limit", &self.default_limit),
+            &mut errors,
+        );
+        errors
+    }
+}
+
+impl Validate for PodDisruptionBudget {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        labels(
+            "selector.match_labels",
+            &self.selector.match_labels,
+            &mut errors,
+        );
+        if self.min_available.is_some() && self.max_unavailable.is_some() {
+            errors.push(FieldError::new(
+                "max_unavailable",
+                "only one of min_available and max_unavailable may be set",
+            ));
+        }
+        errors
+    }
+}
+
+impl Validate for ResourceQuota {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        errors
+    }
+}
+
+impl Validate for PriorityClass {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        errors
+    }
+}
+
+impl Validate for NetworkPolicy {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        labels("pod_selector", &self.pod_selector, &mut errors);
+        let peers = |path: String, peers: &[NetworkPolicyPeer], errors: &mut Vec<FieldError>| {
+            for (i, peer) in peers.iter().enumerate() {
+                let selectors = [
+                    ("pod_selector", &peer.pod_selector),
+                    ("namespace_selector", &peer.namespace_selector),
+                ];
+                for (field, selector) in selectors {
+                    if let Some(selector) = selector {
+                        labels(&format!("{}[{}].{}", path, i, field), selector, errors);
+                    }
+                }
+            }
+        };
+        let ports = |path: String, ports: &[NetworkPolicyPort], errors: &mut Vec<FieldError>| {
+            for (i, p) in ports.iter().enumerate() {
+                if let Some(p) = p.port {
+                    port(&format!("{}[{}].port", path, i), p, errors);
+                }
+            }
+        };
+        for (i, rule) in self.ingress.iter().enumerate() {
+            peers(format!("ingress[{}].from", i), &rule.from, &mut errors);
+            ports(format!("ingress[{}].ports", i), &rule.ports, &mut errors);
+        }
+        for (i, rule) in self.egress.iter().enumerate() {
+            peers(format!("egress[{}].to", i), &rule.to, &mut errors);
+            ports(format!("egress[{}].ports", i), &rule.ports, &mut errors);
+        }
+        errors
+    }
+}
+
+impl Validate for PersistentVolumeClaim {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        name("name", &self.name, &mut errors);
+        if self.requested_bytes == 0 {
+            errors.push(FieldError::new("requested_bytes", "must be greater than 0"));
+        }
+        errors
+    }
+}
+
+/// Taint keys and values follow the label rules.
+impl Validate for Taint {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        label_key("key", &self.key, "taint", &mut errors);
+        label_value("value", &self.value, "taint", &mut errors);
+        errors
+    }
+}
+
+/// The fields that can change after registration: labels and taints.
+impl Validate for Node {
+    fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        labels("labels", &self.labels, &mut errors);
+        let mut seen = HashSet::new();
+        for (i, taint) in self.taints.iter().enumerate() {
+            let at = format!("taints[{}]", i);
+            label_key(&format!("{}.key", at), &taint.key, "taint", &mut errors);
+            label_value(&format!("{}.value", at), &taint.value, "taint", &mut errors);
+            if !seen.insert((&taint.key, taint.effect)) {
+                errors.push(FieldError::new(
+                    at,
+                    format!("duplicate taint {}:{}", taint.key, taint.effect),
+                ));
+            }
+        }
+        errors
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -47,4 +641,367 @@ mod tests {
         assert!(validate_name("special!char").is_err());
         assert!(validate_name(&"a".repeat(64)).is_err());
     }
+
+    #[test]
+    fn test_container_ids() {
+        let uuid = "6f1c1f4e-2b7a-4c8e-9a55-0d3c2f1e8b90";
+        assert!(validate_container_id(uuid).is_ok());
+        assert!(validate_container_id(&format!("{}-app", uuid)).is_ok());
+        assert!(validate_container_id(&format!("{}-init-0", uuid)).is_ok());
+        let longest = format!(
+            "static-{}-{}-{}",
+            "n".repeat(63),
+            "p".repeat(63),
+            "c".repeat(63)
+        );
+        assert!(validate_container_id(&longest).is_ok());
+
+        assert!(validate_container_id("").is_err());
+        assert!(validate_container_id(&"a".repeat(MAX_CONTAINER_ID_LEN + 1)).is_err());
+        assert!(validate_container_id("-app").is_err());
+        assert!(validate_container_id("app-").is_err());
+        assert!(validate_container_id("App").is_err());
+        assert!(validate_container_id("my_app").is_err());
+        assert!(validate_container_id("my app").is_err());
+        assert!(validate_container_id("my-app\n").is_err());
+    }
+
+    #[test]
+    fn test_container_id_traversal() {
+        for id in [
+            "..",
+            "../../etc",
+            "pod/../../root",
+            "/etc/passwd",
+            "pod.sock",
+        ] {
+            let err = validate_container_id(id).unwrap_err();
+            assert!(err.to_string().contains("[a-z0-9-]"), "{}: {}", id, err);
+        }
+    }
+
+    /// The fields `errors` points at, in order.
+    fn fields(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().map(|e| e.field).collect()
+    }
+
+    /// `base` with the YAML `patch` (if any) merged over it, as `T`.
+    /// Mappings merge key by key unless the patch's is empty; anything else
+    /// is replaced.
+    fn manifest<T: serde::de::DeserializeOwned>(base: &str, patch: &str) -> T {
+        let mut value: serde_yaml::Value = serde_yaml::from_str(base).unwrap();
+        if !patch.is_empty() {
+            merge(&mut value, serde_yaml::from_str(patch).unwrap());
+        }
+        serde_yaml::from_value(value).unwrap()
+    }
+
+    fn merge(into: &mut serde_yaml::Value, patch: serde_yaml::Value) {
+        match (into, patch) {
+            (serde_yaml::Value::Mapping(into), serde_yaml::Value::Mapping(patch))
+                if !patch.is_empty() =>
+            {
+                for (k, v) in patch {
+                    match into.get_mut(&k) {
+                        Some(slot) => merge(slot, v),
+                        None => {
+                            into.insert(k, v);
+                        }
+                    }
+                }
+            }
+            (into, patch) => *into = patch,
+        }
+    }
+
+    const POD: &str = r#"
+id: ""
+name: web
+namespace: default
+status: Pending
+created_at: "2024-02-25T00:00:00Z"
+labels: { app: web }
+spec:
+  containers:
+    - name: app
+      image: nginx
+"#;
+
+    #[test]
+    fn test_pod_validation() {
+        assert!(manifest::<Pod>(POD, "").validate().is_empty());
+
+        let cases = [
+            (r#"name: Web_1"#, vec!["name"]),
+            (r#"id: "../../etc""#, vec!["id"]),
+            (
+                r#"labels: { "-app": "x y" }"#,
+                vec!["labels.-app", "labels.-app"],
+            ),
+            (r#"spec: { containers: [] }"#, vec!["spec.containers"]),
+            (
+                r#"spec:
+  containers:
+    - { name: app, image: "" }
+    - { name: app, image: busybox }"#,
+                vec!["spec.containers[0].image", "spec.containers[1].name"],
+            ),
+            (
+                r#"spec:
+  init_containers: [{ name: app, image: busybox }]"#,
+                vec!["spec.containers[0].name"],
+            ),
+            (
+                r#"spec:
+  containers:
+    - name: app
+      image: nginx
+      resources: { cpu_millis: 500 }
+      limits: { cpu_millis: 250 }
+      readiness_probe: { tcp_socket: { port: 0 } }
+      volume_mounts: [{ name: data, mount_path: data }]"#,
+                vec![
+                    "spec.containers[0].limits.cpu_millis",
+                    "spec.containers[0].readiness_probe.port",
+                    "spec.containers[0].volume_mounts[0].name",
+                    "spec.containers[0].volume_mounts[0].mount_path",
+                ],
+            ),
+            (
+                r#"spec:
+  containers:
+    - name: app
+      image: nginx
+      security_context: { privileged: true, run_as_non_root: true }"#,
+                vec!["spec.containers[0].security_context.run_as_non_root"],
+            ),
+        ];
+        for (patch, expected) in cases {
+            let pod: Pod = manifest(POD, patch);
+            assert_eq!(fields(pod.validate()), expected, "patch: {}", patch);
+        }
+    }
+
+    const SERVICE: &str = r#"
+id: ""
+name: web
+namespace: default
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  selector: { app: web }
+  service_type: ClusterIP
+  ports: [{ name: http, port: 80, target_port: 8080 }]
+"#;
+
+    #[test]
+    fn test_service_validation() {
+        assert!(manifest::<Service>(SERVICE, "").validate().is_empty());
+
+        let cases = [
+            (r#"name: "web.""#, vec!["name"]),
+            (
+                r#"spec:
+  ports:
+    - { name: http, port: 0, target_port: 8080 }
+    - { name: https, port: 443, target_port: 0 }
+    - { name: alt, port: 443, target_port: 8443 }"#,
+                vec![
+                    "spec.ports[0].port",
+                    "spec.ports[1].target_port",
+                    "spec.ports[2].port",
+                ],
+            ),
+            (
+                r#"spec: { selector: { "example.com/app": web, "a/b/c": web } }"#,
+                vec!["spec.selector.a/b/c"],
+            ),
+        ];
+        for (patch, expected) in cases {
+            let svc: Service = manifest(SERVICE, patch);
+            assert_eq!(fields(svc.validate()), expected, "patch: {}", patch);
+        }
+    }
+
+    const DEPLOYMENT: &str = r#"
+id: ""
+name: web
+namespace: default
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  replicas: 2
+  selector: { app: web }
+  template:
+    containers: [{ name: app, image: nginx }]
+"#;
+
+    #[test]
+    fn test_workload_validation() {
+        assert!(manifest::<Deployment>(DEPLOYMENT, "").validate().is_empty());
+
+        let cases = [
+            (r#"spec: { selector: {} }"#, vec!["spec.selector"]),
+            (
+                r#"spec: { template: { containers: [{ name: app, image: " " }] } }"#,
+                vec!["spec.template.containers[0].image"],
+            ),
+        ];
+        for (patch, expected) in cases {
+            let deploy: Deployment = manifest(DEPLOYMENT, patch);
+            assert_eq!(fields(deploy.validate()), expected, "patch: {}", patch);
+            let rs: ReplicaSet = manifest(DEPLOYMENT, patch);
+            assert_eq!(fields(rs.validate()), expected, "patch: {}", patch);
+        }
+
+        let ds: DaemonSet = manifest(
+            DEPLOYMENT,
+            r#"spec: { node_selector: { "k3rs.io/role": "edge node" } }"#,
+        );
+        assert_eq!(fields(ds.validate()), ["spec.node_selector.k3rs.io/role"]);
+    }
+
+    const CRONJOB: &str = r#"
+id: ""
+name: backup
+namespace: default
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  schedule: "*/5 * * * *"
+  job_template:
+    template:
+      restart_policy: Never
+      containers: [{ name: backup, image: busybox }]
+"#;
+
+    #[test]
+    fn test_job_validation() {
+        assert!(manifest::<CronJob>(CRONJOB, "").validate().is_empty());
+
+        let cases = [
+            (
+                r#"spec: { schedule: "every minute" }"#,
+                vec!["spec.schedule"],
+            ),
+            (r#"spec: { schedule: "61 * * * *" }"#, vec!["spec.schedule"]),
+            (
+                r#"spec: { job_template: { completions: 0, parallelism: 0 } }"#,
+                vec![
+                    "spec.job_template.completions",
+                    "spec.job_template.parallelism",
+                ],
+            ),
+        ];
+        for (patch, expected) in cases {
+            let cj: CronJob = manifest(CRONJOB, patch);
+            assert_eq!(fields(cj.validate()), expected, "patch: {}", patch);
+        }
+
+        let job = r#"
+id: ""
+name: once
+namespace: default
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  completions: 0
+  template: { containers: [] }
+"#;
+        let job: Job = manifest(job, "");
+        assert_eq!(
+            fields(job.validate()),
+            ["spec.completions", "spec.template.containers"]
+        );
+    }
+
+    #[test]
+    fn test_config_and_policy_validation() {
+        let cm = r#"
+id: ""
+name: settings
+namespace: default
+created_at: "2024-02-25T00:00:00Z"
+data: { "app.conf": "", "bad key": "", "../escape": "" }
+"#;
+        let cm: ConfigMap = manifest(cm, "");
+        assert_eq!(fields(cm.validate()), ["data.../escape", "data.bad key"]);
+
+        let hpa = r#"
+id: ""
+name: web
+namespace: default
+created_at: "2024-02-25T00:00:00Z"
+spec:
+  target_deployment: web
+  min_replicas: 0
+  max_replicas: 3
+  metrics: { cpu_utilization_percent: 0 }
+"#;
+        let hpa: HorizontalPodAutoscaler = manifest(hpa, "");
+        assert_eq!(
+            fields(hpa.validate()),
+            ["spec.min_replicas", "spec.metrics.cpu_utilization_percent"]
+        );
+        let hpa: HorizontalPodAutoscaler = manifest(
+            &serde_yaml::to_string(&hpa).unwrap(),
+            r#"spec: { min_replicas: 4, metrics: { cpu_utilization_percent: 80 } }"#,
+        );
+        assert_eq!(fields(hpa.validate()), ["spec.max_replicas"]);
+
+        let range = r#"
+name: bounds
+created_at: "2024-02-25T00:00:00Z"
+min: { memory_bytes: 1048576 }
+max: { memory_bytes: 1024 }
+"#;
+        let range: LimitRange = manifest(range, "");
+        assert_eq!(fields(range.validate()), ["max.memory_bytes"]);
+
+        let ns: Namespace = manifest(
+            r#"{ name: team-a, created_at: "2024-02-25T00:00:00Z" }"#,
+            r#"labels: { "": x }"#,
+        );
+        assert_eq!(fields(ns.validate()), ["labels."]);
+    }
+
+    #[test]
+    fn test_node_labels_and_taints() {
+        let node = r#"
+id: n1
+name: worker-1
+address: 10.0.0.2
+agent_api_port: 10250
+status: Ready
+registered_at: "2024-02-25T00:00:00Z"
+last_heartbeat: "2024-02-25T00:00:00Z"
+labels: { "topology.k3rs.io/zone": a }
+taints:
+  - { key: dedicated, value: gpu, effect: NoSchedule }
+  - { key: dedicated, value: gpu, effect: NoExecute }
+  - { key: node.k3rs/maintenance, effect: PreferNoSchedule }
+"#;
+        let node: Node = manifest(node, "");
+        assert!(node.validate().is_empty());
+
+        let node: Node = manifest(
+            &serde_yaml::to_string(&node).unwrap(),
+            r#"
+labels: { "zone!": a }
+taints:
+  - { key: dedicated, value: "g p u", effect: NoSchedule }
+  - { key: dedicated, value: cpu, effect: NoSchedule }
+"#,
+        );
+        assert_eq!(
+            fields(node.validate()),
+            ["labels.zone!", "taints[0].value", "taints[1]"]
+        );
+
+        let taint = Taint {
+            key: "-bad".to_string(),
+            value: String::new(),
+            effect: crate::pod::TaintEffect::NoExecute,
+        };
+        assert_eq!(
+            taint.validate(),
+            [FieldError::new("key", "invalid taint key '-bad'")]
+        );
+    }
 }
@@ -81,5 +81,106 @@ pub struct PersistentVolumeClaim {
     /// Current phase
     #[serde(default)]
     pub phase: PVCPhase,
+    /// What happens to the provisioned volume once the claim is deleted
+    #[serde(default)]
+    pub reclaim_policy: ReclaimPolicy,
+    /// PersistentVolume bound to the claim, set when it is provisioned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume_name: Option<String>,
     pub created_at: DateTime<Utc>,
 }
+
+impl PersistentVolumeClaim {
+    /// Whether the local-path provisioner serves this claim: its storage
+    /// class is `local-path` or unset.
+    pub fn is_local_path(&self) -> bool {
+        matches!(
+            self.storage_class.as_deref(),
+            None | Some("") | Some(LOCAL_PATH_STORAGE_CLASS)
+        )
+    }
+}
+
+// --- Persistent Volumes ---
+
+/// Storage class of the built-in provisioner, which makes a directory on a
+/// node for each claim.
+pub const LOCAL_PATH_STORAGE_CLASS: &str = "local-path";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum ReclaimPolicy {
+    /// Remove the volume and its data with the claim
+    #[default]
+    Delete,
+    /// Keep the data; the volume is left `Released`
+    Retain,
+}
+
+impl std::fmt::Display for ReclaimPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReclaimPolicy::Delete => write!(f, "Delete"),
+            ReclaimPolicy::Retain => write!(f, "Retain"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PVPhase {
+    Bound,
+    /// The claim is gone and the volume was retained
+    Released,
+}
+
+impl std::fmt::Display for PVPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PVPhase::Bound => write!(f, "Bound"),
+            PVPhase::Released => write!(f, "Released"),
+        }
+    }
+}
+
+/// The claim a volume was provisioned for.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClaimRef {
+    pub namespace: String,
+    pub name: String,
+    /// ID of the claim, so one recreated under the same name is not mistaken
+    /// for it
+    pub id: String,
+}
+
+/// Persistent Volume — storage provisioned for a claim (cluster-scoped).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PersistentVolume {
+    pub name: String,
+    pub storage_class: String,
+    pub capacity_bytes: u64,
+    #[serde(default)]
+    pub access_modes: Vec<AccessMode>,
+    pub reclaim_policy: ReclaimPolicy,
+    /// Node holding the volume; pods using it are pinned there
+    pub node_name: String,
+    /// Directory of the volume on the node
+    pub path: String,
+    pub claim_ref: ClaimRef,
+    pub phase: PVPhase,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Body of the agent's `PUT /volumes/{name}`, which creates the directory of
+/// a local-path volume.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalVolumeRequest {
+    /// `namespace/name` of the claim, recorded next to the data
+    pub claim: String,
+    pub capacity_bytes: u64,
+}
+
+/// Reply to `PUT /volumes/{name}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalVolume {
+    /// Directory of the volume on the node
+    pub path: String,
+}